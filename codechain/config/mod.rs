@@ -16,19 +16,27 @@
 
 mod chain_type;
 
+use std::collections::HashMap;
 use std::fs;
+use std::net;
+use std::path::Path;
 use std::str::{self, FromStr};
 use std::time::Duration;
 
-use ccore::{MinerOptions, StratumConfig, TimeGapParams};
+use ccore::{FeeFloorOptions, MinerOptions, ProposalBudget, StratumConfig, TimeGapParams};
 use cidr::IpCidr;
 use ckey::PlatformAddress;
 use clap;
-use cnetwork::{FilterEntry, NetworkConfig, SocketAddr};
+use cnetwork::session::RekeyPolicy;
+use cnetwork::DEFAULT_CONNECTION_LOG_SIZE;
+use cnetwork::{BandwidthLimits, FilterEntry, NetworkConfig, PeerClass, SocketAddr};
 use toml;
 
 pub use self::chain_type::ChainType;
+use crate::constants::BANDWIDTH_LIMITS_PATH;
 use crate::rpc::{RpcHttpConfig, RpcIpcConfig, RpcWsConfig};
+use crpc::v1::{HealthThresholds, MethodPolicies, MethodPolicy, Transport};
+use csync::ForkMonitorConfig;
 
 #[derive(Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -44,6 +52,10 @@ pub struct Config {
     pub stratum: Stratum,
     #[serde(default)]
     pub email_alarm: EmailAlarm,
+    #[serde(default)]
+    pub metrics: Metrics,
+    #[serde(default)]
+    pub health: Health,
 }
 
 impl Config {
@@ -57,6 +69,8 @@ impl Config {
         self.snapshot.merge(&other.snapshot);
         self.stratum.merge(&other.stratum);
         self.email_alarm.merge(&other.email_alarm);
+        self.metrics.merge(&other.metrics);
+        self.health.merge(&other.health);
     }
 
     pub fn miner_options(&self) -> Result<MinerOptions, String> {
@@ -82,6 +96,8 @@ impl Config {
                 mem_size => Some(mem_size * 1024 * 1024),
             },
             mem_pool_fee_bump_shift: self.mining.mem_pool_fee_bump_shift.unwrap(),
+            mem_pool_min_fee: self.mining.mem_pool_min_fee.unwrap_or(0),
+            mem_pool_rpc_min_fee: self.mining.mem_pool_rpc_min_fee.unwrap_or(0),
             allow_create_shard: self.mining.allow_create_shard.unwrap_or(false),
             new_work_notify: self.mining.notify_work.clone().unwrap(),
             force_sealing: self.mining.force_sealing.unwrap(),
@@ -91,6 +107,37 @@ impl Config {
             reseal_max_period: Duration::from_millis(self.mining.reseal_max_period.unwrap()),
             no_reseal_timer: self.mining.no_reseal_timer.unwrap(),
             work_queue_size: self.mining.work_queue_size.unwrap(),
+            seen_transactions_window: Duration::from_secs(self.mining.seen_transactions_window.unwrap()),
+            seen_transactions_capacity: self.mining.seen_transactions_capacity.unwrap(),
+            rpc_tx_rate_limit: self.mining.rpc_tx_rate_limit.unwrap_or(0),
+            rpc_tx_rate_window: Duration::from_secs(self.mining.rpc_tx_rate_window.unwrap_or(60)),
+            proposal_budget: ProposalBudget {
+                max_body_size: match self.mining.proposal_max_body_size.unwrap_or(0) {
+                    0 => None,
+                    size => Some(size),
+                },
+                max_transactions: match self.mining.proposal_max_transactions.unwrap_or(0) {
+                    0 => None,
+                    count => Some(count),
+                },
+            },
+            deterministic_ordering: self.mining.deterministic_ordering.unwrap_or(false),
+            mem_pool_fee_floor: match self.mining.mem_pool_fee_floor_max.unwrap_or(0) {
+                0 => None,
+                max => {
+                    let min = self.mining.mem_pool_min_fee.unwrap_or(0);
+                    if max < min {
+                        return Err("mem-pool-fee-floor-max must not be less than mem-pool-min-fee".to_string())
+                    }
+                    Some(FeeFloorOptions {
+                        min,
+                        max,
+                        window: Duration::from_secs(self.mining.mem_pool_fee_floor_window.unwrap_or(60)),
+                        interval: Duration::from_secs(self.mining.mem_pool_fee_floor_interval.unwrap_or(10)),
+                        exempt_rpc: self.mining.mem_pool_fee_floor_exempt_rpc.unwrap_or(false),
+                    })
+                }
+            },
         })
     }
 
@@ -169,6 +216,20 @@ impl Config {
 
         let whitelist = make_ipaddr_list(self.network.whitelist_path.as_ref(), "white")?;
         let blacklist = make_ipaddr_list(self.network.blacklist_path.as_ref(), "black")?;
+        let nat = match self.network.nat.as_ref().map(String::as_str) {
+            None | Some("none") => None,
+            Some(nat) => Some(nat.parse()?),
+        };
+        let mut bandwidth_limits = BandwidthLimits::default();
+        for (class, limit) in self.network.bandwidth_limits.iter().flatten() {
+            bandwidth_limits.set(class.parse::<PeerClass>()?, Some(*limit));
+        }
+        let bandwidth_limits_path = Path::new(self.operating.base_path.as_ref().unwrap()).join(BANDWIDTH_LIMITS_PATH);
+        if bandwidth_limits_path.exists() {
+            let saved = fs::read_to_string(&bandwidth_limits_path)
+                .map_err(|e| format!("Cannot open the bandwidth limits file {:?}: {:?}", bandwidth_limits_path, e))?;
+            bandwidth_limits = BandwidthLimits::parse_lines(&saved)?;
+        }
 
         Ok(NetworkConfig {
             address: self.network.interface.clone().unwrap(),
@@ -178,6 +239,16 @@ impl Config {
             max_peers: self.network.max_peers.unwrap(),
             whitelist,
             blacklist,
+            nat,
+            message_size_limits: self.network.message_size_limits.clone().unwrap_or_default(),
+            // The peers without the rekey frames close the session on a rekey request, so it's off unless configured.
+            rekey_policy: RekeyPolicy {
+                max_bytes: self.network.rekey_bytes.unwrap_or(0),
+                max_interval: Duration::from_secs(self.network.rekey_interval.unwrap_or(0)),
+            },
+            connection_log_size: self.network.connection_log_size.unwrap_or(DEFAULT_CONNECTION_LOG_SIZE),
+            bandwidth_limits,
+            bandwidth_limits_path: Some(bandwidth_limits_path),
         })
     }
 
@@ -191,6 +262,71 @@ impl Config {
             secret: None,
         }
     }
+
+    pub fn metrics_address(&self) -> Result<net::SocketAddr, String> {
+        debug_assert!(!self.metrics.disable.unwrap());
+
+        let interface = self.metrics.interface.as_ref().unwrap();
+        let ip = interface.parse().map_err(|_| format!("Invalid metrics interface given: {}", interface))?;
+        Ok(net::SocketAddr::new(ip, self.metrics.port.unwrap()))
+    }
+
+    pub fn health_thresholds(&self) -> HealthThresholds {
+        let max_block_age = self.health.max_block_age.unwrap();
+        let stuck_step_multiple = self.health.stuck_step_multiple.unwrap();
+        HealthThresholds {
+            max_block_age: if max_block_age == 0 {
+                None
+            } else {
+                Some(Duration::from_secs(max_block_age))
+            },
+            min_peers: self.health.min_peers.unwrap(),
+            max_sync_gap: self.health.max_sync_gap.unwrap(),
+            stuck_step_multiple: if stuck_step_multiple == 0 {
+                None
+            } else {
+                Some(stuck_step_multiple)
+            },
+            max_clock_skew: self.mining.create_time_gaps().max_clock_skew,
+        }
+    }
+
+    /// None if the fork detection is disabled.
+    pub fn fork_monitor_config(&self) -> Option<ForkMonitorConfig> {
+        let grace_period = self.health.fork_grace_period.unwrap();
+        if grace_period == 0 {
+            return None
+        }
+        Some(ForkMonitorConfig {
+            score_margin: self.health.fork_score_margin.unwrap().into(),
+            min_peers: self.health.fork_min_peers.unwrap(),
+            grace_period: Duration::from_secs(grace_period),
+        })
+    }
+
+    /// The transports without `method_allow` and `method_deny` follow `MethodPolicy::default_for`.
+    pub fn method_policies(&self) -> MethodPolicies {
+        let mut policies = HashMap::new();
+        let configured = [
+            (Transport::Http, &self.rpc.method_allow, &self.rpc.method_deny),
+            (Transport::Ws, &self.ws.method_allow, &self.ws.method_deny),
+            (Transport::Ipc, &self.ipc.method_allow, &self.ipc.method_deny),
+        ];
+        for &(transport, allow, deny) in &configured {
+            if allow.is_none() && deny.is_none() {
+                continue
+            }
+            policies.insert(transport, MethodPolicy {
+                allow: allow.clone().unwrap_or_else(|| MethodPolicy::default_for(transport).allow),
+                deny: deny.clone().unwrap_or_default(),
+            });
+        }
+        MethodPolicies::new(policies)
+    }
+
+    pub fn rpc_slow_call_threshold(&self) -> Option<Duration> {
+        self.rpc.slow_call_threshold.map(Duration::from_millis)
+    }
 }
 
 #[derive(Deserialize)]
@@ -198,18 +334,30 @@ impl Config {
 pub struct Ipc {
     pub disable: Option<bool>,
     pub path: Option<String>,
+    pub method_allow: Option<Vec<String>>,
+    pub method_deny: Option<Vec<String>>,
 }
 
 #[derive(Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Operating {
     pub quiet: Option<bool>,
+    /// Follows only the finalized headers with the help of the full nodes, without the bodies and the state.
+    pub light: Option<bool>,
     pub instance_id: Option<usize>,
     pub base_path: Option<String>,
     pub db_path: Option<String>,
     pub keys_path: Option<String>,
     pub password_path: Option<String>,
     pub chain: Option<ChainType>,
+    pub log_format: Option<String>,
+    pub max_reorg_depth: Option<u64>,
+    pub seal_checkpoint_interval: Option<u64>,
+    pub address_history: Option<bool>,
+    pub action_index: Option<bool>,
+    pub state_warming_max_entries: Option<usize>,
+    /// In milliseconds
+    pub state_warming_timeout: Option<u64>,
 }
 
 #[derive(Deserialize)]
@@ -218,9 +366,12 @@ pub struct Mining {
     pub disable: Option<bool>,
     pub author: Option<PlatformAddress>,
     pub engine_signer: Option<PlatformAddress>,
+    pub validator_standby: Option<bool>,
     pub mem_pool_size: Option<usize>,
     pub mem_pool_mem_limit: Option<usize>,
     pub mem_pool_fee_bump_shift: Option<usize>,
+    pub mem_pool_min_fee: Option<u64>,
+    pub mem_pool_rpc_min_fee: Option<u64>,
     pub allow_create_shard: Option<bool>,
     pub notify_work: Option<Vec<String>>,
     pub force_sealing: Option<bool>,
@@ -231,6 +382,19 @@ pub struct Mining {
     pub work_queue_size: Option<usize>,
     pub allowed_past_gap: Option<u64>,
     pub allowed_future_gap: Option<u64>,
+    pub max_clock_skew: Option<u64>,
+    pub max_future_drift: Option<u64>,
+    pub seen_transactions_window: Option<u64>,
+    pub seen_transactions_capacity: Option<usize>,
+    pub rpc_tx_rate_limit: Option<usize>,
+    pub rpc_tx_rate_window: Option<u64>,
+    pub proposal_max_body_size: Option<usize>,
+    pub proposal_max_transactions: Option<usize>,
+    pub deterministic_ordering: Option<bool>,
+    pub mem_pool_fee_floor_max: Option<u64>,
+    pub mem_pool_fee_floor_window: Option<u64>,
+    pub mem_pool_fee_floor_interval: Option<u64>,
+    pub mem_pool_fee_floor_exempt_rpc: Option<bool>,
 }
 
 #[derive(Deserialize)]
@@ -248,8 +412,19 @@ pub struct Network {
     pub discovery_type: Option<String>,
     pub discovery_refresh: Option<u32>,
     pub discovery_bucket_size: Option<u8>,
+    pub peer_exchange: Option<bool>,
+    /// Share and learn the loopback and the private-range addresses through the peer exchange.
+    pub peer_exchange_share_private: Option<bool>,
     pub blacklist_path: Option<String>,
     pub whitelist_path: Option<String>,
+    pub nat: Option<String>,
+    pub message_size_limits: Option<HashMap<String, usize>>,
+    pub rekey_bytes: Option<usize>,
+    pub rekey_interval: Option<u64>,
+    pub connection_log_size: Option<usize>,
+    /// Bytes per second of the extension messages for each peer class.
+    pub bandwidth_limits: Option<HashMap<String, usize>>,
+    pub consensus_full_gossip: Option<bool>,
 }
 
 #[derive(Deserialize)]
@@ -260,8 +435,20 @@ pub struct Rpc {
     pub hosts: Option<Vec<String>>,
     pub cors: Option<Vec<String>>,
     pub port: Option<u16>,
+    /// The methods served on HTTP. `*` at the end of a pattern matches any suffix.
+    pub method_allow: Option<Vec<String>>,
+    pub method_deny: Option<Vec<String>>,
+    /// The calls that take longer than this are logged one by one. In milliseconds
+    pub slow_call_threshold: Option<u64>,
     #[serde(default = "default_enable_devel_api")]
     pub enable_devel_api: bool,
+    #[serde(default)]
+    pub enable_force_view: bool,
+    #[serde(default)]
+    pub enable_fault_injection: bool,
+    /// Reject the state queries while the node is suspected to be on a minority fork.
+    #[serde(default)]
+    pub strict_fork_safe_mode: bool,
 }
 
 #[derive(Deserialize)]
@@ -271,6 +458,8 @@ pub struct Ws {
     pub interface: Option<String>,
     pub port: Option<u16>,
     pub max_connections: Option<usize>,
+    pub method_allow: Option<Vec<String>>,
+    pub method_deny: Option<Vec<String>>,
 }
 
 fn default_enable_devel_api() -> bool {
@@ -292,6 +481,31 @@ pub struct Stratum {
 }
 
 
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Metrics {
+    pub disable: Option<bool>,
+    pub interface: Option<String>,
+    pub port: Option<u16>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Health {
+    /// In seconds. 0 disables the check.
+    pub max_block_age: Option<u64>,
+    pub min_peers: Option<usize>,
+    pub max_sync_gap: Option<u64>,
+    /// 0 disables the check.
+    pub stuck_step_multiple: Option<u32>,
+    /// The node is suspected to be on a minority fork if the majority of its peers disagree with its best block
+    /// for this many seconds. 0 disables the check.
+    pub fork_grace_period: Option<u64>,
+    pub fork_min_peers: Option<usize>,
+    /// A peer is regarded as ahead of the node only if its total score exceeds ours by more than this.
+    pub fork_score_margin: Option<u64>,
+}
+
 #[derive(Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct EmailAlarm {
@@ -308,6 +522,12 @@ impl Ipc {
         if other.path.is_some() {
             self.path = other.path.clone();
         }
+        if other.method_allow.is_some() {
+            self.method_allow = other.method_allow.clone();
+        }
+        if other.method_deny.is_some() {
+            self.method_deny = other.method_deny.clone();
+        }
     }
 
     pub fn overwrite_with(&mut self, matches: &clap::ArgMatches) -> Result<(), String> {
@@ -326,6 +546,9 @@ impl Operating {
         if other.quiet.is_some() {
             self.quiet = other.quiet;
         }
+        if other.light.is_some() {
+            self.light = other.light;
+        }
         if other.instance_id.is_some() {
             self.instance_id = other.instance_id;
         }
@@ -344,12 +567,36 @@ impl Operating {
         if other.chain.is_some() {
             self.chain = other.chain.clone();
         }
+        if other.log_format.is_some() {
+            self.log_format = other.log_format.clone();
+        }
+        if other.max_reorg_depth.is_some() {
+            self.max_reorg_depth = other.max_reorg_depth;
+        }
+        if other.seal_checkpoint_interval.is_some() {
+            self.seal_checkpoint_interval = other.seal_checkpoint_interval;
+        }
+        if other.address_history.is_some() {
+            self.address_history = other.address_history;
+        }
+        if other.action_index.is_some() {
+            self.action_index = other.action_index;
+        }
+        if other.state_warming_max_entries.is_some() {
+            self.state_warming_max_entries = other.state_warming_max_entries;
+        }
+        if other.state_warming_timeout.is_some() {
+            self.state_warming_timeout = other.state_warming_timeout;
+        }
     }
 
     pub fn overwrite_with(&mut self, matches: &clap::ArgMatches) -> Result<(), String> {
         if matches.is_present("quiet") {
             self.quiet = Some(true);
         }
+        if matches.is_present("light") {
+            self.light = Some(true);
+        }
         if let Some(instance_id) = matches.value_of("instance-id") {
             self.instance_id = Some(instance_id.parse().map_err(|e| format!("{}", e))?);
         }
@@ -368,6 +615,30 @@ impl Operating {
         if let Some(chain) = matches.value_of("chain") {
             self.chain = Some(chain.parse().unwrap());
         }
+        if let Some(log_format) = matches.value_of("log-format") {
+            self.log_format = Some(log_format.to_string());
+        }
+        if let Some(max_reorg_depth) = matches.value_of("max-reorg-depth") {
+            self.max_reorg_depth = Some(max_reorg_depth.parse().map_err(|_| "Invalid max-reorg-depth")?);
+        }
+        if let Some(seal_checkpoint_interval) = matches.value_of("seal-checkpoint-interval") {
+            self.seal_checkpoint_interval =
+                Some(seal_checkpoint_interval.parse().map_err(|_| "Invalid seal-checkpoint-interval")?);
+        }
+        if matches.is_present("address-history") {
+            self.address_history = Some(true);
+        }
+        if matches.is_present("action-index") {
+            self.action_index = Some(true);
+        }
+        if let Some(state_warming_max_entries) = matches.value_of("state-warming-max-entries") {
+            self.state_warming_max_entries =
+                Some(state_warming_max_entries.parse().map_err(|_| "Invalid state-warming-max-entries")?);
+        }
+        if let Some(state_warming_timeout) = matches.value_of("state-warming-timeout") {
+            self.state_warming_timeout =
+                Some(state_warming_timeout.parse().map_err(|_| "Invalid state-warming-timeout")?);
+        }
         Ok(())
     }
 }
@@ -383,12 +654,21 @@ impl Mining {
         if other.engine_signer.is_some() {
             self.engine_signer = other.engine_signer;
         }
+        if other.validator_standby.is_some() {
+            self.validator_standby = other.validator_standby;
+        }
         if other.mem_pool_size.is_some() {
             self.mem_pool_size = other.mem_pool_size;
         }
         if other.mem_pool_mem_limit.is_some() {
             self.mem_pool_mem_limit = other.mem_pool_mem_limit;
         }
+        if other.mem_pool_min_fee.is_some() {
+            self.mem_pool_min_fee = other.mem_pool_min_fee;
+        }
+        if other.mem_pool_rpc_min_fee.is_some() {
+            self.mem_pool_rpc_min_fee = other.mem_pool_rpc_min_fee;
+        }
         if other.allow_create_shard.is_some() {
             self.allow_create_shard = other.allow_create_shard;
         }
@@ -413,6 +693,45 @@ impl Mining {
         if other.work_queue_size.is_some() {
             self.work_queue_size = other.work_queue_size;
         }
+        if other.seen_transactions_window.is_some() {
+            self.seen_transactions_window = other.seen_transactions_window;
+        }
+        if other.seen_transactions_capacity.is_some() {
+            self.seen_transactions_capacity = other.seen_transactions_capacity;
+        }
+        if other.rpc_tx_rate_limit.is_some() {
+            self.rpc_tx_rate_limit = other.rpc_tx_rate_limit;
+        }
+        if other.rpc_tx_rate_window.is_some() {
+            self.rpc_tx_rate_window = other.rpc_tx_rate_window;
+        }
+        if other.proposal_max_body_size.is_some() {
+            self.proposal_max_body_size = other.proposal_max_body_size;
+        }
+        if other.proposal_max_transactions.is_some() {
+            self.proposal_max_transactions = other.proposal_max_transactions;
+        }
+        if other.deterministic_ordering.is_some() {
+            self.deterministic_ordering = other.deterministic_ordering;
+        }
+        if other.mem_pool_fee_floor_max.is_some() {
+            self.mem_pool_fee_floor_max = other.mem_pool_fee_floor_max;
+        }
+        if other.mem_pool_fee_floor_window.is_some() {
+            self.mem_pool_fee_floor_window = other.mem_pool_fee_floor_window;
+        }
+        if other.mem_pool_fee_floor_interval.is_some() {
+            self.mem_pool_fee_floor_interval = other.mem_pool_fee_floor_interval;
+        }
+        if other.mem_pool_fee_floor_exempt_rpc.is_some() {
+            self.mem_pool_fee_floor_exempt_rpc = other.mem_pool_fee_floor_exempt_rpc;
+        }
+        if other.max_clock_skew.is_some() {
+            self.max_clock_skew = other.max_clock_skew;
+        }
+        if other.max_future_drift.is_some() {
+            self.max_future_drift = other.max_future_drift;
+        }
     }
 
     pub fn overwrite_with(&mut self, matches: &clap::ArgMatches) -> Result<(), String> {
@@ -426,6 +745,9 @@ impl Mining {
         if let Some(engine_signer) = matches.value_of("engine-signer") {
             self.engine_signer = Some(engine_signer.parse().map_err(|_| "Invalid address format")?);
         }
+        if matches.is_present("validator-standby") {
+            self.validator_standby = Some(true);
+        }
         if let Some(mem_pool_fee_bump_shift) = matches.value_of("mem-pool-fee-bump-shift") {
             self.mem_pool_mem_limit =
                 Some(mem_pool_fee_bump_shift.parse().map_err(|_| "Invalid mem pool fee bump shift")?);
@@ -436,6 +758,12 @@ impl Mining {
         if let Some(mem_pool_size) = matches.value_of("mem-pool-size") {
             self.mem_pool_size = Some(mem_pool_size.parse().map_err(|_| "Invalid size")?);
         }
+        if let Some(mem_pool_min_fee) = matches.value_of("mem-pool-min-fee") {
+            self.mem_pool_min_fee = Some(mem_pool_min_fee.parse().map_err(|_| "Invalid min fee")?);
+        }
+        if let Some(mem_pool_rpc_min_fee) = matches.value_of("mem-pool-rpc-min-fee") {
+            self.mem_pool_rpc_min_fee = Some(mem_pool_rpc_min_fee.parse().map_err(|_| "Invalid min fee")?);
+        }
         if matches.is_present("allow-create-shard") {
             self.allow_create_shard = Some(true)
         }
@@ -466,16 +794,64 @@ impl Mining {
         if let Some(allowed_future_gap) = matches.value_of("allowed-future-gap") {
             self.allowed_future_gap = Some(allowed_future_gap.parse().map_err(|_| "Invalid time gap")?);
         }
+        if let Some(max_clock_skew) = matches.value_of("max-clock-skew") {
+            self.max_clock_skew = Some(max_clock_skew.parse().map_err(|_| "Invalid clock skew")?);
+        }
+        if let Some(max_future_drift) = matches.value_of("max-future-drift") {
+            self.max_future_drift = Some(max_future_drift.parse().map_err(|_| "Invalid time gap")?);
+        }
+        if let Some(seen_transactions_window) = matches.value_of("seen-transactions-window") {
+            self.seen_transactions_window = Some(seen_transactions_window.parse().map_err(|_| "Invalid window")?);
+        }
+        if let Some(seen_transactions_capacity) = matches.value_of("seen-transactions-capacity") {
+            self.seen_transactions_capacity = Some(seen_transactions_capacity.parse().map_err(|_| "Invalid capacity")?);
+        }
+        if let Some(rpc_tx_rate_limit) = matches.value_of("rpc-tx-rate-limit") {
+            self.rpc_tx_rate_limit = Some(rpc_tx_rate_limit.parse().map_err(|_| "Invalid rate limit")?);
+        }
+        if let Some(rpc_tx_rate_window) = matches.value_of("rpc-tx-rate-window") {
+            self.rpc_tx_rate_window = Some(rpc_tx_rate_window.parse().map_err(|_| "Invalid window")?);
+        }
+        if let Some(proposal_max_body_size) = matches.value_of("proposal-max-body-size") {
+            self.proposal_max_body_size = Some(proposal_max_body_size.parse().map_err(|_| "Invalid size")?);
+        }
+        if let Some(proposal_max_transactions) = matches.value_of("proposal-max-transactions") {
+            self.proposal_max_transactions =
+                Some(proposal_max_transactions.parse().map_err(|_| "Invalid number of transactions")?);
+        }
+        if matches.is_present("deterministic-ordering") {
+            self.deterministic_ordering = Some(true);
+        }
+        if let Some(mem_pool_fee_floor_max) = matches.value_of("mem-pool-fee-floor-max") {
+            self.mem_pool_fee_floor_max = Some(mem_pool_fee_floor_max.parse().map_err(|_| "Invalid fee")?);
+        }
+        if let Some(mem_pool_fee_floor_window) = matches.value_of("mem-pool-fee-floor-window") {
+            self.mem_pool_fee_floor_window = Some(mem_pool_fee_floor_window.parse().map_err(|_| "Invalid window")?);
+        }
+        if let Some(mem_pool_fee_floor_interval) = matches.value_of("mem-pool-fee-floor-interval") {
+            self.mem_pool_fee_floor_interval =
+                Some(mem_pool_fee_floor_interval.parse().map_err(|_| "Invalid interval")?);
+        }
+        if matches.is_present("mem-pool-fee-floor-exempt-rpc") {
+            self.mem_pool_fee_floor_exempt_rpc = Some(true);
+        }
         Ok(())
     }
 
     pub fn create_time_gaps(&self) -> TimeGapParams {
         let allowed_past_gap = Duration::from_millis(self.allowed_past_gap.unwrap_or(30000));
         let allowed_future_gap = Duration::from_millis(self.allowed_future_gap.unwrap_or(5000));
+        let max_clock_skew = match self.max_clock_skew.unwrap_or(0) {
+            0 => None,
+            max_clock_skew => Some(Duration::from_millis(max_clock_skew)),
+        };
+        let max_future_drift = Duration::from_millis(self.max_future_drift.unwrap_or(3000));
 
         TimeGapParams {
             allowed_past_gap,
             allowed_future_gap,
+            max_clock_skew,
+            max_future_drift,
         }
     }
 }
@@ -518,12 +894,39 @@ impl Network {
         if other.discovery_bucket_size.is_some() {
             self.discovery_bucket_size = other.discovery_bucket_size;
         }
+        if other.peer_exchange.is_some() {
+            self.peer_exchange = other.peer_exchange;
+        }
+        if other.peer_exchange_share_private.is_some() {
+            self.peer_exchange_share_private = other.peer_exchange_share_private;
+        }
         if other.blacklist_path.is_some() {
             self.blacklist_path = other.blacklist_path.clone();
         }
         if other.whitelist_path.is_some() {
             self.whitelist_path = other.whitelist_path.clone();
         }
+        if other.nat.is_some() {
+            self.nat = other.nat.clone();
+        }
+        if other.message_size_limits.is_some() {
+            self.message_size_limits = other.message_size_limits.clone();
+        }
+        if other.rekey_bytes.is_some() {
+            self.rekey_bytes = other.rekey_bytes;
+        }
+        if other.rekey_interval.is_some() {
+            self.rekey_interval = other.rekey_interval;
+        }
+        if other.connection_log_size.is_some() {
+            self.connection_log_size = other.connection_log_size;
+        }
+        if other.bandwidth_limits.is_some() {
+            self.bandwidth_limits = other.bandwidth_limits.clone();
+        }
+        if other.consensus_full_gossip.is_some() {
+            self.consensus_full_gossip = other.consensus_full_gossip;
+        }
     }
 
     pub fn overwrite_with(&mut self, matches: &clap::ArgMatches) -> Result<(), String> {
@@ -558,6 +961,9 @@ impl Network {
         if matches.is_present("no-tx-relay") {
             self.transaction_relay = Some(false);
         }
+        if matches.is_present("consensus-full-gossip") {
+            self.consensus_full_gossip = Some(true);
+        }
 
         if matches.is_present("no-discovery") {
             self.discovery = Some(false);
@@ -571,6 +977,12 @@ impl Network {
         if let Some(bucket_size) = matches.value_of("discovery-bucket-size") {
             self.discovery_bucket_size = Some(bucket_size.parse().map_err(|_| "Invalid discovery-bucket-size")?);
         }
+        if matches.is_present("no-peer-exchange") {
+            self.peer_exchange = Some(false);
+        }
+        if matches.is_present("peer-exchange-share-private") {
+            self.peer_exchange_share_private = Some(true);
+        }
 
         if let Some(file_path) = matches.value_of("whitelist-path") {
             self.whitelist_path = Some(file_path.to_string());
@@ -579,6 +991,20 @@ impl Network {
             self.blacklist_path = Some(file_path.to_string());
         }
 
+        if let Some(nat) = matches.value_of("nat") {
+            self.nat = Some(nat.to_string());
+        }
+
+        if let Some(rekey_bytes) = matches.value_of("rekey-bytes") {
+            self.rekey_bytes = Some(rekey_bytes.parse().map_err(|_| "Invalid rekey-bytes")?);
+        }
+        if let Some(rekey_interval) = matches.value_of("rekey-interval") {
+            self.rekey_interval = Some(rekey_interval.parse().map_err(|_| "Invalid rekey-interval")?);
+        }
+        if let Some(connection_log_size) = matches.value_of("connection-log-size") {
+            self.connection_log_size = Some(connection_log_size.parse().map_err(|_| "Invalid connection-log-size")?);
+        }
+
         Ok(())
     }
 }
@@ -600,6 +1026,15 @@ impl Rpc {
         if other.port.is_some() {
             self.port = other.port;
         }
+        if other.method_allow.is_some() {
+            self.method_allow = other.method_allow.clone();
+        }
+        if other.method_deny.is_some() {
+            self.method_deny = other.method_deny.clone();
+        }
+        if other.slow_call_threshold.is_some() {
+            self.slow_call_threshold = other.slow_call_threshold;
+        }
     }
 
     pub fn overwrite_with(&mut self, matches: &clap::ArgMatches) -> Result<(), String> {
@@ -618,9 +1053,21 @@ impl Rpc {
         if let Some(cors) = matches.values_of_lossy("jsonrpc-cors") {
             self.cors = Some(cors);
         }
+        if let Some(threshold) = matches.value_of("jsonrpc-slow-call-threshold") {
+            self.slow_call_threshold = Some(threshold.parse().map_err(|_| "Invalid slow call threshold")?);
+        }
         if matches.is_present("enable-devel-api") {
             self.enable_devel_api = true;
         }
+        if matches.is_present("enable-force-view") {
+            self.enable_force_view = true;
+        }
+        if matches.is_present("enable-fault-injection") {
+            self.enable_fault_injection = true;
+        }
+        if matches.is_present("strict-fork-safe-mode") {
+            self.strict_fork_safe_mode = true;
+        }
         Ok(())
     }
 }
@@ -639,6 +1086,12 @@ impl Ws {
         if other.max_connections.is_some() {
             self.max_connections = other.max_connections;
         }
+        if other.method_allow.is_some() {
+            self.method_allow = other.method_allow.clone();
+        }
+        if other.method_deny.is_some() {
+            self.method_deny = other.method_deny.clone();
+        }
     }
 
     pub fn overwrite_with(&mut self, matches: &clap::ArgMatches) -> Result<(), String> {
@@ -731,6 +1184,85 @@ impl EmailAlarm {
     }
 }
 
+impl Metrics {
+    pub fn merge(&mut self, other: &Metrics) {
+        if other.disable.is_some() {
+            self.disable = other.disable;
+        }
+        if other.interface.is_some() {
+            self.interface = other.interface.clone();
+        }
+        if other.port.is_some() {
+            self.port = other.port;
+        }
+    }
+
+    pub fn overwrite_with(&mut self, matches: &clap::ArgMatches) -> Result<(), String> {
+        if matches.is_present("metrics") {
+            self.disable = Some(false);
+        }
+        if let Some(interface) = matches.value_of("metrics-interface") {
+            self.interface = Some(interface.to_string());
+        }
+        if let Some(port) = matches.value_of("metrics-port") {
+            self.port = Some(port.parse().map_err(|_| "Invalid port")?);
+        }
+        Ok(())
+    }
+}
+
+impl Health {
+    pub fn merge(&mut self, other: &Health) {
+        if other.max_block_age.is_some() {
+            self.max_block_age = other.max_block_age;
+        }
+        if other.min_peers.is_some() {
+            self.min_peers = other.min_peers;
+        }
+        if other.max_sync_gap.is_some() {
+            self.max_sync_gap = other.max_sync_gap;
+        }
+        if other.stuck_step_multiple.is_some() {
+            self.stuck_step_multiple = other.stuck_step_multiple;
+        }
+        if other.fork_grace_period.is_some() {
+            self.fork_grace_period = other.fork_grace_period;
+        }
+        if other.fork_min_peers.is_some() {
+            self.fork_min_peers = other.fork_min_peers;
+        }
+        if other.fork_score_margin.is_some() {
+            self.fork_score_margin = other.fork_score_margin;
+        }
+    }
+
+    pub fn overwrite_with(&mut self, matches: &clap::ArgMatches) -> Result<(), String> {
+        if let Some(max_block_age) = matches.value_of("health-max-block-age") {
+            self.max_block_age = Some(max_block_age.parse().map_err(|_| "Invalid health-max-block-age")?);
+        }
+        if let Some(min_peers) = matches.value_of("health-min-peers") {
+            self.min_peers = Some(min_peers.parse().map_err(|_| "Invalid health-min-peers")?);
+        }
+        if let Some(max_sync_gap) = matches.value_of("health-max-sync-gap") {
+            self.max_sync_gap = Some(max_sync_gap.parse().map_err(|_| "Invalid health-max-sync-gap")?);
+        }
+        if let Some(stuck_step_multiple) = matches.value_of("health-stuck-step-multiple") {
+            self.stuck_step_multiple =
+                Some(stuck_step_multiple.parse().map_err(|_| "Invalid health-stuck-step-multiple")?);
+        }
+        if let Some(fork_grace_period) = matches.value_of("health-fork-grace-period") {
+            self.fork_grace_period = Some(fork_grace_period.parse().map_err(|_| "Invalid health-fork-grace-period")?);
+        }
+        if let Some(fork_min_peers) = matches.value_of("health-fork-min-peers") {
+            self.fork_min_peers = Some(fork_min_peers.parse().map_err(|_| "Invalid health-fork-min-peers")?);
+        }
+        if let Some(fork_score_margin) = matches.value_of("health-fork-score-margin") {
+            self.fork_score_margin = Some(fork_score_margin.parse().map_err(|_| "Invalid health-fork-score-margin")?);
+        }
+        Ok(())
+    }
+}
+
 impl Default for EmailAlarm {
     fn default() -> Self {
         Self {
@@ -775,5 +1307,7 @@ pub fn load_config(matches: &clap::ArgMatches) -> Result<Config, String> {
     config.snapshot.overwrite_with(&matches)?;
     config.stratum.overwrite_with(&matches)?;
     config.email_alarm.overwrite_with(&matches)?;
+    config.metrics.overwrite_with(&matches)?;
+    config.health.overwrite_with(&matches)?;
     Ok(config)
 }
@@ -16,3 +16,7 @@
 
 pub const DEFAULT_KEYS_PATH: &str = "keys";
 pub const DEFAULT_DB_PATH: &str = "db";
+/// The bandwidth limits changed by net_setBandwidthLimits, which replace the configured ones
+pub const BANDWIDTH_LIMITS_PATH: &str = "bandwidth_limits.toml";
+/// Unit: second
+pub const SHUTDOWN_TIMEOUT_IN_SECONDS: u64 = 30;
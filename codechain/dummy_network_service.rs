@@ -16,10 +16,14 @@
 
 use std::collections::HashMap;
 use std::net::IpAddr;
+use std::time::Duration;
 
 use cidr::IpCidr;
 use ckey::Public;
-use cnetwork::{FilterEntry, NetworkControl, NetworkControlError, SocketAddr};
+use cnetwork::{
+    BandwidthLimits, ConnectionAttempt, ConnectionOutcomeKind, DiscoveredPeer, FilterDecision, FilterEntry, NatStatus,
+    NetworkControl, NetworkControlError, PeerExchangeStats, PeerSession, SocketAddr,
+};
 
 pub struct DummyNetworkService {}
 
@@ -67,6 +71,10 @@ impl NetworkControl for DummyNetworkService {
         Err(NetworkControlError::Disabled)
     }
 
+    fn peer_sessions(&self) -> Result<Vec<PeerSession>, NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
     fn add_to_whitelist(&self, _addr: IpCidr, _tag: Option<String>) -> Result<(), NetworkControlError> {
         Err(NetworkControlError::Disabled)
     }
@@ -83,6 +91,18 @@ impl NetworkControl for DummyNetworkService {
         Err(NetworkControlError::Disabled)
     }
 
+    fn replace_blacklist(&self, _entries: Vec<(FilterEntry, Option<Duration>)>) -> Result<(), NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
+    fn remove_by_tag(&self, _tag: &str) -> Result<usize, NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
+    fn list_by_tag(&self, _tag: &str) -> Result<(Vec<IpCidr>, Vec<IpCidr>), NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
     fn enable_whitelist(&self) -> Result<(), NetworkControlError> {
         Err(NetworkControlError::Disabled)
     }
@@ -107,7 +127,55 @@ impl NetworkControl for DummyNetworkService {
         Err(NetworkControlError::Disabled)
     }
 
+    fn test_filter(&self, _addr: &IpAddr) -> Result<FilterDecision, NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
     fn recent_network_usage(&self) -> Result<HashMap<String, usize>, NetworkControlError> {
         Err(NetworkControlError::Disabled)
     }
+
+    fn connection_log(
+        &self,
+        _limit: usize,
+        _outcome: Option<ConnectionOutcomeKind>,
+    ) -> Result<Vec<ConnectionAttempt>, NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
+    fn nat_status(&self) -> Result<NatStatus, NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
+    fn refresh_nat_mapping(&self) -> Result<NatStatus, NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
+    fn extension_message_size_limits(&self) -> Result<HashMap<String, usize>, NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
+    fn bandwidth_limits(&self) -> Result<BandwidthLimits, NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
+    fn set_bandwidth_limits(&self, _limits: BandwidthLimits) -> Result<(), NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
+    fn discovered_peers(&self) -> Result<Vec<DiscoveredPeer>, NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
+    fn add_seed_peer(&self, _addr: SocketAddr) -> Result<bool, NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
+    fn request_peer_exchange(&self, _addr: SocketAddr) -> Result<(), NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
+    fn peer_exchange_stats(&self) -> Result<PeerExchangeStats, NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
 }
@@ -35,6 +35,7 @@ extern crate codechain_keystore as ckeystore;
 #[macro_use]
 extern crate codechain_logger as clogger;
 extern crate cidr;
+extern crate codechain_metrics as cmetrics;
 extern crate codechain_network as cnetwork;
 extern crate codechain_rpc as crpc;
 extern crate codechain_state as cstate;
@@ -60,7 +61,9 @@ mod dummy_network_service;
 mod json;
 mod rpc;
 mod rpc_apis;
+mod run_light_node;
 mod run_node;
+mod shutdown;
 mod subcommand;
 
 use app_dirs::AppInfo;
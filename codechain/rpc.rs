@@ -16,10 +16,12 @@
 
 use std::io;
 use std::net::SocketAddr;
+use std::sync::Arc;
 
-use crate::rpc_apis;
+use crate::rpc_apis::{self, ApiSet};
+use crpc::v1::{MethodPolicyMiddleware, StatisticsMiddleware, Transport};
 use crpc::{
-    jsonrpc_core, start_http, start_ipc, start_ws, HttpServer, IpcServer, MetaIoHandler, Middleware, WsError,
+    jsonrpc_core, start_http, start_ipc, start_ws, HttpServer, IpcServer, MetaIoHandler, Metadata, Middleware, WsError,
     WsErrorKind, WsServer,
 };
 use serde_json;
@@ -32,11 +34,7 @@ pub struct RpcHttpConfig {
     pub hosts: Option<Vec<String>>,
 }
 
-pub fn rpc_http_start(
-    cfg: RpcHttpConfig,
-    enable_devel_api: bool,
-    deps: &rpc_apis::ApiDependencies,
-) -> Result<HttpServer, String> {
+pub fn rpc_http_start(cfg: RpcHttpConfig, enable_devel_api: bool, deps: &impl ApiSet) -> Result<HttpServer, String> {
     let url = format!("{}:{}", cfg.interface, cfg.port);
     let addr = url.parse().map_err(|_| format!("Invalid JSONRPC listen host/port given: {}", url))?;
     let server = setup_http_rpc_server(&addr, cfg.cors.clone(), cfg.hosts.clone(), enable_devel_api, deps)?;
@@ -55,9 +53,9 @@ fn setup_http_rpc_server(
     cors_domains: Option<Vec<String>>,
     allowed_hosts: Option<Vec<String>>,
     enable_devel_api: bool,
-    deps: &rpc_apis::ApiDependencies,
+    deps: &impl ApiSet,
 ) -> Result<HttpServer, String> {
-    let server = setup_rpc_server(enable_devel_api, deps);
+    let server = setup_rpc_server(Transport::Http, enable_devel_api, deps);
     let start_result = start_http(url, cors_domains, allowed_hosts, server);
     match start_result {
         Err(ref err) if err.kind() == io::ErrorKind::AddrInUse => {
@@ -73,12 +71,8 @@ pub struct RpcIpcConfig {
     pub socket_addr: String,
 }
 
-pub fn rpc_ipc_start(
-    cfg: &RpcIpcConfig,
-    enable_devel_api: bool,
-    deps: &rpc_apis::ApiDependencies,
-) -> Result<IpcServer, String> {
-    let server = setup_rpc_server(enable_devel_api, deps);
+pub fn rpc_ipc_start(cfg: &RpcIpcConfig, enable_devel_api: bool, deps: &impl ApiSet) -> Result<IpcServer, String> {
+    let server = setup_rpc_server(Transport::Ipc, enable_devel_api, deps);
     let start_result = start_ipc(&cfg.socket_addr, server);
     match start_result {
         Err(ref err) if err.kind() == io::ErrorKind::AddrInUse => {
@@ -99,12 +93,8 @@ pub struct RpcWsConfig {
     pub max_connections: usize,
 }
 
-pub fn rpc_ws_start(
-    cfg: &RpcWsConfig,
-    enable_devel_api: bool,
-    deps: &rpc_apis::ApiDependencies,
-) -> Result<WsServer, String> {
-    let server = setup_rpc_server(enable_devel_api, deps);
+pub fn rpc_ws_start(cfg: &RpcWsConfig, enable_devel_api: bool, deps: &impl ApiSet) -> Result<WsServer, String> {
+    let server = setup_rpc_server(Transport::Ws, enable_devel_api, deps);
     let url = format!("{}:{}", cfg.interface, cfg.port);
     let addr = url.parse().map_err(|_| format!("Invalid WebSockets listen host/port given: {}", url))?;
     let start_result = start_ws(&addr, server, cfg.max_connections);
@@ -121,12 +111,17 @@ pub fn rpc_ws_start(
 }
 
 fn setup_rpc_server(
+    transport: Transport,
     enable_devel_api: bool,
-    deps: &rpc_apis::ApiDependencies,
-) -> MetaIoHandler<(), impl Middleware<()>> {
-    let mut handler = MetaIoHandler::with_middleware(LogMiddleware::new());
-    deps.extend_api(enable_devel_api, &mut handler);
-    rpc_apis::setup_rpc(handler)
+    deps: &impl ApiSet,
+) -> MetaIoHandler<Metadata, impl Middleware<Metadata>> {
+    let middleware = MethodPolicyMiddleware::new(transport, Arc::clone(deps.method_policies()), LogMiddleware::new())
+        .with_fork_safe_mode(Arc::clone(deps.fork_safe_mode()));
+    // The rejected calls are counted too.
+    let middleware = StatisticsMiddleware::new(Arc::clone(deps.rpc_statistics()), middleware);
+    let mut handler = MetaIoHandler::with_middleware(middleware);
+    deps.extend_api(transport, enable_devel_api, &mut handler);
+    rpc_apis::setup_rpc(handler, deps.rpc_statistics())
 }
 
 struct LogMiddleware {}
@@ -17,10 +17,28 @@
 use std::sync::Arc;
 
 use ccore::{AccountProvider, Client, Miner};
+use ckey::NetworkId;
 use clogger::SLOGGER;
 use cnetwork::{EventSender, NetworkControl};
-use crpc::{MetaIoHandler, Middleware, Params, Value};
-use csync::BlockSyncEvent;
+use crpc::v1::{CallStatistics, ForkSafeMode, HealthMonitor, MethodPolicies, TransactionSubscriptions, Transport};
+use crpc::{MetaIoHandler, Metadata, Middleware, Params, Value};
+use csync::{BlockSyncEvent, LightClientEvent};
+
+/// The RPCs that a node serves, and the middleware settings of the servers.
+pub trait ApiSet {
+    fn method_policies(&self) -> &Arc<MethodPolicies>;
+
+    fn fork_safe_mode(&self) -> &Arc<ForkSafeMode>;
+
+    fn rpc_statistics(&self) -> &Arc<CallStatistics>;
+
+    fn extend_api(
+        &self,
+        transport: Transport,
+        enable_devel_api: bool,
+        handler: &mut MetaIoHandler<Metadata, impl Middleware<Metadata>>,
+    );
+}
 
 pub struct ApiDependencies {
     pub client: Arc<Client>,
@@ -28,30 +46,149 @@ pub struct ApiDependencies {
     pub network_control: Arc<NetworkControl>,
     pub account_provider: Arc<AccountProvider>,
     pub block_sync: Option<EventSender<BlockSyncEvent>>,
+    pub enable_force_view: bool,
+    pub enable_fault_injection: bool,
+    pub transaction_subscriptions: Arc<TransactionSubscriptions>,
+    pub health_monitor: Arc<HealthMonitor<Client, Miner>>,
+    pub method_policies: Arc<MethodPolicies>,
+    pub fork_safe_mode: Arc<ForkSafeMode>,
+    pub rpc_statistics: Arc<CallStatistics>,
 }
 
-impl ApiDependencies {
-    pub fn extend_api(&self, enable_devel_api: bool, handler: &mut MetaIoHandler<(), impl Middleware<()>>) {
+impl ApiSet for ApiDependencies {
+    fn method_policies(&self) -> &Arc<MethodPolicies> {
+        &self.method_policies
+    }
+
+    fn fork_safe_mode(&self) -> &Arc<ForkSafeMode> {
+        &self.fork_safe_mode
+    }
+
+    fn rpc_statistics(&self) -> &Arc<CallStatistics> {
+        &self.rpc_statistics
+    }
+
+    fn extend_api(
+        &self,
+        transport: Transport,
+        enable_devel_api: bool,
+        handler: &mut MetaIoHandler<Metadata, impl Middleware<Metadata>>,
+    ) {
         use crpc::v1::*;
-        handler.extend_with(ChainClient::new(Arc::clone(&self.client)).to_delegate());
-        handler.extend_with(MempoolClient::new(Arc::clone(&self.client)).to_delegate());
+        // Only the registered methods are counted by their names in the statistics.
+        let statistics = &self.rpc_statistics;
+        extend_with(
+            handler,
+            statistics,
+            ChainClient::new(Arc::clone(&self.client), self.block_sync.clone()).to_delegate(),
+        );
+        extend_with(handler, statistics, MempoolClient::new(Arc::clone(&self.client)).to_delegate());
         if enable_devel_api {
-            handler.extend_with(
-                DevelClient::new(Arc::clone(&self.client), Arc::clone(&self.miner), self.block_sync.clone())
-                    .to_delegate(),
+            extend_with(
+                handler,
+                statistics,
+                DevelClient::new(
+                    Arc::clone(&self.client),
+                    Arc::clone(&self.miner),
+                    self.block_sync.clone(),
+                    self.enable_fault_injection,
+                )
+                .to_delegate(),
             );
         }
-        handler.extend_with(EngineClient::new(Arc::clone(&self.client), Arc::clone(&self.miner)).to_delegate());
-        handler.extend_with(MinerClient::new(Arc::clone(&self.client), Arc::clone(&self.miner)).to_delegate());
-        handler.extend_with(NetClient::new(Arc::clone(&self.network_control)).to_delegate());
-        handler.extend_with(
+        extend_with(
+            handler,
+            statistics,
+            EngineClient::new(Arc::clone(&self.client), Arc::clone(&self.miner), self.enable_force_view).to_delegate(),
+        );
+        extend_with(
+            handler,
+            statistics,
+            MinerClient::new(Arc::clone(&self.client), Arc::clone(&self.miner)).to_delegate(),
+        );
+        extend_with(handler, statistics, NetClient::new(Arc::clone(&self.network_control)).to_delegate());
+        extend_with(handler, statistics, NodeClient::new(Arc::clone(&self.health_monitor)).to_delegate());
+        extend_with(
+            handler,
+            statistics,
             AccountClient::new(Arc::clone(&self.account_provider), Arc::clone(&self.client), Arc::clone(&self.miner))
                 .to_delegate(),
         );
+        extend_with(handler, statistics, PubSubClient::new(Arc::clone(&self.transaction_subscriptions)).to_delegate());
+        // Changing the policies of the methods and the accounts is only possible through the local socket.
+        if transport == Transport::Ipc {
+            extend_with(
+                handler,
+                statistics,
+                RpcClient::new(Arc::clone(&self.method_policies), Arc::clone(statistics)).to_delegate(),
+            );
+            extend_with(
+                handler,
+                statistics,
+                AccountPolicyClient::new(Arc::clone(&self.client), Arc::clone(&self.miner)).to_delegate(),
+            );
+        }
+    }
+}
+
+/// The dependencies of the light node, which answers the restricted chain RPCs from the verified headers.
+pub struct LightApiDependencies {
+    pub light_sync: EventSender<LightClientEvent>,
+    pub network_control: Arc<NetworkControl>,
+    pub network_id: NetworkId,
+    pub method_policies: Arc<MethodPolicies>,
+    pub fork_safe_mode: Arc<ForkSafeMode>,
+    pub rpc_statistics: Arc<CallStatistics>,
+}
+
+impl ApiSet for LightApiDependencies {
+    fn method_policies(&self) -> &Arc<MethodPolicies> {
+        &self.method_policies
+    }
+
+    fn fork_safe_mode(&self) -> &Arc<ForkSafeMode> {
+        &self.fork_safe_mode
+    }
+
+    fn rpc_statistics(&self) -> &Arc<CallStatistics> {
+        &self.rpc_statistics
+    }
+
+    fn extend_api(
+        &self,
+        transport: Transport,
+        _enable_devel_api: bool,
+        handler: &mut MetaIoHandler<Metadata, impl Middleware<Metadata>>,
+    ) {
+        use crpc::v1::*;
+        let statistics = &self.rpc_statistics;
+        extend_with(handler, statistics, LightChainClient::new(self.light_sync.clone(), self.network_id).to_delegate());
+        extend_with(handler, statistics, NetClient::new(Arc::clone(&self.network_control)).to_delegate());
+        if transport == Transport::Ipc {
+            extend_with(
+                handler,
+                statistics,
+                RpcClient::new(Arc::clone(&self.method_policies), Arc::clone(statistics)).to_delegate(),
+            );
+        }
+    }
+}
+
+/// Serves `GET /health` of the metrics server with the same report as `node_health`.
+pub struct MetricsHealthCheck(pub Arc<HealthMonitor<Client, Miner>>);
+
+impl cmetrics::HealthCheck for MetricsHealthCheck {
+    fn check(&self) -> (bool, String) {
+        let health = self.0.check();
+        (health.is_healthy(), serde_json::to_string(&health).expect("NodeHealth is serializable"))
     }
 }
 
-pub fn setup_rpc<M: Middleware<()>>(mut handler: MetaIoHandler<(), M>) -> MetaIoHandler<(), M> {
+pub fn setup_rpc<M: Middleware<Metadata>>(
+    mut handler: MetaIoHandler<Metadata, M>,
+    statistics: &CallStatistics,
+) -> MetaIoHandler<Metadata, M> {
+    statistics.register_methods(vec!["ping", "version", "commitHash", "slog"]);
     handler.add_method("ping", |_params: Params| Ok(Value::String("pong".to_string())));
     handler.add_method("version", |_params: Params| Ok(Value::String(env!("CARGO_PKG_VERSION").to_string())));
     handler.add_method("commitHash", |_params: Params| Ok(Value::String(env!("VERGEN_SHA").to_string())));
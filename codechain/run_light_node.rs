@@ -0,0 +1,132 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ccore::ClientConfig;
+use clogger::{self, LoggerConfig};
+use cnetwork::RoutingTable;
+use crpc::v1::{CallStatistics, ForkSafeMode};
+use csync::LightClientExtension;
+use ctimer::TimerLoop;
+
+use crate::config::Config;
+use crate::constants::SHUTDOWN_TIMEOUT_IN_SECONDS;
+use crate::rpc::{rpc_http_start, rpc_ipc_start, rpc_ws_start};
+use crate::rpc_apis::LightApiDependencies;
+use crate::run_node::{discovery_start, network_start, open_db, wait_for_exit};
+use crate::shutdown::ShutdownSequence;
+
+/// Runs the node that keeps only the finalized headers and the validators of the terms.
+/// The blocks and the state are never stored; the database only keeps the discovered peers.
+pub fn run_light_node(config: Config, timer_loop: TimerLoop) -> Result<(), String> {
+    let scheme = match &config.operating.chain {
+        Some(chain) => chain.scheme()?,
+        None => return Err("chain is not specified".to_string()),
+    };
+    if config.network.disable.unwrap() {
+        return Err("A light node cannot run without the network".to_string())
+    }
+
+    let instance_id = config.operating.instance_id.unwrap_or(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Current time should be later than unix epoch")
+            .subsec_nanos() as usize,
+    );
+    let json_log = config.operating.log_format.as_ref().map(String::as_str) == Some("json");
+    clogger::init(&LoggerConfig::new(instance_id, json_log), None).expect("Logger must be successfully initialized");
+
+    let network_id = scheme.genesis_params().network_id();
+    let routing_table = RoutingTable::new();
+    let service = network_start(network_id, timer_loop, &config.network_config()?, Arc::clone(&routing_table))?;
+
+    let mut maybe_discovered_peers = None;
+    if config.network.discovery.unwrap() {
+        let db = open_db(&config.operating, &ClientConfig::default())?;
+        if let Some(store) = discovery_start(&service, &config.network, Arc::clone(&routing_table), db)? {
+            maybe_discovered_peers = Some((store, routing_table));
+        }
+    } else {
+        cwarn!(DISCOVERY, "Light node runs without discovery extension");
+    }
+
+    let light_sync = {
+        let genesis = scheme.genesis_header();
+        let initial_validators = scheme.engine.initial_validators();
+        service.register_extension(move |api| LightClientExtension::new(genesis, &initial_validators, api))
+    };
+
+    let rpc_apis_deps = Arc::new(LightApiDependencies {
+        light_sync,
+        network_control: Arc::clone(&service),
+        network_id,
+        method_policies: Arc::new(config.method_policies()),
+        // The light node follows only the finalized headers, which cannot be on a minority fork.
+        fork_safe_mode: Arc::new(ForkSafeMode::new(None, false)),
+        rpc_statistics: Arc::new(CallStatistics::new(config.rpc_slow_call_threshold())),
+    });
+
+    let rpc_server = {
+        if !config.rpc.disable.unwrap() {
+            Some(rpc_http_start(config.rpc_http_config(), config.rpc.enable_devel_api, &*rpc_apis_deps)?)
+        } else {
+            None
+        }
+    };
+
+    let ipc_server = {
+        if !config.ipc.disable.unwrap() {
+            Some(rpc_ipc_start(&config.rpc_ipc_config(), config.rpc.enable_devel_api, &*rpc_apis_deps)?)
+        } else {
+            None
+        }
+    };
+
+    let ws_server = {
+        if !config.ws.disable.unwrap() {
+            Some(rpc_ws_start(&config.rpc_ws_config(), config.rpc.enable_devel_api, &*rpc_apis_deps)?)
+        } else {
+            None
+        }
+    };
+
+    drop(scheme);
+
+    cinfo!(TEST_SCRIPT, "Initialization complete");
+
+    wait_for_exit();
+
+    cinfo!(SHUTDOWN, "Shutting down");
+    let mut shutdown = ShutdownSequence::new(Duration::from_secs(SHUTDOWN_TIMEOUT_IN_SECONDS));
+    shutdown.add_phase("Stop RPC servers", move || {
+        drop(rpc_server);
+        drop(ipc_server);
+        drop(ws_server);
+    });
+    {
+        let network = Arc::clone(&service);
+        shutdown.add_phase("Disconnect peers", move || network.disconnect_all());
+        shutdown.add_phase("Remove the port mapping", move || service.remove_port_mapping());
+    }
+    if let Some((store, routing_table)) = maybe_discovered_peers {
+        shutdown.add_phase("Save the discovered peers", move || store.save(&routing_table.discovered_peers()));
+    }
+    shutdown.run();
+
+    Ok(())
+}
@@ -17,20 +17,21 @@
 use std::fs;
 use std::path::Path;
 use std::sync::{Arc, Weak};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use ccore::{
     AccountProvider, AccountProviderError, BlockId, ChainNotify, Client, ClientConfig, ClientService, EngineInfo,
-    EngineType, Miner, MinerService, Scheme, Stratum, StratumConfig, StratumError, NUM_COLUMNS,
+    EngineType, Miner, MinerService, Scheme, Stratum, StratumConfig, StratumError, COL_EXTRA, NUM_COLUMNS,
 };
-use cdiscovery::{Config, Discovery};
+use cdiscovery::{Config, Discovery, PeerStore};
 use ckey::{Address, NetworkId, PlatformAddress};
 use ckeystore::accounts_dir::RootDiskDirectory;
 use ckeystore::KeyStore;
 use clap::ArgMatches;
 use clogger::{self, EmailAlarm, EmailAlarmConfig, LoggerConfig};
-use cnetwork::{Filters, NetworkConfig, NetworkControl, NetworkService, RoutingTable, SocketAddr};
-use csync::{BlockSyncExtension, BlockSyncSender, SnapshotService, TransactionSyncExtension};
+use cnetwork::{Filters, NetworkConfig, NetworkControl, NetworkService, PeerExchangeConfig, RoutingTable, SocketAddr};
+use crpc::v1::{CallStatistics, ForkSafeMode, HealthMonitor, TransactionSubscriptions};
+use csync::{BlockSyncExtension, BlockSyncSender, LightServerExtension, SnapshotService, TransactionSyncExtension};
 use ctimer::TimerLoop;
 use ctrlc::CtrlC;
 use fdlimit::raise_fd_limit;
@@ -39,13 +40,15 @@ use kvdb_rocksdb::{Database, DatabaseConfig};
 use parking_lot::{Condvar, Mutex};
 
 use crate::config::{self, load_config};
-use crate::constants::{DEFAULT_DB_PATH, DEFAULT_KEYS_PATH};
+use crate::constants::{DEFAULT_DB_PATH, DEFAULT_KEYS_PATH, SHUTDOWN_TIMEOUT_IN_SECONDS};
 use crate::dummy_network_service::DummyNetworkService;
 use crate::json::PasswordFile;
 use crate::rpc::{rpc_http_start, rpc_ipc_start, rpc_ws_start};
-use crate::rpc_apis::ApiDependencies;
+use crate::rpc_apis::{ApiDependencies, MetricsHealthCheck};
+use crate::run_light_node::run_light_node;
+use crate::shutdown::ShutdownSequence;
 
-fn network_start(
+pub fn network_start(
     network_id: NetworkId,
     timer_loop: TimerLoop,
     cfg: &NetworkConfig,
@@ -63,17 +66,24 @@ fn network_start(
         cfg.max_peers,
         filters,
         routing_table,
+        cfg.nat,
+        cfg.message_size_limits.clone(),
+        cfg.rekey_policy,
+        cfg.connection_log_size,
+        cfg.bandwidth_limits,
+        cfg.bandwidth_limits_path.clone(),
     )
     .map_err(|e| format!("Network service error: {:?}", e))?;
 
     Ok(service)
 }
 
-fn discovery_start(
+pub fn discovery_start(
     service: &NetworkService,
     cfg: &config::Network,
     routing_table: Arc<RoutingTable>,
-) -> Result<(), String> {
+    db: Arc<KeyValueDB>,
+) -> Result<Option<Arc<PeerStore>>, String> {
     let config = Config {
         bucket_size: cfg.discovery_bucket_size.unwrap(),
         t_refresh: cfg.discovery_refresh.unwrap(),
@@ -82,10 +92,14 @@ fn discovery_start(
         Some("unstructured") => false,
         Some("kademlia") => true,
         Some(discovery_type) => return Err(format!("Unknown discovery {}", discovery_type)),
-        None => return Ok(()),
+        None => return Ok(None),
     };
-    service.register_extension(move |api| Discovery::new(routing_table, config, api, use_kademlia));
-    Ok(())
+    let store = Arc::new(PeerStore::new(db, COL_EXTRA));
+    {
+        let store = Arc::clone(&store);
+        service.register_extension(move |api| Discovery::new(routing_table, config, api, use_kademlia, store));
+    }
+    Ok(Some(store))
 }
 
 fn client_start(
@@ -100,6 +114,7 @@ fn client_start(
     let service = ClientService::start(client_config, &scheme, db, miner, reseal_timer.clone())
         .map_err(|e| format!("Client service error: {}", e))?;
     reseal_timer.set_handler(Arc::downgrade(&service.client()));
+    service.client().start_scheduled_transactions_timer();
 
     Ok(service)
 }
@@ -124,7 +139,7 @@ fn new_miner(
     ap: Arc<AccountProvider>,
     db: Arc<KeyValueDB>,
 ) -> Result<Arc<Miner>, String> {
-    let miner = Miner::new(config.miner_options()?, scheme, Some(ap), db);
+    let miner = Miner::new(config.miner_options()?, scheme, Some(Arc::clone(&ap)), db);
 
     if !config.mining.disable.unwrap() {
         match miner.engine_type() {
@@ -150,16 +165,32 @@ fn new_miner(
                         .to_string())
                 }
             },
-            EngineType::Solo => miner
-                .set_author(config.mining.author.map_or(Address::default(), PlatformAddress::into_address))
-                .expect("set_author never fails when Solo is used"),
+            EngineType::Solo => {
+                // The engine signer is needed only when the authors are rotated.
+                if let Some(ref engine_signer) = config.mining.engine_signer {
+                    let address = (*engine_signer).into_address();
+                    match ap.get_unlocked_account(&address) {
+                        Err(AccountProviderError::NotUnlocked) => {
+                            return Err(
+                                "The account is not unlocked. Specify the password path using --password-path option."
+                                    .to_string(),
+                            )
+                        }
+                        Err(e) => return Err(format!("{}", e)),
+                        Ok(_) => scheme.engine.set_signer(Arc::clone(&ap), address),
+                    }
+                }
+                miner
+                    .set_author(config.mining.author.map_or(Address::default(), PlatformAddress::into_address))
+                    .expect("set_author never fails when Solo is used")
+            }
         }
     }
 
     Ok(miner)
 }
 
-fn wait_for_exit() {
+pub fn wait_for_exit() {
     let exit = Arc::new((Mutex::new(()), Condvar::new()));
 
     // Handle possible exits
@@ -224,6 +255,9 @@ pub fn run_node(matches: &ArgMatches) -> Result<(), String> {
     let timer_loop = TimerLoop::new(2);
 
     let config = load_config(matches)?;
+    if config.operating.light.unwrap() {
+        return run_light_node(config, timer_loop)
+    }
 
     let time_gap_params = config.mining.create_time_gaps();
     let scheme = match &config.operating.chain {
@@ -231,6 +265,10 @@ pub fn run_node(matches: &ArgMatches) -> Result<(), String> {
         None => return Err("chain is not specified".to_string()),
     };
     scheme.engine.register_time_gap_config_to_worker(time_gap_params);
+    if config.mining.validator_standby.unwrap_or(false) {
+        // It must be set before the engine signer, so that nothing is signed in between.
+        scheme.engine.demote_to_standby().map_err(|e| format!("{}", e))?;
+    }
 
     let instance_id = config.operating.instance_id.unwrap_or(
         SystemTime::now()
@@ -252,7 +290,8 @@ pub fn run_node(matches: &ArgMatches) -> Result<(), String> {
     } else {
         None
     };
-    clogger::init(&LoggerConfig::new(instance_id), email_alarm.clone())
+    let json_log = config.operating.log_format.as_ref().map(String::as_str) == Some("json");
+    clogger::init(&LoggerConfig::new(instance_id, json_log), email_alarm.clone())
         .expect("Logger must be successfully initialized");
     if let Some(email_alarm) = email_alarm {
         panic_hook::set_with_email_alarm(email_alarm);
@@ -265,7 +304,13 @@ pub fn run_node(matches: &ArgMatches) -> Result<(), String> {
     let ap = prepare_account_provider(&keys_path)?;
     unlock_accounts(&*ap, &pf)?;
 
-    let client_config: ClientConfig = Default::default();
+    let mut client_config: ClientConfig = Default::default();
+    client_config.max_reorg_depth = config.operating.max_reorg_depth;
+    client_config.seal_checkpoint_interval = config.operating.seal_checkpoint_interval.unwrap();
+    client_config.address_history = config.operating.address_history.unwrap();
+    client_config.action_index = config.operating.action_index.unwrap();
+    client_config.state_warming_max_entries = config.operating.state_warming_max_entries.unwrap();
+    client_config.state_warming_timeout = Duration::from_millis(config.operating.state_warming_timeout.unwrap());
     let db = open_db(&config.operating, &client_config)?;
 
     let miner = new_miner(&config, &scheme, ap.clone(), Arc::clone(&db))?;
@@ -274,6 +319,8 @@ pub fn run_node(matches: &ArgMatches) -> Result<(), String> {
 
     let mut _maybe_sync = None;
     let mut maybe_sync_sender = None;
+    let mut maybe_network = None;
+    let mut maybe_discovered_peers = None;
 
     scheme.engine.register_chain_notify(client.client().as_ref());
 
@@ -287,43 +334,87 @@ pub fn run_node(matches: &ArgMatches) -> Result<(), String> {
             let service = network_start(network_id, timer_loop, &network_config, Arc::clone(&routing_table))?;
 
             if config.network.discovery.unwrap() {
-                discovery_start(&service, &config.network, routing_table)?;
+                let db = Arc::clone(client.client().db());
+                if let Some(store) = discovery_start(&service, &config.network, Arc::clone(&routing_table), db)? {
+                    maybe_discovered_peers = Some((store, routing_table));
+                }
             } else {
                 cwarn!(DISCOVERY, "Node runs without discovery extension");
             }
 
+            if config.network.peer_exchange.unwrap() {
+                service.start_peer_exchange(PeerExchangeConfig {
+                    share_private_addresses: config.network.peer_exchange_share_private.unwrap(),
+                    ..PeerExchangeConfig::default()
+                });
+            }
+
             if config.network.sync.unwrap() {
                 let sync_sender = {
                     let client = client.client();
-                    service.register_extension(move |api| BlockSyncExtension::new(client, api))
+                    let fork_monitor = config.fork_monitor_config();
+                    service.register_extension(move |api| BlockSyncExtension::new(client, api, fork_monitor))
                 };
                 let sync = Arc::new(BlockSyncSender::from(sync_sender.clone()));
                 client.client().add_notify(Arc::downgrade(&sync) as Weak<ChainNotify>);
                 _maybe_sync = Some(sync); // Hold sync to ensure it not to be destroyed.
                 maybe_sync_sender = Some(sync_sender);
+
+                let client = client.client();
+                service.register_extension(move |api| LightServerExtension::new(client, api));
             }
             if config.network.transaction_relay.unwrap() {
                 let client = client.client();
-                service.register_extension(move |api| TransactionSyncExtension::new(client, api));
+                let seen_transactions = miner.seen_transactions();
+                let relay_stats = miner.relay_stats();
+                service.register_extension(move |api| {
+                    TransactionSyncExtension::new(client, seen_transactions, relay_stats, api)
+                });
             }
 
+            scheme.engine.set_full_gossip(config.network.consensus_full_gossip.unwrap_or(false));
             scheme.engine.register_network_extension_to_service(&service);
 
+            maybe_network = Some(Arc::clone(&service));
             service
         } else {
             Arc::new(DummyNetworkService::new())
         }
     };
 
+    let transaction_subscriptions = TransactionSubscriptions::new();
+    {
+        let transaction_subscriptions = Arc::clone(&transaction_subscriptions);
+        miner.add_transaction_event_listener(Box::new(move |events| transaction_subscriptions.notify(events)));
+    }
+
+    let health_monitor = Arc::new(HealthMonitor::new(
+        client.client(),
+        Arc::clone(&miner),
+        Arc::clone(&network_service),
+        maybe_sync_sender.clone(),
+        config.health_thresholds(),
+    ));
+    let fork_safe_mode = Arc::new(ForkSafeMode::new(maybe_sync_sender.clone(), config.rpc.strict_fork_safe_mode));
+    let rpc_statistics = Arc::new(CallStatistics::new(config.rpc_slow_call_threshold()));
+    cmetrics::METRICS.register_collector(Arc::clone(&rpc_statistics) as Arc<cmetrics::Collector>);
+
     let rpc_apis_deps = Arc::new(ApiDependencies {
         client: client.client(),
         miner: Arc::clone(&miner),
         network_control: Arc::clone(&network_service),
         account_provider: ap,
         block_sync: maybe_sync_sender,
+        enable_force_view: config.rpc.enable_force_view,
+        enable_fault_injection: config.rpc.enable_fault_injection,
+        transaction_subscriptions,
+        health_monitor: Arc::clone(&health_monitor),
+        method_policies: Arc::new(config.method_policies()),
+        fork_safe_mode,
+        rpc_statistics,
     });
 
-    let _rpc_server = {
+    let rpc_server = {
         if !config.rpc.disable.unwrap() {
             Some(rpc_http_start(config.rpc_http_config(), config.rpc.enable_devel_api, &*rpc_apis_deps)?)
         } else {
@@ -331,7 +422,7 @@ pub fn run_node(matches: &ArgMatches) -> Result<(), String> {
         }
     };
 
-    let _ipc_server = {
+    let ipc_server = {
         if !config.ipc.disable.unwrap() {
             Some(rpc_ipc_start(&config.rpc_ipc_config(), config.rpc.enable_devel_api, &*rpc_apis_deps)?)
         } else {
@@ -339,7 +430,7 @@ pub fn run_node(matches: &ArgMatches) -> Result<(), String> {
         }
     };
 
-    let _ws_server = {
+    let ws_server = {
         if !config.ws.disable.unwrap() {
             Some(rpc_ws_start(&config.rpc_ws_config(), config.rpc.enable_devel_api, &*rpc_apis_deps)?)
         } else {
@@ -351,6 +442,12 @@ pub fn run_node(matches: &ArgMatches) -> Result<(), String> {
         stratum_start(&config.stratum_config(), &miner, client.client())?
     }
 
+    if !config.metrics.disable.unwrap() {
+        let address = config.metrics_address()?;
+        let health: Arc<cmetrics::HealthCheck> = Arc::new(MetricsHealthCheck(health_monitor));
+        cmetrics::start_http(&address, Some(health)).map_err(|e| format!("Metrics server error: {}", e))?;
+    }
+
     let _snapshot_service = {
         if !config.snapshot.disable.unwrap() {
             // FIXME: Let's make it load snapshot period dynamically to support changing the period.
@@ -371,5 +468,39 @@ pub fn run_node(matches: &ArgMatches) -> Result<(), String> {
 
     wait_for_exit();
 
+    cinfo!(SHUTDOWN, "Shutting down");
+    let mut shutdown = ShutdownSequence::new(Duration::from_secs(SHUTDOWN_TIMEOUT_IN_SECONDS));
+    shutdown.add_phase("Stop RPC servers", move || {
+        drop(rpc_server);
+        drop(ipc_server);
+        drop(ws_server);
+    });
+    {
+        let miner = Arc::clone(&miner);
+        shutdown.add_phase("Stop sealing", move || miner.stop_sealing());
+    }
+    {
+        let client = client.client();
+        shutdown.add_phase("Stop consensus engine", move || client.engine().stop());
+    }
+    if let Some(network) = maybe_network {
+        let network_to_disconnect = Arc::clone(&network);
+        shutdown.add_phase("Disconnect peers", move || network_to_disconnect.disconnect_all());
+        shutdown.add_phase("Remove the port mapping", move || network.remove_port_mapping());
+    }
+    if let Some((store, routing_table)) = maybe_discovered_peers {
+        shutdown.add_phase("Save the discovered peers", move || store.save(&routing_table.discovered_peers()));
+    }
+    {
+        let client = client.client();
+        shutdown.add_phase("Flush database", move || {
+            client.save_hot_state_keys();
+            if let Err(err) = client.db().flush() {
+                cerror!(SHUTDOWN, "Failed to flush the database: {}", err);
+            }
+        });
+    }
+    shutdown.run();
+
     Ok(())
 }
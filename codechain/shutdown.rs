@@ -0,0 +1,157 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::mpsc;
+use std::thread::Builder;
+use std::time::{Duration, Instant};
+
+type Phase = Box<FnOnce() + Send>;
+
+/// Runs the shutdown phases in the order they were added.
+///
+/// Every phase runs on a dedicated thread. If the whole sequence doesn't finish within the timeout,
+/// the remaining phases are abandoned and the caller is expected to exit the process.
+pub struct ShutdownSequence {
+    timeout: Duration,
+    phases: Vec<(&'static str, Phase)>,
+}
+
+impl ShutdownSequence {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            phases: Vec::new(),
+        }
+    }
+
+    pub fn add_phase<F>(&mut self, name: &'static str, phase: F)
+    where
+        F: FnOnce() + Send + 'static, {
+        self.phases.push((name, Box::new(phase)));
+    }
+
+    /// Returns false if the sequence was forced to stop because of the timeout.
+    pub fn run(self) -> bool {
+        let deadline = Instant::now() + self.timeout;
+        let (sender, receiver) = mpsc::channel();
+        let phases = self.phases;
+        let names: Vec<_> = phases.iter().map(|(name, _)| *name).collect();
+
+        Builder::new()
+            .name("shutdown".to_string())
+            .spawn(move || {
+                for (index, (name, phase)) in phases.into_iter().enumerate() {
+                    cinfo!(SHUTDOWN, "Start phase {}: {}", index + 1, name);
+                    let started_at = Instant::now();
+                    phase();
+                    cinfo!(SHUTDOWN, "Finish phase {}: {} ({:?})", index + 1, name, started_at.elapsed());
+                    if sender.send(index).is_err() {
+                        // The sequence has already timed out.
+                        return
+                    }
+                }
+            })
+            .expect("Shutdown thread must be spawned");
+
+        for (index, name) in names.iter().enumerate() {
+            let now = Instant::now();
+            let remaining = if deadline > now {
+                deadline - now
+            } else {
+                Duration::from_secs(0)
+            };
+            match receiver.recv_timeout(remaining) {
+                Ok(finished) => debug_assert_eq!(index, finished),
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    cwarn!(
+                        SHUTDOWN,
+                        "Shutdown timed out in phase {}: {}. Skip the remaining phases: {:?}",
+                        index + 1,
+                        name,
+                        &names[index + 1..]
+                    );
+                    return false
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    cerror!(SHUTDOWN, "Phase {}: {} panicked", index + 1, name);
+                    return false
+                }
+            }
+        }
+        cinfo!(SHUTDOWN, "Shutdown complete");
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use super::*;
+
+    fn recorder() -> (Arc<Mutex<Vec<&'static str>>>, impl Fn(&'static str) -> Box<FnOnce() + Send>) {
+        let history = Arc::new(Mutex::new(Vec::new()));
+        let h = Arc::clone(&history);
+        let hook = move |name: &'static str| -> Box<FnOnce() + Send> {
+            let h = Arc::clone(&h);
+            Box::new(move || h.lock().unwrap().push(name))
+        };
+        (history, hook)
+    }
+
+    #[test]
+    fn phases_run_in_order() {
+        let (history, hook) = recorder();
+        let mut sequence = ShutdownSequence::new(Duration::from_secs(10));
+        sequence.add_phase("rpc", hook("rpc"));
+        sequence.add_phase("miner", hook("miner"));
+        sequence.add_phase("consensus", hook("consensus"));
+        sequence.add_phase("network", hook("network"));
+        sequence.add_phase("db", hook("db"));
+
+        assert!(sequence.run());
+        assert_eq!(*history.lock().unwrap(), vec!["rpc", "miner", "consensus", "network", "db"]);
+    }
+
+    #[test]
+    fn timeout_forces_the_remaining_phases() {
+        let (history, hook) = recorder();
+        let mut sequence = ShutdownSequence::new(Duration::from_millis(100));
+        sequence.add_phase("rpc", hook("rpc"));
+        sequence.add_phase("stuck", || thread::sleep(Duration::from_secs(1)));
+        sequence.add_phase("db", hook("db"));
+
+        assert!(!sequence.run());
+        assert_eq!(*history.lock().unwrap(), vec!["rpc"]);
+
+        // The stuck phase finishes later, but the abandoned phases never run.
+        thread::sleep(Duration::from_millis(1500));
+        assert_eq!(*history.lock().unwrap(), vec!["rpc"]);
+    }
+
+    #[test]
+    fn panicking_phase_stops_the_sequence() {
+        let (history, hook) = recorder();
+        let mut sequence = ShutdownSequence::new(Duration::from_secs(10));
+        sequence.add_phase("rpc", hook("rpc"));
+        sequence.add_phase("panic", || panic!("intended"));
+        sequence.add_phase("db", hook("db"));
+
+        assert!(!sequence.run());
+        assert_eq!(*history.lock().unwrap(), vec!["rpc"]);
+    }
+}
@@ -23,7 +23,10 @@ use std::time::{Duration, Instant};
 
 use ckey::{public_to_address, Address, Error as KeyError, Generator, KeyPair, Password, Private, Public, Random};
 use ckeystore::accounts_dir::MemoryDirectory;
-use ckeystore::{DecryptedAccount, Error as KeystoreError, KeyStore, SecretStore, SimpleSecretStore};
+use ckeystore::{
+    parse_seed, DecryptedAccount, DerivedAccount, Error as KeystoreError, KeyStore, SecretStore, SimpleSecretStore,
+    Uuid,
+};
 use parking_lot::RwLock;
 
 /// Type of unlock.
@@ -146,6 +149,21 @@ impl AccountProvider {
         Ok(self.keystore.import_wallet(json, password, false)?)
     }
 
+    /// Imports a BIP39 mnemonic or a hex seed, which is encrypted with the password.
+    pub fn import_seed(&self, mnemonic_or_seed: &str, password: &Password) -> Result<Uuid, Error> {
+        let seed = parse_seed(mnemonic_or_seed)?;
+        Ok(self.keystore.import_seed(&seed, password)?)
+    }
+
+    /// Derives a new account from the seed. It's signed with the password of the seed.
+    pub fn derive_account(&self, seed: &Uuid, password: &Password) -> Result<DerivedAccount, Error> {
+        Ok(self.keystore.derive_account(seed, password)?)
+    }
+
+    pub fn derived_accounts(&self, seed: &Uuid) -> Result<Vec<DerivedAccount>, Error> {
+        Ok(self.keystore.derived_accounts(seed)?)
+    }
+
     pub fn change_password(
         &self,
         address: Address,
@@ -285,4 +303,17 @@ mod tests {
         assert!(ap.get_account(&kp.address(), None).is_ok());
         assert!(ap.get_account(&kp.address(), None).is_ok());
     }
+
+    #[test]
+    fn unlock_derived_account() {
+        let ap = AccountProvider::transient_provider();
+        let seed = ap.import_seed("0x000102030405060708090a0b0c0d0e0f", &"seed".into()).unwrap();
+        let address = ap.derive_account(&seed, &"seed".into()).unwrap().address;
+        assert!(ap.has_account(&address).unwrap());
+        assert!(ap.unlock_account_temporarily(address, "wrong".into()).is_err());
+        assert!(ap.unlock_account_temporarily(address, "seed".into()).is_ok());
+        assert!(ap.get_account(&address, None).is_ok());
+        assert!(ap.get_account(&address, None).is_err());
+        assert!(ap.get_account(&address, Some(&"seed".into())).is_ok());
+    }
 }
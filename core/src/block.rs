@@ -14,12 +14,12 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use ccrypto::BLAKE_NULL_RLP;
 use ckey::Address;
 use cmerkle::skewed_merkle_root;
-use cstate::{FindActionHandler, StateDB, StateError, StateWithCache, TopLevelState};
+use cstate::{ActionHandler, FindActionHandler, StateDB, StateError, StateWithCache, TopLevelState, TopStateView};
 use ctypes::errors::HistoryError;
 use ctypes::header::{Header, Seal};
 use ctypes::util::unexpected::Mismatch;
@@ -30,7 +30,7 @@ use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
 
 use super::invoice::Invoice;
 use crate::client::{EngineInfo, TermInfo};
-use crate::consensus::CodeChainEngine;
+use crate::consensus::{stake, CodeChainEngine};
 use crate::error::{BlockError, Error};
 use crate::transaction::{SignedTransaction, UnverifiedTransaction};
 
@@ -88,16 +88,20 @@ impl Decodable for Block {
 pub struct ExecutedBlock {
     header: Header,
     state: TopLevelState,
+    /// The stakes before the transactions of the block are executed.
+    /// They're kept only if the block distributes its fees by them.
+    parent_stakes: Option<HashMap<Address, u64>>,
     transactions: Vec<SignedTransaction>,
     invoices: Vec<Invoice>,
     transactions_set: HashSet<H256>,
 }
 
 impl ExecutedBlock {
-    fn new(state: TopLevelState, parent: &Header) -> ExecutedBlock {
+    fn new(state: TopLevelState, parent: &Header, parent_stakes: Option<HashMap<Address, u64>>) -> ExecutedBlock {
         ExecutedBlock {
             header: parent.generate_child(),
             state,
+            parent_stakes,
             transactions: Default::default(),
             invoices: Default::default(),
             transactions_set: Default::default(),
@@ -109,6 +113,12 @@ impl ExecutedBlock {
         &mut self.state
     }
 
+    /// Get the stakes at the parent block, which the transactions haven't changed.
+    /// None if the params of the parent don't distribute the fees by them.
+    pub fn parent_stakes(&self) -> Option<&HashMap<Address, u64>> {
+        self.parent_stakes.as_ref()
+    }
+
     pub fn transactions(&self) -> &[SignedTransaction] {
         &self.transactions
     }
@@ -122,6 +132,8 @@ impl ExecutedBlock {
 pub struct OpenBlock<'x> {
     block: ExecutedBlock,
     engine: &'x CodeChainEngine,
+    /// The ids of the action handlers whose `on_close_block` are skipped.
+    disabled_action_handlers: HashSet<u64>,
 }
 
 impl<'x> OpenBlock<'x> {
@@ -134,9 +146,19 @@ impl<'x> OpenBlock<'x> {
         extra_data: Bytes,
     ) -> Result<Self, Error> {
         let state = TopLevelState::from_existing(db, *parent.state_root()).map_err(StateError::from)?;
+        let parent_stakes = {
+            let metadata = state.metadata()?.expect("Metadata always exist");
+            let params = metadata.params().unwrap_or_else(|| engine.machine().genesis_common_params());
+            if params.distribute_fees_by_parent_stakes() {
+                Some(stake::get_stakes(&state)?)
+            } else {
+                None
+            }
+        };
         let mut r = OpenBlock {
-            block: ExecutedBlock::new(state, parent),
+            block: ExecutedBlock::new(state, parent, parent_stakes),
             engine,
+            disabled_action_handlers: Default::default(),
         };
 
         r.block.header.set_author(author);
@@ -232,6 +254,9 @@ impl<'x> OpenBlock<'x> {
         }
         let header = self.block.header().clone();
         for handler in self.engine.action_handlers() {
+            if self.disabled_action_handlers.contains(&handler.handler_id()) {
+                continue
+            }
             handler.on_close_block(self.block.state_mut(), &header, parent_header, parent_common_params).map_err(
                 |e| {
                     warn!("Encountered error in {}::on_close_block", handler.name());
@@ -272,6 +297,9 @@ impl<'x> OpenBlock<'x> {
         }
         let header = self.block.header().clone();
         for handler in self.engine.action_handlers() {
+            if self.disabled_action_handlers.contains(&handler.handler_id()) {
+                continue
+            }
             handler.on_close_block(self.block.state_mut(), &header, parent_header, parent_common_params).map_err(
                 |e| {
                     warn!("Encountered error in {}::on_close_block", handler.name());
@@ -353,6 +381,7 @@ impl ClosedBlock {
         OpenBlock {
             block,
             engine,
+            disabled_action_handlers: Default::default(),
         }
     }
 }
@@ -498,6 +527,40 @@ pub fn enact<C: ChainTimeInfo + EngineInfo + FindActionHandler + TermInfo>(
     b.populate_from(header);
     b.push_transactions(transactions, client, parent.number(), parent.timestamp())?;
 
+    let (parent_common_params, term_common_params) = common_params_for(header, client);
+    b.close_and_lock(parent, &parent_common_params, term_common_params.as_ref())
+}
+
+/// Re-execute the block given by block header and transactions with some of the action handlers disabled.
+/// Unlike `enact`, a failed transaction doesn't stop the execution, so every transaction has its invoice.
+/// The state is committed only to the given `db`, so the caller decides whether it is persisted.
+pub fn replay<C: ChainTimeInfo + EngineInfo + FindActionHandler + TermInfo>(
+    header: &Header,
+    transactions: &[SignedTransaction],
+    engine: &CodeChainEngine,
+    client: &C,
+    db: StateDB,
+    parent: &Header,
+    disabled_action_handlers: &HashSet<u64>,
+) -> Result<ClosedBlock, Error> {
+    let mut b = OpenBlock::try_new(engine, db, parent, Address::default(), vec![])?;
+    b.disabled_action_handlers = disabled_action_handlers.clone();
+
+    b.populate_from(header);
+    let handlers = ActionHandlerFilter {
+        client,
+        disabled: disabled_action_handlers,
+    };
+    for tx in transactions {
+        // The error is recorded in the invoice.
+        let _ = b.push_transaction(tx.clone(), None, &handlers, parent.number(), parent.timestamp());
+    }
+
+    let (parent_common_params, term_common_params) = common_params_for(header, client);
+    b.close(parent, &parent_common_params, term_common_params.as_ref())
+}
+
+fn common_params_for<C: EngineInfo + TermInfo>(header: &Header, client: &C) -> (CommonParams, Option<CommonParams>) {
     let parent_common_params = client.common_params((*header.parent_hash()).into()).unwrap();
     let term_common_params = {
         let block_number = client
@@ -509,7 +572,32 @@ pub fn enact<C: ChainTimeInfo + EngineInfo + FindActionHandler + TermInfo>(
             Some(client.common_params((block_number).into()).expect("Common params should exist"))
         }
     };
-    b.close_and_lock(parent, &parent_common_params, term_common_params.as_ref())
+    (parent_common_params, term_common_params)
+}
+
+/// Hides the disabled action handlers from the transactions.
+struct ActionHandlerFilter<'a, C> {
+    client: &'a C,
+    disabled: &'a HashSet<u64>,
+}
+
+impl<'a, C: ChainTimeInfo> ChainTimeInfo for ActionHandlerFilter<'a, C> {
+    fn transaction_block_age(&self, tracker: &H256, parent_block_number: BlockNumber) -> Option<u64> {
+        self.client.transaction_block_age(tracker, parent_block_number)
+    }
+
+    fn transaction_time_age(&self, tracker: &H256, parent_timestamp: u64) -> Option<u64> {
+        self.client.transaction_time_age(tracker, parent_timestamp)
+    }
+}
+
+impl<'a, C: FindActionHandler> FindActionHandler for ActionHandlerFilter<'a, C> {
+    fn find_action_handler_for(&self, id: u64) -> Option<&ActionHandler> {
+        if self.disabled.contains(&id) {
+            return None
+        }
+        self.client.find_action_handler_for(id)
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,346 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::sync::Arc;
+
+use ctypes::transaction::Action;
+use ctypes::BlockNumber;
+use kvdb::{DBTransaction, KeyValueDB};
+use parking_lot::Mutex;
+use primitives::H256;
+use rlp::Encodable;
+
+use super::block_info::BestBlockChanged;
+use super::body_db::BodyProvider;
+use super::extras::{ActionIndexEntry, ActionIndexPosition};
+use super::headerchain::HeaderProvider;
+use crate::db::{self, Writable};
+use crate::UnverifiedTransaction;
+
+const ACTION_INDEX_BACKFILL_KEY: &[u8] = b"action-index-backfill";
+
+/// The names of the built-in actions, which `Action::name` returns, with their RLP tags.
+/// The tags are written in the keys of the index.
+const BUILTIN_ACTIONS: [(&str, u8); 15] = [
+    ("pay", 0x02),
+    ("setRegularKey", 0x03),
+    ("createShard", 0x04),
+    ("setShardOwners", 0x05),
+    ("setShardUsers", 0x06),
+    ("wrapCCC", 0x07),
+    ("store", 0x08),
+    ("remove", 0x09),
+    ("unwrapCCC", 0x11),
+    ("mintAsset", 0x13),
+    ("transferAsset", 0x14),
+    ("changeAssetScheme", 0x15),
+    ("composeAsset", 0x16),
+    ("decomposeAsset", 0x17),
+    ("increaseAssetSupply", 0x18),
+];
+const CUSTOM_TAG: u8 = 0xFF;
+
+/// The type of the actions that the index distinguishes.
+/// The custom actions are distinguished by their handlers, e.g. the stake actions are `custom:2`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub enum ActionType {
+    Builtin(&'static str),
+    Custom(u64),
+}
+
+impl ActionType {
+    pub fn of(action: &Action) -> Self {
+        if let Action::Custom {
+            handler_id,
+            ..
+        } = action
+        {
+            return ActionType::Custom(*handler_id)
+        }
+        ActionType::Builtin(action.name())
+    }
+
+    /// Parses the name of a built-in action, or `custom:` followed by the id of the handler.
+    pub fn from_name(name: &str) -> Option<Self> {
+        if name.starts_with("custom:") {
+            return name["custom:".len()..].parse().ok().map(ActionType::Custom)
+        }
+        BUILTIN_ACTIONS.iter().find(|(builtin, _)| *builtin == name).map(|(builtin, _)| ActionType::Builtin(*builtin))
+    }
+
+    pub fn from_tag(tag: u8, handler_id: u64) -> Option<Self> {
+        if tag == CUSTOM_TAG {
+            return Some(ActionType::Custom(handler_id))
+        }
+        BUILTIN_ACTIONS.iter().find(|(_, builtin)| *builtin == tag).map(|(name, _)| ActionType::Builtin(*name))
+    }
+
+    pub fn tag(self) -> u8 {
+        match self {
+            ActionType::Builtin(name) => {
+                let builtin = BUILTIN_ACTIONS.iter().find(|(builtin, _)| *builtin == name);
+                builtin.expect("All the built-in actions are listed").1
+            }
+            ActionType::Custom(_) => CUSTOM_TAG,
+        }
+    }
+
+    /// 0 for the built-in actions
+    pub fn handler_id(self) -> u64 {
+        match self {
+            ActionType::Builtin(_) => 0,
+            ActionType::Custom(handler_id) => handler_id,
+        }
+    }
+}
+
+impl fmt::Display for ActionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ActionType::Builtin(name) => write!(f, "{}", name),
+            ActionType::Custom(handler_id) => write!(f, "custom:{}", handler_id),
+        }
+    }
+}
+
+/// The progress of indexing the blocks imported before the index was enabled.
+/// The blocks imported after it are indexed when they're imported.
+#[derive(Debug, PartialEq, Clone, Copy, RlpEncodable, RlpDecodable)]
+pub struct ActionIndexBackfill {
+    /// The next block to index
+    pub next: BlockNumber,
+    /// The best block when the index was enabled
+    pub target: BlockNumber,
+}
+
+impl ActionIndexBackfill {
+    pub fn is_finished(&self) -> bool {
+        self.next > self.target
+    }
+}
+
+/// The index from the action types to the locations of the transactions on the canonical chain.
+///
+/// The index is kept only when it's enabled because it costs disk.
+pub struct ActionIndexDB {
+    /// None if the index is disabled
+    backfill: Mutex<Option<ActionIndexBackfill>>,
+
+    db: Arc<KeyValueDB>,
+}
+
+/// A page of the transactions of an action type
+#[derive(Debug, PartialEq, Clone)]
+pub struct ActionIndexPage {
+    pub entries: Vec<(ActionIndexPosition, ActionIndexEntry)>,
+    /// The position where the next page starts. None if there are no more entries.
+    pub next: Option<ActionIndexPosition>,
+}
+
+impl ActionIndexDB {
+    /// The blocks up to `best_block_number` are indexed by `backfill` if the index is enabled for the first time.
+    pub fn new(enabled: bool, db: Arc<KeyValueDB>, best_block_number: BlockNumber) -> Self {
+        let recorded = db
+            .get(db::COL_EXTRA, ACTION_INDEX_BACKFILL_KEY)
+            .expect("Low level database error. Some issue with disk?")
+            .map(|encoded| rlp::decode::<ActionIndexBackfill>(&encoded));
+        let mut batch = DBTransaction::new();
+        let backfill = match (enabled, recorded) {
+            (true, Some(backfill)) => Some(backfill),
+            (true, None) => {
+                // The entries may be left by the index enabled before.
+                // They can be stale because the reorgs in the meantime are not reflected.
+                for (key, _) in db
+                    .iter_from_prefix(db::COL_EXTRA, &ActionIndexPosition::INDEX_PREFIX)
+                    .take_while(|(key, _)| key.starts_with(&ActionIndexPosition::INDEX_PREFIX))
+                {
+                    batch.delete(db::COL_EXTRA, &key);
+                }
+                let backfill = ActionIndexBackfill {
+                    next: 0,
+                    target: best_block_number,
+                };
+                batch.put(db::COL_EXTRA, ACTION_INDEX_BACKFILL_KEY, &backfill.rlp_bytes());
+                Some(backfill)
+            }
+            (false, Some(_)) => {
+                // The blocks imported while it's disabled are not indexed, so the index is built again next time.
+                batch.delete(db::COL_EXTRA, ACTION_INDEX_BACKFILL_KEY);
+                None
+            }
+            (false, None) => None,
+        };
+        db.write(batch).expect("Low level database error. Some issue with disk?");
+        Self {
+            backfill: Mutex::new(backfill),
+            db,
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.backfill.lock().is_some()
+    }
+
+    /// Removes the entries of the retracted blocks and inserts the entries of the enacted blocks.
+    pub fn update_best_block<C>(&self, batch: &mut DBTransaction, best_block_changed: &BestBlockChanged, chain: &C)
+    where
+        C: HeaderProvider + BodyProvider, {
+        if !self.is_enabled() {
+            return
+        }
+        let block = match best_block_changed.best_block() {
+            Some(block) => block,
+            None => return,
+        };
+
+        if let BestBlockChanged::BranchBecomingCanonChain {
+            tree_route,
+            ..
+        } = best_block_changed
+        {
+            // The order here is important! The entries of the enacted blocks may have the same keys.
+            for hash in &tree_route.retracted {
+                let number = chain.block_number(hash).expect("Retracted block must be in database.");
+                let body = chain.block_body(hash).expect("Retracted block must be in database.");
+                for (position, _) in index_entries(number, *hash, body.transactions()) {
+                    Writable::delete::<ActionIndexEntry, _>(&mut *batch, db::COL_EXTRA, &position);
+                }
+            }
+            for hash in &tree_route.enacted {
+                let number = chain.block_number(hash).expect("Enacted block must be in database.");
+                let body = chain.block_body(hash).expect("Enacted block must be in database.");
+                for (position, entry) in index_entries(number, *hash, body.transactions()) {
+                    batch.write(db::COL_EXTRA, &position, &entry);
+                }
+            }
+        }
+
+        for (position, entry) in index_entries(block.header_view().number(), block.hash(), block.transactions()) {
+            batch.write(db::COL_EXTRA, &position, &entry);
+        }
+    }
+
+    /// Indexes at most `max_blocks` canonical blocks that were imported before the index was enabled.
+    /// The caller must keep the canonical chain from being changed meanwhile.
+    /// Returns None if the index is disabled.
+    pub fn backfill<C>(&self, chain: &C, max_blocks: u64) -> Option<ActionIndexBackfill>
+    where
+        C: HeaderProvider + BodyProvider, {
+        let mut backfill = self.backfill.lock();
+        let progress = backfill.as_mut()?;
+        if progress.is_finished() || max_blocks == 0 {
+            return Some(*progress)
+        }
+        let to = progress.target.min(progress.next + max_blocks - 1);
+        let mut batch = DBTransaction::new();
+        for number in progress.next..=to {
+            let hash = chain.block_hash(number).expect("Canonical block must be in database.");
+            let body = chain.block_body(&hash).expect("Canonical block must be in database.");
+            for (position, entry) in index_entries(number, hash, body.transactions()) {
+                batch.write(db::COL_EXTRA, &position, &entry);
+            }
+        }
+        progress.next = to + 1;
+        batch.put(db::COL_EXTRA, ACTION_INDEX_BACKFILL_KEY, &progress.rlp_bytes());
+        self.db.write(batch).expect("Low level database error. Some issue with disk?");
+        Some(*progress)
+    }
+
+    /// Returns None if the index is disabled.
+    pub fn backfill_progress(&self) -> Option<ActionIndexBackfill> {
+        *self.backfill.lock()
+    }
+
+    /// Returns at most `limit` entries starting from `from` and not after `to_block`.
+    /// The blocks that are not backfilled yet are missing.
+    /// Returns None if the index is disabled.
+    pub fn transactions(
+        &self,
+        from: &ActionIndexPosition,
+        to_block: BlockNumber,
+        limit: usize,
+    ) -> Option<ActionIndexPage> {
+        if !self.is_enabled() {
+            return None
+        }
+        let prefix = ActionIndexPosition::prefix(from.action_type);
+        let from_key = db::Key::<ActionIndexEntry>::key(from);
+        let positions = self
+            .db
+            .iter_from_prefix(db::COL_EXTRA, &from_key)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .filter_map(|(key, value)| Some((ActionIndexPosition::from_key(&key)?, value)))
+            .take_while(|(position, _)| position.block_number <= to_block);
+
+        let mut entries = Vec::new();
+        for (position, value) in positions {
+            if entries.len() == limit {
+                return Some(ActionIndexPage {
+                    entries,
+                    next: Some(position),
+                })
+            }
+            entries.push((position, rlp::decode(&value)));
+        }
+        Some(ActionIndexPage {
+            entries,
+            next: None,
+        })
+    }
+}
+
+fn index_entries(
+    block_number: BlockNumber,
+    block_hash: H256,
+    transactions: Vec<UnverifiedTransaction>,
+) -> impl Iterator<Item = (ActionIndexPosition, ActionIndexEntry)> {
+    transactions.into_iter().enumerate().map(move |(index, transaction)| {
+        (
+            ActionIndexPosition {
+                action_type: ActionType::of(&transaction.action),
+                block_number,
+                index,
+            },
+            ActionIndexEntry {
+                block_hash,
+                transaction_hash: transaction.hash(),
+            },
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn action_type_names_round_trip() {
+        for (name, tag) in BUILTIN_ACTIONS.iter() {
+            let action_type = ActionType::from_name(name).unwrap();
+            assert_eq!(*name, action_type.to_string());
+            assert_eq!(*tag, action_type.tag());
+            assert_eq!(Some(action_type), ActionType::from_tag(*tag, 0));
+        }
+        assert_eq!(Some(ActionType::Custom(2)), ActionType::from_name("custom:2"));
+        assert_eq!("custom:2", ActionType::Custom(2).to_string());
+        assert_eq!(Some(ActionType::Custom(2)), ActionType::from_tag(CUSTOM_TAG, 2));
+        assert_eq!(None, ActionType::from_name("custom"));
+        assert_eq!(None, ActionType::from_name("custom:stake"));
+        assert_eq!(None, ActionType::from_name("delegateCCC"));
+        assert_eq!(None, ActionType::from_tag(0x01, 0));
+    }
+}
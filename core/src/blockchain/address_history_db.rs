@@ -0,0 +1,285 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use ckey::{public_to_address, Address};
+use ctypes::transaction::Action;
+use ctypes::BlockNumber;
+use kvdb::{DBTransaction, KeyValueDB};
+use lru_cache::LruCache;
+use parking_lot::Mutex;
+use primitives::H256;
+use rlp::Encodable;
+
+use super::block_info::BestBlockChanged;
+use super::body_db::BodyProvider;
+use super::extras::{AddressHistoryEntry, AddressHistoryPosition};
+use super::headerchain::HeaderProvider;
+use crate::db::{self, Writable};
+use crate::{SignedTransaction, UnverifiedTransaction};
+
+const ADDRESS_HISTORY_BACKFILL_KEY: &[u8] = b"address-history-backfill";
+/// The number of the transactions whose signers are kept after their blocks are verified
+const SIGNER_CACHE_SIZE: usize = 16 * 1024;
+
+/// The progress of indexing the blocks imported before the index was enabled.
+/// The blocks imported after it are indexed when they're imported.
+#[derive(Debug, PartialEq, Clone, Copy, RlpEncodable, RlpDecodable)]
+pub struct AddressHistoryBackfill {
+    /// The next block to index
+    pub next: BlockNumber,
+    /// The best block when the index was enabled
+    pub target: BlockNumber,
+}
+
+impl AddressHistoryBackfill {
+    pub fn is_finished(&self) -> bool {
+        self.next > self.target
+    }
+}
+
+/// The index from the addresses to the locations of the transactions on the canonical chain.
+///
+/// The index is kept only when it's enabled because it costs disk.
+pub struct AddressHistoryDB {
+    /// None if the index is disabled
+    backfill: Mutex<Option<AddressHistoryBackfill>>,
+    /// The signers recovered when the blocks were verified, by the transaction hashes.
+    /// The signatures are recovered again only for the transactions that are not here.
+    signers: Mutex<LruCache<H256, Address>>,
+
+    db: Arc<KeyValueDB>,
+}
+
+/// A page of the history of an address
+#[derive(Debug, PartialEq, Clone)]
+pub struct AddressHistoryPage {
+    pub entries: Vec<(AddressHistoryPosition, AddressHistoryEntry)>,
+    /// The position where the next page starts. None if there are no more entries.
+    pub next: Option<AddressHistoryPosition>,
+}
+
+impl AddressHistoryDB {
+    /// The blocks up to `best_block_number` are indexed by `backfill` if the index is enabled for the first time.
+    pub fn new(enabled: bool, db: Arc<KeyValueDB>, best_block_number: BlockNumber) -> Self {
+        let recorded = db
+            .get(db::COL_EXTRA, ADDRESS_HISTORY_BACKFILL_KEY)
+            .expect("Low level database error. Some issue with disk?")
+            .map(|encoded| rlp::decode::<AddressHistoryBackfill>(&encoded));
+        let mut batch = DBTransaction::new();
+        let backfill = match (enabled, recorded) {
+            (true, Some(backfill)) => Some(backfill),
+            (true, None) => {
+                // The entries may be left by the index enabled before.
+                // They can be stale because the reorgs in the meantime are not reflected.
+                for (key, _) in db
+                    .iter_from_prefix(db::COL_EXTRA, &AddressHistoryPosition::INDEX_PREFIX)
+                    .take_while(|(key, _)| key.starts_with(&AddressHistoryPosition::INDEX_PREFIX))
+                {
+                    batch.delete(db::COL_EXTRA, &key);
+                }
+                let backfill = AddressHistoryBackfill {
+                    next: 0,
+                    target: best_block_number,
+                };
+                batch.put(db::COL_EXTRA, ADDRESS_HISTORY_BACKFILL_KEY, &backfill.rlp_bytes());
+                Some(backfill)
+            }
+            (false, Some(_)) => {
+                // The blocks imported while it's disabled are not indexed, so the index is built again next time.
+                batch.delete(db::COL_EXTRA, ADDRESS_HISTORY_BACKFILL_KEY);
+                None
+            }
+            (false, None) => None,
+        };
+        db.write(batch).expect("Low level database error. Some issue with disk?");
+        Self {
+            backfill: Mutex::new(backfill),
+            signers: Mutex::new(LruCache::new(SIGNER_CACHE_SIZE)),
+            db,
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.backfill.lock().is_some()
+    }
+
+    /// Keeps the signers of the verified transactions, so they're not recovered again when their block is indexed.
+    pub fn cache_signers(&self, transactions: &[SignedTransaction]) {
+        if !self.is_enabled() {
+            return
+        }
+        let mut signers = self.signers.lock();
+        for transaction in transactions {
+            signers.insert(transaction.hash(), public_to_address(&transaction.signer_public()));
+        }
+    }
+
+    /// Removes the entries of the retracted blocks and inserts the entries of the enacted blocks.
+    pub fn update_best_block<C>(&self, batch: &mut DBTransaction, best_block_changed: &BestBlockChanged, chain: &C)
+    where
+        C: HeaderProvider + BodyProvider, {
+        if !self.is_enabled() {
+            return
+        }
+        let block = match best_block_changed.best_block() {
+            Some(block) => block,
+            None => return,
+        };
+
+        if let BestBlockChanged::BranchBecomingCanonChain {
+            tree_route,
+            ..
+        } = best_block_changed
+        {
+            // The order here is important! The entries of the enacted blocks may have the same keys.
+            for hash in &tree_route.retracted {
+                let number = chain.block_number(hash).expect("Retracted block must be in database.");
+                let body = chain.block_body(hash).expect("Retracted block must be in database.");
+                for (position, _) in self.history_entries(number, *hash, body.transactions()) {
+                    Writable::delete::<AddressHistoryEntry, _>(&mut *batch, db::COL_EXTRA, &position);
+                }
+            }
+            for hash in &tree_route.enacted {
+                let number = chain.block_number(hash).expect("Enacted block must be in database.");
+                let body = chain.block_body(hash).expect("Enacted block must be in database.");
+                for (position, entry) in self.history_entries(number, *hash, body.transactions()) {
+                    batch.write(db::COL_EXTRA, &position, &entry);
+                }
+            }
+        }
+
+        for (position, entry) in self.history_entries(block.header_view().number(), block.hash(), block.transactions())
+        {
+            batch.write(db::COL_EXTRA, &position, &entry);
+        }
+    }
+
+    /// Indexes at most `max_blocks` canonical blocks that were imported before the index was enabled.
+    /// The caller must keep the canonical chain from being changed meanwhile.
+    /// Returns None if the index is disabled.
+    pub fn backfill<C>(&self, chain: &C, max_blocks: u64) -> Option<AddressHistoryBackfill>
+    where
+        C: HeaderProvider + BodyProvider, {
+        let mut backfill = self.backfill.lock();
+        let progress = backfill.as_mut()?;
+        if progress.is_finished() || max_blocks == 0 {
+            return Some(*progress)
+        }
+        let to = progress.target.min(progress.next + max_blocks - 1);
+        let mut batch = DBTransaction::new();
+        for number in progress.next..=to {
+            let hash = chain.block_hash(number).expect("Canonical block must be in database.");
+            let body = chain.block_body(&hash).expect("Canonical block must be in database.");
+            for (position, entry) in self.history_entries(number, hash, body.transactions()) {
+                batch.write(db::COL_EXTRA, &position, &entry);
+            }
+        }
+        progress.next = to + 1;
+        batch.put(db::COL_EXTRA, ADDRESS_HISTORY_BACKFILL_KEY, &progress.rlp_bytes());
+        self.db.write(batch).expect("Low level database error. Some issue with disk?");
+        Some(*progress)
+    }
+
+    /// Returns None if the index is disabled.
+    pub fn backfill_progress(&self) -> Option<AddressHistoryBackfill> {
+        *self.backfill.lock()
+    }
+
+    /// Returns at most `limit` entries starting from `from` and not after `to_block`.
+    /// The blocks that are not backfilled yet are missing.
+    /// Returns None if the index is disabled.
+    pub fn history(
+        &self,
+        from: &AddressHistoryPosition,
+        to_block: BlockNumber,
+        limit: usize,
+    ) -> Option<AddressHistoryPage> {
+        if !self.is_enabled() {
+            return None
+        }
+        let prefix = AddressHistoryPosition::prefix(&from.address);
+        let from_key = db::Key::<AddressHistoryEntry>::key(from);
+        let positions = self
+            .db
+            .iter_from_prefix(db::COL_EXTRA, &from_key)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .filter_map(|(key, value)| Some((AddressHistoryPosition::from_key(&key)?, value)))
+            .take_while(|(position, _)| position.block_number <= to_block);
+
+        let mut entries = Vec::new();
+        for (position, value) in positions {
+            if entries.len() == limit {
+                return Some(AddressHistoryPage {
+                    entries,
+                    next: Some(position),
+                })
+            }
+            entries.push((position, rlp::decode(&value)));
+        }
+        Some(AddressHistoryPage {
+            entries,
+            next: None,
+        })
+    }
+
+    /// The addresses related to the transaction: the signer and the receiver of the payment.
+    fn related_addresses(&self, transaction: &UnverifiedTransaction) -> Vec<Address> {
+        let mut addresses = Vec::with_capacity(2);
+        let cached = self.signers.lock().get_mut(&transaction.hash()).cloned();
+        let signer = cached.or_else(|| transaction.recover_public().ok().map(|public| public_to_address(&public)));
+        if let Some(signer) = signer {
+            addresses.push(signer);
+        }
+        if let Action::Pay {
+            receiver,
+            ..
+        } = &transaction.action
+        {
+            if !addresses.contains(receiver) {
+                addresses.push(*receiver);
+            }
+        }
+        addresses
+    }
+
+    fn history_entries(
+        &self,
+        block_number: BlockNumber,
+        block_hash: H256,
+        transactions: Vec<UnverifiedTransaction>,
+    ) -> Vec<(AddressHistoryPosition, AddressHistoryEntry)> {
+        let mut entries = Vec::new();
+        for (index, transaction) in transactions.into_iter().enumerate() {
+            let entry = AddressHistoryEntry {
+                block_hash,
+                transaction_hash: transaction.hash(),
+            };
+            for address in self.related_addresses(&transaction) {
+                entries.push((
+                    AddressHistoryPosition {
+                        address,
+                        block_number,
+                        index,
+                    },
+                    entry,
+                ));
+            }
+        }
+        entries
+    }
+}
@@ -18,27 +18,40 @@ use std::sync::Arc;
 
 use ctypes::BlockNumber;
 use kvdb::{DBTransaction, KeyValueDB};
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use primitives::H256;
 use rlp::RlpStream;
 
+use super::action_index_db::{ActionIndexBackfill, ActionIndexDB, ActionIndexPage};
+use super::address_history_db::{AddressHistoryBackfill, AddressHistoryDB, AddressHistoryPage};
 use super::block_info::BestBlockChanged;
 use super::body_db::{BodyDB, BodyProvider};
-use super::extras::{BlockDetails, TransactionAddress};
+use super::bytes_cache::{BytesCache, CacheBudgetError, CacheStats};
+use super::extras::{
+    ActionIndexPosition, AddressHistoryPosition, BlockDetails, SkippedSealVerification, TransactionAddress,
+};
 use super::headerchain::{HeaderChain, HeaderProvider};
+use super::integrity::{self, DatabaseAnomaly};
 use super::invoice_db::{InvoiceDB, InvoiceProvider};
+use super::reorg_protection::{Reorg, ReorgProtection, ReorgProtectionStatus};
 use super::route::{tree_route, ImportRoute};
 use crate::blockchain_info::BlockChainInfo;
 use crate::consensus::CodeChainEngine;
-use crate::db;
+use crate::db::{self, Readable, Writable};
 use crate::encoded;
 use crate::invoice::Invoice;
-use crate::transaction::LocalizedTransaction;
+use crate::transaction::{LocalizedTransaction, SignedTransaction};
 use crate::views::{BlockView, HeaderView};
 
 const BEST_BLOCK_KEY: &[u8] = b"best-block";
 const BEST_PROPOSAL_BLOCK_KEY: &[u8] = b"best-proposal-block";
 
+/// The cap of the sum of the budgets of the caches in front of the columns.
+pub const MAX_CACHE_BUDGET: usize = 1024 * 1024 * 1024;
+
+/// The columns that have a cache with a budget.
+const CACHED_COLUMNS: [Option<u32>; 2] = [db::COL_HEADERS, db::COL_BODIES];
+
 /// Structure providing fast access to blockchain data.
 ///
 /// **Does not do input data verification.**
@@ -51,14 +64,30 @@ pub struct BlockChain {
     headerchain: HeaderChain,
     body_db: BodyDB,
     invoice_db: InvoiceDB,
+    address_history_db: AddressHistoryDB,
+    action_index_db: ActionIndexDB,
+
+    reorg_protection: Arc<ReorgProtection>,
 
     pending_best_block_hash: RwLock<Option<H256>>,
     pending_best_proposal_block_hash: RwLock<Option<H256>>,
+
+    db: Arc<KeyValueDB>,
 }
 
 impl BlockChain {
     /// Create new instance of blockchain from given Genesis.
-    pub fn new(genesis: &[u8], db: Arc<KeyValueDB>) -> Self {
+    ///
+    /// The reorgs that retract more than `max_reorg_depth` canonical blocks are refused.
+    /// The index of the address histories is kept only if `address_history` is true,
+    /// and the index of the action types is kept only if `action_index` is true.
+    pub fn new(
+        genesis: &[u8],
+        db: Arc<KeyValueDB>,
+        max_reorg_depth: u64,
+        address_history: bool,
+        action_index: bool,
+    ) -> Self {
         let genesis_block = BlockView::new(genesis);
 
         // load best block
@@ -85,16 +114,26 @@ impl BlockChain {
             }
         };
 
+        let reorg_protection = Arc::new(ReorgProtection::new(max_reorg_depth));
+        let headerchain = HeaderChain::new(&genesis_block.header_view(), db.clone(), Arc::clone(&reorg_protection));
+        let best_block_number = headerchain.block_number(&best_block_hash).expect("Best block must be in database.");
+
         Self {
             best_block_hash: RwLock::new(best_block_hash),
             best_proposal_block_hash: RwLock::new(best_proposal_block_hash),
 
-            headerchain: HeaderChain::new(&genesis_block.header_view(), db.clone()),
+            headerchain,
             body_db: BodyDB::new(&genesis_block, db.clone()),
             invoice_db: InvoiceDB::new(db.clone()),
+            address_history_db: AddressHistoryDB::new(address_history, db.clone(), best_block_number),
+            action_index_db: ActionIndexDB::new(action_index, db.clone(), best_block_number),
+
+            reorg_protection,
 
             pending_best_block_hash: RwLock::new(None),
             pending_best_proposal_block_hash: RwLock::new(None),
+
+            db,
         }
     }
 
@@ -140,6 +179,8 @@ impl BlockChain {
         self.headerchain.insert_header(batch, &new_header, engine);
         self.body_db.insert_body(batch, &new_block);
         self.body_db.update_best_block(batch, &best_block_changed);
+        self.address_history_db.update_best_block(batch, &best_block_changed, self);
+        self.action_index_db.update_best_block(batch, &best_block_changed, self);
         for invoice in invoices {
             self.invoice_db.insert_invoice(batch, invoice.hash, invoice.tracker, invoice.error);
         }
@@ -157,6 +198,23 @@ impl BlockChain {
         ImportRoute::new(new_block_hash, &best_block_changed)
     }
 
+    /// Records that the seal of the block was not verified when it was imported.
+    pub fn insert_skipped_seal_verification(&self, batch: &mut DBTransaction, hash: &H256, checkpoint: BlockNumber) {
+        batch.write(db::COL_EXTRA, hash, &SkippedSealVerification {
+            checkpoint,
+        });
+    }
+
+    /// Removes the record after the seal of the block is verified.
+    pub fn remove_skipped_seal_verification(&self, batch: &mut DBTransaction, hash: &H256) {
+        Writable::delete::<SkippedSealVerification, _>(batch, db::COL_EXTRA, hash);
+    }
+
+    /// Returns the record if the seal of the block was not verified when it was imported.
+    pub fn skipped_seal_verification(&self, hash: &H256) -> Option<SkippedSealVerification> {
+        self.db.read(db::COL_EXTRA, hash)
+    }
+
     /// Apply pending insertion updates
     pub fn commit(&self) {
         ctrace!(BLOCKCHAIN, "Committing.");
@@ -185,6 +243,16 @@ impl BlockChain {
         let parent_hash_of_new_block = new_header.parent_hash();
         let parent_details_of_new_block = self.block_details(&parent_hash_of_new_block).expect("Invalid parent hash");
 
+        if self.reorg_protection.is_on_rejected_branch(new_header.hash(), &parent_hash_of_new_block) {
+            cwarn!(
+                BLOCKCHAIN,
+                "Block #{}({}) is on a branch refused by the reorg protection.",
+                new_header.number(),
+                new_header.hash()
+            );
+            return BestBlockChanged::None
+        }
+
         if parent_details_of_new_block.total_score + new_header.score() > self.best_proposal_block_detail().total_score
             && engine.can_change_canon_chain(&new_header)
         {
@@ -197,6 +265,18 @@ impl BlockChain {
             let prev_best_hash = self.best_block_hash();
             let route = tree_route(self, prev_best_hash, parent_hash_of_new_block)
                 .expect("blocks being imported always within recent history; qed");
+            if !route.retracted.is_empty() {
+                let reorg = Reorg {
+                    best_block_hash: prev_best_hash,
+                    best_block_number: self.best_block_detail().number,
+                    new_block_hash: new_header.hash(),
+                    new_block_number: new_header.number(),
+                    depth: route.retracted.len() as u64,
+                };
+                if !self.reorg_protection.allows(reorg, &route.enacted) {
+                    return BestBlockChanged::None
+                }
+            }
 
             let new_best_block_hash = engine.get_best_block_from_best_proposal_header(&new_header);
             let new_best_block = if new_best_block_hash != new_header.hash() {
@@ -268,6 +348,8 @@ impl BlockChain {
 
         self.headerchain.update_best_as_committed(batch, block_hash);
         self.body_db.update_best_block(batch, &best_block_changed);
+        self.address_history_db.update_best_block(batch, &best_block_changed, self);
+        self.action_index_db.update_best_block(batch, &best_block_changed, self);
 
         let mut pending_best_block_hash = self.pending_best_block_hash.write();
         batch.put(db::COL_EXTRA, BEST_BLOCK_KEY, &block_hash);
@@ -280,6 +362,117 @@ impl BlockChain {
         ImportRoute::new(block_hash, &best_block_changed)
     }
 
+    /// Get at most `limit` transactions of the address from the given position up to `to_block`.
+    /// Returns None if the index of the address histories is disabled.
+    pub fn address_history(
+        &self,
+        from: &AddressHistoryPosition,
+        to_block: BlockNumber,
+        limit: usize,
+    ) -> Option<AddressHistoryPage> {
+        self.address_history_db.history(from, to_block, limit)
+    }
+
+    /// Keeps the signers of the transactions of a verified block, so they're not recovered again when it's indexed.
+    pub fn cache_signers(&self, transactions: &[SignedTransaction]) {
+        self.address_history_db.cache_signers(transactions)
+    }
+
+    /// Indexes at most `max_blocks` canonical blocks that were imported before the index of the address histories
+    /// was enabled. The caller must hold the import lock. Returns None if the index is disabled.
+    pub fn backfill_address_history(&self, max_blocks: u64) -> Option<AddressHistoryBackfill> {
+        self.address_history_db.backfill(self, max_blocks)
+    }
+
+    /// Returns None if the index of the address histories is disabled.
+    pub fn address_history_backfill(&self) -> Option<AddressHistoryBackfill> {
+        self.address_history_db.backfill_progress()
+    }
+
+    /// Get at most `limit` transactions of the action type from the given position up to `to_block`.
+    /// Returns None if the index of the action types is disabled.
+    pub fn transactions_by_action_type(
+        &self,
+        from: &ActionIndexPosition,
+        to_block: BlockNumber,
+        limit: usize,
+    ) -> Option<ActionIndexPage> {
+        self.action_index_db.transactions(from, to_block, limit)
+    }
+
+    /// Indexes at most `max_blocks` canonical blocks that were imported before the index of the action types was
+    /// enabled. The caller must hold the import lock. Returns None if the index is disabled.
+    pub fn backfill_action_index(&self, max_blocks: u64) -> Option<ActionIndexBackfill> {
+        self.action_index_db.backfill(self, max_blocks)
+    }
+
+    /// Returns None if the index of the action types is disabled.
+    pub fn action_index_backfill(&self) -> Option<ActionIndexBackfill> {
+        self.action_index_db.backfill_progress()
+    }
+
+    /// Checks the headers, the bodies and the invoices of the canonical blocks from `from` to `to`.
+    pub fn check_integrity(&self, from: BlockNumber, to: BlockNumber) -> Vec<DatabaseAnomaly> {
+        integrity::check_blocks(&*self.db, from, to)
+    }
+
+    /// Finds the bodies without headers among the bodies whose hashes start with `prefix`.
+    pub fn find_orphaned_bodies(&self, prefix: u8) -> Vec<DatabaseAnomaly> {
+        integrity::find_orphaned_bodies(&*self.db, prefix)
+    }
+
+    /// Removes the bodies so that they can be downloaded again.
+    pub fn remove_bodies(&self, batch: &mut DBTransaction, hashes: &[H256]) {
+        self.body_db.remove_bodies(batch, hashes);
+    }
+
+    /// Writes the body of an imported block again, e.g. after it was removed by the repair.
+    /// The body must match the transactions root of the header.
+    pub fn restore_body(&self, batch: &mut DBTransaction, block: &BlockView) {
+        self.body_db.insert_body(batch, block);
+    }
+
+    pub fn reorg_protection_status(&self) -> ReorgProtectionStatus {
+        self.reorg_protection.status()
+    }
+
+    pub fn override_reorg_protection(&self) {
+        self.reorg_protection.override_protection()
+    }
+
+    fn cache(&self, col: Option<u32>) -> Option<&Mutex<BytesCache>> {
+        match col {
+            db::COL_HEADERS => Some(self.headerchain.header_cache()),
+            db::COL_BODIES => Some(self.body_db.body_cache()),
+            _ => None,
+        }
+    }
+
+    /// The statistics of the cache in front of the column, if the column has one.
+    pub fn cache_stats(&self, col: Option<u32>) -> Option<CacheStats> {
+        self.cache(col).map(|cache| cache.lock().stats())
+    }
+
+    /// Changes the budget of the cache in front of the column. The sum of the budgets can't exceed `MAX_CACHE_BUDGET`.
+    pub fn set_cache_budget(&self, col: Option<u32>, budget: usize) -> Result<(), CacheBudgetError> {
+        let cache = self.cache(col).ok_or(CacheBudgetError::NoCache)?;
+        let others: usize = CACHED_COLUMNS
+            .iter()
+            .filter(|other| **other != col)
+            .filter_map(|other| self.cache(*other))
+            .map(|other| other.lock().budget())
+            .sum();
+        let requested = others.saturating_add(budget);
+        if requested > MAX_CACHE_BUDGET {
+            return Err(CacheBudgetError::ExceedsCap {
+                cap: MAX_CACHE_BUDGET,
+                requested,
+            })
+        }
+        cache.lock().set_budget(budget);
+        Ok(())
+    }
+
     /// Returns general blockchain information
     pub fn chain_info(&self) -> BlockChainInfo {
         let best_block_hash = self.best_block_hash();
@@ -433,3 +626,518 @@ impl InvoiceProvider for BlockChain {
 }
 
 impl BlockProvider for BlockChain {}
+
+#[cfg(test)]
+mod tests {
+    use ckey::{public_to_address, Address, Generator, Public, Random};
+    use ctypes::transaction::{Action, Transaction};
+    use ctypes::Header;
+    use primitives::U256;
+
+    use super::*;
+    use crate::blockchain::action_index_db::ActionType;
+    use crate::blockchain::body_db::DEFAULT_BODY_CACHE_BUDGET;
+    use crate::scheme::Scheme;
+    use crate::tests::helpers::{create_test_block, create_test_block_with_data};
+    use crate::verification::SealCheckpoints;
+
+    fn new_chain(scheme: &Scheme, max_reorg_depth: u64) -> (BlockChain, Arc<KeyValueDB>) {
+        let db: Arc<KeyValueDB> = Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap()));
+        let chain = BlockChain::new(&scheme.genesis_block(), Arc::clone(&db), max_reorg_depth, true, true);
+        (chain, db)
+    }
+
+    fn build_branch(parent: &Header, length: usize, score: u64) -> Vec<Header> {
+        let mut headers: Vec<Header> = Vec::with_capacity(length);
+        for _ in 0..length {
+            let parent = headers.last().unwrap_or(parent);
+            let mut header = Header::new();
+            header.set_parent_hash(parent.hash());
+            header.set_number(parent.number() + 1);
+            header.set_score(U256::from(score));
+            headers.push(header);
+        }
+        headers
+    }
+
+    fn insert(chain: &BlockChain, db: &Arc<KeyValueDB>, header: &Header, scheme: &Scheme) {
+        let mut batch = DBTransaction::new();
+        chain.insert_block(&mut batch, &create_test_block(header), vec![], &*scheme.engine);
+        db.write(batch).unwrap();
+        chain.commit();
+    }
+
+    fn insert_with_transactions(
+        chain: &BlockChain,
+        db: &Arc<KeyValueDB>,
+        header: &Header,
+        transactions: &[SignedTransaction],
+        scheme: &Scheme,
+    ) {
+        let mut batch = DBTransaction::new();
+        chain.insert_block(
+            &mut batch,
+            &create_test_block_with_data(header, transactions, &[]),
+            vec![],
+            &*scheme.engine,
+        );
+        db.write(batch).unwrap();
+        chain.commit();
+    }
+
+    fn sign(action: Action) -> SignedTransaction {
+        let keypair = Random.generate().unwrap();
+        let tx = Transaction {
+            seq: 0,
+            fee: 10,
+            network_id: "tc".into(),
+            action,
+        };
+        SignedTransaction::new_with_sign(tx, keypair.private())
+    }
+
+    fn pay(receiver: Address) -> SignedTransaction {
+        sign(Action::Pay {
+            receiver,
+            quantity: 1,
+        })
+    }
+
+    fn custom(handler_id: u64) -> SignedTransaction {
+        sign(Action::Custom {
+            handler_id,
+            bytes: vec![],
+        })
+    }
+
+    fn set_regular_key() -> SignedTransaction {
+        sign(Action::SetRegularKey {
+            key: Public::random(),
+        })
+    }
+
+    fn read_by_action_type(
+        chain: &BlockChain,
+        action_type: ActionType,
+        limit: usize,
+    ) -> Vec<(ActionIndexPosition, H256)> {
+        let mut transactions = Vec::new();
+        let mut from = ActionIndexPosition {
+            action_type,
+            block_number: 0,
+            index: 0,
+        };
+        loop {
+            let page = chain.transactions_by_action_type(&from, BlockNumber::max_value(), limit).unwrap();
+            assert!(page.entries.len() <= limit);
+            transactions.extend(page.entries.into_iter().map(|(position, entry)| (position, entry.block_hash)));
+            match page.next {
+                Some(next) => from = next,
+                None => return transactions,
+            }
+        }
+    }
+
+    fn read_history(chain: &BlockChain, address: Address, limit: usize) -> Vec<(AddressHistoryPosition, H256)> {
+        let mut history = Vec::new();
+        let mut from = AddressHistoryPosition {
+            address,
+            block_number: 0,
+            index: 0,
+        };
+        loop {
+            let page = chain.address_history(&from, BlockNumber::max_value(), limit).unwrap();
+            assert!(page.entries.len() <= limit);
+            history.extend(page.entries.into_iter().map(|(position, entry)| (position, entry.block_hash)));
+            match page.next {
+                Some(next) => from = next,
+                None => return history,
+            }
+        }
+    }
+
+    #[test]
+    fn reorg_within_the_limit_is_allowed() {
+        let scheme = Scheme::new_test();
+        let (chain, db) = new_chain(&scheme, 2);
+        let genesis = chain.genesis_header();
+
+        let a = build_branch(&genesis, 2, 10);
+        for header in &a {
+            insert(&chain, &db, header, &scheme);
+        }
+        assert_eq!(a[1].hash(), chain.best_block_hash());
+
+        let b = build_branch(&genesis, 2, 30);
+        for header in &b {
+            insert(&chain, &db, header, &scheme);
+        }
+        assert_eq!(b[1].hash(), chain.best_block_hash());
+        assert_eq!(None, chain.reorg_protection_status().last_rejected);
+    }
+
+    #[test]
+    fn deep_reorg_is_refused_until_overridden() {
+        let scheme = Scheme::new_test();
+        let (chain, db) = new_chain(&scheme, 2);
+        let genesis = chain.genesis_header();
+
+        let a = build_branch(&genesis, 4, 10);
+        for header in &a {
+            insert(&chain, &db, header, &scheme);
+        }
+        assert_eq!(a[3].hash(), chain.best_block_hash());
+
+        // b[1] has more score than a[3], but switching to it retracts 4 blocks.
+        let b = build_branch(&genesis, 4, 30);
+        insert(&chain, &db, &b[0], &scheme);
+        insert(&chain, &db, &b[1], &scheme);
+        assert_eq!(a[3].hash(), chain.best_block_hash());
+        assert_eq!(a[3].hash(), chain.best_header().hash());
+        assert_eq!(Some(a[1].hash()), chain.block_hash(2));
+
+        let status = chain.reorg_protection_status();
+        assert_eq!(2, status.max_depth);
+        assert_eq!(
+            Some(Reorg {
+                best_block_hash: a[3].hash(),
+                best_block_number: 4,
+                new_block_hash: b[1].hash(),
+                new_block_number: 2,
+                depth: 4,
+            }),
+            status.last_rejected
+        );
+
+        // The descendants of the refused branch are refused too.
+        insert(&chain, &db, &b[2], &scheme);
+        assert_eq!(a[3].hash(), chain.best_block_hash());
+
+        chain.override_reorg_protection();
+        assert_eq!(None, chain.reorg_protection_status().last_rejected);
+
+        insert(&chain, &db, &b[3], &scheme);
+        assert_eq!(b[3].hash(), chain.best_block_hash());
+        assert_eq!(b[3].hash(), chain.best_header().hash());
+        assert_eq!(Some(b[1].hash()), chain.block_hash(2));
+        assert_eq!(None, chain.reorg_protection_status().last_rejected);
+    }
+
+    #[test]
+    fn address_history_follows_the_canonical_chain() {
+        let scheme = Scheme::new_test();
+        let (chain, db) = new_chain(&scheme, 10);
+        let genesis = chain.genesis_header();
+        let hot = Address::random();
+
+        let a = build_branch(&genesis, 4, 10);
+        for header in &a {
+            let transactions = vec![pay(hot), pay(Address::random()), pay(hot), pay(hot)];
+            insert_with_transactions(&chain, &db, header, &transactions, &scheme);
+        }
+
+        let history = read_history(&chain, hot, 5);
+        assert_eq!(12, history.len());
+        let expected: Vec<_> = a
+            .iter()
+            .flat_map(|header| {
+                vec![0, 2, 3].into_iter().map(move |index| {
+                    (
+                        AddressHistoryPosition {
+                            address: hot,
+                            block_number: header.number(),
+                            index,
+                        },
+                        header.hash(),
+                    )
+                })
+            })
+            .collect();
+        assert_eq!(expected, history);
+
+        // The range is inclusive and the page ends at `to_block`.
+        let from = AddressHistoryPosition {
+            address: hot,
+            block_number: 2,
+            index: 1,
+        };
+        let page = chain.address_history(&from, 3, 100).unwrap();
+        assert_eq!(5, page.entries.len());
+        assert_eq!(None, page.next);
+        assert_eq!(expected[4..9].to_vec(), page.entries.iter().map(|(p, e)| (*p, e.block_hash)).collect::<Vec<_>>());
+
+        // Retract a[2] and a[3].
+        let b = build_branch(&a[1], 3, 30);
+        for header in &b {
+            insert_with_transactions(&chain, &db, header, &[pay(Address::random()), pay(hot)], &scheme);
+        }
+        assert_eq!(b[2].hash(), chain.best_block_hash());
+
+        let history = read_history(&chain, hot, 4);
+        assert_eq!(9, history.len());
+        assert_eq!(expected[..6].to_vec(), history[..6].to_vec());
+        for (position, block_hash) in &history[6..] {
+            assert_eq!(1, position.index);
+            assert_eq!(Some(*block_hash), chain.block_hash(position.block_number));
+        }
+    }
+
+    #[test]
+    fn action_index_follows_the_canonical_chain() {
+        let scheme = Scheme::new_test();
+        let (chain, db) = new_chain(&scheme, 10);
+        let genesis = chain.genesis_header();
+        let stake = ActionType::Custom(2);
+        let pay_type = ActionType::from_name("pay").unwrap();
+
+        let a = build_branch(&genesis, 4, 10);
+        for header in &a {
+            let transactions = vec![pay(Address::random()), custom(2), set_regular_key(), custom(2), custom(3)];
+            insert_with_transactions(&chain, &db, header, &transactions, &scheme);
+        }
+
+        let expected: Vec<_> = a
+            .iter()
+            .flat_map(|header| {
+                vec![1, 3].into_iter().map(move |index| {
+                    (
+                        ActionIndexPosition {
+                            action_type: stake,
+                            block_number: header.number(),
+                            index,
+                        },
+                        header.hash(),
+                    )
+                })
+            })
+            .collect();
+        assert_eq!(expected, read_by_action_type(&chain, stake, 3));
+        assert_eq!(4, read_by_action_type(&chain, pay_type, 3).len());
+        assert_eq!(4, read_by_action_type(&chain, ActionType::Custom(3), 3).len());
+        assert_eq!(0, read_by_action_type(&chain, ActionType::Custom(1), 3).len());
+
+        // Retract a[2] and a[3].
+        let b = build_branch(&a[1], 3, 30);
+        for header in &b {
+            insert_with_transactions(&chain, &db, header, &[custom(2)], &scheme);
+        }
+        assert_eq!(b[2].hash(), chain.best_block_hash());
+
+        let transactions = read_by_action_type(&chain, stake, 2);
+        assert_eq!(7, transactions.len());
+        assert_eq!(expected[..4].to_vec(), transactions[..4].to_vec());
+        for (position, block_hash) in &transactions[4..] {
+            assert_eq!(0, position.index);
+            assert_eq!(Some(*block_hash), chain.block_hash(position.block_number));
+        }
+        assert_eq!(2, read_by_action_type(&chain, pay_type, 3).len());
+        assert_eq!(2, read_by_action_type(&chain, ActionType::Custom(3), 3).len());
+    }
+
+    #[test]
+    fn action_index_backfill_resumes_after_restart() {
+        let scheme = Scheme::new_test();
+        let db: Arc<KeyValueDB> = Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap()));
+        let open =
+            |action_index: bool| BlockChain::new(&scheme.genesis_block(), Arc::clone(&db), 10, false, action_index);
+        let stake = ActionType::Custom(2);
+        let block_numbers = |transactions: Vec<(ActionIndexPosition, H256)>| -> Vec<BlockNumber> {
+            transactions.iter().map(|(position, _)| position.block_number).collect()
+        };
+
+        let headers = build_branch(&scheme.genesis_header(), 6, 10);
+        {
+            let chain = open(false);
+            for header in &headers[..5] {
+                insert_with_transactions(&chain, &db, header, &[pay(Address::random()), custom(2)], &scheme);
+            }
+            let from = ActionIndexPosition {
+                action_type: stake,
+                block_number: 0,
+                index: 0,
+            };
+            assert_eq!(None, chain.transactions_by_action_type(&from, 100, 100));
+            assert_eq!(None, chain.action_index_backfill());
+        }
+
+        {
+            let chain = open(true);
+            assert_eq!(
+                Some(ActionIndexBackfill {
+                    next: 0,
+                    target: 5,
+                }),
+                chain.action_index_backfill()
+            );
+            // The blocks imported from now on are indexed regardless of the backfill.
+            insert_with_transactions(&chain, &db, &headers[5], &[custom(2)], &scheme);
+            assert_eq!(vec![6], block_numbers(read_by_action_type(&chain, stake, 10)));
+
+            let backfill = chain.backfill_action_index(3).unwrap();
+            assert_eq!(3, backfill.next);
+            assert!(!backfill.is_finished());
+            assert_eq!(3, read_by_action_type(&chain, stake, 10).len());
+        }
+
+        {
+            let chain = open(true);
+            assert_eq!(
+                Some(ActionIndexBackfill {
+                    next: 3,
+                    target: 5,
+                }),
+                chain.action_index_backfill()
+            );
+            assert!(chain.backfill_action_index(100).unwrap().is_finished());
+            let transactions = read_by_action_type(&chain, stake, 4);
+            for (position, block_hash) in &transactions {
+                assert_eq!(Some(*block_hash), chain.block_hash(position.block_number));
+            }
+            assert_eq!((1..=6).collect::<Vec<_>>(), block_numbers(transactions));
+        }
+
+        // Disabling the index discards it, so it's built again when it's enabled again.
+        drop(open(false));
+        let chain = open(true);
+        assert_eq!(
+            Some(ActionIndexBackfill {
+                next: 0,
+                target: 6,
+            }),
+            chain.action_index_backfill()
+        );
+        assert_eq!(0, read_by_action_type(&chain, stake, 10).len());
+    }
+
+    #[test]
+    fn address_history_is_backfilled_when_it_is_enabled() {
+        let scheme = Scheme::new_test();
+        let db: Arc<KeyValueDB> = Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap()));
+        let open = |address_history: bool| {
+            BlockChain::new(&scheme.genesis_block(), Arc::clone(&db), 10, address_history, false)
+        };
+        let hot = Address::random();
+        let block_numbers = |history: Vec<(AddressHistoryPosition, H256)>| -> Vec<BlockNumber> {
+            history.iter().map(|(position, _)| position.block_number).collect()
+        };
+
+        let headers = build_branch(&scheme.genesis_header(), 5, 10);
+        {
+            let chain = open(false);
+            for header in &headers[..4] {
+                insert_with_transactions(&chain, &db, header, &[pay(Address::random()), pay(hot)], &scheme);
+            }
+            assert_eq!(None, chain.address_history_backfill());
+        }
+
+        let chain = open(true);
+        assert_eq!(
+            Some(AddressHistoryBackfill {
+                next: 0,
+                target: 4,
+            }),
+            chain.address_history_backfill()
+        );
+        // The signers of the verified blocks are cached before they're imported.
+        let transactions = vec![pay(hot)];
+        chain.cache_signers(&transactions);
+        insert_with_transactions(&chain, &db, &headers[4], &transactions, &scheme);
+        assert_eq!(vec![5], block_numbers(read_history(&chain, hot, 10)));
+
+        let backfill = chain.backfill_address_history(2).unwrap();
+        assert_eq!(2, backfill.next);
+        assert_eq!(vec![1, 2, 5], block_numbers(read_history(&chain, hot, 10)));
+
+        assert!(chain.backfill_address_history(100).unwrap().is_finished());
+        let history = read_history(&chain, hot, 3);
+        for (position, block_hash) in &history {
+            assert_eq!(Some(*block_hash), chain.block_hash(position.block_number));
+        }
+        assert_eq!((1..=5).collect::<Vec<_>>(), block_numbers(history));
+        let signer = public_to_address(&transactions[0].signer_public());
+        assert_eq!(vec![5], block_numbers(read_history(&chain, signer, 10)));
+    }
+
+    #[test]
+    fn skipped_seal_verification_is_recorded_until_removed() {
+        let scheme = Scheme::new_test();
+        let (chain, db) = new_chain(&scheme, 2);
+        let genesis = chain.genesis_header();
+        let checkpoints = SealCheckpoints::new(10);
+
+        let headers = build_branch(&genesis, 40, 10);
+        let mut batch = DBTransaction::new();
+        for header in &headers {
+            if let Some(checkpoint) = checkpoints.checkpoint_of(header.number(), 1, 40, 40) {
+                chain.insert_skipped_seal_verification(&mut batch, &header.hash(), checkpoint);
+            }
+        }
+        db.write(batch).unwrap();
+
+        assert_eq!(None, chain.skipped_seal_verification(&headers[0].hash()));
+        assert_eq!(
+            Some(SkippedSealVerification {
+                checkpoint: 10,
+            }),
+            chain.skipped_seal_verification(&headers[4].hash())
+        );
+        assert_eq!(None, chain.skipped_seal_verification(&headers[9].hash()));
+        assert_eq!(None, chain.skipped_seal_verification(&headers[35].hash()));
+
+        let mut batch = DBTransaction::new();
+        chain.remove_skipped_seal_verification(&mut batch, &headers[4].hash());
+        db.write(batch).unwrap();
+        assert_eq!(None, chain.skipped_seal_verification(&headers[4].hash()));
+        assert_eq!(
+            Some(SkippedSealVerification {
+                checkpoint: 20,
+            }),
+            chain.skipped_seal_verification(&headers[14].hash())
+        );
+    }
+
+    #[test]
+    fn shrinking_the_header_cache_budget_lowers_the_hit_rate() {
+        let scheme = Scheme::new_test();
+        let (chain, db) = new_chain(&scheme, 100);
+        let headers = build_branch(&chain.genesis_header(), 10, 10);
+        for header in &headers {
+            insert(&chain, &db, header, &scheme);
+        }
+        let hashes: Vec<H256> = headers.iter().map(Header::hash).collect();
+        let largest =
+            hashes.iter().map(|hash| chain.block_header_data(hash).unwrap().into_inner().len()).max().unwrap();
+
+        let hits_while_reading = |chain: &BlockChain| {
+            let before = chain.cache_stats(db::COL_HEADERS).unwrap().hits;
+            for hash in &hashes {
+                chain.block_header_data(hash).unwrap();
+            }
+            chain.cache_stats(db::COL_HEADERS).unwrap().hits - before
+        };
+        assert_eq!(10, hits_while_reading(&chain));
+
+        // Only one header fits, so reading the headers in turn always misses.
+        chain.set_cache_budget(db::COL_HEADERS, largest).unwrap();
+        hits_while_reading(&chain);
+        assert_eq!(0, hits_while_reading(&chain));
+        assert_eq!(1, chain.cache_stats(db::COL_HEADERS).unwrap().entries);
+    }
+
+    #[test]
+    fn cache_budgets_are_capped() {
+        let scheme = Scheme::new_test();
+        let (chain, _db) = new_chain(&scheme, 100);
+
+        assert_eq!(Err(CacheBudgetError::NoCache), chain.set_cache_budget(db::COL_STATE, 1024));
+        assert_eq!(
+            Err(CacheBudgetError::ExceedsCap {
+                cap: MAX_CACHE_BUDGET,
+                requested: MAX_CACHE_BUDGET + DEFAULT_BODY_CACHE_BUDGET,
+            }),
+            chain.set_cache_budget(db::COL_HEADERS, MAX_CACHE_BUDGET)
+        );
+        assert_eq!(Ok(()), chain.set_cache_budget(db::COL_HEADERS, MAX_CACHE_BUDGET - DEFAULT_BODY_CACHE_BUDGET));
+        assert_eq!(MAX_CACHE_BUDGET - DEFAULT_BODY_CACHE_BUDGET, chain.cache_stats(db::COL_HEADERS).unwrap().budget);
+    }
+}
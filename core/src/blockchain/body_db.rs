@@ -25,14 +25,18 @@ use rlp::RlpStream;
 use rlp_compress::{blocks_swapper, compress, decompress};
 
 use super::block_info::BestBlockChanged;
+use super::bytes_cache::BytesCache;
 use super::extras::{TransactionAddress, TransactionAddresses};
 use crate::db::{self, CacheUpdatePolicy, Readable, Writable};
 use crate::views::BlockView;
 use crate::{encoded, UnverifiedTransaction};
 
+/// The default budget of the cache of the decompressed bodies.
+pub const DEFAULT_BODY_CACHE_BUDGET: usize = 64 * 1024 * 1024;
+
 pub struct BodyDB {
     // block cache
-    body_cache: RwLock<HashMap<H256, Bytes>>,
+    body_cache: Mutex<BytesCache>,
     parcel_address_cache: RwLock<HashMap<H256, TransactionAddress>>,
     pending_parcel_addresses: RwLock<HashMap<H256, Option<TransactionAddress>>>,
 
@@ -48,7 +52,7 @@ impl BodyDB {
     /// Create new instance of blockchain from given Genesis.
     pub fn new(genesis: &BlockView, db: Arc<KeyValueDB>) -> Self {
         let bdb = Self {
-            body_cache: RwLock::new(HashMap::new()),
+            body_cache: Mutex::new(BytesCache::new(DEFAULT_BODY_CACHE_BUDGET)),
             parcel_address_cache: RwLock::new(HashMap::new()),
             pending_parcel_addresses: RwLock::new(HashMap::new()),
 
@@ -85,6 +89,15 @@ impl BodyDB {
         batch.put(db::COL_BODIES, &hash, &compressed_body);
     }
 
+    /// Removes the bodies from the backing database and the cache.
+    pub fn remove_bodies(&self, batch: &mut DBTransaction, hashes: &[H256]) {
+        let mut body_cache = self.body_cache.lock();
+        for hash in hashes {
+            batch.delete(db::COL_BODIES, hash);
+            body_cache.remove(hash);
+        }
+    }
+
     pub fn update_best_block(&self, batch: &mut DBTransaction, best_block_changed: &BestBlockChanged) {
         let mut pending_parcel_addresses = self.pending_parcel_addresses.write();
         let mut pending_transaction_addresses = self.pending_transaction_addresses.lock();
@@ -271,6 +284,10 @@ impl BodyDB {
         body.append_raw(block.rlp().at(1).as_raw(), 1);
         body.out()
     }
+
+    pub fn body_cache(&self) -> &Mutex<BytesCache> {
+        &self.body_cache
+    }
 }
 
 /// Interface for querying block bodiess by hash and by number.
@@ -307,11 +324,8 @@ impl BodyProvider for BodyDB {
     /// Get block body data
     fn block_body(&self, hash: &H256) -> Option<encoded::Body> {
         // Check cache first
-        {
-            let read = self.body_cache.read();
-            if let Some(v) = read.get(hash) {
-                return Some(encoded::Body::new(v.clone()))
-            }
+        if let Some(raw_body) = self.body_cache.lock().get(hash) {
+            return Some(encoded::Body::new(raw_body))
         }
 
         // Read from DB and populate cache
@@ -319,8 +333,7 @@ impl BodyProvider for BodyDB {
             self.db.get(db::COL_BODIES, hash).expect("Low level database error. Some issue with disk?")?;
 
         let raw_body = decompress(&compressed_body, blocks_swapper()).into_vec();
-        let mut write = self.body_cache.write();
-        write.insert(*hash, raw_body.clone());
+        self.body_cache.lock().insert(*hash, raw_body.clone());
 
         Some(encoded::Body::new(raw_body))
     }
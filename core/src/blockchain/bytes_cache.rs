@@ -0,0 +1,189 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt;
+
+use linked_hash_map::LinkedHashMap;
+use primitives::{Bytes, H256};
+
+/// The statistics of a cache in front of a database column.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CacheStats {
+    pub entries: usize,
+    /// The sum of the sizes of the cached values in bytes
+    pub size: usize,
+    pub budget: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CacheBudgetError {
+    /// The column doesn't have a cache whose budget can be changed.
+    NoCache,
+    /// The sum of the budgets would exceed the cap.
+    ExceedsCap {
+        cap: usize,
+        requested: usize,
+    },
+}
+
+impl fmt::Display for CacheBudgetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CacheBudgetError::NoCache => write!(f, "The column doesn't have a cache with a budget"),
+            CacheBudgetError::ExceedsCap {
+                cap,
+                requested,
+            } => write!(f, "The sum of the cache budgets would be {} bytes, which exceeds the cap {}", requested, cap),
+        }
+    }
+}
+
+/// An LRU cache of encoded values whose total size is kept within a budget.
+pub struct BytesCache {
+    values: LinkedHashMap<H256, Bytes>,
+    size: usize,
+    budget: usize,
+    hits: u64,
+    misses: u64,
+}
+
+impl BytesCache {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            values: LinkedHashMap::new(),
+            size: 0,
+            budget,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Counts a hit or a miss.
+    pub fn get(&mut self, key: &H256) -> Option<Bytes> {
+        match self.values.get_refresh(key) {
+            Some(value) => {
+                self.hits += 1;
+                Some(value.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Evicts the least recently used values if the budget is exceeded.
+    /// A value larger than the budget is not cached.
+    pub fn insert(&mut self, key: H256, value: Bytes) {
+        self.remove(&key);
+        if value.len() > self.budget {
+            return
+        }
+        self.size += value.len();
+        self.values.insert(key, value);
+        self.shrink();
+    }
+
+    pub fn remove(&mut self, key: &H256) {
+        if let Some(value) = self.values.remove(key) {
+            self.size -= value.len();
+        }
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.shrink();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self.values.len(),
+            size: self.size,
+            budget: self.budget,
+            hits: self.hits,
+            misses: self.misses,
+        }
+    }
+
+    fn shrink(&mut self) {
+        while self.size > self.budget {
+            let (_, value) = self.values.pop_front().expect("The size is positive only if there are values");
+            self.size -= value.len();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn least_recently_used_values_are_evicted_first() {
+        let mut cache = BytesCache::new(30);
+        let (a, b, c) = (H256::random(), H256::random(), H256::random());
+        cache.insert(a, vec![0; 10]);
+        cache.insert(b, vec![0; 10]);
+        cache.insert(c, vec![0; 10]);
+        assert!(cache.get(&a).is_some());
+
+        cache.insert(H256::random(), vec![0; 10]);
+        assert!(cache.get(&b).is_none());
+        assert!(cache.get(&a).is_some());
+        assert!(cache.get(&c).is_some());
+        assert_eq!(30, cache.stats().size);
+    }
+
+    #[test]
+    fn shrinking_the_budget_evicts_values() {
+        let mut cache = BytesCache::new(100);
+        for _ in 0..10 {
+            cache.insert(H256::random(), vec![0; 10]);
+        }
+        cache.set_budget(25);
+        let stats = cache.stats();
+        assert_eq!(2, stats.entries);
+        assert_eq!(20, stats.size);
+        assert_eq!(25, stats.budget);
+    }
+
+    #[test]
+    fn too_large_value_is_not_cached() {
+        let mut cache = BytesCache::new(10);
+        let key = H256::random();
+        cache.insert(key, vec![0; 5]);
+        cache.insert(key, vec![0; 11]);
+        assert!(cache.get(&key).is_none());
+        assert_eq!(0, cache.stats().size);
+    }
+
+    #[test]
+    fn hits_and_misses_are_counted() {
+        let mut cache = BytesCache::new(10);
+        let key = H256::random();
+        assert!(cache.get(&key).is_none());
+        cache.insert(key, vec![1]);
+        assert_eq!(Some(vec![1]), cache.get(&key));
+        let stats = cache.stats();
+        assert_eq!(1, stats.hits);
+        assert_eq!(1, stats.misses);
+    }
+}
@@ -16,9 +16,11 @@
 
 use std::ops::{Add, AddAssign, Deref, Sub, SubAssign};
 
+use ckey::Address;
 use ctypes::BlockNumber;
 use primitives::{H256, H264, U256};
 
+use super::action_index_db::ActionType;
 use crate::db::Key;
 use crate::types::TransactionId;
 
@@ -35,6 +37,12 @@ enum ExtrasIndex {
     TransactionAddress = 3,
     // (Reserved) = 4,
     // (Reserved) = 5,
+    /// Skipped seal verification index
+    SkippedSealVerification = 6,
+    /// Address history index
+    AddressHistory = 7,
+    /// Action type index
+    ActionIndex = 8,
 }
 
 fn with_index(hash: &H256, i: ExtrasIndex) -> H264 {
@@ -69,6 +77,50 @@ impl Key<H256> for BlockNumber {
     }
 }
 
+pub struct AddressHistoryKey([u8; 29]);
+
+impl Deref for AddressHistoryKey {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Key<AddressHistoryEntry> for AddressHistoryPosition {
+    type Target = AddressHistoryKey;
+
+    fn key(&self) -> Self::Target {
+        let mut result = [0u8; 29];
+        result[..21].copy_from_slice(&AddressHistoryPosition::prefix(&self.address));
+        result[21..25].copy_from_slice(&(self.block_number as u32).to_be_bytes());
+        result[25..].copy_from_slice(&(self.index as u32).to_be_bytes());
+        AddressHistoryKey(result)
+    }
+}
+
+pub struct ActionIndexKey([u8; 18]);
+
+impl Deref for ActionIndexKey {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Key<ActionIndexEntry> for ActionIndexPosition {
+    type Target = ActionIndexKey;
+
+    fn key(&self) -> Self::Target {
+        let mut result = [0u8; 18];
+        result[..10].copy_from_slice(&ActionIndexPosition::prefix(self.action_type));
+        result[10..14].copy_from_slice(&(self.block_number as u32).to_be_bytes());
+        result[14..].copy_from_slice(&(self.index as u32).to_be_bytes());
+        ActionIndexKey(result)
+    }
+}
+
 impl Key<BlockDetails> for H256 {
     type Target = H264;
 
@@ -93,6 +145,14 @@ impl Key<TransactionAddresses> for H256 {
     }
 }
 
+impl Key<SkippedSealVerification> for H256 {
+    type Target = H264;
+
+    fn key(&self) -> H264 {
+        with_index(self, ExtrasIndex::SkippedSealVerification)
+    }
+}
+
 /// Familial details concerning a block
 #[derive(Debug, Clone, RlpEncodable, RlpDecodable)]
 pub struct BlockDetails {
@@ -104,6 +164,14 @@ pub struct BlockDetails {
     pub parent: H256,
 }
 
+/// The record of a block whose seal was not verified when it was imported
+#[derive(Debug, PartialEq, Clone, Copy, RlpEncodable, RlpDecodable)]
+pub struct SkippedSealVerification {
+    /// The number of the block whose seal was verified instead.
+    /// Its seal vouches for this block through the parent hashes.
+    pub checkpoint: BlockNumber,
+}
+
 /// Represents address of certain transaction within block
 #[derive(Debug, PartialEq, Clone, Copy, RlpEncodable, RlpDecodable)]
 pub struct TransactionAddress {
@@ -119,6 +187,101 @@ impl From<TransactionAddress> for TransactionId {
     }
 }
 
+/// The position of a transaction in the history of an address.
+/// The positions are ordered by the block number, and then by the index in the block.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct AddressHistoryPosition {
+    pub address: Address,
+    pub block_number: BlockNumber,
+    /// Transaction index within the block
+    pub index: usize,
+}
+
+impl AddressHistoryPosition {
+    /// The common prefix of the keys of all the positions of all the addresses.
+    pub const INDEX_PREFIX: [u8; 1] = [ExtrasIndex::AddressHistory as u8];
+
+    /// The common prefix of the keys of all the positions of the address.
+    pub fn prefix(address: &Address) -> [u8; 21] {
+        let mut result = [0u8; 21];
+        result[0] = ExtrasIndex::AddressHistory as u8;
+        result[1..].copy_from_slice(address);
+        result
+    }
+
+    /// Decodes the position from the database key.
+    pub fn from_key(key: &[u8]) -> Option<Self> {
+        if key.len() != 29 || key[0] != ExtrasIndex::AddressHistory as u8 {
+            return None
+        }
+        let mut block_number = [0u8; 4];
+        block_number.copy_from_slice(&key[21..25]);
+        let mut index = [0u8; 4];
+        index.copy_from_slice(&key[25..]);
+        Some(Self {
+            address: Address::from_slice(&key[1..21]),
+            block_number: BlockNumber::from(u32::from_be_bytes(block_number)),
+            index: u32::from_be_bytes(index) as usize,
+        })
+    }
+}
+
+/// The transaction at a position in the history of an address
+#[derive(Debug, PartialEq, Clone, Copy, RlpEncodable, RlpDecodable)]
+pub struct AddressHistoryEntry {
+    pub block_hash: H256,
+    pub transaction_hash: H256,
+}
+
+/// The position of a transaction in the index of an action type.
+/// The positions are ordered by the block number, and then by the index in the block.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct ActionIndexPosition {
+    pub action_type: ActionType,
+    pub block_number: BlockNumber,
+    /// Transaction index within the block
+    pub index: usize,
+}
+
+impl ActionIndexPosition {
+    /// The common prefix of the keys of all the positions of all the action types.
+    pub const INDEX_PREFIX: [u8; 1] = [ExtrasIndex::ActionIndex as u8];
+
+    /// The common prefix of the keys of all the positions of the action type.
+    pub fn prefix(action_type: ActionType) -> [u8; 10] {
+        let mut result = [0u8; 10];
+        result[0] = ExtrasIndex::ActionIndex as u8;
+        result[1] = action_type.tag();
+        result[2..].copy_from_slice(&action_type.handler_id().to_be_bytes());
+        result
+    }
+
+    /// Decodes the position from the database key.
+    pub fn from_key(key: &[u8]) -> Option<Self> {
+        if key.len() != 18 || key[0] != ExtrasIndex::ActionIndex as u8 {
+            return None
+        }
+        let mut handler_id = [0u8; 8];
+        handler_id.copy_from_slice(&key[2..10]);
+        let mut block_number = [0u8; 4];
+        block_number.copy_from_slice(&key[10..14]);
+        let mut index = [0u8; 4];
+        index.copy_from_slice(&key[14..]);
+        Some(Self {
+            action_type: ActionType::from_tag(key[1], u64::from_be_bytes(handler_id))?,
+            block_number: BlockNumber::from(u32::from_be_bytes(block_number)),
+            index: u32::from_be_bytes(index) as usize,
+        })
+    }
+}
+
+/// The transaction at a position in the index of an action type
+#[derive(Debug, PartialEq, Clone, Copy, RlpEncodable, RlpDecodable)]
+pub struct ActionIndexEntry {
+    pub block_hash: H256,
+    pub transaction_hash: H256,
+}
+
 /// Represents address of certain transaction that has the same tracker
 #[derive(Debug, Default, PartialEq, Clone, RlpEncodableWrapper, RlpDecodableWrapper)]
 pub struct TransactionAddresses {
@@ -188,6 +351,74 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn address_history_keys_are_ordered_by_position() {
+        let address = Address::random();
+        let positions = vec![
+            AddressHistoryPosition {
+                address,
+                block_number: 1,
+                index: 300,
+            },
+            AddressHistoryPosition {
+                address,
+                block_number: 2,
+                index: 0,
+            },
+            AddressHistoryPosition {
+                address,
+                block_number: 2,
+                index: 1,
+            },
+            AddressHistoryPosition {
+                address,
+                block_number: 256,
+                index: 0,
+            },
+        ];
+        for pair in positions.windows(2) {
+            let (lhs, rhs): (AddressHistoryKey, AddressHistoryKey) = (pair[0].key(), pair[1].key());
+            assert!(*lhs < *rhs);
+        }
+        for position in positions {
+            let key: AddressHistoryKey = position.key();
+            assert!(key.starts_with(&AddressHistoryPosition::prefix(&address)));
+            assert_eq!(Some(position), AddressHistoryPosition::from_key(&key));
+        }
+    }
+
+    #[test]
+    fn action_index_keys_are_ordered_by_position() {
+        let action_type = ActionType::Custom(2);
+        let positions = vec![
+            ActionIndexPosition {
+                action_type,
+                block_number: 1,
+                index: 300,
+            },
+            ActionIndexPosition {
+                action_type,
+                block_number: 2,
+                index: 0,
+            },
+            ActionIndexPosition {
+                action_type,
+                block_number: 256,
+                index: 1,
+            },
+        ];
+        for pair in positions.windows(2) {
+            let (lhs, rhs): (ActionIndexKey, ActionIndexKey) = (pair[0].key(), pair[1].key());
+            assert!(*lhs < *rhs);
+        }
+        for position in positions {
+            let key: ActionIndexKey = position.key();
+            assert!(key.starts_with(&ActionIndexPosition::prefix(action_type)));
+            assert!(!key.starts_with(&ActionIndexPosition::prefix(ActionType::Custom(3))));
+            assert_eq!(Some(position), ActionIndexPosition::from_key(&key));
+        }
+    }
+
     #[test]
     fn encode_and_decode_transaction_address_with_single_address() {
         rlp_encode_and_decode_test!(TransactionAddresses::new(TransactionAddress {
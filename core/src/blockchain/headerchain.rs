@@ -22,11 +22,13 @@ use ctypes::header::{Header, Seal};
 use ctypes::BlockNumber;
 use kvdb::{DBTransaction, KeyValueDB};
 use parking_lot::{Mutex, RwLock};
-use primitives::{Bytes, H256};
+use primitives::H256;
 use rlp_compress::{blocks_swapper, compress, decompress};
 
 use super::block_info::BestHeaderChanged;
+use super::bytes_cache::BytesCache;
 use super::extras::BlockDetails;
+use super::reorg_protection::{Reorg, ReorgProtection};
 use super::route::tree_route;
 use crate::consensus::CodeChainEngine;
 use crate::db::{self, CacheUpdatePolicy, Readable, Writable};
@@ -36,6 +38,9 @@ use crate::views::HeaderView;
 const BEST_HEADER_KEY: &[u8] = b"best-header";
 const BEST_PROPOSAL_HEADER_KEY: &[u8] = b"best-proposal-header";
 
+/// The default budget of the cache of the decompressed headers.
+pub const DEFAULT_HEADER_CACHE_BUDGET: usize = 16 * 1024 * 1024;
+
 /// Structure providing fast access to blockchain data.
 ///
 /// **Does not do input data verification.**
@@ -47,12 +52,14 @@ pub struct HeaderChain {
     best_proposal_header_hash: RwLock<H256>,
 
     // cache
-    header_cache: RwLock<HashMap<H256, Bytes>>,
+    header_cache: Mutex<BytesCache>,
     detail_cache: RwLock<HashMap<H256, BlockDetails>>,
     hash_cache: Mutex<HashMap<BlockNumber, H256>>,
 
     db: Arc<KeyValueDB>,
 
+    reorg_protection: Arc<ReorgProtection>,
+
     pending_best_header_hash: RwLock<Option<H256>>,
     pending_best_proposal_block_hash: RwLock<Option<H256>>,
     pending_hashes: RwLock<HashMap<BlockNumber, H256>>,
@@ -61,7 +68,7 @@ pub struct HeaderChain {
 
 impl HeaderChain {
     /// Create new instance of blockchain from given Genesis.
-    pub fn new(genesis: &HeaderView, db: Arc<KeyValueDB>) -> Self {
+    pub fn new(genesis: &HeaderView, db: Arc<KeyValueDB>, reorg_protection: Arc<ReorgProtection>) -> Self {
         // load best header
         let best_header_hash = match db.get(db::COL_EXTRA, BEST_HEADER_KEY).unwrap() {
             Some(hash) => H256::from_slice(&hash),
@@ -99,12 +106,14 @@ impl HeaderChain {
             best_header_hash: RwLock::new(best_header_hash),
             best_proposal_header_hash: RwLock::new(best_proposal_header_hash),
 
-            header_cache: RwLock::new(HashMap::new()),
+            header_cache: Mutex::new(BytesCache::new(DEFAULT_HEADER_CACHE_BUDGET)),
             detail_cache: Default::default(),
             hash_cache: Default::default(),
 
             db,
 
+            reorg_protection,
+
             pending_best_header_hash: RwLock::new(None),
             pending_best_proposal_block_hash: RwLock::new(None),
             pending_hashes: RwLock::new(HashMap::new()),
@@ -240,6 +249,9 @@ impl HeaderChain {
     fn best_header_changed(&self, new_header: &HeaderView, engine: &CodeChainEngine) -> BestHeaderChanged {
         let parent_hash_of_new_header = new_header.parent_hash();
         let parent_details_of_new_header = self.block_details(&parent_hash_of_new_header).expect("Invalid parent hash");
+        if self.reorg_protection.is_on_rejected_branch(new_header.hash(), &parent_hash_of_new_header) {
+            return BestHeaderChanged::None
+        }
         let is_new_best = parent_details_of_new_header.total_score + new_header.score()
             > self.best_proposal_header_detail().total_score
             && engine.can_change_canon_chain(&new_header);
@@ -257,6 +269,18 @@ impl HeaderChain {
             let prev_best_hash = self.best_header_hash();
             let route = tree_route(self, prev_best_hash, parent_hash_of_new_header)
                 .expect("blocks being imported always within recent history; qed");
+            if !route.retracted.is_empty() {
+                let reorg = Reorg {
+                    best_block_hash: prev_best_hash,
+                    best_block_number: self.best_header().number(),
+                    new_block_hash: new_header.hash(),
+                    new_block_number: new_header.number(),
+                    depth: route.retracted.len() as u64,
+                };
+                if !self.reorg_protection.allows(reorg, &route.enacted) {
+                    return BestHeaderChanged::None
+                }
+            }
 
             let new_best_block_hash = engine.get_best_block_from_best_proposal_header(&new_header);
             let new_best_header = if new_best_block_hash != new_header.hash() {
@@ -331,6 +355,10 @@ impl HeaderChain {
     pub fn best_proposal_header_detail(&self) -> BlockDetails {
         self.block_details(&self.best_proposal_header_hash()).expect("Best Proposal header always exists")
     }
+
+    pub fn header_cache(&self) -> &Mutex<BytesCache> {
+        &self.header_cache
+    }
 }
 
 /// Interface for querying blocks by hash and by number.
@@ -401,26 +429,17 @@ impl HeaderProvider for HeaderChain {
 }
 
 /// Get block header data
-fn block_header_data(hash: &H256, header_cache: &RwLock<HashMap<H256, Bytes>>, db: &KeyValueDB) -> Option<Vec<u8>> {
+fn block_header_data(hash: &H256, header_cache: &Mutex<BytesCache>, db: &KeyValueDB) -> Option<Vec<u8>> {
     // Check cache first
-    {
-        let read = header_cache.read();
-        if let Some(v) = read.get(hash) {
-            return Some(v.clone())
-        }
+    if let Some(bytes) = header_cache.lock().get(hash) {
+        return Some(bytes)
     }
     // Read from DB and populate cache
     let b = db.get(db::COL_HEADERS, hash).expect("Low level database error. Some issue with disk?")?;
 
     let bytes = decompress(&b, blocks_swapper()).into_vec();
 
-    let mut write = header_cache.write();
-    if let Some(v) = write.get(hash) {
-        assert_eq!(&bytes, v);
-        return Some(v.clone())
-    }
-
-    write.insert(*hash, bytes.clone());
+    header_cache.lock().insert(*hash, bytes.clone());
 
     Some(bytes)
 }
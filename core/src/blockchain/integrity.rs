@@ -0,0 +1,342 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use ccrypto::blake256;
+use cmerkle::skewed_merkle_root;
+use ctypes::{BlockNumber, Header};
+use kvdb::KeyValueDB;
+use primitives::H256;
+use rlp::UntrustedRlp;
+use rlp_compress::{blocks_swapper, decompress};
+
+use crate::db::{self, Readable};
+use crate::transaction::UnverifiedTransaction;
+
+/// An inconsistency between the headers, the bodies and the invoices in the database.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DatabaseAnomaly {
+    /// The hash of the canonical block of the number is not recorded.
+    MissingBlockHash {
+        number: BlockNumber,
+    },
+    MissingHeader {
+        number: BlockNumber,
+        hash: H256,
+    },
+    /// The stored header cannot be decoded, or it is not the header of the hash.
+    CorruptHeader {
+        number: BlockNumber,
+        hash: H256,
+    },
+    MissingBody {
+        number: BlockNumber,
+        hash: H256,
+    },
+    /// The stored body cannot be decoded, or it doesn't match the transactions root of the header.
+    CorruptBody {
+        number: BlockNumber,
+        hash: H256,
+    },
+    MissingInvoice {
+        number: BlockNumber,
+        hash: H256,
+        transaction: H256,
+    },
+    /// The body is stored without its header.
+    OrphanedBody {
+        hash: H256,
+    },
+}
+
+/// Checks the canonical blocks from `from` to `to`, reading the database directly without the caches.
+pub fn check_blocks(db: &KeyValueDB, from: BlockNumber, to: BlockNumber) -> Vec<DatabaseAnomaly> {
+    let mut anomalies = Vec::new();
+    for number in from..=to {
+        check_block(db, number, &mut anomalies);
+    }
+    anomalies
+}
+
+fn check_block(db: &KeyValueDB, number: BlockNumber, anomalies: &mut Vec<DatabaseAnomaly>) {
+    let hash: H256 = match db.read(db::COL_EXTRA, &number) {
+        Some(hash) => hash,
+        None => {
+            anomalies.push(DatabaseAnomaly::MissingBlockHash {
+                number,
+            });
+            return
+        }
+    };
+    let header = match read_header(db, &hash) {
+        Some(Ok(header)) => header,
+        Some(Err(())) => {
+            anomalies.push(DatabaseAnomaly::CorruptHeader {
+                number,
+                hash,
+            });
+            return
+        }
+        None => {
+            anomalies.push(DatabaseAnomaly::MissingHeader {
+                number,
+                hash,
+            });
+            return
+        }
+    };
+    let compressed_body = match db.get(db::COL_BODIES, &hash).expect("Low level database error. Some issue with disk?")
+    {
+        Some(body) => body,
+        None => {
+            anomalies.push(DatabaseAnomaly::MissingBody {
+                number,
+                hash,
+            });
+            return
+        }
+    };
+    let body = decompress(&compressed_body, blocks_swapper()).into_vec();
+    let transactions = UntrustedRlp::new(&body).at(0);
+    let decoded = transactions.as_ref().ok().and_then(|rlp| rlp.as_list::<UnverifiedTransaction>().ok());
+    let (transactions, decoded) = match (transactions, decoded) {
+        (Ok(transactions), Some(decoded)) => (transactions, decoded),
+        _ => {
+            anomalies.push(DatabaseAnomaly::CorruptBody {
+                number,
+                hash,
+            });
+            return
+        }
+    };
+    // The transactions root of the genesis block is not chained to a parent,
+    // and the broken parent is reported as its own number.
+    let parent_transactions_root = match read_header(db, header.parent_hash()) {
+        Some(Ok(ref parent)) if number != 0 => Some(*parent.transactions_root()),
+        _ => None,
+    };
+    if let Some(parent_transactions_root) = parent_transactions_root {
+        let expected_root = skewed_merkle_root(parent_transactions_root, transactions.iter().map(|r| r.as_raw()));
+        if &expected_root != header.transactions_root() {
+            anomalies.push(DatabaseAnomaly::CorruptBody {
+                number,
+                hash,
+            });
+            return
+        }
+    }
+    for transaction in decoded {
+        let transaction_hash = transaction.hash();
+        if !db.exists::<Option<String>, _>(db::COL_ERROR_HINT, &transaction_hash) {
+            anomalies.push(DatabaseAnomaly::MissingInvoice {
+                number,
+                hash,
+                transaction: transaction_hash,
+            });
+        }
+    }
+}
+
+fn read_header(db: &KeyValueDB, hash: &H256) -> Option<Result<Header, ()>> {
+    let compressed = db.get(db::COL_HEADERS, hash).expect("Low level database error. Some issue with disk?")?;
+    let bytes = decompress(&compressed, blocks_swapper()).into_vec();
+    if blake256(&bytes) != *hash {
+        return Some(Err(()))
+    }
+    Some(UntrustedRlp::new(&bytes).as_val().map_err(|_| ()))
+}
+
+/// Finds the bodies whose hashes start with `prefix` and whose headers are not stored.
+pub fn find_orphaned_bodies(db: &KeyValueDB, prefix: u8) -> Vec<DatabaseAnomaly> {
+    let prefix = [prefix];
+    db.iter_from_prefix(db::COL_BODIES, &prefix)
+        .take_while(|(key, _)| key.starts_with(&prefix))
+        .filter(|(key, _)| {
+            db.get(db::COL_HEADERS, key).expect("Low level database error. Some issue with disk?").is_none()
+        })
+        .map(|(key, _)| DatabaseAnomaly::OrphanedBody {
+            hash: H256::from_slice(&key),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ckey::{Address, Generator, Random};
+    use ctypes::transaction::{Action, Transaction};
+    use kvdb::DBTransaction;
+    use primitives::U256;
+
+    use super::*;
+    use crate::blockchain::{BlockChain, BodyProvider, DEFAULT_MAX_REORG_DEPTH};
+    use crate::db::Writable;
+    use crate::invoice::Invoice;
+    use crate::scheme::Scheme;
+    use crate::tests::helpers::create_test_block_with_data;
+    use crate::transaction::SignedTransaction;
+
+    fn pay() -> SignedTransaction {
+        let keypair = Random.generate().unwrap();
+        let tx = Transaction {
+            seq: 0,
+            fee: 10,
+            network_id: "tc".into(),
+            action: Action::Pay {
+                receiver: Address::default(),
+                quantity: 1,
+            },
+        };
+        SignedTransaction::new_with_sign(tx, keypair.private())
+    }
+
+    /// Builds a chain whose blocks after the genesis have a transaction each.
+    fn new_chain(length: usize) -> (BlockChain, Arc<KeyValueDB>, Vec<H256>, Vec<H256>) {
+        let scheme = Scheme::new_test();
+        let db: Arc<KeyValueDB> = Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap()));
+        let chain = BlockChain::new(&scheme.genesis_block(), Arc::clone(&db), DEFAULT_MAX_REORG_DEPTH, false, false);
+        let mut parent = scheme.genesis_header();
+        let mut hashes = vec![parent.hash()];
+        let mut transactions = vec![H256::zero()];
+        for _ in 0..length {
+            let tx = pay();
+            let mut header = Header::new();
+            header.set_parent_hash(parent.hash());
+            header.set_number(parent.number() + 1);
+            header.set_score(U256::from(10));
+            header.set_transactions_root(skewed_merkle_root(*parent.transactions_root(), vec![
+                rlp::encode(&tx).into_vec()
+            ]));
+            let invoice = Invoice {
+                tracker: None,
+                hash: tx.hash(),
+                error: None,
+            };
+            let mut batch = DBTransaction::new();
+            chain.insert_block(
+                &mut batch,
+                &create_test_block_with_data(&header, &[tx.clone()], &[]),
+                vec![invoice],
+                &*scheme.engine,
+            );
+            db.write(batch).unwrap();
+            chain.commit();
+            hashes.push(header.hash());
+            transactions.push(tx.hash());
+            parent = header;
+        }
+        (chain, db, hashes, transactions)
+    }
+
+    fn all_orphaned_bodies(db: &KeyValueDB) -> Vec<DatabaseAnomaly> {
+        (0..=255u8).flat_map(|prefix| find_orphaned_bodies(db, prefix)).collect()
+    }
+
+    #[test]
+    fn intact_chain_has_no_anomalies() {
+        let (_chain, db, _hashes, _transactions) = new_chain(5);
+        assert_eq!(Vec::<DatabaseAnomaly>::new(), check_blocks(&*db, 0, 5));
+        assert_eq!(Vec::<DatabaseAnomaly>::new(), all_orphaned_bodies(&*db));
+    }
+
+    #[test]
+    fn detect_corrupted_entries() {
+        let (_chain, db, hashes, transactions) = new_chain(5);
+        let orphan = H256::from(7);
+        let mut batch = DBTransaction::new();
+        batch.delete(db::COL_BODIES, &hashes[1]);
+        let other_body = db.get(db::COL_BODIES, &hashes[3]).unwrap().unwrap();
+        batch.put(db::COL_BODIES, &hashes[2], &other_body);
+        let other_header = db.get(db::COL_HEADERS, &hashes[4]).unwrap().unwrap();
+        batch.put(db::COL_HEADERS, &hashes[3], &other_header);
+        Writable::delete::<Option<String>, _>(&mut batch, db::COL_ERROR_HINT, &transactions[4]);
+        batch.delete(db::COL_HEADERS, &hashes[5]);
+        batch.put(db::COL_BODIES, &orphan, &other_body);
+        db.write(batch).unwrap();
+
+        assert_eq!(
+            vec![
+                DatabaseAnomaly::MissingBody {
+                    number: 1,
+                    hash: hashes[1],
+                },
+                DatabaseAnomaly::CorruptBody {
+                    number: 2,
+                    hash: hashes[2],
+                },
+                DatabaseAnomaly::CorruptHeader {
+                    number: 3,
+                    hash: hashes[3],
+                },
+                DatabaseAnomaly::MissingInvoice {
+                    number: 4,
+                    hash: hashes[4],
+                    transaction: transactions[4],
+                },
+                DatabaseAnomaly::MissingHeader {
+                    number: 5,
+                    hash: hashes[5],
+                },
+            ],
+            check_blocks(&*db, 0, 5)
+        );
+
+        let orphans = all_orphaned_bodies(&*db);
+        assert_eq!(2, orphans.len());
+        assert!(orphans.contains(&DatabaseAnomaly::OrphanedBody {
+            hash: orphan,
+        }));
+        assert!(orphans.contains(&DatabaseAnomaly::OrphanedBody {
+            hash: hashes[5],
+        }));
+    }
+
+    #[test]
+    fn undecodable_body_is_corrupt() {
+        let (_chain, db, hashes, _transactions) = new_chain(2);
+        let mut batch = DBTransaction::new();
+        batch.put(db::COL_BODIES, &hashes[2], &[0x80]);
+        db.write(batch).unwrap();
+
+        assert_eq!(
+            vec![DatabaseAnomaly::CorruptBody {
+                number: 2,
+                hash: hashes[2],
+            }],
+            check_blocks(&*db, 0, 2)
+        );
+    }
+
+    #[test]
+    fn removed_body_is_not_served_from_the_cache() {
+        let (chain, db, hashes, _transactions) = new_chain(2);
+        assert!(chain.block_body(&hashes[1]).is_some());
+
+        let mut batch = DBTransaction::new();
+        chain.remove_bodies(&mut batch, &[hashes[1]]);
+        db.write(batch).unwrap();
+
+        assert!(chain.block_body(&hashes[1]).is_none());
+        assert_eq!(
+            vec![DatabaseAnomaly::MissingBody {
+                number: 1,
+                hash: hashes[1],
+            }],
+            chain.check_integrity(0, 2)
+        );
+    }
+}
@@ -14,18 +14,31 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod action_index_db;
+mod address_history_db;
 mod block_info;
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::module_inception))]
 mod blockchain;
 mod body_db;
+mod bytes_cache;
 mod extras;
 mod headerchain;
+mod integrity;
 mod invoice_db;
+mod reorg_protection;
 mod route;
 
-pub use self::blockchain::{BlockChain, BlockProvider};
-pub use self::body_db::BodyProvider;
-pub use self::extras::{BlockDetails, TransactionAddress, TransactionAddresses};
-pub use self::headerchain::HeaderProvider;
+pub use self::action_index_db::{ActionIndexBackfill, ActionIndexPage, ActionType};
+pub use self::address_history_db::{AddressHistoryBackfill, AddressHistoryPage};
+pub use self::blockchain::{BlockChain, BlockProvider, MAX_CACHE_BUDGET};
+pub use self::body_db::{BodyProvider, DEFAULT_BODY_CACHE_BUDGET};
+pub use self::bytes_cache::{CacheBudgetError, CacheStats};
+pub use self::extras::{
+    ActionIndexEntry, ActionIndexPosition, AddressHistoryEntry, AddressHistoryPosition, BlockDetails,
+    SkippedSealVerification, TransactionAddress, TransactionAddresses,
+};
+pub use self::headerchain::{HeaderProvider, DEFAULT_HEADER_CACHE_BUDGET};
+pub use self::integrity::DatabaseAnomaly;
 pub use self::invoice_db::InvoiceProvider;
+pub use self::reorg_protection::{Reorg, ReorgProtectionStatus, DEFAULT_MAX_REORG_DEPTH};
 pub use self::route::ImportRoute;
@@ -0,0 +1,139 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+
+use ctypes::BlockNumber;
+use parking_lot::Mutex;
+use primitives::H256;
+
+/// The reorg depth limit used when neither the engine nor the operator gives one.
+pub const DEFAULT_MAX_REORG_DEPTH: u64 = 100;
+
+/// A reorg that retracts canonical blocks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reorg {
+    pub best_block_hash: H256,
+    pub best_block_number: BlockNumber,
+    /// The imported block that switches the canonical chain.
+    pub new_block_hash: H256,
+    pub new_block_number: BlockNumber,
+    /// The number of the retracted canonical blocks.
+    pub depth: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReorgProtectionStatus {
+    pub max_depth: u64,
+    /// The last refused reorg. It stays until the operator overrides the protection.
+    pub last_rejected: Option<Reorg>,
+}
+
+#[derive(Default)]
+struct State {
+    last_rejected: Option<Reorg>,
+    /// The blocks of the refused branches. Their descendants are refused too.
+    rejected_blocks: HashSet<H256>,
+    overridden: bool,
+    /// The block whose reorg is allowed by the override.
+    /// The header chain and the block chain check the same block, so the decision is kept.
+    allowed_block: Option<H256>,
+}
+
+/// Refuses the reorgs that retract more canonical blocks than the limit.
+pub struct ReorgProtection {
+    max_depth: u64,
+    state: Mutex<State>,
+}
+
+impl ReorgProtection {
+    pub fn new(max_depth: u64) -> Self {
+        Self {
+            max_depth,
+            state: Default::default(),
+        }
+    }
+
+    /// Returns true if the block is on a refused branch, marking the block as rejected too.
+    pub fn is_on_rejected_branch(&self, hash: H256, parent_hash: &H256) -> bool {
+        let mut state = self.state.lock();
+        if !state.rejected_blocks.contains(parent_hash) {
+            return false
+        }
+        state.rejected_blocks.insert(hash);
+        true
+    }
+
+    /// Returns false if the reorg must be refused.
+    ///
+    /// `enacted` are the blocks of the new branch. They are marked as rejected when the reorg is refused.
+    pub fn allows(&self, reorg: Reorg, enacted: &[H256]) -> bool {
+        if reorg.depth <= self.max_depth {
+            return true
+        }
+        let mut state = self.state.lock();
+        if state.allowed_block == Some(reorg.new_block_hash) {
+            return true
+        }
+        if state.rejected_blocks.contains(&reorg.new_block_hash) {
+            return false
+        }
+        if state.overridden {
+            cwarn!(
+                BLOCKCHAIN,
+                "Reorg of depth {} to #{}({}) is allowed by the override",
+                reorg.depth,
+                reorg.new_block_number,
+                reorg.new_block_hash
+            );
+            state.overridden = false;
+            state.allowed_block = Some(reorg.new_block_hash);
+            return true
+        }
+        cerror!(
+            BLOCKCHAIN,
+            "REORG REFUSED: Importing #{}({}) retracts {} blocks, more than the limit {}. \
+             The best block stays at #{}({}). Call devel_overrideReorgProtection to allow it.",
+            reorg.new_block_number,
+            reorg.new_block_hash,
+            reorg.depth,
+            self.max_depth,
+            reorg.best_block_number,
+            reorg.best_block_hash
+        );
+        state.rejected_blocks.extend(enacted.iter().cloned());
+        state.rejected_blocks.insert(reorg.new_block_hash);
+        state.last_rejected = Some(reorg);
+        false
+    }
+
+    pub fn status(&self) -> ReorgProtectionStatus {
+        ReorgProtectionStatus {
+            max_depth: self.max_depth,
+            last_rejected: self.state.lock().last_rejected.clone(),
+        }
+    }
+
+    /// Clears the alert and the rejected branches, and lets the next reorg pass regardless of its depth.
+    pub fn override_protection(&self) {
+        let mut state = self.state.lock();
+        cwarn!(BLOCKCHAIN, "Reorg protection is overridden. The next reorg will be allowed regardless of its depth");
+        state.last_rejected = None;
+        state.rejected_blocks.clear();
+        state.overridden = true;
+        state.allowed_block = None;
+    }
+}
@@ -0,0 +1,189 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use ckey::{Address, Public};
+use cstate::{ActionDataAccess, ActionHandler, FindActionHandler, StateResult, TopLevelState};
+use ctypes::errors::{RuntimeError, SyntaxError};
+use ctypes::transaction::Action;
+use ctypes::{BlockNumber, CommonParams, Header};
+use cvm::ChainTimeInfo;
+use parking_lot::Mutex;
+use primitives::H256;
+
+use crate::error::Error;
+use crate::transaction::SignedTransaction;
+
+/// The transaction to trace.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TraceTarget {
+    /// A transaction in the canonical chain. It is re-executed at the state right before it.
+    Hash(H256),
+    /// A transaction that is not mined yet. It is executed at the state of the best block.
+    Transaction(SignedTransaction),
+}
+
+/// The action data that a custom action touched and the result of its action handler.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActionHandlerTrace {
+    pub handler_id: u64,
+    pub handler_name: &'static str,
+    /// The reads and the writes in the order of the execution.
+    pub accesses: Vec<ActionDataAccess>,
+    /// The error hint of the action handler. None if it succeeded.
+    pub error: Option<String>,
+}
+
+/// Records the action data that the wrapped handler reads and writes while executing a custom action.
+pub struct TracingActionHandler<'a> {
+    inner: &'a ActionHandler,
+    trace: Mutex<Option<(Vec<ActionDataAccess>, Option<String>)>>,
+}
+
+impl<'a> TracingActionHandler<'a> {
+    pub fn new(inner: &'a ActionHandler) -> Self {
+        Self {
+            inner,
+            trace: Default::default(),
+        }
+    }
+
+    /// Returns None if no custom action is executed.
+    pub fn into_trace(self) -> Option<ActionHandlerTrace> {
+        let handler_id = self.inner.handler_id();
+        let handler_name = self.inner.name();
+        self.trace.into_inner().map(|(accesses, error)| ActionHandlerTrace {
+            handler_id,
+            handler_name,
+            accesses,
+            error,
+        })
+    }
+}
+
+impl<'a> ActionHandler for TracingActionHandler<'a> {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn handler_id(&self) -> u64 {
+        self.inner.handler_id()
+    }
+
+    fn init(&self, state: &mut TopLevelState) -> StateResult<()> {
+        self.inner.init(state)
+    }
+
+    fn execute(
+        &self,
+        bytes: &[u8],
+        state: &mut TopLevelState,
+        fee_payer: &Address,
+        sender_pubkey: &Public,
+    ) -> StateResult<()> {
+        state.trace_action_data();
+        let result = self.inner.execute(bytes, state, fee_payer, sender_pubkey);
+        let accesses = state.take_action_data_trace();
+        let error = result.as_ref().err().map(ToString::to_string);
+        *self.trace.lock() = Some((accesses, error));
+        result
+    }
+
+    fn verify(&self, bytes: &[u8], common_params: &CommonParams) -> Result<(), SyntaxError> {
+        self.inner.verify(bytes, common_params)
+    }
+
+    fn query(&self, key_fragment: &[u8], state: &TopLevelState) -> StateResult<Option<Vec<u8>>> {
+        self.inner.query(key_fragment, state)
+    }
+
+    fn on_close_block(
+        &self,
+        state: &mut TopLevelState,
+        header: &Header,
+        parent_header: &Header,
+        parent_common_params: &CommonParams,
+    ) -> StateResult<()> {
+        self.inner.on_close_block(state, header, parent_header, parent_common_params)
+    }
+}
+
+/// Replaces the traced action handler with its tracer.
+struct TracedHandlers<'a, C> {
+    client: &'a C,
+    tracer: &'a TracingActionHandler<'a>,
+}
+
+impl<'a, C: ChainTimeInfo> ChainTimeInfo for TracedHandlers<'a, C> {
+    fn transaction_block_age(&self, tracker: &H256, parent_block_number: BlockNumber) -> Option<u64> {
+        self.client.transaction_block_age(tracker, parent_block_number)
+    }
+
+    fn transaction_time_age(&self, tracker: &H256, parent_timestamp: u64) -> Option<u64> {
+        self.client.transaction_time_age(tracker, parent_timestamp)
+    }
+}
+
+impl<'a, C: FindActionHandler> FindActionHandler for TracedHandlers<'a, C> {
+    fn find_action_handler_for(&self, id: u64) -> Option<&ActionHandler> {
+        if id == self.tracer.handler_id() {
+            return Some(self.tracer)
+        }
+        self.client.find_action_handler_for(id)
+    }
+}
+
+/// Apply the custom action on the given state while tracing its action handler.
+/// The changes stay in the cache of the state, so the caller must drop it without committing.
+pub fn trace_custom_action<C: ChainTimeInfo + FindActionHandler>(
+    state: &mut TopLevelState,
+    tx: &SignedTransaction,
+    client: &C,
+    parent_block_number: BlockNumber,
+    parent_block_timestamp: u64,
+    current_block_timestamp: u64,
+) -> Result<ActionHandlerTrace, Error> {
+    let handler_id = match &tx.action {
+        Action::Custom {
+            handler_id,
+            ..
+        } => *handler_id,
+        _ => return Err(RuntimeError::FailedToHandleCustomAction("Not a custom action".to_string()).into()),
+    };
+    let handler = client
+        .find_action_handler_for(handler_id)
+        .ok_or_else(|| RuntimeError::FailedToHandleCustomAction(format!("Unknown handler id: {}", handler_id)))?;
+    let tracer = TracingActionHandler::new(handler);
+    let result = state.apply(
+        tx,
+        &tx.hash(),
+        &tx.signer_public(),
+        &TracedHandlers {
+            client,
+            tracer: &tracer,
+        },
+        parent_block_number,
+        parent_block_timestamp,
+        current_block_timestamp,
+    );
+    match tracer.into_trace() {
+        Some(trace) => Ok(trace),
+        // The transaction failed before its custom action, e.g. it couldn't pay the fee.
+        None => Err(result.err().map(Error::from).unwrap_or_else(|| {
+            RuntimeError::FailedToHandleCustomAction("The action handler is not executed".to_string()).into()
+        })),
+    }
+}
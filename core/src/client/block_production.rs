@@ -0,0 +1,150 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use ckey::Address;
+use ctypes::Header;
+
+use crate::consensus::CodeChainEngine;
+
+/// The maximum number of blocks that the statistics cover.
+pub const MAX_BLOCK_PRODUCTION_WINDOW: u64 = 10_000;
+
+/// The statistics of the recent blocks.
+/// The intervals are in seconds.
+#[derive(Debug, PartialEq)]
+pub struct BlockProductionStats {
+    pub blocks: u64,
+    pub mean_interval: Option<f64>,
+    pub median_interval: Option<u64>,
+    pub p95_interval: Option<u64>,
+    /// The number of blocks proposed after a view change. None if the engine doesn't have views.
+    pub view_changed_blocks: Option<u64>,
+    pub proposers: BTreeMap<Address, u64>,
+}
+
+impl BlockProductionStats {
+    /// `headers` are ordered from the newest one.
+    /// The last header only gives the parent timestamp of the previous one, and isn't counted.
+    pub fn new<I>(headers: I, engine: &CodeChainEngine) -> Self
+    where
+        I: IntoIterator<Item = Header>, {
+        let mut intervals = Vec::new();
+        let mut view_changed_blocks = None;
+        let mut proposers = BTreeMap::new();
+
+        let mut child: Option<Header> = None;
+        for header in headers {
+            if let Some(child) = child {
+                intervals.push(child.timestamp().saturating_sub(header.timestamp()));
+                if let Some(view) = engine.block_view(&child) {
+                    *view_changed_blocks.get_or_insert(0) += u64::from(view > 0);
+                }
+                *proposers.entry(*child.author()).or_insert(0) += 1;
+            }
+            child = Some(header);
+        }
+
+        let mean_interval = if intervals.is_empty() {
+            None
+        } else {
+            Some(intervals.iter().sum::<u64>() as f64 / intervals.len() as f64)
+        };
+        intervals.sort_unstable();
+        Self {
+            blocks: intervals.len() as u64,
+            mean_interval,
+            median_interval: percentile(&intervals, 50),
+            p95_interval: percentile(&intervals, 95),
+            view_changed_blocks,
+            proposers,
+        }
+    }
+}
+
+/// The nearest-rank percentile of the sorted values
+fn percentile(sorted: &[u64], percent: usize) -> Option<u64> {
+    if sorted.is_empty() {
+        return None
+    }
+    let rank = (sorted.len() * percent + 99) / 100;
+    Some(sorted[rank.max(1) - 1])
+}
+
+#[cfg(test)]
+mod tests {
+    use rlp::Encodable;
+
+    use super::*;
+    use crate::scheme::Scheme;
+
+    fn header(number: u64, timestamp: u64, author: Address, view: u64) -> Header {
+        let mut header = Header::new();
+        header.set_number(number);
+        header.set_timestamp(timestamp);
+        header.set_author(author);
+        header.set_seal(vec![0u64.rlp_bytes().into_vec(), view.rlp_bytes().into_vec()]);
+        header
+    }
+
+    #[test]
+    fn statistics_of_tendermint_blocks() {
+        let engine = Scheme::new_test_tendermint().engine;
+        let a = Address::random();
+        let b = Address::random();
+        // From the newest block. The intervals are 3, 9, 3, 3, 4 and 3 seconds.
+        let headers = vec![
+            header(6, 125, a, 0),
+            header(5, 122, b, 1),
+            header(4, 113, a, 0),
+            header(3, 110, b, 0),
+            header(2, 107, a, 2),
+            header(1, 103, b, 0),
+            header(0, 100, Address::default(), 0),
+        ];
+
+        let stats = BlockProductionStats::new(headers, &*engine);
+        assert_eq!(6, stats.blocks);
+        assert_eq!(Some(25.0 / 6.0), stats.mean_interval);
+        assert_eq!(Some(3), stats.median_interval);
+        assert_eq!(Some(9), stats.p95_interval);
+        assert_eq!(Some(2), stats.view_changed_blocks);
+        assert_eq!(vec![(a, 3), (b, 3)].into_iter().collect::<BTreeMap<_, _>>(), stats.proposers);
+    }
+
+    #[test]
+    fn engine_without_views() {
+        let engine = Scheme::new_test_solo().engine;
+        let author = Address::random();
+        let headers = vec![header(2, 110, author, 0), header(1, 100, author, 0)];
+
+        let stats = BlockProductionStats::new(headers, &*engine);
+        assert_eq!(1, stats.blocks);
+        assert_eq!(Some(10), stats.median_interval);
+        assert_eq!(None, stats.view_changed_blocks);
+    }
+
+    #[test]
+    fn no_blocks() {
+        let engine = Scheme::new_test_solo().engine;
+        let stats = BlockProductionStats::new(vec![Header::new()], &*engine);
+        assert_eq!(0, stats.blocks);
+        assert_eq!(None, stats.mean_interval);
+        assert_eq!(None, stats.p95_interval);
+        assert!(stats.proposers.is_empty());
+    }
+}
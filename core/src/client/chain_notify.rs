@@ -50,4 +50,9 @@ pub trait ChainNotify: Send + Sync {
     fn transactions_received(&self, _hashes: Vec<H256>, _peer_id: NodeId) {
         // does nothing by default
     }
+
+    /// fires when the bodies of the imported headers should be downloaded again
+    fn bodies_missing(&self, _hashes: Vec<H256>) {
+        // does nothing by default
+    }
 }
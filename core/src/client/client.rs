@@ -14,49 +14,86 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::cmp;
+use std::iter;
+use std::net::IpAddr;
 use std::ops::Range;
-use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrdering};
 use std::sync::{Arc, Weak};
-use std::time::Instant;
+use std::thread::Builder;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use cio::IoChannel;
 use ckey::{Address, PlatformAddress, Public};
-use cmerkle::Result as TrieResult;
+use cmerkle::{skewed_merkle_root, Result as TrieResult};
 use cnetwork::NodeId;
 use cstate::{
     ActionHandler, AssetScheme, FindActionHandler, OwnedAsset, StateDB, StateResult, Text, TopLevelState, TopStateView,
 };
 use ctimer::{TimeoutHandler, TimerApi, TimerScheduleError, TimerToken};
-use ctypes::transaction::{AssetTransferInput, PartialHashing, ShardTransaction};
-use ctypes::{BlockNumber, CommonParams, ShardId};
+use ctypes::transaction::{Action, AssetTransferInput, PartialHashing, ShardTransaction, Transaction};
+use ctypes::header::Seal;
+use ctypes::{BlockNumber, CommonParams, Header, ShardId};
 use cvm::{decode, execute, ChainTimeInfo, ScriptResult, VMConfig};
 use hashdb::AsHashDB;
 use journaldb;
 use kvdb::{DBTransaction, KeyValueDB};
 use parking_lot::{Mutex, RwLock, RwLockReadGuard};
 use primitives::{Bytes, H160, H256, U256};
-use rlp::UntrustedRlp;
+use rlp::{Encodable, UntrustedRlp};
 
+use super::database_stats::column_stats;
+use super::finality::{block_finality, FinalityRule, FinalitySource};
+use super::finality_conflict::{find_finality_conflict, ConflictSource, ConsensusHalt, FinalityConflict};
 use super::importer::Importer;
+use super::params_history::{ParamsHistory, ParamsHistorySource};
+use super::state_warming::StateWarming;
+use super::term_index::{timestamp_window, TermIndex};
 use super::{
-    AccountData, AssetClient, BlockChainClient, BlockChainInfo, BlockChainTrait, BlockProducer, ChainNotify,
-    ClientConfig, DatabaseClient, EngineClient, EngineInfo, Error as ClientError, ExecuteClient, ImportBlock,
-    ImportResult, MiningBlockChainClient, Shard, StateInfo, StateOrBlock, TextClient,
+    AccountData, AssetClient, BlockChainClient, BlockChainInfo, BlockChainTrait, BlockFinality, BlockImportTimings,
+    BlockProducer, BlockProductionStats, BlockRewardDistribution, ChainNotify, ClientConfig, DatabaseClient,
+    DatabaseStats, EngineClient, EngineInfo, Error as ClientError, ExecuteClient, ExecutionCache, ExecutionCacheStats,
+    ImportBlock, ImportResult, IntegrityCheck, IntegrityReport, MiningBlockChainClient, OptimisticExecutionStats,
+    OptimisticExecutions,
+    ParamsActivation, PhaseTimer, RangeStatistics, RangeStatisticsBuilder, RepairOptions, Shard, StateInfo,
+    StateOrBlock, StateWarmingStatus, TermDetails, TextClient, MAX_BLOCK_PRODUCTION_WINDOW, MAX_WAIT_FOR_EXECUTION,
 };
-use crate::block::{ClosedBlock, IsBlock, OpenBlock, SealedBlock};
-use crate::blockchain::{BlockChain, BlockProvider, BodyProvider, HeaderProvider, InvoiceProvider, TransactionAddress};
-use crate::client::{ConsensusClient, TermInfo};
-use crate::consensus::{CodeChainEngine, EngineError};
+use crate::block::{enact, replay, Block, ClosedBlock, IsBlock, LockedBlock, OpenBlock, SealedBlock};
+use crate::blockchain::{
+    ActionIndexBackfill, ActionIndexPage, ActionIndexPosition, AddressHistoryPage, AddressHistoryPosition, BlockChain,
+    BlockProvider, BodyProvider, CacheBudgetError, HeaderProvider, InvoiceProvider, ReorgProtectionStatus,
+    SkippedSealVerification, TransactionAddress, MAX_CACHE_BUDGET,
+};
+use crate::client::{
+    dry_run, trace_custom_action, ActionHandlerTrace, BlockDivergence, ConsensusClient, DryRunOptions, DryRunResult,
+    ReplayOptions, ReplayReport, ReplayedTransaction, TermInfo, TraceTarget,
+};
+use crate::codechain_machine::CodeChainMachine;
+use crate::consensus::{
+    stake, CandidateStatus, CodeChainEngine, ConsensusFault, ConsensusStatus, DecodedSeal, EngineDescription,
+    EngineError, RestoreStatus, SignerEligibility, WeightedValidator,
+};
+use crate::db::Readable;
 use crate::encoded;
-use crate::error::{BlockImportError, Error, ImportError, SchemeError};
-use crate::miner::{Miner, MinerService};
+use crate::error::{BlockError, BlockImportError, Error, ImportError, SchemeError};
+use crate::miner::{
+    FeeFloorStatus, Miner, MinerService, PendingQueue, PooledTransaction, RelayStatus, ScheduledTransaction,
+    SignerDiagnosis, TxOrigin,
+};
 use crate::scheme::Scheme;
 use crate::service::ClientIoMessage;
 use crate::transaction::{LocalizedTransaction, PendingSignedTransactions, SignedTransaction, UnverifiedTransaction};
 use crate::types::{BlockId, BlockStatus, TransactionId, VerificationQueueInfo as BlockQueueInfo};
+use crate::verification::BlockRejection;
+use crate::views::BlockView;
 
 const MAX_MEM_POOL_SIZE: usize = 4096;
 
+/// The number of blocks indexed at once by the backfill of the action index.
+/// The block import and the other client messages are handled between the chunks.
+const ACTION_INDEX_BACKFILL_CHUNK_SIZE: u64 = 256;
+const ADDRESS_HISTORY_BACKFILL_CHUNK_SIZE: u64 = 256;
+
 pub struct Client {
     engine: Arc<CodeChainEngine>,
 
@@ -79,8 +116,37 @@ pub struct Client {
 
     importer: Importer,
 
+    term_index: TermIndex,
+
+    params_history: ParamsHistory,
+
+    state_warming: StateWarming,
+
     /// Timer for reseal_min_period/reseal_max_period on miner client
     reseal_timer: TimerApi,
+
+    /// The blocks are neither imported nor produced while the chain is frozen.
+    /// It's kept only in memory, so a restarted node isn't frozen.
+    frozen: AtomicBool,
+
+    /// The import and the sealing are halted after two conflicting blocks are finalized.
+    consensus_halt: ConsensusHalt,
+
+    /// The running integrity check of the database, or the last one.
+    integrity_check: Mutex<Option<IntegrityCheck>>,
+
+    /// The proposals executed before their import.
+    optimistic_executions: OptimisticExecutions,
+
+    /// The blocks closed by the miner, which are not imported yet.
+    execution_cache: ExecutionCache,
+
+    /// The engine signer and the term that its eligibility was checked last.
+    signer_checked_at: Mutex<Option<(Address, u64)>>,
+
+    /// Every block execution sleeps for this long. It imitates a slow executor.
+    #[cfg(any(test, feature = "fault-injection"))]
+    execution_delay: Mutex<Option<Duration>>,
 }
 
 impl Client {
@@ -106,13 +172,26 @@ impl Client {
         }
 
         let gb = scheme.genesis_block();
-        let chain = BlockChain::new(&gb, db.clone());
+        let max_reorg_depth = config.max_reorg_depth.unwrap_or_else(|| scheme.engine.max_reorg_depth());
+        let chain = BlockChain::new(&gb, db.clone(), max_reorg_depth, config.address_history, config.action_index);
         scheme.check_genesis_common_params(&chain)?;
 
         let engine = scheme.engine.clone();
 
         let importer = Importer::try_new(config, engine.clone(), message_channel.clone(), miner)?;
         let genesis_accounts = scheme.genesis_accounts();
+        let state_warming = StateWarming::new(db.as_ref());
+        let params_history = ParamsHistory::new(db.as_ref());
+        let consensus_halt = ConsensusHalt::new(db.as_ref());
+        match consensus_halt.conflict() {
+            Some(conflict) => {
+                cerror!(CLIENT, "The chain is halted by the conflicting blocks #{} found before", conflict.block_number)
+            }
+            None if consensus_halt.is_halted() => {
+                cerror!(CLIENT, "The chain is halted by the conflicting blocks found before, but the proof is lost")
+            }
+            None => {}
+        }
 
         let client = Arc::new(Client {
             engine,
@@ -124,14 +203,82 @@ impl Client {
             queue_transactions: AtomicUsize::new(0),
             genesis_accounts,
             importer,
+            term_index: Default::default(),
+            params_history,
+            state_warming,
             reseal_timer,
+            frozen: AtomicBool::new(false),
+            consensus_halt,
+            integrity_check: Mutex::new(None),
+            optimistic_executions: Default::default(),
+            execution_cache: Default::default(),
+            signer_checked_at: Mutex::new(None),
+            #[cfg(any(test, feature = "fault-injection"))]
+            execution_delay: Mutex::new(None),
         });
 
+        // Backfills the history of the common params if the database was created by an older version.
+        client.sync_params_history();
+
         // ensure buffered changes are flushed.
         client.db.flush().map_err(ClientError::Database)?;
+        Self::warm_state_cache(&client, config.state_warming_max_entries, config.state_warming_timeout);
         Ok(client)
     }
 
+    /// Loads the state items that the latest blocks touched into the cache in the background.
+    fn warm_state_cache(client: &Arc<Client>, max_entries: usize, timeout: Duration) {
+        let keys = client.state_warming.hot_keys(max_entries);
+        if keys.is_empty() {
+            client.state_warming.finish();
+            return
+        }
+        let weak = Arc::downgrade(client);
+        let spawned = Builder::new().name("state-warming".to_string()).spawn(move || {
+            let client = match weak.upgrade() {
+                Some(client) => client,
+                None => return,
+            };
+            let root = client.block_chain().best_block_header().state_root();
+            let db = client.state_db.read().clone(&root);
+            match TopLevelState::from_existing(db, root) {
+                Ok(state) => {
+                    let loaded = client.state_warming.load(&state, &keys, timeout);
+                    let cached = client.state_db.write().warm_cache(&state);
+                    cinfo!(
+                        CLIENT,
+                        "{} of {} hot state items are loaded, and {} of them are cached",
+                        loaded,
+                        keys.len(),
+                        cached
+                    );
+                }
+                Err(err) => cwarn!(CLIENT, "Cannot warm the state cache: {}", err),
+            }
+            client.state_warming.finish();
+        });
+        if let Err(err) = spawned {
+            cwarn!(CLIENT, "Cannot start warming the state cache: {}", err);
+            client.state_warming.finish();
+        }
+    }
+
+    /// Must be called when the block becomes the best block.
+    /// The keys are written to the database only once in a while.
+    pub fn record_hot_state_keys(&self, number: BlockNumber, state: &TopLevelState) {
+        if self.state_warming.record(number, state.touched_keys()) {
+            self.save_hot_state_keys();
+        }
+    }
+
+    /// Writes the hot state keys recorded after they were written last. It's called at the shutdown too.
+    pub fn save_hot_state_keys(&self) {
+        let mut batch = DBTransaction::new();
+        if self.state_warming.save(&mut batch) {
+            self.db.write_buffered(batch);
+        }
+    }
+
     /// Returns engine reference.
     pub fn engine(&self) -> &CodeChainEngine {
         &*self.engine
@@ -214,6 +361,10 @@ impl Client {
 
     /// This is triggered by a message coming from a engine when a new block should be created
     pub fn update_sealing(&self, parent_block: BlockId, allow_empty_block: bool) {
+        if self.is_chain_frozen() {
+            ctrace!(CLIENT, "The chain is frozen, skip sealing");
+            return
+        }
         self.importer.miner.update_sealing(self, parent_block, allow_empty_block);
     }
 
@@ -251,7 +402,7 @@ impl Client {
             transactions.iter().filter_map(|bytes| UntrustedRlp::new(bytes).as_val().ok()).collect();
         let hashes: Vec<_> = transactions.iter().map(UnverifiedTransaction::hash).collect();
         self.transactions_received(&hashes, peer_id);
-        let results = self.importer.miner.import_external_transactions(self, transactions);
+        let results = self.importer.miner.import_external_transactions(self, transactions, peer_id);
         results.len()
     }
 
@@ -280,6 +431,8 @@ impl Client {
         if route.is_none() {
             return
         }
+        self.sync_params_history();
+        self.check_signer_eligibility();
 
         let (enacted, retracted) = self.importer.calculate_enacted_retracted(&[route]);
         self.importer.miner.chain_new_blocks(self, &[], &[], &enacted, &retracted);
@@ -320,9 +473,347 @@ impl Client {
         self.chain.read()
     }
 
+    pub fn term_index(&self) -> &TermIndex {
+        &self.term_index
+    }
+
     pub fn db(&self) -> &Arc<KeyValueDB> {
         &self.db
     }
+
+    /// Makes the history of the common params follow the canonical chain.
+    pub fn sync_params_history(&self) {
+        let mut batch = DBTransaction::new();
+        self.params_history.sync(&CanonicalChain(self), &mut batch);
+        self.db.write(batch).expect("DB write failed.");
+    }
+
+    fn common_params_in_state(&self, block_id: BlockId) -> Option<CommonParams> {
+        self.state_info(block_id.into()).map(|state| {
+            state
+                .metadata()
+                .unwrap_or_else(|err| unreachable!("Unexpected failure. Maybe DB was corrupted: {:?}", err))
+                .unwrap()
+                .params()
+                .map(Clone::clone)
+                .unwrap_or_else(|| *self.engine().machine().genesis_common_params())
+        })
+    }
+
+    /// Executes the block against the given state of its parent.
+    pub fn execute_block(
+        &self,
+        header: &Header,
+        transactions: &[SignedTransaction],
+        db: StateDB,
+        parent: &Header,
+    ) -> Result<LockedBlock, Error> {
+        #[cfg(any(test, feature = "fault-injection"))]
+        {
+            if let Some(delay) = *self.execution_delay.lock() {
+                ::std::thread::sleep(delay);
+            }
+        }
+        enact(header, transactions, &*self.engine, self, db, parent)
+    }
+
+    /// Makes every block execution slower by the given delay.
+    #[cfg(any(test, feature = "fault-injection"))]
+    pub fn set_execution_delay(&self, delay: Option<Duration>) {
+        *self.execution_delay.lock() = delay;
+    }
+
+    /// Executes the proposal against the state of its parent, and keeps the result for its import.
+    /// Nothing is written to the database here.
+    pub fn execute_proposal_in_advance(&self, block: &[u8]) {
+        let view = BlockView::new(block);
+        let header = view.header();
+        let hash = header.hash();
+        if !self.optimistic_executions.begin(&hash) {
+            ctrace!(CLIENT, "The execution of the proposal #{} ({}) in advance is dropped", header.number(), hash);
+            return
+        }
+        let result = self
+            .block_chain()
+            .block_header(header.parent_hash())
+            .ok_or_else(|| Error::from(BlockError::UnknownParent(*header.parent_hash())));
+        let result = result.and_then(|parent| {
+            let transactions = view
+                .transactions()
+                .into_iter()
+                .map(|tx| CodeChainMachine::verify_transaction_seal(tx, &header))
+                .collect::<Result<Vec<_>, _>>()?;
+            let db = self.state_db.read().clone(&parent.state_root());
+            self.execute_block(&header, &transactions, db, &parent)
+        });
+        let executed = match result {
+            Ok(executed) => {
+                ctrace!(CLIENT, "The proposal #{} ({}) is executed before its import", header.number(), hash);
+                Some(executed)
+            }
+            Err(err) => {
+                cdebug!(CLIENT, "Cannot execute the proposal #{} ({}) in advance: {:?}", header.number(), hash, err);
+                None
+            }
+        };
+        self.optimistic_executions.finish(&hash, executed);
+    }
+
+    /// Halts the import and the sealing if the header finalizes a block that conflicts with a finalized canonical one.
+    /// Returns true if it's halted. The seal of the header must be verified before.
+    pub fn check_finality_conflict(&self, header: &Header) -> bool {
+        let engine = self.engine();
+        if FinalityRule::new(engine.engine_type(), engine.recommended_confirmation()) != FinalityRule::Precommits {
+            return false
+        }
+        let conflict = match find_finality_conflict(&CanonicalChain(self), header) {
+            Some(conflict) => conflict,
+            None => return false,
+        };
+        cerror!(
+            CLIENT,
+            "Both #{} ({}) and {} are finalized. More than a third of the validators are byzantine. \
+             The import and the sealing are halted until devel_clearConsensusHalt is called",
+            conflict.block_number,
+            conflict.canonical.header.hash(),
+            conflict.conflicting.header.hash()
+        );
+        self.consensus_halt.halt(conflict, self.db.as_ref());
+        true
+    }
+
+    /// Imports the headers and the blocks that were verified while the import was stopped.
+    fn resume_import(&self) {
+        let io_channel = self.io_channel.lock();
+        for message in vec![ClientIoMessage::HeaderVerified, ClientIoMessage::BlockVerified] {
+            if let Err(e) = io_channel.send(message) {
+                cerror!(CLIENT, "Error while resuming the import: {}", e);
+            }
+        }
+    }
+
+    /// Takes the result of the proposal executed in advance, waiting for it for a while if it's being executed.
+    /// The result is dropped if it's not valid.
+    pub fn take_executed_proposal<F>(&self, hash: &H256, is_valid: F) -> Option<LockedBlock>
+    where
+        F: FnOnce(&LockedBlock) -> bool, {
+        self.optimistic_executions.take(hash, MAX_WAIT_FOR_EXECUTION, is_valid)
+    }
+
+    /// Waits until the proposals requested to be executed in advance are executed.
+    #[cfg(test)]
+    pub fn wait_for_executed_proposals(&self, timeout: Duration) -> bool {
+        self.optimistic_executions.wait_until_idle(timeout)
+    }
+
+    pub fn optimistic_execution_stats(&self) -> OptimisticExecutionStats {
+        self.optimistic_executions.stats()
+    }
+
+    /// The number of the results of the proposals executed in advance, which are not imported yet.
+    pub fn num_executed_proposals(&self) -> usize {
+        self.optimistic_executions.len()
+    }
+
+    /// Keeps the execution of the block, so that its import doesn't execute it again.
+    /// It's reused only for the block of the same hash executed against the same parent state.
+    pub fn cache_execution(&self, parent_state_root: H256, hash: H256, number: BlockNumber, block: LockedBlock) {
+        self.execution_cache.insert(parent_state_root, hash, number, block);
+    }
+
+    /// Takes the execution of the block closed by the miner. The execution is dropped if it's not valid.
+    pub fn take_cached_execution<F>(&self, parent_state_root: &H256, hash: &H256, is_valid: F) -> Option<LockedBlock>
+    where
+        F: FnOnce(&LockedBlock) -> bool, {
+        self.execution_cache.take(parent_state_root, hash, is_valid)
+    }
+
+    /// Drops the executions of the blocks at or below the best block.
+    pub fn evict_cached_executions(&self, best_block_number: BlockNumber) {
+        self.execution_cache.evict_up_to(best_block_number);
+    }
+
+    pub fn execution_cache_stats(&self) -> ExecutionCacheStats {
+        self.execution_cache.stats()
+    }
+
+    /// The number of the executions of the blocks closed by the miner, which are not imported yet.
+    pub fn num_cached_executions(&self) -> usize {
+        self.execution_cache.len()
+    }
+
+    /// Runs the next chunk of the integrity check, and schedules the one after it.
+    pub fn check_database_integrity(&self) {
+        let mut integrity_check = self.integrity_check.lock();
+        let check = match integrity_check.as_mut() {
+            Some(check) if !check.is_finished() => check,
+            _ => return,
+        };
+        check.step(&*self.block_chain(), &*self.db);
+        if !check.is_finished() {
+            if let Err(e) = self.io_channel.lock().send(ClientIoMessage::CheckDatabaseIntegrity) {
+                cerror!(CLIENT, "Error while continuing the integrity check: {}", e);
+            }
+            return
+        }
+        let report = check.report();
+        cinfo!(
+            CLIENT,
+            "The integrity check of #{}..=#{} found {} anomalies",
+            report.from,
+            report.to,
+            report.anomalies.len()
+        );
+        if !report.refetched_bodies.is_empty() {
+            let hashes = report.refetched_bodies.clone();
+            self.notify(|notify| notify.bodies_missing(hashes.clone()));
+        }
+    }
+
+    /// Warns if the engine signer cannot sign the blocks.
+    /// It's checked once for each term, and again when the engine signer is changed.
+    pub fn check_signer_eligibility(&self) {
+        let signer = self.importer.miner.authoring_params().author;
+        let term_id = match self.current_term_id(BlockId::Latest) {
+            Some(term_id) => term_id,
+            None => return,
+        };
+        {
+            let mut checked_at = self.signer_checked_at.lock();
+            if *checked_at == Some((signer, term_id)) {
+                return
+            }
+            *checked_at = Some((signer, term_id));
+        }
+        match self.signer_eligibility() {
+            None | Some(SignerEligibility::Validator) => {}
+            Some(SignerEligibility::Candidate) => {
+                cwarn!(ENGINE, "The engine signer {} is not elected for the term {}", signer, term_id)
+            }
+            Some(SignerEligibility::Jailed {
+                custody_until,
+                released_at,
+            }) => cwarn!(
+                ENGINE,
+                "The engine signer {} is jailed until the term {}, and is released at the term {}",
+                signer,
+                custody_until,
+                released_at
+            ),
+            Some(SignerEligibility::Banned) => cwarn!(ENGINE, "The engine signer {} is banned. It never signs", signer),
+            Some(SignerEligibility::Unknown) => cwarn!(
+                ENGINE,
+                "The engine signer {} is neither a validator nor a candidate. Check the registered signing key",
+                signer
+            ),
+        }
+    }
+
+    /// Starts indexing the blocks imported before the action index was enabled, if they're not indexed yet.
+    /// It must be called after the client is set as the handler of the IO messages.
+    pub fn start_action_index_backfill(&self) {
+        let backfill = match self.block_chain().action_index_backfill() {
+            Some(backfill) if !backfill.is_finished() => backfill,
+            _ => return,
+        };
+        cinfo!(CLIENT, "Indexing the actions of #{}..=#{} in the background", backfill.next, backfill.target);
+        if let Err(e) = self.io_channel.lock().send(ClientIoMessage::BackfillActionIndex) {
+            cerror!(CLIENT, "Error while starting the backfill of the action index: {}", e);
+        }
+    }
+
+    /// Indexes the next chunk of the blocks imported before the action index was enabled,
+    /// and schedules the one after it.
+    pub fn backfill_action_index(&self) {
+        let backfill = {
+            // The canonical chain must not be changed while the chunk is indexed.
+            let _import_lock = self.importer.import_lock.lock();
+            self.block_chain().backfill_action_index(ACTION_INDEX_BACKFILL_CHUNK_SIZE)
+        };
+        let backfill = match backfill {
+            Some(backfill) => backfill,
+            None => return,
+        };
+        if backfill.is_finished() {
+            cinfo!(CLIENT, "The actions of #0..=#{} are indexed", backfill.target);
+            return
+        }
+        if let Err(e) = self.io_channel.lock().send(ClientIoMessage::BackfillActionIndex) {
+            cerror!(CLIENT, "Error while continuing the backfill of the action index: {}", e);
+        }
+    }
+
+    /// Starts indexing the blocks imported before the address history was enabled, if they're not indexed yet.
+    /// It must be called after the client is set as the handler of the IO messages.
+    pub fn start_address_history_backfill(&self) {
+        let backfill = match self.block_chain().address_history_backfill() {
+            Some(backfill) if !backfill.is_finished() => backfill,
+            _ => return,
+        };
+        cinfo!(CLIENT, "Indexing the address histories of #{}..=#{} in the background", backfill.next, backfill.target);
+        if let Err(e) = self.io_channel.lock().send(ClientIoMessage::BackfillAddressHistory) {
+            cerror!(CLIENT, "Error while starting the backfill of the address history: {}", e);
+        }
+    }
+
+    /// Indexes the next chunk of the blocks imported before the address history was enabled,
+    /// and schedules the one after it.
+    pub fn backfill_address_history(&self) {
+        let backfill = {
+            // The canonical chain must not be changed while the chunk is indexed.
+            let _import_lock = self.importer.import_lock.lock();
+            self.block_chain().backfill_address_history(ADDRESS_HISTORY_BACKFILL_CHUNK_SIZE)
+        };
+        let backfill = match backfill {
+            Some(backfill) => backfill,
+            None => return,
+        };
+        if backfill.is_finished() {
+            cinfo!(CLIENT, "The address histories of #0..=#{} are indexed", backfill.target);
+            return
+        }
+        if let Err(e) = self.io_channel.lock().send(ClientIoMessage::BackfillAddressHistory) {
+            cerror!(CLIENT, "Error while continuing the backfill of the address history: {}", e);
+        }
+    }
+
+    /// Writes the body of a block whose header is imported, without executing the block again.
+    /// It's for the bodies removed by the repair of the integrity check.
+    /// Returns false if the header is unknown or the transactions don't match its transactions root.
+    pub fn restore_block_body(&self, hash: &H256, transactions: &[UnverifiedTransaction]) -> bool {
+        let chain = self.block_chain();
+        let header = match chain.block_header(hash) {
+            Some(header) => header,
+            None => return false,
+        };
+        let parent = match chain.block_header(header.parent_hash()) {
+            Some(parent) => parent,
+            None => return false,
+        };
+        let transactions_root =
+            skewed_merkle_root(*parent.transactions_root(), transactions.iter().map(Encodable::rlp_bytes));
+        if transactions_root != *header.transactions_root() {
+            return false
+        }
+
+        let block = Block {
+            header,
+            transactions: transactions.to_vec(),
+        }
+        .rlp_bytes(&Seal::With);
+        let mut batch = DBTransaction::new();
+        chain.restore_body(&mut batch, &BlockView::new(&block));
+        self.db.write(batch).expect("DB write failed.");
+        true
+    }
+
+    /// Starts moving the scheduled transactions to the mem pool periodically.
+    /// It must be called after the client is set as the handler of the reseal timer.
+    pub fn start_scheduled_transactions_timer(&self) {
+        self.reseal_timer
+            .schedule_repeat(SCHEDULED_TRANSACTIONS_TIMER_INTERVAL, SCHEDULED_TRANSACTIONS_TIMER_TOKEN)
+            .expect("Scheduled transactions timer is scheduled only once");
+    }
 }
 
 /// When RESEAL_MAX_TIMER invoked, a block is created although the block is empty.
@@ -330,6 +821,9 @@ const RESEAL_MAX_TIMER_TOKEN: TimerToken = 0;
 /// The minimum time between blocks, the miner creates a block when RESEAL_MIN_TIMER is invoked.
 /// Do not create a block before RESEAL_MIN_TIMER event.
 const RESEAL_MIN_TIMER_TOKEN: TimerToken = 1;
+/// The scheduled transactions whose `not_before` has passed enter the mem pool when SCHEDULED_TRANSACTIONS_TIMER is invoked.
+const SCHEDULED_TRANSACTIONS_TIMER_TOKEN: TimerToken = 2;
+const SCHEDULED_TRANSACTIONS_TIMER_INTERVAL: Duration = Duration::from_secs(1);
 
 impl TimeoutHandler for Client {
     fn on_timeout(&self, token: TimerToken) {
@@ -346,6 +840,10 @@ impl TimeoutHandler for Client {
                     self.update_sealing(BlockId::Latest, false);
                 }
             }
+            SCHEDULED_TRANSACTIONS_TIMER_TOKEN => {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("There is no time machine.").as_secs();
+                self.importer.miner.promote_scheduled_transactions(self, now);
+            }
             _ => unreachable!(),
         }
     }
@@ -355,6 +853,20 @@ impl DatabaseClient for Client {
     fn database(&self) -> Arc<KeyValueDB> {
         Arc::clone(&self.db())
     }
+
+    fn database_stats(&self) -> DatabaseStats {
+        DatabaseStats {
+            columns: column_stats(&**self.db(), &self.block_chain()),
+            running_compactions: self.db().running_compactions(),
+            cache_budget_cap: MAX_CACHE_BUDGET,
+            state_cache_entries: self.state_db.read().cache_entries(),
+            state_warming: self.state_warming.status(),
+        }
+    }
+
+    fn set_cache_budget(&self, column: Option<u32>, budget: usize) -> Result<(), CacheBudgetError> {
+        self.block_chain().set_cache_budget(column, budget)
+    }
 }
 
 impl AssetClient for Client {
@@ -438,6 +950,30 @@ impl ExecuteClient for Client {
         )
     }
 
+    fn dry_run_transaction(
+        &self,
+        tx: &Transaction,
+        sender: &Address,
+        sender_public: Option<&Public>,
+        block_id: BlockId,
+        options: &DryRunOptions,
+    ) -> Option<Result<DryRunResult, Error>> {
+        let parent = self.block_header(&block_id)?.decode();
+        let common_params = self.common_params(BlockId::Hash(parent.hash()))?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("There is no time machine.").as_secs();
+        Some(dry_run(
+            &self.state_db().read(),
+            tx,
+            sender,
+            sender_public,
+            self,
+            &parent,
+            &common_params,
+            cmp::max(now, parent.timestamp() + 1),
+            options,
+        ))
+    }
+
     fn execute_vm(
         &self,
         tx: &PartialHashing,
@@ -490,19 +1026,24 @@ impl StateInfo for Client {
             TopLevelState::from_existing(self.state_db.read().clone(&root), root).ok()
         })
     }
+
+    fn state_proof(&self, id: BlockId, key: &[u8]) -> Option<Vec<Bytes>> {
+        let root = self.block_header(&id)?.state_root();
+        cmerkle::prove(self.state_db.read().as_hashdb(), &root, key).ok()
+    }
 }
 
 impl EngineInfo for Client {
     fn common_params(&self, block_id: BlockId) -> Option<CommonParams> {
-        self.state_info(block_id.into()).map(|state| {
-            state
-                .metadata()
-                .unwrap_or_else(|err| unreachable!("Unexpected failure. Maybe DB was corrupted: {:?}", err))
-                .unwrap()
-                .params()
-                .map(Clone::clone)
-                .unwrap_or_else(|| *self.engine().machine().genesis_common_params())
-        })
+        if let Some(params) = self.common_params_in_state(block_id) {
+            return Some(params)
+        }
+        // The history only knows the canonical blocks.
+        let block_number = self.block_number(&block_id)?;
+        if self.block_hash(&block_number.into())? != self.block_hash(&block_id)? {
+            return None
+        }
+        self.params_history.params_at(block_number)
     }
 
     fn metadata_seq(&self, block_id: BlockId) -> Option<u64> {
@@ -515,14 +1056,18 @@ impl EngineInfo for Client {
         })
     }
 
+    fn params_change_history(&self) -> Vec<ParamsActivation> {
+        self.params_history.activations()
+    }
+
     fn block_reward(&self, block_number: u64) -> u64 {
         self.engine().block_reward(block_number)
     }
 
     fn mining_reward(&self, block_number: u64) -> Option<u64> {
         let block = self.block(&block_number.into())?;
-        let block_fee = self.engine().block_fee(Box::new(block.transactions().into_iter()));
-        Some(self.engine().block_reward(block_number) + block_fee)
+        let block_fee = self.engine().block_fee(Box::new(block.transactions().into_iter()))?;
+        self.engine().block_reward(block_number).checked_add(block_fee)
     }
 
     fn recommended_confirmation(&self) -> u32 {
@@ -540,6 +1085,134 @@ impl EngineInfo for Client {
             addresses.into_iter().map(|address| PlatformAddress::new_v1(network_id, address)).collect()
         }))
     }
+
+    fn validator_set(&self, block_id: BlockId) -> Option<Vec<WeightedValidator>> {
+        let header = self.block_header(&block_id)?;
+        if header.number() == 0 {
+            // The genesis block is not sealed by the validators.
+            return Some(Vec::new())
+        }
+        Some(self.engine().validator_set(&header.parent_hash()))
+    }
+
+    fn force_next_view(&self) -> Result<(), EngineError> {
+        self.engine().force_next_view()
+    }
+
+    fn demote_to_standby(&self) -> Result<(), EngineError> {
+        self.engine().demote_to_standby()
+    }
+
+    fn promote_from_standby(&self, min_quiet_period: Option<Duration>) -> Result<(), EngineError> {
+        self.engine().promote_from_standby(min_quiet_period)
+    }
+
+    fn set_consensus_fault(&self, fault: ConsensusFault) -> Result<(), EngineError> {
+        self.engine().set_consensus_fault(fault)
+    }
+
+    fn consensus_status(&self) -> Option<ConsensusStatus> {
+        self.engine().consensus_status()
+    }
+
+    fn restore_status(&self) -> Option<RestoreStatus> {
+        self.engine().restore_status()
+    }
+
+    fn block_production_stats(&self, window_blocks: u64) -> BlockProductionStats {
+        let window_blocks = cmp::min(window_blocks, MAX_BLOCK_PRODUCTION_WINDOW);
+        let chain = self.block_chain();
+        // The headers are read without the bodies.
+        let headers =
+            iter::successors(Some(chain.best_block_header()), |header| chain.block_header_data(&header.parent_hash()))
+                .take(window_blocks as usize + 1)
+                .map(|header| header.decode());
+        BlockProductionStats::new(headers, self.engine())
+    }
+
+    fn total_burnt(&self, block_id: BlockId) -> Option<u64> {
+        let state = self.state_at(block_id)?;
+        Some(stake::get_total_burnt(&state).expect("Cannot get the burnt fees"))
+    }
+
+    fn block_reward_distribution(&self, block_number: u64) -> Option<BlockRewardDistribution> {
+        if block_number == 0 {
+            // The genesis block doesn't have the reward.
+            return None
+        }
+        self.engine().find_action_handler_for(stake::CUSTOM_ACTION_HANDLER_ID)?;
+        let block = self.block(&block_number.into())?;
+        let parent_common_params = self.common_params((block_number - 1).into())?;
+        let transactions = block.transactions();
+        let total_fee = transactions.iter().map(|tx| tx.fee).sum();
+        let total_min_fee =
+            transactions.iter().map(|tx| CodeChainMachine::min_cost(&parent_common_params, &tx.action)).sum();
+        // The shares are recomputed with the stakes the block distributed its fees by.
+        let stakes_block_number = if parent_common_params.distribute_fees_by_parent_stakes() {
+            block_number - 1
+        } else {
+            block_number
+        };
+        let state = self.state_at(stakes_block_number.into())?;
+        let stakes = stake::get_stakes(&state).expect("Cannot get Stake status");
+        Some(BlockRewardDistribution::new(
+            block.header_view().author(),
+            self.engine().block_reward(block_number),
+            total_fee,
+            total_min_fee,
+            parent_common_params.fee_burn_permille(),
+            &stakes,
+        ))
+    }
+
+    fn decode_seal(&self, seal: &[Bytes]) -> Option<Result<DecodedSeal, String>> {
+        self.engine().decode_seal(seal)
+    }
+
+    fn block_finality(&self, hash: &H256) -> Option<BlockFinality> {
+        let engine = self.engine();
+        let rule = FinalityRule::new(engine.engine_type(), engine.recommended_confirmation());
+        block_finality(&CanonicalChain(self), rule, hash)
+    }
+
+    fn engine_description(&self) -> EngineDescription {
+        let common_params = self.common_params(BlockId::Latest).expect("Common params of the best block must exist");
+        self.engine().describe(&common_params)
+    }
+
+    fn range_statistics(&self, from: BlockNumber, to: BlockNumber) -> Option<RangeStatistics> {
+        let mut builder = RangeStatisticsBuilder::default();
+        for block_number in from..=to {
+            let block = self.block(&block_number.into())?;
+            // The genesis block doesn't have the reward.
+            let block_reward = if block_number == 0 {
+                0
+            } else {
+                self.engine().block_reward(block_number)
+            };
+            builder.add_block(&block.transactions(), block_reward);
+        }
+        Some(builder.build())
+    }
+
+    fn signer_eligibility(&self) -> Option<SignerEligibility> {
+        let signer = self.importer.miner.authoring_params().author;
+        if signer.is_zero() {
+            return None
+        }
+        self.engine().find_action_handler_for(stake::CUSTOM_ACTION_HANDLER_ID)?;
+        let best_block_hash = self.block_chain().best_block_hash();
+        let validators: Vec<Public> =
+            self.engine().validator_set(&best_block_hash).into_iter().map(|validator| validator.pubkey).collect();
+        let state = self.state_at(best_block_hash.into())?;
+        Some(stake::signer_eligibility(&state, &signer, &validators).expect("Cannot read the stake state"))
+    }
+
+    fn candidates(&self, block_id: BlockId) -> Option<Vec<CandidateStatus>> {
+        self.engine().find_action_handler_for(stake::CUSTOM_ACTION_HANDLER_ID)?;
+        let state = self.state_at(block_id)?;
+        Some(stake::candidate_statuses(&state).expect("Cannot read the stake state"))
+    }
 }
 
 impl EngineClient for Client {
@@ -581,6 +1254,23 @@ impl EngineClient for Client {
         }
     }
 
+    fn execute_proposal(&self, block: Bytes) {
+        let hash = BlockView::new(&block).hash();
+        if !self.optimistic_executions.start(hash) {
+            return
+        }
+        ctrace!(ENGINE, "Requesting the execution of the proposal {} in advance", hash);
+        if let Err(e) = self.io_channel.lock().send(ClientIoMessage::ExecuteProposal(block)) {
+            cdebug!(CLIENT, "Error while triggering the execution of a proposal: {}", e);
+            // The importer must not wait for the execution that never runs.
+            self.optimistic_executions.finish(&hash, None);
+        }
+    }
+
+    fn discard_executed_proposals(&self, keep: Option<H256>) {
+        self.optimistic_executions.discard_except(keep.as_ref());
+    }
+
     fn get_kvdb(&self) -> Arc<KeyValueDB> {
         self.db.clone()
     }
@@ -648,6 +1338,17 @@ impl ImportBlock for Client {
         Ok(self.importer.block_queue.import(unverified)?)
     }
 
+    fn import_block_from(&self, bytes: Bytes, peer: NodeId) -> Result<H256, BlockImportError> {
+        let hash = BlockView::new(&bytes).hash();
+        self.importer.rejections.set_source(hash, peer);
+        let result = self.import_block(bytes);
+        if let Err(BlockImportError::Import(_)) = result {
+            // The block is known or already queued, so it's not blamed on the peer.
+            self.importer.rejections.clear_source(&hash);
+        }
+        result
+    }
+
     fn import_header(&self, bytes: Bytes) -> Result<H256, BlockImportError> {
         let unverified = ::encoded::Header::new(bytes).decode();
         {
@@ -671,7 +1372,7 @@ impl ImportBlock for Client {
 
             self.importer.import_headers(vec![header], self, &import_lock);
 
-            let route = self.importer.commit_block(block, header, &block_data, self);
+            let route = self.importer.commit_block(block, header, &block_data, self, &mut PhaseTimer::start());
             cinfo!(CLIENT, "Imported sealed block #{} ({})", number, h);
             route
         };
@@ -722,10 +1423,49 @@ impl BlockChainClient for Client {
 
     /// Import own transaction
     fn queue_own_transaction(&self, transaction: SignedTransaction) -> Result<(), Error> {
-        self.importer.miner.import_own_transaction(self, transaction)?;
+        self.importer.miner.import_own_transaction(self, transaction, TxOrigin::Local)?;
+        Ok(())
+    }
+
+    fn queue_own_transactions(&self, transactions: Vec<SignedTransaction>) -> Result<(), Vec<Option<Error>>> {
+        self.importer.miner.import_own_transactions(self, transactions, TxOrigin::Local)?;
+        Ok(())
+    }
+
+    fn queue_rpc_transaction(&self, transaction: SignedTransaction, source: Option<IpAddr>) -> Result<(), Error> {
+        self.importer.miner.import_own_transaction(self, transaction, TxOrigin::Rpc {
+            source,
+        })?;
+        Ok(())
+    }
+
+    fn queue_rpc_transactions(
+        &self,
+        transactions: Vec<SignedTransaction>,
+        source: Option<IpAddr>,
+    ) -> Result<(), Vec<Option<Error>>> {
+        self.importer.miner.import_own_transactions(self, transactions, TxOrigin::Rpc {
+            source,
+        })?;
         Ok(())
     }
 
+    fn schedule_rpc_transaction(
+        &self,
+        transaction: SignedTransaction,
+        source: Option<IpAddr>,
+        not_before: u64,
+    ) -> Result<(), Error> {
+        self.importer.miner.schedule_own_transaction(
+            self,
+            transaction,
+            TxOrigin::Rpc {
+                source,
+            },
+            not_before,
+        )
+    }
+
     fn queue_transactions(&self, transactions: Vec<Bytes>, peer_id: NodeId) {
         let queue_size = self.queue_transactions.load(AtomicOrdering::Relaxed);
         ctrace!(EXTERNAL_PARCEL, "Queue size: {}", queue_size);
@@ -745,13 +1485,38 @@ impl BlockChainClient for Client {
     }
 
     fn ready_transactions(&self, range: Range<u64>) -> PendingSignedTransactions {
-        self.importer.miner.ready_transactions(range)
+        let size_limit = self.common_params(BlockId::Latest).unwrap().max_body_size();
+        self.importer.miner.ready_transactions(size_limit, range)
     }
 
     fn count_pending_transactions(&self, range: Range<u64>) -> usize {
         self.importer.miner.count_pending_transactions(range)
     }
 
+    fn diagnose_signer(&self, address: &Address) -> SignerDiagnosis {
+        self.importer.miner.diagnose_signer(self, address)
+    }
+
+    fn fee_floor(&self) -> Option<FeeFloorStatus> {
+        self.importer.miner.fee_floor()
+    }
+
+    fn relay_status(&self) -> RelayStatus {
+        self.importer.miner.relay_stats().status()
+    }
+
+    fn pending_transactions_by_signer(&self, address: &Address) -> Vec<PooledTransaction> {
+        self.importer.miner.transactions_by_signer(self, address)
+    }
+
+    fn pending_queue(&self, hash: &H256) -> Option<PendingQueue> {
+        self.importer.miner.pending_queue(hash)
+    }
+
+    fn scheduled_transactions(&self) -> Vec<ScheduledTransaction> {
+        self.importer.miner.scheduled_transactions()
+    }
+
     fn is_pending_queue_empty(&self) -> bool {
         self.importer.miner.status().transactions_in_pending_queue == 0
     }
@@ -806,6 +1571,263 @@ impl BlockChainClient for Client {
         let chain = self.block_chain();
         chain.error_hints_by_tracker(tracker)
     }
+
+    fn address_history(
+        &self,
+        from: &AddressHistoryPosition,
+        to_block: BlockNumber,
+        limit: usize,
+    ) -> Option<AddressHistoryPage> {
+        self.block_chain().address_history(from, to_block, limit)
+    }
+
+    fn transactions_by_action_type(
+        &self,
+        from: &ActionIndexPosition,
+        to_block: BlockNumber,
+        limit: usize,
+    ) -> Option<ActionIndexPage> {
+        self.block_chain().transactions_by_action_type(from, to_block, limit)
+    }
+
+    fn action_index_backfill(&self) -> Option<ActionIndexBackfill> {
+        self.block_chain().action_index_backfill()
+    }
+
+    fn reorg_protection_status(&self) -> ReorgProtectionStatus {
+        self.block_chain().reorg_protection_status()
+    }
+
+    fn override_reorg_protection(&self) {
+        self.block_chain().override_reorg_protection()
+    }
+
+    fn freeze_chain(&self) {
+        self.frozen.store(true, AtomicOrdering::SeqCst);
+        // Wait for the block being imported.
+        let _import_lock = self.importer.import_lock.lock();
+        cinfo!(CLIENT, "The chain is frozen");
+    }
+
+    fn unfreeze_chain(&self) {
+        if !self.frozen.swap(false, AtomicOrdering::SeqCst) {
+            return
+        }
+        cinfo!(CLIENT, "The chain is unfrozen");
+        if self.consensus_halt.is_halted() {
+            cwarn!(CLIENT, "The import is still halted by the finality conflict");
+            return
+        }
+        self.resume_import();
+    }
+
+    fn is_chain_frozen(&self) -> bool {
+        self.frozen.load(AtomicOrdering::SeqCst) || self.consensus_halt.is_halted()
+    }
+
+    fn finality_conflict(&self) -> Option<FinalityConflict> {
+        self.consensus_halt.conflict()
+    }
+
+    fn clear_consensus_halt(&self) -> Option<FinalityConflict> {
+        let conflict = self.consensus_halt.clear(self.db.as_ref())?;
+        match &conflict {
+            Some(conflict) => cwarn!(
+                CLIENT,
+                "The halt by the conflicting blocks #{} is cleared by the operator",
+                conflict.block_number
+            ),
+            None => cwarn!(CLIENT, "The halt by the lost proof is cleared by the operator"),
+        }
+        if !self.frozen.load(AtomicOrdering::SeqCst) {
+            self.resume_import();
+        }
+        conflict
+    }
+
+    fn state_warming_status(&self) -> StateWarmingStatus {
+        self.state_warming.status()
+    }
+
+    fn import_timings(&self, limit: usize) -> Vec<BlockImportTimings> {
+        self.importer.import_timings(limit)
+    }
+
+    fn recent_rejections(&self, limit: usize) -> Vec<BlockRejection> {
+        self.importer.rejections.recent(limit)
+    }
+
+    fn skipped_seal_verification(&self, hash: &H256) -> Option<SkippedSealVerification> {
+        self.block_chain().skipped_seal_verification(hash)
+    }
+
+    fn reverify_block_seal(&self, hash: &H256) -> Option<Result<(), Error>> {
+        let header = self.block_chain().block_header(hash)?;
+        let result = self.engine().verify_block_external(&header);
+        if result.is_ok() {
+            let chain = self.block_chain();
+            if chain.skipped_seal_verification(hash).is_some() {
+                let mut batch = DBTransaction::new();
+                chain.remove_skipped_seal_verification(&mut batch, hash);
+                self.db().write(batch).expect("DB write failed.");
+            }
+        }
+        Some(result)
+    }
+
+    fn replay_blocks(
+        &self,
+        from: BlockNumber,
+        to: BlockNumber,
+        options: &ReplayOptions,
+    ) -> Option<Result<ReplayReport, Error>> {
+        let mut replayed_blocks = 0;
+        for number in from..=to {
+            let block = self.block(&BlockId::Number(number))?;
+            let header = block.decode_header();
+            let parent = self.block_header(&BlockId::Hash(*header.parent_hash()))?.decode();
+            let transactions: Result<Vec<_>, Error> = block
+                .transactions()
+                .into_iter()
+                .map(|tx| SignedTransaction::try_new(tx).map_err(Error::from))
+                .collect();
+            let transactions = match transactions {
+                Ok(transactions) => transactions,
+                Err(err) => return Some(Err(err)),
+            };
+            // The state is committed only to this clone, which is dropped without being journaled.
+            let db = self.state_db().read().clone(&parent.state_root());
+            let replayed = match replay(
+                &header,
+                &transactions,
+                self.engine(),
+                self,
+                db,
+                &parent,
+                &options.disabled_action_handlers,
+            ) {
+                Ok(replayed) => replayed,
+                Err(err) => return Some(Err(err)),
+            };
+            replayed_blocks += 1;
+
+            let transactions = transactions
+                .iter()
+                .map(|tx| {
+                    let hash = tx.hash();
+                    // Read the database directly to bypass the cache of the invoices.
+                    let expected_error =
+                        self.db().read::<Option<String>, _>(::db::COL_ERROR_HINT, &hash).unwrap_or(None);
+                    let actual_error = replayed
+                        .invoices()
+                        .iter()
+                        .find(|invoice| invoice.hash == hash)
+                        .and_then(|invoice| invoice.error.clone());
+                    ReplayedTransaction {
+                        hash,
+                        expected_error,
+                        actual_error,
+                    }
+                })
+                .collect();
+            if let Some(divergence) = BlockDivergence::check(
+                number,
+                header.hash(),
+                *header.state_root(),
+                *replayed.header().state_root(),
+                transactions,
+            ) {
+                cwarn!(CLIENT, "The replay of block #{} diverges: {:?}", number, divergence);
+                return Some(Ok(ReplayReport {
+                    replayed_blocks,
+                    divergence: Some(divergence),
+                }))
+            }
+        }
+        Some(Ok(ReplayReport {
+            replayed_blocks,
+            divergence: None,
+        }))
+    }
+
+    fn trace_transaction(&self, target: &TraceTarget) -> Option<Result<ActionHandlerTrace, Error>> {
+        match target {
+            TraceTarget::Hash(hash) => {
+                let address = self.transaction_address(&TransactionId::Hash(*hash))?;
+                let block = self.block(&BlockId::Hash(address.block_hash))?;
+                let header = block.decode_header();
+                let parent = self.block_header(&BlockId::Hash(*header.parent_hash()))?.decode();
+                let transactions: Result<Vec<_>, Error> = block
+                    .transactions()
+                    .into_iter()
+                    .take(address.index + 1)
+                    .map(|tx| SignedTransaction::try_new(tx).map_err(Error::from))
+                    .collect();
+                let mut transactions = match transactions {
+                    Ok(transactions) => transactions,
+                    Err(err) => return Some(Err(err)),
+                };
+                let tx = transactions.pop()?;
+                // The state is never committed, so the changes are dropped with it.
+                let root = *parent.state_root();
+                let mut state = match TopLevelState::from_existing(self.state_db().read().clone(&root), root) {
+                    Ok(state) => state,
+                    Err(err) => return Some(Err(Error::from(err))),
+                };
+                for preceding in &transactions {
+                    // The failed transactions are reverted in the same way as the import.
+                    let _ = state.apply(
+                        preceding,
+                        &preceding.hash(),
+                        &preceding.signer_public(),
+                        self,
+                        parent.number(),
+                        parent.timestamp(),
+                        header.timestamp(),
+                    );
+                }
+                Some(trace_custom_action(
+                    &mut state,
+                    &tx,
+                    self,
+                    parent.number(),
+                    parent.timestamp(),
+                    header.timestamp(),
+                ))
+            }
+            TraceTarget::Transaction(tx) => {
+                let best_header = self.best_block_header();
+                let mut state = self.state_at(BlockId::Hash(best_header.hash()))?;
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("There is no time machine.").as_secs();
+                Some(trace_custom_action(
+                    &mut state,
+                    tx,
+                    self,
+                    best_header.number(),
+                    best_header.timestamp(),
+                    ::std::cmp::max(now, best_header.timestamp() + 1),
+                ))
+            }
+        }
+    }
+
+    fn start_integrity_check(&self, from: BlockNumber, to: BlockNumber, repair: Option<RepairOptions>) -> bool {
+        let mut integrity_check = self.integrity_check.lock();
+        if integrity_check.as_ref().map_or(false, |check| !check.is_finished()) {
+            return false
+        }
+        // The blocks after the best block may not have their bodies yet.
+        let to = cmp::min(to, self.block_chain().best_block_detail().number);
+        *integrity_check = Some(IntegrityCheck::new(from, to, repair));
+        if let Err(e) = self.io_channel.lock().send(ClientIoMessage::CheckDatabaseIntegrity) {
+            cerror!(CLIENT, "Error while starting the integrity check: {}", e);
+        }
+        true
+    }
+
+    fn integrity_report(&self) -> Option<IntegrityReport> {
+        self.integrity_check.lock().as_ref().map(|check| check.report().clone())
+    }
 }
 
 impl TermInfo for Client {
@@ -820,6 +1842,101 @@ impl TermInfo for Client {
             .map(|state| state.metadata().unwrap().expect("Metadata always exist"))
             .map(|metadata| metadata.current_term_id())
     }
+
+    fn term_details(&self, block_number: BlockNumber) -> Option<TermDetails> {
+        let best_block_number = self.chain_info().best_block_number;
+        let (id, first_block_number, last_block_number) =
+            self.term_index.boundaries(block_number, best_block_number, |number| {
+                let metadata = self.state_at(number.into())?.metadata().unwrap().expect("Metadata always exist");
+                Some((metadata.last_term_finished_block_num(), metadata.current_term_id()))
+            })?;
+
+        // The engines use the parameters and the timestamp of the block right before the term.
+        let previous_block_number = first_block_number.saturating_sub(1);
+        let term_seconds = self.common_params(previous_block_number.into())?.term_seconds();
+        let previous_timestamp = self.block_header(&previous_block_number.into())?.timestamp();
+        Some(TermDetails {
+            id,
+            first_block_number,
+            last_block_number,
+            term_seconds,
+            timestamp_window: timestamp_window(term_seconds, previous_timestamp),
+        })
+    }
+}
+
+/// The canonical chain that the history of the common params is built from.
+struct CanonicalChain<'a>(&'a Client);
+
+impl<'a> ParamsHistorySource for CanonicalChain<'a> {
+    fn best_block_number(&self) -> BlockNumber {
+        self.0.chain_info().best_block_number
+    }
+
+    fn canonical_hash(&self, block_number: BlockNumber) -> Option<H256> {
+        self.0.block_hash(&block_number.into())
+    }
+
+    fn metadata_seq_at(&self, block_number: BlockNumber) -> Option<u64> {
+        self.0.metadata_seq(block_number.into())
+    }
+
+    fn common_params_at(&self, block_number: BlockNumber) -> Option<CommonParams> {
+        self.0.common_params_in_state(block_number.into())
+    }
+
+    fn change_params_transaction(&self, block_number: BlockNumber, metadata_seq: u64) -> Option<H256> {
+        let block = self.0.block(&block_number.into())?;
+        block
+            .transactions()
+            .into_iter()
+            .rev()
+            .filter(|tx| match &tx.action {
+                Action::Custom {
+                    handler_id,
+                    bytes,
+                } => {
+                    *handler_id == stake::CUSTOM_ACTION_HANDLER_ID
+                        && stake::change_params_metadata_seq(bytes) == Some(metadata_seq)
+                }
+                _ => false,
+            })
+            .map(|tx| tx.hash())
+            .find(|hash| self.0.error_hint(hash).is_none())
+    }
+}
+
+impl<'a> FinalitySource for CanonicalChain<'a> {
+    fn best_block_number(&self) -> BlockNumber {
+        self.0.chain_info().best_block_number
+    }
+
+    fn block_number(&self, hash: &H256) -> Option<BlockNumber> {
+        self.0.block_number(&BlockId::Hash(*hash))
+    }
+
+    fn canonical_hash(&self, block_number: BlockNumber) -> Option<H256> {
+        self.0.block_hash(&block_number.into())
+    }
+
+    fn decode_seal(&self, hash: &H256) -> Option<Result<DecodedSeal, String>> {
+        let header = self.0.block_header(&BlockId::Hash(*hash))?;
+        self.0.engine().decode_seal(&header.seal())
+    }
+}
+
+impl<'a> ConflictSource for CanonicalChain<'a> {
+    fn canonical_hash(&self, block_number: BlockNumber) -> Option<H256> {
+        self.0.block_hash(&block_number.into())
+    }
+
+    fn block_header(&self, hash: &H256) -> Option<Header> {
+        self.0.block_header(&BlockId::Hash(*hash)).map(|header| header.decode())
+    }
+
+    fn validators(&self, parent: &H256) -> Vec<Public> {
+        self.0.engine().validator_set(parent).into_iter().map(|validator| validator.pubkey).collect()
+    }
 }
 
 impl AccountData for Client {
@@ -890,6 +2007,11 @@ impl BlockProducer for Client {
             extra_data,
         ).expect("OpenBlock::new only fails if parent state root invalid; state root of best block's header is never invalid; qed")
     }
+
+    fn cache_closed_block(&self, parent_state_root: H256, block: &ClosedBlock) {
+        let header = block.header();
+        self.cache_execution(parent_state_root, header.hash(), header.number(), block.clone().lock());
+    }
 }
 
 impl MiningBlockChainClient for Client {}
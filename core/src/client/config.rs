@@ -16,6 +16,7 @@
 
 use std::path::Path;
 use std::str::FromStr;
+use std::time::Duration;
 
 use kvdb_rocksdb::CompactionProfile;
 
@@ -77,12 +78,30 @@ pub struct ClientConfig {
     pub state_cache_size: usize,
     /// Type of block verifier used by client.
     pub verifier_type: VerifierType,
+    /// The maximum number of canonical blocks a reorg can retract. The engine decides it if not given.
+    pub max_reorg_depth: Option<u64>,
+    /// Verify the seals of only every this many blocks while importing the blocks far below the tip.
+    /// 0 means verifying every seal.
+    pub seal_checkpoint_interval: u64,
+    /// Keep the index from the addresses to their transactions.
+    pub address_history: bool,
+    /// Keep the index from the action types to their transactions.
+    /// The blocks imported before it's enabled are indexed in the background.
+    pub action_index: bool,
+    /// Load at most this many of the state items that the latest blocks touched into the cache after the start.
+    /// 0 disables the warming.
+    pub state_warming_max_entries: usize,
+    /// Stop warming the state cache after this.
+    pub state_warming_timeout: Duration,
 }
 
 impl Default for ClientConfig {
     fn default() -> Self {
         let mb = 1024 * 1024;
         const DEFAULT_STATE_CACHE_SIZE: u32 = 25;
+        const DEFAULT_SEAL_CHECKPOINT_INTERVAL: u64 = 100;
+        const DEFAULT_STATE_WARMING_MAX_ENTRIES: usize = 400;
+        const DEFAULT_STATE_WARMING_TIMEOUT_MS: u64 = 5000;
         Self {
             queue: Default::default(),
             db_cache_size: Default::default(),
@@ -90,6 +109,12 @@ impl Default for ClientConfig {
             db_wal: true,
             state_cache_size: DEFAULT_STATE_CACHE_SIZE as usize * mb,
             verifier_type: Default::default(),
+            max_reorg_depth: None,
+            seal_checkpoint_interval: DEFAULT_SEAL_CHECKPOINT_INTERVAL,
+            address_history: false,
+            action_index: false,
+            state_warming_max_entries: DEFAULT_STATE_WARMING_MAX_ENTRIES,
+            state_warming_timeout: Duration::from_millis(DEFAULT_STATE_WARMING_TIMEOUT_MS),
         }
     }
 }
@@ -0,0 +1,122 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use kvdb::KeyValueDB;
+
+use super::StateWarmingStatus;
+use crate::blockchain::{BlockChain, CacheStats};
+use crate::db::COLUMN_NAMES;
+
+/// The statistics of a database column and the cache in front of it.
+/// The numbers of the column are the estimates of the backend, and None if the backend doesn't estimate them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnStats {
+    pub name: &'static str,
+    /// The estimated number of the keys
+    pub keys: Option<u64>,
+    /// The approximate size of the column, in bytes
+    pub size: Option<u64>,
+    /// The estimated number of the bytes that the compactions have to rewrite
+    pub pending_compaction_bytes: Option<u64>,
+    pub cache: Option<CacheStats>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DatabaseStats {
+    pub columns: Vec<ColumnStats>,
+    /// The number of the running compactions
+    pub running_compactions: Option<u64>,
+    /// The cap of the sum of the cache budgets
+    pub cache_budget_cap: usize,
+    /// The number of the items in the state cache
+    pub state_cache_entries: usize,
+    pub state_warming: StateWarmingStatus,
+}
+
+/// Reads the estimates that the database keeps, so it doesn't walk the columns.
+pub fn column_stats(db: &KeyValueDB, chain: &BlockChain) -> Vec<ColumnStats> {
+    COLUMN_NAMES
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            let col = Some(index as u32);
+            let estimates = db.column_estimates(col).unwrap_or_default();
+            ColumnStats {
+                name,
+                keys: estimates.keys,
+                size: estimates.size,
+                pending_compaction_bytes: estimates.pending_compaction_bytes,
+                cache: chain.cache_stats(col),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ctypes::Header;
+    use kvdb::DBTransaction;
+    use primitives::U256;
+
+    use super::*;
+    use crate::blockchain::HeaderProvider;
+    use crate::db;
+    use crate::scheme::Scheme;
+    use crate::tests::helpers::create_test_block;
+
+    #[test]
+    fn stats_of_a_populated_database() {
+        let scheme = Scheme::new_test();
+        let db: Arc<KeyValueDB> = Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap()));
+        let chain = BlockChain::new(&scheme.genesis_block(), Arc::clone(&db), 100, false, false);
+
+        let mut parent = chain.genesis_header();
+        for _ in 0..5 {
+            let mut header = Header::new();
+            header.set_parent_hash(parent.hash());
+            header.set_number(parent.number() + 1);
+            header.set_score(U256::from(10));
+            let mut batch = DBTransaction::new();
+            chain.insert_block(&mut batch, &create_test_block(&header), vec![], &*scheme.engine);
+            db.write(batch).unwrap();
+            chain.commit();
+            parent = header;
+        }
+        chain.block_header_data(&parent.hash()).unwrap();
+
+        let stats = column_stats(&*db, &chain);
+        let names: Vec<_> = stats.iter().map(|column| column.name).collect();
+        assert_eq!(COLUMN_NAMES.to_vec(), names);
+
+        let headers = &stats[db::COL_HEADERS.unwrap() as usize];
+        assert_eq!(Some(6), headers.keys);
+        assert_ne!(0, headers.size.unwrap());
+        assert_eq!(None, headers.pending_compaction_bytes);
+        let header_cache = headers.cache.unwrap();
+        assert_ne!(0, header_cache.entries);
+        assert_ne!(0, header_cache.size);
+
+        let bodies = &stats[db::COL_BODIES.unwrap() as usize];
+        assert_eq!(Some(6), bodies.keys);
+        assert!(bodies.cache.is_some());
+
+        let state = &stats[db::COL_STATE.unwrap() as usize];
+        assert_eq!(None, state.cache);
+        assert_eq!(Some(0), stats[db::COL_MEMPOOL.unwrap() as usize].keys);
+    }
+}
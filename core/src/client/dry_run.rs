@@ -0,0 +1,184 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ckey::{Address, Public};
+use cstate::{FindActionHandler, HotKey, StateDB, StateResult, TopLevelState, TopStateView};
+use ctypes::errors::SyntaxError;
+use ctypes::transaction::Transaction;
+use ctypes::{CommonParams, Header};
+use cvm::ChainTimeInfo;
+use primitives::{Bytes, H256};
+
+use crate::codechain_machine::CodeChainMachine;
+use crate::error::Error;
+use crate::invoice::Invoice;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DryRunOptions {
+    /// The transaction pays no fee, and the minimum fee is not checked.
+    pub ignore_fee: bool,
+    /// The current seq of the fee payer is used instead of the seq of the transaction.
+    pub ignore_seq: bool,
+}
+
+/// An account whose balance or seq is changed by the transaction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountChange {
+    pub address: Address,
+    pub old_balance: u64,
+    pub new_balance: u64,
+    pub old_seq: u64,
+    pub new_seq: u64,
+}
+
+/// An action data, e.g. the stake state, changed by the transaction. `new` is None if the data is removed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActionDataChange {
+    pub key: H256,
+    pub old: Option<Bytes>,
+    pub new: Option<Bytes>,
+}
+
+/// What would happen if the transaction were included in the block right after the given one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DryRunResult {
+    pub invoice: Invoice,
+    /// The changes in the order that the transaction touched them.
+    pub account_changes: Vec<AccountChange>,
+    pub action_data_changes: Vec<ActionDataChange>,
+}
+
+/// Executes the transaction on a scratch state built on the state root of `parent`.
+/// The state is never committed, so the changes are dropped with it.
+///
+/// The fee payer is resolved from `sender_public` if it is given. Otherwise `sender` pays the fee,
+/// and the actions that use the public key of the signer must not be executed.
+pub fn dry_run<C: ChainTimeInfo + FindActionHandler>(
+    db: &StateDB,
+    tx: &Transaction,
+    sender: &Address,
+    sender_public: Option<&Public>,
+    client: &C,
+    parent: &Header,
+    common_params: &CommonParams,
+    current_block_timestamp: u64,
+    options: &DryRunOptions,
+) -> Result<DryRunResult, Error> {
+    let root = *parent.state_root();
+    let mut state = TopLevelState::from_existing(db.clone(&root), root)?;
+    // The values before the execution are read from another state, because the scratch state caches the changes.
+    let original = TopLevelState::from_existing(db.clone(&root), root)?;
+
+    let fee_payer = match sender_public {
+        Some(public) => state.public_to_owner_address(public)?,
+        None => *sender,
+    };
+
+    let mut tx = tx.clone();
+    if options.ignore_seq {
+        tx.seq = state.seq(&fee_payer)?;
+    }
+    let hash = tx.hash();
+    if options.ignore_fee {
+        tx.fee = 0;
+    } else {
+        let min_cost = CodeChainMachine::min_cost(common_params, &tx.action);
+        if tx.fee < min_cost {
+            return Ok(DryRunResult {
+                invoice: Invoice {
+                    tracker: tx.tracker(),
+                    hash,
+                    error: Some(
+                        SyntaxError::InsufficientFee {
+                            minimal: min_cost,
+                            got: tx.fee,
+                        }
+                        .to_string(),
+                    ),
+                },
+                account_changes: Vec::new(),
+                action_data_changes: Vec::new(),
+            })
+        }
+    }
+
+    let result = match sender_public {
+        Some(public) => {
+            state.apply(&tx, &hash, public, client, parent.number(), parent.timestamp(), current_block_timestamp)
+        }
+        None => state.apply_as(
+            &tx,
+            &hash,
+            &fee_payer,
+            &Public::default(),
+            client,
+            parent.number(),
+            parent.timestamp(),
+            current_block_timestamp,
+        ),
+    };
+
+    let mut account_changes = Vec::new();
+    let mut action_data_changes = Vec::new();
+    for key in state.touched_keys() {
+        match key {
+            HotKey::Account(address) => {
+                if let Some(change) = account_change(&original, &state, address)? {
+                    account_changes.push(change);
+                }
+            }
+            HotKey::ActionData(key) => {
+                let old = original.action_data(&key)?.map(Into::into);
+                let new = state.action_data(&key)?.map(Into::into);
+                if old != new {
+                    action_data_changes.push(ActionDataChange {
+                        key,
+                        old,
+                        new,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(DryRunResult {
+        invoice: Invoice {
+            tracker: tx.tracker(),
+            hash,
+            error: result.err().map(|err| err.to_string()),
+        },
+        account_changes,
+        action_data_changes,
+    })
+}
+
+fn account_change(
+    original: &TopLevelState,
+    state: &TopLevelState,
+    address: Address,
+) -> StateResult<Option<AccountChange>> {
+    let change = AccountChange {
+        address,
+        old_balance: original.balance(&address)?,
+        new_balance: state.balance(&address)?,
+        old_seq: original.seq(&address)?,
+        new_seq: state.seq(&address)?,
+    };
+    if change.old_balance == change.new_balance && change.old_seq == change.new_seq {
+        return Ok(None)
+    }
+    Ok(Some(change))
+}
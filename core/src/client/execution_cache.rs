@@ -0,0 +1,186 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::VecDeque;
+
+use ctypes::BlockNumber;
+use parking_lot::Mutex;
+use primitives::H256;
+
+use crate::block::LockedBlock;
+
+/// The number of the blocks closed by the miner whose executions are kept until their import.
+pub const MAX_CACHED_EXECUTIONS: usize = 4;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ExecutionCacheStats {
+    /// The number of the executions kept by the miner.
+    pub cached: usize,
+    /// The number of the imports that used the cached executions instead of executing the blocks again.
+    pub reused: usize,
+    /// The number of the cached executions that didn't match the imported blocks, which were executed again.
+    pub rejected: usize,
+    /// The number of the cached executions dropped without being reused.
+    pub evicted: usize,
+}
+
+struct CachedExecution<B> {
+    parent_state_root: H256,
+    hash: H256,
+    number: BlockNumber,
+    block: B,
+}
+
+struct CachedExecutions<B> {
+    executions: VecDeque<CachedExecution<B>>,
+    stats: ExecutionCacheStats,
+}
+
+impl<B> CachedExecutions<B> {
+    fn position(&self, parent_state_root: &H256, hash: &H256) -> Option<usize> {
+        self.executions
+            .iter()
+            .position(|execution| execution.parent_state_root == *parent_state_root && execution.hash == *hash)
+    }
+}
+
+/// The executions of the blocks closed by the miner, which the import of the same blocks reuses.
+///
+/// An execution is keyed by the state root of the parent and the block hash, so that it's reused only for the block
+/// executed against the same state.
+pub struct ExecutionCache<B = LockedBlock> {
+    inner: Mutex<CachedExecutions<B>>,
+}
+
+impl<B> Default for ExecutionCache<B> {
+    fn default() -> Self {
+        Self {
+            inner: Mutex::new(CachedExecutions {
+                executions: VecDeque::with_capacity(MAX_CACHED_EXECUTIONS),
+                stats: Default::default(),
+            }),
+        }
+    }
+}
+
+impl<B> ExecutionCache<B> {
+    /// Keeps the execution of the block. The oldest one is dropped if there are too many.
+    pub fn insert(&self, parent_state_root: H256, hash: H256, number: BlockNumber, block: B) {
+        let mut inner = self.inner.lock();
+        if let Some(index) = inner.position(&parent_state_root, &hash) {
+            inner.executions.remove(index);
+            inner.stats.evicted += 1;
+        } else if inner.executions.len() == MAX_CACHED_EXECUTIONS {
+            inner.executions.pop_front();
+            inner.stats.evicted += 1;
+        }
+        inner.executions.push_back(CachedExecution {
+            parent_state_root,
+            hash,
+            number,
+            block,
+        });
+        inner.stats.cached += 1;
+    }
+
+    /// Takes the execution of the block. Returns None if the block is not cached or the execution is not valid.
+    pub fn take<F: FnOnce(&B) -> bool>(&self, parent_state_root: &H256, hash: &H256, is_valid: F) -> Option<B> {
+        let mut inner = self.inner.lock();
+        let index = inner.position(parent_state_root, hash)?;
+        let execution = inner.executions.remove(index).expect("The index is found above");
+        if !is_valid(&execution.block) {
+            inner.stats.rejected += 1;
+            return None
+        }
+        inner.stats.reused += 1;
+        Some(execution.block)
+    }
+
+    /// Drops the executions of the blocks that cannot be imported on top of the best block anymore.
+    pub fn evict_up_to(&self, best_block_number: BlockNumber) {
+        let mut inner = self.inner.lock();
+        let before = inner.executions.len();
+        inner.executions.retain(|execution| execution.number > best_block_number);
+        inner.stats.evicted += before - inner.executions.len();
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().executions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.lock().executions.is_empty()
+    }
+
+    pub fn stats(&self) -> ExecutionCacheStats {
+        self.inner.lock().stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn execution_is_reused_only_for_the_same_parent_state() {
+        let cache = ExecutionCache::<u32>::default();
+        cache.insert(H256::from(1), H256::from(10), 1, 10);
+        assert_eq!(None, cache.take(&H256::from(2), &H256::from(10), |_| true));
+        assert_eq!(Some(10), cache.take(&H256::from(1), &H256::from(10), |_| true));
+        assert!(cache.is_empty());
+        assert_eq!(
+            ExecutionCacheStats {
+                cached: 1,
+                reused: 1,
+                rejected: 0,
+                evicted: 0,
+            },
+            cache.stats()
+        );
+    }
+
+    #[test]
+    fn the_oldest_execution_is_dropped() {
+        let cache = ExecutionCache::<u32>::default();
+        for i in 0..=MAX_CACHED_EXECUTIONS {
+            cache.insert(H256::from(1), H256::from(i as u64), 1, i as u32);
+        }
+        assert_eq!(MAX_CACHED_EXECUTIONS, cache.len());
+        assert_eq!(None, cache.take(&H256::from(1), &H256::from(0), |_| true));
+        assert_eq!(Some(1), cache.take(&H256::from(1), &H256::from(1), |_| true));
+        assert_eq!(1, cache.stats().evicted);
+    }
+
+    #[test]
+    fn invalid_execution_is_dropped() {
+        let cache = ExecutionCache::<u32>::default();
+        cache.insert(H256::from(1), H256::from(10), 1, 10);
+        assert_eq!(None, cache.take(&H256::from(1), &H256::from(10), |execution| *execution == 11));
+        assert!(cache.is_empty());
+        assert_eq!(1, cache.stats().rejected);
+    }
+
+    #[test]
+    fn executions_are_evicted_when_the_height_advances() {
+        let cache = ExecutionCache::<u32>::default();
+        cache.insert(H256::from(1), H256::from(10), 1, 10);
+        cache.insert(H256::from(2), H256::from(20), 2, 20);
+        cache.evict_up_to(1);
+        assert_eq!(1, cache.len());
+        assert_eq!(Some(20), cache.take(&H256::from(2), &H256::from(20), |_| true));
+        assert_eq!(1, cache.stats().evicted);
+    }
+}
@@ -0,0 +1,274 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use ctypes::BlockNumber;
+use primitives::H256;
+
+use crate::consensus::{DecodedSeal, EngineType, View};
+
+/// Whether a block can be reverted, and the evidence that it cannot.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockFinality {
+    pub finalized: bool,
+    /// False if the block is on a branch that is not the canonical chain.
+    pub canonical: bool,
+    /// None for the blocks that are not on the canonical chain, and for the Tendermint blocks without children.
+    pub evidence: Option<FinalityEvidence>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FinalityEvidence {
+    /// The seal of the child block has the precommits that finalized the block.
+    Precommits {
+        child_hash: H256,
+        view: View,
+        signature_count: usize,
+    },
+    /// The engine cannot finalize blocks, so the number of the canonical blocks from the block to the best block
+    /// is compared with the recommended confirmation.
+    Confirmations {
+        confirmations: u64,
+        recommended: u32,
+    },
+}
+
+/// How the engine decides whether a block is final.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FinalityRule {
+    /// The precommits in the child block finalize the block.
+    Precommits,
+    /// The block is final after the given number of confirmations.
+    Confirmations(u32),
+}
+
+impl FinalityRule {
+    pub fn new(engine_type: EngineType, recommended_confirmation: u32) -> Self {
+        match engine_type {
+            EngineType::PBFT => FinalityRule::Precommits,
+            EngineType::PoA | EngineType::PoW | EngineType::Solo => {
+                FinalityRule::Confirmations(recommended_confirmation)
+            }
+        }
+    }
+}
+
+pub trait FinalitySource {
+    fn best_block_number(&self) -> BlockNumber;
+    /// Returns the number of the block whether it's canonical or not.
+    fn block_number(&self, hash: &H256) -> Option<BlockNumber>;
+    /// Returns the hash of the canonical block.
+    fn canonical_hash(&self, block_number: BlockNumber) -> Option<H256>;
+    fn decode_seal(&self, hash: &H256) -> Option<Result<DecodedSeal, String>>;
+}
+
+/// Returns None if the block is unknown.
+pub fn block_finality<S: FinalitySource>(source: &S, rule: FinalityRule, hash: &H256) -> Option<BlockFinality> {
+    let block_number = source.block_number(hash)?;
+    if source.canonical_hash(block_number).as_ref() != Some(hash) {
+        return Some(BlockFinality {
+            finalized: false,
+            canonical: false,
+            evidence: None,
+        })
+    }
+    let best_block_number = source.best_block_number();
+    let evidence = match rule {
+        FinalityRule::Precommits => precommits_of_child(source, block_number, best_block_number),
+        FinalityRule::Confirmations(recommended) => Some(FinalityEvidence::Confirmations {
+            confirmations: best_block_number.saturating_sub(block_number) + 1,
+            recommended,
+        }),
+    };
+    let finalized = match &evidence {
+        Some(FinalityEvidence::Precommits {
+            ..
+        }) => true,
+        Some(FinalityEvidence::Confirmations {
+            confirmations,
+            recommended,
+        }) => *confirmations >= u64::from(*recommended),
+        None => false,
+    };
+    Some(BlockFinality {
+        finalized,
+        canonical: true,
+        evidence,
+    })
+}
+
+fn precommits_of_child<S: FinalitySource>(
+    source: &S,
+    block_number: BlockNumber,
+    best_block_number: BlockNumber,
+) -> Option<FinalityEvidence> {
+    if block_number >= best_block_number {
+        return None
+    }
+    let child_hash = source.canonical_hash(block_number + 1)?;
+    match source.decode_seal(&child_hash)? {
+        Ok(DecodedSeal::Tendermint {
+            finalized_view,
+            signature_count,
+            ..
+        }) => Some(FinalityEvidence::Precommits {
+            child_hash,
+            view: finalized_view,
+            signature_count,
+        }),
+        Ok(DecodedSeal::Empty) => None,
+        Err(err) => {
+            cwarn!(CLIENT, "Cannot decode the seal of {}: {}", child_hash, err);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// The blocks with their numbers and the canonical chain.
+    struct Chain {
+        numbers: HashMap<H256, BlockNumber>,
+        canonical: Vec<H256>,
+        seals: HashMap<H256, DecodedSeal>,
+    }
+
+    impl Chain {
+        /// The canonical chain of the given length, whose blocks are finalized in the view of their numbers.
+        fn new(length: usize) -> Self {
+            let mut chain = Self {
+                numbers: HashMap::new(),
+                canonical: Vec::new(),
+                seals: HashMap::new(),
+            };
+            for number in 0..length as BlockNumber {
+                let hash = chain.add_block(number);
+                chain.canonical.push(hash);
+            }
+            chain
+        }
+
+        fn add_block(&mut self, number: BlockNumber) -> H256 {
+            let hash = H256::random();
+            self.numbers.insert(hash, number);
+            let seal = if number == 0 {
+                DecodedSeal::Empty
+            } else {
+                DecodedSeal::Tendermint {
+                    finalized_view: number - 1,
+                    author_view: 0,
+                    signer_indices: vec![0, 1, 2],
+                    signature_count: 3,
+                }
+            };
+            self.seals.insert(hash, seal);
+            hash
+        }
+    }
+
+    impl FinalitySource for Chain {
+        fn best_block_number(&self) -> BlockNumber {
+            self.canonical.len() as BlockNumber - 1
+        }
+
+        fn block_number(&self, hash: &H256) -> Option<BlockNumber> {
+            self.numbers.get(hash).cloned()
+        }
+
+        fn canonical_hash(&self, block_number: BlockNumber) -> Option<H256> {
+            self.canonical.get(block_number as usize).cloned()
+        }
+
+        fn decode_seal(&self, hash: &H256) -> Option<Result<DecodedSeal, String>> {
+            self.seals.get(hash).cloned().map(Ok)
+        }
+    }
+
+    #[test]
+    fn interior_block_is_finalized_by_the_child() {
+        let chain = Chain::new(5);
+        let finality = block_finality(&chain, FinalityRule::Precommits, &chain.canonical[2]).unwrap();
+        assert_eq!(
+            BlockFinality {
+                finalized: true,
+                canonical: true,
+                evidence: Some(FinalityEvidence::Precommits {
+                    child_hash: chain.canonical[3],
+                    view: 2,
+                    signature_count: 3,
+                }),
+            },
+            finality
+        );
+    }
+
+    #[test]
+    fn tip_waits_for_the_child() {
+        let chain = Chain::new(5);
+        let finality = block_finality(&chain, FinalityRule::Precommits, &chain.canonical[4]).unwrap();
+        assert_eq!(
+            BlockFinality {
+                finalized: false,
+                canonical: true,
+                evidence: None,
+            },
+            finality
+        );
+    }
+
+    #[test]
+    fn fork_block_is_not_finalized() {
+        let mut chain = Chain::new(5);
+        let fork = chain.add_block(3);
+        let expected = BlockFinality {
+            finalized: false,
+            canonical: false,
+            evidence: None,
+        };
+        assert_eq!(Some(expected.clone()), block_finality(&chain, FinalityRule::Precommits, &fork));
+        assert_eq!(Some(expected), block_finality(&chain, FinalityRule::Confirmations(1), &fork));
+    }
+
+    #[test]
+    fn unknown_block() {
+        let chain = Chain::new(5);
+        assert_eq!(None, block_finality(&chain, FinalityRule::Precommits, &H256::random()));
+    }
+
+    #[test]
+    fn confirmations() {
+        let chain = Chain::new(5);
+        let tip = block_finality(&chain, FinalityRule::Confirmations(1), &chain.canonical[4]).unwrap();
+        assert!(tip.finalized);
+        assert_eq!(
+            Some(FinalityEvidence::Confirmations {
+                confirmations: 1,
+                recommended: 1,
+            }),
+            tip.evidence
+        );
+
+        let interior = block_finality(&chain, FinalityRule::Confirmations(3), &chain.canonical[3]).unwrap();
+        assert!(!interior.finalized);
+        assert!(interior.canonical);
+        let interior = block_finality(&chain, FinalityRule::Confirmations(3), &chain.canonical[2]).unwrap();
+        assert!(interior.finalized);
+    }
+}
@@ -0,0 +1,328 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ckey::Public;
+use ctypes::{BlockNumber, Header};
+use kvdb::{DBTransaction, KeyValueDB};
+use parking_lot::Mutex;
+use primitives::H256;
+use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
+
+use crate::db::COL_EXTRA;
+
+const FINALITY_CONFLICT_KEY: &[u8] = b"finality-conflict";
+
+/// A block and its child, whose seal has the precommits that finalized the block.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FinalizedBlock {
+    pub header: Header,
+    pub child: Header,
+}
+
+/// Two different blocks at the same height, both finalized by the precommits of the same validators.
+/// It proves that more than a third of the validators precommitted both of them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FinalityConflict {
+    pub block_number: BlockNumber,
+    /// The block on the canonical chain
+    pub canonical: FinalizedBlock,
+    /// The block on the other branch, whose child was being imported
+    pub conflicting: FinalizedBlock,
+    /// In seconds since the epoch
+    pub detected_at: u64,
+}
+
+impl Encodable for FinalityConflict {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(6)
+            .append(&self.block_number)
+            .append(&self.canonical.header)
+            .append(&self.canonical.child)
+            .append(&self.conflicting.header)
+            .append(&self.conflicting.child)
+            .append(&self.detected_at);
+    }
+}
+
+impl Decodable for FinalityConflict {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 6 {
+            return Err(DecoderError::RlpInvalidLength {
+                got: item_count,
+                expected: 6,
+            })
+        }
+        Ok(Self {
+            block_number: rlp.val_at(0)?,
+            canonical: FinalizedBlock {
+                header: rlp.val_at(1)?,
+                child: rlp.val_at(2)?,
+            },
+            conflicting: FinalizedBlock {
+                header: rlp.val_at(3)?,
+                child: rlp.val_at(4)?,
+            },
+            detected_at: rlp.val_at(5)?,
+        })
+    }
+}
+
+pub trait ConflictSource {
+    /// Returns the hash of the canonical block.
+    fn canonical_hash(&self, block_number: BlockNumber) -> Option<H256>;
+    /// Returns the header whether it's canonical or not.
+    fn block_header(&self, hash: &H256) -> Option<Header>;
+    /// Returns the validators of the block whose parent is given, who precommit the block.
+    fn validators(&self, parent: &H256) -> Vec<Public>;
+}
+
+/// Returns the conflict if the parent of the header is not the canonical block at its height,
+/// though the canonical one is already finalized by the same validators.
+///
+/// The seal of the header must be verified before, because it finalizes the parent.
+pub fn find_finality_conflict<S: ConflictSource>(source: &S, header: &Header) -> Option<FinalityConflict> {
+    let block_number = header.number().checked_sub(1)?;
+    let canonical_hash = source.canonical_hash(block_number)?;
+    if canonical_hash == *header.parent_hash() {
+        return None
+    }
+    // The canonical block is finalized only if it has a child.
+    let canonical_child = source.block_header(&source.canonical_hash(block_number + 1)?)?;
+    let canonical = source.block_header(&canonical_hash)?;
+    let conflicting = source.block_header(header.parent_hash())?;
+    if source.validators(canonical.parent_hash()) != source.validators(conflicting.parent_hash()) {
+        cwarn!(
+            CLIENT,
+            "#{} ({}) is finalized on another branch, but by the validators different from the canonical one",
+            block_number,
+            conflicting.hash()
+        );
+        return None
+    }
+    Some(FinalityConflict {
+        block_number,
+        canonical: FinalizedBlock {
+            header: canonical,
+            child: canonical_child,
+        },
+        conflicting: FinalizedBlock {
+            header: conflicting,
+            child: header.clone(),
+        },
+        detected_at: SystemTime::now().duration_since(UNIX_EPOCH).expect("There is no time machine.").as_secs(),
+    })
+}
+
+/// Stops the import and the sealing after a finality conflict is found, until the operator clears it.
+/// The conflict is kept in the database, so a restarted node stays halted.
+pub struct ConsensusHalt {
+    /// None if it's not halted. Some(None) if it's halted by the recorded conflict that cannot be read.
+    conflict: Mutex<Option<Option<FinalityConflict>>>,
+}
+
+impl ConsensusHalt {
+    pub fn new(db: &KeyValueDB) -> Self {
+        let conflict = match db.get(COL_EXTRA, FINALITY_CONFLICT_KEY) {
+            Ok(Some(encoded)) => Some(UntrustedRlp::new(&encoded).as_val().map(Some).unwrap_or_else(|err| {
+                // It's safer to stay halted, though the proof cannot be shown.
+                cerror!(CLIENT, "The recorded finality conflict is corrupted: {:?}", err);
+                None
+            })),
+            Ok(None) => None,
+            Err(err) => {
+                cerror!(CLIENT, "Cannot read the recorded finality conflict: {}", err);
+                Some(None)
+            }
+        };
+        Self {
+            conflict: Mutex::new(conflict),
+        }
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.conflict.lock().is_some()
+    }
+
+    /// Returns None if it's not halted, or the recorded conflict cannot be read.
+    pub fn conflict(&self) -> Option<FinalityConflict> {
+        self.conflict.lock().clone().unwrap_or(None)
+    }
+
+    /// Keeps the first conflict if it's already halted.
+    pub fn halt(&self, conflict: FinalityConflict, db: &KeyValueDB) {
+        let mut current = self.conflict.lock();
+        if current.is_some() {
+            return
+        }
+        let mut batch = DBTransaction::new();
+        batch.put(COL_EXTRA, FINALITY_CONFLICT_KEY, &conflict.rlp_bytes());
+        if let Err(err) = db.write(batch) {
+            cerror!(CLIENT, "Cannot record the finality conflict: {}", err);
+        }
+        *current = Some(Some(conflict));
+    }
+
+    /// Returns the cleared conflict, or None if it's not halted.
+    /// The cleared conflict is None if it couldn't be read.
+    pub fn clear(&self, db: &KeyValueDB) -> Option<Option<FinalityConflict>> {
+        let mut current = self.conflict.lock();
+        let conflict = current.take()?;
+        let mut batch = DBTransaction::new();
+        batch.delete(COL_EXTRA, FINALITY_CONFLICT_KEY);
+        if let Err(err) = db.write(batch) {
+            cerror!(CLIENT, "Cannot remove the recorded finality conflict: {}", err);
+        }
+        Some(conflict)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use super::*;
+
+    /// The canonical chain and the branches, whose validators are decided by the parent.
+    #[derive(Default)]
+    struct Chain {
+        headers: HashMap<H256, Header>,
+        canonical: Vec<H256>,
+        validators: HashMap<H256, Vec<Public>>,
+    }
+
+    impl Chain {
+        fn new(length: usize) -> Self {
+            let mut chain = Self::default();
+            let mut parent = H256::zero();
+            for number in 0..length as BlockNumber {
+                parent = chain.add_block(number, parent);
+                chain.canonical.push(parent);
+            }
+            chain
+        }
+
+        fn add_block(&mut self, number: BlockNumber, parent: H256) -> H256 {
+            let mut header = Header::new();
+            header.set_number(number);
+            header.set_parent_hash(parent);
+            header.set_extra_data(H256::random().to_vec());
+            let hash = header.hash();
+            self.headers.insert(hash, header);
+            hash
+        }
+    }
+
+    impl ConflictSource for Chain {
+        fn canonical_hash(&self, block_number: BlockNumber) -> Option<H256> {
+            self.canonical.get(block_number as usize).cloned()
+        }
+
+        fn block_header(&self, hash: &H256) -> Option<Header> {
+            self.headers.get(hash).cloned()
+        }
+
+        fn validators(&self, parent: &H256) -> Vec<Public> {
+            self.validators.get(parent).cloned().unwrap_or_else(|| vec![Public::from(1), Public::from(2)])
+        }
+    }
+
+    #[test]
+    fn block_finalized_on_another_branch_conflicts() {
+        let mut chain = Chain::new(5);
+        let fork = chain.add_block(2, chain.canonical[1]);
+        let fork_child = chain.add_block(3, fork);
+        let header = chain.headers[&fork_child].clone();
+
+        let conflict = find_finality_conflict(&chain, &header).unwrap();
+        assert_eq!(2, conflict.block_number);
+        assert_eq!(chain.headers[&chain.canonical[2]], conflict.canonical.header);
+        assert_eq!(chain.headers[&chain.canonical[3]], conflict.canonical.child);
+        assert_eq!(chain.headers[&fork], conflict.conflicting.header);
+        assert_eq!(header, conflict.conflicting.child);
+    }
+
+    #[test]
+    fn canonical_block_is_not_a_conflict() {
+        let chain = Chain::new(5);
+        for number in 1..5 {
+            let header = chain.headers[&chain.canonical[number]].clone();
+            assert_eq!(None, find_finality_conflict(&chain, &header));
+        }
+    }
+
+    #[test]
+    fn fork_of_the_unfinalized_tip_is_not_a_conflict() {
+        let mut chain = Chain::new(5);
+        let fork = chain.add_block(4, chain.canonical[3]);
+        let fork_child = chain.add_block(5, fork);
+        assert_eq!(None, find_finality_conflict(&chain, &chain.headers[&fork_child].clone()));
+    }
+
+    #[test]
+    fn blocks_finalized_by_different_validators_do_not_conflict() {
+        let mut chain = Chain::new(5);
+        let fork_parent = chain.add_block(1, chain.canonical[0]);
+        chain.validators.insert(fork_parent, vec![Public::from(3), Public::from(4)]);
+        let fork = chain.add_block(2, fork_parent);
+        let fork_child = chain.add_block(3, fork);
+        assert_eq!(None, find_finality_conflict(&chain, &chain.headers[&fork_child].clone()));
+    }
+
+    #[test]
+    fn halt_survives_the_restart_until_cleared() {
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap()));
+        let mut chain = Chain::new(5);
+        let fork = chain.add_block(2, chain.canonical[1]);
+        let fork_child = chain.add_block(3, fork);
+        let conflict = find_finality_conflict(&chain, &chain.headers[&fork_child].clone()).unwrap();
+
+        let halt = ConsensusHalt::new(db.as_ref());
+        assert!(!halt.is_halted());
+        halt.halt(conflict.clone(), db.as_ref());
+        assert!(halt.is_halted());
+
+        let restarted = ConsensusHalt::new(db.as_ref());
+        assert_eq!(Some(conflict.clone()), restarted.conflict());
+        assert_eq!(Some(Some(conflict)), restarted.clear(db.as_ref()));
+        assert_eq!(None, restarted.clear(db.as_ref()));
+        assert!(!ConsensusHalt::new(db.as_ref()).is_halted());
+    }
+
+    #[test]
+    fn corrupted_conflict_keeps_it_halted() {
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap()));
+        let mut chain = Chain::new(5);
+        let fork = chain.add_block(2, chain.canonical[1]);
+        let fork_child = chain.add_block(3, fork);
+        let conflict = find_finality_conflict(&chain, &chain.headers[&fork_child].clone()).unwrap();
+        ConsensusHalt::new(db.as_ref()).halt(conflict, db.as_ref());
+        let encoded = db.get(COL_EXTRA, FINALITY_CONFLICT_KEY).unwrap().unwrap();
+        let mut batch = DBTransaction::new();
+        batch.put(COL_EXTRA, FINALITY_CONFLICT_KEY, &encoded[..encoded.len() - 2]);
+        db.write(batch).unwrap();
+
+        let restarted = ConsensusHalt::new(db.as_ref());
+        assert!(restarted.is_halted());
+        assert_eq!(None, restarted.conflict());
+        assert_eq!(Some(None), restarted.clear(db.as_ref()));
+        assert!(!restarted.is_halted());
+        assert!(!ConsensusHalt::new(db.as_ref()).is_halted());
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use std::collections::VecDeque;
+use std::ops::AddAssign;
+use std::time::{Duration, Instant};
+
+use ctypes::BlockNumber;
+use parking_lot::Mutex;
+use primitives::H256;
+
+/// The number of the recent blocks whose import timings are kept.
+pub const IMPORT_TIMINGS_WINDOW: usize = 256;
+
+/// The phases of the block import, in the order of the pipeline.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImportPhase {
+    Decode,
+    HeaderVerification,
+    /// The verification of the seal signatures, separated from the other header checks.
+    SealVerification,
+    BodyVerification,
+    Execution,
+    TrieCommit,
+    DbWrite,
+}
+
+/// The time spent in each phase of a block import.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ImportTimings {
+    pub decode: Duration,
+    pub header_verification: Duration,
+    pub seal_verification: Duration,
+    pub body_verification: Duration,
+    pub execution: Duration,
+    pub trie_commit: Duration,
+    pub db_write: Duration,
+    /// The time spent in the verification queue and the importer.
+    /// The time waiting in the queue is not counted.
+    pub total: Duration,
+}
+
+impl ImportTimings {
+    /// Returns the durations in the order of `ImportPhase`.
+    pub fn phases(&self) -> [Duration; 7] {
+        [
+            self.decode,
+            self.header_verification,
+            self.seal_verification,
+            self.body_verification,
+            self.execution,
+            self.trie_commit,
+            self.db_write,
+        ]
+    }
+
+    fn phase_mut(&mut self, phase: ImportPhase) -> &mut Duration {
+        match phase {
+            ImportPhase::Decode => &mut self.decode,
+            ImportPhase::HeaderVerification => &mut self.header_verification,
+            ImportPhase::SealVerification => &mut self.seal_verification,
+            ImportPhase::BodyVerification => &mut self.body_verification,
+            ImportPhase::Execution => &mut self.execution,
+            ImportPhase::TrieCommit => &mut self.trie_commit,
+            ImportPhase::DbWrite => &mut self.db_write,
+        }
+    }
+}
+
+impl AddAssign for ImportTimings {
+    fn add_assign(&mut self, other: Self) {
+        self.decode += other.decode;
+        self.header_verification += other.header_verification;
+        self.seal_verification += other.seal_verification;
+        self.body_verification += other.body_verification;
+        self.execution += other.execution;
+        self.trie_commit += other.trie_commit;
+        self.db_write += other.db_write;
+        self.total += other.total;
+    }
+}
+
+/// Measures the consecutive phases with the monotonic clock.
+/// Each lap is charged to the given phase, so the phases add up to the total.
+pub struct PhaseTimer {
+    timings: ImportTimings,
+    started_at: Instant,
+    lapped_at: Instant,
+}
+
+impl PhaseTimer {
+    pub fn start() -> Self {
+        let now = Instant::now();
+        Self {
+            timings: ImportTimings::default(),
+            started_at: now,
+            lapped_at: now,
+        }
+    }
+
+    /// Charges the time since the previous lap to the phase.
+    pub fn lap(&mut self, phase: ImportPhase) {
+        let now = Instant::now();
+        *self.timings.phase_mut(phase) += now.duration_since(self.lapped_at);
+        self.lapped_at = now;
+    }
+
+    pub fn finish(mut self) -> ImportTimings {
+        self.timings.total += self.lapped_at.duration_since(self.started_at);
+        self.timings
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlockImportTimings {
+    pub number: BlockNumber,
+    pub hash: H256,
+    pub timings: ImportTimings,
+}
+
+/// Keeps the import timings of the recent blocks.
+/// The buffer is allocated once, so recording a block doesn't allocate.
+pub struct ImportTimingsLog {
+    records: Mutex<VecDeque<BlockImportTimings>>,
+}
+
+impl Default for ImportTimingsLog {
+    fn default() -> Self {
+        Self {
+            records: Mutex::new(VecDeque::with_capacity(IMPORT_TIMINGS_WINDOW)),
+        }
+    }
+}
+
+impl ImportTimingsLog {
+    pub fn record(&self, timings: BlockImportTimings) {
+        let mut records = self.records.lock();
+        if records.len() == IMPORT_TIMINGS_WINDOW {
+            records.pop_front();
+        }
+        records.push_back(timings);
+    }
+
+    /// Returns at most `limit` records from the newest one.
+    pub fn recent(&self, limit: usize) -> Vec<BlockImportTimings> {
+        self.records.lock().iter().rev().take(limit).cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread::sleep;
+
+    use super::*;
+
+    #[test]
+    fn phases_add_up_to_the_total() {
+        let mut timer = PhaseTimer::start();
+        sleep(Duration::from_millis(2));
+        timer.lap(ImportPhase::Decode);
+        sleep(Duration::from_millis(3));
+        timer.lap(ImportPhase::SealVerification);
+        sleep(Duration::from_millis(1));
+        timer.lap(ImportPhase::Execution);
+        let mut timings = timer.finish();
+
+        let mut timer = PhaseTimer::start();
+        sleep(Duration::from_millis(2));
+        timer.lap(ImportPhase::DbWrite);
+        timings += timer.finish();
+
+        assert!(timings.decode >= Duration::from_millis(2));
+        assert!(timings.seal_verification >= Duration::from_millis(3));
+        assert!(timings.execution >= Duration::from_millis(1));
+        assert!(timings.db_write >= Duration::from_millis(2));
+        assert_eq!(Duration::default(), timings.header_verification);
+
+        let sum: Duration = timings.phases().iter().sum();
+        let gap = if sum > timings.total {
+            sum - timings.total
+        } else {
+            timings.total - sum
+        };
+        assert!(gap < Duration::from_millis(1), "sum: {:?}, total: {:?}", sum, timings.total);
+    }
+
+    fn record(number: BlockNumber) -> BlockImportTimings {
+        BlockImportTimings {
+            number,
+            hash: H256::from(number),
+            timings: ImportTimings::default(),
+        }
+    }
+
+    #[test]
+    fn recent_returns_the_newest_first() {
+        let log = ImportTimingsLog::default();
+        for number in 1..=3 {
+            log.record(record(number));
+        }
+        let numbers: Vec<_> = log.recent(2).into_iter().map(|record| record.number).collect();
+        assert_eq!(vec![3, 2], numbers);
+    }
+
+    #[test]
+    fn log_wraps_around() {
+        let log = ImportTimingsLog::default();
+        let capacity = log.records.lock().capacity();
+        for number in 0..(IMPORT_TIMINGS_WINDOW as u64 + 44) {
+            log.record(record(number));
+        }
+        let recent = log.recent(usize::max_value());
+        assert_eq!(IMPORT_TIMINGS_WINDOW, recent.len());
+        assert_eq!(IMPORT_TIMINGS_WINDOW as u64 + 43, recent[0].number);
+        assert_eq!(44, recent[IMPORT_TIMINGS_WINDOW - 1].number);
+        assert_eq!(capacity, log.records.lock().capacity(), "The buffer must not grow");
+    }
+}
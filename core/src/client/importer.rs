@@ -20,14 +20,20 @@ use std::sync::Arc;
 use std::time::Instant;
 
 use cio::IoChannel;
+use cmetrics::METRICS;
+use cstate::TopStateView;
 use ctypes::header::Header;
+use ctypes::BlockNumber;
 use kvdb::DBTransaction;
 use parking_lot::{Mutex, MutexGuard};
 use primitives::H256;
 use rlp::Encodable;
 
-use super::{BlockChainTrait, Client, ClientConfig};
-use crate::block::{enact, IsBlock, LockedBlock};
+use super::{
+    BlockChainClient, BlockChainTrait, BlockImportTimings, Client, ClientConfig, ImportPhase, ImportTimings,
+    ImportTimingsLog, PhaseTimer,
+};
+use crate::block::{IsBlock, LockedBlock};
 use crate::blockchain::{BodyProvider, HeaderProvider, ImportRoute};
 use crate::consensus::CodeChainEngine;
 use crate::encoded;
@@ -36,7 +42,9 @@ use crate::miner::{Miner, MinerService};
 use crate::service::ClientIoMessage;
 use crate::types::BlockId;
 use crate::verification::queue::{BlockQueue, HeaderQueue};
-use crate::verification::{self, PreverifiedBlock, Verifier};
+use crate::verification::{
+    self, PreverifiedBlock, RejectionCode, RejectionLog, SealCheckpoints, UnvouchedBlocks, Verifier,
+};
 use crate::views::{BlockView, HeaderView};
 use client::EngineInfo;
 
@@ -58,6 +66,15 @@ pub struct Importer {
 
     /// CodeChain engine to be used during import
     pub engine: Arc<CodeChainEngine>,
+
+    /// Decides which seals are skipped while importing the blocks far below the tip
+    seal_checkpoints: SealCheckpoints,
+
+    /// The time spent importing the recent blocks
+    import_timings: ImportTimingsLog,
+
+    /// The blocks rejected by the verification queues and the importer
+    pub rejections: Arc<RejectionLog>,
 }
 
 impl Importer {
@@ -67,15 +84,22 @@ impl Importer {
         message_channel: IoChannel<ClientIoMessage>,
         miner: Arc<Miner>,
     ) -> Result<Importer, Error> {
+        let rejections = Arc::new(RejectionLog::default());
         let block_queue = BlockQueue::new(
             &config.queue,
             engine.clone(),
             message_channel.clone(),
             config.verifier_type.verifying_seal(),
+            Arc::clone(&rejections),
         );
 
-        let header_queue =
-            HeaderQueue::new(&config.queue, engine.clone(), message_channel, config.verifier_type.verifying_seal());
+        let header_queue = HeaderQueue::new(
+            &config.queue,
+            engine.clone(),
+            message_channel,
+            config.verifier_type.verifying_seal(),
+            Arc::clone(&rejections),
+        );
 
         Ok(Importer {
             import_lock: Mutex::new(()),
@@ -84,6 +108,9 @@ impl Importer {
             header_queue,
             miner,
             engine,
+            seal_checkpoints: SealCheckpoints::new(config.seal_checkpoint_interval),
+            import_timings: Default::default(),
+            rejections,
         })
     }
 
@@ -94,8 +121,14 @@ impl Importer {
             let mut imported_blocks = Vec::with_capacity(MAX_BLOCKS_TO_IMPORT);
             let mut invalid_blocks = HashSet::new();
             let mut import_results = Vec::with_capacity(MAX_BLOCKS_TO_IMPORT);
+            // The blocks left unimported because the import is halted by a finality conflict
+            let mut held_blocks = Vec::new();
 
             let import_lock = self.import_lock.lock();
+            if client.is_chain_frozen() {
+                ctrace!(CLIENT, "The chain is frozen, keep the verified blocks in the queue");
+                return 0
+            }
             let blocks = self.block_queue.drain(MAX_BLOCKS_TO_IMPORT);
             if blocks.is_empty() {
                 return 0
@@ -108,26 +141,62 @@ impl Importer {
 
             let start = Instant::now();
 
-            for block in blocks {
-                let header = &block.header;
-                ctrace!(CLIENT, "Importing block {}", header.number());
-                let is_invalid = invalid_blocks.contains(header.parent_hash());
+            let checkpoints = self.seal_checkpoints_of(&blocks, client);
+            // The blocks whose seals are skipped wait here until a verified seal vouches for them.
+            let mut unvouched = UnvouchedBlocks::default();
+            // The blocks left unimported because their seals cannot be vouched for
+            let mut unverified_blocks = Vec::new();
+            for (block, checkpoint) in blocks.into_iter().zip(checkpoints) {
+                let hash = block.header.hash();
+                if !held_blocks.is_empty() {
+                    held_blocks.push(hash);
+                    continue
+                }
+                ctrace!(CLIENT, "Importing block {}", block.header.number());
+                let is_invalid = invalid_blocks.contains(block.header.parent_hash());
                 if is_invalid {
-                    invalid_blocks.insert(header.hash());
+                    invalid_blocks.insert(hash);
                     continue
                 }
-                if let Ok(closed_block) = self.check_and_close_block(&block, client) {
-                    if self.engine.is_proposal(&block.header) {
-                        self.engine.on_verified_proposal(encoded::Block::new(block.bytes.clone()))
+                let mut timer = PhaseTimer::start();
+                let is_parent_unvouched = !unvouched.is_empty();
+                let closed_block = match self.check_and_close_block(
+                    &block,
+                    client,
+                    checkpoint.is_none(),
+                    is_parent_unvouched,
+                    &mut timer,
+                ) {
+                    Ok(closed_block) => closed_block,
+                    Err(()) => {
+                        invalid_blocks.insert(hash);
+                        unverified_blocks
+                            .extend(unvouched.discard().into_iter().map(|(block, ..)| block.header.hash()));
+                        continue
                     }
-
-                    imported_blocks.push(header.hash());
-                    let route = self.commit_block(&closed_block, &header, &block.bytes, client);
+                };
+                if checkpoint.is_some() {
+                    // The state is written for the execution of the children, but the block is not committed.
+                    self.write_state(&closed_block, block.header.number(), client);
+                    unvouched.hold(block.header.number(), (block, closed_block, timer));
+                    continue
+                }
+                if client.check_finality_conflict(&block.header) {
+                    held_blocks.push(hash);
+                    held_blocks.extend(unvouched.discard().into_iter().map(|(block, ..)| block.header.hash()));
+                    continue
+                }
+                let vouched_by = block.header.number();
+                for (vouched, closed_block, timer) in unvouched.vouch(vouched_by) {
+                    imported_blocks.push(vouched.header.hash());
+                    let route = self.commit_verified_block(vouched, closed_block, Some(vouched_by), client, timer);
                     import_results.push(route);
-                } else {
-                    invalid_blocks.insert(header.hash());
                 }
+                imported_blocks.push(hash);
+                let route = self.commit_verified_block(block, closed_block, None, client, timer);
+                import_results.push(route);
             }
+            unverified_blocks.extend(unvouched.discard().into_iter().map(|(block, ..)| block.header.hash()));
 
             let imported = imported_blocks.len();
             let invalid_blocks = invalid_blocks.into_iter().collect::<Vec<H256>>();
@@ -135,9 +204,14 @@ impl Importer {
             if !invalid_blocks.is_empty() {
                 self.block_queue.mark_as_bad(&invalid_blocks);
             }
+            // They are forgotten, so that they can be downloaded again after the halt is cleared.
+            self.block_queue.mark_as_good(&held_blocks);
+            // They are forgotten, so that they can be downloaded again with a valid checkpoint.
+            self.block_queue.mark_as_good(&unverified_blocks);
             let is_empty = self.block_queue.mark_as_good(&imported_blocks);
             let duration_ns = {
                 let elapsed = start.elapsed();
+                METRICS.block_import_time.observe(elapsed);
                 elapsed.as_secs() * 1_000_000_000 + u64::from(elapsed.subsec_nanos())
             };
             (imported_blocks, import_results, invalid_blocks, imported, duration_ns, is_empty)
@@ -146,6 +220,11 @@ impl Importer {
         {
             if !imported_blocks.is_empty() && is_empty {
                 let (enacted, retracted) = self.calculate_enacted_retracted(&import_results);
+                if !retracted.is_empty() {
+                    client.term_index().clear();
+                }
+                client.sync_params_history();
+                client.check_signer_eligibility();
                 self.miner.chain_new_blocks(client, &imported_blocks, &invalid_blocks, &enacted, &retracted);
                 client.new_blocks(&imported_blocks, &invalid_blocks, &enacted, &retracted, &[], duration);
             }
@@ -181,11 +260,69 @@ impl Importer {
         (map_to_vec(enacted), map_to_vec(retracted))
     }
 
+    /// Returns the checkpoints vouching for the blocks whose seals can be skipped.
+    ///
+    /// The seals are skipped only if the blocks are contiguous.
+    /// The skipped blocks are not committed until a verified seal vouches for them,
+    /// so a peer announcing a fake tip can delay the commit of the blocks, but cannot make them skip the seals.
+    fn seal_checkpoints_of(&self, blocks: &[PreverifiedBlock], client: &Client) -> Vec<Option<BlockNumber>> {
+        let is_contiguous = blocks.windows(2).all(|pair| *pair[1].header.parent_hash() == pair[0].header.hash());
+        let (first, last) = match (blocks.first(), blocks.last()) {
+            (Some(first), Some(last)) if is_contiguous => (first.header.number(), last.header.number()),
+            _ => return vec![None; blocks.len()],
+        };
+        let best_proposal_number = client.block_chain().best_proposal_header().number();
+        blocks
+            .iter()
+            .map(|block| self.seal_checkpoints.checkpoint_of(block.header.number(), first, last, best_proposal_number))
+            .collect()
+    }
+
+    /// Commits the block whose seal is verified, or vouched for by the seal of `vouched_by`.
+    fn commit_verified_block(
+        &self,
+        block: PreverifiedBlock,
+        closed_block: LockedBlock,
+        vouched_by: Option<BlockNumber>,
+        client: &Client,
+        mut timer: PhaseTimer,
+    ) -> ImportRoute {
+        let header = &block.header;
+        if self.engine.is_proposal(header) {
+            self.engine.on_verified_proposal(encoded::Block::new(block.bytes.clone()))
+        }
+        if let Some(vouched_by) = vouched_by {
+            let mut batch = DBTransaction::new();
+            client.block_chain().insert_skipped_seal_verification(&mut batch, &header.hash(), vouched_by);
+            client.db().write_buffered(batch);
+        }
+        timer.lap(ImportPhase::DbWrite);
+        self.rejections.clear_source(&header.hash());
+        let route = self.commit_block(&closed_block, header, &block.bytes, client, &mut timer);
+        self.record_import_timings(header, block.timings, timer);
+        route
+    }
+
+    /// Writes the state of the block, so that its children can be executed before it's committed.
+    /// The state is addressed by its root, so it's harmless if the block is never committed.
+    fn write_state(&self, block: &LockedBlock, number: BlockNumber, client: &Client) {
+        let mut batch = DBTransaction::new();
+        block.state().journal_under(&mut batch, number).expect("DB commit failed");
+        client.db().write_buffered(batch);
+    }
+
     // NOTE: the header of the block passed here is not necessarily sealed, as
     // it is for reconstructing the state transition.
     //
     // The header passed is from the original block data and is sealed.
-    pub fn commit_block<B>(&self, block: &B, header: &Header, block_data: &[u8], client: &Client) -> ImportRoute
+    pub fn commit_block<B>(
+        &self,
+        block: &B,
+        header: &Header,
+        block_data: &[u8],
+        client: &Client,
+        timer: &mut PhaseTimer,
+    ) -> ImportRoute
     where
         B: IsBlock, {
         let hash = header.hash();
@@ -201,6 +338,8 @@ impl Importer {
         let mut batch = DBTransaction::new();
 
         block.state().journal_under(&mut batch, number).expect("DB commit failed");
+        timer.lap(ImportPhase::TrieCommit);
+        chain.cache_signers(block.transactions());
         let route = chain.insert_block(&mut batch, block_data, invoices.clone(), self.engine.borrow());
 
         // Final commit to the DB
@@ -208,15 +347,48 @@ impl Importer {
         chain.commit();
 
         if hash == chain.best_block_hash() {
-            let mut state_db = client.state_db().write();
+            // The blocks closed at the heights up to the best block cannot be imported on top of it.
+            client.evict_cached_executions(number);
             let state = block.state();
-            state_db.override_state(&state);
+            client.state_db().write().override_state(&state);
+            client.record_hot_state_keys(number, state);
+
+            let metadata = state.metadata().unwrap().expect("Metadata always exist");
+            if metadata.current_term_id() != 0 && metadata.last_term_finished_block_num() == number {
+                client.term_index().on_term_closed(metadata.current_term_id() - 1, number);
+            }
         }
+        timer.lap(ImportPhase::DbWrite);
 
         route
     }
 
-    fn check_and_close_block(&self, block: &PreverifiedBlock, client: &Client) -> Result<LockedBlock, ()> {
+    fn record_import_timings(&self, header: &Header, preverification: ImportTimings, timer: PhaseTimer) {
+        let mut timings = timer.finish();
+        timings += preverification;
+        for (summary, duration) in METRICS.block_import_phase_time.iter().zip(timings.phases().iter()) {
+            summary.observe(*duration);
+        }
+        self.import_timings.record(BlockImportTimings {
+            number: header.number(),
+            hash: header.hash(),
+            timings,
+        });
+    }
+
+    /// Returns the import timings of the recent blocks from the newest one.
+    pub fn import_timings(&self, limit: usize) -> Vec<BlockImportTimings> {
+        self.import_timings.recent(limit)
+    }
+
+    fn check_and_close_block(
+        &self,
+        block: &PreverifiedBlock,
+        client: &Client,
+        verify_seal: bool,
+        is_parent_unvouched: bool,
+        timer: &mut PhaseTimer,
+    ) -> Result<LockedBlock, ()> {
         let engine = &*self.engine;
         let header = &block.header;
 
@@ -233,15 +405,18 @@ impl Importer {
             );
         })?;
 
-        chain.block_body(header.parent_hash()).ok_or_else(|| {
-            cerror!(
-                CLIENT,
-                "Block import failed for #{} ({}): Parent block not found ({}) ",
-                header.number(),
-                header.hash(),
-                parent.hash()
-            );
-        })?;
+        // The unvouched parent is executed, but its body is not committed yet.
+        if !is_parent_unvouched {
+            chain.block_body(header.parent_hash()).ok_or_else(|| {
+                cerror!(
+                    CLIENT,
+                    "Block import failed for #{} ({}): Parent block not found ({}) ",
+                    header.number(),
+                    header.hash(),
+                    parent.hash()
+                );
+            })?;
+        }
 
         let common_params = client.common_params(parent.hash().into()).unwrap();
 
@@ -260,53 +435,77 @@ impl Importer {
                 }),
                 &common_params,
             )
-            .map_err(|e| {
-                cwarn!(
-                    CLIENT,
-                    "Stage 3 block verification failed for #{} ({})\nError: {:?}",
-                    header.number(),
-                    header.hash(),
-                    e
-                );
-            })?;
+            .map_err(|e| self.reject(header, RejectionCode::HeaderBasic, &e))?;
+        timer.lap(ImportPhase::HeaderVerification);
 
-        self.verifier.verify_block_external(header, engine).map_err(|e| {
-            cwarn!(
-                CLIENT,
-                "Stage 4 block verification failed for #{} ({})\nError: {:?}",
-                header.number(),
-                header.hash(),
-                e
-            );
-        })?;
+        if verify_seal {
+            self.verifier
+                .verify_block_external(header, engine)
+                .map_err(|e| self.reject(header, RejectionCode::SealSignature, &e))?;
+        } else {
+            ctrace!(CLIENT, "Skip the seal verification of #{} ({})", header.number(), header.hash());
+        }
+        timer.lap(ImportPhase::SealVerification);
 
 
         // Enact Verified Block
-        let db = client.state_db().read().clone(&parent.state_root());
-
-        let enact_result = enact(&block.header, &block.transactions, engine, client, db, &parent);
-        let locked_block = enact_result.map_err(|e| {
-            cwarn!(CLIENT, "Block import failed for #{} ({})\nError: {:?}", header.number(), header.hash(), e);
-        })?;
+        // The blocks closed by the miner and the proposals executed in advance are not executed again,
+        // unless the results don't match the headers.
+        // The chain is released first, because the execution in advance may be waiting for it.
+        drop(chain);
+        let matches_header = |locked_block: &LockedBlock| {
+            match self.verifier.verify_block_final(header, locked_block.block().header()) {
+                Ok(()) => true,
+                Err(err) => {
+                    cwarn!(
+                        CLIENT,
+                        "The execution of #{} ({}) in advance is wrong, execute it again: {}",
+                        header.number(),
+                        header.hash(),
+                        err
+                    );
+                    false
+                }
+            }
+        };
+        let executed_in_advance = client
+            .take_cached_execution(&parent.state_root(), &header.hash(), &matches_header)
+            .or_else(|| client.take_executed_proposal(&header.hash(), &matches_header));
+        let locked_block = match executed_in_advance {
+            Some(locked_block) => {
+                ctrace!(CLIENT, "Reuse the execution of #{} ({})", header.number(), header.hash());
+                locked_block
+            }
+            None => {
+                let db = client.state_db().read().clone(&parent.state_root());
+                let enact_result = client.execute_block(&block.header, &block.transactions, db, &parent);
+                enact_result.map_err(|e| self.reject(header, RejectionCode::Execution, &e))?
+            }
+        };
 
         // Final Verification
-        self.verifier.verify_block_final(header, locked_block.block().header()).map_err(|e| {
-            cwarn!(
-                CLIENT,
-                "Stage 5 block verification failed for #{} ({})\nError: {:?}",
-                header.number(),
-                header.hash(),
-                e
-            );
-        })?;
+        self.verifier
+            .verify_block_final(header, locked_block.block().header())
+            .map_err(|e| self.reject(header, RejectionCode::StateRoot, &e))?;
+        timer.lap(ImportPhase::Execution);
 
         Ok(locked_block)
     }
 
+    /// Records the failure of the verification or the execution, which is logged once per block and category.
+    fn reject(&self, header: &Header, stage: RejectionCode, error: &Error) {
+        let code = RejectionCode::classify(error, header, stage);
+        self.rejections.reject(header.hash(), header.number(), code, error);
+    }
+
     /// This is triggered by a message coming from a header queue when the header is ready for insertion
     pub fn import_verified_headers(&self, client: &Client) -> usize {
         const MAX_HEADERS_TO_IMPORT: usize = 1_000;
         let lock = self.import_lock.lock();
+        if client.is_chain_frozen() {
+            ctrace!(CLIENT, "The chain is frozen, keep the verified headers in the queue");
+            return 0
+        }
         let headers = self.header_queue.drain(MAX_HEADERS_TO_IMPORT);
         self.import_headers(&headers, client, &lock)
     }
@@ -0,0 +1,282 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use ctypes::BlockNumber;
+use kvdb::{DBTransaction, KeyValueDB};
+use primitives::H256;
+
+use crate::blockchain::{BlockChain, DatabaseAnomaly};
+
+/// The number of blocks checked at once.
+/// The block import and the other client messages are handled between the chunks.
+pub const INTEGRITY_CHECK_CHUNK_SIZE: u64 = 256;
+/// The bodies are scanned for the orphans by the first byte of their hashes.
+pub const BODY_KEY_PREFIXES: u16 = 256;
+
+/// What to do with the anomalies after the check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RepairOptions {
+    /// Delete the bodies stored without headers.
+    pub delete_orphans: bool,
+    /// Delete the corrupt bodies, and download the missing and the corrupt bodies from the peers again.
+    pub refetch_bodies: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntegrityReport {
+    pub from: BlockNumber,
+    pub to: BlockNumber,
+    pub repair: Option<RepairOptions>,
+    /// The number of blocks checked so far.
+    pub checked_blocks: u64,
+    /// The number of key prefixes scanned for the orphaned bodies so far, out of `BODY_KEY_PREFIXES`.
+    pub scanned_body_prefixes: u16,
+    pub anomalies: Vec<DatabaseAnomaly>,
+    pub deleted_bodies: usize,
+    /// The bodies requested to the peers.
+    pub refetched_bodies: Vec<H256>,
+    pub finished: bool,
+}
+
+impl IntegrityReport {
+    pub fn total_blocks(&self) -> u64 {
+        if self.from > self.to {
+            0
+        } else {
+            self.to - self.from + 1
+        }
+    }
+}
+
+/// Checks the database chunk by chunk, and repairs it at the end if requested.
+pub struct IntegrityCheck {
+    report: IntegrityReport,
+}
+
+impl IntegrityCheck {
+    pub fn new(from: BlockNumber, to: BlockNumber, repair: Option<RepairOptions>) -> Self {
+        Self {
+            report: IntegrityReport {
+                from,
+                to,
+                repair,
+                checked_blocks: 0,
+                scanned_body_prefixes: 0,
+                anomalies: Vec::new(),
+                deleted_bodies: 0,
+                refetched_bodies: Vec::new(),
+                finished: false,
+            },
+        }
+    }
+
+    pub fn report(&self) -> &IntegrityReport {
+        &self.report
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.report.finished
+    }
+
+    /// Runs the next chunk of the check.
+    pub fn step(&mut self, chain: &BlockChain, db: &KeyValueDB) {
+        let report = &mut self.report;
+        if report.finished {
+            return
+        }
+        if report.checked_blocks < report.total_blocks() {
+            let from = report.from + report.checked_blocks;
+            let to = report.to.min(from + INTEGRITY_CHECK_CHUNK_SIZE - 1);
+            report.anomalies.extend(chain.check_integrity(from, to));
+            report.checked_blocks += to - from + 1;
+            return
+        }
+        if report.scanned_body_prefixes < BODY_KEY_PREFIXES {
+            report.anomalies.extend(chain.find_orphaned_bodies(report.scanned_body_prefixes as u8));
+            report.scanned_body_prefixes += 1;
+            return
+        }
+        if let Some(options) = report.repair {
+            self.repair(options, chain, db);
+        }
+        self.report.finished = true;
+    }
+
+    fn repair(&mut self, options: RepairOptions, chain: &BlockChain, db: &KeyValueDB) {
+        let mut bodies_to_delete = Vec::new();
+        let mut bodies_to_refetch = Vec::new();
+        for anomaly in &self.report.anomalies {
+            match anomaly {
+                DatabaseAnomaly::OrphanedBody {
+                    hash,
+                } if options.delete_orphans => bodies_to_delete.push(*hash),
+                DatabaseAnomaly::CorruptBody {
+                    hash,
+                    ..
+                } if options.refetch_bodies => {
+                    bodies_to_delete.push(*hash);
+                    bodies_to_refetch.push(*hash);
+                }
+                DatabaseAnomaly::MissingBody {
+                    hash,
+                    ..
+                } if options.refetch_bodies => bodies_to_refetch.push(*hash),
+                _ => {}
+            }
+        }
+        if !bodies_to_delete.is_empty() {
+            let mut batch = DBTransaction::new();
+            chain.remove_bodies(&mut batch, &bodies_to_delete);
+            db.write(batch).expect("Low level database error. Some issue with disk?");
+            cinfo!(CLIENT, "{} orphaned or corrupt bodies are deleted", bodies_to_delete.len());
+        }
+        self.report.deleted_bodies = bodies_to_delete.len();
+        self.report.refetched_bodies = bodies_to_refetch;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ctypes::Header;
+    use primitives::U256;
+
+    use super::*;
+    use crate::blockchain::DEFAULT_MAX_REORG_DEPTH;
+    use crate::db;
+    use crate::scheme::Scheme;
+    use crate::tests::helpers::create_test_block;
+
+    fn new_chain(length: usize) -> (BlockChain, Arc<KeyValueDB>, Vec<H256>) {
+        let scheme = Scheme::new_test();
+        let db: Arc<KeyValueDB> = Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap()));
+        let chain = BlockChain::new(&scheme.genesis_block(), Arc::clone(&db), DEFAULT_MAX_REORG_DEPTH, false, false);
+        let mut parent = scheme.genesis_header();
+        let mut hashes = vec![parent.hash()];
+        for _ in 0..length {
+            let mut header = Header::new();
+            header.set_parent_hash(parent.hash());
+            header.set_number(parent.number() + 1);
+            header.set_score(U256::from(10));
+            let mut batch = DBTransaction::new();
+            chain.insert_block(&mut batch, &create_test_block(&header), vec![], &*scheme.engine);
+            db.write(batch).unwrap();
+            chain.commit();
+            hashes.push(header.hash());
+            parent = header;
+        }
+        (chain, db, hashes)
+    }
+
+    fn run(check: &mut IntegrityCheck, chain: &BlockChain, db: &KeyValueDB) -> usize {
+        let mut steps = 0;
+        while !check.is_finished() {
+            check.step(chain, db);
+            steps += 1;
+        }
+        steps
+    }
+
+    #[test]
+    fn check_in_chunks() {
+        let (chain, db, _hashes) = new_chain(300);
+        let mut check = IntegrityCheck::new(0, 300, None);
+        check.step(&chain, &*db);
+        assert_eq!(INTEGRITY_CHECK_CHUNK_SIZE, check.report().checked_blocks);
+        check.step(&chain, &*db);
+        assert_eq!(301, check.report().checked_blocks);
+        assert_eq!(0, check.report().scanned_body_prefixes);
+
+        let steps = run(&mut check, &chain, &*db);
+        assert_eq!(usize::from(BODY_KEY_PREFIXES) + 1, steps);
+        assert_eq!(Vec::<DatabaseAnomaly>::new(), check.report().anomalies);
+    }
+
+    #[test]
+    fn repair_deletes_orphans_and_refetches_bodies() {
+        let (chain, db, hashes) = new_chain(5);
+        let orphan = H256::from(7);
+        let body = db.get(db::COL_BODIES, &hashes[1]).unwrap().unwrap();
+        let mut batch = DBTransaction::new();
+        batch.put(db::COL_BODIES, &orphan, &body);
+        batch.delete(db::COL_BODIES, &hashes[2]);
+        batch.put(db::COL_BODIES, &hashes[4], &[0x80]);
+        db.write(batch).unwrap();
+
+        let options = RepairOptions {
+            delete_orphans: true,
+            refetch_bodies: true,
+        };
+        let mut check = IntegrityCheck::new(0, 5, Some(options));
+        run(&mut check, &chain, &*db);
+
+        let report = check.report();
+        assert_eq!(
+            vec![
+                DatabaseAnomaly::MissingBody {
+                    number: 2,
+                    hash: hashes[2],
+                },
+                DatabaseAnomaly::CorruptBody {
+                    number: 4,
+                    hash: hashes[4],
+                },
+                DatabaseAnomaly::OrphanedBody {
+                    hash: orphan,
+                },
+            ],
+            report.anomalies
+        );
+        assert_eq!(2, report.deleted_bodies);
+        assert_eq!(vec![hashes[2], hashes[4]], report.refetched_bodies);
+        assert_eq!(None, db.get(db::COL_BODIES, &orphan).unwrap());
+        assert_eq!(None, db.get(db::COL_BODIES, &hashes[4]).unwrap());
+
+        let mut check = IntegrityCheck::new(0, 5, None);
+        run(&mut check, &chain, &*db);
+        assert_eq!(
+            vec![
+                DatabaseAnomaly::MissingBody {
+                    number: 2,
+                    hash: hashes[2],
+                },
+                DatabaseAnomaly::MissingBody {
+                    number: 4,
+                    hash: hashes[4],
+                },
+            ],
+            check.report().anomalies
+        );
+    }
+
+    #[test]
+    fn check_without_repair_keeps_the_database() {
+        let (chain, db, hashes) = new_chain(2);
+        let orphan = H256::from(7);
+        let body = db.get(db::COL_BODIES, &hashes[1]).unwrap().unwrap();
+        let mut batch = DBTransaction::new();
+        batch.put(db::COL_BODIES, &orphan, &body);
+        db.write(batch).unwrap();
+
+        let mut check = IntegrityCheck::new(0, 2, None);
+        run(&mut check, &chain, &*db);
+        assert_eq!(0, check.report().deleted_bodies);
+        assert!(check.report().refetched_bodies.is_empty());
+        assert!(db.get(db::COL_BODIES, &orphan).unwrap().is_some());
+    }
+}
@@ -14,41 +14,90 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod action_trace;
+mod block_production;
 mod chain_notify;
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::module_inception))]
 mod client;
 mod config;
+mod database_stats;
+mod dry_run;
 mod error;
+mod execution_cache;
+mod finality;
+mod finality_conflict;
+mod import_timings;
 mod importer;
+mod integrity_check;
+mod optimistic_execution;
+mod params_history;
+mod range_statistics;
+mod replay;
+mod reward_distribution;
+mod state_warming;
+mod term_index;
 mod test_client;
 
+pub use self::action_trace::{trace_custom_action, ActionHandlerTrace, TraceTarget, TracingActionHandler};
+pub use self::block_production::{BlockProductionStats, MAX_BLOCK_PRODUCTION_WINDOW};
 pub use self::chain_notify::ChainNotify;
+pub use self::database_stats::{ColumnStats, DatabaseStats};
+pub use self::dry_run::{dry_run, AccountChange, ActionDataChange, DryRunOptions, DryRunResult};
+pub use self::execution_cache::{ExecutionCache, ExecutionCacheStats, MAX_CACHED_EXECUTIONS};
+pub use self::finality::{BlockFinality, FinalityEvidence};
+pub use self::finality_conflict::{FinalityConflict, FinalizedBlock};
+pub use self::integrity_check::{IntegrityCheck, IntegrityReport, RepairOptions, INTEGRITY_CHECK_CHUNK_SIZE};
+pub use self::optimistic_execution::{
+    OptimisticExecutionStats, OptimisticExecutions, MAX_OPTIMISTIC_EXECUTIONS, MAX_WAIT_FOR_EXECUTION,
+};
+pub use self::params_history::ParamsActivation;
+pub use self::range_statistics::{RangeStatistics, RangeStatisticsBuilder, MAX_STATISTICS_RANGE};
+pub use self::replay::{BlockDivergence, ReplayOptions, ReplayReport, ReplayedTransaction, MAX_REPLAY_BLOCKS};
+pub use self::reward_distribution::BlockRewardDistribution;
+pub use self::state_warming::StateWarmingStatus;
 
 pub use self::client::Client;
 pub use self::config::ClientConfig;
 pub use self::error::Error;
+pub use self::import_timings::{
+    BlockImportTimings, ImportPhase, ImportTimings, ImportTimingsLog, PhaseTimer, IMPORT_TIMINGS_WINDOW,
+};
+pub use self::term_index::TermDetails;
 pub use self::test_client::TestBlockChainClient;
 
+use std::net::IpAddr;
 use std::ops::Range;
 use std::sync::Arc;
+use std::time::Duration;
 
 use ckey::{Address, PlatformAddress, Public};
 use cmerkle::Result as TrieResult;
 use cnetwork::NodeId;
 use cstate::{AssetScheme, FindActionHandler, OwnedAsset, StateResult, Text, TopLevelState, TopStateView};
-use ctypes::transaction::{AssetTransferInput, PartialHashing, ShardTransaction};
+use ctypes::transaction::{AssetTransferInput, PartialHashing, ShardTransaction, Transaction};
 use ctypes::{BlockNumber, CommonParams, ShardId};
 use cvm::ChainTimeInfo;
 use kvdb::KeyValueDB;
 use primitives::{Bytes, H160, H256, U256};
 
 use crate::block::{ClosedBlock, OpenBlock, SealedBlock};
+use crate::blockchain::{
+    ActionIndexBackfill, ActionIndexPage, ActionIndexPosition, AddressHistoryPage, AddressHistoryPosition,
+    CacheBudgetError, ReorgProtectionStatus, SkippedSealVerification,
+};
 use crate::blockchain_info::BlockChainInfo;
-use crate::consensus::EngineError;
+use crate::consensus::{
+    CandidateStatus, ConsensusFault, ConsensusStatus, DecodedSeal, EngineDescription, EngineError, RestoreStatus,
+    SignerEligibility, WeightedValidator,
+};
 use crate::encoded;
 use crate::error::{BlockImportError, Error as GenericError};
+use crate::miner::{
+    FeeFloorStatus, PendingQueue, PooledTransaction, RelayStatus, ScheduledTransaction, SignerDiagnosis,
+};
 use crate::transaction::{LocalizedTransaction, PendingSignedTransactions, SignedTransaction};
 use crate::types::{BlockId, BlockStatus, TransactionId, VerificationQueueInfo as BlockQueueInfo};
+use crate::verification::BlockRejection;
 
 /// Provides various blockchain information, like block header, chain state etc.
 pub trait BlockChainTrait {
@@ -89,10 +138,47 @@ pub trait BlockChainTrait {
 pub trait EngineInfo: Send + Sync {
     fn common_params(&self, block_id: BlockId) -> Option<CommonParams>;
     fn metadata_seq(&self, block_id: BlockId) -> Option<u64>;
+    /// Returns the blocks that activated the common params on the canonical chain, from the genesis block.
+    fn params_change_history(&self) -> Vec<ParamsActivation>;
     fn block_reward(&self, block_number: u64) -> u64;
     fn mining_reward(&self, block_number: u64) -> Option<u64>;
     fn recommended_confirmation(&self) -> u32;
     fn possible_authors(&self, block_number: Option<u64>) -> Result<Option<Vec<PlatformAddress>>, EngineError>;
+    /// Returns the validators that seal the given block, in the order of their indices in the seal.
+    fn validator_set(&self, block_id: BlockId) -> Option<Vec<WeightedValidator>>;
+    /// Makes the consensus engine leave the current view without waiting for the timeout.
+    fn force_next_view(&self) -> Result<(), EngineError>;
+    /// Makes the consensus engine stop signing while following the consensus.
+    fn demote_to_standby(&self) -> Result<(), EngineError>;
+    /// Makes the consensus engine start signing again. See `ConsensusEngine::promote_from_standby`.
+    fn promote_from_standby(&self, min_quiet_period: Option<Duration>) -> Result<(), EngineError>;
+    /// Makes the consensus engine misbehave on purpose. It fails unless the node is built with the fault injection.
+    fn set_consensus_fault(&self, fault: ConsensusFault) -> Result<(), EngineError>;
+    /// Returns None if the consensus engine doesn't have views.
+    fn consensus_status(&self) -> Option<ConsensusStatus>;
+    /// Returns None if the consensus engine doesn't keep a backup.
+    fn restore_status(&self) -> Option<RestoreStatus>;
+    /// Returns the statistics of the given number of the latest blocks, up to `MAX_BLOCK_PRODUCTION_WINDOW`.
+    fn block_production_stats(&self, window_blocks: u64) -> BlockProductionStats;
+    /// Returns the sum of the fees burnt up to the given block.
+    fn total_burnt(&self, block_id: BlockId) -> Option<u64>;
+    /// Returns None for the genesis block and for the engines that don't distribute the fees to the stakeholders.
+    fn block_reward_distribution(&self, block_number: u64) -> Option<BlockRewardDistribution>;
+    /// Returns None if the consensus engine doesn't know how to decode its seals.
+    fn decode_seal(&self, seal: &[Bytes]) -> Option<Result<DecodedSeal, String>>;
+    /// Returns None if the block is unknown.
+    fn block_finality(&self, hash: &H256) -> Option<BlockFinality>;
+    /// Describes the consensus engine with the latest common params.
+    fn engine_description(&self) -> EngineDescription;
+    /// Returns the sums of the blocks from `from` to `to`, both inclusive, or None if any of them is unknown.
+    /// The caller should limit the range to `MAX_STATISTICS_RANGE`, because every body in it is read.
+    fn range_statistics(&self, from: BlockNumber, to: BlockNumber) -> Option<RangeStatistics>;
+    /// Tells whether the engine signer can sign the next block.
+    /// Returns None if the node doesn't have the engine signer, or the engine doesn't have the stake.
+    fn signer_eligibility(&self) -> Option<SignerEligibility>;
+    /// Returns the candidates in the state of the block, with the reasons they were not elected at the last election.
+    /// Returns None if the block is unknown, or the engine doesn't have the stake.
+    fn candidates(&self, block_id: BlockId) -> Option<Vec<CandidateStatus>>;
 }
 
 /// Client facilities used by internally sealing Engines.
@@ -111,6 +197,14 @@ pub trait EngineClient: Sync + Send + BlockChainTrait + ImportBlock {
     /// Used in Tendermint, when going to the commit step.
     fn update_best_as_committed(&self, block_hash: H256);
 
+    /// Executes the proposal in the background without writing the result, so that its import reuses the result.
+    ///
+    /// Used in Tendermint, when a proposal of the current view is received.
+    fn execute_proposal(&self, block: Bytes);
+
+    /// Drops the results of the proposals executed in advance, except the given block's.
+    fn discard_executed_proposals(&self, keep: Option<H256>);
+
     fn get_kvdb(&self) -> Arc<KeyValueDB>;
 }
 
@@ -119,6 +213,8 @@ pub trait ConsensusClient: BlockChainClient + EngineClient + EngineInfo + TermIn
 pub trait TermInfo {
     fn last_term_finished_block_num(&self, id: BlockId) -> Option<BlockNumber>;
     fn current_term_id(&self, id: BlockId) -> Option<u64>;
+    /// Get the term that the block belongs to.
+    fn term_details(&self, block_number: BlockNumber) -> Option<TermDetails>;
 }
 
 /// Provides methods to access account info
@@ -197,6 +293,9 @@ pub trait ImportBlock {
     /// Import a block into the blockchain.
     fn import_block(&self, bytes: Bytes) -> Result<H256, BlockImportError>;
 
+    /// Import a block received from the peer. The rejection of the block is recorded against the peer.
+    fn import_block_from(&self, bytes: Bytes, peer: NodeId) -> Result<H256, BlockImportError>;
+
     /// Import a header into the blockchain
     fn import_header(&self, bytes: Bytes) -> Result<H256, BlockImportError>;
 
@@ -217,6 +316,30 @@ pub trait BlockChainClient: Sync + Send + AccountData + BlockChainTrait + Import
     /// Queue own transaction for importing
     fn queue_own_transaction(&self, transaction: SignedTransaction) -> Result<(), GenericError>;
 
+    /// Queue own transactions for importing only if all of them are valid.
+    /// The errors are given in the order of the transactions.
+    fn queue_own_transactions(&self, transactions: Vec<SignedTransaction>) -> Result<(), Vec<Option<GenericError>>>;
+
+    /// Queue the transaction submitted over RPC for importing.
+    /// `source` is the address of the RPC client if it is known.
+    fn queue_rpc_transaction(&self, transaction: SignedTransaction, source: Option<IpAddr>)
+        -> Result<(), GenericError>;
+
+    /// Queue the transactions submitted over RPC for importing only if all of them are valid.
+    fn queue_rpc_transactions(
+        &self,
+        transactions: Vec<SignedTransaction>,
+        source: Option<IpAddr>,
+    ) -> Result<(), Vec<Option<GenericError>>>;
+
+    /// Hold the transaction submitted over RPC until the clock of the node passes `not_before`, then queue it.
+    fn schedule_rpc_transaction(
+        &self,
+        transaction: SignedTransaction,
+        source: Option<IpAddr>,
+        not_before: u64,
+    ) -> Result<(), GenericError>;
+
     /// Queue transactions for importing.
     fn queue_transactions(&self, transactions: Vec<Bytes>, peer_id: NodeId);
 
@@ -226,6 +349,24 @@ pub trait BlockChainClient: Sync + Send + AccountData + BlockChainTrait + Import
     /// Get the count of all pending transactions currently in the mem_pool.
     fn count_pending_transactions(&self, range: Range<u64>) -> usize;
 
+    /// Diagnose why the transactions of the account are not included.
+    fn diagnose_signer(&self, address: &Address) -> SignerDiagnosis;
+
+    /// Get the fee floor that follows the load of the mem pool. None if it's not enabled.
+    fn fee_floor(&self) -> Option<FeeFloorStatus>;
+
+    /// Get the counters of the transaction relay.
+    fn relay_status(&self) -> RelayStatus;
+
+    /// Get the transactions of the account in the mem pool with their origins.
+    fn pending_transactions_by_signer(&self, address: &Address) -> Vec<PooledTransaction>;
+
+    /// Get the queue that holds the transaction in the mem pool.
+    fn pending_queue(&self, hash: &H256) -> Option<PendingQueue>;
+
+    /// List the transactions that wait for their `not_before`, in the order they become eligible.
+    fn scheduled_transactions(&self) -> Vec<ScheduledTransaction>;
+
     /// Check there are transactions which are allowed into the next block.
     fn is_pending_queue_empty(&self) -> bool;
 
@@ -256,6 +397,92 @@ pub trait BlockChainClient: Sync + Send + AccountData + BlockChainTrait + Import
     fn transaction_by_tracker(&self, tracker: &H256) -> Option<LocalizedTransaction>;
 
     fn error_hints_by_tracker(&self, tracker: &H256) -> Vec<(H256, Option<String>)>;
+
+    /// Get at most `limit` transactions of the address from the given position up to `to_block`.
+    /// Returns None if the index of the address histories is disabled.
+    fn address_history(
+        &self,
+        from: &AddressHistoryPosition,
+        to_block: BlockNumber,
+        limit: usize,
+    ) -> Option<AddressHistoryPage>;
+
+    /// Get at most `limit` transactions of the action type from the given position up to `to_block`.
+    /// The blocks that are not backfilled yet are missing.
+    /// Returns None if the index of the action types is disabled.
+    fn transactions_by_action_type(
+        &self,
+        from: &ActionIndexPosition,
+        to_block: BlockNumber,
+        limit: usize,
+    ) -> Option<ActionIndexPage>;
+
+    /// The progress of indexing the blocks imported before the index of the action types was enabled.
+    /// Returns None if the index is disabled.
+    fn action_index_backfill(&self) -> Option<ActionIndexBackfill>;
+
+    /// Get the status of the protection against deep reorgs.
+    fn reorg_protection_status(&self) -> ReorgProtectionStatus;
+
+    /// Clear the alert of the reorg protection, and allow the next reorg regardless of its depth.
+    fn override_reorg_protection(&self);
+
+    /// Stop importing blocks and producing proposals until `unfreeze_chain` is called.
+    /// It returns after the block being imported is committed.
+    fn freeze_chain(&self);
+
+    /// Import the blocks queued while frozen, and produce proposals again.
+    fn unfreeze_chain(&self);
+
+    /// True while the chain is frozen, or halted by a finality conflict.
+    fn is_chain_frozen(&self) -> bool;
+
+    /// The conflicting finalized blocks that halted the import and the sealing.
+    fn finality_conflict(&self) -> Option<FinalityConflict>;
+
+    /// Resume the import and the sealing halted by a finality conflict, and return the cleared conflict.
+    /// The chain stays frozen if it's frozen by `freeze_chain`.
+    fn clear_consensus_halt(&self) -> Option<FinalityConflict>;
+
+    /// The engine doesn't propose until the state cache is warmed after the start.
+    fn state_warming_status(&self) -> StateWarmingStatus;
+
+    /// The time spent in each phase of the recent block imports, from the newest block.
+    fn import_timings(&self, limit: usize) -> Vec<BlockImportTimings>;
+
+    /// The recent rejections of the blocks, from the newest one.
+    fn recent_rejections(&self, limit: usize) -> Vec<BlockRejection>;
+
+    /// Get the record if the seal of the block was not verified when it was imported.
+    fn skipped_seal_verification(&self, hash: &H256) -> Option<SkippedSealVerification>;
+
+    /// Verify the seal of the block regardless of how it was imported.
+    /// The record of the skipped verification is removed if the seal is valid.
+    /// Returns None if the block is unknown.
+    fn reverify_block_seal(&self, hash: &H256) -> Option<Result<(), GenericError>>;
+
+    /// Re-execute the canonical blocks from `from` to `to` on a scratch state, and compare the state roots
+    /// and the invoices with the stored ones. The live database is never modified.
+    /// Returns None if a block in the range or its parent is unknown.
+    fn replay_blocks(
+        &self,
+        from: BlockNumber,
+        to: BlockNumber,
+        options: &ReplayOptions,
+    ) -> Option<Result<ReplayReport, GenericError>>;
+
+    /// Re-execute the custom action of the transaction on a scratch state, and record the action data
+    /// that its action handler reads and writes. The live database is never modified.
+    /// Returns None if the transaction or its block is unknown.
+    fn trace_transaction(&self, target: &TraceTarget) -> Option<Result<ActionHandlerTrace, GenericError>>;
+
+    /// Start checking the canonical blocks from `from` to `to`, and the bodies without headers, in the background.
+    /// The anomalies found are repaired at the end if `repair` is given.
+    /// Returns false if another check is running.
+    fn start_integrity_check(&self, from: BlockNumber, to: BlockNumber, repair: Option<RepairOptions>) -> bool;
+
+    /// The progress of the running integrity check, or the result of the last one.
+    fn integrity_report(&self) -> Option<IntegrityReport>;
 }
 
 /// Result of import block operation.
@@ -268,6 +495,10 @@ pub trait BlockProducer {
 
     /// Returns OpenBlock prepared for closing.
     fn prepare_open_block(&self, parent_block: BlockId, author: Address, extra_data: Bytes) -> OpenBlock;
+
+    /// Keeps the execution of the closed block, so that the import of the block doesn't execute it again.
+    /// The hash of the block must not be changed after it's closed.
+    fn cache_closed_block(&self, parent_state_root: H256, block: &ClosedBlock);
 }
 
 /// Extended client interface used for mining
@@ -276,6 +507,12 @@ pub trait MiningBlockChainClient: BlockChainClient + BlockProducer + FindActionH
 /// Provides methods to access database.
 pub trait DatabaseClient {
     fn database(&self) -> Arc<KeyValueDB>;
+
+    /// The estimated size of each column, the compaction statistics and the statistics of the caches.
+    fn database_stats(&self) -> DatabaseStats;
+
+    /// Changes the budget of the cache in front of the column at runtime.
+    fn set_cache_budget(&self, column: Option<u32>, budget: usize) -> Result<(), CacheBudgetError>;
 }
 
 /// Provides methods to access asset
@@ -301,6 +538,19 @@ pub trait TextClient {
 pub trait ExecuteClient: ChainTimeInfo {
     fn execute_transaction(&self, transaction: &ShardTransaction, sender: &Address) -> StateResult<()>;
 
+    /// Execute the transaction on a scratch copy of the state of the block, as if it were included in the next block.
+    /// The live database is never modified.
+    /// `sender_public` is required for the actions that use the public key of the signer.
+    /// Returns None if the block is unknown.
+    fn dry_run_transaction(
+        &self,
+        tx: &Transaction,
+        sender: &Address,
+        sender_public: Option<&Public>,
+        block_id: BlockId,
+        options: &DryRunOptions,
+    ) -> Option<Result<DryRunResult, GenericError>>;
+
     fn execute_vm(
         &self,
         tx: &PartialHashing,
@@ -317,4 +567,7 @@ pub trait StateInfo {
     /// Otherwise, this can fail (but may not) if the DB prunes state or the block
     /// is unknown.
     fn state_at(&self, id: BlockId) -> Option<TopLevelState>;
+
+    /// Returns the trie nodes that prove the value of the key in the state of the block.
+    fn state_proof(&self, id: BlockId, key: &[u8]) -> Option<Vec<Bytes>>;
 }
@@ -0,0 +1,365 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use parking_lot::{Condvar, Mutex};
+use primitives::H256;
+
+use crate::block::LockedBlock;
+
+/// The number of the proposals whose execution results are kept.
+/// A proposal of the current view and the locked proposal are enough.
+pub const MAX_OPTIMISTIC_EXECUTIONS: usize = 2;
+
+/// The longest time that the import waits for the running execution.
+/// The import falls back to the execution of its own after that.
+pub const MAX_WAIT_FOR_EXECUTION: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OptimisticExecutionStats {
+    /// The number of the proposals executed before their import.
+    pub started: usize,
+    /// The number of the imports that used the results instead of executing the blocks again.
+    pub reused: usize,
+    /// The number of the results dropped without being imported.
+    pub discarded: usize,
+    /// The number of the results that didn't match the imported blocks, which were executed again.
+    pub rejected: usize,
+}
+
+enum Execution<B> {
+    /// Waiting for an IO worker. The import doesn't wait for it, since it may be queued behind the import.
+    Queued,
+    Running,
+    /// None if the execution failed. The importer executes the block again to report the error.
+    Finished(Option<B>),
+}
+
+struct Executions<B> {
+    executions: VecDeque<(H256, Execution<B>)>,
+    stats: OptimisticExecutionStats,
+    /// The number of the imports waiting for the running executions
+    waiting: usize,
+}
+
+impl<B> Executions<B> {
+    fn position(&self, hash: &H256) -> Option<usize> {
+        self.executions.iter().position(|(executed, _)| executed == hash)
+    }
+}
+
+/// The results of the proposals executed against the states of their parents before the proposals are imported.
+///
+/// The results are kept only in memory. Nothing is written to the database until the importer takes a result
+/// and commits it as the import of the block.
+pub struct OptimisticExecutions<B = LockedBlock> {
+    inner: Mutex<Executions<B>>,
+    finished: Condvar,
+}
+
+impl<B> Default for OptimisticExecutions<B> {
+    fn default() -> Self {
+        Self {
+            inner: Mutex::new(Executions {
+                executions: VecDeque::with_capacity(MAX_OPTIMISTIC_EXECUTIONS),
+                stats: Default::default(),
+                waiting: 0,
+            }),
+            finished: Condvar::new(),
+        }
+    }
+}
+
+impl<B> OptimisticExecutions<B> {
+    /// Marks the block as queued for the execution. The oldest result is dropped if there are too many.
+    /// Returns false if the block is already executed or being executed.
+    pub fn start(&self, hash: H256) -> bool {
+        let mut inner = self.inner.lock();
+        if inner.position(&hash).is_some() {
+            return false
+        }
+        if inner.executions.len() == MAX_OPTIMISTIC_EXECUTIONS {
+            inner.executions.pop_front();
+            inner.stats.discarded += 1;
+            self.finished.notify_all();
+        }
+        inner.executions.push_back((hash, Execution::Queued));
+        inner.stats.started += 1;
+        true
+    }
+
+    /// Marks the queued block as running.
+    /// Returns false if the block should not be executed, because it's dropped or taken by the import.
+    pub fn begin(&self, hash: &H256) -> bool {
+        let mut inner = self.inner.lock();
+        match inner.position(hash) {
+            Some(index) => match inner.executions[index].1 {
+                Execution::Queued => {
+                    inner.executions[index].1 = Execution::Running;
+                    true
+                }
+                _ => false,
+            },
+            None => false,
+        }
+    }
+
+    /// Stores the result of the execution unless it was discarded while running.
+    pub fn finish(&self, hash: &H256, result: Option<B>) {
+        let mut inner = self.inner.lock();
+        if let Some(index) = inner.position(hash) {
+            inner.executions[index].1 = Execution::Finished(result);
+        }
+        self.finished.notify_all();
+    }
+
+    /// Takes the result of the block, waiting for the execution up to `timeout` if it's running.
+    /// Returns None if the block is not executed, the execution failed or the result is not valid.
+    /// The queued execution and the execution running longer than `timeout` are dropped, so that the import
+    /// executes the block by itself.
+    /// The block hash decides the parent, so the result is always of the same parent state.
+    pub fn take<F: FnOnce(&B) -> bool>(&self, hash: &H256, timeout: Duration, is_valid: F) -> Option<B> {
+        let deadline = Instant::now() + timeout;
+        let mut inner = self.inner.lock();
+        loop {
+            let index = inner.position(hash)?;
+            if let Execution::Running = inner.executions[index].1 {
+                if Instant::now() < deadline {
+                    inner.waiting += 1;
+                    self.finished.wait_until(&mut inner, deadline);
+                    inner.waiting -= 1;
+                    continue
+                }
+            }
+            let (_, execution) = inner.executions.remove(index).expect("The index is found above");
+            return match execution {
+                Execution::Finished(Some(block)) => {
+                    if !is_valid(&block) {
+                        inner.stats.rejected += 1;
+                        return None
+                    }
+                    inner.stats.reused += 1;
+                    Some(block)
+                }
+                Execution::Finished(None) => None,
+                Execution::Queued | Execution::Running => {
+                    inner.stats.discarded += 1;
+                    None
+                }
+            }
+        }
+    }
+
+    /// Drops the results except the given block's.
+    pub fn discard_except(&self, keep: Option<&H256>) {
+        let mut inner = self.inner.lock();
+        let before = inner.executions.len();
+        inner.executions.retain(|(hash, _)| Some(hash) == keep);
+        let discarded = before - inner.executions.len();
+        if discarded != 0 {
+            inner.stats.discarded += discarded;
+            self.finished.notify_all();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().executions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.lock().executions.is_empty()
+    }
+
+    pub fn stats(&self) -> OptimisticExecutionStats {
+        self.inner.lock().stats
+    }
+
+    /// Waits until no execution is queued or running. Returns false if they are still running after `timeout`.
+    #[cfg(test)]
+    pub fn wait_until_idle(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut inner = self.inner.lock();
+        loop {
+            let is_idle = inner.executions.iter().all(|(_, execution)| match execution {
+                Execution::Finished(_) => true,
+                _ => false,
+            });
+            if is_idle {
+                return true
+            }
+            if self.finished.wait_until(&mut inner, deadline).timed_out() {
+                return false
+            }
+        }
+    }
+
+    #[cfg(test)]
+    fn num_waiting(&self) -> usize {
+        self.inner.lock().waiting
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::*;
+
+    /// Long enough not to time out in the tests, unless the execution never finishes.
+    const NO_TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// Starts the execution of the block, and lets it run.
+    fn run(executions: &OptimisticExecutions<u32>, hash: H256) {
+        assert!(executions.start(hash));
+        assert!(executions.begin(&hash));
+    }
+
+    /// Blocks until the import waits for the execution.
+    fn wait_for_the_import(executions: &OptimisticExecutions<u32>) {
+        while executions.num_waiting() == 0 {
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn the_oldest_result_is_dropped() {
+        let executions = OptimisticExecutions::<u32>::default();
+        for i in 0..=MAX_OPTIMISTIC_EXECUTIONS {
+            assert!(executions.start(H256::from(i as u64)));
+            executions.finish(&H256::from(i as u64), Some(i as u32));
+        }
+        assert_eq!(MAX_OPTIMISTIC_EXECUTIONS, executions.len());
+        assert_eq!(None, executions.take(&H256::from(0), NO_TIMEOUT, |_| true));
+        assert_eq!(Some(1), executions.take(&H256::from(1), NO_TIMEOUT, |_| true));
+        assert_eq!(
+            OptimisticExecutionStats {
+                started: MAX_OPTIMISTIC_EXECUTIONS + 1,
+                reused: 1,
+                discarded: 1,
+                rejected: 0,
+            },
+            executions.stats()
+        );
+    }
+
+    #[test]
+    fn the_same_block_is_executed_once() {
+        let executions = OptimisticExecutions::<u32>::default();
+        assert!(executions.start(H256::from(1)));
+        assert!(!executions.start(H256::from(1)));
+        assert_eq!(1, executions.stats().started);
+    }
+
+    #[test]
+    fn take_waits_for_the_running_execution() {
+        let executions = Arc::new(OptimisticExecutions::<u32>::default());
+        run(&executions, H256::from(1));
+        let executor = {
+            let executions = Arc::clone(&executions);
+            thread::spawn(move || {
+                wait_for_the_import(&executions);
+                executions.finish(&H256::from(1), Some(1));
+            })
+        };
+        assert_eq!(Some(1), executions.take(&H256::from(1), NO_TIMEOUT, |_| true));
+        assert_eq!(0, executions.len());
+        executor.join().unwrap();
+    }
+
+    #[test]
+    fn take_gives_up_the_discarded_execution() {
+        let executions = Arc::new(OptimisticExecutions::<u32>::default());
+        run(&executions, H256::from(1));
+        let discarder = {
+            let executions = Arc::clone(&executions);
+            thread::spawn(move || {
+                wait_for_the_import(&executions);
+                executions.discard_except(Some(&H256::from(2)));
+            })
+        };
+        assert_eq!(None, executions.take(&H256::from(1), NO_TIMEOUT, |_| true));
+        discarder.join().unwrap();
+
+        // The result of the discarded execution is not stored.
+        executions.finish(&H256::from(1), Some(1));
+        assert_eq!(0, executions.len());
+        assert_eq!(1, executions.stats().discarded);
+    }
+
+    #[test]
+    fn take_gives_up_the_execution_running_too_long() {
+        let executions = OptimisticExecutions::<u32>::default();
+        run(&executions, H256::from(1));
+        assert_eq!(None, executions.take(&H256::from(1), Duration::from_secs(0), |_| true));
+        assert_eq!(0, executions.len());
+        assert_eq!(1, executions.stats().discarded);
+
+        // The result of the execution that finishes late is not stored.
+        executions.finish(&H256::from(1), Some(1));
+        assert_eq!(0, executions.len());
+    }
+
+    #[test]
+    fn take_does_not_wait_for_the_queued_execution() {
+        let executions = OptimisticExecutions::<u32>::default();
+        assert!(executions.start(H256::from(1)));
+        // The IO worker that would run the execution may be the one importing the block.
+        assert_eq!(None, executions.take(&H256::from(1), NO_TIMEOUT, |_| true));
+        assert!(!executions.begin(&H256::from(1)));
+        assert_eq!(1, executions.stats().discarded);
+    }
+
+    #[test]
+    fn failed_execution_is_not_reused() {
+        let executions = OptimisticExecutions::<u32>::default();
+        executions.start(H256::from(1));
+        executions.finish(&H256::from(1), None);
+        assert_eq!(None, executions.take(&H256::from(1), NO_TIMEOUT, |_| true));
+        assert_eq!(0, executions.stats().reused);
+    }
+
+    #[test]
+    fn invalid_result_is_dropped_for_the_execution_again() {
+        let executions = OptimisticExecutions::<u32>::default();
+        executions.start(H256::from(1));
+        executions.finish(&H256::from(1), Some(1));
+        assert_eq!(None, executions.take(&H256::from(1), NO_TIMEOUT, |result| *result == 2));
+        assert_eq!(0, executions.len());
+        assert_eq!(
+            OptimisticExecutionStats {
+                started: 1,
+                reused: 0,
+                discarded: 0,
+                rejected: 1,
+            },
+            executions.stats()
+        );
+    }
+
+    #[test]
+    fn the_locked_block_is_kept() {
+        let executions = OptimisticExecutions::<u32>::default();
+        executions.start(H256::from(1));
+        executions.start(H256::from(2));
+        executions.discard_except(Some(&H256::from(2)));
+        assert_eq!(1, executions.len());
+        executions.finish(&H256::from(2), Some(2));
+        assert_eq!(Some(2), executions.take(&H256::from(2), NO_TIMEOUT, |_| true));
+    }
+}
@@ -0,0 +1,498 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use ctypes::{BlockNumber, CommonParams};
+use kvdb::{DBTransaction, KeyValueDB};
+use parking_lot::RwLock;
+use primitives::H256;
+use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
+
+use crate::db::COL_EXTRA;
+
+const PARAMS_HISTORY_KEY: &[u8] = b"params-history";
+
+/// The common params that took effect at a block.
+/// The state after the block has the params.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParamsActivation {
+    pub block_number: BlockNumber,
+    pub block_hash: H256,
+    /// The metadata seq of the state after the block.
+    pub metadata_seq: u64,
+    /// The ChangeParams transaction that changed the params.
+    /// None for the genesis params, and when the transaction cannot be found.
+    pub transaction_hash: Option<H256>,
+    pub params: CommonParams,
+}
+
+impl Encodable for ParamsActivation {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(5).append(&self.block_number).append(&self.block_hash).append(&self.metadata_seq);
+        match &self.transaction_hash {
+            Some(hash) => s.begin_list(1).append(hash),
+            None => s.begin_list(0),
+        };
+        s.append(&self.params);
+    }
+}
+
+impl Decodable for ParamsActivation {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 5 {
+            return Err(DecoderError::RlpInvalidLength {
+                got: item_count,
+                expected: 5,
+            })
+        }
+        let transaction_hash = rlp.at(3)?;
+        let transaction_hash = match transaction_hash.item_count()? {
+            0 => None,
+            1 => Some(transaction_hash.val_at(0)?),
+            got => {
+                return Err(DecoderError::RlpInvalidLength {
+                    got,
+                    expected: 1,
+                })
+            }
+        };
+        Ok(Self {
+            block_number: rlp.val_at(0)?,
+            block_hash: rlp.val_at(1)?,
+            metadata_seq: rlp.val_at(2)?,
+            transaction_hash,
+            params: rlp.val_at(4)?,
+        })
+    }
+}
+
+/// The canonical chain that the history is built from.
+pub trait ParamsHistorySource {
+    fn best_block_number(&self) -> BlockNumber;
+    /// Returns the hash of the canonical block.
+    fn canonical_hash(&self, block_number: BlockNumber) -> Option<H256>;
+    /// Returns None if the state of the block is not available.
+    fn metadata_seq_at(&self, block_number: BlockNumber) -> Option<u64>;
+    fn common_params_at(&self, block_number: BlockNumber) -> Option<CommonParams>;
+    /// Returns the succeeded ChangeParams transaction in the block that expects the given metadata seq.
+    fn change_params_transaction(&self, block_number: BlockNumber, metadata_seq: u64) -> Option<H256>;
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Index {
+    /// The activations on the canonical chain, from the genesis block.
+    activations: Vec<ParamsActivation>,
+    /// The last block that the index has searched.
+    tip: Option<(BlockNumber, H256)>,
+}
+
+impl Index {
+    fn load(db: &KeyValueDB) -> Self {
+        let encoded = match db.get(COL_EXTRA, PARAMS_HISTORY_KEY) {
+            Ok(Some(encoded)) => encoded,
+            Ok(None) => return Default::default(),
+            Err(err) => {
+                cwarn!(CLIENT, "Cannot read the history of the common params: {}", err);
+                return Default::default()
+            }
+        };
+        UntrustedRlp::new(&encoded).as_val().unwrap_or_else(|err| {
+            cwarn!(CLIENT, "The history of the common params is corrupted: {:?}", err);
+            Default::default()
+        })
+    }
+
+    /// Drops the activations and the tip that are not on the canonical chain anymore.
+    fn roll_back<S: ParamsHistorySource>(&mut self, source: &S) {
+        while let Some(last) = self.activations.last() {
+            if source.canonical_hash(last.block_number) == Some(last.block_hash) {
+                break
+            }
+            self.activations.pop();
+        }
+        if let Some((number, hash)) = self.tip {
+            if source.canonical_hash(number) != Some(hash) {
+                self.tip = self.activations.last().map(|last| (last.block_number, last.block_hash));
+            }
+        }
+    }
+
+    fn activate<S: ParamsHistorySource>(
+        &mut self,
+        source: &S,
+        block_number: BlockNumber,
+        metadata_seq: u64,
+    ) -> Option<()> {
+        let transaction_hash = if block_number == 0 {
+            None
+        } else {
+            source.change_params_transaction(block_number, metadata_seq - 1)
+        };
+        let activation = ParamsActivation {
+            block_number,
+            block_hash: source.canonical_hash(block_number)?,
+            metadata_seq,
+            transaction_hash,
+            params: source.common_params_at(block_number)?,
+        };
+        self.tip = Some((block_number, activation.block_hash));
+        self.activations.push(activation);
+        Some(())
+    }
+
+    /// Searches the blocks that increased the metadata seq from the tip to the best block.
+    fn roll_forward<S: ParamsHistorySource>(&mut self, source: &S) -> Option<()> {
+        if self.tip.is_none() {
+            let metadata_seq = source.metadata_seq_at(0)?;
+            self.activate(source, 0, metadata_seq)?;
+        }
+        let best_block_number = source.best_block_number();
+        let (mut low, _) = self.tip?;
+        let mut low_seq = source.metadata_seq_at(low)?;
+        loop {
+            let best_seq = source.metadata_seq_at(best_block_number)?;
+            if best_seq <= low_seq {
+                break
+            }
+            // The seq of `low` is `low_seq`, and the seq of `high` is greater than it.
+            let mut high = best_block_number;
+            while high - low > 1 {
+                let middle = low + (high - low) / 2;
+                if source.metadata_seq_at(middle)? > low_seq {
+                    high = middle;
+                } else {
+                    low = middle;
+                }
+            }
+            low_seq = source.metadata_seq_at(high)?;
+            low = high;
+            self.activate(source, high, low_seq)?;
+        }
+        self.tip = Some((best_block_number, source.canonical_hash(best_block_number)?));
+        Some(())
+    }
+}
+
+impl Encodable for Index {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(2).append_list(&self.activations);
+        match &self.tip {
+            Some((number, hash)) => s.begin_list(2).append(number).append(hash),
+            None => s.begin_list(0),
+        };
+    }
+}
+
+impl Decodable for Index {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 2 {
+            return Err(DecoderError::RlpInvalidLength {
+                got: item_count,
+                expected: 2,
+            })
+        }
+        let tip = rlp.at(1)?;
+        let tip = match tip.item_count()? {
+            0 => None,
+            2 => Some((tip.val_at(0)?, tip.val_at(1)?)),
+            got => {
+                return Err(DecoderError::RlpInvalidLength {
+                    got,
+                    expected: 2,
+                })
+            }
+        };
+        Ok(Self {
+            activations: rlp.list_at(0)?,
+            tip,
+        })
+    }
+}
+
+/// Remembers the blocks that activated the common params.
+///
+/// The index is built from the states of the canonical chain, so the first sync after an upgrade
+/// backfills it from the genesis block. The activations of the retracted blocks are rolled back.
+pub struct ParamsHistory {
+    index: RwLock<Index>,
+}
+
+impl ParamsHistory {
+    pub fn new(db: &KeyValueDB) -> Self {
+        Self {
+            index: RwLock::new(Index::load(db)),
+        }
+    }
+
+    /// Catches up with the canonical chain. It's called whenever the best block changes.
+    pub fn sync<S: ParamsHistorySource>(&self, source: &S, batch: &mut DBTransaction) {
+        let mut index = self.index.write();
+        let tip = index.tip;
+        let number_of_activations = index.activations.len();
+        index.roll_back(source);
+        if index.roll_forward(source).is_none() {
+            cdebug!(CLIENT, "The history of the common params stopped at {:?}", index.tip);
+        }
+        if index.tip != tip || index.activations.len() != number_of_activations {
+            batch.put(COL_EXTRA, PARAMS_HISTORY_KEY, &index.rlp_bytes());
+        }
+    }
+
+    /// Returns the activations on the canonical chain, from the oldest one.
+    pub fn activations(&self) -> Vec<ParamsActivation> {
+        self.index.read().activations.clone()
+    }
+
+    /// Returns the params in the state after the canonical block.
+    /// Returns None if the index hasn't reached the block yet.
+    pub fn params_at(&self, block_number: BlockNumber) -> Option<CommonParams> {
+        let index = self.index.read();
+        let (tip, _) = index.tip?;
+        if block_number > tip {
+            return None
+        }
+        index
+            .activations
+            .iter()
+            .rev()
+            .find(|activation| activation.block_number <= block_number)
+            .map(|activation| activation.params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use super::*;
+
+    /// A chain whose params are changed at the given blocks.
+    struct Chain {
+        hashes: Vec<H256>,
+        seqs: Vec<u64>,
+        transactions: HashMap<(BlockNumber, u64), H256>,
+    }
+
+    impl Chain {
+        fn new(length: usize) -> Self {
+            Self {
+                hashes: (0..length).map(|_| H256::random()).collect(),
+                seqs: vec![0; length],
+                transactions: HashMap::new(),
+            }
+        }
+
+        /// Changes the params at the block with a new transaction.
+        fn change_params(&mut self, block_number: BlockNumber) -> H256 {
+            let seq = self.seqs[block_number as usize];
+            for later in &mut self.seqs[block_number as usize..] {
+                *later += 1;
+            }
+            let hash = H256::random();
+            self.transactions.insert((block_number, seq), hash);
+            hash
+        }
+
+        /// Replaces the blocks from the given number with the new ones.
+        fn reorg(&mut self, from: BlockNumber, length: usize) {
+            let from = from as usize;
+            let seq = self.seqs[from - 1];
+            self.hashes.truncate(from);
+            self.seqs.truncate(from);
+            self.transactions.retain(|(number, _), _| (*number as usize) < from);
+            self.hashes.extend((from..length).map(|_| H256::random()));
+            self.seqs.resize(length, seq);
+        }
+    }
+
+    fn params_of(seq: u64) -> CommonParams {
+        let mut params = CommonParams::default_for_test();
+        params.set_max_body_size_for_test(seq as usize + 1);
+        params
+    }
+
+    impl ParamsHistorySource for Chain {
+        fn best_block_number(&self) -> BlockNumber {
+            self.hashes.len() as BlockNumber - 1
+        }
+
+        fn canonical_hash(&self, block_number: BlockNumber) -> Option<H256> {
+            self.hashes.get(block_number as usize).cloned()
+        }
+
+        fn metadata_seq_at(&self, block_number: BlockNumber) -> Option<u64> {
+            self.seqs.get(block_number as usize).cloned()
+        }
+
+        fn common_params_at(&self, block_number: BlockNumber) -> Option<CommonParams> {
+            self.metadata_seq_at(block_number).map(params_of)
+        }
+
+        fn change_params_transaction(&self, block_number: BlockNumber, metadata_seq: u64) -> Option<H256> {
+            self.transactions.get(&(block_number, metadata_seq)).cloned()
+        }
+    }
+
+    fn sync(history: &ParamsHistory, chain: &Chain, db: &KeyValueDB) {
+        let mut batch = DBTransaction::new();
+        history.sync(chain, &mut batch);
+        db.write(batch).unwrap();
+    }
+
+    fn max_body_size_at(history: &ParamsHistory, block_number: BlockNumber) -> Option<usize> {
+        history.params_at(block_number).map(|params| params.max_body_size())
+    }
+
+    #[test]
+    fn params_on_every_side_of_the_activations() {
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap()));
+        let mut chain = Chain::new(100);
+        let first = chain.change_params(30);
+        let second = chain.change_params(70);
+
+        let history = ParamsHistory::new(db.as_ref());
+        sync(&history, &chain, db.as_ref());
+
+        let activations = history.activations();
+        assert_eq!(vec![0, 30, 70], activations.iter().map(|activation| activation.block_number).collect::<Vec<_>>());
+        assert_eq!(
+            vec![None, Some(first), Some(second)],
+            activations.iter().map(|activation| activation.transaction_hash).collect::<Vec<_>>()
+        );
+        assert_eq!(vec![0, 1, 2], activations.iter().map(|activation| activation.metadata_seq).collect::<Vec<_>>());
+
+        for (block_number, max_body_size) in &[(0, 1), (29, 1), (30, 2), (31, 2), (69, 2), (70, 3), (71, 3), (99, 3)] {
+            assert_eq!(Some(*max_body_size), max_body_size_at(&history, *block_number), "block {}", block_number);
+        }
+        assert_eq!(None, max_body_size_at(&history, 100));
+    }
+
+    #[test]
+    fn history_is_kept_in_the_db() {
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap()));
+        let mut chain = Chain::new(100);
+        chain.change_params(30);
+        chain.change_params(70);
+
+        let history = ParamsHistory::new(db.as_ref());
+        sync(&history, &chain, db.as_ref());
+
+        let restarted = ParamsHistory::new(db.as_ref());
+        assert_eq!(*history.index.read(), *restarted.index.read());
+    }
+
+    #[test]
+    fn new_activations_are_appended() {
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap()));
+        let mut chain = Chain::new(50);
+        chain.change_params(30);
+        let history = ParamsHistory::new(db.as_ref());
+        sync(&history, &chain, db.as_ref());
+
+        chain.reorg(50, 100);
+        chain.change_params(70);
+        sync(&history, &chain, db.as_ref());
+        assert_eq!(
+            vec![0, 30, 70],
+            history.activations().iter().map(|activation| activation.block_number).collect::<Vec<_>>()
+        );
+        assert_eq!(Some(2), max_body_size_at(&history, 69));
+        assert_eq!(Some(3), max_body_size_at(&history, 70));
+    }
+
+    #[test]
+    fn two_changes_in_a_block_are_one_activation() {
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap()));
+        let mut chain = Chain::new(100);
+        chain.change_params(30);
+        let second = chain.change_params(30);
+        let history = ParamsHistory::new(db.as_ref());
+        sync(&history, &chain, db.as_ref());
+
+        let activations = history.activations();
+        assert_eq!(2, activations.len());
+        assert_eq!(2, activations[1].metadata_seq);
+        assert_eq!(Some(second), activations[1].transaction_hash);
+    }
+
+    #[test]
+    fn reorg_across_an_activation_rolls_it_back() {
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap()));
+        let mut chain = Chain::new(100);
+        chain.change_params(30);
+        chain.change_params(70);
+        let history = ParamsHistory::new(db.as_ref());
+        sync(&history, &chain, db.as_ref());
+
+        // The new chain changes the params at 60 instead of 70.
+        chain.reorg(50, 90);
+        let replaced = chain.change_params(60);
+        sync(&history, &chain, db.as_ref());
+
+        let activations = history.activations();
+        assert_eq!(vec![0, 30, 60], activations.iter().map(|activation| activation.block_number).collect::<Vec<_>>());
+        assert_eq!(Some(replaced), activations[2].transaction_hash);
+        assert_eq!(Some(2), max_body_size_at(&history, 59));
+        assert_eq!(Some(3), max_body_size_at(&history, 60));
+        assert_eq!(None, max_body_size_at(&history, 90));
+
+        // The new chain doesn't change the params after 30.
+        chain.reorg(20, 40);
+        chain.change_params(25);
+        sync(&history, &chain, db.as_ref());
+        assert_eq!(
+            vec![0, 25],
+            history.activations().iter().map(|activation| activation.block_number).collect::<Vec<_>>()
+        );
+        assert_eq!(Some(1), max_body_size_at(&history, 24));
+        assert_eq!(Some(2), max_body_size_at(&history, 39));
+    }
+
+    #[test]
+    fn sync_stops_where_the_states_are_missing() {
+        struct Pruned(Chain);
+        impl ParamsHistorySource for Pruned {
+            fn best_block_number(&self) -> BlockNumber {
+                self.0.best_block_number()
+            }
+
+            fn canonical_hash(&self, block_number: BlockNumber) -> Option<H256> {
+                self.0.canonical_hash(block_number)
+            }
+
+            fn metadata_seq_at(&self, _block_number: BlockNumber) -> Option<u64> {
+                None
+            }
+
+            fn common_params_at(&self, _block_number: BlockNumber) -> Option<CommonParams> {
+                None
+            }
+
+            fn change_params_transaction(&self, block_number: BlockNumber, metadata_seq: u64) -> Option<H256> {
+                self.0.change_params_transaction(block_number, metadata_seq)
+            }
+        }
+
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap()));
+        let history = ParamsHistory::new(db.as_ref());
+        sync(&history, &Pruned(Chain::new(10)), db.as_ref());
+        assert_eq!(Vec::<ParamsActivation>::new(), history.activations());
+        assert_eq!(None, history.params_at(0));
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, HashSet};
+
+use ckey::{public_to_address, Address};
+
+use crate::transaction::UnverifiedTransaction;
+
+/// The maximum number of blocks that the statistics of a range cover.
+pub const MAX_STATISTICS_RANGE: u64 = 10_000;
+
+/// The sums of the blocks in a range.
+#[derive(Debug, Default, PartialEq)]
+pub struct RangeStatistics {
+    pub blocks: u64,
+    /// The number of the transactions of each action type
+    pub transactions: BTreeMap<&'static str, u64>,
+    pub total_fee: u64,
+    pub total_block_reward: u64,
+    pub unique_signers: u64,
+}
+
+/// Adds the blocks one by one, so only the transactions of a block are kept at once.
+#[derive(Default)]
+pub struct RangeStatisticsBuilder {
+    statistics: RangeStatistics,
+    signers: HashSet<Address>,
+}
+
+impl RangeStatisticsBuilder {
+    pub fn add_block(&mut self, transactions: &[UnverifiedTransaction], block_reward: u64) {
+        let statistics = &mut self.statistics;
+        statistics.blocks += 1;
+        statistics.total_block_reward += block_reward;
+        for tx in transactions {
+            *statistics.transactions.entry(tx.action.name()).or_insert(0) += 1;
+            statistics.total_fee += tx.fee;
+            // The transactions in the imported blocks are already verified.
+            if let Ok(public) = tx.recover_public() {
+                self.signers.insert(public_to_address(&public));
+            }
+        }
+    }
+
+    pub fn build(self) -> RangeStatistics {
+        RangeStatistics {
+            unique_signers: self.signers.len() as u64,
+            ..self.statistics
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ckey::{Generator, KeyPair, Random};
+    use ctypes::transaction::{Action, Transaction};
+    use rand::Rng;
+
+    use super::*;
+    use crate::transaction::SignedTransaction;
+
+    fn random_transaction(signers: &[KeyPair]) -> UnverifiedTransaction {
+        let mut rng = rand::thread_rng();
+        let action = match rng.gen_range(0, 3) {
+            0 => Action::Pay {
+                receiver: Address::random(),
+                quantity: rng.gen_range(0, 1000),
+            },
+            1 => Action::SetRegularKey {
+                key: *Random.generate().unwrap().public(),
+            },
+            _ => Action::Custom {
+                handler_id: 2,
+                bytes: vec![rng.gen()],
+            },
+        };
+        let tx = Transaction {
+            seq: rng.gen_range(0, 100),
+            fee: rng.gen_range(10, 1000),
+            network_id: "tc".into(),
+            action,
+        };
+        let signer = &signers[rng.gen_range(0, signers.len())];
+        SignedTransaction::new_with_sign(tx, signer.private()).into()
+    }
+
+    #[test]
+    fn statistics_are_the_sums_over_all_transactions() {
+        let mut rng = rand::thread_rng();
+        let signers: Vec<_> = (0..5).map(|_| Random.generate().unwrap()).collect();
+        let blocks: Vec<(Vec<UnverifiedTransaction>, u64)> = (0..50)
+            .map(|_| {
+                let transactions = (0..rng.gen_range(0, 5)).map(|_| random_transaction(&signers)).collect();
+                (transactions, rng.gen_range(0, 100))
+            })
+            .collect();
+
+        let mut builder = RangeStatisticsBuilder::default();
+        for (transactions, block_reward) in &blocks {
+            builder.add_block(transactions, *block_reward);
+        }
+        let statistics = builder.build();
+
+        let all: Vec<_> = blocks.iter().flat_map(|(transactions, _)| transactions).collect();
+        assert_eq!(50, statistics.blocks);
+        assert_eq!(blocks.iter().map(|(_, block_reward)| block_reward).sum::<u64>(), statistics.total_block_reward);
+        assert_eq!(all.iter().map(|tx| tx.fee).sum::<u64>(), statistics.total_fee);
+        for name in &["pay", "setRegularKey", "custom"] {
+            let count = all.iter().filter(|tx| tx.action.name() == *name).count() as u64;
+            assert_eq!(count, statistics.transactions.get(name).cloned().unwrap_or(0));
+        }
+        assert_eq!(all.len() as u64, statistics.transactions.values().sum::<u64>());
+        let mut senders: Vec<_> = all.iter().map(|tx| public_to_address(&tx.recover_public().unwrap())).collect();
+        senders.sort();
+        senders.dedup();
+        assert_eq!(senders.len() as u64, statistics.unique_signers);
+    }
+
+    #[test]
+    fn statistics_of_empty_blocks() {
+        let mut builder = RangeStatisticsBuilder::default();
+        builder.add_block(&[], 10);
+        builder.add_block(&[], 10);
+        assert_eq!(
+            RangeStatistics {
+                blocks: 2,
+                total_block_reward: 20,
+                ..Default::default()
+            },
+            builder.build()
+        );
+    }
+}
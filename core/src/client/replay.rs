@@ -0,0 +1,91 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use std::collections::HashSet;
+
+use ctypes::BlockNumber;
+use primitives::H256;
+
+/// The maximum number of the blocks replayed at once.
+pub const MAX_REPLAY_BLOCKS: u64 = 1000;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReplayOptions {
+    /// The ids of the action handlers to disable. Their custom actions fail and their `on_close_block` are skipped.
+    pub disabled_action_handlers: HashSet<u64>,
+}
+
+/// The result of a transaction in the replayed block.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayedTransaction {
+    pub hash: H256,
+    /// The error hint stored when the block was imported. None if the transaction succeeded.
+    pub expected_error: Option<String>,
+    /// The error hint of the replay. None if the transaction succeeded.
+    pub actual_error: Option<String>,
+}
+
+impl ReplayedTransaction {
+    pub fn diverges(&self) -> bool {
+        self.expected_error != self.actual_error
+    }
+}
+
+/// The first replayed block whose state root or invoices differ from the stored ones.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockDivergence {
+    pub block_number: BlockNumber,
+    pub block_hash: H256,
+    pub expected_state_root: H256,
+    pub actual_state_root: H256,
+    /// The index of the first transaction whose invoice differs.
+    pub transaction_index: Option<usize>,
+    /// The transactions in the order of the block.
+    pub transactions: Vec<ReplayedTransaction>,
+}
+
+impl BlockDivergence {
+    /// Returns None if the replay matches the stored block.
+    pub fn check(
+        block_number: BlockNumber,
+        block_hash: H256,
+        expected_state_root: H256,
+        actual_state_root: H256,
+        transactions: Vec<ReplayedTransaction>,
+    ) -> Option<Self> {
+        let transaction_index = transactions.iter().position(ReplayedTransaction::diverges);
+        if expected_state_root == actual_state_root && transaction_index.is_none() {
+            return None
+        }
+        Some(Self {
+            block_number,
+            block_hash,
+            expected_state_root,
+            actual_state_root,
+            transaction_index,
+            transactions,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayReport {
+    /// The number of the replayed blocks, including the diverged one.
+    pub replayed_blocks: u64,
+    /// The replay stops at the first divergence.
+    pub divergence: Option<BlockDivergence>,
+}
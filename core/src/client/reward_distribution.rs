@@ -0,0 +1,59 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, HashMap};
+
+use ckey::Address;
+
+use crate::consensus::stake;
+
+/// How the reward and the fees of a block were paid.
+#[derive(Debug, PartialEq)]
+pub struct BlockRewardDistribution {
+    pub author: Address,
+    pub block_reward: u64,
+    /// The sum of the fees paid by the transactions in the block.
+    pub total_fee: u64,
+    /// The sum of the minimum fees of the transactions in the block.
+    pub total_min_fee: u64,
+    /// The part of the minimum fees that was removed from the supply.
+    pub burnt: u64,
+    pub stakeholders: BTreeMap<Address, u64>,
+    pub author_reward: u64,
+}
+
+impl BlockRewardDistribution {
+    pub fn new(
+        author: Address,
+        block_reward: u64,
+        total_fee: u64,
+        total_min_fee: u64,
+        fee_burn_permille: u64,
+        stakes: &HashMap<Address, u64>,
+    ) -> Self {
+        let distribution =
+            stake::distribute_rewards(block_reward + total_fee, total_min_fee, fee_burn_permille, stakes);
+        Self {
+            author,
+            block_reward,
+            total_fee,
+            total_min_fee,
+            burnt: distribution.burnt,
+            stakeholders: distribution.shares.into_iter().collect(),
+            author_reward: distribution.author_reward,
+        }
+    }
+}
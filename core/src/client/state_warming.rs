@@ -0,0 +1,283 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use cstate::{HotKey, TopLevelState};
+use ctypes::BlockNumber;
+use kvdb::{DBTransaction, KeyValueDB};
+use parking_lot::Mutex;
+use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
+
+use crate::db::COL_EXTRA;
+
+const HOT_KEY_LOG_KEY: &[u8] = b"hot-state-keys";
+/// The keys touched by this many latest blocks are kept.
+pub const HOT_KEY_BLOCKS: usize = 32;
+/// The keys are written to the database once per this many best blocks, and at the shutdown.
+const HOT_KEY_SAVE_INTERVAL: usize = 16;
+
+/// The progress of loading the state items that the latest blocks touched into the cache.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StateWarmingStatus {
+    pub loaded: usize,
+    pub total: usize,
+    pub finished: bool,
+}
+
+/// The keys of the state items that the latest best blocks touched.
+#[derive(Debug, Default, PartialEq)]
+struct HotKeyLog {
+    /// From the oldest block to the newest one
+    blocks: VecDeque<(BlockNumber, Vec<HotKey>)>,
+}
+
+impl HotKeyLog {
+    fn load(db: &KeyValueDB) -> Self {
+        let encoded = match db.get(COL_EXTRA, HOT_KEY_LOG_KEY) {
+            Ok(Some(encoded)) => encoded,
+            Ok(None) => return Default::default(),
+            Err(err) => {
+                cwarn!(CLIENT, "Cannot read the hot state keys: {}", err);
+                return Default::default()
+            }
+        };
+        UntrustedRlp::new(&encoded).as_val().unwrap_or_else(|err| {
+            cwarn!(CLIENT, "The hot state keys are corrupted: {:?}", err);
+            Default::default()
+        })
+    }
+
+    fn record(&mut self, number: BlockNumber, keys: Vec<HotKey>) {
+        // The retracted blocks are replaced.
+        while self.blocks.back().map_or(false, |(last, _)| *last >= number) {
+            self.blocks.pop_back();
+        }
+        self.blocks.push_back((number, keys));
+        while self.blocks.len() > HOT_KEY_BLOCKS {
+            self.blocks.pop_front();
+        }
+    }
+
+    /// Returns at most `max_entries` keys, the ones touched by the newer blocks first.
+    fn keys(&self, max_entries: usize) -> Vec<HotKey> {
+        let mut seen = HashSet::new();
+        self.blocks
+            .iter()
+            .rev()
+            .flat_map(|(_, keys)| keys.iter())
+            .filter(|key| seen.insert(**key))
+            .take(max_entries)
+            .cloned()
+            .collect()
+    }
+}
+
+impl Encodable for HotKeyLog {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(self.blocks.len());
+        for (number, keys) in &self.blocks {
+            s.begin_list(2).append(number).append_list(keys);
+        }
+    }
+}
+
+impl Decodable for HotKeyLog {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        let blocks = rlp
+            .iter()
+            .map(|block| {
+                let item_count = block.item_count()?;
+                if item_count != 2 {
+                    return Err(DecoderError::RlpInvalidLength {
+                        got: item_count,
+                        expected: 2,
+                    })
+                }
+                Ok((block.val_at(0)?, block.list_at(1)?))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            blocks,
+        })
+    }
+}
+
+/// Records the state items that the best blocks touch, and loads them into the cache after restarts,
+/// so that the first blocks after a restart aren't verified with an empty cache.
+pub struct StateWarming {
+    log: Mutex<HotKeyLog>,
+    /// The number of the best blocks recorded after the log was written last
+    unsaved: AtomicUsize,
+    loaded: AtomicUsize,
+    total: AtomicUsize,
+    finished: AtomicBool,
+}
+
+impl StateWarming {
+    pub fn new(db: &KeyValueDB) -> Self {
+        Self {
+            log: Mutex::new(HotKeyLog::load(db)),
+            unsaved: AtomicUsize::new(0),
+            loaded: AtomicUsize::new(0),
+            total: AtomicUsize::new(0),
+            finished: AtomicBool::new(false),
+        }
+    }
+
+    /// Must be called when the block becomes the best block.
+    /// Returns true if it's time to save the log.
+    pub fn record(&self, number: BlockNumber, keys: Vec<HotKey>) -> bool {
+        self.log.lock().record(number, keys);
+        self.unsaved.fetch_add(1, Ordering::SeqCst) + 1 >= HOT_KEY_SAVE_INTERVAL
+    }
+
+    /// Writes the log if any block is recorded after it was written last. Returns false if there's nothing to write.
+    pub fn save(&self, batch: &mut DBTransaction) -> bool {
+        let log = self.log.lock();
+        if self.unsaved.swap(0, Ordering::SeqCst) == 0 {
+            return false
+        }
+        batch.put(COL_EXTRA, HOT_KEY_LOG_KEY, &log.rlp_bytes());
+        true
+    }
+
+    pub fn hot_keys(&self, max_entries: usize) -> Vec<HotKey> {
+        self.log.lock().keys(max_entries)
+    }
+
+    /// Reads the items of the keys into the cache of the state until the timeout, and returns how many are read.
+    pub fn load(&self, state: &TopLevelState, keys: &[HotKey], timeout: Duration) -> usize {
+        let started_at = Instant::now();
+        self.total.store(keys.len(), Ordering::SeqCst);
+        for key in keys {
+            if started_at.elapsed() > timeout {
+                cinfo!(CLIENT, "Warming the state cache timed out");
+                break
+            }
+            if let Err(err) = state.load_hot_key(key) {
+                cwarn!(CLIENT, "Cannot load {:?} into the state cache: {}", key, err);
+                break
+            }
+            self.loaded.fetch_add(1, Ordering::SeqCst);
+        }
+        self.loaded.load(Ordering::SeqCst)
+    }
+
+    pub fn finish(&self) {
+        self.finished.store(true, Ordering::SeqCst);
+    }
+
+    pub fn status(&self) -> StateWarmingStatus {
+        StateWarmingStatus {
+            loaded: self.loaded.load(Ordering::SeqCst),
+            total: self.total.load(Ordering::SeqCst),
+            finished: self.finished.load(Ordering::SeqCst),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ckey::Address;
+    use cstate::{RegularAccountAddress, ShardAddress};
+    use primitives::H256;
+
+    use super::*;
+
+    fn keys_of(number: BlockNumber) -> Vec<HotKey> {
+        vec![HotKey::Account(Address::from(number)), HotKey::ActionData(H256::from(number))]
+    }
+
+    #[test]
+    fn hot_keys_round_trip_through_the_db() {
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap()));
+        let warming = StateWarming::new(db.as_ref());
+        let keys = vec![
+            HotKey::Account(Address::random()),
+            HotKey::RegularAccount(RegularAccountAddress::from_address(&Address::random())),
+            HotKey::Shard(ShardAddress::new(0)),
+            HotKey::ActionData(H256::random()),
+        ];
+        assert!(!warming.record(1, keys.clone()));
+        let mut batch = DBTransaction::new();
+        assert!(warming.save(&mut batch));
+        db.write(batch).unwrap();
+        assert!(!warming.save(&mut DBTransaction::new()));
+
+        let restarted = StateWarming::new(db.as_ref());
+        assert_eq!(keys, restarted.hot_keys(100));
+        assert_eq!(*warming.log.lock(), *restarted.log.lock());
+    }
+
+    #[test]
+    fn hot_keys_are_saved_once_per_interval() {
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap()));
+        let warming = StateWarming::new(db.as_ref());
+        for number in 1..HOT_KEY_SAVE_INTERVAL as u64 {
+            assert!(!warming.record(number, keys_of(number)));
+        }
+        assert!(warming.record(HOT_KEY_SAVE_INTERVAL as u64, keys_of(HOT_KEY_SAVE_INTERVAL as u64)));
+        let mut batch = DBTransaction::new();
+        assert!(warming.save(&mut batch));
+        db.write(batch).unwrap();
+        assert!(!warming.record(HOT_KEY_SAVE_INTERVAL as u64 + 1, keys_of(HOT_KEY_SAVE_INTERVAL as u64 + 1)));
+
+        let restarted = StateWarming::new(db.as_ref());
+        assert_eq!(Some(HOT_KEY_SAVE_INTERVAL as u64), restarted.log.lock().blocks.back().map(|(number, _)| *number));
+    }
+
+    #[test]
+    fn only_the_latest_blocks_are_kept() {
+        let mut log = HotKeyLog::default();
+        for number in 1..=(HOT_KEY_BLOCKS as u64 + 10) {
+            log.record(number, keys_of(number));
+        }
+        assert_eq!(HOT_KEY_BLOCKS, log.blocks.len());
+        assert_eq!(Some(11), log.blocks.front().map(|(number, _)| *number));
+    }
+
+    #[test]
+    fn retracted_blocks_are_replaced() {
+        let mut log = HotKeyLog::default();
+        log.record(1, keys_of(1));
+        log.record(2, keys_of(2));
+        log.record(3, keys_of(3));
+        log.record(2, keys_of(20));
+        assert_eq!(vec![1, 2], log.blocks.iter().map(|(number, _)| *number).collect::<Vec<_>>());
+        assert_eq!(keys_of(20), log.blocks[1].1);
+    }
+
+    #[test]
+    fn keys_of_newer_blocks_come_first_without_duplicates() {
+        let mut log = HotKeyLog::default();
+        log.record(1, keys_of(1));
+        log.record(2, vec![HotKey::Account(Address::from(1)), HotKey::Account(Address::from(2))]);
+        assert_eq!(
+            vec![
+                HotKey::Account(Address::from(1)),
+                HotKey::Account(Address::from(2)),
+                HotKey::ActionData(H256::from(1))
+            ],
+            log.keys(100)
+        );
+        assert_eq!(vec![HotKey::Account(Address::from(1))], log.keys(1));
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use ctypes::BlockNumber;
+use parking_lot::RwLock;
+
+/// The term that a block belongs to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TermDetails {
+    pub id: u64,
+    pub first_block_number: BlockNumber,
+    /// None if the term is not closed yet.
+    pub last_block_number: Option<BlockNumber>,
+    /// The term_seconds in effect at the start of the term.
+    pub term_seconds: u64,
+    /// The term is closed by the first block whose timestamp is not in [start, end).
+    /// None if term_seconds is zero, because the term never closes.
+    pub timestamp_window: Option<(u64, u64)>,
+}
+
+/// Remembers the blocks that closed the terms.
+///
+/// The blocks are recorded when they are imported.
+/// The blocks that closed the terms before the node started are searched from the states on demand.
+#[derive(Default)]
+pub struct TermIndex {
+    /// The term id to the number of the last block of the term
+    closing_blocks: RwLock<BTreeMap<u64, BlockNumber>>,
+}
+
+impl TermIndex {
+    pub fn on_term_closed(&self, term_id: u64, block_number: BlockNumber) {
+        self.closing_blocks.write().insert(term_id, block_number);
+    }
+
+    /// Must be called when blocks are retracted, because the terms might have been closed by them.
+    pub fn clear(&self) {
+        self.closing_blocks.write().clear();
+    }
+
+    /// Returns the id, the first and the last block numbers of the term that the block belongs to.
+    /// `metadata_at` returns the last term finished block number and the current term id in the state of the block.
+    pub fn boundaries<F>(
+        &self,
+        block_number: BlockNumber,
+        best_block_number: BlockNumber,
+        metadata_at: F,
+    ) -> Option<(u64, BlockNumber, Option<BlockNumber>)>
+    where
+        F: Fn(BlockNumber) -> Option<(BlockNumber, u64)>, {
+        if block_number > best_block_number {
+            return None
+        }
+        let (last_term_finished_block_num, current_term_id) = metadata_at(block_number)?;
+        if current_term_id != 0 && last_term_finished_block_num == block_number {
+            // The block closed the term.
+            let term_id = current_term_id - 1;
+            let first_block_number = if term_id == 0 {
+                0
+            } else {
+                metadata_at(block_number - 1)?.0 + 1
+            };
+            self.on_term_closed(term_id, block_number);
+            return Some((term_id, first_block_number, Some(block_number)))
+        }
+
+        let first_block_number = if current_term_id == 0 {
+            0
+        } else {
+            last_term_finished_block_num + 1
+        };
+        let last_block_number = self.closing_block(current_term_id, block_number, best_block_number, &metadata_at);
+        Some((current_term_id, first_block_number, last_block_number))
+    }
+
+    fn closing_block<F>(
+        &self,
+        term_id: u64,
+        block_in_term: BlockNumber,
+        best_block_number: BlockNumber,
+        metadata_at: &F,
+    ) -> Option<BlockNumber>
+    where
+        F: Fn(BlockNumber) -> Option<(BlockNumber, u64)>, {
+        if let Some(block_number) = self.closing_blocks.read().get(&term_id) {
+            return Some(*block_number)
+        }
+        let (_, best_term_id) = metadata_at(best_block_number)?;
+        if best_term_id <= term_id {
+            // The term is still open.
+            return None
+        }
+
+        // The term of `low` is the given term, and the term of `high` is a later one.
+        let mut low = block_in_term;
+        let mut high = best_block_number;
+        while high - low > 1 {
+            let middle = low + (high - low) / 2;
+            let (_, term_id_at_middle) = metadata_at(middle)?;
+            if term_id_at_middle > term_id {
+                high = middle;
+            } else {
+                low = middle;
+            }
+        }
+        self.on_term_closed(term_id, high);
+        Some(high)
+    }
+}
+
+/// Returns the timestamps that the blocks of a term can have until the term is closed.
+/// `previous_timestamp` is the timestamp of the block right before the term.
+pub fn timestamp_window(term_seconds: u64, previous_timestamp: u64) -> Option<(u64, u64)> {
+    if term_seconds == 0 {
+        return None
+    }
+    let period = previous_timestamp / term_seconds;
+    Some((period * term_seconds, (period + 1) * term_seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TERM_SECONDS: u64 = 10;
+
+    /// Builds the term metadata of each block in the same way as the engines do.
+    fn metadata_of(timestamps: &[u64]) -> Vec<(BlockNumber, u64)> {
+        let mut metadata = vec![(0, 0)];
+        for number in 1..timestamps.len() {
+            let (last_term_finished_block_num, current_term_id) = metadata[number - 1];
+            if timestamps[number] / TERM_SECONDS != timestamps[number - 1] / TERM_SECONDS {
+                metadata.push((number as BlockNumber, current_term_id + 1));
+            } else {
+                metadata.push((last_term_finished_block_num, current_term_id));
+            }
+        }
+        metadata
+    }
+
+    #[test]
+    fn blocks_on_the_division_edge_close_the_terms() {
+        // Block 3 and 6 land exactly on the edges, and block 7 skips a whole period.
+        let timestamps = [0, 5, 9, 10, 15, 19, 20, 30, 31];
+        let metadata = metadata_of(&timestamps);
+        let best_block_number = (timestamps.len() - 1) as BlockNumber;
+        let metadata_at = |number: BlockNumber| metadata.get(number as usize).cloned();
+
+        let index = TermIndex::default();
+        assert_eq!(Some((0, 0, Some(3))), index.boundaries(0, best_block_number, metadata_at));
+        assert_eq!(Some((0, 0, Some(3))), index.boundaries(2, best_block_number, metadata_at));
+        assert_eq!(Some((0, 0, Some(3))), index.boundaries(3, best_block_number, metadata_at));
+        assert_eq!(Some((1, 4, Some(6))), index.boundaries(4, best_block_number, metadata_at));
+        assert_eq!(Some((1, 4, Some(6))), index.boundaries(6, best_block_number, metadata_at));
+        assert_eq!(Some((2, 7, Some(7))), index.boundaries(7, best_block_number, metadata_at));
+        assert_eq!(Some((3, 8, None)), index.boundaries(8, best_block_number, metadata_at));
+        assert_eq!(None, index.boundaries(9, best_block_number, metadata_at));
+
+        assert_eq!(Some((0, 10)), timestamp_window(TERM_SECONDS, timestamps[0]));
+        assert_eq!(Some((10, 20)), timestamp_window(TERM_SECONDS, timestamps[3]));
+        assert_eq!(Some((20, 30)), timestamp_window(TERM_SECONDS, timestamps[6]));
+        assert_eq!(Some((30, 40)), timestamp_window(TERM_SECONDS, timestamps[7]));
+        assert_eq!(None, timestamp_window(0, timestamps[7]));
+    }
+
+    #[test]
+    fn closing_blocks_are_searched_when_they_are_not_indexed() {
+        let timestamps = [0, 5, 10, 15, 20, 25, 30];
+        let metadata = metadata_of(&timestamps);
+        let metadata_at = |number: BlockNumber| metadata.get(number as usize).cloned();
+
+        let index = TermIndex::default();
+        assert_eq!(Some((1, 3, Some(4))), index.boundaries(3, 6, metadata_at));
+        assert_eq!(Some(&4), index.closing_blocks.read().get(&1));
+
+        // The indexed block is used without searching the states again.
+        assert_eq!(
+            Some((1, 3, Some(4))),
+            index.boundaries(3, 6, |number: BlockNumber| {
+                assert!(number == 3 || number == 6, "Block {} must not be searched", number);
+                metadata.get(number as usize).cloned()
+            })
+        );
+
+        index.clear();
+        assert!(index.closing_blocks.read().is_empty());
+    }
+}
@@ -32,9 +32,11 @@
 
 use std::collections::HashMap;
 use std::mem;
+use std::net::{IpAddr, Ipv4Addr};
 use std::ops::Range;
 use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrder};
 use std::sync::Arc;
+use std::time::Duration;
 
 use ckey::{public_to_address, Address, Generator, KeyPair, NetworkId, PlatformAddress, Private, Public, Random};
 use cmerkle::skewed_merkle_root;
@@ -53,21 +55,34 @@ use primitives::{Bytes, H256, U256};
 use rlp::*;
 
 use crate::block::{ClosedBlock, OpenBlock, SealedBlock};
+use crate::blockchain::{
+    ActionIndexBackfill, ActionIndexPage, ActionIndexPosition, AddressHistoryPage, AddressHistoryPosition,
+    ReorgProtectionStatus, SkippedSealVerification,
+};
 use crate::blockchain_info::BlockChainInfo;
 use crate::client::ImportResult;
 use crate::client::{
-    AccountData, BlockChainClient, BlockChainTrait, BlockProducer, BlockStatus, EngineInfo, ImportBlock,
-    MiningBlockChainClient, StateInfo, StateOrBlock, TermInfo,
+    AccountData, ActionHandlerTrace, BlockChainClient, BlockChainTrait, BlockFinality, BlockImportTimings,
+    BlockProducer, BlockProductionStats, BlockRewardDistribution, BlockStatus, EngineInfo, FinalityConflict,
+    ImportBlock, IntegrityReport, MiningBlockChainClient, ParamsActivation, RangeStatistics, RepairOptions,
+    ReplayOptions, ReplayReport, StateInfo, StateOrBlock, StateWarmingStatus, TermDetails, TermInfo, TraceTarget,
 };
 use crate::consensus::stake::{Validator, Validators};
-use crate::consensus::EngineError;
+use crate::consensus::{
+    CandidateStatus, ConsensusFault, ConsensusStatus, DecodedSeal, EngineDescription, EngineError, RestoreStatus,
+    SignerEligibility, WeightedValidator,
+};
 use crate::db::{COL_STATE, NUM_COLUMNS};
 use crate::encoded;
 use crate::error::{BlockImportError, Error as GenericError};
-use crate::miner::{Miner, MinerService, TransactionImportResult};
+use crate::miner::{
+    FeeFloorStatus, Miner, MinerService, PendingQueue, PooledTransaction, RelayStatus, ScheduledTransaction,
+    SignerDiagnosis, TransactionImportResult, TxOrigin,
+};
 use crate::scheme::Scheme;
 use crate::transaction::{LocalizedTransaction, PendingSignedTransactions, SignedTransaction};
 use crate::types::{BlockId, TransactionId, VerificationQueueInfo as QueueInfo};
+use crate::verification::BlockRejection;
 use client::ConsensusClient;
 
 /// Test client.
@@ -104,6 +119,8 @@ pub struct TestBlockChainClient {
     pub history: RwLock<Option<u64>>,
     /// Term ID
     pub term_id: Option<u64>,
+    /// The number of the block that closed the last term
+    pub last_term_finished_block_num: Option<BlockNumber>,
     /// Fixed validator keys
     pub validator_keys: RwLock<HashMap<Public, Private>>,
     /// Fixed validators
@@ -160,6 +177,7 @@ impl TestBlockChainClient {
             latest_block_timestamp: RwLock::new(10_000_000),
             history: RwLock::new(None),
             term_id: Some(1),
+            last_term_finished_block_num: None,
             validator_keys: RwLock::new(HashMap::new()),
             validators: Validators::from_vector_to_test(vec![]),
         };
@@ -306,7 +324,8 @@ impl TestBlockChainClient {
         let sender_address = public_to_address(&signed.signer_public());
         self.set_balance(sender_address, 10_000_000_000_000_000_000);
         let hash = signed.hash();
-        let res = self.miner.import_external_transactions(self, vec![signed.into()]);
+        let peer = NodeId::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 3485);
+        let res = self.miner.import_external_transactions(self, vec![signed.into()], peer);
         let res = res.into_iter().next().unwrap().expect("Successful import");
         assert_eq!(res, TransactionImportResult::Current);
         hash
@@ -360,6 +379,8 @@ impl BlockProducer for TestBlockChainClient {
         open_block.set_timestamp(*self.latest_block_timestamp.read());
         open_block
     }
+
+    fn cache_closed_block(&self, _parent_state_root: H256, _block: &ClosedBlock) {}
 }
 
 impl MiningBlockChainClient for TestBlockChainClient {}
@@ -481,6 +502,10 @@ impl ImportBlock for TestBlockChainClient {
         Ok(h)
     }
 
+    fn import_block_from(&self, bytes: Bytes, _peer: NodeId) -> Result<H256, BlockImportError> {
+        self.import_block(bytes)
+    }
+
     fn import_header(&self, _bytes: Bytes) -> Result<H256, BlockImportError> {
         unimplemented!()
     }
@@ -508,25 +533,93 @@ impl BlockChainClient for TestBlockChainClient {
     }
 
     fn queue_own_transaction(&self, transaction: SignedTransaction) -> Result<(), GenericError> {
-        self.miner.import_own_transaction(self, transaction)?;
+        self.miner.import_own_transaction(self, transaction, TxOrigin::Local)?;
+        Ok(())
+    }
+
+    fn queue_own_transactions(&self, transactions: Vec<SignedTransaction>) -> Result<(), Vec<Option<GenericError>>> {
+        self.miner.import_own_transactions(self, transactions, TxOrigin::Local)?;
         Ok(())
     }
 
-    fn queue_transactions(&self, transactions: Vec<Bytes>, _peer_id: NodeId) {
+    fn queue_rpc_transaction(
+        &self,
+        transaction: SignedTransaction,
+        source: Option<IpAddr>,
+    ) -> Result<(), GenericError> {
+        self.miner.import_own_transaction(self, transaction, TxOrigin::Rpc {
+            source,
+        })?;
+        Ok(())
+    }
+
+    fn queue_rpc_transactions(
+        &self,
+        transactions: Vec<SignedTransaction>,
+        source: Option<IpAddr>,
+    ) -> Result<(), Vec<Option<GenericError>>> {
+        self.miner.import_own_transactions(self, transactions, TxOrigin::Rpc {
+            source,
+        })?;
+        Ok(())
+    }
+
+    fn schedule_rpc_transaction(
+        &self,
+        transaction: SignedTransaction,
+        source: Option<IpAddr>,
+        not_before: u64,
+    ) -> Result<(), GenericError> {
+        self.miner.schedule_own_transaction(
+            self,
+            transaction,
+            TxOrigin::Rpc {
+                source,
+            },
+            not_before,
+        )
+    }
+
+    fn queue_transactions(&self, transactions: Vec<Bytes>, peer_id: NodeId) {
         // import right here
         let transactions =
             transactions.into_iter().filter_map(|bytes| UntrustedRlp::new(&bytes).as_val().ok()).collect();
-        self.miner.import_external_transactions(self, transactions);
+        self.miner.import_external_transactions(self, transactions, peer_id);
     }
 
     fn ready_transactions(&self, range: Range<u64>) -> PendingSignedTransactions {
-        self.miner.ready_transactions(range)
+        let size_limit = self.scheme.genesis_params().max_body_size();
+        self.miner.ready_transactions(size_limit, range)
     }
 
     fn count_pending_transactions(&self, range: Range<u64>) -> usize {
         self.miner.count_pending_transactions(range)
     }
 
+    fn diagnose_signer(&self, address: &Address) -> SignerDiagnosis {
+        self.miner.diagnose_signer(self, address)
+    }
+
+    fn fee_floor(&self) -> Option<FeeFloorStatus> {
+        self.miner.fee_floor()
+    }
+
+    fn relay_status(&self) -> RelayStatus {
+        self.miner.relay_stats().status()
+    }
+
+    fn pending_transactions_by_signer(&self, address: &Address) -> Vec<PooledTransaction> {
+        self.miner.transactions_by_signer(self, address)
+    }
+
+    fn pending_queue(&self, hash: &H256) -> Option<PendingQueue> {
+        self.miner.pending_queue(hash)
+    }
+
+    fn scheduled_transactions(&self) -> Vec<ScheduledTransaction> {
+        self.miner.scheduled_transactions()
+    }
+
 
     fn is_pending_queue_empty(&self) -> bool {
         self.miner.status().transactions_in_pending_queue == 0
@@ -579,6 +672,96 @@ impl BlockChainClient for TestBlockChainClient {
     fn error_hints_by_tracker(&self, _: &H256) -> Vec<(H256, Option<String>)> {
         unimplemented!();
     }
+
+    fn address_history(&self, _: &AddressHistoryPosition, _: BlockNumber, _: usize) -> Option<AddressHistoryPage> {
+        unimplemented!();
+    }
+
+    fn transactions_by_action_type(
+        &self,
+        _: &ActionIndexPosition,
+        _: BlockNumber,
+        _: usize,
+    ) -> Option<ActionIndexPage> {
+        unimplemented!();
+    }
+
+    fn action_index_backfill(&self) -> Option<ActionIndexBackfill> {
+        unimplemented!();
+    }
+
+    fn reorg_protection_status(&self) -> ReorgProtectionStatus {
+        unimplemented!();
+    }
+
+    fn override_reorg_protection(&self) {
+        unimplemented!();
+    }
+
+    fn freeze_chain(&self) {
+        unimplemented!();
+    }
+
+    fn unfreeze_chain(&self) {
+        unimplemented!();
+    }
+
+    fn is_chain_frozen(&self) -> bool {
+        false
+    }
+
+    fn finality_conflict(&self) -> Option<FinalityConflict> {
+        None
+    }
+
+    fn clear_consensus_halt(&self) -> Option<FinalityConflict> {
+        None
+    }
+
+    fn state_warming_status(&self) -> StateWarmingStatus {
+        StateWarmingStatus {
+            loaded: 0,
+            total: 0,
+            finished: true,
+        }
+    }
+
+    fn import_timings(&self, _limit: usize) -> Vec<BlockImportTimings> {
+        Vec::new()
+    }
+
+    fn recent_rejections(&self, _limit: usize) -> Vec<BlockRejection> {
+        Vec::new()
+    }
+
+    fn skipped_seal_verification(&self, _hash: &H256) -> Option<SkippedSealVerification> {
+        unimplemented!();
+    }
+
+    fn reverify_block_seal(&self, _hash: &H256) -> Option<Result<(), GenericError>> {
+        unimplemented!();
+    }
+
+    fn replay_blocks(
+        &self,
+        _from: BlockNumber,
+        _to: BlockNumber,
+        _options: &ReplayOptions,
+    ) -> Option<Result<ReplayReport, GenericError>> {
+        unimplemented!();
+    }
+
+    fn trace_transaction(&self, _target: &TraceTarget) -> Option<Result<ActionHandlerTrace, GenericError>> {
+        unimplemented!();
+    }
+
+    fn start_integrity_check(&self, _from: BlockNumber, _to: BlockNumber, _repair: Option<RepairOptions>) -> bool {
+        unimplemented!();
+    }
+
+    fn integrity_report(&self) -> Option<IntegrityReport> {
+        None
+    }
 }
 
 impl TimeoutHandler for TestBlockChainClient {
@@ -614,6 +797,10 @@ impl super::EngineClient for TestBlockChainClient {
 
     fn update_best_as_committed(&self, _block_hash: H256) {}
 
+    fn execute_proposal(&self, _block: Bytes) {}
+
+    fn discard_executed_proposals(&self, _keep: Option<H256>) {}
+
     fn get_kvdb(&self) -> Arc<KeyValueDB> {
         let db = kvdb_memorydb::create(NUM_COLUMNS.unwrap_or(0));
         Arc::new(db)
@@ -644,18 +831,90 @@ impl EngineInfo for TestBlockChainClient {
     fn possible_authors(&self, _block_number: Option<u64>) -> Result<Option<Vec<PlatformAddress>>, EngineError> {
         unimplemented!()
     }
+
+    fn validator_set(&self, _block_id: BlockId) -> Option<Vec<WeightedValidator>> {
+        unimplemented!()
+    }
+
+    fn force_next_view(&self) -> Result<(), EngineError> {
+        unimplemented!()
+    }
+
+    fn demote_to_standby(&self) -> Result<(), EngineError> {
+        unimplemented!()
+    }
+
+    fn promote_from_standby(&self, _min_quiet_period: Option<Duration>) -> Result<(), EngineError> {
+        unimplemented!()
+    }
+
+    fn set_consensus_fault(&self, _fault: ConsensusFault) -> Result<(), EngineError> {
+        unimplemented!()
+    }
+
+    fn consensus_status(&self) -> Option<ConsensusStatus> {
+        unimplemented!()
+    }
+
+    fn restore_status(&self) -> Option<RestoreStatus> {
+        unimplemented!()
+    }
+
+    fn block_production_stats(&self, _window_blocks: u64) -> BlockProductionStats {
+        unimplemented!()
+    }
+
+    fn total_burnt(&self, _block_id: BlockId) -> Option<u64> {
+        unimplemented!()
+    }
+
+    fn block_reward_distribution(&self, _block_number: u64) -> Option<BlockRewardDistribution> {
+        unimplemented!()
+    }
+
+    fn decode_seal(&self, _seal: &[Bytes]) -> Option<Result<DecodedSeal, String>> {
+        unimplemented!()
+    }
+
+    fn block_finality(&self, _hash: &H256) -> Option<BlockFinality> {
+        unimplemented!()
+    }
+
+    fn params_change_history(&self) -> Vec<ParamsActivation> {
+        unimplemented!()
+    }
+
+    fn engine_description(&self) -> EngineDescription {
+        unimplemented!()
+    }
+
+    fn range_statistics(&self, _from: BlockNumber, _to: BlockNumber) -> Option<RangeStatistics> {
+        unimplemented!()
+    }
+
+    fn signer_eligibility(&self) -> Option<SignerEligibility> {
+        unimplemented!()
+    }
+
+    fn candidates(&self, _block_id: BlockId) -> Option<Vec<CandidateStatus>> {
+        unimplemented!()
+    }
 }
 
 impl ConsensusClient for TestBlockChainClient {}
 
 impl TermInfo for TestBlockChainClient {
     fn last_term_finished_block_num(&self, _id: BlockId) -> Option<BlockNumber> {
-        None
+        self.last_term_finished_block_num
     }
 
     fn current_term_id(&self, _id: BlockId) -> Option<u64> {
         self.term_id
     }
+
+    fn term_details(&self, _block_number: BlockNumber) -> Option<TermDetails> {
+        unimplemented!()
+    }
 }
 
 impl StateInfo for TestBlockChainClient {
@@ -666,4 +925,8 @@ impl StateInfo for TestBlockChainClient {
 
         Some(top_state)
     }
+
+    fn state_proof(&self, _id: BlockId, _key: &[u8]) -> Option<Vec<Bytes>> {
+        None
+    }
 }
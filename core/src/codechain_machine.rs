@@ -19,11 +19,12 @@ use ckey::Address;
 use cstate::{StateError, TopState, TopStateView};
 use ctypes::errors::{HistoryError, SyntaxError};
 use ctypes::transaction::{Action, AssetTransferInput, OrderOnTransfer, Timelock};
+use ctypes::util::unexpected::OutOfBounds;
 use ctypes::{CommonParams, Header};
 
 use crate::block::{ExecutedBlock, IsBlock};
 use crate::client::BlockChainTrait;
-use crate::error::Error;
+use crate::error::{BlockError, Error};
 use crate::transaction::{SignedTransaction, UnverifiedTransaction};
 
 pub struct CodeChainMachine {
@@ -189,6 +190,31 @@ impl CodeChainMachine {
         Ok(())
     }
 
+    /// Returns the sum of the block reward and the fees, and the sum of the minimum costs of the transactions.
+    pub fn total_rewards(
+        block_reward: u64,
+        transactions: &[SignedTransaction],
+        params: &CommonParams,
+    ) -> Result<(u64, u64), Error> {
+        let total_reward = transactions
+            .iter()
+            .try_fold(block_reward, |sum, tx| sum.checked_add(tx.fee))
+            .ok_or(BlockError::TotalFeeOverflow)?;
+        let total_min_fee = transactions
+            .iter()
+            .try_fold(0u64, |sum, tx| sum.checked_add(Self::min_cost(params, &tx.action)))
+            .ok_or(BlockError::TotalFeeOverflow)?;
+        if total_reward < total_min_fee {
+            return Err(BlockError::InsufficientReward(OutOfBounds {
+                min: Some(total_min_fee),
+                max: None,
+                found: total_reward,
+            })
+            .into())
+        }
+        Ok((total_reward, total_min_fee))
+    }
+
     pub fn min_cost(params: &CommonParams, action: &Action) -> u64 {
         match action {
             Action::MintAsset {
@@ -269,3 +295,27 @@ fn is_order_disabled() -> bool {
         Err(err) => unreachable!("{:?}", err),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::helpers::pay_with_fee;
+
+    #[test]
+    fn total_rewards() {
+        let params = CommonParams::default_for_test();
+        let transactions = vec![pay_with_fee(100), pay_with_fee(200)];
+        let min_cost = CodeChainMachine::min_cost(&params, &transactions[0].action);
+        assert_eq!((1_300, min_cost * 2), CodeChainMachine::total_rewards(1_000, &transactions, &params).unwrap());
+    }
+
+    #[test]
+    fn total_rewards_overflow() {
+        let params = CommonParams::default_for_test();
+        let transactions = vec![pay_with_fee(u64::max_value() - 10), pay_with_fee(10)];
+        match CodeChainMachine::total_rewards(1, &transactions, &params) {
+            Err(Error::Block(BlockError::TotalFeeOverflow)) => {}
+            result => panic!("The overflow must be an error: {:?}", result),
+        }
+    }
+}
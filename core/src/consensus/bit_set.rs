@@ -21,7 +21,8 @@ use std::ops::Sub;
 
 use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
 
-const MAX_VALIDATOR_SIZE: usize = 800;
+/// The maximum number of the validators whose signatures a seal can hold.
+pub const MAX_VALIDATOR_SIZE: usize = 800;
 const BITSET_SIZE: usize = MAX_VALIDATOR_SIZE / 8;
 
 #[derive(Copy, Clone)]
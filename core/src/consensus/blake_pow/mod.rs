@@ -97,6 +97,10 @@ impl ConsensusEngine for BlakePoW {
         1
     }
 
+    fn seal_layout(&self) -> &'static [&'static str] {
+        &["nonce"]
+    }
+
     fn engine_type(&self) -> EngineType {
         EngineType::PoW
     }
@@ -168,8 +172,10 @@ impl ConsensusEngine for BlakePoW {
         _term_common_params: Option<&CommonParams>,
     ) -> Result<(), Error> {
         let author = *block.header().author();
-        let total_reward = self.block_reward(block.header().number())
-            + self.block_fee(Box::new(block.transactions().to_owned().into_iter().map(Into::into)));
+        let total_reward = self
+            .block_fee(Box::new(block.transactions().to_owned().into_iter().map(Into::into)))
+            .and_then(|fee| fee.checked_add(self.block_reward(block.header().number())))
+            .ok_or(BlockError::TotalFeeOverflow)?;
         self.machine.add_balance(block, &author, total_reward)
     }
 
@@ -31,18 +31,24 @@ pub use self::cuckoo::Cuckoo;
 pub use self::null_engine::NullEngine;
 pub use self::simple_poa::SimplePoA;
 pub use self::solo::Solo;
+pub use self::stake::{CandidateStatus, ExclusionReason, SignerEligibility};
+pub use self::tendermint::light as tendermint_light;
+#[cfg(any(test, feature = "test-support"))]
+pub use self::tendermint::simulation::Simulation as TendermintSimulation;
 pub use self::tendermint::{
     message_info_rlp, ConsensusMessage, Height, Step, Tendermint, TendermintParams, TimeGapParams, View, VoteOn,
     VoteStep,
 };
 pub use self::validator_set::validator_list::RoundRobinValidator;
-pub use self::validator_set::{DynamicValidator, ValidatorSet};
+pub use self::validator_set::{DynamicValidator, ValidatorSet, WeightedValidator};
 pub use self::vote_collector::Message;
 
+use std::collections::BTreeMap;
 use std::fmt;
 use std::sync::{Arc, Weak};
+use std::time::Duration;
 
-use ckey::{Address, SchnorrSignature};
+use ckey::{Address, Public, SchnorrAggregate, SchnorrSignature};
 use cnetwork::NetworkService;
 use cstate::ActionHandler;
 use ctypes::errors::SyntaxError;
@@ -54,6 +60,7 @@ use primitives::{Bytes, H256, U256};
 use self::bit_set::BitSet;
 use crate::account_provider::AccountProvider;
 use crate::block::{ExecutedBlock, SealedBlock};
+use crate::blockchain::DEFAULT_MAX_REORG_DEPTH;
 use crate::client::ConsensusClient;
 use crate::codechain_machine::CodeChainMachine;
 use crate::encoded;
@@ -71,6 +78,13 @@ pub enum Seal {
         precommits: Vec<SchnorrSignature>,
         precommit_bitset: BitSet,
     },
+    /// The Tendermint seal of which precommits are aggregated into one.
+    AggregatedTendermint {
+        prev_view: View,
+        cur_view: View,
+        precommits: SchnorrAggregate,
+        precommit_bitset: BitSet,
+    },
     None,
 }
 
@@ -91,12 +105,23 @@ impl Seal {
                 ::rlp::encode_list(precommits).into_vec(),
                 ::rlp::encode(precommit_bitset).into_vec(),
             ]),
+            Seal::AggregatedTendermint {
+                prev_view,
+                cur_view,
+                precommits,
+                precommit_bitset,
+            } => Some(vec![
+                ::rlp::encode(prev_view).into_vec(),
+                ::rlp::encode(cur_view).into_vec(),
+                ::rlp::encode(precommits).into_vec(),
+                ::rlp::encode(precommit_bitset).into_vec(),
+            ]),
         }
     }
 }
 
 /// Engine type.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum EngineType {
     PoA,
     PBFT,
@@ -155,6 +180,11 @@ pub trait ConsensusEngine: Sync + Send {
         0
     }
 
+    /// The names of the seal fields in order.
+    fn seal_layout(&self) -> &'static [&'static str] {
+        &[]
+    }
+
     /// None means that it requires external input (e.g. PoW) to seal a block.
     /// Some(true) means the engine is currently prime for seal generation (i.e. node is the current validator).
     /// Some(false) means that the node might seal internally but is not qualified now.
@@ -219,6 +249,12 @@ pub trait ConsensusEngine: Sync + Send {
     /// Usually implements the chain scoring rule based on weight.
     fn populate_from_parent(&self, _header: &mut Header, _parent: &Header) {}
 
+    /// The timestamp of the block that this node proposes on the parent.
+    /// None keeps the timestamp of the open block, which follows the clock.
+    fn proposal_timestamp(&self, _parent: &Header) -> Option<u64> {
+        None
+    }
+
     /// Called when the step is not changed in time
     fn on_timeout(&self, _token: usize) {}
 
@@ -254,6 +290,10 @@ pub trait ConsensusEngine: Sync + Send {
 
     fn register_network_extension_to_service(&self, _: &NetworkService) {}
 
+    /// Sends the votes to every peer, even to the ones that don't vote. It must be called before the
+    /// network extension is registered.
+    fn set_full_gossip(&self, _full_gossip: bool) {}
+
     fn register_time_gap_config_to_worker(&self, _time_gap_params: TimeGapParams) {}
 
     fn score_to_target(&self, _score: &U256) -> U256 {
@@ -262,8 +302,9 @@ pub trait ConsensusEngine: Sync + Send {
 
     fn block_reward(&self, block_number: u64) -> u64;
 
-    fn block_fee(&self, transactions: Box<Iterator<Item = UnverifiedTransaction>>) -> u64 {
-        transactions.map(|tx| tx.fee).sum()
+    /// Returns None if the sum of the fees overflows.
+    fn block_fee(&self, transactions: Box<Iterator<Item = UnverifiedTransaction>>) -> Option<u64> {
+        transactions.map(|tx| tx.fee).try_fold(0u64, u64::checked_add)
     }
 
     fn recommended_confirmation(&self) -> u32;
@@ -278,6 +319,11 @@ pub trait ConsensusEngine: Sync + Send {
         true
     }
 
+    /// The maximum number of canonical blocks a reorg can retract.
+    fn max_reorg_depth(&self) -> u64 {
+        DEFAULT_MAX_REORG_DEPTH
+    }
+
     fn action_handlers(&self) -> &[Arc<ActionHandler>] {
         &[]
     }
@@ -287,6 +333,239 @@ pub trait ConsensusEngine: Sync + Send {
     }
 
     fn possible_authors(&self, block_number: Option<u64>) -> Result<Option<Vec<Address>>, EngineError>;
+
+    /// Returns the validators of the block whose parent is given, in the order of their indices in the seal.
+    ///
+    /// Engines that don't have validators return an empty list.
+    fn validator_set(&self, _parent: &H256) -> Vec<WeightedValidator> {
+        Vec::new()
+    }
+
+    /// Returns the validators that the scheme fixes for the blocks before the first election.
+    ///
+    /// Unlike `validator_set`, it doesn't read the chain, so it works without a client.
+    fn initial_validators(&self) -> Vec<Public> {
+        Vec::new()
+    }
+
+    /// Skips the current step as if its timeout expired, to leave a view whose proposer is stuck.
+    fn force_next_view(&self) -> Result<(), EngineError> {
+        Err(EngineError::CannotForceNextView(format!("{} doesn't have views", self.name())))
+    }
+
+    /// Stops signing while following the consensus, to keep the node as a hot spare of the validator.
+    fn demote_to_standby(&self) -> Result<(), EngineError> {
+        Err(EngineError::CannotChangeStandby(format!("{} doesn't have the standby mode", self.name())))
+    }
+
+    /// Starts signing again. It's refused if a message signed by the same key is found, or if a vote signed by
+    /// the same key was received within `min_quiet_period`.
+    fn promote_from_standby(&self, _min_quiet_period: Option<Duration>) -> Result<(), EngineError> {
+        Err(EngineError::CannotChangeStandby(format!("{} doesn't have the standby mode", self.name())))
+    }
+
+    /// Makes the engine misbehave on purpose. `ConsensusFault::None` makes it honest again.
+    fn set_consensus_fault(&self, _fault: ConsensusFault) -> Result<(), EngineError> {
+        Err(EngineError::CannotInjectFault(format!("{} is built without the fault-injection feature", self.name())))
+    }
+
+    /// Returns the view in which the block was proposed.
+    ///
+    /// Engines that don't have views return None.
+    fn block_view(&self, _header: &Header) -> Option<u64> {
+        None
+    }
+
+    /// Returns the current height, view, and step of the consensus.
+    ///
+    /// Engines that don't have views return None.
+    /// Tendermint also returns None if its worker doesn't answer in a second.
+    fn consensus_status(&self) -> Option<ConsensusStatus> {
+        None
+    }
+
+    /// Returns where the engine restored its state from. Engines that don't keep a backup return None.
+    fn restore_status(&self) -> Option<RestoreStatus> {
+        None
+    }
+
+    /// Interprets the seal fields of a block, which may not be verified.
+    ///
+    /// Engines that don't know how to decode their seals return None.
+    fn decode_seal(&self, _seal: &[Bytes]) -> Option<Result<DecodedSeal, String>> {
+        None
+    }
+
+    /// Describes the engine for the tools that work with any engine, so they don't have to probe its behavior.
+    fn describe(&self, common_params: &CommonParams) -> EngineDescription {
+        EngineDescription {
+            name: self.name().to_string(),
+            engine_type: self.engine_type(),
+            seals_internally: self.seals_internally(),
+            recommended_confirmation: self.recommended_confirmation(),
+            seal_field_count: self.seal_fields(&Header::new()),
+            seal_layout: self.seal_layout().to_vec(),
+            term_seconds: common_params.term_seconds(),
+            action_handlers: (self.action_handlers().iter())
+                .map(|handler| (handler.handler_id(), handler.name()))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EngineDescription {
+    pub name: String,
+    pub engine_type: EngineType,
+    pub seals_internally: Option<bool>,
+    pub recommended_confirmation: u32,
+    pub seal_field_count: usize,
+    /// The names of the seal fields in order
+    pub seal_layout: Vec<&'static str>,
+    /// 0 if the chain doesn't have terms
+    pub term_seconds: u64,
+    /// The ids and the names of the action handlers
+    pub action_handlers: Vec<(u64, &'static str)>,
+}
+
+/// The seal fields interpreted by the engine.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DecodedSeal {
+    /// The seal doesn't have any fields.
+    Empty,
+    Tendermint {
+        /// The view in which the parent block was finalized.
+        finalized_view: View,
+        /// The view in which the block was proposed.
+        author_view: View,
+        /// The indices of the validators who signed the precommits.
+        signer_indices: Vec<usize>,
+        signature_count: usize,
+    },
+}
+
+/// The misbehavior that a validator injects into its own consensus messages on purpose.
+///
+/// It's used to test how the other validators handle a faulty validator.
+/// The engines ignore it unless the node is built with the `fault-injection` feature.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConsensusFault {
+    None,
+    /// Proposes two different blocks in the same view.
+    DoublePropose,
+    /// Prevotes for two different blocks in the same view.
+    DoublePrevote,
+    /// Doesn't send its precommits.
+    WithholdPrecommit,
+    /// Sends its proposals late.
+    DelayProposal(Duration),
+    /// Estimates the clock skew with a clock that runs ahead of the system time.
+    SkewedClock(Duration),
+}
+
+impl Default for ConsensusFault {
+    fn default() -> Self {
+        ConsensusFault::None
+    }
+}
+
+/// The number of times a node entered the commit step without the body of the committed block.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CommitRecoveryStats {
+    /// The body was requested from the peers that know the proposal.
+    pub requested: usize,
+    /// The request was sent again to another peer because the previous one didn't answer in time.
+    pub retried: usize,
+    /// No peer that knows the proposal answered, so the proposal was requested from any peer.
+    pub fell_back: usize,
+    /// The body was imported while waiting for it.
+    pub recovered: usize,
+}
+
+/// How the requests of the missing votes were answered.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct VoteRequestStats {
+    /// The responses that didn't fit in a message and were split.
+    pub split_responses: usize,
+    /// The messages sent for the split responses.
+    pub response_batches: usize,
+    /// The requests sent again for the votes that didn't arrive.
+    pub re_requested: usize,
+}
+
+/// How far the received proposals went through the checks.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ProposalCheckStats {
+    /// The proposal was rejected from its header, before its body was decompressed.
+    pub rejected_before_body: usize,
+    /// The body was decompressed and decoded because the header was valid.
+    pub bodies_decoded: usize,
+    /// The body couldn't be decompressed or decoded.
+    pub rejected_by_body: usize,
+}
+
+/// The size of the votes that the consensus engine keeps.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct VoteCollectorStats {
+    pub votes: usize,
+    /// In bytes
+    pub memory_estimate: usize,
+}
+
+/// The current position of the consensus engine.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsensusStatus {
+    pub height: Height,
+    pub view: View,
+    pub step: Step,
+    pub commit_recovery: CommitRecoveryStats,
+    /// The number of the double votes found in the messages from the other validators.
+    pub double_votes_found: usize,
+    pub vote_collector: VoteCollectorStats,
+    pub time_in_step: Duration,
+    /// The timeout of the current step in the current view.
+    pub step_timeout: Duration,
+    /// The estimated skew of the local clock from the other validators' clocks, in milliseconds.
+    /// It's positive if the local clock is ahead.
+    pub clock_skew: Option<i64>,
+    pub proposal_checks: ProposalCheckStats,
+    pub vote_requests: VoteRequestStats,
+    /// The size of the proposal blocks that the engine holds, in bytes.
+    pub retained_proposal_bytes: usize,
+    /// The engine follows the consensus without signing.
+    pub standby: bool,
+    /// The number of the invalid precommit signatures found in the received seals, by the signer index.
+    pub bad_seal_signatures: BTreeMap<usize, usize>,
+    /// The engine escalates when the view at a height goes above this.
+    pub escalation_view: View,
+    /// Set while the engine escalates. It's cleared when the height is committed.
+    pub degradation: Option<ConsensusDegradation>,
+}
+
+/// The consensus couldn't commit a height in many views.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsensusDegradation {
+    pub height: Height,
+    /// The view in which the engine started to escalate.
+    pub since_view: View,
+    /// The signer indices of the validators whose prevotes were missing in the last view.
+    pub missing_prevotes: Vec<usize>,
+    /// The signer indices of the validators whose precommits were missing in the last view.
+    pub missing_precommits: Vec<usize>,
+}
+
+/// Where the consensus engine restored its state from at startup.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RestoreStatus {
+    /// There was no backup, e.g. on the first start.
+    NoBackup,
+    Clean,
+    /// The backup was corrupted and moved aside. It has the hash of the corrupted bytes.
+    /// The engine doesn't sign while it's observing the height and the view of the network.
+    Quarantined {
+        hash: H256,
+        observing: bool,
+    },
 }
 
 /// Voting errors.
@@ -317,6 +596,11 @@ pub enum EngineError {
     DoubleVote(Address),
     /// The received block is from an incorrect proposer.
     NotProposer(Mismatch<Address>),
+    /// The precommit signature of the given signer index in the seal is invalid.
+    InvalidSealSignature {
+        signer_index: usize,
+        block_hash: H256,
+    },
     /// Message was not expected.
     UnexpectedMessage,
     /// Seal field has an unexpected size.
@@ -324,6 +608,12 @@ pub enum EngineError {
     /// Malformed consensus message.
     MalformedMessage(String),
     CannotOpenBlock,
+    /// The view cannot be changed in the current state.
+    CannotForceNextView(String),
+    /// The engine is built without the fault injection.
+    CannotInjectFault(String),
+    /// The engine cannot leave or enter the standby mode.
+    CannotChangeStandby(String),
 }
 
 impl fmt::Display for EngineError {
@@ -349,10 +639,17 @@ impl fmt::Display for EngineError {
             } => format!("The previous block of height {} does not exist.", height),
             DoubleVote(address) => format!("Author {} issued too many blocks.", address),
             NotProposer(mis) => format!("Author is not a current proposer: {}", mis),
+            InvalidSealSignature {
+                signer_index,
+                block_hash,
+            } => format!("The precommit of the {}th validator in the seal of {} is invalid.", signer_index, block_hash),
             UnexpectedMessage => "This Engine should not be fed messages.".into(),
             BadSealFieldSize(oob) => format!("Seal field has an unexpected length: {}", oob),
             MalformedMessage(msg) => format!("Received malformed consensus message: {}", msg),
             CannotOpenBlock => "Cannot open a block".to_string(),
+            CannotForceNextView(reason) => format!("Cannot force the next view: {}", reason),
+            CannotInjectFault(reason) => format!("Cannot inject the fault: {}", reason),
+            CannotChangeStandby(reason) => format!("Cannot change the standby mode: {}", reason),
         };
 
         f.write_fmt(format_args!("Engine error ({})", msg))
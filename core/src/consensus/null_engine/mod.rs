@@ -24,7 +24,7 @@ use super::ConsensusEngine;
 use crate::block::ExecutedBlock;
 use crate::codechain_machine::CodeChainMachine;
 use crate::consensus::{EngineError, EngineType};
-use crate::error::Error;
+use crate::error::{BlockError, Error};
 
 /// An engine which does not provide any consensus mechanism and does not seal blocks.
 pub struct NullEngine {
@@ -65,8 +65,10 @@ impl ConsensusEngine for NullEngine {
         let (author, total_reward) = {
             let header = block.header();
             let author = *header.author();
-            let total_reward = self.block_reward(header.number())
-                + self.block_fee(Box::new(block.transactions().to_owned().into_iter().map(Into::into)));
+            let total_reward = self
+                .block_fee(Box::new(block.transactions().to_owned().into_iter().map(Into::into)))
+                .and_then(|fee| fee.checked_add(self.block_reward(header.number())))
+                .ok_or(BlockError::TotalFeeOverflow)?;
             (author, total_reward)
         };
         self.machine.add_balance(block, &author, total_reward)
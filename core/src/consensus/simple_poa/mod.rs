@@ -21,11 +21,12 @@ use std::sync::{Arc, Weak};
 use ckey::{public_to_address, recover, Address, Signature};
 use ctypes::{CommonParams, Header};
 use parking_lot::RwLock;
+use primitives::H256;
 
 use self::params::SimplePoAParams;
 use super::signer::EngineSigner;
 use super::validator_set::validator_list::RoundRobinValidator;
-use super::validator_set::ValidatorSet;
+use super::validator_set::{ValidatorSet, WeightedValidator};
 use super::{ConsensusEngine, EngineError, Seal};
 use crate::account_provider::AccountProvider;
 use crate::block::ExecutedBlock;
@@ -87,6 +88,10 @@ impl ConsensusEngine for SimplePoA {
         1
     }
 
+    fn seal_layout(&self) -> &'static [&'static str] {
+        &["signature"]
+    }
+
     fn seals_internally(&self) -> Option<bool> {
         Some(self.signer.read().is_some())
     }
@@ -125,8 +130,10 @@ impl ConsensusEngine for SimplePoA {
         _term_common_params: Option<&CommonParams>,
     ) -> Result<(), Error> {
         let author = *block.header().author();
-        let total_reward = self.block_reward(block.header().number())
-            + self.block_fee(Box::new(block.transactions().to_owned().into_iter().map(Into::into)));
+        let total_reward = self
+            .block_fee(Box::new(block.transactions().to_owned().into_iter().map(Into::into)))
+            .and_then(|fee| fee.checked_add(self.block_reward(block.header().number())))
+            .ok_or(BlockError::TotalFeeOverflow)?;
         self.machine.add_balance(block, &author, total_reward)
     }
 
@@ -152,6 +159,10 @@ impl ConsensusEngine for SimplePoA {
         let parent = 0.into();
         Ok(Some(self.validators.addresses(&parent)))
     }
+
+    fn validator_set(&self, parent: &H256) -> Vec<WeightedValidator> {
+        self.validators.weighted_validators(parent)
+    }
 }
 
 #[cfg(test)]
@@ -20,16 +20,20 @@ use std::sync::Arc;
 
 use ckey::{Address, Error as KeyError, Public, SchnorrSignature};
 use cstate::{ActionHandler, HitHandler};
-use ctypes::{CommonParams, Header};
-use primitives::H256;
+use ctypes::util::unexpected::Mismatch;
+use ctypes::{BlockNumber, CommonParams, Header};
+use parking_lot::RwLock;
+use primitives::{Bytes, H256};
 use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
 
 use self::params::SoloParams;
+use super::signer::EngineSigner;
 use super::stake;
 use super::{ConsensusEngine, Seal};
+use crate::account_provider::AccountProvider;
 use crate::block::{ExecutedBlock, IsBlock};
 use crate::codechain_machine::CodeChainMachine;
-use crate::consensus::{EngineError, EngineType, Message};
+use crate::consensus::{DecodedSeal, EngineError, EngineType, Message};
 use crate::error::Error;
 
 /// A consensus engine which does not provide any consensus mechanism.
@@ -37,6 +41,7 @@ pub struct Solo {
     params: SoloParams,
     machine: CodeChainMachine,
     action_handlers: Vec<Arc<ActionHandler>>,
+    signer: RwLock<EngineSigner>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Hash, Default)]
@@ -99,8 +104,18 @@ impl Solo {
             params,
             machine,
             action_handlers,
+            signer: Default::default(),
         }
     }
+
+    /// Returns the author of the given block number if the authors are rotated.
+    fn expected_author(&self, block_number: BlockNumber) -> Option<&Address> {
+        let rotation = &self.params.author_rotation;
+        if rotation.len() < 2 {
+            return None
+        }
+        Some(&rotation[(block_number % rotation.len() as u64) as usize])
+    }
 }
 
 impl ConsensusEngine for Solo {
@@ -120,8 +135,33 @@ impl ConsensusEngine for Solo {
         EngineType::Solo
     }
 
-    fn generate_seal(&self, _block: Option<&ExecutedBlock>, _parent: &Header) -> Seal {
-        Seal::Solo
+    fn generate_seal(&self, block: Option<&ExecutedBlock>, _parent: &Header) -> Seal {
+        let header = match block {
+            Some(block) => block.header(),
+            None => return Seal::Solo,
+        };
+        match self.expected_author(header.number()) {
+            None => Seal::Solo,
+            Some(expected) if header.author() == expected && self.signer.read().is_address(expected) => Seal::Solo,
+            Some(expected) => {
+                ctrace!(ENGINE, "generate_seal: the block {} must be authored by {}", header.number(), expected);
+                Seal::None
+            }
+        }
+    }
+
+    fn verify_header_basic(&self, header: &Header) -> Result<(), Error> {
+        if header.number() == 0 {
+            return Ok(())
+        }
+        match self.expected_author(header.number()) {
+            Some(expected) if header.author() != expected => Err(EngineError::NotProposer(Mismatch {
+                expected: *expected,
+                found: *header.author(),
+            })
+            .into()),
+            _ => Ok(()),
+        }
     }
 
     fn on_close_block(
@@ -132,24 +172,24 @@ impl ConsensusEngine for Solo {
         _term_common_params: Option<&CommonParams>,
     ) -> Result<(), Error> {
         let author = *block.header().author();
-        let (total_reward, total_min_fee) = {
-            let transactions = block.transactions();
-            let block_reward = self.block_reward(block.header().number());
-            let total_min_fee: u64 = transactions.iter().map(|tx| tx.fee).sum();
-            let min_fee: u64 =
-                transactions.iter().map(|tx| CodeChainMachine::min_cost(&parent_common_params, &tx.action)).sum();
-            (block_reward + total_min_fee, min_fee)
+        let block_reward = self.block_reward(block.header().number());
+        let (total_reward, total_min_fee) =
+            CodeChainMachine::total_rewards(block_reward, block.transactions(), parent_common_params)?;
+        // The transactions of the block don't change who receives its fees if the params say so.
+        let stakes = match block.parent_stakes() {
+            Some(parent_stakes) => parent_stakes.clone(),
+            None => stake::get_stakes(block.state()).expect("Cannot get Stake status"),
         };
 
-        assert!(total_reward >= total_min_fee, "{} >= {}", total_reward, total_min_fee);
-        let stakes = stake::get_stakes(block.state()).expect("Cannot get Stake status");
-
-        let mut distributor = stake::fee_distribute(total_min_fee, &stakes);
-        for (address, share) in &mut distributor {
-            self.machine.add_balance(block, &address, share)?
+        let distribution =
+            stake::distribute_rewards(total_reward, total_min_fee, parent_common_params.fee_burn_permille(), &stakes);
+        for (address, share) in &distribution.shares {
+            self.machine.add_balance(block, address, *share)?
         }
+        // The burnt fee isn't added to anyone's balance.
+        stake::add_burnt_fee(block.state_mut(), distribution.burnt)?;
 
-        let block_author_reward = total_reward - total_min_fee + distributor.remaining_fee();
+        let block_author_reward = distribution.author_reward;
 
         let term_seconds = parent_common_params.term_seconds();
         if term_seconds == 0 {
@@ -176,6 +216,11 @@ impl ConsensusEngine for Solo {
         Ok(())
     }
 
+    /// Register an account which signs the blocks in its turn of the author rotation.
+    fn set_signer(&self, ap: Arc<AccountProvider>, address: Address) {
+        self.signer.write().set(ap, address);
+    }
+
     fn block_reward(&self, _block_number: u64) -> u64 {
         self.params.block_reward
     }
@@ -188,19 +233,139 @@ impl ConsensusEngine for Solo {
         &self.action_handlers
     }
 
-    fn possible_authors(&self, _block_number: Option<u64>) -> Result<Option<Vec<Address>>, EngineError> {
-        Ok(None)
+    fn possible_authors(&self, block_number: Option<u64>) -> Result<Option<Vec<Address>>, EngineError> {
+        if self.params.author_rotation.len() < 2 {
+            return Ok(None)
+        }
+        match block_number.and_then(|number| self.expected_author(number)) {
+            Some(author) => Ok(Some(vec![*author])),
+            None => Ok(Some(self.params.author_rotation.clone())),
+        }
+    }
+
+    fn decode_seal(&self, seal: &[Bytes]) -> Option<Result<DecodedSeal, String>> {
+        if seal.is_empty() {
+            Some(Ok(DecodedSeal::Empty))
+        } else {
+            Some(Err(format!("The seal must be empty, but it has {} fields", seal.len())))
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
+    use ccrypto::blake256;
+    use ckey::{Address, Private};
+    use cstate::{ActionHandler, FindActionHandler, StateWithCache, TopLevelState, TopState, TopStateView};
+    use ctypes::transaction::{Action, Transaction};
     use ctypes::{CommonParams, Header};
-    use primitives::H520;
+    use cvm::ChainTimeInfo;
+    use primitives::{H256, H520};
+    use rlp::Encodable;
 
+    use super::params::SoloParams;
+    use super::{stake, Solo, SoloMessage};
+    use crate::account_provider::AccountProvider;
     use crate::block::{IsBlock, OpenBlock};
+    use crate::codechain_machine::CodeChainMachine;
+    use crate::consensus::{CodeChainEngine, ConsensusEngine, DecodedSeal, EngineDescription, EngineType};
     use crate::scheme::Scheme;
-    use crate::tests::helpers::get_temp_state_db;
+    use crate::tests::helpers::{get_temp_state_db, pay_with_fee};
+    use crate::transaction::SignedTransaction;
+
+    fn insert_and_unlock(tap: &Arc<AccountProvider>, acc: &str) -> Address {
+        let addr = tap.insert_account(blake256(acc).into(), &acc.into()).unwrap();
+        tap.unlock_account_permanently(addr, acc.into()).unwrap();
+        addr
+    }
+
+    fn rotating_engine(rotation: Vec<Address>, tap: &Arc<AccountProvider>, signer: Address) -> Solo {
+        let params = SoloParams {
+            author_rotation: rotation,
+            ..Default::default()
+        };
+        let engine = Solo::new(params, CodeChainMachine::new(CommonParams::default_for_test()));
+        engine.set_signer(Arc::clone(tap), signer);
+        engine
+    }
+
+    struct EngineHandlers<'a>(&'a CodeChainEngine);
+
+    impl<'a> FindActionHandler for EngineHandlers<'a> {
+        fn find_action_handler_for(&self, id: u64) -> Option<&ActionHandler> {
+            self.0.find_action_handler_for(id)
+        }
+    }
+
+    impl<'a> ChainTimeInfo for EngineHandlers<'a> {
+        fn transaction_block_age(&self, _: &H256, _parent_block_number: u64) -> Option<u64> {
+            None
+        }
+
+        fn transaction_time_age(&self, _: &H256, _parent_timestamp: u64) -> Option<u64> {
+            None
+        }
+    }
+
+    /// Closes the block in which the largest stakeholder transfers all of its CCS,
+    /// and returns the fee share of the receiver.
+    fn fee_share_of_the_ccs_receiver(distribute_fees_by_parent_stakes: bool) -> u64 {
+        let scheme = Scheme::new_test_solo();
+        let engine = &*scheme.engine;
+        let db = scheme.ensure_genesis_state(get_temp_state_db()).unwrap();
+        let mut parent_header = scheme.genesis_header();
+        let mut parent_common_params = scheme.genesis_params();
+        parent_common_params.set_distribute_fees_by_parent_stakes_for_test(distribute_fees_by_parent_stakes);
+        // The block opened on the parent reads the params from the state of the parent.
+        let mut parent_state = TopLevelState::from_existing(db, *parent_header.state_root()).unwrap();
+        parent_state.update_params(0, parent_common_params).unwrap();
+        let (db, parent_state_root) = parent_state.commit_and_into_db().unwrap();
+        parent_header.set_state_root(parent_state_root);
+        let mut b = OpenBlock::try_new(engine, db, &parent_header, Address::random(), vec![]).unwrap();
+        // The stakes at the parent are kept only if they're needed.
+        assert_eq!(distribute_fees_by_parent_stakes, b.block().parent_stakes().is_some());
+
+        // It has 70000 of the 100000 CCS in the bundled scheme.
+        let stakeholder: Private = "ede1d4ccb4ec9a8bbbae9a13db3f4a7b56ea04189be86ac3a6a439d9a0a1addd".into();
+        let receiver = Address::random();
+        let transfer = stake::Action::<SoloMessage>::TransferCCS {
+            address: receiver,
+            quantity: 70_000,
+        };
+        let tx = Transaction {
+            seq: 0,
+            fee: 10,
+            network_id: "tc".into(),
+            action: Action::Custom {
+                handler_id: stake::CUSTOM_ACTION_HANDLER_ID,
+                bytes: transfer.rlp_bytes().into_vec(),
+            },
+        };
+        let signed = SignedTransaction::new_with_sign(tx, &stakeholder);
+        b.push_transaction(signed, None, &EngineHandlers(engine), 0, 0).unwrap();
+
+        let b = b.close_and_lock(&parent_header, &parent_common_params, None).unwrap();
+        b.state().balance(&receiver).unwrap()
+    }
+
+    #[test]
+    fn fees_are_distributed_by_the_stakes_after_the_transactions() {
+        // The minimum fee of the transfer is 10, and the receiver has 70% of the stakes after it.
+        assert_eq!(7, fee_share_of_the_ccs_receiver(false));
+    }
+
+    #[test]
+    fn fees_are_distributed_by_the_stakes_at_the_parent() {
+        assert_eq!(0, fee_share_of_the_ccs_receiver(true));
+    }
+
+    fn is_sealed(engine: &Solo, scheme: &Scheme, parent: &Header, author: Address) -> bool {
+        let db = scheme.ensure_genesis_state(get_temp_state_db()).unwrap();
+        let b = OpenBlock::try_new(engine, db, parent, author, vec![]).unwrap();
+        engine.generate_seal(Some(b.block()), parent).seal_fields().is_some()
+    }
 
     #[test]
     fn seal() {
@@ -217,6 +382,15 @@ mod tests {
         }
     }
 
+    #[test]
+    fn block_fee_overflow() {
+        let engine = Scheme::new_test_solo().engine;
+        let transactions = vec![pay_with_fee(u64::max_value() - 1), pay_with_fee(1)];
+        assert_eq!(Some(u64::max_value()), engine.block_fee(Box::new(transactions.into_iter().map(Into::into))));
+        let transactions = vec![pay_with_fee(u64::max_value()), pay_with_fee(1)];
+        assert_eq!(None, engine.block_fee(Box::new(transactions.into_iter().map(Into::into))));
+    }
+
     #[test]
     fn fail_to_verify() {
         let engine = Scheme::new_test_solo().engine;
@@ -228,4 +402,90 @@ mod tests {
 
         assert!(engine.verify_block_seal(&header).is_ok());
     }
+
+    #[test]
+    fn decode_seal() {
+        let engine = Scheme::new_test_solo().engine;
+        assert_eq!(Some(Ok(DecodedSeal::Empty)), engine.decode_seal(&[]));
+        assert!(engine.decode_seal(&[vec![0x80]]).unwrap().is_err());
+    }
+
+    #[test]
+    fn describe() {
+        let scheme = Scheme::new_test_solo();
+        let common_params = scheme.genesis_params();
+        assert_eq!(
+            EngineDescription {
+                name: "Solo".to_string(),
+                engine_type: EngineType::Solo,
+                seals_internally: Some(true),
+                recommended_confirmation: 1,
+                seal_field_count: 0,
+                seal_layout: vec![],
+                term_seconds: common_params.term_seconds(),
+                action_handlers: vec![(1, "hit handler"), (2, "stake handler")],
+            },
+            scheme.engine.describe(&common_params)
+        );
+    }
+
+    #[test]
+    fn authors_take_turns() {
+        let tap = AccountProvider::transient_provider();
+        let author0 = insert_and_unlock(&tap, "0");
+        let author1 = insert_and_unlock(&tap, "1");
+        let rotation = vec![author0, author1];
+        let engine0 = rotating_engine(rotation.clone(), &tap, author0);
+        let engine1 = rotating_engine(rotation, &tap, author1);
+
+        let scheme = Scheme::new_test_solo();
+        let genesis_header = scheme.genesis_header();
+        // The block 1 is the turn of author1.
+        assert!(!is_sealed(&engine0, &scheme, &genesis_header, author0));
+        assert!(is_sealed(&engine1, &scheme, &genesis_header, author1));
+
+        // The block 2 is the turn of author0.
+        let mut parent = genesis_header.clone();
+        parent.set_number(1);
+        assert!(is_sealed(&engine0, &scheme, &parent, author0));
+        assert!(!is_sealed(&engine1, &scheme, &parent, author1));
+    }
+
+    #[test]
+    fn reject_out_of_turn_author() {
+        let tap = AccountProvider::transient_provider();
+        let author0 = insert_and_unlock(&tap, "0");
+        let author1 = insert_and_unlock(&tap, "1");
+        let engine = rotating_engine(vec![author0, author1], &tap, author0);
+
+        let mut header = Header::default();
+        header.set_number(1);
+        header.set_author(author1);
+        assert!(engine.verify_header_basic(&header).is_ok());
+        header.set_author(author0);
+        assert!(engine.verify_header_basic(&header).is_err());
+
+        header.set_number(2);
+        assert!(engine.verify_header_basic(&header).is_ok());
+        header.set_author(author1);
+        assert!(engine.verify_header_basic(&header).is_err());
+    }
+
+    #[test]
+    fn single_author_is_not_rotated() {
+        let tap = AccountProvider::transient_provider();
+        let author0 = insert_and_unlock(&tap, "0");
+        let author1 = insert_and_unlock(&tap, "1");
+        let engine = rotating_engine(vec![author0], &tap, author0);
+
+        let scheme = Scheme::new_test_solo();
+        let genesis_header = scheme.genesis_header();
+        assert!(is_sealed(&engine, &scheme, &genesis_header, author1));
+
+        let mut header = Header::default();
+        header.set_number(1);
+        header.set_author(author1);
+        assert!(engine.verify_header_basic(&header).is_ok());
+        assert_eq!(None, engine.possible_authors(Some(1)).unwrap());
+    }
 }
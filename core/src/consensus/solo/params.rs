@@ -26,6 +26,9 @@ pub struct SoloParams {
     pub block_reward: u64,
     pub enable_hit_handler: bool,
     pub genesis_stakes: HashMap<Address, u64>,
+    /// The block of number N is authored by `author_rotation[N % len]`.
+    /// The authors are not rotated if it has less than two addresses.
+    pub author_rotation: Vec<Address>,
 }
 
 impl From<cjson::scheme::SoloParams> for SoloParams {
@@ -33,6 +36,12 @@ impl From<cjson::scheme::SoloParams> for SoloParams {
         SoloParams {
             block_reward: p.block_reward.map_or_else(Default::default, Into::into),
             enable_hit_handler: p.action_handlers.hit.is_some(),
+            author_rotation: p
+                .author_rotation
+                .unwrap_or_default()
+                .into_iter()
+                .map(PlatformAddress::into_address)
+                .collect(),
             genesis_stakes: p
                 .action_handlers
                 .genesis_stakes
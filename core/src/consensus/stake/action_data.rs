@@ -19,13 +19,13 @@ use std::collections::btree_set::{self, BTreeSet};
 use std::collections::{btree_map, HashMap, HashSet};
 use std::mem;
 use std::ops::Deref;
-use std::vec;
+use std::{slice, vec};
 
 use ckey::{public_to_address, Address, Public};
 use cstate::{ActionData, ActionDataKeyBuilder, StateResult, TopLevelState, TopState, TopStateView};
 use ctypes::errors::RuntimeError;
 use primitives::{Bytes, H256};
-use rlp::{decode_list, encode_list, Decodable, Encodable, Rlp, RlpStream};
+use rlp::{decode_list, encode_list, Decodable, DecoderError, Encodable, Rlp, RlpStream, UntrustedRlp};
 
 use super::CUSTOM_ACTION_HANDLER_ID;
 
@@ -43,16 +43,26 @@ lazy_static! {
         ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 1).append(&"Banned").into_key();
     pub static ref VALIDATORS_KEY: H256 =
         ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 1).append(&"Validators").into_key();
+    pub static ref ELECTION_EXCLUSIONS_KEY: H256 =
+        ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 1).append(&"ElectionExclusions").into_key();
 }
 
 pub fn get_delegation_key(address: &Address) -> H256 {
     ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 2).append(&"Delegation").append(address).into_key()
 }
 
+pub fn get_nomination_record_key(address: &Address) -> H256 {
+    ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 2).append(&"NominationRecord").append(address).into_key()
+}
+
 pub fn get_intermediate_rewards_key() -> H256 {
     ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 1).append(&"IntermediateRewards").into_key()
 }
 
+pub fn get_total_burnt_key() -> H256 {
+    ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 1).append(&"TotalBurnt").into_key()
+}
+
 pub type StakeQuantity = u64;
 pub type Deposit = u64;
 
@@ -229,30 +239,35 @@ impl<'a> Delegation<'a> {
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, RlpDecodable, RlpEncodable)]
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub struct Validator {
     weight: StakeQuantity,
     delegation: StakeQuantity,
     deposit: Deposit,
     pubkey: Public,
+    signing_pubkey: Public,
 }
 
 impl Validator {
     pub fn new_for_test(delegation: StakeQuantity, deposit: Deposit, pubkey: Public) -> Self {
+        Self::new(delegation, deposit, pubkey, pubkey)
+    }
+
+    #[cfg(test)]
+    pub fn with_signing_pubkey_for_test(self, signing_pubkey: Public) -> Self {
         Self {
-            weight: delegation,
-            delegation,
-            deposit,
-            pubkey,
+            signing_pubkey,
+            ..self
         }
     }
 
-    fn new(delegation: StakeQuantity, deposit: Deposit, pubkey: Public) -> Self {
+    fn new(delegation: StakeQuantity, deposit: Deposit, pubkey: Public, signing_pubkey: Public) -> Self {
         Self {
             weight: delegation,
             delegation,
             deposit,
             pubkey,
+            signing_pubkey,
         }
     }
 
@@ -260,13 +275,65 @@ impl Validator {
         self.weight = self.delegation;
     }
 
+    /// The key of the candidate. The rewards go to its address.
     pub fn pubkey(&self) -> &Public {
         &self.pubkey
     }
 
+    /// The key that signs the blocks and the votes.
+    /// It's the key of the candidate unless the candidate registered another one.
+    pub fn signing_pubkey(&self) -> &Public {
+        &self.signing_pubkey
+    }
+
     pub fn delegation(&self) -> StakeQuantity {
         self.delegation
     }
+
+    pub fn deposit(&self) -> Deposit {
+        self.deposit
+    }
+}
+
+// The signing key is encoded only if it differs from the key of the candidate,
+// so the validators that didn't register a signing key are encoded as before.
+impl Encodable for Validator {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        let has_signing_pubkey = self.signing_pubkey != self.pubkey;
+        s.begin_list(if has_signing_pubkey {
+            5
+        } else {
+            4
+        });
+        s.append(&self.weight).append(&self.delegation).append(&self.deposit).append(&self.pubkey);
+        if has_signing_pubkey {
+            s.append(&self.signing_pubkey);
+        }
+    }
+}
+
+impl Decodable for Validator {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 4 && item_count != 5 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                expected: 4,
+                got: item_count,
+            })
+        }
+        let pubkey = rlp.val_at(3)?;
+        Ok(Self {
+            weight: rlp.val_at(0)?,
+            delegation: rlp.val_at(1)?,
+            deposit: rlp.val_at(2)?,
+            pubkey,
+            signing_pubkey: if item_count == 5 {
+                rlp.val_at(4)?
+            } else {
+                pubkey
+            },
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -283,8 +350,13 @@ impl Validators {
         Ok(Validators(validators))
     }
 
-    pub fn elect(state: &TopLevelState) -> StateResult<Self> {
-        let (delegation_threshold, max_num_of_validators, min_num_of_validators, min_deposit) = {
+    /// Elects the validators of the next term with the params in the state of the term-closing block.
+    /// Returns the candidates excluded by the minimum self-nomination with them.
+    ///
+    /// A change of the params in the middle of a term doesn't affect the current validators.
+    /// It takes effect at this election.
+    pub fn elect(state: &TopLevelState) -> StateResult<(Self, ElectionExclusions)> {
+        let (delegation_threshold, max_num_of_validators, min_num_of_validators, min_deposit, min_self_nomination) = {
             let metadata = state.metadata()?.expect("Metadata must exist");
             let common_params = metadata.params().expect("CommonParams must exist in the metadata when elect");
             (
@@ -292,13 +364,15 @@ impl Validators {
                 common_params.max_num_of_validators(),
                 common_params.min_num_of_validators(),
                 common_params.min_deposit(),
+                common_params.min_self_nomination_quantity(),
             )
         };
         assert!(max_num_of_validators >= min_num_of_validators);
 
         let delegatees = Stakeholders::delegatees(&state)?;
         // Step 1 & 2.
-        let mut validators = Candidates::prepare_validators(&state, min_deposit, &delegatees)?;
+        let (mut validators, exclusions) =
+            Candidates::prepare_validators(&state, min_deposit, min_self_nomination, &delegatees)?;
         // validators are now sorted in descending order of (delegation, deposit, priority)
         validators.reverse();
 
@@ -326,7 +400,7 @@ impl Validators {
 
         let mut result: Vec<_> = minimum.iter().chain(over_threshold).cloned().collect();
         result.reverse(); // Ascending order of (delegation, deposit, priority)
-        Ok(Self(result))
+        Ok((Self(result), exclusions))
     }
 
 
@@ -344,11 +418,11 @@ impl Validators {
         let min_delegation = self.min_delegation();
         for Validator {
             weight,
-            pubkey,
+            signing_pubkey,
             ..
         } in self.0.iter_mut().rev()
         {
-            if public_to_address(pubkey) == *block_author {
+            if public_to_address(signing_pubkey) == *block_author {
                 // block author
                 *weight = weight.saturating_sub(min_delegation);
                 break
@@ -451,13 +525,142 @@ impl IntermediateRewards {
     }
 }
 
+/// The sum of the fees burnt since the genesis block.
+#[derive(Default, Debug, PartialEq)]
+pub struct TotalBurnt(u64);
+
+impl TotalBurnt {
+    pub fn load_from_state(state: &TopLevelState) -> StateResult<Self> {
+        let key = get_total_burnt_key();
+        let action_data = state.action_data(&key)?;
+        let total = match action_data {
+            Some(data) => Rlp::new(&data).as_val(),
+            None => 0,
+        };
+        Ok(TotalBurnt(total))
+    }
+
+    pub fn save_to_state(&self, state: &mut TopLevelState) -> StateResult<()> {
+        let key = get_total_burnt_key();
+        if self.0 != 0 {
+            state.update_action_data(&key, rlp::encode(&self.0).into_vec())?;
+        } else {
+            state.remove_action_data(&key);
+        }
+        Ok(())
+    }
+
+    pub fn add(&mut self, burnt: u64) {
+        self.0 = self.0.checked_add(burnt).expect("The burnt fees cannot exceed the total supply");
+    }
+
+    pub fn total(&self) -> u64 {
+        self.0
+    }
+}
+
+/// The last sequenced self-nomination of an account.
+/// Unlike the candidate, it's kept after the account retires, so an older nomination cannot resurrect its metadata.
+/// It's pruned when the account is kicked out of the jail or banned.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct NominationRecord {
+    pub seq: u64,
+    /// The term when the nomination was accepted
+    pub term: u64,
+}
+
+impl NominationRecord {
+    pub fn load_from_state(state: &TopLevelState, address: &Address) -> StateResult<Self> {
+        let key = get_nomination_record_key(address);
+        Ok(state.action_data(&key)?.map(|data| Rlp::new(&data).as_val()).unwrap_or_default())
+    }
+
+    pub fn save_to_state(&self, state: &mut TopLevelState, address: &Address) -> StateResult<()> {
+        let key = get_nomination_record_key(address);
+        if self.seq != 0 {
+            state.update_action_data(&key, rlp::encode(self).into_vec())?;
+        } else {
+            state.remove_action_data(&key);
+        }
+        Ok(())
+    }
+
+    pub fn remove_from_state(state: &mut TopLevelState, address: &Address) {
+        state.remove_action_data(&get_nomination_record_key(address));
+    }
+
+    /// The nominations without the sequence are accepted until the account nominates itself with one.
+    pub fn accept(&mut self, address: &Address, nomination_seq: Option<u64>, term: u64) -> Result<(), RuntimeError> {
+        let seq = nomination_seq.unwrap_or(0);
+        if self.seq != 0 && seq <= self.seq {
+            return Err(RuntimeError::StaleNomination {
+                address: *address,
+                last_seq: self.seq,
+                got: seq,
+            })
+        }
+        if seq != 0 {
+            self.seq = seq;
+            self.term = term;
+        }
+        Ok(())
+    }
+}
+
 pub struct Candidates(Vec<Candidate>);
-#[derive(Clone, Debug, Eq, PartialEq, RlpEncodable, RlpDecodable)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Candidate {
     pub pubkey: Public,
     pub deposit: Deposit,
     pub nomination_ends_at: u64,
     pub metadata: Bytes,
+    /// The key registered to sign the blocks and the votes on behalf of the candidate.
+    /// It's used from the next election.
+    pub signing_pubkey: Option<Public>,
+}
+
+impl Candidate {
+    fn signing_pubkey_or_own(&self) -> Public {
+        self.signing_pubkey.unwrap_or(self.pubkey)
+    }
+}
+
+// Same as the validators, the candidates without a signing key keep their encoding.
+impl Encodable for Candidate {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(if self.signing_pubkey.is_some() {
+            5
+        } else {
+            4
+        });
+        s.append(&self.pubkey).append(&self.deposit).append(&self.nomination_ends_at).append(&self.metadata);
+        if let Some(signing_pubkey) = &self.signing_pubkey {
+            s.append(signing_pubkey);
+        }
+    }
+}
+
+impl Decodable for Candidate {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 4 && item_count != 5 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                expected: 4,
+                got: item_count,
+            })
+        }
+        Ok(Self {
+            pubkey: rlp.val_at(0)?,
+            deposit: rlp.val_at(1)?,
+            nomination_ends_at: rlp.val_at(2)?,
+            metadata: rlp.val_at(3)?,
+            signing_pubkey: if item_count == 5 {
+                Some(rlp.val_at(4)?)
+            } else {
+                None
+            },
+        })
+    }
 }
 
 impl Candidates {
@@ -478,31 +681,59 @@ impl Candidates {
         Ok(())
     }
 
-    // Sorted list of validators in ascending order of (delegation, deposit, priority).
+    // Sorted list of validators in ascending order of (delegation, deposit, priority),
+    // and the candidates that are excluded only because they delegate too little to themselves.
     fn prepare_validators(
         state: &TopLevelState,
         min_deposit: Deposit,
+        min_self_nomination: StakeQuantity,
         delegations: &HashMap<Address, StakeQuantity>,
-    ) -> StateResult<Vec<Validator>> {
+    ) -> StateResult<(Vec<Validator>, ElectionExclusions)> {
         let Candidates(candidates) = Self::load_from_state(state)?;
         let mut result = Vec::new();
+        let mut exclusions = ElectionExclusions::default();
         for candidate in candidates.into_iter().filter(|c| c.deposit >= min_deposit) {
             let address = public_to_address(&candidate.pubkey);
             if let Some(delegation) = delegations.get(&address).cloned() {
-                result.push(Validator::new(delegation, candidate.deposit, candidate.pubkey));
+                let self_nomination = Delegation::load_from_state(state, &address)?.get_quantity(&address);
+                if self_nomination < min_self_nomination {
+                    exclusions.0.push(ElectionExclusion {
+                        pubkey: candidate.pubkey,
+                        reason: ExclusionReason::InsufficientSelfNomination {
+                            self_nomination,
+                            minimum: min_self_nomination,
+                        },
+                    });
+                    continue
+                }
+                result.push(Validator::new(
+                    delegation,
+                    candidate.deposit,
+                    candidate.pubkey,
+                    candidate.signing_pubkey_or_own(),
+                ));
             }
         }
         // Candidates are sorted in low priority: low index, high priority: high index
         // so stable sorting with the key (delegation, deposit) preserves its priority order.
         // ascending order of (delegation, deposit, priority)
         result.sort_by_key(|v| (v.delegation, v.deposit));
-        Ok(result)
+        Ok((result, exclusions))
     }
 
     pub fn get_candidate(&self, account: &Address) -> Option<&Candidate> {
         self.0.iter().find(|c| public_to_address(&c.pubkey) == *account)
     }
 
+    /// Finds the candidate that signs with the key of the given address, whether it's registered or its own.
+    pub fn get_candidate_by_signer(&self, signer: &Address) -> Option<&Candidate> {
+        self.0.iter().find(|c| public_to_address(&c.signing_pubkey_or_own()) == *signer)
+    }
+
+    pub fn iter(&self) -> slice::Iter<Candidate> {
+        self.0.iter()
+    }
+
     #[cfg(test)]
     pub fn len(&self) -> usize {
         self.0.len()
@@ -527,6 +758,7 @@ impl Candidates {
                 deposit: quantity,
                 nomination_ends_at,
                 metadata,
+                signing_pubkey: None,
             });
         };
         self.reprioritize(&[public_to_address(pubkey)]);
@@ -562,6 +794,28 @@ impl Candidates {
         expired
     }
 
+    /// Checks whether a candidate other than the given account signs with the key.
+    pub fn is_signing_pubkey_of_others(&self, pubkey: &Public, address: &Address) -> bool {
+        self.0.iter().any(|c| public_to_address(&c.pubkey) != *address && c.signing_pubkey_or_own() == *pubkey)
+    }
+
+    /// Registers the key that signs on behalf of the candidate from the next election.
+    /// Registering the key of the candidate itself cancels the registration.
+    pub fn set_signing_key(&mut self, address: &Address, signing_pubkey: Public) -> Result<(), RuntimeError> {
+        if self.is_signing_pubkey_of_others(&signing_pubkey, address) {
+            return Err(RuntimeError::FailedToHandleCustomAction("The signing key is used by another candidate".into()))
+        }
+        let candidate = (self.0.iter_mut())
+            .find(|c| public_to_address(&c.pubkey) == *address)
+            .ok_or_else(|| RuntimeError::FailedToHandleCustomAction("The account is not a candidate".into()))?;
+        candidate.signing_pubkey = if candidate.pubkey == signing_pubkey {
+            None
+        } else {
+            Some(signing_pubkey)
+        };
+        Ok(())
+    }
+
     pub fn remove(&mut self, address: &Address) -> Option<Candidate> {
         if let Some(index) = self.0.iter().position(|c| public_to_address(&c.pubkey) == *address) {
             Some(self.0.remove(index))
@@ -690,6 +944,83 @@ impl Banned {
     }
 }
 
+/// Why a candidate with enough deposit and delegations was not elected.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExclusionReason {
+    /// The candidate delegated less than the minimum to itself.
+    InsufficientSelfNomination {
+        self_nomination: StakeQuantity,
+        minimum: StakeQuantity,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ElectionExclusion {
+    pub pubkey: Public,
+    pub reason: ExclusionReason,
+}
+
+impl Encodable for ElectionExclusion {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        match self.reason {
+            ExclusionReason::InsufficientSelfNomination {
+                self_nomination,
+                minimum,
+            } => {
+                s.begin_list(4).append(&self.pubkey).append(&0u8).append(&self_nomination).append(&minimum);
+            }
+        }
+    }
+}
+
+impl Decodable for ElectionExclusion {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 4 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                expected: 4,
+                got: item_count,
+            })
+        }
+        let reason = match rlp.val_at::<u8>(1)? {
+            0 => ExclusionReason::InsufficientSelfNomination {
+                self_nomination: rlp.val_at(2)?,
+                minimum: rlp.val_at(3)?,
+            },
+            _ => return Err(DecoderError::Custom("Unknown exclusion reason")),
+        };
+        Ok(Self {
+            pubkey: rlp.val_at(0)?,
+            reason,
+        })
+    }
+}
+
+/// The candidates that were not elected at the last election, though they are still candidates.
+#[derive(Debug, Default)]
+pub struct ElectionExclusions(Vec<ElectionExclusion>);
+impl ElectionExclusions {
+    pub fn load_from_state(state: &TopLevelState) -> StateResult<Self> {
+        let key = *ELECTION_EXCLUSIONS_KEY;
+        let exclusions = state.action_data(&key)?.map(|data| decode_list(&data)).unwrap_or_default();
+        Ok(ElectionExclusions(exclusions))
+    }
+
+    pub fn save_to_state(&self, state: &mut TopLevelState) -> StateResult<()> {
+        let key = *ELECTION_EXCLUSIONS_KEY;
+        if !self.0.is_empty() {
+            state.update_action_data(&key, encode_list(&self.0).to_vec())?;
+        } else {
+            state.remove_action_data(&key);
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, pubkey: &Public) -> Option<ExclusionReason> {
+        self.0.iter().find(|exclusion| exclusion.pubkey == *pubkey).map(|exclusion| exclusion.reason)
+    }
+}
+
 fn decode_set<V>(data: Option<&ActionData>) -> BTreeSet<V>
 where
     V: Ord + Decodable, {
@@ -1186,6 +1517,34 @@ mod tests {
         assert_eq!(candidate.unwrap().deposit, 55);
     }
 
+    #[test]
+    fn signing_key_is_encoded_only_if_registered() {
+        let pubkey = Public::random();
+        let candidate = Candidate {
+            pubkey,
+            deposit: 10,
+            nomination_ends_at: 3,
+            metadata: b"metadata".to_vec(),
+            signing_pubkey: None,
+        };
+        let validator = Validator::new_for_test(20, 10, pubkey);
+        assert_eq!(4, Rlp::new(&rlp::encode(&candidate)).item_count());
+        assert_eq!(4, Rlp::new(&rlp::encode(&validator)).item_count());
+        assert_eq!(candidate, rlp::decode(&rlp::encode(&candidate)));
+        assert_eq!(validator, rlp::decode(&rlp::encode(&validator)));
+
+        let signing_pubkey = Public::random();
+        let candidate = Candidate {
+            signing_pubkey: Some(signing_pubkey),
+            ..candidate
+        };
+        let validator = validator.with_signing_pubkey_for_test(signing_pubkey);
+        assert_eq!(5, Rlp::new(&rlp::encode(&candidate)).item_count());
+        assert_eq!(5, Rlp::new(&rlp::encode(&validator)).item_count());
+        assert_eq!(candidate, rlp::decode(&rlp::encode(&candidate)));
+        assert_eq!(validator, rlp::decode(&rlp::encode(&validator)));
+    }
+
     #[test]
     fn candidates_metadata() {
         let mut state = helpers::get_temp_state();
@@ -1313,24 +1672,28 @@ mod tests {
                 deposit: 20,
                 nomination_ends_at: 11,
                 metadata: b"".to_vec(),
+                signing_pubkey: None,
             },
             Candidate {
                 pubkey: pubkey1,
                 deposit: 30,
                 nomination_ends_at: 22,
                 metadata: b"".to_vec(),
+                signing_pubkey: None,
             },
             Candidate {
                 pubkey: pubkey2,
                 deposit: 40,
                 nomination_ends_at: 33,
                 metadata: b"".to_vec(),
+                signing_pubkey: None,
             },
             Candidate {
                 pubkey: pubkey3,
                 deposit: 50,
                 nomination_ends_at: 44,
                 metadata: b"".to_vec(),
+                signing_pubkey: None,
             },
         ];
 
@@ -1339,6 +1702,7 @@ mod tests {
             deposit,
             nomination_ends_at,
             metadata,
+            ..
         } in &candidates_prepared
         {
             let mut candidates = Candidates::load_from_state(&state).unwrap();
@@ -1384,24 +1748,28 @@ mod tests {
                 deposit: 20,
                 nomination_ends_at: 11,
                 metadata: b"".to_vec(),
+                signing_pubkey: None,
             },
             Candidate {
                 pubkey: pubkey1,
                 deposit: 30,
                 nomination_ends_at: 22,
                 metadata: b"".to_vec(),
+                signing_pubkey: None,
             },
             Candidate {
                 pubkey: pubkey2,
                 deposit: 40,
                 nomination_ends_at: 33,
                 metadata: b"".to_vec(),
+                signing_pubkey: None,
             },
             Candidate {
                 pubkey: pubkey3,
                 deposit: 50,
                 nomination_ends_at: 44,
                 metadata: b"".to_vec(),
+                signing_pubkey: None,
             },
         ];
 
@@ -1410,6 +1778,7 @@ mod tests {
             deposit,
             nomination_ends_at,
             metadata,
+            ..
         } in &candidates_prepared
         {
             let mut candidates = Candidates::load_from_state(&state).unwrap();
@@ -1446,6 +1815,7 @@ mod tests {
                 deposit: 100,
                 nomination_ends_at: 0,
                 metadata: b"".to_vec(),
+                signing_pubkey: None,
             },
             10,
             20,
@@ -1473,6 +1843,7 @@ mod tests {
                 deposit: 100,
                 nomination_ends_at: 0,
                 metadata: b"".to_vec(),
+                signing_pubkey: None,
             },
             10,
             20,
@@ -1500,6 +1871,7 @@ mod tests {
                 deposit: 100,
                 nomination_ends_at: 0,
                 metadata: b"".to_vec(),
+                signing_pubkey: None,
             },
             10,
             20,
@@ -1544,6 +1916,7 @@ mod tests {
                 deposit: 100,
                 nomination_ends_at: 0,
                 metadata: b"".to_vec(),
+                signing_pubkey: None,
             },
             10,
             20,
@@ -1554,6 +1927,7 @@ mod tests {
                 deposit: 200,
                 nomination_ends_at: 0,
                 metadata: b"".to_vec(),
+                signing_pubkey: None,
             },
             15,
             25,
@@ -1589,6 +1963,7 @@ mod tests {
                 deposit: 100,
                 nomination_ends_at: 0,
                 metadata: b"".to_vec(),
+                signing_pubkey: None,
             },
             10,
             20,
@@ -1599,6 +1974,7 @@ mod tests {
                 deposit: 200,
                 nomination_ends_at: 0,
                 metadata: b"".to_vec(),
+                signing_pubkey: None,
             },
             15,
             25,
@@ -1639,6 +2015,7 @@ mod tests {
                 deposit: 100,
                 nomination_ends_at: 0,
                 metadata: b"".to_vec(),
+                signing_pubkey: None,
             },
             10,
             20,
@@ -1649,6 +2026,7 @@ mod tests {
                 deposit: 200,
                 nomination_ends_at: 0,
                 metadata: b"".to_vec(),
+                signing_pubkey: None,
             },
             15,
             25,
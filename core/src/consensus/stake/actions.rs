@@ -17,8 +17,9 @@
 use std::sync::Arc;
 
 use ccrypto::Blake;
-use ckey::{recover, Address, Signature};
+use ckey::{recover, Address, Public, Signature};
 use client::ConsensusClient;
+use consensus::bit_set::MAX_VALIDATOR_SIZE;
 use consensus::vote_collector::Message;
 use consensus::ValidatorSet;
 use ctypes::errors::SyntaxError;
@@ -31,6 +32,7 @@ const ACTION_TAG_DELEGATE_CCS: u8 = 2;
 const ACTION_TAG_REVOKE: u8 = 3;
 const ACTION_TAG_SELF_NOMINATE: u8 = 4;
 const ACTION_TAG_REPORT_DOUBLE_VOTE: u8 = 5;
+const ACTION_TAG_SET_SIGNING_KEY: u8 = 6;
 const ACTION_TAG_CHANGE_PARAMS: u8 = 0xFF;
 
 #[derive(Debug, PartialEq)]
@@ -50,6 +52,9 @@ pub enum Action<M: Message> {
     SelfNominate {
         deposit: u64,
         metadata: Bytes,
+        /// Starts from 1. Once an account nominates itself with a sequence,
+        /// the nominations of the account must have larger sequences than the last one.
+        nomination_seq: Option<u64>,
     },
     ChangeParams {
         metadata_seq: u64,
@@ -60,6 +65,11 @@ pub enum Action<M: Message> {
         message1: M,
         message2: M,
     },
+    /// Lets the candidate sign the blocks and the votes with another key from the next term.
+    SetSigningKey {
+        candidate_address: Address,
+        signing_public: Public,
+    },
 }
 
 impl<M: Message> Action<M> {
@@ -79,10 +89,22 @@ impl<M: Message> Action<M> {
             Action::Revoke {
                 ..
             } => {}
+            Action::SetSigningKey {
+                ..
+            } => {}
             Action::SelfNominate {
                 metadata,
+                nomination_seq,
                 ..
             } => {
+                if nomination_seq.is_some() && !current_params.nomination_seq_enabled() {
+                    return Err(SyntaxError::InvalidCustomAction(
+                        "The nomination sequence is not enabled by the params".to_string(),
+                    ))
+                }
+                if *nomination_seq == Some(0) {
+                    return Err(SyntaxError::InvalidCustomAction("The nomination sequence starts from 1".to_string()))
+                }
                 if metadata.len() > current_params.max_candidate_metadata_size() {
                     return Err(SyntaxError::InvalidCustomAction(format!(
                         "Too long candidate metadata: the size limit is {}",
@@ -104,6 +126,13 @@ impl<M: Message> Action<M> {
                     )))
                 }
                 params.verify().map_err(SyntaxError::InvalidCustomAction)?;
+                if params.max_num_of_validators() > MAX_VALIDATOR_SIZE {
+                    return Err(SyntaxError::InvalidCustomAction(format!(
+                        "The maximum number of validators({}) is larger than the seal can hold({})",
+                        params.max_num_of_validators(),
+                        MAX_VALIDATOR_SIZE
+                    )))
+                }
                 let action = Action::<M>::ChangeParams {
                     metadata_seq: *metadata_seq,
                     params: params.clone(),
@@ -198,8 +227,18 @@ impl<M: Message> Encodable for Action<M> {
             Action::SelfNominate {
                 deposit,
                 metadata,
+                nomination_seq,
             } => {
-                s.begin_list(3).append(&ACTION_TAG_SELF_NOMINATE).append(deposit).append(metadata);
+                // The nominations without the sequence are encoded as before.
+                s.begin_list(if nomination_seq.is_some() {
+                    4
+                } else {
+                    3
+                });
+                s.append(&ACTION_TAG_SELF_NOMINATE).append(deposit).append(metadata);
+                if let Some(nomination_seq) = nomination_seq {
+                    s.append(nomination_seq);
+                }
             }
             Action::ChangeParams {
                 metadata_seq,
@@ -220,6 +259,12 @@ impl<M: Message> Encodable for Action<M> {
             } => {
                 s.begin_list(3).append(&ACTION_TAG_REPORT_DOUBLE_VOTE).append(message1).append(message2);
             }
+            Action::SetSigningKey {
+                candidate_address,
+                signing_public,
+            } => {
+                s.begin_list(3).append(&ACTION_TAG_SET_SIGNING_KEY).append(candidate_address).append(signing_public);
+            }
         };
     }
 }
@@ -269,15 +314,24 @@ impl<M: Message> Decodable for Action<M> {
             }
             ACTION_TAG_SELF_NOMINATE => {
                 let item_count = rlp.item_count()?;
-                if item_count != 3 {
+                if item_count != 3 && item_count != 4 {
                     return Err(DecoderError::RlpInvalidLength {
-                        expected: 3,
+                        expected: if item_count < 3 {
+                            3
+                        } else {
+                            4
+                        },
                         got: item_count,
                     })
                 }
                 Ok(Action::SelfNominate {
                     deposit: rlp.val_at(1)?,
                     metadata: rlp.val_at(2)?,
+                    nomination_seq: if item_count == 4 {
+                        Some(rlp.val_at(3)?)
+                    } else {
+                        None
+                    },
                 })
             }
             ACTION_TAG_CHANGE_PARAMS => {
@@ -312,11 +366,35 @@ impl<M: Message> Decodable for Action<M> {
                     message2,
                 })
             }
+            ACTION_TAG_SET_SIGNING_KEY => {
+                let item_count = rlp.item_count()?;
+                if item_count != 3 {
+                    return Err(DecoderError::RlpInvalidLength {
+                        expected: 3,
+                        got: item_count,
+                    })
+                }
+                Ok(Action::SetSigningKey {
+                    candidate_address: rlp.val_at(1)?,
+                    signing_public: rlp.val_at(2)?,
+                })
+            }
             _ => Err(DecoderError::Custom("Unexpected Tendermint Stake Action Type")),
         }
     }
 }
 
+/// Returns the metadata seq that the action expects if the bytes are a ChangeParams action.
+///
+/// The other actions are not decoded, so it doesn't depend on the consensus message type.
+pub fn change_params_metadata_seq(bytes: &[u8]) -> Option<u64> {
+    let rlp = UntrustedRlp::new(bytes);
+    if rlp.val_at::<u8>(0).ok()? != ACTION_TAG_CHANGE_PARAMS || rlp.item_count().ok()? < 4 {
+        return None
+    }
+    rlp.val_at(1).ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -343,6 +421,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn change_params_cannot_elect_more_validators_than_the_seal_can_hold() {
+        let mut params = CommonParams::from(cjson::scheme::Params {
+            max_text_content_size: Some(200.into()),
+            ..Default::default()
+        });
+        params.set_dynamic_validator_params_for_test(30, 10, 3, 20, MAX_VALIDATOR_SIZE, 4, 1000, 10000, 100);
+        let action = Action::<SoloMessage>::ChangeParams {
+            metadata_seq: 3,
+            params: params.into(),
+            signatures: vec![],
+        };
+        assert_eq!(Ok(()), action.verify(&CommonParams::default_for_test(), None, None));
+
+        params.set_dynamic_validator_params_for_test(30, 10, 3, 20, MAX_VALIDATOR_SIZE + 1, 4, 1000, 10000, 100);
+        let action = Action::<SoloMessage>::ChangeParams {
+            metadata_seq: 3,
+            params: params.into(),
+            signatures: vec![],
+        };
+        assert!(action.verify(&CommonParams::default_for_test(), None, None).is_err());
+    }
+
     #[test]
     fn rlp_of_change_params() {
         rlp_encode_and_decode_test!(Action::<SoloMessage>::ChangeParams {
@@ -352,6 +453,95 @@ mod tests {
         });
     }
 
+    #[test]
+    fn rlp_of_set_signing_key() {
+        rlp_encode_and_decode_test!(Action::<SoloMessage>::SetSigningKey {
+            candidate_address: Address::random(),
+            signing_public: Public::random(),
+        });
+    }
+
+    #[test]
+    fn rlp_of_self_nominate() {
+        rlp_encode_and_decode_test!(Action::<SoloMessage>::SelfNominate {
+            deposit: 100,
+            metadata: b"metadata".to_vec(),
+            nomination_seq: None,
+        });
+        rlp_encode_and_decode_test!(Action::<SoloMessage>::SelfNominate {
+            deposit: 100,
+            metadata: b"metadata".to_vec(),
+            nomination_seq: Some(3),
+        });
+    }
+
+    #[test]
+    fn self_nominate_without_the_sequence_keeps_the_encoding() {
+        let action = Action::<SoloMessage>::SelfNominate {
+            deposit: 100,
+            metadata: b"metadata".to_vec(),
+            nomination_seq: None,
+        };
+        let mut expected = RlpStream::new_list(3);
+        expected.append(&ACTION_TAG_SELF_NOMINATE).append(&100u64).append(&b"metadata".to_vec());
+        assert_eq!(expected.out(), rlp::encode(&action).into_vec());
+    }
+
+    #[test]
+    fn nomination_seq_starts_from_one() {
+        let action = Action::<SoloMessage>::SelfNominate {
+            deposit: 100,
+            metadata: vec![],
+            nomination_seq: Some(0),
+        };
+        let mut params = CommonParams::default_for_test();
+        params.set_nomination_seq_enabled_for_test(true);
+        assert!(action.verify(&params, None, None).is_err());
+    }
+
+    #[test]
+    fn nomination_seq_is_rejected_until_the_params_enable_it() {
+        let action = Action::<SoloMessage>::SelfNominate {
+            deposit: 100,
+            metadata: vec![],
+            nomination_seq: Some(1),
+        };
+        let mut params = CommonParams::default_for_test();
+        assert!(action.verify(&params, None, None).is_err());
+        params.set_nomination_seq_enabled_for_test(true);
+        assert_eq!(Ok(()), action.verify(&params, None, None));
+    }
+
+    #[test]
+    fn decode_fail_if_self_nominate_has_too_many_items() {
+        let mut s = RlpStream::new_list(5);
+        s.append(&ACTION_TAG_SELF_NOMINATE).append(&100u64).append(&b"metadata".to_vec()).append(&1u64).append(&2u64);
+        assert_eq!(
+            Err(DecoderError::RlpInvalidLength {
+                expected: 4,
+                got: 5,
+            }),
+            UntrustedRlp::new(&s.out()).as_val::<Action<SoloMessage>>()
+        );
+    }
+
+    #[test]
+    fn metadata_seq_of_change_params() {
+        let action = Action::<SoloMessage>::ChangeParams {
+            metadata_seq: 3,
+            params: CommonParams::default_for_test().into(),
+            signatures: vec![Signature::random()],
+        };
+        assert_eq!(Some(3), change_params_metadata_seq(&rlp::encode(&action)));
+
+        let action = Action::<SoloMessage>::TransferCCS {
+            address: Address::random(),
+            quantity: 3,
+        };
+        assert_eq!(None, change_params_metadata_seq(&rlp::encode(&action)));
+        assert_eq!(None, change_params_metadata_seq(&[0xc0]));
+    }
+
     struct ConsensusMessageInfo {
         pub height: u64,
         pub view: u64,
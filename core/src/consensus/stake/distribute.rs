@@ -21,6 +21,42 @@ use ckey::Address;
 use std::collections::hash_map;
 use std::collections::HashMap;
 
+const PERMILLE: u64 = 1000;
+
+/// How the reward and the fees of a block are paid.
+#[derive(Debug, PartialEq)]
+pub struct RewardDistribution {
+    /// The part of the minimum fees that is removed from the supply.
+    pub burnt: u64,
+    /// The rest of the minimum fees, shared by the stakeholders in proportion to their stakes.
+    pub shares: Vec<(Address, u64)>,
+    /// The block reward, the fees above the minimum fees, and the remainder of the shares.
+    pub author_reward: u64,
+}
+
+/// Burns `fee_burn_permille` of the minimum fees, and distributes the rest of them to the stakeholders.
+/// The author takes everything else.
+pub fn distribute_rewards(
+    total_reward: u64,
+    total_min_fee: u64,
+    fee_burn_permille: u64,
+    stakes: &HashMap<Address, u64>,
+) -> RewardDistribution {
+    assert!(total_min_fee <= total_reward);
+    assert!(fee_burn_permille <= PERMILLE);
+    let burnt =
+        u64::try_from(u128::from(total_min_fee) * u128::from(fee_burn_permille) / u128::from(PERMILLE)).unwrap();
+
+    let mut distributor = fee_distribute(total_min_fee - burnt, stakes);
+    let shares = (&mut distributor).map(|(address, share)| (*address, share)).collect();
+    let author_reward = total_reward - total_min_fee + distributor.remaining_fee();
+    RewardDistribution {
+        burnt,
+        shares,
+        author_reward,
+    }
+}
+
 pub fn fee_distribute(total_min_fee: u64, stakes: &HashMap<Address, u64>) -> FeeDistributeIter {
     FeeDistributeIter {
         total_stakes: stakes.values().sum(),
@@ -105,4 +141,70 @@ mod tests {
             expected
         });
     }
+
+    fn assert_conservation(total_reward: u64, distribution: &RewardDistribution) {
+        let shares: u64 = distribution.shares.iter().map(|(_, share)| share).sum();
+        assert_eq!(total_reward, shares + distribution.author_reward + distribution.burnt);
+    }
+
+    #[test]
+    fn nothing_is_burnt_at_zero_permille() {
+        let address1 = Address::random();
+        let address2 = Address::random();
+        let mut stakes = HashMap::new();
+        stakes.insert(address1, 10);
+        stakes.insert(address2, 30);
+
+        let distribution = distribute_rewards(1_000, 100, 0, &stakes);
+        assert_eq!(0, distribution.burnt);
+        let shares: HashMap<Address, u64> = distribution.shares.iter().cloned().collect();
+        assert_eq!(shares, fee_distribute(100, &stakes).map(|(k, v)| (*k, v)).collect());
+        assert_eq!(900, distribution.author_reward);
+        assert_conservation(1_000, &distribution);
+    }
+
+    #[test]
+    fn burnt_fee_is_not_distributed() {
+        let address1 = Address::random();
+        let address2 = Address::random();
+        let mut stakes = HashMap::new();
+        stakes.insert(address1, 10);
+        stakes.insert(address2, 30);
+
+        let distribution = distribute_rewards(1_000, 100, 200, &stakes);
+        assert_eq!(20, distribution.burnt);
+        let shares: HashMap<Address, u64> = distribution.shares.iter().cloned().collect();
+        assert_eq!(Some(&20), shares.get(&address1));
+        assert_eq!(Some(&60), shares.get(&address2));
+        assert_eq!(900, distribution.author_reward);
+        assert_conservation(1_000, &distribution);
+    }
+
+    #[test]
+    fn author_takes_the_remainder_of_the_shares_after_burning() {
+        let addresses: Vec<_> = (0..7).map(|_| Address::random()).collect();
+        let mut stakes = HashMap::with_capacity(addresses.len());
+        for (index, address) in addresses.iter().enumerate() {
+            stakes.insert(*address, index as u64 + 1);
+        }
+
+        for fee_burn_permille in &[0, 1, 333, 999, 1000] {
+            for (total_reward, total_min_fee) in &[(0, 0), (1, 1), (997, 997), (12_345, 9_871), (std::u64::MAX, 31)] {
+                let distribution = distribute_rewards(*total_reward, *total_min_fee, *fee_burn_permille, &stakes);
+                assert!(distribution.burnt <= *total_min_fee);
+                assert_conservation(*total_reward, &distribution);
+            }
+        }
+    }
+
+    #[test]
+    fn everything_is_burnt_at_one_thousand_permille() {
+        let mut stakes = HashMap::new();
+        stakes.insert(Address::random(), 10);
+
+        let distribution = distribute_rewards(150, 100, 1000, &stakes);
+        assert_eq!(100, distribution.burnt);
+        assert!(distribution.shares.iter().all(|(_, share)| *share == 0));
+        assert_eq!(50, distribution.author_reward);
+    }
 }
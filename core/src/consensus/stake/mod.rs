@@ -35,10 +35,13 @@ use parking_lot::RwLock;
 use primitives::{Bytes, H256};
 use rlp::{Decodable, UntrustedRlp};
 
-pub use self::action_data::{Banned, Validator, Validators};
-use self::action_data::{Candidates, Delegation, IntermediateRewards, Jail, ReleaseResult, StakeAccount, Stakeholders};
-pub use self::actions::Action;
-pub use self::distribute::fee_distribute;
+pub use self::action_data::{Banned, ExclusionReason, Validator, Validators, VALIDATORS_KEY};
+use self::action_data::{
+    Candidates, Delegation, ElectionExclusions, IntermediateRewards, Jail, NominationRecord, ReleaseResult,
+    StakeAccount, Stakeholders, TotalBurnt,
+};
+pub use self::actions::{change_params_metadata_seq, Action};
+pub use self::distribute::{distribute_rewards, fee_distribute, RewardDistribution};
 use super::ValidatorSet;
 
 pub const CUSTOM_ACTION_HANDLER_ID: u64 = 2;
@@ -100,7 +103,17 @@ impl<M: Message> ActionHandler for Stake<M> {
             Action::TransferCCS {
                 address,
                 quantity,
-            } => transfer_ccs(state, fee_payer, &address, quantity),
+            } => {
+                // The params changed in this block take effect from the next block.
+                let transfers_enabled = state
+                    .committed_metadata()?
+                    .and_then(|metadata| metadata.params().map(CommonParams::ccs_transfers_enabled))
+                    .unwrap_or(true);
+                if !transfers_enabled {
+                    return Err(RuntimeError::CCSTransfersDisabled.into())
+                }
+                transfer_ccs(state, fee_payer, &address, quantity)
+            }
             Action::DelegateCCS {
                 address,
                 quantity,
@@ -112,7 +125,13 @@ impl<M: Message> ActionHandler for Stake<M> {
             Action::SelfNominate {
                 deposit,
                 metadata,
+                nomination_seq,
             } => {
+                // The params changed in this block take effect from the next block.
+                let nomination_seq_enabled = state
+                    .committed_metadata()?
+                    .and_then(|metadata| metadata.params().map(CommonParams::nomination_seq_enabled))
+                    .unwrap_or(false);
                 let (current_term, nomination_ends_at) = {
                     let metadata = state.metadata()?.expect("Metadata must exist");
                     const DEFAULT_NOMINATION_EXPIRATION: u64 = 24;
@@ -124,8 +143,22 @@ impl<M: Message> ActionHandler for Stake<M> {
                     let nomination_ends_at = current_term + expiration;
                     (current_term, nomination_ends_at)
                 };
-                self_nominate(state, fee_payer, sender_public, deposit, current_term, nomination_ends_at, metadata)
+                self_nominate(
+                    state,
+                    fee_payer,
+                    sender_public,
+                    deposit,
+                    current_term,
+                    nomination_ends_at,
+                    metadata,
+                    nomination_seq_enabled,
+                    nomination_seq,
+                )
             }
+            Action::SetSigningKey {
+                candidate_address,
+                signing_public,
+            } => set_signing_key(state, fee_payer, sender_public, &candidate_address, &signing_public),
             Action::ChangeParams {
                 metadata_seq,
                 params,
@@ -141,8 +174,10 @@ impl<M: Message> ActionHandler for Stake<M> {
                 let parent_hash =
                     client.block_header(&(message1.height() - 1).into()).expect("Parent header verified").hash();
                 let malicious_user_public = validator_set.get(&parent_hash, message1.signer_index());
+                let malicious_user =
+                    validator_set.owner_address(&parent_hash, &public_to_address(&malicious_user_public));
 
-                ban(state, sender_public, public_to_address(&malicious_user_public))
+                ban(state, sender_public, malicious_user)
             }
         }
     }
@@ -234,11 +269,23 @@ fn self_nominate(
     current_term: u64,
     nomination_ends_at: u64,
     metadata: Bytes,
+    nomination_seq_enabled: bool,
+    nomination_seq: Option<u64>,
 ) -> StateResult<()> {
     if public_to_address(sender_public) != *fee_payer {
         return Err(RuntimeError::FailedToHandleCustomAction("Cannot Self-nominate with regular key".to_string()).into())
     }
 
+    // The seq of the transaction only prevents the exact replays.
+    // A nomination signed again with an outdated payload must not overwrite the newer metadata.
+    let record = if nomination_seq_enabled {
+        let mut record = NominationRecord::load_from_state(state, fee_payer)?;
+        record.accept(fee_payer, nomination_seq, current_term)?;
+        Some(record)
+    } else {
+        None
+    };
+
     let blacklist = Banned::load_from_state(state)?;
     if blacklist.is_banned(&fee_payer) {
         return Err(RuntimeError::FailedToHandleCustomAction("Account is blacklisted".to_string()).into())
@@ -257,11 +304,20 @@ fn self_nominate(
     };
 
     let mut candidates = Candidates::load_from_state(&state)?;
+    if candidates.is_signing_pubkey_of_others(sender_public, fee_payer) {
+        return Err(RuntimeError::FailedToHandleCustomAction(
+            "The key is the signing key of another candidate".to_string(),
+        )
+        .into())
+    }
     state.sub_balance(fee_payer, deposit)?;
     candidates.add_deposit(sender_public, total_deposit, nomination_ends_at, metadata);
 
     jail.save_to_state(state)?;
     candidates.save_to_state(state)?;
+    if let Some(record) = record {
+        record.save_to_state(state, fee_payer)?;
+    }
 
     ctrace!(
         ENGINE,
@@ -274,6 +330,32 @@ fn self_nominate(
     Ok(())
 }
 
+fn set_signing_key(
+    state: &mut TopLevelState,
+    fee_payer: &Address,
+    sender_public: &Public,
+    candidate_address: &Address,
+    signing_public: &Public,
+) -> StateResult<()> {
+    if public_to_address(sender_public) != *fee_payer {
+        return Err(
+            RuntimeError::FailedToHandleCustomAction("Cannot set the signing key with regular key".to_string()).into()
+        )
+    }
+    if fee_payer != candidate_address {
+        return Err(
+            RuntimeError::FailedToHandleCustomAction("Only the candidate can set its signing key".to_string()).into()
+        )
+    }
+
+    let mut candidates = Candidates::load_from_state(state)?;
+    candidates.set_signing_key(candidate_address, *signing_public)?;
+    candidates.save_to_state(state)?;
+
+    ctrace!(ENGINE, "Set the signing key. candidate: {}, signing key: {}", candidate_address, signing_public);
+    Ok(())
+}
+
 pub fn get_stakes(state: &TopLevelState) -> StateResult<HashMap<Address, u64>> {
     let stakeholders = Stakeholders::load_from_state(state)?;
     let mut result = HashMap::new();
@@ -289,6 +371,87 @@ pub fn get_validators(state: &TopLevelState) -> StateResult<Validators> {
     Validators::load_from_state(state)
 }
 
+/// Whether the engine signer can sign the blocks, decided from the stake state.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SignerEligibility {
+    /// It's one of the current validators.
+    Validator,
+    /// It's nominated but not elected for the current term.
+    Candidate,
+    /// It's jailed. It can nominate itself again after the term `custody_until`,
+    /// and is kicked out at the end of the term `released_at` if it doesn't.
+    Jailed {
+        custody_until: u64,
+        released_at: u64,
+    },
+    Banned,
+    /// It's neither a validator nor a candidate, so it never gets to sign.
+    Unknown,
+}
+
+/// Decides the eligibility of the signer, given the signing keys of the current validators.
+/// The candidate is found by its own key or by the signing key it registered.
+/// The jailed and the banned are no longer candidates, so they're found only if they sign with their own keys.
+pub fn signer_eligibility(
+    state: &TopLevelState,
+    signer: &Address,
+    validators: &[Public],
+) -> StateResult<SignerEligibility> {
+    let candidates = Candidates::load_from_state(state)?;
+    let candidate = candidates.get_candidate_by_signer(signer);
+    let account = candidate.map_or(*signer, |candidate| public_to_address(&candidate.pubkey));
+    if Banned::load_from_state(state)?.is_banned(&account) {
+        return Ok(SignerEligibility::Banned)
+    }
+    if let Some(prisoner) = Jail::load_from_state(state)?.get_prisoner(&account) {
+        return Ok(SignerEligibility::Jailed {
+            custody_until: prisoner.custody_until,
+            released_at: prisoner.released_at,
+        })
+    }
+    if validators.iter().any(|validator| public_to_address(validator) == *signer) {
+        return Ok(SignerEligibility::Validator)
+    }
+    if candidate.is_some() {
+        return Ok(SignerEligibility::Candidate)
+    }
+    Ok(SignerEligibility::Unknown)
+}
+
+/// A candidate with the stakes that decide whether it's elected.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CandidateStatus {
+    pub pubkey: Public,
+    pub deposit: u64,
+    pub nomination_ends_at: u64,
+    pub metadata: Bytes,
+    /// The sum of the delegations to the candidate, including its own.
+    pub delegation: u64,
+    /// The quantity that the candidate delegates to itself.
+    pub self_nomination: u64,
+    /// Why it was not elected at the last election, though it was eligible otherwise.
+    pub exclusion: Option<ExclusionReason>,
+}
+
+pub fn candidate_statuses(state: &TopLevelState) -> StateResult<Vec<CandidateStatus>> {
+    let delegatees = Stakeholders::delegatees(state)?;
+    let exclusions = ElectionExclusions::load_from_state(state)?;
+    let mut statuses = Vec::new();
+    for candidate in Candidates::load_from_state(state)?.iter() {
+        let address = public_to_address(&candidate.pubkey);
+        statuses.push(CandidateStatus {
+            pubkey: candidate.pubkey,
+            deposit: candidate.deposit,
+            nomination_ends_at: candidate.nomination_ends_at,
+            metadata: candidate.metadata.clone(),
+            delegation: delegatees.get(&address).cloned().unwrap_or(0),
+            self_nomination: Delegation::load_from_state(state, &address)?.get_quantity(&address),
+            exclusion: exclusions.get(&candidate.pubkey),
+        });
+    }
+    Ok(statuses)
+}
+
 pub fn add_intermediate_rewards(state: &mut TopLevelState, address: Address, reward: u64) -> StateResult<()> {
     let mut rewards = IntermediateRewards::load_from_state(state)?;
     rewards.add_quantity(address, reward);
@@ -309,6 +472,21 @@ pub fn move_current_to_previous_intermediate_rewards(state: &mut TopLevelState)
     rewards.save_to_state(state)
 }
 
+/// Returns the sum of the fees burnt until the state.
+pub fn get_total_burnt(state: &TopLevelState) -> StateResult<u64> {
+    Ok(TotalBurnt::load_from_state(state)?.total())
+}
+
+pub fn add_burnt_fee(state: &mut TopLevelState, burnt: u64) -> StateResult<()> {
+    // The state doesn't change while nothing is burnt.
+    if burnt == 0 {
+        return Ok(())
+    }
+    let mut total = TotalBurnt::load_from_state(state)?;
+    total.add(burnt);
+    total.save_to_state(state)
+}
+
 pub fn update_validator_weights(state: &mut TopLevelState, block_author: &Address) -> StateResult<()> {
     let mut validators = Validators::load_from_state(state)?;
     validators.update_weight(block_author);
@@ -382,8 +560,9 @@ pub fn on_term_close(
 
     jail(state, inactive_validators, custody_until, kick_at)?;
 
-    let validators = Validators::elect(state)?;
+    let (validators, exclusions) = Validators::elect(state)?;
     validators.save_to_state(state)?;
+    exclusions.save_to_state(state)?;
 
     state.increase_term_id(last_term_finished_block_num)?;
     Ok(())
@@ -418,6 +597,8 @@ fn release_jailed_prisoners(state: &mut TopLevelState, current_term: u64) -> Sta
     let released = jailed.drain_released_prisoners(current_term);
     for prisoner in &released {
         state.add_balance(&prisoner.address, prisoner.deposit)?;
+        // Nothing of the kicked out account is left in the stake states.
+        NominationRecord::remove_from_state(state, &prisoner.address);
         ctrace!(ENGINE, "on_term_close::released. prisoner: {}, deposit: {}", prisoner.address, prisoner.deposit);
     }
     jailed.save_to_state(state)?;
@@ -464,6 +645,8 @@ pub fn ban(state: &mut TopLevelState, informant: &Public, criminal: Address) ->
     jailed.remove(&criminal);
     banned.add(criminal);
     validators.remove(&criminal);
+    // The banned account never nominates itself again.
+    NominationRecord::remove_from_state(state, &criminal);
 
     jailed.save_to_state(state)?;
     banned.save_to_state(state)?;
@@ -509,10 +692,14 @@ mod tests {
     use super::action_data::get_account_key;
     use super::*;
 
+    use crate::client::TracingActionHandler;
+
     use consensus::solo::SoloMessage;
-    use consensus::stake::action_data::{get_delegation_key, Candidate, Prisoner};
+    use consensus::stake::action_data::{
+        get_delegation_key, Candidate, Prisoner, BANNED_KEY, CANDIDATES_KEY, JAIL_KEY, STAKEHOLDER_ADDRESSES_KEY,
+    };
     use cstate::tests::helpers;
-    use cstate::TopStateView;
+    use cstate::{ActionDataAccess, StateWithCache, TopStateView};
     use rlp::Encodable;
 
     fn metadata_for_election() -> TopLevelState {
@@ -549,6 +736,20 @@ mod tests {
         assert!(!stakeholders.contains(&address2));
     }
 
+    #[test]
+    fn burnt_fees_are_accumulated() {
+        let mut state = helpers::get_temp_state();
+        let root = state.commit().unwrap();
+        assert_eq!(0, get_total_burnt(&state).unwrap());
+
+        add_burnt_fee(&mut state, 0).unwrap();
+        assert_eq!(root, state.commit().unwrap(), "Burning nothing doesn't change the state");
+
+        add_burnt_fee(&mut state, 30).unwrap();
+        add_burnt_fee(&mut state, 12).unwrap();
+        assert_eq!(42, get_total_burnt(&state).unwrap());
+    }
+
     #[test]
     fn balance_transfer_partial() {
         let address1 = Address::random();
@@ -621,7 +822,7 @@ mod tests {
             Stake::<SoloMessage>::new(genesis_stakes)
         };
         stake.init(&mut state).unwrap();
-        self_nominate(&mut state, &delegatee, &delegatee_pubkey, 0, 0, 10, b"".to_vec()).unwrap();
+        self_nominate(&mut state, &delegatee, &delegatee_pubkey, 0, 0, 10, b"".to_vec(), false, None).unwrap();
 
         let action = Action::<SoloMessage>::DelegateCCS {
             address: delegatee,
@@ -664,7 +865,7 @@ mod tests {
             Stake::<SoloMessage>::new(genesis_stakes)
         };
         stake.init(&mut state).unwrap();
-        self_nominate(&mut state, &delegatee, &delegatee_pubkey, 0, 0, 10, b"".to_vec()).unwrap();
+        self_nominate(&mut state, &delegatee, &delegatee_pubkey, 0, 0, 10, b"".to_vec(), false, None).unwrap();
 
         let action = Action::<SoloMessage>::DelegateCCS {
             address: delegatee,
@@ -732,7 +933,7 @@ mod tests {
             Stake::<SoloMessage>::new(genesis_stakes)
         };
         stake.init(&mut state).unwrap();
-        self_nominate(&mut state, &delegatee, &delegatee_pubkey, 0, 0, 10, b"".to_vec()).unwrap();
+        self_nominate(&mut state, &delegatee, &delegatee_pubkey, 0, 0, 10, b"".to_vec(), false, None).unwrap();
 
         let action = Action::<SoloMessage>::DelegateCCS {
             address: delegatee,
@@ -742,6 +943,100 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn trace_delegate() {
+        let delegatee_pubkey = Public::random();
+        let delegatee = public_to_address(&delegatee_pubkey);
+        let delegator_pubkey = Public::random();
+        let delegator = public_to_address(&delegator_pubkey);
+
+        let mut state = helpers::get_temp_state();
+        let stake = {
+            let mut genesis_stakes = HashMap::new();
+            genesis_stakes.insert(delegatee, 100);
+            genesis_stakes.insert(delegator, 100);
+            Stake::<SoloMessage>::new(genesis_stakes)
+        };
+        stake.init(&mut state).unwrap();
+        self_nominate(&mut state, &delegatee, &delegatee_pubkey, 0, 0, 10, b"".to_vec(), false, None).unwrap();
+
+        let action = Action::<SoloMessage>::DelegateCCS {
+            address: delegatee,
+            quantity: 40,
+        };
+        let tracer = TracingActionHandler::new(&stake);
+        let result = tracer.execute(&action.rlp_bytes(), &mut state, &delegator, &delegator_pubkey);
+        assert_eq!(result, Ok(()));
+
+        let trace = tracer.into_trace().unwrap();
+        assert_eq!(trace.handler_id, CUSTOM_ACTION_HANDLER_ID);
+        assert_eq!(trace.error, None);
+
+        let reads: Vec<_> =
+            trace.accesses.iter().filter(|access| !access.is_write()).map(|access| *access.key()).collect();
+        assert_eq!(reads, vec![
+            *CANDIDATES_KEY,
+            *BANNED_KEY,
+            *JAIL_KEY,
+            get_account_key(&delegator),
+            get_delegation_key(&delegator)
+        ]);
+        let writes: Vec<_> = trace.accesses.iter().filter(|access| access.is_write()).cloned().collect();
+        assert_eq!(writes, vec![
+            ActionDataAccess::Write {
+                key: get_delegation_key(&delegator),
+                old: None,
+                new: Some(state.action_data(&get_delegation_key(&delegator)).unwrap().unwrap().to_vec()),
+            },
+            ActionDataAccess::Write {
+                key: get_account_key(&delegator),
+                old: Some(rlp::encode(&100u64).into_vec()),
+                new: Some(rlp::encode(&60u64).into_vec()),
+            }
+        ]);
+        assert!(
+            trace.accesses.iter().all(|access| access.key() != &*STAKEHOLDER_ADDRESSES_KEY),
+            "Delegation doesn't touch the stakeholders"
+        );
+    }
+
+    #[test]
+    fn trace_delegate_too_much() {
+        let delegatee_pubkey = Public::random();
+        let delegatee = public_to_address(&delegatee_pubkey);
+        let delegator_pubkey = Public::random();
+        let delegator = public_to_address(&delegator_pubkey);
+
+        let mut state = helpers::get_temp_state();
+        let stake = {
+            let mut genesis_stakes = HashMap::new();
+            genesis_stakes.insert(delegatee, 100);
+            genesis_stakes.insert(delegator, 100);
+            Stake::<SoloMessage>::new(genesis_stakes)
+        };
+        stake.init(&mut state).unwrap();
+        self_nominate(&mut state, &delegatee, &delegatee_pubkey, 0, 0, 10, b"".to_vec(), false, None).unwrap();
+
+        let action = Action::<SoloMessage>::DelegateCCS {
+            address: delegatee,
+            quantity: 200,
+        };
+        let tracer = TracingActionHandler::new(&stake);
+        let result = tracer.execute(&action.rlp_bytes(), &mut state, &delegator, &delegator_pubkey);
+        assert!(result.is_err());
+
+        let trace = tracer.into_trace().unwrap();
+        assert_eq!(trace.error, Some(result.unwrap_err().to_string()));
+        assert!(trace.accesses.iter().all(|access| !access.is_write()), "A failed delegation must not write");
+        assert!(trace.accesses.contains(&ActionDataAccess::Read {
+            key: get_account_key(&delegator),
+            value: Some(rlp::encode(&100u64).into_vec()),
+        }));
+
+        let delegator_account = StakeAccount::load_from_state(&state, &delegator).unwrap();
+        assert_eq!(delegator_account.balance, 100, "Shouldn't be touched");
+    }
+
     #[test]
     fn can_transfer_within_non_delegated_tokens() {
         let delegatee_pubkey = Public::random();
@@ -757,7 +1052,7 @@ mod tests {
             Stake::<SoloMessage>::new(genesis_stakes)
         };
         stake.init(&mut state).unwrap();
-        self_nominate(&mut state, &delegatee, &delegatee_pubkey, 0, 0, 10, b"".to_vec()).unwrap();
+        self_nominate(&mut state, &delegatee, &delegatee_pubkey, 0, 0, 10, b"".to_vec(), false, None).unwrap();
 
         let action = Action::<SoloMessage>::DelegateCCS {
             address: delegatee,
@@ -788,7 +1083,7 @@ mod tests {
             Stake::<SoloMessage>::new(genesis_stakes)
         };
         stake.init(&mut state).unwrap();
-        self_nominate(&mut state, &delegatee, &delegatee_pubkey, 0, 0, 10, b"".to_vec()).unwrap();
+        self_nominate(&mut state, &delegatee, &delegatee_pubkey, 0, 0, 10, b"".to_vec(), false, None).unwrap();
 
         let action = Action::<SoloMessage>::DelegateCCS {
             address: delegatee,
@@ -804,6 +1099,59 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn ccs_transfers_are_paused_by_the_params_from_the_next_block() {
+        let delegatee_pubkey = Public::random();
+        let delegatee = public_to_address(&delegatee_pubkey);
+        let sender_pubkey = Public::random();
+        let sender = public_to_address(&sender_pubkey);
+        let receiver = Address::random();
+
+        let mut state = helpers::get_temp_state_with_metadata();
+        let stake = {
+            let mut genesis_stakes = HashMap::new();
+            genesis_stakes.insert(delegatee, 100);
+            genesis_stakes.insert(sender, 100);
+            Stake::<SoloMessage>::new(genesis_stakes)
+        };
+        stake.init(&mut state).unwrap();
+        self_nominate(&mut state, &delegatee, &delegatee_pubkey, 0, 0, 10, b"".to_vec(), false, None).unwrap();
+        let transfer = Action::<SoloMessage>::TransferCCS {
+            address: receiver,
+            quantity: 10,
+        }
+        .rlp_bytes();
+        assert_eq!(Ok(()), stake.execute(&transfer, &mut state, &sender, &sender_pubkey));
+
+        // The transfers are still allowed in the block that pauses them.
+        let mut paused = CommonParams::default_for_test();
+        paused.set_ccs_transfers_enabled_for_test(false);
+        assert_eq!(Ok(()), state.update_params(0, paused));
+        assert_eq!(Ok(()), stake.execute(&transfer, &mut state, &sender, &sender_pubkey));
+        state.commit().unwrap();
+
+        assert_eq!(
+            Err(RuntimeError::CCSTransfersDisabled.into()),
+            stake.execute(&transfer, &mut state, &sender, &sender_pubkey)
+        );
+        let delegate = Action::<SoloMessage>::DelegateCCS {
+            address: delegatee,
+            quantity: 10,
+        };
+        assert_eq!(Ok(()), stake.execute(&delegate.rlp_bytes(), &mut state, &sender, &sender_pubkey));
+
+        let mut resumed = CommonParams::default_for_test();
+        resumed.set_ccs_transfers_enabled_for_test(true);
+        assert_eq!(Ok(()), state.update_params(1, resumed));
+        state.commit().unwrap();
+        assert_eq!(Ok(()), stake.execute(&transfer, &mut state, &sender, &sender_pubkey));
+
+        let sender_account = StakeAccount::load_from_state(&state, &sender).unwrap();
+        assert_eq!(60, sender_account.balance);
+        let receiver_account = StakeAccount::load_from_state(&state, &receiver).unwrap();
+        assert_eq!(30, receiver_account.balance);
+    }
+
     #[test]
     fn can_revoke_delegated_tokens() {
         let delegatee_pubkey = Public::random();
@@ -819,7 +1167,7 @@ mod tests {
             Stake::<SoloMessage>::new(genesis_stakes)
         };
         stake.init(&mut state).unwrap();
-        self_nominate(&mut state, &delegatee, &delegatee_pubkey, 0, 0, 10, b"".to_vec()).unwrap();
+        self_nominate(&mut state, &delegatee, &delegatee_pubkey, 0, 0, 10, b"".to_vec(), false, None).unwrap();
 
         let action = Action::<SoloMessage>::DelegateCCS {
             address: delegatee,
@@ -857,7 +1205,7 @@ mod tests {
             Stake::<SoloMessage>::new(genesis_stakes)
         };
         stake.init(&mut state).unwrap();
-        self_nominate(&mut state, &delegatee, &delegatee_pubkey, 0, 0, 10, b"".to_vec()).unwrap();
+        self_nominate(&mut state, &delegatee, &delegatee_pubkey, 0, 0, 10, b"".to_vec(), false, None).unwrap();
 
         let action = Action::<SoloMessage>::DelegateCCS {
             address: delegatee,
@@ -895,7 +1243,7 @@ mod tests {
             Stake::<SoloMessage>::new(genesis_stakes)
         };
         stake.init(&mut state).unwrap();
-        self_nominate(&mut state, &delegatee, &delegatee_pubkey, 0, 0, 10, b"".to_vec()).unwrap();
+        self_nominate(&mut state, &delegatee, &delegatee_pubkey, 0, 0, 10, b"".to_vec(), false, None).unwrap();
 
         let action = Action::<SoloMessage>::DelegateCCS {
             address: delegatee,
@@ -928,7 +1276,7 @@ mod tests {
         stake.init(&mut state).unwrap();
 
         // TODO: change with stake.execute()
-        let result = self_nominate(&mut state, &address, &address_pubkey, 0, 0, 5, b"metadata1".to_vec());
+        let result = self_nominate(&mut state, &address, &address_pubkey, 0, 0, 5, b"metadata1".to_vec(), false, None);
         assert_eq!(result, Ok(()));
 
         assert_eq!(state.balance(&address).unwrap(), 1000);
@@ -940,11 +1288,13 @@ mod tests {
                 deposit: 0,
                 nomination_ends_at: 5,
                 metadata: b"metadata1".to_vec(),
+                signing_pubkey: None,
             }),
             "nomination_ends_at should be updated even if candidate deposits 0"
         );
 
-        let result = self_nominate(&mut state, &address, &address_pubkey, 200, 0, 10, b"metadata2".to_vec());
+        let result =
+            self_nominate(&mut state, &address, &address_pubkey, 200, 0, 10, b"metadata2".to_vec(), false, None);
         assert_eq!(result, Ok(()));
 
         assert_eq!(state.balance(&address).unwrap(), 800);
@@ -956,10 +1306,11 @@ mod tests {
                 deposit: 200,
                 nomination_ends_at: 10,
                 metadata: b"metadata2".to_vec(),
+                signing_pubkey: None,
             })
         );
 
-        let result = self_nominate(&mut state, &address, &address_pubkey, 0, 0, 15, b"metadata3".to_vec());
+        let result = self_nominate(&mut state, &address, &address_pubkey, 0, 0, 15, b"metadata3".to_vec(), false, None);
         assert_eq!(result, Ok(()));
 
         assert_eq!(state.balance(&address).unwrap(), 800);
@@ -971,6 +1322,7 @@ mod tests {
                 deposit: 200,
                 nomination_ends_at: 15,
                 metadata: b"metadata3".to_vec(),
+                signing_pubkey: None,
             }),
             "nomination_ends_at should be updated even if candidate deposits 0"
         );
@@ -988,7 +1340,7 @@ mod tests {
         stake.init(&mut state).unwrap();
 
         // TODO: change with stake.execute()
-        let result = self_nominate(&mut state, &address, &address_pubkey, 2000, 0, 5, b"".to_vec());
+        let result = self_nominate(&mut state, &address, &address_pubkey, 2000, 0, 5, b"".to_vec(), false, None);
         assert!(result.is_err(), "Cannot self-nominate without a sufficient balance");
     }
 
@@ -1013,7 +1365,7 @@ mod tests {
         stake.init(&mut state).unwrap();
 
         // TODO: change with stake.execute()
-        self_nominate(&mut state, &address, &address_pubkey, 200, 0, 30, b"".to_vec()).unwrap();
+        self_nominate(&mut state, &address, &address_pubkey, 200, 0, 30, b"".to_vec(), false, None).unwrap();
 
         let result = on_term_close(&mut state, pseudo_term_to_block_num_calculator(29), &[]);
         assert_eq!(result, Ok(()));
@@ -1027,6 +1379,7 @@ mod tests {
                 deposit: 200,
                 nomination_ends_at: 30,
                 metadata: b"".to_vec(),
+                signing_pubkey: None,
             }),
             "Keep deposit before expiration",
         );
@@ -1039,6 +1392,183 @@ mod tests {
         assert_eq!(candidates.get_candidate(&address), None, "Removed from candidates after expiration");
     }
 
+    #[test]
+    fn stale_nomination_cannot_resurrect_the_metadata_of_the_retired_candidate() {
+        let address_pubkey = Public::random();
+        let address = public_to_address(&address_pubkey);
+
+        let mut state = metadata_for_election();
+        let mut params = *state.metadata().unwrap().unwrap().params().unwrap();
+        params.set_nomination_seq_enabled_for_test(true);
+        assert_eq!(Ok(()), state.update_params(1, params));
+        state.commit().unwrap();
+        increase_term_id_until(&mut state, 29);
+        state.add_balance(&address, 1000).unwrap();
+
+        let stake = Stake::<SoloMessage>::new(HashMap::new());
+        stake.init(&mut state).unwrap();
+
+        self_nominate(&mut state, &address, &address_pubkey, 200, 29, 30, b"old".to_vec(), true, Some(1)).unwrap();
+        self_nominate(&mut state, &address, &address_pubkey, 0, 29, 30, b"new".to_vec(), true, Some(2)).unwrap();
+        let candidates = Candidates::load_from_state(&state).unwrap();
+        assert_eq!(b"new".to_vec(), candidates.get_candidate(&address).unwrap().metadata);
+
+        on_term_close(&mut state, pseudo_term_to_block_num_calculator(30), &[]).unwrap();
+        assert_eq!(None, Candidates::load_from_state(&state).unwrap().get_candidate(&address), "Retired");
+        assert_eq!(
+            NominationRecord {
+                seq: 2,
+                term: 29,
+            },
+            NominationRecord::load_from_state(&state, &address).unwrap()
+        );
+
+        // The automation signs the outdated payload again with a fresh transaction.
+        let replayed = Action::<SoloMessage>::SelfNominate {
+            deposit: 200,
+            metadata: b"old".to_vec(),
+            nomination_seq: Some(1),
+        };
+        assert_eq!(
+            Err(RuntimeError::StaleNomination {
+                address,
+                last_seq: 2,
+                got: 1,
+            }
+            .into()),
+            stake.execute(&replayed.rlp_bytes(), &mut state, &address, &address_pubkey)
+        );
+        let unsequenced = Action::<SoloMessage>::SelfNominate {
+            deposit: 200,
+            metadata: b"old".to_vec(),
+            nomination_seq: None,
+        };
+        assert_eq!(
+            Err(RuntimeError::StaleNomination {
+                address,
+                last_seq: 2,
+                got: 0,
+            }
+            .into()),
+            stake.execute(&unsequenced.rlp_bytes(), &mut state, &address, &address_pubkey)
+        );
+        assert_eq!(None, Candidates::load_from_state(&state).unwrap().get_candidate(&address));
+        assert_eq!(1000, state.balance(&address).unwrap(), "The deposit is not taken");
+
+        let renewed = Action::<SoloMessage>::SelfNominate {
+            deposit: 200,
+            metadata: b"newer".to_vec(),
+            nomination_seq: Some(3),
+        };
+        assert_eq!(Ok(()), stake.execute(&renewed.rlp_bytes(), &mut state, &address, &address_pubkey));
+        let candidates = Candidates::load_from_state(&state).unwrap();
+        assert_eq!(b"newer".to_vec(), candidates.get_candidate(&address).unwrap().metadata);
+        assert_eq!(800, state.balance(&address).unwrap());
+    }
+
+    #[test]
+    fn nominations_without_the_sequence_are_accepted_until_it_is_used() {
+        let address_pubkey = Public::random();
+        let address = public_to_address(&address_pubkey);
+
+        let mut state = helpers::get_temp_state();
+        state.add_balance(&address, 1000).unwrap();
+
+        let stake = Stake::<SoloMessage>::new(HashMap::new());
+        stake.init(&mut state).unwrap();
+
+        assert_eq!(Ok(()), self_nominate(&mut state, &address, &address_pubkey, 0, 0, 5, b"1".to_vec(), true, None));
+        assert_eq!(Ok(()), self_nominate(&mut state, &address, &address_pubkey, 0, 0, 5, b"2".to_vec(), true, None));
+        assert_eq!(Ok(()), self_nominate(&mut state, &address, &address_pubkey, 0, 0, 5, b"3".to_vec(), true, Some(5)));
+        assert_eq!(
+            Err(RuntimeError::StaleNomination {
+                address,
+                last_seq: 5,
+                got: 5,
+            }
+            .into()),
+            self_nominate(&mut state, &address, &address_pubkey, 0, 0, 5, b"4".to_vec(), true, Some(5))
+        );
+        // The sequences don't have to be consecutive.
+        assert_eq!(Ok(()), self_nominate(&mut state, &address, &address_pubkey, 0, 0, 5, b"5".to_vec(), true, Some(7)));
+        let candidates = Candidates::load_from_state(&state).unwrap();
+        assert_eq!(b"5".to_vec(), candidates.get_candidate(&address).unwrap().metadata);
+    }
+
+    #[test]
+    fn nomination_record_is_not_kept_while_the_sequences_are_disabled() {
+        let address_pubkey = Public::random();
+        let address = public_to_address(&address_pubkey);
+
+        let mut state = helpers::get_temp_state();
+        state.add_balance(&address, 1000).unwrap();
+
+        let stake = Stake::<SoloMessage>::new(HashMap::new());
+        stake.init(&mut state).unwrap();
+
+        assert_eq!(
+            Ok(()),
+            self_nominate(&mut state, &address, &address_pubkey, 0, 0, 5, b"1".to_vec(), false, Some(5))
+        );
+        assert_eq!(NominationRecord::default(), NominationRecord::load_from_state(&state, &address).unwrap());
+        assert_eq!(
+            Ok(()),
+            self_nominate(&mut state, &address, &address_pubkey, 0, 0, 5, b"2".to_vec(), false, Some(1))
+        );
+        let candidates = Candidates::load_from_state(&state).unwrap();
+        assert_eq!(b"2".to_vec(), candidates.get_candidate(&address).unwrap().metadata);
+    }
+
+    #[test]
+    fn nomination_record_is_pruned_when_the_prisoner_is_kicked_out() {
+        let address_pubkey = Public::random();
+        let address = public_to_address(&address_pubkey);
+
+        let mut state = metadata_for_election();
+        state.add_balance(&address, 1000).unwrap();
+
+        let stake = Stake::<SoloMessage>::new(HashMap::new());
+        stake.init(&mut state).unwrap();
+
+        let custody_until = 10;
+        let released_at = 20;
+        self_nominate(&mut state, &address, &address_pubkey, 200, 0, 5, b"".to_vec(), true, Some(1)).unwrap();
+        jail(&mut state, &[address], custody_until, released_at).unwrap();
+
+        for current_term in 0..released_at {
+            on_term_close(&mut state, pseudo_term_to_block_num_calculator(current_term), &[]).unwrap();
+        }
+        assert_eq!(
+            NominationRecord {
+                seq: 1,
+                term: 0,
+            },
+            NominationRecord::load_from_state(&state, &address).unwrap(),
+            "The record is kept while the account is in the jail"
+        );
+
+        on_term_close(&mut state, pseudo_term_to_block_num_calculator(released_at), &[]).unwrap();
+        assert_eq!(None, Jail::load_from_state(&state).unwrap().get_prisoner(&address));
+        assert_eq!(NominationRecord::default(), NominationRecord::load_from_state(&state, &address).unwrap());
+    }
+
+    #[test]
+    fn nomination_record_is_pruned_when_the_candidate_is_banned() {
+        let informant_pubkey = Public::random();
+        let criminal_pubkey = Public::random();
+        let criminal = public_to_address(&criminal_pubkey);
+
+        let mut state = helpers::get_temp_state();
+        state.add_balance(&criminal, 1000).unwrap();
+
+        let stake = Stake::<SoloMessage>::new(HashMap::new());
+        stake.init(&mut state).unwrap();
+
+        self_nominate(&mut state, &criminal, &criminal_pubkey, 100, 0, 10, b"".to_vec(), true, Some(1)).unwrap();
+        assert_eq!(Ok(()), ban(&mut state, &informant_pubkey, criminal));
+        assert_eq!(NominationRecord::default(), NominationRecord::load_from_state(&state, &criminal).unwrap());
+    }
+
     #[test]
     fn self_nominate_reverts_delegations_after_expiration() {
         let address_pubkey = Public::random();
@@ -1058,7 +1588,7 @@ mod tests {
         stake.init(&mut state).unwrap();
 
         // TODO: change with stake.execute()
-        self_nominate(&mut state, &address, &address_pubkey, 0, 0, 30, b"".to_vec()).unwrap();
+        self_nominate(&mut state, &address, &address_pubkey, 0, 0, 30, b"".to_vec(), false, None).unwrap();
 
         let action = Action::<SoloMessage>::DelegateCCS {
             address,
@@ -1096,7 +1626,7 @@ mod tests {
 
         // TODO: change with stake.execute()
         let deposit = 200;
-        self_nominate(&mut state, &address, &address_pubkey, deposit, 0, 5, b"".to_vec()).unwrap();
+        self_nominate(&mut state, &address, &address_pubkey, deposit, 0, 5, b"".to_vec(), false, None).unwrap();
 
         let custody_until = 10;
         let released_at = 20;
@@ -1137,7 +1667,8 @@ mod tests {
         let nominate_expire = 5;
         let custody_until = 10;
         let released_at = 20;
-        self_nominate(&mut state, &address, &address_pubkey, deposit, 0, nominate_expire, b"".to_vec()).unwrap();
+        self_nominate(&mut state, &address, &address_pubkey, deposit, 0, nominate_expire, b"".to_vec(), false, None)
+            .unwrap();
         jail(&mut state, &[address], custody_until, released_at).unwrap();
 
         for current_term in 0..=custody_until {
@@ -1149,6 +1680,8 @@ mod tests {
                 current_term,
                 current_term + nominate_expire,
                 b"".to_vec(),
+                false,
+                None,
             );
             assert!(
                 result.is_err(),
@@ -1176,8 +1709,18 @@ mod tests {
         let nominate_expire = 5;
         let custody_until = 10;
         let released_at = 20;
-        self_nominate(&mut state, &address, &address_pubkey, deposit, 0, nominate_expire, b"metadata-before".to_vec())
-            .unwrap();
+        self_nominate(
+            &mut state,
+            &address,
+            &address_pubkey,
+            deposit,
+            0,
+            nominate_expire,
+            b"metadata-before".to_vec(),
+            false,
+            None,
+        )
+        .unwrap();
         jail(&mut state, &[address], custody_until, released_at).unwrap();
         for current_term in 0..=custody_until {
             on_term_close(&mut state, pseudo_term_to_block_num_calculator(current_term), &[]).unwrap();
@@ -1193,6 +1736,8 @@ mod tests {
             current_term,
             current_term + nominate_expire,
             b"metadata-after".to_vec(),
+            false,
+            None,
         );
         assert!(result.is_ok());
 
@@ -1203,7 +1748,8 @@ mod tests {
                 deposit: deposit + additional_deposit,
                 nomination_ends_at: current_term + nominate_expire,
                 pubkey: address_pubkey,
-                metadata: "metadata-after".into()
+                metadata: "metadata-after".into(),
+                signing_pubkey: None,
             }),
             "The prisoner is become a candidate",
         );
@@ -1230,7 +1776,8 @@ mod tests {
         let nominate_expire = 5;
         let custody_until = 10;
         let released_at = 20;
-        self_nominate(&mut state, &address, &address_pubkey, deposit, 0, nominate_expire, b"".to_vec()).unwrap();
+        self_nominate(&mut state, &address, &address_pubkey, deposit, 0, nominate_expire, b"".to_vec(), false, None)
+            .unwrap();
         jail(&mut state, &[address], custody_until, released_at).unwrap();
 
         for current_term in 0..released_at {
@@ -1276,7 +1823,8 @@ mod tests {
         let nominate_expire = 5;
         let custody_until = 10;
         let released_at = 20;
-        self_nominate(&mut state, &address, &address_pubkey, deposit, 0, nominate_expire, b"".to_vec()).unwrap();
+        self_nominate(&mut state, &address, &address_pubkey, deposit, 0, nominate_expire, b"".to_vec(), false, None)
+            .unwrap();
         jail(&mut state, &[address], custody_until, released_at).unwrap();
 
         for current_term in 0..=released_at {
@@ -1320,7 +1868,8 @@ mod tests {
         let nominate_expire = 5;
         let custody_until = 10;
         let released_at = 20;
-        self_nominate(&mut state, &address, &address_pubkey, deposit, 0, nominate_expire, b"".to_vec()).unwrap();
+        self_nominate(&mut state, &address, &address_pubkey, deposit, 0, nominate_expire, b"".to_vec(), false, None)
+            .unwrap();
 
         let action = Action::<SoloMessage>::DelegateCCS {
             address,
@@ -1362,7 +1911,7 @@ mod tests {
         let nominate_expire = 5;
         let custody_until = 10;
         let released_at = 20;
-        self_nominate(&mut state, &address, &address_pubkey, 0, 0, nominate_expire, b"".to_vec()).unwrap();
+        self_nominate(&mut state, &address, &address_pubkey, 0, 0, nominate_expire, b"".to_vec(), false, None).unwrap();
 
         let action = Action::<SoloMessage>::DelegateCCS {
             address,
@@ -1385,6 +1934,8 @@ mod tests {
             current_term,
             current_term + nominate_expire,
             b"".to_vec(),
+            false,
+            None,
         );
         assert!(result.is_ok());
 
@@ -1414,7 +1965,7 @@ mod tests {
         stake.init(&mut state).unwrap();
 
         let deposit = 100;
-        self_nominate(&mut state, &criminal, &criminal_pubkey, deposit, 0, 10, b"".to_vec()).unwrap();
+        self_nominate(&mut state, &criminal, &criminal_pubkey, deposit, 0, 10, b"".to_vec(), false, None).unwrap();
         let action = Action::<SoloMessage>::DelegateCCS {
             address: criminal,
             quantity: 40,
@@ -1450,7 +2001,7 @@ mod tests {
         assert_eq!(Ok(()), state.add_balance(&criminal, 100));
 
         let deposit = 10;
-        self_nominate(&mut state, &criminal, &criminal_pubkey, deposit, 0, 10, b"".to_vec()).unwrap();
+        self_nominate(&mut state, &criminal, &criminal_pubkey, deposit, 0, 10, b"".to_vec(), false, None).unwrap();
         let custody_until = 10;
         let released_at = 20;
         jail(&mut state, &[criminal], custody_until, released_at).unwrap();
@@ -1464,4 +2015,295 @@ mod tests {
     fn pseudo_term_to_block_num_calculator(term_id: u64) -> u64 {
         term_id * 10 + 1
     }
+
+    fn params_with_max_num_of_validators(max_num_of_validators: usize) -> CommonParams {
+        let mut params = CommonParams::default_for_test();
+        params.set_dynamic_validator_params_for_test(30, 10, 3, 20, max_num_of_validators, 1, 1, 10, 100);
+        params
+    }
+
+    /// Nominates the candidates and delegates more to the later ones.
+    fn state_with_candidates(max_num_of_validators: usize, num_of_candidates: usize) -> (TopLevelState, Vec<Public>) {
+        let mut state = helpers::get_temp_state_with_metadata();
+        state.metadata().unwrap().unwrap().set_params(CommonParams::default_for_test());
+        assert_eq!(Ok(()), state.update_params(0, params_with_max_num_of_validators(max_num_of_validators)));
+
+        let delegator_pubkey = Public::random();
+        let delegator = public_to_address(&delegator_pubkey);
+        let stake = {
+            let mut genesis_stakes = HashMap::new();
+            genesis_stakes.insert(delegator, 1000);
+            Stake::<SoloMessage>::new(genesis_stakes)
+        };
+        stake.init(&mut state).unwrap();
+
+        let candidates: Vec<_> = (0..num_of_candidates).map(|_| Public::random()).collect();
+        for (index, pubkey) in candidates.iter().enumerate() {
+            let address = public_to_address(pubkey);
+            state.add_balance(&address, 100).unwrap();
+            self_nominate(&mut state, &address, pubkey, 10, 0, 10, b"".to_vec(), false, None).unwrap();
+
+            let action = Action::<SoloMessage>::DelegateCCS {
+                address,
+                quantity: 10 + index as u64,
+            };
+            stake.execute(&action.rlp_bytes(), &mut state, &delegator, &delegator_pubkey).unwrap();
+        }
+        (state, candidates)
+    }
+
+    fn elected(state: &TopLevelState) -> Vec<Public> {
+        let validators: Vec<Validator> = Validators::load_from_state(state).unwrap().into();
+        validators.iter().map(|validator| *validator.pubkey()).collect()
+    }
+
+    #[test]
+    fn increased_max_num_of_validators_takes_effect_at_the_next_election() {
+        let (mut state, candidates) = state_with_candidates(4, 10);
+
+        on_term_close(&mut state, pseudo_term_to_block_num_calculator(0), &[]).unwrap();
+        let validators = elected(&state);
+        assert_eq!(4, validators.len());
+        assert!(candidates[6..].iter().all(|candidate| validators.contains(candidate)));
+
+        // Change the params in the middle of the term.
+        assert_eq!(Ok(()), state.update_params(1, params_with_max_num_of_validators(8)));
+        assert_eq!(validators, elected(&state), "The current validators must not change until the term ends");
+
+        on_term_close(&mut state, pseudo_term_to_block_num_calculator(1), &[]).unwrap();
+        let validators = elected(&state);
+        assert_eq!(8, validators.len());
+        assert!(candidates[2..].iter().all(|candidate| validators.contains(candidate)));
+    }
+
+    #[test]
+    fn decreased_max_num_of_validators_takes_effect_at_the_next_election() {
+        let (mut state, candidates) = state_with_candidates(8, 10);
+
+        on_term_close(&mut state, pseudo_term_to_block_num_calculator(0), &[]).unwrap();
+        let validators = elected(&state);
+        assert_eq!(8, validators.len());
+
+        // Shrinking below the current number of validators doesn't kick anyone in the middle of the term.
+        assert_eq!(Ok(()), state.update_params(1, params_with_max_num_of_validators(4)));
+        assert_eq!(validators, elected(&state));
+
+        on_term_close(&mut state, pseudo_term_to_block_num_calculator(1), &[]).unwrap();
+        let validators = elected(&state);
+        assert_eq!(4, validators.len());
+        assert!(candidates[6..].iter().all(|candidate| validators.contains(candidate)));
+    }
+
+    #[test]
+    fn candidate_without_enough_self_nomination_is_not_elected() {
+        let (mut state, candidates) = state_with_candidates(4, 4);
+        let stake = Stake::<SoloMessage>::new(HashMap::new());
+        on_term_close(&mut state, pseudo_term_to_block_num_calculator(0), &[]).unwrap();
+        assert_eq!(4, elected(&state).len());
+
+        // The last candidate has the most delegations, but delegates little to itself.
+        let mut stakeholders = Stakeholders::load_from_state(&state).unwrap();
+        for (index, candidate) in candidates.iter().enumerate() {
+            let address = public_to_address(candidate);
+            let quantity = if index == 3 {
+                2
+            } else {
+                5
+            };
+            let mut account = StakeAccount::load_from_state(&state, &address).unwrap();
+            account.add_balance(quantity).unwrap();
+            account.save_to_state(&mut state).unwrap();
+            stakeholders.update_by_increased_balance(&account);
+            stakeholders.save_to_state(&mut state).unwrap();
+            let action = Action::<SoloMessage>::DelegateCCS {
+                address,
+                quantity,
+            };
+            stake.execute(&action.rlp_bytes(), &mut state, &address, candidate).unwrap();
+        }
+        let mut params = params_with_max_num_of_validators(4);
+        params.set_min_self_nomination_quantity_for_test(5);
+        assert_eq!(Ok(()), state.update_params(1, params));
+        assert_eq!(4, elected(&state).len(), "The threshold is read at the term-closing block");
+
+        on_term_close(&mut state, pseudo_term_to_block_num_calculator(1), &[]).unwrap();
+        assert_eq!(candidates[..3].to_vec(), elected(&state));
+
+        let statuses = candidate_statuses(&state).unwrap();
+        assert_eq!(4, statuses.len(), "The excluded one is still a candidate");
+        let excluded = statuses.iter().find(|status| status.pubkey == candidates[3]).unwrap();
+        assert_eq!(10, excluded.deposit);
+        assert_eq!(15, excluded.delegation);
+        assert_eq!(
+            Some(ExclusionReason::InsufficientSelfNomination {
+                self_nomination: 2,
+                minimum: 5,
+            }),
+            excluded.exclusion
+        );
+        let others = statuses.iter().filter(|status| status.pubkey != candidates[3]);
+        assert!(others.all(|status| status.exclusion.is_none() && status.self_nomination == 5));
+    }
+
+    fn signing_key_of(state: &TopLevelState, candidate: &Public) -> Option<Public> {
+        Validators::load_from_state(state)
+            .unwrap()
+            .iter()
+            .find(|validator| validator.pubkey() == candidate)
+            .map(|validator| *validator.signing_pubkey())
+    }
+
+    #[test]
+    fn signing_key_takes_effect_at_the_next_election() {
+        let (mut state, candidates) = state_with_candidates(4, 4);
+        let stake = Stake::<SoloMessage>::new(HashMap::new());
+        on_term_close(&mut state, pseudo_term_to_block_num_calculator(0), &[]).unwrap();
+
+        let candidate = candidates[3];
+        let owner = public_to_address(&candidate);
+        let signing_public = Public::random();
+        let signer = public_to_address(&signing_public);
+        let action = Action::<SoloMessage>::SetSigningKey {
+            candidate_address: owner,
+            signing_public,
+        };
+        assert_eq!(Ok(()), stake.execute(&action.rlp_bytes(), &mut state, &owner, &candidate));
+        assert_eq!(
+            Some(signing_public),
+            Candidates::load_from_state(&state).unwrap().get_candidate(&owner).unwrap().signing_pubkey
+        );
+        assert_eq!(
+            Some(candidate),
+            signing_key_of(&state, &candidate),
+            "The key must not change in the middle of the term"
+        );
+
+        on_term_close(&mut state, pseudo_term_to_block_num_calculator(1), &[]).unwrap();
+        assert_eq!(Some(signing_public), signing_key_of(&state, &candidate));
+
+        // The blocks of the term are authored by the signer.
+        let weight_before = Validators::load_from_state(&state).unwrap();
+        update_validator_weights(&mut state, &signer).unwrap();
+        assert_ne!(weight_before.to_vec(), Validators::load_from_state(&state).unwrap().to_vec());
+
+        // Registering the own key cancels the delegation from the next term.
+        let action = Action::<SoloMessage>::SetSigningKey {
+            candidate_address: owner,
+            signing_public: candidate,
+        };
+        assert_eq!(Ok(()), stake.execute(&action.rlp_bytes(), &mut state, &owner, &candidate));
+        assert_eq!(Some(signing_public), signing_key_of(&state, &candidate));
+        on_term_close(&mut state, pseudo_term_to_block_num_calculator(2), &[]).unwrap();
+        assert_eq!(Some(candidate), signing_key_of(&state, &candidate));
+    }
+
+    #[test]
+    fn only_the_candidate_sets_its_signing_key() {
+        let (mut state, candidates) = state_with_candidates(4, 2);
+        let stake = Stake::<SoloMessage>::new(HashMap::new());
+        let owner = public_to_address(&candidates[0]);
+        let action = Action::<SoloMessage>::SetSigningKey {
+            candidate_address: owner,
+            signing_public: Public::random(),
+        };
+
+        let other = Public::random();
+        assert!(stake.execute(&action.rlp_bytes(), &mut state, &public_to_address(&other), &other).is_err());
+        assert!(stake.execute(&action.rlp_bytes(), &mut state, &owner, &other).is_err(), "Regular keys can't set it");
+
+        let not_candidate = Public::random();
+        let action = Action::<SoloMessage>::SetSigningKey {
+            candidate_address: public_to_address(&not_candidate),
+            signing_public: Public::random(),
+        };
+        assert!(stake
+            .execute(&action.rlp_bytes(), &mut state, &public_to_address(&not_candidate), &not_candidate)
+            .is_err());
+    }
+
+    #[test]
+    fn signing_key_cannot_be_shared_with_another_candidate() {
+        let (mut state, candidates) = state_with_candidates(4, 2);
+        let stake = Stake::<SoloMessage>::new(HashMap::new());
+        let (owner0, owner1) = (public_to_address(&candidates[0]), public_to_address(&candidates[1]));
+
+        let action = Action::<SoloMessage>::SetSigningKey {
+            candidate_address: owner0,
+            signing_public: candidates[1],
+        };
+        assert!(stake.execute(&action.rlp_bytes(), &mut state, &owner0, &candidates[0]).is_err());
+
+        let signing_public = Public::random();
+        let action = Action::<SoloMessage>::SetSigningKey {
+            candidate_address: owner0,
+            signing_public,
+        };
+        assert_eq!(Ok(()), stake.execute(&action.rlp_bytes(), &mut state, &owner0, &candidates[0]));
+        let action = Action::<SoloMessage>::SetSigningKey {
+            candidate_address: owner1,
+            signing_public,
+        };
+        assert!(stake.execute(&action.rlp_bytes(), &mut state, &owner1, &candidates[1]).is_err());
+
+        let signer = public_to_address(&signing_public);
+        state.add_balance(&signer, 100).unwrap();
+        assert!(
+            self_nominate(&mut state, &signer, &signing_public, 10, 0, 10, b"".to_vec(), false, None).is_err(),
+            "The signing key can't be nominated"
+        );
+    }
+
+    fn eligibility_of(state: &TopLevelState, signer: &Public) -> SignerEligibility {
+        let validators: Vec<Public> =
+            Validators::load_from_state(state).unwrap().iter().map(|validator| *validator.signing_pubkey()).collect();
+        signer_eligibility(state, &public_to_address(signer), &validators).unwrap()
+    }
+
+    #[test]
+    fn signer_eligibility_follows_the_stake_state() {
+        let (mut state, candidates) = state_with_candidates(2, 4);
+        on_term_close(&mut state, pseudo_term_to_block_num_calculator(0), &[]).unwrap();
+
+        assert_eq!(SignerEligibility::Validator, eligibility_of(&state, &candidates[3]));
+        assert_eq!(SignerEligibility::Validator, eligibility_of(&state, &candidates[2]));
+        assert_eq!(SignerEligibility::Candidate, eligibility_of(&state, &candidates[1]));
+        assert_eq!(SignerEligibility::Unknown, eligibility_of(&state, &Public::random()));
+
+        jail(&mut state, &[public_to_address(&candidates[1])], 10, 20).unwrap();
+        assert_eq!(
+            SignerEligibility::Jailed {
+                custody_until: 10,
+                released_at: 20,
+            },
+            eligibility_of(&state, &candidates[1])
+        );
+
+        assert_eq!(Ok(()), ban(&mut state, &Public::random(), public_to_address(&candidates[0])));
+        assert_eq!(SignerEligibility::Banned, eligibility_of(&state, &candidates[0]));
+    }
+
+    #[test]
+    fn signer_eligibility_of_the_registered_signing_key() {
+        let (mut state, candidates) = state_with_candidates(2, 2);
+        let stake = Stake::<SoloMessage>::new(HashMap::new());
+        on_term_close(&mut state, pseudo_term_to_block_num_calculator(0), &[]).unwrap();
+
+        let owner = public_to_address(&candidates[0]);
+        let signing_public = Public::random();
+        let action = Action::<SoloMessage>::SetSigningKey {
+            candidate_address: owner,
+            signing_public,
+        };
+        assert_eq!(Ok(()), stake.execute(&action.rlp_bytes(), &mut state, &owner, &candidates[0]));
+        assert_eq!(
+            SignerEligibility::Candidate,
+            eligibility_of(&state, &signing_public),
+            "The registered key signs from the next term"
+        );
+        assert_eq!(SignerEligibility::Validator, eligibility_of(&state, &candidates[0]));
+
+        on_term_close(&mut state, pseudo_term_to_block_num_calculator(1), &[]).unwrap();
+        assert_eq!(SignerEligibility::Validator, eligibility_of(&state, &signing_public));
+        assert_eq!(SignerEligibility::Unknown, eligibility_of(&state, &candidates[0]));
+    }
 }
@@ -14,14 +14,18 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use ccrypto::blake256;
 use kvdb::{DBTransaction, KeyValueDB};
 use primitives::H256;
+use rlp::{DecoderError, UntrustedRlp};
 
 use super::message::ConsensusMessage;
 use super::types::{Height, Step, View};
 use crate::db;
 
-const BACKUP_KEY: &[u8] = b"tendermint-backup";
+pub(super) const BACKUP_KEY: &[u8] = b"tendermint-backup";
+/// The corrupted backup is moved here, so that it can be inspected later and is not read again.
+const QUARANTINE_KEY: &[u8] = b"tendermint-backup-quarantine";
 
 pub struct BackupView<'a> {
     pub height: &'a Height,
@@ -29,6 +33,7 @@ pub struct BackupView<'a> {
     pub step: &'a Step,
     pub votes: &'a [ConsensusMessage],
     pub last_confirmed_view: &'a View,
+    pub quarantined: &'a Option<H256>,
 }
 
 pub struct BackupData {
@@ -38,6 +43,8 @@ pub struct BackupData {
     pub votes: Vec<ConsensusMessage>,
     pub proposal: Option<H256>,
     pub last_confirmed_view: View,
+    /// The hash of the quarantined backup if the engine was still observing the network when it backed up.
+    pub quarantined: Option<H256>,
 }
 
 pub fn backup(db: &KeyValueDB, backup_data: BackupView) {
@@ -47,34 +54,64 @@ pub fn backup(db: &KeyValueDB, backup_data: BackupView) {
         step,
         votes,
         last_confirmed_view,
+        quarantined,
     } = backup_data;
     let mut s = rlp::RlpStream::new();
-    s.begin_list(5);
+    s.begin_list(6);
     s.append(height).append(view).append(step).append_list(votes);
-    s.append(last_confirmed_view);
+    s.append(last_confirmed_view).append(quarantined);
 
     let mut batch = DBTransaction::new();
     batch.put(db::COL_EXTRA, BACKUP_KEY, &s.drain().into_vec());
     db.write(batch).expect("Low level database error. Some issue with disk?");
 }
 
-pub fn restore(db: &KeyValueDB) -> Option<BackupData> {
-    let value = db.get(db::COL_EXTRA, BACKUP_KEY).expect("Low level database error. Some issue with disk?");
-    let (height, view, step, votes, last_confirmed_view) = value.map(|bytes| {
-        let bytes = bytes.into_vec();
-        let rlp = rlp::Rlp::new(&bytes);
-        (rlp.val_at(0), rlp.val_at(1), rlp.val_at(2), rlp.at(3).as_list(), rlp.val_at(4))
-    })?;
+pub enum Restored {
+    Backup(BackupData),
+    /// The backup couldn't be decoded, e.g. after a partial write, and was moved aside.
+    /// It has the hash of the corrupted bytes.
+    Quarantined(H256),
+    Nothing,
+}
 
-    let proposal = find_proposal(&votes, height, view);
+pub fn restore(db: &KeyValueDB) -> Restored {
+    let bytes = match db.get(db::COL_EXTRA, BACKUP_KEY).expect("Low level database error. Some issue with disk?") {
+        Some(bytes) => bytes.into_vec(),
+        None => return Restored::Nothing,
+    };
+    match decode(&bytes) {
+        Ok(backup) => Restored::Backup(backup),
+        Err(err) => {
+            let hash = blake256(&bytes);
+            cerror!(ENGINE, "The consensus backup({} bytes, hash {}) is corrupted: {:?}", bytes.len(), hash, err);
+            let mut batch = DBTransaction::new();
+            batch.put(db::COL_EXTRA, QUARANTINE_KEY, &bytes);
+            batch.delete(db::COL_EXTRA, BACKUP_KEY);
+            db.write(batch).expect("Low level database error. Some issue with disk?");
+            Restored::Quarantined(hash)
+        }
+    }
+}
 
-    Some(BackupData {
+fn decode(bytes: &[u8]) -> Result<BackupData, DecoderError> {
+    let rlp = UntrustedRlp::new(bytes);
+    let height = rlp.val_at(0)?;
+    let view = rlp.val_at(1)?;
+    let votes: Vec<ConsensusMessage> = rlp.list_at(3)?;
+    let proposal = find_proposal(&votes, height, view);
+    Ok(BackupData {
         height,
         view,
-        step,
+        step: rlp.val_at(2)?,
         votes,
         proposal,
-        last_confirmed_view,
+        last_confirmed_view: rlp.val_at(4)?,
+        // The backups written before the observation was recorded have only 5 items.
+        quarantined: if rlp.item_count()? > 5 {
+            rlp.val_at(5)?
+        } else {
+            None
+        },
     })
 }
 
@@ -89,3 +126,105 @@ fn find_proposal(votes: &[ConsensusMessage], height: Height, view: View) -> Opti
         .map(|vote_on| vote_on.block_hash)
         .unwrap_or(None)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn backup_at(db: &KeyValueDB, height: Height) {
+        backup(db, BackupView {
+            height: &height,
+            view: &2,
+            step: &Step::Prevote,
+            votes: &[],
+            last_confirmed_view: &1,
+            quarantined: &None,
+        });
+    }
+
+    #[test]
+    fn clean_backup_is_restored() {
+        let db = Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap()));
+        assert!(match restore(db.as_ref()) {
+            Restored::Nothing => true,
+            _ => false,
+        });
+
+        backup_at(db.as_ref(), 10);
+        match restore(db.as_ref()) {
+            Restored::Backup(backup) => {
+                assert_eq!(10, backup.height);
+                assert_eq!(2, backup.view);
+                assert_eq!(Step::Prevote, backup.step);
+                assert_eq!(1, backup.last_confirmed_view);
+                assert_eq!(None, backup.quarantined);
+            }
+            _ => panic!("The backup is not corrupted"),
+        }
+    }
+
+    #[test]
+    fn observing_state_is_restored() {
+        let db = Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap()));
+        let quarantined = Some(H256::random());
+        backup(db.as_ref(), BackupView {
+            height: &10,
+            view: &2,
+            step: &Step::Prevote,
+            votes: &[],
+            last_confirmed_view: &1,
+            quarantined: &quarantined,
+        });
+        match restore(db.as_ref()) {
+            Restored::Backup(backup) => assert_eq!(quarantined, backup.quarantined),
+            _ => panic!("The backup is not corrupted"),
+        }
+    }
+
+    #[test]
+    fn backup_without_observing_state_is_restored() {
+        let db = Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap()));
+        let mut s = rlp::RlpStream::new();
+        s.begin_list(5);
+        s.append(&10u64).append(&2u64).append(&Step::Prevote).append_list::<ConsensusMessage, _>(&[]);
+        s.append(&1u64);
+        let mut batch = DBTransaction::new();
+        batch.put(db::COL_EXTRA, BACKUP_KEY, &s.drain().into_vec());
+        db.write(batch).unwrap();
+
+        match restore(db.as_ref()) {
+            Restored::Backup(backup) => {
+                assert_eq!(10, backup.height);
+                assert_eq!(1, backup.last_confirmed_view);
+                assert_eq!(None, backup.quarantined);
+            }
+            _ => panic!("The old backup is not corrupted"),
+        }
+    }
+
+    #[test]
+    fn corrupted_backup_is_quarantined() {
+        let db = Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap()));
+        backup_at(db.as_ref(), 10);
+        let bytes = db.get(db::COL_EXTRA, BACKUP_KEY).unwrap().unwrap().into_vec();
+        // A partial write
+        let corrupted = &bytes[..bytes.len() - 2];
+        let mut batch = DBTransaction::new();
+        batch.put(db::COL_EXTRA, BACKUP_KEY, corrupted);
+        db.write(batch).unwrap();
+
+        match restore(db.as_ref()) {
+            Restored::Quarantined(hash) => assert_eq!(blake256(corrupted), hash),
+            _ => panic!("The backup is corrupted"),
+        }
+        assert_eq!(None, db.get(db::COL_EXTRA, BACKUP_KEY).unwrap());
+        let quarantined = db.get(db::COL_EXTRA, QUARANTINE_KEY).unwrap().unwrap().into_vec();
+        assert_eq!(corrupted.to_vec(), quarantined);
+        assert!(match restore(db.as_ref()) {
+            Restored::Nothing => true,
+            _ => false,
+        });
+    }
+}
@@ -14,20 +14,25 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::sync::Arc;
+
 use crossbeam_channel as crossbeam;
 use primitives::H256;
 
 use super::worker;
 use crate::client::ChainNotify;
+use crate::consensus::ValidatorSet;
 
 pub struct TendermintChainNotify {
     inner: crossbeam::Sender<worker::Event>,
+    validators: Arc<ValidatorSet>,
 }
 
 impl TendermintChainNotify {
-    pub fn new(inner: crossbeam::Sender<worker::Event>) -> Self {
+    pub fn new(inner: crossbeam::Sender<worker::Event>, validators: Arc<ValidatorSet>) -> Self {
         Self {
             inner,
+            validators,
         }
     }
 }
@@ -39,10 +44,11 @@ impl ChainNotify for TendermintChainNotify {
         imported: Vec<H256>,
         _invalid: Vec<H256>,
         enacted: Vec<H256>,
-        _retracted: Vec<H256>,
+        retracted: Vec<H256>,
         _sealed: Vec<H256>,
         _duration: u64,
     ) {
+        self.validators.on_chain_updated(&enacted, &retracted);
         self.inner
             .send(worker::Event::NewBlocks {
                 imported,
@@ -0,0 +1,211 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+//! Estimates how far the local clock is from the clocks of the other validators.
+//!
+//! The StepState messages don't carry a timestamp, but the peers send them as soon as they enter a
+//! step. So the time when the peers announced that they entered the propose step of a view is when
+//! the view started, measured by the local clock. The timestamp of the block proposed in the view
+//! is when the view started, measured by the clock of the proposer. The difference of the two is a
+//! sample of the skew between the clocks.
+//!
+//! Each proposer keeps only its latest sample, and the estimate is the median of the proposers'
+//! samples. So a minority of the peers cannot move the estimate by announcing wrong times.
+
+use std::collections::HashMap;
+
+use ckey::Public;
+use cnetwork::NodeId;
+
+use super::types::{Height, View};
+
+/// The estimate needs the samples from at least this number of proposers.
+const MIN_PROPOSERS: usize = 3;
+/// The samples older than this are not used. In milliseconds.
+const SAMPLE_LIFETIME: u64 = 10 * 60 * 1000;
+/// The skew larger than this is logged. The block timestamps are in seconds, so the samples have an
+/// error up to a second. In milliseconds.
+pub const WARNING_THRESHOLD: i64 = 2_000;
+
+struct Sample {
+    /// The local time minus the proposer's time, in milliseconds.
+    offset: i64,
+    /// The local time when the sample was taken, in milliseconds.
+    taken_at: u64,
+}
+
+#[derive(Default)]
+pub struct ClockSkewEstimator {
+    /// The local times when the peers announced that they entered the propose step of each view.
+    round_starts: HashMap<(Height, View), HashMap<NodeId, u64>>,
+    /// The latest sample of each proposer.
+    samples: HashMap<Public, Sample>,
+}
+
+impl ClockSkewEstimator {
+    /// Records that the peer entered the propose step of the view at `now`.
+    /// Only the first announcement of each peer is used, because the peers repeat it periodically.
+    pub fn on_round_start(&mut self, peer: NodeId, height: Height, view: View, now: u64) {
+        self.round_starts.retain(|(round_height, _), _| *round_height >= height);
+        self.round_starts.entry((height, view)).or_default().entry(peer).or_insert(now);
+    }
+
+    /// Takes a sample from the block proposed in the view and returns its offset in milliseconds.
+    /// `timestamp` is the timestamp of the block in seconds. The arrival time of the proposal is
+    /// used when no peer announced the start of the view.
+    pub fn on_proposal(&mut self, proposer: Public, height: Height, view: View, timestamp: u64, now: u64) -> i64 {
+        let started_at = self
+            .round_starts
+            .get(&(height, view))
+            .and_then(|announcements| median(announcements.values().map(|time| *time as i64).collect()))
+            .unwrap_or(now as i64);
+        let offset = started_at - (timestamp * 1000) as i64;
+        self.samples.insert(proposer, Sample {
+            offset,
+            taken_at: now,
+        });
+        offset
+    }
+
+    /// The estimated skew of the local clock in milliseconds. It's positive if the local clock is
+    /// ahead of the others. Returns `None` if there are not enough recent samples.
+    pub fn estimate(&self, now: u64) -> Option<i64> {
+        let offsets: Vec<_> = self
+            .samples
+            .values()
+            .filter(|sample| now.saturating_sub(sample.taken_at) <= SAMPLE_LIFETIME)
+            .map(|sample| sample.offset)
+            .collect();
+        if offsets.len() < MIN_PROPOSERS {
+            return None
+        }
+        median(offsets)
+    }
+}
+
+fn median(mut values: Vec<i64>) -> Option<i64> {
+    if values.is_empty() {
+        return None
+    }
+    values.sort();
+    let middle = values.len() / 2;
+    if values.len() % 2 == 0 {
+        Some((values[middle - 1] + values[middle]) / 2)
+    } else {
+        Some(values[middle])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use ckey::{Generator, Random};
+
+    use super::*;
+
+    /// The local clock is ahead of the others by this, in milliseconds.
+    const SKEW: i64 = 7_000;
+    /// The time of the others when the first view started, in milliseconds.
+    const START: u64 = 1_560_000_000_000;
+
+    fn peer(index: u16) -> NodeId {
+        NodeId::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 3485 + index)
+    }
+
+    fn proposers(count: usize) -> Vec<Public> {
+        (0..count).map(|_| *Random.generate().unwrap().public()).collect()
+    }
+
+    fn local(time: u64) -> u64 {
+        (time as i64 + SKEW) as u64
+    }
+
+    #[test]
+    fn estimate_needs_enough_proposers() {
+        let mut estimator = ClockSkewEstimator::default();
+        let proposers = proposers(MIN_PROPOSERS);
+        for (view, proposer) in proposers.iter().enumerate().take(MIN_PROPOSERS - 1) {
+            let view = view as View;
+            let start = START + view * 10_000;
+            estimator.on_proposal(*proposer, 1, view, start / 1000, local(start + 100));
+        }
+        assert_eq!(None, estimator.estimate(local(START + 60_000)));
+    }
+
+    #[test]
+    fn estimate_the_skew_of_the_local_clock() {
+        let mut estimator = ClockSkewEstimator::default();
+        let proposers = proposers(5);
+        for (height, proposer) in proposers.iter().enumerate() {
+            let height = height as Height + 1;
+            let start = START + height * 5_000;
+            for index in 0..4 {
+                estimator.on_round_start(peer(index), height, 0, local(start + 20 * u64::from(index)));
+            }
+            // The proposals arrive late, but the announcements of the round start fix it.
+            estimator.on_proposal(*proposer, height, 0, start / 1000, local(start + 3_000));
+        }
+        let estimate = estimator.estimate(local(START + 60_000)).unwrap();
+        assert!((estimate - SKEW).abs() < 1_000, "{} is not close to {}", estimate, SKEW);
+    }
+
+    #[test]
+    fn estimate_is_robust_to_the_lying_minority() {
+        let mut estimator = ClockSkewEstimator::default();
+        let proposers = proposers(5);
+        for (height, proposer) in proposers.iter().enumerate() {
+            let height = height as Height + 1;
+            let start = START + height * 5_000;
+            // One of the peers announces the round start too early.
+            estimator.on_round_start(peer(0), height, 0, local(start - 3_600_000));
+            for index in 1..4 {
+                estimator.on_round_start(peer(index), height, 0, local(start + 20 * u64::from(index)));
+            }
+            // Two of the proposers put the timestamps far in the future.
+            let timestamp = if height <= 2 {
+                start / 1000 + 3_600
+            } else {
+                start / 1000
+            };
+            estimator.on_proposal(*proposer, height, 0, timestamp, local(start + 100));
+        }
+        let estimate = estimator.estimate(local(START + 60_000)).unwrap();
+        assert!((estimate - SKEW).abs() < 1_000, "{} is not close to {}", estimate, SKEW);
+    }
+
+    #[test]
+    fn old_samples_are_not_used() {
+        let mut estimator = ClockSkewEstimator::default();
+        for (view, proposer) in proposers(MIN_PROPOSERS).into_iter().enumerate() {
+            let view = view as View;
+            let start = START + view * 10_000;
+            estimator.on_proposal(proposer, 1, view, start / 1000, local(start + 100));
+        }
+        assert!(estimator.estimate(local(START + 60_000)).is_some());
+        assert_eq!(None, estimator.estimate(local(START + 60_000) + SAMPLE_LIFETIME));
+    }
+
+    #[test]
+    fn announcements_of_the_old_heights_are_dropped() {
+        let mut estimator = ClockSkewEstimator::default();
+        estimator.on_round_start(peer(0), 1, 0, START);
+        estimator.on_round_start(peer(0), 1, 1, START + 10_000);
+        estimator.on_round_start(peer(0), 2, 0, START + 20_000);
+        assert_eq!(1, estimator.round_starts.len());
+    }
+}
@@ -14,23 +14,27 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::cmp;
 use std::collections::btree_map::BTreeMap;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::iter::Iterator;
 use std::sync::atomic::Ordering as AtomicOrdering;
 use std::sync::{Arc, Weak};
+use std::time::Duration;
 
-use ckey::{public_to_address, Address};
+use ckey::{public_to_address, Address, Public};
 use cnetwork::NetworkService;
 use crossbeam_channel as crossbeam;
 use cstate::{ActionHandler, TopStateView};
 use ctypes::{BlockNumber, CommonParams, Header};
 use num_rational::Ratio;
-use primitives::H256;
+use primitives::{Bytes, H256};
 
 use super::super::stake;
-use super::super::{ConsensusEngine, EngineError, Seal};
+#[cfg(any(test, feature = "fault-injection"))]
+use super::super::ConsensusFault;
+use super::super::{ConsensusEngine, ConsensusStatus, DecodedSeal, EngineError, RestoreStatus, Seal};
 use super::network::TendermintExtension;
 pub use super::params::{TendermintParams, TimeoutParams};
 use super::types::TendermintSealView;
@@ -41,13 +45,16 @@ use crate::block::*;
 use crate::client::{Client, ConsensusClient};
 use crate::codechain_machine::CodeChainMachine;
 use crate::consensus::tendermint::params::TimeGapParams;
-use crate::consensus::{EngineType, ValidatorSet};
+use crate::consensus::{EngineType, ValidatorSet, WeightedValidator};
 use crate::encoded;
 use crate::error::Error;
 use crate::views::HeaderView;
 use crate::BlockId;
 use rlp::Encodable;
 
+/// How long `consensus_status` waits for the worker.
+const CONSENSUS_STATUS_TIMEOUT: Duration = Duration::from_secs(1);
+
 impl ConsensusEngine for Tendermint {
     fn name(&self) -> &str {
         "Tendermint"
@@ -62,6 +69,10 @@ impl ConsensusEngine for Tendermint {
         SEAL_FIELDS
     }
 
+    fn seal_layout(&self) -> &'static [&'static str] {
+        &["prev_view", "cur_view", "precommits", "precommit_bitset"]
+    }
+
     /// Should this node participate.
     fn seals_internally(&self) -> Option<bool> {
         Some(self.has_signer.load(AtomicOrdering::SeqCst))
@@ -128,12 +139,107 @@ impl ConsensusEngine for Tendermint {
         header.set_score(score);
     }
 
+    fn proposal_timestamp(&self, parent: &Header) -> Option<u64> {
+        let (result, receiver) = crossbeam::bounded(1);
+        self.inner
+            .send(worker::Event::ProposalTimestamp {
+                parent_timestamp: parent.timestamp(),
+                result,
+            })
+            .ok()?;
+        receiver.recv().ok()
+    }
+
     /// Equivalent to a timeout: to be used for tests.
     fn on_timeout(&self, token: usize) {
         self.inner.send(worker::Event::OnTimeout(token)).unwrap();
     }
 
-    fn stop(&self) {}
+    fn stop(&self) {
+        self.quit_worker();
+    }
+
+    fn force_next_view(&self) -> Result<(), EngineError> {
+        let (result, receiver) = crossbeam::bounded(1);
+        self.inner
+            .send(worker::Event::ForceNextView {
+                result,
+            })
+            .unwrap();
+        receiver.recv().unwrap()
+    }
+
+    /// It doesn't wait for the worker, so it can be called before the worker starts.
+    fn demote_to_standby(&self) -> Result<(), EngineError> {
+        self.inner.send(worker::Event::DemoteToStandby).unwrap();
+        Ok(())
+    }
+
+    fn promote_from_standby(&self, min_quiet_period: Option<Duration>) -> Result<(), EngineError> {
+        let (result, receiver) = crossbeam::bounded(1);
+        self.inner
+            .send(worker::Event::PromoteFromStandby {
+                min_quiet_period,
+                result,
+            })
+            .unwrap();
+        receiver.recv().unwrap()
+    }
+
+    #[cfg(any(test, feature = "fault-injection"))]
+    fn set_consensus_fault(&self, fault: ConsensusFault) -> Result<(), EngineError> {
+        cwarn!(ENGINE, "The consensus fault is set to {:?}", fault);
+        self.inner.send(worker::Event::SetConsensusFault(fault)).unwrap();
+        Ok(())
+    }
+
+    fn block_view(&self, header: &Header) -> Option<u64> {
+        TendermintSealView::new(header.seal()).consensus_view().ok()
+    }
+
+    fn consensus_status(&self) -> Option<ConsensusStatus> {
+        let (result, receiver) = crossbeam::bounded(1);
+        self.inner
+            .send(worker::Event::GetStatus {
+                result,
+            })
+            .ok()?;
+        // The worker may be busy with a long event, e.g. a block verification. The health check doesn't wait for it.
+        let status = receiver.recv_timeout(CONSENSUS_STATUS_TIMEOUT).ok()?;
+        let vote_step = status.vote_step;
+        Some(ConsensusStatus {
+            height: vote_step.height,
+            view: vote_step.view,
+            step: vote_step.step,
+            commit_recovery: self.commit_recovery.stats(),
+            double_votes_found: self.double_votes_found.load(AtomicOrdering::SeqCst),
+            vote_collector: status.vote_collector,
+            time_in_step: status.time_in_step,
+            step_timeout: self.timeouts.timeout(vote_step.step, vote_step.view),
+            clock_skew: status.clock_skew,
+            proposal_checks: status.proposal_checks,
+            vote_requests: self.vote_requests.stats(),
+            retained_proposal_bytes: status.retained_proposal_bytes,
+            standby: status.standby,
+            bad_seal_signatures: self.bad_seal_signatures.stats(),
+            escalation_view: self.timeouts.escalation_view,
+            degradation: status.degradation,
+        })
+    }
+
+    fn restore_status(&self) -> Option<RestoreStatus> {
+        let (result, receiver) = crossbeam::bounded(1);
+        self.inner
+            .send(worker::Event::GetRestoreStatus {
+                result,
+            })
+            .unwrap();
+        Some(receiver.recv().unwrap())
+    }
+
+    fn decode_seal(&self, seal: &[Bytes]) -> Option<Result<DecodedSeal, String>> {
+        Some(TendermintSealView::new(seal).decode())
+    }
 
     fn on_close_block(
         &self,
@@ -143,27 +249,30 @@ impl ConsensusEngine for Tendermint {
         term_common_params: Option<&CommonParams>,
     ) -> Result<(), Error> {
         let author = *block.header().author();
-        let (total_reward, total_min_fee) = {
-            let transactions = block.transactions();
-            let block_reward = self.block_reward(block.header().number());
-            let total_min_fee: u64 = transactions.iter().map(|tx| tx.fee).sum();
-            let min_fee =
-                transactions.iter().map(|tx| CodeChainMachine::min_cost(&parent_common_params, &tx.action)).sum();
-            (block_reward + total_min_fee, min_fee)
+        // The author signed the block with its signing key, but the rewards go to the owner of the key.
+        let reward_receiver = self.validators.owner_address(block.header().parent_hash(), &author);
+        let block_reward = self.block_reward(block.header().number());
+        let (total_reward, total_min_fee) =
+            CodeChainMachine::total_rewards(block_reward, block.transactions(), parent_common_params)?;
+        // The transactions of the block don't change who receives its fees if the params say so.
+        let stakes = match block.parent_stakes() {
+            Some(parent_stakes) => parent_stakes.clone(),
+            None => stake::get_stakes(block.state()).expect("Cannot get Stake status"),
         };
-        assert!(total_reward >= total_min_fee, "{} >= {}", total_reward, total_min_fee);
-        let stakes = stake::get_stakes(block.state()).expect("Cannot get Stake status");
 
-        let mut distributor = stake::fee_distribute(total_min_fee, &stakes);
-        for (address, share) in &mut distributor {
-            self.machine.add_balance(block, &address, share)?
+        let distribution =
+            stake::distribute_rewards(total_reward, total_min_fee, parent_common_params.fee_burn_permille(), &stakes);
+        for (address, share) in &distribution.shares {
+            self.machine.add_balance(block, address, *share)?
         }
+        // The burnt fee isn't added to anyone's balance.
+        stake::add_burnt_fee(block.state_mut(), distribution.burnt)?;
 
-        let block_author_reward = total_reward - total_min_fee + distributor.remaining_fee();
+        let block_author_reward = distribution.author_reward;
 
         let metadata = block.state().metadata()?.expect("Metadata must exist");
         if metadata.current_term_id() == 0 {
-            self.machine.add_balance(block, &author, block_author_reward)?;
+            self.machine.add_balance(block, &reward_receiver, block_author_reward)?;
 
             if let Some(block_number) =
                 block_number_if_term_changed(block.header(), parent_header, parent_common_params)
@@ -177,7 +286,7 @@ impl ConsensusEngine for Tendermint {
         let block_author = *block.header().author();
         stake::update_validator_weights(&mut block.state_mut(), &block_author)?;
 
-        stake::add_intermediate_rewards(block.state_mut(), author, block_author_reward)?;
+        stake::add_intermediate_rewards(block.state_mut(), reward_receiver, block_author_reward)?;
 
         let term_common_params = term_common_params.expect("TermCommonParams should exist");
         let last_term_finished_block_num = if let Some(block_number) =
@@ -201,10 +310,7 @@ impl ConsensusEngine for Tendermint {
         let inactive_validators = if metadata.current_term_id() == 1 {
             assert!(rewards.is_empty());
 
-            let validators = stake::Validators::load_from_state(block.state())?
-                .into_iter()
-                .map(|val| public_to_address(val.pubkey()))
-                .collect();
+            let validators = signers_and_owners(stake::Validators::load_from_state(block.state())?);
             inactive_validators(&*client, start_of_the_current_term, block.header(), validators)
         } else {
             let start_of_the_previous_term = {
@@ -235,10 +341,7 @@ impl ConsensusEngine for Tendermint {
                 self.machine.add_balance(block, &address, reward)?;
             }
 
-            let validators = stake::Validators::load_from_state(block.state())?
-                .into_iter()
-                .map(|val| public_to_address(val.pubkey()))
-                .collect();
+            let validators = signers_and_owners(stake::Validators::load_from_state(block.state())?);
             inactive_validators(&*client, start_of_the_current_term, block.header(), validators)
         };
 
@@ -278,16 +381,26 @@ impl ConsensusEngine for Tendermint {
     fn register_network_extension_to_service(&self, service: &NetworkService) {
         let timeouts = self.timeouts;
 
-        let inner = self.inner.clone();
-        let extension = service.register_extension(move |api| TendermintExtension::new(inner, timeouts, api));
+        let inner = self.consensus_inner.clone();
+        let commit_recovery = Arc::clone(&self.commit_recovery);
+        let vote_requests = Arc::clone(&self.vote_requests);
+        let full_gossip = self.full_gossip.load(AtomicOrdering::SeqCst);
+        let extension = service.register_extension(move |api| {
+            TendermintExtension::new(inner, timeouts, commit_recovery, vote_requests, full_gossip, api)
+        });
         let client = Weak::clone(self.client.read().as_ref().unwrap());
         self.extension_initializer.send((extension, client)).unwrap();
 
+        // Restore before the worker handles any message from the extension.
         let (result, receiver) = crossbeam::bounded(1);
-        self.inner.send(worker::Event::Restore(result)).unwrap();
+        self.consensus_inner.send(worker::Event::Restore(result)).unwrap();
         receiver.recv().unwrap();
     }
 
+    fn set_full_gossip(&self, full_gossip: bool) {
+        self.full_gossip.store(full_gossip, AtomicOrdering::SeqCst);
+    }
+
     fn register_time_gap_config_to_worker(&self, time_gap_params: TimeGapParams) {
         self.external_params_initializer.send(time_gap_params).unwrap();
     }
@@ -319,6 +432,11 @@ impl ConsensusEngine for Tendermint {
         header.number() >= allowed_height
     }
 
+    /// The committed blocks are final.
+    fn max_reorg_depth(&self) -> u64 {
+        0
+    }
+
     fn action_handlers(&self) -> &[Arc<ActionHandler>] {
         &self.action_handlers
     }
@@ -342,6 +460,14 @@ impl ConsensusEngine for Tendermint {
         };
         Ok(Some(self.validators.addresses(&block_hash)))
     }
+
+    fn validator_set(&self, parent: &H256) -> Vec<WeightedValidator> {
+        self.validators.weighted_validators(parent)
+    }
+
+    fn initial_validators(&self) -> Vec<Public> {
+        self.initial_validators.clone()
+    }
 }
 
 fn block_number_if_term_changed(
@@ -362,11 +488,35 @@ fn block_number_if_term_changed(
     Some(header.number())
 }
 
+/// The latest timestamp that a proposal on the parent can have.
+pub(super) fn max_proposal_timestamp(parent_timestamp: u64, max_timestamp_step: Duration) -> u64 {
+    parent_timestamp.saturating_add(cmp::max(max_timestamp_step.as_secs(), 1))
+}
+
+/// The timestamp of a block proposed on the parent at `now`, in seconds.
+/// It follows the clock, but doesn't go beyond `max_timestamp_step` from the parent.
+pub(super) fn proposal_timestamp(parent_timestamp: u64, now: u64, max_timestamp_step: Option<Duration>) -> u64 {
+    let now = match max_timestamp_step {
+        Some(max_timestamp_step) => cmp::min(now, max_proposal_timestamp(parent_timestamp, max_timestamp_step)),
+        None => now,
+    };
+    cmp::max(parent_timestamp.saturating_add(1), now)
+}
+
+/// Maps the addresses of the signing keys to the addresses of the validators.
+fn signers_and_owners(validators: stake::Validators) -> HashMap<Address, Address> {
+    validators
+        .into_iter()
+        .map(|val| (public_to_address(val.signing_pubkey()), public_to_address(val.pubkey())))
+        .collect()
+}
+
+/// Returns the owners of the validators that didn't author any block in the current term.
 fn inactive_validators(
     client: &ConsensusClient,
     start_of_the_current_term: u64,
     current_block: &Header,
-    mut validators: HashSet<Address>,
+    mut validators: HashMap<Address, Address>,
 ) -> Vec<Address> {
     validators.remove(current_block.author());
     let hash = *current_block.parent_hash();
@@ -376,7 +526,7 @@ fn inactive_validators(
         header = client.block_header(&header.parent_hash().into()).expect("Header of the parent must exist");
     }
 
-    validators.into_iter().collect()
+    validators.into_iter().map(|(_signer, owner)| owner).collect()
 }
 
 fn calculate_pending_rewards_of_the_previous_term(
@@ -398,7 +548,7 @@ fn calculate_pending_rewards_of_the_previous_term(
     let mut header = start_of_the_current_term_header;
     let mut parent_validators = {
         let grand_parent_header = chain.block_header(&header.parent_hash().into()).unwrap();
-        validators.addresses(&grand_parent_header.parent_hash())
+        validators.owner_addresses(&grand_parent_header.parent_hash())
     };
     while start_of_the_previous_term != header.number() {
         for index in TendermintSealView::new(&header.seal()).bitset()?.true_index_iter() {
@@ -411,10 +561,10 @@ fn calculate_pending_rewards_of_the_previous_term(
             // The seal of the current block has the signatures of the parent block.
             // It needs the hash of the grand parent block to find the validators of the parent block.
             let grand_parent_header = chain.block_header(&header.parent_hash().into()).unwrap();
-            validators.addresses(&grand_parent_header.parent_hash())
+            validators.owner_addresses(&grand_parent_header.parent_hash())
         };
 
-        let author = header.author();
+        let author = validators.owner_address(&header.parent_hash(), &header.author());
         let (proposed, missed) = missed_signatures.entry(author).or_default();
         *proposed += 1;
         *missed += parent_validators.len() - TendermintSealView::new(&header.seal()).bitset()?.count();
@@ -668,4 +818,47 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn proposal_timestamp_follows_the_clock_within_the_step() {
+        let step = Some(Duration::from_secs(30));
+        assert_eq!(1_010, proposal_timestamp(1_000, 1_010, step));
+        // The clock behind the parent.
+        assert_eq!(1_001, proposal_timestamp(1_000, 900, step));
+        assert_eq!(1_001, proposal_timestamp(1_000, 1_000, step));
+        // The clock far ahead of the parent.
+        assert_eq!(1_030, proposal_timestamp(1_000, 1_600, step));
+        assert_eq!(1_600, proposal_timestamp(1_000, 1_600, None));
+        // The step shorter than a second doesn't stop the chain.
+        assert_eq!(1_001, proposal_timestamp(1_000, 1_600, Some(Duration::from_millis(500))));
+    }
+
+    #[test]
+    fn outlier_timestamp_does_not_close_the_term_early() {
+        let term_seconds = 3_600;
+        let mut common_params = CommonParams::default_for_test();
+        common_params.set_dynamic_validator_params_for_test(term_seconds, 10, 10, 10, 30, 4, 1000, 10000, 100);
+        let step = Some(Duration::from_secs(30));
+
+        let mut parent = Header::new();
+        parent.set_number(10);
+        parent.set_timestamp(2 * term_seconds - 100);
+        let mut header = Header::new();
+        header.set_number(11);
+
+        // The proposer's clock is 10 minutes fast.
+        let now = parent.timestamp() + 10 + 600;
+        header.set_timestamp(proposal_timestamp(parent.timestamp(), now, step));
+        assert_eq!(parent.timestamp() + 30, header.timestamp());
+        assert_eq!(None, block_number_if_term_changed(&header, &parent, &common_params));
+
+        // Without the limit, the term would be closed a block early.
+        header.set_timestamp(proposal_timestamp(parent.timestamp(), now, None));
+        assert_eq!(Some(11), block_number_if_term_changed(&header, &parent, &common_params));
+
+        // The boundary is crossed when the parent is close enough to it.
+        parent.set_timestamp(2 * term_seconds - 20);
+        header.set_timestamp(proposal_timestamp(parent.timestamp(), parent.timestamp() + 600, step));
+        assert_eq!(Some(11), block_number_if_term_changed(&header, &parent, &common_params));
+    }
 }
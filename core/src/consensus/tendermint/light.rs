@@ -0,0 +1,321 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The verification that the light clients do with the headers and the state proofs only.
+//!
+//! A block is final when the next block carries the precommits of more than two thirds of its validators in the seal.
+//! The validators of a block are the ones in the state of its parent, and the initial validators while it's the term 0
+//! or the state doesn't have the validators yet.
+
+use ckey::{verify_schnorr, verify_schnorr_aggregate, Public};
+use cmerkle::verify_proof;
+use cstate::{Metadata, MetadataAddress};
+use ctypes::Header;
+use primitives::{Bytes, H256};
+use rlp::UntrustedRlp;
+
+use super::super::stake::{Validator, VALIDATORS_KEY};
+use super::super::{BitSet, DecodedSeal, WeightedValidator};
+use super::message::message_hash;
+use super::types::{SealSignatures, TendermintSealView};
+use super::{Step, VoteStep};
+use crate::client::FinalityEvidence;
+
+/// The term of a block and its validators, which a light client reads from the proof of the parent's state.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TermSnapshot {
+    pub term_id: u64,
+    pub last_term_finished_block_num: u64,
+    /// None while the initial validators seal the blocks.
+    pub validators: Option<Vec<WeightedValidator>>,
+}
+
+/// The state keys of which values make a `TermSnapshot`.
+pub fn term_snapshot_keys() -> Vec<H256> {
+    vec![MetadataAddress::new().into(), *VALIDATORS_KEY]
+}
+
+/// Reads the term and the validators from the trie nodes that prove the keys of `term_snapshot_keys`.
+pub fn term_snapshot_from_proof(state_root: &H256, proof: &[Bytes]) -> Result<TermSnapshot, String> {
+    let metadata = verify_proof(state_root, &MetadataAddress::new(), proof)
+        .map_err(|err| format!("Invalid proof of the metadata: {}", err))?
+        .ok_or_else(|| "The state doesn't have the metadata".to_string())?;
+    let metadata: Metadata =
+        UntrustedRlp::new(&metadata).as_val().map_err(|err| format!("Cannot decode the metadata: {}", err))?;
+
+    let validators = verify_proof(state_root, &*VALIDATORS_KEY, proof)
+        .map_err(|err| format!("Invalid proof of the validators: {}", err))?;
+    let validators = match validators {
+        Some(action_data) => {
+            let encoded: Bytes = UntrustedRlp::new(&action_data)
+                .as_val()
+                .map_err(|err| format!("Cannot decode the validators: {}", err))?;
+            let mut validators: Vec<Validator> = UntrustedRlp::new(&encoded)
+                .as_list()
+                .map_err(|err| format!("Cannot decode the validators: {}", err))?;
+            // The state keeps them in the ascending order, while the seals index them in the descending order.
+            validators.reverse();
+            Some(
+                validators
+                    .into_iter()
+                    .map(|validator| WeightedValidator {
+                        pubkey: *validator.signing_pubkey(),
+                        delegation: Some(validator.delegation()),
+                        deposit: Some(validator.deposit()),
+                    })
+                    .collect(),
+            )
+        }
+        None => None,
+    };
+    Ok(TermSnapshot {
+        term_id: metadata.current_term_id(),
+        last_term_finished_block_num: metadata.last_term_finished_block_num(),
+        validators: if metadata.current_term_id() == 0 {
+            None
+        } else {
+            validators.filter(|validators: &Vec<_>| !validators.is_empty())
+        },
+    })
+}
+
+/// The validators of the term 0, of which every vote weighs the same.
+pub fn initial_snapshot(validators: &[Public]) -> Vec<WeightedValidator> {
+    validators
+        .iter()
+        .map(|pubkey| WeightedValidator {
+            pubkey: *pubkey,
+            delegation: None,
+            deposit: None,
+        })
+        .collect()
+}
+
+/// The precommits in the seal of `child`, which finalize its parent.
+pub fn finality_evidence(child: &Header) -> Option<FinalityEvidence> {
+    match TendermintSealView::new(child.seal()).decode() {
+        Ok(DecodedSeal::Tendermint {
+            finalized_view,
+            signature_count,
+            ..
+        }) => Some(FinalityEvidence::Precommits {
+            child_hash: child.hash(),
+            view: finalized_view,
+            signature_count,
+        }),
+        _ => None,
+    }
+}
+
+/// Checks that the seal of `child` has the precommits of `header` signed by more than two thirds of `validators`.
+pub fn verify_finality(header: &Header, child: &Header, validators: &[WeightedValidator]) -> Result<(), String> {
+    if child.parent_hash() != &header.hash() {
+        return Err(format!("#{} is not the child of #{}", child.number(), header.number()))
+    }
+    if validators.is_empty() {
+        return Err("There are no validators".to_string())
+    }
+    let seal_view = TendermintSealView::new(child.seal());
+    seal_view.decode()?;
+    let finalized_view = seal_view.previous_block_view().map_err(|err| err.to_string())?;
+    let precommit_hash = message_hash(VoteStep::new(header.number(), finalized_view, Step::Precommit), header.hash());
+
+    let public_of = |index: usize| {
+        validators
+            .get(index)
+            .map(|validator| validator.pubkey)
+            .ok_or_else(|| format!("The signer #{} is not a validator", index))
+    };
+    let signers = match seal_view.signatures().map_err(|err| err.to_string())? {
+        SealSignatures::Separate(signatures) => {
+            for (index, signature) in &signatures {
+                let public = public_of(*index)?;
+                if !verify_schnorr(&public, signature, &precommit_hash).map_err(|err| err.to_string())? {
+                    return Err(format!("The precommit of the signer #{} is invalid", index))
+                }
+            }
+            signatures.into_iter().map(|(index, _)| index).collect()
+        }
+        SealSignatures::Aggregated(signers, aggregate) => {
+            let publics = signers.iter().map(|index| public_of(*index)).collect::<Result<Vec<_>, _>>()?;
+            if !verify_schnorr_aggregate(&publics, &aggregate, &precommit_hash).map_err(|err| err.to_string())? {
+                return Err("The aggregated precommits are invalid".to_string())
+            }
+            signers
+        }
+    };
+
+    let mut voted = BitSet::new();
+    for index in signers {
+        if voted.is_set(index) {
+            return Err(format!("The signer #{} voted twice", index))
+        }
+        voted.set(index);
+    }
+    let weight = |validator: &WeightedValidator| validator.delegation.unwrap_or(1);
+    let voted_weight: u64 = voted.true_index_iter().map(|index| weight(&validators[index])).sum();
+    let total_weight: u64 = validators.iter().map(weight).sum();
+    if voted_weight * 3 > total_weight * 2 {
+        Ok(())
+    } else {
+        Err(format!("The precommits weigh {} out of {}", voted_weight, total_weight))
+    }
+}
+
+/// Builds the seals and the state proofs, to test the light clients without running the chain.
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_helpers {
+    use ckey::{sign_schnorr, Private, Public};
+    use cmerkle::{prove, TrieFactory};
+    use cstate::{Metadata, MetadataAddress};
+    use ctypes::Header;
+    use memorydb::MemoryDB;
+    use primitives::{Bytes, H256};
+    use rlp::{encode, encode_list, Encodable};
+
+    use super::super::super::stake::{Validator, VALIDATORS_KEY};
+    use super::super::super::{BitSet, Seal};
+    use super::super::message::message_hash;
+    use super::super::{Step, View, VoteStep};
+    use super::term_snapshot_keys;
+
+    /// The seal of the child of `header`, with the precommits of the signers given with their indices.
+    pub fn seal_with_precommits(header: &Header, view: View, signers: &[(usize, &Private)]) -> Vec<Bytes> {
+        let precommit_hash = message_hash(VoteStep::new(header.number(), view, Step::Precommit), header.hash());
+        let mut signers = signers.to_vec();
+        signers.sort_by_key(|(index, _)| *index);
+        let mut precommit_bitset = BitSet::new();
+        let mut precommits = Vec::new();
+        for (index, private) in signers {
+            precommit_bitset.set(index);
+            precommits.push(sign_schnorr(private, &precommit_hash).unwrap());
+        }
+        Seal::Tendermint {
+            prev_view: view,
+            cur_view: view,
+            precommits,
+            precommit_bitset,
+        }
+        .seal_fields()
+        .unwrap()
+    }
+
+    /// The state root that has the term and the validators, and the proof of them.
+    ///
+    /// The validators are given in the order of their indices in the seals, with their delegation.
+    pub fn term_snapshot_proof(closed_term_at: Option<u64>, validators: &[(Public, u64)]) -> (H256, Vec<Bytes>) {
+        let mut metadata = Metadata::new(1);
+        if let Some(block_number) = closed_term_at {
+            metadata.increase_term_id(block_number);
+        }
+        // The state keeps them in the reverse order.
+        let validators: Vec<_> = validators
+            .iter()
+            .rev()
+            .map(|(pubkey, delegation)| Validator::new_for_test(*delegation, 100, *pubkey))
+            .collect();
+        let mut db = MemoryDB::new();
+        let mut root = H256::new();
+        {
+            let mut trie = TrieFactory::create(&mut db, &mut root);
+            trie.insert(&*MetadataAddress::new(), &metadata.rlp_bytes()).unwrap();
+            if !validators.is_empty() {
+                trie.insert(&*VALIDATORS_KEY, &encode(&encode_list(&validators).into_vec())).unwrap();
+            }
+        }
+        let mut proof: Vec<_> = term_snapshot_keys().iter().flat_map(|key| prove(&db, &root, key).unwrap()).collect();
+        proof.sort();
+        proof.dedup();
+        (root, proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ckey::{Generator, KeyPair, Random};
+
+    use super::test_helpers::{seal_with_precommits, term_snapshot_proof};
+    use super::*;
+
+    #[test]
+    fn finality_across_the_term_boundary() {
+        let initial: Vec<KeyPair> = (0..4).map(|_| Random.generate().unwrap()).collect();
+        let elected: Vec<KeyPair> = (0..3).map(|_| Random.generate().unwrap()).collect();
+        // The descending order of the delegation, as the seals index them.
+        let stakes: Vec<_> =
+            elected.iter().enumerate().map(|(index, key_pair)| (*key_pair.public(), 30 - 10 * index as u64)).collect();
+
+        // The block #1 closes the term 0, so the elected validators seal the block #2.
+        let (root_before, proof_before) = term_snapshot_proof(None, &[]);
+        let (root_after, proof_after) = term_snapshot_proof(Some(1), &stakes);
+        let before = term_snapshot_from_proof(&root_before, &proof_before).unwrap();
+        assert_eq!(0, before.term_id);
+        assert_eq!(None, before.validators);
+        let after = term_snapshot_from_proof(&root_after, &proof_after).unwrap();
+        assert_eq!(1, after.term_id);
+        assert_eq!(1, after.last_term_finished_block_num);
+        let elected_snapshot = after.validators.unwrap();
+        assert_eq!(*elected[0].public(), elected_snapshot[0].pubkey);
+        assert_eq!(Some(30), elected_snapshot[0].delegation);
+
+        let mut genesis = Header::new();
+        genesis.set_state_root(root_before);
+        let mut block1 = genesis.generate_child();
+        block1.set_state_root(root_after);
+        let initial_snapshot = initial_snapshot(&initial.iter().map(|key_pair| *key_pair.public()).collect::<Vec<_>>());
+
+        // Three of the four initial validators finalize the block #1.
+        let mut block2 = block1.generate_child();
+        block2.set_seal(seal_with_precommits(&block1, 0, &[
+            (0, initial[0].private()),
+            (1, initial[1].private()),
+            (3, initial[3].private()),
+        ]));
+        assert_eq!(Ok(()), verify_finality(&block1, &block2, &initial_snapshot));
+        assert!(verify_finality(&block1, &block2, &elected_snapshot).is_err());
+
+        // Two of them are not enough.
+        let mut weak = block1.generate_child();
+        weak.set_seal(seal_with_precommits(&block1, 0, &[(0, initial[0].private()), (1, initial[1].private())]));
+        assert!(verify_finality(&block1, &weak, &initial_snapshot).is_err());
+
+        // The two validators of the most delegation finalize the block #2 of the new term.
+        let mut block3 = block2.generate_child();
+        block3.set_seal(seal_with_precommits(&block2, 2, &[(0, elected[0].private()), (1, elected[1].private())]));
+        assert_eq!(Ok(()), verify_finality(&block2, &block3, &elected_snapshot));
+        assert!(verify_finality(&block2, &block3, &initial_snapshot).is_err());
+        assert!(verify_finality(&block1, &block3, &elected_snapshot).is_err());
+
+        // The ones of the least delegation are not enough.
+        let mut weak = block2.generate_child();
+        weak.set_seal(seal_with_precommits(&block2, 0, &[(1, elected[1].private()), (2, elected[2].private())]));
+        assert!(verify_finality(&block2, &weak, &elected_snapshot).is_err());
+    }
+
+    #[test]
+    fn snapshot_needs_the_proof_of_both_keys() {
+        let key_pair: KeyPair = Random.generate().unwrap();
+        let (root, proof) = term_snapshot_proof(Some(10), &[(*key_pair.public(), 10)]);
+        assert!(term_snapshot_from_proof(&root, &proof).is_ok());
+        // Every node of the proof is needed.
+        for index in 0..proof.len() {
+            let mut partial = proof.clone();
+            partial.remove(index);
+            assert!(term_snapshot_from_proof(&root, &partial).is_err());
+        }
+        assert!(term_snapshot_from_proof(&H256::random(), &proof).is_err());
+    }
+}
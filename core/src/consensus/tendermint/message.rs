@@ -29,7 +29,7 @@ use super::super::BitSet;
 use super::{BlockHash, Height, Step, View};
 
 /// Complete step of the consensus process.
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, RlpDecodable, RlpEncodable)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, RlpEncodable)]
 pub struct VoteStep {
     pub height: Height,
     pub view: View,
@@ -50,6 +50,23 @@ impl VoteStep {
     }
 }
 
+impl Decodable for VoteStep {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 3 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected: 3,
+            })
+        }
+        Ok(VoteStep {
+            height: rlp.val_at(0)?,
+            view: rlp.val_at(1)?,
+            step: rlp.val_at(2)?,
+        })
+    }
+}
+
 impl Default for VoteStep {
     fn default() -> Self {
         VoteStep::new(0, 0, Step::Propose)
@@ -79,6 +96,17 @@ const MESSAGE_ID_PROPOSAL_BLOCK: u8 = 0x02;
 const MESSAGE_ID_STEP_STATE: u8 = 0x03;
 const MESSAGE_ID_REQUEST_MESSAGE: u8 = 0x04;
 const MESSAGE_ID_REQUEST_PROPOSAL: u8 = 0x05;
+const MESSAGE_ID_COMMIT: u8 = 0x06;
+const MESSAGE_ID_PROPOSAL_HEADER_AND_BODY: u8 = 0x07;
+const MESSAGE_ID_CAPABILITY: u8 = 0x08;
+
+/// Bounds the memory that a malicious proposal makes the decoder allocate. The compressed proposal is at most 16MiB.
+const MAX_DECOMPRESSED_SIZE: usize = 64 * 1024 * 1024;
+
+/// The first version of the extension that sends the header of a proposal separately from its compressed body.
+pub const PROPOSAL_HEADER_AND_BODY_VERSION: u64 = 1;
+/// The first version of the extension that advertises whether the node takes part in the voting.
+pub const CAPABILITY_VERSION: u64 = 2;
 
 #[derive(Debug, PartialEq)]
 pub enum TendermintMessage {
@@ -102,6 +130,126 @@ pub enum TendermintMessage {
         height: Height,
         view: View,
     },
+    /// The precommits of a committed block, encoded in the same way as the seal of the next block.
+    Commit {
+        height: Height,
+        view: View,
+        block_hash: BlockHash,
+        precommits: Vec<SchnorrSignature>,
+        precommit_bitset: BitSet,
+    },
+    /// The proposal whose body is decompressed only after it is validated from the header.
+    ProposalHeaderAndBody {
+        signature: SchnorrSignature,
+        view: View,
+        header: Bytes,
+        compressed_body: Bytes,
+    },
+    /// The public key that the node signs the votes with, and whether it relays all the votes anyway.
+    /// The signer is proven by signing the challenge of the receiver, which is sent in its own capability.
+    Capability {
+        signer: Option<Public>,
+        full_gossip: bool,
+        challenge: H256,
+        proof: Option<SchnorrSignature>,
+    },
+}
+
+impl TendermintMessage {
+    /// Creates the proposal message in the format that the peer of the given extension version understands.
+    pub fn proposal(signature: SchnorrSignature, view: View, block: Bytes, version: u64) -> Self {
+        if version < PROPOSAL_HEADER_AND_BODY_VERSION {
+            return TendermintMessage::ProposalBlock {
+                signature,
+                view,
+                message: block,
+            }
+        }
+        let rlp = UntrustedRlp::new(&block);
+        let header = rlp.at(0).expect("The proposal is a valid block").as_raw().to_vec();
+        let body = rlp.at(1).expect("The proposal is a valid block").as_raw().to_vec();
+        TendermintMessage::ProposalHeaderAndBody {
+            signature,
+            view,
+            header,
+            compressed_body: compress(&body),
+        }
+    }
+}
+
+/// Unlike `bool`, which takes any non-zero byte as true, accepts only what `bool` is encoded into.
+fn decode_flag(rlp: &UntrustedRlp) -> Result<bool, DecoderError> {
+    rlp.decoder().decode_value(|bytes| match bytes {
+        [0] => Ok(false),
+        [1] => Ok(true),
+        _ => Err(DecoderError::Custom("Invalid flag")),
+    })
+}
+
+fn compress(bytes: &[u8]) -> Bytes {
+    // TODO: Cache the Encoder object
+    let mut snappy_encoder = snap::Encoder::new();
+    snappy_encoder.compress_vec(bytes).expect("Compression always succeed")
+}
+
+fn decompress(bytes: &[u8]) -> Result<Bytes, DecoderError> {
+    // The decoder allocates the length written in the input before it decompresses anything.
+    let len = snap::decompress_len(bytes).map_err(|_| DecoderError::Custom("Invalid compression format"))?;
+    if len > MAX_DECOMPRESSED_SIZE {
+        return Err(DecoderError::RlpIsTooBig {
+            expected: MAX_DECOMPRESSED_SIZE,
+            got: len,
+        })
+    }
+    // TODO: Cache the Decoder object
+    let mut snappy_decoder = snap::Decoder::new();
+    snappy_decoder.decompress_vec(bytes).map_err(|err| {
+        // The caller warns, which is rate limited for each peer.
+        cdebug!(ENGINE, "Decompression failed while decoding a proposal: {}", err);
+        DecoderError::Custom("Invalid compression format")
+    })
+}
+
+/// The block of a received proposal.
+#[derive(Debug, PartialEq)]
+pub enum ProposedBlock {
+    Decoded(Bytes),
+    Compressed {
+        header: Bytes,
+        compressed_body: Bytes,
+    },
+}
+
+impl ProposedBlock {
+    pub fn header(&self) -> Result<Header, DecoderError> {
+        match self {
+            ProposedBlock::Decoded(block) => UntrustedRlp::new(block).val_at(0),
+            ProposedBlock::Compressed {
+                header,
+                ..
+            } => UntrustedRlp::new(header).as_val(),
+        }
+    }
+
+    /// Returns the RLP of the block, decompressing the body if it is still compressed.
+    pub fn into_block(self) -> Result<Bytes, DecoderError> {
+        match self {
+            ProposedBlock::Decoded(block) => Ok(block),
+            ProposedBlock::Compressed {
+                header,
+                compressed_body,
+            } => {
+                let body = decompress(&compressed_body)?;
+                if !UntrustedRlp::new(&body).is_list() {
+                    return Err(DecoderError::RlpExpectedToBeList)
+                }
+                let mut s = RlpStream::new_list(2);
+                s.append_raw(&header, 1);
+                s.append_raw(&body, 1);
+                Ok(s.out())
+            }
+        }
+    }
 }
 
 impl Encodable for TendermintMessage {
@@ -121,13 +269,7 @@ impl Encodable for TendermintMessage {
                 s.append(&MESSAGE_ID_PROPOSAL_BLOCK);
                 s.append(signature);
                 s.append(view);
-
-                let compressed = {
-                    // TODO: Cache the Encoder object
-                    let mut snappy_encoder = snap::Encoder::new();
-                    snappy_encoder.compress_vec(message).expect("Compression always succeed")
-                };
-                s.append(&compressed);
+                s.append(&compress(message));
             }
             TendermintMessage::StepState {
                 vote_step,
@@ -160,6 +302,47 @@ impl Encodable for TendermintMessage {
                 s.append(height);
                 s.append(view);
             }
+            TendermintMessage::Commit {
+                height,
+                view,
+                block_hash,
+                precommits,
+                precommit_bitset,
+            } => {
+                s.begin_list(6);
+                s.append(&MESSAGE_ID_COMMIT);
+                s.append(height);
+                s.append(view);
+                s.append(block_hash);
+                s.append_list::<SchnorrSignature, SchnorrSignature>(precommits);
+                s.append(precommit_bitset);
+            }
+            TendermintMessage::ProposalHeaderAndBody {
+                signature,
+                view,
+                header,
+                compressed_body,
+            } => {
+                s.begin_list(5);
+                s.append(&MESSAGE_ID_PROPOSAL_HEADER_AND_BODY);
+                s.append(signature);
+                s.append(view);
+                s.append(header);
+                s.append(compressed_body);
+            }
+            TendermintMessage::Capability {
+                signer,
+                full_gossip,
+                challenge,
+                proof,
+            } => {
+                s.begin_list(5);
+                s.append(&MESSAGE_ID_CAPABILITY);
+                s.append(signer);
+                s.append(full_gossip);
+                s.append(challenge);
+                s.append(proof);
+            }
         }
     }
 }
@@ -189,14 +372,7 @@ impl Decodable for TendermintMessage {
                 let signature = rlp.at(1)?;
                 let view = rlp.at(2)?;
                 let compressed_message: Vec<u8> = rlp.val_at(3)?;
-                let uncompressed_message = {
-                    // TODO: Cache the Decoder object
-                    let mut snappy_decoder = snap::Decoder::new();
-                    snappy_decoder.decompress_vec(&compressed_message).map_err(|err| {
-                        cwarn!(SYNC, "Decompression failed while decoding a body response: {}", err);
-                        DecoderError::Custom("Invalid compression format")
-                    })?
-                };
+                let uncompressed_message = decompress(&compressed_message)?;
 
                 TendermintMessage::ProposalBlock {
                     signature: signature.as_val()?,
@@ -253,25 +429,109 @@ impl Decodable for TendermintMessage {
                     view,
                 }
             }
+            MESSAGE_ID_COMMIT => {
+                let item_count = rlp.item_count()?;
+                if item_count != 6 {
+                    return Err(DecoderError::RlpIncorrectListLen {
+                        got: item_count,
+                        expected: 6,
+                    })
+                }
+                let height = rlp.at(1)?.as_val()?;
+                let view = rlp.at(2)?.as_val()?;
+                let block_hash = rlp.at(3)?.as_val()?;
+                let precommits = rlp.list_at(4)?;
+                let precommit_bitset = rlp.at(5)?.as_val()?;
+                TendermintMessage::Commit {
+                    height,
+                    view,
+                    block_hash,
+                    precommits,
+                    precommit_bitset,
+                }
+            }
+            MESSAGE_ID_PROPOSAL_HEADER_AND_BODY => {
+                let item_count = rlp.item_count()?;
+                if item_count != 5 {
+                    return Err(DecoderError::RlpIncorrectListLen {
+                        got: item_count,
+                        expected: 5,
+                    })
+                }
+                TendermintMessage::ProposalHeaderAndBody {
+                    signature: rlp.val_at(1)?,
+                    view: rlp.val_at(2)?,
+                    header: rlp.val_at(3)?,
+                    compressed_body: rlp.val_at(4)?,
+                }
+            }
+            MESSAGE_ID_CAPABILITY => {
+                let item_count = rlp.item_count()?;
+                if item_count != 5 {
+                    return Err(DecoderError::RlpIncorrectListLen {
+                        got: item_count,
+                        expected: 5,
+                    })
+                }
+                TendermintMessage::Capability {
+                    signer: rlp.val_at(1)?,
+                    full_gossip: decode_flag(&rlp.at(2)?)?,
+                    challenge: rlp.val_at(3)?,
+                    proof: rlp.val_at(4)?,
+                }
+            }
             _ => return Err(DecoderError::Custom("Unknown message id detected")),
         })
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash, Default, RlpDecodable, RlpEncodable)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Default, RlpEncodable)]
 pub struct VoteOn {
     pub step: VoteStep,
     pub block_hash: Option<BlockHash>,
 }
 
+impl Decodable for VoteOn {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 2 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected: 2,
+            })
+        }
+        Ok(VoteOn {
+            step: rlp.val_at(0)?,
+            block_hash: rlp.val_at(1)?,
+        })
+    }
+}
+
 /// Message transmitted between consensus participants.
-#[derive(Debug, PartialEq, Eq, Clone, Hash, Default, RlpDecodable, RlpEncodable)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Default, RlpEncodable)]
 pub struct ConsensusMessage {
     pub on: VoteOn,
     pub signature: SchnorrSignature,
     pub signer_index: usize,
 }
 
+impl Decodable for ConsensusMessage {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 3 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected: 3,
+            })
+        }
+        Ok(ConsensusMessage {
+            on: rlp.val_at(0)?,
+            signature: rlp.val_at(1)?,
+            signer_index: rlp.val_at(2)?,
+        })
+    }
+}
+
 impl ConsensusMessage {
     /// If a locked node re-proposes locked proposal, the proposed_view is different from the header's view.
     pub fn new_proposal(
@@ -345,6 +605,12 @@ pub fn message_hash(step: VoteStep, block_hash: H256) -> H256 {
     blake256(&vote_on.rlp_bytes())
 }
 
+/// The hash that a peer signs to prove that it owns the signer in its capability.
+/// It's prefixed so that the signature cannot be taken for a vote.
+pub fn capability_proof_hash(challenge: &H256) -> H256 {
+    blake256(&[b"capability".as_ref(), challenge.as_ref()].concat())
+}
+
 #[cfg(test)]
 mod tests {
     use rlp::{self, rlp_encode_and_decode_test};
@@ -408,6 +674,130 @@ mod tests {
         });
     }
 
+    #[test]
+    fn encode_and_decode_tendermint_message_6() {
+        rlp_encode_and_decode_test!(TendermintMessage::Commit {
+            height: 10,
+            view: 123,
+            block_hash: H256::random(),
+            precommits: vec![SchnorrSignature::random(), SchnorrSignature::random()],
+            precommit_bitset: BitSet::new_with_indices(&[0, 2]),
+        });
+    }
+
+    #[test]
+    fn encode_and_decode_tendermint_message_6_without_precommits() {
+        rlp_encode_and_decode_test!(TendermintMessage::Commit {
+            height: 10,
+            view: 0,
+            block_hash: H256::random(),
+            precommits: vec![],
+            precommit_bitset: BitSet::new(),
+        });
+    }
+
+    #[test]
+    fn encode_and_decode_tendermint_message_7() {
+        rlp_encode_and_decode_test!(TendermintMessage::ProposalHeaderAndBody {
+            signature: SchnorrSignature::random(),
+            view: 1,
+            header: vec![1u8, 2u8],
+            compressed_body: vec![3u8, 4u8],
+        });
+    }
+
+    fn proposed_block(message: TendermintMessage) -> ProposedBlock {
+        match message {
+            TendermintMessage::ProposalBlock {
+                message,
+                ..
+            } => ProposedBlock::Decoded(message),
+            TendermintMessage::ProposalHeaderAndBody {
+                header,
+                compressed_body,
+                ..
+            } => ProposedBlock::Compressed {
+                header,
+                compressed_body,
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn proposal_is_sent_in_the_format_of_the_peer_version() {
+        let header = Header::default();
+        let mut s = RlpStream::new_list(2);
+        s.append(&header);
+        s.append_list::<Bytes, Bytes>(&[vec![1u8, 2u8]]);
+        let block = s.out();
+
+        for version in 0..=PROPOSAL_HEADER_AND_BODY_VERSION {
+            let message = TendermintMessage::proposal(SchnorrSignature::random(), 1, block.clone(), version);
+            let proposed = proposed_block(UntrustedRlp::new(&message.rlp_bytes()).as_val().unwrap());
+            match (version, &proposed) {
+                (0, ProposedBlock::Decoded(_)) => {}
+                (
+                    1,
+                    ProposedBlock::Compressed {
+                        ..
+                    },
+                ) => {}
+                _ => panic!("Unexpected format {:?} for version {}", proposed, version),
+            }
+            assert_eq!(Ok(header.clone()), proposed.header());
+            assert_eq!(Ok(block.clone()), proposed.into_block());
+        }
+    }
+
+    #[test]
+    fn header_of_a_proposal_with_a_garbage_body_is_decodable() {
+        let header = Header::default();
+        let proposed = ProposedBlock::Compressed {
+            header: header.rlp_bytes().into_vec(),
+            compressed_body: vec![0xffu8; 16],
+        };
+        assert_eq!(Ok(header), proposed.header());
+        assert!(proposed.into_block().is_err());
+    }
+
+    #[test]
+    fn decode_commit_with_wrong_length() {
+        let mut s = RlpStream::new_list(5);
+        s.append(&MESSAGE_ID_COMMIT);
+        s.append(&10u64);
+        s.append(&123u64);
+        s.append(&H256::random());
+        s.append_list::<SchnorrSignature, SchnorrSignature>(&[SchnorrSignature::random()]);
+        assert_eq!(
+            Err(DecoderError::RlpIncorrectListLen {
+                got: 5,
+                expected: 6,
+            }),
+            UntrustedRlp::new(&s.out()).as_val::<TendermintMessage>()
+        );
+    }
+
+    #[test]
+    fn encode_and_decode_capability_of_a_signer() {
+        rlp_encode_and_decode_test!(TendermintMessage::Capability {
+            signer: Some(Public::random()),
+            full_gossip: false,
+            challenge: H256::random(),
+            proof: Some(SchnorrSignature::random()),
+        });
+    }
+
+    #[test]
+    fn encode_and_decode_capability_of_a_relay() {
+        rlp_encode_and_decode_test!(TendermintMessage::Capability {
+            signer: None,
+            full_gossip: true,
+            challenge: H256::random(),
+            proof: None,
+        });
+    }
+
     #[test]
     fn encode_and_decode_consensus_message_1() {
         let message = ConsensusMessage::default();
@@ -0,0 +1,421 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+//! Round-trip tests of the messages that the Tendermint extension exchanges.
+//! The messages are generated from a fixed seed, so a failure is reproduced on every run.
+
+use std::fmt::Debug;
+
+use ckey::{Public, SchnorrSignature};
+use ctypes::Header;
+use primitives::{Bytes, H256};
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
+
+use super::super::bit_set::MAX_VALIDATOR_SIZE;
+use super::super::BitSet;
+use super::message::{ConsensusMessage, ProposedBlock, TendermintMessage, VoteOn, VoteStep};
+use super::Step;
+
+const ITERATIONS: usize = 512;
+
+fn rng() -> XorShiftRng {
+    let seed: [u8; 16] = [7, 6, 5, 4, 3, 2, 1, 0, 7, 6, 5, 4, 3, 2, 1, 0];
+    XorShiftRng::from_seed(seed)
+}
+
+fn edge_u64(rng: &mut XorShiftRng) -> u64 {
+    match rng.gen_range(0, 4) {
+        0 => 0,
+        1 => u64::max_value(),
+        _ => rng.gen(),
+    }
+}
+
+fn edge_usize(rng: &mut XorShiftRng) -> usize {
+    match rng.gen_range(0, 4) {
+        0 => 0,
+        1 => usize::max_value(),
+        _ => rng.gen(),
+    }
+}
+
+fn bytes(rng: &mut XorShiftRng) -> Bytes {
+    let len = match rng.gen_range(0, 4) {
+        0 => 0,
+        1 => 1,
+        _ => rng.gen_range(0, 256),
+    };
+    (0..len).map(|_| rng.gen()).collect()
+}
+
+fn h256(rng: &mut XorShiftRng) -> H256 {
+    let mut bytes = [0u8; 32];
+    rng.fill(&mut bytes[..]);
+    H256::from(bytes)
+}
+
+fn public(rng: &mut XorShiftRng) -> Public {
+    let mut bytes = [0u8; 64];
+    rng.fill(&mut bytes[..]);
+    Public::from(bytes)
+}
+
+fn signature(rng: &mut XorShiftRng) -> SchnorrSignature {
+    if rng.gen_range(0, 8) == 0 {
+        return SchnorrSignature::default()
+    }
+    let mut bytes = [0u8; 64];
+    rng.fill(&mut bytes[..]);
+    SchnorrSignature::from(bytes)
+}
+
+fn bit_set(rng: &mut XorShiftRng) -> BitSet {
+    match rng.gen_range(0, 4) {
+        0 => BitSet::new(),
+        1 => BitSet::all_set(),
+        _ => {
+            let indices: Vec<usize> = (0..rng.gen_range(0, 32)).map(|_| rng.gen_range(0, MAX_VALIDATOR_SIZE)).collect();
+            BitSet::new_with_indices(&indices)
+        }
+    }
+}
+
+fn step(rng: &mut XorShiftRng) -> Step {
+    match rng.gen_range(0, 4) {
+        0 => Step::Propose,
+        1 => Step::Prevote,
+        2 => Step::Precommit,
+        _ => Step::Commit,
+    }
+}
+
+fn vote_step(rng: &mut XorShiftRng) -> VoteStep {
+    VoteStep::new(edge_u64(rng), edge_u64(rng), step(rng))
+}
+
+fn optional<T, F>(rng: &mut XorShiftRng, f: F) -> Option<T>
+where
+    F: FnOnce(&mut XorShiftRng) -> T, {
+    if rng.gen() {
+        Some(f(rng))
+    } else {
+        None
+    }
+}
+
+fn vote_on(rng: &mut XorShiftRng) -> VoteOn {
+    VoteOn {
+        step: vote_step(rng),
+        block_hash: optional(rng, h256),
+    }
+}
+
+fn consensus_message(rng: &mut XorShiftRng) -> ConsensusMessage {
+    ConsensusMessage {
+        on: vote_on(rng),
+        signature: signature(rng),
+        signer_index: edge_usize(rng),
+    }
+}
+
+fn tendermint_message(rng: &mut XorShiftRng) -> TendermintMessage {
+    match rng.gen_range(0, 8) {
+        0 => {
+            let votes = (0..rng.gen_range(0, 4)).map(|_| consensus_message(rng).rlp_bytes().into_vec()).collect();
+            TendermintMessage::ConsensusMessage(votes)
+        }
+        1 => TendermintMessage::ProposalBlock {
+            signature: signature(rng),
+            view: edge_u64(rng),
+            message: bytes(rng),
+        },
+        2 => TendermintMessage::StepState {
+            vote_step: vote_step(rng),
+            proposal: optional(rng, h256),
+            lock_view: optional(rng, edge_u64),
+            known_votes: bit_set(rng),
+        },
+        3 => TendermintMessage::RequestMessage {
+            vote_step: vote_step(rng),
+            requested_votes: bit_set(rng),
+        },
+        4 => TendermintMessage::RequestProposal {
+            height: edge_u64(rng),
+            view: edge_u64(rng),
+        },
+        5 => TendermintMessage::Commit {
+            height: edge_u64(rng),
+            view: edge_u64(rng),
+            block_hash: h256(rng),
+            precommits: (0..rng.gen_range(0, 8)).map(|_| signature(rng)).collect(),
+            precommit_bitset: bit_set(rng),
+        },
+        6 => TendermintMessage::ProposalHeaderAndBody {
+            signature: signature(rng),
+            view: edge_u64(rng),
+            header: bytes(rng),
+            compressed_body: bytes(rng),
+        },
+        _ => TendermintMessage::Capability {
+            signer: optional(rng, public),
+            full_gossip: rng.gen(),
+        },
+    }
+}
+
+/// Asserts that the value is decoded from its encoding, and that the decoded value is encoded into the same bytes.
+fn assert_round_trip<T>(value: &T)
+where
+    T: Encodable + Decodable + Debug + PartialEq, {
+    let encoded = value.rlp_bytes().into_vec();
+    let decoded: T =
+        UntrustedRlp::new(&encoded).as_val().unwrap_or_else(|err| panic!("Cannot decode {:?}: {:?}", value, err));
+    assert_eq!(*value, decoded);
+    assert_eq!(encoded, decoded.rlp_bytes().into_vec(), "{:?} is encoded differently after decoding", value);
+}
+
+fn from_hex(hex: &str) -> Bytes {
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect()
+}
+
+fn decode(hex: &str) -> Result<TendermintMessage, DecoderError> {
+    UntrustedRlp::new(&from_hex(hex)).as_val()
+}
+
+#[test]
+fn vote_steps_round_trip() {
+    let mut rng = rng();
+    for _ in 0..ITERATIONS {
+        assert_round_trip(&vote_step(&mut rng));
+    }
+}
+
+#[test]
+fn vote_ons_round_trip() {
+    let mut rng = rng();
+    for _ in 0..ITERATIONS {
+        assert_round_trip(&vote_on(&mut rng));
+    }
+}
+
+#[test]
+fn consensus_messages_round_trip() {
+    let mut rng = rng();
+    for _ in 0..ITERATIONS {
+        assert_round_trip(&consensus_message(&mut rng));
+    }
+}
+
+#[test]
+fn bit_sets_round_trip() {
+    let mut rng = rng();
+    for _ in 0..ITERATIONS {
+        assert_round_trip(&bit_set(&mut rng));
+    }
+}
+
+#[test]
+fn tendermint_messages_round_trip() {
+    let mut rng = rng();
+    for _ in 0..ITERATIONS {
+        assert_round_trip(&tendermint_message(&mut rng));
+    }
+}
+
+#[test]
+fn truncated_messages_are_not_decoded() {
+    let mut rng = rng();
+    for _ in 0..ITERATIONS {
+        let encoded = tendermint_message(&mut rng).rlp_bytes().into_vec();
+        let len = rng.gen_range(0, encoded.len());
+        assert!(UntrustedRlp::new(&encoded[..len]).as_val::<TendermintMessage>().is_err());
+    }
+}
+
+#[test]
+fn messages_are_encoded_into_the_test_vectors() {
+    let vectors = vec![
+        (
+            TendermintMessage::RequestProposal {
+                height: 10,
+                view: 123,
+            },
+            "c3050a7b",
+        ),
+        (
+            TendermintMessage::Capability {
+                signer: None,
+                full_gossip: true,
+            },
+            "c308c001",
+        ),
+        (
+            TendermintMessage::Capability {
+                signer: None,
+                full_gossip: false,
+            },
+            "c308c000",
+        ),
+    ];
+    for (message, hex) in vectors {
+        assert_eq!(from_hex(hex), message.rlp_bytes().into_vec(), "{:?}", message);
+        assert_eq!(Ok(message), decode(hex));
+    }
+}
+
+#[test]
+fn vote_is_encoded_into_the_test_vector() {
+    let vote = ConsensusMessage {
+        on: VoteOn {
+            step: VoteStep::new(1, 0, Step::Prevote),
+            block_hash: None,
+        },
+        signature: SchnorrSignature::default(),
+        signer_index: 2,
+    };
+    let hex = format!("f849c5c3018001c0b840{}02", "00".repeat(64));
+    assert_eq!(from_hex(&hex), vote.rlp_bytes().into_vec());
+}
+
+#[test]
+fn malformed_messages_are_not_decoded() {
+    let corpus = vec![
+        // Not a list
+        "",
+        "05",
+        "8405050505",
+        // No message id
+        "c0",
+        // Unknown message id
+        "c109",
+        // The payload is longer than the input
+        "f8ff01",
+        "c50501",
+        // Missing or extra fields
+        "c101",
+        "c30101c0",
+        "c105",
+        "c405050505",
+        "c208c0",
+        "c408c00100",
+        // The height is not canonical
+        "c405810a7b",
+        "c305007b",
+        // The height doesn't fit in u64
+        "cc05890100000000000000007b",
+        // The votes are not a list
+        "c20180",
+        // The capability is true only as 0x01
+        "c308c002",
+        "c408c08180",
+        // The signer is neither none nor a public key
+        "c508c2808001",
+        "c608c382010201",
+    ];
+    for hex in corpus {
+        assert!(decode(hex).is_err(), "{} must not be decoded", hex);
+    }
+}
+
+#[test]
+fn malformed_structures_in_messages_are_not_decoded() {
+    let mut rng = rng();
+    let mut corpus = Vec::new();
+
+    // A vote step with an extra field
+    let mut s = RlpStream::new_list(3);
+    s.append(&0x04u8);
+    s.begin_list(4).append(&1u64).append(&0u64).append(&Step::Prevote).append(&0u64);
+    s.append(&BitSet::new());
+    corpus.push(s.out());
+
+    // An invalid step
+    let mut s = RlpStream::new_list(3);
+    s.append(&0x04u8);
+    s.begin_list(3).append(&1u64).append(&0u64).append(&4u8);
+    s.append(&BitSet::new());
+    corpus.push(s.out());
+
+    // A bit set that is a byte short
+    let mut s = RlpStream::new_list(3);
+    s.append(&0x04u8);
+    s.append(&VoteStep::new(1, 0, Step::Prevote));
+    s.append(&vec![0u8; MAX_VALIDATOR_SIZE / 8 - 1]);
+    corpus.push(s.out());
+
+    // A signature that is a byte short
+    let mut s = RlpStream::new_list(6);
+    s.append(&0x06u8);
+    s.append(&1u64);
+    s.append(&0u64);
+    s.append(&h256(&mut rng));
+    s.begin_list(1).append(&vec![0u8; 63]);
+    s.append(&BitSet::new());
+    corpus.push(s.out());
+
+    // A proposal that is not compressed
+    let mut s = RlpStream::new_list(4);
+    s.append(&0x02u8);
+    s.append(&signature(&mut rng));
+    s.append(&0u64);
+    s.append(&vec![0xffu8; 16]);
+    corpus.push(s.out());
+
+    // A proposal that claims to be decompressed into 4GiB
+    let mut s = RlpStream::new_list(4);
+    s.append(&0x02u8);
+    s.append(&signature(&mut rng));
+    s.append(&0u64);
+    s.append(&vec![0xffu8, 0xff, 0xff, 0xff, 0x0f]);
+    corpus.push(s.out());
+
+    for bytes in corpus {
+        assert!(UntrustedRlp::new(&bytes).as_val::<TendermintMessage>().is_err(), "{:?} must not be decoded", bytes);
+    }
+}
+
+#[test]
+fn malformed_votes_are_not_decoded() {
+    let mut rng = rng();
+    let vote = consensus_message(&mut rng);
+
+    let mut s = RlpStream::new_list(4);
+    s.append(&vote.on).append(&vote.signature).append(&vote.signer_index).append(&0u8);
+    assert!(UntrustedRlp::new(&s.out()).as_val::<ConsensusMessage>().is_err());
+
+    let mut s = RlpStream::new_list(3);
+    s.begin_list(3).append(&vote.on.step).append(&vote.on.block_hash).append(&0u8);
+    s.append(&vote.signature).append(&vote.signer_index);
+    assert!(UntrustedRlp::new(&s.out()).as_val::<ConsensusMessage>().is_err());
+
+    let mut s = RlpStream::new_list(2);
+    s.append(&vote.on).append(&vote.signature);
+    assert!(UntrustedRlp::new(&s.out()).as_val::<ConsensusMessage>().is_err());
+}
+
+#[test]
+fn body_that_claims_to_be_huge_is_not_decompressed() {
+    let header = Header::default();
+    let proposed = ProposedBlock::Compressed {
+        header: header.rlp_bytes().into_vec(),
+        compressed_body: vec![0xffu8, 0xff, 0xff, 0xff, 0x0f],
+    };
+    assert_eq!(Ok(header), proposed.header());
+    assert!(proposed.into_block().is_err());
+}
@@ -16,26 +16,34 @@
 
 mod backup;
 mod chain_notify;
+mod clock_skew;
 mod engine;
+pub mod light;
 mod message;
+#[cfg(test)]
+mod message_fuzz;
 mod network;
 mod params;
+#[cfg(any(test, feature = "test-support"))]
+pub mod simulation;
 pub mod types;
 mod worker;
 
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, AtomicUsize};
 use std::sync::{Arc, Weak};
 use std::thread::JoinHandle;
 
+use ckey::Public;
 use crossbeam_channel as crossbeam;
 use cstate::ActionHandler;
 use ctimer::TimerToken;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use primitives::H256;
 
 use self::chain_notify::TendermintChainNotify;
 pub use self::message::{message_info_rlp, ConsensusMessage, VoteOn, VoteStep};
 pub use self::params::{TendermintParams, TimeGapParams, TimeoutParams};
+use self::types::{BadSealSignatureCounters, CommitRecoveryCounters, VoteRequestCounters};
 pub use self::types::{Height, Step, View};
 use super::{stake, ValidatorSet};
 use crate::client::ConsensusClient;
@@ -48,9 +56,17 @@ const ENGINE_TIMEOUT_TOKEN_NONCE_BASE: TimerToken = 23;
 const ENGINE_TIMEOUT_EMPTY_PROPOSAL: TimerToken = 22;
 /// Timer token for broadcasting step state.
 const ENGINE_TIMEOUT_BROADCAST_STEP_STATE: TimerToken = 21;
+/// Timer token for retrying the request of the committed block to another peer.
+const ENGINE_TIMEOUT_COMMITTED_PROPOSAL: TimerToken = 20;
+/// Timer token for requesting the votes that didn't arrive again.
+const ENGINE_TIMEOUT_VOTE_REQUEST: TimerToken = 19;
 
 /// Unit: second
 const ENGINE_TIMEOUT_BROADCAT_STEP_STATE_INTERVAL: u64 = 1;
+/// Unit: millisecond
+const ENGINE_TIMEOUT_COMMITTED_PROPOSAL_INTERVAL: u64 = 500;
+/// Unit: millisecond
+const ENGINE_TIMEOUT_VOTE_REQUEST_INTERVAL: u64 = 200;
 
 pub type BlockHash = H256;
 
@@ -60,10 +76,14 @@ pub struct Tendermint {
     external_params_initializer: crossbeam::Sender<TimeGapParams>,
     extension_initializer: crossbeam::Sender<(crossbeam::Sender<network::Event>, Weak<ConsensusClient>)>,
     timeouts: TimeoutParams,
-    join: Option<JoinHandle<()>>,
+    join: Mutex<Option<JoinHandle<()>>>,
     quit_tendermint: crossbeam::Sender<()>,
     inner: crossbeam::Sender<worker::Event>,
+    /// Handled by the worker ahead of `inner`. Only the network extension uses it.
+    consensus_inner: crossbeam::Sender<worker::Event>,
     validators: Arc<ValidatorSet>,
+    /// The validators in the scheme, which seal the blocks of the term 0.
+    initial_validators: Vec<Public>,
     /// Reward per block, in base units.
     block_reward: u64,
     /// codechain machine descriptor
@@ -75,14 +95,17 @@ pub struct Tendermint {
     /// Chain notify
     chain_notify: Arc<TendermintChainNotify>,
     has_signer: AtomicBool,
+    /// Relays the votes to the observers too.
+    full_gossip: AtomicBool,
+    commit_recovery: Arc<CommitRecoveryCounters>,
+    vote_requests: Arc<VoteRequestCounters>,
+    double_votes_found: Arc<AtomicUsize>,
+    bad_seal_signatures: Arc<BadSealSignatureCounters>,
 }
 
 impl Drop for Tendermint {
     fn drop(&mut self) {
-        self.quit_tendermint.send(()).unwrap();
-        if let Some(handler) = self.join.take() {
-            handler.join().unwrap();
-        }
+        self.quit_worker();
     }
 }
 
@@ -90,53 +113,93 @@ impl Tendermint {
     /// Create a new instance of Tendermint engine
     pub fn new(our_params: TendermintParams, machine: CodeChainMachine) -> Arc<Self> {
         let validators = Arc::clone(&our_params.validators);
+        let initial_validators = our_params.validators.initial_validators().to_vec();
         let stake = Arc::new(stake::Stake::<ConsensusMessage>::new(our_params.genesis_stakes));
         let timeouts = our_params.timeouts;
         let machine = Arc::new(machine);
-
-        let (join, external_params_initializer, extension_initializer, inner, quit_tendermint) =
-            worker::spawn(our_params.validators);
+        let commit_recovery = Arc::new(CommitRecoveryCounters::default());
+        let double_votes_found = Arc::new(AtomicUsize::new(0));
+        let bad_seal_signatures = Arc::new(BadSealSignatureCounters::default());
+
+        let (join, external_params_initializer, extension_initializer, inner, consensus_inner, quit_tendermint) =
+            worker::spawn(
+                our_params.validators,
+                timeouts.propose_grace,
+                timeouts.restore_observation,
+                timeouts.escalation_view,
+                timeouts.max_timestamp_step,
+                Arc::clone(&commit_recovery),
+                Arc::clone(&double_votes_found),
+                Arc::clone(&bad_seal_signatures),
+            );
         let action_handlers: Vec<Arc<ActionHandler>> = vec![stake.clone()];
-        let chain_notify = Arc::new(TendermintChainNotify::new(inner.clone()));
+        let chain_notify = Arc::new(TendermintChainNotify::new(inner.clone(), Arc::clone(&validators)));
 
         Arc::new(Tendermint {
             client: Default::default(),
             external_params_initializer,
             extension_initializer,
             timeouts,
-            join: Some(join),
+            join: Mutex::new(Some(join)),
             quit_tendermint,
             inner,
+            consensus_inner,
             validators,
+            initial_validators,
             block_reward: our_params.block_reward,
             machine,
             action_handlers,
             stake,
             chain_notify,
             has_signer: false.into(),
+            full_gossip: false.into(),
+            commit_recovery,
+            vote_requests: Default::default(),
+            double_votes_found,
+            bad_seal_signatures,
         })
     }
+
+    /// Make the worker flush its backup and wait until it quits.
+    fn quit_worker(&self) {
+        if let Some(handler) = self.join.lock().take() {
+            self.quit_tendermint.send(()).unwrap();
+            handler.join().unwrap();
+        }
+    }
 }
 
 const SEAL_FIELDS: usize = 4;
 
+/// The blocks of which parent has this seal version or higher aggregate the precommits in their seals.
+const AGGREGATED_SEAL_VERSION: u64 = 1;
+
+/// The precommits in a seal are verified in a batch if there are at least this many of them.
+const MIN_PRECOMMITS_TO_VERIFY_IN_BATCH: usize = 8;
+
 #[cfg(test)]
 mod tests {
     use ccrypto::blake256;
-    use ckey::Address;
+    use ckey::{aggregate_schnorr, sign_schnorr, Address, Generator, Private, Random, SchnorrSignature};
+    use cstate::TopState;
+    use ctypes::transaction::{Action, Transaction};
     use ctypes::{CommonParams, Header};
-    use primitives::Bytes;
+    use primitives::{Bytes, H256};
 
     use super::super::BitSet;
     use super::message::{message_info_rlp, VoteStep};
+    use super::types::{SealSignatures, TendermintSealView};
     use crate::account_provider::AccountProvider;
     use crate::block::{ClosedBlock, OpenBlock};
     use crate::client::TestBlockChainClient;
-    use crate::consensus::{CodeChainEngine, EngineError, Seal};
+    use crate::codechain_machine::CodeChainMachine;
+    use crate::consensus::stake::{Validator, Validators};
+    use crate::consensus::{CodeChainEngine, DecodedSeal, EngineDescription, EngineError, EngineType, Seal};
     use crate::error::BlockError;
     use crate::error::Error;
     use crate::scheme::Scheme;
     use crate::tests::helpers::get_temp_state_db;
+    use crate::transaction::SignedTransaction;
 
     use super::*;
 
@@ -183,11 +246,201 @@ mod tests {
         let time_gap_params = TimeGapParams {
             allowed_past_gap: Duration::from_millis(30000),
             allowed_future_gap: Duration::from_millis(5000),
+            max_clock_skew: None,
+            max_future_drift: Duration::from_millis(3000),
         };
         engine.register_time_gap_config_to_worker(time_gap_params);
         assert!(!engine.name().is_empty());
     }
 
+    fn seal_signed_by(indices: &[usize], signatures: usize) -> Vec<Bytes> {
+        Seal::Tendermint {
+            prev_view: 1,
+            cur_view: 3,
+            precommits: (0..signatures).map(|_| SchnorrSignature::random()).collect(),
+            precommit_bitset: BitSet::new_with_indices(indices),
+        }
+        .seal_fields()
+        .unwrap()
+    }
+
+    #[test]
+    fn decode_seal() {
+        let engine = Scheme::new_test_tendermint().engine;
+        let seal = seal_signed_by(&[0, 2, 3], 3);
+        assert_eq!(
+            Some(Ok(DecodedSeal::Tendermint {
+                finalized_view: 1,
+                author_view: 3,
+                signer_indices: vec![0, 2, 3],
+                signature_count: 3,
+            })),
+            engine.decode_seal(&seal)
+        );
+    }
+
+    #[test]
+    fn describe() {
+        let scheme = Scheme::new_test_tendermint();
+        let common_params = scheme.genesis_params();
+        assert_eq!(
+            EngineDescription {
+                name: "Tendermint".to_string(),
+                engine_type: EngineType::PBFT,
+                seals_internally: Some(false),
+                recommended_confirmation: 1,
+                seal_field_count: 4,
+                seal_layout: vec!["prev_view", "cur_view", "precommits", "precommit_bitset"],
+                term_seconds: common_params.term_seconds(),
+                action_handlers: vec![(2, "stake handler")],
+            },
+            scheme.engine.describe(&common_params)
+        );
+    }
+
+    #[test]
+    fn author_reward_goes_to_the_owner_of_the_signing_key() {
+        let owner = Random.generate().unwrap();
+        let signer = Random.generate().unwrap();
+        let validators =
+            vec![Validator::new_for_test(100, 10, *owner.public()).with_signing_pubkey_for_test(*signer.public())];
+
+        let scheme = Scheme::new_test_tendermint();
+        let test_client: Arc<TestBlockChainClient> = Arc::new({
+            let mut client = TestBlockChainClient::new_with_scheme(Scheme::new_test_tendermint());
+            client.term_id = Some(1);
+            client.validators = Validators::from_vector_to_test(validators.clone());
+            client
+        });
+        let consensus_client = Arc::clone(&test_client) as Arc<ConsensusClient>;
+        scheme.engine.register_client(Arc::downgrade(&consensus_client));
+
+        let db = scheme.ensure_genesis_state(get_temp_state_db()).unwrap();
+        let genesis_header = scheme.genesis_header();
+        let mut block = OpenBlock::try_new(&*scheme.engine, db, &genesis_header, signer.address(), vec![]).unwrap();
+        // The account that has the most balance in the bundled scheme.
+        let faucet: Private = "ede1d4ccb4ec9a8bbbae9a13db3f4a7b56ea04189be86ac3a6a439d9a0a1addd".into();
+        let tx = Transaction {
+            seq: 0,
+            fee: 1_000,
+            network_id: "tc".into(),
+            action: Action::Pay {
+                receiver: Address::random(),
+                quantity: 1,
+            },
+        };
+        let tx = SignedTransaction::new_with_sign(tx, &faucet);
+        block.push_transaction(tx.clone(), None, &*test_client, 0, genesis_header.timestamp()).unwrap();
+
+        let mut executed_block = block.block().clone();
+        executed_block.state_mut().increase_term_id(0).unwrap();
+        Validators::from_vector_to_test(validators).save_to_state(executed_block.state_mut()).unwrap();
+
+        let common_params = CommonParams::default_for_test();
+        scheme
+            .engine
+            .on_close_block(&mut executed_block, &genesis_header, &common_params, Some(&common_params))
+            .unwrap();
+
+        // The genesis stakeholder takes the min fee, and the block author takes the rest.
+        let author_reward = tx.fee - CodeChainMachine::min_cost(&common_params, &tx.action);
+        stake::move_current_to_previous_intermediate_rewards(executed_block.state_mut()).unwrap();
+        let rewards = stake::drain_previous_rewards(executed_block.state_mut()).unwrap();
+        assert_eq!(vec![(owner.address(), author_reward)], rewards.into_iter().collect::<Vec<_>>());
+        scheme.engine.stop();
+    }
+
+    fn aggregated_seal_signed_by(indices: &[usize]) -> Vec<Bytes> {
+        let message = H256::random();
+        let signed: Vec<_> = indices
+            .iter()
+            .map(|_| {
+                let keypair = Random.generate().unwrap();
+                (*keypair.public(), sign_schnorr(keypair.private(), &message).unwrap())
+            })
+            .collect();
+        Seal::AggregatedTendermint {
+            prev_view: 1,
+            cur_view: 3,
+            precommits: aggregate_schnorr(&signed, &message).unwrap(),
+            precommit_bitset: BitSet::new_with_indices(indices),
+        }
+        .seal_fields()
+        .unwrap()
+    }
+
+    #[test]
+    fn decode_aggregated_seal() {
+        let engine = Scheme::new_test_tendermint().engine;
+        let seal = aggregated_seal_signed_by(&[0, 2, 3]);
+        assert_eq!(
+            Some(Ok(DecodedSeal::Tendermint {
+                finalized_view: 1,
+                author_view: 3,
+                signer_indices: vec![0, 2, 3],
+                signature_count: 3,
+            })),
+            engine.decode_seal(&seal)
+        );
+    }
+
+    #[test]
+    fn seal_signatures_follow_the_format() {
+        let seal = seal_signed_by(&[0, 2], 2);
+        let view = TendermintSealView::new(&seal);
+        assert!(!view.is_aggregated().unwrap());
+        match view.signatures().unwrap() {
+            SealSignatures::Separate(signatures) => {
+                assert_eq!(vec![0, 2], signatures.iter().map(|(index, _)| *index).collect::<Vec<_>>())
+            }
+            SealSignatures::Aggregated(..) => panic!("The signatures are not aggregated"),
+        }
+
+        let seal = aggregated_seal_signed_by(&[0, 2]);
+        let view = TendermintSealView::new(&seal);
+        assert!(view.is_aggregated().unwrap());
+        assert_eq!(SealSignatures::Aggregated(vec![0, 2], view.aggregate().unwrap()), view.signatures().unwrap());
+
+        // Neither a single signature nor an empty list is an aggregate.
+        let seal = seal_signed_by(&[1], 1);
+        assert!(!TendermintSealView::new(&seal).is_aggregated().unwrap());
+        let seal = seal_signed_by(&[], 0);
+        assert!(!TendermintSealView::new(&seal).is_aggregated().unwrap());
+    }
+
+    #[test]
+    fn corrupt_aggregated_seal_is_not_decoded() {
+        let engine = Scheme::new_test_tendermint().engine;
+
+        let mismatched_seal = aggregated_seal_signed_by(&[0, 2]);
+        let mut fewer_signers = aggregated_seal_signed_by(&[0]);
+        fewer_signers[3] = mismatched_seal[3].clone();
+        assert!(engine.decode_seal(&fewer_signers).unwrap().is_err());
+
+        let mut invalid_scalar_seal = aggregated_seal_signed_by(&[0, 2]);
+        invalid_scalar_seal[2] = vec![0xc2, 0xc0, 0x01];
+        assert!(engine.decode_seal(&invalid_scalar_seal).unwrap().is_err());
+    }
+
+    #[test]
+    fn corrupt_seal_is_not_decoded() {
+        let engine = Scheme::new_test_tendermint().engine;
+
+        let short_seal = seal_signed_by(&[0, 2], 2)[..3].to_vec();
+        assert!(engine.decode_seal(&short_seal).unwrap().is_err());
+
+        let mismatched_seal = seal_signed_by(&[0, 2], 1);
+        assert!(engine.decode_seal(&mismatched_seal).unwrap().is_err());
+
+        let mut invalid_bitset_seal = seal_signed_by(&[0, 2], 2);
+        invalid_bitset_seal[3] = vec![0x82, 0x01, 0x02];
+        assert!(engine.decode_seal(&invalid_bitset_seal).unwrap().is_err());
+
+        let mut invalid_view_seal = seal_signed_by(&[0, 2], 2);
+        invalid_view_seal[1] = vec![0xff];
+        assert!(engine.decode_seal(&invalid_view_seal).unwrap().is_err());
+    }
+
     #[test]
     #[ignore] // FIXME
     fn verification_fails_on_short_seal() {
@@ -317,7 +570,10 @@ mod tests {
 
         // Two good and one bad signature.
         match engine.verify_block_external(&header) {
-            Err(Error::Engine(EngineError::BlockNotAuthorized(_))) => {}
+            Err(Error::Engine(EngineError::InvalidSealSignature {
+                signer_index: 3,
+                ..
+            })) => {}
             _ => panic!(),
         };
         engine.stop();
@@ -15,30 +15,32 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use std::cmp;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::iter::Iterator;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use ckey::SchnorrSignature;
-use cnetwork::{Api, NetworkExtension, NodeId};
+use ckey::{verify_schnorr, Public, SchnorrSignature};
+use clogger::LogRateLimiter;
+use cnetwork::{Api, NetworkExtension, NodeId, PeerClass};
 use crossbeam_channel as crossbeam;
 use ctimer::TimerToken;
 use primitives::{Bytes, H256};
 use rand::prelude::SliceRandom;
 use rand::thread_rng;
-use rlp::{Encodable, UntrustedRlp};
+use rlp::{DecoderError, Encodable, UntrustedRlp};
 
 use super::super::BitSet;
 use super::message::*;
 use super::params::TimeoutParams;
-use super::types::{Height, PeerState, Step, View};
+use super::types::{CommitRecoveryCounters, Height, PeerCapability, PeerState, Step, View, VoteRequestCounters};
 use super::worker;
 use crate::consensus::EngineError;
 
 use super::{
-    ENGINE_TIMEOUT_BROADCAST_STEP_STATE, ENGINE_TIMEOUT_BROADCAT_STEP_STATE_INTERVAL, ENGINE_TIMEOUT_EMPTY_PROPOSAL,
-    ENGINE_TIMEOUT_TOKEN_NONCE_BASE,
+    ENGINE_TIMEOUT_BROADCAST_STEP_STATE, ENGINE_TIMEOUT_BROADCAT_STEP_STATE_INTERVAL,
+    ENGINE_TIMEOUT_COMMITTED_PROPOSAL, ENGINE_TIMEOUT_COMMITTED_PROPOSAL_INTERVAL, ENGINE_TIMEOUT_EMPTY_PROPOSAL,
+    ENGINE_TIMEOUT_TOKEN_NONCE_BASE, ENGINE_TIMEOUT_VOTE_REQUEST, ENGINE_TIMEOUT_VOTE_REQUEST_INTERVAL,
 };
 
 pub struct TendermintExtension {
@@ -46,13 +48,89 @@ pub struct TendermintExtension {
     peers: HashMap<NodeId, PeerState>,
     api: Box<Api>,
     timeouts: TimeoutParams,
+    committed_proposal_request: Option<CommittedProposalRequest>,
+    commit_recovery: Arc<CommitRecoveryCounters>,
+    /// The last request of the votes to each peer, which is sent again if some of the votes don't arrive.
+    vote_requests: HashMap<NodeId, VoteRequest>,
+    vote_request_timer_set: bool,
+    vote_request_counters: Arc<VoteRequestCounters>,
+    /// Sends the votes to the observers too, which is what the relay nodes need.
+    full_gossip: bool,
+    /// The validators of the current height. The votes are gossiped only to them.
+    validators: HashSet<Public>,
+    signer: Option<Public>,
+    /// The warnings of the invalid messages for each peer and each class of the error.
+    invalid_message_log: LogRateLimiter<(NodeId, &'static str)>,
+}
+
+struct VoteRequest {
+    vote_step: VoteStep,
+    requested_votes: BitSet,
+    requested_at: Instant,
+    retries: usize,
+}
+
+/// The request of the block that is committed but not imported.
+struct CommittedProposalRequest {
+    height: Height,
+    view: View,
+    block_hash: H256,
+    /// The peers that know the proposal and haven't been asked yet.
+    candidates: Vec<NodeId>,
 }
 
 const MIN_PEERS_PROPAGATION: usize = 4;
 const MAX_PEERS_PROPAGATION: usize = 128;
+// A proposal carries a whole block.
+const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+// An encoded vote is at most this large, even with the largest height, view and signer index.
+const MAX_VOTE_SIZE: usize = 128;
+// The votes of a step can be as many as the validators, so the responses to the requests are split into the
+// messages of this many votes. It keeps a response from holding back the other messages to the peer.
+const MAX_VOTES_PER_BATCH: usize = 128;
+const MAX_VOTES_BATCH_SIZE: usize = MAX_VOTE_SIZE * MAX_VOTES_PER_BATCH;
+const MAX_VOTE_REQUEST_RETRIES: usize = 3;
+const INVALID_MESSAGE_LOG_WINDOW: Duration = Duration::from_secs(60);
+
+fn select_random(mut peers: Vec<NodeId>) -> Vec<NodeId> {
+    let mut count = (peers.len() as f64).powf(0.5).round() as usize;
+    count = cmp::min(count, MAX_PEERS_PROPAGATION);
+    count = cmp::max(count, MIN_PEERS_PROPAGATION);
+    peers.shuffle(&mut thread_rng());
+    peers.truncate(count);
+    peers
+}
+
+/// Splits the votes into the batches whose total size is at most `max_batch_size`.
+/// A vote larger than the limit is sent alone.
+fn split_votes(votes: Vec<Bytes>, max_batch_size: usize) -> Vec<Vec<Bytes>> {
+    let mut batches = Vec::new();
+    let mut batch = Vec::new();
+    let mut batch_size = 0;
+    for vote in votes {
+        if !batch.is_empty() && batch_size + vote.len() > max_batch_size {
+            batches.push(batch);
+            batch = Vec::new();
+            batch_size = 0;
+        }
+        batch_size += vote.len();
+        batch.push(vote);
+    }
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+    batches
+}
 
 impl TendermintExtension {
-    pub fn new(inner: crossbeam::Sender<worker::Event>, timeouts: TimeoutParams, api: Box<Api>) -> Self {
+    pub fn new(
+        inner: crossbeam::Sender<worker::Event>,
+        timeouts: TimeoutParams,
+        commit_recovery: Arc<CommitRecoveryCounters>,
+        vote_request_counters: Arc<VoteRequestCounters>,
+        full_gossip: bool,
+        api: Box<Api>,
+    ) -> Self {
         let initial = timeouts.initial();
         ctrace!(ENGINE, "Setting the initial timeout to {:?}.", initial);
         api.set_timer_once(ENGINE_TIMEOUT_TOKEN_NONCE_BASE, initial).expect("Timer set succeeds");
@@ -66,6 +144,173 @@ impl TendermintExtension {
             peers: Default::default(),
             api,
             timeouts,
+            committed_proposal_request: None,
+            commit_recovery,
+            vote_requests: Default::default(),
+            vote_request_timer_set: false,
+            vote_request_counters,
+            full_gossip,
+            validators: Default::default(),
+            signer: None,
+            invalid_message_log: LogRateLimiter::new(INVALID_MESSAGE_LOG_WINDOW),
+        }
+    }
+
+    fn send_capability(&self, token: &NodeId) {
+        let peer = &self.peers[token];
+        // The signer is proven only after the challenge of the peer arrives.
+        let proof = match (self.signer, peer.peer_challenge) {
+            (Some(_), Some(peer_challenge)) => self.sign_challenge(peer_challenge),
+            _ => None,
+        };
+        let message = TendermintMessage::Capability {
+            signer: self.signer,
+            full_gossip: self.full_gossip,
+            challenge: peer.challenge,
+            proof,
+        };
+        self.api.send(token, Arc::new(message.rlp_bytes().into_vec()));
+    }
+
+    fn sign_challenge(&self, challenge: H256) -> Option<SchnorrSignature> {
+        let (result, receiver) = crossbeam::bounded(1);
+        self.inner
+            .send(worker::Event::SignCapabilityChallenge {
+                challenge,
+                result,
+            })
+            .unwrap();
+        receiver.recv().unwrap()
+    }
+
+    fn on_capability(
+        &mut self,
+        token: &NodeId,
+        signer: Option<Public>,
+        full_gossip: bool,
+        peer_challenge: H256,
+        proof: Option<SchnorrSignature>,
+    ) {
+        let peer_state = match self.peers.get_mut(token) {
+            Some(peer_state) => peer_state,
+            None => return,
+        };
+        let proof_hash = capability_proof_hash(&peer_state.challenge);
+        let is_proven = |signer: &Public| {
+            proof.map_or(false, |proof| verify_schnorr(signer, &proof, &proof_hash).unwrap_or(false))
+        };
+        let proven_signer = signer.filter(is_proven);
+        if signer.is_some() && proven_signer.is_none() {
+            ctrace!(ENGINE, "{} advertised {:?} without the proof", token, signer);
+        }
+        let capability = PeerCapability {
+            signer: proven_signer,
+            full_gossip,
+        };
+        ctrace!(ENGINE, "{} advertised {:?}", token, capability);
+        peer_state.capability = Some(capability);
+        let is_new_challenge = peer_state.peer_challenge != Some(peer_challenge);
+        peer_state.peer_challenge = Some(peer_challenge);
+        let class = self.peer_class(&self.peers[token]);
+        self.api.set_peer_class(token, class);
+        // The peer learns our signer only from the capability that answers its challenge.
+        if is_new_challenge && self.signer.is_some() {
+            self.send_capability(token);
+        }
+    }
+
+    fn peer_class(&self, peer: &PeerState) -> PeerClass {
+        match peer.capability {
+            Some(PeerCapability {
+                signer: Some(signer),
+                ..
+            }) if self.validators.contains(&signer) => PeerClass::Validator,
+            Some(_) => PeerClass::Observer,
+            None => PeerClass::Unknown,
+        }
+    }
+
+    /// The peers that haven't advertised their capabilities are treated as validators, so that the peers of the
+    /// old versions keep receiving the votes.
+    fn receives_votes(&self, peer: &PeerState) -> bool {
+        // The validators are not known until the worker starts the first height.
+        if self.full_gossip || self.validators.is_empty() {
+            return true
+        }
+        match peer.capability {
+            None => true,
+            Some(PeerCapability {
+                full_gossip: true,
+                ..
+            }) => true,
+            Some(PeerCapability {
+                signer: Some(signer),
+                ..
+            }) => self.validators.contains(&signer),
+            Some(PeerCapability {
+                signer: None,
+                ..
+            }) => false,
+        }
+    }
+
+    fn set_validators(&mut self, validators: Vec<Public>) {
+        let validators: HashSet<Public> = validators.into_iter().collect();
+        if validators != self.validators {
+            cdebug!(ENGINE, "The votes are gossiped to the {} validators", validators.len());
+            self.validators = validators;
+            for (token, peer) in &self.peers {
+                self.api.set_peer_class(token, self.peer_class(peer));
+            }
+        }
+    }
+
+    fn set_signer(&mut self, signer: Public) {
+        if self.signer == Some(signer) {
+            return
+        }
+        self.signer = Some(signer);
+        for (token, peer) in &self.peers {
+            if peer.version >= CAPABILITY_VERSION {
+                self.send_capability(token);
+            }
+        }
+    }
+
+    fn on_proposal(&self, from: &NodeId, signature: SchnorrSignature, view: View, block: ProposedBlock) {
+        let (result, receiver) = crossbeam::bounded(1);
+        self.inner
+            .send(worker::Event::ProposalBlock {
+                signature,
+                view,
+                block,
+                result,
+            })
+            .unwrap();
+        if let Some((c, block)) = receiver.recv().unwrap() {
+            if let Err(e) = c.import_block_from(block, *from) {
+                cinfo!(ENGINE, "Failed to import proposal block {:?}", e);
+            }
+        }
+    }
+
+    /// Every invalid message counts toward the misbehavior of the peer, but only a few of them are logged.
+    fn on_invalid_message(&mut self, token: &NodeId, err: &DecoderError) {
+        self.api.report_misbehavior(token);
+        let class = match err {
+            DecoderError::Custom(reason) => *reason,
+            _ => "Malformed RLP",
+        };
+        match self.invalid_message_log.check((*token, class), Instant::now()) {
+            Some(0) => cwarn!(ENGINE, "Invalid message from peer {}: {:?}", token, err),
+            Some(suppressed) => cwarn!(
+                ENGINE,
+                "Invalid message from peer {}: {:?} ({} similar warnings were suppressed)",
+                token,
+                err,
+                suppressed
+            ),
+            None => {}
         }
     }
 
@@ -80,18 +325,24 @@ impl TendermintExtension {
         peer_state.messages = messages;
     }
 
+    /// The congested peers are skipped. They will get the messages by the requests later.
     fn select_random_peers(&self) -> Vec<NodeId> {
-        let mut peers: Vec<NodeId> = self.peers.keys().cloned().collect();
-        let mut count = (peers.len() as f64).powf(0.5).round() as usize;
-        count = cmp::min(count, MAX_PEERS_PROPAGATION);
-        count = cmp::max(count, MIN_PEERS_PROPAGATION);
-        peers.shuffle(&mut thread_rng());
-        peers.truncate(count);
-        peers
+        let peers: Vec<NodeId> = self.peers.keys().filter(|token| !self.api.is_congested(token)).cloned().collect();
+        select_random(peers)
+    }
+
+    fn select_random_vote_receivers(&self) -> Vec<NodeId> {
+        let peers: Vec<NodeId> = self
+            .peers
+            .iter()
+            .filter(|(token, peer)| self.receives_votes(peer) && !self.api.is_congested(token))
+            .map(|(token, _)| *token)
+            .collect();
+        select_random(peers)
     }
 
     fn broadcast_message(&self, message: Bytes) {
-        let tokens = self.select_random_peers();
+        let tokens = self.select_random_vote_receivers();
         let message = Arc::new(TendermintMessage::ConsensusMessage(vec![message]).rlp_bytes().into_vec());
         for token in tokens {
             self.api.send(&token, Arc::clone(&message));
@@ -100,8 +351,15 @@ impl TendermintExtension {
 
     fn send_votes(&self, token: &NodeId, messages: Vec<Bytes>) {
         ctrace!(ENGINE, "Send messages({}) to {}", messages.len(), token);
-        let message = Arc::new(TendermintMessage::ConsensusMessage(messages).rlp_bytes().into_vec());
-        self.api.send(token, message);
+        let batches = split_votes(messages, MAX_VOTES_BATCH_SIZE);
+        if batches.len() > 1 {
+            cdebug!(ENGINE, "Split the votes to {} into {} messages", token, batches.len());
+            self.vote_request_counters.on_split_response(batches.len());
+        }
+        for batch in batches {
+            let message = Arc::new(TendermintMessage::ConsensusMessage(batch).rlp_bytes().into_vec());
+            self.api.send(token, message);
+        }
     }
 
     fn broadcast_state(&self, vote_step: VoteStep, proposal: Option<H256>, lock_view: Option<View>, votes: BitSet) {
@@ -124,17 +382,50 @@ impl TendermintExtension {
     }
 
     fn broadcast_proposal_block(&self, signature: SchnorrSignature, view: View, message: Bytes) {
+        let mut encoded: HashMap<u64, Arc<Bytes>> = HashMap::new();
+        for (token, peer) in &self.peers {
+            let message = encoded.entry(peer.version).or_insert_with(|| {
+                Arc::new(
+                    TendermintMessage::proposal(signature, view, message.clone(), peer.version).rlp_bytes().into_vec(),
+                )
+            });
+            self.api.send(token, Arc::clone(message));
+        }
+    }
+
+    /// Sends the precommits of the committed block to the peers that haven't committed the height yet,
+    /// so that they don't have to wait for the precommits or the next proposal.
+    fn broadcast_commit(
+        &self,
+        height: Height,
+        view: View,
+        block_hash: H256,
+        precommits: Vec<SchnorrSignature>,
+        precommit_bitset: BitSet,
+    ) {
+        let lagging_peers: Vec<_> = self
+            .peers
+            .iter()
+            .filter(|(_, peer)| peer.vote_step.height == height && peer.vote_step.step != Step::Commit)
+            .map(|(token, _)| *token)
+            .collect();
+        if lagging_peers.is_empty() {
+            return
+        }
+        ctrace!(ENGINE, "Broadcast the commit of {} at {}-{} to {:?}", block_hash, height, view, lagging_peers);
         let message = Arc::new(
-            TendermintMessage::ProposalBlock {
-                signature,
-                message,
+            TendermintMessage::Commit {
+                height,
                 view,
+                block_hash,
+                precommits,
+                precommit_bitset,
             }
             .rlp_bytes()
             .into_vec(),
         );
-        for token in self.peers.keys() {
-            self.api.send(token, Arc::clone(&message));
+        for token in lagging_peers {
+            self.api.send(&token, Arc::clone(&message));
         }
     }
 
@@ -172,8 +463,73 @@ impl TendermintExtension {
         self.api.send(&token, message);
     }
 
-    fn request_messages_to_all(&self, vote_step: VoteStep, requested_votes: BitSet) {
-        for token in self.select_random_peers() {
+    fn peers_with_proposal(&self, block_hash: &H256) -> Vec<NodeId> {
+        let mut peers: Vec<NodeId> = self
+            .peers
+            .iter()
+            .filter(|(_, peer)| peer.proposal.as_ref() == Some(block_hash))
+            .map(|(token, _)| *token)
+            .collect();
+        peers.shuffle(&mut thread_rng());
+        peers
+    }
+
+    /// Asks the peers that know the committed proposal one by one, and falls back to asking any peer when all of
+    /// them fail to answer in time.
+    fn request_committed_proposal(&mut self, height: Height, view: View, block_hash: H256) {
+        let candidates = self.peers_with_proposal(&block_hash);
+        cdebug!(ENGINE, "Request the committed proposal {} at {}-{} to {:?}", block_hash, height, view, candidates);
+        self.request_committed_proposal_to_next_peer(CommittedProposalRequest {
+            height,
+            view,
+            block_hash,
+            candidates,
+        });
+    }
+
+    fn request_committed_proposal_to_next_peer(&mut self, mut request: CommittedProposalRequest) {
+        // The peers could be disconnected after the request started.
+        let peers = &self.peers;
+        request.candidates.retain(|token| peers.contains_key(token));
+        match request.candidates.pop() {
+            Some(token) => self.request_proposal(&token, request.height, request.view),
+            None => {
+                cdebug!(ENGINE, "No peer that knows {} answered, request it to any peer", request.block_hash);
+                self.commit_recovery.on_fell_back();
+                self.request_proposal_to_any(request.height, request.view);
+                // The peers could learn the proposal until the next try.
+                request.candidates = self.peers_with_proposal(&request.block_hash);
+            }
+        }
+        self.api
+            .set_timer_once(
+                ENGINE_TIMEOUT_COMMITTED_PROPOSAL,
+                Duration::from_millis(ENGINE_TIMEOUT_COMMITTED_PROPOSAL_INTERVAL),
+            )
+            .expect("Timer set succeeds");
+        self.committed_proposal_request = Some(request);
+    }
+
+    fn on_committed_proposal_timeout(&mut self) {
+        if let Some(request) = self.committed_proposal_request.take() {
+            self.commit_recovery.on_retried();
+            self.request_committed_proposal_to_next_peer(request);
+        }
+    }
+
+    fn cancel_committed_proposal_request(&mut self) {
+        self.committed_proposal_request = None;
+        self.api.clear_timer(ENGINE_TIMEOUT_COMMITTED_PROPOSAL).expect("Timer clear succeeds");
+    }
+
+    /// The votes are requested to every peer only while the consensus is degraded.
+    fn request_messages_to_all(&mut self, vote_step: VoteStep, requested_votes: BitSet, every_peer: bool) {
+        let tokens = if every_peer {
+            self.peers.keys().filter(|token| !self.api.is_congested(token)).cloned().collect()
+        } else {
+            self.select_random_peers()
+        };
+        for token in tokens {
             let peer = &self.peers[&token];
             if vote_step <= peer.vote_step && !peer.messages.is_empty() {
                 self.request_messages(&token, vote_step, requested_votes);
@@ -181,7 +537,7 @@ impl TendermintExtension {
         }
     }
 
-    fn request_messages(&self, token: &NodeId, vote_step: VoteStep, requested_votes: BitSet) {
+    fn request_messages(&mut self, token: &NodeId, vote_step: VoteStep, requested_votes: BitSet) {
         ctrace!(ENGINE, "Request messages {:?} {:?} to {:?}", vote_step, requested_votes, token);
         let message = Arc::new(
             TendermintMessage::RequestMessage {
@@ -192,6 +548,81 @@ impl TendermintExtension {
             .into_vec(),
         );
         self.api.send(&token, message);
+        self.track_vote_request(token, vote_step, requested_votes, 0);
+    }
+
+    /// Remembers the request to send it again for the votes that don't arrive.
+    fn track_vote_request(&mut self, token: &NodeId, vote_step: VoteStep, requested_votes: BitSet, retries: usize) {
+        self.vote_requests.insert(*token, VoteRequest {
+            vote_step,
+            requested_votes,
+            requested_at: Instant::now(),
+            retries,
+        });
+        self.set_timer_vote_request();
+    }
+
+    fn set_timer_vote_request(&mut self) {
+        if self.vote_request_timer_set {
+            return
+        }
+        self.api
+            .set_timer_once(ENGINE_TIMEOUT_VOTE_REQUEST, Duration::from_millis(ENGINE_TIMEOUT_VOTE_REQUEST_INTERVAL))
+            .expect("Timer set succeeds");
+        self.vote_request_timer_set = true;
+    }
+
+    /// Requests the votes that didn't arrive in time again, only from the peers that still know them.
+    fn on_vote_request_timeout(&mut self) {
+        self.vote_request_timer_set = false;
+        let interval = Duration::from_millis(ENGINE_TIMEOUT_VOTE_REQUEST_INTERVAL);
+        let expired: Vec<NodeId> = self
+            .vote_requests
+            .iter()
+            .filter(|(_, request)| request.requested_at.elapsed() >= interval)
+            .map(|(token, _)| *token)
+            .collect();
+        for token in expired {
+            let request = self.vote_requests.remove(&token).expect("The token is collected from the requests");
+            let peer = match self.peers.get(&token) {
+                Some(peer) => peer,
+                None => continue,
+            };
+            if request.retries >= MAX_VOTE_REQUEST_RETRIES {
+                cdebug!(ENGINE, "Give up the votes {:?} requested to {}", request.vote_step, token);
+                continue
+            }
+            let (result, receiver) = crossbeam::bounded(1);
+            self.inner
+                .send(worker::Event::GetMissingVotes {
+                    vote_step: request.vote_step,
+                    requested: request.requested_votes,
+                    result,
+                })
+                .unwrap();
+            let mut missing = receiver.recv().unwrap();
+            if peer.vote_step == request.vote_step {
+                missing = &missing - &(&BitSet::all_set() - &peer.messages);
+            }
+            if missing.is_empty() {
+                continue
+            }
+            ctrace!(ENGINE, "Request the missing votes {:?} {:?} to {} again", request.vote_step, missing, token);
+            self.vote_request_counters.on_re_requested();
+            let message = Arc::new(
+                TendermintMessage::RequestMessage {
+                    vote_step: request.vote_step,
+                    requested_votes: missing,
+                }
+                .rlp_bytes()
+                .into_vec(),
+            );
+            self.api.send(&token, message);
+            self.track_vote_request(&token, request.vote_step, missing, request.retries + 1);
+        }
+        if !self.vote_requests.is_empty() {
+            self.set_timer_vote_request();
+        }
     }
 
     fn set_timer_step(&self, step: Step, view: View, expired_token_nonce: TimerToken) {
@@ -202,6 +633,11 @@ impl TendermintExtension {
             .expect("Timer set succeeds");
     }
 
+    fn extend_timer_step(&self, duration: Duration, expired_token_nonce: TimerToken) {
+        self.api.clear_timer(expired_token_nonce).expect("Timer clear succeeds");
+        self.api.set_timer_once(expired_token_nonce + 1, duration).expect("Timer set succeeds");
+    }
+
     fn set_timer_empty_proposal(&self, view: View) {
         self.api.clear_timer(ENGINE_TIMEOUT_EMPTY_PROPOSAL).expect("Timer clear succeeds");
         self.api
@@ -219,17 +655,26 @@ impl NetworkExtension<Event> for TendermintExtension {
         false
     }
 
+    fn max_message_size() -> usize {
+        MAX_MESSAGE_SIZE
+    }
+
     fn versions() -> &'static [u64] {
-        const VERSIONS: &[u64] = &[0];
+        const VERSIONS: &[u64] = &[0, PROPOSAL_HEADER_AND_BODY_VERSION, CAPABILITY_VERSION];
         &VERSIONS
     }
 
-    fn on_node_added(&mut self, token: &NodeId, _version: u64) {
-        self.peers.insert(*token, PeerState::new());
+    fn on_node_added(&mut self, token: &NodeId, version: u64) {
+        self.peers.insert(*token, PeerState::new(version, H256::random()));
+        if version >= CAPABILITY_VERSION {
+            self.send_capability(token);
+        }
     }
 
     fn on_node_removed(&mut self, token: &NodeId) {
         self.peers.remove(token);
+        self.vote_requests.remove(token);
+        self.invalid_message_log.forget(|(node, _)| node == token);
     }
 
     fn on_message(&mut self, token: &NodeId, data: &[u8]) {
@@ -269,20 +714,18 @@ impl NetworkExtension<Event> for TendermintExtension {
                 view,
                 message,
             }) => {
-                let (result, receiver) = crossbeam::bounded(1);
-                self.inner
-                    .send(worker::Event::ProposalBlock {
-                        signature,
-                        view,
-                        message: message.clone(),
-                        result,
-                    })
-                    .unwrap();
-                if let Some(c) = receiver.recv().unwrap() {
-                    if let Err(e) = c.import_block(message) {
-                        cinfo!(ENGINE, "Failed to import proposal block {:?}", e);
-                    }
-                }
+                self.on_proposal(token, signature, view, ProposedBlock::Decoded(message));
+            }
+            Ok(TendermintMessage::ProposalHeaderAndBody {
+                signature,
+                view,
+                header,
+                compressed_body,
+            }) => {
+                self.on_proposal(token, signature, view, ProposedBlock::Compressed {
+                    header,
+                    compressed_body,
+                });
             }
             Ok(TendermintMessage::StepState {
                 vote_step,
@@ -312,6 +755,13 @@ impl NetworkExtension<Event> for TendermintExtension {
                     .unwrap();
 
                 while let Ok(message) = receiver.recv() {
+                    if let Ok(TendermintMessage::RequestMessage {
+                        vote_step,
+                        requested_votes,
+                    }) = UntrustedRlp::new(&message).as_val()
+                    {
+                        self.track_vote_request(token, vote_step, requested_votes, 0);
+                    }
                     self.api.send(token, Arc::new(message));
                 }
             }
@@ -320,9 +770,11 @@ impl NetworkExtension<Event> for TendermintExtension {
                 view,
             }) => {
                 let (result, receiver) = crossbeam::bounded(1);
+                let version = self.peers.get(token).map_or(0, |peer| peer.version);
                 self.inner
                     .send(worker::Event::RequestProposal {
                         token: *token,
+                        version,
                         height,
                         view,
                         result,
@@ -337,6 +789,12 @@ impl NetworkExtension<Event> for TendermintExtension {
                 requested_votes,
             }) => {
                 ctrace!(ENGINE, "Received RequestMessage for {:?} from {:?}", vote_step, requested_votes);
+                let receives_votes = self.peers.get(token).map_or(false, |peer| self.receives_votes(peer));
+                if !receives_votes && vote_step.step != Step::Precommit {
+                    // The observers only need the precommits to finalize the blocks.
+                    ctrace!(ENGINE, "Ignore the request of the {:?} votes from the observer {}", vote_step.step, token);
+                    return
+                }
 
                 let (result, receiver) = crossbeam::unbounded();
                 self.inner
@@ -352,11 +810,50 @@ impl NetworkExtension<Event> for TendermintExtension {
                     self.send_votes(token, votes);
                 }
             }
-            _ => cinfo!(ENGINE, "Invalid message from peer {}", token),
+            Ok(TendermintMessage::Commit {
+                height,
+                view,
+                block_hash,
+                precommits,
+                precommit_bitset,
+            }) => {
+                ctrace!(ENGINE, "Received the commit of {} at {}-{} from {}", block_hash, height, view, token);
+                let (result, receiver) = crossbeam::bounded(1);
+                self.inner
+                    .send(worker::Event::Commit {
+                        height,
+                        view,
+                        block_hash,
+                        precommits,
+                        precommit_bitset,
+                        result,
+                    })
+                    .unwrap();
+                if let Ok(Err(e)) = receiver.recv() {
+                    cinfo!(ENGINE, "Failed to handle the commit from {}: {:?}", token, e);
+                }
+            }
+            Ok(TendermintMessage::Capability {
+                signer,
+                full_gossip,
+                challenge,
+                proof,
+            }) => {
+                self.on_capability(token, signer, full_gossip, challenge, proof);
+            }
+            Err(err) => self.on_invalid_message(token, &err),
         }
     }
 
     fn on_timeout(&mut self, token: TimerToken) {
+        if token == ENGINE_TIMEOUT_COMMITTED_PROPOSAL {
+            self.on_committed_proposal_timeout();
+            return
+        }
+        if token == ENGINE_TIMEOUT_VOTE_REQUEST {
+            self.on_vote_request_timeout();
+            return
+        }
         debug_assert!(
             token >= ENGINE_TIMEOUT_TOKEN_NONCE_BASE
                 || token == ENGINE_TIMEOUT_EMPTY_PROPOSAL
@@ -383,8 +880,9 @@ impl NetworkExtension<Event> for TendermintExtension {
             Event::RequestMessagesToAll {
                 vote_step,
                 requested_votes,
+                every_peer,
             } => {
-                self.request_messages_to_all(vote_step, requested_votes);
+                self.request_messages_to_all(vote_step, requested_votes, every_peer);
             }
             Event::RequestProposalToAny {
                 height,
@@ -397,6 +895,10 @@ impl NetworkExtension<Event> for TendermintExtension {
                 view,
                 expired_token_nonce,
             } => self.set_timer_step(step, view, expired_token_nonce),
+            Event::ExtendTimerStep {
+                duration,
+                expired_token_nonce,
+            } => self.extend_timer_step(duration, expired_token_nonce),
             Event::SetTimerEmptyProposal {
                 view,
             } => {
@@ -409,6 +911,35 @@ impl NetworkExtension<Event> for TendermintExtension {
             } => {
                 self.broadcast_proposal_block(signature, view, message);
             }
+            Event::BroadcastCommit {
+                height,
+                view,
+                block_hash,
+                precommits,
+                precommit_bitset,
+            } => {
+                self.broadcast_commit(height, view, block_hash, precommits, precommit_bitset);
+            }
+            Event::RequestCommittedProposal {
+                height,
+                view,
+                block_hash,
+            } => {
+                self.request_committed_proposal(height, view, block_hash);
+            }
+            Event::CancelCommittedProposalRequest => {
+                self.cancel_committed_proposal_request();
+            }
+            Event::SetValidators {
+                validators,
+            } => {
+                self.set_validators(validators);
+            }
+            Event::SetSigner {
+                public,
+            } => {
+                self.set_signer(public);
+            }
         }
     }
 }
@@ -426,6 +957,7 @@ pub enum Event {
     RequestMessagesToAll {
         vote_step: VoteStep,
         requested_votes: BitSet,
+        every_peer: bool,
     },
     RequestProposalToAny {
         height: Height,
@@ -436,6 +968,11 @@ pub enum Event {
         view: View,
         expired_token_nonce: TimerToken,
     },
+    /// Sets the timer of the current step again without changing the step.
+    ExtendTimerStep {
+        duration: Duration,
+        expired_token_nonce: TimerToken,
+    },
     SetTimerEmptyProposal {
         view: View,
     },
@@ -444,4 +981,280 @@ pub enum Event {
         view: View,
         message: Bytes,
     },
+    BroadcastCommit {
+        height: Height,
+        view: View,
+        block_hash: H256,
+        precommits: Vec<SchnorrSignature>,
+        precommit_bitset: BitSet,
+    },
+    RequestCommittedProposal {
+        height: Height,
+        view: View,
+        block_hash: H256,
+    },
+    CancelCommittedProposalRequest,
+    SetValidators {
+        validators: Vec<Public>,
+    },
+    SetSigner {
+        public: Public,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use cnetwork::{IntoSocketAddr, NetworkExtensionResult, RoutingTable, SocketAddr};
+    use parking_lot::Mutex;
+    use rlp::RlpStream;
+
+    use super::super::super::bit_set::MAX_VALIDATOR_SIZE;
+    use super::*;
+
+    /// Disconnects the peer when the routing table bans it.
+    struct TestApi {
+        routing_table: Arc<RoutingTable>,
+        disconnected: Arc<Mutex<Vec<NodeId>>>,
+        sent: Arc<Mutex<Vec<NodeId>>>,
+    }
+
+    impl Api for TestApi {
+        fn send(&self, node: &NodeId, _message: Arc<Bytes>) {
+            self.sent.lock().push(*node);
+        }
+
+        fn set_timer(&self, _timer: TimerToken, _d: Duration) -> NetworkExtensionResult<()> {
+            Ok(())
+        }
+
+        fn set_timer_once(&self, _timer: TimerToken, _d: Duration) -> NetworkExtensionResult<()> {
+            Ok(())
+        }
+
+        fn clear_timer(&self, _timer: TimerToken) -> NetworkExtensionResult<()> {
+            Ok(())
+        }
+
+        fn report_misbehavior(&self, node: &NodeId) {
+            if self.routing_table.report_misbehavior(node.into_addr()) {
+                self.disconnected.lock().push(*node);
+            }
+        }
+    }
+
+    /// A ProposalBlock whose block is not in the snappy format.
+    fn proposal_with_bad_compression() -> Bytes {
+        let valid = TendermintMessage::ProposalBlock {
+            signature: SchnorrSignature::random(),
+            view: 1,
+            message: vec![0xc0],
+        }
+        .rlp_bytes();
+        let rlp = UntrustedRlp::new(&valid);
+        let mut s = RlpStream::new_list(4);
+        for i in 0..3 {
+            s.append_raw(rlp.at(i).unwrap().as_raw(), 1);
+        }
+        s.append(&vec![0x05u8, 0xff, 0xff, 0xff, 0xff, 0xff]);
+        s.out()
+    }
+
+    #[test]
+    fn bad_proposals_are_logged_once_a_minute_and_disconnect_the_peer() {
+        let (sender, _receiver) = crossbeam::unbounded();
+        let disconnected = Arc::new(Mutex::new(Vec::new()));
+        let api = TestApi {
+            routing_table: RoutingTable::new(),
+            disconnected: Arc::clone(&disconnected),
+            sent: Default::default(),
+        };
+        let mut extension = TendermintExtension::new(
+            sender,
+            TimeoutParams::default(),
+            Default::default(),
+            Default::default(),
+            false,
+            Box::new(api),
+        );
+        let address = SocketAddr::v4(1, 2, 3, 4, 3485);
+        let peer = NodeId::new(address.ip(), address.port());
+        let frame = proposal_with_bad_compression();
+        for _ in 0..1000 {
+            extension.on_message(&peer, &frame);
+        }
+
+        // Only the first one is logged.
+        assert_eq!(999, extension.invalid_message_log.suppressed(&(peer, "Invalid compression format")));
+        assert!(disconnected.lock().contains(&peer));
+    }
+
+    #[test]
+    fn votes_are_requested_to_every_peer_only_if_degraded() {
+        let (sender, _receiver) = crossbeam::unbounded();
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let api = TestApi {
+            routing_table: RoutingTable::new(),
+            disconnected: Default::default(),
+            sent: Arc::clone(&sent),
+        };
+        let mut extension = TendermintExtension::new(
+            sender,
+            TimeoutParams::default(),
+            Default::default(),
+            Default::default(),
+            false,
+            Box::new(api),
+        );
+        let vote_step = VoteStep::new(3, 11, Step::Prevote);
+        for i in 0..9 {
+            let address = SocketAddr::v4(1, 2, 3, i, 3485);
+            let peer = NodeId::new(address.ip(), address.port());
+            extension.on_node_added(&peer, 0);
+            extension.update_peer_state(&peer, vote_step, None, BitSet::all_set());
+        }
+
+        extension.request_messages_to_all(vote_step, BitSet::all_set(), false);
+        assert_eq!(MIN_PEERS_PROPAGATION, sent.lock().len());
+
+        sent.lock().clear();
+        extension.request_messages_to_all(vote_step, BitSet::all_set(), true);
+        assert_eq!(9, sent.lock().len());
+    }
+
+    #[test]
+    fn split_votes_keeps_every_vote_in_order() {
+        let votes: Vec<Bytes> = (0..100u8).map(|i| vec![i; 100]).collect();
+        let max_batch_size = votes[0].len() * 10;
+
+        let batches = split_votes(votes.clone(), max_batch_size);
+        assert_eq!(10, batches.len());
+
+        let mut received = Vec::new();
+        for batch in batches {
+            assert_eq!(10, batch.len());
+            let message = TendermintMessage::ConsensusMessage(batch).rlp_bytes().into_vec();
+            match UntrustedRlp::new(&message).as_val() {
+                Ok(TendermintMessage::ConsensusMessage(messages)) => received.extend(messages),
+                _ => panic!("A batch must be decoded into a ConsensusMessage"),
+            }
+        }
+        assert_eq!(votes, received);
+    }
+
+    #[test]
+    fn votes_of_a_step_are_split_by_the_batch_size() {
+        let vote = ConsensusMessage {
+            on: VoteOn {
+                step: VoteStep::new(Height::max_value(), View::max_value(), Step::Precommit),
+                block_hash: Some(H256::random()),
+            },
+            signature: SchnorrSignature::random(),
+            signer_index: MAX_VALIDATOR_SIZE - 1,
+        }
+        .rlp_bytes()
+        .into_vec();
+        assert!(vote.len() <= MAX_VOTE_SIZE, "{} > {}", vote.len(), MAX_VOTE_SIZE);
+
+        // All the votes of a step with the most validators
+        let votes = vec![vote; MAX_VALIDATOR_SIZE];
+        let batches = split_votes(votes, MAX_VOTES_BATCH_SIZE);
+        assert_eq!((MAX_VALIDATOR_SIZE + MAX_VOTES_PER_BATCH - 1) / MAX_VOTES_PER_BATCH, batches.len());
+        for batch in &batches[..batches.len() - 1] {
+            assert!(batch.len() >= MAX_VOTES_PER_BATCH);
+        }
+    }
+
+    #[test]
+    fn only_the_missing_votes_are_requested_again() {
+        let (sender, receiver) = crossbeam::unbounded();
+        // Vote 0 arrived after the requests.
+        thread::spawn(move || {
+            for event in receiver {
+                if let worker::Event::GetMissingVotes {
+                    requested,
+                    result,
+                    ..
+                } = event
+                {
+                    result.send(&requested - &BitSet::new_with_indices(&[0])).unwrap();
+                }
+            }
+        });
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let api = TestApi {
+            routing_table: RoutingTable::new(),
+            disconnected: Default::default(),
+            sent: Arc::clone(&sent),
+        };
+        let mut extension = TendermintExtension::new(
+            sender,
+            TimeoutParams::default(),
+            Default::default(),
+            Default::default(),
+            false,
+            Box::new(api),
+        );
+        let vote_step = VoteStep::new(3, 11, Step::Prevote);
+        let requested = BitSet::new_with_indices(&[0, 1, 2, 3]);
+        let peers: Vec<_> = (0..5)
+            .map(|i| {
+                let address = SocketAddr::v4(1, 2, 3, i, 3485);
+                NodeId::new(address.ip(), address.port())
+            })
+            .collect();
+        let known_votes = [vec![0, 1, 2, 3], vec![0, 1, 2], vec![0, 1, 2, 3], vec![0, 1, 2, 3], vec![0]];
+        for (peer, known) in peers.iter().zip(known_votes.iter()) {
+            extension.on_node_added(peer, 0);
+            extension.update_peer_state(peer, vote_step, None, BitSet::new_with_indices(known));
+            extension.request_messages(peer, vote_step, requested);
+        }
+        let expired = Instant::now() - Duration::from_millis(ENGINE_TIMEOUT_VOTE_REQUEST_INTERVAL);
+        for (i, peer) in peers.iter().enumerate() {
+            // The request to peers[3] is not expired yet.
+            if i != 3 {
+                extension.vote_requests.get_mut(peer).unwrap().requested_at = expired;
+            }
+        }
+        // The requests to peers[2] have been sent too many times.
+        extension.vote_requests.get_mut(&peers[2]).unwrap().retries = MAX_VOTE_REQUEST_RETRIES;
+        sent.lock().clear();
+
+        extension.on_vote_request_timeout();
+
+        assert_eq!(vec![peers[0], peers[1]], {
+            let mut sent = sent.lock().clone();
+            sent.sort();
+            sent
+        });
+        let request = &extension.vote_requests[&peers[0]];
+        assert_eq!(BitSet::new_with_indices(&[1, 2, 3]), request.requested_votes);
+        assert_eq!(1, request.retries);
+        // peers[1] doesn't know vote 3.
+        let request = &extension.vote_requests[&peers[1]];
+        assert_eq!(BitSet::new_with_indices(&[1, 2]), request.requested_votes);
+        assert_eq!(1, request.retries);
+        assert!(!extension.vote_requests.contains_key(&peers[2]));
+        let request = &extension.vote_requests[&peers[3]];
+        assert_eq!(requested, request.requested_votes);
+        assert_eq!(0, request.retries);
+        // peers[4] doesn't know any of the missing votes.
+        assert!(!extension.vote_requests.contains_key(&peers[4]));
+        assert_eq!(2, extension.vote_request_counters.stats().re_requested);
+    }
+
+    #[test]
+    fn oversized_vote_is_sent_alone() {
+        let votes = vec![vec![0u8; 10], vec![1u8; 100], vec![2u8; 10]];
+        let batches = split_votes(votes, 50);
+        assert_eq!(vec![vec![vec![0u8; 10]], vec![vec![1u8; 100]], vec![vec![2u8; 10]]], batches);
+    }
+
+    #[test]
+    fn votes_under_the_limit_are_not_split() {
+        let votes = vec![vec![0u8; 10], vec![1u8; 10]];
+        let batches = split_votes(votes.clone(), 50);
+        assert_eq!(vec![votes], batches);
+    }
 }
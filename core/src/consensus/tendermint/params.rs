@@ -50,6 +50,10 @@ impl From<cjson::scheme::TendermintParams> for TendermintParams {
                 precommit: p.timeout_precommit.map_or(dt.precommit, to_duration),
                 precommit_delta: p.timeout_precommit_delta.map_or(dt.precommit_delta, to_duration),
                 commit: p.timeout_commit.map_or(dt.commit, to_duration),
+                propose_grace: p.timeout_propose_grace.map_or(dt.propose_grace, to_duration),
+                restore_observation: p.restore_observation_window.map_or(dt.restore_observation, to_duration),
+                escalation_view: p.escalation_view.map_or(dt.escalation_view, Into::into),
+                max_timestamp_step: p.max_timestamp_step.map(to_duration),
             },
             block_reward: p.block_reward.map_or(0, Into::into),
             genesis_stakes: p
@@ -70,6 +74,10 @@ fn to_duration(ms: cjson::uint::Uint) -> Duration {
 pub struct TimeGapParams {
     pub allowed_past_gap: Duration,
     pub allowed_future_gap: Duration,
+    /// The validator doesn't propose while its clock is skewed more than this.
+    pub max_clock_skew: Option<Duration>,
+    /// The headers whose timestamps are ahead of the local time by more than this are rejected.
+    pub max_future_drift: Duration,
 }
 
 /// Base timeout of each step in ms.
@@ -82,6 +90,19 @@ pub struct TimeoutParams {
     pub precommit: Duration,
     pub precommit_delta: Duration,
     pub commit: Duration,
+    /// The propose timeout is extended by this once per view if a peer already has the proposal.
+    /// Zero disables the extension.
+    pub propose_grace: Duration,
+    /// The engine that restarted with a corrupted backup doesn't sign for at least this long,
+    /// and until it moves past the height that the peers announced meanwhile.
+    pub restore_observation: Duration,
+    /// When the view at a height goes above this, the engine logs the missing votes in every view
+    /// and requests the votes to all peers until the height is committed.
+    pub escalation_view: View,
+    /// The timestamp of a proposal doesn't go beyond this from its parent's even if the proposer's clock is ahead,
+    /// so a skewed proposer can't close the term early. None doesn't limit it.
+    /// All validators must use the same value, because the others don't prevote the proposal out of the range.
+    pub max_timestamp_step: Option<Duration>,
 }
 
 impl Default for TimeoutParams {
@@ -94,6 +115,10 @@ impl Default for TimeoutParams {
             precommit: Duration::from_millis(1000),
             precommit_delta: Duration::from_millis(500),
             commit: Duration::from_millis(1000),
+            propose_grace: Duration::default(),
+            restore_observation: Duration::from_secs(10),
+            escalation_view: 10,
+            max_timestamp_step: None,
         }
     }
 }
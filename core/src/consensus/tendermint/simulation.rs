@@ -0,0 +1,2157 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Runs several Tendermint engines in a single process.
+//!
+//! The engines don't use the real network and timers. The messages are passed through an in-memory
+//! network which can drop, delay, or partition them, and the timers are driven by a mock clock that
+//! advances only when `Simulation::step` is called.
+
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::atomic::Ordering as AtomicOrdering;
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::Duration;
+
+use ccrypto::blake256;
+use cjson;
+use ckey::Address;
+use cnetwork::{Api, NetworkExtension, NetworkExtensionResult, NodeId};
+use crossbeam_channel as crossbeam;
+use ctimer::{TimerLoop, TimerToken};
+use kvdb::{DBTransaction, KeyValueDB};
+use kvdb_memorydb;
+use parking_lot::Mutex;
+use primitives::{Bytes, H256};
+use rlp::UntrustedRlp;
+
+use super::backup::BACKUP_KEY;
+use super::message::{ConsensusMessage, TendermintMessage, VoteStep, CAPABILITY_VERSION};
+use super::network::{self, TendermintExtension};
+use super::params::{TendermintParams, TimeGapParams};
+use super::types::{Step, View};
+use super::worker;
+use super::Tendermint;
+use crate::account_provider::AccountProvider;
+use crate::client::{BlockChainClient, BlockChainTrait, Client, ClientConfig, ReplayOptions, ReplayReport};
+use crate::codechain_machine::CodeChainMachine;
+use crate::consensus::{
+    CodeChainEngine, ConsensusEngine, ConsensusFault, ConsensusStatus, EngineError, RestoreStatus, ValidatorSet,
+};
+use crate::db::{COL_EXTRA, NUM_COLUMNS};
+use crate::miner::{Miner, MinerOptions, MinerService, ProposalBudget};
+use crate::scheme::Scheme;
+use crate::service::ClientService;
+use crate::transaction::SignedTransaction;
+use crate::types::TransactionId;
+use crate::BlockId;
+
+/// The number of validators in the bundled Tendermint scheme.
+/// The secret of the i-th validator is `blake256(i.to_string())`. The observers use the secrets after them.
+pub const MAX_NODES: usize = 4;
+/// The amount of time that the mock clock advances in a step.
+const TICK: Duration = Duration::from_millis(500);
+/// The block timestamps follow the real clock, so the gaps must be wide enough for a long simulation.
+const TIME_GAP: Duration = Duration::from_secs(60 * 60);
+
+/// The engines work on their own threads. The network is regarded as idle when nothing happens
+/// while polling it this many times.
+const IDLE_POLLS_TO_SETTLE: usize = 4;
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+const BASE_PORT: u16 = 3485;
+
+struct Envelope {
+    from: usize,
+    to: usize,
+    deliver_at: Duration,
+    message: Arc<Bytes>,
+}
+
+struct Timer {
+    deadline: Duration,
+    interval: Option<Duration>,
+}
+
+#[derive(Default)]
+struct Faults {
+    /// The messages sent by these nodes are dropped.
+    silenced: HashSet<usize>,
+    /// The precommits sent to these nodes are dropped.
+    precommits_dropped: HashSet<usize>,
+    /// The ProposalBlock messages sent to these nodes are dropped.
+    proposal_blocks_dropped: HashSet<usize>,
+    /// ProposalBlock messages are delivered after this delay.
+    proposal_delay: Option<Duration>,
+    /// The group of each node, and the number of steps before the partition heals.
+    partition: Option<(Vec<usize>, usize)>,
+}
+
+#[derive(Default)]
+struct Network {
+    /// The elapsed time of the mock clock.
+    now: Duration,
+    node_ids: Vec<NodeId>,
+    in_flight: Vec<Envelope>,
+    timers: HashMap<(usize, TimerToken), Timer>,
+    faults: Faults,
+    /// The senders, the receivers and the messages of the dropped ProposalBlock messages.
+    dropped_proposal_blocks: Vec<(usize, usize, Arc<Bytes>)>,
+    /// The number of the messages carrying prevotes that each node received.
+    prevotes_received: HashMap<usize, usize>,
+    /// The number of the delivered votes signed by each validator index.
+    votes_signed: HashMap<usize, usize>,
+    /// The steps of the votes signed by each validator index.
+    vote_steps_signed: HashMap<usize, HashSet<VoteStep>>,
+}
+
+impl Network {
+    fn send(&mut self, from: usize, to: &NodeId, message: Arc<Bytes>) {
+        let to = self.node_ids.iter().position(|id| id == to).expect("Messages are sent to the simulated nodes");
+        if self.faults.silenced.contains(&from) {
+            ctrace!(ENGINE, "Drop a message from {} to {}", from, to);
+            return
+        }
+        if self.faults.precommits_dropped.contains(&to) && has_vote(&message, Step::Precommit) {
+            ctrace!(ENGINE, "Drop precommits from {} to {}", from, to);
+            return
+        }
+        if self.faults.proposal_blocks_dropped.contains(&to) && is_proposal_block(&message) {
+            ctrace!(ENGINE, "Drop a proposal block from {} to {}", from, to);
+            self.dropped_proposal_blocks.push((from, to, message));
+            return
+        }
+        if let Some((groups, _)) = &self.faults.partition {
+            if groups[from] != groups[to] {
+                ctrace!(ENGINE, "Drop a message from {} to {} across the partition", from, to);
+                return
+            }
+        }
+        let delay = match self.faults.proposal_delay {
+            Some(delay) if is_proposal_block(&message) => delay,
+            _ => Duration::from_secs(0),
+        };
+        if has_vote(&message, Step::Prevote) {
+            *self.prevotes_received.entry(to).or_default() += 1;
+        }
+        for vote in votes(&message) {
+            *self.votes_signed.entry(vote.signer_index).or_default() += 1;
+            self.vote_steps_signed.entry(vote.signer_index).or_default().insert(vote.on.step);
+        }
+        self.in_flight.push(Envelope {
+            from,
+            to,
+            deliver_at: self.now + delay,
+            message,
+        });
+    }
+
+    fn take_deliverable(&mut self) -> Vec<Envelope> {
+        let now = self.now;
+        let (deliverable, in_flight) = self.in_flight.drain(..).partition(|envelope| envelope.deliver_at <= now);
+        self.in_flight = in_flight;
+        deliverable
+    }
+
+    /// Advances the mock clock and returns the expired timers in the order of their deadlines.
+    fn advance(&mut self, tick: Duration) -> Vec<(usize, TimerToken)> {
+        self.now += tick;
+        if let Some((_, remaining_steps)) = &mut self.faults.partition {
+            *remaining_steps -= 1;
+        }
+        if let Some((_, 0)) = self.faults.partition {
+            cdebug!(ENGINE, "The network partition is healed");
+            self.faults.partition = None;
+        }
+
+        let now = self.now;
+        let mut expired: Vec<_> = self
+            .timers
+            .iter()
+            .filter(|(_, timer)| timer.deadline <= now)
+            .map(|(key, timer)| (timer.deadline, *key))
+            .collect();
+        expired.sort();
+        for (_, key) in &expired {
+            let interval = self.timers[key].interval;
+            match interval {
+                Some(interval) => self.timers.get_mut(key).unwrap().deadline += interval,
+                None => {
+                    self.timers.remove(key);
+                }
+            }
+        }
+        expired.into_iter().map(|(_, key)| key).collect()
+    }
+}
+
+fn is_proposal_block(message: &[u8]) -> bool {
+    match UntrustedRlp::new(message).as_val() {
+        Ok(TendermintMessage::ProposalBlock {
+            ..
+        })
+        | Ok(TendermintMessage::ProposalHeaderAndBody {
+            ..
+        }) => true,
+        _ => false,
+    }
+}
+
+fn has_vote(message: &[u8], step: Step) -> bool {
+    match UntrustedRlp::new(message).as_val() {
+        Ok(TendermintMessage::ConsensusMessage(votes)) => votes.iter().any(|vote| {
+            UntrustedRlp::new(vote).as_val::<ConsensusMessage>().map(|vote| vote.on.step.step == step).unwrap_or(false)
+        }),
+        _ => false,
+    }
+}
+
+fn votes(message: &[u8]) -> Vec<ConsensusMessage> {
+    match UntrustedRlp::new(message).as_val() {
+        Ok(TendermintMessage::ConsensusMessage(votes)) => {
+            votes.iter().filter_map(|vote| UntrustedRlp::new(vote).as_val().ok()).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// The extension API of a simulated node.
+struct SimulatedApi {
+    index: usize,
+    network: Arc<Mutex<Network>>,
+}
+
+impl Api for SimulatedApi {
+    fn send(&self, node: &NodeId, message: Arc<Bytes>) {
+        self.network.lock().send(self.index, node, message);
+    }
+
+    fn set_timer(&self, timer: TimerToken, d: Duration) -> NetworkExtensionResult<()> {
+        let mut network = self.network.lock();
+        let deadline = network.now + d;
+        network.timers.insert((self.index, timer), Timer {
+            deadline,
+            interval: Some(d),
+        });
+        Ok(())
+    }
+
+    fn set_timer_once(&self, timer: TimerToken, d: Duration) -> NetworkExtensionResult<()> {
+        let mut network = self.network.lock();
+        let deadline = network.now + d;
+        network.timers.insert((self.index, timer), Timer {
+            deadline,
+            interval: None,
+        });
+        Ok(())
+    }
+
+    fn clear_timer(&self, timer: TimerToken) -> NetworkExtensionResult<()> {
+        self.network.lock().timers.remove(&(self.index, timer));
+        Ok(())
+    }
+}
+
+impl Tendermint {
+    /// Does the same thing as `register_network_extension_to_service` with the given API.
+    fn register_simulated_extension(
+        &self,
+        api: Box<Api>,
+    ) -> (TendermintExtension, crossbeam::Receiver<network::Event>) {
+        let extension = TendermintExtension::new(
+            self.consensus_inner.clone(),
+            self.timeouts,
+            Arc::clone(&self.commit_recovery),
+            Arc::clone(&self.vote_requests),
+            self.full_gossip.load(AtomicOrdering::SeqCst),
+            api,
+        );
+        let (sender, receiver) = crossbeam::unbounded();
+        let client = Weak::clone(self.client.read().as_ref().unwrap());
+        self.extension_initializer.send((sender, client)).unwrap();
+
+        let (result, restored) = crossbeam::bounded(1);
+        self.consensus_inner.send(worker::Event::Restore(result)).unwrap();
+        restored.recv().unwrap();
+        (extension, receiver)
+    }
+}
+
+/// The parameters of the engines that differ from the bundled scheme.
+#[derive(Clone, Copy, Default)]
+struct EngineOptions {
+    seal_version: u64,
+    propose_grace: Duration,
+    restore_observation: Duration,
+    escalation_view: Option<View>,
+    max_future_drift: Option<Duration>,
+    /// Replaces the seal checkpoint interval of the default client config.
+    seal_checkpoint_interval: Option<u64>,
+}
+
+fn tendermint_scheme(options: EngineOptions) -> (Scheme, Arc<Tendermint>) {
+    let mut scheme = Scheme::new_test_tendermint();
+    let mut params: TendermintParams =
+        match cjson::scheme::Scheme::load(include_bytes!("../../../res/tendermint.json") as &[u8]) {
+            Ok(cjson::scheme::Scheme {
+                engine: cjson::scheme::Engine::Tendermint(tendermint),
+                ..
+            }) => tendermint.params.into(),
+            _ => unreachable!("The bundled tendermint scheme uses the Tendermint engine"),
+        };
+    params.timeouts.propose_grace = options.propose_grace;
+    params.timeouts.restore_observation = options.restore_observation;
+    if let Some(escalation_view) = options.escalation_view {
+        params.timeouts.escalation_view = escalation_view;
+    }
+    let mut genesis_params = scheme.genesis_params();
+    if options.seal_version != genesis_params.seal_version() {
+        genesis_params.set_seal_version_for_test(options.seal_version);
+    }
+    let engine = Tendermint::new(params, CodeChainMachine::new(genesis_params));
+    scheme.engine = Arc::clone(&engine) as Arc<CodeChainEngine>;
+    (scheme, engine)
+}
+
+struct Node {
+    // The extension must be dropped before the engine, because the engine waits for its worker.
+    extension: TendermintExtension,
+    events: crossbeam::Receiver<network::Event>,
+    address: Address,
+    service: ClientService,
+    miner: Arc<Miner>,
+    engine: Arc<Tendermint>,
+    _scheme: Scheme,
+    secret: usize,
+    full_gossip: bool,
+    /// Kept to restart the node with the same database.
+    db: Arc<KeyValueDB>,
+}
+
+impl Node {
+    #[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
+    fn new(
+        index: usize,
+        secret: usize,
+        full_gossip: bool,
+        standby: bool,
+        network: &Arc<Mutex<Network>>,
+        timer_loop: &TimerLoop,
+        options: EngineOptions,
+        db: Arc<KeyValueDB>,
+    ) -> Self {
+        let (scheme, engine) = tendermint_scheme(options);
+        engine.register_time_gap_config_to_worker(TimeGapParams {
+            allowed_past_gap: TIME_GAP,
+            allowed_future_gap: TIME_GAP,
+            max_clock_skew: Some(TIME_GAP),
+            max_future_drift: options.max_future_drift.unwrap_or(TIME_GAP),
+        });
+        engine.set_full_gossip(full_gossip);
+        if standby {
+            engine.demote_to_standby().unwrap();
+        }
+
+        let passphrase = secret.to_string();
+        let ap = AccountProvider::transient_provider();
+        let address = ap.insert_account(blake256(&passphrase).into(), &passphrase.as_str().into()).unwrap();
+        ap.unlock_account_permanently(address, passphrase.as_str().into()).unwrap();
+
+        let miner_options = MinerOptions {
+            force_sealing: true,
+            ..Default::default()
+        };
+        let miner = Miner::new(miner_options, &scheme, Some(ap), Arc::clone(&db));
+        let reseal_timer = timer_loop.new_timer_with_name("Client reseal timer");
+        let mut client_config = ClientConfig::default();
+        if let Some(seal_checkpoint_interval) = options.seal_checkpoint_interval {
+            client_config.seal_checkpoint_interval = seal_checkpoint_interval;
+        }
+        let service = ClientService::start(
+            &client_config,
+            &scheme,
+            Arc::clone(&db),
+            Arc::clone(&miner),
+            reseal_timer.clone(),
+        )
+        .expect("Client service must be started");
+        reseal_timer.set_handler(Arc::downgrade(&service.client()));
+        engine.register_chain_notify(service.client().as_ref());
+
+        let (extension, events) = engine.register_simulated_extension(Box::new(SimulatedApi {
+            index,
+            network: Arc::clone(network),
+        }));
+        miner.set_author(address).expect("The account is unlocked");
+
+        Self {
+            extension,
+            events,
+            address,
+            service,
+            miner,
+            engine,
+            _scheme: scheme,
+            secret,
+            full_gossip,
+            db,
+        }
+    }
+
+    fn client(&self) -> Arc<Client> {
+        self.service.client()
+    }
+}
+
+/// A network of Tendermint validators running in this process.
+pub struct Simulation {
+    nodes: Vec<Node>,
+    network: Arc<Mutex<Network>>,
+    timer_loop: TimerLoop,
+    options: EngineOptions,
+}
+
+impl Simulation {
+    /// Starts `num_nodes` validators of the bundled Tendermint scheme.
+    /// The validators that are not started never vote.
+    pub fn new(num_nodes: usize) -> Self {
+        Self::with_seal_version(num_nodes, 0)
+    }
+
+    /// Starts the validators of which genesis parameters have the given seal version.
+    pub fn with_seal_version(num_nodes: usize, seal_version: u64) -> Self {
+        Self::start(num_nodes, 0, &[], None, EngineOptions {
+            seal_version,
+            ..Default::default()
+        })
+    }
+
+    /// Starts the validators that wait for the proposal once more for the given duration
+    /// when the propose step times out though a peer has the proposal.
+    pub fn with_propose_grace(num_nodes: usize, propose_grace: Duration) -> Self {
+        Self::start(num_nodes, 0, &[], None, EngineOptions {
+            propose_grace,
+            ..Default::default()
+        })
+    }
+
+    /// Starts the validators that don't sign for at least the given duration after restarting with a corrupted backup.
+    pub fn with_restore_observation(num_nodes: usize, restore_observation: Duration) -> Self {
+        Self::start(num_nodes, 0, &[], None, EngineOptions {
+            restore_observation,
+            ..Default::default()
+        })
+    }
+
+    /// Starts the validators that report the degraded consensus after the given view.
+    pub fn with_escalation_view(num_nodes: usize, escalation_view: View) -> Self {
+        Self::start(num_nodes, 0, &[], None, EngineOptions {
+            escalation_view: Some(escalation_view),
+            ..Default::default()
+        })
+    }
+
+    /// Starts the validators that reject the headers ahead of their clocks by more than the given drift.
+    /// The blocks are committed faster than the clock in the simulation, so it's usable only for a few heights.
+    pub fn with_max_future_drift(num_nodes: usize, max_future_drift: Duration) -> Self {
+        Self::start(num_nodes, 0, &[], None, EngineOptions {
+            max_future_drift: Some(max_future_drift),
+            ..Default::default()
+        })
+    }
+
+    /// Starts the validators followed by the observers, all of which skip the seals between the checkpoints
+    /// of the given interval while importing the blocks far below the tip.
+    pub fn with_seal_checkpoints(num_validators: usize, num_observers: usize, interval: u64) -> Self {
+        Self::start(num_validators, num_observers, &[], None, EngineOptions {
+            seal_checkpoint_interval: Some(interval),
+            ..Default::default()
+        })
+    }
+
+    /// Starts the validators followed by the observers, which never vote.
+    /// The nodes in `full_gossip` relay the votes to the observers.
+    pub fn with_observers(num_validators: usize, num_observers: usize, full_gossip: &[usize]) -> Self {
+        Self::start(num_validators, num_observers, full_gossip, None, Default::default())
+    }
+
+    /// Starts the validators followed by a standby node, which uses the key of the `primary` validator.
+    pub fn with_hot_spare(num_validators: usize, primary: usize) -> Self {
+        Self::start(num_validators, 0, &[], Some(primary), Default::default())
+    }
+
+    fn start(
+        num_validators: usize,
+        num_observers: usize,
+        full_gossip: &[usize],
+        hot_spare: Option<usize>,
+        options: EngineOptions,
+    ) -> Self {
+        assert!(num_validators <= MAX_NODES, "The scheme has only {} validators", MAX_NODES);
+        let num_nodes = num_validators + num_observers + hot_spare.map_or(0, |_| 1);
+        let timer_loop = TimerLoop::new(1);
+        let network = Arc::new(Mutex::new(Network {
+            node_ids: (0..num_nodes)
+                .map(|index| NodeId::new(IpAddr::V4(Ipv4Addr::LOCALHOST), BASE_PORT + index as u16))
+                .collect(),
+            ..Default::default()
+        }));
+        let mut nodes: Vec<_> = (0..num_nodes)
+            .map(|index| {
+                let standby = index >= num_validators + num_observers;
+                let secret = if index < num_validators {
+                    index
+                } else if standby {
+                    hot_spare.expect("Only the hot spare stands by")
+                } else {
+                    MAX_NODES + index - num_validators
+                };
+                let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+                Node::new(index, secret, full_gossip.contains(&index), standby, &network, &timer_loop, options, db)
+            })
+            .collect();
+
+        let node_ids = network.lock().node_ids.clone();
+        for (index, node) in nodes.iter_mut().enumerate() {
+            for (peer, id) in node_ids.iter().enumerate() {
+                if peer != index {
+                    node.extension.on_node_added(id, CAPABILITY_VERSION);
+                }
+            }
+        }
+
+        Self {
+            nodes,
+            network,
+            timer_loop,
+            options,
+        }
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// The client of the node, e.g. to run the other extensions on its chain.
+    pub fn client(&self, index: usize) -> Arc<Client> {
+        self.nodes[index].client()
+    }
+
+    /// The elapsed time of the mock clock.
+    pub fn now(&self) -> Duration {
+        self.network.lock().now
+    }
+
+    /// Drops every message sent by the node until `clear_faults` is called.
+    pub fn drop_messages_from(&mut self, index: usize) {
+        self.network.lock().faults.silenced.insert(index);
+    }
+
+    /// Drops every precommit sent to the node until `clear_faults` is called.
+    pub fn drop_precommits_to(&mut self, index: usize) {
+        self.network.lock().faults.precommits_dropped.insert(index);
+    }
+
+    /// Drops every ProposalBlock message sent to the node until `clear_faults` is called.
+    /// The node votes with the others, but it doesn't have the body of the proposal.
+    pub fn drop_proposal_blocks_to(&mut self, index: usize) {
+        self.network.lock().faults.proposal_blocks_dropped.insert(index);
+    }
+
+    /// The number of the messages carrying prevotes that the node received so far.
+    pub fn prevotes_received(&self, index: usize) -> usize {
+        self.network.lock().prevotes_received.get(&index).cloned().unwrap_or(0)
+    }
+
+    /// The senders, the receivers and the messages of the ProposalBlock messages dropped so far.
+    pub fn dropped_proposal_blocks(&self) -> Vec<(usize, usize, Arc<Bytes>)> {
+        self.network.lock().dropped_proposal_blocks.clone()
+    }
+
+    /// Delivers the message regardless of the faults, and waits until the engines become idle.
+    pub fn send_message(&mut self, from: usize, to: usize, message: Bytes) {
+        {
+            let mut network = self.network.lock();
+            let now = network.now;
+            network.in_flight.push(Envelope {
+                from,
+                to,
+                deliver_at: now,
+                message: Arc::new(message),
+            });
+        }
+        self.settle();
+    }
+
+    /// Delays the delivery of ProposalBlock messages until `clear_faults` is called.
+    pub fn delay_proposal_blocks(&mut self, delay: Duration) {
+        self.network.lock().faults.proposal_delay = Some(delay);
+    }
+
+    /// Drops the messages between the groups for the given number of steps.
+    /// The nodes that don't belong to any group are isolated.
+    pub fn partition(&mut self, groups: &[&[usize]], steps: usize) {
+        assert_ne!(0, steps);
+        let mut group_of_node: Vec<_> = (0..self.nodes.len()).map(|index| groups.len() + index).collect();
+        for (group, indices) in groups.iter().enumerate() {
+            for index in indices.iter() {
+                group_of_node[*index] = group;
+            }
+        }
+        self.network.lock().faults.partition = Some((group_of_node, steps));
+    }
+
+    pub fn clear_faults(&mut self) {
+        self.network.lock().faults = Default::default();
+    }
+
+    /// Does the same thing as the `engine_forceNextView` RPC on the node.
+    pub fn force_next_view(&mut self, index: usize) -> Result<(), EngineError> {
+        let result = self.nodes[index].engine.force_next_view();
+        self.settle();
+        result
+    }
+
+    /// Does the same thing as the `engine_promoteFromStandby` RPC on the node.
+    pub fn promote_from_standby(
+        &mut self,
+        index: usize,
+        min_quiet_period: Option<Duration>,
+    ) -> Result<(), EngineError> {
+        let result = self.nodes[index].engine.promote_from_standby(min_quiet_period);
+        self.settle();
+        result
+    }
+
+    /// The number of the delivered votes signed by the validator index.
+    pub fn votes_signed_by(&self, signer_index: usize) -> usize {
+        self.network.lock().votes_signed.get(&signer_index).cloned().unwrap_or(0)
+    }
+
+    /// The steps of the delivered votes signed by the validator index.
+    /// Unlike `votes_signed_by`, it doesn't grow when the peers relay the old votes.
+    pub fn vote_steps_signed_by(&self, signer_index: usize) -> HashSet<VoteStep> {
+        self.network.lock().vote_steps_signed.get(&signer_index).cloned().unwrap_or_default()
+    }
+
+    /// Restarts the node as if it crashed while writing the consensus backup.
+    /// The messages to the node and its timers are lost.
+    pub fn restart(&mut self, index: usize) {
+        self.restart_with(index, false)
+    }
+
+    pub fn restart_with_corrupted_backup(&mut self, index: usize) {
+        self.restart_with(index, true)
+    }
+
+    fn restart_with(&mut self, index: usize, corrupt_backup: bool) {
+        self.settle();
+        let node = self.nodes.remove(index);
+        let (secret, full_gossip, db) = (node.secret, node.full_gossip, Arc::clone(&node.db));
+        // The engine writes the backup when it stops.
+        drop(node);
+        if corrupt_backup {
+            let backup = db.get(COL_EXTRA, BACKUP_KEY).unwrap().expect("The node backed up its state");
+            let mut batch = DBTransaction::new();
+            batch.put(COL_EXTRA, BACKUP_KEY, &backup[..backup.len() - 2]);
+            db.write(batch).unwrap();
+        }
+        {
+            let mut network = self.network.lock();
+            network.timers.retain(|(node, _), _| *node != index);
+            network.in_flight.retain(|envelope| envelope.to != index);
+        }
+
+        let node = Node::new(index, secret, full_gossip, false, &self.network, &self.timer_loop, self.options, db);
+        self.nodes.insert(index, node);
+        let node_ids = self.network.lock().node_ids.clone();
+        for peer in (0..self.nodes.len()).filter(|peer| *peer != index) {
+            self.nodes[index].extension.on_node_added(&node_ids[peer], CAPABILITY_VERSION);
+            self.nodes[peer].extension.on_node_removed(&node_ids[index]);
+            self.nodes[peer].extension.on_node_added(&node_ids[index], CAPABILITY_VERSION);
+        }
+        self.settle();
+    }
+
+    /// Does the same thing as the `engine_getRestoreStatus` RPC on the node.
+    pub fn restore_status(&self, index: usize) -> RestoreStatus {
+        self.nodes[index].engine.restore_status().expect("Tendermint keeps a backup")
+    }
+
+    /// The index of the node's key in the current validator set.
+    pub fn signer_index(&self, index: usize) -> usize {
+        let parent = self.nodes[index].client().chain_info().best_block_hash;
+        let address = &self.nodes[index].address;
+        self.nodes[index].engine.validators.get_index_by_address(&parent, address).expect("The node is a validator")
+    }
+
+    /// Does the same thing as the `devel_setConsensusFault` RPC on the node.
+    /// Unlike the network faults, it isn't cleared by `clear_faults`.
+    pub fn set_consensus_fault(&mut self, index: usize, fault: ConsensusFault) -> Result<(), EngineError> {
+        let result = self.nodes[index].engine.set_consensus_fault(fault);
+        self.settle();
+        result
+    }
+
+    /// Does the same thing as the `devel_freezeChain` RPC on the node.
+    pub fn freeze_chain(&mut self, index: usize) {
+        self.nodes[index].client().freeze_chain();
+        self.settle();
+    }
+
+    /// Does the same thing as the `devel_unfreezeChain` RPC on the node.
+    pub fn unfreeze_chain(&mut self, index: usize) {
+        self.nodes[index].client().unfreeze_chain();
+        self.settle();
+    }
+
+    /// Does the same thing as the `engine_setProposalBudget` RPC on all nodes.
+    pub fn set_proposal_budget(&mut self, budget: ProposalBudget) {
+        for node in &self.nodes {
+            node.miner.set_proposal_budget(budget);
+        }
+    }
+
+    /// Queues the transactions to the mem pools of all nodes, so that any proposer includes them.
+    pub fn queue_transactions(&mut self, transactions: Vec<SignedTransaction>) {
+        for node in &self.nodes {
+            node.client().queue_own_transactions(transactions.clone()).expect("The transactions must be valid");
+        }
+        self.settle();
+    }
+
+    /// The number of the block that includes the transaction in the node's chain.
+    pub fn transaction_block_number(&self, index: usize, hash: &H256) -> Option<u64> {
+        self.nodes[index].client().transaction(&TransactionId::Hash(*hash)).map(|tx| tx.block_number)
+    }
+
+    /// Does the same thing as the `devel_replayBlocks` RPC on the node.
+    pub fn replay_blocks(&self, index: usize, from: u64, to: u64, options: &ReplayOptions) -> ReplayReport {
+        self.nodes[index]
+            .client()
+            .replay_blocks(from, to, options)
+            .expect("The blocks must be committed")
+            .expect("The blocks must be replayed")
+    }
+
+    /// Delivers the pending messages, advances the mock clock by a tick, and fires the expired timers.
+    pub fn step(&mut self) {
+        self.settle();
+        let expired = self.network.lock().advance(TICK);
+        for (index, token) in expired {
+            self.nodes[index].extension.on_timeout(token);
+        }
+        self.settle();
+    }
+
+    /// Returns false if the condition is not satisfied within the given number of steps.
+    pub fn run_until<F>(&mut self, max_steps: usize, mut condition: F) -> bool
+    where
+        F: FnMut(&Simulation) -> bool, {
+        for _ in 0..max_steps {
+            if condition(self) {
+                return true
+            }
+            self.step();
+        }
+        condition(self)
+    }
+
+    pub fn consensus_status(&self, index: usize) -> ConsensusStatus {
+        self.nodes[index].engine.consensus_status().expect("Tendermint has views")
+    }
+
+    /// The index of the node that proposes the next block of the first node's best block in the view.
+    pub fn next_proposer(&self, view: View) -> usize {
+        let parent = self.nodes[0].client().chain_info().best_block_hash;
+        let proposer = self.nodes[0].engine.validators.next_block_proposer(&parent, view).expect("Validators exist");
+        self.nodes.iter().position(|node| node.address == proposer).expect("The proposer is one of the simulated nodes")
+    }
+
+    /// The hash of the block that the node committed at the height.
+    pub fn committed_block(&self, index: usize, height: u64) -> Option<H256> {
+        let client = self.nodes[index].client();
+        if client.chain_info().best_block_number < height {
+            return None
+        }
+        client.block_hash(&BlockId::Number(height))
+    }
+
+    /// The view in which the node committed the block at the height.
+    pub fn committed_view(&self, index: usize, height: u64) -> Option<View> {
+        let header = self.nodes[index].client().block_header(&BlockId::Number(height))?.decode();
+        self.nodes[index].engine.block_view(&header)
+    }
+
+    pub fn all_committed(&self, height: u64) -> bool {
+        (0..self.nodes.len()).all(|index| self.committed_block(index, height).is_some())
+    }
+
+    pub fn any_committed(&self, height: u64) -> bool {
+        (0..self.nodes.len()).any(|index| self.committed_block(index, height).is_some())
+    }
+
+    /// Panics unless all nodes committed the same block at the height.
+    pub fn assert_committed_same_block(&self, height: u64) {
+        let hashes: Vec<_> = (0..self.nodes.len()).map(|index| self.committed_block(index, height)).collect();
+        assert!(hashes.iter().all(Option::is_some), "Not all nodes committed height {}: {:?}", height, hashes);
+        assert!(
+            hashes.iter().all(|hash| *hash == hashes[0]),
+            "Nodes committed different blocks at height {}: {:?}",
+            height,
+            hashes
+        );
+    }
+
+    /// Delivers the messages and the events until the engines become idle.
+    fn settle(&mut self) {
+        let mut idle_polls = 0;
+        while idle_polls < IDLE_POLLS_TO_SETTLE {
+            if self.deliver() {
+                idle_polls = 0;
+            } else {
+                idle_polls += 1;
+                thread::sleep(IDLE_POLL_INTERVAL);
+            }
+        }
+    }
+
+    /// Returns true if anything was delivered.
+    fn deliver(&mut self) -> bool {
+        let mut delivered = false;
+        for node in &mut self.nodes {
+            while let Ok(event) = node.events.try_recv() {
+                node.extension.on_event(event);
+                delivered = true;
+            }
+        }
+
+        let envelopes = self.network.lock().take_deliverable();
+        for envelope in envelopes {
+            let from = self.network.lock().node_ids[envelope.from];
+            self.nodes[envelope.to].extension.on_message(&from, &envelope.message);
+            delivered = true;
+        }
+        delivered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+    use ckey::{aggregate_schnorr, sign_schnorr, KeyPair, Private, SchnorrSignature};
+    use ctypes::transaction::{Action, Transaction};
+    use ctypes::Header;
+    use kvdb::DBTransaction;
+    use primitives::U256;
+    use rlp::{Encodable, RlpStream};
+
+    use super::super::backup::{restore, Restored};
+    use super::super::message::{message_hash, VoteOn, VoteStep};
+    use super::super::types::{SealSignatures, TendermintSealView};
+    use super::*;
+    use crate::client::{AccountChange, AccountData, DryRunOptions, EngineClient, ExecuteClient, ImportBlock};
+    use crate::db::{Writable, COL_ERROR_HINT};
+    use crate::error::Error;
+    use crate::verification::RejectionCode;
+    use crate::views::BlockView;
+
+    /// A height takes about 15 seconds, because the proposer waits 5 seconds before proposing an empty block
+    /// and the commit timeout is 10 seconds.
+    const STEPS_PER_HEIGHT: usize = 100;
+
+    #[test]
+    fn all_nodes_commit_the_same_blocks() {
+        let mut simulation = Simulation::new(MAX_NODES);
+
+        assert!(simulation.run_until(3 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(3)));
+        for height in 1..=3 {
+            simulation.assert_committed_same_block(height);
+        }
+    }
+
+    #[test]
+    fn silent_proposer_does_not_stop_the_consensus() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        let proposer = simulation.next_proposer(0);
+        simulation.drop_messages_from(proposer);
+
+        // The others can't commit without a view change, because the proposer of view 0 is silent.
+        assert!(simulation.run_until(3 * STEPS_PER_HEIGHT, |simulation| {
+            (0..simulation.num_nodes())
+                .filter(|index| *index != proposer)
+                .all(|index| simulation.committed_block(index, 1).is_some())
+        }));
+        simulation.clear_faults();
+        assert!(simulation.run_until(3 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(2)));
+        simulation.assert_committed_same_block(1);
+        simulation.assert_committed_same_block(2);
+    }
+
+    #[test]
+    fn lagging_node_commits_with_the_broadcast_precommits() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        let lagging = (simulation.next_proposer(0) + 1) % MAX_NODES;
+        simulation.drop_precommits_to(lagging);
+
+        assert!(
+            simulation.run_until(3 * STEPS_PER_HEIGHT, |simulation| simulation.committed_block(lagging, 1).is_some())
+        );
+        // Without the commit broadcast, the lagging node would have to wait for the seal of the next block.
+        assert!(!simulation.any_committed(2));
+        simulation.assert_committed_same_block(1);
+    }
+
+    #[test]
+    fn committed_proposal_is_requested_from_the_peers() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        let lagging = (simulation.next_proposer(0) + 1) % MAX_NODES;
+        simulation.drop_proposal_blocks_to(lagging);
+
+        assert!(simulation.run_until(STEPS_PER_HEIGHT, |simulation| {
+            simulation.consensus_status(lagging).commit_recovery.requested > 0
+        }));
+        let status = simulation.consensus_status(lagging);
+        assert_eq!((1, Step::Commit), (status.height, status.step));
+        assert_eq!(None, simulation.committed_block(lagging, 1));
+
+        // The body arrives with the retried request.
+        simulation.clear_faults();
+        assert!(simulation.run_until(10, |simulation| simulation.committed_block(lagging, 1).is_some()));
+        simulation.assert_committed_same_block(1);
+        let stats = simulation.consensus_status(lagging).commit_recovery;
+        assert_eq!(1, stats.requested);
+        assert_ne!(0, stats.retried);
+        assert_eq!(1, stats.recovered);
+    }
+
+    #[test]
+    fn forced_view_change_skips_the_silent_proposer() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        let proposer = simulation.next_proposer(0);
+        simulation.drop_messages_from(proposer);
+        let others: Vec<_> = (0..MAX_NODES).filter(|index| *index != proposer).collect();
+
+        // The proposer is waiting for its own block.
+        assert!(simulation.force_next_view(proposer).is_err());
+        for index in &others {
+            simulation.force_next_view(*index).unwrap();
+        }
+
+        // Without the force, it takes 17.5 seconds: the propose timeout of view 0 and the empty proposal
+        // timer of view 1.
+        assert!(simulation.run_until(30, |simulation| {
+            others.iter().all(|index| simulation.committed_block(*index, 1).is_some())
+        }));
+    }
+
+    #[test]
+    fn forcing_the_next_view_is_refused_after_the_commit() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        assert!(simulation.run_until(STEPS_PER_HEIGHT, |simulation| simulation.all_committed(1)));
+
+        for index in 0..MAX_NODES {
+            match simulation.force_next_view(index) {
+                Err(EngineError::CannotForceNextView(_)) => {}
+                result => panic!("Node {} must refuse to leave the committed height: {:?}", index, result),
+            }
+        }
+    }
+
+    #[test]
+    fn proposal_arriving_after_the_propose_timeout() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        // Longer than the propose timeout of view 0, and shorter than that of view 1.
+        simulation.delay_proposal_blocks(Duration::from_secs(12));
+
+        assert!(simulation.run_until(4 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(2)));
+        simulation.assert_committed_same_block(1);
+        simulation.assert_committed_same_block(2);
+    }
+
+    #[test]
+    fn propose_grace_waits_for_the_proposal_that_a_peer_has() {
+        let mut simulation = Simulation::with_propose_grace(MAX_NODES, Duration::from_secs(10));
+        // The proposal arrives after the propose timeout of view 0, but before the grace period ends.
+        simulation.delay_proposal_blocks(Duration::from_secs(8));
+
+        assert!(simulation.run_until(2 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(1)));
+        simulation.assert_committed_same_block(1);
+        for index in 0..MAX_NODES {
+            assert_eq!(Some(0), simulation.committed_view(index, 1));
+        }
+    }
+
+    #[test]
+    fn propose_grace_expires_when_the_proposal_does_not_arrive() {
+        let mut simulation = Simulation::with_propose_grace(MAX_NODES, Duration::from_secs(10));
+        let proposer = simulation.next_proposer(0);
+        for index in (0..MAX_NODES).filter(|index| *index != proposer) {
+            simulation.drop_proposal_blocks_to(index);
+        }
+        let other = (proposer + 1) % MAX_NODES;
+
+        assert!(simulation.run_until(STEPS_PER_HEIGHT, |simulation| simulation.consensus_status(other).view > 0));
+        // The propose timeout of 10 seconds is extended only once.
+        assert!(simulation.now() >= Duration::from_secs(20), "{:?}", simulation.now());
+        simulation.clear_faults();
+        assert!(simulation.run_until(2 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(1)));
+        simulation.assert_committed_same_block(1);
+    }
+
+    #[test]
+    fn consensus_resumes_after_the_partition_heals() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        let partition_steps = 2 * STEPS_PER_HEIGHT;
+        simulation.partition(&[&[0, 1], &[2, 3]], partition_steps);
+
+        // Neither half has more than 2/3 of the validators.
+        for _ in 0..partition_steps - 1 {
+            simulation.step();
+        }
+        assert!(!simulation.any_committed(1));
+
+        assert!(simulation.run_until(4 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(2)));
+        simulation.assert_committed_same_block(1);
+        simulation.assert_committed_same_block(2);
+    }
+
+    #[test]
+    fn double_prevote_is_found_by_the_honest_validators() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        let faulty = 0;
+        simulation.set_consensus_fault(faulty, ConsensusFault::DoublePrevote).unwrap();
+        let honest: Vec<_> = (0..MAX_NODES).filter(|index| *index != faulty).collect();
+
+        assert!(simulation.run_until(STEPS_PER_HEIGHT, |simulation| {
+            honest.iter().all(|index| simulation.consensus_status(*index).double_votes_found > 0)
+        }));
+
+        // The other validators are enough to keep the consensus going.
+        assert!(simulation.run_until(2 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(2)));
+        simulation.assert_committed_same_block(1);
+        simulation.assert_committed_same_block(2);
+    }
+
+    #[test]
+    fn withheld_precommits_do_not_stop_the_consensus() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        simulation.set_consensus_fault(0, ConsensusFault::WithholdPrecommit).unwrap();
+
+        assert!(simulation.run_until(2 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(2)));
+        simulation.assert_committed_same_block(1);
+        simulation.assert_committed_same_block(2);
+        for index in 0..MAX_NODES {
+            assert_eq!(0, simulation.consensus_status(index).double_votes_found);
+        }
+    }
+
+    #[test]
+    fn frozen_node_imports_the_blocks_after_unfreezing() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        simulation.freeze_chain(3);
+
+        // The frozen node doesn't propose, so the others may need a view change.
+        assert!(simulation.run_until(2 * STEPS_PER_HEIGHT, |simulation| (0..3)
+            .all(|index| simulation.committed_block(index, 1).is_some())));
+        assert_eq!(None, simulation.committed_block(3, 1));
+
+        simulation.unfreeze_chain(3);
+        assert!(simulation.run_until(STEPS_PER_HEIGHT, |simulation| simulation.committed_block(3, 1).is_some()));
+        simulation.assert_committed_same_block(1);
+    }
+
+    /// The executor of the slow node sleeps for this long before executing a block.
+    const SLOW_EXECUTION: Duration = Duration::from_millis(300);
+
+    /// Returns how long the slow node takes to precommit after its chain is unfrozen.
+    /// The precommits need its prevote, which is cast after the proposal is imported.
+    fn precommit_delay_of_the_slow_node(reuse_execution: bool) -> Duration {
+        let mut simulation = Simulation::new(MAX_NODES);
+        let proposer = simulation.next_proposer(0);
+        let slow = (proposer + 1) % MAX_NODES;
+        simulation.drop_messages_from((proposer + 3) % MAX_NODES);
+        simulation.nodes[slow].client().set_execution_delay(Some(SLOW_EXECUTION));
+        // The import of the proposal waits until the chain is unfrozen, but its execution doesn't.
+        simulation.freeze_chain(slow);
+        assert!(simulation.run_until(STEPS_PER_HEIGHT, |simulation| simulation.nodes[slow]
+            .client()
+            .optimistic_execution_stats()
+            .started
+            != 0));
+        let client = simulation.nodes[slow].client();
+        assert!(client.wait_for_executed_proposals(10 * SLOW_EXECUTION));
+        if !reuse_execution {
+            client.discard_executed_proposals(None);
+        }
+
+        // The mock clock is stopped, so that no timeout changes the votes while it's measured.
+        let precommit = VoteStep::new(1, 0, Step::Precommit);
+        let signer_index = simulation.signer_index(slow);
+        let started_at = Instant::now();
+        simulation.unfreeze_chain(slow);
+        while !simulation.vote_steps_signed_by(signer_index).contains(&precommit) {
+            assert!(started_at.elapsed() < 10 * SLOW_EXECUTION, "The slow node didn't precommit");
+            simulation.settle();
+        }
+        let delay = started_at.elapsed();
+
+        assert!(simulation.run_until(STEPS_PER_HEIGHT, |simulation| simulation.committed_block(slow, 1).is_some()));
+        assert_eq!(simulation.committed_block(proposer, 1), simulation.committed_block(slow, 1));
+        let reused = client.optimistic_execution_stats().reused;
+        assert_eq!(reuse_execution, reused != 0, "{} executions are reused", reused);
+        delay
+    }
+
+    #[test]
+    fn proposal_executed_in_advance_is_precommitted_without_the_execution() {
+        let delay = precommit_delay_of_the_slow_node(true);
+        assert!(delay < SLOW_EXECUTION, "The precommit took {:?} after the proposal executed in advance", delay);
+        let delay = precommit_delay_of_the_slow_node(false);
+        assert!(delay >= SLOW_EXECUTION, "The precommit took {:?} without the execution in advance", delay);
+    }
+
+    #[test]
+    fn proposal_executed_in_advance_is_discarded_when_another_one_is_locked() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        let proposer = simulation.next_proposer(0);
+        let receiver = (proposer + 1) % MAX_NODES;
+        // Only the frozen receiver gets the first proposal, so it can't get enough prevotes.
+        for index in (0..MAX_NODES).filter(|index| *index != receiver) {
+            simulation.drop_proposal_blocks_to(index);
+        }
+        simulation.freeze_chain(receiver);
+        assert!(simulation.run_until(STEPS_PER_HEIGHT, |simulation| (0..MAX_NODES)
+            .filter(|index| *index != receiver)
+            .all(|index| simulation.consensus_status(index).view != 0)));
+        let (_, _, message) =
+            simulation.dropped_proposal_blocks().into_iter().find(|(from, ..)| *from == proposer).unwrap();
+        let first_proposal = match UntrustedRlp::new(&message).as_val().unwrap() {
+            TendermintMessage::ProposalHeaderAndBody {
+                header,
+                ..
+            } => UntrustedRlp::new(&header).as_val::<Header>().unwrap().hash(),
+            message => panic!("Unexpected proposal {:?}", message),
+        };
+        assert_ne!(0, simulation.nodes[receiver].client().optimistic_execution_stats().started);
+
+        simulation.clear_faults();
+        assert!(simulation.run_until(2 * STEPS_PER_HEIGHT, |simulation| simulation.nodes[receiver]
+            .client()
+            .optimistic_execution_stats()
+            .discarded
+            != 0));
+
+        simulation.unfreeze_chain(receiver);
+        assert!(simulation.run_until(2 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(1)));
+        simulation.assert_committed_same_block(1);
+        assert_ne!(Some(first_proposal), simulation.committed_block(receiver, 1));
+        assert_eq!(0, simulation.nodes[receiver].client().num_executed_proposals());
+    }
+
+    #[test]
+    fn proposer_with_the_skewed_clock_skips_its_turn() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        let skewed = 0;
+        simulation.set_consensus_fault(skewed, ConsensusFault::SkewedClock(2 * TIME_GAP)).unwrap();
+
+        // The estimate needs the proposals of the three other validators.
+        assert!(simulation
+            .run_until(8 * STEPS_PER_HEIGHT, |simulation| simulation.consensus_status(skewed).clock_skew.is_some()));
+        let skew = simulation.consensus_status(skewed).clock_skew.unwrap();
+        assert!(skew as u128 > TIME_GAP.as_millis(), "The estimated skew is {}ms", skew);
+
+        // Every validator has a turn in these heights, but the skewed one gives it to the next proposer.
+        let best = simulation.nodes[0].client().chain_info().best_block_number;
+        assert!(simulation.run_until(2 * MAX_NODES * STEPS_PER_HEIGHT, |simulation| simulation
+            .all_committed(best + MAX_NODES as u64)));
+        for height in best + 1..=best + MAX_NODES as u64 {
+            simulation.assert_committed_same_block(height);
+            let header = simulation.nodes[0].client().block_header(&BlockId::Number(height)).unwrap();
+            assert_ne!(
+                simulation.nodes[skewed].address,
+                header.author(),
+                "Block {} is proposed by the skewed node",
+                height
+            );
+        }
+    }
+
+    /// Returns the sender, the signature, the view, the header and the compressed body of a proposal dropped
+    /// on the way to the node.
+    fn dropped_proposal(simulation: &mut Simulation, to: usize) -> (usize, SchnorrSignature, View, Bytes, Bytes) {
+        simulation.drop_proposal_blocks_to(to);
+        assert!(simulation.run_until(STEPS_PER_HEIGHT, |simulation| !simulation.dropped_proposal_blocks().is_empty()));
+        let (from, _, message) = simulation.dropped_proposal_blocks().remove(0);
+        match UntrustedRlp::new(&message).as_val().unwrap() {
+            TendermintMessage::ProposalHeaderAndBody {
+                signature,
+                view,
+                header,
+                compressed_body,
+            } => (from, signature, view, header, compressed_body),
+            message => panic!("Unexpected proposal {:?}", message),
+        }
+    }
+
+    #[test]
+    fn proposal_with_an_invalid_signature_is_rejected_before_its_body() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        let victim = (simulation.next_proposer(0) + 1) % MAX_NODES;
+        let (from, _, view, header, compressed_body) = dropped_proposal(&mut simulation, victim);
+
+        let before = simulation.consensus_status(victim).proposal_checks;
+        let forged = TendermintMessage::ProposalHeaderAndBody {
+            signature: SchnorrSignature::random(),
+            view,
+            header,
+            compressed_body,
+        };
+        simulation.send_message(from, victim, forged.rlp_bytes().into_vec());
+
+        let after = simulation.consensus_status(victim).proposal_checks;
+        assert_eq!(before.rejected_before_body + 1, after.rejected_before_body);
+        assert_eq!(before.bodies_decoded, after.bodies_decoded);
+        assert_eq!(None, simulation.committed_block(victim, 1));
+    }
+
+    #[test]
+    fn proposal_with_a_garbage_body_is_rejected_after_its_header_is_checked() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        let victim = (simulation.next_proposer(0) + 1) % MAX_NODES;
+        let (from, signature, view, header, compressed_body) = dropped_proposal(&mut simulation, victim);
+
+        let before = simulation.consensus_status(victim).proposal_checks;
+        let garbage = TendermintMessage::ProposalHeaderAndBody {
+            signature,
+            view,
+            header: header.clone(),
+            compressed_body: vec![0xff; 16],
+        };
+        simulation.send_message(from, victim, garbage.rlp_bytes().into_vec());
+
+        let after = simulation.consensus_status(victim).proposal_checks;
+        assert_eq!(before.rejected_before_body, after.rejected_before_body);
+        assert_eq!(before.bodies_decoded + 1, after.bodies_decoded);
+        assert_eq!(before.rejected_by_body + 1, after.rejected_by_body);
+        assert_eq!(None, simulation.committed_block(victim, 1));
+
+        // The rejected body doesn't make the proposal known, so the intact one is still accepted.
+        let intact = TendermintMessage::ProposalHeaderAndBody {
+            signature,
+            view,
+            header,
+            compressed_body,
+        };
+        simulation.send_message(from, victim, intact.rlp_bytes().into_vec());
+        assert_eq!(before.rejected_by_body + 1, simulation.consensus_status(victim).proposal_checks.rejected_by_body);
+        assert!(simulation.run_until(10, |simulation| simulation.committed_block(victim, 1).is_some()));
+        simulation.assert_committed_same_block(1);
+    }
+
+    #[test]
+    fn proposal_from_a_fast_clock_is_rejected() {
+        // The generic header check rejects the timestamps far in the future, and marks them bad.
+        for (fast_by, marked_bad) in vec![(Duration::from_secs(10), false), (Duration::from_secs(10 * 60), true)] {
+            let mut simulation = Simulation::with_max_future_drift(MAX_NODES, Duration::from_secs(3));
+            let victim = (simulation.next_proposer(0) + 1) % MAX_NODES;
+            let (from, _, view, header, compressed_body) = dropped_proposal(&mut simulation, victim);
+
+            let mut header: Header = rlp::decode(&header);
+            let fast_clock = SystemTime::now() + fast_by;
+            header.set_timestamp(fast_clock.duration_since(UNIX_EPOCH).unwrap().as_secs());
+            let fast_hash = header.hash();
+            let private = Private::from(blake256(simulation.nodes[from].secret.to_string()));
+            let step = VoteStep::new(header.number(), view, Step::Propose);
+            let signature = sign_schnorr(&private, &message_hash(step, fast_hash)).unwrap();
+            let fast = TendermintMessage::ProposalHeaderAndBody {
+                signature,
+                view,
+                header: header.rlp_bytes().into_vec(),
+                compressed_body,
+            };
+            simulation.send_message(from, victim, fast.rlp_bytes().into_vec());
+            simulation.step();
+
+            let client = simulation.nodes[victim].client();
+            assert!(client.block(&BlockId::Hash(fast_hash)).is_none(), "Imported the proposal {:?} ahead", fast_by);
+            let rejections = client.recent_rejections(usize::max_value());
+            assert_eq!(marked_bad, rejections.iter().any(|rejection| rejection.hash == fast_hash));
+
+            simulation.clear_faults();
+            assert!(simulation.run_until(3 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(1)));
+            simulation.assert_committed_same_block(1);
+            assert_ne!(Some(fast_hash), simulation.committed_block(victim, 1));
+        }
+    }
+
+    /// Returns the header of the block at the height with the seal field 2 replaced.
+    fn header_with_precommits(simulation: &Simulation, height: u64, precommits: Bytes) -> Header {
+        let mut header = simulation.nodes[0].client().block_header(&BlockId::Number(height)).unwrap().decode();
+        let mut seal = header.seal().to_vec();
+        seal[2] = precommits;
+        header.set_seal(seal);
+        header
+    }
+
+    #[test]
+    fn separate_precommits_are_kept_without_the_seal_version() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        assert!(simulation.run_until(3 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(3)));
+
+        let header = simulation.nodes[0].client().block_header(&BlockId::Number(3)).unwrap().decode();
+        let seal_view = TendermintSealView::new(header.seal());
+        assert!(!seal_view.is_aggregated().unwrap());
+        assert!(simulation.nodes[1].engine.verify_block_external(&header).is_ok());
+
+        // Aggregating them is not allowed before the seal version changes.
+        let signatures = match seal_view.signatures().unwrap() {
+            SealSignatures::Separate(signatures) => signatures,
+            SealSignatures::Aggregated(..) => unreachable!(),
+        };
+        let validators = simulation.nodes[0].engine.validators.clone();
+        let grand_parent = simulation.nodes[0].client().block_hash(&BlockId::Number(1)).unwrap();
+        let signed: Vec<_> = signatures
+            .into_iter()
+            .map(|(index, signature)| (validators.get(&grand_parent, index), signature))
+            .collect();
+        let step = VoteStep::new(2, seal_view.previous_block_view().unwrap(), Step::Precommit);
+        let aggregate = aggregate_schnorr(&signed, &message_hash(step, *header.parent_hash())).unwrap();
+        let aggregated = header_with_precommits(&simulation, 3, rlp::encode(&aggregate).into_vec());
+        assert!(simulation.nodes[1].engine.verify_block_external(&aggregated).is_err());
+    }
+
+    #[test]
+    fn aggregated_precommits_are_sealed_and_verified() {
+        let mut simulation = Simulation::with_seal_version(MAX_NODES, 1);
+        assert!(simulation.run_until(3 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(3)));
+        for height in 1..=3 {
+            simulation.assert_committed_same_block(height);
+        }
+
+        let header = simulation.nodes[0].client().block_header(&BlockId::Number(3)).unwrap().decode();
+        let seal_view = TendermintSealView::new(header.seal());
+        assert!(seal_view.is_aggregated().unwrap());
+        assert_eq!(seal_view.bitset().unwrap().count(), seal_view.signature_count().unwrap());
+        assert!(simulation.nodes[1].engine.verify_block_external(&header).is_ok());
+
+        // The separate signatures are not allowed after the seal version changes.
+        let separate = header_with_precommits(&simulation, 3, RlpStream::new_list(0).out());
+        assert!(simulation.nodes[1].engine.verify_block_external(&separate).is_err());
+    }
+
+    #[test]
+    fn malformed_aggregate_is_rejected() {
+        let mut simulation = Simulation::with_seal_version(MAX_NODES, 1);
+        assert!(simulation.run_until(3 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(3)));
+        let header = simulation.nodes[0].client().block_header(&BlockId::Number(3)).unwrap().decode();
+        let precommits = header.seal()[2].clone();
+
+        // The scalar is the last item of the aggregate.
+        let mut tampered_scalar = precommits.clone();
+        *tampered_scalar.last_mut().unwrap() ^= 1;
+        let tampered = header_with_precommits(&simulation, 3, tampered_scalar);
+        assert!(simulation.nodes[1].engine.verify_block_external(&tampered).is_err());
+
+        // The nonces of a signer are missing.
+        let mut nonces: Vec<H256> = UntrustedRlp::new(&precommits).list_at(0).unwrap();
+        nonces.pop();
+        let mut stream = RlpStream::new_list(2);
+        stream.append_list(&nonces).append(&UntrustedRlp::new(&precommits).val_at::<H256>(1).unwrap());
+        let truncated = header_with_precommits(&simulation, 3, stream.out());
+        assert!(simulation.nodes[1].engine.verify_block_external(&truncated).is_err());
+
+        let garbage = header_with_precommits(&simulation, 3, vec![0xc2, 0xc0, 0x01]);
+        assert!(simulation.nodes[1].engine.verify_block_external(&garbage).is_err());
+    }
+
+    #[test]
+    fn invalid_seal_signature_is_pinpointed() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        assert!(simulation.run_until(3 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(3)));
+        let header = simulation.nodes[0].client().block_header(&BlockId::Number(3)).unwrap().decode();
+        let signatures = match TendermintSealView::new(header.seal()).signatures().unwrap() {
+            SealSignatures::Separate(signatures) => signatures,
+            SealSignatures::Aggregated(..) => unreachable!(),
+        };
+        assert!(signatures.len() >= 3);
+        assert!(simulation.consensus_status(1).bad_seal_signatures.is_empty());
+
+        // The second precommit is replaced with the signature of the first signer.
+        let (corrupted_index, _) = signatures[1];
+        let mut precommits: Vec<SchnorrSignature> = signatures.iter().map(|(_, signature)| *signature).collect();
+        precommits[1] = precommits[0];
+        let corrupted = header_with_precommits(&simulation, 3, rlp::encode_list(&precommits).into_vec());
+        match simulation.nodes[1].engine.verify_block_external(&corrupted) {
+            Err(Error::Engine(EngineError::InvalidSealSignature {
+                signer_index,
+                block_hash,
+            })) => {
+                assert_eq!(corrupted_index, signer_index);
+                assert_eq!(corrupted.hash(), block_hash);
+            }
+            result => panic!("Unexpected result: {:?}", result),
+        }
+        let bad_seal_signatures = simulation.consensus_status(1).bad_seal_signatures;
+        assert_eq!(vec![(corrupted_index, 1)], bad_seal_signatures.into_iter().collect::<Vec<_>>());
+    }
+
+    /// The number of the blocks that the sync asks the engine to verify at once in the test below.
+    const SYNC_BACKLOG: usize = 2000;
+
+    #[test]
+    fn votes_are_handled_ahead_of_the_sync_backlog() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        assert!(simulation.run_until(3 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(3)));
+        let header = simulation.nodes[0].client().block_header(&BlockId::Number(3)).unwrap().decode();
+        let seal_view = TendermintSealView::new(header.seal());
+        let (signer_index, signature) = match seal_view.signatures().unwrap() {
+            SealSignatures::Separate(signatures) => signatures[0],
+            SealSignatures::Aggregated(..) => unreachable!(),
+        };
+        let vote = ConsensusMessage {
+            on: VoteOn {
+                step: VoteStep::new(2, seal_view.previous_block_view().unwrap(), Step::Precommit),
+                block_hash: Some(*header.parent_hash()),
+            },
+            signature,
+            signer_index,
+        };
+
+        let engine = &simulation.nodes[1].engine;
+        let (verified, verified_receiver) = crossbeam::unbounded();
+        for _ in 0..SYNC_BACKLOG {
+            engine
+                .inner
+                .send(worker::Event::VerifyBlockExternal {
+                    header: Box::new(header.clone()),
+                    result: verified.clone(),
+                })
+                .unwrap();
+        }
+        let sent_at = Instant::now();
+        let (handled, handled_receiver) = crossbeam::unbounded();
+        engine
+            .consensus_inner
+            .send(worker::Event::HandleMessages {
+                messages: vec![vote.rlp_bytes().into_vec()],
+                result: handled,
+            })
+            .unwrap();
+        handled_receiver.recv().unwrap().unwrap();
+        let latency = sent_at.elapsed();
+
+        // The vote doesn't wait until the backlog is drained.
+        let verified_ahead = verified_receiver.len();
+        assert!(verified_ahead < SYNC_BACKLOG / 2, "{} blocks are verified ahead of the vote", verified_ahead);
+        assert!(latency < Duration::from_secs(1), "The vote is handled in {:?}", latency);
+        for _ in 0..SYNC_BACKLOG {
+            verified_receiver.recv().unwrap().unwrap();
+        }
+    }
+
+    fn pay_transactions(count: u64) -> Vec<SignedTransaction> {
+        // The account that has the most balance in the bundled scheme.
+        let faucet: Private = "ede1d4ccb4ec9a8bbbae9a13db3f4a7b56ea04189be86ac3a6a439d9a0a1addd".into();
+        (0..count)
+            .map(|seq| {
+                let tx = Transaction {
+                    seq,
+                    fee: 10,
+                    network_id: "tc".into(),
+                    action: Action::Pay {
+                        receiver: Address::random(),
+                        quantity: 1,
+                    },
+                };
+                SignedTransaction::new_with_sign(tx, &faucet)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn proposal_budget_limits_the_number_of_transactions() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        simulation.set_proposal_budget(ProposalBudget {
+            max_body_size: None,
+            max_transactions: Some(10),
+        });
+        let transactions = pay_transactions(50);
+        let hashes: Vec<_> = transactions.iter().map(SignedTransaction::hash).collect();
+        simulation.queue_transactions(transactions);
+        assert!(simulation.run_until(3 * STEPS_PER_HEIGHT, |simulation| simulation
+            .transaction_block_number(0, &hashes[0])
+            .is_some()));
+
+        let number = simulation.transaction_block_number(0, &hashes[0]).unwrap();
+        let body = simulation.nodes[0].client().block_body(&BlockId::Number(number)).unwrap();
+        assert_eq!(10, body.transactions_count());
+        assert_eq!(Some(number), simulation.transaction_block_number(0, &hashes[9]));
+        assert_ne!(Some(number), simulation.transaction_block_number(0, &hashes[10]));
+    }
+
+    #[test]
+    fn replaying_the_committed_blocks_finds_no_divergence() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        let transactions = pay_transactions(2);
+        let hash = transactions[1].hash();
+        simulation.queue_transactions(transactions);
+        assert!(simulation.run_until(3 * STEPS_PER_HEIGHT, |simulation| simulation
+            .transaction_block_number(0, &hash)
+            .map_or(false, |number| simulation.all_committed(number + 1))));
+
+        let best = simulation.nodes[0].client().chain_info().best_block_number;
+        let report = simulation.replay_blocks(0, 1, best, &ReplayOptions::default());
+        assert_eq!(best, report.replayed_blocks);
+        assert_eq!(None, report.divergence);
+    }
+
+    #[test]
+    fn replay_reports_the_corrupted_invoice() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        let transactions = pay_transactions(2);
+        let hashes: Vec<_> = transactions.iter().map(SignedTransaction::hash).collect();
+        simulation.queue_transactions(transactions);
+        assert!(simulation.run_until(3 * STEPS_PER_HEIGHT, |simulation| simulation
+            .transaction_block_number(0, &hashes[1])
+            .is_some()));
+        let number = simulation.transaction_block_number(0, &hashes[1]).unwrap();
+        assert_eq!(Some(number), simulation.transaction_block_number(0, &hashes[0]));
+
+        let client = simulation.nodes[0].client();
+        let mut batch = DBTransaction::new();
+        batch.write(COL_ERROR_HINT, &hashes[1], &Some("corrupted".to_string()));
+        client.db().write(batch).unwrap();
+
+        let report = simulation.replay_blocks(0, 1, number, &ReplayOptions::default());
+        assert_eq!(number, report.replayed_blocks);
+        let divergence = report.divergence.expect("The stored invoice is corrupted");
+        assert_eq!(number, divergence.block_number);
+        assert_eq!(divergence.expected_state_root, divergence.actual_state_root);
+        assert_eq!(Some(1), divergence.transaction_index);
+        assert_eq!(hashes, divergence.transactions.iter().map(|tx| tx.hash).collect::<Vec<_>>());
+        assert_eq!(Some("corrupted".to_string()), divergence.transactions[1].expected_error);
+        assert_eq!(None, divergence.transactions[1].actual_error);
+    }
+
+    #[test]
+    fn dry_run_transaction_against_the_historical_states() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        let sender = Address::random();
+        let receiver = Address::random();
+        let faucet: Private = "ede1d4ccb4ec9a8bbbae9a13db3f4a7b56ea04189be86ac3a6a439d9a0a1addd".into();
+        let funding = SignedTransaction::new_with_sign(
+            Transaction {
+                seq: 0,
+                fee: 10,
+                network_id: "tc".into(),
+                action: Action::Pay {
+                    receiver: sender,
+                    quantity: 1000,
+                },
+            },
+            &faucet,
+        );
+        let hash = funding.hash();
+        simulation.queue_transactions(vec![funding]);
+        assert!(simulation.run_until(3 * STEPS_PER_HEIGHT, |simulation| simulation
+            .transaction_block_number(0, &hash)
+            .is_some()));
+        let funded_at = simulation.transaction_block_number(0, &hash).unwrap();
+
+        let payment = Transaction {
+            seq: 0,
+            fee: 10,
+            network_id: "tc".into(),
+            action: Action::Pay {
+                receiver,
+                quantity: 500,
+            },
+        };
+        let client = simulation.nodes[0].client();
+        let execute = |number: u64| {
+            client
+                .dry_run_transaction(&payment, &sender, None, BlockId::Number(number), &DryRunOptions::default())
+                .expect("The block exists")
+                .expect("The state exists")
+        };
+
+        let before = execute(funded_at - 1);
+        assert!(before.invoice.error.is_some());
+        assert_eq!(Vec::<AccountChange>::new(), before.account_changes);
+
+        let after = execute(funded_at);
+        assert_eq!(None, after.invoice.error);
+        assert_eq!(
+            vec![
+                AccountChange {
+                    address: sender,
+                    old_balance: 1000,
+                    new_balance: 490,
+                    old_seq: 0,
+                    new_seq: 1,
+                },
+                AccountChange {
+                    address: receiver,
+                    old_balance: 0,
+                    new_balance: 500,
+                    old_seq: 0,
+                    new_seq: 0,
+                },
+            ],
+            after.account_changes
+        );
+
+        // The live state is not changed.
+        assert_eq!(1000, client.latest_balance(&sender));
+        assert_eq!(0, client.latest_seq(&sender));
+        assert_eq!(0, client.latest_balance(&receiver));
+    }
+
+    /// Returns the committed block at the height with its header changed by `tamper`.
+    fn tampered_block<F>(simulation: &Simulation, height: u64, tamper: F) -> Bytes
+    where
+        F: FnOnce(&mut Header), {
+        let block = simulation.nodes[0].client().block(&BlockId::Number(height)).unwrap();
+        let mut header = block.decode_header();
+        tamper(&mut header);
+        let bytes = block.into_inner();
+        let mut stream = RlpStream::new_list(2);
+        stream.append(&header).append_raw(UntrustedRlp::new(&bytes).at(1).unwrap().as_raw(), 1);
+        stream.out()
+    }
+
+    /// Returns the committed blocks from 1 to `tip` of the first node.
+    /// The header at `tampered` is changed, and the headers after it are linked to the changed one.
+    fn tampered_chain<F>(simulation: &Simulation, tip: u64, tampered: u64, tamper: F) -> Vec<Bytes>
+    where
+        F: FnOnce(&mut Header), {
+        let mut tamper = Some(tamper);
+        let mut parent_hash = None;
+        (1..=tip)
+            .map(|height| {
+                let block = simulation.nodes[0].client().block(&BlockId::Number(height)).unwrap();
+                let mut header = block.decode_header();
+                if let Some(parent_hash) = parent_hash {
+                    header.set_parent_hash(parent_hash);
+                }
+                if height == tampered {
+                    (tamper.take().unwrap())(&mut header);
+                }
+                if height >= tampered {
+                    parent_hash = Some(header.hash());
+                }
+                let bytes = block.into_inner();
+                let mut stream = RlpStream::new_list(2);
+                stream.append(&header).append_raw(UntrustedRlp::new(&bytes).at(1).unwrap().as_raw(), 1);
+                stream.out()
+            })
+            .collect()
+    }
+
+    /// Imports the headers first, and then the blocks in a single batch, the way the sync does far below the tip.
+    fn sync_blocks(simulation: &mut Simulation, index: usize, blocks: &[Bytes]) {
+        let client = simulation.nodes[index].client();
+        let tip = BlockView::new(blocks.last().unwrap()).header_view().number();
+        for block in blocks {
+            client.import_header(UntrustedRlp::new(block).at(0).unwrap().as_raw().to_vec()).unwrap();
+        }
+        assert!(simulation.run_until(10, |_| client.block_chain().best_proposal_header().number() == tip));
+
+        // The verified blocks are kept in the queue until the chain is unfrozen.
+        simulation.freeze_chain(index);
+        for block in blocks {
+            client.import_block(block.clone()).unwrap();
+        }
+        assert!(simulation.run_until(10, |_| client.queue_info().incomplete_queue_size() == 0));
+        simulation.unfreeze_chain(index);
+        assert!(simulation.run_until(10, |_| client.queue_info().total_queue_size() == 0));
+    }
+
+    /// The seal checkpoint interval of the nodes in the tests below
+    const CHECKPOINT_INTERVAL: u64 = 3;
+    /// The number of the blocks synced in the tests below
+    const SYNCED_BLOCKS: u64 = 12;
+
+    /// Starts the validators and an observer that receives nothing, and lets the validators commit the blocks.
+    fn simulation_to_sync() -> (Simulation, usize) {
+        let observer = MAX_NODES;
+        let mut simulation = Simulation::with_seal_checkpoints(MAX_NODES, 1, CHECKPOINT_INTERVAL);
+        let validators: Vec<_> = (0..MAX_NODES).collect();
+        simulation.partition(&[&validators, &[observer]], usize::max_value());
+        assert!(simulation.run_until(SYNCED_BLOCKS as usize * 2 * STEPS_PER_HEIGHT, |simulation| {
+            (0..MAX_NODES).all(|index| simulation.committed_block(index, SYNCED_BLOCKS).is_some())
+        }));
+        assert_eq!(None, simulation.committed_block(observer, 1));
+        (simulation, observer)
+    }
+
+    #[test]
+    fn skipped_seals_are_recorded_until_they_are_verified_again() {
+        let (mut simulation, observer) = simulation_to_sync();
+        let blocks = tampered_chain(&simulation, SYNCED_BLOCKS, SYNCED_BLOCKS + 1, |_| {});
+        sync_blocks(&mut simulation, observer, &blocks);
+        for height in 1..=SYNCED_BLOCKS {
+            assert_eq!(simulation.committed_block(0, height), simulation.committed_block(observer, height));
+        }
+
+        // The endpoints, the checkpoints and the blocks near the tip are fully verified.
+        let client = simulation.nodes[observer].client();
+        let checkpoints: Vec<_> = (1..=SYNCED_BLOCKS)
+            .map(|height| {
+                let hash = simulation.committed_block(observer, height).unwrap();
+                client.skipped_seal_verification(&hash).map(|skipped| skipped.checkpoint)
+            })
+            .collect();
+        assert_eq!(
+            vec![None, Some(3), None, Some(6), Some(6), None, Some(9), Some(9), None, None, None, None],
+            checkpoints
+        );
+
+        let skipped = simulation.committed_block(observer, 4).unwrap();
+        match client.reverify_block_seal(&skipped) {
+            Some(Ok(())) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+        assert!(client.skipped_seal_verification(&skipped).is_none());
+    }
+
+    #[test]
+    fn tampered_header_in_a_skipped_span_is_found_at_the_checkpoint() {
+        let (mut simulation, observer) = simulation_to_sync();
+        // The seal of the block 4 is skipped, and the seal of the block 6 vouches for it.
+        let blocks = tampered_chain(&simulation, SYNCED_BLOCKS, 4, |header| {
+            let mut seal = header.seal().to_vec();
+            let mut precommits: Vec<SchnorrSignature> = UntrustedRlp::new(&seal[2]).as_list().unwrap();
+            precommits[1] = precommits[0];
+            seal[2] = rlp::encode_list(&precommits).into_vec();
+            header.set_seal(seal);
+        });
+        sync_blocks(&mut simulation, observer, &blocks);
+
+        // Nothing after the last verified seal before the tampered header is committed.
+        let client = simulation.nodes[observer].client();
+        assert_eq!(3, client.chain_info().best_block_number);
+        for (index, block) in blocks.iter().enumerate().skip(3) {
+            assert!(client.block(&BlockId::Hash(BlockView::new(block).hash())).is_none(), "#{}", index + 1);
+        }
+        let rejection = client.recent_rejections(1).remove(0);
+        assert_eq!(6, rejection.number);
+        assert_eq!(RejectionCode::SealSignature, rejection.code);
+    }
+
+    #[test]
+    fn rejected_blocks_are_categorized_and_blamed_on_the_sender() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        assert!(simulation.run_until(2 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(2)));
+
+        let victim = 1;
+        let sender = simulation.network.lock().node_ids[2];
+        let author = simulation.nodes[0].client().block_header(&BlockId::Number(2)).unwrap().author();
+        let other_validator =
+            simulation.nodes.iter().map(|node| node.address).find(|address| *address != author).unwrap();
+
+        let wrong_score = tampered_block(&simulation, 2, |header| {
+            let score = *header.score() + U256::from(1);
+            header.set_score(score);
+        });
+        let missing_precommit = tampered_block(&simulation, 2, |header| {
+            let mut seal = header.seal().to_vec();
+            let mut precommits: Vec<SchnorrSignature> = UntrustedRlp::new(&seal[2]).as_list().unwrap();
+            precommits.pop();
+            seal[2] = rlp::encode_list(&precommits).into_vec();
+            header.set_seal(seal);
+        });
+        let wrong_author = tampered_block(&simulation, 2, |header| header.set_author(other_validator));
+        let wrong_transactions_root =
+            tampered_block(&simulation, 2, |header| header.set_transactions_root(H256::random()));
+        let wrong_state_root = tampered_block(&simulation, 2, |header| header.set_state_root(H256::random()));
+        let expected = vec![
+            (wrong_score, RejectionCode::HeaderBasic),
+            (missing_precommit, RejectionCode::SealSignature),
+            (wrong_author, RejectionCode::ProposerMismatch),
+            (wrong_transactions_root, RejectionCode::Body),
+            (wrong_state_root, RejectionCode::StateRoot),
+        ];
+
+        let client = simulation.nodes[victim].client();
+        for (index, (bytes, code)) in expected.iter().enumerate() {
+            // The header is rejected at once, and the others are rejected while they are imported.
+            let _ = client.import_block_from(bytes.clone(), sender);
+            assert!(simulation.run_until(10, |_| client.recent_rejections(usize::max_value()).len() > index));
+            let rejection = client.recent_rejections(1).remove(0);
+            assert_eq!(BlockView::new(bytes).hash(), rejection.hash);
+            assert_eq!(2, rejection.number);
+            assert_eq!(*code, rejection.code);
+            assert_eq!(Some(sender), rejection.peer);
+        }
+
+        // The rejected block is not logged again.
+        let (wrong_state_root, _) = &expected[4];
+        assert!(client.import_block_from(wrong_state_root.clone(), sender).is_err());
+        simulation.run_until(10, |_| false);
+        assert_eq!(expected.len(), client.recent_rejections(usize::max_value()).len());
+        simulation.assert_committed_same_block(2);
+    }
+
+    #[test]
+    fn conflicting_finalized_blocks_halt_the_import() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        assert!(simulation.run_until(3 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(3)));
+
+        // The same validators finalize another block at height 1 in another network.
+        let mut fork = Simulation::new(MAX_NODES);
+        fork.queue_transactions(pay_transactions(1));
+        assert!(fork.run_until(3 * STEPS_PER_HEIGHT, |fork| fork.all_committed(2)));
+        let conflicting = fork.committed_block(0, 1).unwrap();
+        assert_ne!(Some(conflicting), simulation.committed_block(0, 1));
+
+        let victim = 1;
+        let client = simulation.nodes[victim].client();
+        let fork_block = |height| fork.nodes[0].client().block(&BlockId::Number(height)).unwrap().into_inner();
+        // The blocks are imported one by one, so that none of their seals are skipped.
+        client.import_block(fork_block(1)).unwrap();
+        assert!(simulation.run_until(10, |_| client.block(&BlockId::Hash(conflicting)).is_some()));
+        assert_eq!(None, client.finality_conflict());
+        client.import_block(fork_block(2)).unwrap();
+        assert!(simulation.run_until(10, |_| client.finality_conflict().is_some()));
+
+        let conflict = client.finality_conflict().unwrap();
+        assert_eq!(1, conflict.block_number);
+        assert_eq!(simulation.committed_block(victim, 1), Some(conflict.canonical.header.hash()));
+        assert_eq!(simulation.committed_block(victim, 2), Some(conflict.canonical.child.hash()));
+        assert_eq!(conflicting, conflict.conflicting.header.hash());
+        assert_eq!(fork.committed_block(0, 2), Some(conflict.conflicting.child.hash()));
+        // The exported seals prove the conflict by themselves.
+        let engine = &simulation.nodes[victim].engine;
+        assert!(engine.verify_block_external(&conflict.canonical.child).is_ok());
+        assert!(engine.verify_block_external(&conflict.conflicting.child).is_ok());
+        assert!(client.block(&BlockId::Hash(conflict.conflicting.child.hash())).is_none());
+
+        // The halted node neither imports nor proposes, while the others go on.
+        assert!(client.is_chain_frozen());
+        let halted_at = client.chain_info().best_block_number;
+        assert!(simulation.run_until(3 * STEPS_PER_HEIGHT, |simulation| simulation
+            .committed_block(0, halted_at + 1)
+            .is_some()));
+        assert_eq!(halted_at, client.chain_info().best_block_number);
+        // Unfreezing doesn't override the halt.
+        simulation.unfreeze_chain(victim);
+        assert!(client.is_chain_frozen());
+
+        assert_eq!(Some(conflict), client.clear_consensus_halt());
+        assert!(!client.is_chain_frozen());
+        assert!(simulation.run_until(STEPS_PER_HEIGHT, |simulation| simulation
+            .committed_block(victim, halted_at + 1)
+            .is_some()));
+        simulation.assert_committed_same_block(halted_at + 1);
+        assert_eq!(None, client.clear_consensus_halt());
+    }
+
+    #[test]
+    fn observers_finalize_blocks_without_the_prevotes() {
+        let observer = MAX_NODES;
+        let relay = MAX_NODES + 1;
+        let mut simulation = Simulation::with_observers(MAX_NODES, 2, &[relay]);
+
+        // The votes are gossiped to everyone until the validators of the next height are known.
+        assert!(simulation.run_until(2 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(1)));
+        let received_by_observer = simulation.prevotes_received(observer);
+        let received_by_relay = simulation.prevotes_received(relay);
+
+        assert!(simulation.run_until(3 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(4)));
+        assert_eq!(received_by_observer, simulation.prevotes_received(observer));
+        assert!(simulation.prevotes_received(relay) > received_by_relay);
+        for height in 1..=4 {
+            simulation.assert_committed_same_block(height);
+        }
+    }
+
+    #[test]
+    fn observer_advertising_the_key_of_a_validator_gets_no_prevotes() {
+        let observer = MAX_NODES;
+        let mut simulation = Simulation::with_observers(MAX_NODES, 1, &[]);
+        assert!(simulation.run_until(2 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(1)));
+        let received_by_observer = simulation.prevotes_received(observer);
+
+        // The observer cannot sign the challenges of the validators with the key that it advertises.
+        let validator_key = *KeyPair::from_private(Private::from(blake256("0"))).unwrap().public();
+        let observer_key = Private::from(blake256(MAX_NODES.to_string()));
+        let observer_id = simulation.network.lock().node_ids[observer];
+        for index in 0..MAX_NODES {
+            let forged = TendermintMessage::Capability {
+                signer: Some(validator_key),
+                full_gossip: false,
+                challenge: H256::random(),
+                proof: Some(sign_schnorr(&observer_key, &H256::random()).unwrap()),
+            };
+            simulation.nodes[index].extension.on_message(&observer_id, &forged.rlp_bytes());
+        }
+
+        assert!(simulation.run_until(3 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(4)));
+        assert_eq!(received_by_observer, simulation.prevotes_received(observer));
+        for height in 1..=4 {
+            simulation.assert_committed_same_block(height);
+        }
+    }
+
+    #[test]
+    fn proposals_of_the_previous_heights_are_not_retained() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        // The block that includes all of them is much larger than an empty block.
+        let transactions = pay_transactions(300);
+        let payload: usize = transactions.iter().map(|tx| tx.rlp_bytes().len()).sum();
+        let last = transactions[299].hash();
+        simulation.queue_transactions(transactions);
+        assert!(simulation.run_until(3 * STEPS_PER_HEIGHT, |simulation| simulation
+            .transaction_block_number(0, &last)
+            .map_or(false, |number| simulation.all_committed(number))));
+        let number = simulation.transaction_block_number(0, &last).unwrap();
+
+        assert!(simulation.run_until(STEPS_PER_HEIGHT, |simulation| (0..MAX_NODES)
+            .all(|index| simulation.consensus_status(index).height > number)));
+        for index in 0..MAX_NODES {
+            let retained = simulation.consensus_status(index).retained_proposal_bytes;
+            assert!(retained < payload, "Node {} retains {} bytes of proposals", index, retained);
+        }
+
+        // Notifying the import of the old proposal again doesn't bring it back.
+        let old = simulation.committed_block(0, number).unwrap();
+        let heights: Vec<_> = (0..MAX_NODES).map(|index| simulation.consensus_status(index).height).collect();
+        for node in &simulation.nodes {
+            node.engine
+                .inner
+                .send(worker::Event::NewBlocks {
+                    imported: vec![old],
+                    enacted: vec![],
+                })
+                .unwrap();
+        }
+        simulation.settle();
+        for (index, height) in heights.into_iter().enumerate() {
+            let status = simulation.consensus_status(index);
+            assert!(status.height >= height);
+            assert!(status.retained_proposal_bytes < payload);
+        }
+        assert!(simulation.run_until(2 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(number + 2)));
+        simulation.assert_committed_same_block(number + 1);
+    }
+
+    const PRIMARY: usize = 0;
+    const SPARE: usize = MAX_NODES;
+
+    fn committed_without_the_primary(simulation: &Simulation, height: u64) -> bool {
+        (0..simulation.num_nodes())
+            .filter(|index| *index != PRIMARY)
+            .all(|index| simulation.committed_block(index, height).is_some())
+    }
+
+    /// Isolates the primary as if it's down.
+    fn stop_primary(simulation: &mut Simulation) {
+        simulation.partition(&[&[1, 2, 3, SPARE]], usize::max_value());
+    }
+
+    #[test]
+    fn standby_node_follows_the_consensus_without_signing() {
+        let mut simulation = Simulation::with_hot_spare(MAX_NODES, PRIMARY);
+        // Every vote signed by the key of the primary would be from the spare.
+        stop_primary(&mut simulation);
+
+        assert!(simulation.run_until(4 * STEPS_PER_HEIGHT, |simulation| committed_without_the_primary(simulation, 3)));
+        for height in 1..=3 {
+            let hashes: Vec<_> = (1..=SPARE).map(|index| simulation.committed_block(index, height)).collect();
+            assert!(hashes.iter().all(|hash| *hash == hashes[0]));
+        }
+        assert_eq!(0, simulation.votes_signed_by(simulation.signer_index(PRIMARY)));
+        assert!(simulation.votes_signed_by(simulation.signer_index(1)) > 0);
+        assert!(simulation.consensus_status(SPARE).standby);
+    }
+
+    #[test]
+    fn promotion_is_refused_after_a_recent_own_vote() {
+        let mut simulation = Simulation::with_hot_spare(MAX_NODES, PRIMARY);
+        assert!(simulation.run_until(2 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(1)));
+        assert!(simulation.votes_signed_by(simulation.signer_index(PRIMARY)) > 0);
+
+        assert!(simulation.promote_from_standby(SPARE, Some(Duration::from_secs(60 * 60))).is_err());
+        assert!(simulation.consensus_status(SPARE).standby);
+    }
+
+    #[test]
+    fn promotion_succeeds_after_the_quiet_period() {
+        let mut simulation = Simulation::with_hot_spare(MAX_NODES, PRIMARY);
+        assert!(simulation.run_until(2 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(1)));
+        stop_primary(&mut simulation);
+        // The spare has no vote of the primary at the new height.
+        let height = simulation.consensus_status(SPARE).height;
+        assert!(
+            simulation.run_until(2 * STEPS_PER_HEIGHT, |simulation| simulation.consensus_status(SPARE).height > height)
+        );
+
+        // The last vote of the primary was received at least a height ago.
+        assert!(simulation.promote_from_standby(SPARE, Some(Duration::from_secs(60 * 60))).is_err());
+        simulation.promote_from_standby(SPARE, Some(Duration::from_millis(100))).unwrap();
+        assert!(!simulation.consensus_status(SPARE).standby);
+
+        let signed = simulation.votes_signed_by(simulation.signer_index(PRIMARY));
+        let best = simulation.consensus_status(SPARE).height as u64;
+        assert!(simulation
+            .run_until(4 * STEPS_PER_HEIGHT, |simulation| committed_without_the_primary(simulation, best + 2)));
+        assert!(simulation.votes_signed_by(simulation.signer_index(PRIMARY)) > signed);
+    }
+
+    #[test]
+    fn node_with_corrupted_backup_signs_after_observing_the_network() {
+        let window = Duration::from_secs(3);
+        let mut simulation = Simulation::with_restore_observation(MAX_NODES, window);
+        assert!(simulation.run_until(2 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(1)));
+        let restarted = (simulation.next_proposer(0) + 1) % MAX_NODES;
+        let signer_index = simulation.signer_index(restarted);
+        assert_eq!(RestoreStatus::NoBackup, simulation.restore_status(restarted));
+
+        let restarting_at = Instant::now();
+        simulation.restart_with_corrupted_backup(restarted);
+        let hash = match simulation.restore_status(restarted) {
+            RestoreStatus::Quarantined {
+                hash,
+                observing: true,
+            } => hash,
+            status => panic!("The corrupted backup must be quarantined: {:?}", status),
+        };
+        let best = simulation.nodes[restarted].client().chain_info().best_block_number;
+        assert_eq!(best + 1, simulation.consensus_status(restarted).height);
+
+        // The others keep the consensus going while the restarted node signs nothing.
+        let signed = simulation.vote_steps_signed_by(signer_index);
+        assert!(simulation.run_until(6 * STEPS_PER_HEIGHT, |simulation| {
+            let observing = match simulation.restore_status(restarted) {
+                RestoreStatus::Quarantined {
+                    observing,
+                    ..
+                } => observing,
+                status => panic!("The status must not change: {:?}", status),
+            };
+            if observing {
+                assert_eq!(signed, simulation.vote_steps_signed_by(signer_index), "Signed while observing");
+            }
+            !observing
+        }));
+        assert!(restarting_at.elapsed() >= window);
+        assert_eq!(
+            RestoreStatus::Quarantined {
+                hash,
+                observing: false,
+            },
+            simulation.restore_status(restarted)
+        );
+
+        let height = simulation.consensus_status(restarted).height as u64;
+        assert!(simulation.run_until(4 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(height + 1)));
+        for height in 1..=height + 1 {
+            simulation.assert_committed_same_block(height);
+        }
+        assert!(simulation.vote_steps_signed_by(signer_index).iter().any(|step| step.height >= height));
+    }
+
+    #[test]
+    fn node_restarted_while_observing_keeps_observing() {
+        let window = Duration::from_secs(60 * 60);
+        let mut simulation = Simulation::with_restore_observation(MAX_NODES, window);
+        assert!(simulation.run_until(2 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(1)));
+        let restarted = (simulation.next_proposer(0) + 1) % MAX_NODES;
+        let signer_index = simulation.signer_index(restarted);
+
+        simulation.restart_with_corrupted_backup(restarted);
+        let hash = match simulation.restore_status(restarted) {
+            RestoreStatus::Quarantined {
+                hash,
+                observing: true,
+            } => hash,
+            status => panic!("The corrupted backup must be quarantined: {:?}", status),
+        };
+
+        // The backup written at the shutdown is clean, but the node hasn't finished observing yet.
+        simulation.restart(restarted);
+        assert_eq!(
+            RestoreStatus::Quarantined {
+                hash,
+                observing: true,
+            },
+            simulation.restore_status(restarted)
+        );
+        let signed = simulation.vote_steps_signed_by(signer_index);
+        let height = simulation.consensus_status(restarted).height as u64;
+        assert!(simulation.run_until(4 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(height + 1)));
+        assert_eq!(signed, simulation.vote_steps_signed_by(signer_index), "Signed while observing");
+    }
+
+    #[test]
+    fn stopping_the_engine_flushes_the_backup() {
+        let mut simulation = Simulation::new(MAX_NODES);
+        assert!(simulation.run_until(2 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(1)));
+        simulation.settle();
+        let status = simulation.consensus_status(0);
+        let db = Arc::clone(&simulation.nodes[0].db);
+        let mut batch = DBTransaction::new();
+        batch.delete(COL_EXTRA, BACKUP_KEY);
+        db.write(batch).unwrap();
+
+        // The shutdown sequence stops the engine while the client is still alive.
+        simulation.nodes[0].engine.stop();
+        let backup = match restore(db.as_ref()) {
+            Restored::Backup(backup) => backup,
+            _ => panic!("The engine must back up its state when it stops"),
+        };
+        assert_eq!((status.height, status.view), (backup.height, backup.view));
+
+        // The engine is dropped after the shutdown sequence, and doesn't wait for the worker again.
+        simulation.nodes[0].engine.stop();
+    }
+
+    #[test]
+    fn climbing_view_is_reported_until_the_height_is_committed() {
+        let escalation_view = 1;
+        let mut simulation = Simulation::with_escalation_view(MAX_NODES, escalation_view);
+        let silent = simulation.next_proposer(0);
+        let observer = (silent + 1) % MAX_NODES;
+        let silent_signer = simulation.signer_index(silent);
+        simulation.drop_messages_from(silent);
+        for index in 0..MAX_NODES {
+            simulation.drop_proposal_blocks_to(index);
+        }
+
+        assert!(simulation.run_until(4 * STEPS_PER_HEIGHT, |simulation| {
+            simulation.consensus_status(observer).degradation.is_some()
+        }));
+        let status = simulation.consensus_status(observer);
+        assert_eq!(escalation_view, status.escalation_view);
+        let degradation = status.degradation.unwrap();
+        assert_eq!(1, degradation.height);
+        assert_eq!(escalation_view + 1, degradation.since_view);
+        assert_eq!(vec![silent_signer], degradation.missing_prevotes);
+        assert_eq!(vec![silent_signer], degradation.missing_precommits);
+        assert!(!simulation.any_committed(1));
+
+        simulation.clear_faults();
+        assert!(simulation.run_until(4 * STEPS_PER_HEIGHT, |simulation| simulation.all_committed(1)));
+        simulation.assert_committed_same_block(1);
+        for index in 0..MAX_NODES {
+            assert_eq!(None, simulation.consensus_status(index).degradation);
+        }
+    }
+}
@@ -14,15 +14,20 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::collections::BTreeMap;
 use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-use ckey::SchnorrSignature;
+use ckey::{Public, SchnorrAggregate, SchnorrSignature};
+use parking_lot::Mutex;
 use primitives::{Bytes, H256};
 use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
 
 use super::super::BitSet;
 use super::message::VoteStep;
+use super::SEAL_FIELDS;
 use crate::block::{IsBlock, SealedBlock};
+use crate::consensus::{CommitRecoveryStats, DecodedSeal, VoteRequestStats};
 
 pub type Height = u64;
 pub type View = u64;
@@ -79,6 +84,19 @@ impl TendermintState {
             _ => false,
         }
     }
+
+    /// Returns the block that the proposer holds until it's imported or the empty block timer expires.
+    pub fn proposal_block(&self) -> Option<&SealedBlock> {
+        match self {
+            TendermintState::ProposeWaitImported {
+                block,
+            } => Some(&**block),
+            TendermintState::ProposeWaitEmptyBlockTimer {
+                block,
+            } => Some(&**block),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Debug for TendermintState {
@@ -159,21 +177,133 @@ impl Encodable for Step {
 }
 
 pub struct PeerState {
+    /// The version of the extension negotiated with the peer.
+    pub version: u64,
     pub vote_step: VoteStep,
     pub proposal: Option<H256>,
     pub messages: BitSet,
+    /// None until the peer advertises its capability. The peers of the old versions never do.
+    /// The signer in it is None unless the peer proved that it owns the signer.
+    pub capability: Option<PeerCapability>,
+    /// The random value that the peer signs to prove its signer.
+    pub challenge: H256,
+    /// The challenge of the peer, which the proof of our signer is signed on.
+    pub peer_challenge: Option<H256>,
 }
 
 impl PeerState {
-    pub fn new() -> Self {
+    pub fn new(version: u64, challenge: H256) -> Self {
         PeerState {
+            version,
             vote_step: VoteStep::new(0, 0, Step::Propose),
             proposal: None,
             messages: BitSet::new(),
+            capability: None,
+            challenge,
+            peer_challenge: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PeerCapability {
+    /// The public key that the peer signs the votes with.
+    pub signer: Option<Public>,
+    /// The peer relays the votes even if it is not a validator.
+    pub full_gossip: bool,
+}
+
+/// Counts how often the body of a committed block had to be requested from the peers.
+/// The worker and the network extension share it.
+#[derive(Default)]
+pub struct CommitRecoveryCounters {
+    requested: AtomicUsize,
+    retried: AtomicUsize,
+    fell_back: AtomicUsize,
+    recovered: AtomicUsize,
+}
+
+impl CommitRecoveryCounters {
+    pub fn on_requested(&self) {
+        self.requested.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn on_retried(&self) {
+        self.retried.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn on_fell_back(&self) {
+        self.fell_back.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn on_recovered(&self) {
+        self.recovered.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn stats(&self) -> CommitRecoveryStats {
+        CommitRecoveryStats {
+            requested: self.requested.load(Ordering::SeqCst),
+            retried: self.retried.load(Ordering::SeqCst),
+            fell_back: self.fell_back.load(Ordering::SeqCst),
+            recovered: self.recovered.load(Ordering::SeqCst),
+        }
+    }
+}
+
+/// Counts how the requests of the missing votes were answered.
+/// The network extension updates it.
+#[derive(Default)]
+pub struct VoteRequestCounters {
+    split_responses: AtomicUsize,
+    response_batches: AtomicUsize,
+    re_requested: AtomicUsize,
+}
+
+impl VoteRequestCounters {
+    pub fn on_split_response(&self, batches: usize) {
+        self.split_responses.fetch_add(1, Ordering::SeqCst);
+        self.response_batches.fetch_add(batches, Ordering::SeqCst);
+    }
+
+    pub fn on_re_requested(&self) {
+        self.re_requested.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn stats(&self) -> VoteRequestStats {
+        VoteRequestStats {
+            split_responses: self.split_responses.load(Ordering::SeqCst),
+            response_batches: self.response_batches.load(Ordering::SeqCst),
+            re_requested: self.re_requested.load(Ordering::SeqCst),
         }
     }
 }
 
+/// Counts the invalid precommit signatures found in the received seals, by the signer index.
+/// The worker updates it.
+#[derive(Default)]
+pub struct BadSealSignatureCounters {
+    counts: Mutex<BTreeMap<usize, usize>>,
+}
+
+impl BadSealSignatureCounters {
+    pub fn on_bad_signature(&self, signer_index: usize) {
+        *self.counts.lock().entry(signer_index).or_default() += 1;
+    }
+
+    pub fn stats(&self) -> BTreeMap<usize, usize> {
+        self.counts.lock().clone()
+    }
+}
+
+/// The precommits in the seal.
+#[derive(Debug, PartialEq)]
+pub enum SealSignatures {
+    /// The signature of each signer.
+    Separate(Vec<(usize, SchnorrSignature)>),
+    /// The signers and the aggregate of their signatures.
+    Aggregated(Vec<usize>, SchnorrAggregate),
+}
+
 pub struct TendermintSealView<'a> {
     seal: &'a [Bytes],
 }
@@ -186,41 +316,86 @@ impl<'a> TendermintSealView<'a> {
     }
 
     pub fn previous_block_view(&self) -> Result<u64, DecoderError> {
-        let view_rlp =
-            self.seal.get(0).expect("block went through verify_block_basic; block has .seal_fields() fields; qed");
+        let view_rlp = self.seal.get(0).expect("verify_header_with_engine checked the number of the seal fields; qed");
         UntrustedRlp::new(view_rlp.as_slice()).as_val()
     }
 
     pub fn consensus_view(&self) -> Result<u64, DecoderError> {
-        let view_rlp =
-            self.seal.get(1).expect("block went through verify_block_basic; block has .seal_fields() fields; qed");
+        let view_rlp = self.seal.get(1).expect("verify_header_with_engine checked the number of the seal fields; qed");
         UntrustedRlp::new(view_rlp.as_slice()).as_val()
     }
 
     pub fn bitset(&self) -> Result<BitSet, DecoderError> {
-        let view_rlp =
-            self.seal.get(3).expect("block went through verify_block_basic; block has .seal_fields() fields; qed");
+        let view_rlp = self.seal.get(3).expect("verify_header_with_engine checked the number of the seal fields; qed");
         UntrustedRlp::new(view_rlp.as_slice()).as_val()
     }
 
     pub fn precommits(&self) -> UntrustedRlp<'a> {
         UntrustedRlp::new(
-            &self.seal.get(2).expect("block went through verify_block_basic; block has .seal_fields() fields; qed"),
+            &self.seal.get(2).expect("verify_header_with_engine checked the number of the seal fields; qed"),
         )
     }
 
-    pub fn signatures(&self) -> Result<Vec<(usize, SchnorrSignature)>, DecoderError> {
+    /// The separate precommits are a list of signatures, while the aggregate is a list of the nonces and the scalar.
+    pub fn is_aggregated(&self) -> Result<bool, DecoderError> {
         let precommits = self.precommits();
+        Ok(precommits.item_count()? == 2 && precommits.at(0)?.is_list())
+    }
+
+    pub fn aggregate(&self) -> Result<SchnorrAggregate, DecoderError> {
+        self.precommits().as_val()
+    }
+
+    pub fn signature_count(&self) -> Result<usize, DecoderError> {
+        if self.is_aggregated()? {
+            Ok(self.aggregate()?.len())
+        } else {
+            self.precommits().item_count()
+        }
+    }
+
+    pub fn signatures(&self) -> Result<SealSignatures, DecoderError> {
         let bitset = self.bitset()?;
-        debug_assert_eq!(bitset.count(), precommits.item_count()?);
+        debug_assert_eq!(bitset.count(), self.signature_count()?);
 
         let bitset_iter = bitset.true_index_iter();
 
-        let signatures = precommits.iter().map(|rlp| rlp.as_val::<SchnorrSignature>());
+        if self.is_aggregated()? {
+            return Ok(SealSignatures::Aggregated(bitset_iter.collect(), self.aggregate()?))
+        }
+        let signatures = self.precommits().iter().map(|rlp| rlp.as_val::<SchnorrSignature>());
         bitset_iter
             .zip(signatures)
             .map(|(index, signature)| signature.map(|signature| (index, signature)))
             .collect::<Result<_, _>>()
+            .map(SealSignatures::Separate)
+    }
+
+    /// Decodes all the fields without assuming that the block went through the verification.
+    pub fn decode(&self) -> Result<DecodedSeal, String> {
+        if self.seal.len() != SEAL_FIELDS {
+            return Err(format!("The seal must have {} fields, but it has {}", SEAL_FIELDS, self.seal.len()))
+        }
+        let finalized_view =
+            self.previous_block_view().map_err(|err| format!("Cannot decode the finalized view: {}", err))?;
+        let author_view = self.consensus_view().map_err(|err| format!("Cannot decode the author view: {}", err))?;
+        let bitset = self.bitset().map_err(|err| format!("Cannot decode the bitset: {}", err))?;
+        let signature_count = self.signature_count().map_err(|err| format!("Cannot decode the precommits: {}", err))?;
+        let signer_indices: Vec<_> = bitset.true_index_iter().collect();
+        if signer_indices.len() != signature_count {
+            return Err(format!(
+                "The bitset has {} signers, but there are {} signatures",
+                signer_indices.len(),
+                signature_count
+            ))
+        }
+        self.signatures().map_err(|err| format!("Cannot decode the signatures: {}", err))?;
+        Ok(DecodedSeal::Tendermint {
+            finalized_view,
+            author_view,
+            signer_indices,
+            signature_count,
+        })
     }
 }
 
@@ -294,4 +469,12 @@ impl Proposal {
             _ => false,
         }
     }
+
+    /// The size of the block that is kept until the proposal is imported.
+    pub fn retained_bytes(&self) -> usize {
+        match self {
+            Proposal::ProposalReceived(_, block, _) => block.len(),
+            _ => 0,
+        }
+    }
 }
@@ -14,33 +14,48 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::cmp;
 use std::iter::Iterator;
 use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 use std::sync::{Arc, Weak};
+#[cfg(any(test, feature = "fault-injection"))]
+use std::thread;
 use std::thread::{Builder, JoinHandle};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use ccrypto::blake256;
-use ckey::{public_to_address, verify_schnorr, Address, SchnorrSignature};
+use ckey::{
+    aggregate_schnorr, public_to_address, verify_schnorr, verify_schnorr_aggregate, verify_schnorr_batch, Address,
+    Public, SchnorrSignature,
+};
+use cmetrics::{ENGINE_EVENT_CLASSES, METRICS};
 use cnetwork::{EventSender, NodeId};
 use crossbeam_channel as crossbeam;
 use ctypes::transaction::{Action, Transaction};
-use ctypes::util::unexpected::Mismatch;
+use ctypes::util::unexpected::{Mismatch, OutOfBounds};
 use ctypes::{BlockNumber, Header};
 use primitives::{u256_from_u128, Bytes, H256, U256};
+#[cfg(any(test, feature = "fault-injection"))]
+use rlp::RlpStream;
 use rlp::{Encodable, UntrustedRlp};
 
 use super::super::vote_collector::DoubleVote;
 use super::super::BitSet;
-use super::backup::{backup, restore, BackupView};
+use super::backup::{backup, restore, BackupView, Restored};
+use super::clock_skew::{ClockSkewEstimator, WARNING_THRESHOLD};
+use super::engine::{max_proposal_timestamp, proposal_timestamp};
 use super::message::*;
 use super::network;
 use super::params::TimeGapParams;
 use super::stake::CUSTOM_ACTION_HANDLER_ID;
-use super::types::{Height, Proposal, Step, TendermintSealView, TendermintState, TwoThirdsMajority, View};
+use super::types::{
+    BadSealSignatureCounters, CommitRecoveryCounters, Height, Proposal, SealSignatures, Step, TendermintSealView,
+    TendermintState, TwoThirdsMajority, View,
+};
 use super::{
-    BlockHash, ENGINE_TIMEOUT_BROADCAST_STEP_STATE, ENGINE_TIMEOUT_EMPTY_PROPOSAL, ENGINE_TIMEOUT_TOKEN_NONCE_BASE,
-    SEAL_FIELDS,
+    BlockHash, AGGREGATED_SEAL_VERSION, ENGINE_TIMEOUT_BROADCAST_STEP_STATE, ENGINE_TIMEOUT_EMPTY_PROPOSAL,
+    ENGINE_TIMEOUT_TOKEN_NONCE_BASE, MIN_PRECOMMITS_TO_VERIFY_IN_BATCH, SEAL_FIELDS,
 };
 use crate::account_provider::AccountProvider;
 use crate::block::*;
@@ -48,11 +63,14 @@ use crate::client::ConsensusClient;
 use crate::consensus::signer::EngineSigner;
 use crate::consensus::validator_set::{DynamicValidator, ValidatorSet};
 use crate::consensus::vote_collector::{Message, VoteCollector};
-use crate::consensus::{EngineError, Seal};
+#[cfg(any(test, feature = "fault-injection"))]
+use crate::consensus::ConsensusFault;
+use crate::consensus::{
+    ConsensusDegradation, EngineError, ProposalCheckStats, RestoreStatus, Seal, VoteCollectorStats,
+};
 use crate::encoded;
 use crate::error::{BlockError, Error};
 use crate::transaction::{SignedTransaction, UnverifiedTransaction};
-use crate::views::BlockView;
 use crate::BlockId;
 
 type SpawnResult = (
@@ -60,11 +78,59 @@ type SpawnResult = (
     crossbeam::Sender<TimeGapParams>,
     crossbeam::Sender<(crossbeam::Sender<network::Event>, Weak<ConsensusClient>)>,
     crossbeam::Sender<Event>,
+    crossbeam::Sender<Event>,
     crossbeam::Sender<()>,
 );
 
-pub fn spawn(validators: Arc<DynamicValidator>) -> SpawnResult {
-    Worker::spawn(validators)
+/// The capacity of the queue of the events from the network extension.
+/// The extension waits when the queue is full.
+const CONSENSUS_EVENT_QUEUE_SIZE: usize = 1024;
+/// Indices into `ENGINE_EVENT_CLASSES`.
+const CONSENSUS_EVENT: usize = 0;
+const SYNC_EVENT: usize = 1;
+
+/// Returns two event senders. The events of the second one, which the network extension uses,
+/// are handled ahead of the events of the first one, which the client and the sync use.
+#[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
+pub fn spawn(
+    validators: Arc<DynamicValidator>,
+    propose_grace: Duration,
+    restore_observation_window: Duration,
+    escalation_view: View,
+    max_timestamp_step: Option<Duration>,
+    commit_recovery: Arc<CommitRecoveryCounters>,
+    double_votes_found: Arc<AtomicUsize>,
+    bad_seal_signatures: Arc<BadSealSignatureCounters>,
+) -> SpawnResult {
+    Worker::spawn(
+        validators,
+        propose_grace,
+        restore_observation_window,
+        escalation_view,
+        max_timestamp_step,
+        commit_recovery,
+        double_votes_found,
+        bad_seal_signatures,
+    )
+}
+
+/// The engine that quarantined the corrupted backup doesn't know what it signed before the restart.
+struct RestoreObservation {
+    started_at: Instant,
+    /// The highest step that the peers announced within the observation window.
+    observed: Option<VoteStep>,
+}
+
+/// The part of `ConsensusStatus` that the worker owns. It's taken at once to be consistent.
+pub struct WorkerStatus {
+    pub vote_step: VoteStep,
+    pub vote_collector: VoteCollectorStats,
+    pub time_in_step: Duration,
+    pub clock_skew: Option<i64>,
+    pub proposal_checks: ProposalCheckStats,
+    pub retained_proposal_bytes: usize,
+    pub standby: bool,
+    pub degradation: Option<ConsensusDegradation>,
 }
 
 struct Worker {
@@ -75,6 +141,8 @@ struct Worker {
     view: View,
     /// Consensus step.
     step: TendermintState,
+    /// When the current step started.
+    step_started_at: Instant,
     /// Record current round's received votes as bit set
     votes_received: BitSet,
     /// The votes_received field is changed after last state broadcast.
@@ -94,7 +162,36 @@ struct Worker {
     /// Channel to the network extension, must be set later.
     extension: EventSender<network::Event>,
     time_gap_params: TimeGapParams,
+    clock_skew: ClockSkewEstimator,
+    proposal_checks: ProposalCheckStats,
     timeout_token_nonce: usize,
+    /// See `TimeoutParams::propose_grace`.
+    propose_grace: Duration,
+    /// The last (height, view) of which proposal a peer announced to have.
+    proposal_seen_by_peer: Option<(Height, View)>,
+    /// The last (height, view) whose propose timeout was extended.
+    propose_extended: Option<(Height, View)>,
+    /// The committed block whose body is being requested from the peers.
+    pending_committed_block: Option<BlockHash>,
+    commit_recovery: Arc<CommitRecoveryCounters>,
+    double_votes_found: Arc<AtomicUsize>,
+    bad_seal_signatures: Arc<BadSealSignatureCounters>,
+    /// Follows the consensus without signing anything, to stand by as a hot spare of the validator.
+    standby: bool,
+    /// When a vote signed by the engine signer was last received from the other nodes.
+    last_own_vote_observed: Option<Instant>,
+    restore_status: RestoreStatus,
+    /// The engine doesn't sign anything while it's set.
+    restore_observation: Option<RestoreObservation>,
+    /// See `TimeoutParams::restore_observation`.
+    restore_observation_window: Duration,
+    /// See `TimeoutParams::escalation_view`.
+    escalation_view: View,
+    degradation: Option<ConsensusDegradation>,
+    /// See `TimeoutParams::max_timestamp_step`.
+    max_timestamp_step: Option<Duration>,
+    #[cfg(any(test, feature = "fault-injection"))]
+    fault: ConsensusFault,
 }
 
 pub enum Event {
@@ -120,6 +217,10 @@ pub enum Event {
         block_number: Height,
         result: crossbeam::Sender<U256>,
     },
+    ProposalTimestamp {
+        parent_timestamp: u64,
+        result: crossbeam::Sender<u64>,
+    },
     OnTimeout(usize),
     HandleMessages {
         messages: Vec<Vec<u8>>,
@@ -134,6 +235,11 @@ pub enum Event {
         ap: Arc<AccountProvider>,
         address: Address,
     },
+    /// Signs the challenge of a peer to prove the signer advertised to it.
+    SignCapabilityChallenge {
+        challenge: H256,
+        result: crossbeam::Sender<Option<SchnorrSignature>>,
+    },
     AllowedHeight {
         result: crossbeam::Sender<Height>,
     },
@@ -141,8 +247,8 @@ pub enum Event {
     ProposalBlock {
         signature: SchnorrSignature,
         view: View,
-        message: Bytes,
-        result: crossbeam::Sender<Option<Arc<ConsensusClient>>>,
+        block: ProposedBlock,
+        result: crossbeam::Sender<Option<(Arc<ConsensusClient>, Bytes)>>,
     },
     StepState {
         token: NodeId,
@@ -154,6 +260,7 @@ pub enum Event {
     },
     RequestProposal {
         token: NodeId,
+        version: u64,
         height: Height,
         view: View,
         result: crossbeam::Sender<Bytes>,
@@ -163,21 +270,59 @@ pub enum Event {
         requested: BitSet,
         result: crossbeam::Sender<ConsensusMessage>,
     },
+    GetMissingVotes {
+        vote_step: VoteStep,
+        requested: BitSet,
+        result: crossbeam::Sender<BitSet>,
+    },
+    Commit {
+        height: Height,
+        view: View,
+        block_hash: H256,
+        precommits: Vec<SchnorrSignature>,
+        precommit_bitset: BitSet,
+        result: crossbeam::Sender<Result<(), EngineError>>,
+    },
+    ForceNextView {
+        result: crossbeam::Sender<Result<(), EngineError>>,
+    },
+    GetStatus {
+        result: crossbeam::Sender<WorkerStatus>,
+    },
+    DemoteToStandby,
+    PromoteFromStandby {
+        min_quiet_period: Option<Duration>,
+        result: crossbeam::Sender<Result<(), EngineError>>,
+    },
+    GetRestoreStatus {
+        result: crossbeam::Sender<RestoreStatus>,
+    },
+    #[cfg(any(test, feature = "fault-injection"))]
+    SetConsensusFault(ConsensusFault),
 }
 
 impl Worker {
     /// Create a new instance of Tendermint engine
+    #[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
     fn new(
         validators: Arc<DynamicValidator>,
         extension: EventSender<network::Event>,
         client: Weak<ConsensusClient>,
         time_gap_params: TimeGapParams,
+        propose_grace: Duration,
+        restore_observation_window: Duration,
+        escalation_view: View,
+        max_timestamp_step: Option<Duration>,
+        commit_recovery: Arc<CommitRecoveryCounters>,
+        double_votes_found: Arc<AtomicUsize>,
+        bad_seal_signatures: Arc<BadSealSignatureCounters>,
     ) -> Self {
         Worker {
             client,
             height: 1,
             view: 0,
             step: TendermintState::Propose,
+            step_started_at: Instant::now(),
             votes: Default::default(),
             signer: Default::default(),
             last_two_thirds_majority: TwoThirdsMajority::Empty,
@@ -188,12 +333,42 @@ impl Worker {
             votes_received: BitSet::new(),
             votes_received_changed: false,
             time_gap_params,
+            clock_skew: Default::default(),
+            proposal_checks: Default::default(),
             timeout_token_nonce: ENGINE_TIMEOUT_TOKEN_NONCE_BASE,
+            propose_grace,
+            proposal_seen_by_peer: None,
+            propose_extended: None,
+            pending_committed_block: None,
+            commit_recovery,
+            double_votes_found,
+            bad_seal_signatures,
+            standby: false,
+            last_own_vote_observed: None,
+            restore_status: RestoreStatus::NoBackup,
+            restore_observation: None,
+            restore_observation_window,
+            escalation_view,
+            degradation: None,
+            max_timestamp_step,
+            #[cfg(any(test, feature = "fault-injection"))]
+            fault: ConsensusFault::None,
         }
     }
 
-    fn spawn(validators: Arc<DynamicValidator>) -> SpawnResult {
+    #[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
+    fn spawn(
+        validators: Arc<DynamicValidator>,
+        propose_grace: Duration,
+        restore_observation_window: Duration,
+        escalation_view: View,
+        max_timestamp_step: Option<Duration>,
+        commit_recovery: Arc<CommitRecoveryCounters>,
+        double_votes_found: Arc<AtomicUsize>,
+        bad_seal_signatures: Arc<BadSealSignatureCounters>,
+    ) -> SpawnResult {
         let (sender, receiver) = crossbeam::unbounded();
+        let (consensus_sender, consensus_receiver) = crossbeam::bounded(CONSENSUS_EVENT_QUEUE_SIZE);
         let (quit, quit_receiver) = crossbeam::bounded(1);
         let (external_params_initializer, external_params_receiver) = crossbeam::bounded(1);
         let (extension_initializer, extension_receiver) = crossbeam::bounded(1);
@@ -241,127 +416,243 @@ impl Worker {
                 }
                 };
                 validators.register_client(Weak::clone(&client));
-                let mut inner = Self::new(validators, extension, client, time_gap_params);
+                let mut inner = Self::new(
+                    validators,
+                    extension,
+                    client,
+                    time_gap_params,
+                    propose_grace,
+                    restore_observation_window,
+                    escalation_view,
+                    max_timestamp_step,
+                    commit_recovery,
+                    double_votes_found,
+                    bad_seal_signatures,
+                );
                 loop {
-                    crossbeam::select! {
-                    recv(receiver) -> msg => {
-                        match msg {
-                            Ok(Event::NewBlocks {
-                                imported,
-                                enacted,
-                            }) => {
-                                inner.new_blocks(imported, enacted);
-                            }
-                            Ok(Event::GenerateSeal {
-                                block_number,
-                                parent_hash,
-                                result,
-                            }) => {
-                                let seal = inner.generate_seal(block_number, parent_hash);
-                                result.send(seal).unwrap();
-                            }
-                            Ok(Event::ProposalGenerated(sealed)) => {
-                                inner.proposal_generated(&*sealed);
-                            }
-                            Ok(Event::VerifyHeaderBasic{header, result}) => {
-                                result.send(inner.verify_header_basic(&*header)).unwrap();
-                            }
-                            Ok(Event::VerifyBlockExternal{header, result, }) => {
-                                result.send(inner.verify_block_external(&*header)).unwrap();
-                            }
-                            Ok(Event::CalculateScore {
-                                block_number,
-                                result,
-                            }) => {
-                                result.send(inner.calculate_score(block_number)).unwrap();
-                            }
-                            Ok(Event::OnTimeout(token)) => {
-                                inner.on_timeout(token);
-                            }
-                            Ok(Event::HandleMessages {
-                                messages,
-                                result,
-                            }) => {
-                                for message in messages {
-                                    result.send(inner.handle_message(&message, false)).unwrap();
+                    // The consensus events are handled first, so that a sync backlog doesn't delay the votes.
+                    let (class, msg) = match consensus_receiver.try_recv() {
+                        Ok(event) => (CONSENSUS_EVENT, Ok(event)),
+                        Err(_) => crossbeam::select! {
+                            recv(consensus_receiver) -> msg => (CONSENSUS_EVENT, msg),
+                            recv(receiver) -> msg => (SYNC_EVENT, msg),
+                            recv(quit_receiver) -> msg => {
+                                match msg {
+                                    Ok(()) => {},
+                                    Err(crossbeam::RecvError) => {
+                                        cerror!(ENGINE, "The quit channel for tendermint thread had been closed.");
+                                    }
                                 }
-                            }
-                            Ok(Event::IsProposal {
-                                block_number,
-                                block_hash,
-                                result,
-                            }) => {
-                                result.send(inner.is_proposal(block_number, block_hash)).unwrap();
-                            }
-                            Ok(Event::SetSigner {
-                                ap,
-                                address,
-                            }) => {
-                                inner.set_signer(ap, address);
-                            }
-                            Ok(Event::AllowedHeight {
-                                result,
-                            }) => {
-                                let allowed_height = if inner.step.is_commit() {
-                                    inner.height + 1
-                                } else {
-                                    inner.height
-                                };
-                                result.send(allowed_height).unwrap();
-                            }
-                            Ok(Event::Restore(result)) => {
-                                inner.restore();
-                                result.send(()).unwrap();
-                            }
-                            Ok(Event::ProposalBlock {
-                                signature,
-                                view,
-                                message,
-                                result,
-                            }) => {
-                                let client = inner.on_proposal_message(signature, view, message);
-                                result.send(client).unwrap();
-                            }
-                            Ok(Event::StepState {
-                                token, vote_step, proposal, lock_view, known_votes, result
-                            }) => {
-                                inner.on_step_state_message(&token, vote_step, proposal, lock_view, *known_votes, result);
-                            }
-                            Ok(Event::RequestProposal {
-                                token,
-                                height,
-                                view,
-                                result,
-                            }) => {
-                                inner.on_request_proposal_message(&token, height, view, result);
-                            }
-                            Ok(Event::GetAllVotesAndAuthors {
-                                vote_step,
-                                requested,
-                                result,
-                            }) => {
-                                inner.get_all_votes_and_authors(&vote_step, &requested, result);
-                            }
-                            Err(crossbeam::RecvError) => {
-                                cerror!(ENGINE, "The event channel for tendermint thread had been closed.");
+                                inner.flush_backup();
                                 break
                             }
+                        },
+                    };
+                    let event = match msg {
+                        Ok(event) => event,
+                        Err(crossbeam::RecvError) => {
+                            cerror!(
+                                ENGINE,
+                                "The {} event channel for tendermint thread had been closed.",
+                                ENGINE_EVENT_CLASSES[class]
+                            );
+                            break
                         }
-                    }
-                    recv(quit_receiver) -> msg => {
-                        match msg {
-                            Ok(()) => {},
-                            Err(crossbeam::RecvError) => {
-                                cerror!(ENGINE, "The quit channel for tendermint thread had been closed.");
-                            }
-                        }
-                        break
-                    }
-                    }
+                    };
+                    METRICS.engine_queue_depth[CONSENSUS_EVENT].set(consensus_receiver.len());
+                    METRICS.engine_queue_depth[SYNC_EVENT].set(receiver.len());
+                    let started_at = Instant::now();
+                    inner.handle_event(event);
+                    METRICS.engine_event_time[class].observe(started_at.elapsed());
                 }
             })
             .unwrap();
-        (join, external_params_initializer, extension_initializer, sender, quit)
+        (join, external_params_initializer, extension_initializer, sender, consensus_sender, quit)
+    }
+
+    fn handle_event(&mut self, event: Event) {
+        match event {
+            Event::NewBlocks {
+                imported,
+                enacted,
+            } => {
+                self.new_blocks(imported, enacted);
+            }
+            Event::GenerateSeal {
+                block_number,
+                parent_hash,
+                result,
+            } => {
+                let seal = self.generate_seal(block_number, parent_hash);
+                result.send(seal).unwrap();
+            }
+            Event::ProposalGenerated(sealed) => {
+                self.proposal_generated(&*sealed);
+            }
+            Event::VerifyHeaderBasic {
+                header,
+                result,
+            } => {
+                result.send(self.verify_header_basic(&*header)).unwrap();
+            }
+            Event::VerifyBlockExternal {
+                header,
+                result,
+            } => {
+                result.send(self.verify_block_external(&*header)).unwrap();
+            }
+            Event::CalculateScore {
+                block_number,
+                result,
+            } => {
+                result.send(self.calculate_score(block_number)).unwrap();
+            }
+            Event::ProposalTimestamp {
+                parent_timestamp,
+                result,
+            } => {
+                let now = self.local_time() / 1000;
+                result.send(proposal_timestamp(parent_timestamp, now, self.max_timestamp_step)).unwrap();
+            }
+            Event::OnTimeout(token) => {
+                self.on_timeout(token);
+            }
+            Event::HandleMessages {
+                messages,
+                result,
+            } => {
+                for message in messages {
+                    result.send(self.handle_message(&message, false)).unwrap();
+                }
+            }
+            Event::IsProposal {
+                block_number,
+                block_hash,
+                result,
+            } => {
+                result.send(self.is_proposal(block_number, block_hash)).unwrap();
+            }
+            Event::SetSigner {
+                ap,
+                address,
+            } => {
+                self.set_signer(ap, address);
+            }
+            Event::SignCapabilityChallenge {
+                challenge,
+                result,
+            } => {
+                result.send(self.sign(capability_proof_hash(&challenge)).ok()).unwrap();
+            }
+            Event::AllowedHeight {
+                result,
+            } => {
+                let allowed_height = if self.step.is_commit() {
+                    self.height + 1
+                } else {
+                    self.height
+                };
+                result.send(allowed_height).unwrap();
+            }
+            Event::Restore(result) => {
+                self.restore();
+                result.send(()).unwrap();
+            }
+            Event::ProposalBlock {
+                signature,
+                view,
+                block,
+                result,
+            } => {
+                let proposal = self.on_proposal_message(signature, view, block);
+                result.send(proposal).unwrap();
+            }
+            Event::StepState {
+                token,
+                vote_step,
+                proposal,
+                lock_view,
+                known_votes,
+                result,
+            } => {
+                self.on_step_state_message(&token, vote_step, proposal, lock_view, *known_votes, result);
+            }
+            Event::RequestProposal {
+                token,
+                version,
+                height,
+                view,
+                result,
+            } => {
+                self.on_request_proposal_message(&token, version, height, view, result);
+            }
+            Event::GetAllVotesAndAuthors {
+                vote_step,
+                requested,
+                result,
+            } => {
+                self.get_all_votes_and_authors(&vote_step, &requested, result);
+            }
+            Event::GetMissingVotes {
+                vote_step,
+                requested,
+                result,
+            } => {
+                result.send(self.get_missing_votes(&vote_step, &requested)).unwrap();
+            }
+            Event::Commit {
+                height,
+                view,
+                block_hash,
+                precommits,
+                precommit_bitset,
+                result,
+            } => {
+                result.send(self.on_commit_message(height, view, block_hash, precommits, precommit_bitset)).unwrap();
+            }
+            Event::ForceNextView {
+                result,
+            } => {
+                result.send(self.force_next_view()).unwrap();
+            }
+            Event::GetStatus {
+                result,
+            } => {
+                // The requester doesn't wait longer than its timeout.
+                let _ = result.send(self.status());
+            }
+            Event::DemoteToStandby => {
+                self.demote_to_standby();
+            }
+            Event::PromoteFromStandby {
+                min_quiet_period,
+                result,
+            } => {
+                result.send(self.promote_from_standby(min_quiet_period)).unwrap();
+            }
+            Event::GetRestoreStatus {
+                result,
+            } => {
+                result.send(self.restore_status).unwrap();
+            }
+            #[cfg(any(test, feature = "fault-injection"))]
+            Event::SetConsensusFault(fault) => {
+                self.fault = fault;
+            }
+        }
+    }
+
+    fn status(&self) -> WorkerStatus {
+        WorkerStatus {
+            vote_step: self.vote_step(),
+            vote_collector: self.votes.stats(),
+            time_in_step: self.step_started_at.elapsed(),
+            clock_skew: self.clock_skew.estimate(self.local_time()),
+            proposal_checks: self.proposal_checks,
+            retained_proposal_bytes: self.retained_proposal_bytes(),
+            standby: self.standby,
+            degradation: self.degradation.clone(),
+        }
     }
 
     /// The client is a thread-safe struct. Using it in multi-threads is safe.
@@ -470,6 +761,23 @@ impl Worker {
         }
     }
 
+    /// Returns the requested votes that haven't arrived yet.
+    /// The votes of the other heights are not requested again.
+    pub fn get_missing_votes(&self, vote_step: &VoteStep, requested: &BitSet) -> BitSet {
+        if vote_step.height != self.height {
+            return BitSet::new()
+        }
+        let validator_count = self.validators.count(&self.prev_block_hash());
+        let mut missing = BitSet::new();
+        for index in requested.true_index_iter().take_while(|index| *index < validator_count) {
+            missing.set(index);
+        }
+        for (index, _) in self.votes.get_all_votes_and_indices_in_round(vote_step) {
+            missing.reset(index);
+        }
+        missing
+    }
+
     /// Check if address is a proposer for given view.
     fn check_view_proposer(
         &self,
@@ -493,6 +801,9 @@ impl Worker {
 
     /// Check if current signer is the current proposer.
     fn is_signer_proposer(&self, bh: &H256) -> bool {
+        if self.standby || self.restore_observation.is_some() {
+            return false
+        }
         self.view_proposer(bh, self.view).map_or(false, |proposer| self.signer.is_address(&proposer))
     }
 
@@ -538,6 +849,21 @@ impl Worker {
             .unwrap();
     }
 
+    /// Lets the extension know whom to gossip the votes of the current height to.
+    fn announce_validators(&self) {
+        // Nothing to announce until the parent block is imported.
+        let parent_hash = match self.prev_block_header_of_height(self.height) {
+            Some(header) => header.hash(),
+            None => return,
+        };
+        let validators = self.validators.weighted_validators(&parent_hash).into_iter().map(|v| v.pubkey).collect();
+        self.extension
+            .send(network::Event::SetValidators {
+                validators,
+            })
+            .unwrap();
+    }
+
     fn broadcast_state(&self, vote_step: VoteStep, proposal: Option<H256>, lock_view: Option<View>, votes: BitSet) {
         self.extension
             .send(network::Event::BroadcastState {
@@ -554,6 +880,21 @@ impl Worker {
             .send(network::Event::RequestMessagesToAll {
                 vote_step,
                 requested_votes,
+                every_peer: self.degradation.is_some(),
+            })
+            .unwrap();
+    }
+
+    fn broadcast_commit(&self, view: View, block_hash: H256) {
+        let (precommits, precommit_indices) =
+            self.votes.round_signatures_and_indices(&VoteStep::new(self.height, view, Step::Precommit), &block_hash);
+        self.extension
+            .send(network::Event::BroadcastCommit {
+                height: self.height,
+                view,
+                block_hash,
+                precommits,
+                precommit_bitset: BitSet::new_with_indices(&precommit_indices),
             })
             .unwrap();
     }
@@ -567,6 +908,34 @@ impl Worker {
             .unwrap();
     }
 
+    /// Requests the body of the committed block, which was lost or never received.
+    fn request_committed_block(&mut self, view: View, block_hash: BlockHash) {
+        self.pending_committed_block = Some(block_hash);
+        self.commit_recovery.on_requested();
+        self.extension
+            .send(network::Event::RequestCommittedProposal {
+                height: self.height,
+                view,
+                block_hash,
+            })
+            .unwrap();
+    }
+
+    fn cancel_committed_block_request(&mut self) {
+        if self.pending_committed_block.take().is_some() {
+            self.extension.send(network::Event::CancelCommittedProposalRequest).unwrap();
+        }
+    }
+
+    fn on_committed_block_imported(&mut self, block_hash: BlockHash) {
+        cinfo!(ENGINE, "The committed block {} is imported", block_hash);
+        self.cancel_committed_block_request();
+        self.commit_recovery.on_recovered();
+        if self.step.is_commit() {
+            self.client().update_best_as_committed(block_hash);
+        }
+    }
+
     fn update_sealing(&self, parent_block_hash: H256) {
         self.client().update_sealing(BlockId::Hash(parent_block_hash), true);
     }
@@ -577,23 +946,115 @@ impl Worker {
 
     fn increment_view(&mut self, n: View) {
         cinfo!(ENGINE, "increment_view: New view.");
+        let ended_view = self.view;
         self.view += n;
         self.proposal = Proposal::None;
         self.votes_received = BitSet::new();
+        self.throw_out_old_views();
+        if self.view > self.escalation_view {
+            self.escalate(ended_view);
+        }
+    }
+
+    /// Logs who didn't vote in the ended view. The votes are requested to all peers until the height is committed.
+    fn escalate(&mut self, ended_view: View) {
+        let missing_voters = |step| -> Vec<usize> {
+            let vote_step = VoteStep::new(self.height, ended_view, step);
+            self.get_missing_votes(&vote_step, &BitSet::all_set()).true_index_iter().collect()
+        };
+        let degradation = ConsensusDegradation {
+            height: self.height,
+            since_view: self.degradation.as_ref().map_or(self.view, |degradation| degradation.since_view),
+            missing_prevotes: missing_voters(Step::Prevote),
+            missing_precommits: missing_voters(Step::Precommit),
+        };
+        cwarn!(
+            ENGINE,
+            {
+                height: degradation.height,
+                view: self.view,
+                since_view: degradation.since_view,
+                missing_prevotes: format!("{:?}", degradation.missing_prevotes),
+                missing_precommits: format!("{:?}", degradation.missing_precommits)
+            },
+            "Consensus degraded: height {} is not committed until view {}",
+            degradation.height,
+            self.view
+        );
+        self.degradation = Some(degradation);
+    }
+
+    /// Throws out the votes of the views before the previous view at this height.
+    /// The proposal and the prevotes of the view of the last two-thirds majority are kept to serve its proof.
+    fn throw_out_old_views(&self) {
+        if self.view < 2 {
+            return
+        }
+        let keep = match self.last_two_thirds_majority.view() {
+            Some(view) => {
+                vec![VoteStep::new(self.height, view, Step::Propose), VoteStep::new(self.height, view, Step::Prevote)]
+            }
+            None => Vec::new(),
+        };
+        self.votes.throw_out_range(
+            &VoteStep::new(self.height, 0, Step::Propose),
+            &VoteStep::new(self.height, self.view - 1, Step::Propose),
+            &keep,
+        );
     }
 
     fn move_to_height(&mut self, height: Height) {
         assert!(height > self.height, "{} < {}", height, self.height);
-        cinfo!(ENGINE, "Transitioning to height {}.", height);
+        cinfo!(ENGINE, { height: height }, "Transitioning to height {}.", height);
+        if let Some(degradation) = self.degradation.take() {
+            cinfo!(
+                ENGINE,
+                "Consensus recovered: height {} is committed after escalating since view {}",
+                degradation.height,
+                degradation.since_view
+            );
+        }
+        self.cancel_committed_block_request();
+        self.clear_proposals();
         self.last_two_thirds_majority = TwoThirdsMajority::Empty;
         self.height = height;
         self.view = 0;
-        self.proposal = Proposal::None;
         self.votes_received = BitSet::new();
+        self.announce_validators();
+        self.check_restore_observation();
+    }
+
+    /// Drops the proposals of the previous height as soon as the height advances,
+    /// rather than keeping their blocks until the next proposal replaces them.
+    fn clear_proposals(&mut self) {
+        self.proposal = Proposal::None;
+        // The step is set to the new height's step right after this, but it must not hold the old block until then.
+        match self.step {
+            TendermintState::ProposeWaitBlockGeneration {
+                ..
+            }
+            | TendermintState::ProposeWaitImported {
+                ..
+            }
+            | TendermintState::ProposeWaitEmptyBlockTimer {
+                ..
+            } => self.step = TendermintState::Propose,
+            _ => {}
+        }
+        // The proposals of the previous height are never imported as the best block.
+        self.client().discard_executed_proposals(None);
+    }
+
+    fn retained_proposal_bytes(&self) -> usize {
+        self.proposal.retained_bytes() + self.step.proposal_block().map_or(0, |block| block.rlp_bytes().len())
     }
 
     fn move_to_step(&mut self, step: Step, is_restoring: bool) {
         let prev_step = mem::replace(&mut self.step, step.into());
+        if prev_step.to_step() != step {
+            self.step_started_at = Instant::now();
+        }
+        METRICS.set_consensus_state(self.height as usize, self.view as usize, step.number() as usize);
         if !is_restoring {
             self.backup();
         }
@@ -640,12 +1101,20 @@ impl Worker {
                 } else {
                     let parent_block_hash = self.prev_block_hash();
                     if self.is_signer_proposer(&parent_block_hash) {
-                        if let TwoThirdsMajority::Lock(lock_view, _) = self.last_two_thirds_majority {
+                        if self.client().is_chain_frozen() {
+                            cinfo!(ENGINE, "I am a proposer, but the chain is frozen");
+                        } else if let TwoThirdsMajority::Lock(lock_view, _) = self.last_two_thirds_majority {
                             cinfo!(ENGINE, "I am a proposer, I'll re-propose a locked block");
                             match self.locked_proposal_block(lock_view) {
                                 Ok(block) => self.repropose_block(block),
                                 Err(error_msg) => cwarn!(ENGINE, "{}", error_msg),
                             }
+                        } else if let Some(skew) = self.excessive_clock_skew() {
+                            // The timestamp of the new block would be skewed too.
+                            cwarn!(ENGINE, "I am a proposer, but my clock is skewed by {}ms. Skip proposing", skew);
+                        } else if !self.client().state_warming_status().finished {
+                            // The block would be created too slowly with the empty state cache.
+                            cwarn!(ENGINE, "I am a proposer, but the state cache is not warmed yet. Skip proposing");
                         } else {
                             cinfo!(ENGINE, "I am a proposer, I'll create a block");
                             self.update_sealing(parent_block_hash);
@@ -681,6 +1150,12 @@ impl Worker {
                         self.is_generation_time_relevant(&block.decode_header())
                     });
                     self.generate_and_broadcast_message(block_hash, is_restoring);
+                    #[cfg(any(test, feature = "fault-injection"))]
+                    {
+                        if self.fault == ConsensusFault::DoublePrevote && !is_restoring {
+                            self.broadcast_conflicting_prevote(block_hash);
+                        }
+                    }
                 }
             }
             Step::Precommit => {
@@ -701,6 +1176,13 @@ impl Worker {
                             }
                         }
                     };
+                    #[cfg(any(test, feature = "fault-injection"))]
+                    {
+                        if self.fault == ConsensusFault::WithholdPrecommit {
+                            cwarn!(ENGINE, "Withhold the precommit on {:?}", block_hash);
+                            return
+                        }
+                    }
                     self.generate_and_broadcast_message(block_hash, is_restoring);
                 }
             }
@@ -714,8 +1196,22 @@ impl Worker {
         let acceptable_past_gap = self.time_gap_params.allowed_past_gap;
         let acceptable_future_gap = self.time_gap_params.allowed_future_gap;
         let now = SystemTime::now();
-        let allowed_min = now - acceptable_past_gap;
+        let mut allowed_min = now - acceptable_past_gap;
         let allowed_max = now + acceptable_future_gap;
+        if let Some(max_timestamp_step) = self.max_timestamp_step {
+            let parent_timestamp = match self.client().block_header(&(*block_header.parent_hash()).into()) {
+                Some(parent) => parent.timestamp(),
+                None => return false,
+            };
+            let max_timestamp = max_proposal_timestamp(parent_timestamp, max_timestamp_step);
+            if block_header.timestamp() > max_timestamp {
+                return false
+            }
+            // After a long stall, the proposers catch up with the clock by the step.
+            if let Some(max_time) = UNIX_EPOCH.checked_add(Duration::from_secs(max_timestamp)) {
+                allowed_min = cmp::min(allowed_min, max_time);
+            }
+        }
         let block_generation_time = UNIX_EPOCH.checked_add(Duration::from_secs(block_header.timestamp()));
 
         match block_generation_time {
@@ -725,6 +1221,23 @@ impl Worker {
         }
     }
 
+    /// The local time in milliseconds, which is compared with the other validators' clocks.
+    fn local_time(&self) -> u64 {
+        let now = SystemTime::now();
+        #[cfg(any(test, feature = "fault-injection"))]
+        let now = match self.fault {
+            ConsensusFault::SkewedClock(skew) => now + skew,
+            _ => now,
+        };
+        now.duration_since(UNIX_EPOCH).expect("The system time is after the UNIX epoch").as_millis() as u64
+    }
+
+    /// Returns the estimated clock skew if it exceeds the configured limit.
+    fn excessive_clock_skew(&self) -> Option<i64> {
+        let max_clock_skew = self.time_gap_params.max_clock_skew?;
+        self.clock_skew.estimate(self.local_time()).filter(|skew| skew.abs() as u128 > max_clock_skew.as_millis())
+    }
+
     fn locked_proposal_block(&self, locked_view: View) -> Result<encoded::Block, String> {
         let vote_step = VoteStep::new(self.height, locked_view, Step::Propose);
         let locked_proposal_hash = self.votes.get_block_hashes(&vote_step).first().cloned();
@@ -772,6 +1285,14 @@ impl Worker {
             ctrace!(ENGINE, "No message, since there is no engine signer.");
             None
         })?;
+        if self.standby {
+            ctrace!(ENGINE, "No message, since the engine is standing by.");
+            return None
+        }
+        if self.restore_observation.is_some() {
+            ctrace!(ENGINE, "No message, since the engine is observing the network after the restart.");
+            return None
+        }
         let signature = self
             .sign(blake256(&vote_info))
             .map_err(|error| {
@@ -816,6 +1337,9 @@ impl Worker {
             );
             self.last_two_thirds_majority =
                 TwoThirdsMajority::from_message(message.on.step.view, message.on.block_hash);
+            // Only the locked proposal can be committed at this height.
+            self.client().discard_executed_proposals(message.on.block_hash);
+            self.throw_out_old_views();
         }
         // Check if it can affect the step transition.
         if self.is_step(message) {
@@ -826,18 +1350,21 @@ impl Worker {
                 }
                 TendermintState::Precommit if has_enough_aligned_votes => {
                     let bh = message.on.block_hash.expect("previous guard ensures is_some; qed");
-                    if self.client().block(&BlockId::Hash(bh)).is_some() {
-                        // Commit the block, and update the last confirmed view
-                        self.save_last_confirmed_view(message.on.step.view);
+                    // Commit the block, and update the last confirmed view
+                    self.save_last_confirmed_view(message.on.step.view);
 
+                    if self.client().block(&BlockId::Hash(bh)).is_some() {
                         // Update the best block hash as the hash of the committed block
                         self.client().update_best_as_committed(bh);
-                        Some(Step::Commit)
                     } else {
-                        cwarn!(ENGINE, "Cannot find a proposal which committed");
-                        self.increment_view(1);
-                        Some(Step::Propose)
+                        // The best block is updated when the requested block is imported.
+                        cwarn!(ENGINE, "Cannot find a proposal which committed, request {} to the peers", bh);
+                        self.request_committed_block(message.on.step.view, bh);
+                    }
+                    if !is_restoring {
+                        self.broadcast_commit(message.on.step.view, bh);
                     }
+                    Some(Step::Commit)
                 }
                 // Avoid counting votes twice.
                 TendermintState::Prevote if lock_change => Some(Step::Precommit),
@@ -869,6 +1396,11 @@ impl Worker {
         if proposal.number() < 1 {
             return
         }
+        if proposal.number() < self.height {
+            // The proposals of the previous heights are cleared, and their imports must not bring them back.
+            ctrace!(ENGINE, "The proposal of the previous height {} is imported", proposal.number());
+            return
+        }
 
         let height = proposal.number() as Height;
         let seal_view = TendermintSealView::new(proposal.seal());
@@ -877,24 +1409,31 @@ impl Worker {
             step: VoteStep::new(height - 1, prev_block_view, Step::Precommit),
             block_hash: Some(*proposal.parent_hash()),
         };
-        for (index, signature) in seal_view.signatures().expect("The proposal is verified") {
-            let message = ConsensusMessage {
-                signature,
-                signer_index: index,
-                on: on.clone(),
-            };
-            if !self.votes.is_old_or_known(&message) {
-                self.votes.vote(message);
-            }
-        }
-
-        // Since the votes needs at least one vote to check the old votes,
-        // we should remove old votes after inserting current votes.
-        self.votes.throw_out_old(&VoteStep {
+        let oldest = VoteStep {
             height: proposal.number() - 1,
             view: 0,
             step: Step::Propose,
-        });
+        };
+        match seal_view.signatures().expect("The proposal is verified") {
+            SealSignatures::Separate(signatures) => {
+                for (index, signature) in signatures {
+                    let message = ConsensusMessage {
+                        signature,
+                        signer_index: index,
+                        on: on.clone(),
+                    };
+                    if !self.votes.is_old_or_known(&message) {
+                        self.votes.vote(message);
+                    }
+                }
+
+                // Since the votes needs at least one vote to check the old votes,
+                // we should remove old votes after inserting current votes.
+                self.votes.throw_out_old(&oldest);
+            }
+            // The signatures cannot be restored from the aggregate, so only the received precommits remain.
+            SealSignatures::Aggregated(..) => self.votes.throw_out_older_than(&oldest),
+        }
 
         let current_height = self.height;
         let vote_step = VoteStep::new(self.height, self.view, self.step.to_step());
@@ -949,49 +1488,135 @@ impl Worker {
     }
 
     fn submit_proposal_block(&mut self, sealed_block: &SealedBlock) {
-        cinfo!(ENGINE, "Submitting proposal block {}", sealed_block.header().hash());
+        cinfo!(
+            ENGINE,
+            { height: sealed_block.header().number(), hash: sealed_block.header().hash() },
+            "Submitting proposal block {}",
+            sealed_block.header().hash()
+        );
         self.move_to_step(Step::Prevote, false);
         self.broadcast_proposal_block(self.view, encoded::Block::new(sealed_block.rlp_bytes()));
     }
 
     fn backup(&self) {
+        let quarantined = match self.restore_status {
+            RestoreStatus::Quarantined {
+                hash,
+                observing: true,
+            } => Some(hash),
+            _ => None,
+        };
         backup(self.client().get_kvdb().as_ref(), BackupView {
             height: &self.height,
             view: &self.view,
             step: &self.step.to_step(),
             votes: &self.votes.get_all(),
             last_confirmed_view: &self.last_confirmed_view,
+            quarantined: &quarantined,
         });
     }
 
+    /// Persist the current state before the worker quits so that the node can rejoin the height after restarting.
+    fn flush_backup(&self) {
+        if self.client.upgrade().is_none() {
+            cwarn!(ENGINE, "Cannot flush the backup because the client is already dropped");
+            return
+        }
+        cinfo!(ENGINE, "Flush the backup at {}-{}-{:?}", self.height, self.view, self.step.to_step());
+        self.backup();
+    }
+
     fn restore(&mut self) {
         let client = self.client();
-        let backup = restore(client.get_kvdb().as_ref());
-        if let Some(backup) = backup {
-            let backup_step = if backup.step == Step::Commit {
-                // If the backuped step is `Commit`, we should start at `Precommit` to update the
-                // chain's best block safely.
-                Step::Precommit
-            } else {
-                backup.step
-            };
-            self.step = backup_step.into();
-            self.height = backup.height;
-            self.view = backup.view;
-            self.last_confirmed_view = backup.last_confirmed_view;
-            if let Some(proposal) = backup.proposal {
-                if client.block(&BlockId::Hash(proposal)).is_some() {
-                    self.proposal = Proposal::ProposalImported(proposal);
-                }
+        let backup = match restore(client.get_kvdb().as_ref()) {
+            Restored::Backup(backup) => backup,
+            Restored::Quarantined(hash) => {
+                self.restore_from_best_block(hash);
+                return
             }
+            Restored::Nothing => return,
+        };
+        let backup_step = if backup.step == Step::Commit {
+            // If the backuped step is `Commit`, we should start at `Precommit` to update the
+            // chain's best block safely.
+            Step::Precommit
+        } else {
+            backup.step
+        };
+        self.step = backup_step.into();
+        self.height = backup.height;
+        self.view = backup.view;
+        self.last_confirmed_view = backup.last_confirmed_view;
+        match backup.quarantined {
+            // The engine backed up while observing the network, so it may have signed anything at this height.
+            Some(hash) => self.start_restore_observation(hash),
+            None => self.restore_status = RestoreStatus::Clean,
+        }
+        if let Some(proposal) = backup.proposal {
+            if client.block(&BlockId::Hash(proposal)).is_some() {
+                self.proposal = Proposal::ProposalImported(proposal);
+            }
+        }
 
-            for vote in backup.votes {
-                let bytes = rlp::encode(&vote);
-                if let Err(err) = self.handle_message(&bytes, true) {
-                    cinfo!(ENGINE, "Fail to load backuped message {:?}", err);
-                }
+        for vote in backup.votes {
+            let bytes = rlp::encode(&vote);
+            if let Err(err) = self.handle_message(&bytes, true) {
+                cinfo!(ENGINE, "Fail to load backuped message {:?}", err);
             }
         }
+        self.announce_validators();
+    }
+
+    /// Starts at the height after the best block, since the backed-up state is lost.
+    /// The engine may have signed anything at that height before the restart, so it doesn't sign
+    /// until it moves past the height that the network was at.
+    fn restore_from_best_block(&mut self, quarantined: H256) {
+        let best_header = self.client().best_block_header();
+        self.height = best_header.number() + 1;
+        self.view = 0;
+        self.step = TendermintState::Propose;
+        self.last_confirmed_view = TendermintSealView::new(&best_header.seal()).consensus_view().unwrap_or(0);
+        self.start_restore_observation(quarantined);
+        self.announce_validators();
+    }
+
+    fn start_restore_observation(&mut self, quarantined: H256) {
+        self.restore_status = RestoreStatus::Quarantined {
+            hash: quarantined,
+            observing: true,
+        };
+        self.restore_observation = Some(RestoreObservation {
+            started_at: Instant::now(),
+            observed: None,
+        });
+        cwarn!(
+            ENGINE,
+            "The consensus backup is quarantined. Start at {} without signing for at least {:?}",
+            self.height,
+            self.restore_observation_window
+        );
+    }
+
+    fn check_restore_observation(&mut self) {
+        let finished = match &self.restore_observation {
+            Some(RestoreObservation {
+                started_at,
+                observed: Some(observed),
+            }) => started_at.elapsed() >= self.restore_observation_window && self.height > observed.height,
+            _ => false,
+        };
+        if !finished {
+            return
+        }
+        self.restore_observation = None;
+        if let RestoreStatus::Quarantined {
+            observing,
+            ..
+        } = &mut self.restore_status
+        {
+            *observing = false;
+        }
+        cwarn!(ENGINE, "Start signing at {}-{} after observing the network", self.height, self.view);
     }
 
     fn seal_fields(&self) -> usize {
@@ -1003,6 +1628,18 @@ impl Worker {
         if height < self.height {
             return Seal::None
         }
+        if self.client().is_chain_frozen() {
+            cinfo!(ENGINE, "The chain is frozen, decline to seal the block {}", height);
+            return Seal::None
+        }
+        if self.standby {
+            cinfo!(ENGINE, "The engine is demoted to the standby mode, decline to seal the block {}", height);
+            return Seal::None
+        }
+        if self.restore_observation.is_some() {
+            cinfo!(ENGINE, "The engine is observing the network, decline to seal the block {}", height);
+            return Seal::None
+        }
 
         assert!(self.is_signer_proposer(&parent_hash));
         assert_eq!(Proposal::None, self.proposal);
@@ -1018,6 +1655,30 @@ impl Worker {
             .round_signatures_and_indices(&VoteStep::new(height - 1, *last_block_view, Step::Precommit), &parent_hash);
         ctrace!(ENGINE, "Collected seal: {:?}({:?})", precommits, precommit_indices);
         let precommit_bitset = BitSet::new_with_indices(&precommit_indices);
+        if self.aggregates_precommits(&parent_hash) == Some(true) {
+            let grand_parent_hash =
+                self.client().block_header(&parent_hash.into()).expect("The parent block must exist").parent_hash();
+            let signed: Vec<_> = precommit_indices
+                .iter()
+                .map(|index| self.validators.get(&grand_parent_hash, *index))
+                .zip(precommits)
+                .collect();
+            let precommit_hash =
+                message_hash(VoteStep::new(height - 1, *last_block_view, Step::Precommit), parent_hash);
+            let precommits = match aggregate_schnorr(&signed, &precommit_hash) {
+                Ok(precommits) => precommits,
+                Err(err) => {
+                    cwarn!(ENGINE, "Cannot aggregate the precommits of the block {}: {:?}", height - 1, err);
+                    return Seal::None
+                }
+            };
+            return Seal::AggregatedTendermint {
+                prev_view: *last_block_view,
+                cur_view: view,
+                precommits,
+                precommit_bitset,
+            }
+        }
         Seal::Tendermint {
             prev_view: *last_block_view,
             cur_view: view,
@@ -1026,27 +1687,46 @@ impl Worker {
         }
     }
 
+    /// Returns whether the seals of the children of the given block aggregate the precommits.
+    /// It returns None if the state of the block is not available.
+    fn aggregates_precommits(&self, parent_hash: &H256) -> Option<bool> {
+        let params = self.client().common_params(BlockId::Hash(*parent_hash))?;
+        Some(params.seal_version() >= AGGREGATED_SEAL_VERSION)
+    }
+
     fn proposal_generated(&mut self, sealed_block: &SealedBlock) {
         let header = sealed_block.header();
         let hash = header.hash();
         let parent_hash = header.parent_hash();
+        if self.standby {
+            cinfo!(ENGINE, "Discard the proposal {} generated before the demotion to the standby mode", hash);
+            return
+        }
+        if self.restore_observation.is_some() {
+            cinfo!(ENGINE, "Discard the proposal {} generated while observing the network", hash);
+            return
+        }
 
-        if let TendermintState::ProposeWaitBlockGeneration {
-            parent_hash: expected_parent_hash,
-        } = self.step
-        {
-            assert_eq!(
-                *parent_hash, expected_parent_hash,
-                "Generated hash({:?}) is different from expected({:?})",
-                parent_hash, expected_parent_hash
-            );
-        } else {
-            panic!("Block is generated at unexpected step {:?}", self.step);
+        let is_current =
+            header.number() == self.height && TendermintSealView::new(header.seal()).consensus_view() == Ok(self.view);
+        match self.step {
+            TendermintState::ProposeWaitBlockGeneration {
+                parent_hash: expected_parent_hash,
+            } if is_current => {
+                assert_eq!(
+                    *parent_hash, expected_parent_hash,
+                    "Generated hash({:?}) is different from expected({:?})",
+                    parent_hash, expected_parent_hash
+                );
+            }
+            _ => {
+                // The height or the view moved on while the block was being generated.
+                cdebug!(ENGINE, "Discard the stale proposal {} generated at height {}", hash, header.number());
+                return
+            }
         }
         let prev_proposer_idx = self.block_proposer_idx(*parent_hash).expect("Prev block must exists");
 
-        debug_assert_eq!(Ok(self.view), TendermintSealView::new(header.seal()).consensus_view());
-
         let vote_step = VoteStep::new(header.number() as Height, self.view, Step::Propose);
         let vote_info = message_info_rlp(vote_step, Some(hash));
         let signature = self.sign(blake256(&vote_info)).expect("I am proposer");
@@ -1083,6 +1763,17 @@ impl Worker {
             .into())
         }
 
+        // The proposer's clock, which sets the timestamp, must not be ahead of the local clock.
+        let max_timestamp = (self.local_time() + self.time_gap_params.max_future_drift.as_millis() as u64) / 1000;
+        if header.timestamp() > max_timestamp {
+            return Err(BlockError::TemporarilyInvalid(OutOfBounds {
+                min: None,
+                max: Some(max_timestamp),
+                found: header.timestamp(),
+            })
+            .into())
+        }
+
         Ok(())
     }
 
@@ -1096,8 +1787,30 @@ impl Worker {
         }
         self.check_view_proposer(header.parent_hash(), header.number(), view, &proposer)?;
         let seal_view = TendermintSealView::new(header.seal());
+        let aggregated = seal_view.is_aggregated()?;
+        // The format cannot be trusted without the params, because a forged seal can choose either of them.
+        let expected = match self.aggregates_precommits(header.parent_hash()) {
+            Some(expected) => expected,
+            None => {
+                cwarn!(
+                    ENGINE,
+                    "verify_block_external: The header({})'s seal format is unknown without the params of the parent",
+                    header.hash()
+                );
+                return Err(BlockError::InvalidSeal.into())
+            }
+        };
+        if aggregated != expected {
+            cwarn!(
+                ENGINE,
+                "verify_block_external: The header({})'s precommits are in the wrong format, aggregated: {}",
+                header.hash(),
+                aggregated
+            );
+            return Err(BlockError::InvalidSeal.into())
+        }
         let bitset_count = seal_view.bitset()?.count();
-        let precommits_count = seal_view.precommits().item_count()?;
+        let precommits_count = seal_view.signature_count()?;
 
         if bitset_count < precommits_count {
             cwarn!(
@@ -1127,15 +1840,47 @@ impl Worker {
             .block_header(&(*header.parent_hash()).into())
             .expect("The parent block must exist")
             .parent_hash();
-        for (bitset_index, signature) in seal_view.signatures()? {
-            let public = self.validators.get(&grand_parent_hash, bitset_index);
-            if !verify_schnorr(&public, &signature, &precommit_hash)? {
-                let address = public_to_address(&public);
-                return Err(EngineError::BlockNotAuthorized(address.to_owned()).into())
+        let verification_started_at = Instant::now();
+        let signatures = match seal_view.signatures()? {
+            SealSignatures::Separate(signatures) => signatures,
+            SealSignatures::Aggregated(signers, aggregate) => {
+                let publics: Vec<Public> =
+                    signers.iter().map(|index| self.validators.get(&grand_parent_hash, *index)).collect();
+                if !verify_schnorr_aggregate(&publics, &aggregate, &precommit_hash)? {
+                    cwarn!(
+                        ENGINE,
+                        "verify_block_external: The header({})'s aggregated precommits are invalid",
+                        header.hash()
+                    );
+                    return Err(BlockError::InvalidSeal.into())
+                }
+                for index in signers {
+                    assert!(!voted_validators.is_set(index), "Double vote");
+                    voted_validators.set(index);
+                }
+                Vec::new()
+            }
+        };
+        if let Some(position) = self.find_invalid_precommit(&grand_parent_hash, &signatures, &precommit_hash) {
+            let signer_index = signatures[position].0;
+            self.bad_seal_signatures.on_bad_signature(signer_index);
+            cwarn!(
+                ENGINE,
+                "verify_block_external: The precommit of the {}th validator in the header({})'s seal is invalid",
+                signer_index,
+                header.hash()
+            );
+            return Err(EngineError::InvalidSealSignature {
+                signer_index,
+                block_hash: header.hash(),
             }
+            .into())
+        }
+        for (bitset_index, _) in signatures {
             assert!(!voted_validators.is_set(bitset_index), "Double vote");
             voted_validators.set(bitset_index);
         }
+        METRICS.signature_verification_time.observe(verification_started_at.elapsed());
 
         // Genesisblock does not have signatures
         if header.number() == 1 {
@@ -1145,6 +1890,30 @@ impl Worker {
         Ok(())
     }
 
+    /// Returns the position of the first precommit in `signatures` that is not signed by its validator.
+    /// The signatures are checked one by one if the batch verification fails.
+    fn find_invalid_precommit(
+        &self,
+        grand_parent_hash: &H256,
+        signatures: &[(usize, SchnorrSignature)],
+        precommit_hash: &H256,
+    ) -> Option<usize> {
+        let items: Vec<_> = signatures
+            .iter()
+            .map(|(bitset_index, signature)| {
+                (self.validators.get(grand_parent_hash, *bitset_index), *signature, *precommit_hash)
+            })
+            .collect();
+        if items.len() >= MIN_PRECOMMITS_TO_VERIFY_IN_BATCH {
+            if let Ok(result) = verify_schnorr_batch(&items) {
+                return result
+            }
+        }
+        items
+            .iter()
+            .position(|(public, signature, message)| !verify_schnorr(public, signature, message).unwrap_or(false))
+    }
+
     fn calculate_score(&self, block_number: Height) -> U256 {
         calculate_score(block_number, self.view)
     }
@@ -1160,8 +1929,10 @@ impl Worker {
                     cdebug!(ENGINE, "Empty proposal timer is finished, go to the prevote step and broadcast the block");
                     self.submit_proposal_block(block.as_ref());
                 }
-                _ => {
+                prev_step => {
+                    // The block was dropped when the height advanced.
                     cwarn!(ENGINE, "Empty proposal timer was not cleared.");
+                    self.step = prev_step;
                 }
             }
             return
@@ -1186,6 +1957,7 @@ impl Worker {
         }
 
         let next_step = match self.step {
+            TendermintState::Propose if self.extend_propose_timeout() => return,
             TendermintState::Propose => {
                 cinfo!(ENGINE, "Propose timeout.");
                 if self.proposal.is_none() {
@@ -1250,6 +2022,105 @@ impl Worker {
         self.move_to_step(next_step, false);
     }
 
+    /// Waits for the proposal once more in the view, rather than prevoting nil right away,
+    /// if a peer announced to have it and the nil prevotes are not enough yet.
+    fn extend_propose_timeout(&mut self) -> bool {
+        let current = (self.height, self.view);
+        if self.propose_grace == Duration::default()
+            || !self.proposal.is_none()
+            || self.proposal_seen_by_peer != Some(current)
+            || self.propose_extended == Some(current)
+        {
+            return false
+        }
+        let nil_prevotes = self.votes.block_round_votes(&VoteStep::new(self.height, self.view, Step::Prevote), &None);
+        if self.validators.check_enough_votes(&self.prev_block_hash(), &nil_prevotes).is_ok() {
+            return false
+        }
+        cinfo!(ENGINE, "Propose timeout, but a peer has the proposal. Wait {:?} more.", self.propose_grace);
+        self.propose_extended = Some(current);
+        let expired_token_nonce = self.timeout_token_nonce;
+        self.timeout_token_nonce += 1;
+        self.extension
+            .send(network::Event::ExtendTimerStep {
+                duration: self.propose_grace,
+                expired_token_nonce,
+            })
+            .unwrap();
+        true
+    }
+
+    /// Moves to the next step as if the timeout of the current step expired.
+    /// The operators use it when the proposer is stuck and waiting for the timeout takes too long.
+    fn force_next_view(&mut self) -> Result<(), EngineError> {
+        if let TwoThirdsMajority::Lock(lock_view, block_hash) = self.last_two_thirds_majority {
+            return Err(EngineError::CannotForceNextView(format!(
+                "The node is locked on {} since view {}",
+                block_hash, lock_view
+            )))
+        }
+        let next_step = match self.step {
+            TendermintState::Propose => Step::Prevote,
+            // Unlike the timeout, it doesn't wait for 2/3 of the prevotes.
+            // The precommit is nil because there is no lock.
+            TendermintState::Prevote => Step::Precommit,
+            TendermintState::Commit | TendermintState::CommitTimedout => {
+                return Err(EngineError::CannotForceNextView(format!("Height {} is already committed", self.height)))
+            }
+            _ => {
+                return Err(EngineError::CannotForceNextView(format!(
+                    "Only the propose and prevote steps can be skipped, but the current state is {:?}",
+                    self.step
+                )))
+            }
+        };
+        cwarn!(ENGINE, "Force to leave the {:?} step of {}-{}", self.step.to_step(), self.height, self.view);
+        self.move_to_step(next_step, false);
+        Ok(())
+    }
+
+    fn demote_to_standby(&mut self) {
+        if !self.standby {
+            cwarn!(ENGINE, "Stop signing at {}-{} and stand by", self.height, self.view);
+        }
+        self.standby = true;
+    }
+
+    /// Starts signing again. It's refused if another node may be signing with the same key:
+    /// the collected votes of the current height include one signed by the key, or a vote signed by the key
+    /// was received within `min_quiet_period`.
+    fn promote_from_standby(&mut self, min_quiet_period: Option<Duration>) -> Result<(), EngineError> {
+        if !self.standby {
+            return Ok(())
+        }
+        if let Some(signer_index) = self.signer_index() {
+            let height = self.height;
+            if self
+                .votes
+                .get_all()
+                .iter()
+                .any(|vote| vote.signer_index == signer_index && vote.on.step.height == height)
+            {
+                return Err(EngineError::CannotChangeStandby(format!(
+                    "The {}th validator already signed a message at height {}",
+                    signer_index, height
+                )))
+            }
+        }
+        if let (Some(min_quiet_period), Some(observed)) = (min_quiet_period, self.last_own_vote_observed) {
+            let elapsed = observed.elapsed();
+            if elapsed < min_quiet_period {
+                return Err(EngineError::CannotChangeStandby(format!(
+                    "A vote signed by the same key was received {:?} ago",
+                    elapsed
+                )))
+            }
+        }
+        cwarn!(ENGINE, "Start signing at {}-{}", self.height, self.view);
+        self.standby = false;
+        Ok(())
+    }
+
     fn is_expired_timeout_token(&self, nonce: usize) -> bool {
         nonce < self.timeout_token_nonce
     }
@@ -1296,6 +2167,10 @@ impl Worker {
             }
 
             let sender = public_to_address(&sender_public);
+            if self.signer.public() == Some(&sender_public) {
+                // Another node is signing with the same key, or this node signed it before restarting.
+                self.last_own_vote_observed = Some(Instant::now());
+            }
 
             if message.on.step > self.vote_step() {
                 ctrace!(ENGINE, "Ignore future message {:?} from {}.", message, sender);
@@ -1325,6 +2200,7 @@ impl Worker {
             if let Some(double) = self.votes.vote(message.clone()) {
                 let height = message.on.step.height as BlockNumber;
                 cerror!(ENGINE, "Double vote found {:?}", double);
+                self.double_votes_found.fetch_add(1, AtomicOrdering::SeqCst);
                 self.report_double_vote(&double);
                 self.validators.report_malicious(&sender, height, height, ::rlp::encode(&double).into_vec());
                 return Err(EngineError::DoubleVote(sender))
@@ -1336,6 +2212,10 @@ impl Worker {
     }
 
     fn report_double_vote(&self, double: &DoubleVote<ConsensusMessage>) {
+        if self.standby {
+            cwarn!(ENGINE, "Found double vote, but the engine doesn't sign the report while standing by");
+            return
+        }
         let network_id = self.client().common_params(BlockId::Latest).unwrap().network_id();
         let seq = match self.signer.address() {
             Some(address) => self.client().latest_seq(address),
@@ -1415,6 +2295,32 @@ impl Worker {
         assert!(self.is_signer_proposer(&parent_hash));
 
         let signature = self.votes.round_signature(&vote_step, &hash).expect("Proposal vote is generated before");
+        #[cfg(any(test, feature = "fault-injection"))]
+        {
+            match self.fault {
+                ConsensusFault::DoublePropose => self.broadcast_conflicting_proposal(view, &block),
+                ConsensusFault::DelayProposal(delay) => {
+                    cwarn!(ENGINE, "Delay the proposal {:?} for {:?}", vote_step, delay);
+                    let extension = self.extension.clone();
+                    let message = block.into_inner();
+                    Builder::new()
+                        .name("delayed proposal".to_string())
+                        .spawn(move || {
+                            thread::sleep(delay);
+                            extension
+                                .send(network::Event::BroadcastProposalBlock {
+                                    signature,
+                                    view,
+                                    message,
+                                })
+                                .unwrap();
+                        })
+                        .unwrap();
+                    return
+                }
+                _ => {}
+            }
+        }
         self.extension
             .send(network::Event::BroadcastProposalBlock {
                 signature,
@@ -1424,8 +2330,61 @@ impl Worker {
             .unwrap();
     }
 
+    /// Signs a prevote which conflicts with the given one, without recording it.
+    #[cfg(any(test, feature = "fault-injection"))]
+    fn broadcast_conflicting_prevote(&self, block_hash: Option<BlockHash>) {
+        let on = VoteOn {
+            step: VoteStep::new(self.height, self.view, Step::Prevote),
+            block_hash: match block_hash {
+                Some(_) => None,
+                None => Some(self.prev_block_hash()),
+            },
+        };
+        let (signature, signer_index) = match (self.sign(blake256(&on.rlp_bytes())), self.signer_index()) {
+            (Ok(signature), Some(signer_index)) => (signature, signer_index),
+            _ => return,
+        };
+        let message = ConsensusMessage {
+            signature,
+            signer_index,
+            on,
+        };
+        cwarn!(ENGINE, "Send the conflicting prevote {:?}", message);
+        self.broadcast_message(message.rlp_bytes().into_vec());
+    }
+
+    /// Proposes a copy of the block whose extra data is changed, without recording it.
+    #[cfg(any(test, feature = "fault-injection"))]
+    fn broadcast_conflicting_proposal(&self, view: View, block: &encoded::Block) {
+        let mut header = block.decode_header();
+        header.set_extra_data(b"conflicting proposal".to_vec());
+        let vote_step = VoteStep::new(header.number() as Height, view, Step::Propose);
+        let signature = match self.sign(blake256(&message_info_rlp(vote_step, Some(header.hash())))) {
+            Ok(signature) => signature,
+            Err(_) => return,
+        };
+        let mut conflicting = RlpStream::new_list(2);
+        conflicting.append(&header);
+        conflicting.append_raw(block.rlp().at(1).as_raw(), 1);
+        cwarn!(ENGINE, "Send the conflicting proposal {}", header.hash());
+        self.extension
+            .send(network::Event::BroadcastProposalBlock {
+                signature,
+                view,
+                message: conflicting.out(),
+            })
+            .unwrap();
+    }
+
     fn set_signer(&mut self, ap: Arc<AccountProvider>, address: Address) {
         self.signer.set_to_keep_decrypted_account(ap, address);
+        if let Some(public) = self.signer.public() {
+            self.extension
+                .send(network::Event::SetSigner {
+                    public: *public,
+                })
+                .unwrap();
+        }
     }
 
     fn sign(&self, hash: H256) -> Result<SchnorrSignature, Error> {
@@ -1450,6 +2409,10 @@ impl Worker {
         if !imported.is_empty() {
             let mut height_changed = false;
             for hash in imported {
+                if self.pending_committed_block == Some(hash) {
+                    self.on_committed_block_imported(hash);
+                    continue
+                }
                 // New Commit received, skip to next height.
                 let header = c.block_header(&hash.into()).expect("ChainNotify is called after the block is imported");
 
@@ -1480,18 +2443,13 @@ impl Worker {
 
     fn send_proposal_block(
         &self,
+        version: u64,
         signature: SchnorrSignature,
         view: View,
         message: Bytes,
         result: crossbeam::Sender<Bytes>,
     ) {
-        let message = TendermintMessage::ProposalBlock {
-            signature,
-            message,
-            view,
-        }
-        .rlp_bytes()
-        .into_vec();
+        let message = TendermintMessage::proposal(signature, view, message, version).rlp_bytes().into_vec();
         result.send(message).unwrap();
     }
 
@@ -1527,116 +2485,171 @@ impl Worker {
         &mut self,
         signature: SchnorrSignature,
         proposed_view: View,
-        bytes: Bytes,
-    ) -> Option<Arc<ConsensusClient>> {
+        block: ProposedBlock,
+    ) -> Option<(Arc<ConsensusClient>, Bytes)> {
         let c = self.client.upgrade()?;
 
-        // This block borrows bytes
-        {
-            let block_view = BlockView::new(&bytes);
-            let header_view = block_view.header();
-            let number = header_view.number();
-            cinfo!(ENGINE, "Proposal received for {}-{:?}", number, header_view.hash());
+        // The body is decompressed and decoded only after the proposal is validated from its header.
+        let header_view = match block.header() {
+            Ok(header) => header,
+            Err(err) => {
+                cwarn!(ENGINE, "Invalid proposal header received: {:?}", err);
+                self.proposal_checks.rejected_before_body += 1;
+                return None
+            }
+        };
+        let (message, signer_public) = match self.verify_proposal_header(&*c, signature, proposed_view, &header_view) {
+            Some(verified) => verified,
+            None => {
+                self.proposal_checks.rejected_before_body += 1;
+                return None
+            }
+        };
+        let number = header_view.number();
+
+        if self.votes.is_old_or_known(&message) {
+            // The committed block is imported even if its proposal is already known, because its body was lost.
+            if self.pending_committed_block == Some(header_view.hash()) {
+                cdebug!(ENGINE, "Import the committed proposal {} again", header_view.hash());
+                let bytes = self.decode_proposal_body(block)?;
+                return Some((c, bytes))
+            }
+            cdebug!(ENGINE, "Proposal is already known");
+            return None
+        }
 
-            let parent_hash = header_view.parent_hash();
-            {
-                if c.block(&BlockId::Hash(*parent_hash)).is_none() {
-                    let best_block_number = c.best_block_header().number();
-                    ctrace!(
-                        ENGINE,
-                        "Received future proposal {}-{}, current best block number is {}. ignore it",
-                        number,
-                        parent_hash,
-                        best_block_number
-                    );
-                    return None
-                }
+        let bytes = self.decode_proposal_body(block)?;
+
+        if number == self.height as u64 && proposed_view == self.view {
+            // The proposer re-proposed its locked proposal.
+            // If we already imported the proposal, we should set `proposal` here.
+            if c.block(&BlockId::Hash(header_view.hash())).is_some() {
+                let generated_view =
+                    TendermintSealView::new(header_view.seal()).consensus_view().expect("Imported block is verified");
+                cdebug!(
+                    ENGINE,
+                    "Received a proposal({}) by a locked proposer. current view: {}, original proposal's view: {}",
+                    header_view.hash(),
+                    proposed_view,
+                    generated_view
+                );
+                self.proposal = Proposal::new_imported(header_view.hash());
+            } else {
+                self.proposal = Proposal::new_received(header_view.hash(), bytes.clone(), signature);
+                // The proposal is executed while it's verified and the prevotes are collected.
+                c.execute_proposal(bytes.clone());
             }
+            self.broadcast_state(
+                VoteStep::new(self.height, self.view, self.step.to_step()),
+                self.proposal.block_hash(),
+                self.last_two_thirds_majority.view(),
+                self.votes_received,
+            );
+        }
 
-            let prev_proposer_idx = match self.block_proposer_idx(*parent_hash) {
-                Some(idx) => idx,
-                None => {
-                    cwarn!(ENGINE, "Prev block proposer does not exist for height {}", number);
-                    return None
-                }
-            };
+        // The re-proposed blocks have the timestamps of the views in which they were generated.
+        let generated_view = TendermintSealView::new(header_view.seal()).consensus_view().ok();
+        if number == self.height as u64 && generated_view == Some(proposed_view) {
+            self.sample_clock_skew(signer_public, proposed_view, header_view.timestamp());
+        }
 
-            let message = ConsensusMessage::new_proposal(
-                signature,
-                &*self.validators,
-                &header_view,
-                proposed_view,
-                prev_proposer_idx,
-            )
-            .map_err(|err| {
-                cwarn!(ENGINE, "Invalid proposal received: {:?}", err);
-            })
-            .ok()?;
+        self.votes.vote(message);
 
-            // If the proposal's height is current height + 1 and the proposal has valid precommits,
-            // we should import it and increase height
-            if number > (self.height + 1) as u64 {
-                ctrace!(ENGINE, "Received future proposal, ignore it");
-                return None
-            }
+        Some((c, bytes))
+    }
 
-            if number == self.height as u64 && proposed_view > self.view {
-                ctrace!(ENGINE, "Received future proposal, ignore it");
+    /// Checks the proposer's right and the signature of a proposal, returning its vote and the proposer's key.
+    fn verify_proposal_header(
+        &self,
+        c: &ConsensusClient,
+        signature: SchnorrSignature,
+        proposed_view: View,
+        header_view: &Header,
+    ) -> Option<(ConsensusMessage, Public)> {
+        let number = header_view.number();
+        cinfo!(ENGINE, "Proposal received for {}-{:?}", number, header_view.hash());
+        let is_committed_block = self.pending_committed_block == Some(header_view.hash());
+
+        let parent_hash = header_view.parent_hash();
+        if c.block(&BlockId::Hash(*parent_hash)).is_none() {
+            let best_block_number = c.best_block_header().number();
+            ctrace!(
+                ENGINE,
+                "Received future proposal {}-{}, current best block number is {}. ignore it",
+                number,
+                parent_hash,
+                best_block_number
+            );
+            return None
+        }
+
+        let prev_proposer_idx = match self.block_proposer_idx(*parent_hash) {
+            Some(idx) => idx,
+            None => {
+                cwarn!(ENGINE, "Prev block proposer does not exist for height {}", number);
                 return None
             }
+        };
 
-            let signer_public = self.validators.get(&parent_hash, message.signer_index);
-            match message.verify(&signer_public) {
-                Ok(false) => {
-                    cwarn!(ENGINE, "Proposal verification failed: signer is different");
-                    return None
-                }
-                Err(err) => {
-                    cwarn!(ENGINE, "Proposal verification failed: {:?}", err);
-                    return None
-                }
-                _ => {}
-            }
+        let message =
+            ConsensusMessage::new_proposal(signature, &*self.validators, header_view, proposed_view, prev_proposer_idx)
+                .map_err(|err| {
+                    cwarn!(ENGINE, "Invalid proposal received: {:?}", err);
+                })
+                .ok()?;
+
+        // If the proposal's height is current height + 1 and the proposal has valid precommits,
+        // we should import it and increase height
+        if number > (self.height + 1) as u64 {
+            ctrace!(ENGINE, "Received future proposal, ignore it");
+            return None
+        }
 
-            if self.votes.is_old_or_known(&message) {
-                cdebug!(ENGINE, "Proposal is already known");
+        if number == self.height as u64 && proposed_view > self.view && !is_committed_block {
+            ctrace!(ENGINE, "Received future proposal, ignore it");
+            return None
+        }
+
+        let signer_public = self.validators.get(&parent_hash, message.signer_index);
+        match message.verify(&signer_public) {
+            Ok(false) => {
+                cwarn!(ENGINE, "Proposal verification failed: signer is different");
                 return None
             }
-
-            if number == self.height as u64 && proposed_view == self.view {
-                // The proposer re-proposed its locked proposal.
-                // If we already imported the proposal, we should set `proposal` here.
-                if c.block(&BlockId::Hash(header_view.hash())).is_some() {
-                    let generated_view = TendermintSealView::new(header_view.seal())
-                        .consensus_view()
-                        .expect("Imported block is verified");
-                    cdebug!(
-                        ENGINE,
-                        "Received a proposal({}) by a locked proposer. current view: {}, original proposal's view: {}",
-                        header_view.hash(),
-                        proposed_view,
-                        generated_view
-                    );
-                    self.proposal = Proposal::new_imported(header_view.hash());
-                } else {
-                    self.proposal = Proposal::new_received(header_view.hash(), bytes.clone(), signature);
-                }
-                self.broadcast_state(
-                    VoteStep::new(self.height, self.view, self.step.to_step()),
-                    self.proposal.block_hash(),
-                    self.last_two_thirds_majority.view(),
-                    self.votes_received,
-                );
+            Err(err) => {
+                cwarn!(ENGINE, "Proposal verification failed: {:?}", err);
+                return None
             }
+            _ => {}
+        }
+        Some((message, signer_public))
+    }
 
-            self.votes.vote(message);
+    fn decode_proposal_body(&mut self, block: ProposedBlock) -> Option<Bytes> {
+        self.proposal_checks.bodies_decoded += 1;
+        match block.into_block() {
+            Ok(bytes) => Some(bytes),
+            Err(err) => {
+                cwarn!(ENGINE, "Invalid proposal body received: {:?}", err);
+                self.proposal_checks.rejected_by_body += 1;
+                None
+            }
         }
+    }
 
-        Some(c)
+    fn sample_clock_skew(&mut self, proposer: Public, view: View, timestamp: u64) {
+        let now = self.local_time();
+        let offset = self.clock_skew.on_proposal(proposer, self.height, view, timestamp, now);
+        ctrace!(ENGINE, "The clock offset from the proposer of {}-{} is {}ms", self.height, view, offset);
+        if let Some(skew) = self.clock_skew.estimate(now) {
+            if skew.abs() > WARNING_THRESHOLD {
+                cwarn!(ENGINE, "The local clock seems to be skewed by {}ms from the other validators' clocks", skew);
+            }
+        }
     }
 
     fn on_step_state_message(
-        &self,
+        &mut self,
         token: &NodeId,
         peer_vote_step: VoteStep,
         peer_proposal: Option<H256>,
@@ -1656,6 +2669,29 @@ impl Worker {
             self.vote_step()
         };
 
+        let observation_window = self.restore_observation_window;
+        if let Some(observation) = &mut self.restore_observation {
+            // The peers' later steps are not recorded, or the engine would chase them forever.
+            let within_window = observation.started_at.elapsed() < observation_window;
+            if (within_window || observation.observed.is_none())
+                && observation.observed.map_or(true, |observed| observed < peer_vote_step)
+            {
+                observation.observed = Some(peer_vote_step);
+            }
+        }
+        self.check_restore_observation();
+
+        if peer_vote_step.step == Step::Propose
+            && peer_vote_step.height == self.height
+            && peer_vote_step.view <= self.view + 1
+        {
+            let now = self.local_time();
+            self.clock_skew.on_round_start(*token, peer_vote_step.height, peer_vote_step.view, now);
+        }
+        if peer_proposal.is_some() && peer_vote_step.height == self.height && peer_vote_step.view == self.view {
+            self.proposal_seen_by_peer = Some((self.height, self.view));
+        }
+
         if self.height > peer_vote_step.height {
             // no messages to receive
             return
@@ -1747,9 +2783,100 @@ impl Worker {
         }
     }
 
+    fn on_commit_message(
+        &mut self,
+        height: Height,
+        view: View,
+        block_hash: H256,
+        precommits: Vec<SchnorrSignature>,
+        precommit_bitset: BitSet,
+    ) -> Result<(), EngineError> {
+        if height < self.height || (height == self.height && self.step.is_commit()) {
+            ctrace!(ENGINE, "Ignore the commit of {} at {}-{}, which is already committed", block_hash, height, view);
+            return Ok(())
+        }
+        if height > self.height {
+            // The validators of the future height are not known yet.
+            return Err(EngineError::FutureMessage {
+                future_height: height as u64,
+                current_height: self.height as u64,
+            })
+        }
+        if precommit_bitset.count() != precommits.len() {
+            return Err(EngineError::MalformedMessage(format!(
+                "The commit has {} precommits, but its bitset has {} validators",
+                precommits.len(),
+                precommit_bitset.count()
+            )))
+        }
+
+        // Verify all precommits with the validators of the current height before using any of them.
+        let prev_block_hash = self.prev_block_hash();
+        let validator_count = self.validators.count(&prev_block_hash);
+        let vote_step = VoteStep::new(height, view, Step::Precommit);
+        let precommit_hash = message_hash(vote_step, block_hash);
+        let precommits: Vec<_> = precommit_bitset.true_index_iter().zip(precommits).collect();
+        for (signer_index, signature) in &precommits {
+            if *signer_index >= validator_count {
+                return Err(EngineError::ValidatorNotExist {
+                    height: (height - 1) as u64,
+                    index: *signer_index,
+                })
+            }
+            let public = self.validators.get(&prev_block_hash, *signer_index);
+            let is_valid = verify_schnorr(&public, signature, &precommit_hash)
+                .map_err(|err| EngineError::MalformedMessage(format!("{:?}", err)))?;
+            if !is_valid {
+                return Err(EngineError::MessageWithInvalidSignature {
+                    height: (height - 1) as u64,
+                    signer_index: *signer_index,
+                    address: public_to_address(&public),
+                })
+            }
+        }
+        self.validators.check_enough_votes(&prev_block_hash, &precommit_bitset)?;
+
+        for (signer_index, signature) in precommits {
+            let message = ConsensusMessage {
+                signature,
+                signer_index,
+                on: VoteOn {
+                    step: vote_step,
+                    block_hash: Some(block_hash),
+                },
+            };
+            if !self.votes.is_old_or_known(&message) {
+                self.votes.vote(message);
+            }
+            if view == self.view {
+                self.votes_received.set(signer_index);
+            }
+        }
+
+        if self.client().block(&BlockId::Hash(block_hash)).is_none() {
+            cwarn!(
+                ENGINE,
+                "Received the commit of {} at {}-{}, but the block is not imported yet",
+                block_hash,
+                height,
+                view
+            );
+            self.save_last_confirmed_view(view);
+            self.request_committed_block(view, block_hash);
+            self.move_to_step(Step::Commit, false);
+            return Ok(())
+        }
+        cinfo!(ENGINE, "Commit {} at {}-{} with the received precommits", block_hash, height, view);
+        self.save_last_confirmed_view(view);
+        self.client().update_best_as_committed(block_hash);
+        self.move_to_step(Step::Commit, false);
+        Ok(())
+    }
+
     fn on_request_proposal_message(
         &self,
         token: &NodeId,
+        version: u64,
         request_height: Height,
         request_view: View,
         result: crossbeam::Sender<Bytes>,
@@ -1764,13 +2891,13 @@ impl Worker {
 
         if let Some((signature, _signer_index, block)) = self.proposal_at(request_height, request_view) {
             ctrace!(ENGINE, "Send proposal {}-{} to {:?}", request_height, request_view, token);
-            self.send_proposal_block(signature, request_view, block, result);
+            self.send_proposal_block(version, signature, request_view, block, result);
             return
         }
 
         if request_height == self.height && request_view == self.view {
             if let Proposal::ProposalReceived(_hash, block, signature) = &self.proposal {
-                self.send_proposal_block(*signature, request_view, block.clone(), result);
+                self.send_proposal_block(version, *signature, request_view, block.clone(), result);
             }
         }
     }
@@ -18,10 +18,12 @@ use std::sync::{Arc, Weak};
 
 use ckey::{public_to_address, Address, Public};
 use ctypes::util::unexpected::OutOfBounds;
+use ctypes::BlockNumber;
 use parking_lot::RwLock;
 use primitives::H256;
 
-use super::{RoundRobinValidator, ValidatorSet};
+use super::snapshot::ValidatorSnapshots;
+use super::{RoundRobinValidator, ValidatorSet, WeightedValidator};
 use crate::client::ConsensusClient;
 use crate::consensus::bit_set::BitSet;
 use crate::consensus::stake::{get_validators, Validator};
@@ -31,6 +33,7 @@ use crate::consensus::EngineError;
 pub struct DynamicValidator {
     initial_list: RoundRobinValidator,
     client: RwLock<Option<Weak<ConsensusClient>>>,
+    snapshots: ValidatorSnapshots,
 }
 
 impl DynamicValidator {
@@ -38,10 +41,19 @@ impl DynamicValidator {
         DynamicValidator {
             initial_list: RoundRobinValidator::new(initial_validators),
             client: Default::default(),
+            snapshots: ValidatorSnapshots::new(),
         }
     }
 
+    /// The validators in the scheme.
+    pub fn initial_validators(&self) -> &[Public] {
+        self.initial_list.validators()
+    }
+
     fn validators(&self, parent: H256) -> Option<Vec<Validator>> {
+        if let Some(validators) = self.snapshots.cached(&parent) {
+            return Some(validators)
+        }
         let client: Arc<ConsensusClient> =
             self.client.read().as_ref().and_then(Weak::upgrade).expect("Client is not initialized");
         let block_id = parent.into();
@@ -53,7 +65,18 @@ impl DynamicValidator {
         if term_id == 0 {
             return None
         }
-        let state = client.state_at(block_id)?;
+        if let Some(last_term_finished) = client.last_term_finished_block_num(block_id) {
+            if let Some(validators) = self.snapshots.load(term_id, last_term_finished + 1, &parent) {
+                return Some(validators)
+            }
+        }
+        let validators = Self::validators_from_state(&*client, parent)?;
+        self.snapshots.cache(parent, validators.clone());
+        Some(validators)
+    }
+
+    fn validators_from_state(client: &ConsensusClient, parent: H256) -> Option<Vec<Validator>> {
+        let state = client.state_at(parent.into())?;
         let validators = get_validators(&state).unwrap();
         if validators.is_empty() {
             None
@@ -64,8 +87,22 @@ impl DynamicValidator {
         }
     }
 
+    /// Returns the id and the first block number of the new term if the block closed the previous term.
+    fn closed_term(client: &ConsensusClient, hash: H256) -> Option<(u64, BlockNumber)> {
+        let block_id = hash.into();
+        let number = client.block_header(&block_id)?.number();
+        let term_id = client.current_term_id(block_id)?;
+        let last_term_finished = client.last_term_finished_block_num(block_id)?;
+        if term_id != 0 && last_term_finished == number {
+            Some((term_id, number + 1))
+        } else {
+            None
+        }
+    }
+
+    /// The keys that sign the blocks and the votes.
     fn validators_pubkey(&self, parent: H256) -> Option<Vec<Public>> {
-        self.validators(parent).map(|validators| validators.into_iter().map(|val| *val.pubkey()).collect())
+        self.validators(parent).map(|validators| validators.into_iter().map(|val| *val.signing_pubkey()).collect())
     }
 
     pub fn proposer_index(&self, parent: H256, prev_proposer_index: usize, proposed_view: usize) -> usize {
@@ -176,11 +213,33 @@ impl ValidatorSet for DynamicValidator {
     /// Allows blockchain state access.
     fn register_client(&self, client: Weak<ConsensusClient>) {
         self.initial_list.register_client(Weak::clone(&client));
+        if let Some(client) = client.upgrade() {
+            self.snapshots.set_db(client.get_kvdb());
+        }
         let mut client_lock = self.client.write();
         assert!(client_lock.is_none());
         *client_lock = Some(client);
     }
 
+    fn on_chain_updated(&self, enacted: &[H256], retracted: &[H256]) {
+        let client = match self.client.read().as_ref().and_then(Weak::upgrade) {
+            Some(client) => client,
+            None => return,
+        };
+        for hash in retracted {
+            if let Some((term_id, first_block)) = Self::closed_term(&*client, *hash) {
+                self.snapshots.remove(term_id, first_block, hash);
+            }
+        }
+        for hash in enacted {
+            if let Some((term_id, first_block)) = Self::closed_term(&*client, *hash) {
+                if let Some(validators) = Self::validators_from_state(&*client, *hash) {
+                    self.snapshots.save(term_id, first_block, *hash, validators);
+                }
+            }
+        }
+    }
+
     fn addresses(&self, parent: &H256) -> Vec<Address> {
         if let Some(validators) = self.validators_pubkey(*parent) {
             validators.iter().map(public_to_address).collect()
@@ -188,6 +247,37 @@ impl ValidatorSet for DynamicValidator {
             self.initial_list.addresses(parent)
         }
     }
+
+    fn owner_address(&self, parent: &H256, signer: &Address) -> Address {
+        self.validators(*parent)
+            .and_then(|validators| {
+                validators.into_iter().find(|val| public_to_address(val.signing_pubkey()) == *signer)
+            })
+            .map_or(*signer, |val| public_to_address(val.pubkey()))
+    }
+
+    fn owner_addresses(&self, parent: &H256) -> Vec<Address> {
+        if let Some(validators) = self.validators(*parent) {
+            validators.iter().map(|val| public_to_address(val.pubkey())).collect()
+        } else {
+            self.initial_list.addresses(parent)
+        }
+    }
+
+    fn weighted_validators(&self, parent: &H256) -> Vec<WeightedValidator> {
+        if let Some(validators) = self.validators(*parent) {
+            validators
+                .into_iter()
+                .map(|validator| WeightedValidator {
+                    pubkey: *validator.signing_pubkey(),
+                    delegation: Some(validator.delegation()),
+                    deposit: Some(validator.deposit()),
+                })
+                .collect()
+        } else {
+            self.initial_list.weighted_validators(parent)
+        }
+    }
 }
 
 #[cfg(test)]
@@ -195,11 +285,16 @@ mod tests {
     use std::str::FromStr;
     use std::sync::Arc;
 
-    use ckey::Public;
+    use ccrypto::blake256;
+    use ckey::{public_to_address, sign_schnorr, Generator, Public, Random};
+    use primitives::H256;
 
-    use super::super::ValidatorSet;
+    use super::super::{ValidatorSet, WeightedValidator};
     use super::DynamicValidator;
     use crate::client::TestBlockChainClient;
+    use crate::consensus::stake::{Validator, Validators};
+    use crate::consensus::vote_collector::Message;
+    use crate::consensus::{message_info_rlp, ConsensusMessage, Step, VoteOn, VoteStep};
     use client::ConsensusClient;
 
     #[test]
@@ -218,4 +313,146 @@ mod tests {
         assert_eq!(set.get(&Default::default(), 1), a2);
         assert_eq!(set.get(&Default::default(), 2), a1);
     }
+
+    #[test]
+    fn snapshot_lookups_match_the_state_across_reorg() {
+        let set = DynamicValidator::new(vec![Public::random()]);
+        let mut client = TestBlockChainClient::new();
+        client.add_blocks(3, 0);
+        client.set_random_validators(4);
+        client.term_id = Some(2);
+        client.last_term_finished_block_num = Some(3);
+        let closed_at = client.block_hash_delta_minus(1);
+        let test_client: Arc<ConsensusClient> = Arc::new(client);
+        set.register_client(Arc::downgrade(&test_client));
+
+        let from_state = DynamicValidator::validators_from_state(&*test_client, closed_at).unwrap();
+        let assert_lookups_match_the_state = || {
+            assert_eq!(from_state.len(), set.count(&closed_at));
+            for (index, validator) in from_state.iter().enumerate() {
+                assert_eq!(*validator.pubkey(), set.get(&closed_at, index));
+                assert_eq!(Some(index), set.get_index(&closed_at, validator.pubkey()));
+            }
+        };
+
+        set.on_chain_updated(&[closed_at], &[]);
+        assert_eq!(Some(from_state.clone()), set.snapshots.load(2, 4, &closed_at));
+        assert_lookups_match_the_state();
+
+        // A reorg retracts the block that closed the term.
+        set.on_chain_updated(&[], &[closed_at]);
+        assert_eq!(None, set.snapshots.load(2, 4, &closed_at));
+        assert_lookups_match_the_state();
+    }
+
+    #[test]
+    fn weighted_validators_change_at_term_boundary() {
+        let initial: Vec<Public> = (0..3).map(|_| Public::random()).collect();
+        let elected: Vec<Public> = (0..3).map(|_| Public::random()).collect();
+        let parent = H256::default();
+
+        // The initial validators seal the blocks of the term 0.
+        let before = DynamicValidator::new(initial.clone());
+        let client_before: Arc<ConsensusClient> = Arc::new({
+            let mut client = TestBlockChainClient::new();
+            client.term_id = Some(0);
+            client
+        });
+        before.register_client(Arc::downgrade(&client_before));
+
+        // The parent closed the term 0, so the elected validators seal the block.
+        let after = DynamicValidator::new(initial.clone());
+        let client_after: Arc<ConsensusClient> = Arc::new({
+            let mut client = TestBlockChainClient::new();
+            client.term_id = Some(1);
+            client.validators = Validators::from_vector_to_test(vec![
+                Validator::new_for_test(100, 10, elected[0]),
+                Validator::new_for_test(200, 20, elected[1]),
+                Validator::new_for_test(300, 30, elected[2]),
+            ]);
+            client
+        });
+        after.register_client(Arc::downgrade(&client_after));
+
+        let validators = before.weighted_validators(&parent);
+        assert_eq!(initial, validators.iter().map(|validator| validator.pubkey).collect::<Vec<_>>());
+        assert!(validators.iter().all(|validator| validator.delegation.is_none() && validator.deposit.is_none()));
+
+        let validators = after.weighted_validators(&parent);
+        assert_eq!(
+            vec![
+                WeightedValidator {
+                    pubkey: elected[2],
+                    delegation: Some(300),
+                    deposit: Some(30),
+                },
+                WeightedValidator {
+                    pubkey: elected[1],
+                    delegation: Some(200),
+                    deposit: Some(20),
+                },
+                WeightedValidator {
+                    pubkey: elected[0],
+                    delegation: Some(100),
+                    deposit: Some(10),
+                },
+            ],
+            validators
+        );
+        for (index, validator) in validators.iter().enumerate() {
+            assert_eq!(validator.pubkey, after.get(&parent, index));
+        }
+    }
+
+    #[test]
+    fn votes_are_verified_with_the_signing_key_and_rewarded_to_the_owner() {
+        let owner = Random.generate().unwrap();
+        let signer = Random.generate().unwrap();
+        let other = Public::random();
+        let parent = H256::default();
+
+        let set = DynamicValidator::new(vec![Public::random()]);
+        let test_client: Arc<ConsensusClient> = Arc::new({
+            let mut client = TestBlockChainClient::new();
+            client.term_id = Some(1);
+            client.validators = Validators::from_vector_to_test(vec![
+                Validator::new_for_test(100, 10, *owner.public()).with_signing_pubkey_for_test(*signer.public()),
+                Validator::new_for_test(200, 20, other),
+            ]);
+            client
+        });
+        set.register_client(Arc::downgrade(&test_client));
+
+        // The validators are in the descending order.
+        assert_eq!(*signer.public(), set.get(&parent, 1));
+        assert_eq!(Some(1), set.get_index(&parent, signer.public()));
+        assert_eq!(None, set.get_index(&parent, owner.public()));
+        assert!(set.contains_address(&parent, &signer.address()));
+        assert!(!set.contains_address(&parent, &owner.address()));
+
+        let on = VoteOn {
+            step: VoteStep::new(3, 0, Step::Precommit),
+            block_hash: Some(H256::random()),
+        };
+        let message = blake256(message_info_rlp(on.step, on.block_hash));
+        let sign = |private| sign_schnorr(private, &message).unwrap();
+        let signed_by_signer = ConsensusMessage {
+            on: on.clone(),
+            signature: sign(signer.private()),
+            signer_index: 1,
+        };
+        let signed_by_owner = ConsensusMessage {
+            on,
+            signature: sign(owner.private()),
+            signer_index: 1,
+        };
+        let public = set.get(&parent, signed_by_signer.signer_index);
+        assert_eq!(Ok(true), signed_by_signer.verify(&public));
+        assert_eq!(Ok(false), signed_by_owner.verify(&public));
+
+        assert_eq!(owner.address(), set.owner_address(&parent, &signer.address()));
+        assert_eq!(public_to_address(&other), set.owner_address(&parent, &public_to_address(&other)));
+        assert_eq!(vec![public_to_address(&other), owner.address()], set.owner_addresses(&parent));
+        assert_eq!(vec![public_to_address(&other), signer.address()], set.addresses(&parent));
+    }
 }
@@ -26,10 +26,21 @@ use crate::client::ConsensusClient;
 use crate::consensus::EngineError;
 
 mod dynamic_validator;
+mod snapshot;
 pub mod validator_list;
 
 pub use self::dynamic_validator::DynamicValidator;
 
+/// A validator with the stakes that weigh its votes.
+///
+/// The stakes are `None` while the validators are not elected yet and every vote weighs the same.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeightedValidator {
+    pub pubkey: Public,
+    pub delegation: Option<u64>,
+    pub deposit: Option<u64>,
+}
+
 /// A validator set.
 pub trait ValidatorSet: Send + Sync {
     /// Checks if a given public key is a validator,
@@ -61,6 +72,22 @@ pub trait ValidatorSet: Send + Sync {
     fn report_benign(&self, _validator: &Address, _set_block: BlockNumber, _block: BlockNumber) {}
     /// Allows blockchain state access.
     fn register_client(&self, _client: Weak<ConsensusClient>) {}
+    /// Notifies about the blocks enacted and retracted by a chain update.
+    fn on_chain_updated(&self, _enacted: &[H256], _retracted: &[H256]) {}
 
     fn addresses(&self, _parent: &H256) -> Vec<Address>;
+
+    /// Returns the address of the validator that signs with the given address.
+    /// The rewards go to it, and it's the one jailed or banned for the faults of the signer.
+    fn owner_address(&self, _parent: &H256, signer: &Address) -> Address {
+        *signer
+    }
+
+    /// Returns the addresses of the validators' owners in the order of their indices.
+    fn owner_addresses(&self, parent: &H256) -> Vec<Address> {
+        self.addresses(parent)
+    }
+
+    /// Returns the validators in the order of their indices.
+    fn weighted_validators(&self, parent: &H256) -> Vec<WeightedValidator>;
 }
@@ -0,0 +1,170 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use ctypes::BlockNumber;
+use kvdb::{DBTransaction, KeyValueDB};
+use lru_cache::LruCache;
+use parking_lot::{Mutex, RwLock};
+use primitives::H256;
+
+use crate::consensus::stake::Validator;
+use crate::db;
+
+const SNAPSHOT_KEY_PREFIX: &[u8] = b"validator-snapshot";
+const CACHE_SIZE: usize = 128;
+
+#[derive(RlpDecodable, RlpEncodable)]
+struct Snapshot {
+    closed_at: H256,
+    validators: Vec<Validator>,
+}
+
+/// Validator lists saved when a term is closed.
+///
+/// A snapshot is read from the state of the block that closed the previous term, and it is keyed by the id and
+/// the first block number of the new term. It is used only when the parent is the block it was taken at, so a
+/// snapshot left by a retracted block is never returned. The lists looked up recently are kept in memory by their
+/// parent hash.
+pub struct ValidatorSnapshots {
+    db: RwLock<Option<Arc<KeyValueDB>>>,
+    cache: Mutex<LruCache<H256, Vec<Validator>>>,
+}
+
+impl ValidatorSnapshots {
+    pub fn new() -> Self {
+        Self {
+            db: Default::default(),
+            cache: Mutex::new(LruCache::new(CACHE_SIZE)),
+        }
+    }
+
+    pub fn set_db(&self, db: Arc<KeyValueDB>) {
+        *self.db.write() = Some(db);
+    }
+
+    pub fn cached(&self, parent: &H256) -> Option<Vec<Validator>> {
+        self.cache.lock().get_mut(parent).cloned()
+    }
+
+    pub fn cache(&self, parent: H256, validators: Vec<Validator>) {
+        self.cache.lock().insert(parent, validators);
+    }
+
+    /// Returns the snapshot of the term if it was taken at the given parent.
+    pub fn load(&self, term_id: u64, first_block: BlockNumber, parent: &H256) -> Option<Vec<Validator>> {
+        let snapshot = self.read(term_id, first_block)?;
+        if snapshot.closed_at != *parent {
+            return None
+        }
+        self.cache(*parent, snapshot.validators.clone());
+        Some(snapshot.validators)
+    }
+
+    pub fn save(&self, term_id: u64, first_block: BlockNumber, closed_at: H256, validators: Vec<Validator>) {
+        self.cache(closed_at, validators.clone());
+        let db = match self.db.read().as_ref() {
+            Some(db) => Arc::clone(db),
+            None => return,
+        };
+        let snapshot = Snapshot {
+            closed_at,
+            validators,
+        };
+        let mut batch = DBTransaction::new();
+        batch.put(db::COL_EXTRA, &snapshot_key(term_id, first_block), &rlp::encode(&snapshot).into_vec());
+        db.write(batch).expect("Low level database error. Some issue with disk?");
+    }
+
+    /// Removes the snapshot of the term if it was taken at the given block.
+    pub fn remove(&self, term_id: u64, first_block: BlockNumber, closed_at: &H256) {
+        self.cache.lock().remove(closed_at);
+        match self.read(term_id, first_block) {
+            Some(ref snapshot) if snapshot.closed_at == *closed_at => {}
+            _ => return,
+        }
+        let db = match self.db.read().as_ref() {
+            Some(db) => Arc::clone(db),
+            None => return,
+        };
+        let mut batch = DBTransaction::new();
+        batch.delete(db::COL_EXTRA, &snapshot_key(term_id, first_block));
+        db.write(batch).expect("Low level database error. Some issue with disk?");
+    }
+
+    fn read(&self, term_id: u64, first_block: BlockNumber) -> Option<Snapshot> {
+        let db = self.db.read();
+        let value = db
+            .as_ref()?
+            .get(db::COL_EXTRA, &snapshot_key(term_id, first_block))
+            .expect("Low level database error. Some issue with disk?")?;
+        Some(rlp::decode(&value))
+    }
+}
+
+fn snapshot_key(term_id: u64, first_block: BlockNumber) -> Vec<u8> {
+    let mut key = SNAPSHOT_KEY_PREFIX.to_vec();
+    key.extend_from_slice(&term_id.to_be_bytes());
+    key.extend_from_slice(&first_block.to_be_bytes());
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use ckey::Public;
+
+    use super::*;
+
+    fn validators(count: usize) -> Vec<Validator> {
+        (0..count).map(|i| Validator::new_for_test(i as u64 + 1, 100, Public::random())).collect()
+    }
+
+    #[test]
+    fn snapshot_is_used_only_for_the_block_it_was_taken_at() {
+        let snapshots = ValidatorSnapshots::new();
+        snapshots.set_db(Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap())));
+
+        let closed_at = H256::random();
+        let list = validators(4);
+        snapshots.save(2, 11, closed_at, list.clone());
+        assert_eq!(Some(list), snapshots.load(2, 11, &closed_at));
+        assert_eq!(None, snapshots.load(2, 11, &H256::random()));
+        assert_eq!(None, snapshots.load(3, 11, &closed_at));
+    }
+
+    #[test]
+    fn retracted_snapshot_is_replaced() {
+        let snapshots = ValidatorSnapshots::new();
+        snapshots.set_db(Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap())));
+
+        let (retracted, enacted) = (H256::random(), H256::random());
+        snapshots.save(2, 11, retracted, validators(4));
+
+        // Removing the snapshot of another block doesn't affect it.
+        snapshots.remove(2, 11, &enacted);
+        assert!(snapshots.load(2, 11, &retracted).is_some());
+
+        snapshots.remove(2, 11, &retracted);
+        assert_eq!(None, snapshots.load(2, 11, &retracted));
+        assert_eq!(None, snapshots.cached(&retracted));
+
+        let list = validators(3);
+        snapshots.save(2, 11, enacted, list.clone());
+        assert_eq!(Some(list), snapshots.load(2, 11, &enacted));
+        assert_eq!(None, snapshots.load(2, 11, &retracted));
+    }
+}
@@ -23,7 +23,7 @@ use parking_lot::RwLock;
 use primitives::H256;
 
 use super::super::BitSet;
-use super::ValidatorSet;
+use super::{ValidatorSet, WeightedValidator};
 use crate::client::ConsensusClient;
 use crate::consensus::EngineError;
 use crate::types::BlockId;
@@ -44,6 +44,10 @@ impl RoundRobinValidator {
             client: Default::default(),
         }
     }
+
+    pub fn validators(&self) -> &[Public] {
+        &self.validators
+    }
 }
 
 impl ValidatorSet for RoundRobinValidator {
@@ -108,6 +112,17 @@ impl ValidatorSet for RoundRobinValidator {
     fn addresses(&self, _parent: &H256) -> Vec<Address> {
         self.validators.iter().map(public_to_address).collect()
     }
+
+    fn weighted_validators(&self, _parent: &H256) -> Vec<WeightedValidator> {
+        self.validators
+            .iter()
+            .map(|pubkey| WeightedValidator {
+                pubkey: *pubkey,
+                delegation: None,
+                deposit: None,
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -18,6 +18,7 @@ use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Debug;
 use std::hash::Hash;
 use std::iter::Iterator;
+use std::mem::size_of;
 
 use ckey::{Error as KeyError, Public, SchnorrSignature};
 use parking_lot::RwLock;
@@ -25,7 +26,7 @@ use primitives::H256;
 use rlp::{Decodable, Encodable, RlpStream};
 
 use super::stake::Action;
-use super::BitSet;
+use super::{BitSet, VoteCollectorStats};
 
 pub trait Message: Clone + PartialEq + Eq + Hash + Encodable + Decodable + Debug + Sync + Send {
     type Round: Clone + Copy + PartialEq + Eq + Hash + Default + Debug + Ord;
@@ -171,6 +172,48 @@ impl<M: Message + Default + Encodable + Debug> VoteCollector<M> {
         *guard = new_collector;
     }
 
+    /// Throws out the rounds older than `vote_round` even if nothing newer was voted.
+    /// An empty collector is left as the marker for the oldest in that case.
+    pub fn throw_out_older_than(&self, vote_round: &M::Round) {
+        let mut guard = self.votes.write();
+        let mut new_collector = guard.split_off(vote_round);
+        if new_collector.is_empty() {
+            new_collector.insert(*vote_round, Default::default());
+        }
+        *guard = new_collector;
+    }
+
+    /// Throws out the rounds in `[from, to)` except the ones in `keep`.
+    /// Unlike `throw_out_old`, the messages of the thrown out rounds are not regarded as old.
+    /// If the oldest round is thrown out, an empty collector is left as the marker for the oldest.
+    pub fn throw_out_range(&self, from: &M::Round, to: &M::Round, keep: &[M::Round]) {
+        let mut guard = self.votes.write();
+        let oldest = *guard.keys().next().expect("The marker for the oldest always exists");
+        let mut thrown = guard.split_off(from);
+        let mut newer = thrown.split_off(to);
+        guard.append(&mut newer);
+        for (round, collector) in thrown {
+            if keep.contains(&round) {
+                guard.insert(round, collector);
+            } else if round == oldest {
+                guard.insert(round, Default::default());
+            }
+        }
+    }
+
+    /// The number of the stored messages and the estimated bytes to store them.
+    pub fn stats(&self) -> VoteCollectorStats {
+        let guard = self.votes.read();
+        let votes = guard.values().map(|c| c.messages.len()).sum();
+        // A message is stored twice and its signature once more, with the index of the signer.
+        let bytes_per_vote = 2 * size_of::<M>() + size_of::<SchnorrSignature>() + 2 * size_of::<usize>();
+        let bytes_per_round = size_of::<M::Round>() + size_of::<StepCollector<M>>();
+        VoteCollectorStats {
+            votes,
+            memory_estimate: votes * bytes_per_vote + guard.len() * bytes_per_round,
+        }
+    }
+
     /// Collects the signatures and the indices for the given round and hash.
     /// Returning indices is in ascending order, and signature and indices are matched with another.
     pub fn round_signatures_and_indices(
@@ -245,3 +288,102 @@ impl<M: Message + Default + Encodable + Debug> VoteCollector<M> {
         guard.get(round).map(|c| c.voted.iter().map(|(k, v)| (*k, v.clone())).collect()).unwrap_or_default()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{ConsensusMessage, Step, VoteOn, VoteStep};
+
+    fn vote(
+        collector: &VoteCollector<ConsensusMessage>,
+        step: VoteStep,
+        block_hash: Option<H256>,
+        signer_index: usize,
+    ) {
+        collector.vote(ConsensusMessage {
+            on: VoteOn {
+                step,
+                block_hash,
+            },
+            signature: SchnorrSignature::random(),
+            signer_index,
+        });
+    }
+
+    #[test]
+    fn throw_out_range_bounds_the_votes_of_many_views() {
+        let collector = VoteCollector::<ConsensusMessage>::default();
+        let height = 1;
+        let block_hash = H256::random();
+
+        // Lock on the block at view 0.
+        let lock_proposal = VoteStep::new(height, 0, Step::Propose);
+        let lock_prevote = VoteStep::new(height, 0, Step::Prevote);
+        vote(&collector, lock_proposal, Some(block_hash), 0);
+        for signer_index in 0..3 {
+            vote(&collector, lock_prevote, Some(block_hash), signer_index);
+        }
+
+        let mut max_votes = 0;
+        for view in 1..=200 {
+            for signer_index in 0..4 {
+                vote(&collector, VoteStep::new(height, view, Step::Prevote), None, signer_index);
+                vote(&collector, VoteStep::new(height, view, Step::Precommit), None, signer_index);
+            }
+            collector.throw_out_range(
+                &VoteStep::new(height, 0, Step::Propose),
+                &VoteStep::new(height, view - 1, Step::Propose),
+                &[lock_proposal, lock_prevote],
+            );
+            max_votes = max_votes.max(collector.stats().votes);
+        }
+
+        // The lock proof and the nil votes of the last two views.
+        assert_eq!(4 + 2 * 8, max_votes);
+        assert_eq!(4 + 2 * 8, collector.stats().votes);
+        assert_eq!(vec![block_hash], collector.get_block_hashes(&lock_proposal));
+        assert_eq!(3, collector.block_round_votes(&lock_prevote, &Some(block_hash)).count());
+        assert!(collector.get_all_votes_in_round(&VoteStep::new(height, 100, Step::Prevote)).is_empty());
+    }
+
+    #[test]
+    fn throw_out_range_keeps_the_marker_for_the_oldest() {
+        let collector = VoteCollector::<ConsensusMessage>::default();
+        vote(&collector, VoteStep::new(1, 0, Step::Prevote), None, 0);
+        collector.throw_out_old(&VoteStep::new(1, 0, Step::Prevote));
+        vote(&collector, VoteStep::new(1, 1, Step::Prevote), None, 0);
+
+        collector.throw_out_range(&VoteStep::new(1, 0, Step::Propose), &VoteStep::new(1, 1, Step::Propose), &[]);
+
+        let old = ConsensusMessage {
+            on: VoteOn {
+                step: VoteStep::new(1, 0, Step::Propose),
+                block_hash: None,
+            },
+            signature: SchnorrSignature::random(),
+            signer_index: 1,
+        };
+        assert!(collector.is_old_or_known(&old));
+        assert_eq!(1, collector.stats().votes);
+    }
+
+    #[test]
+    fn throw_out_older_than_leaves_the_marker_without_votes() {
+        let collector = VoteCollector::<ConsensusMessage>::default();
+        vote(&collector, VoteStep::new(1, 0, Step::Precommit), None, 0);
+        collector.throw_out_older_than(&VoteStep::new(2, 0, Step::Propose));
+        assert_eq!(0, collector.stats().votes);
+
+        let old = ConsensusMessage {
+            on: VoteOn {
+                step: VoteStep::new(1, 0, Step::Precommit),
+                block_hash: None,
+            },
+            signature: SchnorrSignature::random(),
+            signer_index: 1,
+        };
+        assert!(collector.is_old_or_known(&old));
+        vote(&collector, VoteStep::new(2, 0, Step::Prevote), None, 0);
+        assert_eq!(1, collector.stats().votes);
+    }
+}
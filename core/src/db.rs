@@ -37,6 +37,13 @@ pub const COL_MEMPOOL: Option<u32> = Some(4);
 pub const COL_ERROR_HINT: Option<u32> = Some(5);
 /// Number of columns in DB
 pub const NUM_COLUMNS: Option<u32> = Some(6);
+/// The names of the columns in the order of their numbers
+pub const COLUMN_NAMES: [&str; 6] = ["state", "headers", "bodies", "extra", "mempool", "errorHints"];
+
+/// Returns the column of the given name.
+pub fn column_by_name(name: &str) -> Option<Option<u32>> {
+    COLUMN_NAMES.iter().position(|column| *column == name).map(|index| Some(index as u32))
+}
 
 /// Modes for updating caches.
 #[derive(Clone, Copy)]
@@ -30,6 +30,7 @@ use util_error::UtilError;
 use crate::account_provider::Error as AccountProviderError;
 use crate::client::Error as ClientError;
 use crate::consensus::EngineError;
+use crate::miner::SpendPolicyViolation;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 /// Import to the block queue result
@@ -115,6 +116,10 @@ pub enum BlockError {
     UnknownParent(H256),
     /// Body size limit is exceeded.
     BodySizeIsTooBig,
+    /// The sum of the block reward and the transaction fees doesn't fit in u64.
+    TotalFeeOverflow,
+    /// The sum of the block reward and the transaction fees is less than the sum of the minimum costs.
+    InsufficientReward(OutOfBounds<u64>),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -156,6 +161,8 @@ impl fmt::Display for BlockError {
             UnknownParent(hash) => format!("Unknown parent: {}", hash),
             TooManyTransactions(address) => format!("Too many transactions from: {}", address),
             BodySizeIsTooBig => "Block's body size is too big".to_string(),
+            TotalFeeOverflow => "The sum of the block reward and the fees overflows".to_string(),
+            InsufficientReward(oob) => format!("Block reward and fees are less than the minimum costs: {}", oob),
         };
 
         f.write_fmt(format_args!("Block error ({})", msg))
@@ -190,6 +197,8 @@ pub enum Error {
     Runtime(RuntimeError),
     History(HistoryError),
     Syntax(SyntaxError),
+    /// The node refused to sign a transaction of its account.
+    SpendPolicy(SpendPolicyViolation),
 }
 
 impl fmt::Display for Error {
@@ -210,6 +219,7 @@ impl fmt::Display for Error {
             Error::Runtime(err) => err.fmt(f),
             Error::History(err) => err.fmt(f),
             Error::Syntax(err) => err.fmt(f),
+            Error::SpendPolicy(err) => err.fmt(f),
         }
     }
 }
@@ -316,3 +326,9 @@ impl From<SyntaxError> for Error {
         Error::Syntax(err)
     }
 }
+
+impl From<SpendPolicyViolation> for Error {
+    fn from(err: SpendPolicyViolation) -> Self {
+        Error::SpendPolicy(err)
+    }
+}
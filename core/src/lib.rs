@@ -24,6 +24,7 @@ extern crate codechain_keystore as ckeystore;
 #[macro_use]
 extern crate codechain_logger as clogger;
 extern crate codechain_merkle as cmerkle;
+extern crate codechain_metrics as cmetrics;
 extern crate codechain_network as cnetwork;
 extern crate codechain_state as cstate;
 extern crate codechain_stratum as cstratum;
@@ -38,6 +39,7 @@ extern crate kvdb;
 extern crate kvdb_memorydb;
 extern crate kvdb_rocksdb;
 extern crate linked_hash_map;
+extern crate lru_cache;
 extern crate memorydb;
 extern crate num_rational;
 extern crate primitives;
@@ -82,19 +84,42 @@ mod tests;
 
 pub use crate::account_provider::{AccountProvider, Error as AccountProviderError};
 pub use crate::block::Block;
+pub use crate::blockchain::{
+    ActionIndexBackfill, ActionIndexEntry, ActionIndexPage, ActionIndexPosition, ActionType, AddressHistoryEntry,
+    AddressHistoryPage, AddressHistoryPosition, CacheBudgetError, CacheStats, DatabaseAnomaly, Reorg,
+    ReorgProtectionStatus,
+};
 pub use crate::client::Error::Database;
 pub use crate::client::{
-    AccountData, AssetClient, BlockChainClient, BlockChainTrait, ChainNotify, Client, ClientConfig, DatabaseClient,
-    EngineClient, EngineInfo, ExecuteClient, ImportBlock, MiningBlockChainClient, Shard, StateInfo, TermInfo,
-    TestBlockChainClient, TextClient,
+    trace_custom_action, AccountChange, AccountData, ActionDataChange, ActionHandlerTrace, AssetClient,
+    BlockChainClient, BlockChainTrait, BlockDivergence, BlockFinality, BlockImportTimings, BlockProductionStats,
+    BlockRewardDistribution, ChainNotify, Client, ClientConfig, ColumnStats, DatabaseClient, DatabaseStats,
+    DryRunOptions, DryRunResult, EngineClient, EngineInfo, ExecuteClient, FinalityConflict, FinalityEvidence,
+    FinalizedBlock, ImportBlock, ImportTimings, IntegrityReport, MiningBlockChainClient, ParamsActivation,
+    RangeStatistics, RepairOptions, ReplayOptions, ReplayReport, ReplayedTransaction, Shard, StateInfo,
+    StateWarmingStatus, TermDetails, TermInfo, TestBlockChainClient, TextClient, TraceTarget, IMPORT_TIMINGS_WINDOW,
+    MAX_REPLAY_BLOCKS, MAX_STATISTICS_RANGE,
+};
+#[cfg(feature = "test-support")]
+pub use crate::consensus::TendermintSimulation;
+pub use crate::consensus::{
+    tendermint_light, CandidateStatus, CommitRecoveryStats, ConsensusDegradation, ConsensusFault, ConsensusStatus,
+    DecodedSeal, EngineDescription, EngineType, ExclusionReason, Message, ProposalCheckStats, RestoreStatus,
+    SignerEligibility, Step as ConsensusStep, TimeGapParams, VoteCollectorStats, VoteRequestStats, WeightedValidator,
 };
-pub use crate::consensus::{EngineType, Message, TimeGapParams};
-pub use crate::db::{COL_STATE, NUM_COLUMNS};
+pub use crate::db::{column_by_name, COLUMN_NAMES, COL_BODIES, COL_EXTRA, COL_STATE, NUM_COLUMNS};
 pub use crate::error::{BlockImportError, Error, ImportError};
-pub use crate::miner::{Miner, MinerOptions, MinerService, Stratum, StratumConfig, StratumError};
-pub use crate::scheme::Scheme;
+pub use crate::invoice::Invoice;
+pub use crate::miner::{
+    DropReason, FeeFloorOptions, FeeFloorStatus, Miner, MinerOptions, MinerService, PendingQueue, PooledTransaction,
+    ProposalBudget, QueuedTransaction, RelayStats, RelayStatus, ScheduledTransaction, SeenTransactions, SignerDiagnosis,
+    SpendPolicy, SpendPolicyStatus, SpendPolicyViolation, Stratum, StratumConfig, StratumError, TransactionEvent,
+    TxOrigin,
+};
+pub use crate::scheme::{Scheme, SchemeProblem};
 pub use crate::service::ClientService;
 pub use crate::transaction::{
     LocalizedTransaction, PendingSignedTransactions, SignedTransaction, UnverifiedTransaction,
 };
-pub use crate::types::{BlockId, TransactionId};
+pub use crate::types::{BlockId, TransactionId, VerificationQueueInfo as BlockQueueInfo};
+pub use crate::verification::{BlockRejection, RejectionCode};
@@ -20,11 +20,12 @@ use kvdb::{DBTransaction, KeyValueDB};
 use primitives::H256;
 use rlp::Encodable;
 
-use super::mem_pool_types::MemPoolItem;
+use super::mem_pool_types::{MemPoolItem, ScheduledTransaction};
 use crate::db as dblib;
 
 const PREFIX_SIZE: usize = 5;
 const PREFIX_ITEM: &[u8; PREFIX_SIZE] = b"item_";
+const PREFIX_SCHEDULED: &[u8; PREFIX_SIZE] = b"schd_";
 
 pub fn backup_batch_with_capacity(length: usize) -> DBTransaction {
     DBTransaction::with_capacity(length)
@@ -42,10 +43,25 @@ pub fn remove_item(batch: &mut DBTransaction, key: &H256) {
     batch.delete(dblib::COL_MEMPOOL, db_key.as_ref());
 }
 
+pub fn backup_scheduled(batch: &mut DBTransaction, key: H256, scheduled: &ScheduledTransaction) {
+    let mut db_key = PREFIX_SCHEDULED.to_vec();
+    db_key.extend_from_slice(key.as_ref());
+    batch.put(dblib::COL_MEMPOOL, db_key.as_ref(), scheduled.rlp_bytes().as_ref());
+}
+
+pub fn remove_scheduled(batch: &mut DBTransaction, key: &H256) {
+    let mut db_key = PREFIX_SCHEDULED.to_vec();
+    db_key.extend_from_slice(key.as_ref());
+    batch.delete(dblib::COL_MEMPOOL, db_key.as_ref());
+}
+
 pub fn recover_to_data(db: &KeyValueDB) -> HashMap<H256, MemPoolItem> {
     let mut by_hash = HashMap::new();
 
-    for (key, value) in db.iter(dblib::COL_MEMPOOL) {
+    for (key, value) in db.iter_from_prefix(dblib::COL_MEMPOOL, PREFIX_ITEM) {
+        if !key.starts_with(PREFIX_ITEM) {
+            break
+        }
         let bytes = (*value).to_vec();
         let rlp = rlp::Rlp::new(&bytes);
 
@@ -56,3 +72,10 @@ pub fn recover_to_data(db: &KeyValueDB) -> HashMap<H256, MemPoolItem> {
 
     by_hash
 }
+
+pub fn recover_scheduled(db: &KeyValueDB) -> Vec<ScheduledTransaction> {
+    db.iter_from_prefix(dblib::COL_MEMPOOL, PREFIX_SCHEDULED)
+        .take_while(|(key, _)| key.starts_with(PREFIX_SCHEDULED))
+        .map(|(_, value)| rlp::decode(&value))
+        .collect()
+}
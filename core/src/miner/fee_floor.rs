@@ -0,0 +1,219 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::cmp;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// The floor stays at the minimum while the pool is filled less than this, in permille.
+const LOAD_THRESHOLD: u64 = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FeeFloorOptions {
+    /// The floor when the pool is not loaded.
+    pub min: u64,
+    /// The floor when the pool is full for the whole window.
+    pub max: u64,
+    /// How long the fullness of the pool is averaged.
+    pub window: Duration,
+    /// How often the floor is recomputed.
+    pub interval: Duration,
+    /// The transactions submitted over RPC don't need to pay the floor.
+    pub exempt_rpc: bool,
+}
+
+/// The current floor and the inputs it was computed from.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FeeFloorStatus {
+    pub floor: u64,
+    pub min: u64,
+    pub max: u64,
+    /// The average fullness of the pool in the window, in permille.
+    pub load: u64,
+    /// How much of the count limit the pool used when it was last sampled, in permille.
+    pub count_fullness: u64,
+    /// How much of the memory limit the pool used when it was last sampled, in permille.
+    pub memory_fullness: u64,
+    /// The number of the samples in the window.
+    pub samples: usize,
+}
+
+/// The minimum fee of the remote transactions, scaled by how full the mem pool has been.
+///
+/// The fullness is sampled whenever the floor is recomputed, at most once an interval.
+/// Each recomputation moves the floor halfway to the target, which grows linearly
+/// from `min` to `max` as the average fullness goes from `LOAD_THRESHOLD` to 1000 permille.
+#[derive(Clone)]
+pub struct FeeFloor {
+    options: FeeFloorOptions,
+    floor: u64,
+    samples: VecDeque<(Instant, u64)>,
+    count_fullness: u64,
+    memory_fullness: u64,
+    recomputed_at: Option<Instant>,
+}
+
+impl FeeFloor {
+    pub fn new(options: FeeFloorOptions) -> Self {
+        assert!(options.min <= options.max, "The minimum fee floor must not exceed the maximum");
+        Self {
+            options,
+            floor: options.min,
+            samples: VecDeque::new(),
+            count_fullness: 0,
+            memory_fullness: 0,
+            recomputed_at: None,
+        }
+    }
+
+    pub fn floor(&self) -> u64 {
+        self.floor
+    }
+
+    pub fn exempts_rpc(&self) -> bool {
+        self.options.exempt_rpc
+    }
+
+    /// Samples the fullness, in permille, and recomputes the floor if an interval has passed.
+    pub fn update(&mut self, now: Instant, count_fullness: u64, memory_fullness: u64) {
+        if let Some(recomputed_at) = self.recomputed_at {
+            if now.duration_since(recomputed_at) < self.options.interval {
+                return
+            }
+        }
+        self.recomputed_at = Some(now);
+        self.count_fullness = count_fullness;
+        self.memory_fullness = memory_fullness;
+        self.samples.push_back((now, cmp::min(1000, cmp::max(count_fullness, memory_fullness))));
+        while let Some((sampled_at, _)) = self.samples.front() {
+            if now.duration_since(*sampled_at) < self.options.window {
+                break
+            }
+            self.samples.pop_front();
+        }
+
+        let target = self.target(self.load());
+        self.floor = if target > self.floor {
+            target - (target - self.floor) / 2
+        } else {
+            target + (self.floor - target) / 2
+        };
+    }
+
+    pub fn status(&self) -> FeeFloorStatus {
+        FeeFloorStatus {
+            floor: self.floor,
+            min: self.options.min,
+            max: self.options.max,
+            load: self.load(),
+            count_fullness: self.count_fullness,
+            memory_fullness: self.memory_fullness,
+            samples: self.samples.len(),
+        }
+    }
+
+    fn load(&self) -> u64 {
+        if self.samples.is_empty() {
+            return 0
+        }
+        self.samples.iter().map(|(_, fullness)| fullness).sum::<u64>() / self.samples.len() as u64
+    }
+
+    fn target(&self, load: u64) -> u64 {
+        if load <= LOAD_THRESHOLD {
+            return self.options.min
+        }
+        let range = u128::from(self.options.max - self.options.min);
+        let scaled = range * u128::from(load - LOAD_THRESHOLD) / u128::from(1000 - LOAD_THRESHOLD);
+        self.options.min + scaled as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> FeeFloorOptions {
+        FeeFloorOptions {
+            min: 100,
+            max: 10_000,
+            window: Duration::from_secs(30),
+            interval: Duration::from_secs(10),
+            exempt_rpc: false,
+        }
+    }
+
+    #[test]
+    fn floor_stays_at_the_minimum_under_the_threshold() {
+        let mut fee_floor = FeeFloor::new(options());
+        let start = Instant::now();
+        for i in 0..10 {
+            fee_floor.update(start + Duration::from_secs(10 * i), 400, 500);
+            assert_eq!(100, fee_floor.floor());
+        }
+    }
+
+    #[test]
+    fn floor_is_recomputed_once_an_interval() {
+        let mut fee_floor = FeeFloor::new(options());
+        let start = Instant::now();
+        fee_floor.update(start, 1000, 0);
+        let raised = fee_floor.floor();
+        assert!(raised > 100);
+
+        fee_floor.update(start + Duration::from_secs(9), 1000, 0);
+        assert_eq!(raised, fee_floor.floor());
+        assert_eq!(1, fee_floor.status().samples);
+
+        fee_floor.update(start + Duration::from_secs(10), 1000, 0);
+        assert!(fee_floor.floor() > raised);
+    }
+
+    #[test]
+    fn floor_rises_under_flood_and_decays_after_it() {
+        let mut fee_floor = FeeFloor::new(options());
+        let start = Instant::now();
+        let mut now = start;
+        let mut previous = fee_floor.floor();
+        for _ in 0..20 {
+            fee_floor.update(now, 1000, 1000);
+            assert!(fee_floor.floor() >= previous);
+            previous = fee_floor.floor();
+            now += Duration::from_secs(10);
+        }
+        assert_eq!(10_000, fee_floor.floor());
+        assert_eq!(1000, fee_floor.status().load);
+
+        for _ in 0..20 {
+            fee_floor.update(now, 0, 0);
+            assert!(fee_floor.floor() <= previous);
+            previous = fee_floor.floor();
+            now += Duration::from_secs(10);
+        }
+        assert_eq!(
+            FeeFloorStatus {
+                floor: 100,
+                min: 100,
+                max: 10_000,
+                load: 0,
+                count_fullness: 0,
+                memory_fullness: 0,
+                samples: 3,
+            },
+            fee_floor.status()
+        );
+    }
+}
@@ -14,22 +14,26 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use std::collections::{BTreeSet, HashMap, HashSet};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet};
 use std::ops::Range;
 use std::sync::Arc;
+use std::time::Instant;
 
-use ckey::{public_to_address, Public};
+use ckey::{public_to_address, Address, Public};
+use cmetrics::METRICS;
 use ctypes::errors::{HistoryError, RuntimeError, SyntaxError};
 use ctypes::BlockNumber;
 use kvdb::{DBTransaction, KeyValueDB};
 use primitives::H256;
-use rlp;
 use table::Table;
 
 use super::backup;
+use super::fee_floor::{FeeFloor, FeeFloorOptions, FeeFloorStatus};
 use super::mem_pool_types::{
-    AccountDetails, CurrentQueue, FutureQueue, MemPoolInput, MemPoolItem, MemPoolStatus, PoolingInstant, QueueTag,
-    TransactionOrder, TransactionOrderWithTag, TxOrigin, TxTimelock,
+    AccountDetails, CurrentQueue, DropReason, FutureQueue, MemPoolInput, MemPoolItem, MemPoolStatus, PendingQueue,
+    PooledTransaction, PoolingInstant, QueueTag, QueuedTransaction, ScheduledQueue, ScheduledTransaction,
+    SignerDiagnosis, TransactionOrder, TransactionOrderWithTag, TxOrigin, TxTimelock,
 };
 use super::TransactionImportResult;
 use crate::client::{AccountData, BlockChainTrait};
@@ -37,6 +41,15 @@ use crate::transaction::{PendingSignedTransactions, SignedTransaction};
 use crate::Error as CoreError;
 
 const DEFAULT_POOLING_PERIOD: BlockNumber = 128;
+/// How far ahead of the clock of the node `not_before` can be, in seconds
+pub const MAX_SCHEDULE_AHEAD: u64 = 7 * 24 * 60 * 60;
+
+fn fullness_in_permille(usage: usize, limit: usize) -> u64 {
+    if limit == 0 {
+        return 1000
+    }
+    (usage as u128 * 1000 / limit as u128) as u64
+}
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Error {
@@ -73,9 +86,12 @@ impl From<SyntaxError> for Error {
     }
 }
 
+#[derive(Clone)]
 pub struct MemPool {
     /// Fee threshold for transactions that can be imported to this pool (defaults to 0)
     minimal_fee: u64,
+    /// Fee threshold for transactions submitted over RPC, which replaces `minimal_fee` for them (defaults to 0)
+    rpc_minimal_fee: u64,
     /// A value which is used to check whether a new transaciton can replace a transaction in the memory pool with the same signer and seq.
     /// If the fee of the new transaction is `new_fee` and the fee of the transaction in the memory pool is `old_fee`,
     /// then `new_fee > old_fee + old_fee >> mem_pool_fee_bump_shift` should be satisfied to replace.
@@ -89,6 +105,8 @@ pub struct MemPool {
     current: CurrentQueue,
     /// Priority queue for transactions that has been received but are not yet valid to go to block
     future: FutureQueue,
+    /// Transactions that enter the other queues when the clock of the node passes their `not_before`
+    scheduled: ScheduledQueue,
     /// All transactions managed by pool indexed by public and seq
     by_signer_public: Table<Public, u64, TransactionOrderWithTag>,
     /// The count(number) limit of each queue
@@ -111,6 +129,12 @@ pub struct MemPool {
     next_transaction_id: u64,
     /// Arc of KeyValueDB in which the backup information is stored.
     db: Arc<KeyValueDB>,
+    /// Transactions that left the pool without being mined, since the last `take_dropped`
+    dropped: Vec<(H256, DropReason)>,
+    /// Orders the top transactions only by their contents, so the same pool gives the same proposal on every node
+    deterministic_ordering: bool,
+    /// Raises the minimum fee of the remote transactions while the pool is loaded
+    fee_floor: Option<FeeFloor>,
 }
 
 impl MemPool {
@@ -118,10 +142,12 @@ impl MemPool {
     pub fn with_limits(limit: usize, memory_limit: usize, fee_bump_shift: usize, db: Arc<KeyValueDB>) -> Self {
         MemPool {
             minimal_fee: 0,
+            rpc_minimal_fee: 0,
             fee_bump_shift,
             max_block_number_period_in_pool: DEFAULT_POOLING_PERIOD,
             current: CurrentQueue::new(),
             future: FutureQueue::new(),
+            scheduled: ScheduledQueue::new(),
             by_signer_public: Table::new(),
             queue_count_limit: limit,
             queue_memory_limit: memory_limit,
@@ -133,6 +159,9 @@ impl MemPool {
             last_timestamp: 0,
             next_transaction_id: 0,
             db,
+            dropped: Vec::new(),
+            deterministic_ordering: false,
+            fee_floor: None,
         }
     }
 
@@ -181,6 +210,7 @@ impl MemPool {
             let hash = order.hash;
             let item = self.by_hash.remove(&hash).expect("`by_hash` and `current/future` should be synced");
             backup::remove_item(batch, &hash);
+            self.dropped.push((hash, DropReason::Evicted));
             let signer_public = item.signer_public();
             let seq = item.seq();
             self.by_signer_public
@@ -213,6 +243,38 @@ impl MemPool {
         self.minimal_fee = min_fee;
     }
 
+    /// Sets new fee threshold for transactions submitted over RPC.
+    /// Any transaction already imported to the pool is not affected.
+    pub fn set_rpc_minimal_fee(&mut self, min_fee: u64) {
+        self.rpc_minimal_fee = min_fee;
+    }
+
+    /// Enables the fee floor that follows the load of the pool.
+    pub fn set_fee_floor(&mut self, options: FeeFloorOptions) {
+        self.fee_floor = Some(FeeFloor::new(options));
+    }
+
+    /// Samples the fullness of the pool and recomputes the fee floor if its interval has passed.
+    pub fn update_fee_floor(&mut self, now: Instant) {
+        let count = ::std::cmp::max(self.current.count, self.future.count);
+        let mem_usage = ::std::cmp::max(self.current.mem_usage, self.future.mem_usage);
+        let count_fullness = fullness_in_permille(count, self.queue_count_limit);
+        let memory_fullness = fullness_in_permille(mem_usage, self.queue_memory_limit);
+        if let Some(fee_floor) = self.fee_floor.as_mut() {
+            fee_floor.update(now, count_fullness, memory_fullness);
+        }
+    }
+
+    /// Returns None if the fee floor is not enabled.
+    pub fn fee_floor(&self) -> Option<FeeFloorStatus> {
+        self.fee_floor.as_ref().map(FeeFloor::status)
+    }
+
+    /// Sets whether `top_transactions` ignores when and from where the transactions came.
+    pub fn set_deterministic_ordering(&mut self, deterministic_ordering: bool) {
+        self.deterministic_ordering = deterministic_ordering;
+    }
+
     /// Get one more than the lowest fee in the pool iff the pool is
     /// full, otherwise 0.
     pub fn effective_minimum_fee(&self) -> u64 {
@@ -245,10 +307,27 @@ impl MemPool {
     where
         F: Fn(&Public) -> AccountDetails, {
         ctrace!(MEM_POOL, "add() called, time: {}, timestamp: {}", inserted_block_number, inserted_timestamp);
+        let mut batch = backup::backup_batch_with_capacity(inputs.len());
+        let results = self.insert(inputs, inserted_block_number, inserted_timestamp, fetch_account, &mut batch);
+        self.update_metrics();
+        self.db.write(batch).expect("Low level database error. Some issue with disk?");
+        results
+    }
+
+    /// Inserts the transactions and enforces the limits. The changes of the backup are written to `batch`.
+    fn insert<F>(
+        &mut self,
+        inputs: Vec<MemPoolInput>,
+        inserted_block_number: PoolingInstant,
+        inserted_timestamp: u64,
+        fetch_account: &F,
+        batch: &mut DBTransaction,
+    ) -> Vec<Result<TransactionImportResult, Error>>
+    where
+        F: Fn(&Public) -> AccountDetails, {
         let mut insert_results = Vec::new();
         let mut to_insert: HashMap<Public, Vec<u64>> = HashMap::new();
         let mut new_local_accounts = HashSet::new();
-        let mut batch = backup::backup_batch_with_capacity(inputs.len());
 
         for input in inputs {
             let tx = input.transaction;
@@ -261,7 +340,9 @@ impl MemPool {
                 self.is_local_account.insert(signer_public);
                 new_local_accounts.insert(signer_public);
                 TxOrigin::Local
-            } else if input.origin.is_external() && self.is_local_account.contains(&signer_public) {
+            } else if (input.origin.is_external() || input.origin.is_rpc())
+                && self.is_local_account.contains(&signer_public)
+            {
                 TxOrigin::Local
             } else {
                 input.origin
@@ -279,7 +360,7 @@ impl MemPool {
             let order = TransactionOrder::for_transaction(&item, client_account.seq);
             let order_with_tag = TransactionOrderWithTag::new(order, QueueTag::New);
 
-            backup::backup_item(&mut batch, hash, &item);
+            backup::backup_item(batch, hash, &item);
             self.by_hash.insert(hash, item);
 
             if let Some(old_order_with_tag) = self.by_signer_public.insert(signer_public, seq, order_with_tag) {
@@ -287,7 +368,8 @@ impl MemPool {
                 let tag = old_order_with_tag.tag;
 
                 self.by_hash.remove(&old_order.hash);
-                backup::remove_item(&mut batch, &old_order.hash);
+                backup::remove_item(batch, &old_order.hash);
+                self.dropped.push((old_order.hash, DropReason::Replaced));
 
                 match tag {
                     QueueTag::Current => {
@@ -331,7 +413,7 @@ impl MemPool {
             let is_this_account_local = new_local_accounts.contains(&public);
             // Need to update transactions because of height/origin change
             if current_seq != first_seq || is_this_account_local {
-                self.update_orders(public, current_seq, new_next_seq, is_this_account_local, &mut batch);
+                self.update_orders(public, current_seq, new_next_seq, is_this_account_local, batch);
                 self.first_seqs.insert(public, current_seq);
                 first_seq = current_seq;
             }
@@ -357,7 +439,7 @@ impl MemPool {
             }
         }
 
-        self.enforce_limit(&mut batch);
+        self.enforce_limit(batch);
 
         self.last_block_number = inserted_block_number;
         self.last_timestamp = inserted_timestamp;
@@ -366,7 +448,6 @@ impl MemPool {
         assert_eq!(self.current.fee_counter.values().sum::<usize>(), self.current.len());
         assert_eq!(self.by_signer_public.len(), self.by_hash.len());
 
-        self.db.write(batch).expect("Low level database error. Some issue with disk?");
         insert_results
             .into_iter()
             .map(|v| match v {
@@ -383,6 +464,120 @@ impl MemPool {
             .collect()
     }
 
+    /// Adds all the transactions, or none of them if any of them is rejected.
+    /// The transactions of the same signer must have consecutive seqs in the given order.
+    ///
+    /// The transactions are inserted into a copy of the pool first, so that neither the pool nor the backup
+    /// changes unless the limits of the pool accept all of them.
+    pub fn add_all_or_nothing<F>(
+        &mut self,
+        inputs: Vec<MemPoolInput>,
+        inserted_block_number: PoolingInstant,
+        inserted_timestamp: u64,
+        fetch_account: &F,
+    ) -> Result<Vec<TransactionImportResult>, Vec<Option<Error>>>
+    where
+        F: Fn(&Public) -> AccountDetails, {
+        let errors = self.verify_batch(&inputs, fetch_account);
+        if errors.iter().any(Option::is_some) {
+            return Err(errors)
+        }
+
+        let mut scratch = self.clone();
+        let mut batch = backup::backup_batch_with_capacity(inputs.len());
+        let results = scratch.insert(inputs, inserted_block_number, inserted_timestamp, fetch_account, &mut batch);
+        if results.iter().any(Result::is_err) {
+            // Only the limit of the pool can reject the verified transactions.
+            return Err(results.into_iter().map(Result::err).collect())
+        }
+        *self = scratch;
+        self.update_metrics();
+        self.db.write(batch).expect("Low level database error. Some issue with disk?");
+        Ok(results.into_iter().map(Result::unwrap).collect())
+    }
+
+    fn verify_batch<F>(&self, inputs: &[MemPoolInput], fetch_account: &F) -> Vec<Option<Error>>
+    where
+        F: Fn(&Public) -> AccountDetails, {
+        let mut hashes = HashSet::new();
+        let mut next_seqs: HashMap<Public, u64> = HashMap::new();
+        inputs
+            .iter()
+            .map(|input| {
+                let tx = &input.transaction;
+                let signer_public = tx.signer_public();
+                let origin = if (input.origin.is_external() || input.origin.is_rpc())
+                    && self.is_local_account.contains(&signer_public)
+                {
+                    TxOrigin::Local
+                } else {
+                    input.origin
+                };
+                let expected_seq = next_seqs.insert(signer_public, tx.seq + 1);
+
+                if let Err(e) = self.verify_transaction(tx, origin, &fetch_account(&signer_public)) {
+                    return Some(e)
+                }
+                if !hashes.insert(tx.hash()) {
+                    return Some(HistoryError::TransactionAlreadyImported.into())
+                }
+                match expected_seq {
+                    Some(expected) if expected != tx.seq => Some(
+                        HistoryError::NonConsecutiveSeq {
+                            expected,
+                            got: tx.seq,
+                        }
+                        .into(),
+                    ),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Keeps the transaction out of the other queues until the clock of the node passes its `not_before`.
+    /// The scheduled transactions are limited to the count and the memory limits of a queue,
+    /// and `not_before` can't be more than `MAX_SCHEDULE_AHEAD` after `now`.
+    pub fn schedule(&mut self, scheduled: ScheduledTransaction, now: u64) -> Result<(), Error> {
+        let hash = scheduled.hash();
+        if self.by_hash.contains_key(&hash) || self.scheduled.contains(&hash) {
+            return Err(HistoryError::TransactionAlreadyImported.into())
+        }
+        if scheduled.not_before > now.saturating_add(MAX_SCHEDULE_AHEAD) {
+            cdebug!(MEM_POOL, "{:?} is scheduled too far ahead: {}", hash, scheduled.not_before);
+            return Err(HistoryError::LimitReached.into())
+        }
+        if self.scheduled.len() >= self.queue_count_limit
+            || self.scheduled.mem_usage() + scheduled.mem_usage() > self.queue_memory_limit
+        {
+            return Err(HistoryError::LimitReached.into())
+        }
+
+        let mut batch = backup::backup_batch_with_capacity(1);
+        backup::backup_scheduled(&mut batch, hash, &scheduled);
+        self.scheduled.insert(scheduled);
+        self.db.write(batch).expect("Low level database error. Some issue with disk?");
+        Ok(())
+    }
+
+    /// Removes the scheduled transactions whose `not_before` has passed at `now`, so that they can be added to the pool.
+    pub fn take_due_scheduled(&mut self, now: u64) -> Vec<ScheduledTransaction> {
+        let due = self.scheduled.take_due(now);
+        if !due.is_empty() {
+            let mut batch = backup::backup_batch_with_capacity(due.len());
+            for scheduled in &due {
+                backup::remove_scheduled(&mut batch, &scheduled.hash());
+            }
+            self.db.write(batch).expect("Low level database error. Some issue with disk?");
+        }
+        due
+    }
+
+    /// Returns the scheduled transactions in the order they become eligible.
+    pub fn scheduled_transactions(&self) -> Vec<ScheduledTransaction> {
+        self.scheduled.iter().cloned().collect()
+    }
+
     /// Checks the current seq for all transactions' senders in the pool and removes the old transactions.
     /// Expired transactions are removed by this function only.
     pub fn remove_old<F>(&mut self, fetch_account: &F, current_block_number: PoolingInstant, current_timestamp: u64)
@@ -405,17 +600,19 @@ impl MemPool {
                 // In that case, transactions which are removed in here can be recovered.
                 if let Some(expiration) = item.expiration() {
                     if expiration < current_timestamp {
-                        return Some(*hash)
+                        return Some((*hash, DropReason::Expired))
                     }
                 }
 
                 if time_diff > max_block_number {
-                    return Some(*hash)
+                    return Some((*hash, DropReason::Outdated))
                 }
 
                 if time_diff > balance_check {
                     return match signers.get(&item.signer_public()) {
-                        Some(details) if item.cost() > details.balance => Some(*hash),
+                        Some(details) if item.cost() > details.balance => {
+                            Some((*hash, DropReason::InsufficientBalance))
+                        }
                         _ => None,
                     }
                 }
@@ -425,7 +622,24 @@ impl MemPool {
             .collect::<Vec<_>>();
         let fetch_seq =
             |a: &Public| signers.get(a).expect("We fetch details for all signers from both current and future").seq;
-        self.remove(&invalid, &fetch_seq, current_block_number, current_timestamp);
+        self.remove_with_reasons(&invalid, &fetch_seq, current_block_number, current_timestamp);
+
+        // The scheduled transactions whose seqs are used can't enter the other queues.
+        let stale = self.scheduled.remove_stale(|scheduled| {
+            let signer_public = scheduled.transaction.signer_public();
+            let seq = match signers.get(&signer_public) {
+                Some(details) => details.seq,
+                None => fetch_account(&signer_public).seq,
+            };
+            scheduled.transaction.seq < seq
+        });
+        if !stale.is_empty() {
+            let mut batch = backup::backup_batch_with_capacity(stale.len());
+            for scheduled in &stale {
+                backup::remove_scheduled(&mut batch, &scheduled.hash());
+            }
+            self.db.write(batch).expect("Low level database error. Some issue with disk?");
+        }
     }
 
     // Recover MemPool state from db stored data
@@ -439,6 +653,9 @@ impl MemPool {
             }
         };
         let by_hash = backup::recover_to_data(self.db.as_ref());
+        for scheduled in backup::recover_scheduled(self.db.as_ref()) {
+            self.scheduled.insert(scheduled);
+        }
 
         let recover_block_number = client.chain_info().best_block_number;
         let recover_timestamp = client.chain_info().best_block_timestamp;
@@ -499,6 +716,7 @@ impl MemPool {
         self.last_block_number = recover_block_number;
         self.last_timestamp = recover_timestamp;
         self.next_transaction_id = max_insertion_id + 1;
+        self.update_metrics();
     }
 
     /// Removes invalid transaction identified by hash from pool.
@@ -512,13 +730,25 @@ impl MemPool {
         fetch_seq: &F,
         current_block_number: PoolingInstant,
         current_timestamp: u64,
+    ) where
+        F: Fn(&Public) -> u64, {
+        let transactions: Vec<_> = transaction_hashes.iter().map(|hash| (*hash, DropReason::Invalid)).collect();
+        self.remove_with_reasons(&transactions, fetch_seq, current_block_number, current_timestamp);
+    }
+
+    fn remove_with_reasons<F>(
+        &mut self,
+        transactions: &[(H256, DropReason)],
+        fetch_seq: &F,
+        current_block_number: PoolingInstant,
+        current_timestamp: u64,
     ) where
         F: Fn(&Public) -> u64, {
         ctrace!(MEM_POOL, "remove() called, time: {}, timestamp: {}", current_block_number, current_timestamp);
         let mut removed: HashMap<_, _> = HashMap::new();
-        let mut batch = backup::backup_batch_with_capacity(transaction_hashes.len());
+        let mut batch = backup::backup_batch_with_capacity(transactions.len());
 
-        for hash in transaction_hashes {
+        for (hash, reason) in transactions {
             if let Some(item) = self.by_hash.get(hash).map(Clone::clone) {
                 let signer_public = item.signer_public();
                 let seq = item.seq();
@@ -537,6 +767,7 @@ impl MemPool {
 
                 self.by_hash.remove(hash);
                 backup::remove_item(&mut batch, hash);
+                self.dropped.push((*hash, *reason));
                 self.by_signer_public.remove(&signer_public, &seq);
                 if current_seq <= seq {
                     let old = removed.get(&signer_public).map(Clone::clone);
@@ -600,6 +831,7 @@ impl MemPool {
         assert_eq!(self.current.len() + self.future.len(), self.by_hash.len());
         assert_eq!(self.current.fee_counter.values().sum::<usize>(), self.current.len());
         assert_eq!(self.by_signer_public.len(), self.by_hash.len());
+        self.update_metrics();
 
         self.db.write(batch).expect("Low level database error. Some issue with disk?");
     }
@@ -753,6 +985,7 @@ impl MemPool {
             if seq < current_seq {
                 self.by_hash.remove(&old_order.hash);
                 backup::remove_item(batch, &old_order.hash);
+                self.dropped.push((old_order.hash, DropReason::SeqUsed));
             } else {
                 let new_order = old_order.update_height(seq, current_seq);
                 let new_order = if to_local {
@@ -782,24 +1015,36 @@ impl MemPool {
         origin: TxOrigin,
         client_account: &AccountDetails,
     ) -> Result<(), Error> {
-        if origin != TxOrigin::Local && tx.fee < self.minimal_fee {
+        let minimal_fee = if origin.is_rpc() {
+            self.rpc_minimal_fee
+        } else {
+            self.minimal_fee
+        };
+        let minimal_fee = match &self.fee_floor {
+            Some(fee_floor) if !origin.is_local_or_retracted() && !(origin.is_rpc() && fee_floor.exempts_rpc()) => {
+                ::std::cmp::max(minimal_fee, fee_floor.floor())
+            }
+            _ => minimal_fee,
+        };
+        if origin != TxOrigin::Local && tx.fee < minimal_fee {
             ctrace!(
                 MEM_POOL,
                 "Dropping transaction below minimal fee: {:?} (gp: {} < {})",
                 tx.hash(),
                 tx.fee,
-                self.minimal_fee
+                minimal_fee
             );
 
             return Err(SyntaxError::InsufficientFee {
-                minimal: self.minimal_fee,
+                minimal: minimal_fee,
                 got: tx.fee,
             }
             .into())
         }
 
+        // The RPC transactions evict the P2P transactions instead.
         let full_pools_lowest = self.effective_minimum_fee();
-        if origin != TxOrigin::Local && tx.fee < full_pools_lowest {
+        if !origin.is_local() && !origin.is_rpc() && tx.fee < full_pools_lowest {
             ctrace!(
                 MEM_POOL,
                 "Dropping transaction below lowest fee in a full pool: {:?} (gp: {} < {})",
@@ -869,53 +1114,87 @@ impl MemPool {
         Ok(())
     }
 
+    /// Returns the transactions that left the pool without being mined since the last call.
+    pub fn take_dropped(&mut self) -> Vec<(H256, DropReason)> {
+        ::std::mem::replace(&mut self.dropped, Vec::new())
+    }
+
     /// Removes all elements (in any state) from the pool
     #[allow(dead_code)]
     pub fn clear(&mut self) {
         self.current.clear();
         self.future.clear();
+        self.scheduled.clear();
         self.by_signer_public.clear();
         self.by_hash.clear();
         self.first_seqs.clear();
         self.next_seqs.clear();
+        self.update_metrics();
+    }
+
+    fn update_metrics(&self) {
+        METRICS.mem_pool_size.set(self.by_hash.len());
+        METRICS.mem_pool_memory.set(self.current.mem_usage + self.future.mem_usage);
     }
 
     /// Returns top transactions whose timestamp are in the given range from the pool ordered by priority.
+    ///
+    /// The transactions of a signer are returned in seq order. Among the signers, the one whose next transaction
+    /// pays the highest fee per byte goes first. An expired transaction excludes the later transactions of its signer.
+    ///
+    /// If the ordering is deterministic, the origin, the fee and the insertion order are not considered.
+    /// The ties of the fee per byte are broken by the signer address, the lower first,
+    /// so the pools that have the same transactions return them in the same order.
     // FIXME: current_timestamp should be `u64`, not `Option<u64>`.
-    // FIXME: if range_contains becomes stable, use range.contains instead of inequality.
     pub fn top_transactions(
         &self,
         size_limit: usize,
         current_timestamp: Option<u64>,
         range: Range<u64>,
     ) -> PendingSignedTransactions {
-        let mut current_size: usize = 0;
-        let pending_items: Vec<_> = self
-            .current
-            .queue
-            .iter()
-            .map(|t| {
-                self.by_hash
-                    .get(&t.hash)
-                    .expect("All transactions in `current` and `future` are always included in `by_hash`")
-            })
-            .filter(|t| {
-                if let Some(expiration) = t.expiration() {
-                    if let Some(timestamp) = current_timestamp {
-                        return expiration >= timestamp
-                    }
+        let mut by_signer: HashMap<Public, Vec<(&TransactionOrder, &MemPoolItem)>> = HashMap::new();
+        for order in &self.current.queue {
+            let item = self
+                .by_hash
+                .get(&order.hash)
+                .expect("All transactions in `current` and `future` are always included in `by_hash`");
+            by_signer.entry(item.signer_public()).or_insert_with(Vec::new).push((order, item));
+        }
+        let mut cursors: BinaryHeap<SignerCursor> = by_signer
+            .into_iter()
+            .map(|(signer_public, mut remaining)| {
+                remaining.sort_unstable_by(|(_, a), (_, b)| b.seq().cmp(&a.seq()));
+                SignerCursor {
+                    remaining,
+                    signer: public_to_address(&signer_public),
+                    deterministic: self.deterministic_ordering,
                 }
-                true
-            })
-            .filter(|t| range.contains(&t.inserted_timestamp))
-            .take_while(|t| {
-                let encoded_byte_array: Vec<u8> = rlp::encode(&t.tx).into_vec();
-                let size_in_byte = encoded_byte_array.len();
-                current_size += size_in_byte;
-                current_size < size_limit
             })
             .collect();
 
+        let mut current_size: usize = 0;
+        let mut pending_items = Vec::new();
+        while let Some(mut cursor) = cursors.pop() {
+            let (order, item) = cursor.remaining.pop().expect("An empty cursor is not pushed into the heap");
+            let is_expired = match (item.expiration(), current_timestamp) {
+                (Some(expiration), Some(timestamp)) => expiration < timestamp,
+                _ => false,
+            };
+            if is_expired {
+                continue
+            }
+            if range.contains(&item.inserted_timestamp) {
+                current_size += order.mem_usage;
+                if current_size >= size_limit {
+                    break
+                }
+                pending_items.push(item);
+            }
+            if !cursor.remaining.is_empty() {
+                cursors.push(cursor);
+            }
+        }
+
         let transactions = pending_items.iter().map(|t| t.tx.clone()).collect();
         let last_timestamp = pending_items.into_iter().map(|t| t.inserted_timestamp).max();
 
@@ -958,6 +1237,97 @@ impl MemPool {
         self.current.queue.iter().any(|tx| tx.origin.is_local())
     }
 
+    /// Returns the queue that holds the transaction, or None if the transaction is not in the pool.
+    pub fn pending_queue(&self, hash: &H256) -> Option<PendingQueue> {
+        let item = self.by_hash.get(hash)?;
+        let order = self.by_signer_public.get(&item.signer_public(), &item.seq())?;
+        match order.tag {
+            QueueTag::Current => Some(PendingQueue::Current),
+            QueueTag::Future => Some(PendingQueue::Future),
+            QueueTag::New => unreachable!(),
+        }
+    }
+
+    /// Returns the transactions of the signers with their origins, ordered by seq.
+    pub fn transactions_by_signer<F>(&self, is_signer: F) -> Vec<PooledTransaction>
+    where
+        F: Fn(&Public) -> bool, {
+        let mut transactions: Vec<_> = self
+            .by_signer_public
+            .keys()
+            .filter(|public| is_signer(*public))
+            .flat_map(|public| self.by_signer_public.row(public).expect("The key exists").values())
+            .map(|order_with_tag| {
+                let item = &self.by_hash[&order_with_tag.order.hash];
+                PooledTransaction {
+                    transaction: item.tx.clone(),
+                    origin: item.origin,
+                    is_pending: order_with_tag.tag == QueueTag::Current,
+                }
+            })
+            .collect();
+        transactions.sort_by_key(|tx| (tx.transaction.seq, tx.transaction.hash()));
+        transactions
+    }
+
+    /// Diagnoses the transactions of an account whose seq is `seq`.
+    /// `is_signer` decides whether the public key signs the transactions of the account.
+    pub fn diagnose_signer<F>(&self, is_signer: F, seq: u64) -> SignerDiagnosis
+    where
+        F: Fn(&Public) -> bool, {
+        let fee_bump_shift = self.fee_bump_shift;
+        let by_signer_public = &self.by_signer_public;
+        let mut transactions: Vec<_> = by_signer_public
+            .keys()
+            .filter(|public| is_signer(*public))
+            .flat_map(|public| {
+                by_signer_public.row(public).expect("The key exists").iter().map(move |(seq, order_with_tag)| {
+                    let order = order_with_tag.order;
+                    QueuedTransaction {
+                        hash: order.hash,
+                        signer_public: *public,
+                        seq: *seq,
+                        fee: order.fee,
+                        is_pending: order_with_tag.tag == QueueTag::Current,
+                        min_fee_to_replace: order.fee + (order.fee >> fee_bump_shift),
+                    }
+                })
+            })
+            .collect();
+        transactions.sort_by_key(|tx| (tx.seq, tx.hash));
+
+        let mut gaps = Vec::new();
+        let mut competing_seqs = Vec::new();
+        let mut next_seq = seq;
+        let mut last_seq = None;
+        for tx in &transactions {
+            if last_seq == Some(tx.seq) {
+                if competing_seqs.last() != Some(&tx.seq) {
+                    competing_seqs.push(tx.seq);
+                }
+                continue
+            }
+            if tx.seq > next_seq {
+                gaps.push((next_seq, tx.seq - 1));
+            }
+            next_seq = ::std::cmp::max(next_seq, tx.seq + 1);
+            last_seq = Some(tx.seq);
+        }
+
+        SignerDiagnosis {
+            seq,
+            lowest_queued_seq: transactions.first().map(|tx| tx.seq),
+            highest_queued_seq: transactions.last().map(|tx| tx.seq),
+            gaps,
+            competing_seqs,
+            min_fee: ::std::cmp::max(
+                ::std::cmp::max(self.minimal_fee, self.effective_minimum_fee()),
+                self.fee_floor.as_ref().map_or(0, FeeFloor::floor),
+            ),
+            transactions,
+        }
+    }
+
     /// Checks the given timelock with the current time/timestamp.
     fn should_wait_timelock(timelock: &TxTimelock, best_block_number: BlockNumber, best_block_timestamp: u64) -> bool {
         if let Some(block_number) = timelock.block {
@@ -974,28 +1344,92 @@ impl MemPool {
     }
 }
 
+/// The transactions of a signer which are not selected yet.
+struct SignerCursor<'a> {
+    /// In the descending order of seq, so the next transaction is the last one.
+    remaining: Vec<(&'a TransactionOrder, &'a MemPoolItem)>,
+    signer: Address,
+    deterministic: bool,
+}
+
+#[derive(Eq, Ord, PartialEq, PartialOrd)]
+enum CursorPriority {
+    /// The local transactions first, then the fee per byte, the fee and the insertion order.
+    Arrival(Reverse<u8>, u64, u64, Reverse<u64>),
+    /// The fee per byte, then the signer address.
+    Deterministic(u64, Reverse<Address>),
+}
+
+impl<'a> SignerCursor<'a> {
+    fn priority(&self) -> CursorPriority {
+        let (order, _) = self.remaining.last().expect("An empty cursor is not pushed into the heap");
+        if self.deterministic {
+            CursorPriority::Deterministic(order.fee_per_byte, Reverse(self.signer))
+        } else {
+            CursorPriority::Arrival(
+                Reverse(order.origin.rank()),
+                order.fee_per_byte,
+                order.fee,
+                Reverse(order.insertion_id),
+            )
+        }
+    }
+}
+
+impl<'a> Ord for SignerCursor<'a> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority().cmp(&other.priority())
+    }
+}
+
+impl<'a> PartialOrd for SignerCursor<'a> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a> PartialEq for SignerCursor<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<'a> Eq for SignerCursor<'a> {}
 
 #[cfg(test)]
 pub mod test {
     use std::cmp::Ordering;
+    use std::thread;
+    use std::time::Duration;
 
     use crate::client::{AccountData, TestBlockChainClient};
     use ckey::{Generator, KeyPair, Random};
     use ctypes::transaction::{Action, AssetMintOutput, Transaction};
+    use parking_lot::RwLock;
     use primitives::H160;
 
     use super::*;
     use rlp::rlp_encode_and_decode_test;
 
+    const P2P: TxOrigin = TxOrigin::P2p {
+        peer: None,
+    };
+    const RPC: TxOrigin = TxOrigin::Rpc {
+        source: None,
+    };
+
     #[test]
     fn origin_ordering() {
-        assert_eq!(TxOrigin::Local.cmp(&TxOrigin::External), Ordering::Less);
+        assert_eq!(TxOrigin::Local.cmp(&P2P), Ordering::Less);
         assert_eq!(TxOrigin::RetractedBlock.cmp(&TxOrigin::Local), Ordering::Less);
-        assert_eq!(TxOrigin::RetractedBlock.cmp(&TxOrigin::External), Ordering::Less);
+        assert_eq!(TxOrigin::RetractedBlock.cmp(&P2P), Ordering::Less);
+        assert_eq!(TxOrigin::Local.cmp(&RPC), Ordering::Less);
+        assert_eq!(RPC.cmp(&P2P), Ordering::Less);
 
-        assert_eq!(TxOrigin::External.cmp(&TxOrigin::Local), Ordering::Greater);
+        assert_eq!(P2P.cmp(&TxOrigin::Local), Ordering::Greater);
         assert_eq!(TxOrigin::Local.cmp(&TxOrigin::RetractedBlock), Ordering::Greater);
-        assert_eq!(TxOrigin::External.cmp(&TxOrigin::RetractedBlock), Ordering::Greater);
+        assert_eq!(P2P.cmp(&TxOrigin::RetractedBlock), Ordering::Greater);
+        assert_eq!(P2P.cmp(&RPC), Ordering::Greater);
     }
 
     #[test]
@@ -1355,7 +1789,9 @@ pub mod test {
 
     #[test]
     fn txorigin_encode_and_decode() {
-        rlp_encode_and_decode_test!(TxOrigin::External);
+        rlp_encode_and_decode_test!(TxOrigin::Local);
+        rlp_encode_and_decode_test!(P2P);
+        rlp_encode_and_decode_test!(RPC);
     }
 
     #[test]
@@ -1605,4 +2041,629 @@ pub mod test {
 
         assert_eq!(vec![create_signed_pay(2, keypair),], mem_pool.future_transactions());
     }
+
+    fn pay_with_fee(seq: u64, fee: u64, keypair: &KeyPair) -> MemPoolInput {
+        let tx = Transaction {
+            seq,
+            fee,
+            network_id: "tc".into(),
+            action: Action::Pay {
+                receiver: 1u64.into(),
+                quantity: 100_000,
+            },
+        };
+        let no_timelock = TxTimelock {
+            block: None,
+            timestamp: None,
+        };
+        MemPoolInput::new(SignedTransaction::new_with_sign(tx, keypair.private()), P2P, no_timelock)
+    }
+
+    fn rpc_pay_with_fee(seq: u64, fee: u64, keypair: &KeyPair) -> MemPoolInput {
+        let mut input = pay_with_fee(seq, fee, keypair);
+        input.origin = TxOrigin::Rpc {
+            source: Some("10.0.0.1".parse().unwrap()),
+        };
+        input
+    }
+
+    fn pool_with(account_seq: u64, inputs: Vec<MemPoolInput>) -> MemPool {
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), 3, db);
+        let fetch_account = |_: &Public| AccountDetails {
+            seq: account_seq,
+            balance: 1_000_000_000_000,
+        };
+        for result in mem_pool.add(inputs, 1, 100, &fetch_account) {
+            assert!(result.is_ok(), "{:?}", result);
+        }
+        mem_pool
+    }
+
+    #[test]
+    fn diagnose_signer_without_transactions() {
+        let keypair = Random.generate().unwrap();
+        let mem_pool = pool_with(3, vec![]);
+        let diagnosis = mem_pool.diagnose_signer(|public| public == keypair.public(), 3);
+        assert_eq!(None, diagnosis.lowest_queued_seq);
+        assert_eq!(Vec::<(u64, u64)>::new(), diagnosis.gaps);
+        assert_eq!(
+            vec!["No transaction of the account is in the pool. The next seq is 3.".to_string()],
+            diagnosis.suggestions()
+        );
+    }
+
+    #[test]
+    fn diagnose_signer_with_gaps() {
+        let keypair = Random.generate().unwrap();
+        let other = Random.generate().unwrap();
+        let inputs = vec![
+            pay_with_fee(0, 100, &keypair),
+            pay_with_fee(2, 100, &keypair),
+            pay_with_fee(3, 100, &keypair),
+            pay_with_fee(7, 100, &keypair),
+            pay_with_fee(1, 100, &other),
+        ];
+        let mem_pool = pool_with(0, inputs);
+        let diagnosis = mem_pool.diagnose_signer(|public| public == keypair.public(), 0);
+
+        assert_eq!(Some(0), diagnosis.lowest_queued_seq);
+        assert_eq!(Some(7), diagnosis.highest_queued_seq);
+        assert_eq!(vec![(1, 1), (4, 6)], diagnosis.gaps);
+        assert_eq!(Vec::<u64>::new(), diagnosis.competing_seqs);
+        assert_eq!(
+            vec![true, false, false, false],
+            diagnosis.transactions.iter().map(|tx| tx.is_pending).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![
+                "Seq 1 is missing. The transactions after it wait until a transaction with seq 1 is sent.".to_string(),
+                "Seqs from 4 to 6 are missing. The transactions after them wait until transactions with these seqs are sent."
+                    .to_string(),
+            ],
+            diagnosis.suggestions()
+        );
+    }
+
+    #[test]
+    fn diagnose_signer_whose_next_seq_is_missing() {
+        let keypair = Random.generate().unwrap();
+        let inputs = vec![pay_with_fee(5, 100, &keypair), pay_with_fee(6, 100, &keypair)];
+        let mem_pool = pool_with(3, inputs);
+        let diagnosis = mem_pool.diagnose_signer(|public| public == keypair.public(), 3);
+
+        assert_eq!(Some(5), diagnosis.lowest_queued_seq);
+        assert_eq!(vec![(3, 4)], diagnosis.gaps);
+        assert!(diagnosis.transactions.iter().all(|tx| !tx.is_pending));
+    }
+
+    #[test]
+    fn diagnose_signer_with_competing_signers() {
+        let owner = Random.generate().unwrap();
+        let regular_key = Random.generate().unwrap();
+        let inputs = vec![pay_with_fee(1, 100, &owner), pay_with_fee(1, 200, &regular_key)];
+        let mem_pool = pool_with(1, inputs);
+        let diagnosis =
+            mem_pool.diagnose_signer(|public| public == owner.public() || public == regular_key.public(), 1);
+
+        assert_eq!(Vec::<(u64, u64)>::new(), diagnosis.gaps);
+        assert_eq!(vec![1], diagnosis.competing_seqs);
+        assert_eq!(2, diagnosis.transactions.len());
+        assert_eq!(
+            vec!["Seq 1 is used by transactions of different signers. Only one of them can be included.".to_string()],
+            diagnosis.suggestions()
+        );
+    }
+
+    #[test]
+    fn diagnose_signer_with_a_low_fee() {
+        let keypair = Random.generate().unwrap();
+        let mut mem_pool = pool_with(0, vec![pay_with_fee(0, 100, &keypair)]);
+        mem_pool.set_minimal_fee(110);
+        let diagnosis = mem_pool.diagnose_signer(|public| public == keypair.public(), 0);
+
+        assert_eq!(110, diagnosis.min_fee);
+        let tx = &diagnosis.transactions[0];
+        assert_eq!(100, tx.fee);
+        assert_eq!(112, tx.min_fee_to_replace);
+        assert_eq!(
+            vec![format!(
+                "The fee of {} is lower than the fee that the pool requires now. Send a transaction with seq 0 and a fee of at least 112 to replace it.",
+                tx.hash
+            )],
+            diagnosis.suggestions()
+        );
+    }
+
+    #[test]
+    fn pending_queue_follows_the_transaction() {
+        let keypair = Random.generate().unwrap();
+        let first = pay_with_fee(0, 100, &keypair);
+        let second = pay_with_fee(1, 100, &keypair);
+        let first_hash = first.transaction.hash();
+        let second_hash = second.transaction.hash();
+
+        let mut mem_pool = pool_with(0, vec![second]);
+        assert_eq!(Some(PendingQueue::Future), mem_pool.pending_queue(&second_hash));
+        assert_eq!(None, mem_pool.pending_queue(&first_hash));
+
+        // Filling the gap moves the waiting transaction to the current queue.
+        let fetch_account = |_: &Public| AccountDetails {
+            seq: 0,
+            balance: 1_000_000_000_000,
+        };
+        for result in mem_pool.add(vec![first], 2, 100, &fetch_account) {
+            assert!(result.is_ok(), "{:?}", result);
+        }
+        assert_eq!(Some(PendingQueue::Current), mem_pool.pending_queue(&first_hash));
+        assert_eq!(Some(PendingQueue::Current), mem_pool.pending_queue(&second_hash));
+
+        // The transactions leave the pool when they are mined.
+        mem_pool.remove(&[first_hash, second_hash], &|_: &Public| 2, 3, 100);
+        assert_eq!(None, mem_pool.pending_queue(&first_hash));
+        assert_eq!(None, mem_pool.pending_queue(&second_hash));
+    }
+
+    #[test]
+    fn dropped_transactions_are_taken_with_their_reasons() {
+        let keypair = Random.generate().unwrap();
+        let cheap = pay_with_fee(0, 100, &keypair);
+        let expensive = pay_with_fee(0, 200, &keypair);
+        let next = pay_with_fee(1, 100, &keypair);
+        let cheap_hash = cheap.transaction.hash();
+        let expensive_hash = expensive.transaction.hash();
+        let next_hash = next.transaction.hash();
+
+        let mut mem_pool = pool_with(0, vec![cheap, next]);
+        assert_eq!(Vec::<(H256, DropReason)>::new(), mem_pool.take_dropped());
+
+        let fetch_account = |_: &Public| AccountDetails {
+            seq: 0,
+            balance: 1_000_000_000_000,
+        };
+        for result in mem_pool.add(vec![expensive], 2, 100, &fetch_account) {
+            assert!(result.is_ok(), "{:?}", result);
+        }
+        assert_eq!(vec![(cheap_hash, DropReason::Replaced)], mem_pool.take_dropped());
+
+        // The seq 0 is used by a mined transaction, and the next one fails while preparing a block.
+        mem_pool.remove(&[next_hash], &|_: &Public| 1, 3, 100);
+        let mut dropped = mem_pool.take_dropped();
+        dropped.sort_by_key(|(_, reason)| reason.as_str());
+        assert_eq!(vec![(next_hash, DropReason::Invalid), (expensive_hash, DropReason::SeqUsed)], dropped);
+        assert_eq!(Vec::<(H256, DropReason)>::new(), mem_pool.take_dropped());
+    }
+
+    #[test]
+    fn batch_with_a_gap_is_not_inserted() {
+        let keypair = Random.generate().unwrap();
+        let batch =
+            vec![pay_with_fee(0, 100, &keypair), pay_with_fee(1, 100, &keypair), pay_with_fee(3, 100, &keypair)];
+
+        let mut mem_pool = pool_with(0, vec![]);
+        let fetch_account = |_: &Public| AccountDetails {
+            seq: 0,
+            balance: 1_000_000_000_000,
+        };
+        let errors = mem_pool.add_all_or_nothing(batch, 1, 100, &fetch_account).unwrap_err();
+        assert_eq!(
+            vec![
+                None,
+                None,
+                Some(
+                    HistoryError::NonConsecutiveSeq {
+                        expected: 2,
+                        got: 3,
+                    }
+                    .into()
+                )
+            ],
+            errors
+        );
+        assert!(mem_pool.by_hash.is_empty());
+    }
+
+    #[test]
+    fn batch_is_not_interleaved_with_a_conflicting_transaction() {
+        let keypair = Random.generate().unwrap();
+        let batch =
+            vec![pay_with_fee(0, 100, &keypair), pay_with_fee(1, 100, &keypair), pay_with_fee(2, 100, &keypair)];
+        let batch_hashes: Vec<_> = batch.iter().map(|input| input.transaction.hash()).collect();
+        // It takes the seq of the second transaction in the batch with a lower fee.
+        let conflicting = pay_with_fee(1, 50, &keypair);
+
+        let mem_pool = Arc::new(RwLock::new(pool_with(0, vec![])));
+        let fetch_account = |_: &Public| AccountDetails {
+            seq: 0,
+            balance: 1_000_000_000_000,
+        };
+        let single = {
+            let mem_pool = Arc::clone(&mem_pool);
+            thread::spawn(move || mem_pool.write().add(vec![conflicting], 1, 100, &fetch_account))
+        };
+        let result = mem_pool.write().add_all_or_nothing(batch, 1, 100, &fetch_account);
+        single.join().unwrap();
+
+        // Whichever comes first, the batch ends up in the pool as a whole.
+        assert_eq!(Ok(vec![TransactionImportResult::Current; 3]), result);
+        let queued: Vec<_> = mem_pool
+            .read()
+            .top_transactions(usize::max_value(), None, 0..u64::max_value())
+            .transactions
+            .iter()
+            .map(|tx| tx.hash())
+            .collect();
+        assert_eq!(batch_hashes, queued);
+    }
+
+    #[test]
+    fn batch_over_the_limit_changes_neither_the_pool_nor_the_backup() {
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(2, usize::max_value(), 3, db.clone());
+        let fetch_account = |_: &Public| AccountDetails {
+            seq: 0,
+            balance: 1_000_000_000_000,
+        };
+        // The batch would evict it if the batch were accepted.
+        let cheap = pay_with_fee(0, 10, &Random.generate().unwrap());
+        let cheap_hash = cheap.transaction.hash();
+        for result in mem_pool.add(vec![cheap], 1, 100, &fetch_account) {
+            assert!(result.is_ok(), "{:?}", result);
+        }
+
+        let keypair = Random.generate().unwrap();
+        let batch =
+            vec![pay_with_fee(0, 100, &keypair), pay_with_fee(1, 100, &keypair), pay_with_fee(2, 100, &keypair)];
+        let errors = mem_pool.add_all_or_nothing(batch, 1, 100, &fetch_account).unwrap_err();
+        assert!(errors.iter().any(Option::is_some));
+
+        assert_eq!(vec![cheap_hash], mem_pool.by_hash.keys().cloned().collect::<Vec<_>>());
+        assert_eq!(Vec::<(H256, DropReason)>::new(), mem_pool.take_dropped());
+        assert_eq!(vec![cheap_hash], backup::recover_to_data(db.as_ref()).keys().cloned().collect::<Vec<_>>());
+    }
+
+    fn collectable_fee(transactions: &[SignedTransaction]) -> u64 {
+        let mut next_seqs = HashMap::new();
+        let mut fee = 0;
+        for tx in transactions {
+            let next_seq = next_seqs.entry(tx.signer_public()).or_insert(0);
+            if tx.seq == *next_seq {
+                fee += tx.fee;
+                *next_seq += 1;
+            }
+        }
+        fee
+    }
+
+    #[test]
+    fn top_transactions_follow_seqs_and_fees() {
+        let a = Random.generate().unwrap();
+        let b = Random.generate().unwrap();
+        let c = Random.generate().unwrap();
+        let inputs = vec![
+            pay_with_fee(0, 100, &a),
+            pay_with_fee(1, 1000, &a),
+            pay_with_fee(2, 100, &a),
+            pay_with_fee(0, 500, &b),
+            pay_with_fee(1, 50, &b),
+            pay_with_fee(0, 300, &c),
+            pay_with_fee(1, 300, &c),
+        ];
+        let max_size = inputs.iter().map(|input| rlp::encode(&input.transaction).len()).max().unwrap();
+        let size_limit = max_size * 4 + 1;
+        let mem_pool = pool_with(0, inputs);
+
+        let selected = mem_pool.top_transactions(size_limit, None, 0..u64::max_value()).transactions;
+        assert_eq!(4, selected.len());
+        assert_eq!(selected.iter().map(|tx| tx.fee).sum::<u64>(), collectable_fee(&selected));
+
+        // The transactions in the order of the queue
+        let mut current_size = 0;
+        let in_queue_order: Vec<_> = mem_pool
+            .current
+            .queue
+            .iter()
+            .map(|order| mem_pool.by_hash[&order.hash].tx.clone())
+            .take_while(|tx| {
+                current_size += rlp::encode(tx).len();
+                current_size < size_limit
+            })
+            .collect();
+        assert!(collectable_fee(&selected) >= collectable_fee(&in_queue_order));
+
+        let all = mem_pool.top_transactions(usize::max_value(), None, 0..u64::max_value()).transactions;
+        assert_eq!(7, all.len());
+        assert_eq!(2350, collectable_fee(&all));
+    }
+
+    #[test]
+    fn deterministic_ordering_does_not_depend_on_the_insertion_order() {
+        let a = Random.generate().unwrap();
+        let b = Random.generate().unwrap();
+        let c = Random.generate().unwrap();
+        // The next transactions of a, b and c pay the same fee per byte, so only the signers break the ties.
+        let inputs = vec![
+            pay_with_fee(0, 300, &a),
+            pay_with_fee(1, 100, &a),
+            pay_with_fee(0, 300, &b),
+            pay_with_fee(1, 500, &b),
+            pay_with_fee(0, 300, &c),
+            rpc_pay_with_fee(1, 300, &c),
+            pay_with_fee(2, 700, &c),
+        ];
+        let mut reordered = inputs.clone();
+        reordered.reverse();
+        reordered[0].origin = TxOrigin::Local;
+
+        let mut first = pool_with(0, inputs.clone());
+        first.set_deterministic_ordering(true);
+        let mut second = pool_with(0, vec![]);
+        second.set_deterministic_ordering(true);
+        let fetch_account = |_: &Public| AccountDetails {
+            seq: 0,
+            balance: 1_000_000_000_000,
+        };
+        // One by one, so the insertion ids and the pooling instants differ from the first pool.
+        for (index, input) in reordered.into_iter().enumerate() {
+            let results = second.add(vec![input], 2 + index as u64, 200 + index as u64, &fetch_account);
+            assert!(results[0].is_ok(), "{:?}", results);
+        }
+
+        let max_size = inputs.iter().map(|input| rlp::encode(&input.transaction).len()).max().unwrap();
+        for size_limit in &[usize::max_value(), max_size * 4 + 1] {
+            let first_body = first.top_transactions(*size_limit, None, 0..u64::max_value()).transactions;
+            let second_body = second.top_transactions(*size_limit, None, 0..u64::max_value()).transactions;
+            assert_eq!(rlp::encode_list(&first_body), rlp::encode_list(&second_body));
+        }
+
+        let all = first.top_transactions(usize::max_value(), None, 0..u64::max_value()).transactions;
+        assert_eq!(inputs.len(), all.len());
+        assert_eq!(all.iter().map(|tx| tx.fee).sum::<u64>(), collectable_fee(&all));
+        // The first transactions of the signers pay the same fee per byte, so they follow the signer addresses.
+        let first_signers: Vec<_> = all.iter().filter(|tx| tx.seq == 0).map(|tx| tx.signer_public()).collect();
+        let mut sorted = first_signers.clone();
+        sorted.sort_by_key(public_to_address);
+        assert_eq!(sorted, first_signers);
+    }
+
+    #[test]
+    fn rpc_transactions_have_their_own_fee_floor() {
+        let keypair = Random.generate().unwrap();
+        let mut mem_pool = pool_with(0, vec![]);
+        mem_pool.set_minimal_fee(200);
+        mem_pool.set_rpc_minimal_fee(50);
+        let fetch_account = |_: &Public| AccountDetails {
+            seq: 0,
+            balance: 1_000_000_000_000,
+        };
+
+        let results = mem_pool.add(
+            vec![pay_with_fee(0, 100, &keypair), rpc_pay_with_fee(0, 40, &keypair), rpc_pay_with_fee(0, 100, &keypair)],
+            1,
+            100,
+            &fetch_account,
+        );
+        assert_eq!(
+            vec![
+                Err(SyntaxError::InsufficientFee {
+                    minimal: 200,
+                    got: 100,
+                }
+                .into()),
+                Err(SyntaxError::InsufficientFee {
+                    minimal: 50,
+                    got: 40,
+                }
+                .into()),
+                Ok(TransactionImportResult::Current),
+            ],
+            results
+        );
+    }
+
+    #[test]
+    fn fee_floor_follows_the_load_of_the_pool() {
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(10, usize::max_value(), 3, db);
+        mem_pool.set_minimal_fee(100);
+        mem_pool.set_fee_floor(FeeFloorOptions {
+            min: 100,
+            max: 10_000,
+            window: Duration::from_secs(30),
+            interval: Duration::from_secs(10),
+            exempt_rpc: false,
+        });
+        let fetch_account = |_: &Public| AccountDetails {
+            seq: 0,
+            balance: 1_000_000_000_000,
+        };
+        let start = Instant::now();
+        mem_pool.update_fee_floor(start);
+        assert_eq!(100, mem_pool.fee_floor().unwrap().floor);
+
+        // The flood fills the pool.
+        let flood: Vec<_> = (0..10).map(|_| pay_with_fee(0, 20_000, &Random.generate().unwrap())).collect();
+        for result in mem_pool.add(flood, 1, 100, &fetch_account) {
+            assert!(result.is_ok(), "{:?}", result);
+        }
+        for i in 1..=3 {
+            mem_pool.update_fee_floor(start + Duration::from_secs(10 * i));
+        }
+        let status = mem_pool.fee_floor().unwrap();
+        assert_eq!(1000, status.count_fullness);
+        let floor = status.floor;
+        assert!(floor > 5_000, "{:?}", status);
+
+        let keypair = Random.generate().unwrap();
+        let results = mem_pool.add(vec![pay_with_fee(0, 150, &keypair)], 1, 100, &fetch_account);
+        assert_eq!(
+            vec![Err(SyntaxError::InsufficientFee {
+                minimal: floor,
+                got: 150,
+            }
+            .into())],
+            results
+        );
+
+        // The flood ends.
+        mem_pool.clear();
+        for i in 4..=20 {
+            mem_pool.update_fee_floor(start + Duration::from_secs(10 * i));
+        }
+        let status = mem_pool.fee_floor().unwrap();
+        assert_eq!(100, status.floor, "{:?}", status);
+        assert_eq!(0, status.load);
+
+        let results = mem_pool.add(vec![pay_with_fee(0, 150, &keypair)], 2, 110, &fetch_account);
+        assert_eq!(vec![Ok(TransactionImportResult::Current)], results);
+    }
+
+    #[test]
+    fn p2p_transactions_are_evicted_before_rpc_transactions() {
+        let p2p_inputs: Vec<_> = (0..2).map(|_| pay_with_fee(0, 1000, &Random.generate().unwrap())).collect();
+        let rpc_inputs: Vec<_> = (0..2).map(|_| rpc_pay_with_fee(0, 100, &Random.generate().unwrap())).collect();
+        let p2p_hashes: Vec<_> = p2p_inputs.iter().map(|input| input.transaction.hash()).collect();
+        let rpc_hashes: Vec<_> = rpc_inputs.iter().map(|input| input.transaction.hash()).collect();
+        let tx_size = rlp::encode(&p2p_inputs[0].transaction).len();
+
+        // There is room for only two transactions.
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(8192, tx_size * 2, 3, db);
+        let fetch_account = |_: &Public| AccountDetails {
+            seq: 0,
+            balance: 1_000_000_000_000,
+        };
+        for result in mem_pool.add(p2p_inputs, 1, 100, &fetch_account) {
+            assert!(result.is_ok(), "{:?}", result);
+        }
+        for result in mem_pool.add(rpc_inputs, 1, 100, &fetch_account) {
+            assert!(result.is_ok(), "{:?}", result);
+        }
+
+        for hash in &rpc_hashes {
+            assert_eq!(Some(PendingQueue::Current), mem_pool.pending_queue(hash));
+        }
+        for hash in &p2p_hashes {
+            assert_eq!(None, mem_pool.pending_queue(hash));
+        }
+        let mut dropped = mem_pool.take_dropped();
+        dropped.sort_by_key(|(hash, _)| *hash);
+        let mut expected: Vec<_> = p2p_hashes.into_iter().map(|hash| (hash, DropReason::Evicted)).collect();
+        expected.sort_by_key(|(hash, _)| *hash);
+        assert_eq!(expected, dropped);
+    }
+
+    #[test]
+    fn transactions_by_signer_have_their_origins() {
+        let keypair = Random.generate().unwrap();
+        let other = Random.generate().unwrap();
+        let mem_pool = pool_with(0, vec![
+            rpc_pay_with_fee(0, 100, &keypair),
+            pay_with_fee(2, 100, &keypair),
+            pay_with_fee(0, 100, &other),
+        ]);
+
+        let transactions = mem_pool.transactions_by_signer(|public| public == keypair.public());
+        assert_eq!(vec![0, 2], transactions.iter().map(|tx| tx.transaction.seq).collect::<Vec<_>>());
+        assert_eq!(
+            vec![
+                TxOrigin::Rpc {
+                    source: Some("10.0.0.1".parse().unwrap()),
+                },
+                P2P
+            ],
+            transactions.iter().map(|tx| tx.origin).collect::<Vec<_>>()
+        );
+        assert_eq!(vec![true, false], transactions.iter().map(|tx| tx.is_pending).collect::<Vec<_>>());
+    }
+
+    fn scheduled_pay(seq: u64, keypair: &KeyPair, not_before: u64) -> ScheduledTransaction {
+        ScheduledTransaction {
+            transaction: pay_with_fee(seq, 100, keypair).transaction,
+            origin: TxOrigin::Rpc {
+                source: None,
+            },
+            not_before,
+        }
+    }
+
+    #[test]
+    fn scheduled_transactions_are_taken_after_not_before() {
+        let keypair = Random.generate().unwrap();
+        let mut mem_pool = pool_with(0, vec![]);
+        mem_pool.schedule(scheduled_pay(1, &keypair, 200), 0).unwrap();
+        mem_pool.schedule(scheduled_pay(0, &keypair, 100), 0).unwrap();
+        assert_eq!(
+            Err(HistoryError::TransactionAlreadyImported.into()),
+            mem_pool.schedule(scheduled_pay(0, &keypair, 150), 0)
+        );
+
+        assert_eq!(
+            vec![100, 200],
+            mem_pool.scheduled_transactions().iter().map(|tx| tx.not_before).collect::<Vec<_>>()
+        );
+        assert!(mem_pool.top_transactions(std::usize::MAX, None, 0..std::u64::MAX).transactions.is_empty());
+        assert_eq!(0, mem_pool.status().pending);
+
+        assert_eq!(Vec::<ScheduledTransaction>::new(), mem_pool.take_due_scheduled(99));
+        assert_eq!(vec![scheduled_pay(0, &keypair, 100)], mem_pool.take_due_scheduled(199));
+        assert_eq!(vec![scheduled_pay(1, &keypair, 200)], mem_pool.take_due_scheduled(200));
+        assert!(mem_pool.scheduled_transactions().is_empty());
+    }
+
+    #[test]
+    fn scheduled_transactions_are_limited() {
+        let keypair = Random.generate().unwrap();
+        let now = 1_000;
+        let tx_size = scheduled_pay(0, &keypair, now).mem_usage();
+        // There is room for only two transactions.
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(8192, tx_size * 2, 3, db);
+
+        assert_eq!(
+            Err(HistoryError::LimitReached.into()),
+            mem_pool.schedule(scheduled_pay(0, &keypair, now + MAX_SCHEDULE_AHEAD + 1), now)
+        );
+        mem_pool.schedule(scheduled_pay(0, &keypair, now + MAX_SCHEDULE_AHEAD), now).unwrap();
+        mem_pool.schedule(scheduled_pay(1, &keypair, now + 10), now).unwrap();
+        assert_eq!(
+            Err(HistoryError::LimitReached.into()),
+            mem_pool.schedule(scheduled_pay(2, &keypair, now + 10), now)
+        );
+
+        // The transactions whose seqs are used are dropped.
+        let fetch_account = |_: &Public| AccountDetails {
+            seq: 1,
+            balance: 1_000_000_000_000,
+        };
+        mem_pool.remove_old(&fetch_account, 1, now);
+        assert_eq!(vec![scheduled_pay(1, &keypair, now + 10)], mem_pool.scheduled_transactions());
+        mem_pool.schedule(scheduled_pay(2, &keypair, now + 10), now).unwrap();
+    }
+
+    #[test]
+    fn scheduled_transactions_are_recovered_from_db() {
+        let test_client = TestBlockChainClient::new();
+        let keypair = Random.generate().unwrap();
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), 3, db.clone());
+        let fetch_account = |_: &Public| AccountDetails {
+            seq: 0,
+            balance: 1_000_000_000_000,
+        };
+        mem_pool.add(vec![pay_with_fee(0, 100, &keypair)], 1, 100, &fetch_account);
+        mem_pool.schedule(scheduled_pay(1, &keypair, 300), 0).unwrap();
+        mem_pool.schedule(scheduled_pay(2, &keypair, 400), 0).unwrap();
+
+        let mut recovered = MemPool::with_limits(8192, usize::max_value(), 3, db.clone());
+        recovered.recover_from_db(&test_client);
+        assert_eq!(mem_pool.scheduled, recovered.scheduled);
+        assert_eq!(mem_pool.by_hash, recovered.by_hash);
+
+        // The taken transactions are removed from the backup.
+        recovered.take_due_scheduled(300);
+        let mut recovered_again = MemPool::with_limits(8192, usize::max_value(), 3, db);
+        recovered_again.recover_from_db(&test_client);
+        assert_eq!(vec![scheduled_pay(2, &keypair, 400)], recovered_again.scheduled_transactions());
+    }
 }
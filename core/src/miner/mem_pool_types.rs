@@ -15,9 +15,11 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use std::cmp::Ordering;
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::net::IpAddr;
 
 use ckey::Public;
+use cnetwork::NodeId;
 use ctypes::transaction::Action;
 use ctypes::BlockNumber;
 use primitives::H256;
@@ -30,26 +32,41 @@ use crate::transaction::SignedTransaction;
 pub type PoolingInstant = BlockNumber;
 
 /// Transaction origin
+///
+/// The source of the RPC transactions and the peer of the P2P transactions are not backed up,
+/// so they are None for the transactions recovered from the backup.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TxOrigin {
-    /// Transaction coming from local RPC
+    /// Transaction created by the node or signed by the accounts of the node
     Local,
-    /// External transaction received from network
-    External,
+    /// Transaction submitted over RPC. The source is known only over HTTP.
+    Rpc {
+        source: Option<IpAddr>,
+    },
+    /// Transaction received from a peer
+    P2p {
+        peer: Option<NodeId>,
+    },
     /// Transaction from retracted blocks
     RetractedBlock,
 }
 
 type TxOriginType = u8;
 const LOCAL: TxOriginType = 0x01;
-const EXTERNAL: TxOriginType = 0x02;
+const P2P: TxOriginType = 0x02;
 const RETRACTEDBLOCK: TxOriginType = 0x03;
+const RPC: TxOriginType = 0x04;
 
 impl Encodable for TxOrigin {
     fn rlp_append(&self, s: &mut RlpStream) {
         match self {
             TxOrigin::Local => LOCAL.rlp_append(s),
-            TxOrigin::External => EXTERNAL.rlp_append(s),
+            TxOrigin::Rpc {
+                ..
+            } => RPC.rlp_append(s),
+            TxOrigin::P2p {
+                ..
+            } => P2P.rlp_append(s),
             TxOrigin::RetractedBlock => RETRACTEDBLOCK.rlp_append(s),
         };
     }
@@ -59,7 +76,12 @@ impl Decodable for TxOrigin {
     fn decode(d: &UntrustedRlp) -> Result<Self, DecoderError> {
         match d.as_val().expect("rlp decode Error") {
             LOCAL => Ok(TxOrigin::Local),
-            EXTERNAL => Ok(TxOrigin::External),
+            RPC => Ok(TxOrigin::Rpc {
+                source: None,
+            }),
+            P2P => Ok(TxOrigin::P2p {
+                peer: None,
+            }),
             RETRACTEDBLOCK => Ok(TxOrigin::RetractedBlock),
             _ => Err(DecoderError::Custom("Unexpected Txorigin type")),
         }
@@ -74,20 +96,43 @@ impl PartialOrd for TxOrigin {
 
 impl Ord for TxOrigin {
     fn cmp(&self, other: &TxOrigin) -> Ordering {
-        if *other == *self {
-            return Ordering::Equal
-        }
-
         match (*self, *other) {
-            (TxOrigin::RetractedBlock, _) => Ordering::Less,
-            (_, TxOrigin::RetractedBlock) => Ordering::Greater,
-            (TxOrigin::Local, _) => Ordering::Less,
-            _ => Ordering::Greater,
+            (
+                TxOrigin::Rpc {
+                    source: a,
+                },
+                TxOrigin::Rpc {
+                    source: b,
+                },
+            ) => a.cmp(&b),
+            (
+                TxOrigin::P2p {
+                    peer: a,
+                },
+                TxOrigin::P2p {
+                    peer: b,
+                },
+            ) => a.cmp(&b),
+            _ => self.rank().cmp(&other.rank()),
         }
     }
 }
 
 impl TxOrigin {
+    /// The transactions of the lower rank are preferred, and evicted later.
+    pub fn rank(self) -> u8 {
+        match self {
+            TxOrigin::RetractedBlock => 0,
+            TxOrigin::Local => 1,
+            TxOrigin::Rpc {
+                ..
+            } => 2,
+            TxOrigin::P2p {
+                ..
+            } => 3,
+        }
+    }
+
     pub fn is_local(self) -> bool {
         self == TxOrigin::Local
     }
@@ -96,8 +141,22 @@ impl TxOrigin {
         self == TxOrigin::Local || self == TxOrigin::RetractedBlock
     }
 
+    pub fn is_rpc(self) -> bool {
+        match self {
+            TxOrigin::Rpc {
+                ..
+            } => true,
+            _ => false,
+        }
+    }
+
     pub fn is_external(self) -> bool {
-        self == TxOrigin::External
+        match self {
+            TxOrigin::P2p {
+                ..
+            } => true,
+            _ => false,
+        }
     }
 }
 
@@ -173,9 +232,9 @@ impl PartialOrd for TransactionOrder {
 
 impl Ord for TransactionOrder {
     fn cmp(&self, b: &TransactionOrder) -> Ordering {
-        // Local transactions should always have priority
-        if self.origin != b.origin {
-            return self.origin.cmp(&b.origin)
+        // Local transactions should always have priority, and then the RPC transactions
+        if self.origin.rank() != b.origin.rank() {
+            return self.origin.rank().cmp(&b.origin.rank())
         }
 
         // Check seq_height
@@ -297,7 +356,7 @@ impl TransactionOrderWithTag {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct CurrentQueue {
     /// Priority queue for transactions
     pub queue: BTreeSet<TransactionOrder>,
@@ -360,7 +419,7 @@ impl CurrentQueue {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct FutureQueue {
     /// Priority queue for transactions
     pub queue: BTreeSet<TransactionOrder>,
@@ -406,6 +465,101 @@ impl FutureQueue {
     }
 }
 
+/// A transaction that is neither included in blocks nor propagated until the clock of the node passes `not_before`
+#[derive(Clone, Debug, PartialEq, Eq, RlpEncodable, RlpDecodable)]
+pub struct ScheduledTransaction {
+    pub transaction: SignedTransaction,
+    pub origin: TxOrigin,
+    /// UNIX timestamp in seconds
+    pub not_before: u64,
+}
+
+impl ScheduledTransaction {
+    pub fn hash(&self) -> H256 {
+        self.transaction.hash()
+    }
+
+    pub fn mem_usage(&self) -> usize {
+        rlp::encode(&self.transaction).len()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScheduledQueue {
+    /// Scheduled transactions ordered by the time they become eligible
+    queue: BTreeMap<(u64, H256), ScheduledTransaction>,
+    not_before_by_hash: HashMap<H256, u64>,
+    /// The sum of the sizes of the scheduled transactions
+    mem_usage: usize,
+}
+
+impl ScheduledQueue {
+    pub fn new() -> Self {
+        Self {
+            queue: BTreeMap::new(),
+            not_before_by_hash: HashMap::new(),
+            mem_usage: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.not_before_by_hash.clear();
+        self.mem_usage = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn mem_usage(&self) -> usize {
+        self.mem_usage
+    }
+
+    pub fn contains(&self, hash: &H256) -> bool {
+        self.not_before_by_hash.contains_key(hash)
+    }
+
+    pub fn insert(&mut self, scheduled: ScheduledTransaction) {
+        let hash = scheduled.hash();
+        self.mem_usage += scheduled.mem_usage();
+        self.not_before_by_hash.insert(hash, scheduled.not_before);
+        self.queue.insert((scheduled.not_before, hash), scheduled);
+    }
+
+    /// Removes and returns the transactions whose `not_before` is not later than `now`.
+    pub fn take_due(&mut self, now: u64) -> Vec<ScheduledTransaction> {
+        let later = self.queue.split_off(&(now.saturating_add(1), H256::zero()));
+        let due = ::std::mem::replace(&mut self.queue, later);
+        for ((_, hash), scheduled) in &due {
+            self.not_before_by_hash.remove(hash);
+            self.mem_usage -= scheduled.mem_usage();
+        }
+        due.into_iter().map(|(_, scheduled)| scheduled).collect()
+    }
+
+    /// Removes and returns the transactions that satisfy `is_stale`.
+    pub fn remove_stale<F>(&mut self, is_stale: F) -> Vec<ScheduledTransaction>
+    where
+        F: Fn(&ScheduledTransaction) -> bool, {
+        let stale: Vec<_> =
+            self.queue.iter().filter(|(_, scheduled)| is_stale(scheduled)).map(|(key, _)| *key).collect();
+        stale
+            .into_iter()
+            .map(|key| {
+                let scheduled = self.queue.remove(&key).expect("The key is taken from the queue");
+                self.not_before_by_hash.remove(&key.1);
+                self.mem_usage -= scheduled.mem_usage();
+                scheduled
+            })
+            .collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ScheduledTransaction> {
+        self.queue.values()
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct MemPoolInput {
     pub transaction: SignedTransaction,
@@ -440,3 +594,172 @@ pub struct AccountDetails {
     /// Current account balance
     pub balance: u64,
 }
+
+/// The queue in the pool that holds a transaction
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PendingQueue {
+    /// The transaction can be included in the next block.
+    Current,
+    /// The transaction waits for the transactions with the lower seqs.
+    Future,
+}
+
+/// The reason why a transaction left the pool without being mined
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DropReason {
+    /// The transaction failed while the block was being prepared.
+    Invalid,
+    /// The expiration time of the transaction has passed.
+    Expired,
+    /// The transaction stayed in the pool for too long.
+    Outdated,
+    /// The signer can no longer pay for the transaction.
+    InsufficientBalance,
+    /// The transaction was evicted to keep the pool within its limits.
+    Evicted,
+    /// A transaction with the same signer and seq replaced it.
+    Replaced,
+    /// The seq is already used by another transaction in the chain.
+    SeqUsed,
+}
+
+impl DropReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DropReason::Invalid => "invalid",
+            DropReason::Expired => "expired",
+            DropReason::Outdated => "outdated",
+            DropReason::InsufficientBalance => "insufficientBalance",
+            DropReason::Evicted => "evicted",
+            DropReason::Replaced => "replaced",
+            DropReason::SeqUsed => "seqUsed",
+        }
+    }
+}
+
+/// A step in the lifecycle of a transaction that the node has seen
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransactionEvent {
+    /// The transaction entered the pool.
+    Queued {
+        hash: H256,
+    },
+    /// The transaction is included in a block of the best chain.
+    Mined {
+        hash: H256,
+        block_hash: H256,
+        block_number: BlockNumber,
+    },
+    /// The transaction left the pool without being mined.
+    Dropped {
+        hash: H256,
+        reason: DropReason,
+    },
+    /// The block that included the transaction is no longer in the best chain.
+    Retracted {
+        hash: H256,
+        block_hash: H256,
+        block_number: BlockNumber,
+    },
+}
+
+impl TransactionEvent {
+    pub fn hash(&self) -> &H256 {
+        match self {
+            TransactionEvent::Queued {
+                hash,
+            }
+            | TransactionEvent::Mined {
+                hash,
+                ..
+            }
+            | TransactionEvent::Dropped {
+                hash,
+                ..
+            }
+            | TransactionEvent::Retracted {
+                hash,
+                ..
+            } => hash,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A transaction of the diagnosed account in the pool
+pub struct QueuedTransaction {
+    pub hash: H256,
+    pub signer_public: Public,
+    pub seq: u64,
+    pub fee: u64,
+    /// False if the transaction waits in the future queue
+    pub is_pending: bool,
+    /// The fee that an external transaction with the same signer and seq needs to replace this one
+    pub min_fee_to_replace: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A transaction in the pool with the origin it was inserted from
+pub struct PooledTransaction {
+    pub transaction: SignedTransaction,
+    pub origin: TxOrigin,
+    /// False if the transaction waits in the future queue
+    pub is_pending: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Why the transactions of an account are stuck in the pool
+pub struct SignerDiagnosis {
+    /// The seq of the account in the best block
+    pub seq: u64,
+    pub lowest_queued_seq: Option<u64>,
+    pub highest_queued_seq: Option<u64>,
+    /// The inclusive ranges of the seqs that are missing from the pool
+    pub gaps: Vec<(u64, u64)>,
+    /// The seqs that are used by the transactions of different signers of the account
+    pub competing_seqs: Vec<u64>,
+    /// The fee that a new external transaction needs to enter the pool
+    pub min_fee: u64,
+    /// Ordered by seq
+    pub transactions: Vec<QueuedTransaction>,
+}
+
+impl SignerDiagnosis {
+    /// The suggestions to unblock the transactions, in the order they should be applied.
+    pub fn suggestions(&self) -> Vec<String> {
+        if self.transactions.is_empty() {
+            return vec![format!("No transaction of the account is in the pool. The next seq is {}.", self.seq)]
+        }
+        let mut suggestions = Vec::new();
+        for (from, to) in &self.gaps {
+            if from == to {
+                suggestions.push(format!(
+                    "Seq {} is missing. The transactions after it wait until a transaction with seq {} is sent.",
+                    from, from
+                ));
+            } else {
+                suggestions.push(format!(
+                    "Seqs from {} to {} are missing. The transactions after them wait until transactions with these seqs are sent.",
+                    from, to
+                ));
+            }
+        }
+        for seq in &self.competing_seqs {
+            suggestions.push(format!(
+                "Seq {} is used by transactions of different signers. Only one of them can be included.",
+                seq
+            ));
+        }
+        for tx in &self.transactions {
+            if tx.fee < self.min_fee {
+                suggestions.push(format!(
+                    "The fee of {} is lower than the fee that the pool requires now. Send a transaction with seq {} and a fee of at least {} to replace it.",
+                    tx.hash,
+                    tx.seq,
+                    ::std::cmp::max(self.min_fee, tx.min_fee_to_replace)
+                ));
+            }
+        }
+        suggestions
+    }
+}
@@ -16,24 +16,38 @@
 
 use std::collections::HashSet;
 use std::iter::once;
+use std::mem;
 use std::ops::Range;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use ckey::{public_to_address, Address, Password, PlatformAddress, Public};
+use cnetwork::NodeId;
 use cstate::{FindActionHandler, TopLevelState};
 use ctypes::errors::HistoryError;
-use ctypes::transaction::{Action, IncompleteTransaction, Timelock};
+use ctypes::transaction::{Action, IncompleteTransaction, Timelock, Transaction};
 use ctypes::{BlockNumber, Header};
 use cvm::ChainTimeInfo;
 use kvdb::KeyValueDB;
 use parking_lot::{Mutex, RwLock};
 use primitives::{Bytes, H256};
+use rlp;
 
+use super::fee_floor::{FeeFloorOptions, FeeFloorStatus};
 use super::mem_pool::{Error as MemPoolError, MemPool};
-use super::mem_pool_types::{AccountDetails, MemPoolInput, TxOrigin, TxTimelock};
+use super::mem_pool_types::{
+    AccountDetails, MemPoolInput, PendingQueue, PooledTransaction, ScheduledTransaction, SignerDiagnosis,
+    TransactionEvent, TxOrigin, TxTimelock,
+};
+use super::proposal_budget::ProposalBudget;
+use super::relay_stats::RelayStats;
+use super::rpc_rate_limiter::{RpcRateLimiter, DEFAULT_RPC_RATE_LIMIT_WINDOW};
 use super::sealing_queue::SealingQueue;
+use super::seen_transactions::{
+    SeenTransactions, DEFAULT_SEEN_TRANSACTIONS_CAPACITY, DEFAULT_SEEN_TRANSACTIONS_WINDOW,
+};
+use super::spend_policy::{SpendPolicies, SpendPolicy, SpendPolicyStatus};
 use super::work_notify::{NotifyWork, WorkPoster};
 use super::{MinerService, MinerStatus, TransactionImportResult};
 use crate::account_provider::{AccountProvider, Error as AccountProviderError};
@@ -75,9 +89,29 @@ pub struct MinerOptions {
     /// then `new_fee > old_fee + old_fee >> mem_pool_fee_bump_shift` should be satisfied to replace.
     /// Local transactions ignore this option.
     pub mem_pool_fee_bump_shift: usize,
+    /// The minimum fee of the transactions received from the peers.
+    pub mem_pool_min_fee: u64,
+    /// The minimum fee of the transactions submitted over RPC.
+    pub mem_pool_rpc_min_fee: u64,
     pub allow_create_shard: bool,
     /// How many historical work packages can we store before running out?
     pub work_queue_size: usize,
+    /// How long the hashes of the handled transactions are remembered to drop the re-announced ones.
+    pub seen_transactions_window: Duration,
+    /// The maximum number of the remembered transaction hashes.
+    pub seen_transactions_capacity: usize,
+    /// The maximum number of the transactions that a source address can submit over RPC in a window.
+    /// 0 means no limit.
+    pub rpc_tx_rate_limit: usize,
+    /// How long the RPC submissions are counted before the counts are reset.
+    pub rpc_tx_rate_window: Duration,
+    /// The node-local limits of the blocks that this node proposes.
+    pub proposal_budget: ProposalBudget,
+    /// Fill the blocks in the order that depends only on the transactions in the mem pool,
+    /// so the nodes with the same mem pool propose the same block on the same parent and timestamp.
+    pub deterministic_ordering: bool,
+    /// Raises the minimum fee of the remote transactions while the mem pool is loaded. None disables it.
+    pub mem_pool_fee_floor: Option<FeeFloorOptions>,
 }
 
 impl Default for MinerOptions {
@@ -93,8 +127,17 @@ impl Default for MinerOptions {
             mem_pool_size: 8192,
             mem_pool_memory_limit: Some(2 * 1024 * 1024),
             mem_pool_fee_bump_shift: 3,
+            mem_pool_min_fee: 0,
+            mem_pool_rpc_min_fee: 0,
             allow_create_shard: false,
             work_queue_size: 20,
+            seen_transactions_window: DEFAULT_SEEN_TRANSACTIONS_WINDOW,
+            seen_transactions_capacity: DEFAULT_SEEN_TRANSACTIONS_CAPACITY,
+            rpc_tx_rate_limit: 0,
+            rpc_tx_rate_window: DEFAULT_RPC_RATE_LIMIT_WINDOW,
+            proposal_budget: ProposalBudget::default(),
+            deterministic_ordering: false,
+            mem_pool_fee_floor: None,
         }
     }
 }
@@ -111,15 +154,26 @@ struct SealingWork {
 }
 
 type TransactionListener = Box<Fn(&[H256]) + Send + Sync>;
+type TransactionEventListener = Box<Fn(&[TransactionEvent]) + Send + Sync>;
 
 pub struct Miner {
     mem_pool: Arc<RwLock<MemPool>>,
+    seen_transactions: Arc<SeenTransactions>,
+    relay_stats: Arc<RelayStats>,
+    rpc_rate_limiter: RpcRateLimiter,
+    spend_policies: SpendPolicies,
     transaction_listener: RwLock<Vec<TransactionListener>>,
+    transaction_event_listener: RwLock<Vec<TransactionEventListener>>,
+    /// The events recorded under the lock of the mem pool, which are notified after the lock is released.
+    recorded_transaction_events: Mutex<Vec<TransactionEvent>>,
+    /// Keeps the order of the events notified by different threads.
+    transaction_event_notification: Mutex<()>,
     next_allowed_reseal: Mutex<Instant>,
     next_mandatory_reseal: RwLock<Instant>,
     sealing_block_last_request: Mutex<u64>,
     sealing_work: Mutex<SealingWork>,
     params: RwLock<AuthoringParams>,
+    proposal_budget: RwLock<ProposalBudget>,
     engine: Arc<CodeChainEngine>,
     options: MinerOptions,
 
@@ -154,13 +208,19 @@ impl Miner {
         accounts: Option<Arc<AccountProvider>>,
         db: Arc<KeyValueDB>,
     ) -> Self {
+        let spend_policies = SpendPolicies::new(Arc::clone(&db));
         let mem_limit = options.mem_pool_memory_limit.unwrap_or_else(usize::max_value);
-        let mem_pool = Arc::new(RwLock::new(MemPool::with_limits(
-            options.mem_pool_size,
-            mem_limit,
-            options.mem_pool_fee_bump_shift,
-            db,
-        )));
+        let mem_pool = {
+            let mut mem_pool =
+                MemPool::with_limits(options.mem_pool_size, mem_limit, options.mem_pool_fee_bump_shift, db);
+            mem_pool.set_minimal_fee(options.mem_pool_min_fee);
+            mem_pool.set_rpc_minimal_fee(options.mem_pool_rpc_min_fee);
+            mem_pool.set_deterministic_ordering(options.deterministic_ordering);
+            if let Some(fee_floor) = options.mem_pool_fee_floor {
+                mem_pool.set_fee_floor(fee_floor);
+            }
+            Arc::new(RwLock::new(mem_pool))
+        };
 
         let notifiers: Vec<Box<NotifyWork>> = if options.new_work_notify.is_empty() {
             Vec::new()
@@ -168,12 +228,24 @@ impl Miner {
             vec![Box::new(WorkPoster::new(&options.new_work_notify))]
         };
 
+        let seen_transactions =
+            Arc::new(SeenTransactions::new(options.seen_transactions_window, options.seen_transactions_capacity));
+        let rpc_rate_limiter = RpcRateLimiter::new(options.rpc_tx_rate_limit, options.rpc_tx_rate_window);
+
         Self {
             mem_pool,
+            seen_transactions,
+            relay_stats: Default::default(),
+            rpc_rate_limiter,
+            spend_policies,
             transaction_listener: RwLock::new(vec![]),
+            transaction_event_listener: RwLock::new(vec![]),
+            recorded_transaction_events: Mutex::new(vec![]),
+            transaction_event_notification: Mutex::new(()),
             next_allowed_reseal: Mutex::new(Instant::now()),
             next_mandatory_reseal: RwLock::new(Instant::now() + options.reseal_max_period),
             params: RwLock::new(AuthoringParams::default()),
+            proposal_budget: RwLock::new(options.proposal_budget),
             sealing_block_last_request: Mutex::new(0),
             sealing_work: Mutex::new(SealingWork {
                 queue: SealingQueue::new(options.work_queue_size),
@@ -187,6 +259,23 @@ impl Miner {
         }
     }
 
+    fn sign_and_import_own_transaction<C: MiningBlockChainClient + EngineInfo + TermInfo>(
+        &self,
+        client: &C,
+        account_provider: &AccountProvider,
+        tx: Transaction,
+        address: &Address,
+        passphrase: Option<Password>,
+    ) -> Result<H256, Error> {
+        let tx_hash = tx.hash();
+        let sig = account_provider.get_account(address, passphrase.as_ref())?.sign(&tx_hash)?;
+        let unverified = UnverifiedTransaction::new(tx, sig);
+        let signed = SignedTransaction::try_new(unverified)?;
+        let hash = signed.hash();
+        self.import_own_transaction(client, signed, TxOrigin::Local)?;
+        Ok(hash)
+    }
+
     pub fn recover_from_db(&self, client: &Client) {
         self.mem_pool.write().recover_from_db(client);
     }
@@ -196,6 +285,77 @@ impl Miner {
         self.transaction_listener.write().push(f);
     }
 
+    /// Set a callback to be notified when transactions are queued, mined, dropped or retracted.
+    pub fn add_transaction_event_listener(&self, f: TransactionEventListener) {
+        self.transaction_event_listener.write().push(f);
+    }
+
+    /// Returns false if the RPC source has submitted too many transactions.
+    /// The transactions submitted over WebSocket or IPC are limited together, because they have no source address.
+    fn acquire_rpc_rate(&self, origin: &TxOrigin, count: usize) -> bool {
+        match origin {
+            TxOrigin::Rpc {
+                source,
+            } => self.rpc_rate_limiter.acquire(*source, count),
+            _ => true,
+        }
+    }
+
+    /// Records the events to notify them with `notify_transaction_events`.
+    fn record_transaction_events(&self, events: Vec<TransactionEvent>) {
+        if events.is_empty() || self.transaction_event_listener.read().is_empty() {
+            return
+        }
+        self.recorded_transaction_events.lock().extend(events);
+    }
+
+    /// Notifies the recorded events.
+    /// It must be called after the lock of the mem pool is released, so that the listeners don't block the pool.
+    fn notify_transaction_events(&self) {
+        let _notification = self.transaction_event_notification.lock();
+        let events = mem::replace(&mut *self.recorded_transaction_events.lock(), Vec::new());
+        if events.is_empty() {
+            return
+        }
+        for listener in &*self.transaction_event_listener.read() {
+            listener(&events);
+        }
+    }
+
+    /// Records the transactions that left the pool, except the ones included in `mined`.
+    fn record_dropped_transactions(&self, mem_pool: &mut MemPool, mined: &HashSet<H256>) {
+        let events: Vec<_> = mem_pool
+            .take_dropped()
+            .into_iter()
+            .filter(|(hash, _)| !mined.contains(hash))
+            .map(|(hash, reason)| TransactionEvent::Dropped {
+                hash,
+                reason,
+            })
+            .collect();
+        self.record_transaction_events(events);
+    }
+
+    fn record_queued_transactions(&self, hashes: &[H256]) {
+        let events: Vec<_> = hashes
+            .iter()
+            .map(|hash| TransactionEvent::Queued {
+                hash: *hash,
+            })
+            .collect();
+        self.record_transaction_events(events);
+    }
+
+    /// The hashes of the recently handled transactions, shared with the transaction sync extension.
+    pub fn seen_transactions(&self) -> Arc<SeenTransactions> {
+        Arc::clone(&self.seen_transactions)
+    }
+
+    /// The counters of the transaction relay, updated by the transaction sync extension.
+    pub fn relay_stats(&self) -> Arc<RelayStats> {
+        Arc::clone(&self.relay_stats)
+    }
+
     /// Get `Some` `clone()` of the current pending block's state or `None` if we're not sealing.
     pub fn pending_state(&self, latest_block_number: BlockNumber) -> Option<TopLevelState> {
         self.map_pending_block(|b| b.state().clone(), latest_block_number)
@@ -257,6 +417,7 @@ impl Miner {
         default_origin: TxOrigin,
         mem_pool: &mut MemPool,
     ) -> Vec<Result<TransactionImportResult, Error>> {
+        mem_pool.update_fee_floor(Instant::now());
         let best_header = client.best_block_header().decode();
         let fake_header = best_header.generate_child();
         let current_block_number = client.chain_info().best_block_number;
@@ -268,49 +429,10 @@ impl Miner {
         let intermediate_results: Vec<Result<(), Error>> = transactions
             .into_iter()
             .map(|tx| {
-                let hash = tx.hash();
-                if client.transaction_block(&TransactionId::Hash(hash)).is_some() {
-                    cdebug!(MINER, "Rejected transaction {:?}: already in the blockchain", hash);
-                    return Err(HistoryError::TransactionAlreadyImported.into())
-                }
-                if !self.is_allowed_transaction(&tx.action) {
-                    cdebug!(MINER, "Rejected transaction {:?}: {:?} is not allowed transaction", hash, tx.action);
-                }
-                match tx
-                    .verify_basic()
-                    .map_err(From::from)
-                    .and_then(|_| {
-                        let common_params = client.common_params(best_header.hash().into()).unwrap();
-                        self.engine.verify_transaction_with_params(&tx, &common_params)
-                    })
-                    .and_then(|_| CodeChainMachine::verify_transaction_seal(tx, &fake_header))
-                {
-                    Err(e) => {
-                        cdebug!(MINER, "Rejected transaction {:?} with invalid signature: {:?}", hash, e);
-                        Err(e)
-                    }
-                    Ok(tx) => {
-                        // This check goes here because verify_transaction takes SignedTransaction parameter
-                        self.engine.machine().verify_transaction(&tx, &fake_header, client, false)?;
-
-                        let origin = self
-                            .accounts
-                            .as_ref()
-                            .and_then(|accounts| match accounts.has_public(&tx.signer_public()) {
-                                Ok(true) => Some(TxOrigin::Local),
-                                Ok(false) => None,
-                                Err(_) => None,
-                            })
-                            .unwrap_or(default_origin);
-
-                        let timelock = self.calculate_timelock(&tx, client)?;
-                        let tx_hash = tx.hash();
-
-                        to_insert.push(MemPoolInput::new(tx, origin, timelock));
-                        tx_hashes.push(tx_hash);
-                        Ok(())
-                    }
-                }
+                let input = self.verify_transaction_for_pool(client, tx, default_origin, &best_header, &fake_header)?;
+                tx_hashes.push(input.transaction.hash());
+                to_insert.push(input);
+                Ok(())
             })
             .collect();
 
@@ -344,10 +466,117 @@ impl Miner {
         for listener in &*self.transaction_listener.read() {
             listener(&inserted);
         }
+        self.record_queued_transactions(&inserted);
 
         results
     }
 
+    /// Adds the transactions only if all of them can be added.
+    /// The caller must hold the lock of the mem pool during the call,
+    /// so that no other transaction can be inserted between the verification and the insertion.
+    fn add_transactions_to_pool_all_or_nothing<C: AccountData + BlockChainTrait + EngineInfo>(
+        &self,
+        client: &C,
+        transactions: Vec<UnverifiedTransaction>,
+        default_origin: TxOrigin,
+        mem_pool: &mut MemPool,
+    ) -> Result<Vec<TransactionImportResult>, Vec<Option<Error>>> {
+        mem_pool.update_fee_floor(Instant::now());
+        let best_header = client.best_block_header().decode();
+        let fake_header = best_header.generate_child();
+        let current_block_number = client.chain_info().best_block_number;
+        let current_timestamp = client.chain_info().best_block_timestamp;
+
+        let mut to_insert = Vec::with_capacity(transactions.len());
+        let mut errors = Vec::with_capacity(transactions.len());
+        for tx in transactions {
+            match self.verify_transaction_for_pool(client, tx, default_origin, &best_header, &fake_header) {
+                Ok(input) => {
+                    to_insert.push(input);
+                    errors.push(None);
+                }
+                Err(e) => errors.push(Some(e)),
+            }
+        }
+        if errors.iter().any(Option::is_some) {
+            return Err(errors)
+        }
+
+        let fetch_account = |p: &Public| -> AccountDetails {
+            let address = public_to_address(p);
+            let a = client.latest_regular_key_owner(&address).unwrap_or(address);
+            AccountDetails {
+                seq: client.latest_seq(&a),
+                balance: client.latest_balance(&a),
+            }
+        };
+
+        let inserted: Vec<_> = to_insert.iter().map(|input| input.transaction.hash()).collect();
+        let results =
+            match mem_pool.add_all_or_nothing(to_insert, current_block_number, current_timestamp, &fetch_account) {
+                Ok(results) => results,
+                Err(errors) => return Err(errors.into_iter().map(|e| e.map(MemPoolError::into_core_error)).collect()),
+            };
+
+        for listener in &*self.transaction_listener.read() {
+            listener(&inserted);
+        }
+        self.record_queued_transactions(&inserted);
+
+        Ok(results)
+    }
+
+    fn verify_transaction_for_pool<C: AccountData + BlockChainTrait + EngineInfo>(
+        &self,
+        client: &C,
+        tx: UnverifiedTransaction,
+        default_origin: TxOrigin,
+        best_header: &Header,
+        fake_header: &Header,
+    ) -> Result<MemPoolInput, Error> {
+        let hash = tx.hash();
+        // Remember the transactions that are rejected too, so that the peers can't make us verify them again.
+        self.seen_transactions.insert(hash);
+        if client.transaction_block(&TransactionId::Hash(hash)).is_some() {
+            cdebug!(MINER, "Rejected transaction {:?}: already in the blockchain", hash);
+            return Err(HistoryError::TransactionAlreadyImported.into())
+        }
+        if !self.is_allowed_transaction(&tx.action) {
+            cdebug!(MINER, "Rejected transaction {:?}: {:?} is not allowed transaction", hash, tx.action);
+        }
+        match tx
+            .verify_basic()
+            .map_err(From::from)
+            .and_then(|_| {
+                let common_params = client.common_params(best_header.hash().into()).unwrap();
+                self.engine.verify_transaction_with_params(&tx, &common_params)
+            })
+            .and_then(|_| CodeChainMachine::verify_transaction_seal(tx, fake_header))
+        {
+            Err(e) => {
+                cdebug!(MINER, "Rejected transaction {:?} with invalid signature: {:?}", hash, e);
+                Err(e)
+            }
+            Ok(tx) => {
+                // This check goes here because verify_transaction takes SignedTransaction parameter
+                self.engine.machine().verify_transaction(&tx, fake_header, client, false)?;
+
+                let origin = self
+                    .accounts
+                    .as_ref()
+                    .and_then(|accounts| match accounts.has_public(&tx.signer_public()) {
+                        Ok(true) => Some(TxOrigin::Local),
+                        Ok(false) => None,
+                        Err(_) => None,
+                    })
+                    .unwrap_or(default_origin);
+
+                let timelock = self.calculate_timelock(&tx, client)?;
+                Ok(MemPoolInput::new(tx, origin, timelock))
+            }
+        }
+    }
+
     fn calculate_timelock<C: BlockChainTrait>(&self, tx: &SignedTransaction, client: &C) -> Result<TxTimelock, Error> {
         let mut max_block = None;
         let mut max_timestamp = None;
@@ -452,27 +681,36 @@ impl Miner {
         parent_block_id: BlockId,
         chain: &C,
     ) -> Result<(ClosedBlock, Option<H256>), Error> {
-        let (transactions, mut open_block, original_work_hash, block_number) = {
+        // The budget only limits the blocks of this node. The others' blocks are verified with the common params.
+        let budget = *self.proposal_budget.read();
+        let (transactions, mut open_block, original_work_hash, block_number, parent_common_params) = {
             let mem_pool = self.mem_pool.read();
             let sealing_work = self.sealing_work.lock();
 
             let last_work_hash = sealing_work.queue.peek_last_ref().map(|pb| pb.block().header().hash());
             ctrace!(MINER, "prepare_block: No existing work - making new block");
             let params = self.params.read().clone();
-            let open_block = chain.prepare_open_block(parent_block_id, params.author, params.extra_data);
+            let mut open_block = chain.prepare_open_block(parent_block_id, params.author, params.extra_data);
             let (block_number, parent_hash) = {
                 let header = open_block.block().header();
                 let block_number = header.number();
                 let parent_hash = *header.parent_hash();
                 (block_number, parent_hash)
             };
-            let max_body_size = chain.common_params(parent_hash.into()).unwrap().max_body_size();
+            // Only the proposals follow the engine's timestamp rule. The imported blocks keep their own timestamps.
+            let parent_header = chain.block_header(&parent_hash.into()).expect("Parent header MUST exist").decode();
+            if let Some(timestamp) = self.engine.proposal_timestamp(&parent_header) {
+                open_block.set_timestamp(timestamp);
+            }
+            // The block is verified with the params of its parent, which may differ from the genesis params.
+            let parent_common_params = chain.common_params(parent_hash.into()).unwrap();
+            let max_body_size = budget.body_size_limit(parent_common_params.max_body_size());
             const DEFAULT_RANGE: Range<u64> = 0..::std::u64::MAX;
             let transactions = mem_pool
                 .top_transactions(max_body_size, Some(open_block.header().timestamp()), DEFAULT_RANGE)
                 .transactions;
 
-            (transactions, open_block, last_work_hash, block_number)
+            (transactions, open_block, last_work_hash, block_number, parent_common_params)
         };
 
         let parent_header = {
@@ -498,7 +736,14 @@ impl Miner {
         let mut tx_count: usize = 0;
         let tx_total = transactions.len();
         let mut invald_tx_users = HashSet::new();
+        let mut body_payload_size: usize = 0;
+        let max_body_size = budget.body_size_limit(parent_common_params.max_body_size());
+        let max_transactions = budget.transactions_limit();
         for tx in transactions {
+            if tx_count >= max_transactions {
+                ctrace!(MINER, "The block is full: {} transactions", tx_count);
+                break
+            }
             let signer_public = tx.signer_public();
             if invald_tx_users.contains(&signer_public) {
                 // The previous transaction has failed
@@ -510,11 +755,20 @@ impl Miner {
                 continue
             }
 
+            let tx_size = rlp::encode(&tx).len();
+            if body_size(body_payload_size + tx_size) > max_body_size {
+                ctrace!(MINER, "The block body is full: {} bytes", body_size(body_payload_size));
+                break
+            }
+
             let hash = tx.hash();
             let start = Instant::now();
-            // Check whether transaction type is allowed for sender
-            let result =
-                self.engine.machine().verify_transaction(&tx, open_block.header(), chain, true).and_then(|_| {
+            // The transactions in the mem pool were verified with the params at the time they were imported.
+            let result = self
+                .engine
+                .verify_transaction_with_params(&tx, &parent_common_params)
+                .and_then(|_| self.engine.machine().verify_transaction(&tx, open_block.header(), chain, true))
+                .and_then(|_| {
                     open_block.push_transaction(tx, None, chain, parent_header.number(), parent_header.timestamp())
                 });
 
@@ -536,6 +790,7 @@ impl Miner {
                     let took = start.elapsed();
                     ctrace!(MINER, "Adding transaction {:?} took {:?}", hash, took);
                     tx_count += 1;
+                    body_payload_size += tx_size;
                 } // imported ok
             }
         }
@@ -546,7 +801,6 @@ impl Miner {
             let parent_header = chain.block_header(&parent_hash.into()).expect("Parent header MUST exist");
             (parent_header.decode(), parent_hash)
         };
-        let parent_common_params = chain.common_params(parent_hash.into()).unwrap();
         let term_common_params = {
             let block_number = chain
                 .last_term_finished_block_num(parent_hash.into())
@@ -558,6 +812,10 @@ impl Miner {
             }
         };
         let block = open_block.close(&parent_header, &parent_common_params, term_common_params.as_ref())?;
+        if self.engine_type().is_seal_first() {
+            // The block is sealed before it's executed, so its hash doesn't change until the import.
+            chain.cache_closed_block(*parent_header.state_root(), &block);
+        }
 
         let fetch_seq = |p: &Public| {
             let address = public_to_address(p);
@@ -573,7 +831,9 @@ impl Miner {
                 chain.chain_info().best_block_number,
                 chain.chain_info().best_block_timestamp,
             );
+            self.record_dropped_transactions(&mut mem_pool, &HashSet::new());
         }
+        self.notify_transaction_events();
         Ok((block, original_work_hash))
     }
 
@@ -719,16 +979,31 @@ impl MinerService for Miner {
         self.mem_pool.write().set_limit(limit)
     }
 
+    fn proposal_budget(&self) -> ProposalBudget {
+        *self.proposal_budget.read()
+    }
+
+    fn set_proposal_budget(&self, budget: ProposalBudget) {
+        *self.proposal_budget.write() = budget;
+    }
+
+    fn fee_floor(&self) -> Option<FeeFloorStatus> {
+        let mut mem_pool = self.mem_pool.write();
+        mem_pool.update_fee_floor(Instant::now());
+        mem_pool.fee_floor()
+    }
+
     fn chain_new_blocks<C>(
         &self,
         chain: &C,
         _imported: &[H256],
         _invalid: &[H256],
-        _enacted: &[H256],
+        enacted: &[H256],
         retracted: &[H256],
     ) where
         C: AccountData + BlockChainTrait + BlockProducer + EngineInfo + ImportBlock, {
         ctrace!(MINER, "chain_new_blocks");
+        let has_event_listener = !self.transaction_event_listener.read().is_empty();
 
         // Then import all transactions...
         {
@@ -737,11 +1012,45 @@ impl MinerService for Miner {
                 let block = chain.block(&(*hash).into()).expect(
                     "Client is sending message after commit to db and inserting to chain; the block is available; qed",
                 );
+                if has_event_listener {
+                    let events: Vec<_> = block
+                        .transaction_hashes()
+                        .into_iter()
+                        .map(|tx_hash| TransactionEvent::Retracted {
+                            hash: tx_hash,
+                            block_hash: *hash,
+                            block_number: block.number(),
+                        })
+                        .collect();
+                    self.record_transaction_events(events);
+                }
                 let transactions = block.transactions();
                 let _ = self.add_transactions_to_pool(chain, transactions, TxOrigin::RetractedBlock, &mut mem_pool);
             }
         }
 
+        let mut mined = HashSet::new();
+        if has_event_listener {
+            for hash in enacted {
+                let block = chain.block(&(*hash).into()).expect(
+                    "Client is sending message after commit to db and inserting to chain; the block is available; qed",
+                );
+                let events: Vec<_> = block
+                    .transaction_hashes()
+                    .into_iter()
+                    .map(|tx_hash| {
+                        mined.insert(tx_hash);
+                        TransactionEvent::Mined {
+                            hash: tx_hash,
+                            block_hash: *hash,
+                            block_number: block.number(),
+                        }
+                    })
+                    .collect();
+                self.record_transaction_events(events);
+            }
+        }
+
         // ...and at the end remove the old ones
         {
             let fetch_account = |p: &Public| {
@@ -757,7 +1066,11 @@ impl MinerService for Miner {
             let current_timestamp = chain.chain_info().best_block_timestamp;
             let mut mem_pool = self.mem_pool.write();
             mem_pool.remove_old(&fetch_account, current_block_number, current_timestamp);
+            // The transactions of the enacted blocks leave the pool because their seqs are used.
+            self.record_dropped_transactions(&mut mem_pool, &mined);
+            mem_pool.update_fee_floor(Instant::now());
         }
+        self.notify_transaction_events();
 
         if !self.options.no_reseal_timer {
             chain.set_min_timer();
@@ -922,12 +1235,19 @@ impl MinerService for Miner {
         &self,
         client: &C,
         transactions: Vec<UnverifiedTransaction>,
+        peer: NodeId,
     ) -> Vec<Result<TransactionImportResult, Error>> {
-        ctrace!(EXTERNAL_PARCEL, "Importing external transactions");
+        ctrace!(EXTERNAL_PARCEL, "Importing external transactions from {}", peer);
         let results = {
             let mut mem_pool = self.mem_pool.write();
-            self.add_transactions_to_pool(client, transactions, TxOrigin::External, &mut mem_pool)
+            let origin = TxOrigin::P2p {
+                peer: Some(peer),
+            };
+            let results = self.add_transactions_to_pool(client, transactions, origin, &mut mem_pool);
+            self.record_dropped_transactions(&mut mem_pool, &HashSet::new());
+            results
         };
+        self.notify_transaction_events();
 
         if !results.is_empty()
             && self.options.reseal_on_external_transaction
@@ -947,17 +1267,23 @@ impl MinerService for Miner {
         &self,
         chain: &C,
         tx: SignedTransaction,
+        origin: TxOrigin,
     ) -> Result<TransactionImportResult, Error> {
-        ctrace!(OWN_PARCEL, "Importing transaction: {:?}", tx);
+        ctrace!(OWN_PARCEL, "Importing transaction: {:?} from {:?}", tx, origin);
+        if !self.acquire_rpc_rate(&origin, 1) {
+            cwarn!(OWN_PARCEL, "Too many transactions from {:?}", origin);
+            return Err(HistoryError::LimitReached.into())
+        }
 
         let imported = {
             // Be sure to release the lock before we call prepare_work_sealing
             let mut mem_pool = self.mem_pool.write();
             // We need to re-validate transactions
             let import = self
-                .add_transactions_to_pool(chain, vec![tx.into()], TxOrigin::Local, &mut mem_pool)
+                .add_transactions_to_pool(chain, vec![tx.into()], origin, &mut mem_pool)
                 .pop()
                 .expect("one result returned per added transaction; one added => one result; qed");
+            self.record_dropped_transactions(&mut mem_pool, &HashSet::new());
 
             match import {
                 Ok(_) => {
@@ -970,6 +1296,7 @@ impl MinerService for Miner {
             }
             import
         };
+        self.notify_transaction_events();
 
         // ------------------------------------------------------------------
         // | NOTE Code below requires mem_pool and sealing_queue locks.     |
@@ -988,6 +1315,133 @@ impl MinerService for Miner {
         imported
     }
 
+    fn import_own_transactions<C: MiningBlockChainClient + EngineInfo + TermInfo>(
+        &self,
+        chain: &C,
+        transactions: Vec<SignedTransaction>,
+        origin: TxOrigin,
+    ) -> Result<Vec<TransactionImportResult>, Vec<Option<Error>>> {
+        ctrace!(OWN_PARCEL, "Importing {} transactions at once from {:?}", transactions.len(), origin);
+        if !self.acquire_rpc_rate(&origin, transactions.len()) {
+            cwarn!(OWN_PARCEL, "Too many transactions from {:?}", origin);
+            return Err(transactions.iter().map(|_| Some(HistoryError::LimitReached.into())).collect())
+        }
+
+        let imported = {
+            // Hold the lock until all the transactions are verified and inserted.
+            let mut mem_pool = self.mem_pool.write();
+            let transactions = transactions.into_iter().map(Into::into).collect();
+            let import = self.add_transactions_to_pool_all_or_nothing(chain, transactions, origin, &mut mem_pool);
+            self.record_dropped_transactions(&mut mem_pool, &HashSet::new());
+            ctrace!(OWN_PARCEL, "Status: {:?}", mem_pool.status());
+            if let Err(ref errors) = import {
+                cwarn!(OWN_PARCEL, "Error importing transactions: {:?}", errors);
+            }
+            import
+        };
+        self.notify_transaction_events();
+
+        // ------------------------------------------------------------------
+        // | NOTE Code below requires mem_pool and sealing_queue locks.     |
+        // | Make sure to release the locks before calling that method.     |
+        // ------------------------------------------------------------------
+        if imported.is_ok()
+            && self.options.reseal_on_own_transaction
+            && self.transaction_reseal_allowed()
+            && !self.engine_type().ignore_reseal_on_transaction()
+            && (self.engine.seals_internally().unwrap_or(false) || !self.prepare_work_sealing(chain))
+        {
+            self.update_sealing(chain, BlockId::Latest, false);
+        }
+        imported
+    }
+
+    fn schedule_own_transaction<C: MiningBlockChainClient + EngineInfo + TermInfo>(
+        &self,
+        chain: &C,
+        tx: SignedTransaction,
+        origin: TxOrigin,
+        not_before: u64,
+    ) -> Result<(), Error> {
+        ctrace!(OWN_PARCEL, "Scheduling transaction: {:?} from {:?} not before {}", tx, origin, not_before);
+        if !self.acquire_rpc_rate(&origin, 1) {
+            cwarn!(OWN_PARCEL, "Too many transactions from {:?}", origin);
+            return Err(HistoryError::LimitReached.into())
+        }
+
+        let best_header = chain.best_block_header().decode();
+        let fake_header = best_header.generate_child();
+        // It's verified again when it enters the other queues.
+        let input = self.verify_transaction_for_pool(chain, tx.into(), origin, &best_header, &fake_header)?;
+        self.mem_pool
+            .write()
+            .schedule(
+                ScheduledTransaction {
+                    transaction: input.transaction,
+                    origin: input.origin,
+                    not_before,
+                },
+                now_in_seconds(),
+            )
+            .map_err(MemPoolError::into_core_error)
+    }
+
+    fn promote_scheduled_transactions<C: MiningBlockChainClient + EngineInfo + TermInfo>(
+        &self,
+        chain: &C,
+        now: u64,
+    ) -> Vec<H256> {
+        let promoted = {
+            let mut mem_pool = self.mem_pool.write();
+            let due = mem_pool.take_due_scheduled(now);
+            let mut promoted = Vec::with_capacity(due.len());
+            for scheduled in due {
+                let hash = scheduled.hash();
+                let import = self
+                    .add_transactions_to_pool(
+                        chain,
+                        vec![scheduled.transaction.into()],
+                        scheduled.origin,
+                        &mut mem_pool,
+                    )
+                    .pop()
+                    .expect("one result returned per added transaction; one added => one result; qed");
+                match import {
+                    Ok(_) => promoted.push(hash),
+                    Err(e) => cwarn!(OWN_PARCEL, "Error importing scheduled transaction {:?}: {:?}", hash, e),
+                }
+            }
+            self.record_dropped_transactions(&mut mem_pool, &HashSet::new());
+            promoted
+        };
+        self.notify_transaction_events();
+
+        // ------------------------------------------------------------------
+        // | NOTE Code below requires mem_pool and sealing_queue locks.     |
+        // | Make sure to release the locks before calling that method.     |
+        // ------------------------------------------------------------------
+        if !promoted.is_empty()
+            && self.options.reseal_on_own_transaction
+            && self.transaction_reseal_allowed()
+            && !self.engine_type().ignore_reseal_on_transaction()
+        {
+            self.update_sealing(chain, BlockId::Latest, false);
+        }
+        promoted
+    }
+
+    fn scheduled_transactions(&self) -> Vec<ScheduledTransaction> {
+        self.mem_pool.read().scheduled_transactions()
+    }
+
+    fn set_spend_policy(&self, address: Address, policy: Option<SpendPolicy>) {
+        self.spend_policies.set(address, policy, now_in_seconds());
+    }
+
+    fn spend_policy(&self, address: &Address) -> Option<SpendPolicyStatus> {
+        self.spend_policies.get(address, now_in_seconds())
+    }
+
     fn import_incomplete_transaction<C: MiningBlockChainClient + AccountData + EngineInfo + TermInfo>(
         &self,
         client: &C,
@@ -1013,7 +1467,8 @@ impl MinerService for Miner {
                     })
                     .unwrap_or_else(|| {
                         const DEFAULT_RANGE: Range<u64> = 0..::std::u64::MAX;
-                        get_next_seq(self.ready_transactions(DEFAULT_RANGE).transactions, &addresses)
+                        let size_limit = client.common_params(BlockId::Latest).unwrap().max_body_size();
+                        get_next_seq(self.ready_transactions(size_limit, DEFAULT_RANGE).transactions, &addresses)
                             .map(|seq| {
                                 cdebug!(RPC, "There are ready transactions for {}", platform_address);
                                 seq
@@ -1023,20 +1478,22 @@ impl MinerService for Miner {
             }
         };
         let tx = tx.complete(seq);
-        let tx_hash = tx.hash();
-        let sig = account_provider.get_account(&address, passphrase.as_ref())?.sign(&tx_hash)?;
-        let unverified = UnverifiedTransaction::new(tx, sig);
-        let signed = SignedTransaction::try_new(unverified)?;
-        let hash = signed.hash();
-        self.import_own_transaction(client, signed)?;
-
-        Ok((hash, seq))
+        let now = now_in_seconds();
+        let spent = self.spend_policies.spend(&address, &tx, now).map_err(|violation| {
+            cwarn!(MINER, "Refused to sign the transaction of {}: {}", platform_address, violation);
+            violation
+        })?;
+        match self.sign_and_import_own_transaction(client, account_provider, tx, &address, passphrase) {
+            Ok(hash) => Ok((hash, seq)),
+            Err(err) => {
+                self.spend_policies.refund(&address, spent, now);
+                Err(err)
+            }
+        }
     }
 
-    fn ready_transactions(&self, range: Range<u64>) -> PendingSignedTransactions {
-        // FIXME: Update the body size when the common params are updated
-        let max_body_size = self.engine.machine().genesis_common_params().max_body_size();
-        self.mem_pool.read().top_transactions(max_body_size, None, range)
+    fn ready_transactions(&self, size_limit: usize, range: Range<u64>) -> PendingSignedTransactions {
+        self.mem_pool.read().top_transactions(size_limit, None, range)
     }
 
     fn count_pending_transactions(&self, range: Range<u64>) -> usize {
@@ -1048,6 +1505,26 @@ impl MinerService for Miner {
         self.mem_pool.read().future_transactions()
     }
 
+    fn diagnose_signer<C: AccountData>(&self, chain: &C, address: &Address) -> SignerDiagnosis {
+        let is_signer = |public: &Public| {
+            let signer = public_to_address(public);
+            chain.latest_regular_key_owner(&signer).unwrap_or(signer) == *address
+        };
+        self.mem_pool.read().diagnose_signer(is_signer, chain.latest_seq(address))
+    }
+
+    fn transactions_by_signer<C: AccountData>(&self, chain: &C, address: &Address) -> Vec<PooledTransaction> {
+        let is_signer = |public: &Public| {
+            let signer = public_to_address(public);
+            chain.latest_regular_key_owner(&signer).unwrap_or(signer) == *address
+        };
+        self.mem_pool.read().transactions_by_signer(is_signer)
+    }
+
+    fn pending_queue(&self, hash: &H256) -> Option<PendingQueue> {
+        self.mem_pool.read().pending_queue(hash)
+    }
+
     fn start_sealing<C: MiningBlockChainClient + EngineInfo + TermInfo>(&self, client: &C) {
         cdebug!(MINER, "Start sealing");
         self.sealing_enabled.store(true, Ordering::Relaxed);
@@ -1079,10 +1556,25 @@ fn get_next_seq(transactions: impl IntoIterator<Item = SignedTransaction>, addre
     }
 }
 
+fn now_in_seconds() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).expect("There is no time machine.").as_secs()
+}
+
+/// The size of the encoded block body, which is an RLP list of the transactions whose total size is `payload_size`.
+fn body_size(payload_size: usize) -> usize {
+    const MAX_SHORT_LIST_PAYLOAD: usize = 55;
+    if payload_size <= MAX_SHORT_LIST_PAYLOAD {
+        return 1 + payload_size
+    }
+    let length_of_length = (0usize.count_zeros() - payload_size.leading_zeros() + 7) / 8;
+    1 + length_of_length as usize + payload_size
+}
+
 #[cfg(test)]
 pub mod test {
+    use ccrypto::Blake;
     use cio::IoService;
-    use ckey::{Private, Signature};
+    use ckey::{sign, Private, Signature};
     use ctimer::TimerLoop;
     use ctypes::transaction::Transaction;
     use primitives::H512;
@@ -1091,8 +1583,12 @@ pub mod test {
     use super::super::super::service::ClientIoMessage;
     use super::super::super::transaction::{SignedTransaction, UnverifiedTransaction};
     use super::*;
+    use rlp::UntrustedRlp;
+
     use crate::client::Client;
+    use crate::consensus::{stake, ConsensusMessage};
     use crate::db::NUM_COLUMNS;
+    use crate::tests::helpers::create_test_block_with_data;
 
     #[test]
     fn check_add_transactions_result_idx() {
@@ -1134,6 +1630,200 @@ pub mod test {
         miner.add_transactions_to_pool(client.as_ref(), transactions, TxOrigin::Local, &mut mem_pool);
     }
 
+    #[test]
+    fn batch_with_an_invalid_signature_is_not_imported() {
+        let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let scheme = Scheme::new_test();
+        let miner = Arc::new(Miner::with_scheme(&scheme, db.clone()));
+
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), 3, db.clone());
+        let client = generate_test_client(db, Arc::clone(&miner), &scheme).unwrap();
+
+        let private: Private = H256::random().into();
+        let valid: UnverifiedTransaction = SignedTransaction::new_with_sign(
+            Transaction {
+                seq: 0,
+                fee: 40,
+                network_id: "tc".into(),
+                action: Action::SetRegularKey {
+                    key: H512::random(),
+                },
+            },
+            &private,
+        )
+        .into();
+        let invalid = UnverifiedTransaction::new(
+            Transaction {
+                seq: 1,
+                fee: 40,
+                network_id: "tc".into(),
+                action: Action::SetRegularKey {
+                    key: H512::random(),
+                },
+            },
+            Signature::random(),
+        );
+
+        let errors = miner
+            .add_transactions_to_pool_all_or_nothing(
+                client.as_ref(),
+                vec![valid, invalid],
+                TxOrigin::Local,
+                &mut mem_pool,
+            )
+            .unwrap_err();
+        assert_eq!(2, errors.len());
+        assert!(errors[1].is_some());
+
+        let status = mem_pool.status();
+        assert_eq!(0, status.pending);
+        assert_eq!(0, status.future);
+    }
+
+    #[test]
+    fn scheduled_transaction_is_ready_after_not_before() {
+        let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let scheme = Scheme::new_test();
+        let miner = Arc::new(Miner::with_scheme(&scheme, db.clone()));
+        let client = generate_test_client(db, Arc::clone(&miner), &scheme).unwrap();
+
+        let faucet: Private = "ede1d4ccb4ec9a8bbbae9a13db3f4a7b56ea04189be86ac3a6a439d9a0a1addd".into();
+        let tx = SignedTransaction::new_with_sign(
+            Transaction {
+                seq: 0,
+                fee: 10,
+                network_id: "tc".into(),
+                action: Action::Pay {
+                    receiver: Address::random(),
+                    quantity: 100,
+                },
+            },
+            &faucet,
+        );
+        let hash = tx.hash();
+        let not_before = 1_000;
+        miner.schedule_own_transaction(client.as_ref(), tx.clone(), TxOrigin::Local, not_before).unwrap();
+
+        // The gossip sends the ready transactions only.
+        let ready = || miner.ready_transactions(usize::max_value(), 0..u64::max_value()).transactions;
+        assert_eq!(vec![hash], miner.scheduled_transactions().iter().map(|tx| tx.hash()).collect::<Vec<_>>());
+        assert!(ready().is_empty());
+
+        assert!(miner.promote_scheduled_transactions(client.as_ref(), not_before - 1).is_empty());
+        assert!(ready().is_empty());
+        assert_eq!(1, miner.scheduled_transactions().len());
+
+        assert_eq!(vec![hash], miner.promote_scheduled_transactions(client.as_ref(), not_before));
+        assert_eq!(vec![tx], ready());
+        assert!(miner.scheduled_transactions().is_empty());
+    }
+
+    #[test]
+    fn transaction_events_are_notified_after_the_mem_pool_is_released() {
+        let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let scheme = Scheme::new_test();
+        let miner = Arc::new(Miner::with_scheme(&scheme, db.clone()));
+        let client = generate_test_client(db, Arc::clone(&miner), &scheme).unwrap();
+
+        // Whether the mem pool could be locked in the listener
+        let notified = Arc::new(Mutex::new(Vec::new()));
+        let listening_miner = Arc::downgrade(&miner);
+        let listener_notified = Arc::clone(&notified);
+        miner.add_transaction_event_listener(Box::new(move |events| {
+            let is_released = listening_miner.upgrade().unwrap().mem_pool.try_write().is_some();
+            listener_notified.lock().extend(events.iter().map(|event| (*event, is_released)));
+        }));
+
+        let faucet: Private = "ede1d4ccb4ec9a8bbbae9a13db3f4a7b56ea04189be86ac3a6a439d9a0a1addd".into();
+        let tx = SignedTransaction::new_with_sign(
+            Transaction {
+                seq: 0,
+                fee: 10,
+                network_id: "tc".into(),
+                action: Action::Pay {
+                    receiver: Address::random(),
+                    quantity: 100,
+                },
+            },
+            &faucet,
+        );
+        let hash = tx.hash();
+        miner.import_own_transaction(client.as_ref(), tx, TxOrigin::Local).unwrap();
+        assert_eq!(
+            vec![(
+                TransactionEvent::Queued {
+                    hash,
+                },
+                true
+            )],
+            *notified.lock()
+        );
+    }
+
+    #[test]
+    fn blocks_are_filled_up_to_the_max_body_size_changed_by_change_params() {
+        let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let scheme = Scheme::new_test_solo();
+        let options = MinerOptions {
+            reseal_on_own_transaction: false,
+            no_reseal_timer: true,
+            ..Default::default()
+        };
+        let miner = Miner::new(options, &scheme, None, db.clone());
+        let client = generate_test_client(db, Arc::clone(&miner), &scheme).unwrap();
+        // It has the most of the stakes in the bundled scheme.
+        let stakeholder: Private = "ede1d4ccb4ec9a8bbbae9a13db3f4a7b56ea04189be86ac3a6a439d9a0a1addd".into();
+        let pay = |seq: u64| {
+            SignedTransaction::new_with_sign(
+                Transaction {
+                    seq,
+                    fee: 10,
+                    network_id: "tc".into(),
+                    action: Action::Pay {
+                        receiver: Address::random(),
+                        quantity: 100,
+                    },
+                },
+                &stakeholder,
+            )
+        };
+
+        // The body of the next block can have three payments.
+        let max_body_size = body_size(3 * rlp::encode(&pay(1)).len());
+        let mut params = scheme.genesis_params();
+        assert!(params.max_body_size() > 2 * max_body_size);
+        params.set_max_body_size_for_test(max_body_size);
+        let change_params = |signatures| {
+            stake::Action::<ConsensusMessage>::ChangeParams {
+                metadata_seq: 0,
+                params: params.into(),
+                signatures,
+            }
+        };
+        let signature = sign(&stakeholder, &H256::blake(rlp::encode(&change_params(vec![])))).unwrap();
+        let tx = SignedTransaction::new_with_sign(
+            Transaction {
+                seq: 0,
+                fee: 10,
+                network_id: "tc".into(),
+                action: Action::Custom {
+                    handler_id: stake::CUSTOM_ACTION_HANDLER_ID,
+                    bytes: rlp::encode(&change_params(vec![signature])).into_vec(),
+                },
+            },
+            &stakeholder,
+        );
+        miner.import_own_transaction(client.as_ref(), tx, TxOrigin::Local).unwrap();
+        miner.update_sealing(client.as_ref(), BlockId::Latest, false);
+        assert_eq!(1, client.chain_info().best_block_number);
+        assert_eq!(max_body_size, client.common_params(BlockId::Latest).unwrap().max_body_size());
+
+        let payments: Vec<_> = (1..=6).map(pay).collect();
+        miner.import_own_transactions(client.as_ref(), payments.clone(), TxOrigin::Local).unwrap();
+        let (block, _) = miner.prepare_block(BlockId::Latest, client.as_ref()).unwrap();
+        assert_eq!(payments[..3].to_vec(), block.block().transactions().to_vec());
+    }
+
     fn generate_test_client(db: Arc<KeyValueDB>, miner: Arc<Miner>, scheme: &Scheme) -> Result<Arc<Client>, Error> {
         let timer_loop = TimerLoop::new(2);
 
@@ -1143,4 +1833,111 @@ pub mod test {
 
         Client::try_new(&client_config, scheme, db, miner, io_service.channel(), reseal_timer)
     }
+
+    #[test]
+    fn body_size_matches_the_encoded_body() {
+        let private: Private = H256::random().into();
+        let mut transactions = Vec::new();
+        let mut payload_size = 0;
+        // Covers the short list, and the long lists whose lengths take one and two bytes.
+        for count in 0..4 {
+            let bytes = create_test_block_with_data(&Header::default(), &transactions, &[]);
+            let body = UntrustedRlp::new(&bytes).at(1).unwrap().as_raw().len();
+            assert_eq!(body, body_size(payload_size), "{} transactions", count);
+
+            let tx = SignedTransaction::new_with_sign(
+                Transaction {
+                    seq: count,
+                    fee: 10,
+                    network_id: "tc".into(),
+                    action: Action::Store {
+                        content: "a".repeat(100 * count as usize),
+                        certifier: Address::random(),
+                        signature: Signature::random(),
+                    },
+                },
+                &private,
+            );
+            payload_size += rlp::encode(&tx).len();
+            transactions.push(tx);
+        }
+        assert!(payload_size > 255);
+    }
+
+    /// Returns a client of the solo engine, whose blocks are sealed before they're closed.
+    fn solo_miner_and_client() -> (Arc<Miner>, Arc<Client>) {
+        let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let scheme = Scheme::new_test_solo();
+        let options = MinerOptions {
+            reseal_on_own_transaction: false,
+            no_reseal_timer: true,
+            ..Default::default()
+        };
+        let miner = Miner::new(options, &scheme, None, db.clone());
+        let client = generate_test_client(db, Arc::clone(&miner), &scheme).unwrap();
+        (miner, client)
+    }
+
+    /// Pays to the receiver from the account that has the most of the balance in the bundled scheme.
+    fn pay_to(receiver: Address, quantity: u64) -> SignedTransaction {
+        let payer: Private = "ede1d4ccb4ec9a8bbbae9a13db3f4a7b56ea04189be86ac3a6a439d9a0a1addd".into();
+        SignedTransaction::new_with_sign(
+            Transaction {
+                seq: 0,
+                fee: 10,
+                network_id: "tc".into(),
+                action: Action::Pay {
+                    receiver,
+                    quantity,
+                },
+            },
+            &payer,
+        )
+    }
+
+    /// Imports the block through the block queue, the way the blocks from the peers are imported.
+    fn import_through_the_queue(client: &Client, block: ClosedBlock) -> H256 {
+        let hash = client.import_block(block.lock().already_sealed().rlp_bytes()).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while client.block_chain().best_block_hash() != hash {
+            assert!(Instant::now() < deadline, "The block {} is not imported", hash);
+            client.import_verified_blocks();
+        }
+        hash
+    }
+
+    #[test]
+    fn block_closed_by_the_miner_is_imported_without_the_execution() {
+        let (miner, client) = solo_miner_and_client();
+        let receiver = Address::random();
+        miner.import_own_transaction(client.as_ref(), pay_to(receiver, 100), TxOrigin::Local).unwrap();
+        let (block, _) = miner.prepare_block(BlockId::Latest, client.as_ref()).unwrap();
+        assert_eq!(1, client.num_cached_executions());
+
+        import_through_the_queue(&client, block);
+        let stats = client.execution_cache_stats();
+        assert_eq!(1, stats.reused);
+        assert_eq!(0, stats.rejected);
+        assert_eq!(100, client.latest_balance(&receiver));
+    }
+
+    #[test]
+    fn corrupted_cached_execution_falls_back_to_the_execution() {
+        let (miner, client) = solo_miner_and_client();
+        let (empty_block, _) = miner.prepare_block(BlockId::Latest, client.as_ref()).unwrap();
+        let receiver = Address::random();
+        miner.import_own_transaction(client.as_ref(), pay_to(receiver, 100), TxOrigin::Local).unwrap();
+        let (block, _) = miner.prepare_block(BlockId::Latest, client.as_ref()).unwrap();
+
+        // The execution of the empty block is kept as if it's the execution of the payment.
+        let genesis = client.block_header(&BlockId::Number(0)).unwrap();
+        let hash = block.header().hash();
+        client.cache_execution(genesis.state_root(), hash, 1, empty_block.lock());
+
+        assert_eq!(hash, import_through_the_queue(&client, block));
+        let stats = client.execution_cache_stats();
+        assert_eq!(0, stats.reused);
+        assert_eq!(1, stats.rejected);
+        assert_eq!(100, client.latest_balance(&receiver));
+    }
 }
@@ -15,23 +15,39 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod backup;
+mod fee_floor;
 mod mem_pool;
 mod mem_pool_types;
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::module_inception))]
 mod miner;
+mod proposal_budget;
+mod relay_stats;
+mod rpc_rate_limiter;
 mod sealing_queue;
+mod seen_transactions;
+mod spend_policy;
 mod stratum;
 mod work_notify;
 
 use std::ops::Range;
 
 use ckey::{Address, Password, PlatformAddress};
+use cnetwork::NodeId;
 use cstate::{FindActionHandler, TopStateView};
 use ctypes::transaction::IncompleteTransaction;
 use cvm::ChainTimeInfo;
 use primitives::{Bytes, H256};
 
+pub use self::fee_floor::{FeeFloorOptions, FeeFloorStatus};
+pub use self::mem_pool_types::{
+    DropReason, PendingQueue, PooledTransaction, QueuedTransaction, ScheduledTransaction, SignerDiagnosis,
+    TransactionEvent, TxOrigin,
+};
 pub use self::miner::{AuthoringParams, Miner, MinerOptions};
+pub use self::proposal_budget::ProposalBudget;
+pub use self::relay_stats::{RelayStats, RelayStatus};
+pub use self::seen_transactions::SeenTransactions;
+pub use self::spend_policy::{SpendPolicy, SpendPolicyStatus, SpendPolicyViolation};
 pub use self::stratum::{Config as StratumConfig, Error as StratumError, Stratum};
 use crate::account_provider::{AccountProvider, Error as AccountProviderError};
 use crate::block::ClosedBlock;
@@ -72,6 +88,15 @@ pub trait MinerService: Send + Sync {
     /// Set maximal number of transactions kept in the queue (both current and future).
     fn set_transactions_limit(&self, limit: usize);
 
+    /// Get the node-local limits of the blocks that this node proposes.
+    fn proposal_budget(&self) -> ProposalBudget;
+
+    /// Set the node-local limits of the blocks that this node proposes.
+    fn set_proposal_budget(&self, budget: ProposalBudget);
+
+    /// Get the fee floor that follows the load of the mem pool. None if it's not enabled.
+    fn fee_floor(&self) -> Option<FeeFloorStatus>;
+
     /// Called when blocks are imported to chain, updates transactions queue.
     fn chain_new_blocks<C>(&self, chain: &C, imported: &[H256], invalid: &[H256], enacted: &[H256], retracted: &[H256])
     where
@@ -111,21 +136,61 @@ pub trait MinerService: Send + Sync {
         F: FnOnce(&ClosedBlock) -> T,
         Self: Sized;
 
-    /// Imports transactions to mem pool.
+    /// Imports transactions received from the peer to mem pool.
     fn import_external_transactions<C: MiningBlockChainClient + EngineInfo + TermInfo>(
         &self,
         client: &C,
         transactions: Vec<UnverifiedTransaction>,
+        peer: NodeId,
     ) -> Vec<Result<TransactionImportResult, Error>>;
 
     /// Imports own (node owner) transaction to mem pool.
+    /// The RPC transactions are refused if their source has submitted too many transactions.
     fn import_own_transaction<C: MiningBlockChainClient + EngineInfo + TermInfo + TermInfo>(
         &self,
         chain: &C,
         tx: SignedTransaction,
+        origin: TxOrigin,
     ) -> Result<TransactionImportResult, Error>;
 
+    /// Imports own (node owner) transactions to mem pool only if all of them can be imported.
+    /// Returns the errors of the rejected transactions otherwise.
+    fn import_own_transactions<C: MiningBlockChainClient + EngineInfo + TermInfo>(
+        &self,
+        chain: &C,
+        transactions: Vec<SignedTransaction>,
+        origin: TxOrigin,
+    ) -> Result<Vec<TransactionImportResult>, Vec<Option<Error>>>;
+
+    /// Imports own (node owner) transaction to the scheduled queue of mem pool.
+    /// It's neither included in blocks nor propagated until `promote_scheduled_transactions` is called after `not_before`.
+    fn schedule_own_transaction<C: MiningBlockChainClient + EngineInfo + TermInfo>(
+        &self,
+        chain: &C,
+        tx: SignedTransaction,
+        origin: TxOrigin,
+        not_before: u64,
+    ) -> Result<(), Error>;
+
+    /// Moves the scheduled transactions whose `not_before` has passed at `now` to the other queues of mem pool.
+    /// Returns the hashes of the transactions that entered the queues.
+    fn promote_scheduled_transactions<C: MiningBlockChainClient + EngineInfo + TermInfo>(
+        &self,
+        chain: &C,
+        now: u64,
+    ) -> Vec<H256>;
+
+    /// Get a list of the transactions in the scheduled queue, in the order they become eligible.
+    fn scheduled_transactions(&self) -> Vec<ScheduledTransaction>;
+
+    /// Set the limits of the transactions that the node signs for the account. None removes them.
+    fn set_spend_policy(&self, address: Address, policy: Option<SpendPolicy>);
+
+    /// Get the limits of the transactions that the node signs for the account.
+    fn spend_policy(&self, address: &Address) -> Option<SpendPolicyStatus>;
+
     /// Imports incomplete (node owner) transaction to mem pool.
+    /// The node refuses to sign it if it violates the spend policy of the account.
     fn import_incomplete_transaction<C: MiningBlockChainClient + AccountData + EngineInfo + TermInfo>(
         &self,
         chain: &C,
@@ -137,7 +202,8 @@ pub trait MinerService: Send + Sync {
     ) -> Result<(H256, u64), Error>;
 
     /// Get a list of all pending transactions in the mem pool.
-    fn ready_transactions(&self, range: Range<u64>) -> PendingSignedTransactions;
+    /// The total size of the transactions is less than `size_limit`.
+    fn ready_transactions(&self, size_limit: usize, range: Range<u64>) -> PendingSignedTransactions;
 
     /// Get a count of all pending transactions in the mem pool.
     fn count_pending_transactions(&self, range: Range<u64>) -> usize;
@@ -145,6 +211,15 @@ pub trait MinerService: Send + Sync {
     /// Get a list of all future transactions.
     fn future_transactions(&self) -> Vec<SignedTransaction>;
 
+    /// Diagnose why the transactions of the account are not included.
+    fn diagnose_signer<C: AccountData>(&self, chain: &C, address: &Address) -> SignerDiagnosis;
+
+    /// Get the transactions of the account in the mem pool with their origins.
+    fn transactions_by_signer<C: AccountData>(&self, chain: &C, address: &Address) -> Vec<PooledTransaction>;
+
+    /// Get the queue that holds the transaction in the mem pool.
+    fn pending_queue(&self, hash: &H256) -> Option<PendingQueue>;
+
     /// Start sealing.
     fn start_sealing<C: MiningBlockChainClient + EngineInfo + TermInfo>(&self, client: &C);
 
@@ -0,0 +1,95 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::cmp;
+
+/// The smallest body size that the budget can limit the proposals to.
+pub const MIN_PROPOSAL_BODY_SIZE: usize = 1024;
+/// The smallest number of the transactions that the budget can limit the proposals to.
+pub const MIN_PROPOSAL_TRANSACTIONS: usize = 1;
+
+/// The node-local limits of the blocks that this node proposes.
+///
+/// The budget only affects how many transactions the miner fills into its own blocks.
+/// The blocks from the others are still verified with the maximums in the common params.
+/// `None` means that there is no limit other than the protocol maximum.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ProposalBudget {
+    pub max_body_size: Option<usize>,
+    pub max_transactions: Option<usize>,
+}
+
+impl ProposalBudget {
+    /// Returns the budget that never exceeds the protocol maximum and never goes below the floor.
+    pub fn clamp(self, protocol_max_body_size: usize) -> Self {
+        let max_body_size =
+            self.max_body_size.map(|size| cmp::min(cmp::max(size, MIN_PROPOSAL_BODY_SIZE), protocol_max_body_size));
+        let max_transactions = self.max_transactions.map(|count| cmp::max(count, MIN_PROPOSAL_TRANSACTIONS));
+        Self {
+            max_body_size,
+            max_transactions,
+        }
+    }
+
+    /// The body size that the proposal must not exceed.
+    pub fn body_size_limit(self, protocol_max_body_size: usize) -> usize {
+        self.clamp(protocol_max_body_size).max_body_size.unwrap_or(protocol_max_body_size)
+    }
+
+    /// The number of the transactions that the proposal must not exceed.
+    pub fn transactions_limit(self) -> usize {
+        self.clamp(usize::max_value()).max_transactions.unwrap_or_else(usize::max_value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlimited_budget_follows_the_protocol() {
+        let budget = ProposalBudget::default();
+        assert_eq!(budget, budget.clamp(4096));
+        assert_eq!(4096, budget.body_size_limit(4096));
+        assert_eq!(usize::max_value(), budget.transactions_limit());
+    }
+
+    #[test]
+    fn budget_does_not_exceed_the_protocol_maximum() {
+        let budget = ProposalBudget {
+            max_body_size: Some(1_000_000),
+            max_transactions: Some(10),
+        };
+        assert_eq!(Some(4096), budget.clamp(4096).max_body_size);
+        assert_eq!(4096, budget.body_size_limit(4096));
+        assert_eq!(10, budget.transactions_limit());
+    }
+
+    #[test]
+    fn budget_does_not_go_below_the_floor() {
+        let budget = ProposalBudget {
+            max_body_size: Some(0),
+            max_transactions: Some(0),
+        };
+        let clamped = budget.clamp(4096);
+        assert_eq!(Some(MIN_PROPOSAL_BODY_SIZE), clamped.max_body_size);
+        assert_eq!(Some(MIN_PROPOSAL_TRANSACTIONS), clamped.max_transactions);
+        assert_eq!(MIN_PROPOSAL_TRANSACTIONS, budget.transactions_limit());
+
+        // The protocol maximum wins even if it is below the floor.
+        assert_eq!(512, budget.body_size_limit(512));
+    }
+}
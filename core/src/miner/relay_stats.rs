@@ -0,0 +1,57 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RelayStatus {
+    /// The ready transactions that some peers didn't get yet
+    pub queued: usize,
+    /// The number of the transactions sent to the peers, counted once for each peer
+    pub sent: usize,
+    /// The number of the transactions that left the mem pool before they were relayed to every peer
+    pub dropped_stale: usize,
+}
+
+/// The counters of the transaction relay, shared between the transaction sync extension and the RPC.
+#[derive(Default)]
+pub struct RelayStats {
+    queued: AtomicUsize,
+    sent: AtomicUsize,
+    dropped_stale: AtomicUsize,
+}
+
+impl RelayStats {
+    pub fn set_queued(&self, queued: usize) {
+        self.queued.store(queued, Ordering::Relaxed);
+    }
+
+    pub fn add_sent(&self, sent: usize) {
+        self.sent.fetch_add(sent, Ordering::Relaxed);
+    }
+
+    pub fn add_dropped_stale(&self, dropped: usize) {
+        self.dropped_stale.fetch_add(dropped, Ordering::Relaxed);
+    }
+
+    pub fn status(&self) -> RelayStatus {
+        RelayStatus {
+            queued: self.queued.load(Ordering::Relaxed),
+            sent: self.sent.load(Ordering::Relaxed),
+            dropped_stale: self.dropped_stale.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+pub const DEFAULT_RPC_RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+/// The maximum number of the sources counted in a window.
+pub const MAX_RPC_RATE_LIMIT_SOURCES: usize = 10_000;
+
+struct Inner {
+    started_at: Instant,
+    counts: HashMap<Option<IpAddr>, usize>,
+}
+
+/// Limits the number of the transactions that each source address submits over RPC in a window.
+///
+/// The transactions without a source address, which are submitted over WebSocket or IPC, share one count.
+/// The counts are reset when a window passes. If more sources than the capacity submit transactions in a window,
+/// the new sources are refused until the counts are reset.
+pub struct RpcRateLimiter {
+    /// 0 means no limit.
+    limit: usize,
+    window: Duration,
+    capacity: usize,
+    inner: Mutex<Inner>,
+}
+
+impl RpcRateLimiter {
+    pub fn new(limit: usize, window: Duration) -> Self {
+        Self::with_capacity(limit, window, MAX_RPC_RATE_LIMIT_SOURCES)
+    }
+
+    fn with_capacity(limit: usize, window: Duration, capacity: usize) -> Self {
+        Self {
+            limit,
+            window,
+            capacity,
+            inner: Mutex::new(Inner {
+                started_at: Instant::now(),
+                counts: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Returns false if the source can't submit the transactions in the current window.
+    pub fn acquire(&self, source: Option<IpAddr>, count: usize) -> bool {
+        self.acquire_at(source, count, Instant::now())
+    }
+
+    fn acquire_at(&self, source: Option<IpAddr>, count: usize, now: Instant) -> bool {
+        if self.limit == 0 {
+            return true
+        }
+        let mut inner = self.inner.lock();
+        if now.duration_since(inner.started_at) >= self.window {
+            inner.started_at = now;
+            inner.counts.clear();
+        }
+        if !inner.counts.contains_key(&source) && inner.counts.len() >= self.capacity {
+            return false
+        }
+        let submitted = inner.counts.entry(source).or_default();
+        if *submitted + count > self.limit {
+            return false
+        }
+        *submitted += count;
+        true
+    }
+}
+
+impl Default for RpcRateLimiter {
+    fn default() -> Self {
+        Self::new(0, DEFAULT_RPC_RATE_LIMIT_WINDOW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sources_are_limited_separately() {
+        let limiter = RpcRateLimiter::new(3, Duration::from_secs(10));
+        let now = Instant::now();
+        let a = Some("10.0.0.1".parse().unwrap());
+        let b = Some("10.0.0.2".parse().unwrap());
+
+        assert!(limiter.acquire_at(a, 2, now));
+        assert!(!limiter.acquire_at(a, 2, now));
+        assert!(limiter.acquire_at(a, 1, now));
+        assert!(!limiter.acquire_at(a, 1, now));
+        assert!(limiter.acquire_at(b, 3, now));
+    }
+
+    #[test]
+    fn counts_are_reset_after_the_window() {
+        let limiter = RpcRateLimiter::new(1, Duration::from_secs(10));
+        let start = Instant::now();
+        let source = Some("10.0.0.1".parse().unwrap());
+
+        assert!(limiter.acquire_at(source, 1, start));
+        assert!(!limiter.acquire_at(source, 1, start + Duration::from_secs(9)));
+        assert!(limiter.acquire_at(source, 1, start + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn new_sources_are_refused_over_the_capacity() {
+        let limiter = RpcRateLimiter::with_capacity(5, Duration::from_secs(10), 2);
+        let start = Instant::now();
+        let sources: Vec<_> = (1..=3).map(|i| Some(format!("10.0.0.{}", i).parse().unwrap())).collect();
+
+        assert!(limiter.acquire_at(sources[0], 1, start));
+        assert!(limiter.acquire_at(sources[1], 1, start));
+        assert!(!limiter.acquire_at(sources[2], 1, start));
+        // The known sources are still counted.
+        assert!(limiter.acquire_at(sources[0], 1, start));
+
+        assert!(limiter.acquire_at(sources[2], 1, start + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn sources_without_address_share_a_count() {
+        let limiter = RpcRateLimiter::new(3, Duration::from_secs(10));
+        let now = Instant::now();
+
+        assert!(limiter.acquire_at(None, 2, now));
+        assert!(limiter.acquire_at(None, 1, now));
+        assert!(!limiter.acquire_at(None, 1, now));
+        assert!(limiter.acquire_at(Some("10.0.0.1".parse().unwrap()), 1, now));
+    }
+}
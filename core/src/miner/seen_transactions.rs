@@ -0,0 +1,182 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::{HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use primitives::H256;
+
+use crate::transaction::UnverifiedTransaction;
+
+pub const DEFAULT_SEEN_TRANSACTIONS_WINDOW: Duration = Duration::from_secs(10 * 60);
+pub const DEFAULT_SEEN_TRANSACTIONS_CAPACITY: usize = 100_000;
+
+#[derive(Default)]
+struct Inner {
+    hashes: HashSet<H256>,
+    /// The hashes in the order they were seen.
+    queue: VecDeque<(Instant, H256)>,
+}
+
+/// The hashes of the transactions that the node has handled recently.
+///
+/// The transaction sync extension drops the re-announced transactions before verifying their signatures.
+/// The entries are not refreshed when they are seen again, so a transaction dropped for its low fee can be
+/// submitted again after the window.
+pub struct SeenTransactions {
+    window: Duration,
+    capacity: usize,
+    inner: Mutex<Inner>,
+}
+
+impl SeenTransactions {
+    pub fn new(window: Duration, capacity: usize) -> Self {
+        Self {
+            window,
+            capacity,
+            inner: Default::default(),
+        }
+    }
+
+    /// Returns false if the hash is already seen in the window.
+    pub fn insert(&self, hash: H256) -> bool {
+        self.insert_at(hash, Instant::now())
+    }
+
+    pub fn contains(&self, hash: &H256) -> bool {
+        self.contains_at(hash, Instant::now())
+    }
+
+    /// Remembers the unseen transactions and returns them with the number of the seen ones.
+    pub fn filter_unseen(&self, transactions: Vec<UnverifiedTransaction>) -> (Vec<UnverifiedTransaction>, usize) {
+        self.filter_unseen_at(transactions, Instant::now())
+    }
+
+    fn insert_at(&self, hash: H256, now: Instant) -> bool {
+        let mut inner = self.inner.lock();
+        self.expire(&mut inner, now);
+        if !inner.hashes.insert(hash) {
+            return false
+        }
+        inner.queue.push_back((now, hash));
+        if inner.queue.len() > self.capacity {
+            let (_, oldest) = inner.queue.pop_front().expect("The queue is not empty");
+            inner.hashes.remove(&oldest);
+        }
+        true
+    }
+
+    fn contains_at(&self, hash: &H256, now: Instant) -> bool {
+        let mut inner = self.inner.lock();
+        self.expire(&mut inner, now);
+        inner.hashes.contains(hash)
+    }
+
+    fn filter_unseen_at(
+        &self,
+        transactions: Vec<UnverifiedTransaction>,
+        now: Instant,
+    ) -> (Vec<UnverifiedTransaction>, usize) {
+        let total = transactions.len();
+        let unseen: Vec<_> = transactions.into_iter().filter(|tx| self.insert_at(tx.hash(), now)).collect();
+        let seen = total - unseen.len();
+        (unseen, seen)
+    }
+
+    fn expire(&self, inner: &mut Inner, now: Instant) {
+        while let Some((seen_at, hash)) = inner.queue.front().cloned() {
+            if now.duration_since(seen_at) < self.window {
+                break
+            }
+            inner.queue.pop_front();
+            inner.hashes.remove(&hash);
+        }
+    }
+}
+
+impl Default for SeenTransactions {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEEN_TRANSACTIONS_WINDOW, DEFAULT_SEEN_TRANSACTIONS_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ckey::{Address, Generator, Random};
+    use ctypes::transaction::{Action, Transaction};
+
+    use super::*;
+    use crate::transaction::SignedTransaction;
+
+    fn transaction(fee: u64) -> UnverifiedTransaction {
+        let keypair = Random.generate().unwrap();
+        let tx = Transaction {
+            seq: 0,
+            fee,
+            network_id: "tc".into(),
+            action: Action::Pay {
+                receiver: Address::random(),
+                quantity: 1,
+            },
+        };
+        SignedTransaction::new_with_sign(tx, keypair.private()).into()
+    }
+
+    #[test]
+    fn re_announced_transactions_are_filtered() {
+        let seen = SeenTransactions::default();
+        let now = Instant::now();
+        let transactions: Vec<_> = (0..3).map(|_| transaction(10)).collect();
+
+        let (unseen, redundant) = seen.filter_unseen_at(transactions.clone(), now);
+        assert_eq!(transactions, unseen);
+        assert_eq!(0, redundant);
+
+        // Nothing is passed to the verification.
+        let (unseen, redundant) = seen.filter_unseen_at(transactions, now + Duration::from_secs(1));
+        assert_eq!(Vec::<UnverifiedTransaction>::new(), unseen);
+        assert_eq!(3, redundant);
+    }
+
+    #[test]
+    fn entries_age_out_after_the_window() {
+        let seen = SeenTransactions::new(Duration::from_secs(10), 100);
+        let start = Instant::now();
+        let dropped_for_low_fee = transaction(1).hash();
+        assert!(seen.insert_at(dropped_for_low_fee, start));
+
+        // Seeing it again doesn't extend its lifetime.
+        assert!(!seen.insert_at(dropped_for_low_fee, start + Duration::from_secs(9)));
+        assert!(seen.contains_at(&dropped_for_low_fee, start + Duration::from_secs(9)));
+
+        assert!(!seen.contains_at(&dropped_for_low_fee, start + Duration::from_secs(10)));
+        assert!(seen.insert_at(dropped_for_low_fee, start + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn the_oldest_entry_is_evicted_over_the_capacity() {
+        let seen = SeenTransactions::new(Duration::from_secs(10), 2);
+        let now = Instant::now();
+        let hashes: Vec<_> = (0..3).map(|_| H256::random()).collect();
+        for hash in &hashes {
+            assert!(seen.insert_at(*hash, now));
+        }
+        assert!(!seen.contains_at(&hashes[0], now));
+        assert!(seen.contains_at(&hashes[1], now));
+        assert!(seen.contains_at(&hashes[2], now));
+    }
+}
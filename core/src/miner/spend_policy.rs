@@ -0,0 +1,529 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use ckey::Address;
+use ctypes::transaction::{Action, Transaction};
+use kvdb::{DBTransaction, KeyValueDB};
+use parking_lot::Mutex;
+use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
+
+use crate::db::COL_EXTRA;
+
+const PREFIX_SPEND_POLICY: &[u8] = b"spend-policy-";
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// The limits of the transactions that the node signs for an account.
+/// It's a node-local policy, so the transactions signed by others are not limited.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpendPolicy {
+    /// The CCC that the transactions signed in a UTC day can spend, including their fees. None means no limit.
+    pub daily_cap: Option<u64>,
+    /// The receivers of the payments. None means any receiver.
+    pub allowed_recipients: Option<Vec<Address>>,
+}
+
+/// The policy of an account and the CCC spent in the current UTC day.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpendPolicyStatus {
+    pub policy: SpendPolicy,
+    pub spent_today: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpendPolicyViolation {
+    DailyCapExceeded {
+        daily_cap: u64,
+        spent_today: u64,
+        requested: u64,
+    },
+    RecipientNotAllowed(Address),
+    /// The action moves value in a way that the policy cannot count, so an account bound by a policy cannot send it.
+    ActionNotAllowed(&'static str),
+    /// The account is bound by a policy, so the node doesn't sign arbitrary messages for it.
+    SigningNotAllowed,
+}
+
+impl fmt::Display for SpendPolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpendPolicyViolation::DailyCapExceeded {
+                daily_cap,
+                spent_today,
+                requested,
+            } => write!(
+                f,
+                "The transaction spends {} CCC, but only {} CCC of the daily cap {} CCC is left",
+                requested,
+                daily_cap.saturating_sub(*spent_today),
+                daily_cap
+            ),
+            SpendPolicyViolation::RecipientNotAllowed(recipient) => {
+                write!(f, "{} is not an allowed recipient", recipient)
+            }
+            SpendPolicyViolation::ActionNotAllowed(action) => {
+                write!(f, "{} is not allowed for an account bound by a spend policy", action)
+            }
+            SpendPolicyViolation::SigningNotAllowed => {
+                write!(f, "Cannot sign a message for an account bound by a spend policy")
+            }
+        }
+    }
+}
+
+/// The CCC that the transaction takes from the signer.
+/// Only the payments and the wrapping of CCC are counted, so the other actions are refused.
+/// The wrapped CCC goes to a lock script, not to an address, so it's refused if the recipients are restricted.
+fn spent_ccc(policy: &SpendPolicy, tx: &Transaction) -> Result<u64, SpendPolicyViolation> {
+    let quantity = match &tx.action {
+        Action::Pay {
+            receiver,
+            quantity,
+        } => {
+            if let Some(allowed_recipients) = &policy.allowed_recipients {
+                if !allowed_recipients.contains(receiver) {
+                    return Err(SpendPolicyViolation::RecipientNotAllowed(*receiver))
+                }
+            }
+            *quantity
+        }
+        Action::WrapCCC {
+            quantity,
+            ..
+        } if policy.allowed_recipients.is_none() => *quantity,
+        action => return Err(SpendPolicyViolation::ActionNotAllowed(action.name())),
+    };
+    Ok(tx.fee.saturating_add(quantity))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct AccountSpending {
+    policy: SpendPolicy,
+    /// The UTC day of `spent`, in days since the epoch.
+    day: u64,
+    spent: u64,
+}
+
+impl AccountSpending {
+    fn spent_on(&self, day: u64) -> u64 {
+        if self.day == day {
+            self.spent
+        } else {
+            0
+        }
+    }
+}
+
+impl Encodable for AccountSpending {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        let daily_cap: Vec<u64> = self.policy.daily_cap.into_iter().collect();
+        s.begin_list(5)
+            .append_list(&daily_cap)
+            .append(&self.policy.allowed_recipients.is_some())
+            .append_list(self.policy.allowed_recipients.as_ref().map_or(&[][..], Vec::as_slice))
+            .append(&self.day)
+            .append(&self.spent);
+    }
+}
+
+impl Decodable for AccountSpending {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 5 {
+            return Err(DecoderError::RlpInvalidLength {
+                got: item_count,
+                expected: 5,
+            })
+        }
+        let daily_cap: Vec<u64> = rlp.list_at(0)?;
+        if daily_cap.len() > 1 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                expected: 1,
+                got: daily_cap.len(),
+            })
+        }
+        let is_restricted: bool = rlp.val_at(1)?;
+        let allowed_recipients = rlp.list_at(2)?;
+        Ok(Self {
+            policy: SpendPolicy {
+                daily_cap: daily_cap.first().cloned(),
+                allowed_recipients: if is_restricted {
+                    Some(allowed_recipients)
+                } else {
+                    None
+                },
+            },
+            day: rlp.val_at(3)?,
+            spent: rlp.val_at(4)?,
+        })
+    }
+}
+
+/// The spend policies of the accounts, checked before the node signs their transactions.
+/// The CCC spent in the current UTC day is kept in the database with the policies, so it survives restarts.
+pub struct SpendPolicies {
+    db: Arc<KeyValueDB>,
+    accounts: Mutex<HashMap<Address, AccountSpending>>,
+}
+
+impl SpendPolicies {
+    pub fn new(db: Arc<KeyValueDB>) -> Self {
+        let mut accounts = HashMap::new();
+        for (key, value) in db.iter_from_prefix(COL_EXTRA, PREFIX_SPEND_POLICY) {
+            if !key.starts_with(PREFIX_SPEND_POLICY) {
+                break
+            }
+            let address = Address::from(&key[PREFIX_SPEND_POLICY.len()..]);
+            match UntrustedRlp::new(&value).as_val() {
+                Ok(spending) => {
+                    accounts.insert(address, spending);
+                }
+                Err(err) => cerror!(MINER, "The spend policy of {} is corrupted: {:?}", address, err),
+            }
+        }
+        Self {
+            db,
+            accounts: Mutex::new(accounts),
+        }
+    }
+
+    /// None removes the policy. The CCC already spent today is still counted by the new policy.
+    pub fn set(&self, address: Address, policy: Option<SpendPolicy>, now: u64) {
+        let mut accounts = self.accounts.lock();
+        match policy {
+            Some(policy) => {
+                let day = now / SECONDS_PER_DAY;
+                let spent = accounts.get(&address).map_or(0, |spending| spending.spent_on(day));
+                let spending = AccountSpending {
+                    policy,
+                    day,
+                    spent,
+                };
+                self.write(&address, Some(&spending));
+                accounts.insert(address, spending);
+            }
+            None => {
+                self.write(&address, None);
+                accounts.remove(&address);
+            }
+        }
+    }
+
+    pub fn get(&self, address: &Address, now: u64) -> Option<SpendPolicyStatus> {
+        let accounts = self.accounts.lock();
+        let spending = accounts.get(address)?;
+        Some(SpendPolicyStatus {
+            policy: spending.policy.clone(),
+            spent_today: spending.spent_on(now / SECONDS_PER_DAY),
+        })
+    }
+
+    /// Counts the CCC that the transaction spends, unless it violates the policy of the signer.
+    /// Returns the counted CCC, which should be refunded if the transaction is not imported.
+    /// `now` is in seconds since the epoch.
+    pub fn spend(&self, signer: &Address, tx: &Transaction, now: u64) -> Result<u64, SpendPolicyViolation> {
+        let mut accounts = self.accounts.lock();
+        let spending = match accounts.get_mut(signer) {
+            Some(spending) => spending,
+            None => return Ok(0),
+        };
+        let requested = spent_ccc(&spending.policy, tx)?;
+        let day = now / SECONDS_PER_DAY;
+        let spent_today = spending.spent_on(day);
+        if let Some(daily_cap) = spending.policy.daily_cap {
+            if spent_today.saturating_add(requested) > daily_cap {
+                return Err(SpendPolicyViolation::DailyCapExceeded {
+                    daily_cap,
+                    spent_today,
+                    requested,
+                })
+            }
+        }
+        spending.day = day;
+        spending.spent = spent_today + requested;
+        self.write(signer, Some(&*spending));
+        Ok(requested)
+    }
+
+    /// Gives back the CCC counted by `spend` if it's still the same day.
+    pub fn refund(&self, signer: &Address, amount: u64, now: u64) {
+        let mut accounts = self.accounts.lock();
+        if let Some(spending) = accounts.get_mut(signer) {
+            if spending.day == now / SECONDS_PER_DAY {
+                spending.spent = spending.spent.saturating_sub(amount);
+                self.write(signer, Some(&*spending));
+            }
+        }
+    }
+
+    fn write(&self, address: &Address, spending: Option<&AccountSpending>) {
+        let mut key = PREFIX_SPEND_POLICY.to_vec();
+        key.extend_from_slice(address.as_ref());
+        let mut batch = DBTransaction::new();
+        match spending {
+            Some(spending) => batch.put(COL_EXTRA, &key, &spending.rlp_bytes()),
+            None => batch.delete(COL_EXTRA, &key),
+        }
+        if let Err(err) = self.db.write(batch) {
+            cerror!(MINER, "Cannot record the spend policy of {}: {}", address, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ckey::Public;
+    use ctypes::transaction::{AssetOutPoint, AssetTransferInput};
+
+    use super::*;
+    use crate::db::NUM_COLUMNS;
+
+    const DAY: u64 = SECONDS_PER_DAY;
+
+    fn pay(receiver: Address, quantity: u64, fee: u64) -> Transaction {
+        Transaction {
+            seq: 0,
+            fee,
+            network_id: "tc".into(),
+            action: Action::Pay {
+                receiver,
+                quantity,
+            },
+        }
+    }
+
+    fn policies() -> SpendPolicies {
+        SpendPolicies::new(Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap())))
+    }
+
+    #[test]
+    fn daily_cap_is_exhausted_by_the_transactions() {
+        let policies = policies();
+        let signer = Address::random();
+        let receiver = Address::random();
+        let now = 100 * DAY + 10;
+        policies.set(
+            signer,
+            Some(SpendPolicy {
+                daily_cap: Some(1000),
+                allowed_recipients: None,
+            }),
+            now,
+        );
+
+        assert_eq!(Ok(410), policies.spend(&signer, &pay(receiver, 400, 10), now));
+        assert_eq!(Ok(410), policies.spend(&signer, &pay(receiver, 400, 10), now + 1));
+        assert_eq!(
+            Err(SpendPolicyViolation::DailyCapExceeded {
+                daily_cap: 1000,
+                spent_today: 820,
+                requested: 210,
+            }),
+            policies.spend(&signer, &pay(receiver, 200, 10), now + 2)
+        );
+        assert_eq!(Ok(180), policies.spend(&signer, &pay(receiver, 170, 10), now + 3));
+        assert_eq!(1000, policies.get(&signer, now + 4).unwrap().spent_today);
+
+        // The others are not limited.
+        assert_eq!(Ok(0), policies.spend(&receiver, &pay(signer, 5000, 10), now));
+    }
+
+    #[test]
+    fn payment_to_an_unlisted_recipient_is_refused() {
+        let policies = policies();
+        let signer = Address::random();
+        let allowed = Address::random();
+        let other = Address::random();
+        policies.set(
+            signer,
+            Some(SpendPolicy {
+                daily_cap: None,
+                allowed_recipients: Some(vec![allowed]),
+            }),
+            0,
+        );
+
+        assert_eq!(Ok(110), policies.spend(&signer, &pay(allowed, 100, 10), 0));
+        assert_eq!(
+            Err(SpendPolicyViolation::RecipientNotAllowed(other)),
+            policies.spend(&signer, &pay(other, 100, 10), 0)
+        );
+        assert_eq!(110, policies.get(&signer, 0).unwrap().spent_today);
+    }
+
+    #[test]
+    fn actions_that_cannot_be_counted_are_refused() {
+        let policies = policies();
+        let signer = Address::random();
+        let receiver = Address::random();
+        policies.set(
+            signer,
+            Some(SpendPolicy {
+                daily_cap: Some(1000),
+                allowed_recipients: None,
+            }),
+            0,
+        );
+        let with_action = |action| Transaction {
+            seq: 0,
+            fee: 10,
+            network_id: "tc".into(),
+            action,
+        };
+
+        let set_regular_key = with_action(Action::SetRegularKey {
+            key: Public::random(),
+        });
+        assert_eq!(
+            Err(SpendPolicyViolation::ActionNotAllowed("setRegularKey")),
+            policies.spend(&signer, &set_regular_key, 0)
+        );
+        let transfer_asset = with_action(Action::TransferAsset {
+            network_id: "tc".into(),
+            burns: Vec::new(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            orders: Vec::new(),
+            metadata: String::new(),
+            approvals: Vec::new(),
+            expiration: None,
+        });
+        assert_eq!(
+            Err(SpendPolicyViolation::ActionNotAllowed("transferAsset")),
+            policies.spend(&signer, &transfer_asset, 0)
+        );
+        let unwrap_ccc = with_action(Action::UnwrapCCC {
+            network_id: "tc".into(),
+            burn: AssetTransferInput {
+                prev_out: AssetOutPoint {
+                    tracker: Default::default(),
+                    index: 0,
+                    asset_type: Default::default(),
+                    shard_id: 0,
+                    quantity: 5000,
+                },
+                timelock: None,
+                lock_script: Vec::new(),
+                unlock_script: Vec::new(),
+            },
+            receiver,
+        });
+        assert_eq!(Err(SpendPolicyViolation::ActionNotAllowed("unwrapCCC")), policies.spend(&signer, &unwrap_ccc, 0));
+        // The stake transfers and the delegations are custom actions.
+        let custom = with_action(Action::Custom {
+            handler_id: 2,
+            bytes: Vec::new(),
+        });
+        assert_eq!(Err(SpendPolicyViolation::ActionNotAllowed("custom")), policies.spend(&signer, &custom, 0));
+        assert_eq!(0, policies.get(&signer, 0).unwrap().spent_today);
+
+        // The others are not limited.
+        assert_eq!(Ok(0), policies.spend(&receiver, &custom, 0));
+
+        let wrap_ccc = with_action(Action::WrapCCC {
+            shard_id: 0,
+            lock_script_hash: Default::default(),
+            parameters: Vec::new(),
+            quantity: 100,
+            payer: signer,
+        });
+        assert_eq!(Ok(110), policies.spend(&signer, &wrap_ccc, 0));
+    }
+
+    #[test]
+    fn wrapping_ccc_is_refused_if_the_recipients_are_restricted() {
+        let policies = policies();
+        let signer = Address::random();
+        policies.set(
+            signer,
+            Some(SpendPolicy {
+                daily_cap: None,
+                allowed_recipients: Some(vec![signer]),
+            }),
+            0,
+        );
+        let wrap = Transaction {
+            seq: 0,
+            fee: 10,
+            network_id: "tc".into(),
+            action: Action::WrapCCC {
+                shard_id: 0,
+                lock_script_hash: Default::default(),
+                parameters: Vec::new(),
+                quantity: 100,
+                payer: signer,
+            },
+        };
+        assert_eq!(Err(SpendPolicyViolation::ActionNotAllowed("wrapCCC")), policies.spend(&signer, &wrap, 0));
+    }
+
+    #[test]
+    fn spending_is_reset_at_the_utc_day_boundary() {
+        let policies = policies();
+        let signer = Address::random();
+        let receiver = Address::random();
+        policies.set(
+            signer,
+            Some(SpendPolicy {
+                daily_cap: Some(100),
+                allowed_recipients: None,
+            }),
+            0,
+        );
+        let last_second = 5 * DAY - 1;
+        assert_eq!(Ok(100), policies.spend(&signer, &pay(receiver, 90, 10), last_second));
+        assert!(policies.spend(&signer, &pay(receiver, 0, 10), last_second).is_err());
+
+        let next_day = 5 * DAY;
+        assert_eq!(0, policies.get(&signer, next_day).unwrap().spent_today);
+        assert_eq!(Ok(100), policies.spend(&signer, &pay(receiver, 90, 10), next_day));
+        // The refund of the previous day is ignored.
+        policies.refund(&signer, 100, last_second);
+        assert_eq!(100, policies.get(&signer, next_day).unwrap().spent_today);
+        policies.refund(&signer, 100, next_day);
+        assert_eq!(0, policies.get(&signer, next_day).unwrap().spent_today);
+    }
+
+    #[test]
+    fn policies_and_spending_survive_the_restart() {
+        let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let signer = Address::random();
+        let removed = Address::random();
+        let receiver = Address::random();
+        let policy = SpendPolicy {
+            daily_cap: Some(500),
+            allowed_recipients: Some(vec![receiver]),
+        };
+        let policies = SpendPolicies::new(Arc::clone(&db));
+        policies.set(signer, Some(policy.clone()), DAY);
+        policies.set(removed, Some(Default::default()), DAY);
+        policies.set(removed, None, DAY);
+        assert_eq!(Ok(110), policies.spend(&signer, &pay(receiver, 100, 10), DAY));
+
+        let restarted = SpendPolicies::new(db);
+        assert_eq!(
+            Some(SpendPolicyStatus {
+                policy,
+                spent_today: 110,
+            }),
+            restarted.get(&signer, DAY)
+        );
+        assert_eq!(None, restarted.get(&removed, DAY));
+    }
+}
@@ -21,6 +21,8 @@ mod pod_state;
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::module_inception))]
 mod scheme;
 mod seal;
+mod validation;
 
 pub use self::genesis::Genesis;
 pub use self::scheme::Scheme;
+pub use self::validation::SchemeProblem;
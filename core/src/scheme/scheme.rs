@@ -33,6 +33,7 @@ use crate::blockchain::HeaderProvider;
 
 use super::pod_state::{PodAccounts, PodShards};
 use super::seal::Generic as GenericSeal;
+use super::validation::{self, SchemeProblem};
 use super::Genesis;
 use crate::codechain_machine::CodeChainMachine;
 use crate::consensus::{BlakePoW, CodeChainEngine, Cuckoo, NullEngine, SimplePoA, Solo, Tendermint};
@@ -225,7 +226,22 @@ impl Scheme {
     pub fn load<R>(reader: R) -> Result<Self, String>
     where
         R: Read, {
-        cjson::scheme::Scheme::load(reader).map_err(fmt_err).and_then(|x| load_from(x).map_err(fmt_err))
+        let scheme = cjson::scheme::Scheme::load(reader).map_err(fmt_err)?;
+        Self::validate(&scheme).map_err(|problems| {
+            let problems: Vec<_> = problems.iter().map(ToString::to_string).collect();
+            fmt_err(problems.join(", "))
+        })?;
+        load_from(scheme).map_err(fmt_err)
+    }
+
+    /// Checks the scheme before the engine is constructed, and returns all the problems found.
+    pub fn validate(scheme: &cjson::scheme::Scheme) -> Result<(), Vec<SchemeProblem>> {
+        let problems = validation::validate(scheme);
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
     }
 
     /// Create a new test Scheme.
@@ -0,0 +1,412 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use cjson;
+use cjson::scheme::{Engine, Seal};
+use ckey::{public_to_address, NetworkId, PlatformAddress, Public};
+use ctypes::CommonParams;
+
+/// A mistake found in a scheme file before the engine is constructed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemeProblem {
+    /// The network id is not two lowercase alphanumeric characters.
+    InvalidNetworkId(NetworkId),
+    /// An address in the scheme belongs to another network.
+    NetworkIdMismatch {
+        field: String,
+        address: PlatformAddress,
+        expected: NetworkId,
+    },
+    /// The common params are out of the allowed ranges.
+    InvalidCommonParams(String),
+    /// The genesis seal is not the one the engine uses.
+    SealMismatch {
+        engine: &'static str,
+        seal: &'static str,
+    },
+    /// The genesis seal has more precommits than the validators.
+    TooManyPrecommits {
+        precommits: usize,
+        validators: usize,
+    },
+    /// The engine needs at least one validator.
+    NoValidators(&'static str),
+    DuplicatedValidator(Public),
+    /// The term is set, but the engine doesn't elect the validators.
+    TermSecondsWithoutValidators(&'static str),
+    /// The term is set, but the validator doesn't have the genesis stake.
+    ValidatorWithoutStake(Public),
+    ZeroGenesisStake(PlatformAddress),
+    ZeroTimeout(&'static str),
+}
+
+impl fmt::Display for SchemeProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchemeProblem::InvalidNetworkId(network_id) => {
+                write!(f, "The network id({}) should be two lowercase alphanumeric characters", network_id)
+            }
+            SchemeProblem::NetworkIdMismatch {
+                field,
+                address,
+                expected,
+            } => write!(
+                f,
+                "The address {} in {} belongs to the network {}, but the network id is {}",
+                address, field, address.network_id, expected
+            ),
+            SchemeProblem::InvalidCommonParams(reason) => write!(f, "Invalid params: {}", reason),
+            SchemeProblem::SealMismatch {
+                engine,
+                seal,
+            } => write!(f, "The {} engine cannot use the {} seal in the genesis", engine, seal),
+            SchemeProblem::TooManyPrecommits {
+                precommits,
+                validators,
+            } => write!(
+                f,
+                "The genesis seal has {} precommits, but there are only {} validators",
+                precommits, validators
+            ),
+            SchemeProblem::NoValidators(engine) => write!(f, "The {} engine needs at least one validator", engine),
+            SchemeProblem::DuplicatedValidator(public) => {
+                write!(f, "The validator {} is listed more than once", public)
+            }
+            SchemeProblem::TermSecondsWithoutValidators(engine) => {
+                write!(f, "termSeconds is set, but the {} engine doesn't have validators", engine)
+            }
+            SchemeProblem::ValidatorWithoutStake(public) => {
+                write!(f, "termSeconds is set, but the validator {} doesn't have an entry in genesisStakes", public)
+            }
+            SchemeProblem::ZeroGenesisStake(address) => write!(f, "The genesis stake of {} is zero", address),
+            SchemeProblem::ZeroTimeout(name) => write!(f, "{} should be larger than 0", name),
+        }
+    }
+}
+
+/// Checks the cross-field consistency of the scheme and returns all the problems found.
+pub fn validate(scheme: &cjson::scheme::Scheme) -> Vec<SchemeProblem> {
+    let mut problems = Vec::new();
+    let network_id = scheme.params.network_id;
+    if !is_valid_network_id(network_id) {
+        problems.push(SchemeProblem::InvalidNetworkId(network_id));
+    }
+
+    let params = CommonParams::from(scheme.params.clone());
+    if let Err(reason) = params.verify() {
+        problems.push(SchemeProblem::InvalidCommonParams(reason));
+    }
+
+    check_addresses(scheme, network_id, &mut problems);
+
+    let engine_name = engine_name(&scheme.engine);
+    match (&scheme.engine, &scheme.genesis.seal) {
+        (Engine::Tendermint(_), Seal::Tendermint(_)) => {}
+        (Engine::Tendermint(_), Seal::Generic(_)) => problems.push(SchemeProblem::SealMismatch {
+            engine: engine_name,
+            seal: "generic",
+        }),
+        (_, Seal::Tendermint(_)) => problems.push(SchemeProblem::SealMismatch {
+            engine: engine_name,
+            seal: "tendermint",
+        }),
+        (_, Seal::Generic(_)) => {}
+    }
+
+    match &scheme.engine {
+        Engine::Tendermint(tendermint) => {
+            let params = &tendermint.params;
+            check_validators(engine_name, &params.validators, &mut problems);
+            if let Seal::Tendermint(seal) = &scheme.genesis.seal {
+                if seal.precommits.len() > params.validators.len() {
+                    problems.push(SchemeProblem::TooManyPrecommits {
+                        precommits: seal.precommits.len(),
+                        validators: params.validators.len(),
+                    });
+                }
+            }
+            let timeouts = [
+                ("timeoutPropose", params.timeout_propose),
+                ("timeoutPrevote", params.timeout_prevote),
+                ("timeoutPrecommit", params.timeout_precommit),
+                ("timeoutCommit", params.timeout_commit),
+            ];
+            for (name, timeout) in timeouts.iter() {
+                if timeout.map_or(false, |timeout| u64::from(timeout) == 0) {
+                    problems.push(SchemeProblem::ZeroTimeout(*name));
+                }
+            }
+            let empty_stakes = HashMap::new();
+            let genesis_stakes = params.genesis_stakes.as_ref().unwrap_or(&empty_stakes);
+            check_genesis_stakes(genesis_stakes, &mut problems);
+            if params_term_seconds(scheme) != 0 {
+                let stakeholders: HashSet<_> = genesis_stakes.keys().map(PlatformAddress::address).collect();
+                for validator in &params.validators {
+                    if !stakeholders.contains(&public_to_address(validator)) {
+                        problems.push(SchemeProblem::ValidatorWithoutStake(*validator));
+                    }
+                }
+            }
+        }
+        Engine::SimplePoA(simple_poa) => {
+            check_validators(engine_name, &simple_poa.params.validators, &mut problems);
+        }
+        Engine::Solo(solo) => {
+            if let Some(genesis_stakes) = &solo.params.action_handlers.genesis_stakes {
+                check_genesis_stakes(genesis_stakes, &mut problems);
+            }
+        }
+        Engine::Null(_) | Engine::Cuckoo(_) | Engine::BlakePoW(_) => {}
+    }
+
+    if params_term_seconds(scheme) != 0 {
+        match &scheme.engine {
+            Engine::Tendermint(tendermint) if !tendermint.params.validators.is_empty() => {}
+            // The empty validator list is already reported.
+            Engine::Tendermint(_) => {}
+            _ => problems.push(SchemeProblem::TermSecondsWithoutValidators(engine_name)),
+        }
+    }
+
+    problems
+}
+
+fn is_valid_network_id(network_id: NetworkId) -> bool {
+    network_id.to_string().chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+}
+
+fn engine_name(engine: &Engine) -> &'static str {
+    match engine {
+        Engine::Null(_) => "null",
+        Engine::Solo(_) => "solo",
+        Engine::SimplePoA(_) => "simplePoA",
+        Engine::Tendermint(_) => "tendermint",
+        Engine::Cuckoo(_) => "cuckoo",
+        Engine::BlakePoW(_) => "blakePoW",
+    }
+}
+
+fn params_term_seconds(scheme: &cjson::scheme::Scheme) -> u64 {
+    scheme.params.term_seconds.map_or(0, Into::into)
+}
+
+fn check_addresses(scheme: &cjson::scheme::Scheme, network_id: NetworkId, problems: &mut Vec<SchemeProblem>) {
+    let mut check = |field: String, address: &PlatformAddress| {
+        if address.network_id != network_id {
+            problems.push(SchemeProblem::NetworkIdMismatch {
+                field,
+                address: *address,
+                expected: network_id,
+            });
+        }
+    };
+    if let Some(author) = &scheme.genesis.author {
+        check("genesis.author".to_string(), author);
+    }
+    for address in scheme.accounts.keys() {
+        check("accounts".to_string(), address);
+    }
+    for (shard_id, shard) in &scheme.shards {
+        for owner in &shard.owners {
+            check(format!("the owners of the shard {}", shard_id), owner);
+        }
+        for user in shard.users.iter().flatten() {
+            check(format!("the users of the shard {}", shard_id), user);
+        }
+    }
+    let genesis_stakes = match &scheme.engine {
+        Engine::Tendermint(tendermint) => tendermint.params.genesis_stakes.as_ref(),
+        Engine::Solo(solo) => solo.params.action_handlers.genesis_stakes.as_ref(),
+        _ => None,
+    };
+    for address in genesis_stakes.into_iter().flat_map(HashMap::keys) {
+        check("genesisStakes".to_string(), address);
+    }
+}
+
+fn check_validators(engine_name: &'static str, validators: &[Public], problems: &mut Vec<SchemeProblem>) {
+    if validators.is_empty() {
+        problems.push(SchemeProblem::NoValidators(engine_name));
+    }
+    let mut found = HashSet::new();
+    for validator in validators {
+        if !found.insert(validator) {
+            problems.push(SchemeProblem::DuplicatedValidator(*validator));
+        }
+    }
+}
+
+fn check_genesis_stakes(genesis_stakes: &HashMap<PlatformAddress, u64>, problems: &mut Vec<SchemeProblem>) {
+    for (address, amount) in genesis_stakes {
+        if *amount == 0 {
+            problems.push(SchemeProblem::ZeroGenesisStake(*address));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ckey::Address;
+
+    use super::*;
+
+    fn load(bytes: &[u8]) -> cjson::scheme::Scheme {
+        cjson::scheme::Scheme::load(bytes).unwrap()
+    }
+
+    fn tendermint() -> cjson::scheme::Scheme {
+        load(include_bytes!("../../res/tendermint.json"))
+    }
+
+    fn tendermint_params(scheme: &mut cjson::scheme::Scheme) -> &mut cjson::scheme::TendermintParams {
+        match &mut scheme.engine {
+            Engine::Tendermint(tendermint) => &mut tendermint.params,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn bundled_schemes_are_valid() {
+        let schemes: [&[u8]; 11] = [
+            include_bytes!("../../res/beagle.json"),
+            include_bytes!("../../res/blake_pow.json"),
+            include_bytes!("../../res/corgi.json"),
+            include_bytes!("../../res/cuckoo.json"),
+            include_bytes!("../../res/husky.json"),
+            include_bytes!("../../res/mainnet.json"),
+            include_bytes!("../../res/null.json"),
+            include_bytes!("../../res/saluki.json"),
+            include_bytes!("../../res/simple_poa.json"),
+            include_bytes!("../../res/solo.json"),
+            include_bytes!("../../res/tendermint.json"),
+        ];
+        for scheme in schemes.iter() {
+            assert_eq!(Vec::<SchemeProblem>::new(), validate(&load(scheme)));
+        }
+    }
+
+    #[test]
+    fn invalid_network_id() {
+        let mut scheme = load(include_bytes!("../../res/cuckoo.json"));
+        scheme.params.network_id = "T!".into();
+        assert!(validate(&scheme).contains(&SchemeProblem::InvalidNetworkId("T!".into())));
+    }
+
+    #[test]
+    fn genesis_stake_of_another_network() {
+        let mut scheme = tendermint();
+        let address = PlatformAddress::new_v1("sc".into(), Address::default());
+        tendermint_params(&mut scheme).genesis_stakes.get_or_insert_with(HashMap::new).insert(address, 100);
+        assert_eq!(
+            vec![SchemeProblem::NetworkIdMismatch {
+                field: "genesisStakes".to_string(),
+                address,
+                expected: "tc".into(),
+            }],
+            validate(&scheme)
+        );
+    }
+
+    #[test]
+    fn tendermint_with_generic_seal() {
+        let mut scheme = tendermint();
+        scheme.genesis.seal = load(include_bytes!("../../res/null.json")).genesis.seal;
+        assert_eq!(
+            vec![SchemeProblem::SealMismatch {
+                engine: "tendermint",
+                seal: "generic",
+            }],
+            validate(&scheme)
+        );
+    }
+
+    #[test]
+    fn duplicated_validator() {
+        let mut scheme = tendermint();
+        let params = tendermint_params(&mut scheme);
+        let validator = params.validators[0];
+        params.validators.push(validator);
+        assert_eq!(vec![SchemeProblem::DuplicatedValidator(validator)], validate(&scheme));
+    }
+
+    #[test]
+    fn term_seconds_without_validators() {
+        let mut scheme = tendermint();
+        tendermint_params(&mut scheme).validators.clear();
+        scheme.params.term_seconds = Some(3600.into());
+        let problems = validate(&scheme);
+        assert!(problems.contains(&SchemeProblem::NoValidators("tendermint")));
+
+        let mut scheme = load(include_bytes!("../../res/null.json"));
+        scheme.params.term_seconds = Some(3600.into());
+        assert!(validate(&scheme).contains(&SchemeProblem::TermSecondsWithoutValidators("null")));
+    }
+
+    #[test]
+    fn validator_without_stake() {
+        let mut scheme = tendermint();
+        scheme.params.term_seconds = Some(3600.into());
+        let validators = tendermint_params(&mut scheme).validators.clone();
+        let stakeholders: Vec<_> = tendermint_params(&mut scheme)
+            .genesis_stakes
+            .as_ref()
+            .unwrap()
+            .keys()
+            .map(|address| *address.address())
+            .collect();
+        let problems = validate(&scheme);
+        for validator in validators {
+            let has_stake = stakeholders.contains(&public_to_address(&validator));
+            assert_eq!(!has_stake, problems.contains(&SchemeProblem::ValidatorWithoutStake(validator)));
+        }
+    }
+
+    #[test]
+    fn zero_timeout_and_zero_stake() {
+        let mut scheme = tendermint();
+        let params = tendermint_params(&mut scheme);
+        params.timeout_prevote = Some(0.into());
+        let address = *params.genesis_stakes.as_ref().unwrap().keys().next().unwrap();
+        params.genesis_stakes.as_mut().unwrap().insert(address, 0);
+        let problems = validate(&scheme);
+        assert_eq!(2, problems.len());
+        assert!(problems.contains(&SchemeProblem::ZeroTimeout("timeoutPrevote")));
+        assert!(problems.contains(&SchemeProblem::ZeroGenesisStake(address)));
+    }
+
+    #[test]
+    fn all_problems_are_reported() {
+        let mut scheme = tendermint();
+        scheme.params.fee_burn_permille = Some(2000.into());
+        scheme.genesis.seal = load(include_bytes!("../../res/null.json")).genesis.seal;
+        tendermint_params(&mut scheme).validators.clear();
+        let problems = validate(&scheme);
+        assert_eq!(3, problems.len(), "{:?}", problems);
+        assert!(problems.iter().any(|problem| match problem {
+            SchemeProblem::InvalidCommonParams(_) => true,
+            _ => false,
+        }));
+        assert!(problems.contains(&SchemeProblem::SealMismatch {
+            engine: "tendermint",
+            seal: "generic",
+        }));
+        assert!(problems.contains(&SchemeProblem::NoValidators("tendermint")));
+    }
+}
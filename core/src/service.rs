@@ -50,8 +50,11 @@ impl ClientService {
             client: client.clone(),
         });
         io_service.register_handler(client_io)?;
+        client.start_action_index_backfill();
+        client.start_address_history_backfill();
 
         scheme.engine.register_client(Arc::downgrade(&client) as _);
+        client.check_signer_eligibility();
 
         Ok(ClientService {
             _io_service: io_service,
@@ -81,6 +84,15 @@ pub enum ClientIoMessage {
     /// Update the best block by the given hash
     /// Only used in Tendermint
     UpdateBestAsCommitted(H256),
+    /// Run the next chunk of the database integrity check
+    CheckDatabaseIntegrity,
+    /// Index the next chunk of the blocks imported before the action index was enabled
+    BackfillActionIndex,
+    /// Index the next chunk of the blocks imported before the address history was enabled
+    BackfillAddressHistory,
+    /// Execute the proposal before it is imported
+    /// Only used in Tendermint
+    ExecuteProposal(Bytes),
 }
 
 /// IO interface for the Client handler
@@ -109,6 +121,18 @@ impl IoHandler<ClientIoMessage> for ClientIoHandler {
             ClientIoMessage::UpdateBestAsCommitted(block_hash) => {
                 self.client.update_best_as_committed(block_hash);
             }
+            ClientIoMessage::CheckDatabaseIntegrity => {
+                self.client.check_database_integrity();
+            }
+            ClientIoMessage::BackfillActionIndex => {
+                self.client.backfill_action_index();
+            }
+            ClientIoMessage::BackfillAddressHistory => {
+                self.client.backfill_address_history();
+            }
+            ClientIoMessage::ExecuteProposal(block) => {
+                self.client.execute_proposal_in_advance(&block);
+            }
         }
         Ok(())
     }
@@ -14,7 +14,9 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use ckey::{Address, Generator, Random};
 use cstate::StateDB;
+use ctypes::transaction::{Action, Transaction};
 use ctypes::Header;
 use primitives::{Bytes, H256, U256};
 use rlp::{self, RlpStream};
@@ -41,6 +43,21 @@ pub fn create_test_block_with_data(header: &Header, txs: &[SignedTransaction], u
     rlp.out()
 }
 
+/// A payment signed by a random key
+pub fn pay_with_fee(fee: u64) -> SignedTransaction {
+    let keypair = Random.generate().unwrap();
+    let tx = Transaction {
+        seq: 0,
+        fee,
+        network_id: "tc".into(),
+        action: Action::Pay {
+            receiver: Address::random(),
+            quantity: 1,
+        },
+    };
+    SignedTransaction::new_with_sign(tx, keypair.private())
+}
+
 pub fn get_good_dummy_block() -> Bytes {
     let (_, bytes) = get_good_dummy_block_hash();
     bytes
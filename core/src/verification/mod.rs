@@ -17,6 +17,8 @@
 mod canon_verifier;
 mod noop_verifier;
 pub mod queue;
+mod rejection;
+mod seal_checkpoints;
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::module_inception))]
 mod verification;
 mod verifier;
@@ -24,6 +26,8 @@ mod verifier;
 pub use self::canon_verifier::CanonVerifier;
 pub use self::noop_verifier::NoopVerifier;
 pub use self::queue::{BlockQueue, Config as QueueConfig};
+pub use self::rejection::{BlockRejection, Rejection, RejectionCode, RejectionLog, REJECTIONS_WINDOW};
+pub use self::seal_checkpoints::{SealCheckpoints, UnvouchedBlocks};
 pub use self::verification::*;
 pub use self::verifier::Verifier;
 
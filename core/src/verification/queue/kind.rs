@@ -20,8 +20,8 @@ use rlp::*;
 pub use self::blocks::Blocks;
 pub use self::headers::Headers;
 
+use super::super::Rejection;
 use crate::consensus::CodeChainEngine;
-use crate::error::Error;
 use crate::service::ClientIoMessage;
 
 /// Something which can produce a hash and a parent hash.
@@ -70,14 +70,14 @@ pub trait Kind: 'static + Sized + Send + Sync {
     fn name() -> &'static str;
 
     /// Attempt to create the `Unverified` item from the input.
-    fn create(input: Self::Input, engine: &CodeChainEngine) -> Result<Self::Unverified, Error>;
+    fn create(input: Self::Input, engine: &CodeChainEngine) -> Result<Self::Unverified, Rejection>;
 
     /// Attempt to verify the `Unverified` item using the given engine.
     fn verify(
         unverified: Self::Unverified,
         engine: &CodeChainEngine,
         check_seal: bool,
-    ) -> Result<Self::Verified, Error>;
+    ) -> Result<Self::Verified, Rejection>;
 
     fn signal() -> ClientIoMessage;
 }
@@ -88,9 +88,9 @@ pub mod headers {
     use primitives::{H256, U256};
 
     use super::super::super::verification::verify_header_basic;
+    use super::super::super::{Rejection, RejectionCode};
     use super::{BlockLike, Kind};
     use crate::consensus::CodeChainEngine;
-    use crate::error::Error;
     use crate::service::ClientIoMessage;
     use verification::verify_header_with_engine;
 
@@ -120,16 +120,24 @@ pub mod headers {
             "Headers"
         }
 
-        fn create(input: Self::Input, engine: &CodeChainEngine) -> Result<Self::Unverified, Error> {
+        fn create(input: Self::Input, engine: &CodeChainEngine) -> Result<Self::Unverified, Rejection> {
             // FIXME: this doesn't seem to match with full block verification
-            verify_header_basic(&input)?;
-            verify_header_with_engine(&input, engine)?;
-            Ok(input)
+            match verify_header_basic(&input).and_then(|_| verify_header_with_engine(&input, engine)) {
+                Ok(()) => Ok(input),
+                Err(e) => Err(Rejection::new(e, &input, RejectionCode::HeaderBasic)),
+            }
         }
 
-        fn verify(un: Self::Unverified, engine: &CodeChainEngine, check_seal: bool) -> Result<Self::Verified, Error> {
+        fn verify(
+            un: Self::Unverified,
+            engine: &CodeChainEngine,
+            check_seal: bool,
+        ) -> Result<Self::Verified, Rejection> {
             if check_seal {
-                engine.verify_block_seal(&un).map(|_| un)
+                match engine.verify_block_seal(&un) {
+                    Ok(()) => Ok(un),
+                    Err(e) => Err(Rejection::new(e, &un, RejectionCode::SealSignature)),
+                }
             } else {
                 Ok(un)
             }
@@ -147,11 +155,12 @@ pub mod blocks {
     use primitives::{Bytes, H256, U256};
 
     use super::super::super::verification::{
-        verify_block_basic, verify_block_seal, verify_header_with_engine, PreverifiedBlock,
+        verify_block_body_basic, verify_block_seal, verify_header_basic, verify_header_with_engine, PreverifiedBlock,
     };
+    use super::super::super::{Rejection, RejectionCode};
     use super::{BlockLike, Kind, MemUsage};
+    use crate::client::{ImportPhase, ImportTimings, PhaseTimer};
     use crate::consensus::CodeChainEngine;
-    use crate::error::Error;
     use crate::service::ClientIoMessage;
 
     /// A mode for verifying blocks.
@@ -166,25 +175,43 @@ pub mod blocks {
             "Blocks"
         }
 
-        fn create(input: Self::Input, engine: &CodeChainEngine) -> Result<Self::Unverified, Error> {
-            match verify_block_basic(&input.header, &input.bytes)
+        fn create(mut input: Self::Input, engine: &CodeChainEngine) -> Result<Self::Unverified, Rejection> {
+            let mut timer = PhaseTimer::start();
+            let result = verify_header_basic(&input.header)
                 .and_then(|_| verify_header_with_engine(&input.header, engine))
-            {
+                .map_err(|e| Rejection::new(e, &input.header, RejectionCode::HeaderBasic))
+                .and_then(|_| {
+                    timer.lap(ImportPhase::HeaderVerification);
+                    verify_block_body_basic(&input.bytes)
+                        .map_err(|e| Rejection::new(e, &input.header, RejectionCode::Body))
+                });
+            timer.lap(ImportPhase::BodyVerification);
+            input.timings += timer.finish();
+            match result {
                 Ok(()) => Ok(input),
-                Err(e) => {
-                    cwarn!(CLIENT, "Stage 1 block verification failed for {}: {:?}", input.hash(), e);
-                    Err(e)
+                Err(rejection) => {
+                    ctrace!(CLIENT, "Stage 1 block verification failed for {}: {:?}", input.hash(), rejection.error);
+                    Err(rejection)
                 }
             }
         }
 
-        fn verify(un: Self::Unverified, engine: &CodeChainEngine, check_seal: bool) -> Result<Self::Verified, Error> {
-            let hash = un.hash();
+        fn verify(
+            un: Self::Unverified,
+            engine: &CodeChainEngine,
+            check_seal: bool,
+        ) -> Result<Self::Verified, Rejection> {
+            let header = un.header.clone();
+            let timings = un.timings;
             match verify_block_seal(un.header, un.bytes, engine, check_seal) {
-                Ok(verified) => Ok(verified),
+                Ok(mut verified) => {
+                    verified.timings += timings;
+                    Ok(verified)
+                }
                 Err(e) => {
-                    cwarn!(CLIENT, "Stage 2 block verification failed for {}: {:?}", hash, e);
-                    Err(e)
+                    ctrace!(CLIENT, "Stage 2 block verification failed for {}: {:?}", header.hash(), e);
+                    // The errors of the seal tell their category, so the others are from the transactions.
+                    Err(Rejection::new(e, &header, RejectionCode::Body))
                 }
             }
         }
@@ -199,6 +226,7 @@ pub mod blocks {
         header: Header,
         bytes: Bytes,
         mem_usage: usize,
+        timings: ImportTimings,
     }
 
     impl Unverified {
@@ -206,12 +234,15 @@ pub mod blocks {
         pub fn new(bytes: Bytes) -> Self {
             use crate::views::BlockView;
 
+            let mut timer = PhaseTimer::start();
             let header = BlockView::new(&bytes).header();
             let mem_usage = header.mem_usage() + bytes.len();
+            timer.lap(ImportPhase::Decode);
             Unverified {
                 header,
                 bytes,
                 mem_usage,
+                timings: timer.finish(),
             }
         }
     }
@@ -27,6 +27,7 @@ use parking_lot::{Mutex, RwLock};
 use primitives::{H256, U256};
 
 use self::kind::{BlockLike, Kind, MemUsage};
+use super::RejectionLog;
 use crate::consensus::CodeChainEngine;
 use crate::error::{BlockError, Error, ImportError};
 use crate::service::ClientIoMessage;
@@ -124,6 +125,7 @@ impl<K: Kind> VerificationQueue<K> {
         engine: Arc<CodeChainEngine>,
         message_channel: IoChannel<ClientIoMessage>,
         check_seal: bool,
+        rejections: Arc<RejectionLog>,
     ) -> Self {
         let verification = Arc::new(Verification {
             unverified: Mutex::new(VecDeque::new()),
@@ -136,6 +138,7 @@ impl<K: Kind> VerificationQueue<K> {
                 verified: AtomicUsize::new(0),
             },
             check_seal,
+            rejections,
             empty_mutex: SMutex::new(()),
             more_to_verify_mutex: SMutex::new(()),
         });
@@ -269,7 +272,8 @@ impl<K: Kind> VerificationQueue<K> {
                         false
                     }
                 }
-                Err(_) => {
+                Err(rejection) => {
+                    verification.rejections.reject(hash, rejection.number, rejection.code, &rejection.error);
                     let mut verifying = verification.verifying.lock();
                     let mut verified = verification.verified.lock();
                     let mut bad = verification.bad.lock();
@@ -366,15 +370,16 @@ impl<K: Kind> VerificationQueue<K> {
                 self.more_to_verify.notify_all();
                 Ok(h)
             }
-            Err(err) => {
-                match err {
+            Err(rejection) => {
+                match rejection.error {
                     // Don't mark future blocks as bad.
                     Error::Block(BlockError::TemporarilyInvalid(_)) => {}
                     _ => {
+                        self.verification.rejections.reject(h, rejection.number, rejection.code, &rejection.error);
                         self.verification.bad.lock().insert(h);
                     }
                 }
-                Err(err)
+                Err(rejection.error)
             }
         }
     }
@@ -512,6 +517,8 @@ struct Verification<K: Kind> {
     bad: Mutex<HashSet<H256>>,
     sizes: Sizes,
     check_seal: bool,
+    /// The failures of the verification are recorded here.
+    rejections: Arc<RejectionLog>,
     #[allow(dead_code)]
     empty_mutex: SMutex<()>,
     more_to_verify_mutex: SMutex<()>,
@@ -540,7 +547,7 @@ mod tests {
         let engine = scheme.engine;
 
         let config = Config::default();
-        BlockQueue::new(&config, engine, IoChannel::disconnected(), true)
+        BlockQueue::new(&config, engine, IoChannel::disconnected(), true, Default::default())
     }
 
     #[test]
@@ -550,7 +557,7 @@ mod tests {
         let engine = scheme.engine;
 
         let config = Config::default();
-        let _ = BlockQueue::new(&config, engine, IoChannel::disconnected(), true);
+        let _ = BlockQueue::new(&config, engine, IoChannel::disconnected(), true, Default::default());
     }
 
     #[test]
@@ -0,0 +1,344 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use cnetwork::NodeId;
+use ctypes::{BlockNumber, Header};
+use linked_hash_map::LinkedHashMap;
+use parking_lot::Mutex;
+use primitives::H256;
+
+use crate::consensus::EngineError;
+use crate::error::{BlockError, Error};
+
+/// The number of the recent rejections that are kept.
+pub const REJECTIONS_WINDOW: usize = 256;
+/// The number of the blocks of which senders are remembered until the blocks are imported or rejected.
+const MAX_BLOCK_SOURCES: usize = 1024;
+/// The number of the (hash, code) pairs that are remembered not to log the same rejection twice.
+const MAX_LOGGED_REJECTIONS: usize = 4096;
+
+/// The category of the verification failure that rejected a block.
+/// The numbers are exposed to the RPC and the peers, so they must not be changed.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum RejectionCode {
+    /// The header is malformed or inconsistent with its parent.
+    HeaderBasic = 1,
+    /// The seal or the signatures in it are invalid.
+    SealSignature = 2,
+    /// The block is not proposed by the expected proposer.
+    ProposerMismatch = 3,
+    /// The transactions are malformed or don't match the header.
+    Body = 4,
+    /// The transactions cannot be executed.
+    Execution = 5,
+    /// The state root differs from the result of the execution.
+    StateRoot = 6,
+}
+
+impl RejectionCode {
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_code(code: u8) -> Option<Self> {
+        Some(match code {
+            1 => RejectionCode::HeaderBasic,
+            2 => RejectionCode::SealSignature,
+            3 => RejectionCode::ProposerMismatch,
+            4 => RejectionCode::Body,
+            5 => RejectionCode::Execution,
+            6 => RejectionCode::StateRoot,
+            _ => return None,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RejectionCode::HeaderBasic => "header-basic",
+            RejectionCode::SealSignature => "seal-signature",
+            RejectionCode::ProposerMismatch => "proposer-mismatch",
+            RejectionCode::Body => "body",
+            RejectionCode::Execution => "execution",
+            RejectionCode::StateRoot => "state-root",
+        }
+    }
+
+    /// Categorizes the error that rejected the block.
+    /// The errors that don't tell their category belong to `stage`, the category of the failed verification.
+    pub fn classify(error: &Error, header: &Header, stage: RejectionCode) -> Self {
+        match error {
+            Error::Engine(EngineError::NotProposer(_)) => RejectionCode::ProposerMismatch,
+            // The author is checked before the signatures of the seal.
+            Error::Engine(EngineError::BlockNotAuthorized(address)) if address == header.author() => {
+                RejectionCode::ProposerMismatch
+            }
+            Error::Engine(EngineError::BlockNotAuthorized(_))
+            | Error::Engine(EngineError::MessageWithInvalidSignature {
+                ..
+            })
+            | Error::Engine(EngineError::InvalidSealSignature {
+                ..
+            })
+            | Error::Engine(EngineError::BadSealFieldSize(_))
+            | Error::Block(BlockError::InvalidSeal)
+            | Error::Block(BlockError::InvalidSealArity(_))
+            | Error::Block(BlockError::InvalidProofOfWork)
+            | Error::Block(BlockError::PowOutOfBounds(_))
+            | Error::PowHashInvalid
+            | Error::PowInvalid => RejectionCode::SealSignature,
+            Error::Block(BlockError::InvalidTransactionsRoot(_))
+            | Error::Block(BlockError::BodySizeIsTooBig)
+            | Error::Block(BlockError::TooManyTransactions(_))
+            | Error::Syntax(_) => RejectionCode::Body,
+            Error::Runtime(_) | Error::History(_) => RejectionCode::Execution,
+            Error::Block(BlockError::InvalidStateRoot(_)) => RejectionCode::StateRoot,
+            _ => stage,
+        }
+    }
+}
+
+impl fmt::Display for RejectionCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}({})", self.name(), self.code())
+    }
+}
+
+/// The error that rejected a block in the verification queue, with its category.
+#[derive(Debug)]
+pub struct Rejection {
+    pub hash: H256,
+    pub number: BlockNumber,
+    pub code: RejectionCode,
+    pub error: Error,
+}
+
+impl Rejection {
+    pub fn new(error: Error, header: &Header, stage: RejectionCode) -> Self {
+        Self {
+            hash: header.hash(),
+            number: header.number(),
+            code: RejectionCode::classify(&error, header, stage),
+            error,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockRejection {
+    pub hash: H256,
+    pub number: BlockNumber,
+    pub code: RejectionCode,
+    pub reason: String,
+    /// The peer that sent the block. None if the block is not received from a peer.
+    pub peer: Option<NodeId>,
+}
+
+/// Keeps the recent rejections and the senders of the blocks being imported.
+pub struct RejectionLog {
+    records: Mutex<VecDeque<BlockRejection>>,
+    sources: Mutex<LinkedHashMap<H256, NodeId>>,
+    logged: Mutex<LinkedHashMap<(H256, RejectionCode), ()>>,
+}
+
+impl Default for RejectionLog {
+    fn default() -> Self {
+        Self {
+            records: Mutex::new(VecDeque::with_capacity(REJECTIONS_WINDOW)),
+            sources: Mutex::new(LinkedHashMap::new()),
+            logged: Mutex::new(LinkedHashMap::new()),
+        }
+    }
+}
+
+impl RejectionLog {
+    /// Remembers the peer that sent the block, so that the peer is blamed if the block is rejected.
+    pub fn set_source(&self, hash: H256, peer: NodeId) {
+        let mut sources = self.sources.lock();
+        sources.insert(hash, peer);
+        if sources.len() > MAX_BLOCK_SOURCES {
+            sources.pop_front();
+        }
+    }
+
+    /// Forgets the sender of the imported block.
+    pub fn clear_source(&self, hash: &H256) {
+        self.sources.lock().remove(hash);
+    }
+
+    /// Records the rejection against the peer that sent the block.
+    /// The same rejection of a block is logged and recorded only once.
+    /// Returns false if it's already recorded.
+    pub fn reject(&self, hash: H256, number: BlockNumber, code: RejectionCode, error: &Error) -> bool {
+        {
+            let mut logged = self.logged.lock();
+            if logged.contains_key(&(hash, code)) {
+                return false
+            }
+            logged.insert((hash, code), ());
+            if logged.len() > MAX_LOGGED_REJECTIONS {
+                logged.pop_front();
+            }
+        }
+
+        let peer = self.sources.lock().remove(&hash);
+        let reason = error.to_string();
+        match peer {
+            Some(peer) => {
+                cwarn!(CLIENT, "Block #{} ({}) from {} is rejected by {}: {}", number, hash, peer, code, reason)
+            }
+            None => cwarn!(CLIENT, "Block #{} ({}) is rejected by {}: {}", number, hash, code, reason),
+        }
+
+        let mut records = self.records.lock();
+        if records.len() == REJECTIONS_WINDOW {
+            records.pop_front();
+        }
+        records.push_back(BlockRejection {
+            hash,
+            number,
+            code,
+            reason,
+            peer,
+        });
+        true
+    }
+
+    /// Returns at most `limit` rejections from the newest one.
+    pub fn recent(&self, limit: usize) -> Vec<BlockRejection> {
+        self.records.lock().iter().rev().take(limit).cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use ckey::Address;
+    use ctypes::util::unexpected::Mismatch;
+
+    use super::*;
+
+    #[test]
+    fn codes_are_stable() {
+        let codes = [
+            (RejectionCode::HeaderBasic, 1),
+            (RejectionCode::SealSignature, 2),
+            (RejectionCode::ProposerMismatch, 3),
+            (RejectionCode::Body, 4),
+            (RejectionCode::Execution, 5),
+            (RejectionCode::StateRoot, 6),
+        ];
+        for (code, number) in codes.iter() {
+            assert_eq!(*number, code.code());
+            assert_eq!(Some(*code), RejectionCode::from_code(*number));
+        }
+        assert_eq!(None, RejectionCode::from_code(0));
+        assert_eq!(None, RejectionCode::from_code(7));
+    }
+
+    #[test]
+    fn errors_are_classified() {
+        let mut header = Header::default();
+        let author = Address::random();
+        header.set_author(author);
+
+        let mismatch = Mismatch {
+            expected: H256::from(1),
+            found: H256::from(2),
+        };
+        let cases: Vec<(Error, RejectionCode)> = vec![
+            (BlockError::InvalidStateRoot(mismatch).into(), RejectionCode::StateRoot),
+            (BlockError::InvalidTransactionsRoot(mismatch).into(), RejectionCode::Body),
+            (BlockError::InvalidSeal.into(), RejectionCode::SealSignature),
+            (EngineError::BlockNotAuthorized(Address::random()).into(), RejectionCode::SealSignature),
+            (EngineError::BlockNotAuthorized(author).into(), RejectionCode::ProposerMismatch),
+            (
+                EngineError::InvalidSealSignature {
+                    signer_index: 1,
+                    block_hash: H256::from(4),
+                }
+                .into(),
+                RejectionCode::SealSignature,
+            ),
+            (
+                EngineError::NotProposer(Mismatch {
+                    expected: Address::random(),
+                    found: author,
+                })
+                .into(),
+                RejectionCode::ProposerMismatch,
+            ),
+            (BlockError::UnknownParent(H256::from(3)).into(), RejectionCode::Execution),
+        ];
+        for (error, expected) in cases {
+            assert_eq!(expected, RejectionCode::classify(&error, &header, RejectionCode::Execution), "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn the_same_rejection_is_recorded_once() {
+        let log = RejectionLog::default();
+        let hash = H256::from(1);
+        let error = Error::from(BlockError::InvalidSeal);
+        assert!(log.reject(hash, 1, RejectionCode::SealSignature, &error));
+        assert!(!log.reject(hash, 1, RejectionCode::SealSignature, &error));
+        // The other category of the same block is a different rejection.
+        assert!(log.reject(hash, 1, RejectionCode::StateRoot, &error));
+        assert!(log.reject(H256::from(2), 1, RejectionCode::SealSignature, &error));
+
+        let codes: Vec<_> = log.recent(10).into_iter().map(|rejection| (rejection.hash, rejection.code)).collect();
+        assert_eq!(
+            vec![
+                (H256::from(2), RejectionCode::SealSignature),
+                (hash, RejectionCode::StateRoot),
+                (hash, RejectionCode::SealSignature),
+            ],
+            codes
+        );
+    }
+
+    #[test]
+    fn rejection_is_recorded_against_the_sender() {
+        let log = RejectionLog::default();
+        let peer = NodeId::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 3485);
+        log.set_source(H256::from(1), peer);
+        log.set_source(H256::from(2), peer);
+        log.clear_source(&H256::from(2));
+
+        let error = Error::from(BlockError::InvalidSeal);
+        log.reject(H256::from(1), 1, RejectionCode::SealSignature, &error);
+        log.reject(H256::from(2), 1, RejectionCode::SealSignature, &error);
+        let peers: Vec<_> = log.recent(10).into_iter().map(|rejection| rejection.peer).collect();
+        assert_eq!(vec![None, Some(peer)], peers);
+    }
+
+    #[test]
+    fn only_the_recent_rejections_are_kept() {
+        let log = RejectionLog::default();
+        let error = Error::from(BlockError::InvalidSeal);
+        for i in 0..=REJECTIONS_WINDOW {
+            log.reject(H256::from(i as u64), i as u64, RejectionCode::SealSignature, &error);
+        }
+        let recent = log.recent(REJECTIONS_WINDOW + 1);
+        assert_eq!(REJECTIONS_WINDOW, recent.len());
+        assert_eq!(REJECTIONS_WINDOW as u64, recent[0].number);
+        assert_eq!(1, recent[REJECTIONS_WINDOW - 1].number);
+    }
+}
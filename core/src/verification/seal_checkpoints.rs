@@ -0,0 +1,206 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::cmp;
+
+use ctypes::BlockNumber;
+
+/// Decides which seals are verified while importing a contiguous range of blocks.
+///
+/// A seal signs the hash of the parent block, and the parent hash commits to all the ancestors.
+/// Since the blocks are final, verifying the seals of every `interval`-th block and the endpoints of the range
+/// is enough to trust the blocks far below the tip. The blocks near the tip are always fully verified.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SealCheckpoints {
+    interval: u64,
+}
+
+impl SealCheckpoints {
+    /// Every seal is verified if the interval is 0 or 1.
+    pub fn new(interval: u64) -> Self {
+        Self {
+            interval,
+        }
+    }
+
+    /// Returns the number of the block whose seal is expected to vouch for the given block,
+    /// or None if the seal of the given block should be verified.
+    ///
+    /// `first` and `last` are the numbers of the endpoints of the imported range,
+    /// and `best_proposal_number` is the number of the best header known to the node.
+    pub fn checkpoint_of(
+        &self,
+        number: BlockNumber,
+        first: BlockNumber,
+        last: BlockNumber,
+        best_proposal_number: BlockNumber,
+    ) -> Option<BlockNumber> {
+        if self.interval <= 1 {
+            return None
+        }
+        if number == first || number == last || number % self.interval == 0 {
+            return None
+        }
+        if number + self.interval > best_proposal_number {
+            // Near the tip
+            return None
+        }
+        let next_checkpoint = (number / self.interval + 1) * self.interval;
+        Some(cmp::min(next_checkpoint, last))
+    }
+}
+
+/// The blocks whose seals are skipped, held uncommitted until a descendant with a verified seal vouches for them.
+///
+/// A seal signs the parent hash, so a verified seal vouches for all the ancestors.
+/// Nothing is committed before its checkpoint is verified, and nothing needs to be rolled back
+/// when the checkpoint turns out to be invalid.
+pub struct UnvouchedBlocks<T> {
+    blocks: Vec<(BlockNumber, T)>,
+}
+
+impl<T> Default for UnvouchedBlocks<T> {
+    fn default() -> Self {
+        Self {
+            blocks: Vec::new(),
+        }
+    }
+}
+
+impl<T> UnvouchedBlocks<T> {
+    pub fn hold(&mut self, number: BlockNumber, block: T) {
+        self.blocks.push((number, block));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Returns the held ancestors of the block whose seal is verified, from the oldest one.
+    /// They should be committed before the block.
+    pub fn vouch(&mut self, number: BlockNumber) -> Vec<T> {
+        let vouched = self.blocks.iter().take_while(|(held, _)| *held < number).count();
+        self.blocks.drain(..vouched).map(|(_, block)| block).collect()
+    }
+
+    /// Returns the held blocks, which cannot be vouched for because their descendant is invalid.
+    pub fn discard(&mut self) -> Vec<T> {
+        self.blocks.drain(..).map(|(_, block)| block).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sync(checkpoints: &SealCheckpoints, tip: BlockNumber, batch_size: u64) -> Vec<Option<BlockNumber>> {
+        let mut tags = vec![None];
+        let mut first = 1;
+        while first <= tip {
+            let last = cmp::min(first + batch_size - 1, tip);
+            for number in first..=last {
+                tags.push(checkpoints.checkpoint_of(number, first, last, tip));
+            }
+            first = last + 1;
+        }
+        tags
+    }
+
+    #[test]
+    fn verify_checkpoints_endpoints_and_the_tip() {
+        let tags = sync(&SealCheckpoints::new(100), 1000, 128);
+
+        assert_eq!(None, tags[1]);
+        assert_eq!(Some(100), tags[2]);
+        assert_eq!(None, tags[100]);
+        assert_eq!(Some(128), tags[127]);
+        assert_eq!(None, tags[128]);
+        assert_eq!(None, tags[129]);
+        assert_eq!(Some(200), tags[150]);
+        assert_eq!(Some(900), tags[899]);
+        assert_eq!(None, tags[900]);
+        for number in 901..=1000 {
+            assert_eq!(None, tags[number], "#{} is near the tip", number);
+        }
+
+        for (number, tag) in tags.iter().enumerate() {
+            if let Some(checkpoint) = tag {
+                assert!(*checkpoint > number as BlockNumber);
+                assert_eq!(None, tags[*checkpoint as usize], "The checkpoint of #{} is skipped", number);
+            }
+        }
+    }
+
+    #[test]
+    fn verify_every_seal_if_disabled() {
+        assert!(sync(&SealCheckpoints::new(0), 1000, 128).iter().all(Option::is_none));
+        assert!(sync(&SealCheckpoints::new(1), 1000, 128).iter().all(Option::is_none));
+    }
+
+    /// Imports the blocks in batches the way the importer does, and returns the committed blocks.
+    fn import(
+        checkpoints: &SealCheckpoints,
+        tip: BlockNumber,
+        batch_size: u64,
+        bad_seal: BlockNumber,
+    ) -> Vec<BlockNumber> {
+        let mut committed = Vec::new();
+        let mut first = 1;
+        while first <= tip {
+            let last = cmp::min(first + batch_size - 1, tip);
+            let mut unvouched = UnvouchedBlocks::default();
+            for number in first..=last {
+                if checkpoints.checkpoint_of(number, first, last, tip).is_some() {
+                    unvouched.hold(number, number);
+                    continue
+                }
+                if number == bad_seal {
+                    assert!(!unvouched.discard().is_empty());
+                    return committed
+                }
+                committed.extend(unvouched.vouch(number));
+                committed.push(number);
+            }
+            assert!(unvouched.is_empty());
+            first = last + 1;
+        }
+        committed
+    }
+
+    #[test]
+    fn nothing_vouched_by_a_bad_checkpoint_is_committed() {
+        let checkpoints = SealCheckpoints::new(100);
+        assert_eq!((1..=1000).collect::<Vec<_>>(), import(&checkpoints, 1000, 128, 0));
+
+        // The blocks since the last verified seal are not committed.
+        assert_eq!((1..=100).collect::<Vec<_>>(), import(&checkpoints, 1000, 128, 128));
+        assert_eq!((1..=129).collect::<Vec<_>>(), import(&checkpoints, 1000, 128, 200));
+        assert_eq!((1..=897).collect::<Vec<_>>(), import(&checkpoints, 1000, 128, 900));
+    }
+
+    #[test]
+    fn verified_seal_vouches_for_the_held_ancestors_only() {
+        let mut unvouched = UnvouchedBlocks::default();
+        for number in 11..15 {
+            unvouched.hold(number, number);
+        }
+        assert_eq!(vec![11, 12], unvouched.vouch(13));
+        assert!(!unvouched.is_empty());
+        assert_eq!(vec![13, 14], unvouched.discard());
+        assert!(unvouched.is_empty());
+        assert_eq!(Vec::<u64>::new(), unvouched.vouch(20));
+    }
+}
@@ -23,7 +23,7 @@ use primitives::{Bytes, H256};
 use rlp::UntrustedRlp;
 
 use crate::blockchain::BlockProvider;
-use crate::client::BlockChainTrait;
+use crate::client::{BlockChainTrait, ImportPhase, ImportTimings, PhaseTimer};
 use crate::codechain_machine::CodeChainMachine;
 use crate::consensus::CodeChainEngine;
 use crate::error::{BlockError, Error};
@@ -38,12 +38,12 @@ pub struct PreverifiedBlock {
     pub transactions: Vec<SignedTransaction>,
     /// Block bytes
     pub bytes: Bytes,
+    /// The time spent in the verification queue so far
+    pub timings: ImportTimings,
 }
 
-/// Phase 1 quick block verification. Only does checks that are cheap. Operates on a single block
-pub fn verify_block_basic(header: &Header, bytes: &[u8]) -> Result<(), Error> {
-    verify_header_basic(header)?;
-
+/// Phase 1 quick body verification. Only does checks that are cheap. The header is checked by `verify_header_basic`.
+pub fn verify_block_body_basic(bytes: &[u8]) -> Result<(), Error> {
     let body_rlp = UntrustedRlp::new(bytes).at(1)?;
 
     for t in body_rlp.iter().map(|rlp| rlp.as_val::<UnverifiedTransaction>()) {
@@ -73,13 +73,15 @@ pub fn verify_block_with_params(
 ) -> Result<(), Error> {
     verify_header_with_params(&header, common_params)?;
 
-    let body_rlp = UntrustedRlp::new(bytes).at(1).expect("verify_block_basic already checked it");
+    let body_rlp = UntrustedRlp::new(bytes).at(1).expect("verify_block_body_basic already checked it");
     if body_rlp.as_raw().len() > common_params.max_body_size() {
         return Err(BlockError::BodySizeIsTooBig.into())
     }
 
-    for t in body_rlp.iter().map(|rlp| rlp.as_val().expect("verify_block_basic already checked it")) {
+    let mut total_reward = engine.block_reward(header.number());
+    for t in body_rlp.iter().map(|rlp| rlp.as_val().expect("verify_block_body_basic already checked it")) {
         engine.verify_transaction_with_params(&t, common_params)?;
+        total_reward = total_reward.checked_add(t.fee).ok_or(BlockError::TotalFeeOverflow)?;
     }
     Ok(())
 }
@@ -160,22 +162,25 @@ pub fn verify_block_seal(
     engine: &CodeChainEngine,
     check_seal: bool,
 ) -> Result<PreverifiedBlock, Error> {
+    let mut timer = PhaseTimer::start();
     if check_seal {
         engine.verify_block_seal(&header)?;
     }
+    timer.lap(ImportPhase::SealVerification);
+    let unverified_transactions = BlockView::new(&bytes).transactions();
+    timer.lap(ImportPhase::Decode);
     // Verify transactions.
-    let mut transactions = Vec::new();
-    {
-        let v = BlockView::new(&bytes);
-        for t in v.transactions() {
-            let signed = CodeChainMachine::verify_transaction_seal(t, &header)?;
-            transactions.push(signed);
-        }
+    let mut transactions = Vec::with_capacity(unverified_transactions.len());
+    for t in unverified_transactions {
+        let signed = CodeChainMachine::verify_transaction_seal(t, &header)?;
+        transactions.push(signed);
     }
+    timer.lap(ImportPhase::BodyVerification);
     Ok(PreverifiedBlock {
         header,
         transactions,
         bytes,
+        timings: timer.finish(),
     })
 }
 
@@ -266,3 +271,55 @@ pub fn verify_block_final(expected: &Header, got: &Header) -> Result<(), Error>
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheme::Scheme;
+    use crate::tests::helpers::{create_test_block_with_data, pay_with_fee};
+
+    fn verify_fees(fees: &[u64]) -> Result<(), Error> {
+        let scheme = Scheme::new_test_solo();
+        let mut header = Header::default();
+        header.set_number(1);
+        let transactions: Vec<_> = fees.iter().map(|fee| pay_with_fee(*fee)).collect();
+        let bytes = create_test_block_with_data(&header, &transactions, &[]);
+        verify_block_with_params(&header, &bytes, &*scheme.engine, &scheme.genesis_params())
+    }
+
+    #[test]
+    fn block_whose_fees_fit_in_u64() {
+        let fee = u64::max_value() / 4;
+        assert!(verify_fees(&[fee, fee, fee]).is_ok());
+    }
+
+    #[test]
+    fn block_whose_fees_overflow_is_rejected() {
+        let fee = u64::max_value() / 2 + 1;
+        match verify_fees(&[fee, fee]) {
+            Err(Error::Block(BlockError::TotalFeeOverflow)) => {}
+            result => panic!("The block must be rejected: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn body_size_limit_follows_the_given_params() {
+        let scheme = Scheme::new_test_solo();
+        let mut header = Header::default();
+        header.set_number(1);
+        let transactions: Vec<_> = (0..3).map(|_| pay_with_fee(10)).collect();
+        let bytes = create_test_block_with_data(&header, &transactions, &[]);
+        let body_size = UntrustedRlp::new(&bytes).at(1).unwrap().as_raw().len();
+
+        // The params that the parent block has, e.g. after a ChangeParams transaction.
+        let mut params = scheme.genesis_params();
+        params.set_max_body_size_for_test(body_size);
+        assert!(verify_block_with_params(&header, &bytes, &*scheme.engine, &params).is_ok());
+
+        params.set_max_body_size_for_test(body_size - 1);
+        match verify_block_with_params(&header, &bytes, &*scheme.engine, &params) {
+            Err(Error::Block(BlockError::BodySizeIsTooBig)) => {}
+            result => panic!("The block must be rejected: {:?}", result),
+        }
+    }
+}
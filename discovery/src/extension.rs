@@ -27,6 +27,7 @@ use rlp::{Decodable, Encodable, UntrustedRlp};
 
 use super::message::Message;
 use super::node_id::{address_to_hash, KademliaId};
+use super::store::PeerStore;
 use super::Config;
 
 pub struct Extension {
@@ -34,11 +35,20 @@ pub struct Extension {
     routing_table: Arc<RoutingTable>,
     api: Box<Api>,
     nodes: HashSet<NodeId>, // FIXME: Find the optimized data structure for it
+    /// The nodes that haven't answered the last request yet.
+    pending: HashSet<NodeId>,
     use_kademlia: bool,
+    store: Arc<PeerStore>,
 }
 
 impl Extension {
-    pub fn new(routing_table: Arc<RoutingTable>, config: Config, api: Box<Api>, use_kademlia: bool) -> Self {
+    pub fn new(
+        routing_table: Arc<RoutingTable>,
+        config: Config,
+        api: Box<Api>,
+        use_kademlia: bool,
+        store: Arc<PeerStore>,
+    ) -> Self {
         if use_kademlia {
             cinfo!(DISCOVERY, "Discovery starts with kademlia option");
         } else {
@@ -46,17 +56,59 @@ impl Extension {
         }
         api.set_timer(REFRESH_TOKEN, Duration::from_millis(u64::from(config.t_refresh)))
             .expect("Refresh must be registered");
+        api.set_timer(PERSIST_TOKEN, PERSIST_INTERVAL).expect("Persist must be registered");
+
+        let peers = store.load();
+        cinfo!(DISCOVERY, "{} discovered peers are restored", peers.len());
+        routing_table.restore_discovered_peers(peers, now());
         Self {
             config,
             routing_table,
             api,
             nodes: Default::default(),
+            pending: Default::default(),
             use_kademlia,
+            store,
+        }
+    }
+
+    fn refresh(&mut self) {
+        for node in self.pending.drain() {
+            let address = node.into_addr();
+            if self.routing_table.mark_discovered_peer_failed(&address) {
+                cdebug!(DISCOVERY, "{} decayed out because it didn't answer", address);
+            }
+        }
+        let connected: HashSet<_> = self.nodes.iter().map(IntoSocketAddr::into_addr).collect();
+        for address in self.routing_table.stale_discovered_peers(now()) {
+            if connected.contains(&address) {
+                continue
+            }
+            if self.routing_table.mark_discovered_peer_failed(&address) {
+                cdebug!(DISCOVERY, "{} decayed out because it's stale", address);
+            }
         }
+
+        let request = Arc::new(Message::Request(self.config.bucket_size).rlp_bytes().into_vec());
+        for node in &self.nodes {
+            self.api.send(node, Arc::clone(&request));
+        }
+        self.pending = self.nodes.clone();
+    }
+
+    fn persist(&self) {
+        self.store.save(&self.routing_table.discovered_peers());
     }
 }
 
 const REFRESH_TOKEN: TimerToken = 0;
+const PERSIST_TOKEN: TimerToken = 1;
+
+const PERSIST_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+fn now() -> u64 {
+    time::get_time().sec as u64
+}
 
 impl NetworkExtension<Never> for Extension {
     fn name() -> &'static str {
@@ -74,6 +126,7 @@ impl NetworkExtension<Never> for Extension {
 
     fn on_node_added(&mut self, node: &NodeId, _version: u64) {
         self.nodes.insert(*node);
+        self.routing_table.mark_discovered_peer_seen(node.into_addr(), now());
         self.api.send(&node, Arc::new(Message::Request(self.config.bucket_size).rlp_bytes().into_vec()));
     }
 
@@ -116,19 +169,18 @@ impl NetworkExtension<Never> for Extension {
                 self.api.send(&node, response);
             }
             Message::Response(addresses) => {
-                self.routing_table.touch_addresses(addresses);
+                self.pending.remove(node);
+                self.routing_table.mark_discovered_peer_seen(node.into_addr(), now());
+                self.routing_table.touch_addresses(addresses.iter().cloned());
+                self.routing_table.learn_discovered_peers(addresses);
             }
         }
     }
 
     fn on_timeout(&mut self, timer: TimerToken) {
         match timer {
-            REFRESH_TOKEN => {
-                let request = Arc::new(Message::Request(self.config.bucket_size).rlp_bytes().into_vec());
-                for node in &self.nodes {
-                    self.api.send(node, Arc::clone(&request));
-                }
-            }
+            REFRESH_TOKEN => self.refresh(),
+            PERSIST_TOKEN => self.persist(),
             _ => unreachable!(),
         }
     }
@@ -16,6 +16,7 @@
 
 #![allow(deprecated)]
 
+extern crate kvdb;
 #[macro_use]
 extern crate log;
 extern crate never_type;
@@ -32,13 +33,18 @@ extern crate codechain_logger as clogger;
 extern crate codechain_network as cnetwork;
 extern crate codechain_timer as ctimer;
 
+#[cfg(test)]
+extern crate kvdb_memorydb;
+
 mod config;
 mod extension;
 mod message;
 mod node_id;
+mod store;
 
 const K: u8 = 16;
 const T_REFRESH: u32 = 60_000;
 
 pub use crate::config::Config;
 pub use crate::extension::Extension as Discovery;
+pub use crate::store::PeerStore;
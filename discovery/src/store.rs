@@ -0,0 +1,96 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use cnetwork::DiscoveredPeer;
+use kvdb::{DBTransaction, KeyValueDB};
+use rlp::UntrustedRlp;
+
+const DISCOVERED_PEERS_KEY: &[u8] = b"discovered-peers";
+
+/// Saves the discovered peers to the database, so the node doesn't depend only on the bootstrap nodes after
+/// a restart.
+pub struct PeerStore {
+    db: Arc<KeyValueDB>,
+    column: Option<u32>,
+}
+
+impl PeerStore {
+    pub fn new(db: Arc<KeyValueDB>, column: Option<u32>) -> Self {
+        Self {
+            db,
+            column,
+        }
+    }
+
+    pub fn load(&self) -> Vec<DiscoveredPeer> {
+        let bytes = match self.db.get(self.column, DISCOVERED_PEERS_KEY) {
+            Ok(Some(bytes)) => bytes,
+            Ok(None) => return Vec::new(),
+            Err(err) => {
+                cwarn!(DISCOVERY, "Cannot read the discovered peers: {}", err);
+                return Vec::new()
+            }
+        };
+        UntrustedRlp::new(&bytes).as_list().unwrap_or_else(|err| {
+            cwarn!(DISCOVERY, "Cannot decode the discovered peers: {:?}", err);
+            Vec::new()
+        })
+    }
+
+    pub fn save(&self, peers: &[DiscoveredPeer]) {
+        let mut batch = DBTransaction::new();
+        batch.put(self.column, DISCOVERED_PEERS_KEY, &rlp::encode_list(peers));
+        if let Err(err) = self.db.write(batch) {
+            cwarn!(DISCOVERY, "Cannot save the discovered peers: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cnetwork::{RoutingTable, SocketAddr};
+
+    use super::*;
+
+    #[test]
+    fn discovered_peers_survive_restart() {
+        let db: Arc<KeyValueDB> = Arc::new(kvdb_memorydb::create(1));
+        let store = PeerStore::new(Arc::clone(&db), Some(0));
+        assert_eq!(Vec::<DiscoveredPeer>::new(), store.load());
+
+        let routing_table = RoutingTable::new();
+        let seen = SocketAddr::v4(127, 0, 0, 1, 3485);
+        let learned = SocketAddr::v4(127, 0, 0, 1, 3486);
+        routing_table.mark_discovered_peer_seen(seen, 1_565_000_000);
+        routing_table.learn_discovered_peers(vec![learned]);
+        store.save(&routing_table.discovered_peers());
+
+        let restarted_store = PeerStore::new(db, Some(0));
+        let peers = restarted_store.load();
+        assert_eq!(routing_table.discovered_peers(), peers);
+
+        let restarted_table = RoutingTable::new();
+        restarted_table.restore_discovered_peers(peers, 1_565_000_060);
+        assert_eq!(2, restarted_table.discovered_peers().len());
+        assert_eq!(vec![seen, learned], {
+            let mut candidates = restarted_table.candidates();
+            candidates.sort_unstable();
+            candidates
+        });
+    }
+}
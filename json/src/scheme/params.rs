@@ -66,6 +66,24 @@ pub struct Params {
     pub delegation_threshold: Option<Uint>,
     pub min_deposit: Option<Uint>,
     pub max_candidate_metadata_size: Option<Uint>,
+
+    /// The permille of the minimum transaction costs that is burnt instead of distributed.
+    pub fee_burn_permille: Option<Uint>,
+
+    /// The format of the precommits in the Tendermint seal.
+    pub seal_version: Option<Uint>,
+
+    /// Whether the CCS can be transferred.
+    pub ccs_transfers_enabled: Option<bool>,
+
+    /// Whether the fees are distributed by the stakes at the parent block.
+    pub distribute_fees_by_parent_stakes: Option<bool>,
+
+    /// The minimum quantity that a candidate delegates to itself to be elected.
+    pub min_self_nomination_quantity: Option<Uint>,
+
+    /// Whether the self-nominations can have the nomination sequences.
+    pub nomination_seq_enabled: Option<bool>,
 }
 
 #[cfg(test)]
@@ -136,6 +154,12 @@ mod tests {
         assert_eq!(deserialized.delegation_threshold, None);
         assert_eq!(deserialized.min_deposit, None);
         assert_eq!(deserialized.max_candidate_metadata_size, None);
+        assert_eq!(deserialized.fee_burn_permille, None);
+        assert_eq!(deserialized.seal_version, None);
+        assert_eq!(deserialized.ccs_transfers_enabled, None);
+        assert_eq!(deserialized.distribute_fees_by_parent_stakes, None);
+        assert_eq!(deserialized.min_self_nomination_quantity, None);
+        assert_eq!(deserialized.nomination_seq_enabled, None);
     }
 
 
@@ -202,6 +226,12 @@ mod tests {
         assert_eq!(deserialized.delegation_threshold, None);
         assert_eq!(deserialized.min_deposit, None);
         assert_eq!(deserialized.max_candidate_metadata_size, None);
+        assert_eq!(deserialized.fee_burn_permille, None);
+        assert_eq!(deserialized.seal_version, None);
+        assert_eq!(deserialized.ccs_transfers_enabled, None);
+        assert_eq!(deserialized.distribute_fees_by_parent_stakes, None);
+        assert_eq!(deserialized.min_self_nomination_quantity, None);
+        assert_eq!(deserialized.nomination_seq_enabled, None);
     }
 
 
@@ -276,5 +306,233 @@ mod tests {
         assert_eq!(deserialized.delegation_threshold, Some(31.into()));
         assert_eq!(deserialized.min_deposit, Some(32.into()));
         assert_eq!(deserialized.max_candidate_metadata_size, Some(33.into()));
+        assert_eq!(deserialized.fee_burn_permille, None);
+        assert_eq!(deserialized.seal_version, None);
+        assert_eq!(deserialized.ccs_transfers_enabled, None);
+        assert_eq!(deserialized.distribute_fees_by_parent_stakes, None);
+        assert_eq!(deserialized.min_self_nomination_quantity, None);
+        assert_eq!(deserialized.nomination_seq_enabled, None);
+    }
+
+    #[test]
+    fn params_deserialization_with_fee_burn_permille() {
+        let s = r#"{
+            "maxExtraDataSize": "0x20",
+            "maxAssetSchemeMetadataSize": "0x0400",
+            "maxTransferMetadataSize": "0x0100",
+            "maxTextContentSize": "0x0200",
+            "networkID" : "tc",
+            "minPayCost" : 10,
+            "minSetRegularKeyCost" : 11,
+            "minCreateShardCost" : 12,
+            "minSetShardOwnersCost" : 13,
+            "minSetShardUsersCost" : 14,
+            "minWrapCccCost" : 15,
+            "minCustomCost" : 16,
+            "minStoreCost" : 17,
+            "minRemoveCost" : 18,
+            "minMintAssetCost" : 19,
+            "minTransferAssetCost" : 20,
+            "minChangeAssetSchemeCost" : 21,
+            "minComposeAssetCost" : 22,
+            "minDecomposeAssetCost" : 23,
+            "minUnwrapCccCost" : 24,
+            "minIncreaseAssetSupplyCost": 25,
+            "maxBodySize" : 4194304,
+            "snapshotPeriod": 16384,
+            "feeBurnPermille": 250
+        }"#;
+
+        let deserialized: Params = serde_json::from_str(s).unwrap();
+        assert_eq!(deserialized.term_seconds, None);
+        assert_eq!(deserialized.fee_burn_permille, Some(250.into()));
+    }
+
+    #[test]
+    fn params_deserialization_with_seal_version() {
+        let s = r#"{
+            "maxExtraDataSize": "0x20",
+            "maxAssetSchemeMetadataSize": "0x0400",
+            "maxTransferMetadataSize": "0x0100",
+            "maxTextContentSize": "0x0200",
+            "networkID" : "tc",
+            "minPayCost" : 10,
+            "minSetRegularKeyCost" : 11,
+            "minCreateShardCost" : 12,
+            "minSetShardOwnersCost" : 13,
+            "minSetShardUsersCost" : 14,
+            "minWrapCccCost" : 15,
+            "minCustomCost" : 16,
+            "minStoreCost" : 17,
+            "minRemoveCost" : 18,
+            "minMintAssetCost" : 19,
+            "minTransferAssetCost" : 20,
+            "minChangeAssetSchemeCost" : 21,
+            "minComposeAssetCost" : 22,
+            "minDecomposeAssetCost" : 23,
+            "minUnwrapCccCost" : 24,
+            "minIncreaseAssetSupplyCost": 25,
+            "maxBodySize" : 4194304,
+            "snapshotPeriod": 16384,
+            "feeBurnPermille": 250,
+            "sealVersion": 1
+        }"#;
+
+        let deserialized: Params = serde_json::from_str(s).unwrap();
+        assert_eq!(deserialized.term_seconds, None);
+        assert_eq!(deserialized.fee_burn_permille, Some(250.into()));
+        assert_eq!(deserialized.seal_version, Some(1.into()));
+    }
+
+    #[test]
+    fn params_deserialization_with_ccs_transfers_enabled() {
+        let s = r#"{
+            "maxExtraDataSize": "0x20",
+            "maxAssetSchemeMetadataSize": "0x0400",
+            "maxTransferMetadataSize": "0x0100",
+            "maxTextContentSize": "0x0200",
+            "networkID" : "tc",
+            "minPayCost" : 10,
+            "minSetRegularKeyCost" : 11,
+            "minCreateShardCost" : 12,
+            "minSetShardOwnersCost" : 13,
+            "minSetShardUsersCost" : 14,
+            "minWrapCccCost" : 15,
+            "minCustomCost" : 16,
+            "minStoreCost" : 17,
+            "minRemoveCost" : 18,
+            "minMintAssetCost" : 19,
+            "minTransferAssetCost" : 20,
+            "minChangeAssetSchemeCost" : 21,
+            "minComposeAssetCost" : 22,
+            "minDecomposeAssetCost" : 23,
+            "minUnwrapCccCost" : 24,
+            "minIncreaseAssetSupplyCost": 25,
+            "maxBodySize" : 4194304,
+            "snapshotPeriod": 16384,
+            "feeBurnPermille": 250,
+            "sealVersion": 1,
+            "ccsTransfersEnabled": false
+        }"#;
+
+        let deserialized: Params = serde_json::from_str(s).unwrap();
+        assert_eq!(deserialized.term_seconds, None);
+        assert_eq!(deserialized.fee_burn_permille, Some(250.into()));
+        assert_eq!(deserialized.seal_version, Some(1.into()));
+        assert_eq!(deserialized.ccs_transfers_enabled, Some(false));
+    }
+
+    #[test]
+    fn params_deserialization_with_distribute_fees_by_parent_stakes() {
+        let s = r#"{
+            "maxExtraDataSize": "0x20",
+            "maxAssetSchemeMetadataSize": "0x0400",
+            "maxTransferMetadataSize": "0x0100",
+            "maxTextContentSize": "0x0200",
+            "networkID" : "tc",
+            "minPayCost" : 10,
+            "minSetRegularKeyCost" : 11,
+            "minCreateShardCost" : 12,
+            "minSetShardOwnersCost" : 13,
+            "minSetShardUsersCost" : 14,
+            "minWrapCccCost" : 15,
+            "minCustomCost" : 16,
+            "minStoreCost" : 17,
+            "minRemoveCost" : 18,
+            "minMintAssetCost" : 19,
+            "minTransferAssetCost" : 20,
+            "minChangeAssetSchemeCost" : 21,
+            "minComposeAssetCost" : 22,
+            "minDecomposeAssetCost" : 23,
+            "minUnwrapCccCost" : 24,
+            "minIncreaseAssetSupplyCost": 25,
+            "maxBodySize" : 4194304,
+            "snapshotPeriod": 16384,
+            "feeBurnPermille": 250,
+            "sealVersion": 1,
+            "ccsTransfersEnabled": true,
+            "distributeFeesByParentStakes": true
+        }"#;
+
+        let deserialized: Params = serde_json::from_str(s).unwrap();
+        assert_eq!(deserialized.ccs_transfers_enabled, Some(true));
+        assert_eq!(deserialized.distribute_fees_by_parent_stakes, Some(true));
+    }
+
+    #[test]
+    fn params_deserialization_with_min_self_nomination_quantity() {
+        let s = r#"{
+            "maxExtraDataSize": "0x20",
+            "maxAssetSchemeMetadataSize": "0x0400",
+            "maxTransferMetadataSize": "0x0100",
+            "maxTextContentSize": "0x0200",
+            "networkID" : "tc",
+            "minPayCost" : 10,
+            "minSetRegularKeyCost" : 11,
+            "minCreateShardCost" : 12,
+            "minSetShardOwnersCost" : 13,
+            "minSetShardUsersCost" : 14,
+            "minWrapCccCost" : 15,
+            "minCustomCost" : 16,
+            "minStoreCost" : 17,
+            "minRemoveCost" : 18,
+            "minMintAssetCost" : 19,
+            "minTransferAssetCost" : 20,
+            "minChangeAssetSchemeCost" : 21,
+            "minComposeAssetCost" : 22,
+            "minDecomposeAssetCost" : 23,
+            "minUnwrapCccCost" : 24,
+            "minIncreaseAssetSupplyCost": 25,
+            "maxBodySize" : 4194304,
+            "snapshotPeriod": 16384,
+            "feeBurnPermille": 250,
+            "sealVersion": 1,
+            "ccsTransfersEnabled": true,
+            "distributeFeesByParentStakes": true,
+            "minSelfNominationQuantity": 100
+        }"#;
+
+        let deserialized: Params = serde_json::from_str(s).unwrap();
+        assert_eq!(deserialized.distribute_fees_by_parent_stakes, Some(true));
+        assert_eq!(deserialized.min_self_nomination_quantity, Some(100.into()));
+    }
+
+    #[test]
+    fn params_deserialization_with_nomination_seq_enabled() {
+        let s = r#"{
+            "maxExtraDataSize": "0x20",
+            "maxAssetSchemeMetadataSize": "0x0400",
+            "maxTransferMetadataSize": "0x0100",
+            "maxTextContentSize": "0x0200",
+            "networkID" : "tc",
+            "minPayCost" : 10,
+            "minSetRegularKeyCost" : 11,
+            "minCreateShardCost" : 12,
+            "minSetShardOwnersCost" : 13,
+            "minSetShardUsersCost" : 14,
+            "minWrapCccCost" : 15,
+            "minCustomCost" : 16,
+            "minStoreCost" : 17,
+            "minRemoveCost" : 18,
+            "minMintAssetCost" : 19,
+            "minTransferAssetCost" : 20,
+            "minChangeAssetSchemeCost" : 21,
+            "minComposeAssetCost" : 22,
+            "minDecomposeAssetCost" : 23,
+            "minUnwrapCccCost" : 24,
+            "minIncreaseAssetSupplyCost": 25,
+            "maxBodySize" : 4194304,
+            "snapshotPeriod": 16384,
+            "feeBurnPermille": 250,
+            "sealVersion": 1,
+            "ccsTransfersEnabled": true,
+            "distributeFeesByParentStakes": true,
+            "minSelfNominationQuantity": 100,
+            "nominationSeqEnabled": true
+        }"#;
+
+        let deserialized: Params = serde_json::from_str(s).unwrap();
+        assert_eq!(deserialized.min_self_nomination_quantity, Some(100.into()));
+        assert_eq!(deserialized.nomination_seq_enabled, Some(true));
     }
 }
@@ -26,6 +26,8 @@ use crate::uint::Uint;
 pub struct SoloParams {
     /// Block reward.
     pub block_reward: Option<Uint>,
+    /// The authors that take turns by the block number.
+    pub author_rotation: Option<Vec<PlatformAddress>>,
     #[serde(flatten)]
     pub action_handlers: SoloActionHandlersParams,
 }
@@ -61,7 +63,29 @@ mod tests {
 
         let deserialized: Solo = serde_json::from_str(s).unwrap();
         assert_eq!(deserialized.params.block_reward, Some(0x0d.into()));
+        assert_eq!(deserialized.params.author_rotation, None);
         assert_eq!(deserialized.params.action_handlers.hit, Some(Default::default()));
         assert_eq!(deserialized.params.action_handlers.genesis_stakes, Some(Default::default()));
     }
+
+    #[test]
+    fn author_rotation_deserialization() {
+        let s = r#"{
+            "params": {
+                "authorRotation": [
+                    "tccq8qlwpt7xcs9lec3c8tyt3kqxlgsus8q4qp3m6ft",
+                    "tccq8fj6lxn9tchqdqqe93yaga6fzxh5rndzu8k2gdw"
+                ]
+            }
+        }"#;
+
+        let deserialized: Solo = serde_json::from_str(s).unwrap();
+        assert_eq!(
+            deserialized.params.author_rotation,
+            Some(vec![
+                "tccq8qlwpt7xcs9lec3c8tyt3kqxlgsus8q4qp3m6ft".parse().unwrap(),
+                "tccq8fj6lxn9tchqdqqe93yaga6fzxh5rndzu8k2gdw".parse().unwrap(),
+            ])
+        );
+    }
 }
@@ -40,6 +40,14 @@ pub struct TendermintParams {
     pub timeout_precommit_delta: Option<Uint>,
     /// Commit step timeout in milliseconds.
     pub timeout_commit: Option<Uint>,
+    /// How long to wait once more in milliseconds, when the propose step times out though a peer has the proposal.
+    pub timeout_propose_grace: Option<Uint>,
+    /// How long not to sign in milliseconds, when the node restarts with a corrupted consensus backup.
+    pub restore_observation_window: Option<Uint>,
+    /// The view at a height above which the consensus is regarded as degraded.
+    pub escalation_view: Option<Uint>,
+    /// How far the timestamp of a proposal can go beyond its parent's in milliseconds.
+    pub max_timestamp_step: Option<Uint>,
     /// Reward per block.
     pub block_reward: Option<Uint>,
     /// How much tokens are distributed at Genesis?
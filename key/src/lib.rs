@@ -58,7 +58,8 @@ pub use crate::platform_address::PlatformAddress;
 pub use crate::private::Private;
 pub use crate::random::Random;
 pub use crate::schnorr::{
-    recover_schnorr, sign_schnorr, verify_schnorr, verify_schnorr_address, SchnorrSignature, SCHNORR_SIGNATURE_LENGTH,
+    aggregate_schnorr, recover_schnorr, sign_schnorr, verify_schnorr, verify_schnorr_address, verify_schnorr_aggregate,
+    verify_schnorr_batch, SchnorrAggregate, SchnorrSignature, SCHNORR_SIGNATURE_LENGTH,
 };
 use primitives::{H256, H512};
 pub use rustc_serialize::hex;
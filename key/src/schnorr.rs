@@ -20,7 +20,9 @@ use std::hash::{Hash, Hasher};
 use std::ops::{Deref, DerefMut};
 use std::str::FromStr;
 
-use primitives::H512;
+use crypto::sha256;
+use primitives::{H256, H512};
+use rand::thread_rng;
 use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
 use rustc_hex::{FromHex, ToHex};
 use secp256k1::{key, schnorr, Error as SecpError, Message as SecpMessage};
@@ -207,6 +209,252 @@ pub fn verify_schnorr(public: &Public, signature: &SchnorrSignature, message: &M
     }
 }
 
+/// Verifies the signatures at once, and returns the index of the first invalid signature if there is any.
+///
+/// It checks a random linear combination of the signatures instead of checking them one by one.
+/// The signatures are verified one by one only when the combination doesn't match, to find the invalid one.
+pub fn verify_schnorr_batch(items: &[(Public, SchnorrSignature, Message)]) -> Result<Option<usize>, Error> {
+    if let Ok(true) = verify_schnorr_combination(items) {
+        return Ok(None)
+    }
+    for (index, (public, signature, message)) in items.iter().enumerate() {
+        if !verify_schnorr(public, signature, message)? {
+            return Ok(Some(index))
+        }
+    }
+    Ok(None)
+}
+
+/// A signature (r, s) of the message m is valid if R = h * P + s * G where R is the point of which x is r and y is even,
+/// and h = sha256(r || m). It checks sum(a * R) = sum(a * h * P) + sum(a * s) * G with the random a of each signature.
+fn verify_schnorr_combination(items: &[(Public, SchnorrSignature, Message)]) -> Result<bool, Error> {
+    let context = &SECP256K1;
+    let mut rng = thread_rng();
+    let mut left: Option<key::PublicKey> = None;
+    let mut right: Option<key::PublicKey> = None;
+    let mut s_sum: Option<key::SecretKey> = None;
+    for (public, signature, message) in items {
+        let a = key::SecretKey::new(context, &mut rng);
+
+        let mut r_point = {
+            let mut data = [2u8; 33];
+            data[1..33].copy_from_slice(&signature.0[0..32]);
+            key::PublicKey::from_slice(context, &data)?
+        };
+        r_point.mul_assign(context, &a)?;
+
+        let mut h = {
+            let mut data = [0u8; 64];
+            data[0..32].copy_from_slice(&signature.0[0..32]);
+            data[32..64].copy_from_slice(&message[..]);
+            key::SecretKey::from_slice(context, &sha256(&data[..]))?
+        };
+        h.mul_assign(context, &a)?;
+        let mut p_point = {
+            let mut data = [4u8; 65];
+            data[1..65].copy_from_slice(&**public);
+            key::PublicKey::from_slice(context, &data)?
+        };
+        p_point.mul_assign(context, &h)?;
+
+        let mut s = key::SecretKey::from_slice(context, &signature.0[32..64])?;
+        s.mul_assign(context, &a)?;
+
+        left = Some(match left {
+            Some(mut sum) => {
+                sum.add_assign(context, &r_point)?;
+                sum
+            }
+            None => r_point,
+        });
+        right = Some(match right {
+            Some(mut sum) => {
+                sum.add_assign(context, &p_point)?;
+                sum
+            }
+            None => p_point,
+        });
+        s_sum = Some(match s_sum {
+            Some(mut sum) => {
+                sum.add_assign(context, &s)?;
+                sum
+            }
+            None => s,
+        });
+    }
+
+    match (left, right, s_sum) {
+        (Some(left), Some(mut right), Some(s_sum)) => {
+            right.add_exp_assign(context, &s_sum)?;
+            Ok(left.serialize_vec(context, true) == right.serialize_vec(context, true))
+        }
+        _ => Ok(true),
+    }
+}
+
+/// The signatures of one message aggregated into a single scalar.
+///
+/// The verification hash of each signature depends on its own nonce, so the nonces can't be merged without an
+/// interactive signing round. Only the `s` parts are folded into one value, which halves the size of the signatures.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SchnorrAggregate {
+    nonces: Vec<H256>,
+    s: H256,
+}
+
+impl SchnorrAggregate {
+    pub fn len(&self) -> usize {
+        self.nonces.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nonces.is_empty()
+    }
+}
+
+impl Encodable for SchnorrAggregate {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(2).append_list(&self.nonces).append(&self.s);
+    }
+}
+
+impl Decodable for SchnorrAggregate {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 2 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected: 2,
+            })
+        }
+        Ok(Self {
+            nonces: rlp.list_at(0)?,
+            s: rlp.val_at(1)?,
+        })
+    }
+}
+
+/// Aggregates the signatures of the message into `(r_1, ..., r_n, sum(z * s))`.
+///
+/// The coefficient z of each signature is derived from all the nonces, the public keys and the message, so that
+/// a signer can't choose its signature to cancel out the others.
+pub fn aggregate_schnorr(items: &[(Public, SchnorrSignature)], message: &Message) -> Result<SchnorrAggregate, Error> {
+    let context = &SECP256K1;
+    let publics: Vec<Public> = items.iter().map(|(public, _)| *public).collect();
+    let nonces: Vec<H256> = items.iter().map(|(_, signature)| H256::from(&signature.0[0..32])).collect();
+    let coefficients = aggregate_coefficients(&publics, &nonces, message)?;
+
+    let mut s_sum: Option<key::SecretKey> = None;
+    for ((_, signature), z) in items.iter().zip(coefficients) {
+        let mut s = key::SecretKey::from_slice(context, &signature.0[32..64])?;
+        s.mul_assign(context, &z)?;
+        s_sum = Some(match s_sum {
+            Some(mut sum) => {
+                sum.add_assign(context, &s)?;
+                sum
+            }
+            None => s,
+        });
+    }
+    Ok(SchnorrAggregate {
+        nonces,
+        s: s_sum.map(|s| H256::from(&s[0..32])).unwrap_or_default(),
+    })
+}
+
+/// Checks sum(z * R) = sum(z * h * P) + s * G, where the i-th public key is the signer of the i-th nonce.
+pub fn verify_schnorr_aggregate(
+    publics: &[Public],
+    aggregate: &SchnorrAggregate,
+    message: &Message,
+) -> Result<bool, Error> {
+    if publics.len() != aggregate.nonces.len() {
+        return Ok(false)
+    }
+    if publics.is_empty() {
+        return Ok(aggregate.s.is_zero())
+    }
+    let context = &SECP256K1;
+    let coefficients = aggregate_coefficients(publics, &aggregate.nonces, message)?;
+
+    let mut left: Option<key::PublicKey> = None;
+    let mut right: Option<key::PublicKey> = None;
+    for ((public, nonce), z) in publics.iter().zip(aggregate.nonces.iter()).zip(coefficients) {
+        let mut r_point = {
+            let mut data = [2u8; 33];
+            data[1..33].copy_from_slice(&nonce[..]);
+            key::PublicKey::from_slice(context, &data)?
+        };
+        r_point.mul_assign(context, &z)?;
+
+        let mut h = {
+            let mut data = [0u8; 64];
+            data[0..32].copy_from_slice(&nonce[..]);
+            data[32..64].copy_from_slice(&message[..]);
+            key::SecretKey::from_slice(context, &sha256(&data[..]))?
+        };
+        h.mul_assign(context, &z)?;
+        let mut p_point = {
+            let mut data = [4u8; 65];
+            data[1..65].copy_from_slice(&**public);
+            key::PublicKey::from_slice(context, &data)?
+        };
+        p_point.mul_assign(context, &h)?;
+
+        left = Some(match left {
+            Some(mut sum) => {
+                sum.add_assign(context, &r_point)?;
+                sum
+            }
+            None => r_point,
+        });
+        right = Some(match right {
+            Some(mut sum) => {
+                sum.add_assign(context, &p_point)?;
+                sum
+            }
+            None => p_point,
+        });
+    }
+
+    let s = match key::SecretKey::from_slice(context, &aggregate.s[..]) {
+        Ok(s) => s,
+        Err(SecpError::InvalidSecretKey) => return Ok(false),
+        Err(err) => return Err(Error::from(err)),
+    };
+    match (left, right) {
+        (Some(left), Some(mut right)) => {
+            right.add_exp_assign(context, &s)?;
+            Ok(left.serialize_vec(context, true) == right.serialize_vec(context, true))
+        }
+        _ => Ok(false),
+    }
+}
+
+fn aggregate_coefficients(
+    publics: &[Public],
+    nonces: &[H256],
+    message: &Message,
+) -> Result<Vec<key::SecretKey>, Error> {
+    let context = &SECP256K1;
+    let mut data = Vec::with_capacity(publics.len() * 96 + 32);
+    for (public, nonce) in publics.iter().zip(nonces) {
+        data.extend_from_slice(&nonce[..]);
+        data.extend_from_slice(&public[..]);
+    }
+    data.extend_from_slice(&message[..]);
+    let seed = sha256(&data[..]);
+
+    (0..publics.len())
+        .map(|index| {
+            let mut data = [0u8; 40];
+            data[0..32].copy_from_slice(&seed[..]);
+            data[32..40].copy_from_slice(&(index as u64).to_be_bytes());
+            Ok(key::SecretKey::from_slice(context, &sha256(&data[..]))?)
+        })
+        .collect()
+}
+
 pub fn verify_schnorr_address(
     address: &Address,
     signature: &SchnorrSignature,
@@ -233,8 +481,14 @@ pub fn recover_schnorr(signature: &SchnorrSignature, message: &Message) -> Resul
 mod tests {
     use std::str::FromStr;
 
-    use super::{recover_schnorr, sign_schnorr, verify_schnorr, verify_schnorr_address, SchnorrSignature};
-    use crate::{Generator, Message, Random};
+    use primitives::H256;
+    use rlp::rlp_encode_and_decode_test;
+
+    use super::{
+        aggregate_schnorr, recover_schnorr, sign_schnorr, verify_schnorr, verify_schnorr_address,
+        verify_schnorr_aggregate, verify_schnorr_batch, SchnorrAggregate, SchnorrSignature,
+    };
+    use crate::{Generator, Message, Public, Random};
 
     #[test]
     fn signature_to_and_from_str() {
@@ -269,4 +523,139 @@ mod tests {
         let signature = sign_schnorr(keypair.private(), &message).unwrap();
         assert!(verify_schnorr_address(&keypair.address(), &signature, &message).unwrap());
     }
+
+    fn signed_items(count: usize) -> Vec<(Public, SchnorrSignature, Message)> {
+        (0..count)
+            .map(|_| {
+                let keypair = Random.generate().unwrap();
+                let message = Message::random();
+                let signature = sign_schnorr(keypair.private(), &message).unwrap();
+                (*keypair.public(), signature, message)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn batch_of_valid_signatures() {
+        assert_eq!(Ok(None), verify_schnorr_batch(&[]));
+        assert_eq!(Ok(None), verify_schnorr_batch(&signed_items(1)));
+        assert_eq!(Ok(None), verify_schnorr_batch(&signed_items(30)));
+    }
+
+    #[test]
+    fn batch_reports_the_invalid_signature() {
+        let mut items = signed_items(30);
+        // Signed for another message
+        items[17].2 = Message::random();
+        assert_eq!(Ok(Some(17)), verify_schnorr_batch(&items));
+
+        let mut items = signed_items(30);
+        // Signed by another key
+        items[3].1 = sign_schnorr(Random.generate().unwrap().private(), &items[3].2).unwrap();
+        assert_eq!(Ok(Some(3)), verify_schnorr_batch(&items));
+    }
+
+    #[test]
+    fn batch_with_duplicated_signers() {
+        let keypair = Random.generate().unwrap();
+        let message = Message::random();
+        let signature = sign_schnorr(keypair.private(), &message).unwrap();
+        let item = (*keypair.public(), signature, message);
+
+        let mut items = signed_items(10);
+        items.push(item.clone());
+        items.push(item.clone());
+        assert_eq!(Ok(None), verify_schnorr_batch(&items));
+
+        // The same signer signs another message, but the signature is paired with the first one.
+        let other_message = Message::random();
+        let other_signature = sign_schnorr(keypair.private(), &other_message).unwrap();
+        items.push((*keypair.public(), other_signature, message));
+        assert_eq!(Ok(Some(12)), verify_schnorr_batch(&items));
+    }
+
+    fn signatures_of(message: &Message, count: usize) -> Vec<(Public, SchnorrSignature)> {
+        (0..count)
+            .map(|_| {
+                let keypair = Random.generate().unwrap();
+                (*keypair.public(), sign_schnorr(keypair.private(), message).unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn aggregate_and_verify() {
+        let message = Message::random();
+        let items = signatures_of(&message, 30);
+        let publics: Vec<Public> = items.iter().map(|(public, _)| *public).collect();
+        let aggregate = aggregate_schnorr(&items, &message).unwrap();
+        assert_eq!(30, aggregate.len());
+        assert_eq!(Ok(true), verify_schnorr_aggregate(&publics, &aggregate, &message));
+        rlp_encode_and_decode_test!(aggregate);
+    }
+
+    #[test]
+    fn aggregate_is_smaller_than_the_signatures() {
+        let message = Message::random();
+        let items = signatures_of(&message, 30);
+        let signatures: Vec<SchnorrSignature> = items.iter().map(|(_, signature)| *signature).collect();
+        let aggregate = aggregate_schnorr(&items, &message).unwrap();
+        assert!(rlp::encode(&aggregate).len() * 3 < rlp::encode_list(&signatures).len() * 2);
+    }
+
+    #[test]
+    fn aggregate_of_another_message_is_rejected() {
+        let message = Message::random();
+        let items = signatures_of(&message, 5);
+        let publics: Vec<Public> = items.iter().map(|(public, _)| *public).collect();
+        let aggregate = aggregate_schnorr(&items, &message).unwrap();
+        assert_eq!(Ok(false), verify_schnorr_aggregate(&publics, &aggregate, &Message::random()));
+    }
+
+    #[test]
+    fn aggregate_with_wrong_signers_is_rejected() {
+        let message = Message::random();
+        let items = signatures_of(&message, 5);
+        let mut publics: Vec<Public> = items.iter().map(|(public, _)| *public).collect();
+        let aggregate = aggregate_schnorr(&items, &message).unwrap();
+
+        publics.swap(1, 2);
+        assert_eq!(Ok(false), verify_schnorr_aggregate(&publics, &aggregate, &message));
+
+        publics.swap(1, 2);
+        publics.pop();
+        assert_eq!(Ok(false), verify_schnorr_aggregate(&publics, &aggregate, &message));
+    }
+
+    #[test]
+    fn aggregate_containing_an_invalid_signature_is_rejected() {
+        let message = Message::random();
+        let mut items = signatures_of(&message, 5);
+        items[3].1 = sign_schnorr(Random.generate().unwrap().private(), &message).unwrap();
+        let publics: Vec<Public> = items.iter().map(|(public, _)| *public).collect();
+        let aggregate = aggregate_schnorr(&items, &message).unwrap();
+        assert_eq!(Ok(false), verify_schnorr_aggregate(&publics, &aggregate, &message));
+    }
+
+    #[test]
+    fn tampered_aggregate_is_rejected() {
+        let message = Message::random();
+        let items = signatures_of(&message, 5);
+        let publics: Vec<Public> = items.iter().map(|(public, _)| *public).collect();
+        let aggregate = aggregate_schnorr(&items, &message).unwrap();
+
+        let mut tampered = aggregate.clone();
+        tampered.s = H256::random();
+        assert_eq!(Ok(false), verify_schnorr_aggregate(&publics, &tampered, &message));
+
+        let mut tampered = aggregate.clone();
+        tampered.s = H256::zero();
+        assert_eq!(Ok(false), verify_schnorr_aggregate(&publics, &tampered, &message));
+
+        let mut tampered = aggregate;
+        tampered.nonces.swap(0, 4);
+        assert_ne!(Ok(true), verify_schnorr_aggregate(&publics, &tampered, &message));
+
+        assert_eq!(Ok(true), verify_schnorr_aggregate(&[], &SchnorrAggregate::default(), &message));
+    }
 }
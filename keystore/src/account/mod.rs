@@ -19,6 +19,7 @@ mod crypto;
 mod decrypted_account;
 mod kdf;
 mod safe_account;
+mod safe_seed;
 mod version;
 
 pub use self::cipher::{Aes128Ctr, Cipher};
@@ -26,4 +27,5 @@ pub use self::crypto::Crypto;
 pub use self::decrypted_account::DecryptedAccount;
 pub use self::kdf::{Kdf, Pbkdf2, Prf, Scrypt};
 pub use self::safe_account::SafeAccount;
+pub use self::safe_seed::{DerivedAccount, SafeSeed};
 pub use self::version::Version;
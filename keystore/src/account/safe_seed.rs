@@ -0,0 +1,126 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use ccrypto;
+use ckey::{Address, KeyPair, Password};
+
+use super::crypto::Crypto;
+use crate::account::{DecryptedAccount, Version};
+use crate::derivation::{derive_secret, MAX_INDEX};
+use crate::{json, Error};
+
+/// An account derived from a seed.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DerivedAccount {
+    pub index: u32,
+    pub address: Address,
+}
+
+/// Seed representation.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SafeSeed {
+    /// Seed ID
+    pub id: [u8; 16],
+    /// Seed version
+    pub version: Version,
+    /// Encrypted seed
+    pub crypto: Crypto,
+    /// The accounts derived so far, in the order of their indices
+    pub derived: Vec<DerivedAccount>,
+    /// Seed filename
+    pub filename: Option<String>,
+}
+
+impl From<SafeSeed> for json::SeedFile {
+    fn from(seed: SafeSeed) -> Self {
+        Self {
+            id: From::from(seed.id),
+            version: seed.version.into(),
+            crypto: seed.crypto.into(),
+            derived: seed
+                .derived
+                .into_iter()
+                .map(|account| json::DerivedKey {
+                    index: account.index,
+                    address: account.address.into(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl SafeSeed {
+    /// Encrypt a new seed
+    pub fn create(seed: &[u8], id: [u8; 16], password: &Password, iterations: u32) -> Result<Self, ccrypto::Error> {
+        Ok(SafeSeed {
+            id,
+            version: Version::V3,
+            crypto: Crypto::with_plain(seed, password, iterations)?,
+            derived: Vec::new(),
+            filename: None,
+        })
+    }
+
+    pub fn from_file(json: json::SeedFile, filename: Option<String>) -> Result<Self, Error> {
+        let derived = json
+            .derived
+            .into_iter()
+            .map(|key| {
+                if key.index >= MAX_INDEX {
+                    return Err(Error::InvalidKeyFile(format!("Invalid derivation index: {}", key.index)))
+                }
+                Ok(DerivedAccount {
+                    index: key.index,
+                    address: key.address.into(),
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(SafeSeed {
+            id: json.id.into(),
+            version: json.version.into(),
+            crypto: json.crypto.into(),
+            derived,
+            filename,
+        })
+    }
+
+    /// Derives the account next to the last derived one. The secret is not kept.
+    pub fn derive_next(&self, password: &Password) -> Result<(Self, DerivedAccount), Error> {
+        let seed = self.crypto.decrypt(password)?;
+        let index = self.derived.last().map_or(0, |account| account.index + 1);
+        let secret = derive_secret(&seed, index)?;
+        let account = DerivedAccount {
+            index,
+            address: KeyPair::from_private(secret.into())?.address(),
+        };
+
+        let mut seed = self.clone();
+        seed.derived.push(account.clone());
+        Ok((seed, account))
+    }
+
+    pub fn derived_account(&self, address: &Address) -> Option<&DerivedAccount> {
+        self.derived.iter().find(|account| account.address == *address)
+    }
+
+    /// Derives the secret of the account again.
+    pub fn decrypt(&self, address: &Address, password: &Password) -> Result<DecryptedAccount, Error> {
+        let account = self.derived_account(address).ok_or(Error::InvalidAccount)?;
+        let seed = self.crypto.decrypt(password)?;
+        Ok(DecryptedAccount::new(derive_secret(&seed, account.index)?))
+    }
+}
@@ -24,9 +24,11 @@ use time;
 
 use super::KeyDirectory;
 use crate::json::Uuid;
-use crate::{json, Error, SafeAccount};
+use crate::{json, Error, SafeAccount, SafeSeed};
 
 const IGNORED_FILES: &[&str] = &["thumbs.db"];
+/// The seeds are kept in a subdirectory, so that they are not read as key files.
+const SEEDS_DIRECTORY: &str = "seeds";
 
 #[cfg(not(windows))]
 fn restrict_permissions_to_owner(file_path: &Path) -> Result<(), i32> {
@@ -203,6 +205,10 @@ where
         Ok(account)
     }
 
+    fn seeds_path(&self) -> PathBuf {
+        self.path.join(SEEDS_DIRECTORY)
+    }
+
     /// Get key file manager referece
     pub fn key_manager(&self) -> &T {
         &self.key_manager
@@ -244,6 +250,49 @@ where
         }
     }
 
+    fn load_seeds(&self) -> Result<Vec<SafeSeed>, Error> {
+        let seeds_path = self.seeds_path();
+        if !seeds_path.exists() {
+            return Ok(Vec::new())
+        }
+        Ok(fs::read_dir(&seeds_path)?
+            .flat_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .filter_map(|path| {
+                let filename = path.file_name().and_then(OsStr::to_str).map(ToOwned::to_owned);
+                fs::File::open(&path)
+                    .map_err(Into::into)
+                    .and_then(|file| json::SeedFile::load(file).map_err(|e| Error::Custom(format!("{:?}", e))))
+                    .and_then(|seed_file| SafeSeed::from_file(seed_file, filename))
+                    .map_err(|e| {
+                        warn!("Invalid seed file: {:?} ({})", path, e);
+                        e
+                    })
+                    .ok()
+            })
+            .collect())
+    }
+
+    fn save_seed(&self, seed: SafeSeed) -> Result<SafeSeed, Error> {
+        let seeds_path = self.seeds_path();
+        fs::create_dir_all(&seeds_path)?;
+        let filename = seed.filename.clone().unwrap_or_else(|| format!("seed--{}", Uuid::from(seed.id)));
+        let seed_file_path = seeds_path.join(&filename);
+        {
+            let mut file = fs::File::create(&seed_file_path)?;
+            let seed_file: json::SeedFile = seed.clone().into();
+            seed_file.write(&mut file).map_err(|e| Error::Custom(format!("{:?}", e)))?;
+            file.flush()?;
+            restrict_permissions_to_owner(&seed_file_path).map_err(|_| Error::Io(io::Error::last_os_error()))?;
+            file.sync_all()?;
+        }
+
+        let mut seed = seed;
+        seed.filename = Some(filename);
+        Ok(seed)
+    }
+
     fn path(&self) -> Option<&PathBuf> {
         Some(&self.path)
     }
@@ -361,4 +410,37 @@ mod test {
 
         assert!(new_hash != hash, "hash of the file list should change once directory content changed");
     }
+
+    #[test]
+    fn seed_file_has_no_plaintext() {
+        use rustc_hex::ToHex;
+
+        use crate::derivation::{derive_secret, parse_seed};
+        use crate::{KeyStore, SecretStore};
+
+        let temp_path = TempDir::new("").unwrap();
+        let store = KeyStore::open(Box::new(RootDiskDirectory::create(&temp_path).unwrap())).unwrap();
+        let mnemonic = "letter advice cage absurd amount doctor acoustic avoid letter advice cage above";
+        let seed = parse_seed(mnemonic).unwrap();
+        let password = &"seed password".into();
+        let id = store.import_seed(&seed, password).unwrap();
+        let derived = store.derive_account(&id, password).unwrap();
+
+        let seed_files: Vec<_> = fs::read_dir(temp_path.path().join(SEEDS_DIRECTORY))
+            .unwrap()
+            .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect();
+        assert_eq!(1, seed_files.len());
+        let content = &seed_files[0];
+        assert!(content.contains(&derived.address.to_hex()), "The derived addresses are recorded");
+        let secret = derive_secret(&seed, derived.index).unwrap();
+        for plaintext in &[seed.to_hex(), secret.to_hex(), "letter".to_string(), "advice".to_string()] {
+            assert!(!content.contains(plaintext.as_str()), "{} is in the seed file", plaintext);
+        }
+
+        // The seed is not read as a key file, and it's loaded again with the derived accounts.
+        let reopened = KeyStore::open(Box::new(RootDiskDirectory::at(&temp_path))).unwrap();
+        assert_eq!(vec![derived.clone()], reopened.derived_accounts(&id).unwrap());
+        assert_eq!(vec![derived.address], reopened.accounts().unwrap());
+    }
 }
@@ -20,12 +20,13 @@ use ckey::Address;
 use parking_lot::RwLock;
 
 use super::KeyDirectory;
-use crate::{Error, SafeAccount};
+use crate::{Error, SafeAccount, SafeSeed};
 
 /// Accounts in-memory storage.
 #[derive(Default)]
 pub struct MemoryDirectory {
     accounts: RwLock<HashMap<Address, Vec<SafeAccount>>>,
+    seeds: RwLock<HashMap<[u8; 16], SafeSeed>>,
 }
 
 impl KeyDirectory for MemoryDirectory {
@@ -65,6 +66,15 @@ impl KeyDirectory for MemoryDirectory {
         Ok(())
     }
 
+    fn load_seeds(&self) -> Result<Vec<SafeSeed>, Error> {
+        Ok(self.seeds.read().values().cloned().collect())
+    }
+
+    fn save_seed(&self, seed: SafeSeed) -> Result<SafeSeed, Error> {
+        self.seeds.write().insert(seed.id, seed.clone());
+        Ok(seed)
+    }
+
     fn unique_repr(&self) -> Result<u64, Error> {
         let mut val = 0u64;
         let accounts = self.accounts.read();
@@ -18,7 +18,7 @@
 
 use std::path::PathBuf;
 
-use crate::{Error, SafeAccount};
+use crate::{Error, SafeAccount, SafeSeed};
 
 mod disk;
 mod memory;
@@ -44,6 +44,10 @@ pub trait KeyDirectory: Send + Sync {
     fn insert(&self, account: SafeAccount) -> Result<SafeAccount, Error>;
     /// Remove key from directory
     fn remove(&self, account: &SafeAccount) -> Result<(), Error>;
+    /// Read seeds from directory
+    fn load_seeds(&self) -> Result<Vec<SafeSeed>, Error>;
+    /// Insert new seed or update the seed with the same id
+    fn save_seed(&self, seed: SafeSeed) -> Result<SafeSeed, Error>;
     /// Get directory filesystem path, if available
     fn path(&self) -> Option<&PathBuf> {
         None
@@ -0,0 +1,162 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+//! The derivation of the accounts from a seed.
+//!
+//! The index-th account is derived from the seed with the keyed BLAKE2b. It's not BIP32, so the accounts are
+//! identified by their indices rather than by the derivation paths of BIP32.
+
+use std::num::NonZeroU32;
+
+use ccrypto::{blake512_with_key, pbkdf2, sha256};
+use ckey::{KeyPair, Secret};
+use rustc_hex::FromHex;
+
+use crate::Error;
+
+const MASTER_KEY: &[u8] = b"CodeChain seed";
+/// The highest bit of the index is always set, so the indices must be lower than this.
+pub const MAX_INDEX: u32 = 0x8000_0000;
+/// The English wordlist of BIP39, sorted
+const MNEMONIC_WORDLIST: &str = include_str!("../res/bip39_english.txt");
+/// The iterations of PBKDF2 that BIP39 uses to stretch a mnemonic.
+const MNEMONIC_ITERATIONS: u32 = 2048;
+const MNEMONIC_WORDS: &[usize] = &[12, 15, 18, 21, 24];
+const MIN_SEED_LENGTH: usize = 16;
+const MAX_SEED_LENGTH: usize = 64;
+
+/// Reads a seed given as a 0x-prefixed hex string, or as a BIP39 mnemonic without a passphrase.
+pub fn parse_seed(mnemonic_or_seed: &str) -> Result<Vec<u8>, Error> {
+    let mnemonic_or_seed = mnemonic_or_seed.trim();
+    if mnemonic_or_seed.starts_with("0x") {
+        let seed: Vec<u8> = mnemonic_or_seed[2..].from_hex().map_err(|_| Error::InvalidSeed)?;
+        if seed.len() < MIN_SEED_LENGTH || MAX_SEED_LENGTH < seed.len() {
+            return Err(Error::InvalidSeed)
+        }
+        return Ok(seed)
+    }
+
+    let words: Vec<_> = mnemonic_or_seed.split_whitespace().collect();
+    if !is_valid_mnemonic(&words) {
+        return Err(Error::InvalidSeed)
+    }
+    let mut seed = [0u8; 64];
+    pbkdf2::sha512(
+        NonZeroU32::new(MNEMONIC_ITERATIONS).expect("The iterations are not zero"),
+        &pbkdf2::Salt(b"mnemonic"),
+        &pbkdf2::Secret(words.join(" ").as_bytes()),
+        &mut seed,
+    );
+    Ok(seed.to_vec())
+}
+
+/// Checks that the words are in the English wordlist of BIP39, and that the checksum of the entropy matches.
+fn is_valid_mnemonic(words: &[&str]) -> bool {
+    if !MNEMONIC_WORDS.contains(&words.len()) {
+        return false
+    }
+    let wordlist: Vec<&str> = MNEMONIC_WORDLIST.lines().collect();
+    let mut bits = Vec::with_capacity(words.len() * 11);
+    for word in words {
+        let index = match wordlist.binary_search(word) {
+            Ok(index) => index,
+            Err(_) => return false,
+        };
+        bits.extend((0..11).rev().map(|shift| (index >> shift) & 1 == 1));
+    }
+    // Every 3 words have 32 bits of the entropy and 1 bit of the checksum.
+    let entropy_bits = bits.len() - words.len() / 3;
+    let entropy: Vec<u8> = bits[..entropy_bits]
+        .chunks(8)
+        .map(|byte| byte.iter().fold(0u8, |acc, bit| (acc << 1) | u8::from(*bit)))
+        .collect();
+    let hash = sha256(&entropy);
+    bits[entropy_bits..].iter().enumerate().all(|(i, bit)| (hash[i / 8] >> (7 - i % 8)) & 1 == u8::from(*bit))
+}
+
+/// Derives the secret of the index-th account from the seed.
+pub fn derive_secret(seed: &[u8], index: u32) -> Result<Secret, Error> {
+    let master = blake512_with_key(seed, MASTER_KEY);
+    let (master_secret, chain_code) = master.split_at(32);
+
+    let mut data = Vec::with_capacity(37);
+    data.push(0);
+    data.extend_from_slice(master_secret);
+    data.extend_from_slice(&(index | MAX_INDEX).to_be_bytes());
+    let child = blake512_with_key(&data, chain_code);
+
+    let secret = Secret::from_slice(&child[..32]);
+    // The probability that it's out of the range of the curve order is lower than 1 in 2^127.
+    KeyPair::from_private(secret.into())?;
+    Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MNEMONIC: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn mnemonic_is_stretched_as_bip39() {
+        let seed = parse_seed(MNEMONIC).unwrap();
+        let expected: Vec<u8> = "5eb00bbddcf069084889a8ab9155568165f5c453ccb85e70811aaed6f6da5fc19a5ac40b389cd370d086206dec8aa6c43daea6690f20ad3d8d48b2d2ce9e38e4"
+            .from_hex()
+            .unwrap();
+        assert_eq!(expected, seed);
+        assert_eq!(seed, parse_seed(&format!("  {}\n", MNEMONIC.replace(" ", "  "))).unwrap());
+    }
+
+    #[test]
+    fn mnemonic_is_validated_against_the_wordlist() {
+        let wordlist: Vec<_> = MNEMONIC_WORDLIST.lines().collect();
+        assert_eq!(2048, wordlist.len());
+        assert_eq!(("abandon", "zoo"), (wordlist[0], wordlist[2047]));
+
+        assert!(parse_seed("legal winner thank year wave sausage worth useful legal winner thank yellow").is_ok());
+        assert!(parse_seed(&format!("{}when", "zoo ".repeat(17))).is_ok());
+        // The checksum doesn't match.
+        assert_matches!(parse_seed(&"abandon ".repeat(12)), Err(Error::InvalidSeed));
+        assert_matches!(
+            parse_seed("legal winner thank year wave sausage worth useful legal winner thank year"),
+            Err(Error::InvalidSeed)
+        );
+        // Not in the wordlist
+        assert_matches!(
+            parse_seed("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abut"),
+            Err(Error::InvalidSeed)
+        );
+        assert_matches!(parse_seed(&MNEMONIC.to_uppercase()), Err(Error::InvalidSeed));
+    }
+
+    #[test]
+    fn hex_seed_is_decoded() {
+        assert_eq!(vec![0x11; 16], parse_seed("0x11111111111111111111111111111111").unwrap());
+        assert_matches!(parse_seed("0x1111"), Err(Error::InvalidSeed));
+        assert_matches!(parse_seed("0xzz"), Err(Error::InvalidSeed));
+        assert_matches!(parse_seed("abandon about"), Err(Error::InvalidSeed));
+    }
+
+    #[test]
+    fn accounts_are_derived_deterministically() {
+        let seed = parse_seed(MNEMONIC).unwrap();
+        assert_eq!(derive_secret(&seed, 0).unwrap(), derive_secret(&seed, 0).unwrap());
+        assert_ne!(derive_secret(&seed, 0).unwrap(), derive_secret(&seed, 1).unwrap());
+        assert_ne!(derive_secret(&seed, 0).unwrap(), derive_secret(&[0x11; 16], 0).unwrap());
+    }
+}
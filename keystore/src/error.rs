@@ -39,6 +39,8 @@ pub enum Error {
     CreationFailed,
     /// Account already exists.
     AlreadyExists,
+    /// Invalid mnemonic or seed, or the seed doesn't exist.
+    InvalidSeed,
     /// `ckeys` error
     CKey(CKeyError),
     /// `CCrypto` error
@@ -58,6 +60,7 @@ impl fmt::Display for Error {
             Error::InvalidKeyFile(ref reason) => format!("Invalid key file: {}", reason),
             Error::CreationFailed => "Account creation failed".into(),
             Error::AlreadyExists => "Account already exists".into(),
+            Error::InvalidSeed => "Invalid seed".into(),
             Error::CKey(ref err) => err.to_string(),
             Error::CCrypto(ref err) => err.to_string(),
             Error::Custom(ref s) => s.clone(),
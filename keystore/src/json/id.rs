@@ -23,7 +23,7 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use super::Error;
 
 /// Universally unique identifier.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub struct Uuid([u8; 16]);
 
 impl From<[u8; 16]> for Uuid {
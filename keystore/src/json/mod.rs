@@ -23,6 +23,7 @@ mod hash;
 mod id;
 mod kdf;
 mod key_file;
+mod seed_file;
 mod version;
 
 pub use self::cipher::{Aes128Ctr, Cipher, CipherSer, CipherSerParams};
@@ -32,4 +33,5 @@ pub use self::hash::{H128, H160, H256};
 pub use self::id::Uuid;
 pub use self::kdf::{Kdf, KdfSer, KdfSerParams, Pbkdf2, Prf, Scrypt};
 pub use self::key_file::{KeyFile, OpaqueKeyFile};
+pub use self::seed_file::{DerivedKey, SeedFile};
 pub use self::version::Version;
@@ -0,0 +1,52 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use std::io::{Read, Write};
+
+use serde_json;
+
+use super::{Crypto, Uuid, Version, H160};
+
+/// The encrypted seed and the accounts derived from it.
+/// The secrets of the derived accounts are not stored, because they are derived again on demand.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SeedFile {
+    pub id: Uuid,
+    pub version: Version,
+    pub crypto: Crypto,
+    pub derived: Vec<DerivedKey>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct DerivedKey {
+    pub index: u32,
+    pub address: H160,
+}
+
+impl SeedFile {
+    pub fn load<R>(reader: R) -> Result<Self, serde_json::Error>
+    where
+        R: Read, {
+        serde_json::from_reader(reader)
+    }
+
+    pub fn write<W>(&self, writer: &mut W) -> Result<(), serde_json::Error>
+    where
+        W: Write, {
+        serde_json::to_writer(writer, self)
+    }
+}
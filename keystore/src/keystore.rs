@@ -23,7 +23,7 @@ use ccrypto::KEY_ITERATIONS;
 use ckey::{Address, KeyPair, Password, Secret};
 use parking_lot::{Mutex, RwLock};
 
-use crate::account::{DecryptedAccount, SafeAccount};
+use crate::account::{DecryptedAccount, DerivedAccount, SafeAccount, SafeSeed};
 use crate::accounts_dir::KeyDirectory;
 use crate::json::{self, OpaqueKeyFile, Uuid};
 use crate::random::Random;
@@ -114,7 +114,7 @@ impl SecretStore for KeyStore {
     }
 
     fn test_password(&self, account: &Address, password: &Password) -> Result<bool, Error> {
-        match self.store.get_verified_account(account, password) {
+        match self.store.decrypt_account(account, password) {
             Ok(_) => Ok(true),
             Err(Error::InvalidPassword) => Ok(false),
             Err(err) => Err(err),
@@ -152,6 +152,18 @@ impl SecretStore for KeyStore {
         self.store.update(account_ref, &old, safe_account)
     }
 
+    fn import_seed(&self, seed: &[u8], password: &Password) -> Result<Uuid, Error> {
+        self.store.import_seed(seed, password)
+    }
+
+    fn derive_account(&self, seed: &Uuid, password: &Password) -> Result<DerivedAccount, Error> {
+        self.store.derive_account(seed, password)
+    }
+
+    fn derived_accounts(&self, seed: &Uuid) -> Result<Vec<DerivedAccount>, Error> {
+        self.store.derived_accounts(seed)
+    }
+
     fn local_path(&self) -> PathBuf {
         self.store.dir.path().cloned().unwrap_or_else(PathBuf::new)
     }
@@ -163,6 +175,7 @@ pub struct KeyMultiStore {
     iterations: u32,
     // order lock: cache
     cache: RwLock<BTreeMap<Address, Vec<SafeAccount>>>,
+    seeds: RwLock<BTreeMap<[u8; 16], SafeSeed>>,
     timestamp: Mutex<Timestamp>,
 }
 
@@ -184,6 +197,7 @@ impl KeyMultiStore {
             dir: directory,
             iterations,
             cache: Default::default(),
+            seeds: Default::default(),
             timestamp: Mutex::new(Timestamp {
                 dir_hash: None,
                 last_checked: Instant::now(),
@@ -230,9 +244,40 @@ impl KeyMultiStore {
         }
 
         mem::replace(&mut *cache, new_accounts);
+
+        let new_seeds = self.dir.load_seeds()?.into_iter().map(|seed| (seed.id, seed)).collect();
+        mem::replace(&mut *self.seeds.write(), new_seeds);
         Ok(())
     }
 
+    /// Imports a seed that accounts are derived from.
+    pub fn import_seed(&self, seed: &[u8], password: &Password) -> Result<Uuid, Error> {
+        let id: [u8; 16] = Random::random();
+        let seed = self.dir.save_seed(SafeSeed::create(seed, id, password, self.iterations)?)?;
+        self.seeds.write().insert(id, seed);
+        Ok(id.into())
+    }
+
+    /// Derives the account next to the last one derived from the seed, and records it.
+    pub fn derive_account(&self, id: &Uuid, password: &Password) -> Result<DerivedAccount, Error> {
+        let id: [u8; 16] = (*id).into();
+        let mut seeds = self.seeds.write();
+        let (seed, account) = seeds.get(&id).ok_or(Error::InvalidSeed)?.derive_next(password)?;
+        let seed = self.dir.save_seed(seed)?;
+        seeds.insert(id, seed);
+        Ok(account)
+    }
+
+    pub fn derived_accounts(&self, id: &Uuid) -> Result<Vec<DerivedAccount>, Error> {
+        let id: [u8; 16] = (*id).into();
+        let seeds = self.seeds.read();
+        Ok(seeds.get(&id).ok_or(Error::InvalidSeed)?.derived.clone())
+    }
+
+    fn get_seed_of(&self, account: &Address) -> Option<SafeSeed> {
+        self.seeds.read().values().find(|seed| seed.derived_account(account).is_some()).cloned()
+    }
+
     fn get_safe_accounts(&self, account: &Address) -> Result<Vec<SafeAccount>, Error> {
         let from_cache = |account| {
             let cache = self.cache.read();
@@ -341,13 +386,17 @@ impl SimpleSecretStore for KeyMultiStore {
 
     fn accounts(&self) -> Result<Vec<Address>, Error> {
         self.reload_if_changed()?;
-        Ok(self.cache.read().keys().cloned().collect())
+        let mut accounts: Vec<_> = self.cache.read().keys().cloned().collect();
+        accounts.extend(self.seeds.read().values().flat_map(|seed| seed.derived.iter().map(|account| account.address)));
+        accounts.sort();
+        accounts.dedup();
+        Ok(accounts)
     }
 
     fn has_account(&self, account: &Address) -> Result<bool, Error> {
         match self.get_safe_accounts(account) {
             Ok(_) => Ok(true),
-            Err(Error::InvalidAccount) => Ok(false),
+            Err(Error::InvalidAccount) => Ok(self.get_seed_of(account).is_some()),
             Err(e) => Err(e),
         }
     }
@@ -391,7 +440,14 @@ impl SimpleSecretStore for KeyMultiStore {
     }
 
     fn decrypt_account(&self, account: &Address, password: &Password) -> Result<DecryptedAccount, Error> {
-        Ok(DecryptedAccount::new(self.get_verified_account(account, password)?.secret))
+        match self.get_verified_account(account, password) {
+            Ok(verified) => Ok(DecryptedAccount::new(verified.secret)),
+            // The secret of a derived account is derived again whenever it's needed.
+            Err(Error::InvalidAccount) => {
+                self.get_seed_of(account).ok_or(Error::InvalidAccount)?.decrypt(account, password)
+            }
+            Err(err) => Err(err),
+        }
     }
 }
 
@@ -404,10 +460,15 @@ struct VerifiedAccount {
 mod tests {
     extern crate tempdir;
 
-    use ckey::{Generator, Random};
+    use std::collections::HashSet;
+
+    use ckey::{verify_address, Generator, Message, Random};
 
     use super::*;
     use crate::accounts_dir::MemoryDirectory;
+    use crate::derivation::parse_seed;
+
+    const MNEMONIC: &str = "legal winner thank year wave sausage worth useful legal winner thank yellow";
 
     fn keypair() -> KeyPair {
         Random.generate().unwrap()
@@ -540,4 +601,48 @@ mod tests {
         // then
         assert!(exported.is_ok(), "Should export single account: {:?}", exported);
     }
+
+    #[test]
+    fn same_seed_derives_same_accounts() {
+        let seed = parse_seed(MNEMONIC).unwrap();
+        let derive = |password: &Password| {
+            let store = store();
+            let id = store.import_seed(&seed, password).unwrap();
+            (0..3).map(|_| store.derive_account(&id, password).unwrap()).collect::<Vec<_>>()
+        };
+
+        let derived = derive(&"test".into());
+        assert_eq!(derived, derive(&"another password".into()));
+        assert_eq!(vec![0, 1, 2], derived.iter().map(|account| account.index).collect::<Vec<_>>());
+        assert_eq!(3, derived.iter().map(|account| account.address).collect::<HashSet<_>>().len());
+    }
+
+    #[test]
+    fn derived_accounts_are_listed() {
+        let store = store();
+        let id = store.import_seed(&parse_seed(MNEMONIC).unwrap(), &"test".into()).unwrap();
+        assert_eq!(Vec::<DerivedAccount>::new(), store.derived_accounts(&id).unwrap());
+
+        let first = store.derive_account(&id, &"test".into()).unwrap();
+        let second = store.derive_account(&id, &"test".into()).unwrap();
+        assert_eq!(vec![first.clone(), second.clone()], store.derived_accounts(&id).unwrap());
+        assert!(store.has_account(&first.address).unwrap());
+        assert_eq!(2, store.accounts().unwrap().len());
+
+        assert_matches!(store.derive_account(&id, &"wrong".into()), Err(Error::InvalidPassword));
+        assert_matches!(store.derived_accounts(&[0u8; 16].into()), Err(Error::InvalidSeed));
+    }
+
+    #[test]
+    fn sign_with_derived_account() {
+        let store = store();
+        let id = store.import_seed(&parse_seed(MNEMONIC).unwrap(), &"test".into()).unwrap();
+        let address = store.derive_account(&id, &"test".into()).unwrap().address;
+
+        let message = Message::random();
+        let signature = store.decrypt_account(&address, &"test".into()).unwrap().sign(&message).unwrap();
+        assert!(verify_address(&address, &signature, &message).unwrap());
+        assert!(store.test_password(&address, &"test".into()).unwrap());
+        assert!(!store.test_password(&address, &"wrong".into()).unwrap());
+    }
 }
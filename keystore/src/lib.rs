@@ -64,16 +64,18 @@ pub mod ckeys;
 mod account;
 mod json;
 
+mod derivation;
 mod error;
 mod import;
 mod keystore;
 mod random;
 mod secret_store;
 
-pub use crate::account::{Crypto, DecryptedAccount, SafeAccount};
+pub use crate::account::{Crypto, DecryptedAccount, DerivedAccount, SafeAccount, SafeSeed};
+pub use crate::derivation::parse_seed;
 pub use crate::error::Error;
 pub use crate::import::{import_account, import_accounts};
-pub use crate::json::OpaqueKeyFile as KeyFile;
+pub use crate::json::{OpaqueKeyFile as KeyFile, Uuid};
 pub use crate::keystore::{KeyMultiStore, KeyStore};
 pub use crate::random::random_string;
 pub use crate::secret_store::{SecretStore, SimpleSecretStore};
@@ -35,7 +35,7 @@ use std::path::PathBuf;
 use ckey::{Address, Password, Secret};
 
 use crate::json::{OpaqueKeyFile, Uuid};
-use crate::{DecryptedAccount, Error};
+use crate::{DecryptedAccount, DerivedAccount, Error};
 
 
 /// Simple Secret Store API
@@ -82,6 +82,13 @@ pub trait SecretStore: SimpleSecretStore {
     /// Modifies account name.
     fn set_meta(&self, account: &Address, meta: String) -> Result<(), Error>;
 
+    /// Imports a seed that accounts are derived from.
+    fn import_seed(&self, seed: &[u8], password: &Password) -> Result<Uuid, Error>;
+    /// Derives the account next to the last one derived from the seed.
+    fn derive_account(&self, seed: &Uuid, password: &Password) -> Result<DerivedAccount, Error>;
+    /// Returns the accounts derived from the seed.
+    fn derived_accounts(&self, seed: &Uuid) -> Result<Vec<DerivedAccount>, Error>;
+
     /// Returns local path of the store.
     fn local_path(&self) -> PathBuf;
 }
@@ -18,7 +18,7 @@ use std::path::PathBuf;
 use std::{env, fs};
 
 use ckeystore::accounts_dir::{KeyDirectory, RootDiskDirectory};
-use ckeystore::{Error, SafeAccount};
+use ckeystore::{Error, SafeAccount, SafeSeed};
 use rand::rngs::OsRng;
 use rand::RngCore;
 
@@ -77,6 +77,14 @@ impl KeyDirectory for TransientDir {
         self.dir.remove(account)
     }
 
+    fn load_seeds(&self) -> Result<Vec<SafeSeed>, Error> {
+        self.dir.load_seeds()
+    }
+
+    fn save_seed(&self, seed: SafeSeed) -> Result<SafeSeed, Error> {
+        self.dir.save_seed(seed)
+    }
+
     fn unique_repr(&self) -> Result<u64, Error> {
         self.dir.unique_repr()
     }
@@ -0,0 +1,546 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+use crate::NodeId;
+
+/// The messages to a peer that wait for the bandwidth are dropped beyond this size.
+pub const MAX_QUEUED_BYTES_PER_PEER: usize = 4 * 1024 * 1024;
+/// The extensions are asked to hold back their messages to a peer once this many bytes wait.
+const CONGESTION_THRESHOLD: usize = MAX_QUEUED_BYTES_PER_PEER / 2;
+/// The bytes sent are reported over this window, the same as the other network usages.
+const USAGE_WINDOW: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PeerClass {
+    /// The peer proved that it signs the consensus messages of the current validator set.
+    Validator,
+    /// The peer told its role but is not a validator.
+    Observer,
+    /// The peer didn't tell its role.
+    Unknown,
+}
+
+impl PeerClass {
+    pub const ALL: [PeerClass; 3] = [PeerClass::Validator, PeerClass::Observer, PeerClass::Unknown];
+}
+
+impl fmt::Display for PeerClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            PeerClass::Validator => "validator",
+            PeerClass::Observer => "observer",
+            PeerClass::Unknown => "unknown",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for PeerClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "validator" => Ok(PeerClass::Validator),
+            "observer" => Ok(PeerClass::Observer),
+            "unknown" => Ok(PeerClass::Unknown),
+            _ => Err(format!("{} is not a peer class. Use validator, observer or unknown", s)),
+        }
+    }
+}
+
+/// The bytes per second of the extension messages sent to each class of the peers.
+/// `None` doesn't limit the class.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct BandwidthLimits {
+    pub validator: Option<usize>,
+    pub observer: Option<usize>,
+    pub unknown: Option<usize>,
+}
+
+impl BandwidthLimits {
+    pub fn get(&self, class: PeerClass) -> Option<usize> {
+        match class {
+            PeerClass::Validator => self.validator,
+            PeerClass::Observer => self.observer,
+            PeerClass::Unknown => self.unknown,
+        }
+    }
+
+    pub fn set(&mut self, class: PeerClass, limit: Option<usize>) {
+        match class {
+            PeerClass::Validator => self.validator = limit,
+            PeerClass::Observer => self.observer = limit,
+            PeerClass::Unknown => self.unknown = limit,
+        }
+    }
+
+    /// Parses the lines of `<class> = <bytes per second>`, which is how the limits are displayed.
+    /// The classes that are not in the lines are not limited.
+    pub fn parse_lines(lines: &str) -> Result<Self, String> {
+        let mut limits = Self::default();
+        for line in lines.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let mut fields = line.splitn(2, '=').map(str::trim);
+            let class = fields.next().unwrap_or_default().parse::<PeerClass>()?;
+            let limit = fields
+                .next()
+                .and_then(|limit| limit.parse::<usize>().ok())
+                .ok_or_else(|| format!("{:?} is not a bandwidth limit", line))?;
+            limits.set(class, Some(limit));
+        }
+        Ok(limits)
+    }
+}
+
+impl fmt::Display for BandwidthLimits {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for class in PeerClass::ALL.iter() {
+            if let Some(limit) = self.get(*class) {
+                writeln!(f, "{} = {}", class, limit)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Holds up to one second of the rate. A message larger than the bucket is sent when the bucket is full, and the
+/// bucket goes into debt so that the rate is kept in the long run.
+struct TokenBucket {
+    rate: usize,
+    tokens: i64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: usize, now: Instant) -> Self {
+        Self {
+            rate,
+            tokens: rate as i64,
+            refilled_at: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        if now <= self.refilled_at {
+            return
+        }
+        let elapsed = now - self.refilled_at;
+        let nanos = u128::from(elapsed.as_secs()) * 1_000_000_000 + u128::from(elapsed.subsec_nanos());
+        let added = nanos * self.rate as u128 / 1_000_000_000;
+        if added == 0 {
+            // Wait until at least a byte is earned, not to lose the fraction.
+            return
+        }
+        self.tokens = (self.tokens + added.min(self.rate as u128) as i64).min(self.rate as i64);
+        self.refilled_at = now;
+    }
+
+    fn try_take(&mut self, size: usize, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens < size.min(self.rate) as i64 {
+            return false
+        }
+        self.tokens -= size as i64;
+        true
+    }
+}
+
+#[derive(Default)]
+struct ClassState {
+    bucket: Option<TokenBucket>,
+    sent: VecDeque<(Instant, usize)>,
+}
+
+impl ClassState {
+    fn try_take(&mut self, size: usize, now: Instant) -> bool {
+        let allowed = match &mut self.bucket {
+            Some(bucket) => bucket.try_take(size, now),
+            None => true,
+        };
+        if allowed {
+            self.sent.push_back((now, size));
+        }
+        allowed
+    }
+
+    fn sent_in_window(&mut self, now: Instant) -> usize {
+        while let Some((sent_at, _)) = self.sent.front() {
+            if now.duration_since(*sent_at) <= USAGE_WINDOW {
+                break
+            }
+            self.sent.pop_front();
+        }
+        self.sent.iter().map(|(_, size)| size).sum()
+    }
+}
+
+struct PeerState<T> {
+    class: PeerClass,
+    queue: VecDeque<(usize, T)>,
+    queued_bytes: usize,
+}
+
+impl<T> Default for PeerState<T> {
+    fn default() -> Self {
+        Self {
+            class: PeerClass::Unknown,
+            queue: VecDeque::new(),
+            queued_bytes: 0,
+        }
+    }
+}
+
+struct State<T> {
+    limits: BandwidthLimits,
+    classes: HashMap<PeerClass, ClassState>,
+    peers: HashMap<NodeId, PeerState<T>>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Admission<T> {
+    /// Write the message now.
+    Send(T),
+    /// The message waits for the bandwidth of the class.
+    Queued,
+    /// The queue of the peer is full.
+    Rejected,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ClassUsage {
+    pub class: PeerClass,
+    pub limit: Option<usize>,
+    /// The bytes sent in the last 10 seconds.
+    pub sent: usize,
+    pub queued: usize,
+}
+
+/// Shapes the extension messages with a token bucket per peer class.
+/// The handshakes and the other messages of the session are not throttled.
+pub struct Bandwidth<T> {
+    state: Mutex<State<T>>,
+}
+
+impl<T> Bandwidth<T> {
+    pub fn new(limits: BandwidthLimits, now: Instant) -> Self {
+        let classes = PeerClass::ALL
+            .iter()
+            .map(|class| {
+                (*class, ClassState {
+                    bucket: limits.get(*class).map(|rate| TokenBucket::new(rate, now)),
+                    sent: VecDeque::new(),
+                })
+            })
+            .collect();
+        Self {
+            state: Mutex::new(State {
+                limits,
+                classes,
+                peers: HashMap::new(),
+            }),
+        }
+    }
+
+    pub fn limits(&self) -> BandwidthLimits {
+        self.state.lock().limits
+    }
+
+    /// The queued messages are kept and sent under the new limits.
+    pub fn set_limits(&self, limits: BandwidthLimits, now: Instant) {
+        let mut state = self.state.lock();
+        for class in PeerClass::ALL.iter() {
+            if state.limits.get(*class) == limits.get(*class) {
+                continue
+            }
+            let class_state = state.classes.get_mut(class).expect("All classes are initialized");
+            class_state.bucket = limits.get(*class).map(|rate| TokenBucket::new(rate, now));
+        }
+        state.limits = limits;
+    }
+
+    pub fn set_class(&self, node: NodeId, class: PeerClass) {
+        self.state.lock().peers.entry(node).or_default().class = class;
+    }
+
+    pub fn class_of(&self, node: &NodeId) -> PeerClass {
+        self.state.lock().peers.get(node).map(|peer| peer.class).unwrap_or(PeerClass::Unknown)
+    }
+
+    pub fn admit(&self, node: NodeId, size: usize, message: T, now: Instant) -> Admission<T> {
+        let mut state = self.state.lock();
+        let State {
+            classes,
+            peers,
+            ..
+        } = &mut *state;
+        let peer = peers.entry(node).or_default();
+        // The messages to a peer are never reordered.
+        if peer.queue.is_empty() {
+            let class_state = classes.get_mut(&peer.class).expect("All classes are initialized");
+            if class_state.try_take(size, now) {
+                return Admission::Send(message)
+            }
+        } else if peer.queued_bytes + size > MAX_QUEUED_BYTES_PER_PEER {
+            return Admission::Rejected
+        }
+        peer.queue.push_back((size, message));
+        peer.queued_bytes += size;
+        Admission::Queued
+    }
+
+    /// Takes the queued messages that the bandwidth allows now, one message per peer in turn.
+    pub fn release(&self, now: Instant) -> Vec<(NodeId, T)> {
+        let mut state = self.state.lock();
+        let State {
+            classes,
+            peers,
+            ..
+        } = &mut *state;
+        let mut released = Vec::new();
+        loop {
+            let mut progressed = false;
+            for (node, peer) in peers.iter_mut() {
+                let size = match peer.queue.front() {
+                    Some((size, _)) => *size,
+                    None => continue,
+                };
+                let class_state = classes.get_mut(&peer.class).expect("All classes are initialized");
+                if !class_state.try_take(size, now) {
+                    continue
+                }
+                let (_, message) = peer.queue.pop_front().expect("The queue is not empty");
+                peer.queued_bytes -= size;
+                released.push((*node, message));
+                progressed = true;
+            }
+            if !progressed {
+                return released
+            }
+        }
+    }
+
+    pub fn has_queued(&self) -> bool {
+        self.state.lock().peers.values().any(|peer| !peer.queue.is_empty())
+    }
+
+    /// The extensions should hold back the messages to the congested peer.
+    pub fn is_congested(&self, node: &NodeId) -> bool {
+        self.state.lock().peers.get(node).map(|peer| peer.queued_bytes >= CONGESTION_THRESHOLD).unwrap_or(false)
+    }
+
+    /// Drops the queued messages of the disconnected peer.
+    pub fn remove(&self, node: &NodeId) {
+        self.state.lock().peers.remove(node);
+    }
+
+    pub fn usage(&self, now: Instant) -> Vec<ClassUsage> {
+        let mut state = self.state.lock();
+        let limits = state.limits;
+        let mut queued: HashMap<PeerClass, usize> = HashMap::new();
+        for peer in state.peers.values() {
+            *queued.entry(peer.class).or_default() += peer.queued_bytes;
+        }
+        PeerClass::ALL
+            .iter()
+            .map(|class| ClassUsage {
+                class: *class,
+                limit: limits.get(*class),
+                sent: state.classes.get_mut(class).expect("All classes are initialized").sent_in_window(now),
+                queued: queued.get(class).cloned().unwrap_or(0),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::SocketAddr;
+
+    fn node(port: u16) -> NodeId {
+        SocketAddr::v4(127, 0, 0, 1, port).into()
+    }
+
+    fn limits(validator: Option<usize>, observer: Option<usize>, unknown: Option<usize>) -> BandwidthLimits {
+        BandwidthLimits {
+            validator,
+            observer,
+            unknown,
+        }
+    }
+
+    fn usage_of(bandwidth: &Bandwidth<u32>, class: PeerClass, now: Instant) -> ClassUsage {
+        bandwidth.usage(now).into_iter().find(|usage| usage.class == class).unwrap()
+    }
+
+    #[test]
+    fn peer_class_names() {
+        for class in PeerClass::ALL.iter() {
+            assert_eq!(Ok(*class), class.to_string().parse());
+        }
+        assert!("proposer".parse::<PeerClass>().is_err());
+    }
+
+    #[test]
+    fn limits_are_parsed_from_their_display() {
+        for limits in &[limits(None, None, None), limits(Some(100), None, Some(0)), limits(Some(1), Some(2), Some(3))] {
+            assert_eq!(Ok(*limits), BandwidthLimits::parse_lines(&limits.to_string()));
+        }
+        assert_eq!(Ok(limits(None, Some(1024), None)), BandwidthLimits::parse_lines("\n  observer=1024  \n"));
+        assert!(BandwidthLimits::parse_lines("proposer = 1024").is_err());
+        assert!(BandwidthLimits::parse_lines("observer = -1").is_err());
+        assert!(BandwidthLimits::parse_lines("observer").is_err());
+    }
+
+    #[test]
+    fn unlimited_classes_are_not_throttled() {
+        let now = Instant::now();
+        let bandwidth = Bandwidth::new(BandwidthLimits::default(), now);
+        for i in 0..1000 {
+            assert_eq!(Admission::Send(i), bandwidth.admit(node(1), 1024 * 1024, i, now));
+        }
+        assert!(!bandwidth.has_queued());
+        assert_eq!(1000 * 1024 * 1024, usage_of(&bandwidth, PeerClass::Unknown, now).sent);
+    }
+
+    #[test]
+    fn messages_beyond_the_rate_wait_for_the_tokens() {
+        let start = Instant::now();
+        let bandwidth = Bandwidth::new(limits(None, None, Some(1000)), start);
+
+        // Ten 500-byte messages in an instant. Only a second of the rate goes out.
+        let mut sent = 0;
+        for i in 0..10 {
+            match bandwidth.admit(node(1), 500, i, start) {
+                Admission::Send(_) => sent += 1,
+                Admission::Queued => {}
+                Admission::Rejected => panic!(),
+            }
+        }
+        assert_eq!(2, sent);
+        assert!(bandwidth.has_queued());
+        assert!(bandwidth.release(start).is_empty());
+
+        // Half a second earns a message.
+        let released = bandwidth.release(start + Duration::from_millis(500));
+        assert_eq!(vec![(node(1), 2)], released);
+
+        // The rest goes out in order at the rate.
+        let mut released = Vec::new();
+        for tick in 1..=8 {
+            released.extend(bandwidth.release(start + Duration::from_millis(500 + tick * 500)));
+        }
+        assert_eq!((3..10).map(|i| (node(1), i)).collect::<Vec<_>>(), released);
+        assert!(!bandwidth.has_queued());
+
+        let usage = usage_of(&bandwidth, PeerClass::Unknown, start + Duration::from_secs(5));
+        assert_eq!(Some(1000), usage.limit);
+        assert_eq!(5000, usage.sent);
+        assert_eq!(0, usage.queued);
+    }
+
+    #[test]
+    fn shaped_rate_stays_within_the_limit() {
+        let start = Instant::now();
+        let rate = 10_000;
+        let bandwidth = Bandwidth::new(limits(None, Some(rate), None), start);
+        bandwidth.set_class(node(1), PeerClass::Observer);
+
+        // A producer tries to send 1KB every millisecond for ten seconds, a hundred times the limit.
+        let mut sent = 0;
+        for ms in 0..10_000 {
+            let now = start + Duration::from_millis(ms);
+            sent += bandwidth.release(now).len() * 1000;
+            if bandwidth.is_congested(&node(1)) {
+                continue
+            }
+            if let Admission::Send(_) = bandwidth.admit(node(1), 1000, 0, now) {
+                sent += 1000;
+            }
+        }
+        // The burst of the first second and the rate of the ten seconds.
+        assert!(sent <= rate * 11, "{} bytes are sent", sent);
+        assert!(sent >= rate * 9, "{} bytes are sent", sent);
+    }
+
+    #[test]
+    fn classes_are_throttled_separately() {
+        let now = Instant::now();
+        let bandwidth = Bandwidth::new(limits(None, Some(100), Some(100)), now);
+        bandwidth.set_class(node(1), PeerClass::Validator);
+        bandwidth.set_class(node(2), PeerClass::Observer);
+
+        assert_eq!(Admission::Send(1), bandwidth.admit(node(2), 100, 1, now));
+        assert_eq!(Admission::Queued, bandwidth.admit(node(2), 100, 2, now));
+        assert_eq!(Admission::Send(3), bandwidth.admit(node(1), 100_000, 3, now));
+        // The unknown class has its own bucket.
+        assert_eq!(Admission::Send(4), bandwidth.admit(node(3), 100, 4, now));
+
+        assert_eq!(PeerClass::Validator, bandwidth.class_of(&node(1)));
+        assert_eq!(PeerClass::Unknown, bandwidth.class_of(&node(3)));
+        assert_eq!(100, usage_of(&bandwidth, PeerClass::Observer, now).queued);
+        assert_eq!(0, usage_of(&bandwidth, PeerClass::Validator, now).queued);
+    }
+
+    #[test]
+    fn full_queue_rejects_and_signals_the_backpressure() {
+        let now = Instant::now();
+        let bandwidth = Bandwidth::new(limits(None, None, Some(1)), now);
+        let size = 256 * 1024;
+
+        assert_eq!(Admission::Send(0), bandwidth.admit(node(1), size, 0, now));
+        let mut queued = 0;
+        let mut congested_at = None;
+        for i in 1..100 {
+            match bandwidth.admit(node(1), size, i, now) {
+                Admission::Send(_) => panic!(),
+                Admission::Queued => queued += size,
+                Admission::Rejected => break,
+            }
+            if congested_at.is_none() && bandwidth.is_congested(&node(1)) {
+                congested_at = Some(queued);
+            }
+        }
+        assert_eq!(MAX_QUEUED_BYTES_PER_PEER, queued);
+        assert_eq!(Some(CONGESTION_THRESHOLD), congested_at);
+        assert!(!bandwidth.is_congested(&node(2)));
+
+        bandwidth.remove(&node(1));
+        assert!(!bandwidth.is_congested(&node(1)));
+        assert!(!bandwidth.has_queued());
+    }
+
+    #[test]
+    fn raising_the_limit_releases_the_queue() {
+        let now = Instant::now();
+        let bandwidth = Bandwidth::new(limits(None, None, Some(10)), now);
+        assert_eq!(Admission::Send(0), bandwidth.admit(node(1), 10, 0, now));
+        assert_eq!(Admission::Queued, bandwidth.admit(node(1), 10, 1, now));
+        assert_eq!(Admission::Queued, bandwidth.admit(node(1), 10, 2, now));
+
+        bandwidth.set_limits(BandwidthLimits::default(), now);
+        assert_eq!(BandwidthLimits::default(), bandwidth.limits());
+        assert_eq!(vec![(node(1), 1), (node(1), 2)], bandwidth.release(now));
+    }
+}
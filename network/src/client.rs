@@ -20,12 +20,14 @@ use std::thread::{Builder, JoinHandle};
 use std::time::Duration;
 
 use cio::IoChannel;
+use cmetrics::{ExtensionTraffic, METRICS};
 use crossbeam_channel as crossbeam;
 use ctimer::{TimeoutHandler, TimerApi, TimerLoop, TimerToken};
 use parking_lot::{Mutex, RwLock};
 use primitives::Bytes;
 
-use crate::p2p::Message as P2pMessage;
+use crate::bandwidth::{Bandwidth, PeerClass};
+use crate::p2p::{Message as P2pMessage, ThrottledMessage};
 use crate::{Api, IntoSocketAddr, NetworkExtension, NetworkExtensionResult, NodeId};
 
 struct ClientApi {
@@ -33,6 +35,8 @@ struct ClientApi {
     timer: TimerApi,
     name: &'static str,
     need_encryption: bool,
+    traffic: Arc<ExtensionTraffic>,
+    bandwidth: Arc<Bandwidth<ThrottledMessage>>,
 }
 
 impl Api for ClientApi {
@@ -57,6 +61,7 @@ impl Api for ClientApi {
             );
         } else {
             cdebug!(NETAPI, "`{}` sends {} bytes to {}", extension_name, bytes, id.into_addr());
+            self.traffic.bytes_out.add(bytes);
         }
     }
 
@@ -74,6 +79,24 @@ impl Api for ClientApi {
         self.timer.cancel(token)?;
         Ok(())
     }
+
+    fn is_congested(&self, node: &NodeId) -> bool {
+        self.bandwidth.is_congested(node)
+    }
+
+    fn set_peer_class(&self, node: &NodeId, class: PeerClass) {
+        self.bandwidth.set_class(*node, class);
+    }
+
+    fn bandwidth_limit(&self, node: &NodeId) -> Option<usize> {
+        self.bandwidth.limits().get(self.bandwidth.class_of(node))
+    }
+
+    fn report_misbehavior(&self, node: &NodeId) {
+        if let Err(err) = self.p2p_channel.send(P2pMessage::ReportMisbehavior(*node)) {
+            cerror!(NETAPI, "`{}` cannot report the misbehavior of {}: {:?}", self.name, node.into_addr(), err);
+        }
+    }
 }
 
 struct Extension {
@@ -82,6 +105,8 @@ struct Extension {
     sender: Mutex<crossbeam::Sender<ExtensionMessage>>,
     quit: Mutex<crossbeam::Sender<()>>,
     join: Mutex<Option<JoinHandle<()>>>,
+    traffic: Arc<ExtensionTraffic>,
+    max_message_size: usize,
 }
 
 impl TimeoutHandler for Extension {
@@ -105,6 +130,8 @@ pub struct Client {
     extensions: RwLock<HashMap<&'static str, Arc<Extension>>>,
     p2p_channel: IoChannel<P2pMessage>,
     timer_loop: TimerLoop,
+    message_size_limits: HashMap<String, usize>,
+    bandwidth: Arc<Bandwidth<ThrottledMessage>>,
 }
 
 impl Client {
@@ -118,12 +145,16 @@ impl Client {
         let timer = self.timer_loop.new_timer_with_name(name);
         let cloned_timer = timer.clone();
         let p2p_channel = self.p2p_channel.clone();
+        let bandwidth = Arc::clone(&self.bandwidth);
         let (channel, rx) = crossbeam::unbounded();
         let sender = channel.clone().into();
 
         let (quit_sender, quit_receiver) = crossbeam::bounded(1);
         let (init_sender, init_receiver) = crossbeam::bounded(1);
         let (event_sender, event_receiver) = crossbeam::unbounded();
+        let traffic = METRICS.register_extension(name);
+        let api_traffic = Arc::clone(&traffic);
+        let max_message_size = self.message_size_limits.get(name).cloned().unwrap_or_else(T::max_message_size);
 
         let join = Some(
             Builder::new()
@@ -135,6 +166,8 @@ impl Client {
                         need_encryption: T::need_encryption(),
                         p2p_channel,
                         timer,
+                        traffic: api_traffic,
+                        bandwidth,
                     };
                     let mut extension = factory(Box::from(api));
 
@@ -211,6 +244,8 @@ impl Client {
             sender,
             quit: quit_sender.into(),
             join,
+            traffic,
+            max_message_size,
         });
         cloned_timer.set_handler(Arc::downgrade(&extension));
         if extensions.insert(name, extension).is_some() {
@@ -220,11 +255,19 @@ impl Client {
         event_sender
     }
 
-    pub fn new(p2p_channel: IoChannel<P2pMessage>, timer_loop: TimerLoop) -> Arc<Self> {
+    /// `message_size_limits` overrides the message size limits that the extensions declare.
+    pub fn new(
+        p2p_channel: IoChannel<P2pMessage>,
+        timer_loop: TimerLoop,
+        message_size_limits: HashMap<String, usize>,
+        bandwidth: Arc<Bandwidth<ThrottledMessage>>,
+    ) -> Arc<Self> {
         Arc::new(Self {
             extensions: RwLock::new(HashMap::new()),
             p2p_channel,
             timer_loop,
+            message_size_limits,
+            bandwidth,
         })
     }
 
@@ -233,6 +276,11 @@ impl Client {
         extensions.iter().map(|(name, extension)| (name.to_string(), extension.versions.clone())).collect()
     }
 
+    pub fn message_size_limits(&self) -> HashMap<String, usize> {
+        let extensions = self.extensions.read();
+        extensions.iter().map(|(name, extension)| (name.to_string(), extension.max_message_size)).collect()
+    }
+
     pub fn on_node_removed(&self, id: &NodeId) {
         let extensions = self.extensions.read();
         for (name, extension) in extensions.iter() {
@@ -253,16 +301,28 @@ impl Client {
         }
     }
 
-    pub fn on_message(&self, name: &str, id: &NodeId, data: Arc<Bytes>) {
+    /// Returns an error without delivering the message if it exceeds the size limit of the extension.
+    pub fn on_message(&self, name: &str, id: &NodeId, data: Arc<Bytes>) -> Result<(), String> {
         let extensions = self.extensions.read();
         if let Some(extension) = extensions.get(name) {
+            if data.len() > extension.max_message_size {
+                return Err(format!(
+                    "`{}` message from {} is {} bytes, which exceeds the limit {}",
+                    name,
+                    id.into_addr(),
+                    data.len(),
+                    extension.max_message_size
+                ))
+            }
             cdebug!(NETAPI, "`{}` receives {} bytes from {}", name, data.len(), id.into_addr());
+            extension.traffic.bytes_in.add(data.len());
             if let Err(err) = extension.sender.lock().send(ExtensionMessage::Message(*id, data)) {
                 cwarn!(NETAPI, "{} cannot message {}: {:?}", name, id, err);
             }
         } else {
             cwarn!(NETAPI, "{} doesn't exist.", name);
         }
+        Ok(())
     }
 }
 
@@ -275,12 +335,19 @@ enum ExtensionMessage {
 
 #[cfg(test)]
 mod tests {
+    use std::thread;
+
     use cio::IoService;
     use never_type::Never;
 
     use super::*;
+    use crate::bandwidth::BandwidthLimits;
     use crate::SocketAddr;
 
+    fn unlimited_bandwidth() -> Arc<Bandwidth<ThrottledMessage>> {
+        Arc::new(Bandwidth::new(BandwidthLimits::default(), std::time::Instant::now()))
+    }
+
     #[allow(dead_code)]
     struct TestApi;
 
@@ -369,7 +436,7 @@ mod tests {
         let p2p_service = IoService::start("P2P").unwrap();
         let timer_loop = TimerLoop::new(2);
 
-        let client = Client::new(p2p_service.channel(), timer_loop);
+        let client = Client::new(p2p_service.channel(), timer_loop, HashMap::new(), unlimited_bandwidth());
 
         let node_id1 = SocketAddr::v4(127, 0, 0, 1, 8081).into();
         let node_id5 = SocketAddr::v4(127, 0, 0, 1, 8085).into();
@@ -379,11 +446,85 @@ mod tests {
 
         // FIXME: The callback is asynchronous, find a way to test it.
 
-        client.on_message(&"e1".to_string(), &node_id1, Default::default());
+        client.on_message(&"e1".to_string(), &node_id1, Default::default()).unwrap();
+
+        client.on_message(&"e2".to_string(), &node_id1, Default::default()).unwrap();
+
+        client.on_message(&"e2".to_string(), &node_id5, Default::default()).unwrap();
+        client.on_message(&"e2".to_string(), &node_id1, Default::default()).unwrap();
+    }
+
+    struct LimitedExtension {
+        received: Arc<Mutex<Vec<usize>>>,
+    }
+
+    impl NetworkExtension<Never> for LimitedExtension {
+        fn name() -> &'static str {
+            "limited"
+        }
+
+        fn need_encryption() -> bool {
+            false
+        }
+
+        fn versions() -> &'static [u64] {
+            const VERSIONS: &[u64] = &[0];
+            &VERSIONS
+        }
+
+        fn max_message_size() -> usize {
+            1024
+        }
+
+        fn on_message(&mut self, _id: &NodeId, message: &[u8]) {
+            self.received.lock().push(message.len());
+        }
+    }
+
+    #[test]
+    fn too_large_message_is_not_delivered() {
+        let p2p_service = IoService::start("P2P").unwrap();
+        let timer_loop = TimerLoop::new(2);
+
+        let client = Client::new(p2p_service.channel(), timer_loop, HashMap::new(), unlimited_bandwidth());
+        let node_id = SocketAddr::v4(127, 0, 0, 1, 8081).into();
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let _e = client.register_extension({
+            let received = Arc::clone(&received);
+            move |_| LimitedExtension {
+                received,
+            }
+        });
+
+        assert!(client.on_message("limited", &node_id, Arc::new(vec![0; 2048])).is_err());
+        assert_eq!(Ok(()), client.on_message("limited", &node_id, Arc::new(vec![0; 1024])));
+
+        // The messages are handled in order, so the large one would be seen before the small one.
+        for _ in 0..100 {
+            if !received.lock().is_empty() {
+                break
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(vec![1024], *received.lock());
+    }
 
-        client.on_message(&"e2".to_string(), &node_id1, Default::default());
+    #[test]
+    fn configured_limit_overrides_the_declared_one() {
+        let p2p_service = IoService::start("P2P").unwrap();
+        let timer_loop = TimerLoop::new(2);
+
+        let mut limits = HashMap::new();
+        limits.insert("limited".to_string(), 4096);
+        let client = Client::new(p2p_service.channel(), timer_loop, limits, unlimited_bandwidth());
+        let node_id = SocketAddr::v4(127, 0, 0, 1, 8081).into();
+
+        let _e = client.register_extension(|_| LimitedExtension {
+            received: Default::default(),
+        });
 
-        client.on_message(&"e2".to_string(), &node_id5, Default::default());
-        client.on_message(&"e2".to_string(), &node_id1, Default::default());
+        assert_eq!(Some(&4096), client.message_size_limits().get("limited"));
+        assert_eq!(Ok(()), client.on_message("limited", &node_id, Arc::new(vec![0; 2048])));
     }
 }
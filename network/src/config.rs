@@ -14,7 +14,13 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::bandwidth::BandwidthLimits;
 use crate::filters::FilterEntry;
+use crate::nat::NatType;
+use crate::session::RekeyPolicy;
 use crate::SocketAddr;
 
 pub struct Config {
@@ -25,4 +31,11 @@ pub struct Config {
     pub max_peers: usize,
     pub whitelist: Vec<FilterEntry>,
     pub blacklist: Vec<FilterEntry>,
+    pub nat: Option<NatType>,
+    pub message_size_limits: HashMap<String, usize>,
+    pub rekey_policy: RekeyPolicy,
+    pub connection_log_size: usize,
+    pub bandwidth_limits: BandwidthLimits,
+    /// The file that the limits changed at runtime are saved to.
+    pub bandwidth_limits_path: Option<PathBuf>,
 }
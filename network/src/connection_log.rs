@@ -0,0 +1,309 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use parking_lot::Mutex;
+use primitives::H128;
+
+use crate::SocketAddr;
+
+pub const DEFAULT_CONNECTION_LOG_SIZE: usize = 4096;
+
+/// Why a handshake with an inbound peer failed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HandshakeError {
+    /// The peer is on another network.
+    InvalidNetworkId,
+    /// The peer didn't send the sync message in time.
+    Timeout,
+    /// The key exchange was refused by the routing table.
+    KeyExchange,
+    /// The peer sent a message that cannot be read.
+    MalformedMessage,
+}
+
+impl fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            HandshakeError::InvalidNetworkId => "invalidNetworkId",
+            HandshakeError::Timeout => "timeout",
+            HandshakeError::KeyExchange => "keyExchange",
+            HandshakeError::MalformedMessage => "malformedMessage",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConnectionOutcome {
+    Established,
+    /// Refused by the whitelist or the blacklist.
+    Filtered,
+    /// Dropped because the node has too many connections.
+    RateLimited,
+    /// Dropped because too many connections are still in the handshake.
+    TooManyIncoming,
+    HandshakeFailed(HandshakeError),
+}
+
+impl ConnectionOutcome {
+    pub fn kind(&self) -> ConnectionOutcomeKind {
+        match self {
+            ConnectionOutcome::Established => ConnectionOutcomeKind::Established,
+            ConnectionOutcome::Filtered => ConnectionOutcomeKind::Filtered,
+            ConnectionOutcome::RateLimited => ConnectionOutcomeKind::RateLimited,
+            ConnectionOutcome::TooManyIncoming => ConnectionOutcomeKind::TooManyIncoming,
+            ConnectionOutcome::HandshakeFailed(_) => ConnectionOutcomeKind::HandshakeFailed,
+        }
+    }
+}
+
+/// The outcomes without the details, used to filter and to count the attempts.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConnectionOutcomeKind {
+    Established,
+    Filtered,
+    RateLimited,
+    TooManyIncoming,
+    HandshakeFailed,
+}
+
+const OUTCOME_KINDS: [ConnectionOutcomeKind; 5] = [
+    ConnectionOutcomeKind::Established,
+    ConnectionOutcomeKind::Filtered,
+    ConnectionOutcomeKind::RateLimited,
+    ConnectionOutcomeKind::TooManyIncoming,
+    ConnectionOutcomeKind::HandshakeFailed,
+];
+
+impl ConnectionOutcomeKind {
+    fn index(self) -> usize {
+        match self {
+            ConnectionOutcomeKind::Established => 0,
+            ConnectionOutcomeKind::Filtered => 1,
+            ConnectionOutcomeKind::RateLimited => 2,
+            ConnectionOutcomeKind::TooManyIncoming => 3,
+            ConnectionOutcomeKind::HandshakeFailed => 4,
+        }
+    }
+}
+
+impl fmt::Display for ConnectionOutcomeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ConnectionOutcomeKind::Established => "established",
+            ConnectionOutcomeKind::Filtered => "filtered",
+            ConnectionOutcomeKind::RateLimited => "rateLimited",
+            ConnectionOutcomeKind::TooManyIncoming => "tooManyIncoming",
+            ConnectionOutcomeKind::HandshakeFailed => "handshakeFailed",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for ConnectionOutcomeKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OUTCOME_KINDS
+            .iter()
+            .find(|kind| kind.to_string() == s)
+            .cloned()
+            .ok_or_else(|| format!("Invalid connection outcome: {}", s))
+    }
+}
+
+/// An inbound connection attempt.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConnectionAttempt {
+    /// The unix time in seconds.
+    pub timestamp: u64,
+    pub address: SocketAddr,
+    pub outcome: ConnectionOutcome,
+    /// The fingerprint of the session key. It exists only if the key exchange succeeded.
+    pub key_fingerprint: Option<H128>,
+}
+
+/// The recent inbound connection attempts.
+///
+/// It's a ring of the fixed size. Recording an attempt takes only the lock of the slot it overwrites,
+/// so the accept path doesn't wait for the readers of the other slots.
+pub struct ConnectionLog {
+    slots: Vec<Mutex<Option<(usize, ConnectionAttempt)>>>,
+    next: AtomicUsize,
+    counts: [AtomicUsize; 5],
+}
+
+impl ConnectionLog {
+    pub fn new(size: usize) -> Self {
+        Self {
+            slots: (0..size).map(|_| Mutex::new(None)).collect(),
+            next: AtomicUsize::new(0),
+            counts: Default::default(),
+        }
+    }
+
+    pub fn record(&self, attempt: ConnectionAttempt) {
+        self.counts[attempt.outcome.kind().index()].fetch_add(1, Ordering::Relaxed);
+        if self.slots.is_empty() {
+            return
+        }
+        let sequence = self.next.fetch_add(1, Ordering::AcqRel);
+        *self.slots[sequence % self.slots.len()].lock() = Some((sequence, attempt));
+    }
+
+    /// Returns at most `limit` attempts, the most recent first.
+    pub fn recent(&self, limit: usize, kind: Option<ConnectionOutcomeKind>) -> Vec<ConnectionAttempt> {
+        let end = self.next.load(Ordering::Acquire);
+        let start = end.saturating_sub(self.slots.len());
+        let mut attempts = Vec::with_capacity(limit.min(end - start));
+        for sequence in (start..end).rev() {
+            if attempts.len() >= limit {
+                break
+            }
+            let slot = self.slots[sequence % self.slots.len()].lock();
+            match &*slot {
+                // The slot is overwritten by a newer attempt after reading `next`.
+                Some((recorded, _)) if *recorded != sequence => continue,
+                Some((_, attempt)) if kind.map_or(true, |kind| attempt.outcome.kind() == kind) => {
+                    attempts.push(attempt.clone())
+                }
+                _ => {}
+            }
+        }
+        attempts
+    }
+
+    /// The number of the attempts per outcome since the node started, including the ones dropped from the ring.
+    pub fn counts(&self) -> Vec<(ConnectionOutcomeKind, usize)> {
+        OUTCOME_KINDS.iter().map(|kind| (*kind, self.counts[kind.index()].load(Ordering::Relaxed))).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attempt(port: u16, outcome: ConnectionOutcome) -> ConnectionAttempt {
+        ConnectionAttempt {
+            timestamp: 1_565_000_000 + u64::from(port),
+            address: SocketAddr::v4(127, 0, 0, 1, port),
+            outcome,
+            key_fingerprint: if outcome == ConnectionOutcome::Established {
+                Some(H128::random())
+            } else {
+                None
+            },
+        }
+    }
+
+    fn mixed_attempts() -> Vec<ConnectionAttempt> {
+        vec![
+            attempt(1, ConnectionOutcome::Established),
+            attempt(2, ConnectionOutcome::Filtered),
+            attempt(3, ConnectionOutcome::RateLimited),
+            attempt(4, ConnectionOutcome::HandshakeFailed(HandshakeError::InvalidNetworkId)),
+            attempt(5, ConnectionOutcome::Established),
+            attempt(6, ConnectionOutcome::HandshakeFailed(HandshakeError::Timeout)),
+            attempt(7, ConnectionOutcome::Filtered),
+            attempt(8, ConnectionOutcome::TooManyIncoming),
+        ]
+    }
+
+    #[test]
+    fn most_recent_attempts_come_first() {
+        let log = ConnectionLog::new(16);
+        let attempts = mixed_attempts();
+        for attempt in &attempts {
+            log.record(attempt.clone());
+        }
+
+        let recent = log.recent(3, None);
+        assert_eq!(recent, attempts.iter().rev().take(3).cloned().collect::<Vec<_>>());
+        assert_eq!(attempts.len(), log.recent(100, None).len());
+    }
+
+    #[test]
+    fn filter_by_outcome() {
+        let log = ConnectionLog::new(16);
+        for attempt in mixed_attempts() {
+            log.record(attempt);
+        }
+
+        let established = log.recent(100, Some(ConnectionOutcomeKind::Established));
+        assert_eq!(vec![5, 1], established.iter().map(|attempt| attempt.address.port()).collect::<Vec<_>>());
+        assert!(established.iter().all(|attempt| attempt.key_fingerprint.is_some()));
+
+        let failed = log.recent(100, Some(ConnectionOutcomeKind::HandshakeFailed));
+        assert_eq!(
+            vec![
+                ConnectionOutcome::HandshakeFailed(HandshakeError::Timeout),
+                ConnectionOutcome::HandshakeFailed(HandshakeError::InvalidNetworkId)
+            ],
+            failed.iter().map(|attempt| attempt.outcome).collect::<Vec<_>>()
+        );
+
+        let filtered = log.recent(1, Some(ConnectionOutcomeKind::Filtered));
+        assert_eq!(vec![7], filtered.iter().map(|attempt| attempt.address.port()).collect::<Vec<_>>());
+
+        let rate_limited = log.recent(100, Some(ConnectionOutcomeKind::RateLimited));
+        assert_eq!(vec![3], rate_limited.iter().map(|attempt| attempt.address.port()).collect::<Vec<_>>());
+        let too_many_incoming = log.recent(100, Some(ConnectionOutcomeKind::TooManyIncoming));
+        assert_eq!(vec![8], too_many_incoming.iter().map(|attempt| attempt.address.port()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn old_attempts_are_dropped_but_counted() {
+        let log = ConnectionLog::new(4);
+        for port in 0..10 {
+            log.record(attempt(port, ConnectionOutcome::RateLimited));
+        }
+        log.record(attempt(10, ConnectionOutcome::Established));
+
+        let recent = log.recent(100, None);
+        assert_eq!(vec![10, 9, 8, 7], recent.iter().map(|attempt| attempt.address.port()).collect::<Vec<_>>());
+        assert_eq!(
+            vec![
+                (ConnectionOutcomeKind::Established, 1),
+                (ConnectionOutcomeKind::Filtered, 0),
+                (ConnectionOutcomeKind::RateLimited, 10),
+                (ConnectionOutcomeKind::TooManyIncoming, 0),
+                (ConnectionOutcomeKind::HandshakeFailed, 0),
+            ],
+            log.counts()
+        );
+    }
+
+    #[test]
+    fn zero_sized_log_only_counts() {
+        let log = ConnectionLog::new(0);
+        log.record(attempt(1, ConnectionOutcome::Filtered));
+        assert_eq!(Vec::<ConnectionAttempt>::new(), log.recent(10, None));
+        assert_eq!((ConnectionOutcomeKind::Filtered, 1), log.counts()[1]);
+    }
+
+    #[test]
+    fn outcome_kind_from_str() {
+        for kind in &OUTCOME_KINDS {
+            assert_eq!(Ok(*kind), kind.to_string().parse());
+        }
+        assert!("unknown".parse::<ConnectionOutcomeKind>().is_err());
+    }
+}
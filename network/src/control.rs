@@ -17,12 +17,18 @@
 use std::collections::HashMap;
 use std::net::IpAddr;
 use std::result::Result;
+use std::time::Duration;
 
 use cidr::IpCidr;
 use ckey::Public;
 
 use crate::addr::SocketAddr;
-use crate::filters::FilterEntry;
+use crate::bandwidth::BandwidthLimits;
+use crate::connection_log::{ConnectionAttempt, ConnectionOutcomeKind};
+use crate::discovered_peers::DiscoveredPeer;
+use crate::filters::{FilterDecision, FilterEntry};
+use crate::nat::NatStatus;
+use crate::peer_exchange::Stats as PeerExchangeStats;
 
 pub trait Control: Send + Sync {
     fn local_key_for(&self, address: IpAddr, port: u16) -> Result<Public, Error>;
@@ -33,12 +39,20 @@ pub trait Control: Send + Sync {
     fn get_port(&self) -> Result<u16, Error>;
     fn get_peer_count(&self) -> Result<usize, Error>;
     fn established_peers(&self) -> Result<Vec<SocketAddr>, Error>;
+    fn peer_sessions(&self) -> Result<Vec<PeerSession>, Error>;
 
     fn add_to_whitelist(&self, addr: IpCidr, tag: Option<String>) -> Result<(), Error>;
     fn remove_from_whitelist(&self, addr: &IpCidr) -> Result<(), Error>;
 
     fn add_to_blacklist(&self, addr: IpCidr, tag: Option<String>) -> Result<(), Error>;
     fn remove_from_blacklist(&self, addr: &IpCidr) -> Result<(), Error>;
+    /// Swaps all the rules of the blacklist at once. The rules with the durations expire after them.
+    fn replace_blacklist(&self, entries: Vec<(FilterEntry, Option<Duration>)>) -> Result<(), Error>;
+
+    /// Removes the rules of the tag from both lists, and returns the number of the removed rules.
+    fn remove_by_tag(&self, tag: &str) -> Result<usize, Error>;
+    /// Returns the rules of the tag in the whitelist and the blacklist.
+    fn list_by_tag(&self, tag: &str) -> Result<(Vec<IpCidr>, Vec<IpCidr>), Error>;
 
     fn enable_whitelist(&self) -> Result<(), Error>;
     fn disable_whitelist(&self) -> Result<(), Error>;
@@ -48,8 +62,43 @@ pub trait Control: Send + Sync {
 
     fn get_whitelist(&self) -> Result<(Vec<FilterEntry>, bool), Error>;
     fn get_blacklist(&self) -> Result<(Vec<FilterEntry>, bool), Error>;
+    /// Tells whether the address would be allowed to connect now, and which rules decide it.
+    fn test_filter(&self, addr: &IpAddr) -> Result<FilterDecision, Error>;
 
     fn recent_network_usage(&self) -> Result<HashMap<String, usize>, Error>;
+    /// Returns the most recent inbound connection attempts first.
+    fn connection_log(
+        &self,
+        limit: usize,
+        outcome: Option<ConnectionOutcomeKind>,
+    ) -> Result<Vec<ConnectionAttempt>, Error>;
+
+    fn nat_status(&self) -> Result<NatStatus, Error>;
+    fn refresh_nat_mapping(&self) -> Result<NatStatus, Error>;
+
+    fn extension_message_size_limits(&self) -> Result<HashMap<String, usize>, Error>;
+
+    fn bandwidth_limits(&self) -> Result<BandwidthLimits, Error>;
+    /// The limits are saved, and they replace the configured ones when the node restarts.
+    fn set_bandwidth_limits(&self, limits: BandwidthLimits) -> Result<(), Error>;
+
+    fn discovered_peers(&self) -> Result<Vec<DiscoveredPeer>, Error>;
+    /// Returns false if the peer is banned.
+    fn add_seed_peer(&self, addr: SocketAddr) -> Result<bool, Error>;
+
+    /// Asks the established peer for its recently connected peers without waiting for the interval.
+    fn request_peer_exchange(&self, addr: SocketAddr) -> Result<(), Error>;
+    fn peer_exchange_stats(&self) -> Result<PeerExchangeStats, Error>;
+}
+
+/// The session with an established peer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PeerSession {
+    pub address: SocketAddr,
+    /// True if the peer initiated the connection.
+    pub inbound: bool,
+    /// The number of the times that the session key was replaced.
+    pub rekeys: u64,
 }
 
 #[derive(Clone, Debug)]
@@ -0,0 +1,214 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+
+use crate::{NodeId, SocketAddr};
+
+/// A peer that fails this many times in a row is removed from the table.
+pub const MAX_FAILURES: u32 = 8;
+/// A peer that hasn't been seen for this many seconds is stale.
+pub const STALE_SECONDS: u64 = 24 * 60 * 60;
+
+#[derive(Clone, Debug, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct DiscoveredPeer {
+    pub node_id: NodeId,
+    pub address: SocketAddr,
+    /// The unix time in seconds when the peer answered last. It's 0 if the peer has never answered.
+    pub last_seen: u64,
+    /// The number of the consecutive failures since the peer answered last.
+    pub failures: u32,
+}
+
+impl DiscoveredPeer {
+    fn new(address: SocketAddr) -> Self {
+        Self {
+            node_id: address.into(),
+            address,
+            last_seen: 0,
+            failures: 0,
+        }
+    }
+
+    fn is_stale(&self, now: u64) -> bool {
+        self.last_seen.saturating_add(STALE_SECONDS) < now
+    }
+}
+
+/// The peers that the discovery learned, kept across restarts.
+///
+/// The time is given by the caller, so the table doesn't read the clock by itself.
+#[derive(Default)]
+pub struct DiscoveredPeers {
+    peers: HashMap<SocketAddr, DiscoveredPeer>,
+}
+
+impl DiscoveredPeers {
+    /// Restores the saved peers. The stale peers are demoted, so they decay out sooner unless they answer.
+    pub fn restore(peers: Vec<DiscoveredPeer>, now: u64) -> Self {
+        let peers = peers
+            .into_iter()
+            .map(|mut peer| {
+                if peer.is_stale(now) {
+                    peer.failures = peer.failures.max(MAX_FAILURES / 2);
+                }
+                (peer.address, peer)
+            })
+            .collect();
+        Self {
+            peers,
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<DiscoveredPeer> {
+        let mut peers: Vec<_> = self.peers.values().cloned().collect();
+        peers.sort_unstable_by_key(|peer| peer.address);
+        peers
+    }
+
+    /// Adds the peer if it's unknown. The known peer keeps its record.
+    pub fn learn(&mut self, address: SocketAddr) {
+        self.peers.entry(address).or_insert_with(|| DiscoveredPeer::new(address));
+    }
+
+    pub fn seen(&mut self, address: SocketAddr, now: u64) {
+        let peer = self.peers.entry(address).or_insert_with(|| DiscoveredPeer::new(address));
+        peer.last_seen = now;
+        peer.failures = 0;
+    }
+
+    /// Returns true if the peer is removed because of this failure.
+    pub fn fail(&mut self, address: &SocketAddr) -> bool {
+        let failures = match self.peers.get_mut(address) {
+            Some(peer) => {
+                peer.failures += 1;
+                peer.failures
+            }
+            None => return false,
+        };
+        if failures < MAX_FAILURES {
+            return false
+        }
+        self.peers.remove(address);
+        true
+    }
+
+    pub fn stale_addresses(&self, now: u64) -> Vec<SocketAddr> {
+        self.peers.values().filter(|peer| peer.is_stale(now)).map(|peer| peer.address).collect()
+    }
+
+    pub fn remove(&mut self, address: &SocketAddr) {
+        self.peers.remove(address);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rlp::{decode_list, encode_list};
+
+    use super::*;
+
+    const NOW: u64 = 1_565_000_000;
+
+    fn address(port: u16) -> SocketAddr {
+        SocketAddr::v4(127, 0, 0, 1, port)
+    }
+
+    #[test]
+    fn discovered_peers_survive_serialization() {
+        let mut table = DiscoveredPeers::default();
+        table.seen(address(3485), NOW);
+        table.learn(address(3486));
+        table.seen(address(3487), NOW - 10);
+        table.fail(&address(3487));
+
+        let peers = table.to_vec();
+        let encoded = encode_list(&peers);
+        let decoded: Vec<DiscoveredPeer> = decode_list(&encoded);
+        assert_eq!(peers, decoded);
+    }
+
+    #[test]
+    fn unresponsive_peer_decays_out() {
+        let mut table = DiscoveredPeers::default();
+        table.seen(address(3485), NOW);
+
+        for _ in 1..MAX_FAILURES {
+            assert!(!table.fail(&address(3485)));
+        }
+        assert!(table.fail(&address(3485)));
+        assert_eq!(Vec::<DiscoveredPeer>::new(), table.to_vec());
+    }
+
+    #[test]
+    fn answer_resets_the_failures() {
+        let mut table = DiscoveredPeers::default();
+        table.seen(address(3485), NOW);
+        for _ in 1..MAX_FAILURES {
+            assert!(!table.fail(&address(3485)));
+        }
+
+        table.seen(address(3485), NOW + 60);
+        assert_eq!(0, table.to_vec()[0].failures);
+        assert!(!table.fail(&address(3485)));
+    }
+
+    #[test]
+    fn learning_a_known_peer_keeps_its_record() {
+        let mut table = DiscoveredPeers::default();
+        table.seen(address(3485), NOW);
+        table.fail(&address(3485));
+
+        table.learn(address(3485));
+        let peer = &table.to_vec()[0];
+        assert_eq!(NOW, peer.last_seen);
+        assert_eq!(1, peer.failures);
+    }
+
+    #[test]
+    fn stale_peers_are_demoted_on_restore() {
+        let mut table = DiscoveredPeers::default();
+        table.seen(address(3485), NOW);
+        table.learn(address(3486));
+
+        let later = NOW + STALE_SECONDS + 1;
+        let restored = DiscoveredPeers::restore(table.to_vec(), later);
+        let peers = restored.to_vec();
+        assert_eq!(MAX_FAILURES / 2, peers[0].failures, "Not seen for a day");
+        assert_eq!(MAX_FAILURES / 2, peers[1].failures, "Never seen");
+        assert_eq!(vec![address(3485), address(3486)], {
+            let mut stale = restored.stale_addresses(later);
+            stale.sort_unstable();
+            stale
+        });
+
+        let restored = DiscoveredPeers::restore(table.to_vec(), NOW + 60);
+        assert!(restored.to_vec().iter().all(|peer| peer.failures == 0));
+        assert_eq!(vec![address(3486)], restored.stale_addresses(NOW + 60));
+    }
+
+    #[test]
+    fn stale_peers_decay_out_in_fewer_failures() {
+        let mut table = DiscoveredPeers::default();
+        table.seen(address(3485), NOW);
+
+        let mut restored = DiscoveredPeers::restore(table.to_vec(), NOW + STALE_SECONDS + 1);
+        for _ in MAX_FAILURES / 2 + 1..MAX_FAILURES {
+            assert!(!restored.fail(&address(3485)));
+        }
+        assert!(restored.fail(&address(3485)));
+    }
+}
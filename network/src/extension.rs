@@ -23,6 +23,7 @@ use cio::IoError;
 use ctimer::{TimerScheduleError, TimerToken};
 use primitives::Bytes;
 
+use crate::bandwidth::PeerClass;
 use crate::NodeId;
 
 #[derive(Debug)]
@@ -48,18 +49,39 @@ impl From<TimerScheduleError> for Error {
 
 pub type Result<T> = result::Result<T, Error>;
 
+/// The size limit of a message for the extensions that don't declare their own.
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+
 pub trait Api {
     fn send(&self, node: &NodeId, message: Arc<Bytes>);
 
     fn set_timer(&self, timer: TimerToken, d: Duration) -> Result<()>;
     fn set_timer_once(&self, timer: TimerToken, d: Duration) -> Result<()>;
     fn clear_timer(&self, timer: TimerToken) -> Result<()>;
+
+    /// True if too many messages to the node wait for the bandwidth.
+    /// The extension should hold back the messages that can be sent later.
+    fn is_congested(&self, _node: &NodeId) -> bool {
+        false
+    }
+    /// The bandwidth of the node is limited by the class. The node is `PeerClass::Unknown` until it's set.
+    fn set_peer_class(&self, _node: &NodeId, _class: PeerClass) {}
+    /// The bytes per second that the class of the node is limited to. None if it's not limited.
+    fn bandwidth_limit(&self, _node: &NodeId) -> Option<usize> {
+        None
+    }
+    /// The node is banned when it misbehaves too many times.
+    fn report_misbehavior(&self, _node: &NodeId) {}
 }
 
 pub trait Extension<Event: Send> {
     fn name() -> &'static str;
     fn need_encryption() -> bool;
     fn versions() -> &'static [u64];
+    /// The peer that sends a larger message than this is disconnected before the message is delivered.
+    fn max_message_size() -> usize {
+        DEFAULT_MAX_MESSAGE_SIZE
+    }
 
     fn on_node_added(&mut self, _node: &NodeId, _version: u64) {}
     fn on_node_removed(&mut self, _node: &NodeId) {}
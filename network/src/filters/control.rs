@@ -15,10 +15,11 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use std::net::IpAddr;
+use std::time::Duration;
 
 use cidr::IpCidr;
 
-use super::filter::FilterEntry;
+use super::filter::{FilterEntry, FilterMatch};
 
 pub trait Control: Send + Sync {
     fn add_to_whitelist(&self, addr: IpCidr, tag: Option<String>);
@@ -26,6 +27,13 @@ pub trait Control: Send + Sync {
 
     fn add_to_blacklist(&self, addr: IpCidr, tag: Option<String>);
     fn remove_from_blacklist(&self, addr: &IpCidr);
+    /// Swaps all the rules of the blacklist at once. The rules with the durations expire after them.
+    fn replace_blacklist(&self, entries: Vec<(FilterEntry, Option<Duration>)>);
+
+    /// Removes the rules of the tag from both lists, and returns the number of the removed rules.
+    fn remove_by_tag(&self, tag: &str) -> usize;
+    /// Returns the rules of the tag in the whitelist and the blacklist.
+    fn list_by_tag(&self, tag: &str) -> (Vec<IpCidr>, Vec<IpCidr>);
 
     fn enable_whitelist(&self);
     fn disable_whitelist(&self);
@@ -36,4 +44,24 @@ pub trait Control: Send + Sync {
     fn get_blacklist(&self) -> (Vec<FilterEntry>, bool);
 
     fn is_allowed(&self, addr: &IpAddr) -> bool;
+    /// Tells how the address would be filtered if it connected now.
+    fn evaluate(&self, addr: &IpAddr) -> FilterDecision;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FilterList {
+    Whitelist,
+    Blacklist,
+}
+
+/// The rules matched regardless of whether the lists are enabled.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FilterDecision {
+    pub allowed: bool,
+    /// None if the address is allowed.
+    pub denied_by: Option<FilterList>,
+    pub whitelist_enabled: bool,
+    pub whitelist_match: Option<FilterMatch>,
+    pub blacklist_enabled: bool,
+    pub blacklist_match: Option<FilterMatch>,
 }
@@ -14,15 +14,30 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::net::IpAddr;
+use std::time::{Duration, Instant};
 
 use cidr::{Cidr, IpCidr};
 
 #[derive(Default)]
 pub struct Filter {
     enabled: bool,
-    list: HashMap<IpCidr, String>,
+    list: HashMap<IpCidr, Rule>,
+    /// The rules of each tag. The rules without tags are not indexed.
+    tags: HashMap<String, HashSet<IpCidr>>,
+}
+
+struct Rule {
+    tag: String,
+    /// None if the rule doesn't expire.
+    expires_at: Option<Instant>,
+}
+
+impl Rule {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.map(|expires_at| expires_at <= now).unwrap_or(false)
+    }
 }
 
 #[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
@@ -31,27 +46,76 @@ pub struct FilterEntry {
     pub tag: String,
 }
 
+/// The rule that matched an address.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FilterMatch {
+    pub entry: FilterEntry,
+    /// The time left until the rule expires. None if the rule doesn't expire.
+    pub ttl: Option<Duration>,
+}
+
 impl Filter {
     pub fn new(input_vector: Vec<FilterEntry>) -> Self {
-        Self {
+        let mut filter = Self {
             enabled: !input_vector.is_empty(),
-            list: input_vector.into_iter().map(|x| (x.cidr, x.tag)).collect(),
+            ..Default::default()
+        };
+        for entry in input_vector {
+            filter.insert(entry.cidr, entry.tag, None);
         }
+        filter
     }
 
     pub fn add(&mut self, addr: IpCidr, tag: Option<String>) {
         match tag {
-            Some(tag) => {
-                self.list.insert(addr, tag);
-            }
+            Some(tag) => self.insert(addr, tag, None),
             None => {
-                self.list.entry(addr).or_insert_with(String::new);
+                if !self.list.contains_key(&addr) {
+                    self.insert(addr, String::new(), None);
+                }
             }
         };
     }
 
     pub fn remove(&mut self, addr: &IpCidr) {
-        self.list.remove(&addr);
+        if let Some(rule) = self.list.remove(&addr) {
+            self.unindex(addr, &rule.tag);
+        }
+    }
+
+    /// Returns the number of the removed rules.
+    pub fn remove_by_tag(&mut self, tag: &str) -> usize {
+        let addrs = match self.tags.remove(tag) {
+            Some(addrs) => addrs,
+            None => return 0,
+        };
+        for addr in &addrs {
+            self.list.remove(addr);
+        }
+        addrs.len()
+    }
+
+    pub fn list_by_tag(&self, tag: &str) -> Vec<IpCidr> {
+        let now = Instant::now();
+        let mut list: Vec<_> = self
+            .tags
+            .get(tag)
+            .into_iter()
+            .flatten()
+            .filter(|addr| !self.list[*addr].is_expired(now))
+            .cloned()
+            .collect();
+        list.sort();
+        list
+    }
+
+    /// Replaces all the rules at once. The rules with the durations expire after them.
+    pub fn replace(&mut self, entries: Vec<(FilterEntry, Option<Duration>)>, now: Instant) {
+        self.list.clear();
+        self.tags.clear();
+        for (entry, ttl) in entries {
+            self.insert(entry.cidr, entry.tag, ttl.map(|ttl| now + ttl));
+        }
     }
 
     pub fn enable(&mut self) {
@@ -63,12 +127,14 @@ impl Filter {
     }
 
     pub fn status(&self) -> (Vec<FilterEntry>, bool) {
+        let now = Instant::now();
         let mut list: Vec<_> = self
             .list
             .iter()
-            .map(|(a, b)| FilterEntry {
+            .filter(|(_, rule)| !rule.is_expired(now))
+            .map(|(a, rule)| FilterEntry {
                 cidr: a.clone(),
-                tag: b.clone(),
+                tag: rule.tag.clone(),
             })
             .collect();
         list.sort();
@@ -80,9 +146,59 @@ impl Filter {
     }
 
     pub fn contains(&self, addr: &IpAddr) -> bool {
+        self.contains_at(addr, Instant::now())
+    }
+
+    fn contains_at(&self, addr: &IpAddr, now: Instant) -> bool {
         debug_assert!(self.enabled);
         debug_assert!(!addr.is_unspecified(), "{:?}", addr);
-        self.list.iter().any(|(filter, _)| is_filtered(addr, filter))
+        self.list.iter().any(|(filter, rule)| !rule.is_expired(now) && is_filtered(addr, filter))
+    }
+
+    /// Returns the most specific rule that contains the address, whether the filter is enabled or not.
+    pub fn find(&self, addr: &IpAddr, now: Instant) -> Option<FilterMatch> {
+        self.list
+            .iter()
+            .filter(|(cidr, rule)| !rule.is_expired(now) && cidr.contains(addr))
+            .max_by_key(|(cidr, _)| cidr.network_length())
+            .map(|(cidr, rule)| FilterMatch {
+                entry: FilterEntry {
+                    cidr: cidr.clone(),
+                    tag: rule.tag.clone(),
+                },
+                ttl: rule.expires_at.map(|expires_at| expires_at.duration_since(now)),
+            })
+    }
+
+    /// Drops the expired rules.
+    pub fn prune(&mut self, now: Instant) {
+        let expired: Vec<_> =
+            self.list.iter().filter(|(_, rule)| rule.is_expired(now)).map(|(addr, _)| addr.clone()).collect();
+        for addr in expired {
+            self.remove(&addr);
+        }
+    }
+
+    fn insert(&mut self, addr: IpCidr, tag: String, expires_at: Option<Instant>) {
+        if let Some(previous) = self.list.remove(&addr) {
+            self.unindex(&addr, &previous.tag);
+        }
+        if !tag.is_empty() {
+            self.tags.entry(tag.clone()).or_default().insert(addr.clone());
+        }
+        self.list.insert(addr, Rule {
+            tag,
+            expires_at,
+        });
+    }
+
+    fn unindex(&mut self, addr: &IpCidr, tag: &str) {
+        if let Some(addrs) = self.tags.get_mut(tag) {
+            addrs.remove(addr);
+            if addrs.is_empty() {
+                self.tags.remove(tag);
+            }
+        }
     }
 }
 
@@ -193,4 +309,83 @@ mod tests {
         filter.remove(&IpCidr::from_str("100.2.7.4").unwrap());
         assert!(!filter.contains(&IpAddr::from_str("100.2.7.4").unwrap()));
     }
+
+    fn entry(cidr: &str, tag: &str) -> FilterEntry {
+        FilterEntry {
+            cidr: IpCidr::from_str(cidr).unwrap(),
+            tag: tag.to_string(),
+        }
+    }
+
+    #[test]
+    fn remove_by_tag() {
+        let mut filter = Filter::new(vec![entry("1.2.3.4", "spam"), entry("1.2.0.0/16", "spam"), entry("5.6.7.8", "")]);
+        filter.add(IpCidr::from_str("9.9.9.9").unwrap(), Some("scan".to_string()));
+        // Retagging moves the rule out of the old tag.
+        filter.add(IpCidr::from_str("1.2.3.4").unwrap(), Some("scan".to_string()));
+
+        assert_eq!(vec![IpCidr::from_str("1.2.0.0/16").unwrap()], filter.list_by_tag("spam"));
+        assert_eq!(
+            vec![IpCidr::from_str("1.2.3.4").unwrap(), IpCidr::from_str("9.9.9.9").unwrap()],
+            filter.list_by_tag("scan")
+        );
+        assert!(filter.list_by_tag("").is_empty());
+
+        assert_eq!(2, filter.remove_by_tag("scan"));
+        assert_eq!(0, filter.remove_by_tag("scan"));
+        assert!(filter.list_by_tag("scan").is_empty());
+        assert!(!filter.contains(&IpAddr::from_str("9.9.9.9").unwrap()));
+        assert!(filter.contains(&IpAddr::from_str("1.2.3.4").unwrap()));
+        assert_eq!(vec![entry("1.2.0.0/16", "spam"), entry("5.6.7.8", "")], filter.status().0);
+    }
+
+    #[test]
+    fn replace() {
+        let mut filter = Filter::new(vec![entry("1.2.3.4", "old")]);
+        let now = Instant::now();
+        filter.replace(
+            vec![(entry("5.6.7.8", "new"), None), (entry("9.9.9.9", "new"), Some(Duration::from_secs(60)))],
+            now,
+        );
+        assert!(filter.is_enabled());
+        assert!(filter.list_by_tag("old").is_empty());
+        assert!(!filter.contains_at(&IpAddr::from_str("1.2.3.4").unwrap(), now));
+        assert!(filter.contains_at(&IpAddr::from_str("5.6.7.8").unwrap(), now));
+        assert!(filter.contains_at(&IpAddr::from_str("9.9.9.9").unwrap(), now));
+
+        let later = now + Duration::from_secs(60);
+        assert!(filter.contains_at(&IpAddr::from_str("5.6.7.8").unwrap(), later));
+        assert!(!filter.contains_at(&IpAddr::from_str("9.9.9.9").unwrap(), later));
+        filter.prune(later);
+        assert_eq!(vec![IpCidr::from_str("5.6.7.8").unwrap()], filter.list_by_tag("new"));
+    }
+
+    #[test]
+    fn find_returns_the_most_specific_rule() {
+        let mut filter = Filter::new(vec![entry("10.0.0.0/8", "wide")]);
+        let now = Instant::now();
+        let addr = IpAddr::from_str("10.1.2.3").unwrap();
+        let narrow = IpCidr::from_str("10.1.0.0/16").unwrap();
+        filter.insert(narrow, "narrow".to_string(), Some(now + Duration::from_secs(60)));
+        assert_eq!(
+            Some(FilterMatch {
+                entry: entry("10.1.0.0/16", "narrow"),
+                ttl: Some(Duration::from_secs(50)),
+            }),
+            filter.find(&addr, now + Duration::from_secs(10))
+        );
+        // The expired rule doesn't match.
+        assert_eq!(
+            Some(FilterMatch {
+                entry: entry("10.0.0.0/8", "wide"),
+                ttl: None,
+            }),
+            filter.find(&addr, now + Duration::from_secs(60))
+        );
+        assert_eq!(None, filter.find(&IpAddr::from_str("11.1.2.3").unwrap(), now));
+
+        // The disabled filter still reports the rule.
+        filter.disable();
+        assert_eq!(entry("10.1.0.0/16", "narrow"), filter.find(&addr, now).unwrap().entry);
+    }
 }
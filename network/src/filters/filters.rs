@@ -16,11 +16,12 @@
 
 use std::net::IpAddr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use cidr::IpCidr;
 use parking_lot::RwLock;
 
-use super::control::Control;
+use super::control::{Control, FilterDecision, FilterList};
 use super::filter::{Filter, FilterEntry};
 
 #[derive(Default)]
@@ -44,6 +45,7 @@ impl Filters {
 impl Control for Filters {
     fn add_to_whitelist(&self, addr: IpCidr, tag: Option<String>) {
         let mut whitelist = self.whitelist.write();
+        whitelist.prune(Instant::now());
         whitelist.add(addr.clone(), tag);
         cinfo!(NETFILTER, "{:?} is added to the whitelist", addr);
     }
@@ -56,6 +58,7 @@ impl Control for Filters {
 
     fn add_to_blacklist(&self, addr: IpCidr, tag: Option<String>) {
         let mut blacklist = self.blacklist.write();
+        blacklist.prune(Instant::now());
         blacklist.add(addr.clone(), tag);
         cinfo!(NETFILTER, "{:?} is added to the blacklist", addr);
     }
@@ -66,6 +69,28 @@ impl Control for Filters {
         cinfo!(NETFILTER, "{:?} is removed from the blacklist", addr);
     }
 
+    fn replace_blacklist(&self, entries: Vec<(FilterEntry, Option<Duration>)>) {
+        let count = entries.len();
+        // The lookups see either the old rules or the new rules, never an empty list in between.
+        let mut blacklist = self.blacklist.write();
+        blacklist.replace(entries, Instant::now());
+        cinfo!(NETFILTER, "The blacklist is replaced with {} rules", count);
+    }
+
+    fn remove_by_tag(&self, tag: &str) -> usize {
+        let mut whitelist = self.whitelist.write();
+        let mut blacklist = self.blacklist.write();
+        let removed = whitelist.remove_by_tag(tag) + blacklist.remove_by_tag(tag);
+        cinfo!(NETFILTER, "{} rules tagged {} are removed", removed, tag);
+        removed
+    }
+
+    fn list_by_tag(&self, tag: &str) -> (Vec<IpCidr>, Vec<IpCidr>) {
+        let whitelist = self.whitelist.read();
+        let blacklist = self.blacklist.read();
+        (whitelist.list_by_tag(tag), blacklist.list_by_tag(tag))
+    }
+
     fn enable_whitelist(&self) {
         let mut whitelist = self.whitelist.write();
         whitelist.enable();
@@ -113,4 +138,155 @@ impl Control for Filters {
         }
         true
     }
+
+    fn evaluate(&self, addr: &IpAddr) -> FilterDecision {
+        let whitelist = self.whitelist.read();
+        let blacklist = self.blacklist.read();
+        let now = Instant::now();
+        let whitelist_match = whitelist.find(addr, now);
+        let blacklist_match = blacklist.find(addr, now);
+
+        // The same order as `is_allowed`
+        let denied_by = if whitelist.is_enabled() && whitelist_match.is_none() {
+            Some(FilterList::Whitelist)
+        } else if blacklist.is_enabled() && blacklist_match.is_some() {
+            Some(FilterList::Blacklist)
+        } else {
+            None
+        };
+        FilterDecision {
+            allowed: denied_by.is_none(),
+            denied_by,
+            whitelist_enabled: whitelist.is_enabled(),
+            whitelist_match,
+            blacklist_enabled: blacklist.is_enabled(),
+            blacklist_match,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+
+    use super::super::filter::FilterMatch;
+    use super::*;
+
+    fn entry(cidr: &str, tag: &str) -> FilterEntry {
+        FilterEntry {
+            cidr: IpCidr::from_str(cidr).unwrap(),
+            tag: tag.to_string(),
+        }
+    }
+
+    #[test]
+    fn replace_blacklist_has_no_empty_window() {
+        let filters = Filters::new(vec![], vec![entry("10.0.0.0/8", "initial")]);
+        let probed = IpAddr::from_str("10.1.2.3").unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let probes: Vec<_> = (0..4)
+            .map(|_| {
+                let filters = Arc::clone(&filters);
+                let stop = Arc::clone(&stop);
+                thread::spawn(move || {
+                    let mut lookups = 0;
+                    while !stop.load(Ordering::SeqCst) {
+                        assert!(!filters.is_allowed(&probed), "The blacklist was empty during the replacement");
+                        lookups += 1;
+                    }
+                    lookups
+                })
+            })
+            .collect();
+
+        for i in 0..1000 {
+            // Every rule set blocks the probed address with a different rule.
+            let rules = vec![
+                (entry(&format!("10.{}.0.0/16", i % 2), "rotating"), None),
+                (entry("10.0.0.0/8", "covering"), Some(Duration::from_secs(60))),
+                (entry(&format!("192.168.{}.0/24", i % 256), "rotating"), None),
+            ];
+            filters.replace_blacklist(rules);
+        }
+        stop.store(true, Ordering::SeqCst);
+        for probe in probes {
+            assert!(probe.join().unwrap() > 0);
+        }
+
+        let (whitelist, blacklist) = filters.list_by_tag("rotating");
+        assert!(whitelist.is_empty());
+        assert_eq!(
+            vec![IpCidr::from_str("10.1.0.0/16").unwrap(), IpCidr::from_str("192.168.231.0/24").unwrap()],
+            blacklist
+        );
+        assert_eq!((vec![], vec![]), filters.list_by_tag("initial"));
+    }
+
+    #[test]
+    fn remove_by_tag_from_both_lists() {
+        let filters = Filters::new(vec![entry("1.1.1.1", "partner"), entry("2.2.2.2", "ops")], vec![
+            entry("3.3.3.3", "partner"),
+            entry("4.4.4.4", "spam"),
+        ]);
+        assert_eq!(2, filters.remove_by_tag("partner"));
+        assert_eq!(vec![entry("2.2.2.2", "ops")], filters.get_whitelist().0);
+        assert_eq!(vec![entry("4.4.4.4", "spam")], filters.get_blacklist().0);
+        assert!(!filters.is_allowed(&IpAddr::from_str("4.4.4.4").unwrap()));
+        assert!(!filters.is_allowed(&IpAddr::from_str("1.1.1.1").unwrap()));
+        assert!(filters.is_allowed(&IpAddr::from_str("2.2.2.2").unwrap()));
+        assert_eq!(0, filters.remove_by_tag("partner"));
+    }
+
+    #[test]
+    fn blacklist_denies_the_address_that_both_lists_match() {
+        let filters = Filters::new(vec![entry("10.0.0.0/8", "partner")], vec![
+            entry("10.1.0.0/16", "spam"),
+            entry("10.1.2.0/24", "scan"),
+        ]);
+        let addr = IpAddr::from_str("10.1.2.3").unwrap();
+        let decision = filters.evaluate(&addr);
+        assert_eq!(
+            FilterDecision {
+                allowed: false,
+                denied_by: Some(FilterList::Blacklist),
+                whitelist_enabled: true,
+                whitelist_match: Some(FilterMatch {
+                    entry: entry("10.0.0.0/8", "partner"),
+                    ttl: None,
+                }),
+                blacklist_enabled: true,
+                blacklist_match: Some(FilterMatch {
+                    entry: entry("10.1.2.0/24", "scan"),
+                    ttl: None,
+                }),
+            },
+            decision
+        );
+        assert_eq!(filters.is_allowed(&addr), decision.allowed);
+
+        filters.disable_blacklist();
+        let decision = filters.evaluate(&addr);
+        assert!(decision.allowed);
+        assert_eq!(None, decision.denied_by);
+        assert_eq!(entry("10.1.2.0/24", "scan"), decision.blacklist_match.unwrap().entry);
+    }
+
+    #[test]
+    fn whitelist_denies_the_address_that_it_does_not_match() {
+        let filters = Filters::new(vec![entry("10.0.0.0/8", "partner")], vec![entry("11.0.0.0/8", "spam")]);
+        let addr = IpAddr::from_str("11.1.2.3").unwrap();
+        let decision = filters.evaluate(&addr);
+        assert_eq!(Some(FilterList::Whitelist), decision.denied_by);
+        assert_eq!(None, decision.whitelist_match);
+        assert_eq!(entry("11.0.0.0/8", "spam"), decision.blacklist_match.unwrap().entry);
+        assert_eq!(filters.is_allowed(&addr), decision.allowed);
+
+        filters.disable_whitelist();
+        assert_eq!(Some(FilterList::Blacklist), filters.evaluate(&addr).denied_by);
+        filters.disable_blacklist();
+        assert!(filters.evaluate(&addr).allowed);
+    }
 }
@@ -19,6 +19,6 @@ mod filter;
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::module_inception))]
 mod filters;
 
-pub use self::control::Control as FiltersControl;
-pub use self::filter::FilterEntry;
+pub use self::control::{Control as FiltersControl, FilterDecision, FilterList};
+pub use self::filter::{FilterEntry, FilterMatch};
 pub use self::filters::Filters;
@@ -22,6 +22,7 @@ extern crate codechain_io as cio;
 extern crate codechain_key as ckey;
 #[macro_use]
 extern crate codechain_logger as clogger;
+extern crate codechain_metrics as cmetrics;
 extern crate codechain_timer as ctimer;
 extern crate codechain_types as ctypes;
 extern crate core;
@@ -42,11 +43,16 @@ extern crate time;
 extern crate token_generator;
 
 mod addr;
+mod bandwidth;
 mod client;
 mod config;
+mod connection_log;
+mod discovered_peers;
 mod extension;
 mod filters;
+mod nat;
 mod node_id;
+mod peer_exchange;
 mod routing_table;
 mod service;
 mod stream;
@@ -56,15 +62,22 @@ mod p2p;
 pub mod session;
 
 pub use crate::addr::SocketAddr;
+pub use crate::bandwidth::{BandwidthLimits, PeerClass, MAX_QUEUED_BYTES_PER_PEER};
 pub use crate::config::Config as NetworkConfig;
-pub use crate::control::{Control as NetworkControl, Error as NetworkControlError};
+pub use crate::connection_log::{
+    ConnectionAttempt, ConnectionOutcome, ConnectionOutcomeKind, HandshakeError, DEFAULT_CONNECTION_LOG_SIZE,
+};
+pub use crate::control::{Control as NetworkControl, Error as NetworkControlError, PeerSession};
+pub use crate::discovered_peers::DiscoveredPeer;
 pub use crate::extension::{
     Api, Error as NetworkExtensionError, Extension as NetworkExtension, Result as NetworkExtensionResult,
 };
 pub use crate::node_id::{IntoSocketAddr, NodeId};
+pub use crate::peer_exchange::{Config as PeerExchangeConfig, Stats as PeerExchangeStats};
 pub use crate::service::{Error as NetworkServiceError, Service as NetworkService};
 
-pub use crate::filters::{FilterEntry, Filters, FiltersControl};
+pub use crate::filters::{FilterDecision, FilterEntry, FilterList, FilterMatch, Filters, FiltersControl};
+pub use crate::nat::{NatStatus, NatType};
 pub use crate::routing_table::RoutingTable;
 
 pub type EventSender<E> = crossbeam_channel::Sender<E>;
@@ -0,0 +1,465 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod natpmp;
+mod upnp;
+
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use ctimer::{TimeoutHandler, TimerToken};
+use parking_lot::Mutex;
+
+pub use self::natpmp::NatPmpGateway;
+pub use self::upnp::UpnpGateway;
+
+/// The lease that the node requests for the port mapping.
+pub const MAPPING_LEASE: Duration = Duration::from_secs(20 * 60);
+/// How often the node checks whether the port mapping should be renewed.
+pub const RENEWAL_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+pub const MAPPING_TIMER_TOKEN: TimerToken = 0;
+pub const RENEWAL_TIMER_TOKEN: TimerToken = 1;
+
+/// A router that maps a port of its external address to a port of this node.
+pub trait Gateway: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn external_ip(&self) -> Result<IpAddr, String>;
+    /// Returns the mapped external port and the lease that the gateway granted.
+    /// The gateway may choose another external port if the requested one is in use.
+    fn add_port_mapping(&self, local_port: u16, external_port: u16, lease: Duration)
+        -> Result<(u16, Duration), String>;
+    fn remove_port_mapping(&self, local_port: u16, external_port: u16) -> Result<(), String>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NatType {
+    Upnp,
+    NatPmp,
+}
+
+impl NatType {
+    pub fn gateway(self) -> Box<Gateway> {
+        match self {
+            NatType::Upnp => Box::new(UpnpGateway::new()),
+            NatType::NatPmp => Box::new(NatPmpGateway::new()),
+        }
+    }
+}
+
+impl FromStr for NatType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "upnp" => Ok(NatType::Upnp),
+            "natpmp" => Ok(NatType::NatPmp),
+            _ => Err(format!("Unknown NAT traversal method {}", s)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum NatStatus {
+    Disabled,
+    Searching,
+    Mapped {
+        external_ip: IpAddr,
+        external_port: u16,
+        lease_remaining: Duration,
+    },
+    Failed {
+        reason: String,
+    },
+}
+
+impl fmt::Display for NatStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NatStatus::Disabled => write!(f, "disabled"),
+            NatStatus::Searching => write!(f, "searching"),
+            NatStatus::Mapped {
+                external_ip,
+                external_port,
+                lease_remaining,
+            } => write!(f, "mapped to {}:{} ({:?} left)", external_ip, external_port, lease_remaining),
+            NatStatus::Failed {
+                reason,
+            } => write!(f, "failed: {}", reason),
+        }
+    }
+}
+
+enum State {
+    Disabled,
+    Searching,
+    Mapped {
+        external_ip: IpAddr,
+        external_port: u16,
+        expires_at: Instant,
+        lease: Duration,
+    },
+    Failed {
+        reason: String,
+    },
+}
+
+/// Maps the listening port of the node on the NAT gateway, and keeps the mapping alive.
+pub struct PortMapping {
+    gateway: Option<Box<Gateway>>,
+    local_port: u16,
+    lease: Duration,
+    state: Mutex<State>,
+}
+
+impl PortMapping {
+    pub fn new(gateway: Box<Gateway>, local_port: u16, lease: Duration) -> Self {
+        Self {
+            gateway: Some(gateway),
+            local_port,
+            lease,
+            state: Mutex::new(State::Searching),
+        }
+    }
+
+    pub fn disabled(local_port: u16) -> Self {
+        Self {
+            gateway: None,
+            local_port,
+            lease: Duration::from_secs(0),
+            state: Mutex::new(State::Disabled),
+        }
+    }
+
+    pub fn status(&self) -> NatStatus {
+        self.status_at(Instant::now())
+    }
+
+    fn status_at(&self, now: Instant) -> NatStatus {
+        match &*self.state.lock() {
+            State::Disabled => NatStatus::Disabled,
+            State::Searching => NatStatus::Searching,
+            State::Mapped {
+                external_ip,
+                external_port,
+                expires_at,
+                ..
+            } => {
+                if *expires_at <= now {
+                    NatStatus::Failed {
+                        reason: "The lease has expired".to_string(),
+                    }
+                } else {
+                    NatStatus::Mapped {
+                        external_ip: *external_ip,
+                        external_port: *external_port,
+                        lease_remaining: *expires_at - now,
+                    }
+                }
+            }
+            State::Failed {
+                reason,
+            } => NatStatus::Failed {
+                reason: reason.clone(),
+            },
+        }
+    }
+
+    /// The port that the node tells its peers to connect to.
+    /// It's the configured port unless the mapping is alive.
+    pub fn advertised_port(&self) -> u16 {
+        self.advertised_port_at(Instant::now())
+    }
+
+    fn advertised_port_at(&self, now: Instant) -> u16 {
+        match self.status_at(now) {
+            NatStatus::Mapped {
+                external_port,
+                ..
+            } => external_port,
+            _ => self.local_port,
+        }
+    }
+
+    /// Maps the port again. The current external port is kept if the gateway allows it.
+    pub fn refresh(&self) -> NatStatus {
+        self.refresh_at(Instant::now())
+    }
+
+    fn refresh_at(&self, now: Instant) -> NatStatus {
+        let gateway = match &self.gateway {
+            Some(gateway) => gateway,
+            None => return NatStatus::Disabled,
+        };
+        let requested_port = match &*self.state.lock() {
+            State::Mapped {
+                external_port,
+                ..
+            } => *external_port,
+            _ => self.local_port,
+        };
+
+        // Don't hold the lock while talking to the gateway.
+        let result = gateway.external_ip().and_then(|external_ip| {
+            let (external_port, lease) = gateway.add_port_mapping(self.local_port, requested_port, self.lease)?;
+            Ok((external_ip, external_port, lease))
+        });
+        let state = match result {
+            Ok((external_ip, external_port, lease)) => {
+                cinfo!(
+                    NETWORK,
+                    "{}:{} is mapped to the port {} via {} for {:?}",
+                    external_ip,
+                    external_port,
+                    self.local_port,
+                    gateway.name(),
+                    lease
+                );
+                State::Mapped {
+                    external_ip,
+                    external_port,
+                    expires_at: now + lease,
+                    lease,
+                }
+            }
+            Err(reason) => {
+                cwarn!(
+                    NETWORK,
+                    "Cannot map the port {} via {}: {}. Advertise the configured port",
+                    self.local_port,
+                    gateway.name(),
+                    reason
+                );
+                State::Failed {
+                    reason,
+                }
+            }
+        };
+        *self.state.lock() = state;
+        self.status_at(now)
+    }
+
+    /// Renews the mapping when a half of the lease has passed, and retries after a failure.
+    fn needs_renewal_at(&self, now: Instant) -> bool {
+        match &*self.state.lock() {
+            State::Disabled => false,
+            State::Searching => true,
+            State::Mapped {
+                expires_at,
+                lease,
+                ..
+            } => *expires_at <= now || *expires_at - now <= *lease / 2,
+            State::Failed {
+                ..
+            } => true,
+        }
+    }
+
+    /// Removes the mapping from the gateway. Called when the node shuts down.
+    pub fn remove(&self) {
+        let gateway = match &self.gateway {
+            Some(gateway) => gateway,
+            None => return,
+        };
+        let external_port = match &*self.state.lock() {
+            State::Mapped {
+                external_port,
+                ..
+            } => *external_port,
+            _ => return,
+        };
+        match gateway.remove_port_mapping(self.local_port, external_port) {
+            Ok(()) => cinfo!(NETWORK, "Removed the port mapping of {} via {}", external_port, gateway.name()),
+            Err(err) => {
+                cwarn!(NETWORK, "Cannot remove the port mapping of {} via {}: {}", external_port, gateway.name(), err)
+            }
+        }
+        *self.state.lock() = State::Disabled;
+    }
+}
+
+impl TimeoutHandler for PortMapping {
+    fn on_timeout(&self, token: TimerToken) {
+        match token {
+            MAPPING_TIMER_TOKEN => {
+                self.refresh();
+            }
+            RENEWAL_TIMER_TOKEN => {
+                if self.needs_renewal_at(Instant::now()) {
+                    self.refresh();
+                }
+            }
+            _ => unreachable!("Unknown timer token {}", token),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::net::Ipv4Addr;
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct MockGateway {
+        responses: Mutex<VecDeque<Result<(u16, Duration), String>>>,
+        requested_ports: Arc<Mutex<Vec<u16>>>,
+        removed_ports: Arc<Mutex<Vec<u16>>>,
+    }
+
+    impl MockGateway {
+        fn respond(self, response: Result<(u16, Duration), String>) -> Self {
+            self.responses.lock().push_back(response);
+            self
+        }
+    }
+
+    impl Gateway for MockGateway {
+        fn name(&self) -> &'static str {
+            "mock"
+        }
+
+        fn external_ip(&self) -> Result<IpAddr, String> {
+            Ok(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7)))
+        }
+
+        fn add_port_mapping(
+            &self,
+            _local_port: u16,
+            external_port: u16,
+            _lease: Duration,
+        ) -> Result<(u16, Duration), String> {
+            self.requested_ports.lock().push(external_port);
+            self.responses.lock().pop_front().unwrap_or_else(|| Err("No response".to_string()))
+        }
+
+        fn remove_port_mapping(&self, _local_port: u16, external_port: u16) -> Result<(), String> {
+            self.removed_ports.lock().push(external_port);
+            Ok(())
+        }
+    }
+
+    const LOCAL_PORT: u16 = 3485;
+    const LEASE: Duration = Duration::from_secs(100);
+
+    #[test]
+    fn disabled() {
+        let mapping = PortMapping::disabled(LOCAL_PORT);
+        assert_eq!(NatStatus::Disabled, mapping.status());
+        assert_eq!(NatStatus::Disabled, mapping.refresh());
+        assert_eq!(LOCAL_PORT, mapping.advertised_port());
+    }
+
+    #[test]
+    fn mapped_port_is_advertised() {
+        let gateway = MockGateway::default().respond(Ok((4000, LEASE)));
+        let mapping = PortMapping::new(Box::new(gateway), LOCAL_PORT, LEASE);
+        let now = Instant::now();
+        assert_eq!(NatStatus::Searching, mapping.status_at(now));
+        assert_eq!(LOCAL_PORT, mapping.advertised_port_at(now));
+
+        assert_eq!(
+            NatStatus::Mapped {
+                external_ip: IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7)),
+                external_port: 4000,
+                lease_remaining: LEASE,
+            },
+            mapping.refresh_at(now)
+        );
+        assert_eq!(4000, mapping.advertised_port_at(now));
+    }
+
+    #[test]
+    fn lease_is_renewed_after_a_half_of_it_passed() {
+        let gateway = MockGateway::default().respond(Ok((4000, LEASE))).respond(Ok((4000, LEASE)));
+        let requested_ports = Arc::clone(&gateway.requested_ports);
+        let mapping = PortMapping::new(Box::new(gateway), LOCAL_PORT, LEASE);
+        let now = Instant::now();
+        assert!(mapping.needs_renewal_at(now));
+        mapping.refresh_at(now);
+
+        assert!(!mapping.needs_renewal_at(now + LEASE / 4));
+        let renewed_at = now + LEASE / 2;
+        assert!(mapping.needs_renewal_at(renewed_at));
+        mapping.refresh_at(renewed_at);
+
+        // The renewal requests the port that is already mapped.
+        assert_eq!(vec![LOCAL_PORT, 4000], *requested_ports.lock());
+        assert!(!mapping.needs_renewal_at(renewed_at + LEASE / 4));
+        match mapping.status_at(now + LEASE) {
+            NatStatus::Mapped {
+                lease_remaining,
+                ..
+            } => assert_eq!(LEASE / 2, lease_remaining),
+            status => panic!("Unexpected status {:?}", status),
+        }
+    }
+
+    #[test]
+    fn failure_falls_back_to_the_configured_port() {
+        let gateway = MockGateway::default().respond(Err("No gateway found".to_string()));
+        let mapping = PortMapping::new(Box::new(gateway), LOCAL_PORT, LEASE);
+        let now = Instant::now();
+        assert_eq!(
+            NatStatus::Failed {
+                reason: "No gateway found".to_string(),
+            },
+            mapping.refresh_at(now)
+        );
+        assert_eq!(LOCAL_PORT, mapping.advertised_port_at(now));
+        assert!(mapping.needs_renewal_at(now));
+    }
+
+    #[test]
+    fn failed_renewal_falls_back_to_the_configured_port() {
+        let gateway = MockGateway::default().respond(Ok((4000, LEASE))).respond(Err("Connection refused".to_string()));
+        let mapping = PortMapping::new(Box::new(gateway), LOCAL_PORT, LEASE);
+        let now = Instant::now();
+        mapping.refresh_at(now);
+        assert_eq!(4000, mapping.advertised_port_at(now));
+
+        mapping.refresh_at(now + LEASE / 2);
+        assert_eq!(LOCAL_PORT, mapping.advertised_port_at(now + LEASE / 2));
+    }
+
+    #[test]
+    fn expired_lease_falls_back_to_the_configured_port() {
+        let gateway = MockGateway::default().respond(Ok((4000, LEASE)));
+        let mapping = PortMapping::new(Box::new(gateway), LOCAL_PORT, LEASE);
+        let now = Instant::now();
+        mapping.refresh_at(now);
+
+        assert_eq!(LOCAL_PORT, mapping.advertised_port_at(now + LEASE));
+        assert!(mapping.needs_renewal_at(now + LEASE));
+    }
+
+    #[test]
+    fn remove_the_mapping() {
+        let gateway = MockGateway::default().respond(Ok((4000, LEASE)));
+        let removed_ports = Arc::clone(&gateway.removed_ports);
+        let mapping = PortMapping::new(Box::new(gateway), LOCAL_PORT, LEASE);
+        mapping.refresh();
+
+        mapping.remove();
+        assert_eq!(vec![4000], *removed_ports.lock());
+        assert_eq!(NatStatus::Disabled, mapping.status());
+        assert_eq!(LOCAL_PORT, mapping.advertised_port());
+    }
+}
@@ -0,0 +1,122 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::Duration;
+
+use super::Gateway;
+
+const NAT_PMP_PORT: u16 = 5351;
+const VERSION: u8 = 0;
+const OPCODE_EXTERNAL_ADDRESS: u8 = 0;
+const OPCODE_MAP_TCP: u8 = 2;
+const RESPONSE_FLAG: u8 = 128;
+const ATTEMPTS: u32 = 4;
+/// RFC 6886 starts with 250ms and doubles the timeout on each retry.
+const INITIAL_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// A NAT-PMP client. The gateway is the default route of the host.
+pub struct NatPmpGateway;
+
+impl NatPmpGateway {
+    pub fn new() -> Self {
+        NatPmpGateway
+    }
+
+    fn request(&self, request: &[u8], response_len: usize) -> Result<Vec<u8>, String> {
+        let gateway = SocketAddr::new(IpAddr::V4(default_gateway()?), NAT_PMP_PORT);
+        let socket = UdpSocket::bind("0.0.0.0:0").map_err(|err| format!("Cannot bind a UDP socket: {}", err))?;
+        socket.connect(gateway).map_err(|err| format!("Cannot connect to {}: {}", gateway, err))?;
+
+        let mut timeout = INITIAL_TIMEOUT;
+        let mut buffer = [0u8; 16];
+        for _ in 0..ATTEMPTS {
+            socket.set_read_timeout(Some(timeout)).map_err(|err| format!("{}", err))?;
+            socket.send(request).map_err(|err| format!("Cannot send a request to {}: {}", gateway, err))?;
+            match socket.recv(&mut buffer) {
+                Ok(len) if len >= response_len => {
+                    let response = &buffer[..response_len];
+                    if response[0] != VERSION || response[1] != request[1] + RESPONSE_FLAG {
+                        return Err(format!("Unexpected response {:?}", response))
+                    }
+                    let result_code = u16::from_be_bytes([response[2], response[3]]);
+                    if result_code != 0 {
+                        return Err(format!("{} responded with the result code {}", gateway, result_code))
+                    }
+                    return Ok(response.to_vec())
+                }
+                Ok(len) => return Err(format!("The response is too short: {} bytes", len)),
+                Err(_) => timeout *= 2,
+            }
+        }
+        Err(format!("{} does not respond", gateway))
+    }
+
+    fn map(&self, local_port: u16, external_port: u16, lease: Duration) -> Result<(u16, Duration), String> {
+        let lease = lease.as_secs() as u32;
+        let mut request = vec![VERSION, OPCODE_MAP_TCP, 0, 0];
+        request.extend_from_slice(&local_port.to_be_bytes());
+        request.extend_from_slice(&external_port.to_be_bytes());
+        request.extend_from_slice(&lease.to_be_bytes());
+
+        let response = self.request(&request, 16)?;
+        let mapped_port = u16::from_be_bytes([response[10], response[11]]);
+        let granted_lease = u32::from_be_bytes([response[12], response[13], response[14], response[15]]);
+        Ok((mapped_port, Duration::from_secs(u64::from(granted_lease))))
+    }
+}
+
+impl Gateway for NatPmpGateway {
+    fn name(&self) -> &'static str {
+        "NAT-PMP"
+    }
+
+    fn external_ip(&self) -> Result<IpAddr, String> {
+        let response = self.request(&[VERSION, OPCODE_EXTERNAL_ADDRESS], 12)?;
+        Ok(IpAddr::V4(Ipv4Addr::new(response[8], response[9], response[10], response[11])))
+    }
+
+    fn add_port_mapping(
+        &self,
+        local_port: u16,
+        external_port: u16,
+        lease: Duration,
+    ) -> Result<(u16, Duration), String> {
+        self.map(local_port, external_port, lease)
+    }
+
+    fn remove_port_mapping(&self, local_port: u16, _external_port: u16) -> Result<(), String> {
+        // A request with a zero lease and a zero external port removes the mapping of the local port.
+        self.map(local_port, 0, Duration::from_secs(0)).map(|_| ())
+    }
+}
+
+/// Reads the default route from the routing table of Linux.
+fn default_gateway() -> Result<Ipv4Addr, String> {
+    let routes = fs::read_to_string("/proc/net/route")
+        .map_err(|err| format!("Cannot read the routing table to find the gateway: {}", err))?;
+    for line in routes.lines().skip(1) {
+        let columns: Vec<_> = line.split_whitespace().collect();
+        if columns.len() < 3 || columns[1] != "00000000" {
+            continue
+        }
+        let gateway = u32::from_str_radix(columns[2], 16).map_err(|err| format!("Invalid gateway: {}", err))?;
+        // The table shows the address in the byte order of the host.
+        return Ok(Ipv4Addr::from(gateway.to_ne_bytes()))
+    }
+    Err("There is no default gateway".to_string())
+}
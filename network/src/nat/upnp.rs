@@ -0,0 +1,332 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4, TcpStream, ToSocketAddrs, UdpSocket};
+use std::str;
+use std::time::Duration;
+
+use rand::{thread_rng, Rng};
+
+use super::Gateway;
+
+const SSDP_ADDRESS: &str = "239.255.255.250:1900";
+const SEARCH_TARGET: &str = "urn:schemas-upnp-org:device:InternetGatewayDevice:1";
+const SERVICE_TYPES: &[&str] =
+    &["urn:schemas-upnp-org:service:WANIPConnection:1", "urn:schemas-upnp-org:service:WANPPPConnection:1"];
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(5);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+const DESCRIPTION: &str = "CodeChain";
+/// The error code of AddPortMapping when the external port is mapped to another client.
+const CONFLICT_IN_MAPPING_ENTRY: &str = "718";
+/// How many random external ports are tried when the requested one is in use.
+const RANDOM_PORT_ATTEMPTS: usize = 8;
+
+/// The control endpoint of the WAN connection service of a gateway.
+struct Service {
+    /// The host and the port of the control URL
+    host: String,
+    /// The path of the control URL
+    path: String,
+    service_type: &'static str,
+}
+
+/// An UPnP IGD client. The gateway is searched on every request, because the router may be restarted.
+pub struct UpnpGateway;
+
+impl UpnpGateway {
+    pub fn new() -> Self {
+        UpnpGateway
+    }
+
+    fn search(&self) -> Result<Service, String> {
+        let location = search_location()?;
+        let (host, path) = split_url(&location)?;
+        let (_, description) = http_request(&host, &format!("GET {} HTTP/1.0\r\nHost: {}\r\n", path, host), "")?;
+        let (service_type, control_url) = control_url(&description)
+            .ok_or_else(|| format!("{} doesn't have a WAN connection service", location))?;
+        // The control URL is either absolute or relative to the host of the description.
+        let (host, path) = if control_url.starts_with("http://") {
+            split_url(&control_url)?
+        } else if control_url.starts_with('/') {
+            (host, control_url)
+        } else {
+            (host, format!("/{}", control_url))
+        };
+        Ok(Service {
+            host,
+            path,
+            service_type,
+        })
+    }
+
+    fn add_port(
+        &self,
+        service: &Service,
+        local_address: &SocketAddrV4,
+        external_port: u16,
+        lease: Duration,
+    ) -> Result<(), String> {
+        call(service, "AddPortMapping", &[
+            ("NewRemoteHost", String::new()),
+            ("NewExternalPort", external_port.to_string()),
+            ("NewProtocol", "TCP".to_string()),
+            ("NewInternalPort", local_address.port().to_string()),
+            ("NewInternalClient", local_address.ip().to_string()),
+            ("NewEnabled", "1".to_string()),
+            ("NewPortMappingDescription", DESCRIPTION.to_string()),
+            ("NewLeaseDuration", lease.as_secs().to_string()),
+        ])
+        .map(|_| ())
+    }
+}
+
+/// Sends an SSDP M-SEARCH and returns the location of the description of the first gateway that answers.
+fn search_location() -> Result<String, String> {
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(|err| format!("Cannot bind a UDP socket: {}", err))?;
+    socket.set_read_timeout(Some(SEARCH_TIMEOUT)).map_err(|err| format!("{}", err))?;
+    let request = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: 3\r\n\r\n",
+        SSDP_ADDRESS, SEARCH_TARGET
+    );
+    socket.send_to(request.as_bytes(), SSDP_ADDRESS).map_err(|err| format!("Cannot send an SSDP search: {}", err))?;
+
+    let mut buffer = [0u8; 1500];
+    loop {
+        let len = socket.recv(&mut buffer).map_err(|_| "Cannot find an UPnP gateway".to_string())?;
+        if let Some(location) = str::from_utf8(&buffer[..len]).ok().and_then(|response| header(response, "location")) {
+            return Ok(location)
+        }
+    }
+}
+
+/// The value of the header in an HTTP message. The case of the name is ignored.
+fn header(message: &str, name: &str) -> Option<String> {
+    message.lines().find_map(|line| {
+        let mut parts = line.splitn(2, ':');
+        let key = parts.next()?.trim();
+        let value = parts.next()?.trim();
+        if key.eq_ignore_ascii_case(name) {
+            Some(value.to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// Splits "http://host:port/path" into "host:port" and "/path".
+fn split_url(url: &str) -> Result<(String, String), String> {
+    let rest = if url.starts_with("http://") {
+        &url["http://".len()..]
+    } else {
+        return Err(format!("{} is not an HTTP URL", url))
+    };
+    match rest.find('/') {
+        Some(index) => Ok((rest[..index].to_string(), rest[index..].to_string())),
+        None => Ok((rest.to_string(), "/".to_string())),
+    }
+}
+
+/// The text of the first element of the tag, e.g. "value" of "<tag>value</tag>".
+fn element<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find(&close)?;
+    Some(xml[start..end].trim())
+}
+
+/// Finds the control URL of the WAN connection service in the device description.
+fn control_url(description: &str) -> Option<(&'static str, String)> {
+    description.split("<service>").skip(1).find_map(|service| {
+        let service_type = element(service, "serviceType")?;
+        let known = SERVICE_TYPES.iter().find(|known| **known == service_type)?;
+        Some((*known, element(service, "controlURL")?.to_string()))
+    })
+}
+
+/// Sends an HTTP request and returns the status code and the body of the response.
+/// The requests are HTTP/1.0, so the gateway doesn't answer with chunks.
+fn http_request(host: &str, head: &str, body: &str) -> Result<(u16, String), String> {
+    let address = host
+        .to_socket_addrs()
+        .map_err(|err| format!("Invalid address {}: {}", host, err))?
+        .next()
+        .ok_or_else(|| format!("Invalid address {}", host))?;
+    let mut stream = TcpStream::connect_timeout(&address, HTTP_TIMEOUT)
+        .map_err(|err| format!("Cannot connect to {}: {}", address, err))?;
+    stream.set_read_timeout(Some(HTTP_TIMEOUT)).map_err(|err| format!("{}", err))?;
+    stream.set_write_timeout(Some(HTTP_TIMEOUT)).map_err(|err| format!("{}", err))?;
+    let request = format!("{}Content-Length: {}\r\nConnection: close\r\n\r\n{}", head, body.len(), body);
+    stream.write_all(request.as_bytes()).map_err(|err| format!("Cannot send a request to {}: {}", address, err))?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).map_err(|err| format!("Cannot read the response of {}: {}", address, err))?;
+    let status = response
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| format!("Invalid response from {}", address))?;
+    let body = match response.find("\r\n\r\n") {
+        Some(index) => response[index + 4..].to_string(),
+        None => String::new(),
+    };
+    Ok((status, body))
+}
+
+/// Calls a SOAP action of the service. The UPnP error code is returned as the error if the action fails.
+fn call(service: &Service, action: &str, arguments: &[(&str, String)]) -> Result<String, String> {
+    let arguments: String = arguments.iter().map(|(name, value)| format!("<{}>{}</{}>", name, value, name)).collect();
+    let body = format!(
+        concat!(
+            r#"<?xml version="1.0"?>"#,
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/" "#,
+            r#"s:encodingStyle="http://schemas.xmlsoap.org/soap/encoding/">"#,
+            r#"<s:Body><u:{action} xmlns:u="{service_type}">{arguments}</u:{action}></s:Body></s:Envelope>"#
+        ),
+        action = action,
+        service_type = service.service_type,
+        arguments = arguments
+    );
+    let head = format!(
+        "POST {} HTTP/1.0\r\nHost: {}\r\nContent-Type: text/xml; charset=\"utf-8\"\r\nSOAPAction: \"{}#{}\"\r\n",
+        service.path, service.host, service.service_type, action
+    );
+    let (status, response) = http_request(&service.host, &head, &body)?;
+    if status == 200 {
+        return Ok(response)
+    }
+    Err(element(&response, "errorCode").map(ToString::to_string).unwrap_or_else(|| format!("HTTP {}", status)))
+}
+
+/// The address of this node in the network of the gateway.
+fn local_address(service: &Service, local_port: u16) -> Result<SocketAddrV4, String> {
+    let gateway = service
+        .host
+        .to_socket_addrs()
+        .map_err(|err| format!("Invalid address {}: {}", service.host, err))?
+        .next()
+        .ok_or_else(|| format!("Invalid address {}", service.host))?;
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(|err| format!("Cannot bind a UDP socket: {}", err))?;
+    socket.connect(gateway).map_err(|err| format!("Cannot connect to {}: {}", gateway, err))?;
+    match socket.local_addr().map_err(|err| format!("{}", err))? {
+        SocketAddr::V4(addr) => Ok(SocketAddrV4::new(*addr.ip(), local_port)),
+        SocketAddr::V6(addr) => Err(format!("{} is not an IPv4 address", addr)),
+    }
+}
+
+impl Gateway for UpnpGateway {
+    fn name(&self) -> &'static str {
+        "UPnP"
+    }
+
+    fn external_ip(&self) -> Result<IpAddr, String> {
+        let service = self.search()?;
+        let response = call(&service, "GetExternalIPAddress", &[])
+            .map_err(|err| format!("Cannot get the external address: {}", err))?;
+        let ip: Ipv4Addr = element(&response, "NewExternalIPAddress")
+            .and_then(|ip| ip.parse().ok())
+            .ok_or_else(|| "The gateway doesn't have an external address".to_string())?;
+        Ok(IpAddr::V4(ip))
+    }
+
+    fn add_port_mapping(
+        &self,
+        local_port: u16,
+        external_port: u16,
+        lease: Duration,
+    ) -> Result<(u16, Duration), String> {
+        let service = self.search()?;
+        let local_address = local_address(&service, local_port)?;
+        let err = match self.add_port(&service, &local_address, external_port, lease) {
+            Ok(()) => return Ok((external_port, lease)),
+            Err(err) => err,
+        };
+        if err != CONFLICT_IN_MAPPING_ENTRY {
+            return Err(format!("Cannot add a port mapping: {}", err))
+        }
+        cdebug!(NETWORK, "The external port {} is in use. Try random ports", external_port);
+        for _ in 0..RANDOM_PORT_ATTEMPTS {
+            let port = thread_rng().gen_range(1024, u16::max_value());
+            match self.add_port(&service, &local_address, port, lease) {
+                Ok(()) => return Ok((port, lease)),
+                Err(ref err) if err == CONFLICT_IN_MAPPING_ENTRY => continue,
+                Err(err) => return Err(format!("Cannot add a port mapping: {}", err)),
+            }
+        }
+        Err("Cannot find a free external port".to_string())
+    }
+
+    fn remove_port_mapping(&self, _local_port: u16, external_port: u16) -> Result<(), String> {
+        let service = self.search()?;
+        call(&service, "DeletePortMapping", &[
+            ("NewRemoteHost", String::new()),
+            ("NewExternalPort", external_port.to_string()),
+            ("NewProtocol", "TCP".to_string()),
+        ])
+        .map(|_| ())
+        .map_err(|err| format!("Cannot remove the port mapping: {}", err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn location_of_the_search_response() {
+        let response = concat!(
+            "HTTP/1.1 200 OK\r\n",
+            "CACHE-CONTROL: max-age=120\r\n",
+            "ST: urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\n",
+            "Location: http://192.168.0.1:5000/rootDesc.xml\r\n\r\n"
+        );
+        assert_eq!(Some("http://192.168.0.1:5000/rootDesc.xml".to_string()), header(response, "location"));
+        assert_eq!(
+            Ok(("192.168.0.1:5000".to_string(), "/rootDesc.xml".to_string())),
+            split_url("http://192.168.0.1:5000/rootDesc.xml")
+        );
+        assert!(split_url("https://192.168.0.1/rootDesc.xml").is_err());
+    }
+
+    #[test]
+    fn control_url_of_the_wan_connection() {
+        let description = concat!(
+            "<root><device><serviceList>",
+            "<service><serviceType>urn:schemas-upnp-org:service:Layer3Forwarding:1</serviceType>",
+            "<controlURL>/ctl/L3F</controlURL></service>",
+            "</serviceList><deviceList><device><serviceList>",
+            "<service><serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType>",
+            "<controlURL>/ctl/IPConn</controlURL></service>",
+            "</serviceList></device></deviceList></device></root>"
+        );
+        assert_eq!(
+            Some(("urn:schemas-upnp-org:service:WANIPConnection:1", "/ctl/IPConn".to_string())),
+            control_url(description)
+        );
+        assert_eq!(None, control_url("<root><device></device></root>"));
+    }
+
+    #[test]
+    fn error_code_of_the_fault() {
+        let fault = concat!(
+            "<s:Body><s:Fault><detail><UPnPError>",
+            "<errorCode>718</errorCode><errorDescription>ConflictInMappingEntry</errorDescription>",
+            "</UPnPError></detail></s:Fault></s:Body>"
+        );
+        assert_eq!(Some(CONFLICT_IN_MAPPING_ENTRY), element(fault, "errorCode"));
+    }
+}
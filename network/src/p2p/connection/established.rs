@@ -16,36 +16,61 @@
 
 use std::io;
 use std::sync::Arc;
+use std::time::Instant;
 
 use cio::IoManager;
 use mio::deprecated::EventLoop;
 use mio::unix::UnixReady;
 use mio::{PollOpt, Ready, Token};
 use primitives::Bytes;
+use rlp::{Decodable, DecoderError, UntrustedRlp};
 
-use super::super::message::{Message, Version};
+use super::super::message::{Message, Version, REKEY_REQUEST_ID, REKEY_RESPONSE_ID};
 use super::super::stream::SignedStream;
-use super::super::{ExtensionMessage, NegotiationMessage};
+use super::super::{ExtensionMessage, NegotiationMessage, RekeyMessage};
 use super::Result;
-use crate::session::Session;
+use crate::session::{RekeyPolicy, RekeyingSession, Session};
 use crate::stream::Stream;
 use crate::SocketAddr;
 
+/// The rekey messages are handled by the connection and never reach the handler.
+enum Frame {
+    Message(Message),
+    Rekey(RekeyMessage),
+}
+
+impl Decodable for Frame {
+    fn decode(rlp: &UntrustedRlp) -> ::std::result::Result<Self, DecoderError> {
+        match rlp.val_at(0)? {
+            REKEY_REQUEST_ID | REKEY_RESPONSE_ID => Ok(Frame::Rekey(Decodable::decode(rlp)?)),
+            _ => Ok(Frame::Message(Decodable::decode(rlp)?)),
+        }
+    }
+}
+
 pub struct EstablishedConnection {
     stream: SignedStream,
     peer_addr: SocketAddr,
 }
 
 impl EstablishedConnection {
-    pub fn new(stream: Stream, session: Session, peer_addr: SocketAddr) -> Self {
+    pub fn new(stream: Stream, session: Session, peer_addr: SocketAddr, rekey_policy: RekeyPolicy) -> Self {
         Self {
-            stream: SignedStream::new(stream, session),
+            stream: SignedStream::new(stream, RekeyingSession::new(session, rekey_policy, Instant::now())),
             peer_addr,
         }
     }
 
     fn write(&mut self, message: &Message) -> usize {
-        self.stream.write(message)
+        let now = Instant::now();
+        let mut size = 0;
+        if self.stream.keys().should_rekey(now) {
+            let nonce = rand::random();
+            self.stream.keys_mut().start(nonce, now);
+            size += self.stream.write(&RekeyMessage::Request(nonce));
+            ctrace!(NETWORK, "Request a rekey to {}", self.peer_addr);
+        }
+        size + self.stream.write(message)
     }
 
     pub fn enqueue_negotiation_request(&mut self, name: String, extension_versions: Vec<Version>) -> usize {
@@ -84,12 +109,34 @@ impl EstablishedConnection {
         &self.peer_addr
     }
 
-    pub fn receive(&mut self) -> Result<Option<Message>> {
-        Ok(self.stream.read()?)
+    /// Returns the message with the session that signed it.
+    /// The encrypted extension messages must be decrypted with that session.
+    pub fn receive(&mut self) -> Result<Option<(Message, Session)>> {
+        loop {
+            let now = Instant::now();
+            self.stream.keys().check_timeout(now)?;
+            match self.stream.read::<Frame>()? {
+                Some((Frame::Message(message), session)) => return Ok(Some((message, session))),
+                Some((Frame::Rekey(RekeyMessage::Request(initiator_nonce)), _)) => {
+                    let responder_nonce = rand::random();
+                    if let Some(old) = self.stream.keys_mut().accept_request(initiator_nonce, responder_nonce, now)? {
+                        // The peer switches to the new key when it receives the response, so it's signed with the old key.
+                        self.stream.write_with_session(&RekeyMessage::Response(responder_nonce), &old);
+                        ctrace!(NETWORK, "Rekeyed by the request from {}", self.peer_addr);
+                    }
+                }
+                Some((Frame::Rekey(RekeyMessage::Response(responder_nonce)), _)) => {
+                    self.stream.keys_mut().accept_response(responder_nonce, now)?;
+                    ctrace!(NETWORK, "Rekeyed by the response from {}", self.peer_addr);
+                }
+                None => return Ok(None),
+            }
+        }
     }
 
-    pub fn session(&self) -> &Session {
-        self.stream.session()
+    /// The number of the times that the session key was replaced.
+    pub fn rekeys(&self) -> u64 {
+        self.stream.keys().rekeys()
     }
 
     pub fn register<Message>(&self, reg: Token, event_loop: &mut EventLoop<IoManager<Message>>) -> io::Result<()>
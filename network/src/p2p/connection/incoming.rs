@@ -24,7 +24,7 @@ use mio::{PollOpt, Ready, Token};
 use primitives::Bytes;
 
 use super::{EstablishedConnection, IncomingMessage, OutgoingMessage, Result};
-use crate::session::Session;
+use crate::session::{RekeyPolicy, Session};
 use crate::stream::Stream;
 use crate::SocketAddr;
 
@@ -39,9 +39,9 @@ impl IncomingConnection {
         }
     }
 
-    pub fn establish(self, session: Session, port: u16) -> Result<EstablishedConnection> {
+    pub fn establish(self, session: Session, port: u16, rekey_policy: RekeyPolicy) -> Result<EstablishedConnection> {
         let peer_addr = SocketAddr::new(self.stream.peer_addr()?.ip(), port);
-        Ok(EstablishedConnection::new(self.stream, session, peer_addr))
+        Ok(EstablishedConnection::new(self.stream, session, peer_addr, rekey_policy))
     }
 
     fn interest(&self) -> Ready {
@@ -68,6 +68,11 @@ impl IncomingConnection {
         Ok(self.stream.read()?)
     }
 
+    /// The address of the socket, whose port isn't the listening port of the peer.
+    pub fn peer_addr(&self) -> Result<SocketAddr> {
+        Ok(self.stream.peer_addr()?)
+    }
+
     pub fn remote_addr(&self, port: u16) -> Result<SocketAddr> {
         Ok(SocketAddr::new(self.stream.peer_addr()?.ip(), port))
     }
@@ -33,6 +33,7 @@ pub use self::outgoing::OutgoingConnection;
 
 use super::super::stream::Error as StreamError;
 use super::stream::Error as P2pStreamError;
+use crate::session::RekeyError;
 
 
 #[derive(Debug)]
@@ -41,6 +42,7 @@ pub enum Error {
     IoError(io::Error),
     Decoder(DecoderError),
     InvalidSign,
+    Rekey(RekeyError),
 }
 
 impl fmt::Display for Error {
@@ -50,6 +52,7 @@ impl fmt::Display for Error {
             Error::Decoder(err) => err.fmt(f),
             Error::IoError(err) => err.fmt(f),
             Error::InvalidSign => write!(f, "Invalid signature"),
+            Error::Rekey(err) => err.fmt(f),
         }
     }
 }
@@ -79,6 +82,12 @@ impl From<P2pStreamError> for Error {
     }
 }
 
+impl From<RekeyError> for Error {
+    fn from(err: RekeyError) -> Self {
+        Error::Rekey(err)
+    }
+}
+
 impl From<SymmetricCipherError> for Error {
     fn from(err: SymmetricCipherError) -> Self {
         Error::SymmetricCipher(err)
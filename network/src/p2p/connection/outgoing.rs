@@ -23,7 +23,7 @@ use mio::unix::UnixReady;
 use mio::{PollOpt, Ready, Token};
 
 use super::{EstablishedConnection, IncomingMessage, OutgoingMessage, Result};
-use crate::session::Session;
+use crate::session::{RekeyPolicy, Session};
 use crate::stream::Stream;
 use crate::SocketAddr;
 
@@ -86,9 +86,9 @@ impl OutgoingConnection {
         &self.peer_addr
     }
 
-    pub fn establish(self, session: Session) -> Result<EstablishedConnection> {
+    pub fn establish(self, session: Session, rekey_policy: RekeyPolicy) -> Result<EstablishedConnection> {
         let peer_addr = self.stream.peer_addr()?;
-        Ok(EstablishedConnection::new(self.stream, session, peer_addr))
+        Ok(EstablishedConnection::new(self.stream, session, peer_addr, rekey_policy))
     }
 
     pub fn register<Message>(&self, reg: Token, event_loop: &mut EventLoop<IoManager<Message>>) -> io::Result<()>
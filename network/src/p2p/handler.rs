@@ -23,25 +23,32 @@ use std::time::{Duration, Instant};
 use ccrypto::aes::SymmetricCipherError;
 use cio::{IoChannel, IoContext, IoHandler, IoHandlerResult, IoManager, StreamToken, TimerToken};
 use ckey::NetworkId;
+use cmetrics::METRICS;
 use finally_block::finally;
 use mio::deprecated::EventLoop;
 use mio::{PollOpt, Ready, Token};
 use parking_lot::{Mutex, RwLock};
-use primitives::Bytes;
+use primitives::{Bytes, H128};
 use rand::prelude::SliceRandom;
 use rand::rngs::OsRng;
 use rand::Rng;
 use token_generator::TokenGenerator;
 
 use super::connection::{
-    EstablishedConnection, IncomingConnection, IncomingMessage, OutgoingConnection, OutgoingMessage,
+    Error as ConnectionError, EstablishedConnection, IncomingConnection, IncomingMessage, OutgoingConnection,
+    OutgoingMessage,
 };
 use super::listener::Listener;
 use super::{NegotiationMessage, NetworkMessage};
+use crate::bandwidth::{Admission, Bandwidth};
 use crate::client::Client;
-use crate::session::Session;
+use crate::connection_log::{
+    ConnectionAttempt, ConnectionLog, ConnectionOutcome, ConnectionOutcomeKind, HandshakeError,
+};
+use crate::nat::PortMapping;
+use crate::session::{RekeyPolicy, Session};
 use crate::stream::Stream;
-use crate::{FiltersControl, NodeId, RoutingTable, SocketAddr};
+use crate::{FiltersControl, IntoSocketAddr, NodeId, PeerSession, RoutingTable, SocketAddr};
 
 pub const MAX_INBOUND_CONNECTIONS: usize = 1000;
 pub const MAX_OUTBOUND_CONNECTIONS: usize = 1000;
@@ -64,6 +71,7 @@ const LAST_OUTGOING: StreamToken = FIRST_OUTGOING + MAX_OUTGOING_CONNECTIONS - 1
 
 const CREATE_CONNECTIONS: TimerToken = 0;
 const CONNECT_TO_BOOTSTRAP: TimerToken = CREATE_CONNECTIONS + 1;
+const RELEASE_THROTTLED: TimerToken = CONNECT_TO_BOOTSTRAP + 1;
 
 const FIRST_WAIT_SYNC: TimerToken = FIRST_INCOMING;
 const LAST_WAIT_SYNC: TimerToken = LAST_INCOMING;
@@ -75,6 +83,7 @@ const FIRST_TRY_SYNC: TimerToken = FIRST_OUTGOING + 1000;
 const LAST_TRY_SYNC: TimerToken = LAST_OUTGOING + 1000;
 
 const CREATE_CONNECTION_INTERVAL: Duration = Duration::from_secs(3);
+const RELEASE_THROTTLED_INTERVAL: Duration = Duration::from_millis(50);
 
 const RETRY_SYNC_MAX: Duration = Duration::from_secs(10); // T1
 const RTT: Duration = Duration::from_secs(10); // T2
@@ -87,6 +96,7 @@ pub struct Handler {
     network_id: NetworkId,
     socket_address: SocketAddr,
     listener: Listener,
+    port_mapping: Arc<PortMapping>,
 
     inbound_connections: RwLock<HashMap<StreamToken, EstablishedConnection>>,
     outbound_connections: RwLock<HashMap<StreamToken, EstablishedConnection>>,
@@ -111,10 +121,14 @@ pub struct Handler {
 
     bootstrap_addresses: Vec<SocketAddr>,
 
+    bandwidth: Arc<Bandwidth<ThrottledMessage>>,
+    release_scheduled: AtomicBool,
     network_usage_in_10_seconds: Mutex<HashMap<String, VecDeque<(Instant, usize)>>>,
+    connection_log: ConnectionLog,
 
     min_peers: usize,
     max_peers: usize,
+    rekey_policy: RekeyPolicy,
 
     rng: Mutex<OsRng>,
 }
@@ -124,12 +138,16 @@ impl Handler {
         channel: IoChannel<Message>,
         network_id: NetworkId,
         socket_address: SocketAddr,
+        port_mapping: Arc<PortMapping>,
         client: Arc<Client>,
         routing_table: Arc<RoutingTable>,
         filters: Arc<FiltersControl>,
         bootstrap_addresses: Vec<SocketAddr>,
         min_peers: usize,
         max_peers: usize,
+        rekey_policy: RekeyPolicy,
+        connection_log_size: usize,
+        bandwidth: Arc<Bandwidth<ThrottledMessage>>,
     ) -> ::std::result::Result<Self, String> {
         if MAX_INBOUND_CONNECTIONS + MAX_OUTBOUND_CONNECTIONS < max_peers {
             return Err(format!("Max peers must be less than {}", MAX_INBOUND_CONNECTIONS + MAX_OUTBOUND_CONNECTIONS))
@@ -141,6 +159,7 @@ impl Handler {
             network_id,
             socket_address,
             listener: Listener::bind(&socket_address).expect("Cannot listen TCP port"),
+            port_mapping,
 
             inbound_connections: Default::default(),
             outbound_connections: Default::default(),
@@ -163,11 +182,15 @@ impl Handler {
 
             client,
 
+            bandwidth,
+            release_scheduled: AtomicBool::new(false),
             network_usage_in_10_seconds: Default::default(),
+            connection_log: ConnectionLog::new(connection_log_size),
 
             bootstrap_addresses,
             min_peers,
             max_peers,
+            rekey_policy,
 
             rng: Mutex::new(OsRng::new().unwrap()),
         })
@@ -195,6 +218,26 @@ impl Handler {
         self.routing_table.established_addresses()
     }
 
+    pub fn peer_sessions(&self) -> Vec<PeerSession> {
+        let inbound_connections = self.inbound_connections.read();
+        let outbound_connections = self.outbound_connections.read();
+        let inbound = inbound_connections.values().map(|con| (con, true));
+        let outbound = outbound_connections.values().map(|con| (con, false));
+        inbound
+            .chain(outbound)
+            .map(|(con, inbound)| PeerSession {
+                address: *con.peer_addr(),
+                inbound,
+                rekeys: con.rekeys(),
+            })
+            .collect()
+    }
+
+    // Don't use get_peer_count here: the callers hold the lock of the connections.
+    fn update_peer_count_metric(&self) {
+        METRICS.peer_count.set(self.remote_node_ids.read().len());
+    }
+
     fn connect(&self, io: &IoContext<Message>, socket_address: SocketAddr) -> IoHandlerResult<()> {
         let ip = socket_address.ip();
         if !self.filters.is_allowed(&ip) {
@@ -215,7 +258,8 @@ impl Handler {
         if let Some(stream) = Stream::connect(&socket_address)? {
             let mut outgoing_connections = self.outgoing_connections.write();
             // Please make sure there is no early return after it.
-            let initiator_port = self.socket_address.port();
+            // The peer connects back to this port, which is the mapped port if the node is behind a NAT.
+            let initiator_port = self.port_mapping.advertised_port();
             let con =
                 OutgoingConnection::new(stream, initiator_pub_key, self.network_id, initiator_port, socket_address)?;
             let token = self
@@ -233,6 +277,16 @@ impl Handler {
         Ok(())
     }
 
+    /// Returns true if the node is banned because of this misbehavior.
+    fn report_misbehavior(&self, node_id: &NodeId) -> bool {
+        let socket_address = node_id.into_addr();
+        if self.routing_table.report_misbehavior(socket_address) {
+            cwarn!(NETWORK, "{} is banned because it misbehaved too many times", socket_address);
+            return true
+        }
+        false
+    }
+
     pub fn recent_network_usage(&self) -> HashMap<String, usize> {
         let mut network_usage_in_10_seconds = self.network_usage_in_10_seconds.lock();
         let mut result = HashMap::with_capacity(network_usage_in_10_seconds.len());
@@ -244,8 +298,97 @@ impl Handler {
                 result.insert(name.clone(), total);
             }
         }
+        for (kind, count) in self.connection_log.counts() {
+            result.insert(format!("connections@{}", kind), count);
+        }
+        for usage in self.bandwidth.usage(now) {
+            result.insert(format!("bandwidth@{}", usage.class), usage.sent);
+            if let Some(limit) = usage.limit {
+                result.insert(format!("bandwidth-limit@{}", usage.class), limit);
+            }
+            if usage.queued != 0 {
+                result.insert(format!("bandwidth-queued@{}", usage.class), usage.queued);
+            }
+        }
         result
     }
+
+    fn schedule_release(&self, io: &IoContext<Message>) {
+        if !self.release_scheduled.swap(true, Ordering::SeqCst) {
+            io.register_timer_once(RELEASE_THROTTLED, RELEASE_THROTTLED_INTERVAL);
+        }
+    }
+
+    fn send_extension_message(
+        &self,
+        io: &IoContext<Message>,
+        node_id: NodeId,
+        message: ThrottledMessage,
+    ) -> IoHandlerResult<()> {
+        let ThrottledMessage {
+            extension_name,
+            need_encryption,
+            data,
+        } = message;
+        let stream = *self.remote_node_ids_reverse.read().get(&node_id).ok_or_else(|| Error::InvalidNode(node_id))?;
+        let (network_message_size, peer_addr) = match stream {
+            FIRST_OUTBOUND...LAST_OUTBOUND => {
+                let mut outbound_connections = self.outbound_connections.write();
+                if let Some(con) = outbound_connections.get_mut(&stream) {
+                    let _f = finally(|| {
+                        io.update_registration(stream);
+                    });
+
+                    (
+                        con.enqueue_extension_message(extension_name.to_string(), need_encryption, data)?,
+                        *con.peer_addr(),
+                    )
+                } else {
+                    return Err(format!("{} is an invalid stream", stream).into())
+                }
+            }
+            FIRST_INBOUND...LAST_INBOUND => {
+                let mut inbound_connections = self.inbound_connections.write();
+                if let Some(con) = inbound_connections.get_mut(&stream) {
+                    let _f = finally(|| {
+                        io.update_registration(stream);
+                    });
+                    (
+                        con.enqueue_extension_message(extension_name.to_string(), need_encryption, data)?,
+                        *con.peer_addr(),
+                    )
+                } else {
+                    return Err(format!("{} is an invalid stream", stream).into())
+                }
+            }
+            _ => unreachable!("{} is an invalid stream", stream),
+        };
+        let mut network_usage_in_10_seconds = self.network_usage_in_10_seconds.lock();
+        insert_network_usage(
+            network_usage_in_10_seconds.entry(format!("::{}@{}", extension_name, peer_addr)).or_default(),
+            network_message_size,
+        );
+        Ok(())
+    }
+
+    pub fn connection_log(&self, limit: usize, kind: Option<ConnectionOutcomeKind>) -> Vec<ConnectionAttempt> {
+        self.connection_log.recent(limit, kind)
+    }
+
+    fn record_inbound_attempt(&self, address: SocketAddr, outcome: ConnectionOutcome, key_fingerprint: Option<H128>) {
+        self.connection_log.record(ConnectionAttempt {
+            timestamp: time::get_time().sec as u64,
+            address,
+            outcome,
+            key_fingerprint,
+        });
+    }
+
+    fn record_handshake_failure(&self, con: &IncomingConnection, error: HandshakeError) {
+        if let Ok(address) = con.peer_addr() {
+            self.record_inbound_attempt(address, ConnectionOutcome::HandshakeFailed(error), None);
+        }
+    }
 }
 
 fn retry_sync_timer(stream: StreamToken) -> TimerToken {
@@ -365,6 +508,9 @@ impl IoHandler<Message> for Handler {
             }
             FIRST_WAIT_SYNC...LAST_WAIT_SYNC => {
                 cwarn!(NETWORK, "No sync message from {}", timer);
+                if let Some(con) = self.incoming_connections.read().get(&wait_sync_stream(timer)) {
+                    self.record_handshake_failure(con, HandshakeError::Timeout);
+                }
                 io.deregister_stream(wait_sync_stream(timer));
             }
             FIRST_WAIT_ACK...LAST_WAIT_ACK => {
@@ -397,6 +543,17 @@ impl IoHandler<Message> for Handler {
                     cdebug!(NETWORK, "Cannot retry {} sync", timer);
                 }
             }
+            RELEASE_THROTTLED => {
+                self.release_scheduled.store(false, Ordering::SeqCst);
+                for (node_id, message) in self.bandwidth.release(Instant::now()) {
+                    if let Err(err) = self.send_extension_message(io, node_id, message) {
+                        cdebug!(NETWORK, "Cannot send the throttled message to {}: {:?}", node_id, err);
+                    }
+                }
+                if self.bandwidth.has_queued() {
+                    self.schedule_release(io);
+                }
+            }
             _ => unreachable!(),
         }
         Ok(())
@@ -427,45 +584,26 @@ impl IoHandler<Message> for Handler {
                 need_encryption,
                 data,
             } => {
-                let stream =
-                    *self.remote_node_ids_reverse.read().get(&node_id).ok_or_else(|| Error::InvalidNode(node_id))?;
-                let (network_message_size, peer_addr) = match stream {
-                    FIRST_OUTBOUND...LAST_OUTBOUND => {
-                        let mut outbound_connections = self.outbound_connections.write();
-                        if let Some(con) = outbound_connections.get_mut(&stream) {
-                            let _f = finally(|| {
-                                io.update_registration(stream);
-                            });
-
-                            (
-                                con.enqueue_extension_message(extension_name.to_string(), need_encryption, data)?,
-                                *con.peer_addr(),
-                            )
-                        } else {
-                            return Err(format!("{} is an invalid stream", stream).into())
-                        }
-                    }
-                    FIRST_INBOUND...LAST_INBOUND => {
-                        let mut inbound_connections = self.inbound_connections.write();
-                        if let Some(con) = inbound_connections.get_mut(&stream) {
-                            let _f = finally(|| {
-                                io.update_registration(stream);
-                            });
-                            (
-                                con.enqueue_extension_message(extension_name.to_string(), need_encryption, data)?,
-                                *con.peer_addr(),
-                            )
-                        } else {
-                            return Err(format!("{} is an invalid stream", stream).into())
-                        }
-                    }
-                    _ => unreachable!("{} is an invalid stream", stream),
+                if !self.remote_node_ids_reverse.read().contains_key(&node_id) {
+                    return Err(Error::InvalidNode(node_id).into())
+                }
+                // Only the payloads of the extensions are throttled. The handshakes and the negotiations are not.
+                let size = data.len();
+                let message = ThrottledMessage {
+                    extension_name,
+                    need_encryption,
+                    data,
                 };
-                let mut network_usage_in_10_seconds = self.network_usage_in_10_seconds.lock();
-                insert_network_usage(
-                    network_usage_in_10_seconds.entry(format!("::{}@{}", extension_name, peer_addr)).or_default(),
-                    network_message_size,
-                );
+                match self.bandwidth.admit(node_id, size, message, Instant::now()) {
+                    Admission::Send(message) => self.send_extension_message(io, node_id, message)?,
+                    Admission::Queued => self.schedule_release(io),
+                    Admission::Rejected => cwarn!(
+                        NETWORK,
+                        "The {} message to {} is dropped because too many messages wait for the bandwidth",
+                        extension_name,
+                        node_id
+                    ),
+                }
             }
             Message::Disconnect(socket_address) => {
                 if let Some(stream) = self.remote_node_ids_reverse.read().get(&socket_address.into()) {
@@ -476,6 +614,12 @@ impl IoHandler<Message> for Handler {
                 }
                 self.routing_table.ban(socket_address);
             }
+            Message::DisconnectAll => {
+                for (node_id, stream) in self.remote_node_ids_reverse.read().iter() {
+                    io.deregister_stream(*stream);
+                    cinfo!(NETWORK, "Disconnect {}:{} because the node is shutting down", node_id, stream);
+                }
+            }
             Message::ApplyFilters => {
                 for addr in self.routing_table.established_addresses() {
                     if !self.filters.is_allowed(&addr.ip()) {
@@ -488,6 +632,14 @@ impl IoHandler<Message> for Handler {
                     }
                 }
             }
+            Message::ReportMisbehavior(node_id) => {
+                if self.report_misbehavior(&node_id) {
+                    if let Some(stream) = self.remote_node_ids_reverse.read().get(&node_id) {
+                        io.deregister_stream(*stream);
+                        cinfo!(NETWORK, "Disconnect {}:{} because it's banned", node_id, stream);
+                    }
+                }
+            }
             Message::Established {
                 connection,
                 is_inbound: true,
@@ -513,6 +665,7 @@ impl IoHandler<Message> for Handler {
                     let t = inbound_connections.insert(token, connection);
                     assert!(t.is_none());
                     io.register_stream(token);
+                    self.update_peer_count_metric();
                 } else {
                     cwarn!(NETWORK, "Cannot establish an inbound connection");
                 }
@@ -554,6 +707,7 @@ impl IoHandler<Message> for Handler {
                     let t = outbound_connections.insert(token, connection);
                     assert!(t.is_none());
                     io.register_stream(token);
+                    self.update_peer_count_metric();
                 } else {
                     cwarn!(NETWORK, "Cannot establish an outbound connection");
                 }
@@ -625,6 +779,7 @@ impl IoHandler<Message> for Handler {
                                 self.max_peers,
                                 current_connections
                             );
+                            self.record_inbound_attempt(socket_address, ConnectionOutcome::RateLimited, None);
                             return Ok(())
                         }
                         incoming_connections
@@ -632,13 +787,15 @@ impl IoHandler<Message> for Handler {
                     let ip = socket_address.ip();
                     if !self.filters.is_allowed(&ip) {
                         cwarn!(NETWORK, "P2P connection request from {} is received. But it's not allowed", ip);
+                        self.record_inbound_attempt(socket_address, ConnectionOutcome::Filtered, None);
                         return Ok(())
                     }
-                    let token = self
-                        .incoming_tokens
-                        .lock()
-                        .gen()
-                        .ok_or_else(|| format!("Too many incoming connections: {}", incoming_connections.len()))?;
+                    let token = if let Some(token) = self.incoming_tokens.lock().gen() {
+                        token
+                    } else {
+                        self.record_inbound_attempt(socket_address, ConnectionOutcome::TooManyIncoming, None);
+                        return Err(format!("Too many incoming connections: {}", incoming_connections.len()).into())
+                    };
                     // Please make sure there is no early return after it.
                     let t = incoming_connections.insert(token, IncomingConnection::new(stream));
                     assert!(t.is_none());
@@ -656,18 +813,36 @@ impl IoHandler<Message> for Handler {
                             io.update_registration(stream_token);
                         }
                     });
-                    match con.receive()? {
-                        Some(NetworkMessage::Extension(msg)) => {
+                    let received = match con.receive() {
+                        Err(ConnectionError::Rekey(err)) => {
+                            cwarn!(NETWORK, "Close the session with {}: {}", con.peer_addr(), err);
+                            should_update.store(false, Ordering::SeqCst);
+                            io.deregister_stream(stream_token);
+                            return Ok(())
+                        }
+                        received => received?,
+                    };
+                    match received {
+                        Some((NetworkMessage::Extension(msg), session)) => {
                             let remote_node_id = *self.remote_node_ids.read().get(&stream_token).unwrap_or_else(|| {
                                 unreachable!("Node id for {}:{} must exist", stream_token, con.peer_addr())
                             });
-                            let unencrypted = msg.unencrypted_data(con.session()).map_err(|e| format!("{:?}", e))?;
-                            self.client.on_message(msg.extension_name(), &remote_node_id, unencrypted);
+                            let unencrypted = msg.unencrypted_data(&session).map_err(|e| format!("{:?}", e))?;
+                            if let Err(err) = self.client.on_message(msg.extension_name(), &remote_node_id, unencrypted)
+                            {
+                                self.report_misbehavior(&remote_node_id);
+                                should_update.store(false, Ordering::SeqCst);
+                                io.deregister_stream(stream_token);
+                                return Err(err.into())
+                            }
                         }
-                        Some(NetworkMessage::Negotiation(NegotiationMessage::Request {
-                            extension_name,
-                            extension_versions,
-                        })) => {
+                        Some((
+                            NetworkMessage::Negotiation(NegotiationMessage::Request {
+                                extension_name,
+                                extension_versions,
+                            }),
+                            _,
+                        )) => {
                             let versions = self
                                 .client
                                 .extension_versions()
@@ -697,9 +872,12 @@ impl IoHandler<Message> for Handler {
                                 network_message_size,
                             );
                         }
-                        Some(NetworkMessage::Negotiation(NegotiationMessage::Response {
-                            ..
-                        })) => {
+                        Some((
+                            NetworkMessage::Negotiation(NegotiationMessage::Response {
+                                ..
+                            }),
+                            _,
+                        )) => {
                             should_update.store(false, Ordering::SeqCst);
                             io.deregister_stream(stream_token);
                             return Err(format!(
@@ -725,17 +903,35 @@ impl IoHandler<Message> for Handler {
                             io.update_registration(stream_token);
                         }
                     });
-                    match con.receive()? {
-                        Some(NetworkMessage::Extension(msg)) => {
+                    let received = match con.receive() {
+                        Err(ConnectionError::Rekey(err)) => {
+                            cwarn!(NETWORK, "Close the session with {}: {}", con.peer_addr(), err);
+                            should_update.store(false, Ordering::SeqCst);
+                            io.deregister_stream(stream_token);
+                            return Ok(())
+                        }
+                        received => received?,
+                    };
+                    match received {
+                        Some((NetworkMessage::Extension(msg), session)) => {
                             let remote_node_id = *self.remote_node_ids.read().get(&stream_token).unwrap_or_else(|| {
                                 unreachable!("Node id for {}:{} must exist", stream_token, con.peer_addr())
                             });
-                            let unencrypted = msg.unencrypted_data(con.session()).map_err(|e| format!("{:?}", e))?;
-                            self.client.on_message(msg.extension_name(), &remote_node_id, unencrypted);
+                            let unencrypted = msg.unencrypted_data(&session).map_err(|e| format!("{:?}", e))?;
+                            if let Err(err) = self.client.on_message(msg.extension_name(), &remote_node_id, unencrypted)
+                            {
+                                self.report_misbehavior(&remote_node_id);
+                                should_update.store(false, Ordering::SeqCst);
+                                io.deregister_stream(stream_token);
+                                return Err(err.into())
+                            }
                         }
-                        Some(NetworkMessage::Negotiation(NegotiationMessage::Request {
-                            ..
-                        })) => {
+                        Some((
+                            NetworkMessage::Negotiation(NegotiationMessage::Request {
+                                ..
+                            }),
+                            _,
+                        )) => {
                             should_update.store(false, Ordering::SeqCst);
                             io.deregister_stream(stream_token);
                             return Err(format!(
@@ -744,10 +940,13 @@ impl IoHandler<Message> for Handler {
                             )
                             .into())
                         }
-                        Some(NetworkMessage::Negotiation(NegotiationMessage::Response {
-                            extension_name,
-                            allowed_version,
-                        })) => {
+                        Some((
+                            NetworkMessage::Negotiation(NegotiationMessage::Response {
+                                extension_name,
+                                allowed_version,
+                            }),
+                            _,
+                        )) => {
                             let remote_node_id = *self.remote_node_ids.read().get(&stream_token).unwrap_or_else(|| {
                                 unreachable!("Node id for {}:{} must exist", stream_token, con.peer_addr())
                             });
@@ -770,7 +969,14 @@ impl IoHandler<Message> for Handler {
                             io.update_registration(stream_token);
                         }
                     });
-                    match con.receive()? {
+                    let received = match con.receive() {
+                        Ok(received) => received,
+                        Err(err) => {
+                            self.record_handshake_failure(con, HandshakeError::MalformedMessage);
+                            return Err(err.into())
+                        }
+                    };
+                    match received {
                         Some(OutgoingMessage::Sync1 {
                             initiator_pub_key,
                             network_id,
@@ -778,13 +984,16 @@ impl IoHandler<Message> for Handler {
                         }) => {
                             let from = con.remote_addr(initiator_port)?;
                             if network_id != self.network_id {
+                                self.record_handshake_failure(con, HandshakeError::InvalidNetworkId);
                                 io.deregister_stream(stream_token);
                                 should_update.store(false, Ordering::SeqCst);
                                 return Err(format!("An invalid network id({}) from {}", network_id, from).into())
                             }
                             let network_message_size = if let Some((encrypted_nonce, local_public, session)) =
-                                self.routing_table.set_recipient_establish1(from, initiator_pub_key)?
-                            {
+                                self.routing_table.set_recipient_establish1(from, initiator_pub_key).map_err(|err| {
+                                    self.record_handshake_failure(con, HandshakeError::KeyExchange);
+                                    err
+                                })? {
                                 cinfo!(NETWORK, "Send ack to {}", from);
                                 let network_message_size = con.send_ack(local_public, encrypted_nonce);
                                 let t = self
@@ -816,14 +1025,18 @@ impl IoHandler<Message> for Handler {
                         }) => {
                             let from = con.remote_addr(initiator_port)?;
                             if network_id != self.network_id {
+                                self.record_handshake_failure(con, HandshakeError::InvalidNetworkId);
                                 should_update.store(false, Ordering::SeqCst);
                                 io.deregister_stream(stream_token);
                                 return Err(format!("An invalid network id({}) from {}", network_id, from).into())
                             }
                             let network_message_size = if let Some((encrypted_nonce, local_public, session)) = self
                                 .routing_table
-                                .set_recipient_establish2(from, recipient_pub_key, initiator_pub_key)?
-                            {
+                                .set_recipient_establish2(from, recipient_pub_key, initiator_pub_key)
+                                .map_err(|err| {
+                                    self.record_handshake_failure(con, HandshakeError::KeyExchange);
+                                    err
+                                })? {
                                 cinfo!(NETWORK, "Send ack to {}", from);
                                 let network_message_size = con.send_ack(local_public, encrypted_nonce);
                                 let t = self
@@ -1049,6 +1262,7 @@ impl IoHandler<Message> for Handler {
                 if let Some(con) = inbound_connections.remove(&stream) {
                     if let Some(node_id) = self.remote_node_ids.write().remove(&stream) {
                         assert_ne!(None, self.remote_node_ids_reverse.write().remove(&node_id));
+                        self.bandwidth.remove(&node_id);
                         self.client.on_node_removed(&node_id);
                     } else {
                         unreachable!("{} has no node id", stream);
@@ -1056,6 +1270,7 @@ impl IoHandler<Message> for Handler {
                     con.deregister(event_loop)?;
                     self.routing_table.remove(con.peer_addr());
                     self.inbound_tokens.lock().restore(stream);
+                    self.update_peer_count_metric();
                     ctrace!(NETWORK, "Inbound connect({}) removed", stream);
                 } else {
                     cdebug!(NETWORK, "Invalid inbound token({}) on deregister", stream);
@@ -1066,6 +1281,7 @@ impl IoHandler<Message> for Handler {
                 if let Some(con) = outbound_connections.remove(&stream) {
                     if let Some(node_id) = self.remote_node_ids.write().remove(&stream) {
                         assert_ne!(None, self.remote_node_ids_reverse.write().remove(&node_id));
+                        self.bandwidth.remove(&node_id);
                         self.client.on_node_removed(&node_id);
                     } else {
                         unreachable!("{} has no node id", stream);
@@ -1073,6 +1289,7 @@ impl IoHandler<Message> for Handler {
                     con.deregister(event_loop)?;
                     self.routing_table.remove(con.peer_addr());
                     self.outbound_tokens.lock().restore(stream);
+                    self.update_peer_count_metric();
                     ctrace!(NETWORK, "Outbound connect({}) removed", stream);
                 } else {
                     cdebug!(NETWORK, "Invalid outbound token({}) on deregister", stream);
@@ -1084,10 +1301,12 @@ impl IoHandler<Message> for Handler {
                     con.deregister(event_loop)?;
                     self.incoming_tokens.lock().restore(stream);
                     if let Some((port, session)) = self.establishing_incoming_session.lock().remove(&stream) {
-                        let connection = con.establish(session, port)?;
+                        let socket_address = con.peer_addr()?;
+                        let connection = con.establish(session, port, self.rekey_policy)?;
                         {
                             let peer_addr = connection.peer_addr();
                             if !self.filters.is_allowed(&peer_addr.ip()) {
+                                self.record_inbound_attempt(socket_address, ConnectionOutcome::Filtered, None);
                                 return Err(format!(
                                     "Incoming connection from {} cannot be established because of filter",
                                     peer_addr
@@ -1095,6 +1314,11 @@ impl IoHandler<Message> for Handler {
                                 .into())
                             }
                         }
+                        self.record_inbound_attempt(
+                            socket_address,
+                            ConnectionOutcome::Established,
+                            Some(session.fingerprint()),
+                        );
                         self.channel.send(Message::Established {
                             connection,
                             is_inbound: true,
@@ -1113,7 +1337,7 @@ impl IoHandler<Message> for Handler {
                     con.deregister(event_loop)?;
                     self.outgoing_tokens.lock().restore(stream);
                     if let Some(session) = self.establishing_outgoing_session.lock().remove(&stream) {
-                        let connection = con.establish(session)?;
+                        let connection = con.establish(session, self.rekey_policy)?;
                         {
                             let peer_addr = connection.peer_addr();
                             if !self.filters.is_allowed(&peer_addr.ip()) {
@@ -1151,6 +1375,13 @@ impl From<SymmetricCipherError> for Error {
     }
 }
 
+/// An extension message that may wait for the bandwidth of the peer class.
+pub struct ThrottledMessage {
+    extension_name: &'static str,
+    need_encryption: bool,
+    data: Arc<Bytes>,
+}
+
 pub enum Message {
     RequestConnection(SocketAddr),
     SendExtensionMessage {
@@ -1160,7 +1391,9 @@ pub enum Message {
         data: Arc<Bytes>,
     },
     Disconnect(SocketAddr),
+    DisconnectAll,
     ApplyFilters,
+    ReportMisbehavior(NodeId),
     Established {
         connection: EstablishedConnection,
         is_inbound: bool,
@@ -18,6 +18,7 @@ mod extension;
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::module_inception))]
 mod message;
 mod negotiation;
+mod rekey;
 mod signed_message;
 
 use primitives::H256;
@@ -25,6 +26,7 @@ use primitives::H256;
 pub use self::extension::Message as ExtensionMessage;
 pub use self::message::Message;
 pub use self::negotiation::Message as NegotiationMessage;
+pub use self::rekey::Message as RekeyMessage;
 pub use self::signed_message::SignedMessage;
 pub use crate::session::Nonce;
 
@@ -35,10 +37,14 @@ pub const REQUEST_ID: u8 = 0x05;
 pub const RESPONSE_ID: u8 = 0x06;
 pub const ENCRYPTED_ID: u8 = 0x07;
 pub const UNENCRYPTED_ID: u8 = 0x08;
+pub const REKEY_REQUEST_ID: u8 = 0x09;
+pub const REKEY_RESPONSE_ID: u8 = 0x0a;
 
 #[cfg(test)]
 mod tests {
     use super::ENCRYPTED_ID;
+    use super::REKEY_REQUEST_ID;
+    use super::REKEY_RESPONSE_ID;
     use super::REQUEST_ID;
     use super::RESPONSE_ID;
     use super::UNENCRYPTED_ID;
@@ -70,4 +76,15 @@ mod tests {
         assert_ne!(UNENCRYPTED_ID, RESPONSE_ID);
         assert_ne!(UNENCRYPTED_ID, ENCRYPTED_ID);
     }
+
+    #[test]
+    fn rekey_ids_are_unique() {
+        for id in &[REKEY_REQUEST_ID, REKEY_RESPONSE_ID] {
+            assert_ne!(*id, REQUEST_ID);
+            assert_ne!(*id, RESPONSE_ID);
+            assert_ne!(*id, ENCRYPTED_ID);
+            assert_ne!(*id, UNENCRYPTED_ID);
+        }
+        assert_ne!(REKEY_REQUEST_ID, REKEY_RESPONSE_ID);
+    }
 }
@@ -0,0 +1,75 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
+
+use super::Nonce;
+
+use super::REKEY_REQUEST_ID;
+use super::REKEY_RESPONSE_ID;
+
+#[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Message {
+    Request(Nonce),
+    Response(Nonce),
+}
+
+impl Encodable for Message {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        match self {
+            Message::Request(nonce) => {
+                s.begin_list(2).append(&REKEY_REQUEST_ID).append(nonce);
+            }
+            Message::Response(nonce) => {
+                s.begin_list(2).append(&REKEY_RESPONSE_ID).append(nonce);
+            }
+        }
+    }
+}
+
+impl Decodable for Message {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 2 {
+            return Err(DecoderError::RlpInvalidLength {
+                expected: 2,
+                got: item_count,
+            })
+        }
+        match rlp.val_at(0)? {
+            REKEY_REQUEST_ID => Ok(Message::Request(rlp.val_at(1)?)),
+            REKEY_RESPONSE_ID => Ok(Message::Response(rlp.val_at(1)?)),
+            _ => Err(DecoderError::Custom("Invalid id in rekey message")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rlp::rlp_encode_and_decode_test;
+
+    use super::*;
+
+    #[test]
+    fn encode_and_decode_request() {
+        rlp_encode_and_decode_test!(Message::Request(0x1234_5678_9abc_def0_1234_5678_9abc_def0));
+    }
+
+    #[test]
+    fn encode_and_decode_response() {
+        rlp_encode_and_decode_test!(Message::Response(1));
+    }
+}
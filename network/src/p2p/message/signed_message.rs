@@ -17,7 +17,7 @@
 use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
 
 use super::Signature;
-use crate::session::Session;
+use crate::session::{RekeyingSession, Session};
 
 #[derive(Debug, PartialEq)]
 pub struct SignedMessage {
@@ -37,8 +37,20 @@ impl SignedMessage {
         }
     }
 
-    pub fn is_valid(&self, session: &Session) -> bool {
-        session.sign(&self.message) == self.signature
+    pub fn signed_with_keys<M>(message: &M, keys: &mut RekeyingSession) -> Self
+    where
+        M: Encodable, {
+        let message = message.rlp_bytes().into_vec();
+        let signature = keys.sign(&message);
+        Self {
+            message,
+            signature,
+        }
+    }
+
+    /// Returns the session that signed the message.
+    pub fn signer(&self, keys: &mut RekeyingSession) -> Option<Session> {
+        keys.verify(&self.message, &self.signature)
     }
 }
 
@@ -20,5 +20,5 @@ mod listener;
 mod message;
 mod stream;
 
-pub use self::handler::{Handler, Message};
-use self::message::{ExtensionMessage, Message as NetworkMessage, NegotiationMessage, SignedMessage};
+pub use self::handler::{Handler, Message, ThrottledMessage, MAX_INCOMING_CONNECTIONS};
+use self::message::{ExtensionMessage, Message as NetworkMessage, NegotiationMessage, RekeyMessage, SignedMessage};
@@ -22,7 +22,7 @@ use mio::{Poll, PollOpt, Ready, Token};
 use rlp::{Decodable, DecoderError, Encodable, UntrustedRlp};
 
 use super::SignedMessage;
-use crate::session::Session;
+use crate::session::{RekeyingSession, Session};
 use crate::stream::{Error as StreamError, Stream};
 use crate::SocketAddr;
 
@@ -68,26 +68,25 @@ pub type Result<T> = ::std::result::Result<T, Error>;
 
 pub struct SignedStream {
     stream: Stream,
-    session: Session,
+    keys: RekeyingSession,
 }
 
 impl SignedStream {
-    pub fn new(stream: Stream, session: Session) -> Self {
+    pub fn new(stream: Stream, keys: RekeyingSession) -> Self {
         Self {
             stream,
-            session,
+            keys,
         }
     }
 
-    pub fn read<M>(&mut self) -> Result<Option<M>>
+    /// Returns the message with the session that signed it.
+    pub fn read<M>(&mut self) -> Result<Option<(M, Session)>>
     where
         M: ?Sized + Decodable, {
         if let Some(signed) = self.stream.read::<SignedMessage>()? {
-            if !signed.is_valid(&self.session) {
-                return Err(Error::InvalidSign)
-            }
+            let session = signed.signer(&mut self.keys).ok_or(Error::InvalidSign)?;
             let rlp = UntrustedRlp::new(&signed.message);
-            Ok(Some(rlp.as_val::<M>()?))
+            Ok(Some((rlp.as_val::<M>()?, session)))
         } else {
             Ok(None)
         }
@@ -96,7 +95,14 @@ impl SignedStream {
     pub fn write<M>(&mut self, message: &M) -> usize
     where
         M: Encodable, {
-        self.stream.write(&SignedMessage::new(message, &self.session))
+        self.stream.write(&SignedMessage::signed_with_keys(message, &mut self.keys))
+    }
+
+    /// Writes the message signed with the given session instead of the current one.
+    pub fn write_with_session<M>(&mut self, message: &M, session: &Session) -> usize
+    where
+        M: Encodable, {
+        self.stream.write(&SignedMessage::new(message, session))
     }
 
     pub fn flush(&mut self) -> Result<()> {
@@ -109,7 +115,15 @@ impl SignedStream {
     }
 
     pub fn session(&self) -> &Session {
-        &self.session
+        self.keys.session()
+    }
+
+    pub fn keys(&self) -> &RekeyingSession {
+        &self.keys
+    }
+
+    pub fn keys_mut(&mut self) -> &mut RekeyingSession {
+        &mut self.keys
     }
 
     pub fn shutdown(&self) -> io::Result<()> {
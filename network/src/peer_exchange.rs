@@ -0,0 +1,504 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ctimer::TimerToken;
+use parking_lot::Mutex;
+use rand::prelude::SliceRandom;
+use rand::thread_rng;
+use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
+
+use crate::filters::FiltersControl;
+use crate::routing_table::RoutingTable;
+use crate::{Api, IntoSocketAddr, NetworkExtension, NodeId, SocketAddr};
+
+/// The peers connected within this are shared.
+const RECENTLY_SEEN: Duration = Duration::from_secs(60 * 60);
+/// A peer is answered at most this many times in an interval.
+const MAX_ANSWERS_PER_INTERVAL: usize = 4;
+
+const REFRESH_TOKEN: TimerToken = 0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Config {
+    /// The maximum number of the addresses in a response.
+    pub max_addresses: u8,
+    /// The maximum number of the new candidates learned in an interval.
+    pub max_learned_per_interval: usize,
+    /// How often a connected peer is asked.
+    pub interval: Duration,
+    /// The loopback and the private-range addresses are shared and learned only if it's set.
+    pub share_private_addresses: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_addresses: 16,
+            max_learned_per_interval: 32,
+            interval: Duration::from_secs(5 * 60),
+            share_private_addresses: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Stats {
+    pub requests_sent: u64,
+    pub requests_answered: u64,
+    /// The addresses added to the connection candidates.
+    pub addresses_learned: u64,
+    /// The learned addresses that were connected later.
+    pub addresses_used: u64,
+}
+
+pub enum Event {
+    /// Asks the connected peer without waiting for the interval.
+    Request(NodeId),
+}
+
+#[derive(Debug, PartialEq)]
+enum Message {
+    Request(u8),
+    Response(Vec<SocketAddr>),
+}
+
+impl Encodable for Message {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        match self {
+            Message::Request(len) => {
+                s.append_single_value(len);
+            }
+            Message::Response(addresses) => {
+                s.append_list(addresses);
+            }
+        }
+    }
+}
+
+impl Decodable for Message {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        if rlp.is_int() {
+            Ok(Message::Request(rlp.as_val()?))
+        } else {
+            Ok(Message::Response(rlp.as_list()?))
+        }
+    }
+}
+
+/// Shares the addresses of the peers that this node has handshaked with recently,
+/// so the nodes that know only a few peers can find the others without the discovery.
+pub struct Extension {
+    config: Config,
+    routing_table: Arc<RoutingTable>,
+    filters: Arc<FiltersControl>,
+    stats: Arc<Mutex<Stats>>,
+    api: Box<Api>,
+    nodes: HashSet<NodeId>,
+    /// When the handshaked peers were connected last.
+    recently_seen: HashMap<SocketAddr, Instant>,
+    /// The peers that haven't answered the requests yet.
+    pending: HashSet<NodeId>,
+    /// The number of the answers to each peer in the current interval.
+    answers: HashMap<NodeId, usize>,
+    /// The number of the new candidates learned in the current interval.
+    learned_in_interval: usize,
+    /// The learned addresses that haven't been connected yet.
+    learned: HashSet<SocketAddr>,
+}
+
+impl Extension {
+    pub fn new(
+        config: Config,
+        routing_table: Arc<RoutingTable>,
+        filters: Arc<FiltersControl>,
+        stats: Arc<Mutex<Stats>>,
+        api: Box<Api>,
+    ) -> Self {
+        api.set_timer(REFRESH_TOKEN, config.interval).expect("Refresh must be registered");
+        Self {
+            config,
+            routing_table,
+            filters,
+            stats,
+            api,
+            nodes: Default::default(),
+            recently_seen: Default::default(),
+            pending: Default::default(),
+            answers: Default::default(),
+            learned_in_interval: 0,
+            learned: Default::default(),
+        }
+    }
+
+    /// The banned and the filtered addresses are never shared nor learned.
+    fn is_shareable(&self, address: &SocketAddr) -> bool {
+        if !self.config.share_private_addresses && !address.is_global() {
+            return false
+        }
+        !self.routing_table.is_banned(address) && self.filters.is_allowed(&address.ip())
+    }
+
+    fn request(&mut self, node: &NodeId) {
+        self.pending.insert(*node);
+        self.stats.lock().requests_sent += 1;
+        self.api.send(node, Arc::new(Message::Request(self.config.max_addresses).rlp_bytes().into_vec()));
+    }
+
+    fn addresses_for(&self, requester: &SocketAddr, len: u8, now: Instant) -> Vec<SocketAddr> {
+        let mut addresses: Vec<_> = self
+            .recently_seen
+            .iter()
+            .filter(|(_, seen_at)| now.duration_since(**seen_at) < RECENTLY_SEEN)
+            .map(|(address, _)| *address)
+            .filter(|address| requester.is_reachable(address) && self.is_shareable(address))
+            .collect();
+        addresses.shuffle(&mut thread_rng());
+        addresses.truncate(cmp::min(len, self.config.max_addresses) as usize);
+        addresses
+    }
+
+    fn learn(&mut self, from: &NodeId, addresses: Vec<SocketAddr>) {
+        let known: HashSet<_> = self.routing_table.all_addresses().into_iter().collect();
+        let mut new_addresses = Vec::new();
+        for address in addresses.into_iter().take(self.config.max_addresses as usize) {
+            if known.contains(&address) || new_addresses.contains(&address) || !self.is_shareable(&address) {
+                continue
+            }
+            if self.learned_in_interval >= self.config.max_learned_per_interval {
+                cdebug!(DISCOVERY, "The rest of the addresses from {} are dropped until the next interval", from);
+                break
+            }
+            self.learned_in_interval += 1;
+            new_addresses.push(address);
+        }
+        if new_addresses.is_empty() {
+            return
+        }
+        cinfo!(DISCOVERY, "{} new peers are learned from {}", new_addresses.len(), from);
+        self.stats.lock().addresses_learned += new_addresses.len() as u64;
+        self.learned.extend(new_addresses.iter().cloned());
+        self.routing_table.touch_addresses(new_addresses.iter().cloned());
+        self.routing_table.learn_discovered_peers(new_addresses);
+    }
+
+    fn refresh(&mut self) {
+        let now = Instant::now();
+        for node in &self.nodes {
+            self.recently_seen.insert(node.into_addr(), now);
+        }
+        self.recently_seen.retain(|_, seen_at| now.duration_since(*seen_at) < RECENTLY_SEEN);
+        // The learned addresses that the routing table gave up are not counted anymore.
+        let known: HashSet<_> = self.routing_table.all_addresses().into_iter().collect();
+        self.learned.retain(|address| known.contains(address));
+        self.answers.clear();
+        self.learned_in_interval = 0;
+        self.pending.clear();
+
+        let nodes: Vec<_> = self.nodes.iter().cloned().collect();
+        if let Some(node) = nodes.choose(&mut thread_rng()) {
+            self.request(node);
+        }
+    }
+}
+
+impl NetworkExtension<Event> for Extension {
+    fn name() -> &'static str {
+        "peer-exchange"
+    }
+
+    fn need_encryption() -> bool {
+        false
+    }
+
+    fn versions() -> &'static [u64] {
+        const VERSIONS: &[u64] = &[0];
+        &VERSIONS
+    }
+
+    fn on_node_added(&mut self, node: &NodeId, _version: u64) {
+        let address = node.into_addr();
+        self.nodes.insert(*node);
+        self.recently_seen.insert(address, Instant::now());
+        if self.learned.remove(&address) {
+            self.stats.lock().addresses_used += 1;
+        }
+        self.request(node);
+    }
+
+    fn on_node_removed(&mut self, node: &NodeId) {
+        self.nodes.remove(node);
+        self.pending.remove(node);
+        self.recently_seen.insert(node.into_addr(), Instant::now());
+    }
+
+    fn on_message(&mut self, node: &NodeId, message: &[u8]) {
+        let message = match Message::decode(&UntrustedRlp::new(message)) {
+            Ok(message) => message,
+            Err(err) => {
+                cwarn!(DISCOVERY, "Invalid peer exchange message from {} : {:?}", node, err);
+                self.api.report_misbehavior(node);
+                return
+            }
+        };
+        match message {
+            Message::Request(len) => {
+                let answers = self.answers.entry(*node).or_default();
+                if *answers >= MAX_ANSWERS_PER_INTERVAL {
+                    cdebug!(DISCOVERY, "Too many peer exchange requests from {}", node);
+                    return
+                }
+                *answers += 1;
+                let addresses = self.addresses_for(&node.into_addr(), len, Instant::now());
+                self.stats.lock().requests_answered += 1;
+                self.api.send(node, Arc::new(Message::Response(addresses).rlp_bytes().into_vec()));
+            }
+            Message::Response(addresses) => {
+                if !self.pending.remove(node) {
+                    cdebug!(DISCOVERY, "Unrequested peer exchange response from {}", node);
+                    return
+                }
+                self.learn(node, addresses);
+            }
+        }
+    }
+
+    fn on_timeout(&mut self, timer: TimerToken) {
+        match timer {
+            REFRESH_TOKEN => self.refresh(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn on_event(&mut self, event: Event) {
+        match event {
+            Event::Request(node) => {
+                if !self.nodes.contains(&node) {
+                    cwarn!(DISCOVERY, "Cannot request the peer exchange to {}, which doesn't support it", node);
+                    return
+                }
+                self.request(&node);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::thread;
+
+    use ckey::NetworkId;
+    use ctimer::TimerLoop;
+    use primitives::Bytes;
+    use rlp::rlp_encode_and_decode_test;
+
+    use super::*;
+    use crate::bandwidth::BandwidthLimits;
+    use crate::filters::{FilterEntry, Filters};
+    use crate::session::RekeyPolicy;
+    use crate::{NetworkControl, NetworkControlError, NetworkExtensionResult, NetworkService};
+
+    #[derive(Default)]
+    struct TestApi {
+        sent: Arc<Mutex<Vec<(NodeId, Bytes)>>>,
+    }
+
+    impl Api for TestApi {
+        fn send(&self, node: &NodeId, message: Arc<Bytes>) {
+            self.sent.lock().push((*node, message.to_vec()));
+        }
+
+        fn set_timer(&self, _timer: TimerToken, _d: Duration) -> NetworkExtensionResult<()> {
+            Ok(())
+        }
+
+        fn set_timer_once(&self, _timer: TimerToken, _d: Duration) -> NetworkExtensionResult<()> {
+            Ok(())
+        }
+
+        fn clear_timer(&self, _timer: TimerToken) -> NetworkExtensionResult<()> {
+            Ok(())
+        }
+    }
+
+    fn node_id(address: SocketAddr) -> NodeId {
+        NodeId::new(address.ip(), address.port())
+    }
+
+    fn new_extension(config: Config, blacklist: Vec<FilterEntry>) -> (Extension, Arc<Mutex<Vec<(NodeId, Bytes)>>>) {
+        let api = TestApi::default();
+        let sent = Arc::clone(&api.sent);
+        let filters = Filters::new(Vec::new(), blacklist);
+        let extension = Extension::new(config, RoutingTable::new(), filters, Default::default(), Box::new(api));
+        (extension, sent)
+    }
+
+    fn answer(extension: &mut Extension, sent: &Mutex<Vec<(NodeId, Bytes)>>, requester: SocketAddr) -> Vec<SocketAddr> {
+        extension.on_message(&node_id(requester), &Message::Request(16).rlp_bytes());
+        let (_, response) = sent.lock().pop().unwrap();
+        match rlp::decode(&response) {
+            Message::Response(mut addresses) => {
+                addresses.sort_unstable();
+                addresses
+            }
+            Message::Request(_) => panic!("A request is not an answer"),
+        }
+    }
+
+    #[test]
+    fn encode_and_decode_messages() {
+        rlp_encode_and_decode_test!(Message::Request(16));
+        rlp_encode_and_decode_test!(Message::Response(vec![]));
+        rlp_encode_and_decode_test!(Message::Response(vec![
+            SocketAddr::v4(1, 2, 3, 4, 3485),
+            SocketAddr::v4(127, 0, 0, 1, 3486),
+        ]));
+    }
+
+    #[test]
+    fn private_and_blacklisted_addresses_are_not_shared() {
+        let global = SocketAddr::v4(1, 2, 3, 4, 3485);
+        let private = SocketAddr::v4(192, 168, 0, 2, 3485);
+        let blacklisted = SocketAddr::v4(5, 6, 7, 8, 3485);
+        let requester = SocketAddr::v4(192, 168, 0, 3, 3485);
+        let blacklist = vec![FilterEntry {
+            cidr: "5.6.7.0/24".parse().unwrap(),
+            tag: String::new(),
+        }];
+
+        let (mut extension, sent) = new_extension(Config::default(), blacklist.clone());
+        for address in &[global, private, blacklisted, requester] {
+            extension.on_node_added(&node_id(*address), 0);
+        }
+        assert_eq!(vec![global], answer(&mut extension, &sent, requester));
+
+        let config = Config {
+            share_private_addresses: true,
+            ..Config::default()
+        };
+        let (mut extension, sent) = new_extension(config, blacklist);
+        for address in &[global, private, blacklisted, requester] {
+            extension.on_node_added(&node_id(*address), 0);
+        }
+        assert_eq!(vec![global, private], answer(&mut extension, &sent, requester));
+        // The private addresses are not reachable from the public ones.
+        assert_eq!(vec![global], answer(&mut extension, &sent, SocketAddr::v4(9, 9, 9, 9, 3485)));
+    }
+
+    #[test]
+    fn only_the_requested_responses_are_learned_up_to_the_limit() {
+        let config = Config {
+            max_learned_per_interval: 2,
+            ..Config::default()
+        };
+        let (mut extension, _sent) = new_extension(config, Vec::new());
+        let peer = node_id(SocketAddr::v4(1, 1, 1, 1, 3485));
+        let addresses: Vec<_> = (0..4).map(|i| SocketAddr::v4(2, 2, 2, i, 3485)).collect();
+        let response = Message::Response(addresses.clone()).rlp_bytes();
+
+        extension.on_message(&peer, &response);
+        assert_eq!(0, extension.stats.lock().addresses_learned);
+
+        extension.on_node_added(&peer, 0);
+        extension.on_message(&peer, &response);
+        assert_eq!(2, extension.stats.lock().addresses_learned);
+        // The answered request is not pending anymore.
+        extension.on_message(&peer, &response);
+        assert_eq!(2, extension.stats.lock().addresses_learned);
+
+        extension.on_node_added(&node_id(addresses[0]), 0);
+        assert_eq!(
+            Stats {
+                requests_sent: 2,
+                requests_answered: 0,
+                addresses_learned: 2,
+                addresses_used: 1,
+            },
+            *extension.stats.lock()
+        );
+    }
+
+    fn start_service(port: u16, bootstrap_addresses: Vec<SocketAddr>) -> Arc<NetworkService> {
+        let config = Config {
+            share_private_addresses: true,
+            ..Config::default()
+        };
+        let service = NetworkService::start(
+            NetworkId::from("tc"),
+            TimerLoop::new(2),
+            SocketAddr::v4(127, 0, 0, 1, port),
+            bootstrap_addresses,
+            3,
+            10,
+            Filters::new(Vec::new(), Vec::new()),
+            RoutingTable::new(),
+            None,
+            HashMap::new(),
+            RekeyPolicy::disabled(),
+            16,
+            BandwidthLimits::default(),
+            None,
+        )
+        .unwrap();
+        service.start_peer_exchange(config);
+        service
+    }
+
+    fn wait_until<F: Fn() -> bool>(condition: F) -> bool {
+        for _ in 0..300 {
+            if condition() {
+                return true
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        false
+    }
+
+    #[test]
+    fn third_node_learns_the_first_via_the_second() {
+        let first = SocketAddr::v4(127, 0, 0, 1, 13_485);
+        let second = SocketAddr::v4(127, 0, 0, 1, 13_486);
+        let first_service = start_service(first.port(), Vec::new());
+        let second_service = start_service(second.port(), vec![first]);
+        assert!(wait_until(|| second_service.is_connected(&first).unwrap()));
+
+        // The third knows only the second.
+        let third_service = start_service(13_487, vec![second]);
+        assert!(wait_until(|| third_service.is_connected(&second).unwrap()));
+        assert!(wait_until(|| third_service.peer_exchange_stats().unwrap().addresses_learned == 1));
+        assert!(wait_until(|| third_service.is_connected(&first).unwrap()));
+        assert!(wait_until(|| third_service.peer_exchange_stats().unwrap().addresses_used == 1));
+
+        // Nothing new is learned from the manual request.
+        third_service.request_peer_exchange(second).unwrap();
+        assert!(wait_until(|| third_service.peer_exchange_stats().unwrap().requests_sent >= 3));
+        assert_eq!(1, third_service.peer_exchange_stats().unwrap().addresses_learned);
+        assert!(second_service.peer_exchange_stats().unwrap().requests_answered >= 2);
+        match third_service.request_peer_exchange(SocketAddr::v4(127, 0, 0, 1, 13_488)) {
+            Err(NetworkControlError::NotConnected) => {}
+            result => panic!("The unknown peer is requested: {:?}", result),
+        }
+
+        for service in &[first_service, second_service, third_service] {
+            service.disconnect_all();
+        }
+    }
+}
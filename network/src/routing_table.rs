@@ -24,6 +24,7 @@ use primitives::Bytes;
 use rand::rngs::OsRng;
 use rand::Rng;
 
+use crate::discovered_peers::{DiscoveredPeer, DiscoveredPeers};
 use crate::session::{Nonce, Session};
 use crate::SocketAddr;
 
@@ -121,8 +122,13 @@ impl Default for State {
     }
 }
 
+/// The node is banned when it misbehaves this many times.
+const MAX_MISBEHAVIOR_SCORE: usize = 3;
+
 pub struct RoutingTable {
     entries: RwLock<HashMap<SocketAddr, State>>,
+    misbehavior_scores: Mutex<HashMap<SocketAddr, usize>>,
+    discovered_peers: Mutex<DiscoveredPeers>,
 
     rng: Mutex<OsRng>,
 }
@@ -131,6 +137,8 @@ impl RoutingTable {
     pub fn new() -> Arc<Self> {
         Arc::new(Self {
             entries: RwLock::new(HashMap::new()),
+            misbehavior_scores: Mutex::new(HashMap::new()),
+            discovered_peers: Default::default(),
             rng: Mutex::new(OsRng::new().unwrap()),
         })
     }
@@ -601,6 +609,19 @@ impl RoutingTable {
             _ => return false,
         }
         *entry = State::default();
+        self.misbehavior_scores.lock().remove(&target);
+        true
+    }
+
+    /// Returns true if the node is banned because of this misbehavior.
+    pub fn report_misbehavior(&self, target: SocketAddr) -> bool {
+        let mut scores = self.misbehavior_scores.lock();
+        let score = scores.entry(target).or_default();
+        *score += 1;
+        if *score < MAX_MISBEHAVIOR_SCORE {
+            return false
+        }
+        self.ban(target);
         true
     }
 
@@ -618,6 +639,53 @@ impl RoutingTable {
         let entry = entries.entry(target).or_default();
         entry.local_public().cloned()
     }
+
+    pub fn discovered_peers(&self) -> Vec<DiscoveredPeer> {
+        self.discovered_peers.lock().to_vec()
+    }
+
+    /// Replaces the discovered peers with the saved ones, and adds them as candidates.
+    pub fn restore_discovered_peers(&self, peers: Vec<DiscoveredPeer>, now: u64) {
+        self.touch_addresses(peers.iter().map(|peer| peer.address));
+        *self.discovered_peers.lock() = DiscoveredPeers::restore(peers, now);
+    }
+
+    pub fn learn_discovered_peers<I: IntoIterator<Item = SocketAddr>>(&self, targets: I) {
+        let mut discovered_peers = self.discovered_peers.lock();
+        for target in targets {
+            discovered_peers.learn(target);
+        }
+    }
+
+    pub fn mark_discovered_peer_seen(&self, target: SocketAddr, now: u64) {
+        self.discovered_peers.lock().seen(target, now);
+    }
+
+    /// Returns true if the peer decayed out because of this failure.
+    /// The candidate entry of the decayed peer is removed too, but the established connection is kept.
+    pub fn mark_discovered_peer_failed(&self, target: &SocketAddr) -> bool {
+        if !self.discovered_peers.lock().fail(target) {
+            return false
+        }
+        if !self.is_establishing_or_established(target) {
+            self.remove(target);
+        }
+        true
+    }
+
+    pub fn stale_discovered_peers(&self, now: u64) -> Vec<SocketAddr> {
+        self.discovered_peers.lock().stale_addresses(now)
+    }
+
+    /// Returns false if the peer is banned.
+    pub fn add_seed_peer(&self, target: SocketAddr) -> bool {
+        if self.is_banned(&target) {
+            return false
+        }
+        self.touch_addresses(vec![target]);
+        self.discovered_peers.lock().learn(target);
+        true
+    }
 }
 
 fn decrypt_nonce(encrypted_bytes: &[u8], shared_secret: &Secret) -> Result<Nonce, SymmetricCipherError> {
@@ -671,4 +739,21 @@ mod tests {
         let nonce = ::std::u128::MAX;
         encrypt_and_decrypt(secret, nonce);
     }
+
+    #[test]
+    fn repeated_misbehavior_bans_the_node() {
+        let routing_table = RoutingTable::new();
+        let target = SocketAddr::v4(127, 0, 0, 1, 3485);
+
+        for _ in 1..MAX_MISBEHAVIOR_SCORE {
+            assert!(!routing_table.report_misbehavior(target));
+            assert!(!routing_table.is_banned(&target));
+        }
+        assert!(routing_table.report_misbehavior(target));
+        assert!(routing_table.is_banned(&target));
+
+        // Unbanning gives the node a fresh start.
+        assert!(routing_table.unban(target));
+        assert!(!routing_table.report_misbehavior(target));
+    }
 }
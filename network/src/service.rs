@@ -15,27 +15,44 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use std::collections::HashMap;
+use std::fs;
 use std::net::IpAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use cidr::IpCidr;
 use cio::{IoError, IoService};
 use ckey::{NetworkId, Public};
 use crossbeam_channel::Sender;
-use ctimer::TimerLoop;
+use ctimer::{TimerApi, TimerLoop};
+use parking_lot::Mutex;
 
+use crate::bandwidth::{Bandwidth, BandwidthLimits};
 use crate::client::Client;
-use crate::control::{Control, Error as ControlError};
-use crate::filters::{FilterEntry, FiltersControl};
+use crate::connection_log::{ConnectionAttempt, ConnectionOutcomeKind};
+use crate::control::{Control, Error as ControlError, PeerSession};
+use crate::discovered_peers::DiscoveredPeer;
+use crate::filters::{FilterDecision, FilterEntry, FiltersControl};
+use crate::nat::{
+    NatStatus, NatType, PortMapping, MAPPING_LEASE, MAPPING_TIMER_TOKEN, RENEWAL_CHECK_INTERVAL, RENEWAL_TIMER_TOKEN,
+};
+use crate::peer_exchange::{self, Config as PeerExchangeConfig, Stats as PeerExchangeStats};
 use crate::routing_table::RoutingTable;
-use crate::{p2p, Api, NetworkExtension, SocketAddr};
+use crate::session::RekeyPolicy;
+use crate::{p2p, Api, NetworkExtension, NodeId, SocketAddr};
 
 pub struct Service {
     p2p: IoService<p2p::Message>,
     client: Arc<Client>,
     routing_table: Arc<RoutingTable>,
     p2p_handler: Arc<p2p::Handler>,
+    bandwidth: Arc<Bandwidth<p2p::ThrottledMessage>>,
+    bandwidth_limits_path: Option<PathBuf>,
     filters_control: Arc<FiltersControl>,
+    port_mapping: Arc<PortMapping>,
+    _port_mapping_timer: TimerApi,
+    peer_exchange: Mutex<Option<(Sender<peer_exchange::Event>, Arc<Mutex<PeerExchangeStats>>)>>,
 }
 
 impl Service {
@@ -48,21 +65,48 @@ impl Service {
         max_peers: usize,
         filters_control: Arc<FiltersControl>,
         routing_table: Arc<RoutingTable>,
+        nat: Option<NatType>,
+        message_size_limits: HashMap<String, usize>,
+        rekey_policy: RekeyPolicy,
+        connection_log_size: usize,
+        bandwidth_limits: BandwidthLimits,
+        bandwidth_limits_path: Option<PathBuf>,
     ) -> Result<Arc<Self>, Error> {
         let p2p = IoService::start("P2P")?;
 
-        let client = Client::new(p2p.channel(), timer_loop);
+        let port_mapping_timer = timer_loop.new_timer_with_name("NAT port mapping");
+        let port_mapping = Arc::new(match nat {
+            Some(nat) => PortMapping::new(nat.gateway(), address.port(), MAPPING_LEASE),
+            None => PortMapping::disabled(address.port()),
+        });
+        if nat.is_some() {
+            port_mapping_timer.set_handler(Arc::downgrade(&port_mapping));
+            // Searching the gateway may take a while, so don't block the startup.
+            port_mapping_timer
+                .schedule_once(Duration::from_secs(0), MAPPING_TIMER_TOKEN)
+                .map_err(|err| format!("Cannot schedule the port mapping: {:?}", err))?;
+            port_mapping_timer
+                .schedule_repeat(RENEWAL_CHECK_INTERVAL, RENEWAL_TIMER_TOKEN)
+                .map_err(|err| format!("Cannot schedule the port mapping renewal: {:?}", err))?;
+        }
+
+        let bandwidth = Arc::new(Bandwidth::new(bandwidth_limits, Instant::now()));
+        let client = Client::new(p2p.channel(), timer_loop, message_size_limits, Arc::clone(&bandwidth));
 
         let p2p_handler = Arc::new(p2p::Handler::try_new(
             p2p.channel(),
             network_id,
             address,
+            Arc::clone(&port_mapping),
             Arc::clone(&client),
             Arc::clone(&routing_table),
             Arc::clone(&filters_control),
             bootstrap_addresses,
             min_peers,
             max_peers,
+            rekey_policy,
+            connection_log_size,
+            Arc::clone(&bandwidth),
         )?);
         p2p.register_handler(p2p_handler.clone())?;
 
@@ -71,7 +115,12 @@ impl Service {
             client,
             routing_table,
             p2p_handler,
+            bandwidth,
+            bandwidth_limits_path,
             filters_control,
+            port_mapping,
+            _port_mapping_timer: port_mapping_timer,
+            peer_exchange: Mutex::new(None),
         }))
     }
 
@@ -83,10 +132,39 @@ impl Service {
         self.client.register_extension(factory)
     }
 
+    /// Shares the recently connected peers with the peers that also started it.
+    pub fn start_peer_exchange(&self, config: PeerExchangeConfig) {
+        let mut registered = self.peer_exchange.lock();
+        if registered.is_some() {
+            cwarn!(NETWORK, "The peer exchange is already started");
+            return
+        }
+        let stats = Arc::new(Mutex::new(PeerExchangeStats::default()));
+        let routing_table = Arc::clone(&self.routing_table);
+        let filters_control = Arc::clone(&self.filters_control);
+        let extension_stats = Arc::clone(&stats);
+        let sender = self.register_extension(move |api| {
+            peer_exchange::Extension::new(config, routing_table, filters_control, extension_stats, api)
+        });
+        *registered = Some((sender, stats));
+    }
+
     pub fn connect_to(&self, address: SocketAddr) -> Result<(), String> {
         self.p2p.send_message(p2p::Message::RequestConnection(address)).map_err(|e| format!("{:?}", e))?;
         Ok(())
     }
+
+    /// Disconnects all peers. Used when the node is shutting down.
+    pub fn disconnect_all(&self) {
+        if let Err(err) = self.p2p.send_message(p2p::Message::DisconnectAll) {
+            cerror!(NETWORK, "Error occurred while sending message DisconnectAll: {:?}", err);
+        }
+    }
+
+    /// Removes the port mapping from the NAT gateway. Used when the node is shutting down.
+    pub fn remove_port_mapping(&self) {
+        self.port_mapping.remove();
+    }
 }
 
 impl Control for Service {
@@ -138,6 +216,10 @@ impl Control for Service {
         Ok(self.p2p_handler.established_peers())
     }
 
+    fn peer_sessions(&self) -> Result<Vec<PeerSession>, ControlError> {
+        Ok(self.p2p_handler.peer_sessions())
+    }
+
     fn add_to_whitelist(&self, addr: IpCidr, tag: Option<String>) -> Result<(), ControlError> {
         self.filters_control.add_to_whitelist(addr, tag);
         Ok(())
@@ -164,6 +246,29 @@ impl Control for Service {
         Ok(())
     }
 
+    fn replace_blacklist(&self, entries: Vec<(FilterEntry, Option<Duration>)>) -> Result<(), ControlError> {
+        self.filters_control.replace_blacklist(entries);
+        if let Err(err) = self.p2p.send_message(p2p::Message::ApplyFilters) {
+            cerror!(NETWORK, "Error occurred while apply filters: {:?}", err);
+        }
+        Ok(())
+    }
+
+    fn remove_by_tag(&self, tag: &str) -> Result<usize, ControlError> {
+        let removed = self.filters_control.remove_by_tag(tag);
+        // The whitelist may not allow the connected peers anymore.
+        if removed != 0 {
+            if let Err(err) = self.p2p.send_message(p2p::Message::ApplyFilters) {
+                cerror!(NETWORK, "Error occurred while apply filters: {:?}", err);
+            }
+        }
+        Ok(removed)
+    }
+
+    fn list_by_tag(&self, tag: &str) -> Result<(Vec<IpCidr>, Vec<IpCidr>), ControlError> {
+        Ok(self.filters_control.list_by_tag(tag))
+    }
+
     fn enable_whitelist(&self) -> Result<(), ControlError> {
         self.filters_control.enable_whitelist();
         if let Err(err) = self.p2p.send_message(p2p::Message::ApplyFilters) {
@@ -198,9 +303,73 @@ impl Control for Service {
         Ok(self.filters_control.get_blacklist())
     }
 
+    fn test_filter(&self, addr: &IpAddr) -> Result<FilterDecision, ControlError> {
+        Ok(self.filters_control.evaluate(addr))
+    }
+
     fn recent_network_usage(&self) -> Result<HashMap<String, usize>, ControlError> {
         Ok(self.p2p_handler.recent_network_usage())
     }
+
+    fn connection_log(
+        &self,
+        limit: usize,
+        outcome: Option<ConnectionOutcomeKind>,
+    ) -> Result<Vec<ConnectionAttempt>, ControlError> {
+        Ok(self.p2p_handler.connection_log(limit, outcome))
+    }
+
+    fn nat_status(&self) -> Result<NatStatus, ControlError> {
+        Ok(self.port_mapping.status())
+    }
+
+    fn refresh_nat_mapping(&self) -> Result<NatStatus, ControlError> {
+        Ok(self.port_mapping.refresh())
+    }
+
+    fn extension_message_size_limits(&self) -> Result<HashMap<String, usize>, ControlError> {
+        Ok(self.client.message_size_limits())
+    }
+
+    fn bandwidth_limits(&self) -> Result<BandwidthLimits, ControlError> {
+        Ok(self.bandwidth.limits())
+    }
+
+    fn set_bandwidth_limits(&self, limits: BandwidthLimits) -> Result<(), ControlError> {
+        cinfo!(NETWORK, "The bandwidth limits are changed to {:?}", limits);
+        self.bandwidth.set_limits(limits, Instant::now());
+        if let Some(path) = &self.bandwidth_limits_path {
+            if let Err(err) = fs::write(path, limits.to_string()) {
+                cerror!(NETWORK, "Cannot save the bandwidth limits to {}: {}", path.display(), err);
+            }
+        }
+        Ok(())
+    }
+
+    fn discovered_peers(&self) -> Result<Vec<DiscoveredPeer>, ControlError> {
+        Ok(self.routing_table.discovered_peers())
+    }
+
+    fn add_seed_peer(&self, addr: SocketAddr) -> Result<bool, ControlError> {
+        Ok(self.routing_table.add_seed_peer(addr))
+    }
+
+    fn request_peer_exchange(&self, addr: SocketAddr) -> Result<(), ControlError> {
+        let registered = self.peer_exchange.lock();
+        let (sender, _) = registered.as_ref().ok_or(ControlError::Disabled)?;
+        if !self.routing_table.is_established(&addr) {
+            return Err(ControlError::NotConnected)
+        }
+        let node = NodeId::new(addr.ip(), addr.port());
+        sender.send(peer_exchange::Event::Request(node)).map_err(|_| ControlError::Disabled)
+    }
+
+    fn peer_exchange_stats(&self) -> Result<PeerExchangeStats, ControlError> {
+        let registered = self.peer_exchange.lock();
+        let (_, stats) = registered.as_ref().ok_or(ControlError::Disabled)?;
+        let stats = *stats.lock();
+        Ok(stats)
+    }
 }
 
 #[derive(Debug)]
@@ -220,3 +389,74 @@ impl From<String> for Error {
         Error::General(err)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::{self, TcpStream};
+    use std::thread;
+
+    use super::*;
+    use crate::filters::Filters;
+    use crate::p2p::MAX_INCOMING_CONNECTIONS;
+
+    fn start_service(port: u16, max_peers: usize) -> Arc<Service> {
+        Service::start(
+            NetworkId::from("tc"),
+            TimerLoop::new(2),
+            SocketAddr::v4(127, 0, 0, 1, port),
+            Vec::new(),
+            1,
+            max_peers,
+            Filters::new(Vec::new(), Vec::new()),
+            RoutingTable::new(),
+            None,
+            HashMap::new(),
+            RekeyPolicy::disabled(),
+            64,
+            BandwidthLimits::default(),
+            None,
+        )
+        .unwrap()
+    }
+
+    /// Opens one more connection than the incoming slots. None of them sends the sync message.
+    fn flood(port: u16) -> Vec<TcpStream> {
+        let address = net::SocketAddr::from(SocketAddr::v4(127, 0, 0, 1, port));
+        (0..=MAX_INCOMING_CONNECTIONS).map(|_| TcpStream::connect(address).unwrap()).collect()
+    }
+
+    fn count(service: &Service, kind: ConnectionOutcomeKind) -> usize {
+        service.connection_log(100, Some(kind)).unwrap().len()
+    }
+
+    fn wait_until<F: Fn() -> bool>(condition: F) -> bool {
+        for _ in 0..300 {
+            if condition() {
+                return true
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        false
+    }
+
+    #[test]
+    fn connections_over_max_peers_are_rate_limited() {
+        let port = 13_495;
+        let service = start_service(port, MAX_INCOMING_CONNECTIONS - 1);
+        let _streams = flood(port);
+
+        assert!(wait_until(|| count(&service, ConnectionOutcomeKind::RateLimited) == 1));
+        assert_eq!(0, count(&service, ConnectionOutcomeKind::TooManyIncoming));
+    }
+
+    #[test]
+    fn connections_over_the_incoming_slots_are_not_rate_limited() {
+        let port = 13_496;
+        // The node can have more peers, but all the incoming slots are waiting for the sync message.
+        let service = start_service(port, MAX_INCOMING_CONNECTIONS);
+        let _streams = flood(port);
+
+        assert!(wait_until(|| count(&service, ConnectionOutcomeKind::TooManyIncoming) == 1));
+        assert_eq!(0, count(&service, ConnectionOutcomeKind::RateLimited));
+    }
+}
@@ -14,8 +14,10 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod rekey;
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::module_inception))]
 mod session;
 
 pub type Nonce = u128;
+pub use self::rekey::{RekeyError, RekeyPolicy, RekeyingSession, DEFAULT_REKEY_BYTES, DEFAULT_REKEY_INTERVAL};
 pub use self::session::Session;
@@ -0,0 +1,380 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use primitives::H256;
+
+use super::{Nonce, Session};
+
+/// The recommended thresholds. The node doesn't rekey unless they are configured.
+pub const DEFAULT_REKEY_BYTES: usize = 1 << 30;
+pub const DEFAULT_REKEY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// How long the initiator waits for the response of its rekey request.
+pub const REKEY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Decides when a session initiates a rekey.
+/// A zero threshold is never reached.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RekeyPolicy {
+    /// The number of the bytes signed or verified with a session key.
+    pub max_bytes: usize,
+    /// How long a session key is used.
+    pub max_interval: Duration,
+}
+
+impl RekeyPolicy {
+    pub fn disabled() -> Self {
+        Self {
+            max_bytes: 0,
+            max_interval: Duration::from_secs(0),
+        }
+    }
+}
+
+impl Default for RekeyPolicy {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_REKEY_BYTES,
+            max_interval: DEFAULT_REKEY_INTERVAL,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RekeyError {
+    /// The peer sent a response, but there is no request.
+    UnexpectedResponse,
+    /// Both sides sent the requests with the same nonce.
+    Collision,
+    /// The peer didn't answer the request in time.
+    Timeout,
+}
+
+impl fmt::Display for RekeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RekeyError::UnexpectedResponse => write!(f, "Unexpected rekey response"),
+            RekeyError::Collision => write!(f, "Rekey requests collided"),
+            RekeyError::Timeout => write!(f, "Rekey timed out"),
+        }
+    }
+}
+
+/// The session keys of a connection that are replaced from time to time.
+///
+/// Either side can start a rekey by sending a request with a nonce, and the other side answers with its own nonce.
+/// The responder switches to the new key right after it sends the response with the old key,
+/// and the initiator switches when it receives the response.
+/// Until the peer uses the new key, the frames signed with the previous key are still accepted,
+/// because the peer may have sent them before it knew about the new key.
+/// If both sides send the requests at the same time, the request with the greater nonce wins.
+pub struct RekeyingSession {
+    current: Session,
+    previous: Option<Session>,
+    /// The nonce of the request that this side sent, and when it was sent.
+    pending: Option<(Nonce, Instant)>,
+    policy: RekeyPolicy,
+    bytes: usize,
+    rekeyed_at: Instant,
+    rekeys: u64,
+}
+
+impl RekeyingSession {
+    pub fn new(session: Session, policy: RekeyPolicy, now: Instant) -> Self {
+        Self {
+            current: session,
+            previous: None,
+            pending: None,
+            policy,
+            bytes: 0,
+            rekeyed_at: now,
+            rekeys: 0,
+        }
+    }
+
+    /// The session that the outgoing frames use.
+    pub fn session(&self) -> &Session {
+        &self.current
+    }
+
+    pub fn rekeys(&self) -> u64 {
+        self.rekeys
+    }
+
+    pub fn sign(&mut self, data: &[u8]) -> H256 {
+        self.bytes += data.len();
+        self.current.sign(data)
+    }
+
+    /// Returns the session that made the signature, or None if no acceptable session made it.
+    pub fn verify(&mut self, data: &[u8], signature: &H256) -> Option<Session> {
+        if self.current.sign(data) == *signature {
+            // The peer uses the current key, so the previous key is never used again.
+            self.previous = None;
+            self.bytes += data.len();
+            return Some(self.current)
+        }
+        let previous = self.previous?;
+        if previous.sign(data) == *signature {
+            self.bytes += data.len();
+            return Some(previous)
+        }
+        None
+    }
+
+    pub fn should_rekey(&self, now: Instant) -> bool {
+        if self.pending.is_some() {
+            return false
+        }
+        let zero = Duration::from_secs(0);
+        (self.policy.max_bytes != 0 && self.policy.max_bytes <= self.bytes)
+            || (self.policy.max_interval != zero && self.rekeyed_at + self.policy.max_interval <= now)
+    }
+
+    /// Records the request that the caller sends with the current session.
+    pub fn start(&mut self, nonce: Nonce, now: Instant) {
+        debug_assert_eq!(None, self.pending);
+        self.pending = Some((nonce, now));
+    }
+
+    /// Switches to the new session for the peer's request.
+    /// Returns the old session with which the caller must send the response,
+    /// or None if the request lost the collision with this side's request.
+    pub fn accept_request(
+        &mut self,
+        initiator_nonce: Nonce,
+        responder_nonce: Nonce,
+        now: Instant,
+    ) -> Result<Option<Session>, RekeyError> {
+        if let Some((nonce, _)) = self.pending {
+            if initiator_nonce < nonce {
+                // The peer answers this side's request instead.
+                return Ok(None)
+            }
+            if initiator_nonce == nonce {
+                return Err(RekeyError::Collision)
+            }
+        }
+        let old = self.current;
+        self.switch(initiator_nonce, responder_nonce, now);
+        Ok(Some(old))
+    }
+
+    /// Switches to the new session for the peer's response.
+    pub fn accept_response(&mut self, responder_nonce: Nonce, now: Instant) -> Result<(), RekeyError> {
+        let (initiator_nonce, _) = self.pending.ok_or(RekeyError::UnexpectedResponse)?;
+        self.switch(initiator_nonce, responder_nonce, now);
+        Ok(())
+    }
+
+    pub fn check_timeout(&self, now: Instant) -> Result<(), RekeyError> {
+        match self.pending {
+            Some((_, sent_at)) if sent_at + REKEY_TIMEOUT < now => Err(RekeyError::Timeout),
+            _ => Ok(()),
+        }
+    }
+
+    fn switch(&mut self, initiator_nonce: Nonce, responder_nonce: Nonce, now: Instant) {
+        self.previous = Some(self.current);
+        self.current = self.current.rekeyed(initiator_nonce, responder_nonce);
+        self.pending = None;
+        self.bytes = 0;
+        self.rekeyed_at = now;
+        self.rekeys += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use ckey::Secret;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use super::*;
+
+    #[derive(Debug)]
+    enum Frame {
+        Data(u32),
+        Request(Nonce),
+        Response(Nonce),
+    }
+
+    impl Frame {
+        fn bytes(&self) -> Vec<u8> {
+            format!("{:?}", self).into_bytes()
+        }
+    }
+
+    /// Does what the established connection does with the frames.
+    struct Peer {
+        keys: RekeyingSession,
+        outbox: VecDeque<(Frame, H256)>,
+        sent: u32,
+        received: Vec<u32>,
+    }
+
+    impl Peer {
+        fn new(session: Session, policy: RekeyPolicy, now: Instant) -> Self {
+            Self {
+                keys: RekeyingSession::new(session, policy, now),
+                outbox: VecDeque::new(),
+                sent: 0,
+                received: Vec::new(),
+            }
+        }
+
+        fn push(&mut self, frame: Frame, session: Option<Session>) {
+            let signature = match session {
+                Some(session) => session.sign(&frame.bytes()),
+                None => self.keys.sign(&frame.bytes()),
+            };
+            self.outbox.push_back((frame, signature));
+        }
+
+        fn send_data(&mut self, rng: &mut OsRng, now: Instant) {
+            if self.keys.should_rekey(now) {
+                let nonce = rng.gen();
+                self.keys.start(nonce, now);
+                self.push(Frame::Request(nonce), None);
+            }
+            self.push(Frame::Data(self.sent), None);
+            self.sent += 1;
+        }
+
+        fn receive(&mut self, frame: Frame, signature: H256, rng: &mut OsRng, now: Instant) {
+            self.keys.check_timeout(now).unwrap();
+            assert!(self.keys.verify(&frame.bytes(), &signature).is_some(), "{:?} has an invalid signature", frame);
+            match frame {
+                Frame::Data(index) => self.received.push(index),
+                Frame::Request(initiator_nonce) => {
+                    let responder_nonce = rng.gen();
+                    if let Some(old) = self.keys.accept_request(initiator_nonce, responder_nonce, now).unwrap() {
+                        self.push(Frame::Response(responder_nonce), Some(old));
+                    }
+                }
+                Frame::Response(responder_nonce) => self.keys.accept_response(responder_nonce, now).unwrap(),
+            }
+        }
+    }
+
+    fn deliver(from: &mut Peer, to: &mut Peer, count: usize, rng: &mut OsRng, now: Instant) {
+        for _ in 0..count {
+            if let Some((frame, signature)) = from.outbox.pop_front() {
+                to.receive(frame, signature, rng, now);
+            }
+        }
+    }
+
+    #[test]
+    fn rekeys_under_concurrent_traffic_lose_no_frame() {
+        let mut rng = OsRng::new().unwrap();
+        let now = Instant::now();
+        let session = Session::new(Secret::random(), rng.gen());
+        let policy = RekeyPolicy {
+            max_bytes: 100,
+            max_interval: Duration::from_secs(0),
+        };
+        let mut a = Peer::new(session, policy, now);
+        let mut b = Peer::new(session, policy, now);
+
+        for _ in 0..1000 {
+            for _ in 0..rng.gen_range(0, 3) {
+                a.send_data(&mut rng, now);
+            }
+            for _ in 0..rng.gen_range(0, 3) {
+                b.send_data(&mut rng, now);
+            }
+            let count = rng.gen_range(0, 4);
+            deliver(&mut a, &mut b, count, &mut rng, now);
+            let count = rng.gen_range(0, 4);
+            deliver(&mut b, &mut a, count, &mut rng, now);
+        }
+        while !a.outbox.is_empty() || !b.outbox.is_empty() {
+            deliver(&mut a, &mut b, 1, &mut rng, now);
+            deliver(&mut b, &mut a, 1, &mut rng, now);
+        }
+
+        assert_eq!((0..a.sent).collect::<Vec<_>>(), b.received);
+        assert_eq!((0..b.sent).collect::<Vec<_>>(), a.received);
+        assert!(a.keys.rekeys() >= 10, "Only {} rekeys", a.keys.rekeys());
+        assert_eq!(a.keys.rekeys(), b.keys.rekeys());
+        assert_eq!(a.keys.session(), b.keys.session());
+    }
+
+    #[test]
+    fn the_greater_nonce_wins_the_collision() {
+        let now = Instant::now();
+        let session = Session::new(Secret::random(), 1000);
+        let mut a = RekeyingSession::new(session, RekeyPolicy::disabled(), now);
+        let mut b = RekeyingSession::new(session, RekeyPolicy::disabled(), now);
+        a.start(1, now);
+        b.start(2, now);
+
+        assert_eq!(Ok(None), b.accept_request(1, 3, now));
+        assert_eq!(Ok(Some(session)), a.accept_request(2, 4, now));
+        assert_eq!(Ok(()), b.accept_response(4, now));
+        assert_eq!(a.session(), b.session());
+        assert_eq!(1, a.rekeys());
+        assert_eq!(1, b.rekeys());
+    }
+
+    #[test]
+    fn previous_key_is_accepted_until_the_peer_uses_the_new_key() {
+        let now = Instant::now();
+        let session = Session::new(Secret::random(), 1000);
+        let mut keys = RekeyingSession::new(session, RekeyPolicy::disabled(), now);
+        assert_eq!(Ok(Some(session)), keys.accept_request(1, 2, now));
+        let new_session = *keys.session();
+
+        assert_eq!(Some(session), keys.verify(b"in flight", &session.sign(b"in flight")));
+        assert_eq!(Some(new_session), keys.verify(b"rekeyed", &new_session.sign(b"rekeyed")));
+        assert_eq!(None, keys.verify(b"too late", &session.sign(b"too late")));
+    }
+
+    #[test]
+    fn rekey_fails_without_the_response() {
+        let now = Instant::now();
+        let mut keys = RekeyingSession::new(Session::new(Secret::random(), 1000), RekeyPolicy::default(), now);
+        assert_eq!(Err(RekeyError::UnexpectedResponse), keys.accept_response(1, now));
+
+        keys.start(1, now);
+        assert_eq!(Ok(()), keys.check_timeout(now + REKEY_TIMEOUT));
+        assert_eq!(Err(RekeyError::Timeout), keys.check_timeout(now + REKEY_TIMEOUT + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn thresholds_start_the_rekey() {
+        let now = Instant::now();
+        let policy = RekeyPolicy {
+            max_bytes: 10,
+            max_interval: Duration::from_secs(60),
+        };
+        let mut keys = RekeyingSession::new(Session::new(Secret::random(), 1000), policy, now);
+        assert!(!keys.should_rekey(now));
+        assert!(keys.should_rekey(now + Duration::from_secs(60)));
+
+        keys.sign(&[0; 10]);
+        assert!(keys.should_rekey(now));
+        keys.start(1, now);
+        assert!(!keys.should_rekey(now));
+        assert_eq!(Ok(()), keys.accept_response(2, now));
+        assert!(!keys.should_rekey(now));
+    }
+}
@@ -17,7 +17,7 @@
 use ccrypto::aes::{self, SymmetricCipherError};
 use ccrypto::Blake;
 use ckey::Secret;
-use primitives::H256;
+use primitives::{H128, H256};
 
 use super::Nonce;
 
@@ -61,9 +61,25 @@ impl Session {
         Ok(aes::decrypt(&data, &self.secret, &self.nonce())?)
     }
 
+    /// Identifies the session key without revealing it.
+    pub fn fingerprint(&self) -> H128 {
+        H128::blake_with_key(&self.secret, b"session fingerprint")
+    }
+
     pub fn sign(&self, data: &[u8]) -> H256 {
         Blake::blake_with_key(data, &self.nonce().to_be_bytes())
     }
+
+    /// Derives the session that replaces this one from the nonces exchanged in a rekey.
+    /// Both sides derive the same session because the initiator's nonce always comes first.
+    pub fn rekeyed(&self, initiator_nonce: Nonce, responder_nonce: Nonce) -> Self {
+        let mut material = Vec::with_capacity(32);
+        material.extend_from_slice(&initiator_nonce.to_be_bytes());
+        material.extend_from_slice(&responder_nonce.to_be_bytes());
+        let secret = Blake::blake_with_key(&material, &self.secret);
+        let nonce = H128::blake_with_key(&material, &self.nonce.to_be_bytes());
+        Session::new(secret, Nonce::from_be_bytes(nonce.0))
+    }
 }
 
 #[cfg(test)]
@@ -133,4 +149,22 @@ mod tests {
 
         assert_ne!(encrypted1, encrypted2);
     }
+
+    #[test]
+    fn rekeyed_sessions_are_the_same_on_both_sides() {
+        let secret = Secret::random();
+        let session1 = Session::new(secret, 1000);
+        let session2 = Session::new(secret, 1000);
+
+        let rekeyed1 = session1.rekeyed(1, 2);
+        let rekeyed2 = session2.rekeyed(1, 2);
+        assert_eq!(rekeyed1, rekeyed2);
+        assert_ne!(session1, rekeyed1);
+        assert_ne!(rekeyed1, session1.rekeyed(2, 1));
+
+        let data = b"some short data".to_vec();
+        let encrypted = rekeyed1.encrypt(&data).ok().unwrap();
+        assert_eq!(data, rekeyed2.decrypt(&encrypted).ok().unwrap());
+        assert_ne!(data, session2.decrypt(&encrypted).unwrap_or_default());
+    }
 }
@@ -21,6 +21,7 @@ extern crate codechain_logger as clogger;
 extern crate codechain_json as cjson;
 extern crate codechain_key as ckey;
 extern crate codechain_keystore as ckeystore;
+extern crate codechain_metrics as cmetrics;
 extern crate codechain_network as cnetwork;
 extern crate codechain_state as cstate;
 extern crate codechain_sync as csync;
@@ -29,6 +30,7 @@ extern crate codechain_vm as cvm;
 pub extern crate jsonrpc_core;
 extern crate jsonrpc_http_server;
 extern crate jsonrpc_ipc_server;
+extern crate jsonrpc_pubsub;
 extern crate jsonrpc_ws_server;
 extern crate kvdb;
 extern crate kvdb_rocksdb as rocksdb;
@@ -69,3 +71,5 @@ pub use rpc_server::start_ipc;
 
 pub use jsonrpc_ws_server::{Error as WsError, ErrorKind as WsErrorKind, Server as WsServer};
 pub use rpc_server::start_ws;
+
+pub use v1::Metadata;
@@ -18,20 +18,22 @@
 use jsonrpc_core;
 use jsonrpc_http_server::{self, Host, Server as HttpServer, ServerBuilder as HttpServerBuilder};
 use jsonrpc_ipc_server::{Server as IpcServer, ServerBuilder as IpcServerBuilder};
-use jsonrpc_ws_server::{Error as WsError, Server as WsServer, ServerBuilder as WsServerBuilder};
+use jsonrpc_pubsub::Session;
+use jsonrpc_ws_server::{self, Error as WsError, Server as WsServer, ServerBuilder as WsServerBuilder};
 use std::default::Default;
 use std::io;
 use std::net::SocketAddr;
+use std::sync::Arc;
+
+use v1::Metadata;
 
 /// Start http server asynchronously and returns result with `Server` handle on success or an error.
-pub fn start_http<M: jsonrpc_core::Metadata>(
+pub fn start_http(
     addr: &SocketAddr,
     cors_domains: Option<Vec<String>>,
     allowed_hosts: Option<Vec<String>>,
-    handler: jsonrpc_core::MetaIoHandler<M, impl jsonrpc_core::Middleware<M>>,
-) -> Result<HttpServer, io::Error>
-where
-    M: Default, {
+    handler: jsonrpc_core::MetaIoHandler<Metadata, impl jsonrpc_core::Middleware<Metadata>>,
+) -> Result<HttpServer, io::Error> {
     let cors_domains = cors_domains.map(|domains| {
         domains
             .into_iter()
@@ -46,9 +48,22 @@ where
     HttpServerBuilder::new(handler)
         .cors(cors_domains.into())
         .allowed_hosts(allowed_hosts.map(|hosts| hosts.into_iter().map(Host::from).collect()).into())
+        .meta_extractor(HttpExtractor)
         .start_http(addr)
 }
 
+/// Records the address of the client, which is used to limit the transactions submitted by each client.
+struct HttpExtractor;
+
+impl jsonrpc_http_server::MetaExtractor<Metadata> for HttpExtractor {
+    fn read_metadata(&self, request: &jsonrpc_http_server::hyper::server::Request) -> Metadata {
+        Metadata {
+            source: request.remote_addr().map(|addr| addr.ip()),
+            ..Default::default()
+        }
+    }
+}
+
 /// Start ipc server asynchronously and returns result with `Server` handle on success or an error.
 pub fn start_ipc<M: jsonrpc_core::Metadata>(
     addr: &str,
@@ -60,13 +75,24 @@ where
 }
 
 /// Start WS server and return `Server` handle.
-pub fn start_ws<M: jsonrpc_core::Metadata>(
+pub fn start_ws(
     addr: &SocketAddr,
-    handler: jsonrpc_core::MetaIoHandler<M, impl jsonrpc_core::Middleware<M>>,
+    handler: jsonrpc_core::MetaIoHandler<Metadata, impl jsonrpc_core::Middleware<Metadata>>,
     max_connections: usize,
-) -> Result<WsServer, WsError>
-where
-    M: Default, {
-    // FIXME: Add Hosts, Origins and Session States
-    WsServerBuilder::new(handler).max_connections(max_connections).start(addr)
+) -> Result<WsServer, WsError> {
+    // FIXME: Add Hosts and Origins
+    WsServerBuilder::new(handler).session_meta_extractor(WsExtractor).max_connections(max_connections).start(addr)
+}
+
+/// Gives each WebSockets connection a session, which is used to send the notifications of the subscriptions.
+struct WsExtractor;
+
+impl jsonrpc_ws_server::MetaExtractor<Metadata> for WsExtractor {
+    fn extract(&self, context: &jsonrpc_ws_server::RequestContext) -> Metadata {
+        Metadata {
+            session_id: Some(context.session_id),
+            session: Some(Arc::new(Session::new(context.sender()))),
+            source: None,
+        }
+    }
 }
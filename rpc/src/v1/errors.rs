@@ -18,7 +18,8 @@ use std::fmt;
 
 use ccore::AccountProviderError;
 use ccore::Error as CoreError;
-use ckey::Error as KeyError;
+use ccore::SpendPolicyViolation;
+use ckey::{Error as KeyError, NetworkId, PlatformAddress};
 use ckeystore::Error as KeystoreError;
 use cnetwork::control::Error as NetworkControlError;
 use cstate::StateError;
@@ -26,6 +27,9 @@ use ctypes::errors::{HistoryError, RuntimeError, SyntaxError};
 use kvdb::Error as KVDBError;
 use rlp::DecoderError;
 use rustc_serialize::hex::FromHexError as HexError;
+use serde_json::to_value;
+
+use super::types::Transport;
 
 use jsonrpc_core::{Error, ErrorCode, Value};
 
@@ -74,6 +78,25 @@ mod codes {
     pub const ASSET_TRANSACTION_ONLY_IN_EXECUTE_TRANSACITON: i64 = -32047;
     pub const STATE_NOT_EXIST: i64 = -32048;
     pub const ACTION_DATA_HANDLER_NOT_FOUND: i64 = -32049;
+    pub const INVALID_LOG_LEVEL: i64 = -32050;
+    pub const FORCE_VIEW_DISABLED: i64 = -32051;
+    pub const TRANSACTIONS_REJECTED: i64 = -32052;
+    pub const SUBSCRIPTION_UNAVAILABLE: i64 = -32053;
+    pub const UNKNOWN_SUBSCRIPTION_KIND: i64 = -32054;
+    pub const TOO_MANY_WATCHED_TRANSACTIONS: i64 = -32055;
+    pub const FAULT_INJECTION_DISABLED: i64 = -32056;
+    pub const LIMIT_REACHED: i64 = -32057;
+    pub const ADDRESS_HISTORY_DISABLED: i64 = -32058;
+    pub const METHOD_NOT_ALLOWED: i64 = -32059;
+    pub const INVALID_SEED: i64 = -32060;
+    pub const INTEGRITY_CHECK_RUNNING: i64 = -32061;
+    pub const INVALID_TRANSACTION_JSON: i64 = -32062;
+    pub const INVALID_CACHE_BUDGET: i64 = -32063;
+    pub const MINORITY_FORK: i64 = -32064;
+    pub const LIGHT_QUERY_FAILED: i64 = -32065;
+    pub const SENDER_PUBLIC_REQUIRED: i64 = -32066;
+    pub const SPEND_POLICY_VIOLATION: i64 = -32067;
+    pub const ACTION_INDEX_DISABLED: i64 = -32068;
     pub const UNKNOWN_ERROR: i64 = -32099;
 }
 
@@ -164,13 +187,19 @@ pub fn transaction_core<T: Into<CoreError>>(error: T) -> Error {
             message: "Too Low Fee".into(),
             data: Some(Value::String(format!("{:?}", error))),
         },
+        CoreError::History(error @ HistoryError::LimitReached) => Error {
+            code: ErrorCode::ServerError(codes::LIMIT_REACHED),
+            message: "Limit Reached".into(),
+            data: Some(Value::String(format!("{:?}", error))),
+        },
         CoreError::History(error @ HistoryError::TooCheapToReplace) => Error {
             code: ErrorCode::ServerError(codes::TOO_CHEAP_TO_REPLACE),
             message: "Too Cheap to Replace".into(),
             data: Some(Value::String(format!("{:?}", error))),
         },
-        CoreError::History(
-            error @ HistoryError::Old {
+        CoreError::History(error @ HistoryError::Old)
+        | CoreError::History(
+            error @ HistoryError::NonConsecutiveSeq {
                 ..
             },
         ) => Error {
@@ -183,6 +212,16 @@ pub fn transaction_core<T: Into<CoreError>>(error: T) -> Error {
     }
 }
 
+pub fn transactions_rejected(errors: Vec<Option<Error>>) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::TRANSACTIONS_REJECTED),
+        message: "Some transactions are rejected. None of them are imported".into(),
+        data: Some(Value::Array(
+            errors.into_iter().map(|error| error.and_then(|e| to_value(e).ok()).unwrap_or(Value::Null)).collect(),
+        )),
+    }
+}
+
 pub fn kvdb(error: &KVDBError) -> Error {
     Error {
         code: ErrorCode::ServerError(codes::KVDB_ERROR),
@@ -217,6 +256,11 @@ pub fn account_provider(error: AccountProviderError) -> Error {
                 message: "Already Exists".into(),
                 data: Some(Value::String(format!("{:?}", error))),
             },
+            KeystoreError::InvalidSeed => Error {
+                code: ErrorCode::ServerError(codes::INVALID_SEED),
+                message: "Invalid Seed".into(),
+                data: Some(Value::String(format!("{:?}", error))),
+            },
             _ => Error {
                 code: ErrorCode::ServerError(codes::KEYSTORE_ERROR),
                 message: "Keystore Error".into(),
@@ -241,6 +285,65 @@ pub fn account_provider(error: AccountProviderError) -> Error {
     }
 }
 
+pub fn invalid_seed_id(seed_id: &str) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::INVALID_SEED),
+        message: format!("Invalid seed id: {}", seed_id),
+        data: None,
+    }
+}
+
+pub fn integrity_check_running() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::INTEGRITY_CHECK_RUNNING),
+        message: "Another integrity check of the database is running".into(),
+        data: None,
+    }
+}
+
+pub fn invalid_transaction_json(reason: String) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::INVALID_TRANSACTION_JSON),
+        message: format!("Invalid transaction JSON: {}", reason),
+        data: None,
+    }
+}
+
+pub fn invalid_cache_budget(reason: String) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::INVALID_CACHE_BUDGET),
+        message: format!("Invalid cache budget: {}", reason),
+        data: None,
+    }
+}
+
+pub fn minority_fork(method: &str, disagreeing_peers: usize, peers: usize) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::MINORITY_FORK),
+        message: format!(
+            "{} is rejected because the node may be on a minority fork: {} of {} peers disagree with the best block",
+            method, disagreeing_peers, peers
+        ),
+        data: None,
+    }
+}
+
+pub fn light_query_failed(reason: String) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::LIGHT_QUERY_FAILED),
+        message: format!("The full nodes couldn't prove the state: {}", reason),
+        data: None,
+    }
+}
+
+pub fn block_offset_beyond_genesis(best_block_number: u64) -> Error {
+    Error {
+        code: ErrorCode::InvalidParams,
+        message: format!("The block offset goes beyond the genesis block. The best block is #{}", best_block_number),
+        data: None,
+    }
+}
+
 pub fn no_author() -> Error {
     Error {
         code: ErrorCode::ServerError(codes::NO_AUTHOR),
@@ -288,6 +391,14 @@ pub fn asset_transaction_only() -> Error {
     }
 }
 
+pub fn sender_public_required() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::SENDER_PUBLIC_REQUIRED),
+        message: "SetRegularKey and the custom actions cannot be executed without the sender public.".into(),
+        data: None,
+    }
+}
+
 pub fn state_not_exist() -> Error {
     Error {
         code: ErrorCode::ServerError(codes::STATE_NOT_EXIST),
@@ -304,6 +415,132 @@ pub fn invalid_custom_action(err: String) -> Error {
     }
 }
 
+pub fn invalid_log_level(level: String) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::INVALID_LOG_LEVEL),
+        message: format!("Invalid log level: {}", level),
+        data: None,
+    }
+}
+
+pub fn force_view_disabled() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::FORCE_VIEW_DISABLED),
+        message: "engine_forceNextView is disabled. Run the node with --enable-force-view to enable it.".into(),
+        data: None,
+    }
+}
+
+pub fn fault_injection_disabled() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::FAULT_INJECTION_DISABLED),
+        message: "devel_setConsensusFault is disabled. Run the node with --enable-fault-injection to enable it.".into(),
+        data: None,
+    }
+}
+
+pub fn address_history_disabled() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::ADDRESS_HISTORY_DISABLED),
+        message: "chain_getTransactionHistory is disabled. Run the node with --address-history to enable it.".into(),
+        data: None,
+    }
+}
+
+pub fn action_index_disabled() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::ACTION_INDEX_DISABLED),
+        message: "chain_getTransactionsByType is disabled. Run the node with --action-index to enable it.".into(),
+        data: None,
+    }
+}
+
+#[derive(Serialize)]
+struct MethodNotAllowed<'a> {
+    method: &'a str,
+    transport: Transport,
+}
+
+pub fn method_not_allowed(method: &str, transport: Transport) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::METHOD_NOT_ALLOWED),
+        message: format!("{} is not allowed on this transport", method),
+        data: to_value(MethodNotAllowed {
+            method,
+            transport,
+        })
+        .ok(),
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+enum SpendPolicyViolationData {
+    #[serde(rename_all = "camelCase")]
+    DailyCapExceeded {
+        daily_cap: u64,
+        spent_today: u64,
+        requested: u64,
+    },
+    RecipientNotAllowed {
+        recipient: PlatformAddress,
+    },
+    ActionNotAllowed {
+        action: &'static str,
+    },
+    SigningNotAllowed,
+}
+
+pub fn spend_policy_violation(violation: &SpendPolicyViolation, network_id: NetworkId) -> Error {
+    let data = match *violation {
+        SpendPolicyViolation::DailyCapExceeded {
+            daily_cap,
+            spent_today,
+            requested,
+        } => SpendPolicyViolationData::DailyCapExceeded {
+            daily_cap,
+            spent_today,
+            requested,
+        },
+        SpendPolicyViolation::RecipientNotAllowed(recipient) => SpendPolicyViolationData::RecipientNotAllowed {
+            recipient: PlatformAddress::new_v1(network_id, recipient),
+        },
+        SpendPolicyViolation::ActionNotAllowed(action) => SpendPolicyViolationData::ActionNotAllowed {
+            action,
+        },
+        SpendPolicyViolation::SigningNotAllowed => SpendPolicyViolationData::SigningNotAllowed,
+    };
+    Error {
+        code: ErrorCode::ServerError(codes::SPEND_POLICY_VIOLATION),
+        message: format!("{}", violation),
+        data: to_value(data).ok(),
+    }
+}
+
+pub fn subscription_unavailable() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::SUBSCRIPTION_UNAVAILABLE),
+        message: "Subscriptions are only available over WebSockets".into(),
+        data: None,
+    }
+}
+
+pub fn unknown_subscription_kind(kind: String) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::UNKNOWN_SUBSCRIPTION_KIND),
+        message: format!("Unknown subscription kind: {}", kind),
+        data: None,
+    }
+}
+
+pub fn too_many_watched_transactions(limit: usize) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::TOO_MANY_WATCHED_TRANSACTIONS),
+        message: format!("A connection can watch at most {} transactions", limit),
+        data: None,
+    }
+}
+
 /// Internal error signifying a logic error in code.
 /// Should not be used when function can just fail
 /// because of invalid parameters or incomplete node state.
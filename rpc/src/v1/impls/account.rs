@@ -15,19 +15,24 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use std::convert::TryInto;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 
-use ccore::{AccountData, AccountProvider, BlockId, EngineInfo, MinerService, MiningBlockChainClient, TermInfo};
+use ccore::{
+    AccountData, AccountProvider, BlockId, EngineInfo, Error as CoreError, MinerService, MiningBlockChainClient,
+    SpendPolicyViolation, TermInfo,
+};
 use ckey::{NetworkId, Password, PlatformAddress, Signature};
+use ckeystore::Uuid;
 use ctypes::transaction::IncompleteTransaction;
 use jsonrpc_core::Result;
 use parking_lot::Mutex;
 use primitives::H256;
 
 use super::super::errors::{self, account_provider};
-use super::super::traits::Account;
-use super::super::types::{SendTransactionResult, UnsignedTransaction};
+use super::super::traits::{Account, AccountPolicy};
+use super::super::types::{DerivedAccount, SendTransactionResult, SpendPolicy, SpendPolicyStatus, UnsignedTransaction};
 
 pub struct AccountClient<C, M> {
     account_provider: Arc<AccountProvider>,
@@ -53,6 +58,10 @@ where
     }
 }
 
+fn parse_seed_id(seed_id: &str) -> Result<Uuid> {
+    Uuid::from_str(seed_id).map_err(|_| errors::invalid_seed_id(seed_id))
+}
+
 impl<C, M> Account for AccountClient<C, M>
 where
     C: EngineInfo + MiningBlockChainClient + AccountData + TermInfo + 'static,
@@ -82,6 +91,10 @@ where
 
     fn sign(&self, message_digest: H256, address: PlatformAddress, passphrase: Option<Password>) -> Result<Signature> {
         let address = address.try_into_address().map_err(errors::core)?;
+        // The signature could authorize any transaction, so the policy cannot be checked.
+        if self.miner.spend_policy(&address).is_some() {
+            return Err(errors::spend_policy_violation(&SpendPolicyViolation::SigningNotAllowed, self.network_id()))
+        }
         self.account_provider
             .get_account(&address, passphrase.as_ref())
             .and_then(|account| Ok(account.sign(&message_digest)?))
@@ -110,7 +123,10 @@ where
                 passphrase,
                 seq,
             )
-            .map_err(errors::core)?;
+            .map_err(|err| match err {
+                CoreError::SpendPolicy(violation) => errors::spend_policy_violation(&violation, self.network_id()),
+                err => errors::core(err),
+            })?;
 
         Ok(SendTransactionResult {
             hash,
@@ -151,4 +167,71 @@ where
         };
         Ok(())
     }
+
+    fn import_seed(&self, mnemonic_or_seed: String, passphrase: Option<Password>) -> Result<String> {
+        self.account_provider
+            .import_seed(&mnemonic_or_seed, &passphrase.unwrap_or_default())
+            .map(|seed_id| seed_id.to_string())
+            .map_err(account_provider)
+    }
+
+    fn derive_new_account(&self, seed_id: String, passphrase: Option<Password>) -> Result<DerivedAccount> {
+        let seed_id = parse_seed_id(&seed_id)?;
+        self.account_provider
+            .derive_account(&seed_id, &passphrase.unwrap_or_default())
+            .map(|account| DerivedAccount::new(&account, self.network_id()))
+            .map_err(account_provider)
+    }
+
+    fn list_derived_accounts(&self, seed_id: String) -> Result<Vec<DerivedAccount>> {
+        let seed_id = parse_seed_id(&seed_id)?;
+        let network_id = self.network_id();
+        self.account_provider
+            .derived_accounts(&seed_id)
+            .map(|accounts| accounts.iter().map(|account| DerivedAccount::new(account, network_id)).collect())
+            .map_err(account_provider)
+    }
+}
+
+pub struct AccountPolicyClient<C, M> {
+    client: Arc<C>,
+    miner: Arc<M>,
+}
+
+impl<C, M> AccountPolicyClient<C, M>
+where
+    C: EngineInfo,
+{
+    pub fn new(client: Arc<C>, miner: Arc<M>) -> Self {
+        AccountPolicyClient {
+            client,
+            miner,
+        }
+    }
+
+    fn network_id(&self) -> NetworkId {
+        self.client.common_params(BlockId::Latest).unwrap().network_id()
+    }
+}
+
+impl<C, M> AccountPolicy for AccountPolicyClient<C, M>
+where
+    C: EngineInfo + 'static,
+    M: MinerService + 'static,
+{
+    fn set_spend_policy(&self, address: PlatformAddress, policy: Option<SpendPolicy>) -> Result<()> {
+        let address = address.try_into_address().map_err(errors::core)?;
+        let policy = match policy {
+            Some(policy) => Some(policy.try_into_core().map_err(errors::core)?),
+            None => None,
+        };
+        self.miner.set_spend_policy(address, policy);
+        Ok(())
+    }
+
+    fn get_spend_policy(&self, address: PlatformAddress) -> Result<Option<SpendPolicyStatus>> {
+        let address = address.try_into_address().map_err(errors::core)?;
+        let network_id = self.network_id();
+        Ok(self.miner.spend_policy(&address).map(|status| SpendPolicyStatus::from_core(status, network_id)))
+    }
 }
@@ -18,38 +18,120 @@ use std::convert::{TryFrom, TryInto};
 use std::sync::Arc;
 
 use ccore::{
-    AccountData, AssetClient, BlockId, EngineInfo, ExecuteClient, MiningBlockChainClient, Shard, TermInfo, TextClient,
+    AccountData, ActionIndexPosition, ActionType, AddressHistoryPosition, AssetClient, BlockChainTrait, BlockId,
+    DryRunOptions as CoreDryRunOptions, EngineInfo, ExecuteClient, MiningBlockChainClient, Shard, TermInfo, TextClient,
+    MAX_STATISTICS_RANGE,
 };
 use ccrypto::Blake;
+use cjson::bytes::Bytes;
 use cjson::scheme::Params;
 use cjson::uint::Uint;
 use ckey::{public_to_address, NetworkId, PlatformAddress, Public};
+use cnetwork::{once_event_callback, EventSender};
 use cstate::FindActionHandler;
-use ctypes::transaction::{Action, ShardTransaction as ShardTransactionType};
+use csync::BlockSyncEvent;
+use ctypes::transaction::{Action, IncompleteTransaction, ShardTransaction as ShardTransactionType};
 use ctypes::{BlockNumber, ShardId};
 use primitives::{Bytes as BytesArray, H160, H256};
+use rlp;
+use serde_json::Value;
 
-use jsonrpc_core::Result;
+use jsonrpc_core::{Error, Result};
 
 use super::super::errors;
 use super::super::traits::Chain;
-use super::super::types::{AssetScheme, Block, BlockNumberAndHash, OwnedAsset, Text, Transaction, UnsignedTransaction};
+use super::super::types::{
+    encode_transaction, AssetScheme, BestBlockId, Block, BlockFinality, BlockNumberOrHash, BlockNumberParam,
+    BlockProductionStats, BlockRewardDistribution, BlockSeal, Candidate, DecodedRaw, DryRunOptions, DryRunResult,
+    OwnedAsset, ParamsActivation, RangeStatistics, RawKind, ReorgProtectionStatus, SealVerification, SyncStatus, Term,
+    Text, Transaction, TransactionHistory, TransactionHistoryOptions, TransactionStatus, UnsignedTransaction, Validator,
+    DEFAULT_TRANSACTION_HISTORY_LIMIT, MAX_TRANSACTION_HISTORY_LIMIT,
+};
 
 pub struct ChainClient<C>
 where
     C: AssetClient + MiningBlockChainClient + Shard + ExecuteClient + EngineInfo, {
     client: Arc<C>,
+    block_sync: Option<EventSender<BlockSyncEvent>>,
 }
 
 impl<C> ChainClient<C>
 where
     C: AssetClient + MiningBlockChainClient + Shard + AccountData + ExecuteClient + EngineInfo + TextClient,
 {
-    pub fn new(client: Arc<C>) -> Self {
+    pub fn new(client: Arc<C>, block_sync: Option<EventSender<BlockSyncEvent>>) -> Self {
         ChainClient {
             client,
+            block_sync,
         }
     }
+
+    fn resolve_block_number(&self, block_number: BlockNumberParam) -> Result<BlockNumber> {
+        resolve_block_number(&*self.client, block_number)
+    }
+
+    /// `None` is kept as it is, which means the best block.
+    fn resolve_optional_block_number(&self, block_number: Option<BlockNumberParam>) -> Result<Option<BlockNumber>> {
+        block_number.map(|block_number| self.resolve_block_number(block_number)).transpose()
+    }
+
+    fn resolve_block(&self, block: BlockNumberOrHash) -> Result<BlockId> {
+        resolve_block(&*self.client, block)
+    }
+
+    /// Returns the position where the page starts as (block number, transaction index), the last block of the range,
+    /// and the maximum number of the transactions in the page.
+    fn resolve_page(&self, options: TransactionHistoryOptions) -> Result<((BlockNumber, usize), BlockNumber, usize)> {
+        let limit = options.limit.unwrap_or(DEFAULT_TRANSACTION_HISTORY_LIMIT);
+        if limit == 0 || limit > MAX_TRANSACTION_HISTORY_LIMIT {
+            return Err(Error::invalid_params(format!(
+                "The limit must be between 1 and {}",
+                MAX_TRANSACTION_HISTORY_LIMIT
+            )))
+        }
+        let from_block = options.from_block.unwrap_or(0);
+        let to_block = options.to_block.unwrap_or_else(|| self.client.chain_info().best_block_number);
+        if from_block > to_block {
+            return Err(Error::invalid_params(format!("Invalid range: {}..={}", from_block, to_block)))
+        }
+
+        let mut from = (from_block, 0);
+        if let Some(cursor) = options.cursor {
+            // A cursor before the range is clamped to the start of the range.
+            if (cursor.block_number, cursor.index) > from {
+                from = (cursor.block_number, cursor.index);
+            }
+        }
+        Ok((from, to_block, limit))
+    }
+
+    fn is_body_download_paused(&self) -> bool {
+        if let Some(block_sync) = self.block_sync.as_ref() {
+            let (sender, receiver) = once_event_callback();
+            block_sync.send(BlockSyncEvent::IsBodyDownloadPaused(sender)).unwrap();
+            receiver.recv().unwrap_or(false)
+        } else {
+            false
+        }
+    }
+}
+
+/// Resolves the block number against a single snapshot of the best block,
+/// so the blocks an RPC reads don't move even if a block is imported meanwhile.
+fn resolve_block_number<C: BlockChainTrait>(client: &C, block_number: BlockNumberParam) -> Result<BlockNumber> {
+    let best_block_number = client.chain_info().best_block_number;
+    block_number.resolve(best_block_number).ok_or_else(|| errors::block_offset_beyond_genesis(best_block_number))
+}
+
+/// Reads the best block from a single header.
+/// The chain info is not used, because the best block can change between the reads of its fields.
+fn best_block_id<C: BlockChainTrait>(client: &C, network_id: NetworkId) -> BestBlockId {
+    BestBlockId::from_core(&client.best_block_header().decode(), network_id)
+}
+
+fn resolve_block<C: BlockChainTrait>(client: &C, block: BlockNumberOrHash) -> Result<BlockId> {
+    let best_block_number = client.chain_info().best_block_number;
+    block.resolve(best_block_number).ok_or_else(|| errors::block_offset_beyond_genesis(best_block_number))
 }
 
 impl<C> Chain for ChainClient<C>
@@ -86,15 +168,68 @@ where
         self.contains_transaction(transaction_hash)
     }
 
+    fn get_transaction_status(&self, transaction_hash: H256) -> Result<TransactionStatus> {
+        // Look up the mem pool first. A mined transaction is removed from the pool after the block is imported,
+        // so it's found in either of them.
+        if let Some(queue) = self.client.pending_queue(&transaction_hash) {
+            return Ok(TransactionStatus::Pending {
+                queue: queue.into(),
+            })
+        }
+        Ok(match self.client.transaction(&transaction_hash.into()) {
+            Some(transaction) => TransactionStatus::Mined {
+                block_hash: transaction.block_hash,
+                index: transaction.transaction_index,
+            },
+            None => TransactionStatus::Unknown,
+        })
+    }
+
     fn get_transaction_by_tracker(&self, tracker: H256) -> Result<Option<Transaction>> {
         Ok(self.client.transaction_by_tracker(&tracker).map(From::from))
     }
 
+    fn get_transaction_history(
+        &self,
+        address: PlatformAddress,
+        options: Option<TransactionHistoryOptions>,
+    ) -> Result<TransactionHistory> {
+        let address = *address.try_address().map_err(errors::core)?;
+        let ((block_number, index), to_block, limit) = self.resolve_page(options.unwrap_or_default())?;
+        let from = AddressHistoryPosition {
+            address,
+            block_number,
+            index,
+        };
+        let page = self.client.address_history(&from, to_block, limit).ok_or_else(errors::address_history_disabled)?;
+        Ok(page.into())
+    }
+
+    fn get_transactions_by_type(
+        &self,
+        action_type: String,
+        options: Option<TransactionHistoryOptions>,
+    ) -> Result<TransactionHistory> {
+        let action_type = ActionType::from_name(&action_type)
+            .ok_or_else(|| Error::invalid_params(format!("Unknown action type: {}", action_type)))?;
+        let ((block_number, index), to_block, limit) = self.resolve_page(options.unwrap_or_default())?;
+        let from = ActionIndexPosition {
+            action_type,
+            block_number,
+            index,
+        };
+        let page = self
+            .client
+            .transactions_by_action_type(&from, to_block, limit)
+            .ok_or_else(errors::action_index_disabled)?;
+        Ok(page.into())
+    }
+
     fn get_asset_scheme_by_tracker(
         &self,
         tracker: H256,
         shard_id: ShardId,
-        block_number: Option<u64>,
+        block_number: Option<BlockNumberParam>,
     ) -> Result<Option<AssetScheme>> {
         let asset_type = Blake::blake(tracker);
         self.get_asset_scheme_by_type(asset_type, shard_id, block_number)
@@ -104,8 +239,9 @@ where
         &self,
         asset_type: H160,
         shard_id: ShardId,
-        block_number: Option<u64>,
+        block_number: Option<BlockNumberParam>,
     ) -> Result<Option<AssetScheme>> {
+        let block_number = self.resolve_optional_block_number(block_number)?;
         if block_number == Some(0) {
             return Ok(None)
         }
@@ -123,7 +259,8 @@ where
         }
     }
 
-    fn get_text(&self, transaction_hash: H256, block_number: Option<u64>) -> Result<Option<Text>> {
+    fn get_text(&self, transaction_hash: H256, block_number: Option<BlockNumberParam>) -> Result<Option<Text>> {
+        let block_number = self.resolve_optional_block_number(block_number)?;
         if block_number == Some(0) {
             return Ok(None)
         }
@@ -139,8 +276,9 @@ where
         tracker: H256,
         index: usize,
         shard_id: ShardId,
-        block_number: Option<u64>,
+        block_number: Option<BlockNumberParam>,
     ) -> Result<Option<OwnedAsset>> {
+        let block_number = self.resolve_optional_block_number(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         let asset = self.client.get_asset(tracker, index, shard_id, block_id).map_err(errors::transaction_state)?;
         Ok(asset.map(From::from))
@@ -151,31 +289,44 @@ where
         transaction_hash: H256,
         index: usize,
         shard_id: ShardId,
-        block_number: Option<u64>,
+        block_number: Option<BlockNumberParam>,
     ) -> Result<Option<bool>> {
+        let block_number = self.resolve_optional_block_number(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         self.client.is_asset_spent(transaction_hash, index, shard_id, block_id).map_err(errors::transaction_state)
     }
 
-    fn get_seq(&self, address: PlatformAddress, block_number: Option<u64>) -> Result<Option<u64>> {
+    fn get_seq(&self, address: PlatformAddress, block_number: Option<BlockNumberParam>) -> Result<Option<u64>> {
+        let block_number = self.resolve_optional_block_number(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         let address = address.try_address().map_err(errors::core)?;
         Ok(self.client.seq(address, block_id))
     }
 
-    fn get_balance(&self, aaddress: PlatformAddress, block_number: Option<u64>) -> Result<Option<Uint>> {
+    fn get_balance(&self, aaddress: PlatformAddress, block_number: Option<BlockNumberParam>) -> Result<Option<Uint>> {
+        let block_number = self.resolve_optional_block_number(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         let address = aaddress.try_address().map_err(errors::core)?;
         Ok(self.client.balance(address, block_id.into()).map(Into::into))
     }
 
-    fn get_regular_key(&self, address: PlatformAddress, block_number: Option<u64>) -> Result<Option<Public>> {
+    fn get_regular_key(
+        &self,
+        address: PlatformAddress,
+        block_number: Option<BlockNumberParam>,
+    ) -> Result<Option<Public>> {
+        let block_number = self.resolve_optional_block_number(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         let address = address.try_address().map_err(errors::core)?;
         Ok(self.client.regular_key(address, block_id.into()))
     }
 
-    fn get_regular_key_owner(&self, public: Public, block_number: Option<u64>) -> Result<Option<PlatformAddress>> {
+    fn get_regular_key_owner(
+        &self,
+        public: Public,
+        block_number: Option<BlockNumberParam>,
+    ) -> Result<Option<PlatformAddress>> {
+        let block_number = self.resolve_optional_block_number(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         Ok(self.client.regular_key_owner(&public_to_address(&public), block_id.into()).and_then(|address| {
             let parent_block_id = block_number.map(|n| (n - 1).into()).unwrap_or(BlockId::ParentOfLatest);
@@ -188,22 +339,34 @@ where
         Ok(self.client.genesis_accounts())
     }
 
-    fn get_number_of_shards(&self, block_number: Option<u64>) -> Result<Option<ShardId>> {
+    fn get_number_of_shards(&self, block_number: Option<BlockNumberParam>) -> Result<Option<ShardId>> {
+        let block_number = self.resolve_optional_block_number(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         Ok(self.client.number_of_shards(block_id.into()))
     }
 
-    fn get_shard_id_by_hash(&self, create_shard_tx_hash: H256, block_number: Option<u64>) -> Result<Option<ShardId>> {
+    fn get_shard_id_by_hash(
+        &self,
+        create_shard_tx_hash: H256,
+        block_number: Option<BlockNumberParam>,
+    ) -> Result<Option<ShardId>> {
+        let block_number = self.resolve_optional_block_number(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         Ok(self.client.shard_id_by_hash(&create_shard_tx_hash, block_id.into()))
     }
 
-    fn get_shard_root(&self, shard_id: ShardId, block_number: Option<u64>) -> Result<Option<H256>> {
+    fn get_shard_root(&self, shard_id: ShardId, block_number: Option<BlockNumberParam>) -> Result<Option<H256>> {
+        let block_number = self.resolve_optional_block_number(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         Ok(self.client.shard_root(shard_id, block_id.into()))
     }
 
-    fn get_shard_owners(&self, shard_id: ShardId, block_number: Option<u64>) -> Result<Option<Vec<PlatformAddress>>> {
+    fn get_shard_owners(
+        &self,
+        shard_id: ShardId,
+        block_number: Option<BlockNumberParam>,
+    ) -> Result<Option<Vec<PlatformAddress>>> {
+        let block_number = self.resolve_optional_block_number(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         Ok(self.client.shard_owners(shard_id, block_id.into()).map(|owners| {
             let parent_block_id = block_number.map(|n| (n - 1).into()).unwrap_or(BlockId::ParentOfLatest);
@@ -212,7 +375,12 @@ where
         }))
     }
 
-    fn get_shard_users(&self, shard_id: ShardId, block_number: Option<u64>) -> Result<Option<Vec<PlatformAddress>>> {
+    fn get_shard_users(
+        &self,
+        shard_id: ShardId,
+        block_number: Option<BlockNumberParam>,
+    ) -> Result<Option<Vec<PlatformAddress>>> {
+        let block_number = self.resolve_optional_block_number(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         Ok(self.client.shard_users(shard_id, block_id.into()).map(|users| {
             let parent_block_id = block_number.map(|n| (n - 1).into()).unwrap_or(BlockId::ParentOfLatest);
@@ -225,19 +393,18 @@ where
         Ok(self.client.chain_info().best_block_number)
     }
 
-    fn get_best_block_id(&self) -> Result<BlockNumberAndHash> {
-        let chain_info = self.client.chain_info();
-        Ok(BlockNumberAndHash {
-            number: chain_info.best_block_number,
-            hash: chain_info.best_block_hash,
-        })
+    fn get_best_block_id(&self) -> Result<BestBlockId> {
+        let network_id = self.client.common_params(BlockId::Latest).unwrap().network_id();
+        Ok(best_block_id(&*self.client, network_id))
     }
 
-    fn get_block_hash(&self, block_number: u64) -> Result<Option<H256>> {
+    fn get_block_hash(&self, block_number: BlockNumberParam) -> Result<Option<H256>> {
+        let block_number = self.resolve_block_number(block_number)?;
         Ok(self.client.block_hash(&BlockId::Number(block_number)))
     }
 
-    fn get_block_by_number(&self, block_number: u64) -> Result<Option<Block>> {
+    fn get_block_by_number(&self, block_number: BlockNumberParam) -> Result<Option<Block>> {
+        let block_number = self.resolve_block_number(block_number)?;
         let id = BlockId::Number(block_number);
         Ok(self.client.block(&id).map(|block| {
             let block_id_to_read_params = if block_number == 0 {
@@ -266,7 +433,12 @@ where
         Ok(self.client.block(&BlockId::Hash(block_hash)).map(|block| block.transactions_count()))
     }
 
-    fn get_min_transaction_fee(&self, action_type: String, block_number: Option<u64>) -> Result<Option<u64>> {
+    fn get_min_transaction_fee(
+        &self,
+        action_type: String,
+        block_number: Option<BlockNumberParam>,
+    ) -> Result<Option<u64>> {
+        let block_number = self.resolve_optional_block_number(block_number)?;
         if block_number == Some(0) {
             return Ok(None)
         }
@@ -298,7 +470,8 @@ where
         }
     }
 
-    fn get_mining_reward(&self, block_number: u64) -> Result<Option<u64>> {
+    fn get_mining_reward(&self, block_number: BlockNumberParam) -> Result<Option<u64>> {
+        let block_number = self.resolve_block_number(block_number)?;
         Ok(self.client.mining_reward(block_number))
     }
 
@@ -306,12 +479,18 @@ where
         Ok(self.client.common_params(BlockId::Latest).unwrap().network_id())
     }
 
-    fn get_common_params(&self, block_number: Option<u64>) -> Result<Option<Params>> {
+    fn get_common_params(&self, block_number: Option<BlockNumberParam>) -> Result<Option<Params>> {
+        let block_number = self.resolve_optional_block_number(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         Ok(self.client.common_params(block_id).map(Params::from))
     }
 
-    fn get_term_metadata(&self, block_number: Option<u64>) -> Result<Option<(u64, u64)>> {
+    fn get_params_change_history(&self) -> Result<Vec<ParamsActivation>> {
+        Ok(self.client.params_change_history().into_iter().map(From::from).collect())
+    }
+
+    fn get_term_metadata(&self, block_number: Option<BlockNumberParam>) -> Result<Option<(u64, u64)>> {
+        let block_number = self.resolve_optional_block_number(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         let last_term_finished_block_num = self.client.last_term_finished_block_num(block_id);
         let current_term_id = self.client.current_term_id(block_id);
@@ -324,15 +503,138 @@ where
         }
     }
 
-    fn get_metadata_seq(&self, block_number: Option<u64>) -> Result<Option<u64>> {
+    fn get_term(&self, block_number: BlockNumberParam) -> Result<Option<Term>> {
+        let block_number = self.resolve_block_number(block_number)?;
+        Ok(self.client.term_details(block_number).map(Term::from))
+    }
+
+    fn get_metadata_seq(&self, block_number: Option<BlockNumberParam>) -> Result<Option<u64>> {
+        let block_number = self.resolve_optional_block_number(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         Ok(self.client.metadata_seq(block_id))
     }
 
-    fn get_possible_authors(&self, block_number: Option<u64>) -> Result<Option<Vec<PlatformAddress>>> {
+    fn get_possible_authors(&self, block_number: Option<BlockNumberParam>) -> Result<Option<Vec<PlatformAddress>>> {
+        let block_number = self.resolve_optional_block_number(block_number)?;
         Ok(self.client.possible_authors(block_number).map_err(errors::core)?)
     }
 
+    fn get_validator_set(&self, block: Option<BlockNumberOrHash>) -> Result<Option<Vec<Validator>>> {
+        let block_id = match block {
+            Some(block) => self.resolve_block(block)?,
+            None => BlockId::Latest,
+        };
+        let network_id = self.client.common_params(BlockId::Latest).unwrap().network_id();
+        Ok(self.client.validator_set(block_id).map(|validators| {
+            validators
+                .into_iter()
+                .enumerate()
+                .map(|(index, validator)| Validator::from_core(index, validator, network_id))
+                .collect()
+        }))
+    }
+
+    fn get_candidates(&self, block: Option<BlockNumberOrHash>) -> Result<Option<Vec<Candidate>>> {
+        let block_id = match block {
+            Some(block) => self.resolve_block(block)?,
+            None => BlockId::Latest,
+        };
+        let network_id = self.client.common_params(BlockId::Latest).unwrap().network_id();
+        Ok(self.client.candidates(block_id).map(|candidates| {
+            candidates.into_iter().map(|candidate| Candidate::from_core(candidate, network_id)).collect()
+        }))
+    }
+
+    fn get_reorg_protection_status(&self) -> Result<ReorgProtectionStatus> {
+        Ok(self.client.reorg_protection_status().into())
+    }
+
+    fn verify_block_seal(&self, block_hash: H256) -> Result<Option<SealVerification>> {
+        let checkpoint = self.client.skipped_seal_verification(&block_hash).map(|skipped| skipped.checkpoint);
+        Ok(self.client.reverify_block_seal(&block_hash).map(|result| SealVerification::new(checkpoint, result.is_ok())))
+    }
+
+    fn get_block_seal(&self, block: BlockNumberOrHash) -> Result<Option<BlockSeal>> {
+        Ok(self.client.block_header(&self.resolve_block(block)?).map(|header| {
+            let seal = header.seal();
+            let decoded = self.client.decode_seal(&seal);
+            BlockSeal::new(seal, decoded)
+        }))
+    }
+
+    fn get_block_finality(&self, block: BlockNumberOrHash) -> Result<Option<BlockFinality>> {
+        let hash = match self.client.block_hash(&self.resolve_block(block)?) {
+            Some(hash) => hash,
+            None => return Ok(None),
+        };
+        Ok(self.client.block_finality(&hash).map(Into::into))
+    }
+
+    fn get_block_raw(&self, block: BlockNumberOrHash) -> Result<Option<Bytes>> {
+        Ok(self.client.block(&self.resolve_block(block)?).map(|block| Bytes::new(block.into_inner())))
+    }
+
+    fn decode_raw(&self, kind: RawKind, raw: Bytes) -> Result<DecodedRaw> {
+        let network_id = self.client.common_params(BlockId::Latest).unwrap().network_id();
+        DecodedRaw::decode(kind, &raw.into_vec(), network_id).map_err(|e| errors::rlp(&e))
+    }
+
+    fn encode_transaction(&self, transaction: Value) -> Result<Bytes> {
+        let transaction = encode_transaction(transaction).map_err(errors::invalid_transaction_json)?;
+        Ok(Bytes::new(rlp::encode(&transaction).into_vec()))
+    }
+
+    fn get_block_production_stats(&self, window_blocks: u64) -> Result<BlockProductionStats> {
+        let network_id = self.client.common_params(BlockId::Latest).unwrap().network_id();
+        let stats = self.client.block_production_stats(window_blocks);
+        Ok(BlockProductionStats::from_core(stats, network_id))
+    }
+
+    fn get_block_reward_distribution(&self, block_number: BlockNumberParam) -> Result<Option<BlockRewardDistribution>> {
+        let block_number = self.resolve_block_number(block_number)?;
+        let network_id = self.client.common_params(BlockId::Latest).unwrap().network_id();
+        Ok(self
+            .client
+            .block_reward_distribution(block_number)
+            .map(|distribution| BlockRewardDistribution::from_core(distribution, network_id)))
+    }
+
+    fn get_range_statistics(
+        &self,
+        from_block: BlockNumberParam,
+        to_block: BlockNumberParam,
+    ) -> Result<Option<RangeStatistics>> {
+        let from_block = self.resolve_block_number(from_block)?;
+        let to_block = self.resolve_block_number(to_block)?;
+        if from_block > to_block {
+            return Err(Error::invalid_params(format!("Invalid range: {}..={}", from_block, to_block)))
+        }
+        if to_block - from_block >= MAX_STATISTICS_RANGE {
+            return Err(Error::invalid_params(format!("The range must not exceed {} blocks", MAX_STATISTICS_RANGE)))
+        }
+        Ok(self
+            .client
+            .range_statistics(from_block, to_block)
+            .map(|statistics| RangeStatistics::from_core(statistics, from_block, to_block)))
+    }
+
+    fn get_total_burnt(&self, block_number: Option<BlockNumberParam>) -> Result<Option<u64>> {
+        let block_number = self.resolve_optional_block_number(block_number)?;
+        let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
+        Ok(self.client.total_burnt(block_id))
+    }
+
+    fn get_sync_status(&self) -> Result<SyncStatus> {
+        let queue_info = self.client.queue_info();
+        Ok(SyncStatus {
+            frozen: self.client.is_chain_frozen(),
+            best_block_number: self.client.chain_info().best_block_number,
+            queued_blocks: queue_info.total_queue_size(),
+            queue_mem_used: queue_info.mem_used,
+            body_download_paused: self.is_body_download_paused(),
+        })
+    }
+
     fn execute_transaction(&self, tx: UnsignedTransaction, sender: PlatformAddress) -> Result<Option<String>> {
         let sender_address = sender.try_address().map_err(errors::core)?;
         let action = Action::try_from(tx.action).map_err(errors::conversion)?;
@@ -347,6 +649,48 @@ where
         }
     }
 
+    fn dry_run_transaction(
+        &self,
+        tx: UnsignedTransaction,
+        sender: PlatformAddress,
+        block_number: Option<BlockNumberParam>,
+        options: Option<DryRunOptions>,
+    ) -> Result<DryRunResult> {
+        let block_number = self.resolve_optional_block_number(block_number)?;
+        let sender_address = sender.try_address().map_err(errors::core)?;
+        let options = options.unwrap_or_default();
+        let (tx, seq): (IncompleteTransaction, Option<u64>) = tx.try_into()?;
+        let sender_public = options.sender_public.as_ref();
+        match sender_public {
+            Some(public) if public_to_address(public) != *sender_address => {
+                return Err(Error::invalid_params("The sender public doesn't match the sender"))
+            }
+            None => match tx.action {
+                Action::SetRegularKey {
+                    ..
+                }
+                | Action::Custom {
+                    ..
+                } => return Err(errors::sender_public_required()),
+                _ => {}
+            },
+            _ => {}
+        }
+        let mut core_options = CoreDryRunOptions::from(&options);
+        // The transaction without seq is executed with the current seq of the fee payer.
+        core_options.ignore_seq |= seq.is_none();
+        let tx = tx.complete(seq.unwrap_or_default());
+
+        let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
+        let network_id = self.client.common_params(block_id).ok_or_else(errors::state_not_exist)?.network_id();
+        let result = self
+            .client
+            .dry_run_transaction(&tx, sender_address, sender_public, block_id, &core_options)
+            .ok_or_else(errors::state_not_exist)?
+            .map_err(errors::core)?;
+        Ok(DryRunResult::from_core(result, network_id))
+    }
+
     fn execute_vm(
         &self,
         tx: UnsignedTransaction,
@@ -366,3 +710,90 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+
+    use ccore::TestBlockChainClient;
+    use jsonrpc_core::ErrorCode;
+
+    use super::*;
+
+    #[test]
+    fn each_form_is_resolved() {
+        let client = TestBlockChainClient::new();
+        client.add_blocks(10, 0);
+        let best = client.chain_info().best_block_number;
+        assert_eq!(0, resolve_block_number(&client, BlockNumberParam::Earliest).unwrap());
+        assert_eq!(best, resolve_block_number(&client, BlockNumberParam::Latest).unwrap());
+        assert_eq!(4, resolve_block_number(&client, BlockNumberParam::Number(4)).unwrap());
+        assert_eq!(best - 3, resolve_block_number(&client, BlockNumberParam::Offset(3)).unwrap());
+        assert_eq!(
+            BlockId::Number(best - 1),
+            resolve_block(&client, BlockNumberOrHash::Number(BlockNumberParam::Offset(1))).unwrap()
+        );
+
+        let error = resolve_block_number(&client, BlockNumberParam::Offset(best + 1)).unwrap_err();
+        assert_eq!(ErrorCode::InvalidParams, error.code);
+        let error = resolve_block(&client, BlockNumberOrHash::Number(BlockNumberParam::Offset(best + 1))).unwrap_err();
+        assert_eq!(ErrorCode::InvalidParams, error.code);
+    }
+
+    #[test]
+    fn block_number_is_resolved_against_a_single_best_block_under_import() {
+        let client = Arc::new(TestBlockChainClient::new());
+        client.add_blocks(10, 0);
+        let importing = Arc::new(AtomicBool::new(true));
+        let importer = {
+            let client = Arc::clone(&client);
+            let importing = Arc::clone(&importing);
+            thread::spawn(move || {
+                for _ in 0..500 {
+                    client.add_blocks(1, 0);
+                }
+                importing.store(false, Ordering::SeqCst);
+            })
+        };
+
+        while importing.load(Ordering::SeqCst) {
+            let before = client.chain_info().best_block_number;
+            let latest = resolve_block_number(&*client, BlockNumberParam::Latest).unwrap();
+            let parent = resolve_block_number(&*client, BlockNumberParam::Offset(1)).unwrap();
+            let after = client.chain_info().best_block_number;
+            assert!(before <= latest && latest <= after, "{} is not in [{}, {}]", latest, before, after);
+            assert!(before - 1 <= parent && parent < after, "{} is not in [{}, {})", parent, before - 1, after);
+        }
+        importer.join().unwrap();
+        assert_eq!(510, resolve_block_number(&*client, BlockNumberParam::Latest).unwrap());
+    }
+
+    #[test]
+    fn best_block_id_is_read_from_a_single_block_under_import() {
+        let client = Arc::new(TestBlockChainClient::new());
+        client.add_blocks(10, 0);
+        let importing = Arc::new(AtomicBool::new(true));
+        let importer = {
+            let client = Arc::clone(&client);
+            let importing = Arc::clone(&importing);
+            thread::spawn(move || {
+                for _ in 0..500 {
+                    client.add_blocks(1, 0);
+                }
+                importing.store(false, Ordering::SeqCst);
+            })
+        };
+
+        let network_id = NetworkId::default();
+        while importing.load(Ordering::SeqCst) {
+            let id = best_block_id(&*client, network_id);
+            let header = client.block_header(&id.hash.into()).unwrap().decode();
+            assert_eq!(header.number(), id.number);
+            assert_eq!(header.timestamp(), id.timestamp);
+            assert_eq!(PlatformAddress::new_v1(network_id, *header.author()), id.author);
+        }
+        importer.join().unwrap();
+        assert_eq!(510, best_block_id(&*client, network_id).number);
+    }
+}
@@ -16,53 +16,66 @@
 
 use std::net::SocketAddr;
 use std::ops::Deref;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
 use std::vec::Vec;
 
 use ccore::{
-    BlockId, DatabaseClient, EngineClient, EngineInfo, MinerService, MiningBlockChainClient, SignedTransaction,
-    TermInfo, COL_STATE,
+    column_by_name, BlockId, DatabaseClient, EngineClient, EngineInfo, MinerService, MiningBlockChainClient, Scheme,
+    SignedTransaction, TermInfo, TraceTarget, COL_STATE, MAX_REPLAY_BLOCKS,
 };
 use ccrypto::Blake;
 use cjson::bytes::Bytes;
 use ckey::{Address, KeyPair, Private};
+use clogger::LevelFilter;
 use cnetwork::{unbounded_event_callback, EventSender, IntoSocketAddr};
 use csync::BlockSyncEvent;
 use ctypes::transaction::{
     Action, AssetMintOutput, AssetOutPoint, AssetTransferInput, AssetTransferOutput, Transaction,
 };
-use jsonrpc_core::Result;
+use jsonrpc_core::{Error, Result, Value};
 use kvdb::KeyValueDB;
 use primitives::{H160, H256};
 use rand::rngs::SmallRng;
 use rand::{Rng, SeedableRng};
 use rlp::UntrustedRlp;
+use serde_json;
 use time::PreciseTime;
 
 use super::super::errors;
 use super::super::traits::Devel;
-use super::super::types::{TPSTestOption, TPSTestSetting};
+use super::super::types::{
+    ActionHandlerTrace, ActionIndexBackfill, BlockImportTimings, BlockRejection, ConsensusFault, DatabaseStats,
+    FinalityConflict, IntegrityReport, RepairOptions, ReplayOptions, ReplayReport, TPSTestOption, TPSTestSetting,
+};
 
 pub struct DevelClient<C, M> {
     client: Arc<C>,
     db: Arc<KeyValueDB>,
     miner: Arc<M>,
     block_sync: Option<EventSender<BlockSyncEvent>>,
+    enable_fault_injection: bool,
 }
 
 impl<C, M> DevelClient<C, M>
 where
     C: DatabaseClient,
 {
-    pub fn new(client: Arc<C>, miner: Arc<M>, block_sync: Option<EventSender<BlockSyncEvent>>) -> Self {
+    pub fn new(
+        client: Arc<C>,
+        miner: Arc<M>,
+        block_sync: Option<EventSender<BlockSyncEvent>>,
+        enable_fault_injection: bool,
+    ) -> Self {
         let db = client.database();
         Self {
             client,
             db,
             miner,
             block_sync,
+            enable_fault_injection,
         }
     }
 }
@@ -349,4 +362,128 @@ where
         let end_time = PreciseTime::now();
         Ok(tps(count, start_time, end_time))
     }
+
+    fn set_log_level(&self, target: String, level: String) -> Result<()> {
+        let level = LevelFilter::from_str(&level).map_err(|_| errors::invalid_log_level(level))?;
+        clogger::set_log_level(&target, level);
+        cinfo!(RPC, "The log level of {} is changed to {}", target, level);
+        Ok(())
+    }
+
+    fn override_reorg_protection(&self) -> Result<()> {
+        self.client.override_reorg_protection();
+        Ok(())
+    }
+
+    fn set_consensus_fault(&self, fault: ConsensusFault) -> Result<()> {
+        if !self.enable_fault_injection {
+            return Err(errors::fault_injection_disabled())
+        }
+        self.client.set_consensus_fault(fault.into()).map_err(errors::core)
+    }
+
+    fn freeze_chain(&self) -> Result<()> {
+        self.client.freeze_chain();
+        Ok(())
+    }
+
+    fn unfreeze_chain(&self) -> Result<()> {
+        self.client.unfreeze_chain();
+        Ok(())
+    }
+
+    fn get_finality_conflict(&self) -> Result<Option<FinalityConflict>> {
+        Ok(self.client.finality_conflict().map(From::from))
+    }
+
+    fn clear_consensus_halt(&self) -> Result<Option<FinalityConflict>> {
+        Ok(self.client.clear_consensus_halt().map(From::from))
+    }
+
+    fn replay_blocks(&self, from: u64, to: u64, options: Option<ReplayOptions>) -> Result<ReplayReport> {
+        if from == 0 || from > to {
+            return Err(Error::invalid_params(format!("Invalid range: {}..={}", from, to)))
+        }
+        if to - from >= MAX_REPLAY_BLOCKS {
+            return Err(Error::invalid_params(format!("Cannot replay more than {} blocks at once", MAX_REPLAY_BLOCKS)))
+        }
+        let options = options.unwrap_or_default().into();
+        match self.client.replay_blocks(from, to, &options) {
+            Some(result) => result.map(From::from).map_err(errors::core),
+            None => Err(Error::invalid_params(format!("Some of the blocks in {}..={} are not committed", from, to))),
+        }
+    }
+
+    fn trace_transaction(&self, hash_or_raw: Bytes) -> Result<ActionHandlerTrace> {
+        let bytes = hash_or_raw.into_vec();
+        let target = if bytes.len() == 32 {
+            TraceTarget::Hash(H256::from_slice(&bytes))
+        } else {
+            let tx = UntrustedRlp::new(&bytes)
+                .as_val()
+                .map_err(|e| errors::rlp(&e))
+                .and_then(|tx| SignedTransaction::try_new(tx).map_err(errors::transaction_core))?;
+            TraceTarget::Transaction(tx)
+        };
+        match self.client.trace_transaction(&target) {
+            Some(result) => result.map(From::from).map_err(errors::core),
+            None => Err(Error::invalid_params("The transaction is not found")),
+        }
+    }
+
+    fn get_import_timings(&self, limit: usize) -> Result<Vec<BlockImportTimings>> {
+        Ok(self.client.import_timings(limit).into_iter().map(From::from).collect())
+    }
+
+    fn get_recent_rejections(&self, limit: usize) -> Result<Vec<BlockRejection>> {
+        Ok(self.client.recent_rejections(limit).into_iter().map(From::from).collect())
+    }
+
+    fn validate_scheme(&self, scheme: Value) -> Result<Vec<String>> {
+        let scheme: cjson::scheme::Scheme = serde_json::from_value(scheme)
+            .map_err(|e| Error::invalid_params(format!("The scheme cannot be deserialized: {}", e)))?;
+        match Scheme::validate(&scheme) {
+            Ok(()) => Ok(Vec::new()),
+            Err(problems) => Ok(problems.iter().map(ToString::to_string).collect()),
+        }
+    }
+
+    fn check_database_integrity(&self, from: u64, to: u64) -> Result<()> {
+        if from > to {
+            return Err(Error::invalid_params(format!("Invalid block range {}..={}", from, to)))
+        }
+        if !self.client.start_integrity_check(from, to, None) {
+            return Err(errors::integrity_check_running())
+        }
+        Ok(())
+    }
+
+    fn repair_database(&self, options: RepairOptions) -> Result<()> {
+        let (from, to) = (options.from, options.to);
+        if from > to {
+            return Err(Error::invalid_params(format!("Invalid block range {}..={}", from, to)))
+        }
+        if !self.client.start_integrity_check(from, to, Some(options.into())) {
+            return Err(errors::integrity_check_running())
+        }
+        Ok(())
+    }
+
+    fn get_database_integrity_report(&self) -> Result<Option<IntegrityReport>> {
+        Ok(self.client.integrity_report().map(From::from))
+    }
+
+    fn get_database_stats(&self) -> Result<DatabaseStats> {
+        Ok(self.client.database_stats().into())
+    }
+
+    fn set_cache_budget(&self, column: String, bytes: usize) -> Result<()> {
+        let col = column_by_name(&column)
+            .ok_or_else(|| errors::invalid_cache_budget(format!("Unknown column {}", column)))?;
+        self.client.set_cache_budget(col, bytes).map_err(|err| errors::invalid_cache_budget(err.to_string()))
+    }
+
+    fn get_action_index_backfill(&self) -> Result<Option<ActionIndexBackfill>> {
+        Ok(self.client.action_index_backfill().map(From::from))
+    }
 }
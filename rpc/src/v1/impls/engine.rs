@@ -15,6 +15,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use ccore::{BlockId, EngineInfo, MinerService, StateInfo};
 use cjson::bytes::{Bytes, WithoutPrefix};
@@ -25,6 +26,7 @@ use jsonrpc_core::Result;
 
 use super::super::errors;
 use super::super::traits::Engine;
+use super::super::types::{ConsensusStatus, EngineDescription, ProposalBudget, RestoreStatus, SignerEligibility};
 
 pub struct EngineClient<C, M>
 where
@@ -32,6 +34,7 @@ where
     M: MinerService, {
     client: Arc<C>,
     miner: Arc<M>,
+    enable_force_view: bool,
 }
 
 impl<C, M> EngineClient<C, M>
@@ -39,10 +42,11 @@ where
     C: EngineInfo + StateInfo + FindActionHandler,
     M: MinerService,
 {
-    pub fn new(client: Arc<C>, miner: Arc<M>) -> Self {
+    pub fn new(client: Arc<C>, miner: Arc<M>, enable_force_view: bool) -> Self {
         Self {
             client,
             miner,
+            enable_force_view,
         }
     }
 }
@@ -92,4 +96,49 @@ where
             Err(e) => Err(errors::transaction_core(e)),
         }
     }
+
+    fn force_next_view(&self) -> Result<()> {
+        if !self.enable_force_view {
+            return Err(errors::force_view_disabled())
+        }
+        self.client.force_next_view().map_err(errors::core)
+    }
+
+    fn get_consensus_status(&self) -> Result<Option<ConsensusStatus>> {
+        Ok(self.client.consensus_status().map(ConsensusStatus::from))
+    }
+
+    fn get_proposal_budget(&self) -> Result<ProposalBudget> {
+        let max_body_size = self.client.common_params(BlockId::Latest).unwrap().max_body_size();
+        Ok(self.miner.proposal_budget().clamp(max_body_size).into())
+    }
+
+    fn set_proposal_budget(&self, budget: ProposalBudget) -> Result<ProposalBudget> {
+        self.miner.set_proposal_budget(budget.into());
+        self.get_proposal_budget()
+    }
+
+    fn get_clock_skew_estimate(&self) -> Result<Option<i64>> {
+        Ok(self.client.consensus_status().and_then(|status| status.clock_skew))
+    }
+
+    fn promote_from_standby(&self, min_quiet_seconds: Option<u64>) -> Result<()> {
+        self.client.promote_from_standby(min_quiet_seconds.map(Duration::from_secs)).map_err(errors::core)
+    }
+
+    fn demote_to_standby(&self) -> Result<()> {
+        self.client.demote_to_standby().map_err(errors::core)
+    }
+
+    fn get_info(&self) -> Result<EngineDescription> {
+        Ok(self.client.engine_description().into())
+    }
+
+    fn get_signer_eligibility(&self) -> Result<Option<SignerEligibility>> {
+        Ok(self.client.signer_eligibility().map(From::from))
+    }
+
+    fn get_restore_status(&self) -> Result<Option<RestoreStatus>> {
+        Ok(self.client.restore_status().map(From::from))
+    }
 }
@@ -0,0 +1,131 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::BlockId;
+use cjson::uint::Uint;
+use ckey::{Address, NetworkId, PlatformAddress};
+use cnetwork::{once_event_callback, EventSender};
+use cstate::Account;
+use csync::{LightChainStatus, LightClientEvent};
+use ctypes::BlockNumber;
+use primitives::H256;
+use rlp::UntrustedRlp;
+
+use jsonrpc_core::Result;
+
+use super::super::errors;
+use super::super::traits::LightChain;
+use super::super::types::{BestBlockId, BlockFinality, BlockNumberOrHash, BlockNumberParam, LightStatus};
+
+pub struct LightChainClient {
+    light_sync: EventSender<LightClientEvent>,
+    network_id: NetworkId,
+}
+
+impl LightChainClient {
+    pub fn new(light_sync: EventSender<LightClientEvent>, network_id: NetworkId) -> Self {
+        Self {
+            light_sync,
+            network_id,
+        }
+    }
+
+    fn status(&self) -> LightChainStatus {
+        let (sender, receiver) = once_event_callback();
+        self.light_sync.send(LightClientEvent::GetStatus(sender)).unwrap();
+        receiver.recv().unwrap()
+    }
+
+    /// Resolves the block number against the best block that the light node follows.
+    fn resolve_block_number(&self, block_number: BlockNumberParam) -> Result<BlockNumber> {
+        let best_number = self.status().best_number;
+        block_number.resolve(best_number).ok_or_else(|| errors::block_offset_beyond_genesis(best_number))
+    }
+
+    /// Reads the account from the full nodes. The state of the last finalized block is used if no number is given.
+    fn account(&self, address: &Address, block_number: Option<BlockNumber>) -> Result<Account> {
+        let block_number = match block_number {
+            Some(block_number) => block_number,
+            None => self.status().finalized_number,
+        };
+        let (sender, receiver) = once_event_callback();
+        self.light_sync
+            .send(LightClientEvent::GetStateValue {
+                block_number,
+                key: address.to_vec(),
+                sender,
+            })
+            .unwrap();
+        let value = receiver
+            .recv()
+            .unwrap_or_else(|_| Err("The light client is stopped".to_string()))
+            .map_err(errors::light_query_failed)?;
+        match value {
+            Some(value) => UntrustedRlp::new(&value).as_val().map_err(|e| errors::rlp(&e)),
+            // The account that is not in the state is treated as an empty one, like the full nodes do.
+            None => Ok(Account::default()),
+        }
+    }
+}
+
+impl LightChain for LightChainClient {
+    fn get_best_block_number(&self) -> Result<BlockNumber> {
+        Ok(self.status().best_number)
+    }
+
+    fn get_best_block_id(&self) -> Result<BestBlockId> {
+        // The status is not used, because the timestamp and the author must come from the same header.
+        let (sender, receiver) = once_event_callback();
+        self.light_sync.send(LightClientEvent::GetHeader(BlockId::Latest, sender)).unwrap();
+        let header = receiver.recv().unwrap().expect("The best header always exists");
+        Ok(BestBlockId::from_core(&header, self.network_id))
+    }
+
+    fn get_block_hash(&self, block_number: BlockNumberParam) -> Result<Option<H256>> {
+        let block_number = self.resolve_block_number(block_number)?;
+        let (sender, receiver) = once_event_callback();
+        self.light_sync.send(LightClientEvent::GetHeader(BlockId::Number(block_number), sender)).unwrap();
+        Ok(receiver.recv().unwrap().map(|header| header.hash()))
+    }
+
+    fn get_block_finality(&self, block: BlockNumberOrHash) -> Result<Option<BlockFinality>> {
+        let best_number = self.status().best_number;
+        let block_id = block.resolve(best_number).ok_or_else(|| errors::block_offset_beyond_genesis(best_number))?;
+        let (sender, receiver) = once_event_callback();
+        self.light_sync.send(LightClientEvent::GetBlockFinality(block_id, sender)).unwrap();
+        Ok(receiver.recv().unwrap().map(Into::into))
+    }
+
+    fn get_seq(&self, address: PlatformAddress, block_number: Option<BlockNumberParam>) -> Result<Option<u64>> {
+        let block_number = block_number.map(|block_number| self.resolve_block_number(block_number)).transpose()?;
+        let address = address.try_address().map_err(errors::core)?;
+        Ok(Some(self.account(address, block_number)?.seq()))
+    }
+
+    fn get_balance(&self, address: PlatformAddress, block_number: Option<BlockNumberParam>) -> Result<Option<Uint>> {
+        let block_number = block_number.map(|block_number| self.resolve_block_number(block_number)).transpose()?;
+        let address = address.try_address().map_err(errors::core)?;
+        Ok(Some(self.account(address, block_number)?.balance().into()))
+    }
+
+    fn get_network_id(&self) -> Result<NetworkId> {
+        Ok(self.network_id)
+    }
+
+    fn get_status(&self) -> Result<LightStatus> {
+        Ok(LightStatus::from_core(self.status(), self.network_id))
+    }
+}
@@ -18,6 +18,7 @@ use std::sync::Arc;
 
 use ccore::{BlockChainClient, SignedTransaction};
 use cjson::bytes::Bytes;
+use ckey::PlatformAddress;
 use primitives::H256;
 use rlp::UntrustedRlp;
 
@@ -25,7 +26,10 @@ use jsonrpc_core::Result;
 
 use super::super::errors;
 use super::super::traits::Mempool;
-use super::super::types::PendingTransactions;
+use super::super::types::{
+    FeeFloor, MempoolStatus, PendingTransactions, PooledTransaction, ScheduledTransaction, SignerDiagnosis,
+};
+use super::super::Metadata;
 
 pub struct MempoolClient<C> {
     client: Arc<C>,
@@ -43,14 +47,16 @@ impl<C> Mempool for MempoolClient<C>
 where
     C: BlockChainClient + 'static,
 {
-    fn send_signed_transaction(&self, raw: Bytes) -> Result<H256> {
+    type Metadata = Metadata;
+
+    fn send_signed_transaction(&self, meta: Self::Metadata, raw: Bytes) -> Result<H256> {
         UntrustedRlp::new(&raw.into_vec())
             .as_val()
             .map_err(|e| errors::rlp(&e))
             .and_then(|tx| SignedTransaction::try_new(tx).map_err(errors::transaction_core))
             .and_then(|signed| {
                 let hash = signed.hash();
-                match self.client.queue_own_transaction(signed) {
+                match self.client.queue_rpc_transaction(signed, meta.source) {
                     Ok(_) => Ok(hash),
                     Err(e) => Err(errors::transaction_core(e)),
                 }
@@ -58,6 +64,42 @@ where
             .map(Into::into)
     }
 
+    fn send_signed_transactions(&self, meta: Self::Metadata, raw_transactions: Vec<Bytes>) -> Result<Vec<H256>> {
+        let decoded: Vec<_> = raw_transactions
+            .into_iter()
+            .map(|raw| {
+                UntrustedRlp::new(&raw.into_vec())
+                    .as_val()
+                    .map_err(|e| errors::rlp(&e))
+                    .and_then(|tx| SignedTransaction::try_new(tx).map_err(errors::transaction_core))
+            })
+            .collect();
+        if decoded.iter().any(|result| result.is_err()) {
+            return Err(errors::transactions_rejected(decoded.into_iter().map(|result| result.err()).collect()))
+        }
+
+        let transactions: Vec<_> = decoded.into_iter().filter_map(|result| result.ok()).collect();
+        let hashes = transactions.iter().map(|tx| tx.hash()).collect();
+        self.client.queue_rpc_transactions(transactions, meta.source).map_err(|rejected| {
+            errors::transactions_rejected(rejected.into_iter().map(|e| e.map(errors::transaction_core)).collect())
+        })?;
+        Ok(hashes)
+    }
+
+    fn schedule_signed_transaction(&self, meta: Self::Metadata, raw: Bytes, not_before: u64) -> Result<H256> {
+        let signed = UntrustedRlp::new(&raw.into_vec())
+            .as_val()
+            .map_err(|e| errors::rlp(&e))
+            .and_then(|tx| SignedTransaction::try_new(tx).map_err(errors::transaction_core))?;
+        let hash = signed.hash();
+        self.client.schedule_rpc_transaction(signed, meta.source, not_before).map_err(errors::transaction_core)?;
+        Ok(hash)
+    }
+
+    fn get_scheduled_transactions(&self) -> Result<Vec<ScheduledTransaction>> {
+        Ok(self.client.scheduled_transactions().into_iter().map(From::from).collect())
+    }
+
     fn get_transaction_results_by_tracker(&self, tracker: H256) -> Result<Vec<bool>> {
         Ok(self
             .client
@@ -78,4 +120,23 @@ where
     fn get_pending_transactions_count(&self, from: Option<u64>, to: Option<u64>) -> Result<usize> {
         Ok(self.client.count_pending_transactions(from.unwrap_or(0)..to.unwrap_or(::std::u64::MAX)))
     }
+
+    fn diagnose_signer(&self, address: PlatformAddress) -> Result<SignerDiagnosis> {
+        let address = address.try_address().map_err(errors::core)?;
+        Ok(self.client.diagnose_signer(address).into())
+    }
+
+    fn get_pending_transactions_by_signer(&self, address: PlatformAddress) -> Result<Vec<PooledTransaction>> {
+        let address = address.try_address().map_err(errors::core)?;
+        Ok(self.client.pending_transactions_by_signer(address).into_iter().map(From::from).collect())
+    }
+
+    fn get_fee_floor(&self) -> Result<Option<FeeFloor>> {
+        Ok(self.client.fee_floor().map(FeeFloor::from))
+    }
+
+    fn get_status(&self) -> Result<MempoolStatus> {
+        let pending_transactions = self.client.count_pending_transactions(0..::std::u64::MAX);
+        Ok(MempoolStatus::new(pending_transactions, self.client.relay_status()))
+    }
 }
@@ -18,14 +18,24 @@ mod account;
 mod chain;
 mod devel;
 mod engine;
+mod light;
 mod mempool;
 mod miner;
 mod net;
+mod node;
+mod pubsub;
+mod rpc;
+mod statistics;
 
-pub use self::account::AccountClient;
+pub use self::account::{AccountClient, AccountPolicyClient};
 pub use self::chain::ChainClient;
 pub use self::devel::DevelClient;
 pub use self::engine::EngineClient;
+pub use self::light::LightChainClient;
 pub use self::mempool::MempoolClient;
 pub use self::miner::MinerClient;
 pub use self::net::NetClient;
+pub use self::node::{HealthMonitor, HealthThresholds, NodeClient};
+pub use self::pubsub::{PubSubClient, TransactionSubscriptions};
+pub use self::rpc::{ForkSafeMode, MethodPolicies, MethodPolicy, MethodPolicyMiddleware, RpcClient, Transport};
+pub use self::statistics::{extend_with, CallStatistics, StatisticsMiddleware};
@@ -17,15 +17,19 @@
 use std::collections::HashMap;
 use std::net::{self, IpAddr};
 use std::sync::Arc;
+use std::time::Duration;
 
 use cidr::IpCidr;
 use ckey::Public;
-use cnetwork::{NetworkControl, SocketAddr};
-use jsonrpc_core::Result;
+use cnetwork::{ConnectionOutcomeKind, FilterEntry, NetworkControl, SocketAddr};
+use jsonrpc_core::{Error, Result};
 
 use super::super::errors;
 use super::super::traits::Net;
-use super::super::types::FilterStatus;
+use super::super::types::{
+    BandwidthLimits, ConnectionAttempt, DiscoveredPeer, FilterDecision, FilterStatus, NatStatus, PeerExchangeStats,
+    PeerSession, TaggedFilters,
+};
 
 pub struct NetClient {
     network_control: Arc<NetworkControl>,
@@ -80,6 +84,31 @@ impl Net for NetClient {
         Ok(peers.into_iter().map(Into::into).collect())
     }
 
+    fn get_peer_sessions(&self) -> Result<Vec<PeerSession>> {
+        let sessions = self.network_control.peer_sessions().map_err(|e| errors::network_control(&e))?;
+        Ok(sessions.into_iter().map(Into::into).collect())
+    }
+
+    fn get_discovered_peers(&self) -> Result<Vec<DiscoveredPeer>> {
+        let peers = self.network_control.discovered_peers().map_err(|e| errors::network_control(&e))?;
+        Ok(peers.into_iter().map(Into::into).collect())
+    }
+
+    fn add_seed_peer(&self, address: IpAddr, port: u16) -> Result<bool> {
+        self.network_control.add_seed_peer(SocketAddr::new(address, port)).map_err(|e| errors::network_control(&e))
+    }
+
+    fn request_peer_exchange(&self, address: IpAddr, port: u16) -> Result<()> {
+        self.network_control
+            .request_peer_exchange(SocketAddr::new(address, port))
+            .map_err(|e| errors::network_control(&e))
+    }
+
+    fn get_peer_exchange_stats(&self) -> Result<PeerExchangeStats> {
+        let stats = self.network_control.peer_exchange_stats().map_err(|e| errors::network_control(&e))?;
+        Ok(stats.into())
+    }
+
     fn add_to_whitelist(&self, addr: IpCidr, tag: Option<String>) -> Result<()> {
         self.network_control.add_to_whitelist(addr, tag).map_err(|e| errors::network_control(&e))
     }
@@ -96,6 +125,32 @@ impl Net for NetClient {
         self.network_control.remove_from_blacklist(&addr).map_err(|e| errors::network_control(&e))
     }
 
+    fn replace_blacklist(&self, rules: Vec<(IpCidr, Option<String>, Option<u64>)>) -> Result<()> {
+        let entries = rules
+            .into_iter()
+            .map(|(cidr, tag, ttl)| {
+                let entry = FilterEntry {
+                    cidr,
+                    tag: tag.unwrap_or_default(),
+                };
+                (entry, ttl.map(Duration::from_secs))
+            })
+            .collect();
+        self.network_control.replace_blacklist(entries).map_err(|e| errors::network_control(&e))
+    }
+
+    fn remove_by_tag(&self, tag: String) -> Result<usize> {
+        self.network_control.remove_by_tag(&tag).map_err(|e| errors::network_control(&e))
+    }
+
+    fn list_by_tag(&self, tag: String) -> Result<TaggedFilters> {
+        let (whitelist, blacklist) = self.network_control.list_by_tag(&tag).map_err(|e| errors::network_control(&e))?;
+        Ok(TaggedFilters {
+            whitelist,
+            blacklist,
+        })
+    }
+
     fn enable_whitelist(&self) -> Result<()> {
         self.network_control.enable_whitelist().map_err(|e| errors::network_control(&e))
     }
@@ -114,21 +169,49 @@ impl Net for NetClient {
 
     fn get_whitelist(&self) -> Result<FilterStatus> {
         let (list, enabled) = self.network_control.get_whitelist().map_err(|e| errors::network_control(&e))?;
-        Ok(FilterStatus {
-            list: list.into_iter().map(|x| (x.cidr, x.tag)).collect(),
-            enabled,
-        })
+        Ok(FilterStatus::new(list.into_iter().map(|x| (x.cidr, x.tag)).collect(), enabled))
     }
 
     fn get_blacklist(&self) -> Result<FilterStatus> {
         let (list, enabled) = self.network_control.get_blacklist().map_err(|e| errors::network_control(&e))?;
-        Ok(FilterStatus {
-            list: list.into_iter().map(|x| (x.cidr, x.tag)).collect(),
-            enabled,
-        })
+        Ok(FilterStatus::new(list.into_iter().map(|x| (x.cidr, x.tag)).collect(), enabled))
+    }
+
+    fn test_filter(&self, address: IpAddr) -> Result<FilterDecision> {
+        Ok(self.network_control.test_filter(&address).map_err(|e| errors::network_control(&e))?.into())
     }
 
     fn recent_network_usage(&self) -> Result<HashMap<String, usize>> {
         Ok(self.network_control.recent_network_usage().map_err(|e| errors::network_control(&e))?)
     }
+
+    fn get_connection_log(&self, limit: usize, outcome: Option<String>) -> Result<Vec<ConnectionAttempt>> {
+        let outcome = match outcome {
+            Some(outcome) => Some(outcome.parse::<ConnectionOutcomeKind>().map_err(Error::invalid_params)?),
+            None => None,
+        };
+        let attempts = self.network_control.connection_log(limit, outcome).map_err(|e| errors::network_control(&e))?;
+        Ok(attempts.into_iter().map(Into::into).collect())
+    }
+
+    fn get_nat_status(&self) -> Result<NatStatus> {
+        Ok(self.network_control.nat_status().map_err(|e| errors::network_control(&e))?.into())
+    }
+
+    fn refresh_nat_mapping(&self) -> Result<NatStatus> {
+        Ok(self.network_control.refresh_nat_mapping().map_err(|e| errors::network_control(&e))?.into())
+    }
+
+    fn get_extension_limits(&self) -> Result<HashMap<String, usize>> {
+        Ok(self.network_control.extension_message_size_limits().map_err(|e| errors::network_control(&e))?)
+    }
+
+    fn get_bandwidth_limits(&self) -> Result<BandwidthLimits> {
+        Ok(self.network_control.bandwidth_limits().map_err(|e| errors::network_control(&e))?.into())
+    }
+
+    fn set_bandwidth_limits(&self, limits: BandwidthLimits) -> Result<()> {
+        self.network_control.set_bandwidth_limits(limits.into()).map_err(|e| errors::network_control(&e))?;
+        Ok(())
+    }
 }
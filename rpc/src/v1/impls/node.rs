@@ -0,0 +1,127 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ccore::{BlockChainClient, DatabaseClient, EngineInfo, MinerService, COL_EXTRA};
+use cnetwork::{once_event_callback, EventSender, NetworkControl};
+use csync::BlockSyncEvent;
+use jsonrpc_core::Result;
+
+use super::super::traits::Node;
+use super::super::types::{HealthSignals, NodeHealth};
+
+pub use super::super::types::HealthThresholds;
+
+/// The key of the best block hash in COL_EXTRA, which exists from the genesis
+const BEST_BLOCK_KEY: &[u8] = b"best-block";
+
+/// Collects the health signals of the node. It's shared by `node_health` and `GET /health` of the metrics server.
+pub struct HealthMonitor<C, M> {
+    client: Arc<C>,
+    miner: Arc<M>,
+    network_control: Arc<NetworkControl>,
+    block_sync: Option<EventSender<BlockSyncEvent>>,
+    thresholds: HealthThresholds,
+}
+
+impl<C, M> HealthMonitor<C, M>
+where
+    C: BlockChainClient + EngineInfo + DatabaseClient,
+    M: MinerService,
+{
+    pub fn new(
+        client: Arc<C>,
+        miner: Arc<M>,
+        network_control: Arc<NetworkControl>,
+        block_sync: Option<EventSender<BlockSyncEvent>>,
+        thresholds: HealthThresholds,
+    ) -> Self {
+        Self {
+            client,
+            miner,
+            network_control,
+            block_sync,
+            thresholds,
+        }
+    }
+
+    pub fn check(&self) -> NodeHealth {
+        NodeHealth::evaluate(&self.signals(), &self.thresholds)
+    }
+
+    fn signals(&self) -> HealthSignals {
+        let chain_info = self.client.chain_info();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("There is no time machine.").as_secs();
+        let miner_status = self.miner.status();
+        let consensus_status = self.client.consensus_status();
+        HealthSignals {
+            best_block_age: Duration::from_secs(now.saturating_sub(chain_info.best_block_timestamp)),
+            // The peer count is 0 if the network is disabled.
+            peer_count: self.network_control.get_peer_count().unwrap_or(0),
+            sync_gap: self.client.best_header().number().saturating_sub(chain_info.best_block_number),
+            mem_pool_accepting: miner_status.transactions_in_pending_queue < self.miner.transactions_limit(),
+            consensus_step: consensus_status.as_ref().map(|status| (status.time_in_step, status.step_timeout)),
+            db_readable: self.is_db_readable(),
+            consensus_degraded: consensus_status.as_ref().map_or(false, |status| status.degradation.is_some()),
+            clock_skew: consensus_status.and_then(|status| status.clock_skew),
+            state_warming: self.client.state_warming_status(),
+            fork_suspect: self.is_fork_suspect(),
+            finality_conflict: self.client.finality_conflict().map(|conflict| conflict.block_number),
+            signer_eligibility: self.client.signer_eligibility(),
+        }
+    }
+
+    fn is_fork_suspect(&self) -> bool {
+        if let Some(block_sync) = self.block_sync.as_ref() {
+            let (sender, receiver) = once_event_callback();
+            block_sync.send(BlockSyncEvent::GetForkStatus(sender)).unwrap();
+            receiver.recv().map(|status| status.suspect).unwrap_or(false)
+        } else {
+            false
+        }
+    }
+
+    fn is_db_readable(&self) -> bool {
+        match self.client.database().get(COL_EXTRA, BEST_BLOCK_KEY) {
+            Ok(value) => value.is_some(),
+            Err(_) => false,
+        }
+    }
+}
+
+pub struct NodeClient<C, M> {
+    monitor: Arc<HealthMonitor<C, M>>,
+}
+
+impl<C, M> NodeClient<C, M> {
+    pub fn new(monitor: Arc<HealthMonitor<C, M>>) -> Self {
+        Self {
+            monitor,
+        }
+    }
+}
+
+impl<C, M> Node for NodeClient<C, M>
+where
+    C: BlockChainClient + EngineInfo + DatabaseClient + 'static,
+    M: MinerService + 'static,
+{
+    fn health(&self) -> Result<NodeHealth> {
+        Ok(self.monitor.check())
+    }
+}
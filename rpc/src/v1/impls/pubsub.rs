@@ -0,0 +1,306 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use ccore::TransactionEvent as CoreTransactionEvent;
+use jsonrpc_core::futures::Future;
+use jsonrpc_core::Result;
+use jsonrpc_macros::pubsub::{Sink, Subscriber};
+use jsonrpc_pubsub::SubscriptionId;
+use parking_lot::Mutex;
+use primitives::H256;
+
+use super::super::errors;
+use super::super::traits::PubSub;
+use super::super::types::{TransactionEvent, TransactionSubscriptionParams};
+use super::super::Metadata;
+
+const TRANSACTION_SUBSCRIPTION: &str = "transaction";
+/// The number of the transactions that a connection can watch at once
+const MAX_WATCHED_TRANSACTIONS_PER_SESSION: usize = 1024;
+
+struct WatchedTransactions {
+    session_id: usize,
+    hashes: HashSet<H256>,
+}
+
+/// Routes the transaction events to the subscriptions that watch the transactions.
+struct TransactionWatcher {
+    max_watched_per_session: usize,
+    subscriptions: HashMap<u64, WatchedTransactions>,
+    watchers: HashMap<H256, HashSet<u64>>,
+    watched_per_session: HashMap<usize, usize>,
+}
+
+impl TransactionWatcher {
+    fn new(max_watched_per_session: usize) -> Self {
+        Self {
+            max_watched_per_session,
+            subscriptions: HashMap::new(),
+            watchers: HashMap::new(),
+            watched_per_session: HashMap::new(),
+        }
+    }
+
+    /// Returns false if the session would watch more transactions than the limit.
+    fn watch(&mut self, subscription_id: u64, session_id: usize, hashes: HashSet<H256>) -> bool {
+        let watched = self.watched_per_session.get(&session_id).cloned().unwrap_or(0);
+        if watched + hashes.len() > self.max_watched_per_session {
+            return false
+        }
+        self.watched_per_session.insert(session_id, watched + hashes.len());
+        for hash in &hashes {
+            self.watchers.entry(*hash).or_default().insert(subscription_id);
+        }
+        self.subscriptions.insert(subscription_id, WatchedTransactions {
+            session_id,
+            hashes,
+        });
+        true
+    }
+
+    fn unwatch(&mut self, subscription_id: u64) -> bool {
+        let watched = match self.subscriptions.remove(&subscription_id) {
+            Some(watched) => watched,
+            None => return false,
+        };
+        for hash in &watched.hashes {
+            let is_empty = match self.watchers.get_mut(hash) {
+                Some(subscriptions) => {
+                    subscriptions.remove(&subscription_id);
+                    subscriptions.is_empty()
+                }
+                None => false,
+            };
+            if is_empty {
+                self.watchers.remove(hash);
+            }
+        }
+        let remaining = self.watched_per_session[&watched.session_id] - watched.hashes.len();
+        if remaining == 0 {
+            self.watched_per_session.remove(&watched.session_id);
+        } else {
+            self.watched_per_session.insert(watched.session_id, remaining);
+        }
+        true
+    }
+
+    fn route(&self, events: &[CoreTransactionEvent]) -> Vec<(u64, TransactionEvent)> {
+        let mut notifications = Vec::new();
+        for event in events {
+            if let Some(subscriptions) = self.watchers.get(event.hash()) {
+                for subscription_id in subscriptions {
+                    notifications.push((*subscription_id, (*event).into()));
+                }
+            }
+        }
+        notifications
+    }
+}
+
+/// The subscriptions to the transaction events.
+///
+/// The notifications are sent on a dedicated thread,
+/// so that a slow connection doesn't block the miner that reports the events.
+pub struct TransactionSubscriptions {
+    next_subscription_id: AtomicUsize,
+    watcher: Mutex<TransactionWatcher>,
+    sinks: Arc<Mutex<HashMap<u64, Sink<TransactionEvent>>>>,
+    notifier: Mutex<mpsc::Sender<Vec<(u64, TransactionEvent)>>>,
+}
+
+impl TransactionSubscriptions {
+    pub fn new() -> Arc<Self> {
+        let sinks: Arc<Mutex<HashMap<u64, Sink<TransactionEvent>>>> = Default::default();
+        let (sender, receiver) = mpsc::channel::<Vec<(u64, TransactionEvent)>>();
+        let notifying_sinks = Arc::clone(&sinks);
+        thread::Builder::new()
+            .name("rpc.subscription".to_string())
+            .spawn(move || {
+                for notifications in receiver {
+                    for (subscription_id, event) in notifications {
+                        let sink = notifying_sinks.lock().get(&subscription_id).cloned();
+                        if let Some(sink) = sink {
+                            if let Err(err) = sink.notify(Ok(event)).wait() {
+                                cdebug!(RPC, "Cannot notify the subscription {}: {:?}", subscription_id, err);
+                            }
+                        }
+                    }
+                }
+            })
+            .expect("Subscription thread must be spawned");
+
+        Arc::new(Self {
+            next_subscription_id: AtomicUsize::new(0),
+            watcher: Mutex::new(TransactionWatcher::new(MAX_WATCHED_TRANSACTIONS_PER_SESSION)),
+            sinks,
+            notifier: Mutex::new(sender),
+        })
+    }
+
+    /// Sends the events to the subscriptions that watch the transactions.
+    pub fn notify(&self, events: &[CoreTransactionEvent]) {
+        let notifications = self.watcher.lock().route(events);
+        if notifications.is_empty() {
+            return
+        }
+        if self.notifier.lock().send(notifications).is_err() {
+            cwarn!(RPC, "The subscription thread is terminated");
+        }
+    }
+
+    fn subscribe(&self, session_id: usize, hashes: HashSet<H256>, subscriber: Subscriber<TransactionEvent>) {
+        let subscription_id = self.next_subscription_id.fetch_add(1, Ordering::SeqCst) as u64;
+        let mut watcher = self.watcher.lock();
+        if !watcher.watch(subscription_id, session_id, hashes) {
+            let _ = subscriber.reject(errors::too_many_watched_transactions(watcher.max_watched_per_session));
+            return
+        }
+        match subscriber.assign_id(SubscriptionId::Number(subscription_id)) {
+            Ok(sink) => {
+                self.sinks.lock().insert(subscription_id, sink);
+            }
+            Err(()) => {
+                // The connection is already closed.
+                watcher.unwatch(subscription_id);
+            }
+        }
+    }
+
+    /// Subscriptions are also removed when their connections are closed.
+    fn unsubscribe(&self, subscription_id: u64) -> bool {
+        self.sinks.lock().remove(&subscription_id);
+        self.watcher.lock().unwatch(subscription_id)
+    }
+}
+
+pub struct PubSubClient {
+    transaction_subscriptions: Arc<TransactionSubscriptions>,
+}
+
+impl PubSubClient {
+    pub fn new(transaction_subscriptions: Arc<TransactionSubscriptions>) -> Self {
+        PubSubClient {
+            transaction_subscriptions,
+        }
+    }
+}
+
+impl PubSub for PubSubClient {
+    type Metadata = Metadata;
+
+    fn subscribe(
+        &self,
+        meta: Self::Metadata,
+        subscriber: Subscriber<TransactionEvent>,
+        kind: String,
+        params: TransactionSubscriptionParams,
+    ) {
+        let session_id = match meta.session_id {
+            Some(session_id) => session_id,
+            None => {
+                let _ = subscriber.reject(errors::subscription_unavailable());
+                return
+            }
+        };
+        if kind != TRANSACTION_SUBSCRIPTION {
+            let _ = subscriber.reject(errors::unknown_subscription_kind(kind));
+            return
+        }
+        self.transaction_subscriptions.subscribe(session_id, params.hashes, subscriber);
+    }
+
+    fn unsubscribe(&self, id: SubscriptionId) -> Result<bool> {
+        match id {
+            SubscriptionId::Number(subscription_id) => Ok(self.transaction_subscriptions.unsubscribe(subscription_id)),
+            SubscriptionId::String(_) => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hashes(hashes: &[H256]) -> HashSet<H256> {
+        hashes.iter().cloned().collect()
+    }
+
+    #[test]
+    fn watched_transaction_is_queued_mined_and_retracted() {
+        let tx = H256::random();
+        let other = H256::random();
+        let block = H256::random();
+        let mut watcher = TransactionWatcher::new(10);
+        assert!(watcher.watch(1, 0, hashes(&[tx])));
+
+        let events = [
+            CoreTransactionEvent::Queued {
+                hash: other,
+            },
+            CoreTransactionEvent::Queued {
+                hash: tx,
+            },
+            CoreTransactionEvent::Mined {
+                hash: tx,
+                block_hash: block,
+                block_number: 3,
+            },
+            CoreTransactionEvent::Retracted {
+                hash: tx,
+                block_hash: block,
+                block_number: 3,
+            },
+        ];
+        let notifications = watcher.route(&events);
+        let expected: Vec<(u64, TransactionEvent)> = events[1..].iter().map(|event| (1, (*event).into())).collect();
+        assert_eq!(expected, notifications);
+    }
+
+    #[test]
+    fn watched_transactions_are_limited_per_session() {
+        let mut watcher = TransactionWatcher::new(3);
+        assert!(watcher.watch(1, 0, hashes(&[H256::random(), H256::random()])));
+        assert!(!watcher.watch(2, 0, hashes(&[H256::random(), H256::random()])));
+        // Another connection has its own limit.
+        assert!(watcher.watch(3, 1, hashes(&[H256::random(), H256::random()])));
+
+        assert!(watcher.unwatch(1));
+        assert!(!watcher.unwatch(1));
+        assert!(watcher.watch(4, 0, hashes(&[H256::random(), H256::random(), H256::random()])));
+    }
+
+    #[test]
+    fn unwatched_transaction_is_not_routed() {
+        let tx = H256::random();
+        let mut watcher = TransactionWatcher::new(10);
+        assert!(watcher.watch(1, 0, hashes(&[tx])));
+        assert!(watcher.watch(2, 1, hashes(&[tx])));
+        assert!(watcher.unwatch(1));
+
+        let notifications = watcher.route(&[CoreTransactionEvent::Queued {
+            hash: tx,
+        }]);
+        assert_eq!(vec![2], notifications.into_iter().map(|(id, _)| id).collect::<Vec<_>>());
+        assert!(watcher.unwatch(2));
+        assert!(watcher.watchers.is_empty());
+        assert!(watcher.watched_per_session.is_empty());
+    }
+}
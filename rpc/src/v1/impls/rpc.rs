@@ -0,0 +1,525 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use cnetwork::{once_event_callback, EventSender};
+use csync::{BlockSyncEvent, ForkStatus};
+use jsonrpc_core::futures::{future, Future};
+use jsonrpc_core::{Call, Error, FutureResponse, Metadata, Middleware, Output, Request, Response, Result};
+use parking_lot::RwLock;
+
+use super::super::errors;
+use super::super::traits::Rpc;
+use super::super::types::RpcStatistics;
+use super::statistics::CallStatistics;
+
+pub use super::super::types::{MethodPolicy, Transport};
+
+/// The method policies of the transports. They are shared by the servers, and changed by `rpc_setMethodPolicy`.
+pub struct MethodPolicies {
+    policies: RwLock<HashMap<Transport, MethodPolicy>>,
+}
+
+impl MethodPolicies {
+    /// The transports that are not in `policies` follow `MethodPolicy::default_for`.
+    pub fn new(mut policies: HashMap<Transport, MethodPolicy>) -> Self {
+        for transport in &[Transport::Http, Transport::Ws, Transport::Ipc] {
+            policies.entry(*transport).or_insert_with(|| MethodPolicy::default_for(*transport));
+        }
+        Self {
+            policies: RwLock::new(policies),
+        }
+    }
+
+    pub fn get(&self, transport: Transport) -> MethodPolicy {
+        self.policies.read()[&transport].clone()
+    }
+
+    pub fn set(&self, transport: Transport, policy: MethodPolicy) {
+        self.policies.write().insert(transport, policy);
+    }
+
+    pub fn is_allowed(&self, transport: Transport, method: &str) -> bool {
+        self.policies.read()[&transport].is_allowed(method)
+    }
+}
+
+impl Default for MethodPolicies {
+    fn default() -> Self {
+        Self::new(HashMap::new())
+    }
+}
+
+/// The queries whose answers come from the state of the best block.
+const STATE_QUERIES: &[&str] = &[
+    "chain_getAsset*",
+    "chain_isAssetSpent",
+    "chain_getText",
+    "chain_getSeq",
+    "chain_getBalance",
+    "chain_getRegularKey*",
+    "chain_getNumberOfShards",
+    "chain_getShard*",
+    "chain_getMinTransactionFee",
+    "chain_getCommonParams",
+    "chain_getTermMetadata",
+    "chain_getMetadataSeq",
+    "chain_getPossibleAuthors",
+    "chain_getValidatorSet",
+    "chain_getCandidates",
+    "chain_getTotalBurnt",
+    "chain_executeTransaction",
+    "chain_dryRunTransaction",
+    "chain_executeVM",
+    "engine_getCustomActionData",
+];
+
+/// Guards the state queries while the sync extension suspects that the node is on a minority fork.
+///
+/// The queries are served with a warning in the log, or rejected if it's strict.
+pub struct ForkSafeMode {
+    block_sync: Option<EventSender<BlockSyncEvent>>,
+    strict: bool,
+    state_queries: MethodPolicy,
+}
+
+impl ForkSafeMode {
+    pub fn new(block_sync: Option<EventSender<BlockSyncEvent>>, strict: bool) -> Self {
+        Self {
+            block_sync,
+            strict,
+            state_queries: MethodPolicy {
+                allow: STATE_QUERIES.iter().map(|pattern| pattern.to_string()).collect(),
+                deny: Vec::new(),
+            },
+        }
+    }
+
+    fn fork_status(&self) -> ForkStatus {
+        if let Some(block_sync) = self.block_sync.as_ref() {
+            let (sender, receiver) = once_event_callback();
+            block_sync.send(BlockSyncEvent::GetForkStatus(sender)).unwrap();
+            receiver.recv().unwrap_or_default()
+        } else {
+            ForkStatus::default()
+        }
+    }
+
+    fn check(&self, method: &str) -> Result<()> {
+        if !self.state_queries.is_allowed(method) {
+            return Ok(())
+        }
+        let status = self.fork_status();
+        if !status.suspect {
+            return Ok(())
+        }
+        if self.strict {
+            cinfo!(RPC, "{} is rejected because the node may be on a minority fork", method);
+            return Err(errors::minority_fork(method, status.disagreeing_peers, status.peers))
+        }
+        cwarn!(
+            RPC,
+            "{} is served while the node may be on a minority fork: {} of {} peers disagree",
+            method,
+            status.disagreeing_peers,
+            status.peers
+        );
+        Ok(())
+    }
+}
+
+/// Rejects the calls that the policy of the transport doesn't allow, before passing the request to `inner`.
+pub struct MethodPolicyMiddleware<N> {
+    transport: Transport,
+    policies: Arc<MethodPolicies>,
+    fork_safe_mode: Option<Arc<ForkSafeMode>>,
+    inner: N,
+}
+
+impl<N> MethodPolicyMiddleware<N> {
+    pub fn new(transport: Transport, policies: Arc<MethodPolicies>, inner: N) -> Self {
+        Self {
+            transport,
+            policies,
+            fork_safe_mode: None,
+            inner,
+        }
+    }
+
+    pub fn with_fork_safe_mode(mut self, fork_safe_mode: Arc<ForkSafeMode>) -> Self {
+        self.fork_safe_mode = Some(fork_safe_mode);
+        self
+    }
+
+    fn check(&self, call: &Call) -> Result<()> {
+        let method = match call {
+            Call::MethodCall(method_call) => &method_call.method,
+            Call::Notification(notification) => &notification.method,
+            Call::Invalid(_) => return Ok(()),
+        };
+        if !self.policies.is_allowed(self.transport, method) {
+            cinfo!(RPC, "{} is not allowed on {}", method, self.transport);
+            return Err(errors::method_not_allowed(method, self.transport))
+        }
+        match self.fork_safe_mode.as_ref() {
+            Some(fork_safe_mode) => fork_safe_mode.check(method),
+            None => Ok(()),
+        }
+    }
+
+    /// The notifications are dropped silently, because they don't have responses.
+    fn rejection(call: &Call, error: Error) -> Option<Output> {
+        match call {
+            Call::MethodCall(method_call) => {
+                Some(Output::from(Err(error), method_call.id.clone(), method_call.jsonrpc))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<M: Metadata, N: Middleware<M>> Middleware<M> for MethodPolicyMiddleware<N> {
+    type Future = FutureResponse;
+
+    fn on_request<F, X>(&self, request: Request, meta: M, next: F) -> Self::Future
+    where
+        F: FnOnce(Request, M) -> X + Send,
+        X: Future<Item = Option<Response>, Error = ()> + Send + 'static, {
+        match request {
+            Request::Single(call) => match self.check(&call) {
+                Ok(()) => Box::new(self.inner.on_request(Request::Single(call), meta, next)),
+                Err(error) => Box::new(future::ok(Self::rejection(&call, error).map(Response::Single))),
+            },
+            Request::Batch(calls) => {
+                let mut allowed = Vec::with_capacity(calls.len());
+                let mut rejected = Vec::new();
+                for call in calls {
+                    match self.check(&call) {
+                        Ok(()) => allowed.push(call),
+                        Err(error) => rejected.extend(Self::rejection(&call, error)),
+                    }
+                }
+                if allowed.is_empty() {
+                    let response = if rejected.is_empty() {
+                        None
+                    } else {
+                        Some(Response::Batch(rejected))
+                    };
+                    return Box::new(future::ok(response))
+                }
+                Box::new(self.inner.on_request(Request::Batch(allowed), meta, next).map(move |response| {
+                    let mut outputs = match response {
+                        Some(Response::Batch(outputs)) => outputs,
+                        Some(Response::Single(output)) => vec![output],
+                        None => Vec::new(),
+                    };
+                    outputs.extend(rejected);
+                    if outputs.is_empty() {
+                        None
+                    } else {
+                        Some(Response::Batch(outputs))
+                    }
+                }))
+            }
+        }
+    }
+}
+
+pub struct RpcClient {
+    policies: Arc<MethodPolicies>,
+    statistics: Arc<CallStatistics>,
+}
+
+impl RpcClient {
+    pub fn new(policies: Arc<MethodPolicies>, statistics: Arc<CallStatistics>) -> Self {
+        Self {
+            policies,
+            statistics,
+        }
+    }
+}
+
+impl Rpc for RpcClient {
+    fn set_method_policy(&self, transport: Transport, policy: MethodPolicy) -> Result<()> {
+        cinfo!(RPC, "The method policy of {} is changed to {:?}", transport, policy);
+        self.policies.set(transport, policy);
+        Ok(())
+    }
+
+    fn get_statistics(&self) -> Result<RpcStatistics> {
+        Ok(self.statistics.snapshot())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{SocketAddr, TcpStream};
+    use std::os::unix::net::UnixStream;
+    use std::process;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+
+    use jsonrpc_core::middleware::Noop;
+    use jsonrpc_core::{MetaIoHandler, Params, Value};
+    use serde_json::{self, json};
+
+    use super::super::super::Metadata;
+    use super::*;
+    use rpc_server::{start_http, start_ipc, start_ws};
+
+    const METHOD_NOT_ALLOWED: i64 = -32059;
+    const MINORITY_FORK: i64 = -32064;
+
+    fn handler(
+        transport: Transport,
+        policies: &Arc<MethodPolicies>,
+    ) -> MetaIoHandler<Metadata, MethodPolicyMiddleware<Noop>> {
+        let mut handler =
+            MetaIoHandler::with_middleware(MethodPolicyMiddleware::new(transport, Arc::clone(policies), Noop));
+        handler.add_method("chain_getBestBlockNumber", |_params: Params| Ok(Value::from(7)));
+        handler.add_method("devel_startSealing", |_params: Params| Ok(Value::Null));
+        handler.add_method("account_create", |_params: Params| {
+            Ok(Value::from("tccq9h7vnl68frvqapzv3tujrxtxtwqdnxw6yamrrgd"))
+        });
+        if transport == Transport::Ipc {
+            let statistics = Arc::new(CallStatistics::new(None));
+            handler.extend_with(RpcClient::new(Arc::clone(policies), statistics).to_delegate());
+        }
+        handler
+    }
+
+    fn request(method: &str, params: serde_json::Value) -> String {
+        json!({"jsonrpc": "2.0", "method": method, "params": params, "id": 1}).to_string()
+    }
+
+    fn http_call(addr: &SocketAddr, body: &str) -> serde_json::Value {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "POST / HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            addr,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let body = &response[response.find("\r\n\r\n").unwrap() + 4..];
+        serde_json::from_str(body.trim()).unwrap()
+    }
+
+    fn ipc_call(path: &str, body: &str) -> serde_json::Value {
+        let mut stream = UnixStream::connect(path).unwrap();
+        stream.write_all(body.as_bytes()).unwrap();
+        stream.write_all(b"\n").unwrap();
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line).unwrap();
+        serde_json::from_str(line.trim()).unwrap()
+    }
+
+    /// Sends the request in a masked text frame after the handshake, and reads the unmasked reply.
+    fn ws_call(addr: &SocketAddr, body: &str) -> serde_json::Value {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "GET / HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            addr
+        )
+        .unwrap();
+        let mut handshake = Vec::new();
+        let mut byte = [0u8; 1];
+        while !handshake.ends_with(b"\r\n\r\n") {
+            stream.read_exact(&mut byte).unwrap();
+            handshake.push(byte[0]);
+        }
+        assert!(handshake.starts_with(b"HTTP/1.1 101"));
+
+        let mut frame = vec![0x81];
+        if body.len() < 126 {
+            frame.push(0x80 | body.len() as u8);
+        } else {
+            frame.push(0x80 | 126);
+            frame.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        }
+        // The zero mask leaves the payload as it is.
+        frame.extend_from_slice(&[0, 0, 0, 0]);
+        frame.extend_from_slice(body.as_bytes());
+        stream.write_all(&frame).unwrap();
+
+        let mut header = [0u8; 2];
+        stream.read_exact(&mut header).unwrap();
+        let len = match header[1] & 0x7f {
+            126 => {
+                let mut len = [0u8; 2];
+                stream.read_exact(&mut len).unwrap();
+                u16::from_be_bytes(len) as usize
+            }
+            127 => {
+                let mut len = [0u8; 8];
+                stream.read_exact(&mut len).unwrap();
+                u64::from_be_bytes(len) as usize
+            }
+            len => len as usize,
+        };
+        let mut payload = vec![0u8; len];
+        stream.read_exact(&mut payload).unwrap();
+        serde_json::from_slice(&payload).unwrap()
+    }
+
+    fn assert_allowed(response: &serde_json::Value) {
+        assert!(response.get("result").is_some(), "{}", response);
+    }
+
+    fn assert_not_allowed(response: &serde_json::Value, transport: &str) {
+        assert_eq!(Some(METHOD_NOT_ALLOWED), response["error"]["code"].as_i64(), "{}", response);
+        assert_eq!(transport, response["error"]["data"]["transport"]);
+    }
+
+    #[test]
+    fn servers_enforce_the_policy_of_their_transport() {
+        let mut configured = HashMap::new();
+        configured.insert(Transport::Ws, MethodPolicy {
+            allow: vec!["*".to_string()],
+            deny: vec!["devel_*".to_string()],
+        });
+        let policies = Arc::new(MethodPolicies::new(configured));
+
+        let http_addr: SocketAddr = "127.0.0.1:38541".parse().unwrap();
+        let _http = start_http(&http_addr, None, None, handler(Transport::Http, &policies)).unwrap();
+        let ws_addr: SocketAddr = "127.0.0.1:38542".parse().unwrap();
+        let _ws = start_ws(&ws_addr, handler(Transport::Ws, &policies), 10).unwrap();
+        let ipc_path = format!("/tmp/codechain-method-policy-{}.ipc", process::id());
+        let _ipc = start_ipc(&ipc_path, handler(Transport::Ipc, &policies)).unwrap();
+
+        let best_block_number = request("chain_getBestBlockNumber", json!([]));
+        let start_sealing = request("devel_startSealing", json!([]));
+        let create_account = request("account_create", json!([]));
+
+        // HTTP follows the default policy.
+        assert_eq!(json!(7), http_call(&http_addr, &best_block_number)["result"]);
+        assert_not_allowed(&http_call(&http_addr, &start_sealing), "http");
+        assert_not_allowed(&http_call(&http_addr, &create_account), "http");
+
+        // WebSockets follows the configured policy.
+        assert_allowed(&ws_call(&ws_addr, &best_block_number));
+        assert_not_allowed(&ws_call(&ws_addr, &start_sealing), "ws");
+        assert_allowed(&ws_call(&ws_addr, &create_account));
+
+        // IPC gets everything.
+        assert_allowed(&ipc_call(&ipc_path, &best_block_number));
+        assert_allowed(&ipc_call(&ipc_path, &start_sealing));
+        assert_allowed(&ipc_call(&ipc_path, &create_account));
+
+        // The policy can be changed only on IPC.
+        let open_http = request("rpc_setMethodPolicy", json!(["http", {"allow": ["*"], "deny": ["account_*"]}]));
+        assert_not_allowed(&http_call(&http_addr, &open_http), "http");
+        assert_allowed(&ipc_call(&ipc_path, &open_http));
+        assert_allowed(&http_call(&http_addr, &start_sealing));
+        assert_not_allowed(&http_call(&http_addr, &create_account), "http");
+        // Even if the policy allows it, the other transports don't serve rpc_setMethodPolicy.
+        assert!(http_call(&http_addr, &open_http)["error"].is_object());
+
+        let _ = std::fs::remove_file(&ipc_path);
+    }
+
+    #[test]
+    fn batch_returns_the_results_of_the_allowed_calls_and_the_errors_of_the_others() {
+        let policies = Arc::new(MethodPolicies::default());
+        let http_addr: SocketAddr = "127.0.0.1:38543".parse().unwrap();
+        let _http = start_http(&http_addr, None, None, handler(Transport::Http, &policies)).unwrap();
+
+        let batch = json!([
+            {"jsonrpc": "2.0", "method": "chain_getBestBlockNumber", "params": [], "id": 1},
+            {"jsonrpc": "2.0", "method": "devel_startSealing", "params": [], "id": 2},
+            {"jsonrpc": "2.0", "method": "devel_startSealing", "params": []},
+        ]);
+        let responses = http_call(&http_addr, &batch.to_string());
+        let mut responses = responses.as_array().unwrap().clone();
+        responses.sort_by_key(|response| response["id"].as_u64());
+        assert_eq!(2, responses.len());
+        assert_eq!(json!(7), responses[0]["result"]);
+        assert_not_allowed(&responses[1], "http");
+        assert_eq!(json!(2), responses[1]["id"]);
+
+        let denied_only = json!([{"jsonrpc": "2.0", "method": "account_create", "params": [], "id": 3}]);
+        let responses = http_call(&http_addr, &denied_only.to_string());
+        assert_not_allowed(&responses[0], "http");
+    }
+
+    /// Answers the fork status queries like the sync extension.
+    fn fake_sync(suspect: Arc<AtomicBool>) -> EventSender<BlockSyncEvent> {
+        let (sender, receiver) = once_event_callback();
+        thread::spawn(move || {
+            while let Ok(event) = receiver.recv() {
+                if let BlockSyncEvent::GetForkStatus(channel) = event {
+                    let status = ForkStatus {
+                        suspect: suspect.load(Ordering::SeqCst),
+                        disagreeing_peers: 3,
+                        peers: 4,
+                    };
+                    channel.send(status).unwrap();
+                }
+            }
+        });
+        sender
+    }
+
+    fn fork_safe_handler(
+        suspect: &Arc<AtomicBool>,
+        strict: bool,
+    ) -> MetaIoHandler<Metadata, MethodPolicyMiddleware<Noop>> {
+        let fork_safe_mode = Arc::new(ForkSafeMode::new(Some(fake_sync(Arc::clone(suspect))), strict));
+        let middleware = MethodPolicyMiddleware::new(Transport::Ipc, Arc::new(MethodPolicies::default()), Noop)
+            .with_fork_safe_mode(fork_safe_mode);
+        let mut handler = MetaIoHandler::with_middleware(middleware);
+        handler.add_method("chain_getBestBlockNumber", |_params: Params| Ok(Value::from(7)));
+        handler.add_method("chain_getBalance", |_params: Params| Ok(Value::from("0x64")));
+        handler
+    }
+
+    fn call(handler: &MetaIoHandler<Metadata, MethodPolicyMiddleware<Noop>>, method: &str) -> serde_json::Value {
+        let response = handler.handle_request_sync(&request(method, json!([])), Metadata::default()).unwrap();
+        serde_json::from_str(&response).unwrap()
+    }
+
+    #[test]
+    fn strict_fork_safe_mode_rejects_the_state_queries_while_suspect() {
+        let suspect = Arc::new(AtomicBool::new(false));
+        let handler = fork_safe_handler(&suspect, true);
+        assert_eq!(json!("0x64"), call(&handler, "chain_getBalance")["result"]);
+
+        suspect.store(true, Ordering::SeqCst);
+        let response = call(&handler, "chain_getBalance");
+        assert_eq!(Some(MINORITY_FORK), response["error"]["code"].as_i64(), "{}", response);
+        // The queries that don't read the state are served.
+        assert_eq!(json!(7), call(&handler, "chain_getBestBlockNumber")["result"]);
+
+        // Recovered
+        suspect.store(false, Ordering::SeqCst);
+        assert_eq!(json!("0x64"), call(&handler, "chain_getBalance")["result"]);
+    }
+
+    #[test]
+    fn fork_safe_mode_serves_the_state_queries_unless_strict() {
+        let suspect = Arc::new(AtomicBool::new(true));
+        let handler = fork_safe_handler(&suspect, false);
+        assert_eq!(json!("0x64"), call(&handler, "chain_getBalance")["result"]);
+    }
+}
@@ -0,0 +1,482 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::net::IpAddr;
+use std::slice;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use cmetrics::{Collector, Counter, Summary};
+use jsonrpc_core::futures::Future;
+use jsonrpc_core::{Call, FutureResponse, Id, MetaIoHandler, Middleware, Output, RemoteProcedure, Request, Response};
+use parking_lot::RwLock;
+
+use super::super::types::{Latency, MethodStatistics, RpcStatistics, SourceStatistics};
+use super::super::Metadata;
+
+/// The unregistered methods and the sources beyond the limit are counted together as `OTHER`,
+/// so the clients cannot grow the tables with random method names or addresses.
+const MAX_SOURCES: usize = 64;
+const OTHER: &str = "other";
+/// The error code of the calls to the unregistered methods
+const METHOD_NOT_FOUND: i64 = -32601;
+
+const QUANTILES: [f64; 3] = [0.5, 0.9, 0.99];
+
+#[derive(Default)]
+struct MethodCounters {
+    calls: Counter,
+    /// The latest calls are sampled for the percentiles.
+    latency: Summary,
+    errors: RwLock<BTreeMap<i64, Counter>>,
+}
+
+impl MethodCounters {
+    fn count_error(&self, code: i64) {
+        if let Some(counter) = self.errors.read().get(&code) {
+            counter.inc();
+            return
+        }
+        self.errors.write().entry(code).or_default().inc();
+    }
+
+    fn errors(&self) -> BTreeMap<i64, u64> {
+        self.errors.read().iter().map(|(code, counter)| (*code, counter.get() as u64)).collect()
+    }
+}
+
+/// Counts the calls of each method and each source, which are shared by the servers of all transports.
+///
+/// The counters are atomic, and the locks are written only when a method is registered, or a source or an error code
+/// is seen first.
+pub struct CallStatistics {
+    /// The calls that take longer than this are logged one by one.
+    slow_call_threshold: Option<Duration>,
+    /// The registered methods
+    methods: RwLock<HashMap<String, Arc<MethodCounters>>>,
+    other_methods: Arc<MethodCounters>,
+    sources: RwLock<HashMap<IpAddr, Counter>>,
+    other_sources: Counter,
+}
+
+impl CallStatistics {
+    pub fn new(slow_call_threshold: Option<Duration>) -> Self {
+        Self {
+            slow_call_threshold,
+            methods: Default::default(),
+            other_methods: Default::default(),
+            sources: Default::default(),
+            other_sources: Default::default(),
+        }
+    }
+
+    /// Only the registered methods are counted by their names. They are registered when they are added to the handlers.
+    pub fn register_methods<I, S>(&self, methods: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>, {
+        let mut registered = self.methods.write();
+        for method in methods {
+            registered.entry(method.into()).or_default();
+        }
+    }
+
+    fn method(&self, method: &str, error: Option<i64>) -> Arc<MethodCounters> {
+        if error == Some(METHOD_NOT_FOUND) {
+            return Arc::clone(&self.other_methods)
+        }
+        match self.methods.read().get(method) {
+            Some(counters) => Arc::clone(counters),
+            None => Arc::clone(&self.other_methods),
+        }
+    }
+
+    fn count_source(&self, source: IpAddr) {
+        if let Some(counter) = self.sources.read().get(&source) {
+            counter.inc();
+            return
+        }
+        let mut sources = self.sources.write();
+        if sources.len() >= MAX_SOURCES && !sources.contains_key(&source) {
+            self.other_sources.inc();
+            return
+        }
+        sources.entry(source).or_default().inc();
+    }
+
+    pub fn record(&self, method: &str, source: Option<IpAddr>, elapsed: Duration, error: Option<i64>) {
+        let counters = self.method(method, error);
+        counters.calls.inc();
+        counters.latency.observe(elapsed);
+        if let Some(code) = error {
+            counters.count_error(code);
+        }
+        if let Some(source) = source {
+            self.count_source(source);
+        }
+        if self.slow_call_threshold.map_or(false, |threshold| elapsed >= threshold) {
+            let source = source.map_or_else(|| "local".to_string(), |source| source.to_string());
+            cwarn!(RPC, "{} from {} took {} ms", method, source, elapsed.as_millis());
+        }
+    }
+
+    pub fn snapshot(&self) -> RpcStatistics {
+        let mut methods: Vec<_> = self
+            .methods
+            .read()
+            .iter()
+            .filter(|(_, counters)| counters.calls.get() > 0)
+            .map(|(method, counters)| method_statistics(method, counters))
+            .collect();
+        methods.sort_unstable_by(|a, b| a.method.cmp(&b.method));
+        if self.other_methods.calls.get() > 0 {
+            methods.push(method_statistics(OTHER, &self.other_methods));
+        }
+
+        let mut sources: Vec<_> = self
+            .sources
+            .read()
+            .iter()
+            .map(|(source, counter)| SourceStatistics {
+                source: source.to_string(),
+                calls: counter.get() as u64,
+            })
+            .collect();
+        sources.sort_unstable_by(|a, b| b.calls.cmp(&a.calls).then_with(|| a.source.cmp(&b.source)));
+        if self.other_sources.get() > 0 {
+            sources.push(SourceStatistics {
+                source: OTHER.to_string(),
+                calls: self.other_sources.get() as u64,
+            });
+        }
+        RpcStatistics {
+            methods,
+            sources,
+        }
+    }
+}
+
+/// Adds the methods to the handler, and registers them to the statistics.
+pub fn extend_with<M, D>(handler: &mut MetaIoHandler<Metadata, M>, statistics: &CallStatistics, methods: D)
+where
+    M: Middleware<Metadata>,
+    D: Into<HashMap<String, RemoteProcedure<Metadata>>>, {
+    let methods = methods.into();
+    statistics.register_methods(methods.keys().cloned());
+    handler.extend_with(methods);
+}
+
+fn method_statistics(method: &str, counters: &MethodCounters) -> MethodStatistics {
+    let quantiles = counters.latency.quantiles(&QUANTILES);
+    let latency = match quantiles.as_slice() {
+        [Some(p50), Some(p90), Some(p99)] => Some(Latency {
+            p50: p50 * 1000.0,
+            p90: p90 * 1000.0,
+            p99: p99 * 1000.0,
+        }),
+        _ => None,
+    };
+    MethodStatistics {
+        method: method.to_string(),
+        calls: counters.calls.get() as u64,
+        latency,
+        errors: counters.errors(),
+    }
+}
+
+/// The method names are escaped in case they have the special characters of the format.
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+impl Collector for CallStatistics {
+    fn render(&self, out: &mut String) {
+        let mut methods: Vec<_> = self
+            .methods
+            .read()
+            .iter()
+            .filter(|(_, counters)| counters.calls.get() > 0)
+            .map(|(method, counters)| (escape_label(method), Arc::clone(counters)))
+            .collect();
+        methods.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        if self.other_methods.calls.get() > 0 {
+            methods.push((OTHER.to_string(), Arc::clone(&self.other_methods)));
+        }
+
+        let name = "codechain_rpc_call_seconds";
+        writeln!(out, "# HELP {} The time spent serving the recent calls of each method", name).unwrap();
+        writeln!(out, "# TYPE {} summary", name).unwrap();
+        for (method, counters) in &methods {
+            for (quantile, value) in QUANTILES.iter().zip(counters.latency.quantiles(&QUANTILES)) {
+                if let Some(value) = value {
+                    writeln!(out, "{}{{method=\"{}\",quantile=\"{}\"}} {}", name, method, quantile, value).unwrap();
+                }
+            }
+            writeln!(out, "{}_sum{{method=\"{}\"}} {}", name, method, counters.latency.sum()).unwrap();
+            writeln!(out, "{}_count{{method=\"{}\"}} {}", name, method, counters.latency.count()).unwrap();
+        }
+
+        let name = "codechain_rpc_errors_total";
+        writeln!(out, "# HELP {} The failed calls of each method by the error code", name).unwrap();
+        writeln!(out, "# TYPE {} counter", name).unwrap();
+        for (method, counters) in &methods {
+            for (code, count) in counters.errors() {
+                writeln!(out, "{}{{method=\"{}\",code=\"{}\"}} {}", name, method, code, count).unwrap();
+            }
+        }
+
+        let name = "codechain_rpc_source_calls_total";
+        writeln!(out, "# HELP {} The calls from each address over HTTP", name).unwrap();
+        writeln!(out, "# TYPE {} counter", name).unwrap();
+        for (source, counter) in self.sources.read().iter() {
+            writeln!(out, "{}{{source=\"{}\"}} {}", name, source, counter.get()).unwrap();
+        }
+        if self.other_sources.get() > 0 {
+            writeln!(out, "{}{{source=\"{}\"}} {}", name, OTHER, self.other_sources.get()).unwrap();
+        }
+    }
+}
+
+/// Records the calls of the requests to `statistics`, after `inner` answers them.
+///
+/// The calls in a batch are answered together, so each of them is recorded with the time spent on the whole batch.
+pub struct StatisticsMiddleware<N> {
+    statistics: Arc<CallStatistics>,
+    inner: N,
+}
+
+impl<N> StatisticsMiddleware<N> {
+    pub fn new(statistics: Arc<CallStatistics>, inner: N) -> Self {
+        Self {
+            statistics,
+            inner,
+        }
+    }
+}
+
+/// The invalid calls don't have the methods to be counted by.
+fn method_of(call: &Call) -> Option<(String, Option<Id>)> {
+    match call {
+        Call::MethodCall(method_call) => Some((method_call.method.clone(), Some(method_call.id.clone()))),
+        Call::Notification(notification) => Some((notification.method.clone(), None)),
+        Call::Invalid(_) => None,
+    }
+}
+
+fn error_of(outputs: &[Output], id: &Id) -> Option<i64> {
+    outputs.iter().find_map(|output| match output {
+        Output::Failure(failure) if failure.id == *id => Some(failure.error.code.code()),
+        _ => None,
+    })
+}
+
+impl<N: Middleware<Metadata>> Middleware<Metadata> for StatisticsMiddleware<N> {
+    type Future = FutureResponse;
+
+    fn on_request<F, X>(&self, request: Request, meta: Metadata, next: F) -> Self::Future
+    where
+        F: FnOnce(Request, Metadata) -> X + Send,
+        X: Future<Item = Option<Response>, Error = ()> + Send + 'static, {
+        let calls: Vec<_> = match &request {
+            Request::Single(call) => method_of(call).into_iter().collect(),
+            Request::Batch(calls) => calls.iter().filter_map(method_of).collect(),
+        };
+        let source = meta.source;
+        let statistics = Arc::clone(&self.statistics);
+        let started_at = Instant::now();
+        Box::new(self.inner.on_request(request, meta, next).map(move |response| {
+            let elapsed = started_at.elapsed();
+            let outputs = match &response {
+                Some(Response::Single(output)) => slice::from_ref(output),
+                Some(Response::Batch(outputs)) => outputs.as_slice(),
+                None => &[],
+            };
+            for (method, id) in calls {
+                let error = id.and_then(|id| error_of(outputs, &id));
+                statistics.record(&method, source, elapsed, error);
+            }
+            response
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonrpc_core::middleware::Noop;
+    use jsonrpc_core::{Error, MetaIoHandler, Params, Value};
+    use serde_json::{self, json};
+
+    use super::super::super::traits::Rpc;
+    use super::super::rpc::{MethodPolicies, MethodPolicy, MethodPolicyMiddleware, RpcClient, Transport};
+    use super::*;
+
+    type Handler = MetaIoHandler<Metadata, StatisticsMiddleware<MethodPolicyMiddleware<Noop>>>;
+
+    fn handler(statistics: &Arc<CallStatistics>) -> Handler {
+        let mut configured = HashMap::new();
+        configured.insert(Transport::Http, MethodPolicy {
+            allow: vec!["chain_*".to_string(), "rpc_*".to_string()],
+            deny: Vec::new(),
+        });
+        let policies = Arc::new(MethodPolicies::new(configured));
+        let middleware = MethodPolicyMiddleware::new(Transport::Http, Arc::clone(&policies), Noop);
+        let mut handler = MetaIoHandler::with_middleware(StatisticsMiddleware::new(Arc::clone(statistics), middleware));
+        handler.add_method("chain_getBestBlockNumber", |_params: Params| Ok(Value::from(7)));
+        handler.add_method("chain_getBlockByNumber", |_params: Params| Err(Error::invalid_params("Unknown block")));
+        handler.add_method("devel_startSealing", |_params: Params| Ok(Value::Null));
+        statistics.register_methods(vec!["chain_getBestBlockNumber", "chain_getBlockByNumber", "devel_startSealing"]);
+        extend_with(&mut handler, statistics, RpcClient::new(policies, Arc::clone(statistics)).to_delegate());
+        handler
+    }
+
+    fn call(handler: &Handler, request: serde_json::Value, source: &str) -> serde_json::Value {
+        let meta = Metadata {
+            source: Some(source.parse().unwrap()),
+            ..Metadata::default()
+        };
+        let response = handler.handle_request_sync(&request.to_string(), meta).unwrap_or_default();
+        serde_json::from_str(&response).unwrap_or(serde_json::Value::Null)
+    }
+
+    fn request(method: &str, id: u64) -> serde_json::Value {
+        json!({"jsonrpc": "2.0", "method": method, "params": [], "id": id})
+    }
+
+    #[test]
+    fn calls_are_counted_by_the_method_the_error_code_and_the_source() {
+        let statistics = Arc::new(CallStatistics::new(None));
+        let handler = handler(&statistics);
+        let (first, second) = ("127.0.0.1", "10.0.0.2");
+
+        for id in 0..3 {
+            assert_eq!(json!(7), call(&handler, request("chain_getBestBlockNumber", id), first)["result"]);
+        }
+        // Rejected by the policy
+        call(&handler, request("devel_startSealing", 3), first);
+        for id in 4..6 {
+            call(&handler, request("chain_getBlockByNumber", id), second);
+        }
+        call(&handler, request("chain_getUnknown", 6), second);
+        let batch = json!([
+            request("chain_getBestBlockNumber", 7),
+            request("chain_getBlockByNumber", 8),
+            {"jsonrpc": "2.0", "method": "chain_getBestBlockNumber", "params": []},
+        ]);
+        call(&handler, batch, first);
+
+        let result = call(&handler, request("rpc_getStatistics", 9), first)["result"].clone();
+        let methods = result["methods"].as_array().unwrap();
+        let counted: Vec<_> = methods
+            .iter()
+            .map(|method| {
+                (method["method"].as_str().unwrap(), method["calls"].as_u64().unwrap(), method["errors"].clone())
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                ("chain_getBestBlockNumber", 5, json!({})),
+                ("chain_getBlockByNumber", 3, json!({"-32602": 3})),
+                ("devel_startSealing", 1, json!({"-32059": 1})),
+                (OTHER, 1, json!({"-32601": 1})),
+            ],
+            counted
+        );
+        assert!(methods.iter().all(|method| method["latency"]["p99"].is_number()), "{}", result);
+        assert_eq!(json!([{"source": first, "calls": 7}, {"source": second, "calls": 3}]), result["sources"]);
+
+        // The query itself is counted after it's answered.
+        let snapshot = statistics.snapshot();
+        let query = snapshot.methods.iter().find(|method| method.method == "rpc_getStatistics").unwrap();
+        assert_eq!(1, query.calls);
+    }
+
+    #[test]
+    fn latency_percentiles_of_the_latest_calls() {
+        let statistics = CallStatistics::new(None);
+        statistics.register_methods(vec!["chain_getBlockByNumber"]);
+        for millis in 1..=100 {
+            statistics.record("chain_getBlockByNumber", None, Duration::from_millis(millis), None);
+        }
+        let snapshot = statistics.snapshot();
+        let latency = snapshot.methods[0].latency.as_ref().unwrap();
+        assert!((latency.p50 - 50.0).abs() < 1e-9, "{:?}", latency);
+        assert!((latency.p90 - 90.0).abs() < 1e-9, "{:?}", latency);
+        assert!((latency.p99 - 99.0).abs() < 1e-9, "{:?}", latency);
+        // The local calls don't have the sources.
+        assert!(snapshot.sources.is_empty());
+    }
+
+    #[test]
+    fn unregistered_methods_and_sources_beyond_the_limit_are_counted_as_other() {
+        let statistics = CallStatistics::new(None);
+        statistics.register_methods(vec!["chain_method0"]);
+        for i in 0..MAX_SOURCES + 10 {
+            let source = IpAddr::from([10, 0, (i / 256) as u8, (i % 256) as u8]);
+            statistics.record(&format!("chain_method{}", i), Some(source), Duration::from_millis(1), Some(-32602));
+        }
+        statistics.record("chain_method0", Some(IpAddr::from([10, 0, 0, 0])), Duration::from_millis(1), None);
+        // The registered methods are also counted as other if the handler doesn't find them.
+        statistics.record("chain_method0", None, Duration::from_millis(1), Some(METHOD_NOT_FOUND));
+
+        let snapshot = statistics.snapshot();
+        assert_eq!(2, snapshot.methods.len());
+        let method0 = &snapshot.methods[0];
+        assert_eq!(("chain_method0", 2), (method0.method.as_str(), method0.calls));
+        assert_eq!(Some(&1), method0.errors.get(&-32602));
+        let other = &snapshot.methods[1];
+        assert_eq!((OTHER, (MAX_SOURCES + 10) as u64), (other.method.as_str(), other.calls));
+        assert_eq!(Some(&(MAX_SOURCES as u64 + 9)), other.errors.get(&-32602));
+        assert_eq!(Some(&1), other.errors.get(&METHOD_NOT_FOUND));
+
+        assert_eq!(MAX_SOURCES + 1, snapshot.sources.len());
+        let other = snapshot.sources.last().unwrap();
+        assert_eq!((OTHER, 10), (other.source.as_str(), other.calls));
+        assert_eq!(
+            SourceStatistics {
+                source: "10.0.0.0".to_string(),
+                calls: 2,
+            },
+            snapshot.sources[0]
+        );
+    }
+
+    #[test]
+    fn statistics_are_rendered_for_prometheus() {
+        let statistics = CallStatistics::new(None);
+        statistics.register_methods(vec!["chain_getBlockByNumber", "chain_\"quoted\""]);
+        let source = IpAddr::from([127, 0, 0, 1]);
+        statistics.record("chain_getBlockByNumber", Some(source), Duration::from_millis(2), None);
+        statistics.record("chain_getBlockByNumber", None, Duration::from_millis(4), Some(-32602));
+        statistics.record("chain_\"quoted\"", None, Duration::from_millis(1), Some(-32602));
+        statistics.record("chain_getUnknown", None, Duration::from_millis(1), Some(METHOD_NOT_FOUND));
+
+        let mut out = String::new();
+        statistics.render(&mut out);
+        let lines: Vec<_> = out.lines().collect();
+        for expected in &[
+            r#"codechain_rpc_call_seconds{method="chain_getBlockByNumber",quantile="0.99"} 0.004"#,
+            r#"codechain_rpc_call_seconds_count{method="chain_getBlockByNumber"} 2"#,
+            r#"codechain_rpc_errors_total{method="chain_getBlockByNumber",code="-32602"} 1"#,
+            r#"codechain_rpc_errors_total{method="chain_\"quoted\"",code="-32602"} 1"#,
+            r#"codechain_rpc_errors_total{method="other",code="-32601"} 1"#,
+            r#"codechain_rpc_source_calls_total{source="127.0.0.1"} 1"#,
+        ] {
+            assert!(lines.contains(expected), "{} is not in\n{}", expected, out);
+        }
+    }
+}
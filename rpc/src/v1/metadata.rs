@@ -0,0 +1,40 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use jsonrpc_core;
+use jsonrpc_pubsub::{PubSubMetadata, Session};
+
+/// RPC methods metadata.
+#[derive(Clone, Debug, Default)]
+pub struct Metadata {
+    /// The id of the WebSockets connection
+    pub session_id: Option<usize>,
+    /// The session of the WebSockets connection, which is used to send the notifications
+    pub session: Option<Arc<Session>>,
+    /// The address of the client. It is known only for the HTTP requests.
+    pub source: Option<IpAddr>,
+}
+
+impl jsonrpc_core::Metadata for Metadata {}
+
+impl PubSubMetadata for Metadata {
+    fn session(&self) -> Option<Arc<Session>> {
+        self.session.clone()
+    }
+}
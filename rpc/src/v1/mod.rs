@@ -16,8 +16,10 @@
 
 mod errors;
 mod impls;
+mod metadata;
 mod traits;
 mod types;
 
 pub use self::impls::*;
+pub use self::metadata::Metadata;
 pub use self::traits::*;
@@ -18,7 +18,7 @@ use ckey::{Password, PlatformAddress, Signature};
 use jsonrpc_core::Result;
 use primitives::H256;
 
-use super::super::types::{SendTransactionResult, UnsignedTransaction};
+use super::super::types::{DerivedAccount, SendTransactionResult, SpendPolicy, SpendPolicyStatus, UnsignedTransaction};
 
 build_rpc_trait! {
     pub trait Account {
@@ -49,5 +49,30 @@ build_rpc_trait! {
         /// Changes the account's password
         # [rpc(name = "account_changePassword")]
         fn change_password(&self, PlatformAddress, Password, Password) -> Result<()>;
+
+        /// Imports a mnemonic or a seed that accounts are derived from
+        # [rpc(name = "account_importSeed")]
+        fn import_seed(&self, String, Option<Password>) -> Result<String>;
+
+        /// Derives the next account from the seed
+        # [rpc(name = "account_deriveNew")]
+        fn derive_new_account(&self, String, Option<Password>) -> Result<DerivedAccount>;
+
+        /// Gets the accounts derived from the seed
+        # [rpc(name = "account_listDerived")]
+        fn list_derived_accounts(&self, String) -> Result<Vec<DerivedAccount>>;
+    }
+}
+
+build_rpc_trait! {
+    /// The limits of the accounts, which must not be changed by the clients that they limit.
+    pub trait AccountPolicy {
+        /// Sets the limits of the transactions signed by the account. Null removes them.
+        # [rpc(name = "account_setSpendPolicy")]
+        fn set_spend_policy(&self, PlatformAddress, Option<SpendPolicy>) -> Result<()>;
+
+        /// Gets the limits of the account and how much it spent today
+        # [rpc(name = "account_getSpendPolicy")]
+        fn get_spend_policy(&self, PlatformAddress) -> Result<Option<SpendPolicyStatus>>;
     }
 }
@@ -14,15 +14,22 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use cjson::bytes::Bytes;
 use cjson::scheme::Params;
 use cjson::uint::Uint;
 use ckey::{NetworkId, PlatformAddress, Public};
 use ctypes::{BlockNumber, ShardId};
 use primitives::{Bytes as BytesArray, H160, H256};
+use serde_json::Value;
 
 use jsonrpc_core::Result;
 
-use super::super::types::{AssetScheme, Block, BlockNumberAndHash, OwnedAsset, Text, Transaction, UnsignedTransaction};
+use super::super::types::{
+    AssetScheme, BestBlockId, Block, BlockFinality, BlockNumberOrHash, BlockNumberParam, BlockProductionStats,
+    BlockRewardDistribution, BlockSeal, Candidate, DecodedRaw, DryRunOptions, DryRunResult, OwnedAsset,
+    ParamsActivation, RangeStatistics, RawKind, ReorgProtectionStatus, SealVerification, SyncStatus, Term, Text,
+    Transaction, TransactionHistory, TransactionHistoryOptions, TransactionStatus, UnsignedTransaction, Validator,
+};
 
 build_rpc_trait! {
     pub trait Chain {
@@ -41,45 +48,57 @@ build_rpc_trait! {
         # [rpc(name = "chain_containTransaction")]
         fn contain_transaction(&self, H256) -> Result<bool>;
 
+        /// Gets whether the transaction with given hash is mined, pending in the mem pool, or unknown.
+        # [rpc(name = "chain_getTransactionStatus")]
+        fn get_transaction_status(&self, H256) -> Result<TransactionStatus>;
+
         /// Gets transaction with given transaction tracker.
         # [rpc(name = "chain_getTransactionByTracker")]
         fn get_transaction_by_tracker(&self, H256) -> Result<Option<Transaction>>;
 
+        /// Gets a page of the transactions signed by or paid to the given account.
+        # [rpc(name = "chain_getTransactionHistory")]
+        fn get_transaction_history(&self, PlatformAddress, Option<TransactionHistoryOptions>) -> Result<TransactionHistory>;
+
+        /// Gets a page of the transactions of the given action type.
+        # [rpc(name = "chain_getTransactionsByType")]
+        fn get_transactions_by_type(&self, String, Option<TransactionHistoryOptions>) -> Result<TransactionHistory>;
+
         /// Gets asset scheme with given transaction tracker.
         # [rpc(name = "chain_getAssetSchemeByTracker")]
-        fn get_asset_scheme_by_tracker(&self, H256, ShardId, Option<u64>) -> Result<Option<AssetScheme>>;
+        fn get_asset_scheme_by_tracker(&self, H256, ShardId, Option<BlockNumberParam>) -> Result<Option<AssetScheme>>;
 
         /// Gets asset scheme with given asset type.
         # [rpc(name = "chain_getAssetSchemeByType")]
-        fn get_asset_scheme_by_type(&self, H160, ShardId, Option<u64>) -> Result<Option<AssetScheme>>;
+        fn get_asset_scheme_by_type(&self, H160, ShardId, Option<BlockNumberParam>) -> Result<Option<AssetScheme>>;
 
         /// Gets text with given transaction hash.
         # [rpc(name = "chain_getText")]
-        fn get_text(&self, H256, Option<u64>) -> Result<Option<Text>>;
+        fn get_text(&self, H256, Option<BlockNumberParam>) -> Result<Option<Text>>;
 
         /// Gets asset with given asset type.
         # [rpc(name = "chain_getAsset")]
-        fn get_asset(&self, H256, usize, ShardId, Option<u64>) -> Result<Option<OwnedAsset>>;
+        fn get_asset(&self, H256, usize, ShardId, Option<BlockNumberParam>) -> Result<Option<OwnedAsset>>;
 
         /// Checks whether an asset is spent or not.
         # [rpc(name = "chain_isAssetSpent")]
-        fn is_asset_spent(&self, H256, usize, ShardId, Option<u64>) -> Result<Option<bool>>;
+        fn is_asset_spent(&self, H256, usize, ShardId, Option<BlockNumberParam>) -> Result<Option<bool>>;
 
         /// Gets seq with given account.
         # [rpc(name = "chain_getSeq")]
-        fn get_seq(&self, PlatformAddress, Option<u64>) -> Result<Option<u64>>;
+        fn get_seq(&self, PlatformAddress, Option<BlockNumberParam>) -> Result<Option<u64>>;
 
         /// Gets balance with given account.
         # [rpc(name = "chain_getBalance")]
-        fn get_balance(&self, PlatformAddress, Option<u64>) -> Result<Option<Uint>>;
+        fn get_balance(&self, PlatformAddress, Option<BlockNumberParam>) -> Result<Option<Uint>>;
 
         /// Gets regular key with given account
         # [rpc(name = "chain_getRegularKey")]
-        fn get_regular_key(&self, PlatformAddress, Option<u64>) -> Result<Option<Public>>;
+        fn get_regular_key(&self, PlatformAddress, Option<BlockNumberParam>) -> Result<Option<Public>>;
 
         /// Gets the owner of given regular key.
         # [rpc(name = "chain_getRegularKeyOwner")]
-        fn get_regular_key_owner(&self, Public, Option<u64>) -> Result<Option<PlatformAddress>>;
+        fn get_regular_key_owner(&self, Public, Option<BlockNumberParam>) -> Result<Option<PlatformAddress>>;
 
         /// Gets the genesis accounts
         # [rpc(name = "chain_getGenesisAccounts")]
@@ -87,39 +106,39 @@ build_rpc_trait! {
 
         /// Gets the number of shards
         # [rpc(name = "chain_getNumberOfShards")]
-        fn get_number_of_shards(&self, Option<u64>) -> Result<Option<ShardId>>;
+        fn get_number_of_shards(&self, Option<BlockNumberParam>) -> Result<Option<ShardId>>;
 
         /// Gets shard id
         # [rpc(name = "chain_getShardIdByHash")]
-        fn get_shard_id_by_hash(&self, H256, Option<u64>) -> Result<Option<ShardId>>;
+        fn get_shard_id_by_hash(&self, H256, Option<BlockNumberParam>) -> Result<Option<ShardId>>;
 
         /// Gets shard root
         # [rpc(name = "chain_getShardRoot")]
-        fn get_shard_root(&self, ShardId, Option<u64>) -> Result<Option<H256>>;
+        fn get_shard_root(&self, ShardId, Option<BlockNumberParam>) -> Result<Option<H256>>;
 
         /// Gets shard owners
         # [rpc(name = "chain_getShardOwners")]
-        fn get_shard_owners(&self, ShardId, Option<u64>) -> Result<Option<Vec<PlatformAddress>>>;
+        fn get_shard_owners(&self, ShardId, Option<BlockNumberParam>) -> Result<Option<Vec<PlatformAddress>>>;
 
         /// Gets shard users
         # [rpc(name = "chain_getShardUsers")]
-        fn get_shard_users(&self, ShardId, Option<u64>) -> Result<Option<Vec<PlatformAddress>>>;
+        fn get_shard_users(&self, ShardId, Option<BlockNumberParam>) -> Result<Option<Vec<PlatformAddress>>>;
 
         /// Gets number of best block.
         # [rpc(name = "chain_getBestBlockNumber")]
         fn get_best_block_number(&self) -> Result<BlockNumber>;
 
-        /// Gets the number and the hash of the best block.
+        /// Gets the hash, the number, the timestamp and the author of the best block, all from the same block.
         # [rpc(name = "chain_getBestBlockId")]
-        fn get_best_block_id(&self) -> Result<BlockNumberAndHash>;
+        fn get_best_block_id(&self) -> Result<BestBlockId>;
 
         /// Gets the hash of the block with given number.
         # [rpc(name = "chain_getBlockHash")]
-        fn get_block_hash(&self, u64) -> Result<Option<H256>>;
+        fn get_block_hash(&self, BlockNumberParam) -> Result<Option<H256>>;
 
         /// Gets block with given number.
         # [rpc(name = "chain_getBlockByNumber")]
-        fn get_block_by_number(&self, u64) -> Result<Option<Block>>;
+        fn get_block_by_number(&self, BlockNumberParam) -> Result<Option<Block>>;
 
         /// Gets block with given hash.
         # [rpc(name = "chain_getBlockByHash")]
@@ -131,11 +150,11 @@ build_rpc_trait! {
 
         ///Gets the minimum transaction fee of the given name.
          # [rpc(name = "chain_getMinTransactionFee")]
-        fn get_min_transaction_fee(&self, String, Option<u64>) -> Result<Option<u64>>;
+        fn get_min_transaction_fee(&self, String, Option<BlockNumberParam>) -> Result<Option<u64>>;
 
         /// Gets the mining given block number
         # [rpc(name = "chain_getMiningReward")]
-        fn get_mining_reward(&self, u64) -> Result<Option<u64>>;
+        fn get_mining_reward(&self, BlockNumberParam) -> Result<Option<u64>>;
 
         /// Return the network id that is used in this chain.
         # [rpc(name = "chain_getNetworkId")]
@@ -143,24 +162,98 @@ build_rpc_trait! {
 
         /// Return common params at given block number
         #[rpc(name = "chain_getCommonParams")]
-        fn get_common_params(&self, Option<u64>) -> Result<Option<Params>>;
+        fn get_common_params(&self, Option<BlockNumberParam>) -> Result<Option<Params>>;
+
+        /// Return the blocks that activated the common params, with the transactions that changed them
+        #[rpc(name = "chain_getParamsChangeHistory")]
+        fn get_params_change_history(&self) -> Result<Vec<ParamsActivation>>;
 
         /// Return the current term id at given block number
         #[rpc(name = "chain_getTermMetadata")]
-        fn get_term_metadata(&self, Option<u64>) -> Result<Option<(u64, u64)>>;
+        fn get_term_metadata(&self, Option<BlockNumberParam>) -> Result<Option<(u64, u64)>>;
+
+        /// Return the term that the given block belongs to
+        #[rpc(name = "chain_getTerm")]
+        fn get_term(&self, BlockNumberParam) -> Result<Option<Term>>;
 
         /// Return the current metadata seq at given block number
         #[rpc(name = "chain_getMetadataSeq")]
-        fn get_metadata_seq(&self, Option<u64>) -> Result<Option<u64>>;
+        fn get_metadata_seq(&self, Option<BlockNumberParam>) -> Result<Option<u64>>;
 
         /// Return the valid block authors
         #[rpc(name = "chain_getPossibleAuthors")]
-        fn get_possible_authors(&self, Option<u64>) -> Result<Option<Vec<PlatformAddress>>>;
+        fn get_possible_authors(&self, Option<BlockNumberParam>) -> Result<Option<Vec<PlatformAddress>>>;
+
+        /// Return the validators that seal the given block, in the order of the seal bitset
+        #[rpc(name = "chain_getValidatorSet")]
+        fn get_validator_set(&self, Option<BlockNumberOrHash>) -> Result<Option<Vec<Validator>>>;
+
+        /// Return the candidates at the given block, with the reasons they were not elected at the last election
+        #[rpc(name = "chain_getCandidates")]
+        fn get_candidates(&self, Option<BlockNumberOrHash>) -> Result<Option<Vec<Candidate>>>;
+
+        /// Return the status of the protection against deep reorgs
+        #[rpc(name = "chain_getReorgProtectionStatus")]
+        fn get_reorg_protection_status(&self) -> Result<ReorgProtectionStatus>;
+
+        /// Verify the seal of the given block again, including the seal skipped while syncing
+        #[rpc(name = "chain_verifyBlockSeal")]
+        fn verify_block_seal(&self, H256) -> Result<Option<SealVerification>>;
+
+        /// Return the seal fields of the given block and their interpretation by the consensus engine
+        #[rpc(name = "chain_getBlockSeal")]
+        fn get_block_seal(&self, BlockNumberOrHash) -> Result<Option<BlockSeal>>;
+
+        /// Return whether the given block is finalized and the evidence
+        #[rpc(name = "chain_getBlockFinality")]
+        fn get_block_finality(&self, BlockNumberOrHash) -> Result<Option<BlockFinality>>;
+
+        /// Return the RLP of the given block
+        #[rpc(name = "chain_getBlockRaw")]
+        fn get_block_raw(&self, BlockNumberOrHash) -> Result<Option<Bytes>>;
+
+        /// Decode the RLP of a block, a header or a transaction into the JSON that the other RPCs return
+        #[rpc(name = "chain_decodeRaw")]
+        fn decode_raw(&self, RawKind, Bytes) -> Result<DecodedRaw>;
+
+        /// Encode the JSON of a signed transaction into its RLP
+        #[rpc(name = "chain_encodeTransaction")]
+        fn encode_transaction(&self, Value) -> Result<Bytes>;
+
+        /// Return the statistics of the intervals, the view changes and the proposers of the latest blocks
+        #[rpc(name = "chain_getBlockProductionStats")]
+        fn get_block_production_stats(&self, u64) -> Result<BlockProductionStats>;
+
+        /// Return how the reward and the fees of the given block were paid
+        #[rpc(name = "chain_getBlockRewardDistribution")]
+        fn get_block_reward_distribution(&self, BlockNumberParam) -> Result<Option<BlockRewardDistribution>>;
+
+        /// Return the transactions, the fees, the rewards and the signers summed over the given blocks
+        #[rpc(name = "chain_getRangeStatistics")]
+        fn get_range_statistics(&self, BlockNumberParam, BlockNumberParam) -> Result<Option<RangeStatistics>>;
+
+        /// Return the sum of the fees burnt up to the given block
+        #[rpc(name = "chain_getTotalBurnt")]
+        fn get_total_burnt(&self, Option<BlockNumberParam>) -> Result<Option<u64>>;
+
+        /// Return whether the chain is frozen and how far the import has progressed
+        #[rpc(name = "chain_getSyncStatus")]
+        fn get_sync_status(&self) -> Result<SyncStatus>;
 
         /// Execute Transactions
         # [rpc(name = "chain_executeTransaction")]
         fn execute_transaction(&self, UnsignedTransaction, PlatformAddress) -> Result<Option<String>>;
 
+        /// Execute the transaction against the state of the given block without importing it
+        # [rpc(name = "chain_dryRunTransaction")]
+        fn dry_run_transaction(
+            &self,
+            UnsignedTransaction,
+            PlatformAddress,
+            Option<BlockNumberParam>,
+            Option<DryRunOptions>,
+        ) -> Result<DryRunResult>;
+
         /// Execute AssetTransfer transaction inputs in VM
         # [rpc(name = "chain_executeVM")]
         fn execute_vm(&self, UnsignedTransaction, Vec<Vec<BytesArray>>, Vec<usize>) -> Result<Vec<String>>;
@@ -17,10 +17,13 @@
 use std::net::SocketAddr;
 
 use cjson::bytes::Bytes;
-use jsonrpc_core::Result;
+use jsonrpc_core::{Result, Value};
 use primitives::H256;
 
-use super::super::types::TPSTestSetting;
+use super::super::types::{
+    ActionHandlerTrace, ActionIndexBackfill, BlockImportTimings, BlockRejection, ConsensusFault, DatabaseStats,
+    FinalityConflict, IntegrityReport, RepairOptions, ReplayOptions, ReplayReport, TPSTestSetting,
+};
 
 build_rpc_trait! {
     pub trait Devel {
@@ -41,5 +44,59 @@ build_rpc_trait! {
 
         # [rpc(name = "devel_testTPS")]
         fn test_tps(&self, TPSTestSetting) -> Result<f64>;
+
+        # [rpc(name = "devel_setLogLevel")]
+        fn set_log_level(&self, String, String) -> Result<()>;
+
+        # [rpc(name = "devel_overrideReorgProtection")]
+        fn override_reorg_protection(&self) -> Result<()>;
+
+        # [rpc(name = "devel_setConsensusFault")]
+        fn set_consensus_fault(&self, ConsensusFault) -> Result<()>;
+
+        # [rpc(name = "devel_freezeChain")]
+        fn freeze_chain(&self) -> Result<()>;
+
+        # [rpc(name = "devel_unfreezeChain")]
+        fn unfreeze_chain(&self) -> Result<()>;
+
+        # [rpc(name = "devel_getFinalityConflict")]
+        fn get_finality_conflict(&self) -> Result<Option<FinalityConflict>>;
+
+        # [rpc(name = "devel_clearConsensusHalt")]
+        fn clear_consensus_halt(&self) -> Result<Option<FinalityConflict>>;
+
+        # [rpc(name = "devel_replayBlocks")]
+        fn replay_blocks(&self, u64, u64, Option<ReplayOptions>) -> Result<ReplayReport>;
+
+        # [rpc(name = "devel_traceTransaction")]
+        fn trace_transaction(&self, Bytes) -> Result<ActionHandlerTrace>;
+
+        # [rpc(name = "devel_getImportTimings")]
+        fn get_import_timings(&self, usize) -> Result<Vec<BlockImportTimings>>;
+
+        # [rpc(name = "devel_getRecentRejections")]
+        fn get_recent_rejections(&self, usize) -> Result<Vec<BlockRejection>>;
+
+        # [rpc(name = "devel_validateScheme")]
+        fn validate_scheme(&self, Value) -> Result<Vec<String>>;
+
+        # [rpc(name = "devel_checkDatabaseIntegrity")]
+        fn check_database_integrity(&self, u64, u64) -> Result<()>;
+
+        # [rpc(name = "devel_repairDatabase")]
+        fn repair_database(&self, RepairOptions) -> Result<()>;
+
+        # [rpc(name = "devel_getDatabaseIntegrityReport")]
+        fn get_database_integrity_report(&self) -> Result<Option<IntegrityReport>>;
+
+        # [rpc(name = "devel_getDatabaseStats")]
+        fn get_database_stats(&self) -> Result<DatabaseStats>;
+
+        # [rpc(name = "devel_setCacheBudget")]
+        fn set_cache_budget(&self, String, usize) -> Result<()>;
+
+        # [rpc(name = "devel_getActionIndexBackfill")]
+        fn get_action_index_backfill(&self) -> Result<Option<ActionIndexBackfill>>;
     }
 }
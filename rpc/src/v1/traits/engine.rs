@@ -19,6 +19,8 @@ use ckey::PlatformAddress;
 
 use jsonrpc_core::Result;
 
+use super::super::types::{ConsensusStatus, EngineDescription, ProposalBudget, RestoreStatus, SignerEligibility};
+
 build_rpc_trait! {
     pub trait Engine {
         /// Gets the reward of the given block number
@@ -36,5 +38,45 @@ build_rpc_trait! {
         /// Gets custom action data for given custom action handler id and rlp encoded key.
         # [rpc(name = "engine_getCustomActionData")]
         fn get_custom_action_data(&self, u64, Bytes, Option<u64>) -> Result<Option<WithoutPrefix<Bytes>>>;
+
+        /// Skips the current consensus step as if its timeout expired
+        # [rpc(name = "engine_forceNextView")]
+        fn force_next_view(&self) -> Result<()>;
+
+        /// Gets the current step of the consensus and how often the committed block had to be requested
+        # [rpc(name = "engine_getConsensusStatus")]
+        fn get_consensus_status(&self) -> Result<Option<ConsensusStatus>>;
+
+        /// Gets the node-local limits of the blocks that this node proposes
+        # [rpc(name = "engine_getProposalBudget")]
+        fn get_proposal_budget(&self) -> Result<ProposalBudget>;
+
+        /// Sets the node-local limits of the blocks that this node proposes
+        # [rpc(name = "engine_setProposalBudget")]
+        fn set_proposal_budget(&self, ProposalBudget) -> Result<ProposalBudget>;
+
+        /// Gets the estimated skew of the local clock from the other validators' clocks in milliseconds
+        # [rpc(name = "engine_getClockSkewEstimate")]
+        fn get_clock_skew_estimate(&self) -> Result<Option<i64>>;
+
+        /// Makes the engine start signing, unless the same key signed messages recently
+        # [rpc(name = "engine_promoteFromStandby")]
+        fn promote_from_standby(&self, Option<u64>) -> Result<()>;
+
+        /// Makes the engine stop signing while following the consensus
+        # [rpc(name = "engine_demoteToStandby")]
+        fn demote_to_standby(&self) -> Result<()>;
+
+        /// Gets the type of the consensus engine, its seal layout and its action handlers
+        # [rpc(name = "engine_getInfo")]
+        fn get_info(&self) -> Result<EngineDescription>;
+
+        /// Tells whether the engine signer can sign the next block. null if the node doesn't sign
+        # [rpc(name = "engine_getSignerEligibility")]
+        fn get_signer_eligibility(&self) -> Result<Option<SignerEligibility>>;
+
+        /// Tells where the consensus engine restored its state from at startup. null if the engine keeps no backup
+        # [rpc(name = "engine_getRestoreStatus")]
+        fn get_restore_status(&self) -> Result<Option<RestoreStatus>>;
     }
 }
@@ -0,0 +1,62 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use cjson::uint::Uint;
+use ckey::{NetworkId, PlatformAddress};
+use ctypes::BlockNumber;
+use primitives::H256;
+
+use jsonrpc_core::Result;
+
+use super::super::types::{BestBlockId, BlockFinality, BlockNumberOrHash, BlockNumberParam, LightStatus};
+
+build_rpc_trait! {
+    /// The chain RPCs that a light node answers. The state is read from the full nodes with the proof,
+    /// and only the finalized blocks can be queried.
+    pub trait LightChain {
+        /// Gets the number of the best block that the light node follows.
+        # [rpc(name = "chain_getBestBlockNumber")]
+        fn get_best_block_number(&self) -> Result<BlockNumber>;
+
+        /// Gets the hash, the number, the timestamp and the author of the best block that the light node follows.
+        # [rpc(name = "chain_getBestBlockId")]
+        fn get_best_block_id(&self) -> Result<BestBlockId>;
+
+        /// Gets the hash of the block with given number.
+        # [rpc(name = "chain_getBlockHash")]
+        fn get_block_hash(&self, BlockNumberParam) -> Result<Option<H256>>;
+
+        /// Gets whether the block is finalized, and the evidence the light node verified.
+        # [rpc(name = "chain_getBlockFinality")]
+        fn get_block_finality(&self, BlockNumberOrHash) -> Result<Option<BlockFinality>>;
+
+        /// Gets the seq of the account in the state of the finalized block with given number.
+        # [rpc(name = "chain_getSeq")]
+        fn get_seq(&self, PlatformAddress, Option<BlockNumberParam>) -> Result<Option<u64>>;
+
+        /// Gets the balance of the account in the state of the finalized block with given number.
+        # [rpc(name = "chain_getBalance")]
+        fn get_balance(&self, PlatformAddress, Option<BlockNumberParam>) -> Result<Option<Uint>>;
+
+        /// Return the network id that is used in this chain.
+        # [rpc(name = "chain_getNetworkId")]
+        fn get_network_id(&self) -> Result<NetworkId>;
+
+        /// Gets the finalized block and the validators that the light node follows.
+        # [rpc(name = "light_getStatus")]
+        fn get_status(&self) -> Result<LightStatus>;
+    }
+}
@@ -15,17 +15,34 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use cjson::bytes::Bytes;
+use ckey::PlatformAddress;
 use primitives::H256;
 
 use jsonrpc_core::Result;
 
-use super::super::types::PendingTransactions;
+use super::super::types::{
+    FeeFloor, MempoolStatus, PendingTransactions, PooledTransaction, ScheduledTransaction, SignerDiagnosis,
+};
 
 build_rpc_trait! {
     pub trait Mempool {
+        type Metadata;
+
         /// Sends signed transaction, returning its hash.
-        # [rpc(name = "mempool_sendSignedTransaction")]
-        fn send_signed_transaction(&self, Bytes) -> Result<H256>;
+        # [rpc(meta, name = "mempool_sendSignedTransaction")]
+        fn send_signed_transaction(&self, Self::Metadata, Bytes) -> Result<H256>;
+
+        /// Sends signed transactions at once, returning their hashes. Nothing is sent if any of them is rejected.
+        # [rpc(meta, name = "mempool_sendSignedTransactions")]
+        fn send_signed_transactions(&self, Self::Metadata, Vec<Bytes>) -> Result<Vec<H256>>;
+
+        /// Sends signed transaction that is neither included nor propagated until the given UNIX timestamp, returning its hash.
+        # [rpc(meta, name = "mempool_scheduleSignedTransaction")]
+        fn schedule_signed_transaction(&self, Self::Metadata, Bytes, u64) -> Result<H256>;
+
+        /// Gets the transactions that wait for their activation timestamps.
+        # [rpc(name = "mempool_getScheduledTransactions")]
+        fn get_scheduled_transactions(&self) -> Result<Vec<ScheduledTransaction>>;
 
         /// Gets transaction results with given transaction tracker.
         # [rpc(name = "mempool_getTransactionResultsByTracker")]
@@ -42,5 +59,21 @@ build_rpc_trait! {
        /// Gets the count of transactions in the current mem pool.
         # [rpc(name = "mempool_getPendingTransactionsCount")]
         fn get_pending_transactions_count(&self, Option<u64>, Option<u64>) -> Result<usize>;
+
+        /// Diagnoses why the transactions of the account are pending.
+        # [rpc(name = "mempool_diagnoseSigner")]
+        fn diagnose_signer(&self, PlatformAddress) -> Result<SignerDiagnosis>;
+
+        /// Gets the transactions of the account in the current mem pool with their origins.
+        # [rpc(name = "mempool_getPendingTransactionsBySigner")]
+        fn get_pending_transactions_by_signer(&self, PlatformAddress) -> Result<Vec<PooledTransaction>>;
+
+        /// Gets the fee floor that follows the load of the mem pool.
+        # [rpc(name = "mempool_getFeeFloor")]
+        fn get_fee_floor(&self) -> Result<Option<FeeFloor>>;
+
+        /// Gets the number of the pending transactions and the statistics of their relay.
+        # [rpc(name = "mempool_getStatus")]
+        fn get_status(&self) -> Result<MempoolStatus>;
     }
 }
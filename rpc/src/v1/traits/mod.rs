@@ -18,14 +18,22 @@ mod account;
 mod chain;
 mod devel;
 mod engine;
+mod light;
 mod mempool;
 mod miner;
 mod net;
+mod node;
+mod pubsub;
+mod rpc;
 
-pub use self::account::Account;
+pub use self::account::{Account, AccountPolicy};
 pub use self::chain::Chain;
 pub use self::devel::Devel;
 pub use self::engine::Engine;
+pub use self::light::LightChain;
 pub use self::mempool::Mempool;
 pub use self::miner::Miner;
 pub use self::net::Net;
+pub use self::node::Node;
+pub use self::pubsub::PubSub;
+pub use self::rpc::Rpc;
@@ -21,7 +21,10 @@ use cidr::IpCidr;
 use ckey::Public;
 use jsonrpc_core::Result;
 
-use super::super::types::FilterStatus;
+use super::super::types::{
+    BandwidthLimits, ConnectionAttempt, DiscoveredPeer, FilterDecision, FilterStatus, NatStatus, PeerExchangeStats,
+    PeerSession, TaggedFilters,
+};
 
 build_rpc_trait! {
     pub trait Net {
@@ -49,6 +52,21 @@ build_rpc_trait! {
         # [rpc(name = "net_getEstablishedPeers")]
         fn get_established_peers(&self) -> Result<Vec<SocketAddr>>;
 
+        #[rpc(name = "net_getPeerSessions")]
+        fn get_peer_sessions(&self) -> Result<Vec<PeerSession>>;
+
+        #[rpc(name = "net_getDiscoveredPeers")]
+        fn get_discovered_peers(&self) -> Result<Vec<DiscoveredPeer>>;
+
+        #[rpc(name = "net_addSeedPeer")]
+        fn add_seed_peer(&self, IpAddr, u16) -> Result<bool>;
+
+        #[rpc(name = "net_requestPeerExchange")]
+        fn request_peer_exchange(&self, IpAddr, u16) -> Result<()>;
+
+        #[rpc(name = "net_getPeerExchangeStats")]
+        fn get_peer_exchange_stats(&self) -> Result<PeerExchangeStats>;
+
         #[rpc(name = "net_addToWhitelist")]
         fn add_to_whitelist(&self, IpCidr, Option<String>) -> Result<()>;
 
@@ -61,6 +79,15 @@ build_rpc_trait! {
         #[rpc(name = "net_removeFromBlacklist")]
         fn remove_from_blacklist(&self, IpCidr) -> Result<()>;
 
+        #[rpc(name = "net_replaceBlacklist")]
+        fn replace_blacklist(&self, Vec<(IpCidr, Option<String>, Option<u64>)>) -> Result<()>;
+
+        #[rpc(name = "net_removeByTag")]
+        fn remove_by_tag(&self, String) -> Result<usize>;
+
+        #[rpc(name = "net_listByTag")]
+        fn list_by_tag(&self, String) -> Result<TaggedFilters>;
+
         #[rpc(name = "net_enableWhitelist")]
         fn enable_whitelist(&self) -> Result<()>;
 
@@ -79,7 +106,28 @@ build_rpc_trait! {
         #[rpc(name = "net_getBlacklist")]
         fn get_blacklist(&self) -> Result<FilterStatus>;
 
+        #[rpc(name = "net_testFilter")]
+        fn test_filter(&self, IpAddr) -> Result<FilterDecision>;
+
         #[rpc(name = "net_recentNetworkUsage")]
         fn recent_network_usage(&self) -> Result<HashMap<String, usize>>;
+
+        #[rpc(name = "net_getConnectionLog")]
+        fn get_connection_log(&self, usize, Option<String>) -> Result<Vec<ConnectionAttempt>>;
+
+        #[rpc(name = "net_getNatStatus")]
+        fn get_nat_status(&self) -> Result<NatStatus>;
+
+        #[rpc(name = "net_refreshNatMapping")]
+        fn refresh_nat_mapping(&self) -> Result<NatStatus>;
+
+        #[rpc(name = "net_getExtensionLimits")]
+        fn get_extension_limits(&self) -> Result<HashMap<String, usize>>;
+
+        #[rpc(name = "net_getBandwidthLimits")]
+        fn get_bandwidth_limits(&self) -> Result<BandwidthLimits>;
+
+        #[rpc(name = "net_setBandwidthLimits")]
+        fn set_bandwidth_limits(&self, BandwidthLimits) -> Result<()>;
     }
 }
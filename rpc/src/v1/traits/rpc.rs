@@ -0,0 +1,31 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use jsonrpc_core::Result;
+
+use super::super::types::{MethodPolicy, RpcStatistics, Transport};
+
+build_rpc_trait! {
+    pub trait Rpc {
+        /// Replaces the methods allowed on the transport. It's served only on IPC.
+        # [rpc(name = "rpc_setMethodPolicy")]
+        fn set_method_policy(&self, Transport, MethodPolicy) -> Result<()>;
+
+        /// Returns the calls counted since the node started. It's served only on IPC.
+        # [rpc(name = "rpc_getStatistics")]
+        fn get_statistics(&self) -> Result<RpcStatistics>;
+    }
+}
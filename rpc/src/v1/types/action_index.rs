@@ -0,0 +1,55 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::ActionIndexBackfill as CoreActionIndexBackfill;
+
+/// The progress of indexing the blocks imported before the action index was enabled
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionIndexBackfill {
+    /// The blocks up to this are indexed in the background. The later blocks are indexed when they're imported.
+    target_block: u64,
+    /// The number of the blocks indexed so far
+    indexed_blocks: u64,
+    finished: bool,
+}
+
+impl From<CoreActionIndexBackfill> for ActionIndexBackfill {
+    fn from(backfill: CoreActionIndexBackfill) -> Self {
+        Self {
+            target_block: backfill.target,
+            indexed_blocks: backfill.next.min(backfill.target + 1),
+            finished: backfill.is_finished(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize() {
+        let backfill: ActionIndexBackfill = CoreActionIndexBackfill {
+            next: 256,
+            target: 1000,
+        }
+        .into();
+        assert_eq!(r#"{"targetBlock":1000,"indexedBlocks":256,"finished":false}"#, to_string(&backfill).unwrap());
+    }
+}
@@ -0,0 +1,114 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use ccore::ActionHandlerTrace as CoreActionHandlerTrace;
+use cjson::bytes::Bytes;
+use cstate::ActionDataAccess as CoreActionDataAccess;
+use primitives::H256;
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ActionDataAccess {
+    Read {
+        key: H256,
+        value: Option<Bytes>,
+    },
+    /// `new` is null if the data is removed.
+    Write {
+        key: H256,
+        old: Option<Bytes>,
+        new: Option<Bytes>,
+    },
+}
+
+impl From<CoreActionDataAccess> for ActionDataAccess {
+    fn from(access: CoreActionDataAccess) -> Self {
+        match access {
+            CoreActionDataAccess::Read {
+                key,
+                value,
+            } => ActionDataAccess::Read {
+                key,
+                value: value.map(Bytes::from),
+            },
+            CoreActionDataAccess::Write {
+                key,
+                old,
+                new,
+            } => ActionDataAccess::Write {
+                key,
+                old: old.map(Bytes::from),
+                new: new.map(Bytes::from),
+            },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionHandlerTrace {
+    handler_id: u64,
+    handler_name: String,
+    accesses: Vec<ActionDataAccess>,
+    error: Option<String>,
+}
+
+impl From<CoreActionHandlerTrace> for ActionHandlerTrace {
+    fn from(trace: CoreActionHandlerTrace) -> Self {
+        Self {
+            handler_id: trace.handler_id,
+            handler_name: trace.handler_name.to_string(),
+            accesses: trace.accesses.into_iter().map(From::from).collect(),
+            error: trace.error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize_trace() {
+        let trace = ActionHandlerTrace::from(CoreActionHandlerTrace {
+            handler_id: 2,
+            handler_name: "stake handler",
+            accesses: vec![
+                CoreActionDataAccess::Read {
+                    key: H256::zero(),
+                    value: None,
+                },
+                CoreActionDataAccess::Write {
+                    key: H256::zero(),
+                    old: Some(vec![0x64]),
+                    new: None,
+                },
+            ],
+            error: None,
+        });
+        let zero = format!("0x{}", "0".repeat(64));
+        assert_eq!(
+            format!(
+                r#"{{"handlerId":2,"handlerName":"stake handler","accesses":[{{"type":"read","key":"{0}","value":null}},{{"type":"write","key":"{0}","old":"0x64","new":null}}],"error":null}}"#,
+                zero
+            ),
+            to_string(&trace).unwrap()
+        );
+    }
+}
@@ -0,0 +1,71 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use cnetwork::BandwidthLimits as NetworkBandwidthLimits;
+
+/// Bytes per second of the extension messages for each peer class. null doesn't limit the class.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BandwidthLimits {
+    #[serde(default)]
+    pub validator: Option<usize>,
+    #[serde(default)]
+    pub observer: Option<usize>,
+    #[serde(default)]
+    pub unknown: Option<usize>,
+}
+
+impl From<BandwidthLimits> for NetworkBandwidthLimits {
+    fn from(limits: BandwidthLimits) -> Self {
+        Self {
+            validator: limits.validator,
+            observer: limits.observer,
+            unknown: limits.unknown,
+        }
+    }
+}
+
+impl From<NetworkBandwidthLimits> for BandwidthLimits {
+    fn from(limits: NetworkBandwidthLimits) -> Self {
+        Self {
+            validator: limits.validator,
+            observer: limits.observer,
+            unknown: limits.unknown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{from_str, to_string};
+
+    use super::*;
+
+    #[test]
+    fn serialize_and_deserialize() {
+        let limits = BandwidthLimits {
+            validator: None,
+            observer: Some(1_048_576),
+            unknown: Some(262_144),
+        };
+        let json = r#"{"validator":null,"observer":1048576,"unknown":262144}"#;
+        assert_eq!(json, to_string(&limits).unwrap());
+        assert_eq!(limits, from_str(json).unwrap());
+        assert_eq!(limits, from_str(r#"{"observer":1048576,"unknown":262144}"#).unwrap());
+        assert!(from_str::<BandwidthLimits>(r#"{"proposer":10}"#).is_err());
+    }
+}
@@ -16,7 +16,7 @@
 
 use ccore::{Block as CoreBlock, LocalizedTransaction};
 use ckey::{NetworkId, PlatformAddress};
-use ctypes::BlockNumber;
+use ctypes::{BlockNumber, Header};
 use primitives::{H256, U256};
 
 use super::Transaction;
@@ -73,9 +73,64 @@ impl Block {
     }
 }
 
+/// The header of a block, in the same form as `Block` without the transactions.
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
-pub struct BlockNumberAndHash {
-    pub number: BlockNumber,
+pub struct BlockHeader {
+    parent_hash: H256,
+    timestamp: u64,
+    number: u64,
+    author: PlatformAddress,
+
+    extra_data: Vec<u8>,
+
+    transactions_root: H256,
+    state_root: H256,
+
+    score: U256,
+    seal: Vec<Vec<u8>>,
+
+    hash: H256,
+}
+
+impl BlockHeader {
+    pub fn from_core(header: Header, network_id: NetworkId) -> Self {
+        BlockHeader {
+            parent_hash: *header.parent_hash(),
+            timestamp: header.timestamp(),
+            number: header.number(),
+            author: PlatformAddress::new_v1(network_id, *header.author()),
+
+            extra_data: header.extra_data().clone(),
+
+            transactions_root: *header.transactions_root(),
+            state_root: *header.state_root(),
+
+            score: *header.score(),
+            seal: header.seal().to_vec(),
+
+            hash: header.hash(),
+        }
+    }
+}
+
+/// The best block, read from a single header so that its fields never belong to different blocks.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BestBlockId {
     pub hash: H256,
+    pub number: BlockNumber,
+    pub timestamp: u64,
+    pub author: PlatformAddress,
+}
+
+impl BestBlockId {
+    pub fn from_core(header: &Header, network_id: NetworkId) -> Self {
+        Self {
+            hash: header.hash(),
+            number: header.number(),
+            timestamp: header.timestamp(),
+            author: PlatformAddress::new_v1(network_id, *header.author()),
+        }
+    }
 }
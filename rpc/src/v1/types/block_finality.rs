@@ -0,0 +1,127 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use ccore::{BlockFinality as CoreBlockFinality, FinalityEvidence as CoreFinalityEvidence};
+use primitives::H256;
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockFinality {
+    finalized: bool,
+    canonical: bool,
+    evidence: Option<FinalityEvidence>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum FinalityEvidence {
+    Precommits {
+        #[serde(rename = "childHash")]
+        child_hash: H256,
+        view: u64,
+        #[serde(rename = "signatureCount")]
+        signature_count: usize,
+    },
+    Confirmations {
+        confirmations: u64,
+        #[serde(rename = "recommendedConfirmation")]
+        recommended_confirmation: u32,
+    },
+}
+
+impl From<CoreBlockFinality> for BlockFinality {
+    fn from(finality: CoreBlockFinality) -> Self {
+        Self {
+            finalized: finality.finalized,
+            canonical: finality.canonical,
+            evidence: finality.evidence.map(Into::into),
+        }
+    }
+}
+
+impl From<CoreFinalityEvidence> for FinalityEvidence {
+    fn from(evidence: CoreFinalityEvidence) -> Self {
+        match evidence {
+            CoreFinalityEvidence::Precommits {
+                child_hash,
+                view,
+                signature_count,
+            } => FinalityEvidence::Precommits {
+                child_hash,
+                view,
+                signature_count,
+            },
+            CoreFinalityEvidence::Confirmations {
+                confirmations,
+                recommended,
+            } => FinalityEvidence::Confirmations {
+                confirmations,
+                recommended_confirmation: recommended,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize_finalized_by_precommits() {
+        let finality: BlockFinality = CoreBlockFinality {
+            finalized: true,
+            canonical: true,
+            evidence: Some(CoreFinalityEvidence::Precommits {
+                child_hash: H256::zero(),
+                view: 2,
+                signature_count: 3,
+            }),
+        }
+        .into();
+        assert_eq!(
+            r#"{"finalized":true,"canonical":true,"evidence":{"type":"precommits","childHash":"0x0000000000000000000000000000000000000000000000000000000000000000","view":2,"signatureCount":3}}"#,
+            to_string(&finality).unwrap()
+        );
+    }
+
+    #[test]
+    fn serialize_confirmations_and_fork() {
+        let finality: BlockFinality = CoreBlockFinality {
+            finalized: true,
+            canonical: true,
+            evidence: Some(CoreFinalityEvidence::Confirmations {
+                confirmations: 5,
+                recommended: 1,
+            }),
+        }
+        .into();
+        assert_eq!(
+            r#"{"finalized":true,"canonical":true,"evidence":{"type":"confirmations","confirmations":5,"recommendedConfirmation":1}}"#,
+            to_string(&finality).unwrap()
+        );
+
+        let fork: BlockFinality = CoreBlockFinality {
+            finalized: false,
+            canonical: false,
+            evidence: None,
+        }
+        .into();
+        assert_eq!(r#"{"finalized":false,"canonical":false,"evidence":null}"#, to_string(&fork).unwrap());
+    }
+}
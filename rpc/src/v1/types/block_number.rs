@@ -0,0 +1,208 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt;
+
+use ccore::BlockId;
+use ctypes::BlockNumber;
+use primitives::H256;
+use serde::de::{self, Unexpected, Visitor};
+use serde::{Deserialize, Deserializer};
+
+/// The block number parameter of the RPCs.
+/// A negative number is an offset from the best block: -1 is the parent of the best block.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BlockNumberParam {
+    Earliest,
+    Latest,
+    Number(BlockNumber),
+    /// The distance from the best block
+    Offset(u64),
+}
+
+impl BlockNumberParam {
+    /// Returns None if the offset goes beyond the genesis block.
+    /// Every RPC resolves its parameter against a single best block number,
+    /// so the blocks it reads don't move even if a block is imported meanwhile.
+    pub fn resolve(self, best_block_number: BlockNumber) -> Option<BlockNumber> {
+        match self {
+            BlockNumberParam::Earliest => Some(0),
+            BlockNumberParam::Latest => Some(best_block_number),
+            BlockNumberParam::Number(number) => Some(number),
+            BlockNumberParam::Offset(offset) => best_block_number.checked_sub(offset),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BlockNumberParam {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>, {
+        deserializer.deserialize_any(BlockNumberVisitor)
+    }
+}
+
+struct BlockNumberVisitor;
+
+impl<'de> Visitor<'de> for BlockNumberVisitor {
+    type Value = BlockNumberParam;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a block number, a negative offset from the best block, \"earliest\" or \"latest\"")
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error, {
+        Ok(BlockNumberParam::Number(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error, {
+        if value >= 0 {
+            Ok(BlockNumberParam::Number(value as u64))
+        } else {
+            Ok(BlockNumberParam::Offset((value as u64).wrapping_neg()))
+        }
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error, {
+        match value {
+            "earliest" => Ok(BlockNumberParam::Earliest),
+            "latest" => Ok(BlockNumberParam::Latest),
+            _ => Err(E::invalid_value(Unexpected::Str(value), &self)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BlockNumberOrHash {
+    Number(BlockNumberParam),
+    Hash(H256),
+}
+
+impl BlockNumberOrHash {
+    /// Returns None if the offset goes beyond the genesis block.
+    pub fn resolve(self, best_block_number: BlockNumber) -> Option<BlockId> {
+        match self {
+            BlockNumberOrHash::Number(number) => number.resolve(best_block_number).map(BlockId::Number),
+            BlockNumberOrHash::Hash(hash) => Some(BlockId::Hash(hash)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BlockNumberOrHash {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>, {
+        deserializer.deserialize_any(BlockNumberOrHashVisitor)
+    }
+}
+
+struct BlockNumberOrHashVisitor;
+
+impl<'de> Visitor<'de> for BlockNumberOrHashVisitor {
+    type Value = BlockNumberOrHash;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "a block hash, a block number, a negative offset from the best block, \"earliest\" or \"latest\""
+        )
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error, {
+        BlockNumberVisitor.visit_u64(value).map(BlockNumberOrHash::Number)
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error, {
+        BlockNumberVisitor.visit_i64(value).map(BlockNumberOrHash::Number)
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error, {
+        match value {
+            "earliest" | "latest" => BlockNumberVisitor.visit_str(value).map(BlockNumberOrHash::Number),
+            _ if value.starts_with("0x") => value[2..]
+                .parse()
+                .map(BlockNumberOrHash::Hash)
+                .map_err(|_| E::invalid_value(Unexpected::Str(value), &self)),
+            _ => Err(E::invalid_value(Unexpected::Str(value), &self)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+
+    use super::*;
+
+    #[test]
+    fn block_number_forms() {
+        assert_eq!(BlockNumberParam::Number(3), serde_json::from_str("3").unwrap());
+        assert_eq!(BlockNumberParam::Offset(2), serde_json::from_str("-2").unwrap());
+        assert_eq!(BlockNumberParam::Offset(1 << 63), serde_json::from_str("-9223372036854775808").unwrap());
+        assert_eq!(BlockNumberParam::Earliest, serde_json::from_str("\"earliest\"").unwrap());
+        assert_eq!(BlockNumberParam::Latest, serde_json::from_str("\"latest\"").unwrap());
+        assert_eq!(
+            Some(BlockNumberParam::Offset(1)),
+            serde_json::from_str::<Option<BlockNumberParam>>("-1").unwrap()
+        );
+        assert_eq!(None, serde_json::from_str::<Option<BlockNumberParam>>("null").unwrap());
+    }
+
+    #[test]
+    fn invalid_block_number_is_rejected() {
+        for invalid in &["\"pending\"", "\"0x10\"", "\"3\"", "1.5", "true"] {
+            let error = serde_json::from_str::<BlockNumberParam>(invalid).unwrap_err();
+            assert!(error.to_string().contains("\"earliest\" or \"latest\""), "{}: {}", invalid, error);
+        }
+    }
+
+    #[test]
+    fn block_number_is_resolved_against_the_best_block() {
+        assert_eq!(Some(0), BlockNumberParam::Earliest.resolve(10));
+        assert_eq!(Some(10), BlockNumberParam::Latest.resolve(10));
+        assert_eq!(Some(12), BlockNumberParam::Number(12).resolve(10));
+        assert_eq!(Some(7), BlockNumberParam::Offset(3).resolve(10));
+        assert_eq!(Some(0), BlockNumberParam::Offset(10).resolve(10));
+        assert_eq!(None, BlockNumberParam::Offset(11).resolve(10));
+    }
+
+    #[test]
+    fn block_number_or_hash_forms() {
+        let hash = H256::random();
+        assert_eq!(BlockNumberOrHash::Hash(hash), serde_json::from_str(&format!("\"{:?}\"", hash)).unwrap());
+        assert_eq!(BlockNumberOrHash::Number(BlockNumberParam::Number(5)), serde_json::from_str("5").unwrap());
+        assert_eq!(BlockNumberOrHash::Number(BlockNumberParam::Offset(5)), serde_json::from_str("-5").unwrap());
+        assert_eq!(BlockNumberOrHash::Number(BlockNumberParam::Latest), serde_json::from_str("\"latest\"").unwrap());
+        assert!(serde_json::from_str::<BlockNumberOrHash>("\"0x1234\"").is_err());
+        assert!(serde_json::from_str::<BlockNumberOrHash>("\"newest\"").is_err());
+
+        assert_eq!(Some(BlockId::Hash(hash)), BlockNumberOrHash::Hash(hash).resolve(10));
+        assert_eq!(Some(BlockId::Number(9)), BlockNumberOrHash::Number(BlockNumberParam::Offset(1)).resolve(10));
+        assert_eq!(None, BlockNumberOrHash::Number(BlockNumberParam::Offset(11)).resolve(10));
+    }
+}
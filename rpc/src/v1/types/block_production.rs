@@ -0,0 +1,48 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use ccore::BlockProductionStats as CoreBlockProductionStats;
+use ckey::{NetworkId, PlatformAddress};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockProductionStats {
+    blocks: u64,
+    mean_interval: Option<f64>,
+    median_interval: Option<u64>,
+    p95_interval: Option<u64>,
+    view_changed_blocks: Option<u64>,
+    proposers: BTreeMap<PlatformAddress, u64>,
+}
+
+impl BlockProductionStats {
+    pub fn from_core(stats: CoreBlockProductionStats, network_id: NetworkId) -> Self {
+        Self {
+            blocks: stats.blocks,
+            mean_interval: stats.mean_interval,
+            median_interval: stats.median_interval,
+            p95_interval: stats.p95_interval,
+            view_changed_blocks: stats.view_changed_blocks,
+            proposers: stats
+                .proposers
+                .into_iter()
+                .map(|(address, count)| (PlatformAddress::new_v1(network_id, address), count))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use ccore::BlockRejection as CoreBlockRejection;
+use primitives::H256;
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockRejection {
+    hash: H256,
+    number: u64,
+    /// The stable code of the category
+    code: u8,
+    category: String,
+    reason: String,
+    /// The address of the peer, formatted as "ip:port"
+    peer: Option<String>,
+}
+
+impl From<CoreBlockRejection> for BlockRejection {
+    fn from(rejection: CoreBlockRejection) -> Self {
+        Self {
+            hash: rejection.hash,
+            number: rejection.number,
+            code: rejection.code.code(),
+            category: rejection.code.name().to_string(),
+            reason: rejection.reason,
+            peer: rejection.peer.map(|peer| peer.into_addr().to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use ccore::RejectionCode;
+    use cnetwork::NodeId;
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize_rejection() {
+        let rejection = BlockRejection::from(CoreBlockRejection {
+            hash: H256::zero(),
+            number: 7,
+            code: RejectionCode::StateRoot,
+            reason: "bad state root".to_string(),
+            peer: Some(NodeId::new(IpAddr::V4(Ipv4Addr::new(192, 168, 0, 3)), 3485)),
+        });
+        assert_eq!(
+            format!(
+                r#"{{"hash":"0x{}","number":7,"code":6,"category":"state-root","reason":"bad state root","peer":"192.168.0.3:3485"}}"#,
+                "0".repeat(64)
+            ),
+            to_string(&rejection).unwrap()
+        );
+    }
+
+    #[test]
+    fn serialize_rejection_without_peer() {
+        let rejection = BlockRejection::from(CoreBlockRejection {
+            hash: H256::zero(),
+            number: 7,
+            code: RejectionCode::HeaderBasic,
+            reason: "too far".to_string(),
+            peer: None,
+        });
+        assert_eq!(
+            format!(
+                r#"{{"hash":"0x{}","number":7,"code":1,"category":"header-basic","reason":"too far","peer":null}}"#,
+                "0".repeat(64)
+            ),
+            to_string(&rejection).unwrap()
+        );
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::DecodedSeal as CoreDecodedSeal;
+use cjson::bytes::Bytes;
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockSeal {
+    fields: Vec<Bytes>,
+    decoded: Option<DecodedSeal>,
+    decode_error: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum DecodedSeal {
+    Empty {},
+    Tendermint(TendermintSeal),
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TendermintSeal {
+    finalized_view: u64,
+    author_view: u64,
+    signer_indices: Vec<usize>,
+    signature_count: usize,
+}
+
+impl BlockSeal {
+    /// The fields are returned even if the engine cannot decode them.
+    pub fn new(fields: Vec<Vec<u8>>, decoded: Option<Result<CoreDecodedSeal, String>>) -> Self {
+        let (decoded, decode_error) = match decoded {
+            Some(Ok(decoded)) => (Some(decoded.into()), None),
+            Some(Err(err)) => (None, Some(err)),
+            None => (None, None),
+        };
+        Self {
+            fields: fields.into_iter().map(Bytes::new).collect(),
+            decoded,
+            decode_error,
+        }
+    }
+}
+
+impl From<CoreDecodedSeal> for DecodedSeal {
+    fn from(decoded: CoreDecodedSeal) -> Self {
+        match decoded {
+            CoreDecodedSeal::Empty => DecodedSeal::Empty {},
+            CoreDecodedSeal::Tendermint {
+                finalized_view,
+                author_view,
+                signer_indices,
+                signature_count,
+            } => DecodedSeal::Tendermint(TendermintSeal {
+                finalized_view,
+                author_view,
+                signer_indices,
+                signature_count,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize_solo_seal() {
+        let seal = BlockSeal::new(vec![], Some(Ok(CoreDecodedSeal::Empty)));
+        assert_eq!(r#"{"fields":[],"decoded":{},"decodeError":null}"#, to_string(&seal).unwrap());
+    }
+
+    #[test]
+    fn serialize_tendermint_seal() {
+        let seal = BlockSeal::new(
+            vec![vec![0x01], vec![0x03]],
+            Some(Ok(CoreDecodedSeal::Tendermint {
+                finalized_view: 1,
+                author_view: 3,
+                signer_indices: vec![0, 2],
+                signature_count: 2,
+            })),
+        );
+        assert_eq!(
+            r#"{"fields":["0x01","0x03"],"decoded":{"finalizedView":1,"authorView":3,"signerIndices":[0,2],"signatureCount":2},"decodeError":null}"#,
+            to_string(&seal).unwrap()
+        );
+    }
+
+    #[test]
+    fn serialize_corrupt_seal() {
+        let seal = BlockSeal::new(vec![vec![0xff]], Some(Err("The seal must have 4 fields, but it has 1".to_string())));
+        assert_eq!(
+            r#"{"fields":["0xff"],"decoded":null,"decodeError":"The seal must have 4 fields, but it has 1"}"#,
+            to_string(&seal).unwrap()
+        );
+    }
+}
@@ -0,0 +1,91 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::{CandidateStatus, ExclusionReason};
+use cjson::bytes::Bytes;
+use ckey::{public_to_address, NetworkId, PlatformAddress, Public};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Candidate {
+    pubkey: Public,
+    address: PlatformAddress,
+    deposit: u64,
+    nomination_ends_at: u64,
+    metadata: Bytes,
+    delegation: u64,
+    self_nomination: u64,
+    /// Why it was not elected at the last election. It's null if it was elected or didn't have enough stakes.
+    exclusion: Option<ElectionExclusion>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "reason", rename_all = "camelCase")]
+pub enum ElectionExclusion {
+    #[serde(rename_all = "camelCase")]
+    InsufficientSelfNomination {
+        self_nomination: u64,
+        minimum: u64,
+    },
+}
+
+impl From<ExclusionReason> for ElectionExclusion {
+    fn from(reason: ExclusionReason) -> Self {
+        match reason {
+            ExclusionReason::InsufficientSelfNomination {
+                self_nomination,
+                minimum,
+            } => ElectionExclusion::InsufficientSelfNomination {
+                self_nomination,
+                minimum,
+            },
+        }
+    }
+}
+
+impl Candidate {
+    pub fn from_core(candidate: CandidateStatus, network_id: NetworkId) -> Self {
+        Self {
+            pubkey: candidate.pubkey,
+            address: PlatformAddress::new_v1(network_id, public_to_address(&candidate.pubkey)),
+            deposit: candidate.deposit,
+            nomination_ends_at: candidate.nomination_ends_at,
+            metadata: candidate.metadata.into(),
+            delegation: candidate.delegation,
+            self_nomination: candidate.self_nomination,
+            exclusion: candidate.exclusion.map(From::from),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize_exclusion() {
+        let exclusion = ElectionExclusion::from(ExclusionReason::InsufficientSelfNomination {
+            self_nomination: 3,
+            minimum: 10,
+        });
+        assert_eq!(
+            r#"{"reason":"insufficientSelfNomination","selfNomination":3,"minimum":10}"#,
+            to_string(&exclusion).unwrap()
+        );
+    }
+}
@@ -0,0 +1,89 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use std::net::SocketAddr;
+
+use cnetwork::{ConnectionAttempt as NetworkConnectionAttempt, ConnectionOutcome};
+use primitives::H128;
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionAttempt {
+    /// The unix time in seconds.
+    pub timestamp: u64,
+    pub address: SocketAddr,
+    /// One of established, filtered, rateLimited, tooManyIncoming and handshakeFailed.
+    pub outcome: String,
+    /// The class of the handshake error. It exists only if the outcome is handshakeFailed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub key_fingerprint: Option<H128>,
+}
+
+impl From<NetworkConnectionAttempt> for ConnectionAttempt {
+    fn from(attempt: NetworkConnectionAttempt) -> Self {
+        let error = match attempt.outcome {
+            ConnectionOutcome::HandshakeFailed(error) => Some(error.to_string()),
+            _ => None,
+        };
+        Self {
+            timestamp: attempt.timestamp,
+            address: attempt.address.into(),
+            outcome: attempt.outcome.kind().to_string(),
+            error,
+            key_fingerprint: attempt.key_fingerprint,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cnetwork::HandshakeError;
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize_established() {
+        let attempt: ConnectionAttempt = NetworkConnectionAttempt {
+            timestamp: 1_565_000_000,
+            address: "127.0.0.1:50123".parse().unwrap(),
+            outcome: ConnectionOutcome::Established,
+            key_fingerprint: Some(H128::from(1)),
+        }
+        .into();
+        assert_eq!(
+            r#"{"timestamp":1565000000,"address":"127.0.0.1:50123","outcome":"established","keyFingerprint":"0x00000000000000000000000000000001"}"#,
+            to_string(&attempt).unwrap()
+        );
+    }
+
+    #[test]
+    fn serialize_handshake_failure() {
+        let attempt: ConnectionAttempt = NetworkConnectionAttempt {
+            timestamp: 1_565_000_000,
+            address: "127.0.0.1:50123".parse().unwrap(),
+            outcome: ConnectionOutcome::HandshakeFailed(HandshakeError::InvalidNetworkId),
+            key_fingerprint: None,
+        }
+        .into();
+        assert_eq!(
+            r#"{"timestamp":1565000000,"address":"127.0.0.1:50123","outcome":"handshakeFailed","error":"invalidNetworkId","keyFingerprint":null}"#,
+            to_string(&attempt).unwrap()
+        );
+    }
+}
@@ -0,0 +1,61 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use ccore::ConsensusFault as CoreConsensusFault;
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsensusFault {
+    None,
+    DoublePropose,
+    DoublePrevote,
+    WithholdPrecommit,
+    /// In milliseconds
+    DelayProposal(u64),
+    /// In milliseconds
+    SkewedClock(u64),
+}
+
+impl From<ConsensusFault> for CoreConsensusFault {
+    fn from(fault: ConsensusFault) -> Self {
+        match fault {
+            ConsensusFault::None => CoreConsensusFault::None,
+            ConsensusFault::DoublePropose => CoreConsensusFault::DoublePropose,
+            ConsensusFault::DoublePrevote => CoreConsensusFault::DoublePrevote,
+            ConsensusFault::WithholdPrecommit => CoreConsensusFault::WithholdPrecommit,
+            ConsensusFault::DelayProposal(ms) => CoreConsensusFault::DelayProposal(Duration::from_millis(ms)),
+            ConsensusFault::SkewedClock(ms) => CoreConsensusFault::SkewedClock(Duration::from_millis(ms)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::from_str;
+
+    use super::*;
+
+    #[test]
+    fn deserialize() {
+        assert_eq!(ConsensusFault::None, from_str(r#""none""#).unwrap());
+        assert_eq!(ConsensusFault::DoublePrevote, from_str(r#""double_prevote""#).unwrap());
+        assert_eq!(ConsensusFault::DelayProposal(1500), from_str(r#"{"delay_proposal":1500}"#).unwrap());
+        assert_eq!(ConsensusFault::SkewedClock(60000), from_str(r#"{"skewed_clock":60000}"#).unwrap());
+        assert!(from_str::<ConsensusFault>(r#""double_precommit""#).is_err());
+    }
+}
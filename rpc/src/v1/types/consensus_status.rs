@@ -0,0 +1,249 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use ccore::{
+    CommitRecoveryStats as CoreCommitRecoveryStats, ConsensusDegradation as CoreConsensusDegradation,
+    ConsensusStatus as CoreConsensusStatus, ConsensusStep as CoreConsensusStep,
+    ProposalCheckStats as CoreProposalCheckStats, VoteCollectorStats as CoreVoteCollectorStats,
+    VoteRequestStats as CoreVoteRequestStats,
+};
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsensusStatus {
+    height: u64,
+    view: u64,
+    step: ConsensusStep,
+    commit_recovery: CommitRecoveryStats,
+    double_votes_found: usize,
+    vote_collector: VoteCollectorStats,
+    /// In milliseconds
+    time_in_step: u64,
+    /// In milliseconds
+    step_timeout: u64,
+    /// In milliseconds
+    clock_skew: Option<i64>,
+    proposal_checks: ProposalCheckStats,
+    vote_requests: VoteRequestStats,
+    /// In bytes
+    retained_proposal_bytes: usize,
+    standby: bool,
+    /// The number of the invalid precommit signatures in the received seals, by the signer index.
+    bad_seal_signatures: BTreeMap<usize, usize>,
+    escalation_view: u64,
+    /// null unless the view at the current height is above escalationView
+    degradation: Option<ConsensusDegradation>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConsensusStep {
+    Propose,
+    Prevote,
+    Precommit,
+    Commit,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitRecoveryStats {
+    requested: usize,
+    retried: usize,
+    fell_back: usize,
+    recovered: usize,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProposalCheckStats {
+    rejected_before_body: usize,
+    bodies_decoded: usize,
+    rejected_by_body: usize,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoteRequestStats {
+    split_responses: usize,
+    response_batches: usize,
+    re_requested: usize,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsensusDegradation {
+    height: u64,
+    since_view: u64,
+    /// The signer indices
+    missing_prevotes: Vec<usize>,
+    /// The signer indices
+    missing_precommits: Vec<usize>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoteCollectorStats {
+    votes: usize,
+    /// In bytes
+    memory_estimate: usize,
+}
+
+impl From<CoreConsensusStatus> for ConsensusStatus {
+    fn from(status: CoreConsensusStatus) -> Self {
+        Self {
+            height: status.height,
+            view: status.view,
+            step: status.step.into(),
+            commit_recovery: status.commit_recovery.into(),
+            double_votes_found: status.double_votes_found,
+            vote_collector: status.vote_collector.into(),
+            time_in_step: duration_in_millis(status.time_in_step),
+            step_timeout: duration_in_millis(status.step_timeout),
+            clock_skew: status.clock_skew,
+            proposal_checks: status.proposal_checks.into(),
+            vote_requests: status.vote_requests.into(),
+            retained_proposal_bytes: status.retained_proposal_bytes,
+            standby: status.standby,
+            bad_seal_signatures: status.bad_seal_signatures,
+            escalation_view: status.escalation_view,
+            degradation: status.degradation.map(From::from),
+        }
+    }
+}
+
+fn duration_in_millis(duration: Duration) -> u64 {
+    duration.as_secs() * 1000 + u64::from(duration.subsec_millis())
+}
+
+impl From<CoreConsensusStep> for ConsensusStep {
+    fn from(step: CoreConsensusStep) -> Self {
+        match step {
+            CoreConsensusStep::Propose => ConsensusStep::Propose,
+            CoreConsensusStep::Prevote => ConsensusStep::Prevote,
+            CoreConsensusStep::Precommit => ConsensusStep::Precommit,
+            CoreConsensusStep::Commit => ConsensusStep::Commit,
+        }
+    }
+}
+
+impl From<CoreCommitRecoveryStats> for CommitRecoveryStats {
+    fn from(stats: CoreCommitRecoveryStats) -> Self {
+        Self {
+            requested: stats.requested,
+            retried: stats.retried,
+            fell_back: stats.fell_back,
+            recovered: stats.recovered,
+        }
+    }
+}
+
+impl From<CoreProposalCheckStats> for ProposalCheckStats {
+    fn from(stats: CoreProposalCheckStats) -> Self {
+        Self {
+            rejected_before_body: stats.rejected_before_body,
+            bodies_decoded: stats.bodies_decoded,
+            rejected_by_body: stats.rejected_by_body,
+        }
+    }
+}
+
+impl From<CoreVoteRequestStats> for VoteRequestStats {
+    fn from(stats: CoreVoteRequestStats) -> Self {
+        Self {
+            split_responses: stats.split_responses,
+            response_batches: stats.response_batches,
+            re_requested: stats.re_requested,
+        }
+    }
+}
+
+impl From<CoreConsensusDegradation> for ConsensusDegradation {
+    fn from(degradation: CoreConsensusDegradation) -> Self {
+        Self {
+            height: degradation.height,
+            since_view: degradation.since_view,
+            missing_prevotes: degradation.missing_prevotes,
+            missing_precommits: degradation.missing_precommits,
+        }
+    }
+}
+
+impl From<CoreVoteCollectorStats> for VoteCollectorStats {
+    fn from(stats: CoreVoteCollectorStats) -> Self {
+        Self {
+            votes: stats.votes,
+            memory_estimate: stats.memory_estimate,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize_consensus_status() {
+        let status: ConsensusStatus = CoreConsensusStatus {
+            height: 3,
+            view: 1,
+            step: CoreConsensusStep::Commit,
+            commit_recovery: CoreCommitRecoveryStats {
+                requested: 2,
+                retried: 1,
+                fell_back: 0,
+                recovered: 2,
+            },
+            double_votes_found: 1,
+            vote_collector: CoreVoteCollectorStats {
+                votes: 12,
+                memory_estimate: 4096,
+            },
+            time_in_step: Duration::from_millis(1500),
+            step_timeout: Duration::from_secs(2),
+            clock_skew: Some(-1200),
+            proposal_checks: CoreProposalCheckStats {
+                rejected_before_body: 3,
+                bodies_decoded: 40,
+                rejected_by_body: 1,
+            },
+            vote_requests: CoreVoteRequestStats {
+                split_responses: 2,
+                response_batches: 5,
+                re_requested: 1,
+            },
+            retained_proposal_bytes: 2048,
+            standby: false,
+            bad_seal_signatures: vec![(2, 1)].into_iter().collect(),
+            escalation_view: 10,
+            degradation: Some(CoreConsensusDegradation {
+                height: 3,
+                since_view: 11,
+                missing_prevotes: vec![1],
+                missing_precommits: vec![1, 2],
+            }),
+        }
+        .into();
+        assert_eq!(
+            r#"{"height":3,"view":1,"step":"commit","commitRecovery":{"requested":2,"retried":1,"fellBack":0,"recovered":2},"doubleVotesFound":1,"voteCollector":{"votes":12,"memoryEstimate":4096},"timeInStep":1500,"stepTimeout":2000,"clockSkew":-1200,"proposalChecks":{"rejectedBeforeBody":3,"bodiesDecoded":40,"rejectedByBody":1},"voteRequests":{"splitResponses":2,"responseBatches":5,"reRequested":1},"retainedProposalBytes":2048,"standby":false,"badSealSignatures":{"2":1},"escalationView":10,"degradation":{"height":3,"sinceView":11,"missingPrevotes":[1],"missingPrecommits":[1,2]}}"#,
+            to_string(&status).unwrap()
+        );
+    }
+}
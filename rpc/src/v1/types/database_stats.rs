@@ -0,0 +1,168 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::{CacheStats as CoreCacheStats, ColumnStats as CoreColumnStats, DatabaseStats as CoreDatabaseStats};
+
+use super::node_health::StateWarming;
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    entries: usize,
+    /// In bytes
+    size: usize,
+    /// In bytes
+    budget: usize,
+    hits: u64,
+    misses: u64,
+    /// null if the cache has never been read
+    hit_rate: Option<f64>,
+}
+
+impl From<CoreCacheStats> for CacheStats {
+    fn from(stats: CoreCacheStats) -> Self {
+        let reads = stats.hits + stats.misses;
+        Self {
+            entries: stats.entries,
+            size: stats.size,
+            budget: stats.budget,
+            hits: stats.hits,
+            misses: stats.misses,
+            hit_rate: if reads == 0 {
+                None
+            } else {
+                Some(stats.hits as f64 / reads as f64)
+            },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnStats {
+    name: &'static str,
+    /// null if the database doesn't estimate it
+    keys: Option<u64>,
+    /// In bytes. null if the database doesn't estimate it
+    size: Option<u64>,
+    /// In bytes. null if the database doesn't compact
+    pending_compaction_bytes: Option<u64>,
+    /// null if the column doesn't have a cache
+    cache: Option<CacheStats>,
+}
+
+impl From<CoreColumnStats> for ColumnStats {
+    fn from(stats: CoreColumnStats) -> Self {
+        Self {
+            name: stats.name,
+            keys: stats.keys,
+            size: stats.size,
+            pending_compaction_bytes: stats.pending_compaction_bytes,
+            cache: stats.cache.map(From::from),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseStats {
+    columns: Vec<ColumnStats>,
+    /// null if the database doesn't compact
+    running_compactions: Option<u64>,
+    /// In bytes
+    cache_budget_cap: usize,
+    state_cache_entries: usize,
+    state_warming: StateWarming,
+}
+
+impl From<CoreDatabaseStats> for DatabaseStats {
+    fn from(stats: CoreDatabaseStats) -> Self {
+        Self {
+            columns: stats.columns.into_iter().map(From::from).collect(),
+            running_compactions: stats.running_compactions,
+            cache_budget_cap: stats.cache_budget_cap,
+            state_cache_entries: stats.state_cache_entries,
+            state_warming: stats.state_warming.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ccore::StateWarmingStatus;
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize_stats() {
+        let stats = DatabaseStats::from(CoreDatabaseStats {
+            columns: vec![
+                CoreColumnStats {
+                    name: "state",
+                    keys: Some(10),
+                    size: Some(1000),
+                    pending_compaction_bytes: Some(4096),
+                    cache: None,
+                },
+                CoreColumnStats {
+                    name: "headers",
+                    keys: Some(3),
+                    size: Some(300),
+                    pending_compaction_bytes: Some(0),
+                    cache: Some(CoreCacheStats {
+                        entries: 2,
+                        size: 200,
+                        budget: 1024,
+                        hits: 3,
+                        misses: 1,
+                    }),
+                },
+                CoreColumnStats {
+                    name: "bodies",
+                    keys: None,
+                    size: None,
+                    pending_compaction_bytes: None,
+                    cache: Some(CoreCacheStats {
+                        budget: 1024,
+                        ..Default::default()
+                    }),
+                },
+            ],
+            running_compactions: Some(1),
+            cache_budget_cap: 4096,
+            state_cache_entries: 7,
+            state_warming: StateWarmingStatus {
+                loaded: 5,
+                total: 5,
+                finished: true,
+            },
+        });
+        assert_eq!(
+            concat!(
+                r#"{"columns":["#,
+                r#"{"name":"state","keys":10,"size":1000,"pendingCompactionBytes":4096,"cache":null},"#,
+                r#"{"name":"headers","keys":3,"size":300,"pendingCompactionBytes":0,"#,
+                r#""cache":{"entries":2,"size":200,"budget":1024,"hits":3,"misses":1,"hitRate":0.75}},"#,
+                r#"{"name":"bodies","keys":null,"size":null,"pendingCompactionBytes":null,"#,
+                r#""cache":{"entries":0,"size":0,"budget":1024,"hits":0,"misses":0,"hitRate":null}}"#,
+                r#"],"runningCompactions":1,"cacheBudgetCap":4096,"stateCacheEntries":7,"#,
+                r#""stateWarming":{"loaded":5,"total":5,"finished":true}}"#
+            ),
+            to_string(&stats).unwrap()
+        );
+    }
+}
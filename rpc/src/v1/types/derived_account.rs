@@ -0,0 +1,57 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use ckey::{NetworkId, PlatformAddress};
+use ckeystore::DerivedAccount as KeystoreDerivedAccount;
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DerivedAccount {
+    pub address: PlatformAddress,
+    pub index: u32,
+}
+
+impl DerivedAccount {
+    pub fn new(account: &KeystoreDerivedAccount, network_id: NetworkId) -> Self {
+        Self {
+            address: PlatformAddress::new_v1(network_id, account.address),
+            index: account.index,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ckey::Address;
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize() {
+        let account = KeystoreDerivedAccount {
+            index: 3,
+            address: Address::default(),
+        };
+        let network_id: NetworkId = "tc".into();
+        let derived = DerivedAccount::new(&account, network_id);
+        assert_eq!(
+            format!(r#"{{"address":"{}","index":3}}"#, PlatformAddress::new_v1(network_id, Address::default())),
+            to_string(&derived).unwrap()
+        );
+    }
+}
@@ -0,0 +1,56 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::net::SocketAddr;
+
+use cnetwork::DiscoveredPeer as NetworkDiscoveredPeer;
+
+/// The node id isn't shown separately, because it's derived from the address.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredPeer {
+    pub address: SocketAddr,
+    /// The unix time in seconds. It's 0 if the peer has never answered.
+    pub last_seen: u64,
+    pub failures: u32,
+}
+
+impl From<NetworkDiscoveredPeer> for DiscoveredPeer {
+    fn from(peer: NetworkDiscoveredPeer) -> Self {
+        Self {
+            address: peer.address.into(),
+            last_seen: peer.last_seen,
+            failures: peer.failures,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize() {
+        let peer = DiscoveredPeer {
+            address: "127.0.0.1:3485".parse().unwrap(),
+            last_seen: 1_565_000_000,
+            failures: 2,
+        };
+        assert_eq!(r#"{"address":"127.0.0.1:3485","lastSeen":1565000000,"failures":2}"#, to_string(&peer).unwrap());
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::{
+    AccountChange as CoreAccountChange, ActionDataChange as CoreActionDataChange, DryRunOptions as CoreDryRunOptions,
+    DryRunResult as CoreDryRunResult,
+};
+use cjson::bytes::Bytes;
+use ckey::{NetworkId, PlatformAddress, Public};
+use primitives::H256;
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DryRunOptions {
+    /// The public key of the sender. It's required by SetRegularKey and the custom actions.
+    pub sender_public: Option<Public>,
+    /// Pay no fee, and skip the minimum fee check
+    #[serde(default)]
+    pub ignore_fee: bool,
+    /// Use the current seq of the fee payer
+    #[serde(default)]
+    pub ignore_seq: bool,
+}
+
+impl From<&DryRunOptions> for CoreDryRunOptions {
+    fn from(options: &DryRunOptions) -> Self {
+        Self {
+            ignore_fee: options.ignore_fee,
+            ignore_seq: options.ignore_seq,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountChange {
+    address: PlatformAddress,
+    old_balance: u64,
+    new_balance: u64,
+    old_seq: u64,
+    new_seq: u64,
+}
+
+impl AccountChange {
+    fn from_core(change: CoreAccountChange, network_id: NetworkId) -> Self {
+        Self {
+            address: PlatformAddress::new_v1(network_id, change.address),
+            old_balance: change.old_balance,
+            new_balance: change.new_balance,
+            old_seq: change.old_seq,
+            new_seq: change.new_seq,
+        }
+    }
+}
+
+/// `new` is null if the data is removed.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionDataChange {
+    key: H256,
+    old: Option<Bytes>,
+    new: Option<Bytes>,
+}
+
+impl From<CoreActionDataChange> for ActionDataChange {
+    fn from(change: CoreActionDataChange) -> Self {
+        Self {
+            key: change.key,
+            old: change.old.map(Bytes::from),
+            new: change.new.map(Bytes::from),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunResult {
+    hash: H256,
+    tracker: Option<H256>,
+    error: Option<String>,
+    account_changes: Vec<AccountChange>,
+    action_data_changes: Vec<ActionDataChange>,
+}
+
+impl DryRunResult {
+    pub fn from_core(result: CoreDryRunResult, network_id: NetworkId) -> Self {
+        Self {
+            hash: result.invoice.hash,
+            tracker: result.invoice.tracker,
+            error: result.invoice.error,
+            account_changes: result
+                .account_changes
+                .into_iter()
+                .map(|change| AccountChange::from_core(change, network_id))
+                .collect(),
+            action_data_changes: result.action_data_changes.into_iter().map(From::from).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ccore::Invoice;
+    use ckey::Address;
+    use serde_json::{from_str, to_string};
+
+    use super::*;
+
+    #[test]
+    fn deserialize_options() {
+        assert_eq!(DryRunOptions::default(), from_str("{}").unwrap());
+        assert_eq!(
+            DryRunOptions {
+                sender_public: None,
+                ignore_fee: true,
+                ignore_seq: false,
+            },
+            from_str(r#"{"ignoreFee":true}"#).unwrap()
+        );
+        assert!(from_str::<DryRunOptions>(r#"{"ignoreNonce":true}"#).is_err());
+    }
+
+    #[test]
+    fn serialize_result() {
+        let network_id = "tc".into();
+        let address = Address::default();
+        let result = DryRunResult::from_core(
+            CoreDryRunResult {
+                invoice: Invoice {
+                    tracker: None,
+                    hash: H256::zero(),
+                    error: None,
+                },
+                account_changes: vec![CoreAccountChange {
+                    address,
+                    old_balance: 100,
+                    new_balance: 80,
+                    old_seq: 0,
+                    new_seq: 1,
+                }],
+                action_data_changes: vec![CoreActionDataChange {
+                    key: H256::zero(),
+                    old: None,
+                    new: Some(vec![0x64]),
+                }],
+            },
+            network_id,
+        );
+        let zero = format!("0x{}", "0".repeat(64));
+        assert_eq!(
+            format!(
+                r#"{{"hash":"{0}","tracker":null,"error":null,"accountChanges":[{{"address":"{1}","oldBalance":100,"newBalance":80,"oldSeq":0,"newSeq":1}}],"actionDataChanges":[{{"key":"{0}","old":null,"new":"0x64"}}]}}"#,
+                zero,
+                PlatformAddress::new_v1(network_id, address)
+            ),
+            to_string(&result).unwrap()
+        );
+    }
+}
@@ -0,0 +1,88 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::EngineDescription as CoreEngineDescription;
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionHandlerInfo {
+    pub handler_id: u64,
+    pub name: String,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EngineDescription {
+    pub name: String,
+    pub engine_type: String,
+    pub seals_internally: Option<bool>,
+    pub recommended_confirmation: u32,
+    pub seal_field_count: usize,
+    /// The names of the seal fields in order
+    pub seal_layout: Vec<String>,
+    /// 0 if the chain doesn't have terms
+    pub term_seconds: u64,
+    pub action_handlers: Vec<ActionHandlerInfo>,
+}
+
+impl From<CoreEngineDescription> for EngineDescription {
+    fn from(description: CoreEngineDescription) -> Self {
+        Self {
+            name: description.name,
+            engine_type: format!("{:?}", description.engine_type),
+            seals_internally: description.seals_internally,
+            recommended_confirmation: description.recommended_confirmation,
+            seal_field_count: description.seal_field_count,
+            seal_layout: description.seal_layout.into_iter().map(ToString::to_string).collect(),
+            term_seconds: description.term_seconds,
+            action_handlers: description
+                .action_handlers
+                .into_iter()
+                .map(|(handler_id, name)| ActionHandlerInfo {
+                    handler_id,
+                    name: name.to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ccore::EngineType;
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize() {
+        let description: EngineDescription = CoreEngineDescription {
+            name: "Tendermint".to_string(),
+            engine_type: EngineType::PBFT,
+            seals_internally: Some(false),
+            recommended_confirmation: 1,
+            seal_field_count: 4,
+            seal_layout: vec!["prev_view", "cur_view", "precommits", "precommit_bitset"],
+            term_seconds: 3600,
+            action_handlers: vec![(2, "stake handler")],
+        }
+        .into();
+        assert_eq!(
+            r#"{"name":"Tendermint","engineType":"PBFT","sealsInternally":false,"recommendedConfirmation":1,"sealFieldCount":4,"sealLayout":["prev_view","cur_view","precommits","precommit_bitset"],"termSeconds":3600,"actionHandlers":[{"handlerId":2,"name":"stake handler"}]}"#,
+            to_string(&description).unwrap()
+        );
+    }
+}
@@ -0,0 +1,46 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::FeeFloorStatus;
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeFloor {
+    floor: u64,
+    min: u64,
+    max: u64,
+    /// In permille
+    load: u64,
+    /// In permille
+    count_fullness: u64,
+    /// In permille
+    memory_fullness: u64,
+    samples: usize,
+}
+
+impl From<FeeFloorStatus> for FeeFloor {
+    fn from(status: FeeFloorStatus) -> Self {
+        Self {
+            floor: status.floor,
+            min: status.min,
+            max: status.max,
+            load: status.load,
+            count_fullness: status.count_fullness,
+            memory_fullness: status.memory_fullness,
+            samples: status.samples,
+        }
+    }
+}
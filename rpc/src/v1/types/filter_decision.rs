@@ -0,0 +1,110 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use cidr::IpCidr;
+use cnetwork::{FilterDecision as NetworkFilterDecision, FilterList, FilterMatch};
+
+/// How an address would be filtered if it connected now.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilterDecision {
+    pub allowed: bool,
+    /// "whitelist" or "blacklist". null if the address is allowed
+    pub denied_by: Option<&'static str>,
+    pub whitelist: ListDecision,
+    pub blacklist: ListDecision,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListDecision {
+    pub enabled: bool,
+    /// The most specific rule containing the address, even if the list is disabled
+    pub matched: Option<MatchedRule>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchedRule {
+    pub cidr: IpCidr,
+    pub tag: String,
+    /// The seconds left until the rule expires. null if it doesn't expire
+    pub ttl: Option<u64>,
+}
+
+impl From<FilterMatch> for MatchedRule {
+    fn from(matched: FilterMatch) -> Self {
+        Self {
+            cidr: matched.entry.cidr,
+            tag: matched.entry.tag,
+            ttl: matched.ttl.map(|ttl| ttl.as_secs()),
+        }
+    }
+}
+
+impl From<NetworkFilterDecision> for FilterDecision {
+    fn from(decision: NetworkFilterDecision) -> Self {
+        Self {
+            allowed: decision.allowed,
+            denied_by: decision.denied_by.map(|list| match list {
+                FilterList::Whitelist => "whitelist",
+                FilterList::Blacklist => "blacklist",
+            }),
+            whitelist: ListDecision {
+                enabled: decision.whitelist_enabled,
+                matched: decision.whitelist_match.map(Into::into),
+            },
+            blacklist: ListDecision {
+                enabled: decision.blacklist_enabled,
+                matched: decision.blacklist_match.map(Into::into),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use cnetwork::FilterEntry;
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize() {
+        let decision: FilterDecision = NetworkFilterDecision {
+            allowed: false,
+            denied_by: Some(FilterList::Blacklist),
+            whitelist_enabled: false,
+            whitelist_match: None,
+            blacklist_enabled: true,
+            blacklist_match: Some(FilterMatch {
+                entry: FilterEntry {
+                    cidr: IpCidr::from_str("203.0.113.0/24").unwrap(),
+                    tag: "scan".to_string(),
+                },
+                ttl: Some(Duration::from_millis(59_500)),
+            }),
+        }
+        .into();
+        assert_eq!(
+            r#"{"allowed":false,"deniedBy":"blacklist","whitelist":{"enabled":false,"matched":null},"blacklist":{"enabled":true,"matched":{"cidr":"203.0.113.0/24","tag":"scan","ttl":59}}}"#,
+            to_string(&decision).unwrap()
+        );
+    }
+}
@@ -0,0 +1,94 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::{FinalityConflict as CoreFinalityConflict, FinalizedBlock as CoreFinalizedBlock};
+use cjson::bytes::Bytes;
+use primitives::H256;
+use rlp::Encodable;
+
+/// The RLP encoded headers, with which the precommits in the seal of the child can be verified again.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FinalizedBlock {
+    hash: H256,
+    header: Bytes,
+    child_hash: H256,
+    child_header: Bytes,
+}
+
+impl From<CoreFinalizedBlock> for FinalizedBlock {
+    fn from(block: CoreFinalizedBlock) -> Self {
+        Self {
+            hash: block.header.hash(),
+            header: Bytes::new(block.header.rlp_bytes().into_vec()),
+            child_hash: block.child.hash(),
+            child_header: Bytes::new(block.child.rlp_bytes().into_vec()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FinalityConflict {
+    block_number: u64,
+    canonical: FinalizedBlock,
+    conflicting: FinalizedBlock,
+    /// In seconds since the epoch
+    detected_at: u64,
+}
+
+impl From<CoreFinalityConflict> for FinalityConflict {
+    fn from(conflict: CoreFinalityConflict) -> Self {
+        Self {
+            block_number: conflict.block_number,
+            canonical: conflict.canonical.into(),
+            conflicting: conflict.conflicting.into(),
+            detected_at: conflict.detected_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ctypes::Header;
+    use serde_json::to_value;
+
+    use super::*;
+
+    fn header(number: u64, parent: H256) -> Header {
+        let mut header = Header::new();
+        header.set_number(number);
+        header.set_parent_hash(parent);
+        header
+    }
+
+    #[test]
+    fn headers_are_exported_with_the_seal() {
+        let mut canonical = header(3, H256::zero());
+        canonical.set_seal(vec![vec![0x80], vec![0x80]]);
+        let block = CoreFinalizedBlock {
+            child: header(4, canonical.hash()),
+            header: canonical.clone(),
+        };
+        let exported = to_value(FinalizedBlock::from(block.clone())).unwrap();
+        assert_eq!(to_value(canonical.hash()).unwrap(), exported["hash"]);
+        assert_eq!(to_value(block.child.hash()).unwrap(), exported["childHash"]);
+        let encoded: Bytes = serde_json::from_value(exported["header"].clone()).unwrap();
+        let decoded: Header = rlp::decode(&encoded.into_vec());
+        assert_eq!(canonical.seal(), decoded.seal());
+        assert_eq!(canonical.hash(), decoded.hash());
+    }
+}
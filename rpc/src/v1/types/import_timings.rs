@@ -0,0 +1,89 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use std::time::Duration;
+
+use ccore::BlockImportTimings as CoreBlockImportTimings;
+use primitives::H256;
+
+/// Unit: microsecond
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockImportTimings {
+    number: u64,
+    hash: H256,
+    decode: u64,
+    header_verification: u64,
+    seal_verification: u64,
+    body_verification: u64,
+    execution: u64,
+    trie_commit: u64,
+    db_write: u64,
+    total: u64,
+}
+
+fn micros(duration: Duration) -> u64 {
+    duration.as_secs() * 1_000_000 + u64::from(duration.subsec_micros())
+}
+
+impl From<CoreBlockImportTimings> for BlockImportTimings {
+    fn from(record: CoreBlockImportTimings) -> Self {
+        let timings = record.timings;
+        Self {
+            number: record.number,
+            hash: record.hash,
+            decode: micros(timings.decode),
+            header_verification: micros(timings.header_verification),
+            seal_verification: micros(timings.seal_verification),
+            body_verification: micros(timings.body_verification),
+            execution: micros(timings.execution),
+            trie_commit: micros(timings.trie_commit),
+            db_write: micros(timings.db_write),
+            total: micros(timings.total),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ccore::ImportTimings;
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize_timings() {
+        let timings = BlockImportTimings::from(CoreBlockImportTimings {
+            number: 3,
+            hash: H256::zero(),
+            timings: ImportTimings {
+                decode: Duration::from_micros(12),
+                seal_verification: Duration::from_millis(2),
+                execution: Duration::from_secs(1),
+                total: Duration::from_micros(1_002_012),
+                ..Default::default()
+            },
+        });
+        assert_eq!(
+            format!(
+                r#"{{"number":3,"hash":"0x{}","decode":12,"headerVerification":0,"sealVerification":2000,"bodyVerification":0,"execution":1000000,"trieCommit":0,"dbWrite":0,"total":1002012}}"#,
+                "0".repeat(64)
+            ),
+            to_string(&timings).unwrap()
+        );
+    }
+}
@@ -0,0 +1,226 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use ccore::{
+    DatabaseAnomaly as CoreDatabaseAnomaly, IntegrityReport as CoreIntegrityReport, RepairOptions as CoreRepairOptions,
+};
+use primitives::H256;
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RepairOptions {
+    pub from: u64,
+    pub to: u64,
+    /// Delete the bodies stored without headers
+    #[serde(default = "default_true")]
+    pub delete_orphans: bool,
+    /// Delete the corrupt bodies, and download the missing and the corrupt bodies again
+    #[serde(default = "default_true")]
+    pub refetch_missing_bodies: bool,
+}
+
+impl From<RepairOptions> for CoreRepairOptions {
+    fn from(options: RepairOptions) -> Self {
+        Self {
+            delete_orphans: options.delete_orphans,
+            refetch_bodies: options.refetch_missing_bodies,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum DatabaseAnomaly {
+    MissingBlockHash {
+        number: u64,
+    },
+    MissingHeader {
+        number: u64,
+        hash: H256,
+    },
+    CorruptHeader {
+        number: u64,
+        hash: H256,
+    },
+    MissingBody {
+        number: u64,
+        hash: H256,
+    },
+    CorruptBody {
+        number: u64,
+        hash: H256,
+    },
+    MissingInvoice {
+        number: u64,
+        hash: H256,
+        transaction: H256,
+    },
+    OrphanedBody {
+        hash: H256,
+    },
+}
+
+impl From<CoreDatabaseAnomaly> for DatabaseAnomaly {
+    fn from(anomaly: CoreDatabaseAnomaly) -> Self {
+        match anomaly {
+            CoreDatabaseAnomaly::MissingBlockHash {
+                number,
+            } => DatabaseAnomaly::MissingBlockHash {
+                number,
+            },
+            CoreDatabaseAnomaly::MissingHeader {
+                number,
+                hash,
+            } => DatabaseAnomaly::MissingHeader {
+                number,
+                hash,
+            },
+            CoreDatabaseAnomaly::CorruptHeader {
+                number,
+                hash,
+            } => DatabaseAnomaly::CorruptHeader {
+                number,
+                hash,
+            },
+            CoreDatabaseAnomaly::MissingBody {
+                number,
+                hash,
+            } => DatabaseAnomaly::MissingBody {
+                number,
+                hash,
+            },
+            CoreDatabaseAnomaly::CorruptBody {
+                number,
+                hash,
+            } => DatabaseAnomaly::CorruptBody {
+                number,
+                hash,
+            },
+            CoreDatabaseAnomaly::MissingInvoice {
+                number,
+                hash,
+                transaction,
+            } => DatabaseAnomaly::MissingInvoice {
+                number,
+                hash,
+                transaction,
+            },
+            CoreDatabaseAnomaly::OrphanedBody {
+                hash,
+            } => DatabaseAnomaly::OrphanedBody {
+                hash,
+            },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityReport {
+    from: u64,
+    to: u64,
+    repair: bool,
+    checked_blocks: u64,
+    total_blocks: u64,
+    scanned_body_prefixes: u16,
+    anomalies: Vec<DatabaseAnomaly>,
+    deleted_bodies: usize,
+    refetched_bodies: Vec<H256>,
+    finished: bool,
+}
+
+impl From<CoreIntegrityReport> for IntegrityReport {
+    fn from(report: CoreIntegrityReport) -> Self {
+        Self {
+            from: report.from,
+            to: report.to,
+            repair: report.repair.is_some(),
+            checked_blocks: report.checked_blocks,
+            total_blocks: report.total_blocks(),
+            scanned_body_prefixes: report.scanned_body_prefixes,
+            anomalies: report.anomalies.into_iter().map(From::from).collect(),
+            deleted_bodies: report.deleted_bodies,
+            refetched_bodies: report.refetched_bodies,
+            finished: report.finished,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{from_str, to_string};
+
+    use super::*;
+
+    #[test]
+    fn deserialize_options() {
+        assert_eq!(
+            RepairOptions {
+                from: 1,
+                to: 10,
+                delete_orphans: true,
+                refetch_missing_bodies: true,
+            },
+            from_str(r#"{"from":1,"to":10}"#).unwrap()
+        );
+        assert_eq!(
+            RepairOptions {
+                from: 1,
+                to: 10,
+                delete_orphans: false,
+                refetch_missing_bodies: true,
+            },
+            from_str(r#"{"from":1,"to":10,"deleteOrphans":false}"#).unwrap()
+        );
+        assert!(from_str::<RepairOptions>(r#"{"from":1,"to":10,"deleteOrphan":false}"#).is_err());
+    }
+
+    #[test]
+    fn serialize_report() {
+        let report = IntegrityReport::from(CoreIntegrityReport {
+            from: 0,
+            to: 9,
+            repair: None,
+            checked_blocks: 10,
+            scanned_body_prefixes: 256,
+            anomalies: vec![
+                CoreDatabaseAnomaly::MissingBody {
+                    number: 3,
+                    hash: H256::zero(),
+                },
+                CoreDatabaseAnomaly::OrphanedBody {
+                    hash: H256::zero(),
+                },
+            ],
+            deleted_bodies: 0,
+            refetched_bodies: Vec::new(),
+            finished: true,
+        });
+        let zero = format!("0x{}", "0".repeat(64));
+        assert_eq!(
+            format!(
+                r#"{{"from":0,"to":9,"repair":false,"checkedBlocks":10,"totalBlocks":10,"scannedBodyPrefixes":256,"anomalies":[{{"kind":"missingBody","number":3,"hash":"{0}"}},{{"kind":"orphanedBody","hash":"{0}"}}],"deletedBodies":0,"refetchedBodies":[],"finished":true}}"#,
+                zero
+            ),
+            to_string(&report).unwrap()
+        );
+    }
+}
@@ -0,0 +1,52 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ckey::NetworkId;
+use csync::LightChainStatus;
+use primitives::H256;
+
+use super::Validator;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LightStatus {
+    pub best_block_number: u64,
+    pub best_block_hash: H256,
+    /// The last block whose precommits are verified against the validators of its term
+    pub finalized_block_number: u64,
+    pub finalized_block_hash: H256,
+    pub term_id: u64,
+    /// The validators of the last known term, in the order of the seal
+    pub validators: Vec<Validator>,
+}
+
+impl LightStatus {
+    pub fn from_core(status: LightChainStatus, network_id: NetworkId) -> Self {
+        Self {
+            best_block_number: status.best_number,
+            best_block_hash: status.best_hash,
+            finalized_block_number: status.finalized_number,
+            finalized_block_hash: status.finalized_hash,
+            term_id: status.term_id,
+            validators: status
+                .validators
+                .into_iter()
+                .enumerate()
+                .map(|(index, validator)| Validator::from_core(index, validator, network_id))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,71 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::RelayStatus;
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Relay {
+    queued: usize,
+    sent: usize,
+    dropped_stale: usize,
+}
+
+impl From<RelayStatus> for Relay {
+    fn from(status: RelayStatus) -> Self {
+        Self {
+            queued: status.queued,
+            sent: status.sent,
+            dropped_stale: status.dropped_stale,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MempoolStatus {
+    pending_transactions: usize,
+    relay: Relay,
+}
+
+impl MempoolStatus {
+    pub fn new(pending_transactions: usize, relay: RelayStatus) -> Self {
+        Self {
+            pending_transactions,
+            relay: relay.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize() {
+        let status = MempoolStatus::new(12, RelayStatus {
+            queued: 3,
+            sent: 40,
+            dropped_stale: 1,
+        });
+        assert_eq!(
+            r#"{"pendingTransactions":12,"relay":{"queued":3,"sent":40,"droppedStale":1}}"#,
+            to_string(&status).unwrap()
+        );
+    }
+}
@@ -0,0 +1,159 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt;
+
+/// The transports that serve the RPC methods.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    Http,
+    Ws,
+    Ipc,
+}
+
+impl fmt::Display for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Transport::Http => write!(f, "http"),
+            Transport::Ws => write!(f, "ws"),
+            Transport::Ipc => write!(f, "ipc"),
+        }
+    }
+}
+
+/// The read-only chain, network and mem pool queries.
+const READ_ONLY_METHODS: &[&str] = &[
+    "ping",
+    "version",
+    "commitHash",
+    "chain_get*",
+    "chain_contain*",
+    "chain_is*",
+    "chain_decodeRaw",
+    "chain_encodeTransaction",
+    "chain_subscribe",
+    "chain_unsubscribe",
+    "net_get*",
+    "net_is*",
+    "mempool_get*",
+    "node_health",
+];
+
+/// The methods allowed on a transport.
+///
+/// A pattern is either a method name or a prefix followed by `*`. A method is allowed if it matches a pattern of
+/// `allow` and doesn't match any pattern of `deny`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct MethodPolicy {
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+impl MethodPolicy {
+    /// IPC is reachable only from the local machine, so it gets everything.
+    /// HTTP and WebSockets get the read-only queries.
+    pub fn default_for(transport: Transport) -> Self {
+        let allow = match transport {
+            Transport::Ipc => vec!["*".to_string()],
+            Transport::Http | Transport::Ws => READ_ONLY_METHODS.iter().map(|pattern| pattern.to_string()).collect(),
+        };
+        Self {
+            allow,
+            deny: Vec::new(),
+        }
+    }
+
+    pub fn is_allowed(&self, method: &str) -> bool {
+        let matches = |pattern: &String| {
+            if pattern.ends_with('*') {
+                method.starts_with(&pattern[..pattern.len() - 1])
+            } else {
+                pattern == method
+            }
+        };
+        self.allow.iter().any(matches) && !self.deny.iter().any(matches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{from_str, to_string};
+
+    use super::*;
+
+    #[test]
+    fn patterns_match_the_name_or_the_prefix() {
+        let policy = MethodPolicy {
+            allow: vec!["chain_get*".to_string(), "ping".to_string()],
+            deny: vec!["chain_getTransactionHistory".to_string()],
+        };
+        assert!(policy.is_allowed("ping"));
+        assert!(policy.is_allowed("chain_getBestBlockNumber"));
+        assert!(!policy.is_allowed("chain_getTransactionHistory"));
+        assert!(!policy.is_allowed("pingpong"));
+        assert!(!policy.is_allowed("chain_executeTransaction"));
+    }
+
+    #[test]
+    fn deny_overrides_allow() {
+        let policy = MethodPolicy {
+            allow: vec!["*".to_string()],
+            deny: vec!["devel_*".to_string(), "account_*".to_string()],
+        };
+        assert!(policy.is_allowed("engine_forceNextView"));
+        assert!(!policy.is_allowed("devel_startSealing"));
+        assert!(!policy.is_allowed("account_unlock"));
+    }
+
+    #[test]
+    fn public_transports_get_the_read_only_queries_by_default() {
+        for transport in &[Transport::Http, Transport::Ws] {
+            let policy = MethodPolicy::default_for(*transport);
+            assert!(policy.is_allowed("chain_getBestBlockNumber"));
+            assert!(policy.is_allowed("chain_containsTransaction"));
+            assert!(policy.is_allowed("net_getPeerCount"));
+            assert!(policy.is_allowed("mempool_getPendingTransactionsCount"));
+            assert!(!policy.is_allowed("mempool_sendSignedTransaction"));
+            assert!(!policy.is_allowed("net_connect"));
+            assert!(!policy.is_allowed("devel_startSealing"));
+            assert!(!policy.is_allowed("account_create"));
+            assert!(!policy.is_allowed("engine_forceNextView"));
+        }
+        assert!(MethodPolicy::default_for(Transport::Ipc).is_allowed("devel_startSealing"));
+    }
+
+    #[test]
+    fn serialize_and_deserialize() {
+        let policy = MethodPolicy {
+            allow: vec!["*".to_string()],
+            deny: vec!["devel_*".to_string()],
+        };
+        let json = r#"{"allow":["*"],"deny":["devel_*"]}"#;
+        assert_eq!(json, to_string(&policy).unwrap());
+        assert_eq!(policy, from_str(json).unwrap());
+        assert_eq!(
+            MethodPolicy {
+                allow: vec!["ping".to_string()],
+                deny: Vec::new(),
+            },
+            from_str(r#"{"allow":["ping"]}"#).unwrap()
+        );
+        assert_eq!(Transport::Ws, from_str(r#""ws""#).unwrap());
+    }
+}
@@ -15,17 +15,68 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod action;
+mod action_index;
+mod action_trace;
 mod asset;
 mod asset_input;
 mod asset_output;
 mod asset_scheme;
+mod bandwidth_limits;
 mod block;
+mod block_finality;
+mod block_number;
+mod block_production;
+mod block_rejection;
+mod block_seal;
+mod candidate;
+mod connection_attempt;
+mod consensus_fault;
+mod consensus_status;
+mod database_stats;
+mod derived_account;
+mod discovered_peer;
+mod dry_run;
+mod engine_description;
+mod fee_floor;
+mod filter_decision;
+mod finality_conflict;
+mod import_timings;
+mod integrity;
+mod light_status;
+mod mempool_status;
+mod method_policy;
+mod nat_status;
+mod node_health;
 mod order;
+mod params_activation;
+mod peer_exchange_stats;
+mod peer_session;
+mod pooled_transaction;
+mod proposal_budget;
+mod range_statistics;
+mod raw;
+mod reorg_protection;
+mod replay;
+mod restore_status;
+mod reward_distribution;
+mod rpc_statistics;
+mod seal_verification;
+mod signer_diagnosis;
+mod signer_eligibility;
+mod spend_policy;
+mod sync_status;
+mod term;
 mod text;
 mod transaction;
+mod transaction_event;
+mod transaction_history;
+mod transaction_status;
 mod unsigned_transaction;
+mod validator;
 mod work;
 
+use std::collections::BTreeMap;
+
 use primitives::H256;
 
 use self::asset::Asset;
@@ -34,13 +85,64 @@ use self::asset_output::{AssetMintOutput, AssetTransferOutput};
 use self::order::OrderOnTransfer;
 
 pub use self::action::{Action, ActionWithTracker};
+pub use self::action_index::ActionIndexBackfill;
+pub use self::action_trace::ActionHandlerTrace;
 pub use self::asset::OwnedAsset;
 pub use self::asset_scheme::AssetScheme;
+pub use self::bandwidth_limits::BandwidthLimits;
 pub use self::block::Block;
-pub use self::block::BlockNumberAndHash;
+pub use self::block::{BestBlockId, BlockHeader};
+pub use self::block_finality::BlockFinality;
+pub use self::block_number::{BlockNumberOrHash, BlockNumberParam};
+pub use self::block_production::BlockProductionStats;
+pub use self::block_rejection::BlockRejection;
+pub use self::block_seal::BlockSeal;
+pub use self::candidate::Candidate;
+pub use self::connection_attempt::ConnectionAttempt;
+pub use self::consensus_fault::ConsensusFault;
+pub use self::consensus_status::ConsensusStatus;
+pub use self::database_stats::DatabaseStats;
+pub use self::derived_account::DerivedAccount;
+pub use self::discovered_peer::DiscoveredPeer;
+pub use self::dry_run::{DryRunOptions, DryRunResult};
+pub use self::engine_description::EngineDescription;
+pub use self::fee_floor::FeeFloor;
+pub use self::filter_decision::FilterDecision;
+pub use self::finality_conflict::FinalityConflict;
+pub use self::import_timings::BlockImportTimings;
+pub use self::integrity::{IntegrityReport, RepairOptions};
+pub use self::light_status::LightStatus;
+pub use self::mempool_status::MempoolStatus;
+pub use self::method_policy::{MethodPolicy, Transport};
+pub use self::nat_status::NatStatus;
+pub use self::node_health::{HealthSignals, HealthThresholds, NodeHealth};
+pub use self::params_activation::ParamsActivation;
+pub use self::peer_exchange_stats::PeerExchangeStats;
+pub use self::peer_session::PeerSession;
+pub use self::pooled_transaction::{PooledTransaction, ScheduledTransaction, TransactionOrigin};
+pub use self::proposal_budget::ProposalBudget;
+pub use self::range_statistics::RangeStatistics;
+pub use self::raw::{encode_transaction, DecodedRaw, RawKind};
+pub use self::reorg_protection::ReorgProtectionStatus;
+pub use self::replay::{ReplayOptions, ReplayReport};
+pub use self::restore_status::RestoreStatus;
+pub use self::reward_distribution::BlockRewardDistribution;
+pub use self::rpc_statistics::{Latency, MethodStatistics, RpcStatistics, SourceStatistics};
+pub use self::seal_verification::SealVerification;
+pub use self::signer_diagnosis::SignerDiagnosis;
+pub use self::signer_eligibility::SignerEligibility;
+pub use self::spend_policy::{SpendPolicy, SpendPolicyStatus};
+pub use self::sync_status::SyncStatus;
+pub use self::term::Term;
 pub use self::text::Text;
 pub use self::transaction::{PendingTransactions, Transaction};
+pub use self::transaction_event::{TransactionEvent, TransactionSubscriptionParams};
+pub use self::transaction_history::{
+    TransactionHistory, TransactionHistoryOptions, DEFAULT_TRANSACTION_HISTORY_LIMIT, MAX_TRANSACTION_HISTORY_LIMIT,
+};
+pub use self::transaction_status::TransactionStatus;
 pub use self::unsigned_transaction::UnsignedTransaction;
+pub use self::validator::Validator;
 pub use self::work::Work;
 
 use serde::de::{self, Deserialize, Deserializer};
@@ -49,6 +151,30 @@ use serde::de::{self, Deserialize, Deserializer};
 pub struct FilterStatus {
     pub list: Vec<(::cidr::IpCidr, String)>,
     pub enabled: bool,
+    /// The number of the rules of each tag. The rules without tags are not counted.
+    #[serde(default)]
+    pub tags: BTreeMap<String, usize>,
+}
+
+impl FilterStatus {
+    pub fn new(list: Vec<(::cidr::IpCidr, String)>, enabled: bool) -> Self {
+        let mut tags = BTreeMap::new();
+        for (_, tag) in list.iter().filter(|(_, tag)| !tag.is_empty()) {
+            *tags.entry(tag.clone()).or_insert(0) += 1;
+        }
+        Self {
+            list,
+            enabled,
+            tags,
+        }
+    }
+}
+
+/// The rules of a tag.
+#[derive(Debug, Serialize)]
+pub struct TaggedFilters {
+    pub whitelist: Vec<::cidr::IpCidr>,
+    pub blacklist: Vec<::cidr::IpCidr>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -0,0 +1,61 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::net::IpAddr;
+
+use cnetwork::NatStatus as NetworkNatStatus;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "state", rename_all = "camelCase")]
+pub enum NatStatus {
+    Disabled,
+    Searching,
+    Mapped {
+        #[serde(rename = "externalIp")]
+        external_ip: IpAddr,
+        #[serde(rename = "externalPort")]
+        external_port: u16,
+        /// In seconds
+        #[serde(rename = "leaseRemaining")]
+        lease_remaining: u64,
+    },
+    Failed {
+        reason: String,
+    },
+}
+
+impl From<NetworkNatStatus> for NatStatus {
+    fn from(status: NetworkNatStatus) -> Self {
+        match status {
+            NetworkNatStatus::Disabled => NatStatus::Disabled,
+            NetworkNatStatus::Searching => NatStatus::Searching,
+            NetworkNatStatus::Mapped {
+                external_ip,
+                external_port,
+                lease_remaining,
+            } => NatStatus::Mapped {
+                external_ip,
+                external_port,
+                lease_remaining: lease_remaining.as_secs(),
+            },
+            NetworkNatStatus::Failed {
+                reason,
+            } => NatStatus::Failed {
+                reason,
+            },
+        }
+    }
+}
@@ -0,0 +1,269 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use ccore::{SignerEligibility as CoreSignerEligibility, StateWarmingStatus};
+
+use super::SignerEligibility;
+
+/// The thresholds that decide whether the node is healthy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HealthThresholds {
+    /// The best block must be younger than this. None disables the check.
+    pub max_block_age: Option<Duration>,
+    pub min_peers: usize,
+    /// The node is syncing while the best header is more than this many blocks ahead of the best block.
+    pub max_sync_gap: u64,
+    /// The consensus is stuck if it stays in a step longer than this multiple of the step timeout.
+    /// None disables the check.
+    pub stuck_step_multiple: Option<u32>,
+    /// The estimated clock skew must be within this. None disables the check.
+    pub max_clock_skew: Option<Duration>,
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        Self {
+            max_block_age: Some(Duration::from_secs(60)),
+            min_peers: 1,
+            max_sync_gap: 10,
+            stuck_step_multiple: Some(10),
+            max_clock_skew: Some(Duration::from_secs(10)),
+        }
+    }
+}
+
+/// The observations that the health is decided from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HealthSignals {
+    pub best_block_age: Duration,
+    pub peer_count: usize,
+    /// How many blocks the best header is ahead of the best block
+    pub sync_gap: u64,
+    pub mem_pool_accepting: bool,
+    /// The time spent in the current step and the timeout of the step. None if the engine doesn't have steps.
+    pub consensus_step: Option<(Duration, Duration)>,
+    pub db_readable: bool,
+    /// The estimated clock skew in milliseconds. None if it's not estimated.
+    pub clock_skew: Option<i64>,
+    pub state_warming: StateWarmingStatus,
+    /// The node is suspected to be on a minority fork.
+    pub fork_suspect: bool,
+    /// The height of the conflicting finalized blocks that halted the node
+    pub finality_conflict: Option<u64>,
+    /// None if the node doesn't sign
+    pub signer_eligibility: Option<CoreSignerEligibility>,
+    /// The view at the current height is above the escalation view.
+    pub consensus_degraded: bool,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateWarming {
+    loaded: usize,
+    total: usize,
+    finished: bool,
+}
+
+impl From<StateWarmingStatus> for StateWarming {
+    fn from(status: StateWarmingStatus) -> Self {
+        Self {
+            loaded: status.loaded,
+            total: status.total,
+            finished: status.finished,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthDetails {
+    /// In seconds
+    best_block_age: u64,
+    /// In seconds. null if the check is disabled
+    max_block_age: Option<u64>,
+    peer_count: usize,
+    min_peers: usize,
+    syncing: bool,
+    mem_pool_accepting: bool,
+    consensus_stuck: bool,
+    db_readable: bool,
+    /// In milliseconds. null if it's not estimated
+    clock_skew: Option<i64>,
+    clock_skewed: bool,
+    state_warming: StateWarming,
+    fork_suspect: bool,
+    /// The height of the conflicting finalized blocks. The import and the sealing are halted until
+    /// `devel_clearConsensusHalt` is called.
+    finality_conflict: Option<u64>,
+    /// Whether the engine signer can sign. It doesn't affect the health, because the node follows the chain anyway.
+    /// null if the node doesn't sign
+    signer_eligibility: Option<SignerEligibility>,
+    /// It doesn't affect the health by itself. The slow blocks are caught by the block age.
+    consensus_degraded: bool,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeHealth {
+    healthy: bool,
+    details: HealthDetails,
+}
+
+impl NodeHealth {
+    pub fn evaluate(signals: &HealthSignals, thresholds: &HealthThresholds) -> Self {
+        let block_fresh = thresholds.max_block_age.map_or(true, |max_age| signals.best_block_age <= max_age);
+        let enough_peers = signals.peer_count >= thresholds.min_peers;
+        let syncing = signals.sync_gap > thresholds.max_sync_gap;
+        let consensus_stuck = match (signals.consensus_step, thresholds.stuck_step_multiple) {
+            (Some((time_in_step, step_timeout)), Some(multiple)) => time_in_step > step_timeout * multiple,
+            _ => false,
+        };
+        let clock_skewed = match (signals.clock_skew, thresholds.max_clock_skew) {
+            (Some(skew), Some(max_skew)) => skew.abs() as u128 > max_skew.as_millis(),
+            _ => false,
+        };
+        Self {
+            healthy: block_fresh
+                && enough_peers
+                && !syncing
+                && signals.mem_pool_accepting
+                && !consensus_stuck
+                && signals.db_readable
+                && !clock_skewed
+                && signals.state_warming.finished
+                && !signals.fork_suspect
+                && signals.finality_conflict.is_none(),
+            details: HealthDetails {
+                best_block_age: signals.best_block_age.as_secs(),
+                max_block_age: thresholds.max_block_age.map(|max_age| max_age.as_secs()),
+                peer_count: signals.peer_count,
+                min_peers: thresholds.min_peers,
+                syncing,
+                mem_pool_accepting: signals.mem_pool_accepting,
+                consensus_stuck,
+                db_readable: signals.db_readable,
+                clock_skew: signals.clock_skew,
+                clock_skewed,
+                state_warming: signals.state_warming.into(),
+                fork_suspect: signals.fork_suspect,
+                finality_conflict: signals.finality_conflict,
+                signer_eligibility: signals.signer_eligibility.map(From::from),
+                consensus_degraded: signals.consensus_degraded,
+            },
+        }
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.healthy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::to_string;
+
+    use super::*;
+
+    fn healthy_signals() -> HealthSignals {
+        HealthSignals {
+            best_block_age: Duration::from_secs(3),
+            peer_count: 4,
+            sync_gap: 1,
+            mem_pool_accepting: true,
+            consensus_step: Some((Duration::from_secs(1), Duration::from_secs(1))),
+            db_readable: true,
+            clock_skew: Some(300),
+            state_warming: StateWarmingStatus {
+                loaded: 120,
+                total: 120,
+                finished: true,
+            },
+            fork_suspect: false,
+            finality_conflict: None,
+            signer_eligibility: Some(CoreSignerEligibility::Validator),
+            consensus_degraded: false,
+        }
+    }
+
+    #[test]
+    fn healthy_node() {
+        let health = NodeHealth::evaluate(&healthy_signals(), &HealthThresholds::default());
+        assert!(health.is_healthy());
+        assert_eq!(
+            r#"{"healthy":true,"details":{"bestBlockAge":3,"maxBlockAge":60,"peerCount":4,"minPeers":1,"syncing":false,"memPoolAccepting":true,"consensusStuck":false,"dbReadable":true,"clockSkew":300,"clockSkewed":false,"stateWarming":{"loaded":120,"total":120,"finished":true},"forkSuspect":false,"finalityConflict":null,"signerEligibility":{"state":"validator"},"consensusDegraded":false}}"#,
+            to_string(&health).unwrap()
+        );
+    }
+
+    #[test]
+    fn each_signal_flips_the_health() {
+        let thresholds = HealthThresholds::default();
+        let toggles: Vec<fn(&mut HealthSignals)> = vec![
+            |signals| signals.best_block_age = Duration::from_secs(61),
+            |signals| signals.peer_count = 0,
+            |signals| signals.sync_gap = 11,
+            |signals| signals.mem_pool_accepting = false,
+            |signals| signals.consensus_step = Some((Duration::from_secs(11), Duration::from_secs(1))),
+            |signals| signals.db_readable = false,
+            |signals| signals.clock_skew = Some(-10_001),
+            |signals| signals.state_warming.finished = false,
+            |signals| signals.fork_suspect = true,
+            |signals| signals.finality_conflict = Some(12),
+        ];
+        for toggle in toggles {
+            let mut signals = healthy_signals();
+            toggle(&mut signals);
+            assert!(!NodeHealth::evaluate(&signals, &thresholds).is_healthy(), "{:?}", signals);
+        }
+    }
+
+    #[test]
+    fn disabled_checks_are_ignored() {
+        let thresholds = HealthThresholds {
+            max_block_age: None,
+            min_peers: 0,
+            max_sync_gap: 10,
+            stuck_step_multiple: None,
+            max_clock_skew: None,
+        };
+        let signals = HealthSignals {
+            best_block_age: Duration::from_secs(3600),
+            peer_count: 0,
+            consensus_step: Some((Duration::from_secs(3600), Duration::from_secs(1))),
+            clock_skew: Some(3_600_000),
+            ..healthy_signals()
+        };
+        assert!(NodeHealth::evaluate(&signals, &thresholds).is_healthy());
+
+        let signals = HealthSignals {
+            consensus_step: None,
+            clock_skew: None,
+            ..healthy_signals()
+        };
+        assert!(NodeHealth::evaluate(&signals, &HealthThresholds::default()).is_healthy());
+    }
+
+    #[test]
+    fn ineligible_signer_is_still_healthy() {
+        let signals = HealthSignals {
+            signer_eligibility: Some(CoreSignerEligibility::Unknown),
+            ..healthy_signals()
+        };
+        assert!(NodeHealth::evaluate(&signals, &HealthThresholds::default()).is_healthy());
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use ccore::ParamsActivation as CoreParamsActivation;
+use cjson::scheme::Params;
+use primitives::H256;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParamsActivation {
+    block_number: u64,
+    block_hash: H256,
+    metadata_seq: u64,
+    /// None for the genesis params
+    transaction_hash: Option<H256>,
+    params: Params,
+}
+
+impl From<CoreParamsActivation> for ParamsActivation {
+    fn from(activation: CoreParamsActivation) -> Self {
+        Self {
+            block_number: activation.block_number,
+            block_hash: activation.block_hash,
+            metadata_seq: activation.metadata_seq,
+            transaction_hash: activation.transaction_hash,
+            params: activation.params.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ctypes::CommonParams;
+    use serde_json::{to_value, Value};
+
+    use super::*;
+
+    #[test]
+    fn serialize_activation() {
+        let transaction_hash = H256::random();
+        let activation = ParamsActivation::from(CoreParamsActivation {
+            block_number: 30,
+            block_hash: H256::zero(),
+            metadata_seq: 1,
+            transaction_hash: Some(transaction_hash),
+            params: CommonParams::default_for_test(),
+        });
+        let value = to_value(&activation).unwrap();
+        assert_eq!(Value::from(30), value["blockNumber"]);
+        assert_eq!(Value::from(format!("0x{}", "0".repeat(64))), value["blockHash"]);
+        assert_eq!(Value::from(1), value["metadataSeq"]);
+        assert_eq!(to_value(&transaction_hash).unwrap(), value["transactionHash"]);
+        assert!(value["params"].is_object());
+    }
+
+    #[test]
+    fn genesis_activation_has_no_transaction() {
+        let activation = ParamsActivation::from(CoreParamsActivation {
+            block_number: 0,
+            block_hash: H256::zero(),
+            metadata_seq: 0,
+            transaction_hash: None,
+            params: CommonParams::default_for_test(),
+        });
+        assert_eq!(Value::Null, to_value(&activation).unwrap()["transactionHash"]);
+    }
+}
@@ -0,0 +1,61 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use cnetwork::PeerExchangeStats as NetworkPeerExchangeStats;
+
+/// The counters since the node started.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerExchangeStats {
+    pub requests_sent: u64,
+    pub requests_answered: u64,
+    /// The addresses added to the connection candidates
+    pub addresses_learned: u64,
+    /// The learned addresses that were connected later
+    pub addresses_used: u64,
+}
+
+impl From<NetworkPeerExchangeStats> for PeerExchangeStats {
+    fn from(stats: NetworkPeerExchangeStats) -> Self {
+        Self {
+            requests_sent: stats.requests_sent,
+            requests_answered: stats.requests_answered,
+            addresses_learned: stats.addresses_learned,
+            addresses_used: stats.addresses_used,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize() {
+        let stats = PeerExchangeStats {
+            requests_sent: 3,
+            requests_answered: 2,
+            addresses_learned: 5,
+            addresses_used: 1,
+        };
+        assert_eq!(
+            r#"{"requestsSent":3,"requestsAnswered":2,"addressesLearned":5,"addressesUsed":1}"#,
+            to_string(&stats).unwrap()
+        );
+    }
+}
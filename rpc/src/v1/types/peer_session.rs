@@ -0,0 +1,54 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::net::SocketAddr;
+
+use cnetwork::PeerSession as NetworkPeerSession;
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerSession {
+    pub address: SocketAddr,
+    pub inbound: bool,
+    pub rekeys: u64,
+}
+
+impl From<NetworkPeerSession> for PeerSession {
+    fn from(session: NetworkPeerSession) -> Self {
+        Self {
+            address: session.address.into(),
+            inbound: session.inbound,
+            rekeys: session.rekeys,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize() {
+        let session = PeerSession {
+            address: "127.0.0.1:3485".parse().unwrap(),
+            inbound: true,
+            rekeys: 3,
+        };
+        assert_eq!(r#"{"address":"127.0.0.1:3485","inbound":true,"rekeys":3}"#, to_string(&session).unwrap());
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use std::net::IpAddr;
+
+use ccore::{PooledTransaction as CorePooledTransaction, ScheduledTransaction as CoreScheduledTransaction, TxOrigin};
+use cnetwork::IntoSocketAddr;
+
+use super::Transaction;
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum TransactionOrigin {
+    Local,
+    /// The address of the client is known only if the transaction is sent over HTTP.
+    Rpc {
+        source: Option<IpAddr>,
+    },
+    /// The address of the peer, formatted as "ip:port"
+    P2p {
+        peer: Option<String>,
+    },
+    RetractedBlock,
+}
+
+impl From<TxOrigin> for TransactionOrigin {
+    fn from(origin: TxOrigin) -> Self {
+        match origin {
+            TxOrigin::Local => TransactionOrigin::Local,
+            TxOrigin::Rpc {
+                source,
+            } => TransactionOrigin::Rpc {
+                source,
+            },
+            TxOrigin::P2p {
+                peer,
+            } => TransactionOrigin::P2p {
+                peer: peer.map(|peer| peer.into_addr().to_string()),
+            },
+            TxOrigin::RetractedBlock => TransactionOrigin::RetractedBlock,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PooledTransaction {
+    transaction: Transaction,
+    origin: TransactionOrigin,
+    is_pending: bool,
+}
+
+impl From<CorePooledTransaction> for PooledTransaction {
+    fn from(tx: CorePooledTransaction) -> Self {
+        Self {
+            transaction: tx.transaction.into(),
+            origin: tx.origin.into(),
+            is_pending: tx.is_pending,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledTransaction {
+    transaction: Transaction,
+    origin: TransactionOrigin,
+    /// UNIX timestamp in seconds
+    not_before: u64,
+}
+
+impl From<CoreScheduledTransaction> for ScheduledTransaction {
+    fn from(scheduled: CoreScheduledTransaction) -> Self {
+        Self {
+            transaction: scheduled.transaction.into(),
+            origin: scheduled.origin.into(),
+            not_before: scheduled.not_before,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use cnetwork::NodeId;
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize_origins() {
+        assert_eq!(r#"{"type":"local"}"#, to_string(&TransactionOrigin::from(TxOrigin::Local)).unwrap());
+        assert_eq!(
+            r#"{"type":"rpc","source":"10.0.0.1"}"#,
+            to_string(&TransactionOrigin::from(TxOrigin::Rpc {
+                source: Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))),
+            }))
+            .unwrap()
+        );
+        assert_eq!(
+            r#"{"type":"rpc","source":null}"#,
+            to_string(&TransactionOrigin::from(TxOrigin::Rpc {
+                source: None,
+            }))
+            .unwrap()
+        );
+        assert_eq!(
+            r#"{"type":"p2p","peer":"192.168.0.3:3485"}"#,
+            to_string(&TransactionOrigin::from(TxOrigin::P2p {
+                peer: Some(NodeId::new(IpAddr::V4(Ipv4Addr::new(192, 168, 0, 3)), 3485)),
+            }))
+            .unwrap()
+        );
+        assert_eq!(
+            r#"{"type":"retractedBlock"}"#,
+            to_string(&TransactionOrigin::from(TxOrigin::RetractedBlock)).unwrap()
+        );
+    }
+}
@@ -0,0 +1,66 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::ProposalBudget as CoreProposalBudget;
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ProposalBudget {
+    /// The maximum body size in bytes. null follows the common params.
+    #[serde(default)]
+    pub max_body_size: Option<usize>,
+    /// The maximum number of the transactions. null means no limit.
+    #[serde(default)]
+    pub max_transactions: Option<usize>,
+}
+
+impl From<ProposalBudget> for CoreProposalBudget {
+    fn from(budget: ProposalBudget) -> Self {
+        Self {
+            max_body_size: budget.max_body_size,
+            max_transactions: budget.max_transactions,
+        }
+    }
+}
+
+impl From<CoreProposalBudget> for ProposalBudget {
+    fn from(budget: CoreProposalBudget) -> Self {
+        Self {
+            max_body_size: budget.max_body_size,
+            max_transactions: budget.max_transactions,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{from_str, to_string};
+
+    use super::*;
+
+    #[test]
+    fn serialize_and_deserialize() {
+        let budget = ProposalBudget {
+            max_body_size: None,
+            max_transactions: Some(10),
+        };
+        let json = r#"{"maxBodySize":null,"maxTransactions":10}"#;
+        assert_eq!(json, to_string(&budget).unwrap());
+        assert_eq!(budget, from_str(json).unwrap());
+        assert_eq!(budget, from_str(r#"{"maxTransactions":10}"#).unwrap());
+        assert!(from_str::<ProposalBudget>(r#"{"maxTxs":10}"#).is_err());
+    }
+}
@@ -0,0 +1,75 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use ccore::RangeStatistics as CoreRangeStatistics;
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RangeStatistics {
+    pub from_block: u64,
+    pub to_block: u64,
+    pub blocks: u64,
+    /// The number of the transactions of each action type
+    pub transactions: BTreeMap<&'static str, u64>,
+    pub total_fee: u64,
+    pub total_block_reward: u64,
+    pub unique_signers: u64,
+}
+
+impl RangeStatistics {
+    pub fn from_core(statistics: CoreRangeStatistics, from_block: u64, to_block: u64) -> Self {
+        Self {
+            from_block,
+            to_block,
+            blocks: statistics.blocks,
+            transactions: statistics.transactions,
+            total_fee: statistics.total_fee,
+            total_block_reward: statistics.total_block_reward,
+            unique_signers: statistics.unique_signers,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize() {
+        let mut transactions = BTreeMap::new();
+        transactions.insert("pay", 3);
+        transactions.insert("transferAsset", 1);
+        let statistics = RangeStatistics::from_core(
+            CoreRangeStatistics {
+                blocks: 10,
+                transactions,
+                total_fee: 400,
+                total_block_reward: 500,
+                unique_signers: 2,
+            },
+            1,
+            10,
+        );
+        assert_eq!(
+            r#"{"fromBlock":1,"toBlock":10,"blocks":10,"transactions":{"pay":3,"transferAsset":1},"totalFee":400,"totalBlockReward":500,"uniqueSigners":2}"#,
+            to_string(&statistics).unwrap()
+        );
+    }
+}
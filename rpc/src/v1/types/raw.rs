@@ -0,0 +1,518 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use ccore::{Block as CoreBlock, UnverifiedTransaction};
+use cjson::uint::Uint;
+use ckey::{NetworkId, Signature};
+use ctypes::header::Seal;
+use ctypes::transaction::{Action as ActionType, Transaction as TransactionType};
+use ctypes::Header;
+use primitives::U256;
+use rlp::{self, DecoderError, UntrustedRlp};
+use serde_json::{self, Value};
+
+use super::super::errors::ConversionError;
+use super::{Action, Block, BlockHeader, Transaction};
+
+/// The largest integer that a JSON number can hold without losing precision in the clients using doubles.
+const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// The integer fields that can be given as strings, in decimal or in hex with the `0x` prefix.
+const INTEGER_FIELDS: &[&str] = &[
+    "seq",
+    "fee",
+    "quantity",
+    "supply",
+    "assetQuantityFrom",
+    "assetQuantityTo",
+    "assetQuantityFee",
+    "expiration",
+    "spentQuantity",
+    "handlerId",
+];
+
+/// The fields of a transaction that are not encoded. They are checked only if they are given.
+const DERIVED_TRANSACTION_FIELDS: &[&str] = &["blockNumber", "blockHash", "transactionIndex", "result", "hash"];
+const DERIVED_ACTION_FIELDS: &[&str] = &["tracker"];
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum RawKind {
+    Block,
+    Header,
+    Transaction,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum DecodedRaw {
+    Block(Block),
+    Header(BlockHeader),
+    Transaction(Transaction),
+}
+
+impl DecodedRaw {
+    /// Decodes the RLP into the JSON that the other RPCs return.
+    /// The RLP is rejected if it isn't the canonical encoding of what it has.
+    pub fn decode(kind: RawKind, raw: &[u8], network_id: NetworkId) -> Result<Self, DecoderError> {
+        let rlp = UntrustedRlp::new(raw);
+        let (decoded, encoded) = match kind {
+            RawKind::Block => {
+                let block: CoreBlock = rlp.as_val()?;
+                let encoded = block.rlp_bytes(&Seal::With);
+                (DecodedRaw::Block(Block::from_core(block, network_id)), encoded)
+            }
+            RawKind::Header => {
+                let header: Header = rlp.as_val()?;
+                let encoded = rlp::encode(&header).into_vec();
+                (DecodedRaw::Header(BlockHeader::from_core(header, network_id)), encoded)
+            }
+            RawKind::Transaction => {
+                let transaction: UnverifiedTransaction = rlp.as_val()?;
+                let encoded = rlp::encode(&transaction).into_vec();
+                (DecodedRaw::Transaction(transaction.into()), encoded)
+            }
+        };
+        if encoded.as_slice() != raw {
+            return Err(DecoderError::Custom("The RLP is not in the canonical form"))
+        }
+        Ok(decoded)
+    }
+}
+
+/// The signed transaction given to chain_encodeTransaction.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SignedTransaction {
+    seq: Uint,
+    fee: Uint,
+    network_id: NetworkId,
+    action: Action,
+    sig: Signature,
+}
+
+/// Encodes the transaction given in the form that chain_getTransaction returns.
+///
+/// Every encoded field must be given, and no unknown field is allowed. The fields that are not encoded, such as
+/// the hash and the tracker, are optional but must match the encoded transaction if they are given. JSON numbers
+/// larger than 2^53 - 1 are rejected since they may have lost precision; such integers must be given as strings.
+pub fn encode_transaction(json: Value) -> Result<UnverifiedTransaction, String> {
+    check_integers(&json, "", "")?;
+    let transaction: SignedTransaction = serde_json::from_value(json.clone()).map_err(|e| e.to_string())?;
+    let action = ActionType::try_from(transaction.action).map_err(|e| match e {
+        ConversionError::Key(e) => e.to_string(),
+        ConversionError::Hex(e) => e.to_string(),
+    })?;
+    let unverified = UnverifiedTransaction::new(
+        TransactionType {
+            seq: transaction.seq.into(),
+            fee: transaction.fee.into(),
+            network_id: transaction.network_id,
+            action,
+        },
+        transaction.sig,
+    );
+    let canonical = serde_json::to_value(Transaction::from(unverified.clone())).map_err(|e| e.to_string())?;
+    compare(&json, &canonical, "", "")?;
+    Ok(unverified)
+}
+
+fn field_path(path: &str, field: &str) -> String {
+    if path.is_empty() {
+        field.to_string()
+    } else {
+        format!("{}.{}", path, field)
+    }
+}
+
+fn parse_integer(value: &Value) -> Option<U256> {
+    match value {
+        Value::Number(number) => number.as_u64().map(U256::from),
+        Value::String(s) if s.starts_with("0x") && s.len() > 2 && s.len() <= 66 => U256::from_str(&s[2..]).ok(),
+        Value::String(s) if !s.is_empty() && s.chars().all(|c| c.is_ascii_digit()) => U256::from_dec_str(s).ok(),
+        _ => None,
+    }
+}
+
+/// Rejects the numbers that may have lost precision and the integers that don't fit in 64 bits.
+fn check_integers(value: &Value, field: &str, path: &str) -> Result<(), String> {
+    match value {
+        Value::Number(number) => match number.as_u64() {
+            Some(number) if number <= MAX_SAFE_INTEGER => Ok(()),
+            _ => Err(format!("`{}` must be an integer not larger than 2^53 - 1 or a decimal string", path)),
+        },
+        Value::String(_) if INTEGER_FIELDS.contains(&field) => match parse_integer(value) {
+            Some(integer) if integer <= U256::from(u64::max_value()) => Ok(()),
+            _ => Err(format!("`{}` must be a 64-bit unsigned integer", path)),
+        },
+        Value::Array(values) => {
+            for (index, value) in values.iter().enumerate() {
+                check_integers(value, field, &format!("{}[{}]", path, index))?;
+            }
+            Ok(())
+        }
+        Value::Object(fields) => {
+            for (field, value) in fields {
+                check_integers(value, field, &field_path(path, field))?;
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Checks that the given JSON is the canonical JSON of the encoded transaction, up to the optional fields and the
+/// notation of the integers and the hex strings.
+fn compare(given: &Value, canonical: &Value, field: &str, path: &str) -> Result<(), String> {
+    match (given, canonical) {
+        (Value::Object(given), Value::Object(canonical)) => {
+            for (field, value) in given {
+                let path = field_path(path, field);
+                match canonical.get(field) {
+                    Some(expected) => compare(value, expected, field, &path)?,
+                    None => return Err(format!("Unknown field `{}`", path)),
+                }
+            }
+            let derived = match path {
+                "" => DERIVED_TRANSACTION_FIELDS,
+                "action" => DERIVED_ACTION_FIELDS,
+                _ => &[],
+            };
+            match canonical.keys().find(|field| !given.contains_key(*field) && !derived.contains(&field.as_str())) {
+                Some(field) => Err(format!("Missing field `{}`", field_path(path, field))),
+                None => Ok(()),
+            }
+        }
+        (Value::Array(given), Value::Array(canonical)) if given.len() == canonical.len() => {
+            for (index, (value, expected)) in given.iter().zip(canonical).enumerate() {
+                compare(value, expected, field, &format!("{}[{}]", path, index))?;
+            }
+            Ok(())
+        }
+        (Value::String(given), Value::String(canonical))
+            if given.starts_with("0x") && given.eq_ignore_ascii_case(canonical) =>
+        {
+            Ok(())
+        }
+        _ if INTEGER_FIELDS.contains(&field) && parse_integer(given).is_some() => {
+            if parse_integer(given) == parse_integer(canonical) {
+                Ok(())
+            } else {
+                Err(format!("`{}` doesn't match the encoded value {}", path, canonical))
+            }
+        }
+        _ if given == canonical => Ok(()),
+        _ => Err(format!("`{}` doesn't match the encoded value {}", path, canonical)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ckey::{Address, Public};
+    use ctypes::transaction::{
+        AssetMintOutput, AssetOutPoint, AssetTransferInput, AssetTransferOutput, Order, OrderOnTransfer, Timelock,
+    };
+    use primitives::{H160, H256};
+    use serde_json::json;
+
+    use super::*;
+
+    fn out_point(quantity: u64) -> AssetOutPoint {
+        AssetOutPoint {
+            tracker: H256::random(),
+            index: 1,
+            asset_type: H160::random(),
+            shard_id: 0,
+            quantity,
+        }
+    }
+
+    fn input(quantity: u64) -> AssetTransferInput {
+        AssetTransferInput {
+            prev_out: out_point(quantity),
+            timelock: Some(Timelock::BlockAge(10)),
+            lock_script: vec![0x30, 0x01],
+            unlock_script: vec![],
+        }
+    }
+
+    fn output(quantity: u64) -> AssetTransferOutput {
+        AssetTransferOutput {
+            lock_script_hash: H160::random(),
+            parameters: vec![vec![0xab, 0xcd]],
+            asset_type: H160::random(),
+            shard_id: 0,
+            quantity,
+        }
+    }
+
+    fn mint_output() -> Box<AssetMintOutput> {
+        Box::new(AssetMintOutput {
+            lock_script_hash: H160::random(),
+            parameters: vec![vec![0x01]],
+            supply: u64::max_value(),
+        })
+    }
+
+    fn order() -> OrderOnTransfer {
+        OrderOnTransfer {
+            order: Order {
+                asset_type_from: H160::random(),
+                asset_type_to: H160::random(),
+                asset_type_fee: H160::random(),
+                shard_id_from: 0,
+                shard_id_to: 0,
+                shard_id_fee: 0,
+                asset_quantity_from: 30,
+                asset_quantity_to: 10,
+                asset_quantity_fee: 0,
+                origin_outputs: vec![out_point(30)],
+                expiration: 1_000_000,
+                lock_script_hash_from: H160::random(),
+                parameters_from: vec![],
+                lock_script_hash_fee: H160::random(),
+                parameters_fee: vec![],
+            },
+            spent_quantity: 30,
+            input_from_indices: vec![0],
+            input_fee_indices: vec![],
+            output_from_indices: vec![],
+            output_to_indices: vec![0],
+            output_owned_fee_indices: vec![],
+            output_transferred_fee_indices: vec![],
+        }
+    }
+
+    fn all_actions() -> Vec<ActionType> {
+        let network_id: NetworkId = "tc".into();
+        vec![
+            ActionType::MintAsset {
+                network_id,
+                shard_id: 0,
+                metadata: "metadata".to_string(),
+                approver: Some(Address::random()),
+                registrar: None,
+                allowed_script_hashes: vec![H160::random()],
+                output: mint_output(),
+                approvals: vec![Signature::random()],
+            },
+            ActionType::TransferAsset {
+                network_id,
+                burns: vec![input(1)],
+                inputs: vec![input(30)],
+                outputs: vec![output(30)],
+                orders: vec![order()],
+                metadata: "".to_string(),
+                approvals: vec![],
+                expiration: Some(u64::max_value()),
+            },
+            ActionType::ChangeAssetScheme {
+                network_id,
+                shard_id: 0,
+                asset_type: H160::random(),
+                seq: 2,
+                metadata: "{\"name\":\"gold\"}".to_string(),
+                approver: None,
+                registrar: Some(Address::random()),
+                allowed_script_hashes: vec![],
+                approvals: vec![],
+            },
+            ActionType::IncreaseAssetSupply {
+                network_id,
+                shard_id: 0,
+                asset_type: H160::random(),
+                seq: 1,
+                output: mint_output(),
+                approvals: vec![],
+            },
+            ActionType::ComposeAsset {
+                network_id,
+                shard_id: 0,
+                metadata: "composed".to_string(),
+                approver: None,
+                registrar: None,
+                allowed_script_hashes: vec![],
+                inputs: vec![input(1), input(1)],
+                output: mint_output(),
+                approvals: vec![],
+            },
+            ActionType::DecomposeAsset {
+                network_id,
+                input: input(1),
+                outputs: vec![output(1), output(1)],
+                approvals: vec![],
+            },
+            ActionType::UnwrapCCC {
+                network_id,
+                burn: input(100),
+                receiver: Address::random(),
+            },
+            ActionType::Pay {
+                receiver: Address::random(),
+                quantity: u64::max_value(),
+            },
+            ActionType::SetRegularKey {
+                key: Public::random(),
+            },
+            ActionType::CreateShard {
+                users: vec![Address::random()],
+            },
+            ActionType::SetShardOwners {
+                shard_id: 1,
+                owners: vec![Address::random(), Address::random()],
+            },
+            ActionType::SetShardUsers {
+                shard_id: 1,
+                users: vec![],
+            },
+            ActionType::WrapCCC {
+                shard_id: 0,
+                lock_script_hash: H160::random(),
+                parameters: vec![vec![0x12, 0x34]],
+                quantity: 100,
+                payer: Address::random(),
+            },
+            ActionType::Custom {
+                handler_id: 2,
+                bytes: vec![0xc0],
+            },
+            ActionType::Store {
+                content: "content".to_string(),
+                certifier: Address::random(),
+                signature: Signature::random(),
+            },
+            ActionType::Remove {
+                hash: H256::random(),
+                signature: Signature::random(),
+            },
+        ]
+    }
+
+    fn raw_transaction(action: ActionType) -> Vec<u8> {
+        let transaction = UnverifiedTransaction::new(
+            TransactionType {
+                seq: 3,
+                fee: 10_000_000_000_000_000,
+                network_id: "tc".into(),
+                action,
+            },
+            Signature::random(),
+        );
+        rlp::encode(&transaction).into_vec()
+    }
+
+    fn decode_transaction(raw: &[u8]) -> Value {
+        let decoded = DecodedRaw::decode(RawKind::Transaction, raw, "tc".into()).unwrap();
+        serde_json::to_value(decoded).unwrap()
+    }
+
+    #[test]
+    fn encoding_the_decoded_transaction_gives_the_same_rlp() {
+        for action in all_actions() {
+            let raw = raw_transaction(action);
+            let encoded = encode_transaction(decode_transaction(&raw)).unwrap();
+            assert_eq!(raw, rlp::encode(&encoded).into_vec());
+        }
+    }
+
+    #[test]
+    fn derived_fields_are_optional() {
+        let raw = raw_transaction(all_actions().remove(0));
+        let mut json = decode_transaction(&raw);
+        for field in DERIVED_TRANSACTION_FIELDS {
+            json.as_object_mut().unwrap().remove(*field);
+        }
+        json["action"].as_object_mut().unwrap().remove("tracker");
+        let encoded = encode_transaction(json).unwrap();
+        assert_eq!(raw, rlp::encode(&encoded).into_vec());
+    }
+
+    #[test]
+    fn integers_can_be_decimal_strings() {
+        let raw = raw_transaction(ActionType::Pay {
+            receiver: Address::random(),
+            quantity: u64::max_value(),
+        });
+        let mut json = decode_transaction(&raw);
+        json["seq"] = json!("3");
+        json["fee"] = json!("10000000000000000");
+        json["action"]["quantity"] = json!("18446744073709551615");
+        let encoded = encode_transaction(json).unwrap();
+        assert_eq!(raw, rlp::encode(&encoded).into_vec());
+    }
+
+    #[test]
+    fn unsafe_numbers_are_rejected() {
+        let raw = raw_transaction(ActionType::Pay {
+            receiver: Address::random(),
+            quantity: 1,
+        });
+        let mut json = decode_transaction(&raw);
+        json["fee"] = json!(10_000_000_000_000_000u64);
+        assert_eq!(
+            Err("`fee` must be an integer not larger than 2^53 - 1 or a decimal string".to_string()),
+            encode_transaction(json)
+        );
+    }
+
+    #[test]
+    fn too_large_integers_are_rejected() {
+        let raw = raw_transaction(ActionType::Pay {
+            receiver: Address::random(),
+            quantity: 1,
+        });
+        let mut json = decode_transaction(&raw);
+        json["action"]["quantity"] = json!("18446744073709551616");
+        assert_eq!(Err("`action.quantity` must be a 64-bit unsigned integer".to_string()), encode_transaction(json));
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let raw = raw_transaction(all_actions().remove(0));
+        let mut json = decode_transaction(&raw);
+        json["action"]["output"]["owner"] = json!("tccq8vapdlstar6ghmqgczp6j2e83njsqq0tsvaxm9u");
+        assert_eq!(Err("Unknown field `action.output.owner`".to_string()), encode_transaction(json));
+    }
+
+    #[test]
+    fn missing_fields_are_rejected() {
+        let raw = raw_transaction(all_actions().remove(0));
+        let mut json = decode_transaction(&raw);
+        json["action"].as_object_mut().unwrap().remove("registrar");
+        assert_eq!(Err("Missing field `action.registrar`".to_string()), encode_transaction(json));
+    }
+
+    #[test]
+    fn wrong_hash_is_rejected() {
+        let raw = raw_transaction(all_actions().remove(0));
+        let mut json = decode_transaction(&raw);
+        json["hash"] = json!(H256::random());
+        assert!(encode_transaction(json).unwrap_err().starts_with("`hash` doesn't match the encoded value"));
+    }
+
+    #[test]
+    fn non_canonical_rlp_is_rejected() {
+        let mut raw = raw_transaction(ActionType::SetShardUsers {
+            shard_id: 1,
+            users: vec![],
+        });
+        raw.push(0x80);
+        assert!(DecodedRaw::decode(RawKind::Transaction, &raw, "tc".into()).is_err());
+    }
+}
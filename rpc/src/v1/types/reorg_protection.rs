@@ -0,0 +1,58 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::{Reorg, ReorgProtectionStatus as CoreReorgProtectionStatus};
+use primitives::H256;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectedReorg {
+    best_block_hash: H256,
+    best_block_number: u64,
+    new_block_hash: H256,
+    new_block_number: u64,
+    depth: u64,
+}
+
+impl From<Reorg> for RejectedReorg {
+    fn from(reorg: Reorg) -> Self {
+        Self {
+            best_block_hash: reorg.best_block_hash,
+            best_block_number: reorg.best_block_number,
+            new_block_hash: reorg.new_block_hash,
+            new_block_number: reorg.new_block_number,
+            depth: reorg.depth,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReorgProtectionStatus {
+    max_depth: u64,
+    triggered: bool,
+    last_rejected: Option<RejectedReorg>,
+}
+
+impl From<CoreReorgProtectionStatus> for ReorgProtectionStatus {
+    fn from(status: CoreReorgProtectionStatus) -> Self {
+        Self {
+            max_depth: status.max_depth,
+            triggered: status.last_rejected.is_some(),
+            last_rejected: status.last_rejected.map(From::from),
+        }
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use ccore::{
+    BlockDivergence as CoreBlockDivergence, ReplayOptions as CoreReplayOptions, ReplayReport as CoreReplayReport,
+    ReplayedTransaction as CoreReplayedTransaction,
+};
+use primitives::H256;
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ReplayOptions {
+    /// The ids of the action handlers to disable
+    #[serde(default)]
+    pub disabled_action_handlers: Vec<u64>,
+}
+
+impl From<ReplayOptions> for CoreReplayOptions {
+    fn from(options: ReplayOptions) -> Self {
+        Self {
+            disabled_action_handlers: options.disabled_action_handlers.into_iter().collect(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayedTransaction {
+    hash: H256,
+    expected_error: Option<String>,
+    actual_error: Option<String>,
+}
+
+impl From<CoreReplayedTransaction> for ReplayedTransaction {
+    fn from(tx: CoreReplayedTransaction) -> Self {
+        Self {
+            hash: tx.hash,
+            expected_error: tx.expected_error,
+            actual_error: tx.actual_error,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockDivergence {
+    block_number: u64,
+    block_hash: H256,
+    expected_state_root: H256,
+    actual_state_root: H256,
+    transaction_index: Option<usize>,
+    transactions: Vec<ReplayedTransaction>,
+}
+
+impl From<CoreBlockDivergence> for BlockDivergence {
+    fn from(divergence: CoreBlockDivergence) -> Self {
+        Self {
+            block_number: divergence.block_number,
+            block_hash: divergence.block_hash,
+            expected_state_root: divergence.expected_state_root,
+            actual_state_root: divergence.actual_state_root,
+            transaction_index: divergence.transaction_index,
+            transactions: divergence.transactions.into_iter().map(From::from).collect(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayReport {
+    replayed_blocks: u64,
+    divergence: Option<BlockDivergence>,
+}
+
+impl From<CoreReplayReport> for ReplayReport {
+    fn from(report: CoreReplayReport) -> Self {
+        Self {
+            replayed_blocks: report.replayed_blocks,
+            divergence: report.divergence.map(From::from),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{from_str, to_string};
+
+    use super::*;
+
+    #[test]
+    fn deserialize_options() {
+        assert_eq!(ReplayOptions::default(), from_str("{}").unwrap());
+        assert_eq!(
+            ReplayOptions {
+                disabled_action_handlers: vec![2],
+            },
+            from_str(r#"{"disabledActionHandlers":[2]}"#).unwrap()
+        );
+        assert!(from_str::<ReplayOptions>(r#"{"disabledHandlers":[2]}"#).is_err());
+    }
+
+    #[test]
+    fn serialize_report() {
+        let report = ReplayReport::from(CoreReplayReport {
+            replayed_blocks: 3,
+            divergence: Some(CoreBlockDivergence {
+                block_number: 3,
+                block_hash: H256::zero(),
+                expected_state_root: H256::zero(),
+                actual_state_root: H256::zero(),
+                transaction_index: Some(0),
+                transactions: vec![CoreReplayedTransaction {
+                    hash: H256::zero(),
+                    expected_error: Some("corrupted".to_string()),
+                    actual_error: None,
+                }],
+            }),
+        });
+        let zero = format!("0x{}", "0".repeat(64));
+        assert_eq!(
+            format!(
+                r#"{{"replayedBlocks":3,"divergence":{{"blockNumber":3,"blockHash":"{0}","expectedStateRoot":"{0}","actualStateRoot":"{0}","transactionIndex":0,"transactions":[{{"hash":"{0}","expectedError":"corrupted","actualError":null}}]}}}}"#,
+                zero
+            ),
+            to_string(&report).unwrap()
+        );
+    }
+}
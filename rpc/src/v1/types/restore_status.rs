@@ -0,0 +1,68 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::RestoreStatus as CoreRestoreStatus;
+use primitives::H256;
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "source", rename_all = "camelCase")]
+pub enum RestoreStatus {
+    #[serde(rename = "none")]
+    NoBackup,
+    Clean,
+    /// The hash of the corrupted backup, and whether the node is still waiting before signing
+    Quarantined {
+        hash: H256,
+        observing: bool,
+    },
+}
+
+impl From<CoreRestoreStatus> for RestoreStatus {
+    fn from(status: CoreRestoreStatus) -> Self {
+        match status {
+            CoreRestoreStatus::NoBackup => RestoreStatus::NoBackup,
+            CoreRestoreStatus::Clean => RestoreStatus::Clean,
+            CoreRestoreStatus::Quarantined {
+                hash,
+                observing,
+            } => RestoreStatus::Quarantined {
+                hash,
+                observing,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize() {
+        assert_eq!(r#"{"source":"none"}"#, to_string(&RestoreStatus::from(CoreRestoreStatus::NoBackup)).unwrap());
+        assert_eq!(r#"{"source":"clean"}"#, to_string(&RestoreStatus::from(CoreRestoreStatus::Clean)).unwrap());
+        let quarantined = CoreRestoreStatus::Quarantined {
+            hash: H256::zero(),
+            observing: true,
+        };
+        assert_eq!(
+            r#"{"source":"quarantined","hash":"0x0000000000000000000000000000000000000000000000000000000000000000","observing":true}"#,
+            to_string(&RestoreStatus::from(quarantined)).unwrap()
+        );
+    }
+}
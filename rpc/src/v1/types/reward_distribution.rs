@@ -0,0 +1,50 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use ccore::BlockRewardDistribution as CoreBlockRewardDistribution;
+use ckey::{NetworkId, PlatformAddress};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockRewardDistribution {
+    author: PlatformAddress,
+    block_reward: u64,
+    total_fee: u64,
+    total_min_fee: u64,
+    burnt: u64,
+    stakeholders: BTreeMap<PlatformAddress, u64>,
+    author_reward: u64,
+}
+
+impl BlockRewardDistribution {
+    pub fn from_core(distribution: CoreBlockRewardDistribution, network_id: NetworkId) -> Self {
+        Self {
+            author: PlatformAddress::new_v1(network_id, distribution.author),
+            block_reward: distribution.block_reward,
+            total_fee: distribution.total_fee,
+            total_min_fee: distribution.total_min_fee,
+            burnt: distribution.burnt,
+            stakeholders: distribution
+                .stakeholders
+                .into_iter()
+                .map(|(address, share)| (PlatformAddress::new_v1(network_id, address), share))
+                .collect(),
+            author_reward: distribution.author_reward,
+        }
+    }
+}
@@ -0,0 +1,88 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+/// The calls counted since the node started.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcStatistics {
+    /// Sorted by the name. The methods beyond the limit are counted together as `other`.
+    pub methods: Vec<MethodStatistics>,
+    /// Sorted by the number of the calls in descending order. The addresses beyond the limit are counted together
+    /// as `other`. Only the HTTP requests have their sources.
+    pub sources: Vec<SourceStatistics>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MethodStatistics {
+    pub method: String,
+    pub calls: u64,
+    /// Null if the method has never been called
+    pub latency: Option<Latency>,
+    /// The number of the failed calls by the error code
+    pub errors: BTreeMap<i64, u64>,
+}
+
+/// The percentiles of the latest calls. Unit: millisecond
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Latency {
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceStatistics {
+    pub source: String,
+    pub calls: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize() {
+        let mut errors = BTreeMap::new();
+        errors.insert(-32602, 2);
+        let statistics = RpcStatistics {
+            methods: vec![MethodStatistics {
+                method: "chain_getBlockByNumber".to_string(),
+                calls: 5,
+                latency: Some(Latency {
+                    p50: 0.5,
+                    p90: 1.25,
+                    p99: 3.0,
+                }),
+                errors,
+            }],
+            sources: vec![SourceStatistics {
+                source: "127.0.0.1".to_string(),
+                calls: 5,
+            }],
+        };
+        assert_eq!(
+            r#"{"methods":[{"method":"chain_getBlockByNumber","calls":5,"latency":{"p50":0.5,"p90":1.25,"p99":3.0},"errors":{"-32602":2}}],"sources":[{"source":"127.0.0.1","calls":5}]}"#,
+            to_string(&statistics).unwrap()
+        );
+    }
+}
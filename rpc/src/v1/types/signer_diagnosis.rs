@@ -0,0 +1,85 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::{QueuedTransaction as CoreQueuedTransaction, SignerDiagnosis as CoreSignerDiagnosis};
+use ckey::Public;
+use primitives::H256;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedTransaction {
+    hash: H256,
+    signer_public: Public,
+    seq: u64,
+    fee: u64,
+    is_pending: bool,
+    min_fee_to_replace: u64,
+}
+
+impl From<CoreQueuedTransaction> for QueuedTransaction {
+    fn from(tx: CoreQueuedTransaction) -> Self {
+        Self {
+            hash: tx.hash,
+            signer_public: tx.signer_public,
+            seq: tx.seq,
+            fee: tx.fee,
+            is_pending: tx.is_pending,
+            min_fee_to_replace: tx.min_fee_to_replace,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SeqGap {
+    from: u64,
+    to: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignerDiagnosis {
+    seq: u64,
+    lowest_queued_seq: Option<u64>,
+    highest_queued_seq: Option<u64>,
+    gaps: Vec<SeqGap>,
+    competing_seqs: Vec<u64>,
+    min_fee: u64,
+    transactions: Vec<QueuedTransaction>,
+    suggestions: Vec<String>,
+}
+
+impl From<CoreSignerDiagnosis> for SignerDiagnosis {
+    fn from(diagnosis: CoreSignerDiagnosis) -> Self {
+        let suggestions = diagnosis.suggestions();
+        Self {
+            seq: diagnosis.seq,
+            lowest_queued_seq: diagnosis.lowest_queued_seq,
+            highest_queued_seq: diagnosis.highest_queued_seq,
+            gaps: diagnosis
+                .gaps
+                .into_iter()
+                .map(|(from, to)| SeqGap {
+                    from,
+                    to,
+                })
+                .collect(),
+            competing_seqs: diagnosis.competing_seqs,
+            min_fee: diagnosis.min_fee,
+            transactions: diagnosis.transactions.into_iter().map(From::from).collect(),
+            suggestions,
+        }
+    }
+}
@@ -0,0 +1,70 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::SignerEligibility as CoreSignerEligibility;
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "camelCase")]
+pub enum SignerEligibility {
+    Validator,
+    Candidate,
+    #[serde(rename_all = "camelCase")]
+    Jailed {
+        custody_until: u64,
+        released_at: u64,
+    },
+    Banned,
+    Unknown,
+}
+
+impl From<CoreSignerEligibility> for SignerEligibility {
+    fn from(eligibility: CoreSignerEligibility) -> Self {
+        match eligibility {
+            CoreSignerEligibility::Validator => SignerEligibility::Validator,
+            CoreSignerEligibility::Candidate => SignerEligibility::Candidate,
+            CoreSignerEligibility::Jailed {
+                custody_until,
+                released_at,
+            } => SignerEligibility::Jailed {
+                custody_until,
+                released_at,
+            },
+            CoreSignerEligibility::Banned => SignerEligibility::Banned,
+            CoreSignerEligibility::Unknown => SignerEligibility::Unknown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize() {
+        let validator = CoreSignerEligibility::Validator;
+        assert_eq!(r#"{"state":"validator"}"#, to_string(&SignerEligibility::from(validator)).unwrap());
+        let jailed = CoreSignerEligibility::Jailed {
+            custody_until: 10,
+            released_at: 20,
+        };
+        assert_eq!(
+            r#"{"state":"jailed","custodyUntil":10,"releasedAt":20}"#,
+            to_string(&SignerEligibility::from(jailed)).unwrap()
+        );
+    }
+}
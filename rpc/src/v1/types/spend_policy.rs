@@ -0,0 +1,109 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::{SpendPolicy as CoreSpendPolicy, SpendPolicyStatus as CoreSpendPolicyStatus};
+use ckey::{Error as KeyError, NetworkId, PlatformAddress};
+
+/// The limits of the transactions that the node signs for an account. The omitted limits are not applied.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SpendPolicy {
+    #[serde(default)]
+    pub daily_cap: Option<u64>,
+    #[serde(default)]
+    pub allowed_recipients: Option<Vec<PlatformAddress>>,
+}
+
+impl SpendPolicy {
+    pub fn try_into_core(self) -> Result<CoreSpendPolicy, KeyError> {
+        let allowed_recipients = match self.allowed_recipients {
+            Some(recipients) => {
+                Some(recipients.into_iter().map(PlatformAddress::try_into_address).collect::<Result<_, _>>()?)
+            }
+            None => None,
+        };
+        Ok(CoreSpendPolicy {
+            daily_cap: self.daily_cap,
+            allowed_recipients,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpendPolicyStatus {
+    pub daily_cap: Option<u64>,
+    pub allowed_recipients: Option<Vec<PlatformAddress>>,
+    /// The CCC spent by the transactions signed in the current UTC day, including their fees
+    pub spent_today: u64,
+}
+
+impl SpendPolicyStatus {
+    pub fn from_core(status: CoreSpendPolicyStatus, network_id: NetworkId) -> Self {
+        Self {
+            daily_cap: status.policy.daily_cap,
+            allowed_recipients: status.policy.allowed_recipients.map(|recipients| {
+                recipients.into_iter().map(|address| PlatformAddress::new_v1(network_id, address)).collect()
+            }),
+            spent_today: status.spent_today,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ckey::Address;
+    use serde_json::{from_str, to_string};
+
+    use super::*;
+
+    #[test]
+    fn deserialize() {
+        let network_id: NetworkId = "tc".into();
+        let recipient = Address::random();
+        let policy: SpendPolicy = from_str(&format!(
+            r#"{{"dailyCap":1000,"allowedRecipients":["{}"]}}"#,
+            PlatformAddress::new_v1(network_id, recipient)
+        ))
+        .unwrap();
+        assert_eq!(
+            CoreSpendPolicy {
+                daily_cap: Some(1000),
+                allowed_recipients: Some(vec![recipient]),
+            },
+            policy.try_into_core().unwrap()
+        );
+
+        let policy: SpendPolicy = from_str(r#"{"dailyCap":1000}"#).unwrap();
+        assert_eq!(None, policy.allowed_recipients);
+        assert!(from_str::<SpendPolicy>(r#"{"daily_cap":1000}"#).is_err());
+    }
+
+    #[test]
+    fn serialize() {
+        let status = SpendPolicyStatus::from_core(
+            CoreSpendPolicyStatus {
+                policy: CoreSpendPolicy {
+                    daily_cap: Some(1000),
+                    allowed_recipients: None,
+                },
+                spent_today: 110,
+            },
+            "tc".into(),
+        );
+        assert_eq!(r#"{"dailyCap":1000,"allowedRecipients":null,"spentToday":110}"#, to_string(&status).unwrap());
+    }
+}
@@ -0,0 +1,52 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    /// True while the chain is frozen by `devel_freezeChain`
+    pub frozen: bool,
+    pub best_block_number: u64,
+    /// The number of blocks waiting in the import queue
+    pub queued_blocks: usize,
+    /// The memory used by the import queue, in bytes
+    pub queue_mem_used: usize,
+    /// True while the body download waits for the import queue to be drained
+    pub body_download_paused: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize_sync_status() {
+        let status = SyncStatus {
+            frozen: true,
+            best_block_number: 15,
+            queued_blocks: 2,
+            queue_mem_used: 4096,
+            body_download_paused: false,
+        };
+        assert_eq!(
+            r#"{"frozen":true,"bestBlockNumber":15,"queuedBlocks":2,"queueMemUsed":4096,"bodyDownloadPaused":false}"#,
+            to_string(&status).unwrap()
+        );
+    }
+}
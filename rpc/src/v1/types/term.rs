@@ -0,0 +1,41 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::TermDetails;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Term {
+    id: u64,
+    first_block_number: u64,
+    last_block_number: Option<u64>,
+    term_seconds: u64,
+    start_timestamp: Option<u64>,
+    end_timestamp: Option<u64>,
+}
+
+impl From<TermDetails> for Term {
+    fn from(term: TermDetails) -> Self {
+        Self {
+            id: term.id,
+            first_block_number: term.first_block_number,
+            last_block_number: term.last_block_number,
+            term_seconds: term.term_seconds,
+            start_timestamp: term.timestamp_window.map(|(start, _)| start),
+            end_timestamp: term.timestamp_window.map(|(_, end)| end),
+        }
+    }
+}
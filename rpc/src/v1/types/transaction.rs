@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use ccore::{LocalizedTransaction, PendingSignedTransactions, SignedTransaction};
+use ccore::{LocalizedTransaction, PendingSignedTransactions, SignedTransaction, UnverifiedTransaction};
 use cjson::uint::Uint;
 use ckey::{NetworkId, Signature};
 use primitives::H256;
@@ -88,3 +88,21 @@ impl From<SignedTransaction> for Transaction {
         }
     }
 }
+
+impl From<UnverifiedTransaction> for Transaction {
+    fn from(p: UnverifiedTransaction) -> Self {
+        let sig = p.signature();
+        Self {
+            block_number: None,
+            block_hash: None,
+            transaction_index: None,
+            result: None,
+            seq: p.seq,
+            fee: p.fee.into(),
+            network_id: p.network_id,
+            action: ActionWithTracker::from_core(p.action.clone(), p.network_id),
+            hash: p.hash(),
+            sig,
+        }
+    }
+}
@@ -0,0 +1,153 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+
+use ccore::TransactionEvent as CoreTransactionEvent;
+use primitives::H256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TransactionEventKind {
+    Queued,
+    Mined,
+    Dropped,
+    Retracted,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionEventDetail {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    block_hash: Option<H256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    block_number: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionEvent {
+    pub hash: H256,
+    pub event: TransactionEventKind,
+    pub detail: Option<TransactionEventDetail>,
+}
+
+impl From<CoreTransactionEvent> for TransactionEvent {
+    fn from(event: CoreTransactionEvent) -> Self {
+        match event {
+            CoreTransactionEvent::Queued {
+                hash,
+            } => TransactionEvent {
+                hash,
+                event: TransactionEventKind::Queued,
+                detail: None,
+            },
+            CoreTransactionEvent::Mined {
+                hash,
+                block_hash,
+                block_number,
+            } => TransactionEvent {
+                hash,
+                event: TransactionEventKind::Mined,
+                detail: Some(TransactionEventDetail {
+                    block_hash: Some(block_hash),
+                    block_number: Some(block_number),
+                    ..Default::default()
+                }),
+            },
+            CoreTransactionEvent::Dropped {
+                hash,
+                reason,
+            } => TransactionEvent {
+                hash,
+                event: TransactionEventKind::Dropped,
+                detail: Some(TransactionEventDetail {
+                    reason: Some(reason.as_str().to_string()),
+                    ..Default::default()
+                }),
+            },
+            CoreTransactionEvent::Retracted {
+                hash,
+                block_hash,
+                block_number,
+            } => TransactionEvent {
+                hash,
+                event: TransactionEventKind::Retracted,
+                detail: Some(TransactionEventDetail {
+                    block_hash: Some(block_hash),
+                    block_number: Some(block_number),
+                    ..Default::default()
+                }),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TransactionSubscriptionParams {
+    /// The hashes of the transactions to watch
+    pub hashes: HashSet<H256>,
+}
+
+#[cfg(test)]
+mod tests {
+    use ccore::DropReason;
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize_queued() {
+        let event: TransactionEvent = CoreTransactionEvent::Queued {
+            hash: H256::zero(),
+        }
+        .into();
+        assert_eq!(
+            r#"{"hash":"0x0000000000000000000000000000000000000000000000000000000000000000","event":"queued","detail":null}"#,
+            to_string(&event).unwrap()
+        );
+    }
+
+    #[test]
+    fn serialize_mined() {
+        let event: TransactionEvent = CoreTransactionEvent::Mined {
+            hash: H256::zero(),
+            block_hash: H256::zero(),
+            block_number: 3,
+        }
+        .into();
+        assert_eq!(
+            r#"{"hash":"0x0000000000000000000000000000000000000000000000000000000000000000","event":"mined","detail":{"blockHash":"0x0000000000000000000000000000000000000000000000000000000000000000","blockNumber":3}}"#,
+            to_string(&event).unwrap()
+        );
+    }
+
+    #[test]
+    fn serialize_dropped() {
+        let event: TransactionEvent = CoreTransactionEvent::Dropped {
+            hash: H256::zero(),
+            reason: DropReason::Replaced,
+        }
+        .into();
+        assert_eq!(
+            r#"{"hash":"0x0000000000000000000000000000000000000000000000000000000000000000","event":"dropped","detail":{"reason":"replaced"}}"#,
+            to_string(&event).unwrap()
+        );
+    }
+}
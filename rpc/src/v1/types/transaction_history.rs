@@ -0,0 +1,185 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt;
+
+use ccore::{ActionIndexPage, ActionIndexPosition, AddressHistoryPage, AddressHistoryPosition};
+use primitives::H256;
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub const DEFAULT_TRANSACTION_HISTORY_LIMIT: usize = 100;
+pub const MAX_TRANSACTION_HISTORY_LIMIT: usize = 1000;
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TransactionHistoryOptions {
+    pub from_block: Option<u64>,
+    /// The best block if not given
+    pub to_block: Option<u64>,
+    pub limit: Option<usize>,
+    /// The cursor returned with the previous page
+    pub cursor: Option<TransactionHistoryCursor>,
+}
+
+/// The position where the next page starts. Clients should treat it as an opaque string.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct TransactionHistoryCursor {
+    pub block_number: u64,
+    pub index: usize,
+}
+
+impl Serialize for TransactionHistoryCursor {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer, {
+        serializer.serialize_str(&format!("0x{:016x}{:08x}", self.block_number, self.index))
+    }
+}
+
+impl<'de> Deserialize<'de> for TransactionHistoryCursor {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>, {
+        deserializer.deserialize_str(CursorVisitor)
+    }
+}
+
+struct CursorVisitor;
+
+impl<'de> Visitor<'de> for CursorVisitor {
+    type Value = TransactionHistoryCursor;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a cursor returned by chain_getTransactionHistory or chain_getTransactionsByType")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error, {
+        let invalid = || E::custom(format!("Invalid cursor: {}", value));
+        if value.len() != 26 || !value.starts_with("0x") {
+            return Err(invalid())
+        }
+        let block_number = u64::from_str_radix(&value[2..18], 16).map_err(|_| invalid())?;
+        let index = u32::from_str_radix(&value[18..], 16).map_err(|_| invalid())?;
+        Ok(TransactionHistoryCursor {
+            block_number,
+            index: index as usize,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionLocation {
+    block_number: u64,
+    block_hash: H256,
+    transaction_index: usize,
+    transaction_hash: H256,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionHistory {
+    locations: Vec<TransactionLocation>,
+    /// None if there are no more transactions in the range
+    cursor: Option<TransactionHistoryCursor>,
+}
+
+impl From<AddressHistoryPage> for TransactionHistory {
+    fn from(page: AddressHistoryPage) -> Self {
+        Self {
+            locations: page
+                .entries
+                .into_iter()
+                .map(|(position, entry)| TransactionLocation {
+                    block_number: position.block_number,
+                    block_hash: entry.block_hash,
+                    transaction_index: position.index,
+                    transaction_hash: entry.transaction_hash,
+                })
+                .collect(),
+            cursor: page.next.map(|position: AddressHistoryPosition| TransactionHistoryCursor {
+                block_number: position.block_number,
+                index: position.index,
+            }),
+        }
+    }
+}
+
+impl From<ActionIndexPage> for TransactionHistory {
+    fn from(page: ActionIndexPage) -> Self {
+        Self {
+            locations: page
+                .entries
+                .into_iter()
+                .map(|(position, entry)| TransactionLocation {
+                    block_number: position.block_number,
+                    block_hash: entry.block_hash,
+                    transaction_index: position.index,
+                    transaction_hash: entry.transaction_hash,
+                })
+                .collect(),
+            cursor: page.next.map(|position: ActionIndexPosition| TransactionHistoryCursor {
+                block_number: position.block_number,
+                index: position.index,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{from_str, to_string};
+
+    use super::*;
+
+    #[test]
+    fn cursor_round_trip() {
+        let cursor = TransactionHistoryCursor {
+            block_number: 0x1234,
+            index: 7,
+        };
+        let serialized = to_string(&cursor).unwrap();
+        assert_eq!(r#""0x000000000000123400000007""#, serialized);
+        assert_eq!(cursor, from_str(&serialized).unwrap());
+    }
+
+    #[test]
+    fn reject_invalid_cursor() {
+        assert!(from_str::<TransactionHistoryCursor>(r#""0x1234""#).is_err());
+        assert!(from_str::<TransactionHistoryCursor>(r#""0x00000000000012340000000g""#).is_err());
+    }
+
+    #[test]
+    fn deserialize_options() {
+        let options: TransactionHistoryOptions =
+            from_str(r#"{"fromBlock":3,"limit":10,"cursor":"0x000000000000000400000001"}"#).unwrap();
+        assert_eq!(
+            TransactionHistoryOptions {
+                from_block: Some(3),
+                to_block: None,
+                limit: Some(10),
+                cursor: Some(TransactionHistoryCursor {
+                    block_number: 4,
+                    index: 1,
+                }),
+            },
+            options
+        );
+    }
+}
@@ -0,0 +1,84 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::PendingQueue as CorePendingQueue;
+use primitives::H256;
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "status")]
+pub enum TransactionStatus {
+    #[serde(rename_all = "camelCase")]
+    Mined {
+        block_hash: H256,
+        index: usize,
+    },
+    Pending {
+        queue: PendingQueue,
+    },
+    Unknown,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PendingQueue {
+    Current,
+    Future,
+}
+
+impl From<CorePendingQueue> for PendingQueue {
+    fn from(queue: CorePendingQueue) -> Self {
+        match queue {
+            CorePendingQueue::Current => PendingQueue::Current,
+            CorePendingQueue::Future => PendingQueue::Future,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::to_string;
+
+    use super::*;
+
+    #[test]
+    fn serialize_mined() {
+        let status = TransactionStatus::Mined {
+            block_hash: H256::zero(),
+            index: 3,
+        };
+        assert_eq!(
+            r#"{"status":"mined","blockHash":"0x0000000000000000000000000000000000000000000000000000000000000000","index":3}"#,
+            to_string(&status).unwrap()
+        );
+    }
+
+    #[test]
+    fn serialize_pending() {
+        let current = TransactionStatus::Pending {
+            queue: CorePendingQueue::Current.into(),
+        };
+        assert_eq!(r#"{"status":"pending","queue":"current"}"#, to_string(&current).unwrap());
+        let future = TransactionStatus::Pending {
+            queue: CorePendingQueue::Future.into(),
+        };
+        assert_eq!(r#"{"status":"pending","queue":"future"}"#, to_string(&future).unwrap());
+    }
+
+    #[test]
+    fn serialize_unknown() {
+        assert_eq!(r#"{"status":"unknown"}"#, to_string(&TransactionStatus::Unknown).unwrap());
+    }
+}
@@ -0,0 +1,40 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::WeightedValidator;
+use ckey::{public_to_address, NetworkId, PlatformAddress, Public};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Validator {
+    index: usize,
+    pubkey: Public,
+    address: PlatformAddress,
+    delegation: Option<u64>,
+    deposit: Option<u64>,
+}
+
+impl Validator {
+    pub fn from_core(index: usize, validator: WeightedValidator, network_id: NetworkId) -> Self {
+        Self {
+            index,
+            pubkey: validator.pubkey,
+            address: PlatformAddress::new_v1(network_id, public_to_address(&validator.pubkey)),
+            delegation: validator.delegation,
+            deposit: validator.deposit,
+        }
+    }
+}
@@ -15,6 +15,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod hit;
+mod trace;
 
 use std::convert::From;
 
@@ -101,6 +102,7 @@ impl ActionDataKeyBuilder {
 }
 
 pub use self::hit::HitHandler;
+pub use self::trace::ActionDataAccess;
 
 #[cfg(test)]
 mod tests {
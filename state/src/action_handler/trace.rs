@@ -0,0 +1,59 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use primitives::{Bytes, H256};
+
+/// A read or a write of the action data, recorded while the state is traced.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ActionDataAccess {
+    Read {
+        key: H256,
+        value: Option<Bytes>,
+    },
+    /// `new` is None if the data is removed.
+    Write {
+        key: H256,
+        old: Option<Bytes>,
+        new: Option<Bytes>,
+    },
+}
+
+impl ActionDataAccess {
+    pub fn key(&self) -> &H256 {
+        match self {
+            ActionDataAccess::Read {
+                key,
+                ..
+            } => key,
+            ActionDataAccess::Write {
+                key,
+                ..
+            } => key,
+        }
+    }
+
+    pub fn is_write(&self) -> bool {
+        match self {
+            ActionDataAccess::Read {
+                ..
+            } => false,
+            ActionDataAccess::Write {
+                ..
+            } => true,
+        }
+    }
+}
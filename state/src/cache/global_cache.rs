@@ -17,7 +17,7 @@
 use std::collections::{HashMap, HashSet};
 
 use super::lru_cache::LruCache;
-use super::{ShardCache, TopCache};
+use super::{CacheableItem, ShardCache, TopCache};
 use crate::{Account, ActionData, AssetScheme, Metadata, OwnedAsset, RegularAccount, Shard, Text};
 
 use ctypes::ShardId;
@@ -151,6 +151,49 @@ impl GlobalCache {
         }
     }
 
+    /// Adds the items of `top_cache` that are not cached yet, and returns how many are added.
+    /// The items that don't exist are not added.
+    pub fn warm(&mut self, top_cache: &TopCache) -> usize {
+        fn warm_with<Item: CacheableItem>(
+            cache: &mut LruCache<Item>,
+            items: Vec<(Item::Address, Option<Item>)>,
+        ) -> usize {
+            let mut added = 0;
+            for (addr, item) in items {
+                if let Some(item) = item {
+                    if !cache.contains_key(&addr) {
+                        cache.insert(addr, item);
+                        added += 1;
+                    }
+                }
+            }
+            added
+        }
+
+        warm_with(&mut self.account, top_cache.cached_accounts())
+            + warm_with(&mut self.regular_account, top_cache.cached_regular_accounts())
+            + warm_with(&mut self.metadata, top_cache.cached_metadata())
+            + warm_with(&mut self.shard, top_cache.cached_shards())
+            + warm_with(&mut self.text, top_cache.cached_texts())
+            + warm_with(&mut self.action_data, top_cache.cached_action_data())
+    }
+
+    /// The number of the cached items.
+    pub fn len(&self) -> usize {
+        self.account.len()
+            + self.regular_account.len()
+            + self.metadata.len()
+            + self.shard.len()
+            + self.text.len()
+            + self.action_data.len()
+            + self.asset_scheme.len()
+            + self.asset.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     pub fn clear(&mut self) {
         self.account.clear();
         self.regular_account.clear();
@@ -0,0 +1,93 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ckey::Address;
+use primitives::H256;
+use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
+
+use crate::{RegularAccountAddress, ShardAddress};
+
+const ACCOUNT: u8 = 1;
+const REGULAR_ACCOUNT: u8 = 2;
+const SHARD: u8 = 3;
+const ACTION_DATA: u8 = 4;
+
+/// The address of a top level item that a block has touched.
+/// The recently touched ones are loaded into the cache after restarts.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum HotKey {
+    Account(Address),
+    RegularAccount(RegularAccountAddress),
+    Shard(ShardAddress),
+    ActionData(H256),
+}
+
+impl Encodable for HotKey {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(2);
+        match self {
+            HotKey::Account(address) => s.append(&ACCOUNT).append(address),
+            HotKey::RegularAccount(address) => s.append(&REGULAR_ACCOUNT).append::<H256>(address.into()),
+            HotKey::Shard(address) => s.append(&SHARD).append::<H256>(address.into()),
+            HotKey::ActionData(key) => s.append(&ACTION_DATA).append(key),
+        };
+    }
+}
+
+impl Decodable for HotKey {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 2 {
+            return Err(DecoderError::RlpInvalidLength {
+                got: item_count,
+                expected: 2,
+            })
+        }
+        match rlp.val_at::<u8>(0)? {
+            ACCOUNT => Ok(HotKey::Account(rlp.val_at(1)?)),
+            REGULAR_ACCOUNT => RegularAccountAddress::from_hash(rlp.val_at(1)?)
+                .map(HotKey::RegularAccount)
+                .ok_or(DecoderError::Custom("Invalid regular account address")),
+            SHARD => ShardAddress::from_hash(rlp.val_at(1)?)
+                .map(HotKey::Shard)
+                .ok_or(DecoderError::Custom("Invalid shard address")),
+            ACTION_DATA => Ok(HotKey::ActionData(rlp.val_at(1)?)),
+            _ => Err(DecoderError::Custom("Unexpected kind of hot key")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rlp::rlp_encode_and_decode_test;
+
+    use super::*;
+
+    #[test]
+    fn encode_and_decode_hot_keys() {
+        rlp_encode_and_decode_test!(HotKey::Account(Address::random()));
+        rlp_encode_and_decode_test!(HotKey::RegularAccount(RegularAccountAddress::from_address(&Address::random())));
+        rlp_encode_and_decode_test!(HotKey::Shard(ShardAddress::new(3)));
+        rlp_encode_and_decode_test!(HotKey::ActionData(H256::random()));
+    }
+
+    #[test]
+    fn reject_the_address_of_another_kind() {
+        let mut s = RlpStream::new_list(2);
+        s.append(&REGULAR_ACCOUNT).append::<H256>((&ShardAddress::new(3)).into());
+        assert!(::rlp::decode::<HotKey>(&s.out()).is_err());
+    }
+}
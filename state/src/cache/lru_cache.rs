@@ -45,6 +45,18 @@ impl<Item: CacheableItem> LruCache<Item> {
     pub fn remove(&mut self, k: &Item::Address) -> Option<Item> {
         self.cache.remove(&k)
     }
+
+    pub fn contains_key(&mut self, k: &Item::Address) -> bool {
+        self.cache.contains_key(k)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
 }
 
 impl<Item: CacheableItem> Clone for LruCache<Item> {
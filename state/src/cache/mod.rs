@@ -16,16 +16,19 @@
 
 use std::fmt;
 use std::hash::Hash;
+use std::ops::Add;
 
 use rlp::{Decodable, Encodable};
 
 mod global_cache;
+mod hot_key;
 mod lru_cache;
 mod shard_cache;
 mod top_cache;
 mod write_back;
 
 pub use self::global_cache::GlobalCache;
+pub use self::hot_key::HotKey;
 pub use self::shard_cache::ShardCache;
 pub use self::top_cache::TopCache;
 pub use self::write_back::WriteBack;
@@ -34,3 +37,21 @@ pub trait CacheableItem: Clone + Default + fmt::Debug + Decodable + Encodable {
     type Address: AsRef<[u8]> + Clone + Copy + fmt::Debug + Eq + Hash;
     fn is_null(&self) -> bool;
 }
+
+/// How many lookups are served from the cache, and how many go to the trie.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+impl Add for CacheStats {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            hits: self.hits + other.hits,
+            misses: self.misses + other.misses,
+        }
+    }
+}
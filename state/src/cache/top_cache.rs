@@ -20,7 +20,7 @@ use ckey::Address;
 use cmerkle::{Result as TrieResult, TrieDB, TrieMut};
 use primitives::H256;
 
-use super::WriteBack;
+use super::{CacheStats, HotKey, WriteBack};
 use crate::{
     Account, ActionData, Metadata, MetadataAddress, RegularAccount, RegularAccountAddress, Shard, ShardAddress, Text,
 };
@@ -159,6 +159,24 @@ impl TopCache {
         self.action_data.remove(address)
     }
 
+    /// Returns the keys that are read or written after this cache is created.
+    pub fn touched_keys(&self) -> Vec<HotKey> {
+        let accounts = self.account.touched_addresses().into_iter().map(HotKey::Account);
+        let regular_accounts = self.regular_account.touched_addresses().into_iter().map(HotKey::RegularAccount);
+        let shards = self.shard.touched_addresses().into_iter().map(HotKey::Shard);
+        let action_data = self.action_data.touched_addresses().into_iter().map(HotKey::ActionData);
+        accounts.chain(regular_accounts).chain(shards).chain(action_data).collect()
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.account.stats()
+            + self.regular_account.stats()
+            + self.metadata.stats()
+            + self.shard.stats()
+            + self.text.stats()
+            + self.action_data.stats()
+    }
+
     pub fn cached_accounts(&self) -> Vec<(Address, Option<Account>)> {
         let mut items = self.account.items();
         items.sort_unstable_by(|lhs, rhs| lhs.0.cmp(&rhs.0));
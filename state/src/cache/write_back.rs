@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use std::cell::{RefCell, RefMut};
+use std::cell::{Cell, RefCell, RefMut};
 use std::collections::hash_map::Entry as HashMapEntry;
 use std::collections::HashMap;
 use std::convert::AsRef;
@@ -24,7 +24,7 @@ use std::vec::Vec;
 
 use cmerkle::{self, Result as TrieResult, Trie, TrieDB, TrieMut};
 
-use super::CacheableItem;
+use super::{CacheStats, CacheableItem};
 
 static TOUCHED_COUNT: AtomicUsize = AtomicUsize::new(0);
 fn touched_count() -> usize {
@@ -78,6 +78,10 @@ where
     cache: RefCell<HashMap<Item::Address, Entry<Item>>>,
     // The original item is preserved in
     checkpoints: RefCell<CheckPoints<Item::Address, Item>>,
+    /// The entries touched before this are the ones copied from the global cache.
+    created_at: usize,
+    hits: Cell<usize>,
+    misses: Cell<usize>,
 }
 
 impl<Item> WriteBack<Item>
@@ -88,6 +92,9 @@ where
         Self {
             cache: Default::default(),
             checkpoints: Default::default(),
+            created_at: TOUCHED_COUNT.load(Ordering::SeqCst),
+            hits: Cell::new(0),
+            misses: Cell::new(0),
         }
     }
 
@@ -199,10 +206,12 @@ where
         // check local cache first
         if let Some(cached_item) = self.cache.borrow_mut().get_mut(a) {
             cached_item.touched = touched_count();
+            self.hits.set(self.hits.get() + 1);
             return Ok(cached_item.item.clone())
         }
 
         // not found in the cache, get from the DB and insert into cache
+        self.misses.set(self.misses.get() + 1);
         let maybe_item = db.get_with(a.as_ref(), ::rlp::decode::<Item>)?;
         self.insert(a, Entry::<Item>::new_clean(maybe_item.clone()));
         Ok(maybe_item)
@@ -212,7 +221,10 @@ where
     /// If it doesn't exist, make item equal the evaluation of `default`.
     pub fn get_mut(&self, a: &Item::Address, db: &TrieDB) -> cmerkle::Result<RefMut<Item>> {
         let contains_key = self.cache.borrow().contains_key(a);
-        if !contains_key {
+        if contains_key {
+            self.hits.set(self.hits.get() + 1);
+        } else {
+            self.misses.set(self.misses.get() + 1);
             let maybe_item = db.get_with(a.as_ref(), ::rlp::decode::<Item>)?;
             self.insert(a, Entry::<Item>::new_clean(maybe_item));
         }
@@ -258,6 +270,25 @@ where
             .collect()
     }
 
+    /// Returns the addresses that are read or written after this cache is created, in the order they are touched.
+    pub fn touched_addresses(&self) -> Vec<Item::Address> {
+        let cache = self.cache.borrow();
+        let mut touched: Vec<_> = cache
+            .iter()
+            .filter(|(_, entry)| entry.touched >= self.created_at)
+            .map(|(addr, entry)| (entry.touched, *addr))
+            .collect();
+        touched.sort_unstable_by(|lhs, rhs| lhs.0.cmp(&rhs.0));
+        touched.into_iter().map(|(_, addr)| addr).collect()
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.get(),
+            misses: self.misses.get(),
+        }
+    }
+
     fn len(&self) -> usize {
         let cache = self.cache.borrow();
         cache.len()
@@ -282,6 +313,9 @@ where
         Self {
             cache: self.cache.clone(),
             checkpoints: RefCell::new(vec![]),
+            created_at: self.created_at,
+            hits: self.hits.clone(),
+            misses: self.misses.clone(),
         }
     }
 }
@@ -123,9 +123,25 @@ impl StateDB {
         }
     }
 
+    /// The number of the items in the cache.
+    pub fn cache_entries(&self) -> usize {
+        self.cache.len()
+    }
+
     pub fn clear_cache(&mut self) {
         self.cache.clear();
     }
+
+    /// Adds the items that `state` has read to the cache, and returns how many are added.
+    /// Nothing is added if the cache already follows another state.
+    pub fn warm_cache(&mut self, state: &TopLevelState) -> usize {
+        match self.current_hash {
+            Some(hash) if hash != state.root() => return 0,
+            _ => {}
+        }
+        self.current_hash = Some(state.root());
+        self.cache.warm(state.top_cache())
+    }
 }
 
 impl AsHashDB for StateDB {
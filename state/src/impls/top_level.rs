@@ -40,7 +40,7 @@ use std::collections::HashMap;
 
 use ccrypto::BLAKE_NULL_RLP;
 use ckey::{public_to_address, recover, verify_address, Address, NetworkId, Public, Signature};
-use cmerkle::{Result as TrieResult, TrieError, TrieFactory};
+use cmerkle::{Result as TrieResult, Trie, TrieError, TrieFactory};
 use ctypes::errors::RuntimeError;
 use ctypes::transaction::{
     Action, AssetOutPoint, AssetTransferInput, AssetWrapCCCOutput, ShardTransaction, Transaction,
@@ -55,14 +55,14 @@ use primitives::H160;
 use primitives::{Bytes, H256};
 use util_error::UtilError;
 
-use crate::cache::{ShardCache, TopCache};
+use crate::cache::{CacheStats, HotKey, ShardCache, TopCache};
 use crate::checkpoint::{CheckpointId, StateWithCheckpoint};
 use crate::traits::{ShardState, ShardStateView, StateWithCache, TopState, TopStateView};
 #[cfg(test)]
 use crate::Asset;
 use crate::{
-    Account, ActionData, FindActionHandler, Metadata, MetadataAddress, RegularAccount, RegularAccountAddress, Shard,
-    ShardAddress, ShardLevelState, StateDB, StateResult, Text,
+    Account, ActionData, ActionDataAccess, FindActionHandler, Metadata, MetadataAddress, RegularAccount,
+    RegularAccountAddress, Shard, ShardAddress, ShardLevelState, StateDB, StateResult, Text,
 };
 
 /// Representation of the entire state of all accounts in the system.
@@ -94,6 +94,8 @@ pub struct TopLevelState {
     top_cache: TopCache,
     shard_caches: HashMap<ShardId, ShardCache>,
     id_of_checkpoints: Vec<CheckpointId>,
+    /// The accesses to the action data, recorded only while the state is traced.
+    action_data_trace: RefCell<Option<Vec<ActionDataAccess>>>,
 }
 
 impl TopStateView for TopLevelState {
@@ -145,9 +147,14 @@ impl TopStateView for TopLevelState {
     }
 
     fn action_data(&self, key: &H256) -> TrieResult<Option<ActionData>> {
-        let db = self.db.borrow();
-        let trie = TrieFactory::readonly(db.as_hashdb(), &self.root)?;
-        Ok(self.top_cache.action_data(key, &trie)?.map(Into::into))
+        let action_data = self.untraced_action_data(key)?;
+        if let Some(trace) = self.action_data_trace.borrow_mut().as_mut() {
+            trace.push(ActionDataAccess::Read {
+                key: *key,
+                value: action_data.clone().map(Vec::from),
+            });
+        }
+        Ok(action_data)
     }
 }
 
@@ -244,6 +251,7 @@ impl TopLevelState {
             top_cache,
             shard_caches,
             id_of_checkpoints: Default::default(),
+            action_data_trace: Default::default(),
         };
 
         Ok(state)
@@ -282,8 +290,44 @@ impl TopLevelState {
         result
     }
 
-    // Change the public to an owner address if it is a regular key.
-    fn public_to_owner_address(&self, public: &Public) -> StateResult<Address> {
+    /// Execute a given transaction as if the fee payer sent it, without resolving the fee payer from the signer.
+    /// `signer_public` is passed only to the actions that use it: SetRegularKey and the custom actions.
+    /// This will change the state accordingly.
+    pub fn apply_as<C: ChainTimeInfo + FindActionHandler>(
+        &mut self,
+        tx: &Transaction,
+        signed_hash: &H256,
+        fee_payer: &Address,
+        signer_public: &Public,
+        client: &C,
+        parent_block_number: BlockNumber,
+        parent_block_timestamp: u64,
+        current_block_timestamp: u64,
+    ) -> StateResult<()> {
+        self.create_checkpoint(FEE_CHECKPOINT);
+        let result = self.apply_with_fee_payer(
+            tx,
+            signed_hash,
+            fee_payer,
+            signer_public,
+            client,
+            parent_block_number,
+            parent_block_timestamp,
+            current_block_timestamp,
+        );
+        match result {
+            Ok(()) => {
+                self.discard_checkpoint(FEE_CHECKPOINT);
+            }
+            Err(_) => {
+                self.revert_to_checkpoint(FEE_CHECKPOINT);
+            }
+        }
+        result
+    }
+
+    /// Change the public to an owner address if it is a regular key.
+    pub fn public_to_owner_address(&self, public: &Public) -> StateResult<Address> {
         Ok(if self.regular_account_exists_and_not_null(public)? {
             let regular_account = self.get_regular_account_mut(public)?;
             public_to_address(&regular_account.owner_public())
@@ -316,7 +360,30 @@ impl TopLevelState {
             }
             address
         };
-        let seq = self.seq(&fee_payer)?;
+        self.apply_with_fee_payer(
+            tx,
+            signed_hash,
+            &fee_payer,
+            signer_public,
+            client,
+            parent_block_number,
+            parent_block_timestamp,
+            current_block_timestamp,
+        )
+    }
+
+    fn apply_with_fee_payer<C: ChainTimeInfo + FindActionHandler>(
+        &mut self,
+        tx: &Transaction,
+        signed_hash: &H256,
+        fee_payer: &Address,
+        signer_public: &Public,
+        client: &C,
+        parent_block_number: BlockNumber,
+        parent_block_timestamp: u64,
+        current_block_timestamp: u64,
+    ) -> StateResult<()> {
+        let seq = self.seq(fee_payer)?;
 
         if tx.seq != seq {
             return Err(RuntimeError::InvalidSeq(Mismatch {
@@ -328,8 +395,8 @@ impl TopLevelState {
 
         let fee = tx.fee;
 
-        self.inc_seq(&fee_payer)?;
-        self.sub_balance(&fee_payer, fee)?;
+        self.inc_seq(fee_payer)?;
+        self.sub_balance(fee_payer, fee)?;
 
         // The failed transaction also must pay the fee and increase seq.
         self.create_checkpoint(ACTION_CHECKPOINT);
@@ -338,7 +405,7 @@ impl TopLevelState {
             tx.network_id,
             tx.hash(),
             signed_hash,
-            &fee_payer,
+            fee_payer,
             signer_public,
             client,
             parent_block_number,
@@ -553,7 +620,10 @@ impl TopLevelState {
                 handler_id,
                 bytes,
             } => {
-                let handler = client.find_action_handler_for(*handler_id).expect("Unknown custom parsel applied!");
+                // The handler can be missing only if it is disabled on purpose, e.g. while replaying blocks.
+                let handler = client.find_action_handler_for(*handler_id).ok_or_else(|| {
+                    RuntimeError::FailedToHandleCustomAction(format!("The handler {} is disabled", handler_id))
+                })?;
                 handler.execute(bytes, self, fee_payer, signer_public)?;
                 return Ok(())
             }
@@ -684,6 +754,36 @@ impl TopLevelState {
         self.top_cache.action_data_mut(key, &trie)
     }
 
+    fn untraced_action_data(&self, key: &H256) -> TrieResult<Option<ActionData>> {
+        let db = self.db.borrow();
+        let trie = TrieFactory::readonly(db.as_hashdb(), &self.root)?;
+        Ok(self.top_cache.action_data(key, &trie)?.map(Into::into))
+    }
+
+    /// Records the reads and the writes of the action data until `take_action_data_trace` is called.
+    pub fn trace_action_data(&self) {
+        *self.action_data_trace.borrow_mut() = Some(Vec::new());
+    }
+
+    /// Stops tracing and returns the accesses in the order they happened.
+    pub fn take_action_data_trace(&self) -> Vec<ActionDataAccess> {
+        self.action_data_trace.borrow_mut().take().unwrap_or_default()
+    }
+
+    fn record_action_data_write(&self, key: &H256, new: Option<Bytes>) {
+        if self.action_data_trace.borrow().is_none() {
+            return
+        }
+        let old = self.untraced_action_data(key).ok().and_then(|old| old.map(Vec::from));
+        if let Some(trace) = self.action_data_trace.borrow_mut().as_mut() {
+            trace.push(ActionDataAccess::Write {
+                key: *key,
+                old,
+                new,
+            });
+        }
+    }
+
     pub fn journal_under(&self, batch: &mut DBTransaction, now: u64) -> Result<u32, UtilError> {
         self.db.borrow_mut().journal_under(batch, now, self.root)
     }
@@ -699,6 +799,35 @@ impl TopLevelState {
         self.root
     }
 
+    /// Returns the metadata at the root, without the changes after the last commit.
+    /// While a block is being executed, it's the metadata of the parent block.
+    pub fn committed_metadata(&self) -> TrieResult<Option<Metadata>> {
+        let db = self.db.borrow();
+        let trie = TrieFactory::readonly(db.as_hashdb(), &self.root)?;
+        trie.get_with(MetadataAddress::new().as_ref(), ::rlp::decode::<Metadata>)
+    }
+
+    /// Returns the keys that are read or written since this state is created.
+    pub fn touched_keys(&self) -> Vec<HotKey> {
+        self.top_cache.touched_keys()
+    }
+
+    /// Reads the item of the key into the cache.
+    pub fn load_hot_key(&self, key: &HotKey) -> TrieResult<()> {
+        let db = self.db.borrow();
+        let trie = TrieFactory::readonly(db.as_hashdb(), &self.root)?;
+        match key {
+            HotKey::Account(address) => self.top_cache.account(address, &trie).map(|_| ()),
+            HotKey::RegularAccount(address) => self.top_cache.regular_account(address, &trie).map(|_| ()),
+            HotKey::Shard(address) => self.top_cache.shard(address, &trie).map(|_| ()),
+            HotKey::ActionData(key) => self.top_cache.action_data(key, &trie).map(|_| ()),
+        }
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        self.top_cache.stats()
+    }
+
     #[cfg(test)]
     fn set_balance(&mut self, a: &Address, balance: u64) -> TrieResult<()> {
         self.get_account_mut(a)?.set_balance(balance);
@@ -799,6 +928,7 @@ impl Clone for TopLevelState {
             id_of_checkpoints: self.id_of_checkpoints.clone(),
             top_cache: self.top_cache.clone(),
             shard_caches: self.shard_caches.clone(),
+            action_data_trace: Default::default(),
         }
     }
 }
@@ -983,12 +1113,14 @@ impl TopState for TopLevelState {
     }
 
     fn update_action_data(&mut self, key: &H256, data: Bytes) -> StateResult<()> {
+        self.record_action_data_write(key, Some(data.clone()));
         let mut action_data = self.get_action_data_mut(key)?;
         *action_data = data.into();
         Ok(())
     }
 
     fn remove_action_data(&mut self, key: &H256) {
+        self.record_action_data_write(key, None);
         self.top_cache.remove_action_data(key)
     }
 
@@ -1145,6 +1277,94 @@ mod tests_state {
         assert_eq!(Ok(1), state.seq(&a));
     }
 
+    #[test]
+    fn touched_keys_are_the_ones_read_or_written() {
+        let memory_db = get_memory_db();
+        let jorunal = journaldb::new(Arc::clone(&memory_db), Algorithm::Archive, Some(0));
+        let mut db = StateDB::new(jorunal.boxed_clone());
+        let a = Address::random();
+        let b = Address::random();
+        let key = H256::random();
+        let root = {
+            let mut state = empty_top_state(StateDB::new(jorunal));
+            assert_eq!(Ok(()), state.add_balance(&a, 69));
+            assert_eq!(Ok(0), state.balance(&b));
+            assert_eq!(Ok(None), state.action_data(&key));
+            assert_eq!(vec![HotKey::Account(a), HotKey::Account(b), HotKey::ActionData(key)], state.touched_keys());
+            let root = state.commit().unwrap();
+
+            let mut transaction = memory_db.transaction();
+            state.journal_under(&mut transaction, 1).unwrap();
+            memory_db.write_buffered(transaction);
+
+            db.override_state(&state);
+            root
+        };
+
+        // The items copied from the global cache are not touched yet.
+        let state = TopLevelState::from_existing(db, root).unwrap();
+        assert_eq!(Vec::<HotKey>::new(), state.touched_keys());
+        assert_eq!(Ok(69), state.balance(&a));
+        assert_eq!(vec![HotKey::Account(a)], state.touched_keys());
+    }
+
+    #[test]
+    fn lookups_hit_the_cache_after_warming() {
+        let memory_db = get_memory_db();
+        let jorunal = journaldb::new(Arc::clone(&memory_db), Algorithm::Archive, Some(0));
+        let a = Address::random();
+        let root = {
+            let mut state = empty_top_state(StateDB::new(jorunal.boxed_clone()));
+            assert_eq!(Ok(()), state.add_balance(&a, 69));
+            let root = state.commit().unwrap();
+
+            let mut transaction = memory_db.transaction();
+            state.journal_under(&mut transaction, 1).unwrap();
+            memory_db.write_buffered(transaction);
+            root
+        };
+
+        let cold = TopLevelState::from_existing(StateDB::new(jorunal.boxed_clone()), root).unwrap();
+        assert_eq!(Ok(69), cold.balance(&a));
+        assert_eq!(
+            CacheStats {
+                hits: 0,
+                misses: 1,
+            },
+            cold.cache_stats()
+        );
+
+        let mut db = StateDB::new(jorunal);
+        let warming = TopLevelState::from_existing(db.clone(&root), root).unwrap();
+        assert_eq!(Ok(()), warming.load_hot_key(&HotKey::Account(a)));
+        assert_eq!(1, db.warm_cache(&warming));
+
+        let warm = TopLevelState::from_existing(db.clone(&root), root).unwrap();
+        assert_eq!(Ok(69), warm.balance(&a));
+        assert_eq!(
+            CacheStats {
+                hits: 1,
+                misses: 0,
+            },
+            warm.cache_stats()
+        );
+    }
+
+    #[test]
+    fn cache_following_another_state_is_not_warmed() {
+        let a = Address::random();
+        let mut db = get_temp_state_db();
+        let mut state = empty_top_state(db.clone(&H256::zero()));
+        assert_eq!(Ok(()), state.add_balance(&a, 69));
+        let root = state.commit().unwrap();
+        db.override_state(&state);
+
+        let other = empty_top_state(get_temp_state_db());
+        assert_eq!(Ok(0), other.balance(&a));
+        assert_ne!(root, other.root());
+        assert_eq!(0, db.warm_cache(&other));
+    }
+
     #[test]
     fn remove() {
         let a = Address::default();
@@ -48,7 +48,8 @@ mod traits;
 
 pub mod tests;
 
-pub use crate::action_handler::{ActionDataKeyBuilder, ActionHandler, FindActionHandler, HitHandler};
+pub use crate::action_handler::{ActionDataAccess, ActionDataKeyBuilder, ActionHandler, FindActionHandler, HitHandler};
+pub use crate::cache::{CacheStats, HotKey};
 pub use crate::checkpoint::{CheckpointId, StateWithCheckpoint};
 pub use crate::db::StateDB;
 pub use crate::error::Error as StateError;
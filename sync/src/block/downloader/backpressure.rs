@@ -0,0 +1,164 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use ccore::BlockQueueInfo;
+
+/// The body requests are paused when the import queue uses this percentage of its limits.
+const HIGH_WATER_MARK_PERCENT: usize = 50;
+/// The paused body requests are resumed when the import queue uses less than this percentage of its limits.
+const LOW_WATER_MARK_PERCENT: usize = 25;
+
+/// Stops the body download while the import queue can't keep up with it.
+///
+/// It pauses the whole download, not a peer, so the peers that don't get requests are not regarded as stalled.
+#[derive(Default)]
+pub struct ImportBackpressure {
+    paused: bool,
+}
+
+impl ImportBackpressure {
+    /// Updates the state with the current import queue, and returns whether the body download is paused.
+    pub fn update(&mut self, queue: &BlockQueueInfo) -> bool {
+        let depth = queue.total_queue_size();
+        if self.paused {
+            if depth < percent_of(queue.max_queue_size, LOW_WATER_MARK_PERCENT)
+                && queue.mem_used < percent_of(queue.max_mem_use, LOW_WATER_MARK_PERCENT)
+            {
+                cinfo!(
+                    SYNC,
+                    "Resume the body download: {} blocks({} bytes) in the import queue",
+                    depth,
+                    queue.mem_used
+                );
+                self.paused = false;
+            }
+        } else if depth >= percent_of(queue.max_queue_size, HIGH_WATER_MARK_PERCENT)
+            || queue.mem_used >= percent_of(queue.max_mem_use, HIGH_WATER_MARK_PERCENT)
+        {
+            cinfo!(SYNC, "Pause the body download: {} blocks({} bytes) in the import queue", depth, queue.mem_used);
+            self.paused = true;
+        }
+        self.paused
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+}
+
+fn percent_of(value: usize, percent: usize) -> usize {
+    value / 100 * percent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_QUEUE_SIZE: usize = 1000;
+    const BLOCK_SIZE: usize = 1000;
+    /// A body response carries this number of bodies.
+    const BODIES_PER_RESPONSE: usize = 128;
+
+    /// The mock peer answers every request at once, and the importer imports `import_rate` blocks per tick.
+    struct SlowImporter {
+        queued: usize,
+        import_rate: usize,
+    }
+
+    impl SlowImporter {
+        fn queue_info(&self) -> BlockQueueInfo {
+            BlockQueueInfo {
+                unverified_queue_size: self.queued,
+                verified_queue_size: 0,
+                verifying_queue_size: 0,
+                max_queue_size: MAX_QUEUE_SIZE,
+                max_mem_use: MAX_QUEUE_SIZE * BLOCK_SIZE * 2,
+                mem_used: self.queued * BLOCK_SIZE,
+            }
+        }
+
+        /// Runs a tick of the download, and returns the number of the sent requests.
+        fn tick(&mut self, backpressure: &mut ImportBackpressure, peers: usize) -> usize {
+            let mut requests = 0;
+            for _ in 0..peers {
+                if backpressure.update(&self.queue_info()) {
+                    break
+                }
+                requests += 1;
+                self.queued += BODIES_PER_RESPONSE;
+            }
+            self.queued = self.queued.saturating_sub(self.import_rate);
+            requests
+        }
+    }
+
+    #[test]
+    fn requests_are_throttled_by_the_slow_importer() {
+        let mut backpressure = ImportBackpressure::default();
+        let mut importer = SlowImporter {
+            queued: 0,
+            import_rate: 10,
+        };
+        // The first tick fills the queue up to the high water mark at full speed.
+        assert_eq!(4, importer.tick(&mut backpressure, 4));
+        assert!(backpressure.is_paused());
+
+        // Then the requests follow the import rate, 10 blocks per tick, instead of 4 * 128 blocks per tick.
+        let requests: Vec<_> = (0..90).map(|_| importer.tick(&mut backpressure, 4)).collect();
+        let requested: usize = requests.iter().sum();
+        assert!(requested * BODIES_PER_RESPONSE <= 90 * 10 + 2 * BODIES_PER_RESPONSE, "{:?}", requests);
+        assert!(requested > 0, "The download must be resumed");
+        assert!(importer.queued < MAX_QUEUE_SIZE / 2 + BODIES_PER_RESPONSE, "{}", importer.queued);
+    }
+
+    #[test]
+    fn requests_are_resumed_below_the_low_water_mark() {
+        let mut backpressure = ImportBackpressure::default();
+        let mut importer = SlowImporter {
+            queued: 0,
+            import_rate: 0,
+        };
+        assert_eq!(4, importer.tick(&mut backpressure, 4));
+        assert_eq!(0, importer.tick(&mut backpressure, 4));
+        assert!(backpressure.is_paused());
+
+        // Still paused between the water marks.
+        importer.import_rate = MAX_QUEUE_SIZE / 4;
+        assert_eq!(0, importer.tick(&mut backpressure, 4));
+        assert!(importer.queued >= MAX_QUEUE_SIZE / 4);
+        assert_eq!(0, importer.tick(&mut backpressure, 4));
+
+        // The importer caught up.
+        assert!(importer.queued < MAX_QUEUE_SIZE / 4);
+        assert_eq!(4, importer.tick(&mut backpressure, 4));
+        assert!(!backpressure.is_paused());
+    }
+
+    #[test]
+    fn memory_use_alone_pauses_the_download() {
+        let mut backpressure = ImportBackpressure::default();
+        let queue = BlockQueueInfo {
+            unverified_queue_size: 1,
+            verified_queue_size: 0,
+            verifying_queue_size: 0,
+            max_queue_size: MAX_QUEUE_SIZE,
+            max_mem_use: 1000,
+            mem_used: 600,
+        };
+        assert!(backpressure.update(&queue));
+    }
+}
@@ -57,6 +57,11 @@ impl BodyDownloader {
         }
     }
 
+    /// The number of the bodies that are requested or downloaded, but not drained yet.
+    pub fn pending_bodies(&self) -> usize {
+        self.downloading.len() + self.downloaded.len()
+    }
+
     pub fn import_bodies(&mut self, hashes: Vec<H256>, bodies: Vec<Vec<UnverifiedTransaction>>) {
         for (hash, body) in hashes.into_iter().zip(bodies) {
             if self.downloading.remove(&hash) {
@@ -17,7 +17,7 @@
 use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use ccore::encoded::Header as EncodedHeader;
 use ccore::{
@@ -36,7 +36,8 @@ use rand::thread_rng;
 use rlp::{Encodable, UntrustedRlp};
 use token_generator::TokenGenerator;
 
-use super::downloader::{BodyDownloader, HeaderDownloader};
+use super::downloader::{BodyDownloader, HeaderDownloader, ImportBackpressure};
+use super::fork_monitor::{ForkMonitor, ForkMonitorConfig, ForkStatus, PeerView};
 use super::message::{Message, RequestMessage, ResponseMessage};
 
 const SYNC_TIMER_TOKEN: TimerToken = 0;
@@ -49,6 +50,9 @@ const SYNC_EXPIRE_REQUEST_INTERVAL: u64 = 15000;
 
 const SNAPSHOT_PERIOD: u64 = (1 << 14);
 
+// A body response carries up to 128 bodies.
+const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
 #[derive(Debug, PartialEq)]
 pub struct TokenInfo {
     node_id: NodeId,
@@ -60,6 +64,11 @@ pub struct Extension {
     connected_nodes: HashSet<NodeId>,
     header_downloaders: HashMap<NodeId, HeaderDownloader>,
     body_downloader: BodyDownloader,
+    /// The bodies removed by the repair of the database, and the total scores of their blocks.
+    /// They are written without importing the blocks again.
+    refetching: HashMap<H256, U256>,
+    import_backpressure: ImportBackpressure,
+    fork_monitor: Option<ForkMonitor>,
     tokens: HashMap<NodeId, TimerToken>,
     tokens_info: HashMap<TimerToken, TokenInfo>,
     token_generator: TokenGenerator,
@@ -69,7 +78,7 @@ pub struct Extension {
 }
 
 impl Extension {
-    pub fn new(client: Arc<Client>, api: Box<Api>) -> Extension {
+    pub fn new(client: Arc<Client>, api: Box<Api>, fork_monitor: Option<ForkMonitorConfig>) -> Extension {
         api.set_timer(SYNC_TIMER_TOKEN, Duration::from_millis(SYNC_TIMER_INTERVAL)).expect("Timer set succeeds");
 
         let mut header = client.best_header();
@@ -93,6 +102,9 @@ impl Extension {
             connected_nodes: Default::default(),
             header_downloaders: Default::default(),
             body_downloader,
+            refetching: Default::default(),
+            import_backpressure: Default::default(),
+            fork_monitor: fork_monitor.map(ForkMonitor::new),
             tokens: Default::default(),
             tokens_info: Default::default(),
             token_generator: TokenGenerator::new(SYNC_EXPIRE_TOKEN_BEGIN, SYNC_EXPIRE_TOKEN_END),
@@ -152,6 +164,48 @@ impl Extension {
         self.check_sync_variable();
     }
 
+    /// Requests bodies to the peers that have better blocks, or the blocks whose bodies are refetched,
+    /// unless the import queue is too deep.
+    fn send_body_requests(&mut self) {
+        if self.update_import_backpressure() {
+            return
+        }
+
+        let total_score = self.client.chain_info().best_proposal_score;
+        let mut peer_ids: Vec<_> = self.header_downloaders.keys().cloned().collect();
+        peer_ids.shuffle(&mut thread_rng());
+
+        for id in peer_ids {
+            let peer_score = if let Some(peer) = self.header_downloaders.get(&id) {
+                peer.total_score()
+            } else {
+                U256::zero()
+            };
+
+            // A peer whose chain is as good as the refetched block has its body, though it's not better than ours.
+            let has_refetching = self.refetching.values().any(|score| peer_score >= *score);
+            if peer_score > total_score || has_refetching {
+                // Every request makes the import queue deeper.
+                if self.update_import_backpressure() {
+                    return
+                }
+                self.send_body_request(&id);
+            }
+        }
+    }
+
+    /// Returns whether the body download is paused.
+    /// The bodies that are requested but not queued yet are counted, since they will be in the import queue soon.
+    fn update_import_backpressure(&mut self) -> bool {
+        let mut queue = self.client.queue_info();
+        queue.unverified_queue_size += self.body_downloader.pending_bodies();
+        let paused = self.import_backpressure.update(&queue);
+        if paused {
+            ctrace!(SYNC, "Body download is paused until the import queue is drained");
+        }
+        paused
+    }
+
     fn check_sync_variable(&self) {
         let mut has_error = false;
         for id in self.header_downloaders.keys() {
@@ -204,13 +258,17 @@ impl NetworkExtension<Event> for Extension {
         false
     }
 
+    fn max_message_size() -> usize {
+        MAX_MESSAGE_SIZE
+    }
+
     fn versions() -> &'static [u64] {
         const VERSIONS: &[u64] = &[0];
         &VERSIONS
     }
 
     fn on_node_added(&mut self, id: &NodeId, _version: u64) {
-        cinfo!(SYNC, "New peer detected #{}", id);
+        cinfo!(SYNC, { peer: id }, "New peer detected #{}", id);
         let chain_info = self.client.chain_info();
         self.api.send(
             id,
@@ -244,7 +302,7 @@ impl NetworkExtension<Event> for Extension {
 
     fn on_node_removed(&mut self, id: &NodeId) {
         if self.connected_nodes.remove(id) {
-            cinfo!(SYNC, "Peer removed #{}", id);
+            cinfo!(SYNC, { peer: id }, "Peer removed #{}", id);
 
             self.header_downloaders.remove(id);
 
@@ -287,7 +345,6 @@ impl NetworkExtension<Event> for Extension {
     fn on_timeout(&mut self, token: TimerToken) {
         match token {
             SYNC_TIMER_TOKEN => {
-                let best_proposal_score = self.client.chain_info().best_proposal_score;
                 let mut peer_ids: Vec<_> = self.header_downloaders.keys().cloned().collect();
                 peer_ids.shuffle(&mut thread_rng());
 
@@ -299,17 +356,8 @@ impl NetworkExtension<Event> for Extension {
                     }
                 }
 
-                for id in peer_ids {
-                    let peer_score = if let Some(peer) = self.header_downloaders.get(&id) {
-                        peer.total_score()
-                    } else {
-                        U256::zero()
-                    };
-
-                    if peer_score > best_proposal_score {
-                        self.send_body_request(&id);
-                    }
-                }
+                self.send_body_requests();
+                self.update_fork_status();
             }
             SYNC_EXPIRE_TOKEN_BEGIN...SYNC_EXPIRE_TOKEN_END => {
                 self.check_sync_variable();
@@ -354,6 +402,13 @@ impl NetworkExtension<Event> for Extension {
                     channel.send(*peer).unwrap();
                 }
             }
+            Event::IsBodyDownloadPaused(channel) => {
+                channel.send(self.import_backpressure.is_paused()).unwrap();
+            }
+            Event::GetForkStatus(channel) => {
+                let status = self.fork_monitor.as_ref().map(ForkMonitor::status).unwrap_or_default();
+                channel.send(status).unwrap();
+            }
             Event::NewHeaders {
                 imported,
                 enacted,
@@ -367,12 +422,19 @@ impl NetworkExtension<Event> for Extension {
             } => {
                 self.new_blocks(imported, invalid);
             }
+            Event::BodiesMissing(hashes) => {
+                self.bodies_missing(hashes);
+                self.send_body_requests();
+            }
         }
     }
 }
 
 pub enum Event {
     GetPeers(EventSender<NodeId>),
+    IsBodyDownloadPaused(EventSender<bool>),
+    /// The default status is sent if the fork monitor is disabled.
+    GetForkStatus(EventSender<ForkStatus>),
     NewHeaders {
         imported: Vec<H256>,
         enacted: Vec<H256>,
@@ -382,9 +444,37 @@ pub enum Event {
         imported: Vec<H256>,
         invalid: Vec<H256>,
     },
+    /// The bodies of the imported headers are missing from the database.
+    BodiesMissing(Vec<H256>),
 }
 
 impl Extension {
+    fn update_fork_status(&mut self) {
+        if self.fork_monitor.is_none() {
+            return
+        }
+        let chain_info = self.client.chain_info();
+        let peers: Vec<_> = self
+            .header_downloaders
+            .values()
+            .map(|downloader| PeerView {
+                total_score: downloader.total_score(),
+                on_our_chain: self.is_on_our_chain(downloader.best_hash(), chain_info.best_proposal_block_hash),
+            })
+            .collect();
+        if let Some(fork_monitor) = self.fork_monitor.as_mut() {
+            fork_monitor.update(chain_info.best_proposal_score, &peers, Instant::now());
+        }
+    }
+
+    fn is_on_our_chain(&self, hash: H256, best_proposal_hash: H256) -> bool {
+        if hash == best_proposal_hash {
+            return true
+        }
+        let number = self.client.block_number(&BlockId::Hash(hash));
+        number.and_then(|number| self.client.block_hash(&BlockId::Number(number))) == Some(hash)
+    }
+
     fn new_headers(&mut self, imported: Vec<H256>, enacted: Vec<H256>, retracted: Vec<H256>) {
         let peer_ids: Vec<_> = self.header_downloaders.keys().cloned().collect();
         for id in peer_ids {
@@ -415,10 +505,35 @@ impl Extension {
         self.body_downloader.remove_target(&retracted);
     }
 
+    fn bodies_missing(&mut self, hashes: Vec<H256>) {
+        for hash in hashes {
+            let header = match self.client.block_header(&BlockId::Hash(hash)) {
+                Some(header) => header,
+                None => continue,
+            };
+            let parent = match self.client.block_header(&BlockId::Hash(header.parent_hash())) {
+                Some(parent) => parent,
+                None => continue,
+            };
+            let total_score = match self.client.block_total_score(&BlockId::Hash(hash)) {
+                Some(total_score) => total_score,
+                None => continue,
+            };
+            cinfo!(SYNC, "Download the missing body of #{}({}) again", header.number(), hash);
+            self.body_downloader.add_target(&header.decode(), &parent.decode());
+            self.refetching.insert(hash, total_score);
+        }
+    }
+
     fn new_blocks(&mut self, imported: Vec<H256>, invalid: Vec<H256>) {
         self.body_downloader.remove_target(&imported);
         self.body_downloader.remove_target(&invalid);
 
+        // The imported blocks left the import queue. Resume the paused download without waiting for the timer.
+        if self.import_backpressure.is_paused() {
+            self.send_body_requests();
+        }
+
 
         let chain_info = self.client.chain_info();
 
@@ -595,7 +710,7 @@ impl Extension {
                         }
                     }
                     self.dismiss_request(from, id);
-                    self.on_body_response(hashes, bodies);
+                    self.on_body_response(from, hashes, bodies);
                     self.check_sync_variable();
                 }
                 _ => unimplemented!(),
@@ -708,12 +823,22 @@ impl Extension {
         }
     }
 
-    fn on_body_response(&mut self, hashes: Vec<H256>, bodies: Vec<Vec<UnverifiedTransaction>>) {
+    fn on_body_response(&mut self, from: &NodeId, hashes: Vec<H256>, bodies: Vec<Vec<UnverifiedTransaction>>) {
         ctrace!(SYNC, "Received body response with lenth({}) {:?}", hashes.len(), hashes);
         {
             self.body_downloader.import_bodies(hashes, bodies);
             let completed = self.body_downloader.drain();
+            let mut mismatched = Vec::new();
             for (hash, transactions) in completed {
+                if self.refetching.remove(&hash).is_some() {
+                    if self.client.restore_block_body(&hash, &transactions) {
+                        cinfo!(SYNC, "The missing body of {} is restored", hash);
+                    } else {
+                        cwarn!(SYNC, "The body of {} from {} doesn't match its header", hash, from);
+                        mismatched.push(hash);
+                    }
+                    continue
+                }
                 let header = self
                     .client
                     .block_header(&BlockId::Hash(hash))
@@ -724,7 +849,7 @@ impl Extension {
                     transactions,
                 };
                 cdebug!(SYNC, "Body download completed for #{}({})", block.header.number(), hash);
-                match self.client.import_block(block.rlp_bytes(&Seal::With)) {
+                match self.client.import_block_from(block.rlp_bytes(&Seal::With), *from) {
                     Err(BlockImportError::Import(ImportError::AlreadyInChain)) => {
                         cwarn!(SYNC, "Downloaded already existing block({})", hash)
                     }
@@ -736,23 +861,11 @@ impl Extension {
                     _ => {}
                 }
             }
+            // Another peer may have the right body.
+            self.bodies_missing(mismatched);
         }
 
-        let total_score = self.client.chain_info().best_proposal_score;
-        let mut peer_ids: Vec<_> = self.header_downloaders.keys().cloned().collect();
-        peer_ids.shuffle(&mut thread_rng());
-
-        for id in peer_ids {
-            let peer_score = if let Some(peer) = self.header_downloaders.get(&id) {
-                peer.total_score()
-            } else {
-                U256::zero()
-            };
-
-            if peer_score > total_score {
-                self.send_body_request(&id);
-            }
-        }
+        self.send_body_requests();
     }
 }
 
@@ -800,4 +913,167 @@ impl ChainNotify for BlockSyncSender {
             })
             .unwrap();
     }
+
+    fn bodies_missing(&self, hashes: Vec<H256>) {
+        self.0.send(Event::BodiesMissing(hashes)).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::thread;
+
+    use ccore::{RepairOptions, TendermintSimulation, COL_BODIES};
+    use cnetwork::NetworkExtensionResult;
+    use kvdb::DBTransaction;
+    use parking_lot::Mutex;
+    use primitives::Bytes;
+
+    use super::*;
+
+    /// Keeps the messages that the extension sends.
+    #[derive(Default)]
+    struct TestApi {
+        sent: Arc<Mutex<Vec<(NodeId, Arc<Bytes>)>>>,
+    }
+
+    impl Api for TestApi {
+        fn send(&self, node: &NodeId, message: Arc<Bytes>) {
+            self.sent.lock().push((*node, message));
+        }
+
+        fn set_timer(&self, _timer: TimerToken, _d: Duration) -> NetworkExtensionResult<()> {
+            Ok(())
+        }
+
+        fn set_timer_once(&self, _timer: TimerToken, _d: Duration) -> NetworkExtensionResult<()> {
+            Ok(())
+        }
+
+        fn clear_timer(&self, _timer: TimerToken) -> NetworkExtensionResult<()> {
+            Ok(())
+        }
+    }
+
+    /// Returns the extension on the client, and the messages that it sends.
+    fn extension(client: &Arc<Client>) -> (Extension, Arc<Mutex<Vec<(NodeId, Arc<Bytes>)>>>) {
+        let api = TestApi::default();
+        let sent = Arc::clone(&api.sent);
+        (Extension::new(Arc::clone(client), Box::new(api), None), sent)
+    }
+
+    fn peer(port: u16) -> NodeId {
+        NodeId::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port)
+    }
+
+    /// Connects the peer, which says that it has the same best block as the client.
+    fn add_synced_peer(extension: &mut Extension, client: &Client, peer: &NodeId) {
+        extension.on_node_added(peer, 0);
+        let chain_info = client.chain_info();
+        let status = Message::Status {
+            total_score: chain_info.best_proposal_score,
+            best_hash: chain_info.best_proposal_block_hash,
+            genesis_hash: chain_info.genesis_hash,
+        };
+        extension.on_message(peer, &status.rlp_bytes());
+    }
+
+    /// Takes the body requests sent so far.
+    fn take_body_requests(sent: &Mutex<Vec<(NodeId, Arc<Bytes>)>>) -> Vec<(NodeId, u64, Vec<H256>)> {
+        sent.lock()
+            .drain(..)
+            .filter_map(|(node, message)| match UntrustedRlp::new(&message).as_val() {
+                Ok(Message::Request(id, RequestMessage::Bodies(hashes))) => Some((node, id, hashes)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn body_removed_by_the_repair_is_downloaded_from_a_synced_peer() {
+        let mut simulation = TendermintSimulation::new(4);
+        assert!(simulation.run_until(1_000, |simulation| simulation.all_committed(3)));
+        let client = simulation.client(0);
+        let peer_client = simulation.client(1);
+        let hash = client.block_hash(&BlockId::Number(2)).unwrap();
+        let body = peer_client.block_body(&BlockId::Hash(hash)).unwrap().transactions();
+
+        let mut batch = DBTransaction::new();
+        batch.delete(COL_BODIES, &hash);
+        client.db().write(batch).unwrap();
+        let repair = RepairOptions {
+            delete_orphans: false,
+            refetch_bodies: true,
+        };
+        assert!(client.start_integrity_check(0, 3, Some(repair)));
+        let mut report = client.integrity_report().unwrap();
+        for _ in 0..100 {
+            if report.finished {
+                break
+            }
+            thread::sleep(Duration::from_millis(50));
+            report = client.integrity_report().unwrap();
+        }
+        assert_eq!(vec![hash], report.refetched_bodies);
+        assert!(client.block_body(&BlockId::Hash(hash)).is_none());
+
+        let (mut extension, sent) = extension(&client);
+        let peer = peer(3485);
+        add_synced_peer(&mut extension, &client, &peer);
+        extension.on_event(Event::BodiesMissing(report.refetched_bodies));
+
+        // The peer isn't better than this node, but it has the body.
+        let requests = take_body_requests(&sent);
+        assert_eq!(1, requests.len());
+        let (to, id, hashes) = requests.into_iter().next().unwrap();
+        assert_eq!(peer, to);
+        assert_eq!(vec![hash], hashes);
+
+        let response = Message::Response(id, ResponseMessage::Bodies(vec![body.clone()]));
+        extension.on_message(&peer, &response.rlp_bytes());
+        assert_eq!(Some(body), client.block_body(&BlockId::Hash(hash)).map(|body| body.transactions()));
+    }
+
+    #[test]
+    fn body_requests_stop_when_the_requested_bodies_would_fill_the_import_queue() {
+        // A body request asks up to 128 bodies.
+        const BODIES_PER_REQUEST: usize = 128;
+        let mut simulation = TendermintSimulation::new(4);
+        assert!(simulation.run_until(1_000, |simulation| simulation.all_committed(1)));
+        let client = simulation.client(0);
+        let queue = client.queue_info();
+        assert_eq!(0, queue.total_queue_size());
+        // The download is paused when the import queue is half full.
+        let high_water_mark = queue.max_queue_size / 100 * 50;
+        let expected_requests = (high_water_mark + BODIES_PER_REQUEST - 1) / BODIES_PER_REQUEST;
+
+        let (mut extension, sent) = extension(&client);
+        let peers: Vec<_> = (0..expected_requests as u16 + 2).map(|index| peer(3485 + index)).collect();
+        for peer in &peers {
+            add_synced_peer(&mut extension, &client, peer);
+        }
+        // The peers have every body that is refetched.
+        let score = client.chain_info().best_proposal_score;
+        let parent = Header::default();
+        for number in 1..=(peers.len() * BODIES_PER_REQUEST) as u64 {
+            let mut header = Header::default();
+            header.set_number(number);
+            extension.body_downloader.add_target(&header, &parent);
+            extension.refetching.insert(header.hash(), score);
+        }
+        take_body_requests(&sent);
+
+        extension.send_body_requests();
+        let requests = take_body_requests(&sent);
+        assert_eq!(expected_requests, requests.len());
+        assert!(requests.iter().all(|(_, _, hashes)| hashes.len() == BODIES_PER_REQUEST));
+        let requested: HashSet<_> = requests.iter().map(|(to, ..)| *to).collect();
+        assert_eq!(expected_requests, requested.len());
+        assert!(extension.import_backpressure.is_paused());
+
+        // Nothing is requested until the import queue is drained.
+        extension.send_body_requests();
+        assert!(take_body_requests(&sent).is_empty());
+    }
 }
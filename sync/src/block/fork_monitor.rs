@@ -0,0 +1,188 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use std::time::{Duration, Instant};
+
+use primitives::U256;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ForkMonitorConfig {
+    /// A peer is regarded as ahead of the node only if its total score exceeds ours by more than this.
+    pub score_margin: U256,
+    /// The detection is skipped while the node has fewer peers than this.
+    pub min_peers: usize,
+    /// The majority of the peers must disagree with the node for this long.
+    pub grace_period: Duration,
+}
+
+/// What a peer advertised in its last status message.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PeerView {
+    pub total_score: U256,
+    /// Whether the best block of the peer is in the canonical chain of the node.
+    pub on_our_chain: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ForkStatus {
+    /// The node is suspected to be on a minority fork.
+    pub suspect: bool,
+    pub disagreeing_peers: usize,
+    pub peers: usize,
+}
+
+/// Detects that the node is left behind or on a branch that the majority of its peers don't follow.
+///
+/// A peer disagrees with the node if it is ahead by more than the margin, or if its best block is not in our chain
+/// while its score is not lower than ours. The node becomes suspect when the majority of the peers keep disagreeing
+/// during the grace period, and it is cleared as soon as they don't.
+pub struct ForkMonitor {
+    config: ForkMonitorConfig,
+    disagreed_since: Option<Instant>,
+    status: ForkStatus,
+}
+
+impl ForkMonitor {
+    pub fn new(config: ForkMonitorConfig) -> Self {
+        Self {
+            config,
+            disagreed_since: None,
+            status: ForkStatus::default(),
+        }
+    }
+
+    pub fn update(&mut self, our_score: U256, peers: &[PeerView], now: Instant) -> ForkStatus {
+        let disagreeing_peers = peers
+            .iter()
+            .filter(|peer| {
+                peer.total_score > our_score.saturating_add(self.config.score_margin)
+                    || (!peer.on_our_chain && peer.total_score >= our_score)
+            })
+            .count();
+        let majority_disagrees = peers.len() >= self.config.min_peers && disagreeing_peers * 2 > peers.len();
+
+        let suspect = if majority_disagrees {
+            let since = *self.disagreed_since.get_or_insert(now);
+            now.duration_since(since) >= self.config.grace_period
+        } else {
+            self.disagreed_since = None;
+            false
+        };
+        if suspect && !self.status.suspect {
+            cwarn!(
+                SYNC,
+                "The node may be on a minority fork: {} of {} peers disagree with the best block",
+                disagreeing_peers,
+                peers.len()
+            );
+        } else if !suspect && self.status.suspect {
+            cinfo!(SYNC, "The node is back on the chain of the majority");
+        }
+        self.status = ForkStatus {
+            suspect,
+            disagreeing_peers,
+            peers: peers.len(),
+        };
+        self.status
+    }
+
+    pub fn status(&self) -> ForkStatus {
+        self.status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor() -> ForkMonitor {
+        ForkMonitor::new(ForkMonitorConfig {
+            score_margin: 10.into(),
+            min_peers: 3,
+            grace_period: Duration::from_secs(30),
+        })
+    }
+
+    fn peer(total_score: u64, on_our_chain: bool) -> PeerView {
+        PeerView {
+            total_score: total_score.into(),
+            on_our_chain,
+        }
+    }
+
+    #[test]
+    fn suspect_after_the_grace_period_and_cleared_on_recovery() {
+        let mut monitor = monitor();
+        let start = Instant::now();
+        let ahead = [peer(200, false), peer(200, false), peer(100, true)];
+
+        assert!(!monitor.update(100.into(), &ahead, start).suspect);
+        assert!(!monitor.update(100.into(), &ahead, start + Duration::from_secs(29)).suspect);
+        let status = monitor.update(100.into(), &ahead, start + Duration::from_secs(30));
+        assert_eq!(
+            ForkStatus {
+                suspect: true,
+                disagreeing_peers: 2,
+                peers: 3,
+            },
+            status
+        );
+        assert_eq!(status, monitor.status());
+
+        // The node caught up with the peers.
+        let caught_up = [peer(200, true), peer(200, true), peer(100, true)];
+        assert!(!monitor.update(200.into(), &caught_up, start + Duration::from_secs(31)).suspect);
+
+        // The grace period starts again.
+        assert!(!monitor.update(100.into(), &ahead, start + Duration::from_secs(32)).suspect);
+        assert!(monitor.update(100.into(), &ahead, start + Duration::from_secs(62)).suspect);
+    }
+
+    #[test]
+    fn divergent_branch_is_detected() {
+        let mut monitor = monitor();
+        let start = Instant::now();
+        let divergent = [peer(100, false), peer(105, false), peer(90, false)];
+        assert!(!monitor.update(100.into(), &divergent, start).suspect);
+        let status = monitor.update(100.into(), &divergent, start + Duration::from_secs(30));
+        assert!(status.suspect);
+        assert_eq!(2, status.disagreeing_peers);
+    }
+
+    #[test]
+    fn small_leads_and_minorities_are_tolerated() {
+        let mut monitor = monitor();
+        let start = Instant::now();
+        // The peers got a new block that the node hasn't imported yet.
+        let slightly_ahead = [peer(105, true), peer(110, true), peer(100, true)];
+        monitor.update(100.into(), &slightly_ahead, start);
+        assert!(!monitor.update(100.into(), &slightly_ahead, start + Duration::from_secs(60)).suspect);
+
+        let minority = [peer(200, false), peer(100, true), peer(100, true), peer(90, false)];
+        monitor.update(100.into(), &minority, start);
+        assert!(!monitor.update(100.into(), &minority, start + Duration::from_secs(60)).suspect);
+    }
+
+    #[test]
+    fn too_few_peers_are_not_a_quorum() {
+        let mut monitor = monitor();
+        let start = Instant::now();
+        let ahead = [peer(200, false), peer(200, false)];
+        monitor.update(100.into(), &ahead, start);
+        assert!(!monitor.update(100.into(), &ahead, start + Duration::from_secs(60)).suspect);
+    }
+}
@@ -16,6 +16,8 @@
 
 mod downloader;
 mod extension;
+mod fork_monitor;
 mod message;
 
 pub use self::extension::{BlockSyncSender, Event as BlockSyncEvent, Extension as BlockSyncExtension};
+pub use self::fork_monitor::{ForkMonitorConfig, ForkStatus};
@@ -17,6 +17,7 @@
 extern crate parking_lot;
 
 extern crate codechain_core as ccore;
+extern crate codechain_key as ckey;
 extern crate codechain_merkle as cmerkle;
 #[macro_use]
 extern crate codechain_logger as clogger;
@@ -47,12 +48,11 @@ extern crate trie_standardmap;
 extern crate util_error;
 
 mod block;
+mod light;
 mod snapshot;
 mod transaction;
 
-pub use crate::block::{BlockSyncEvent, BlockSyncExtension, BlockSyncSender};
+pub use crate::block::{BlockSyncEvent, BlockSyncExtension, BlockSyncSender, ForkMonitorConfig, ForkStatus};
+pub use crate::light::{LightChainStatus, LightClientEvent, LightClientExtension, LightServerExtension};
 pub use crate::snapshot::SnapshotService;
 pub use crate::transaction::TransactionSyncExtension;
-
-#[cfg(test)]
-extern crate codechain_key as ckey;
@@ -0,0 +1,401 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ccore::tendermint_light::finality_evidence;
+use ccore::{BlockFinality, BlockId, WeightedValidator};
+use ckey::Public;
+use cmerkle::verify_proof;
+use cnetwork::{Api, EventSender, NetworkExtension, NodeId};
+use ctimer::TimerToken;
+use ctypes::{BlockNumber, Header};
+use primitives::{Bytes, H256};
+use rand::prelude::SliceRandom;
+use rand::thread_rng;
+use rlp::{Encodable, UntrustedRlp};
+
+use super::header_chain::LightHeaderChain;
+use super::message::{Message, RequestMessage, ResponseMessage};
+use super::{EXTENSION_NAME, MAX_HEADERS, MAX_KEY_LENGTH};
+
+const SYNC_TIMER_TOKEN: TimerToken = 0;
+const SYNC_TIMER_INTERVAL: u64 = 1000;
+/// Unit: millisecond
+const REQUEST_EXPIRATION: u64 = 15000;
+
+/// The headers and the validators that the light client follows.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LightChainStatus {
+    pub best_number: BlockNumber,
+    pub best_hash: H256,
+    pub finalized_number: BlockNumber,
+    pub finalized_hash: H256,
+    pub term_id: u64,
+    /// The validators of the last known term, which sealed the last finalized block.
+    pub validators: Vec<WeightedValidator>,
+}
+
+pub enum Event {
+    GetStatus(EventSender<LightChainStatus>),
+    GetHeader(BlockId, EventSender<Option<Header>>),
+    GetBlockFinality(BlockId, EventSender<Option<BlockFinality>>),
+    /// Reads the value of the key in the state of a finalized block, with the proof that a full node sends.
+    GetStateValue {
+        block_number: BlockNumber,
+        key: Bytes,
+        sender: EventSender<Result<Option<Bytes>, String>>,
+    },
+}
+
+struct Peer {
+    best_number: BlockNumber,
+    /// The header or checkpoint request waiting for the response, and when it's sent.
+    request: Option<(u64, Instant)>,
+    /// The last headers of the peer didn't follow the best header, so the next ones start after the finalized header.
+    on_another_branch: bool,
+}
+
+struct StateQuery {
+    peer: NodeId,
+    state_root: H256,
+    key: Bytes,
+    sent_at: Instant,
+    sender: EventSender<Result<Option<Bytes>, String>>,
+}
+
+/// Follows the finalized headers with the help of the full nodes that serve the light clients.
+pub struct Extension {
+    chain: LightHeaderChain,
+    peers: HashMap<NodeId, Peer>,
+    state_queries: HashMap<u64, StateQuery>,
+    api: Box<Api>,
+    last_request: u64,
+}
+
+impl Extension {
+    pub fn new(genesis: Header, initial_validators: &[Public], api: Box<Api>) -> Self {
+        api.set_timer(SYNC_TIMER_TOKEN, Duration::from_millis(SYNC_TIMER_INTERVAL)).expect("Timer set succeeds");
+        cinfo!(LIGHT, "Light client initialized with {} initial validators", initial_validators.len());
+        Self {
+            chain: LightHeaderChain::new(genesis, initial_validators),
+            peers: Default::default(),
+            state_queries: Default::default(),
+            api,
+            last_request: Default::default(),
+        }
+    }
+
+    fn send(&mut self, id: &NodeId, request: RequestMessage) -> u64 {
+        let request_id = self.last_request;
+        self.last_request += 1;
+        self.api.send(id, Arc::new(Message::Request(request_id, request).rlp_bytes().into_vec()));
+        request_id
+    }
+
+    /// Sends the header or checkpoint request, which waits until the response or the expiration.
+    fn send_to_idle_peer(&mut self, id: &NodeId, request: RequestMessage) {
+        let request_id = self.send(id, request);
+        if let Some(peer) = self.peers.get_mut(id) {
+            debug_assert_eq!(None, peer.request);
+            peer.request = Some((request_id, Instant::now()));
+        }
+    }
+
+    fn idle_peer<F>(&self, is_eligible: F) -> Option<NodeId>
+    where
+        F: Fn(&Peer) -> bool, {
+        let candidates: Vec<_> = self
+            .peers
+            .iter()
+            .filter(|(_, peer)| peer.request.is_none() && is_eligible(peer))
+            .map(|(id, _)| *id)
+            .collect();
+        candidates.choose(&mut thread_rng()).cloned()
+    }
+
+    fn request_headers(&mut self) {
+        let best_number = self.chain.best_header().number();
+        if let Some(id) = self.idle_peer(|peer| peer.best_number > best_number) {
+            let start_number = if self.peers[&id].on_another_branch {
+                self.chain.finalized_header().number() + 1
+            } else {
+                best_number + 1
+            };
+            self.send_to_idle_peer(&id, RequestMessage::Headers {
+                start_number,
+                max_count: MAX_HEADERS,
+            });
+        }
+    }
+
+    fn request_checkpoint(&mut self) {
+        if let Some(number) = self.chain.next_checkpoint() {
+            // The peer needs the child of the block.
+            if let Some(id) = self.idle_peer(|peer| peer.best_number > number) {
+                self.send_to_idle_peer(&id, RequestMessage::Checkpoint(number));
+            }
+        }
+    }
+
+    fn expire_requests(&mut self) {
+        let expiration = Duration::from_millis(REQUEST_EXPIRATION);
+        for (id, peer) in &mut self.peers {
+            if peer.request.map_or(false, |(_, sent_at)| sent_at.elapsed() > expiration) {
+                cdebug!(LIGHT, "The request to {} expired", id);
+                peer.request = None;
+            }
+        }
+        let expired: Vec<_> = self
+            .state_queries
+            .iter()
+            .filter(|(_, query)| query.sent_at.elapsed() > expiration)
+            .map(|(request_id, _)| *request_id)
+            .collect();
+        for request_id in expired {
+            let query = self.state_queries.remove(&request_id).expect("The key is from the map");
+            let _ = query.sender.send(Err(format!("{} didn't send the proof in time", query.peer)));
+        }
+    }
+
+    /// Returns true if the response is the one that the peer is expected to send.
+    fn take_request(&mut self, id: &NodeId, request_id: u64) -> bool {
+        match self.peers.get_mut(id) {
+            Some(peer) if peer.request.map(|(expected, _)| expected) == Some(request_id) => {
+                peer.request = None;
+                true
+            }
+            _ => {
+                cdebug!(LIGHT, "Unexpected response from {}", id);
+                false
+            }
+        }
+    }
+
+    fn on_response(&mut self, id: &NodeId, request_id: u64, response: ResponseMessage) {
+        match response {
+            ResponseMessage::Headers(headers) => {
+                if self.take_request(id, request_id) {
+                    self.on_headers(id, &headers);
+                }
+            }
+            ResponseMessage::Checkpoint {
+                headers,
+                proof,
+            } => {
+                if !self.take_request(id, request_id) {
+                    return
+                }
+                if headers.len() != 2 {
+                    cdebug!(LIGHT, "{} doesn't have the checkpoint", id);
+                    return
+                }
+                match self.chain.import_checkpoint(&headers[0], &headers[1], &proof) {
+                    Ok(()) => cinfo!(LIGHT, "#{} ({}) is finalized", headers[0].number(), headers[0].hash()),
+                    Err(err) => cdebug!(LIGHT, "Cannot finalize #{} with {}: {}", headers[0].number(), id, err),
+                }
+            }
+            ResponseMessage::StateProof(proof) => match self.state_queries.remove(&request_id) {
+                Some(query) => {
+                    let value = verify_proof(&query.state_root, &query.key, &proof)
+                        .map_err(|err| format!("{} sent an invalid proof: {}", id, err));
+                    let _ = query.sender.send(value);
+                }
+                None => cdebug!(LIGHT, "Unexpected state proof from {}", id),
+            },
+        }
+    }
+
+    fn on_headers(&mut self, id: &NodeId, headers: &[Header]) {
+        let result = self.chain.import_headers(headers);
+        let peer = self.peers.get_mut(id).expect("The peer sent the response");
+        match result {
+            Ok(imported) => {
+                peer.on_another_branch = false;
+                if imported != 0 {
+                    cdebug!(LIGHT, "Imported {} headers from {}", imported, id);
+                }
+            }
+            Err(err) if !peer.on_another_branch => {
+                cdebug!(LIGHT, "Headers from {} don't follow the best header: {}", id, err);
+                peer.on_another_branch = true;
+            }
+            Err(err) => {
+                cinfo!(LIGHT, "Invalid headers from {}: {}", id, err);
+                self.api.report_misbehavior(id);
+            }
+        }
+    }
+
+    fn resolve(&self, block_id: BlockId) -> Option<&Header> {
+        match block_id {
+            BlockId::Hash(hash) => self.chain.header_by_hash(&hash),
+            BlockId::Number(number) => self.chain.header(number),
+            BlockId::Earliest => self.chain.header(0),
+            BlockId::Latest => Some(self.chain.best_header()),
+            BlockId::ParentOfLatest => {
+                self.chain.best_header().number().checked_sub(1).and_then(|n| self.chain.header(n))
+            }
+        }
+    }
+
+    fn status(&self) -> LightChainStatus {
+        let best = self.chain.best_header();
+        let finalized = self.chain.finalized_header();
+        let (term_id, term) = self.chain.last_term();
+        LightChainStatus {
+            best_number: best.number(),
+            best_hash: best.hash(),
+            finalized_number: finalized.number(),
+            finalized_hash: finalized.hash(),
+            term_id,
+            validators: term.validators.clone(),
+        }
+    }
+
+    fn block_finality(&self, block_id: BlockId) -> Option<BlockFinality> {
+        let header = self.resolve(block_id)?;
+        let finalized = self.chain.is_finalized(header.number());
+        Some(BlockFinality {
+            finalized,
+            canonical: true,
+            evidence: if finalized {
+                self.chain.header(header.number() + 1).and_then(finality_evidence)
+            } else {
+                None
+            },
+        })
+    }
+
+    fn query_state(
+        &mut self,
+        block_number: BlockNumber,
+        key: Bytes,
+        sender: EventSender<Result<Option<Bytes>, String>>,
+    ) {
+        if key.len() > MAX_KEY_LENGTH {
+            let _ = sender.send(Err(format!("The key is longer than {} bytes", MAX_KEY_LENGTH)));
+            return
+        }
+        if !self.chain.is_finalized(block_number) {
+            let _ = sender.send(Err(format!("#{} is not finalized yet", block_number)));
+            return
+        }
+        let header = self.chain.header(block_number).expect("The finalized headers are kept").clone();
+        let candidates: Vec<_> =
+            self.peers.iter().filter(|(_, peer)| peer.best_number >= block_number).map(|(id, _)| *id).collect();
+        let peer = match candidates.choose(&mut thread_rng()) {
+            Some(peer) => *peer,
+            None => {
+                let _ = sender.send(Err("There are no full nodes to ask".to_string()));
+                return
+            }
+        };
+        let request_id = self.send(&peer, RequestMessage::StateProof {
+            block_hash: header.hash(),
+            key: key.clone(),
+        });
+        self.state_queries.insert(request_id, StateQuery {
+            peer,
+            state_root: *header.state_root(),
+            key,
+            sent_at: Instant::now(),
+            sender,
+        });
+    }
+}
+
+impl NetworkExtension<Event> for Extension {
+    fn name() -> &'static str {
+        EXTENSION_NAME
+    }
+    fn need_encryption() -> bool {
+        false
+    }
+    fn versions() -> &'static [u64] {
+        const VERSIONS: &[u64] = &[0];
+        &VERSIONS
+    }
+
+    fn on_node_removed(&mut self, id: &NodeId) {
+        self.peers.remove(id);
+        let lost: Vec<_> = self
+            .state_queries
+            .iter()
+            .filter(|(_, query)| query.peer == *id)
+            .map(|(request_id, _)| *request_id)
+            .collect();
+        for request_id in lost {
+            let query = self.state_queries.remove(&request_id).expect("The key is from the map");
+            let _ = query.sender.send(Err(format!("{} is disconnected", id)));
+        }
+    }
+
+    fn on_message(&mut self, id: &NodeId, data: &[u8]) {
+        match UntrustedRlp::new(data).as_val() {
+            Ok(Message::Status {
+                best_number,
+                genesis_hash,
+                ..
+            }) => {
+                if genesis_hash != self.chain.genesis_hash() {
+                    cinfo!(LIGHT, "{} follows another genesis {}", id, genesis_hash);
+                    return
+                }
+                self.peers
+                    .entry(*id)
+                    .or_insert_with(|| Peer {
+                        best_number,
+                        request: None,
+                        on_another_branch: false,
+                    })
+                    .best_number = best_number;
+            }
+            Ok(Message::Response(request_id, response)) => self.on_response(id, request_id, response),
+            // The light clients don't serve anything.
+            Ok(Message::Request(..)) => {}
+            Err(err) => cinfo!(LIGHT, "Invalid message from {}: {}", id, err),
+        }
+    }
+
+    fn on_timeout(&mut self, token: TimerToken) {
+        debug_assert_eq!(SYNC_TIMER_TOKEN, token);
+        self.expire_requests();
+        self.request_headers();
+        self.request_checkpoint();
+    }
+
+    fn on_event(&mut self, event: Event) {
+        match event {
+            Event::GetStatus(sender) => {
+                let _ = sender.send(self.status());
+            }
+            Event::GetHeader(block_id, sender) => {
+                let _ = sender.send(self.resolve(block_id).cloned());
+            }
+            Event::GetBlockFinality(block_id, sender) => {
+                let _ = sender.send(self.block_finality(block_id));
+            }
+            Event::GetStateValue {
+                block_number,
+                key,
+                sender,
+            } => self.query_state(block_number, key, sender),
+        }
+    }
+}
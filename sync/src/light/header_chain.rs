@@ -0,0 +1,447 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use ccore::tendermint_light::{initial_snapshot, term_snapshot_from_proof, verify_finality};
+use ccore::WeightedValidator;
+use ckey::Public;
+use ctypes::{BlockNumber, Header};
+use primitives::{Bytes, H256};
+
+/// The validators of a term, and the first block that they seal.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TermValidators {
+    pub first_block: BlockNumber,
+    pub validators: Vec<WeightedValidator>,
+}
+
+/// The headers from the genesis that a light client follows, and the validators of the terms that it has proven.
+///
+/// The headers after the last finalized one are linked by the hashes only, so they can be replaced by another branch.
+/// A block is finalized with the precommits in the seal of its child, signed by the validators that the parent's
+/// state has. The state is trusted only if it has the validators of a known term, or if the parent closed the last
+/// known term and is finalized already. So every new term is proven by the term-closing block.
+pub struct LightHeaderChain {
+    headers: Vec<Header>,
+    terms: BTreeMap<u64, TermValidators>,
+    finalized: BlockNumber,
+    /// The checkpoint that has to be proven before the later ones.
+    wanted_checkpoint: Option<BlockNumber>,
+}
+
+impl LightHeaderChain {
+    pub fn new(genesis: Header, initial_validators: &[Public]) -> Self {
+        assert_eq!(0, genesis.number());
+        let mut terms = BTreeMap::new();
+        terms.insert(0, TermValidators {
+            first_block: 1,
+            validators: initial_snapshot(initial_validators),
+        });
+        Self {
+            headers: vec![genesis],
+            terms,
+            finalized: 0,
+            wanted_checkpoint: None,
+        }
+    }
+
+    pub fn genesis_hash(&self) -> H256 {
+        self.headers[0].hash()
+    }
+
+    pub fn best_header(&self) -> &Header {
+        self.headers.last().expect("The genesis is always there")
+    }
+
+    pub fn finalized_header(&self) -> &Header {
+        &self.headers[self.finalized as usize]
+    }
+
+    pub fn header(&self, number: BlockNumber) -> Option<&Header> {
+        self.headers.get(number as usize)
+    }
+
+    pub fn header_by_hash(&self, hash: &H256) -> Option<&Header> {
+        // The recent blocks are asked more often.
+        self.headers.iter().rev().find(|header| header.hash() == *hash)
+    }
+
+    pub fn is_finalized(&self, number: BlockNumber) -> bool {
+        number <= self.finalized
+    }
+
+    /// The last known term and its validators.
+    pub fn last_term(&self) -> (u64, &TermValidators) {
+        let (term_id, term) = self.terms.iter().next_back().expect("The term 0 is always there");
+        (*term_id, term)
+    }
+
+    /// The validators that seal the block, or None if the block is after the last finalized one.
+    pub fn validators_at(&self, number: BlockNumber) -> Option<&TermValidators> {
+        if number > self.finalized {
+            return None
+        }
+        self.terms.values().rev().find(|term| term.first_block <= number)
+    }
+
+    /// Appends the headers that follow one of the known headers, and returns how many are appended.
+    ///
+    /// The headers after the fork point are replaced, unless they are finalized.
+    pub fn import_headers(&mut self, headers: &[Header]) -> Result<usize, String> {
+        let first = match headers.first() {
+            Some(first) => first,
+            None => return Ok(0),
+        };
+        for pair in headers.windows(2) {
+            if pair[1].number() != pair[0].number() + 1 || pair[1].parent_hash() != &pair[0].hash() {
+                return Err(format!("#{} doesn't follow #{}", pair[1].number(), pair[0].number()))
+            }
+        }
+        if first.number() == 0 || first.number() > self.best_header().number() + 1 {
+            return Err(format!("#{} doesn't follow the known headers", first.number()))
+        }
+        let parent = &self.headers[first.number() as usize - 1];
+        if first.parent_hash() != &parent.hash() {
+            return Err(format!("#{} doesn't follow the known #{}", first.number(), parent.number()))
+        }
+        // Skip the ones that we have.
+        let known = headers
+            .iter()
+            .take_while(|header| self.header(header.number()).map(Header::hash) == Some(header.hash()))
+            .count();
+        let new_headers = &headers[known..];
+        let fork_point = match new_headers.first() {
+            Some(header) => header.number(),
+            None => return Ok(0),
+        };
+        if fork_point <= self.finalized {
+            return Err(format!("#{} conflicts with the finalized #{}", fork_point, self.finalized))
+        }
+        if self.best_header().number() >= fork_point {
+            // The shorter branch is not worth switching to.
+            if self.best_header().number() >= new_headers.last().unwrap().number() {
+                return Ok(0)
+            }
+            self.headers.truncate(fork_point as usize);
+            self.wanted_checkpoint = None;
+        }
+        self.headers.extend_from_slice(new_headers);
+        Ok(new_headers.len())
+    }
+
+    /// The block of which finality proof is to be requested next.
+    pub fn next_checkpoint(&self) -> Option<BlockNumber> {
+        if let Some(number) = self.wanted_checkpoint {
+            if number > self.finalized && number < self.best_header().number() {
+                return Some(number)
+            }
+        }
+        // The block needs the child of which seal has the precommits.
+        let candidate = self.best_header().number().checked_sub(1)?;
+        if candidate > self.finalized {
+            Some(candidate)
+        } else {
+            None
+        }
+    }
+
+    /// Finalizes the block with the seal of the child and the proof of the parent's state.
+    pub fn import_checkpoint(&mut self, header: &Header, child: &Header, proof: &[Bytes]) -> Result<(), String> {
+        let number = header.number();
+        if number == 0 || self.header(number).map(Header::hash) != Some(header.hash()) {
+            return Err(format!("#{} is not on the chain", number))
+        }
+        if number <= self.finalized {
+            return Ok(())
+        }
+        let parent = &self.headers[number as usize - 1];
+        let snapshot = term_snapshot_from_proof(parent.state_root(), proof)?;
+
+        // The block is after the finalized one, so it's in the last known term or in the next one.
+        let (last_term_id, last_term) = self.last_term();
+        let is_new_term = snapshot.term_id != last_term_id;
+        let validators = if !is_new_term {
+            match &snapshot.validators {
+                // The seal indexes the validators in the order of the parent's state, which changes every block.
+                Some(validators) => {
+                    if !is_in_term(validators, &last_term.validators) {
+                        return Err(format!("The validators of the term {} are different", snapshot.term_id))
+                    }
+                    validators.clone()
+                }
+                None => last_term.validators.clone(),
+            }
+        } else if snapshot.term_id > last_term_id + 1 {
+            // A term between them is not proven yet. Look for it in the earlier blocks.
+            self.wanted_checkpoint = Some((self.finalized + 1 + number) / 2);
+            return Err(format!("The term {} is not proven yet", last_term_id + 1))
+        } else if snapshot.term_id < last_term_id {
+            return Err(format!("#{} can't be in the term {}", number, snapshot.term_id))
+        } else {
+            let closed_by = snapshot.last_term_finished_block_num;
+            if closed_by < last_term.first_block {
+                return Err(format!("#{} can't close the term {}", closed_by, last_term_id))
+            }
+            if closed_by > self.finalized {
+                // The closing block is finalized by the validators of the last term.
+                self.wanted_checkpoint = Some(closed_by);
+                return Err(format!("#{} that closed the term {} is not finalized yet", closed_by, last_term_id))
+            }
+            if closed_by + 1 != number {
+                self.wanted_checkpoint = Some(closed_by + 1);
+                return Err(format!("The term {} is proven only by the state of #{}", snapshot.term_id, closed_by))
+            }
+            // The initial validators continue if the election didn't fill the list.
+            snapshot.validators.clone().unwrap_or_else(|| self.terms[&0].validators.clone())
+        };
+        verify_finality(header, child, &validators)?;
+
+        if is_new_term {
+            cinfo!(LIGHT, "The term {} begins at #{}", snapshot.term_id, number);
+            self.terms.insert(snapshot.term_id, TermValidators {
+                first_block: number,
+                validators,
+            });
+        }
+        self.finalized = number;
+        if self.wanted_checkpoint.map_or(false, |wanted| wanted <= number) {
+            self.wanted_checkpoint = None;
+        }
+        if self.header(number + 1).map(Header::hash) != Some(child.hash()) {
+            self.headers.truncate(number as usize + 1);
+            self.headers.push(child.clone());
+        }
+        Ok(())
+    }
+}
+
+/// Whether the validators are the ones of the term, in any order.
+///
+/// The weights reorder them every block, and the banned ones are removed in the middle of the term,
+/// but the delegations don't change until the next election.
+fn is_in_term(validators: &[WeightedValidator], term: &[WeightedValidator]) -> bool {
+    let mut remaining: Vec<&WeightedValidator> = term.iter().collect();
+    validators.iter().all(|validator| {
+        let position = remaining
+            .iter()
+            .position(|member| member.pubkey == validator.pubkey && member.delegation == validator.delegation);
+        match position {
+            Some(position) => {
+                remaining.swap_remove(position);
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ccore::tendermint_light::test_helpers::{seal_with_precommits, term_snapshot_proof};
+    use ckey::{Generator, KeyPair, Private, Random};
+
+    use super::*;
+
+    struct Fixture {
+        initial: Vec<KeyPair>,
+        elected: Vec<KeyPair>,
+        proof_of_term_0: Vec<Bytes>,
+        proof_of_term_1: Vec<Bytes>,
+        headers: Vec<Header>,
+    }
+
+    /// The block #1 closes the term 0. The initial validators seal the blocks till #2 and the elected ones after it.
+    fn fixture(length: usize) -> Fixture {
+        let initial: Vec<KeyPair> = (0..4).map(|_| Random.generate().unwrap()).collect();
+        let elected: Vec<KeyPair> = (0..3).map(|_| Random.generate().unwrap()).collect();
+        let stakes = vec![(*elected[0].public(), 100), (*elected[1].public(), 100), (*elected[2].public(), 50)];
+        let (root_of_term_0, proof_of_term_0) = term_snapshot_proof(None, &[]);
+        let (root_of_term_1, proof_of_term_1) = term_snapshot_proof(Some(1), &stakes);
+
+        let mut genesis = Header::new();
+        genesis.set_state_root(root_of_term_0);
+        let mut headers = vec![genesis];
+        for number in 1..length {
+            let parent = &headers[number - 1];
+            let mut header = parent.generate_child();
+            header.set_state_root(root_of_term_1);
+            let signers = if number <= 2 {
+                vec![(0, initial[0].private()), (1, initial[1].private()), (2, initial[2].private())]
+            } else {
+                vec![(0, elected[0].private()), (1, elected[1].private())]
+            };
+            if number > 1 {
+                header.set_seal(seal_with_precommits(parent, 0, &signers));
+            }
+            headers.push(header);
+        }
+        Fixture {
+            initial,
+            elected,
+            proof_of_term_0,
+            proof_of_term_1,
+            headers,
+        }
+    }
+
+    fn chain_of(fixture: &Fixture) -> LightHeaderChain {
+        let initial: Vec<_> = fixture.initial.iter().map(|key_pair| *key_pair.public()).collect();
+        let mut chain = LightHeaderChain::new(fixture.headers[0].clone(), &initial);
+        assert_eq!(Ok(fixture.headers.len() - 1), chain.import_headers(&fixture.headers[1..]));
+        chain
+    }
+
+    #[test]
+    fn finality_across_the_term_boundary() {
+        let fixture = fixture(6);
+        let headers = &fixture.headers;
+        let mut chain = chain_of(&fixture);
+        assert_eq!(5, chain.best_header().number());
+        assert_eq!(Some(4), chain.next_checkpoint());
+
+        // The block #4 is in the term 1 that is not proven yet.
+        assert!(chain.import_checkpoint(&headers[4], &headers[5], &fixture.proof_of_term_1).is_err());
+        assert_eq!(Some(1), chain.next_checkpoint());
+        assert_eq!(Ok(()), chain.import_checkpoint(&headers[1], &headers[2], &fixture.proof_of_term_0));
+        assert_eq!(1, chain.finalized_header().number());
+        assert_eq!(0, chain.last_term().0);
+
+        // The term 1 is proven by the state of the block #1 that closed the term 0.
+        assert_eq!(Some(4), chain.next_checkpoint());
+        assert!(chain.import_checkpoint(&headers[4], &headers[5], &fixture.proof_of_term_1).is_err());
+        assert_eq!(Some(2), chain.next_checkpoint());
+        assert_eq!(Ok(()), chain.import_checkpoint(&headers[2], &headers[3], &fixture.proof_of_term_1));
+        assert_eq!((1, 2), (chain.last_term().0, chain.last_term().1.first_block));
+        assert_eq!(Ok(()), chain.import_checkpoint(&headers[4], &headers[5], &fixture.proof_of_term_1));
+        assert_eq!(4, chain.finalized_header().number());
+        assert_eq!(None, chain.next_checkpoint());
+
+        let elected: Vec<_> = fixture.elected.iter().map(|key_pair| *key_pair.public()).collect();
+        let validators = |number| -> Vec<Public> {
+            chain.validators_at(number).unwrap().validators.iter().map(|validator| validator.pubkey).collect()
+        };
+        assert_eq!(elected, validators(4));
+        assert_eq!(elected, validators(2));
+        assert_ne!(elected, validators(1));
+        assert_eq!(None, chain.validators_at(5));
+    }
+
+    #[test]
+    fn previous_validators_cannot_finalize_the_new_term() {
+        let fixture = fixture(4);
+        let headers = &fixture.headers;
+        let mut chain = chain_of(&fixture);
+        assert_eq!(Ok(()), chain.import_checkpoint(&headers[1], &headers[2], &fixture.proof_of_term_0));
+
+        let mut forged = headers[2].generate_child();
+        forged.set_seal(seal_with_precommits(&headers[2], 0, &[
+            (0, fixture.initial[0].private()),
+            (1, fixture.initial[1].private()),
+            (2, fixture.initial[2].private()),
+        ]));
+        assert!(chain.import_checkpoint(&headers[2], &forged, &fixture.proof_of_term_1).is_err());
+        // The state of the block #1 doesn't show the term 0.
+        assert!(chain.import_checkpoint(&headers[2], &forged, &fixture.proof_of_term_0).is_err());
+        assert_eq!(Ok(()), chain.import_checkpoint(&headers[2], &headers[3], &fixture.proof_of_term_1));
+    }
+
+    #[test]
+    fn validators_reordered_or_removed_in_the_term_finalize_the_blocks() {
+        let initial: Vec<KeyPair> = (0..4).map(|_| Random.generate().unwrap()).collect();
+        let elected: Vec<KeyPair> = (0..3).map(|_| Random.generate().unwrap()).collect();
+        let stake = |index: usize, delegation| (*elected[index].public(), delegation);
+        let (root_of_term_0, proof_of_term_0) = term_snapshot_proof(None, &[]);
+        let (root_of_term_1, proof_of_term_1) =
+            term_snapshot_proof(Some(1), &[stake(0, 100), stake(1, 80), stake(2, 50)]);
+        // The block #2 changes the weights, and the block #3 bans the validator of the least delegation.
+        let (reordered_root, reordered_proof) =
+            term_snapshot_proof(Some(1), &[stake(1, 80), stake(0, 100), stake(2, 50)]);
+        let (removed_root, removed_proof) = term_snapshot_proof(Some(1), &[stake(1, 80), stake(0, 100)]);
+
+        let mut genesis = Header::new();
+        genesis.set_state_root(root_of_term_0);
+        let mut headers = vec![genesis];
+        let state_roots = [root_of_term_1, reordered_root, removed_root, removed_root, removed_root];
+        // The indices follow the state of the parent of the finalized block.
+        let signers: Vec<Vec<(usize, &Private)>> = vec![
+            vec![],
+            vec![(0, initial[0].private()), (1, initial[1].private()), (2, initial[2].private())],
+            vec![(0, elected[0].private()), (1, elected[1].private())],
+            vec![(0, elected[1].private()), (1, elected[0].private())],
+            vec![(0, elected[1].private()), (1, elected[0].private())],
+        ];
+        for (state_root, signers) in state_roots.iter().zip(&signers) {
+            let parent = headers.last().unwrap();
+            let mut header = parent.generate_child();
+            header.set_state_root(*state_root);
+            if !signers.is_empty() {
+                header.set_seal(seal_with_precommits(parent, 0, signers));
+            }
+            headers.push(header);
+        }
+
+        let mut chain = LightHeaderChain::new(
+            headers[0].clone(),
+            &initial.iter().map(|key_pair| *key_pair.public()).collect::<Vec<_>>(),
+        );
+        assert_eq!(Ok(5), chain.import_headers(&headers[1..]));
+        assert_eq!(Ok(()), chain.import_checkpoint(&headers[1], &headers[2], &proof_of_term_0));
+        assert_eq!(Ok(()), chain.import_checkpoint(&headers[2], &headers[3], &proof_of_term_1));
+        assert_eq!(Ok(()), chain.import_checkpoint(&headers[3], &headers[4], &reordered_proof));
+        assert_eq!(Ok(()), chain.import_checkpoint(&headers[4], &headers[5], &removed_proof));
+        assert_eq!(4, chain.finalized_header().number());
+        assert_eq!(1, chain.last_term().0);
+
+        let term = &chain.last_term().1.validators;
+        let snapshot = |proof: &[Bytes], root| term_snapshot_from_proof(root, proof).unwrap().validators.unwrap();
+        assert!(is_in_term(&snapshot(&reordered_proof, &reordered_root), term));
+        let (stranger_root, stranger_proof) = term_snapshot_proof(Some(1), &[stake(1, 80), (Public::random(), 100)]);
+        assert!(!is_in_term(&snapshot(&stranger_proof, &stranger_root), term));
+        let (changed_root, changed_proof) = term_snapshot_proof(Some(1), &[stake(1, 80), stake(0, 90)]);
+        assert!(!is_in_term(&snapshot(&changed_proof, &changed_root), term));
+        let (duplicated_root, duplicated_proof) = term_snapshot_proof(Some(1), &[stake(1, 80), stake(1, 80)]);
+        assert!(!is_in_term(&snapshot(&duplicated_proof, &duplicated_root), term));
+    }
+
+    #[test]
+    fn finalized_headers_are_not_replaced() {
+        let fixture = fixture(4);
+        let headers = &fixture.headers;
+        let mut chain = chain_of(&fixture);
+        assert_eq!(Ok(()), chain.import_checkpoint(&headers[1], &headers[2], &fixture.proof_of_term_0));
+
+        let mut branch = vec![headers[0].generate_child()];
+        branch[0].set_timestamp(10);
+        for _ in 0..4 {
+            let child = branch.last().unwrap().generate_child();
+            branch.push(child);
+        }
+        assert!(chain.import_headers(&branch).is_err());
+
+        // The headers after the finalized one follow the longer branch.
+        let mut branch = vec![headers[2].generate_child()];
+        branch[0].set_timestamp(10);
+        for _ in 0..2 {
+            let child = branch.last().unwrap().generate_child();
+            branch.push(child);
+        }
+        assert_eq!(Ok(3), chain.import_headers(&branch));
+        assert_eq!(branch[2].hash(), chain.best_header().hash());
+        assert_eq!(Some(&headers[2]), chain.header(2));
+        assert_eq!(Ok(0), chain.import_headers(&headers[1..]));
+    }
+}
@@ -0,0 +1,275 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ctypes::{BlockNumber, Header};
+use primitives::{Bytes, H256};
+use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
+
+const MESSAGE_ID_STATUS: u8 = 0x01;
+const MESSAGE_ID_GET_HEADERS: u8 = 0x02;
+const MESSAGE_ID_HEADERS: u8 = 0x03;
+const MESSAGE_ID_GET_CHECKPOINT: u8 = 0x04;
+const MESSAGE_ID_CHECKPOINT: u8 = 0x05;
+const MESSAGE_ID_GET_STATE_PROOF: u8 = 0x06;
+const MESSAGE_ID_STATE_PROOF: u8 = 0x07;
+
+#[derive(Debug, PartialEq)]
+pub enum Message {
+    Status {
+        best_number: BlockNumber,
+        best_hash: H256,
+        genesis_hash: H256,
+    },
+    Request(u64, RequestMessage),
+    Response(u64, ResponseMessage),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RequestMessage {
+    Headers {
+        start_number: BlockNumber,
+        max_count: u64,
+    },
+    /// The proof that the block is final.
+    Checkpoint(BlockNumber),
+    StateProof {
+        block_hash: H256,
+        key: Bytes,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ResponseMessage {
+    Headers(Vec<Header>),
+    /// The block, its child, and the trie nodes that prove the term and the validators in the state of its parent.
+    /// The headers are empty if the server doesn't have the child yet.
+    Checkpoint {
+        headers: Vec<Header>,
+        proof: Vec<Bytes>,
+    },
+    /// Empty if the server doesn't have the state.
+    StateProof(Vec<Bytes>),
+}
+
+impl RequestMessage {
+    fn message_id(&self) -> u8 {
+        match self {
+            RequestMessage::Headers {
+                ..
+            } => MESSAGE_ID_GET_HEADERS,
+            RequestMessage::Checkpoint(..) => MESSAGE_ID_GET_CHECKPOINT,
+            RequestMessage::StateProof {
+                ..
+            } => MESSAGE_ID_GET_STATE_PROOF,
+        }
+    }
+}
+
+impl ResponseMessage {
+    fn message_id(&self) -> u8 {
+        match self {
+            ResponseMessage::Headers(..) => MESSAGE_ID_HEADERS,
+            ResponseMessage::Checkpoint {
+                ..
+            } => MESSAGE_ID_CHECKPOINT,
+            ResponseMessage::StateProof(..) => MESSAGE_ID_STATE_PROOF,
+        }
+    }
+}
+
+impl Encodable for Message {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        match self {
+            Message::Status {
+                best_number,
+                best_hash,
+                genesis_hash,
+            } => {
+                s.begin_list(4);
+                s.append(&MESSAGE_ID_STATUS);
+                s.append(best_number);
+                s.append(best_hash);
+                s.append(genesis_hash);
+            }
+            Message::Request(request_id, request) => {
+                s.begin_list(3);
+                s.append(&request.message_id());
+                s.append(request_id);
+                match request {
+                    RequestMessage::Headers {
+                        start_number,
+                        max_count,
+                    } => {
+                        s.begin_list(2);
+                        s.append(start_number);
+                        s.append(max_count);
+                    }
+                    RequestMessage::Checkpoint(block_number) => {
+                        s.begin_list(1);
+                        s.append(block_number);
+                    }
+                    RequestMessage::StateProof {
+                        block_hash,
+                        key,
+                    } => {
+                        s.begin_list(2);
+                        s.append(block_hash);
+                        s.append(key);
+                    }
+                }
+            }
+            Message::Response(response_id, response) => {
+                s.begin_list(3);
+                s.append(&response.message_id());
+                s.append(response_id);
+                match response {
+                    ResponseMessage::Headers(headers) => {
+                        s.append_list(headers);
+                    }
+                    ResponseMessage::Checkpoint {
+                        headers,
+                        proof,
+                    } => {
+                        s.begin_list(2);
+                        s.append_list(headers);
+                        s.append_list::<Bytes, _>(proof);
+                    }
+                    ResponseMessage::StateProof(proof) => {
+                        s.append_list::<Bytes, _>(proof);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn check_item_count(rlp: &UntrustedRlp, expected: usize) -> Result<(), DecoderError> {
+    let got = rlp.item_count()?;
+    if got != expected {
+        return Err(DecoderError::RlpIncorrectListLen {
+            got,
+            expected,
+        })
+    }
+    Ok(())
+}
+
+impl Decodable for Message {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        let id = rlp.val_at(0)?;
+        if id == MESSAGE_ID_STATUS {
+            check_item_count(rlp, 4)?;
+            return Ok(Message::Status {
+                best_number: rlp.val_at(1)?,
+                best_hash: rlp.val_at(2)?,
+                genesis_hash: rlp.val_at(3)?,
+            })
+        }
+
+        check_item_count(rlp, 3)?;
+        let request_id = rlp.val_at(1)?;
+        let message = rlp.at(2)?;
+        match id {
+            MESSAGE_ID_GET_HEADERS => {
+                check_item_count(&message, 2)?;
+                Ok(Message::Request(request_id, RequestMessage::Headers {
+                    start_number: message.val_at(0)?,
+                    max_count: message.val_at(1)?,
+                }))
+            }
+            MESSAGE_ID_GET_CHECKPOINT => {
+                check_item_count(&message, 1)?;
+                Ok(Message::Request(request_id, RequestMessage::Checkpoint(message.val_at(0)?)))
+            }
+            MESSAGE_ID_GET_STATE_PROOF => {
+                check_item_count(&message, 2)?;
+                Ok(Message::Request(request_id, RequestMessage::StateProof {
+                    block_hash: message.val_at(0)?,
+                    key: message.val_at(1)?,
+                }))
+            }
+            MESSAGE_ID_HEADERS => Ok(Message::Response(request_id, ResponseMessage::Headers(message.as_list()?))),
+            MESSAGE_ID_CHECKPOINT => {
+                check_item_count(&message, 2)?;
+                Ok(Message::Response(request_id, ResponseMessage::Checkpoint {
+                    headers: message.list_at(0)?,
+                    proof: message.list_at(1)?,
+                }))
+            }
+            MESSAGE_ID_STATE_PROOF => {
+                Ok(Message::Response(request_id, ResponseMessage::StateProof(message.as_list()?)))
+            }
+            _ => Err(DecoderError::Custom("Unknown message id detected")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rlp::{self, rlp_encode_and_decode_test};
+
+    use super::*;
+
+    #[test]
+    fn status_message_rlp() {
+        rlp_encode_and_decode_test!(Message::Status {
+            best_number: 10,
+            best_hash: H256::random(),
+            genesis_hash: H256::random(),
+        });
+    }
+
+    #[test]
+    fn request_messages_rlp() {
+        let requests = vec![
+            RequestMessage::Headers {
+                start_number: 1,
+                max_count: 128,
+            },
+            RequestMessage::Checkpoint(10),
+            RequestMessage::StateProof {
+                block_hash: H256::random(),
+                key: vec![1, 2, 3],
+            },
+        ];
+        for (request_id, request) in requests.into_iter().enumerate() {
+            let message = Message::Request(request_id as u64, request);
+            assert_eq!(message, rlp::decode(&rlp::encode(&message)));
+        }
+    }
+
+    #[test]
+    fn response_messages_rlp() {
+        let mut header = Header::new();
+        header.set_number(3);
+        let responses = vec![
+            ResponseMessage::Headers(vec![header.clone(), header.generate_child()]),
+            ResponseMessage::Checkpoint {
+                headers: vec![header.clone(), header.generate_child()],
+                proof: vec![vec![0xc0], vec![1, 2]],
+            },
+            ResponseMessage::Checkpoint {
+                headers: vec![],
+                proof: vec![],
+            },
+            ResponseMessage::StateProof(vec![vec![3, 4]]),
+        ];
+        for (response_id, response) in responses.into_iter().enumerate() {
+            let message = Message::Response(response_id as u64, response);
+            assert_eq!(message, rlp::decode(&rlp::encode(&message)));
+        }
+    }
+}
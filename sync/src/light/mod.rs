@@ -0,0 +1,30 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod client;
+mod header_chain;
+mod message;
+mod server;
+
+pub use self::client::{Event as LightClientEvent, Extension as LightClientExtension, LightChainStatus};
+pub use self::server::Extension as LightServerExtension;
+
+/// The servers in the full nodes and the light clients talk through the extension of this name.
+const EXTENSION_NAME: &str = "light-sync";
+/// The number of the headers in a response.
+const MAX_HEADERS: u64 = 128;
+/// The keys of the state are the addresses or the hashes.
+const MAX_KEY_LENGTH: usize = 32;
@@ -0,0 +1,171 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ccore::tendermint_light::term_snapshot_keys;
+use ccore::{BlockChainTrait, BlockId, Client, StateInfo};
+use cnetwork::{Api, NetworkExtension, NodeId};
+use ctimer::TimerToken;
+use ctypes::{BlockNumber, Header};
+use never_type::Never;
+use primitives::{Bytes, H256};
+use rlp::{Encodable, UntrustedRlp};
+
+use super::message::{Message, RequestMessage, ResponseMessage};
+use super::{EXTENSION_NAME, MAX_HEADERS, MAX_KEY_LENGTH};
+
+const STATUS_TIMER_TOKEN: TimerToken = 0;
+const STATUS_TIMER_INTERVAL: u64 = 1000;
+
+/// Serves the headers, the finality proofs, and the state proofs to the light clients.
+pub struct Extension {
+    peers: HashSet<NodeId>,
+    client: Arc<Client>,
+    api: Box<Api>,
+    last_sent_best: H256,
+}
+
+impl Extension {
+    pub fn new(client: Arc<Client>, api: Box<Api>) -> Self {
+        api.set_timer(STATUS_TIMER_TOKEN, Duration::from_millis(STATUS_TIMER_INTERVAL)).expect("Timer set succeeds");
+        Self {
+            peers: Default::default(),
+            client,
+            api,
+            last_sent_best: Default::default(),
+        }
+    }
+
+    fn status(&self) -> Message {
+        let chain_info = self.client.chain_info();
+        Message::Status {
+            best_number: chain_info.best_block_number,
+            best_hash: chain_info.best_block_hash,
+            genesis_hash: chain_info.genesis_hash,
+        }
+    }
+
+    fn headers(&self, start_number: BlockNumber, max_count: u64) -> Vec<Header> {
+        (start_number..start_number.saturating_add(max_count.min(MAX_HEADERS)))
+            .map(|number| self.client.block_header(&BlockId::Number(number)))
+            .take_while(Option::is_some)
+            .map(|header| header.expect("take_while guarantees existence of item").decode())
+            .collect()
+    }
+
+    fn checkpoint(&self, block_number: BlockNumber) -> (Vec<Header>, Vec<Bytes>) {
+        if block_number == 0 {
+            return (Vec::new(), Vec::new())
+        }
+        let headers = self.headers(block_number, 2);
+        if headers.len() != 2 {
+            return (Vec::new(), Vec::new())
+        }
+        // The validators of the block are in the state of its parent.
+        let parent = BlockId::Hash(*headers[0].parent_hash());
+        let proofs: Option<Vec<_>> =
+            term_snapshot_keys().iter().map(|key| self.client.state_proof(parent, key)).collect();
+        match proofs {
+            Some(proofs) => {
+                let mut proof: Vec<_> = proofs.into_iter().flatten().collect();
+                proof.sort();
+                proof.dedup();
+                (headers, proof)
+            }
+            None => (Vec::new(), Vec::new()),
+        }
+    }
+
+    fn on_request(&self, id: &NodeId, request_id: u64, request: RequestMessage) {
+        let response = match request {
+            RequestMessage::Headers {
+                start_number,
+                max_count,
+            } => ResponseMessage::Headers(self.headers(start_number, max_count)),
+            RequestMessage::Checkpoint(block_number) => {
+                let (headers, proof) = self.checkpoint(block_number);
+                ResponseMessage::Checkpoint {
+                    headers,
+                    proof,
+                }
+            }
+            RequestMessage::StateProof {
+                block_hash,
+                key,
+            } => {
+                if key.len() > MAX_KEY_LENGTH {
+                    cinfo!(LIGHT, "{} requested the proof of a too long key", id);
+                    self.api.report_misbehavior(id);
+                    return
+                }
+                ResponseMessage::StateProof(
+                    self.client.state_proof(BlockId::Hash(block_hash), &key).unwrap_or_default(),
+                )
+            }
+        };
+        self.api.send(id, Arc::new(Message::Response(request_id, response).rlp_bytes().into_vec()));
+    }
+}
+
+impl NetworkExtension<Never> for Extension {
+    fn name() -> &'static str {
+        EXTENSION_NAME
+    }
+    fn need_encryption() -> bool {
+        false
+    }
+    fn versions() -> &'static [u64] {
+        const VERSIONS: &[u64] = &[0];
+        &VERSIONS
+    }
+
+    fn on_node_added(&mut self, id: &NodeId, _version: u64) {
+        self.peers.insert(*id);
+        self.api.send(id, Arc::new(self.status().rlp_bytes().into_vec()));
+    }
+
+    fn on_node_removed(&mut self, id: &NodeId) {
+        self.peers.remove(id);
+    }
+
+    fn on_message(&mut self, id: &NodeId, data: &[u8]) {
+        match UntrustedRlp::new(data).as_val() {
+            Ok(Message::Request(request_id, request)) => self.on_request(id, request_id, request),
+            // The statuses of the other full nodes are not needed.
+            Ok(Message::Status {
+                ..
+            }) => {}
+            Ok(Message::Response(..)) => cdebug!(LIGHT, "Unexpected response from {}", id),
+            Err(err) => cinfo!(LIGHT, "Invalid message from {}: {}", id, err),
+        }
+    }
+
+    fn on_timeout(&mut self, token: TimerToken) {
+        debug_assert_eq!(STATUS_TIMER_TOKEN, token);
+        let best_hash = self.client.chain_info().best_block_hash;
+        if best_hash == self.last_sent_best {
+            return
+        }
+        self.last_sent_best = best_hash;
+        let message = Arc::new(self.status().rlp_bytes().into_vec());
+        for id in &self.peers {
+            self.api.send(id, Arc::clone(&message));
+        }
+    }
+}
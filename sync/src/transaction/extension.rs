@@ -14,58 +14,57 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use ccore::{BlockChainClient, UnverifiedTransaction};
+use ccore::{BlockChainClient, RelayStats, SeenTransactions, UnverifiedTransaction};
 use cnetwork::{Api, NetworkExtension, NodeId};
 use ctimer::TimerToken;
 use never_type::Never;
 use primitives::H256;
 use rlp::{Encodable, UntrustedRlp};
 
-use super::message::Message;
+use super::gossip::{Gossip, Outgoing, ANNOUNCE_VERSION, MAX_IN_FLIGHT_FETCHES_PER_PEER};
+use super::message::{Announcement, Message};
+use super::relay::RelayQueue;
 
 const BROADCAST_TIMER_TOKEN: TimerToken = 0;
 const BROADCAST_TIMER_INTERVAL: u64 = 1000;
-const MAX_HISTORY_SIZE: usize = 100_000;
-
-#[derive(Default)]
-struct KnownTxs {
-    history_set: HashSet<H256>,
-    history_queue: VecDeque<H256>,
-}
-
-impl KnownTxs {
-    fn push(&mut self, hash: H256) {
-        debug_assert!(!self.history_set.contains(&hash));
-        self.history_set.insert(hash);
-        self.history_queue.push_back(hash);
-        if self.history_queue.len() > MAX_HISTORY_SIZE {
-            self.history_queue.pop_front();
-        }
-    }
-
-    fn contains(&mut self, hash: &H256) -> bool {
-        self.history_set.contains(hash)
-    }
-}
+const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+/// The share of the bandwidth of the peer class that a broadcast round takes.
+/// The rest is left to the blocks and the consensus messages.
+const RELAY_BANDWIDTH_PERCENT: usize = 50;
 
 pub struct Extension {
-    known_txs: KnownTxs,
-    peers: HashMap<NodeId, KnownTxs>,
+    seen_txs: Arc<SeenTransactions>,
+    gossip: Gossip,
+    relay_queue: RelayQueue,
+    relay_stats: Arc<RelayStats>,
+    /// The ready transactions of the last broadcast round, to answer the requests of the announced ones.
+    bodies: HashMap<H256, UnverifiedTransaction>,
+    /// The number of the transactions that each peer announced again.
+    redundant_announcements: HashMap<NodeId, usize>,
     client: Arc<BlockChainClient>,
     api: Box<Api>,
 }
 
 impl Extension {
-    pub fn new(client: Arc<BlockChainClient>, api: Box<Api>) -> Self {
+    pub fn new(
+        client: Arc<BlockChainClient>,
+        seen_txs: Arc<SeenTransactions>,
+        relay_stats: Arc<RelayStats>,
+        api: Box<Api>,
+    ) -> Self {
         api.set_timer(BROADCAST_TIMER_TOKEN, Duration::from_millis(BROADCAST_TIMER_INTERVAL))
             .expect("Timer set succeeds");
         Extension {
-            known_txs: Default::default(),
-            peers: Default::default(),
+            seen_txs,
+            gossip: Default::default(),
+            relay_queue: Default::default(),
+            relay_stats,
+            bodies: Default::default(),
+            redundant_announcements: Default::default(),
             client,
             api,
         }
@@ -80,55 +79,81 @@ impl NetworkExtension<Never> for Extension {
         false
     }
 
+    fn max_message_size() -> usize {
+        MAX_MESSAGE_SIZE
+    }
+
     fn versions() -> &'static [u64] {
-        const VERSIONS: &[u64] = &[0];
+        const VERSIONS: &[u64] = &[0, ANNOUNCE_VERSION];
         &VERSIONS
     }
 
-    fn on_node_added(&mut self, token: &NodeId, _version: u64) {
-        self.peers.insert(*token, KnownTxs::default());
+    fn on_node_added(&mut self, token: &NodeId, version: u64) {
+        self.gossip.add_peer(*token, version);
+        self.redundant_announcements.insert(*token, 0);
     }
     fn on_node_removed(&mut self, token: &NodeId) {
-        self.peers.remove(token);
+        self.gossip.remove_peer(token);
+        if let Some(redundant) = self.redundant_announcements.remove(token) {
+            cdebug!(SYNC_TX, "{} announced {} known transactions", token, redundant);
+        }
+        self.request_wanted();
     }
 
     fn on_message(&mut self, token: &NodeId, data: &[u8]) {
         if let Ok(received_message) = UntrustedRlp::new(data).as_val() {
             match received_message {
                 Message::Transactions(transactions) => {
-                    let transactions: Vec<_> = {
-                        transactions
-                            .into_iter()
-                            .filter(|tx| {
-                                let hash = tx.hash();
-                                if self.known_txs.contains(&hash) {
-                                    false
-                                } else {
-                                    self.known_txs.push(hash);
-                                    true
-                                }
-                            })
-                            .collect()
-                    };
+                    let hashes: Vec<_> = transactions.iter().map(UnverifiedTransaction::hash).collect();
+                    // Drop the known transactions before the client verifies their signatures.
+                    let (transactions, redundant) = self.seen_txs.filter_unseen(transactions);
+                    if redundant != 0 {
+                        ctrace!(SYNC_TX, "{} announced {} known transactions", token, redundant);
+                        if let Some(count) = self.redundant_announcements.get_mut(token) {
+                            *count += redundant;
+                        }
+                    }
 
                     self.client.queue_transactions(
                         transactions.iter().map(|unverified| unverified.rlp_bytes().to_vec()).collect(),
                         *token,
                     );
-                    if let Some(peer) = self.peers.get_mut(token) {
-                        let transactions: Vec<_> = transactions
-                            .iter()
-                            .map(UnverifiedTransaction::hash)
-                            .filter(|tx_hash| !peer.contains(tx_hash))
-                            .collect();
-                        for unverified in transactions.iter() {
-                            peer.push(*unverified);
-                        }
+                    if let Some(transactions) = self.gossip.on_transactions(token, &hashes) {
                         cinfo!(SYNC_TX, "Receive {} transactions from {}", transactions.len(), token);
                         ctrace!(SYNC_TX, "Receive {:?}", transactions);
                     } else {
                         cwarn!(SYNC_TX, "Message from {} but it's already removed", token);
                     }
+                    self.request_wanted();
+                }
+                Message::Announcements(announcements) => {
+                    ctrace!(SYNC_TX, "{} announced {} transactions", token, announcements.len());
+                    let seen_txs = &self.seen_txs;
+                    let bodies = &self.bodies;
+                    self.gossip.on_announcements(token, &announcements, |hash| {
+                        bodies.contains_key(hash) || seen_txs.contains(hash)
+                    });
+                    self.request_wanted();
+                }
+                Message::Request(hashes) => {
+                    let transactions: Vec<_> = hashes
+                        .iter()
+                        .take(MAX_IN_FLIGHT_FETCHES_PER_PEER)
+                        .filter_map(|hash| self.bodies.get(hash))
+                        .cloned()
+                        .collect();
+                    if transactions.len() != hashes.len() {
+                        cdebug!(
+                            SYNC_TX,
+                            "{} requested {} transactions, but only {} of them are available",
+                            token,
+                            hashes.len(),
+                            transactions.len()
+                        );
+                    }
+                    if !transactions.is_empty() {
+                        self.api.send(token, Arc::new(Message::Transactions(transactions).rlp_bytes().into_vec()));
+                    }
                 }
             }
         } else {
@@ -138,7 +163,14 @@ impl NetworkExtension<Never> for Extension {
 
     fn on_timeout(&mut self, timer: TimerToken) {
         match timer {
-            BROADCAST_TIMER_TOKEN => self.random_broadcast(),
+            BROADCAST_TIMER_TOKEN => {
+                for token in self.gossip.expire(Instant::now()) {
+                    cwarn!(SYNC_TX, "{} doesn't send the transactions it announced", token);
+                    self.api.report_misbehavior(&token);
+                }
+                self.request_wanted();
+                self.random_broadcast();
+            }
             _ => unreachable!(),
         }
     }
@@ -147,26 +179,67 @@ impl NetworkExtension<Never> for Extension {
 impl Extension {
     fn random_broadcast(&mut self) {
         let transactions = self.client.ready_transactions(0..(::std::u64::MAX)).transactions;
-        if transactions.is_empty() {
+        self.bodies = transactions
+            .into_iter()
+            .map(|signed| {
+                let unverified = signed.deconstruct().0;
+                (unverified.hash(), unverified)
+            })
+            .collect();
+        let ready: Vec<_> = self
+            .bodies
+            .iter()
+            .map(|(hash, unverified)| Announcement {
+                hash: *hash,
+                size: unverified.rlp_bytes().len(),
+                fee: unverified.fee,
+            })
+            .collect();
+        let (ready, dropped) = self.relay_queue.next_round(ready);
+        if dropped != 0 {
+            cdebug!(SYNC_TX, "{} transactions left the mem pool before they were relayed to every peer", dropped);
+            self.relay_stats.add_dropped_stale(dropped);
+        }
+        if ready.is_empty() {
             ctrace!(SYNC_TX, "No transactions to propagate");
+            self.relay_stats.set_queued(0);
             return
         }
-        for (token, peer) in &mut self.peers {
-            let unsent: Vec<_> = transactions
-                .iter()
-                .filter(|tx| !peer.contains(&tx.hash()))
-                .map(|signed| signed.clone().deconstruct().0)
-                .collect();
-            if unsent.is_empty() {
-                continue
-            }
-            let unsent_hashes = unsent.iter().map(UnverifiedTransaction::hash).collect::<Vec<_>>();
-            for h in unsent_hashes.iter() {
-                peer.push(*h);
-            }
-            cinfo!(SYNC_TX, "Send {} transactions to {}", unsent.len(), token);
-            ctrace!(SYNC_TX, "Send {:?}", unsent_hashes);
-            self.api.send(token, Arc::new(Message::Transactions(unsent).rlp_bytes().into_vec()));
+        let api = &self.api;
+        for (token, outgoing) in self.gossip.broadcast(&ready, |node| relay_budget(api.as_ref(), node)) {
+            let message = match outgoing {
+                Outgoing::Bodies(hashes) => {
+                    self.relay_stats.add_sent(hashes.len());
+                    cinfo!(SYNC_TX, "Send {} transactions to {}", hashes.len(), token);
+                    ctrace!(SYNC_TX, "Send {:?}", hashes);
+                    Message::Transactions(hashes.iter().map(|hash| self.bodies[hash].clone()).collect())
+                }
+                Outgoing::Announcements(announcements) => {
+                    self.relay_stats.add_sent(announcements.len());
+                    cinfo!(SYNC_TX, "Announce {} transactions to {}", announcements.len(), token);
+                    Message::Announcements(announcements)
+                }
+            };
+            self.api.send(&token, Arc::new(message.rlp_bytes().into_vec()));
+        }
+        let gossip = &self.gossip;
+        let queued = self.relay_queue.mark_pending(|hash| gossip.is_pending(hash));
+        self.relay_stats.set_queued(queued);
+    }
+
+    fn request_wanted(&mut self) {
+        for (token, hashes) in self.gossip.fetch_requests(Instant::now()) {
+            ctrace!(SYNC_TX, "Request {} transactions from {}", hashes.len(), token);
+            self.api.send(&token, Arc::new(Message::Request(hashes).rlp_bytes().into_vec()));
         }
     }
 }
+
+/// The bytes that the peer can take in a broadcast round, or None if its class is not limited.
+fn relay_budget(api: &Api, node: &NodeId) -> Option<usize> {
+    if api.is_congested(node) {
+        return Some(0)
+    }
+    api.bandwidth_limit(node)
+        .map(|limit| limit * BROADCAST_TIMER_INTERVAL as usize / 1000 * RELAY_BANDWIDTH_PERCENT / 100)
+}
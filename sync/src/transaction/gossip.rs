@@ -0,0 +1,495 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+use cnetwork::NodeId;
+use primitives::H256;
+use rand::prelude::SliceRandom;
+use rand::thread_rng;
+
+use super::message::Announcement;
+
+/// The first version that announces the transactions before sending their bodies.
+pub const ANNOUNCE_VERSION: u64 = 1;
+const MAX_HISTORY_SIZE: usize = 100_000;
+/// The transactions requested from a peer and not received yet.
+pub const MAX_IN_FLIGHT_FETCHES_PER_PEER: usize = 256;
+/// The sum of the announced sizes of the transactions requested at once.
+const MAX_FETCH_BYTES: usize = 8 * 1024 * 1024;
+/// The requested transaction is requested from another announcer if it doesn't arrive in time.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+/// The number of the announced transactions that wait to be fetched.
+const MAX_WANTED: usize = 32 * 1024;
+/// A peer is reported whenever this many of the transactions it announced are not sent on request.
+const UNFULFILLED_PER_MISBEHAVIOR: usize = 64;
+/// The approximate size of an encoded announcement, which is counted against the relay budget of a peer.
+const ANNOUNCEMENT_BYTES: usize = 48;
+
+#[derive(Default)]
+struct KnownTxs {
+    history_set: HashSet<H256>,
+    history_queue: VecDeque<H256>,
+}
+
+impl KnownTxs {
+    fn push(&mut self, hash: H256) {
+        debug_assert!(!self.history_set.contains(&hash));
+        self.history_set.insert(hash);
+        self.history_queue.push_back(hash);
+        if self.history_queue.len() > MAX_HISTORY_SIZE {
+            self.history_queue.pop_front();
+        }
+    }
+
+    fn contains(&self, hash: &H256) -> bool {
+        self.history_set.contains(hash)
+    }
+}
+
+struct Peer {
+    version: u64,
+    /// The transactions that the peer has, or that are sent to it.
+    known: KnownTxs,
+    in_flight: usize,
+    unfulfilled: usize,
+}
+
+struct Wanted {
+    size: usize,
+    fee: u64,
+    /// The peers that announced the transaction and are not asked for it yet.
+    announcers: VecDeque<NodeId>,
+    /// The peer that is asked for the transaction, and when.
+    fetching: Option<(NodeId, Instant)>,
+}
+
+/// What a broadcast round sends to a peer.
+#[derive(Debug, PartialEq)]
+pub enum Outgoing {
+    Bodies(Vec<H256>),
+    Announcements(Vec<Announcement>),
+}
+
+/// Decides which peers get the bodies of the transactions and which get the announcements,
+/// and which announced transactions are requested from whom.
+///
+/// It only handles the hashes. The extension sends the messages and keeps the bodies.
+#[derive(Default)]
+pub struct Gossip {
+    peers: HashMap<NodeId, Peer>,
+    wanted: HashMap<H256, Wanted>,
+}
+
+impl Gossip {
+    pub fn add_peer(&mut self, node: NodeId, version: u64) {
+        self.peers.insert(node, Peer {
+            version,
+            known: Default::default(),
+            in_flight: 0,
+            unfulfilled: 0,
+        });
+    }
+
+    /// The transactions that were being fetched from the peer are requested from the other announcers.
+    pub fn remove_peer(&mut self, node: &NodeId) {
+        self.peers.remove(node);
+        for wanted in self.wanted.values_mut() {
+            wanted.announcers.retain(|announcer| announcer != node);
+            if wanted.fetching.map_or(false, |(peer, _)| peer == *node) {
+                wanted.fetching = None;
+            }
+        }
+        self.wanted.retain(|_, wanted| wanted.fetching.is_some() || !wanted.announcers.is_empty());
+    }
+
+    /// Called when the peer sends the bodies, whether they are requested or not.
+    /// Returns the transactions that the peer was not known to have, or None if the peer is removed.
+    pub fn on_transactions(&mut self, from: &NodeId, hashes: &[H256]) -> Option<Vec<H256>> {
+        for hash in hashes {
+            if let Some(Wanted {
+                fetching: Some((peer, _)),
+                ..
+            }) = self.wanted.remove(hash)
+            {
+                if let Some(peer) = self.peers.get_mut(&peer) {
+                    peer.in_flight -= 1;
+                }
+            }
+        }
+        let peer = self.peers.get_mut(from)?;
+        let unknown: Vec<_> = hashes.iter().filter(|hash| !peer.known.contains(hash)).cloned().collect();
+        for hash in &unknown {
+            peer.known.push(*hash);
+        }
+        Some(unknown)
+    }
+
+    /// Remembers the announced transactions that `has` returns false for, to request them later.
+    pub fn on_announcements<F>(&mut self, from: &NodeId, announcements: &[Announcement], has: F)
+    where
+        F: Fn(&H256) -> bool, {
+        let peer = match self.peers.get_mut(from) {
+            Some(peer) => peer,
+            None => return,
+        };
+        for announcement in announcements {
+            let hash = announcement.hash;
+            if !peer.known.contains(&hash) {
+                peer.known.push(hash);
+            }
+            if has(&hash) {
+                continue
+            }
+            if let Some(wanted) = self.wanted.get_mut(&hash) {
+                let is_fetching = wanted.fetching.map_or(false, |(peer, _)| peer == *from);
+                if !is_fetching && !wanted.announcers.contains(from) {
+                    wanted.announcers.push_back(*from);
+                }
+                continue
+            }
+            if self.wanted.len() >= MAX_WANTED {
+                ctrace!(SYNC_TX, "Ignore the announced transaction {}, since too many transactions are wanted", hash);
+                continue
+            }
+            self.wanted.insert(hash, Wanted {
+                size: announcement.size,
+                fee: announcement.fee,
+                announcers: vec![*from].into(),
+                fetching: None,
+            });
+        }
+    }
+
+    /// Stops waiting for the requested transactions that didn't arrive in time, so that they are requested from
+    /// the other announcers. Returns the peers that failed to send too many of the transactions they announced.
+    pub fn expire(&mut self, now: Instant) -> Vec<NodeId> {
+        let mut misbehaved = Vec::new();
+        for wanted in self.wanted.values_mut() {
+            let peer = match wanted.fetching {
+                Some((peer, requested_at)) if requested_at + FETCH_TIMEOUT <= now => peer,
+                _ => continue,
+            };
+            wanted.fetching = None;
+            if let Some(state) = self.peers.get_mut(&peer) {
+                state.in_flight -= 1;
+                state.unfulfilled += 1;
+                if state.unfulfilled % UNFULFILLED_PER_MISBEHAVIOR == 0 {
+                    misbehaved.push(peer);
+                }
+            }
+        }
+        self.wanted.retain(|_, wanted| wanted.fetching.is_some() || !wanted.announcers.is_empty());
+        misbehaved
+    }
+
+    /// Assigns the wanted transactions to the announcers that have room for more requests.
+    /// The transactions of the higher fee per byte are requested first.
+    pub fn fetch_requests(&mut self, now: Instant) -> Vec<(NodeId, Vec<H256>)> {
+        let mut idle: Vec<_> = self
+            .wanted
+            .iter()
+            .filter(|(_, wanted)| wanted.fetching.is_none())
+            .map(|(hash, wanted)| (*hash, wanted.fee, wanted.size))
+            .collect();
+        idle.sort_by(|(_, fee_a, size_a), (_, fee_b, size_b)| {
+            (u128::from(*fee_b) * *size_a as u128).cmp(&(u128::from(*fee_a) * *size_b as u128))
+        });
+
+        let mut requests: HashMap<NodeId, (Vec<H256>, usize)> = HashMap::new();
+        for (hash, _, size) in idle {
+            let wanted = self.wanted.get_mut(&hash).expect("The idle transactions are wanted");
+            let peers = &self.peers;
+            let position = wanted.announcers.iter().position(|announcer| {
+                let has_room =
+                    peers.get(announcer).map_or(false, |peer| peer.in_flight < MAX_IN_FLIGHT_FETCHES_PER_PEER);
+                let bytes = requests.get(announcer).map_or(0, |(_, bytes)| *bytes);
+                // A transaction larger than the limit is requested alone.
+                has_room && (bytes == 0 || bytes + size <= MAX_FETCH_BYTES)
+            });
+            let announcer = match position {
+                Some(position) => wanted.announcers.remove(position).expect("The position is found"),
+                None => continue,
+            };
+            self.peers.get_mut(&announcer).expect("Only the peers have room").in_flight += 1;
+            wanted.fetching = Some((announcer, now));
+            let (hashes, bytes) = requests.entry(announcer).or_default();
+            hashes.push(hash);
+            *bytes += size;
+        }
+        requests.into_iter().map(|(node, (hashes, _))| (node, hashes)).collect()
+    }
+
+    /// Sends the bodies to the peers of version 0 and to a random subset of the others,
+    /// whose size is the square root of their number. The rest get the announcements.
+    ///
+    /// The transactions are taken in the given order until the budget of the peer runs out, and the rest wait for
+    /// the next round. `budget` returns the bytes the peer can take in this round, or None if it's not limited.
+    pub fn broadcast<F>(&mut self, ready: &[Announcement], budget: F) -> Vec<(NodeId, Outgoing)>
+    where
+        F: Fn(&NodeId) -> Option<usize>, {
+        let mut announcing: Vec<_> =
+            self.peers.iter().filter(|(_, peer)| peer.version >= ANNOUNCE_VERSION).map(|(node, _)| *node).collect();
+        announcing.shuffle(&mut thread_rng());
+        let eager = (announcing.len() as f64).sqrt() as usize;
+        let eager_peers: HashSet<_> = announcing.into_iter().take(eager).collect();
+
+        let mut outgoing = Vec::new();
+        for (node, peer) in &mut self.peers {
+            let sends_bodies = peer.version < ANNOUNCE_VERSION || eager_peers.contains(node);
+            let mut remaining = budget(node);
+            let mut unsent = Vec::new();
+            for announcement in ready.iter().filter(|announcement| !peer.known.contains(&announcement.hash)) {
+                if let Some(remaining) = remaining.as_mut() {
+                    let cost = if sends_bodies {
+                        announcement.size
+                    } else {
+                        ANNOUNCEMENT_BYTES
+                    };
+                    // A transaction larger than the budget is sent alone.
+                    if *remaining == 0 || (cost > *remaining && !unsent.is_empty()) {
+                        break
+                    }
+                    *remaining = remaining.saturating_sub(cost);
+                }
+                unsent.push(announcement);
+            }
+            if unsent.is_empty() {
+                continue
+            }
+            for announcement in &unsent {
+                peer.known.push(announcement.hash);
+            }
+            if sends_bodies {
+                outgoing.push((*node, Outgoing::Bodies(unsent.iter().map(|announcement| announcement.hash).collect())));
+            } else {
+                outgoing.push((*node, Outgoing::Announcements(unsent.into_iter().cloned().collect())));
+            }
+        }
+        outgoing
+    }
+
+    /// True if some peers are not known to have the transaction.
+    pub fn is_pending(&self, hash: &H256) -> bool {
+        self.peers.values().any(|peer| !peer.known.contains(hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::*;
+
+    fn node_id(index: usize) -> NodeId {
+        NodeId::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 3485 + index as u16)
+    }
+
+    fn announcement(fee: u64, size: usize) -> Announcement {
+        Announcement {
+            hash: H256::random(),
+            size,
+            fee,
+        }
+    }
+
+    enum Packet {
+        Bodies(Vec<H256>),
+        Announcements(Vec<Announcement>),
+        Request(Vec<H256>),
+    }
+
+    /// Fully connected nodes that exchange the messages in order.
+    struct Network {
+        gossips: Vec<Gossip>,
+        pools: Vec<HashMap<H256, Announcement>>,
+        in_flight: VecDeque<(usize, usize, Packet)>,
+        /// The number of times each body is sent over each link, regardless of the direction.
+        bodies_sent: HashMap<(usize, usize, H256), usize>,
+    }
+
+    impl Network {
+        fn new(num_nodes: usize) -> Self {
+            let gossips = (0..num_nodes)
+                .map(|index| {
+                    let mut gossip = Gossip::default();
+                    for peer in (0..num_nodes).filter(|peer| *peer != index) {
+                        gossip.add_peer(node_id(peer), ANNOUNCE_VERSION);
+                    }
+                    gossip
+                })
+                .collect();
+            Self {
+                gossips,
+                pools: vec![HashMap::new(); num_nodes],
+                in_flight: VecDeque::new(),
+                bodies_sent: HashMap::new(),
+            }
+        }
+
+        fn index_of(node: &NodeId) -> usize {
+            (0..).find(|index| node_id(*index) == *node).unwrap()
+        }
+
+        fn broadcast(&mut self, from: usize) {
+            let ready: Vec<_> = self.pools[from].values().cloned().collect();
+            for (to, outgoing) in self.gossips[from].broadcast(&ready, |_| None) {
+                let packet = match outgoing {
+                    Outgoing::Bodies(hashes) => Packet::Bodies(hashes),
+                    Outgoing::Announcements(announcements) => Packet::Announcements(announcements),
+                };
+                self.in_flight.push_back((from, Self::index_of(&to), packet));
+            }
+            self.deliver();
+        }
+
+        fn request(&mut self, at: usize) {
+            for (to, hashes) in self.gossips[at].fetch_requests(Instant::now()) {
+                self.in_flight.push_back((at, Self::index_of(&to), Packet::Request(hashes)));
+            }
+        }
+
+        fn deliver(&mut self) {
+            while let Some((from, to, packet)) = self.in_flight.pop_front() {
+                match packet {
+                    Packet::Bodies(hashes) => {
+                        for hash in &hashes {
+                            let link = (from.min(to), from.max(to), *hash);
+                            *self.bodies_sent.entry(link).or_default() += 1;
+                            let announcement = self.pools[from][hash];
+                            self.pools[to].insert(*hash, announcement);
+                        }
+                        self.gossips[to].on_transactions(&node_id(from), &hashes);
+                        self.request(to);
+                    }
+                    Packet::Announcements(announcements) => {
+                        let pool = &self.pools[to];
+                        self.gossips[to]
+                            .on_announcements(&node_id(from), &announcements, |hash| pool.contains_key(hash));
+                        self.request(to);
+                    }
+                    Packet::Request(hashes) => {
+                        let found = hashes.into_iter().filter(|hash| self.pools[to].contains_key(hash)).collect();
+                        self.in_flight.push_back((to, from, Packet::Bodies(found)));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn each_body_crosses_each_link_at_most_once() {
+        let mut network = Network::new(3);
+        for _ in 0..10 {
+            let announcement = announcement(10, 100);
+            network.pools[0].insert(announcement.hash, announcement);
+        }
+
+        for _ in 0..3 {
+            for index in 0..3 {
+                network.broadcast(index);
+            }
+        }
+        for pool in &network.pools {
+            assert_eq!(10, pool.len());
+        }
+        assert!(network.bodies_sent.values().all(|count| *count == 1), "{:?}", network.bodies_sent);
+        // The transactions are announced to one of the peers of the first node, and fetched.
+        let links_from_first = network.bodies_sent.keys().filter(|(from, ..)| *from == 0).count();
+        assert_eq!(20, links_from_first);
+    }
+
+    #[test]
+    fn bodies_are_pushed_to_the_legacy_peers() {
+        let mut gossip = Gossip::default();
+        gossip.add_peer(node_id(1), 0);
+        gossip.add_peer(node_id(2), ANNOUNCE_VERSION);
+        gossip.add_peer(node_id(3), ANNOUNCE_VERSION);
+        let ready = vec![announcement(10, 100)];
+
+        let outgoing: HashMap<_, _> = gossip.broadcast(&ready, |_| None).into_iter().collect();
+        assert_eq!(Some(&Outgoing::Bodies(vec![ready[0].hash])), outgoing.get(&node_id(1)));
+        let announced = [node_id(2), node_id(3)]
+            .iter()
+            .filter(|node| outgoing.get(node) == Some(&Outgoing::Announcements(ready.clone())))
+            .count();
+        assert_eq!(1, announced);
+        assert_eq!(Vec::<(NodeId, Outgoing)>::new(), gossip.broadcast(&ready, |_| None));
+    }
+
+    #[test]
+    fn timed_out_fetch_is_requested_from_another_announcer() {
+        let mut gossip = Gossip::default();
+        let (first, second) = (node_id(1), node_id(2));
+        gossip.add_peer(first, ANNOUNCE_VERSION);
+        gossip.add_peer(second, ANNOUNCE_VERSION);
+        let announced = announcement(10, 100);
+        gossip.on_announcements(&first, &[announced], |_| false);
+        gossip.on_announcements(&second, &[announced], |_| false);
+
+        let now = Instant::now();
+        assert_eq!(vec![(first, vec![announced.hash])], gossip.fetch_requests(now));
+        assert_eq!(Vec::<NodeId>::new(), gossip.expire(now + Duration::from_secs(1)));
+        assert!(gossip.fetch_requests(now + Duration::from_secs(1)).is_empty());
+
+        let later = now + FETCH_TIMEOUT;
+        assert_eq!(Vec::<NodeId>::new(), gossip.expire(later));
+        assert_eq!(vec![(second, vec![announced.hash])], gossip.fetch_requests(later));
+        gossip.on_transactions(&second, &[announced.hash]);
+        assert!(gossip.wanted.is_empty());
+        assert_eq!(0, gossip.peers[&second].in_flight);
+    }
+
+    #[test]
+    fn fetches_in_flight_are_limited_per_peer() {
+        let mut gossip = Gossip::default();
+        let peer = node_id(1);
+        gossip.add_peer(peer, ANNOUNCE_VERSION);
+        let mut announcements: Vec<_> = (0..MAX_IN_FLIGHT_FETCHES_PER_PEER).map(|_| announcement(10, 100)).collect();
+        let expensive = announcement(1000, 100);
+        announcements.push(expensive);
+        gossip.on_announcements(&peer, &announcements, |_| false);
+
+        let now = Instant::now();
+        let requests = gossip.fetch_requests(now);
+        assert_eq!(1, requests.len());
+        let (_, requested) = &requests[0];
+        assert_eq!(MAX_IN_FLIGHT_FETCHES_PER_PEER, requested.len());
+        assert_eq!(expensive.hash, requested[0]);
+
+        gossip.on_transactions(&peer, &requested[..1]);
+        let requests = gossip.fetch_requests(now);
+        assert_eq!(1, requests[0].1.len());
+    }
+
+    #[test]
+    fn unfulfilled_announcements_are_reported() {
+        let mut gossip = Gossip::default();
+        let spammer = node_id(1);
+        gossip.add_peer(spammer, ANNOUNCE_VERSION);
+        let announcements: Vec<_> = (0..UNFULFILLED_PER_MISBEHAVIOR).map(|_| announcement(10, 100)).collect();
+        gossip.on_announcements(&spammer, &announcements, |_| false);
+
+        let now = Instant::now();
+        assert_eq!(UNFULFILLED_PER_MISBEHAVIOR, gossip.fetch_requests(now)[0].1.len());
+        assert_eq!(vec![spammer], gossip.expire(now + FETCH_TIMEOUT));
+        // Nobody else announced them.
+        assert!(gossip.wanted.is_empty());
+        assert_eq!(0, gossip.peers[&spammer].in_flight);
+    }
+}
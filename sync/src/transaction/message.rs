@@ -15,11 +15,51 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use ccore::UnverifiedTransaction;
+use primitives::H256;
 use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
 
+const MESSAGE_ID_ANNOUNCEMENTS: u8 = 0x01;
+const MESSAGE_ID_REQUEST: u8 = 0x02;
+
 #[derive(Debug, PartialEq)]
 pub enum Message {
+    /// The bodies of the transactions. It's the only message of version 0.
     Transactions(Vec<UnverifiedTransaction>),
+    /// The transactions that the sender has. The receiver requests the ones it lacks.
+    Announcements(Vec<Announcement>),
+    /// The hashes of the announced transactions whose bodies the sender wants.
+    Request(Vec<H256>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Announcement {
+    pub hash: H256,
+    /// The size of the encoded transaction in bytes.
+    pub size: usize,
+    pub fee: u64,
+}
+
+impl Encodable for Announcement {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(3).append(&self.hash).append(&self.size).append(&self.fee);
+    }
+}
+
+impl Decodable for Announcement {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 3 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected: 3,
+            })
+        }
+        Ok(Self {
+            hash: rlp.val_at(0)?,
+            size: rlp.val_at(1)?,
+            fee: rlp.val_at(2)?,
+        })
+    }
 }
 
 impl Encodable for Message {
@@ -40,12 +80,39 @@ impl Encodable for Message {
 
                 s.append(&compressed)
             }
+            Message::Announcements(announcements) => {
+                s.begin_list(2);
+                s.append(&MESSAGE_ID_ANNOUNCEMENTS);
+                s.append_list(announcements)
+            }
+            Message::Request(hashes) => {
+                s.begin_list(2);
+                s.append(&MESSAGE_ID_REQUEST);
+                s.append_list(hashes)
+            }
         };
     }
 }
 
 impl Decodable for Message {
     fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        // The transactions are not wrapped in a list to be compatible with version 0.
+        if rlp.is_list() {
+            let item_count = rlp.item_count()?;
+            if item_count != 2 {
+                return Err(DecoderError::RlpIncorrectListLen {
+                    got: item_count,
+                    expected: 2,
+                })
+            }
+            let id: u8 = rlp.val_at(0)?;
+            return match id {
+                MESSAGE_ID_ANNOUNCEMENTS => Ok(Message::Announcements(rlp.list_at(1)?)),
+                MESSAGE_ID_REQUEST => Ok(Message::Request(rlp.list_at(1)?)),
+                _ => Err(DecoderError::Custom("Unknown message id detected")),
+            }
+        }
+
         let compressed: Vec<u8> = rlp.as_val()?;
         let uncompressed = {
             // TODO: Cache the Decoder object
@@ -69,7 +136,9 @@ mod tests {
     use ckey::{Address, Signature};
     use ctypes::transaction::{Action, Transaction};
 
-    use super::Message;
+    use primitives::H256;
+
+    use super::{Announcement, Message};
 
     #[test]
     fn transactions_message_rlp() {
@@ -92,4 +161,25 @@ mod tests {
 
         rlp_encode_and_decode_test!(Message::Transactions(vec![tx]));
     }
+
+    #[test]
+    fn announcements_message_rlp() {
+        rlp_encode_and_decode_test!(Message::Announcements(vec![
+            Announcement {
+                hash: H256::random(),
+                size: 120,
+                fee: 10,
+            },
+            Announcement {
+                hash: H256::random(),
+                size: 4096,
+                fee: 0,
+            },
+        ]));
+    }
+
+    #[test]
+    fn request_message_rlp() {
+        rlp_encode_and_decode_test!(Message::Request(vec![H256::random(), H256::random()]));
+    }
 }
@@ -15,6 +15,8 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod extension;
+mod gossip;
 mod message;
+mod relay;
 
 pub use self::extension::Extension as TransactionSyncExtension;
@@ -0,0 +1,203 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use primitives::H256;
+
+use super::message::Announcement;
+
+/// The transactions that have waited this many rounds go ahead of the others, oldest first,
+/// so that the low-fee transactions eventually go out while the high-fee ones keep coming.
+pub const MAX_RELAY_DELAY_ROUNDS: u64 = 30;
+
+struct Queued {
+    /// The round that the transaction became ready to relay
+    queued_at: u64,
+    /// Some peers didn't get it yet.
+    pending: bool,
+}
+
+/// Orders the ready transactions of each broadcast round.
+/// The higher fee per byte goes first, and the transactions that waited too long are aged ahead of them.
+#[derive(Default)]
+pub struct RelayQueue {
+    round: u64,
+    queued: HashMap<H256, Queued>,
+}
+
+impl RelayQueue {
+    /// Starts a round with the ready transactions of the mem pool, and returns them in the order to relay.
+    /// The transactions that left the mem pool are forgotten. The number of the ones among them that were not
+    /// relayed to every peer yet is returned too.
+    pub fn next_round(&mut self, mut ready: Vec<Announcement>) -> (Vec<Announcement>, usize) {
+        self.round += 1;
+        let round = self.round;
+        let ready_hashes: HashSet<_> = ready.iter().map(|announcement| announcement.hash).collect();
+        let mut dropped = 0;
+        self.queued.retain(|hash, queued| {
+            let is_ready = ready_hashes.contains(hash);
+            if !is_ready && queued.pending {
+                dropped += 1;
+            }
+            is_ready
+        });
+        for announcement in &ready {
+            self.queued.entry(announcement.hash).or_insert(Queued {
+                queued_at: round,
+                pending: true,
+            });
+        }
+
+        let queued_at = |announcement: &Announcement| self.queued[&announcement.hash].queued_at;
+        let is_overdue = |queued_at: u64| round - queued_at >= MAX_RELAY_DELAY_ROUNDS;
+        ready.sort_by(|a, b| {
+            let (queued_a, queued_b) = (queued_at(a), queued_at(b));
+            match (is_overdue(queued_a), is_overdue(queued_b)) {
+                (true, false) => Ordering::Less,
+                (false, true) => Ordering::Greater,
+                (true, true) => queued_a.cmp(&queued_b),
+                (false, false) => (u128::from(b.fee) * a.size as u128)
+                    .cmp(&(u128::from(a.fee) * b.size as u128))
+                    .then(queued_a.cmp(&queued_b)),
+            }
+        });
+        (ready, dropped)
+    }
+
+    /// Remembers which transactions some peers didn't get yet, and returns their number.
+    pub fn mark_pending<F>(&mut self, is_pending: F) -> usize
+    where
+        F: Fn(&H256) -> bool, {
+        let mut count = 0;
+        for (hash, queued) in &mut self.queued {
+            queued.pending = is_pending(hash);
+            if queued.pending {
+                count += 1;
+            }
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use cnetwork::NodeId;
+
+    use super::super::gossip::{Gossip, Outgoing};
+    use super::*;
+
+    const BUDGET: usize = 300;
+
+    fn announcement(fee: u64, size: usize) -> Announcement {
+        Announcement {
+            hash: H256::random(),
+            size,
+            fee,
+        }
+    }
+
+    /// A peer of version 0, which gets the bodies.
+    fn gossip_with_a_peer() -> Gossip {
+        let mut gossip = Gossip::default();
+        gossip.add_peer(NodeId::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 3486), 0);
+        gossip
+    }
+
+    fn relay(queue: &mut RelayQueue, gossip: &mut Gossip, pool: &[Announcement]) -> (Vec<H256>, usize) {
+        let (ready, dropped) = queue.next_round(pool.to_vec());
+        let mut sent = Vec::new();
+        for (_, outgoing) in gossip.broadcast(&ready, |_| Some(BUDGET)) {
+            match outgoing {
+                Outgoing::Bodies(hashes) => sent.extend(hashes),
+                Outgoing::Announcements(_) => panic!("The peer of version 0 gets the bodies"),
+            }
+        }
+        queue.mark_pending(|hash| gossip.is_pending(hash));
+        (sent, dropped)
+    }
+
+    #[test]
+    fn higher_fee_per_byte_goes_first_within_the_budget() {
+        let mut gossip = gossip_with_a_peer();
+        let mut queue = RelayQueue::default();
+        let pool: Vec<_> = (1..=10).map(|fee| announcement(fee * 100, 100)).collect();
+
+        for round in 0..4 {
+            let (sent, _) = relay(&mut queue, &mut gossip, &pool);
+            let expected: Vec<_> = pool.iter().rev().skip(round * 3).take(3).map(|tx| tx.hash).collect();
+            assert_eq!(expected, sent, "round {}", round);
+        }
+        assert_eq!(0, queue.mark_pending(|hash| gossip.is_pending(hash)));
+        assert_eq!(Vec::<H256>::new(), relay(&mut queue, &mut gossip, &pool).0);
+
+        // The fee per byte decides, not the fee.
+        let mut gossip = gossip_with_a_peer();
+        let mut queue = RelayQueue::default();
+        let large = announcement(1000, 250);
+        let small = announcement(600, 100);
+        assert_eq!(vec![small.hash], relay(&mut queue, &mut gossip, &[large, small]).0);
+    }
+
+    #[test]
+    fn transaction_larger_than_the_budget_goes_alone() {
+        let mut gossip = gossip_with_a_peer();
+        let mut queue = RelayQueue::default();
+        let large = announcement(10_000, BUDGET * 2);
+        let small = announcement(10, 100);
+        assert_eq!(vec![large.hash], relay(&mut queue, &mut gossip, &[large, small]).0);
+        assert_eq!(vec![small.hash], relay(&mut queue, &mut gossip, &[large, small]).0);
+    }
+
+    #[test]
+    fn low_fee_transaction_is_aged_ahead_of_the_high_fee_ones() {
+        let mut gossip = gossip_with_a_peer();
+        let mut queue = RelayQueue::default();
+        let cheap = announcement(1, 100);
+        let mut pool = vec![cheap];
+        for round in 1..=MAX_RELAY_DELAY_ROUNDS + 1 {
+            // More high-fee transactions arrive than the budget in every round.
+            pool.extend((0..4).map(|_| announcement(1000, 100)));
+            let (sent, _) = relay(&mut queue, &mut gossip, &pool);
+            if round <= MAX_RELAY_DELAY_ROUNDS {
+                assert_eq!(3, sent.len());
+                assert!(!sent.contains(&cheap.hash), "round {}", round);
+            } else {
+                assert_eq!(cheap.hash, sent[0], "It waited {} rounds", MAX_RELAY_DELAY_ROUNDS);
+            }
+        }
+    }
+
+    #[test]
+    fn relays_of_the_evicted_transactions_are_dropped() {
+        let mut gossip = gossip_with_a_peer();
+        let mut queue = RelayQueue::default();
+        let pool: Vec<_> = (1..=5).map(|fee| announcement(fee * 100, 100)).collect();
+        let (sent, dropped) = relay(&mut queue, &mut gossip, &pool);
+        assert_eq!(3, sent.len());
+        assert_eq!(0, dropped);
+        assert_eq!(2, queue.mark_pending(|hash| gossip.is_pending(hash)));
+
+        // Every transaction left the pool. Only the two that were not relayed yet are dropped.
+        let (sent, dropped) = relay(&mut queue, &mut gossip, &[]);
+        assert!(sent.is_empty());
+        assert_eq!(2, dropped);
+        assert_eq!(0, queue.mark_pending(|hash| gossip.is_pending(hash)));
+    }
+}
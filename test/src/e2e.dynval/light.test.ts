@@ -0,0 +1,136 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+import * as chai from "chai";
+import { expect } from "chai";
+import * as chaiAsPromised from "chai-as-promised";
+import * as stake from "codechain-stakeholder-sdk";
+import "mocha";
+
+import { validators } from "../../tendermint.dynval/constants";
+import { PromiseExpect, wait } from "../helper/promise";
+import CodeChain from "../helper/spawn";
+import { withNodes } from "./setup";
+
+chai.use(chaiAsPromised);
+
+describe("Light node", function() {
+    const promiseExpect = new PromiseExpect();
+    const alice = validators[0];
+    const betty = validators[1];
+
+    const { nodes, initialParams } = withNodes(this, {
+        promiseExpect,
+        overrideParams: {
+            termSeconds: 3
+        },
+        // The weights reorder the validators every block, so the light node sees them in different orders.
+        validators: [
+            { signer: alice, delegation: 5000, deposit: 100000 },
+            { signer: betty, delegation: 4000, deposit: 100000 }
+        ]
+    });
+
+    let lightNode: CodeChain;
+    beforeEach(async function() {
+        lightNode = new CodeChain({
+            chain: `${__dirname}/../scheme/tendermint-dynval.json`,
+            argv: ["--light", "--no-discovery"]
+        });
+        await lightNode.start();
+    });
+
+    async function lightStatus(): Promise<any> {
+        return lightNode.sdk.rpc.sendRpcRequest("light_getStatus", []);
+    }
+
+    it("follows the finalized headers across the term boundary", async function() {
+        const termSeconds = initialParams.termSeconds;
+        this.slow(termSeconds * 3 * 1000 + 10_000);
+        this.timeout(termSeconds * 6 * 1000 + 20_000);
+
+        const fullNode = nodes[0];
+        await fullNode.waitForTermChange(2, termSeconds * 3);
+        await lightNode.connect(fullNode);
+
+        const termMetadata = (await stake.getTermMetadata(fullNode.sdk))!;
+        // The first block of the term is finalized by the precommits of the elected validators.
+        const firstBlock = termMetadata.lastTermFinishedBlockNumber + 1;
+        while (true) {
+            const status = await lightStatus();
+            if (
+                status.termId >= termMetadata.currentTermId &&
+                status.finalizedBlockNumber >= firstBlock
+            ) {
+                expect(
+                    status.validators.map((v: any) => v.address).sort()
+                ).deep.equal(
+                    [
+                        alice.platformAddress.value,
+                        betty.platformAddress.value
+                    ].sort()
+                );
+                break;
+            }
+            await wait(500);
+        }
+
+        expect(await lightNode.sdk.rpc.chain.getBlockHash(firstBlock)).deep.equal(
+            await fullNode.sdk.rpc.chain.getBlockHash(firstBlock)
+        );
+        const finality = await lightNode.sdk.rpc.sendRpcRequest(
+            "chain_getBlockFinality",
+            [firstBlock]
+        );
+        expect(finality.finalized).be.true;
+
+        // The blocks after the first one of the term are finalized too.
+        const laterBlock = (await fullNode.getBestBlockNumber()) + 1;
+        await fullNode.waitBlockNumber(laterBlock + 1);
+        while ((await lightStatus()).finalizedBlockNumber < laterBlock) {
+            await wait(500);
+        }
+        expect(await lightNode.sdk.rpc.chain.getBlockHash(laterBlock)).deep.equal(
+            await fullNode.sdk.rpc.chain.getBlockHash(laterBlock)
+        );
+    });
+
+    it("reads the balance with the proof from the full node", async function() {
+        const termSeconds = initialParams.termSeconds;
+        this.slow(termSeconds * 3 * 1000 + 10_000);
+        this.timeout(termSeconds * 6 * 1000 + 20_000);
+
+        const fullNode = nodes[0];
+        await lightNode.connect(fullNode);
+        let finalized = 0;
+        while (finalized === 0) {
+            finalized = (await lightStatus()).finalizedBlockNumber;
+            await wait(500);
+        }
+        const address = alice.platformAddress.value;
+        expect(
+            await lightNode.sdk.rpc.chain.getBalance(address, finalized)
+        ).deep.equal(await fullNode.sdk.rpc.chain.getBalance(address, finalized));
+    });
+
+    afterEach(async function() {
+        if (this.currentTest!.state === "failed") {
+            lightNode.keepLogs();
+        }
+        await lightNode.clean();
+        await promiseExpect.checkFulfilled();
+    });
+});
@@ -62,6 +62,25 @@ pub struct CommonParams {
     delegation_threshold: u64,
     min_deposit: u64,
     max_candidate_metadata_size: usize,
+
+    /// The permille of the minimum transaction costs that is burnt instead of distributed to the stakeholders.
+    fee_burn_permille: u64,
+
+    /// The format of the precommits in the Tendermint seal. 0 keeps the signatures, 1 aggregates them.
+    seal_version: u64,
+
+    /// Whether the CCS can be transferred. The delegations are allowed regardless of it.
+    ccs_transfers_enabled: bool,
+
+    /// Whether the fees are distributed by the stakes at the parent block, rather than the stakes
+    /// after the transactions of the block are executed.
+    distribute_fees_by_parent_stakes: bool,
+
+    /// The minimum quantity that a candidate delegates to itself to be elected.
+    min_self_nomination_quantity: u64,
+
+    /// Whether the self-nominations can have the nomination sequences, which reject the older nominations.
+    nomination_seq_enabled: bool,
 }
 
 impl CommonParams {
@@ -166,7 +185,43 @@ impl CommonParams {
         self.max_candidate_metadata_size
     }
 
+    pub fn fee_burn_permille(&self) -> u64 {
+        self.fee_burn_permille
+    }
+
+    pub fn seal_version(&self) -> u64 {
+        self.seal_version
+    }
+
+    pub fn ccs_transfers_enabled(&self) -> bool {
+        self.ccs_transfers_enabled
+    }
+
+    pub fn distribute_fees_by_parent_stakes(&self) -> bool {
+        self.distribute_fees_by_parent_stakes
+    }
+
+    pub fn min_self_nomination_quantity(&self) -> u64 {
+        self.min_self_nomination_quantity
+    }
+
+    pub fn nomination_seq_enabled(&self) -> bool {
+        self.nomination_seq_enabled
+    }
+
     pub fn verify(&self) -> Result<(), String> {
+        if self.fee_burn_permille > MAX_FEE_BURN_PERMILLE {
+            return Err(format!(
+                "The fee burn permille({}) should not be larger than {}",
+                self.fee_burn_permille, MAX_FEE_BURN_PERMILLE
+            ))
+        }
+        if self.seal_version > MAX_SEAL_VERSION {
+            return Err(format!(
+                "The seal version({}) should not be larger than {}",
+                self.seal_version, MAX_SEAL_VERSION
+            ))
+        }
         if self.term_seconds != 0 {
             if self.nomination_expiration == 0 {
                 return Err("You should set the nomination expiration".to_string())
@@ -214,11 +269,88 @@ impl CommonParams {
 
 const DEFAULT_PARAMS_SIZE: usize = 23;
 const NUMBER_OF_STAKE_PARAMS: usize = 9;
+/// The fee burn parameter follows the stake parameters.
+const NUMBER_OF_FEE_BURN_PARAMS: usize = 1;
+/// The seal version follows the fee burn parameter.
+const NUMBER_OF_SEAL_VERSION_PARAMS: usize = 1;
+/// The switch of the CCS transfers follows the seal version.
+const NUMBER_OF_CCS_TRANSFER_PARAMS: usize = 1;
+/// The switch of the stakes the fees are distributed by follows the switch of the CCS transfers.
+const NUMBER_OF_FEE_DISTRIBUTION_PARAMS: usize = 1;
+/// The minimum self-nomination follows the switch of the stakes the fees are distributed by.
+const NUMBER_OF_SELF_NOMINATION_PARAMS: usize = 1;
+/// The switch of the nomination sequences follows the minimum self-nomination.
+const NUMBER_OF_NOMINATION_SEQ_PARAMS: usize = 1;
+const VALID_SIZE: &[usize] = &[
+    DEFAULT_PARAMS_SIZE,
+    DEFAULT_PARAMS_SIZE + NUMBER_OF_STAKE_PARAMS,
+    DEFAULT_PARAMS_SIZE + NUMBER_OF_STAKE_PARAMS + NUMBER_OF_FEE_BURN_PARAMS,
+    DEFAULT_PARAMS_SIZE + NUMBER_OF_STAKE_PARAMS + NUMBER_OF_FEE_BURN_PARAMS + NUMBER_OF_SEAL_VERSION_PARAMS,
+    DEFAULT_PARAMS_SIZE
+        + NUMBER_OF_STAKE_PARAMS
+        + NUMBER_OF_FEE_BURN_PARAMS
+        + NUMBER_OF_SEAL_VERSION_PARAMS
+        + NUMBER_OF_CCS_TRANSFER_PARAMS,
+    DEFAULT_PARAMS_SIZE
+        + NUMBER_OF_STAKE_PARAMS
+        + NUMBER_OF_FEE_BURN_PARAMS
+        + NUMBER_OF_SEAL_VERSION_PARAMS
+        + NUMBER_OF_CCS_TRANSFER_PARAMS
+        + NUMBER_OF_FEE_DISTRIBUTION_PARAMS,
+    DEFAULT_PARAMS_SIZE
+        + NUMBER_OF_STAKE_PARAMS
+        + NUMBER_OF_FEE_BURN_PARAMS
+        + NUMBER_OF_SEAL_VERSION_PARAMS
+        + NUMBER_OF_CCS_TRANSFER_PARAMS
+        + NUMBER_OF_FEE_DISTRIBUTION_PARAMS
+        + NUMBER_OF_SELF_NOMINATION_PARAMS,
+    DEFAULT_PARAMS_SIZE
+        + NUMBER_OF_STAKE_PARAMS
+        + NUMBER_OF_FEE_BURN_PARAMS
+        + NUMBER_OF_SEAL_VERSION_PARAMS
+        + NUMBER_OF_CCS_TRANSFER_PARAMS
+        + NUMBER_OF_FEE_DISTRIBUTION_PARAMS
+        + NUMBER_OF_SELF_NOMINATION_PARAMS
+        + NUMBER_OF_NOMINATION_SEQ_PARAMS,
+];
+
+const MAX_FEE_BURN_PERMILLE: u64 = 1000;
+const MAX_SEAL_VERSION: u64 = 1;
 
 impl From<Params> for CommonParams {
     fn from(p: Params) -> Self {
         let mut size = DEFAULT_PARAMS_SIZE;
-        if p.term_seconds.is_some() {
+        if p.nomination_seq_enabled.is_some() {
+            size += NUMBER_OF_STAKE_PARAMS
+                + NUMBER_OF_FEE_BURN_PARAMS
+                + NUMBER_OF_SEAL_VERSION_PARAMS
+                + NUMBER_OF_CCS_TRANSFER_PARAMS
+                + NUMBER_OF_FEE_DISTRIBUTION_PARAMS
+                + NUMBER_OF_SELF_NOMINATION_PARAMS
+                + NUMBER_OF_NOMINATION_SEQ_PARAMS;
+        } else if p.min_self_nomination_quantity.is_some() {
+            size += NUMBER_OF_STAKE_PARAMS
+                + NUMBER_OF_FEE_BURN_PARAMS
+                + NUMBER_OF_SEAL_VERSION_PARAMS
+                + NUMBER_OF_CCS_TRANSFER_PARAMS
+                + NUMBER_OF_FEE_DISTRIBUTION_PARAMS
+                + NUMBER_OF_SELF_NOMINATION_PARAMS;
+        } else if p.distribute_fees_by_parent_stakes.is_some() {
+            size += NUMBER_OF_STAKE_PARAMS
+                + NUMBER_OF_FEE_BURN_PARAMS
+                + NUMBER_OF_SEAL_VERSION_PARAMS
+                + NUMBER_OF_CCS_TRANSFER_PARAMS
+                + NUMBER_OF_FEE_DISTRIBUTION_PARAMS;
+        } else if p.ccs_transfers_enabled.is_some() {
+            size += NUMBER_OF_STAKE_PARAMS
+                + NUMBER_OF_FEE_BURN_PARAMS
+                + NUMBER_OF_SEAL_VERSION_PARAMS
+                + NUMBER_OF_CCS_TRANSFER_PARAMS;
+        } else if p.seal_version.is_some() {
+            size += NUMBER_OF_STAKE_PARAMS + NUMBER_OF_FEE_BURN_PARAMS + NUMBER_OF_SEAL_VERSION_PARAMS;
+        } else if p.fee_burn_permille.is_some() {
+            size += NUMBER_OF_STAKE_PARAMS + NUMBER_OF_FEE_BURN_PARAMS;
+        } else if p.term_seconds.is_some() {
             size += NUMBER_OF_STAKE_PARAMS;
         }
         Self {
@@ -255,6 +387,12 @@ impl From<Params> for CommonParams {
             delegation_threshold: p.delegation_threshold.map(From::from).unwrap_or_default(),
             min_deposit: p.min_deposit.map(From::from).unwrap_or_default(),
             max_candidate_metadata_size: p.max_candidate_metadata_size.map(From::from).unwrap_or_default(),
+            fee_burn_permille: p.fee_burn_permille.map(From::from).unwrap_or_default(),
+            seal_version: p.seal_version.map(From::from).unwrap_or_default(),
+            ccs_transfers_enabled: p.ccs_transfers_enabled.unwrap_or(true),
+            distribute_fees_by_parent_stakes: p.distribute_fees_by_parent_stakes.unwrap_or_default(),
+            min_self_nomination_quantity: p.min_self_nomination_quantity.map(From::from).unwrap_or_default(),
+            nomination_seq_enabled: p.nomination_seq_enabled.unwrap_or_default(),
         }
     }
 }
@@ -287,7 +425,7 @@ impl From<CommonParams> for Params {
             snapshot_period: p.snapshot_period().into(),
             ..Default::default()
         };
-        if p.size == DEFAULT_PARAMS_SIZE + NUMBER_OF_STAKE_PARAMS {
+        if p.size >= DEFAULT_PARAMS_SIZE + NUMBER_OF_STAKE_PARAMS {
             result.term_seconds = Some(p.term_seconds().into());
             result.nomination_expiration = Some(p.nomination_expiration().into());
             result.custody_period = Some(p.custody_period().into());
@@ -298,13 +436,62 @@ impl From<CommonParams> for Params {
             result.min_deposit = Some(p.min_deposit().into());
             result.max_candidate_metadata_size = Some(p.max_candidate_metadata_size().into());
         }
+        if p.size >= DEFAULT_PARAMS_SIZE + NUMBER_OF_STAKE_PARAMS + NUMBER_OF_FEE_BURN_PARAMS {
+            result.fee_burn_permille = Some(p.fee_burn_permille().into());
+        }
+        if p.size
+            >= DEFAULT_PARAMS_SIZE + NUMBER_OF_STAKE_PARAMS + NUMBER_OF_FEE_BURN_PARAMS + NUMBER_OF_SEAL_VERSION_PARAMS
+        {
+            result.seal_version = Some(p.seal_version().into());
+        }
+        if p.size
+            >= DEFAULT_PARAMS_SIZE
+                + NUMBER_OF_STAKE_PARAMS
+                + NUMBER_OF_FEE_BURN_PARAMS
+                + NUMBER_OF_SEAL_VERSION_PARAMS
+                + NUMBER_OF_CCS_TRANSFER_PARAMS
+        {
+            result.ccs_transfers_enabled = Some(p.ccs_transfers_enabled());
+        }
+        if p.size
+            >= DEFAULT_PARAMS_SIZE
+                + NUMBER_OF_STAKE_PARAMS
+                + NUMBER_OF_FEE_BURN_PARAMS
+                + NUMBER_OF_SEAL_VERSION_PARAMS
+                + NUMBER_OF_CCS_TRANSFER_PARAMS
+                + NUMBER_OF_FEE_DISTRIBUTION_PARAMS
+        {
+            result.distribute_fees_by_parent_stakes = Some(p.distribute_fees_by_parent_stakes());
+        }
+        if p.size
+            >= DEFAULT_PARAMS_SIZE
+                + NUMBER_OF_STAKE_PARAMS
+                + NUMBER_OF_FEE_BURN_PARAMS
+                + NUMBER_OF_SEAL_VERSION_PARAMS
+                + NUMBER_OF_CCS_TRANSFER_PARAMS
+                + NUMBER_OF_FEE_DISTRIBUTION_PARAMS
+                + NUMBER_OF_SELF_NOMINATION_PARAMS
+        {
+            result.min_self_nomination_quantity = Some(p.min_self_nomination_quantity().into());
+        }
+        if p.size
+            >= DEFAULT_PARAMS_SIZE
+                + NUMBER_OF_STAKE_PARAMS
+                + NUMBER_OF_FEE_BURN_PARAMS
+                + NUMBER_OF_SEAL_VERSION_PARAMS
+                + NUMBER_OF_CCS_TRANSFER_PARAMS
+                + NUMBER_OF_FEE_DISTRIBUTION_PARAMS
+                + NUMBER_OF_SELF_NOMINATION_PARAMS
+                + NUMBER_OF_NOMINATION_SEQ_PARAMS
+        {
+            result.nomination_seq_enabled = Some(p.nomination_seq_enabled());
+        }
         result
     }
 }
 
 impl Encodable for CommonParams {
     fn rlp_append(&self, s: &mut RlpStream) {
-        const VALID_SIZE: &[usize] = &[DEFAULT_PARAMS_SIZE, DEFAULT_PARAMS_SIZE + NUMBER_OF_STAKE_PARAMS];
         assert!(VALID_SIZE.contains(&self.size), "{} must be in {:?}", self.size, VALID_SIZE);
         s.begin_list(self.size)
             .append(&self.max_extra_data_size)
@@ -330,7 +517,7 @@ impl Encodable for CommonParams {
             .append(&self.min_asset_unwrap_ccc_cost)
             .append(&self.max_body_size)
             .append(&self.snapshot_period);
-        if self.size == DEFAULT_PARAMS_SIZE + NUMBER_OF_STAKE_PARAMS {
+        if self.size >= DEFAULT_PARAMS_SIZE + NUMBER_OF_STAKE_PARAMS {
             s.append(&self.term_seconds)
                 .append(&self.nomination_expiration)
                 .append(&self.custody_period)
@@ -341,13 +528,62 @@ impl Encodable for CommonParams {
                 .append(&self.min_deposit)
                 .append(&self.max_candidate_metadata_size);
         }
+        if self.size >= DEFAULT_PARAMS_SIZE + NUMBER_OF_STAKE_PARAMS + NUMBER_OF_FEE_BURN_PARAMS {
+            s.append(&self.fee_burn_permille);
+        }
+        if self.size
+            >= DEFAULT_PARAMS_SIZE + NUMBER_OF_STAKE_PARAMS + NUMBER_OF_FEE_BURN_PARAMS + NUMBER_OF_SEAL_VERSION_PARAMS
+        {
+            s.append(&self.seal_version);
+        }
+        if self.size
+            >= DEFAULT_PARAMS_SIZE
+                + NUMBER_OF_STAKE_PARAMS
+                + NUMBER_OF_FEE_BURN_PARAMS
+                + NUMBER_OF_SEAL_VERSION_PARAMS
+                + NUMBER_OF_CCS_TRANSFER_PARAMS
+        {
+            s.append(&self.ccs_transfers_enabled);
+        }
+        if self.size
+            >= DEFAULT_PARAMS_SIZE
+                + NUMBER_OF_STAKE_PARAMS
+                + NUMBER_OF_FEE_BURN_PARAMS
+                + NUMBER_OF_SEAL_VERSION_PARAMS
+                + NUMBER_OF_CCS_TRANSFER_PARAMS
+                + NUMBER_OF_FEE_DISTRIBUTION_PARAMS
+        {
+            s.append(&self.distribute_fees_by_parent_stakes);
+        }
+        if self.size
+            >= DEFAULT_PARAMS_SIZE
+                + NUMBER_OF_STAKE_PARAMS
+                + NUMBER_OF_FEE_BURN_PARAMS
+                + NUMBER_OF_SEAL_VERSION_PARAMS
+                + NUMBER_OF_CCS_TRANSFER_PARAMS
+                + NUMBER_OF_FEE_DISTRIBUTION_PARAMS
+                + NUMBER_OF_SELF_NOMINATION_PARAMS
+        {
+            s.append(&self.min_self_nomination_quantity);
+        }
+        if self.size
+            >= DEFAULT_PARAMS_SIZE
+                + NUMBER_OF_STAKE_PARAMS
+                + NUMBER_OF_FEE_BURN_PARAMS
+                + NUMBER_OF_SEAL_VERSION_PARAMS
+                + NUMBER_OF_CCS_TRANSFER_PARAMS
+                + NUMBER_OF_FEE_DISTRIBUTION_PARAMS
+                + NUMBER_OF_SELF_NOMINATION_PARAMS
+                + NUMBER_OF_NOMINATION_SEQ_PARAMS
+        {
+            s.append(&self.nomination_seq_enabled);
+        }
     }
 }
 
 impl Decodable for CommonParams {
     fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
         let size = rlp.item_count()?;
-        const VALID_SIZE: &[usize] = &[DEFAULT_PARAMS_SIZE, DEFAULT_PARAMS_SIZE + NUMBER_OF_STAKE_PARAMS];
         if !VALID_SIZE.contains(&size) {
             return Err(DecoderError::RlpIncorrectListLen {
                 expected: DEFAULT_PARAMS_SIZE,
@@ -404,6 +640,68 @@ impl Decodable for CommonParams {
         } else {
             Default::default()
         };
+        let fee_burn_permille = if size >= DEFAULT_PARAMS_SIZE + NUMBER_OF_STAKE_PARAMS + NUMBER_OF_FEE_BURN_PARAMS {
+            rlp.val_at(32)?
+        } else {
+            0
+        };
+        let seal_version = if size
+            >= DEFAULT_PARAMS_SIZE + NUMBER_OF_STAKE_PARAMS + NUMBER_OF_FEE_BURN_PARAMS + NUMBER_OF_SEAL_VERSION_PARAMS
+        {
+            rlp.val_at(33)?
+        } else {
+            0
+        };
+        let ccs_transfers_enabled = if size
+            >= DEFAULT_PARAMS_SIZE
+                + NUMBER_OF_STAKE_PARAMS
+                + NUMBER_OF_FEE_BURN_PARAMS
+                + NUMBER_OF_SEAL_VERSION_PARAMS
+                + NUMBER_OF_CCS_TRANSFER_PARAMS
+        {
+            rlp.val_at(34)?
+        } else {
+            true
+        };
+        let distribute_fees_by_parent_stakes = if size
+            >= DEFAULT_PARAMS_SIZE
+                + NUMBER_OF_STAKE_PARAMS
+                + NUMBER_OF_FEE_BURN_PARAMS
+                + NUMBER_OF_SEAL_VERSION_PARAMS
+                + NUMBER_OF_CCS_TRANSFER_PARAMS
+                + NUMBER_OF_FEE_DISTRIBUTION_PARAMS
+        {
+            rlp.val_at(35)?
+        } else {
+            false
+        };
+        let min_self_nomination_quantity = if size
+            >= DEFAULT_PARAMS_SIZE
+                + NUMBER_OF_STAKE_PARAMS
+                + NUMBER_OF_FEE_BURN_PARAMS
+                + NUMBER_OF_SEAL_VERSION_PARAMS
+                + NUMBER_OF_CCS_TRANSFER_PARAMS
+                + NUMBER_OF_FEE_DISTRIBUTION_PARAMS
+                + NUMBER_OF_SELF_NOMINATION_PARAMS
+        {
+            rlp.val_at(36)?
+        } else {
+            0
+        };
+        let nomination_seq_enabled = if size
+            >= DEFAULT_PARAMS_SIZE
+                + NUMBER_OF_STAKE_PARAMS
+                + NUMBER_OF_FEE_BURN_PARAMS
+                + NUMBER_OF_SEAL_VERSION_PARAMS
+                + NUMBER_OF_CCS_TRANSFER_PARAMS
+                + NUMBER_OF_FEE_DISTRIBUTION_PARAMS
+                + NUMBER_OF_SELF_NOMINATION_PARAMS
+                + NUMBER_OF_NOMINATION_SEQ_PARAMS
+        {
+            rlp.val_at(37)?
+        } else {
+            false
+        };
         Ok(Self {
             size,
             max_extra_data_size,
@@ -438,6 +736,12 @@ impl Decodable for CommonParams {
             delegation_threshold,
             min_deposit,
             max_candidate_metadata_size,
+            fee_burn_permille,
+            seal_version,
+            ccs_transfers_enabled,
+            distribute_fees_by_parent_stakes,
+            min_self_nomination_quantity,
+            nomination_seq_enabled,
         })
     }
 }
@@ -462,6 +766,63 @@ impl CommonParams {
         self.max_text_content_size = max_text_content_size;
     }
 
+    pub fn set_max_body_size_for_test(&mut self, max_body_size: usize) {
+        self.max_body_size = max_body_size;
+    }
+
+    pub fn set_fee_burn_permille_for_test(&mut self, fee_burn_permille: u64) {
+        self.size = DEFAULT_PARAMS_SIZE + NUMBER_OF_STAKE_PARAMS + NUMBER_OF_FEE_BURN_PARAMS;
+        self.fee_burn_permille = fee_burn_permille;
+    }
+
+    pub fn set_seal_version_for_test(&mut self, seal_version: u64) {
+        self.size =
+            DEFAULT_PARAMS_SIZE + NUMBER_OF_STAKE_PARAMS + NUMBER_OF_FEE_BURN_PARAMS + NUMBER_OF_SEAL_VERSION_PARAMS;
+        self.seal_version = seal_version;
+    }
+
+    pub fn set_ccs_transfers_enabled_for_test(&mut self, ccs_transfers_enabled: bool) {
+        self.size = DEFAULT_PARAMS_SIZE
+            + NUMBER_OF_STAKE_PARAMS
+            + NUMBER_OF_FEE_BURN_PARAMS
+            + NUMBER_OF_SEAL_VERSION_PARAMS
+            + NUMBER_OF_CCS_TRANSFER_PARAMS;
+        self.ccs_transfers_enabled = ccs_transfers_enabled;
+    }
+
+    pub fn set_distribute_fees_by_parent_stakes_for_test(&mut self, distribute_fees_by_parent_stakes: bool) {
+        self.size = DEFAULT_PARAMS_SIZE
+            + NUMBER_OF_STAKE_PARAMS
+            + NUMBER_OF_FEE_BURN_PARAMS
+            + NUMBER_OF_SEAL_VERSION_PARAMS
+            + NUMBER_OF_CCS_TRANSFER_PARAMS
+            + NUMBER_OF_FEE_DISTRIBUTION_PARAMS;
+        self.distribute_fees_by_parent_stakes = distribute_fees_by_parent_stakes;
+    }
+
+    pub fn set_min_self_nomination_quantity_for_test(&mut self, min_self_nomination_quantity: u64) {
+        self.size = DEFAULT_PARAMS_SIZE
+            + NUMBER_OF_STAKE_PARAMS
+            + NUMBER_OF_FEE_BURN_PARAMS
+            + NUMBER_OF_SEAL_VERSION_PARAMS
+            + NUMBER_OF_CCS_TRANSFER_PARAMS
+            + NUMBER_OF_FEE_DISTRIBUTION_PARAMS
+            + NUMBER_OF_SELF_NOMINATION_PARAMS;
+        self.min_self_nomination_quantity = min_self_nomination_quantity;
+    }
+
+    pub fn set_nomination_seq_enabled_for_test(&mut self, nomination_seq_enabled: bool) {
+        self.size = DEFAULT_PARAMS_SIZE
+            + NUMBER_OF_STAKE_PARAMS
+            + NUMBER_OF_FEE_BURN_PARAMS
+            + NUMBER_OF_SEAL_VERSION_PARAMS
+            + NUMBER_OF_CCS_TRANSFER_PARAMS
+            + NUMBER_OF_FEE_DISTRIBUTION_PARAMS
+            + NUMBER_OF_SELF_NOMINATION_PARAMS
+            + NUMBER_OF_NOMINATION_SEQ_PARAMS;
+        self.nomination_seq_enabled = nomination_seq_enabled;
+    }
+
     pub fn set_dynamic_validator_params_for_test(
         &mut self,
         term_seconds: u64,
@@ -515,6 +876,112 @@ mod tests {
         rlp_encode_and_decode_test!(params);
     }
 
+    #[test]
+    fn rlp_with_fee_burn_permille() {
+        let mut params = CommonParams::default_for_test();
+        params.set_fee_burn_permille_for_test(250);
+        params.term_seconds = 100;
+        rlp_encode_and_decode_test!(params);
+    }
+
+    #[test]
+    fn fee_burn_permille_cannot_exceed_one_thousand() {
+        let mut params = CommonParams::default_for_test();
+        params.set_fee_burn_permille_for_test(1000);
+        assert_eq!(Ok(()), params.verify());
+        params.set_fee_burn_permille_for_test(1001);
+        assert!(params.verify().is_err());
+    }
+
+    #[test]
+    fn rlp_with_seal_version() {
+        let mut params = CommonParams::default_for_test();
+        params.set_seal_version_for_test(1);
+        params.fee_burn_permille = 250;
+        rlp_encode_and_decode_test!(params);
+    }
+
+    #[test]
+    fn rlp_with_ccs_transfers_disabled() {
+        let mut params = CommonParams::default_for_test();
+        assert!(params.ccs_transfers_enabled());
+        params.set_ccs_transfers_enabled_for_test(false);
+        params.seal_version = 1;
+        rlp_encode_and_decode_test!(params);
+    }
+
+    #[test]
+    fn ccs_transfers_are_enabled_in_the_old_rlp() {
+        let mut params = CommonParams::default_for_test();
+        params.set_seal_version_for_test(1);
+        params.ccs_transfers_enabled = false;
+        let decoded: CommonParams = rlp::decode(&rlp::encode(&params));
+        assert!(decoded.ccs_transfers_enabled());
+    }
+
+    #[test]
+    fn rlp_with_fees_distributed_by_parent_stakes() {
+        let mut params = CommonParams::default_for_test();
+        assert!(!params.distribute_fees_by_parent_stakes());
+        params.set_distribute_fees_by_parent_stakes_for_test(true);
+        params.ccs_transfers_enabled = false;
+        rlp_encode_and_decode_test!(params);
+    }
+
+    #[test]
+    fn fees_are_distributed_by_block_stakes_in_the_old_rlp() {
+        let mut params = CommonParams::default_for_test();
+        params.set_ccs_transfers_enabled_for_test(true);
+        params.distribute_fees_by_parent_stakes = true;
+        let decoded: CommonParams = rlp::decode(&rlp::encode(&params));
+        assert!(!decoded.distribute_fees_by_parent_stakes());
+    }
+
+    #[test]
+    fn rlp_with_min_self_nomination_quantity() {
+        let mut params = CommonParams::default_for_test();
+        assert_eq!(0, params.min_self_nomination_quantity());
+        params.set_min_self_nomination_quantity_for_test(100);
+        params.distribute_fees_by_parent_stakes = true;
+        rlp_encode_and_decode_test!(params);
+    }
+
+    #[test]
+    fn min_self_nomination_quantity_is_zero_in_the_old_rlp() {
+        let mut params = CommonParams::default_for_test();
+        params.set_distribute_fees_by_parent_stakes_for_test(true);
+        params.min_self_nomination_quantity = 100;
+        let decoded: CommonParams = rlp::decode(&rlp::encode(&params));
+        assert_eq!(0, decoded.min_self_nomination_quantity());
+    }
+
+    #[test]
+    fn rlp_with_nomination_seq_enabled() {
+        let mut params = CommonParams::default_for_test();
+        assert!(!params.nomination_seq_enabled());
+        params.set_nomination_seq_enabled_for_test(true);
+        params.min_self_nomination_quantity = 100;
+        rlp_encode_and_decode_test!(params);
+    }
+
+    #[test]
+    fn nomination_seq_is_disabled_in_the_old_rlp() {
+        let mut params = CommonParams::default_for_test();
+        params.set_min_self_nomination_quantity_for_test(100);
+        params.nomination_seq_enabled = true;
+        let decoded: CommonParams = rlp::decode(&rlp::encode(&params));
+        assert!(!decoded.nomination_seq_enabled());
+    }
+
+    #[test]
+    fn seal_version_cannot_exceed_one() {
+        let mut params = CommonParams::default_for_test();
+        params.set_seal_version_for_test(1);
+        assert_eq!(Ok(()), params.verify());
+        params.set_seal_version_for_test(2);
+        assert!(params.verify().is_err());
+    }
+
     #[test]
     fn rlp_encoding_are_different_if_the_size_are_different() {
         let origin = CommonParams::default_for_test();
@@ -586,6 +1053,7 @@ mod tests {
         assert_eq!(deserialized.delegation_threshold, 0);
         assert_eq!(deserialized.min_deposit, 0);
         assert_eq!(deserialized.max_candidate_metadata_size, 0);
+        assert_eq!(deserialized.fee_burn_permille, 0);
 
         assert_eq!(params, deserialized.into());
     }
@@ -654,6 +1122,7 @@ mod tests {
         assert_eq!(deserialized.delegation_threshold, 0);
         assert_eq!(deserialized.min_deposit, 0);
         assert_eq!(deserialized.max_candidate_metadata_size, 0);
+        assert_eq!(deserialized.fee_burn_permille, 0);
 
         assert_eq!(
             Params {
@@ -743,7 +1212,116 @@ mod tests {
         assert_eq!(deserialized.delegation_threshold, 31);
         assert_eq!(deserialized.min_deposit, 32);
         assert_eq!(deserialized.max_candidate_metadata_size, 33);
+        assert_eq!(deserialized.fee_burn_permille, 0);
 
         assert_eq!(params, deserialized.into());
     }
+
+    #[test]
+    fn params_from_json_with_fee_burn_permille() {
+        let s = r#"{
+            "maxExtraDataSize": "0x20",
+            "maxAssetSchemeMetadataSize": "0x0400",
+            "maxTransferMetadataSize": "0x0100",
+            "maxTextContentSize": "0x0200",
+            "networkID" : "tc",
+            "minPayCost" : 10,
+            "minSetRegularKeyCost" : 11,
+            "minCreateShardCost" : 12,
+            "minSetShardOwnersCost" : 13,
+            "minSetShardUsersCost" : 14,
+            "minWrapCccCost" : 15,
+            "minCustomCost" : 16,
+            "minStoreCost" : 17,
+            "minRemoveCost" : 18,
+            "minMintAssetCost" : 19,
+            "minTransferAssetCost" : 20,
+            "minChangeAssetSchemeCost" : 21,
+            "minComposeAssetCost" : 22,
+            "minDecomposeAssetCost" : 23,
+            "minUnwrapCccCost" : 24,
+            "minIncreaseAssetSupplyCost": 25,
+            "maxBodySize" : 4194304,
+            "snapshotPeriod": 16384,
+            "feeBurnPermille": 250
+        }"#;
+
+        let params = serde_json::from_str::<Params>(s).unwrap();
+        let deserialized = CommonParams::from(params.clone());
+        assert_eq!(deserialized.fee_burn_permille, 250);
+        assert_eq!(deserialized.term_seconds, 0);
+        rlp_encode_and_decode_test!(deserialized);
+
+        assert_eq!(
+            Params {
+                term_seconds: Some(0.into()),
+                nomination_expiration: Some(0.into()),
+                custody_period: Some(0.into()),
+                release_period: Some(0.into()),
+                max_num_of_validators: Some(0.into()),
+                min_num_of_validators: Some(0.into()),
+                delegation_threshold: Some(0.into()),
+                min_deposit: Some(0.into()),
+                max_candidate_metadata_size: Some(0.into()),
+                ..params
+            },
+            deserialized.into(),
+            "Convert back will fill default values"
+        );
+    }
+
+    #[test]
+    fn params_from_json_with_seal_version() {
+        let s = r#"{
+            "maxExtraDataSize": "0x20",
+            "maxAssetSchemeMetadataSize": "0x0400",
+            "maxTransferMetadataSize": "0x0100",
+            "maxTextContentSize": "0x0200",
+            "networkID" : "tc",
+            "minPayCost" : 10,
+            "minSetRegularKeyCost" : 11,
+            "minCreateShardCost" : 12,
+            "minSetShardOwnersCost" : 13,
+            "minSetShardUsersCost" : 14,
+            "minWrapCccCost" : 15,
+            "minCustomCost" : 16,
+            "minStoreCost" : 17,
+            "minRemoveCost" : 18,
+            "minMintAssetCost" : 19,
+            "minTransferAssetCost" : 20,
+            "minChangeAssetSchemeCost" : 21,
+            "minComposeAssetCost" : 22,
+            "minDecomposeAssetCost" : 23,
+            "minUnwrapCccCost" : 24,
+            "minIncreaseAssetSupplyCost": 25,
+            "maxBodySize" : 4194304,
+            "snapshotPeriod": 16384,
+            "feeBurnPermille": 250,
+            "sealVersion": 1
+        }"#;
+
+        let params = serde_json::from_str::<Params>(s).unwrap();
+        let deserialized = CommonParams::from(params.clone());
+        assert_eq!(deserialized.fee_burn_permille, 250);
+        assert_eq!(deserialized.seal_version, 1);
+        assert_eq!(deserialized.term_seconds, 0);
+        rlp_encode_and_decode_test!(deserialized);
+
+        assert_eq!(
+            Params {
+                term_seconds: Some(0.into()),
+                nomination_expiration: Some(0.into()),
+                custody_period: Some(0.into()),
+                release_period: Some(0.into()),
+                max_num_of_validators: Some(0.into()),
+                min_num_of_validators: Some(0.into()),
+                delegation_threshold: Some(0.into()),
+                min_deposit: Some(0.into()),
+                max_candidate_metadata_size: Some(0.into()),
+                ..params
+            },
+            deserialized.into(),
+            "Convert back will fill default values"
+        );
+    }
 }
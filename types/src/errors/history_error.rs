@@ -27,6 +27,11 @@ use crate::transaction::Timelock;
 pub enum Error {
     /// Transaction was not imported to the queue because limit has been reached.
     LimitReached,
+    /// The seq doesn't follow the preceding transaction of the same signer in a batch
+    NonConsecutiveSeq {
+        expected: u64,
+        got: u64,
+    },
     /// Transaction is not valid anymore (state already has higher seq)
     Old,
     OrderExpired {
@@ -55,6 +60,7 @@ const ERROR_ID_TIMELOCKED: u8 = 5;
 const ERROR_ID_TOO_CHEAP_TO_REPLACE: u8 = 6;
 const ERROR_ID_TX_ALREADY_IMPORTED: u8 = 7;
 const ERROR_ID_TRANSFER_EXPIRED: u8 = 8;
+const ERROR_ID_NON_CONSECUTIVE_SEQ: u8 = 9;
 
 struct RlpHelper;
 impl TaggedRlp for RlpHelper {
@@ -69,6 +75,7 @@ impl TaggedRlp for RlpHelper {
             ERROR_ID_TOO_CHEAP_TO_REPLACE => 1,
             ERROR_ID_TX_ALREADY_IMPORTED => 1,
             ERROR_ID_TRANSFER_EXPIRED => 3,
+            ERROR_ID_NON_CONSECUTIVE_SEQ => 3,
             _ => return Err(DecoderError::Custom("Invalid HistoryError")),
         })
     }
@@ -78,6 +85,10 @@ impl Encodable for Error {
     fn rlp_append(&self, s: &mut RlpStream) {
         match self {
             Error::LimitReached => RlpHelper::new_tagged_list(s, ERROR_ID_LIMIT_REACHED),
+            Error::NonConsecutiveSeq {
+                expected,
+                got,
+            } => RlpHelper::new_tagged_list(s, ERROR_ID_NON_CONSECUTIVE_SEQ).append(expected).append(got),
             Error::Old => RlpHelper::new_tagged_list(s, ERROR_ID_OLD),
             Error::OrderExpired {
                 expiration,
@@ -102,6 +113,10 @@ impl Decodable for Error {
         let tag = rlp.val_at::<u8>(0)?;
         let error = match tag {
             ERROR_ID_LIMIT_REACHED => Error::LimitReached,
+            ERROR_ID_NON_CONSECUTIVE_SEQ => Error::NonConsecutiveSeq {
+                expected: rlp.val_at(1)?,
+                got: rlp.val_at(2)?,
+            },
             ERROR_ID_OLD => Error::Old,
             ERROR_ID_ORDER_EXPIRED => Error::OrderExpired {
                 expiration: rlp.val_at(1)?,
@@ -128,6 +143,10 @@ impl Display for Error {
     fn fmt(&self, f: &mut Formatter) -> FormatResult {
         match self {
             Error::LimitReached => write!(f, "Transaction limit reached"),
+            Error::NonConsecutiveSeq {
+                expected,
+                got,
+            } => write!(f, "The seq is not consecutive. Expected: {}, Got: {}", expected, got),
             Error::Old => write!(f, "No longer valid"),
             Error::OrderExpired {
                 expiration,
@@ -121,6 +121,16 @@ pub enum Error {
         idx: usize,
         parent_height: u64,
     },
+    /// The CCS transfers are paused by the params.
+    CCSTransfersDisabled,
+    /// The self-nomination is not newer than the last one accepted from the account.
+    StaleNomination {
+        address: Address,
+        /// The sequence of the last accepted nomination
+        last_seq: u64,
+        /// 0 if the nomination has no sequence
+        got: u64,
+    },
 }
 
 const ERROR_ID_ASSET_NOT_FOUND: u8 = 1;
@@ -156,6 +166,8 @@ const ERROR_ID_FAILED_TO_HANDLE_CUSTOM_ACTION: u8 = 31;
 const ERROR_ID_SIGNATURE_OF_INVALID_ACCOUNT: u8 = 32;
 const ERROR_ID_INSUFFICIENT_STAKES: u8 = 33;
 const ERROR_ID_INVALID_VALIDATOR_INDEX: u8 = 34;
+const ERROR_ID_CCS_TRANSFERS_DISABLED: u8 = 35;
+const ERROR_ID_STALE_NOMINATION: u8 = 36;
 
 struct RlpHelper;
 impl TaggedRlp for RlpHelper {
@@ -196,6 +208,8 @@ impl TaggedRlp for RlpHelper {
             ERROR_ID_SIGNATURE_OF_INVALID_ACCOUNT => 2,
             ERROR_ID_INSUFFICIENT_STAKES => 3,
             ERROR_ID_INVALID_VALIDATOR_INDEX => 3,
+            ERROR_ID_CCS_TRANSFERS_DISABLED => 1,
+            ERROR_ID_STALE_NOMINATION => 4,
             _ => return Err(DecoderError::Custom("Invalid RuntimeError")),
         })
     }
@@ -327,6 +341,12 @@ impl Encodable for Error {
                 idx,
                 parent_height,
             } => RlpHelper::new_tagged_list(s, ERROR_ID_INVALID_VALIDATOR_INDEX).append(idx).append(parent_height),
+            Error::CCSTransfersDisabled => RlpHelper::new_tagged_list(s, ERROR_ID_CCS_TRANSFERS_DISABLED),
+            Error::StaleNomination {
+                address,
+                last_seq,
+                got,
+            } => RlpHelper::new_tagged_list(s, ERROR_ID_STALE_NOMINATION).append(address).append(last_seq).append(got),
         };
     }
 }
@@ -419,6 +439,12 @@ impl Decodable for Error {
                 idx: rlp.val_at(1)?,
                 parent_height: rlp.val_at(2)?,
             },
+            ERROR_ID_CCS_TRANSFERS_DISABLED => Error::CCSTransfersDisabled,
+            ERROR_ID_STALE_NOMINATION => Error::StaleNomination {
+                address: rlp.val_at(1)?,
+                last_seq: rlp.val_at(2)?,
+                got: rlp.val_at(3)?,
+            },
             _ => return Err(DecoderError::Custom("Invalid RuntimeError")),
         };
         RlpHelper::check_size(rlp, tag)?;
@@ -520,6 +546,10 @@ impl Display for Error {
             Error::InvalidValidatorIndex {
                 idx, parent_height,
             } =>  write!(f, "The validator index {} is invalid at the parent hash {}", idx, parent_height),
+            Error::CCSTransfersDisabled => write!(f, "The CCS transfers are disabled"),
+            Error::StaleNomination {
+                address, last_seq, got,
+            } => write!(f, "The nomination of {} is stale: the sequence must be larger than {}, but {}", address, last_seq, got),
         }
     }
 }
@@ -184,6 +184,60 @@ impl Action {
         self.asset_transaction().map(|tx| tx.tracker())
     }
 
+    /// The type of the action as the RPCs call it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MintAsset {
+                ..
+            } => "mintAsset",
+            Action::TransferAsset {
+                ..
+            } => "transferAsset",
+            Action::ChangeAssetScheme {
+                ..
+            } => "changeAssetScheme",
+            Action::IncreaseAssetSupply {
+                ..
+            } => "increaseAssetSupply",
+            Action::ComposeAsset {
+                ..
+            } => "composeAsset",
+            Action::DecomposeAsset {
+                ..
+            } => "decomposeAsset",
+            Action::UnwrapCCC {
+                ..
+            } => "unwrapCCC",
+            Action::Pay {
+                ..
+            } => "pay",
+            Action::SetRegularKey {
+                ..
+            } => "setRegularKey",
+            Action::CreateShard {
+                ..
+            } => "createShard",
+            Action::SetShardOwners {
+                ..
+            } => "setShardOwners",
+            Action::SetShardUsers {
+                ..
+            } => "setShardUsers",
+            Action::WrapCCC {
+                ..
+            } => "wrapCCC",
+            Action::Custom {
+                ..
+            } => "custom",
+            Action::Store {
+                ..
+            } => "store",
+            Action::Remove {
+                ..
+            } => "remove",
+        }
+    }
+
     pub fn verify(&self) -> Result<(), SyntaxError> {
         match self {
             Action::MintAsset {
@@ -18,7 +18,7 @@ extern crate kvdb;
 extern crate parking_lot;
 extern crate rlp;
 
-use kvdb::{DBOp, DBTransaction, DBValue, KeyValueDB, KeyValueDBIterator, Result};
+use kvdb::{ColumnEstimates, DBOp, DBTransaction, DBValue, KeyValueDB, KeyValueDBIterator, Result};
 use parking_lot::RwLock;
 use rlp::{Compressible, RlpType, UntrustedRlp};
 use std::collections::{BTreeMap, HashMap};
@@ -123,7 +123,7 @@ impl KeyValueDB for InMemory {
             Some(map) => Box::new(
                 map.clone()
                     .into_iter()
-                    .skip_while(move |&(ref k, _)| !k.starts_with(prefix))
+                    .skip_while(move |&(ref k, _)| k.as_slice() < prefix)
                     .map(|(k, v)| (k.into_boxed_slice(), v.into_vec().into_boxed_slice())),
             ),
             None => Box::new(None.into_iter()),
@@ -133,4 +133,13 @@ impl KeyValueDB for InMemory {
     fn restore(&self, _new_db: &str) -> Result<()> {
         Err("Attempted to restore in-memory database".into())
     }
+
+    fn column_estimates(&self, col: Option<u32>) -> Option<ColumnEstimates> {
+        // The exact numbers are cheap to count in memory.
+        self.columns.read().get(&col).map(|map| ColumnEstimates {
+            keys: Some(map.len() as u64),
+            size: Some(map.iter().map(|(key, value)| (key.len() + value.len()) as u64).sum()),
+            pending_compaction_bytes: None,
+        })
+    }
 }
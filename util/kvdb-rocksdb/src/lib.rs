@@ -41,7 +41,7 @@ use rocksdb::{
 };
 
 use elastic_array::ElasticArray32;
-use kvdb::{DBOp, DBTransaction, DBValue, KeyValueDB, KeyValueDBIterator, Result};
+use kvdb::{ColumnEstimates, DBOp, DBTransaction, DBValue, KeyValueDB, KeyValueDBIterator, Result};
 use rlp::{Compressible, RlpType, UntrustedRlp};
 
 #[cfg(target_os = "linux")]
@@ -634,6 +634,39 @@ impl Database {
         }
     }
 
+    /// Get an integer property of a column, e.g. "rocksdb.estimate-num-keys".
+    /// None if the database is closed or RocksDB doesn't know the property.
+    fn int_property(&self, col: Option<u32>, name: &str) -> Option<u64> {
+        match *self.db.read() {
+            Some(DBAndColumns {
+                ref db,
+                ref cfs,
+            }) => col
+                .map_or_else(|| db.property_int_value(name), |c| db.property_int_value_cf(cfs[c as usize], name))
+                .unwrap_or(None),
+            None => None,
+        }
+    }
+
+    /// The statistics that RocksDB estimates for the column without reading it.
+    pub fn column_estimates(&self, col: Option<u32>) -> Option<ColumnEstimates> {
+        if self.db.read().is_none() {
+            return None
+        }
+        let files = self.int_property(col, "rocksdb.total-sst-files-size");
+        let memtables = self.int_property(col, "rocksdb.cur-size-all-mem-tables");
+        Some(ColumnEstimates {
+            keys: self.int_property(col, "rocksdb.estimate-num-keys"),
+            size: files.and_then(|files| memtables.map(|memtables| files + memtables)),
+            pending_compaction_bytes: self.int_property(col, "rocksdb.estimate-pending-compaction-bytes"),
+        })
+    }
+
+    /// The number of the compactions that are running now.
+    pub fn running_compactions(&self) -> Option<u64> {
+        self.int_property(None, "rocksdb.num-running-compactions")
+    }
+
     fn iter_from_prefix(&self, col: Option<u32>, prefix: &[u8]) -> Option<DatabaseIterator> {
         match *self.db.read() {
             Some(DBAndColumns {
@@ -801,6 +834,14 @@ impl KeyValueDB for Database {
     fn restore(&self, new_db: &str) -> Result<()> {
         Database::restore(self, new_db)
     }
+
+    fn column_estimates(&self, col: Option<u32>) -> Option<ColumnEstimates> {
+        Database::column_estimates(self, col)
+    }
+
+    fn running_compactions(&self) -> Option<u64> {
+        Database::running_compactions(self)
+    }
 }
 
 impl Drop for Database {
@@ -879,6 +920,26 @@ mod tests {
         test_db(&DatabaseConfig::default());
     }
 
+    #[test]
+    fn column_estimates() {
+        let tempdir = TempDir::new("").unwrap();
+        let db = Database::open(&DatabaseConfig::with_columns(Some(1)), tempdir.path().to_str().unwrap()).unwrap();
+        let mut transaction = db.transaction();
+        for i in 0..100u64 {
+            transaction.put(Some(0), &H256::from(i), b"value");
+        }
+        db.write(transaction).unwrap();
+
+        let estimates = db.column_estimates(Some(0)).unwrap();
+        assert_ne!(0, estimates.keys.unwrap());
+        assert_ne!(0, estimates.size.unwrap());
+        assert!(estimates.pending_compaction_bytes.is_some());
+        assert!(db.running_compactions().is_some());
+
+        db.close();
+        assert_eq!(None, db.column_estimates(Some(0)));
+    }
+
     #[test]
     #[cfg(target_os = "linux")]
     fn df_to_rotational() {
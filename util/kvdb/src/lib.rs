@@ -181,6 +181,28 @@ pub trait KeyValueDB: Sync + Send {
 
     /// Attempt to replace this database with a new one located at the given path.
     fn restore(&self, new_db: &str) -> Result<()>;
+
+    /// The statistics that the backend keeps for a given column. None if the backend doesn't keep them.
+    /// It must not walk the column.
+    fn column_estimates(&self, _col: Option<u32>) -> Option<ColumnEstimates> {
+        None
+    }
+
+    /// The number of the running compactions. None if the backend doesn't compact.
+    fn running_compactions(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// The statistics of a column that are estimated by the backend.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ColumnEstimates {
+    /// The estimated number of the keys
+    pub keys: Option<u64>,
+    /// The approximate size of the column in the files and the memory tables, in bytes
+    pub size: Option<u64>,
+    /// The estimated number of the bytes that the compactions have to rewrite
+    pub pending_compaction_bytes: Option<u64>,
 }
 
 pub type KeyValueDBIterator<'a> = Box<Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a>;
@@ -29,14 +29,19 @@ extern crate time;
 mod email;
 mod logger;
 mod macros;
+mod rate_limit;
 mod structured_logger;
 
 use log::SetLoggerError;
 
+#[doc(hidden)]
+pub use logger::log_with_fields;
+pub use logger::set_log_level;
 pub use logger::Config as LoggerConfig;
+pub use rate_limit::LogRateLimiter;
 use logger::Logger;
 
-pub use log::Level;
+pub use log::{Level, LevelFilter};
 
 pub fn init(config: &LoggerConfig, email_alarm: Option<EmailAlarm>) -> Result<(), SetLoggerError> {
     let logger = Logger::new(config, email_alarm);
@@ -14,38 +14,100 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
+use std::io::{self, Write};
 use std::thread;
 use time;
 
 use atty;
 use colored::Colorize;
 use env_logger::filter::{Builder as FilterBuilder, Filter};
+use lazy_static::lazy_static;
 use log::{Level, LevelFilter, Log, Metadata, Record};
+use parking_lot::{Mutex, RwLock};
+use serde_derive::Serialize;
+use serde_json;
 
 use crate::{email::EmailAlarm, structured_logger, SLOGGER};
 
 pub struct Config {
     pub instance_id: usize,
+    /// Print each record as a JSON object instead of the human readable line.
+    pub json: bool,
 }
 
 impl Config {
-    pub fn new(instance_id: usize) -> Self {
+    pub fn new(instance_id: usize, json: bool) -> Self {
         Self {
             instance_id,
+            json,
         }
     }
 }
 
+lazy_static! {
+    /// The levels set at runtime. They take precedence over the filter built from `RUST_LOG`.
+    static ref TARGET_LEVELS: RwLock<HashMap<String, LevelFilter>> = RwLock::new(HashMap::new());
+}
+
+thread_local! {
+    static FIELDS: RefCell<Vec<(&'static str, String)>> = RefCell::new(Vec::new());
+}
+
+/// Changes the level of the target and its sub-targets without restarting the node.
+pub fn set_log_level(target: &str, level: LevelFilter) {
+    TARGET_LEVELS.write().insert(target.to_string(), level);
+    if level > log::max_level() {
+        log::set_max_level(level);
+    }
+}
+
+fn target_level(target: &str) -> Option<LevelFilter> {
+    let levels = TARGET_LEVELS.read();
+    levels
+        .iter()
+        .filter(|(name, _)| target == name.as_str() || target.starts_with(&format!("{}::", name)))
+        .max_by_key(|(name, _)| name.len())
+        .map(|(_, level)| *level)
+}
+
+/// Used by `clog!` to attach the fields to the records logged in `f`.
+#[doc(hidden)]
+pub fn log_with_fields<F: FnOnce()>(fields: Vec<(&'static str, String)>, f: F) {
+    FIELDS.with(|cell| *cell.borrow_mut() = fields);
+    f();
+    FIELDS.with(|cell| cell.borrow_mut().clear());
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonRecord<'a> {
+    timestamp: String,
+    level: String,
+    target: &'a str,
+    message: String,
+    thread_name: &'a str,
+    instance_id: usize,
+    fields: BTreeMap<&'static str, String>,
+}
+
 pub struct Logger {
     instance_id: usize,
     filter: Filter,
+    json: bool,
     stderr_is_tty: bool,
     email_alarm: Option<EmailAlarm>,
+    output: Mutex<Box<Write + Send>>,
 }
 
 impl Logger {
     pub fn new(config: &Config, email_alarm: Option<EmailAlarm>) -> Self {
+        Self::with_output(config, email_alarm, Box::new(io::stderr()))
+    }
+
+    fn with_output(config: &Config, email_alarm: Option<EmailAlarm>, output: Box<Write + Send>) -> Self {
         let mut builder = FilterBuilder::new();
         builder.filter(None, LevelFilter::Info);
 
@@ -58,44 +120,86 @@ impl Logger {
         Self {
             instance_id: config.instance_id,
             filter: builder.build(),
+            json: config.json,
             stderr_is_tty,
             email_alarm,
+            output: Mutex::new(output),
         }
     }
 
     pub fn filter(&self) -> LevelFilter {
         self.filter.filter()
     }
+
+    fn write_json(&self, record: &Record, thread_name: &str) {
+        let rfc3339with_nano_second = "%Y-%m-%dT%H:%M:%S.%f%z";
+        let json_record = JsonRecord {
+            timestamp: time::strftime(rfc3339with_nano_second, &time::now()).unwrap(),
+            level: record.level().to_string(),
+            target: record.target(),
+            message: record.args().to_string(),
+            thread_name,
+            instance_id: self.instance_id,
+            fields: FIELDS.with(|cell| cell.borrow().iter().cloned().collect()),
+        };
+        let line = serde_json::to_string(&json_record).expect("Log only has serializable fields. It always success");
+        let _ = writeln!(self.output.lock(), "{}", line);
+    }
+
+    fn write_plain(&self, record: &Record, thread_name: &str) {
+        let timestamp = time::strftime("%Y-%m-%d %H:%M:%S.%f %Z", &time::now()).unwrap();
+
+        let instance_id = self.instance_id;
+        let timestamp = if self.stderr_is_tty {
+            timestamp.bold()
+        } else {
+            timestamp.normal()
+        };
+        let colored_thread_name = if self.stderr_is_tty {
+            thread_name.blue().bold()
+        } else {
+            thread_name.normal()
+        };
+        let fields = FIELDS
+            .with(|cell| cell.borrow().iter().map(|(key, value)| format!(" {}={}", key, value)).collect::<String>());
+        let _ = writeln!(
+            self.output.lock(),
+            "#{} {} {} {} {}  {}{}",
+            instance_id,
+            timestamp,
+            colored_thread_name,
+            record.level(),
+            record.target(),
+            record.args(),
+            fields
+        );
+    }
 }
 
 impl Log for Logger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        self.filter.enabled(metadata)
+        match target_level(metadata.target()) {
+            Some(level) => metadata.level() <= level,
+            None => self.filter.enabled(metadata),
+        }
     }
 
     fn log(&self, record: &Record) {
-        if self.filter.matches(record) {
+        let matches = match target_level(record.target()) {
+            Some(level) => record.level() <= level,
+            None => self.filter.matches(record),
+        };
+        if matches {
             let thread_name = thread::current().name().unwrap_or_default().to_string();
-            let timestamp = time::strftime("%Y-%m-%d %H:%M:%S.%f %Z", &time::now()).unwrap();
-
-            let instance_id = self.instance_id;
-            let timestamp = if self.stderr_is_tty {
-                timestamp.bold()
-            } else {
-                timestamp.normal()
-            };
-            let colored_thread_name = if self.stderr_is_tty {
-                thread_name.blue().bold()
+            if self.json {
+                self.write_json(record, &thread_name);
             } else {
-                thread_name.normal()
-            };
+                self.write_plain(record, &thread_name);
+            }
+
             let log_level = record.level();
             let log_target = record.target();
             let log_message = record.args();
-            eprintln!(
-                "#{} {} {} {} {}  {}",
-                instance_id, timestamp, colored_thread_name, log_level, log_target, log_message
-            );
 
             let rfc3339with_nano_second = "%Y-%m-%dT%H:%M:%S.%f%z";
             let timestamp = time::strftime(rfc3339with_nano_second, &time::now()).unwrap();
@@ -118,3 +222,88 @@ impl Log for Logger {
 
     fn flush(&self) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use log::RecordBuilder;
+    use serde_json::Value;
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Buffer {
+        fn lines(&self) -> Vec<String> {
+            String::from_utf8(self.0.lock().clone()).unwrap().lines().map(ToString::to_string).collect()
+        }
+    }
+
+    fn json_logger() -> (Logger, Buffer) {
+        let buffer = Buffer::default();
+        let logger = Logger::with_output(&Config::new(3, true), None, Box::new(buffer.clone()));
+        (logger, buffer)
+    }
+
+    fn log(logger: &Logger, level: Level, target: &str, message: &str) {
+        logger.log(&RecordBuilder::new().level(level).target(target).args(format_args!("{}", message)).build());
+    }
+
+    #[test]
+    fn json_record() {
+        let (logger, buffer) = json_logger();
+        log_with_fields(vec![("height", 3.to_string()), ("peer", "127.0.0.1:3485".to_string())], || {
+            log(&logger, Level::Warn, "json_record_test", "A \"quoted\" message");
+        });
+        log(&logger, Level::Info, "json_record_test", "No fields");
+
+        let lines = buffer.lines();
+        assert_eq!(2, lines.len());
+        let record: Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!("WARN", record["level"]);
+        assert_eq!("json_record_test", record["target"]);
+        assert_eq!("A \"quoted\" message", record["message"]);
+        assert_eq!(3, record["instanceId"]);
+        assert_eq!("3", record["fields"]["height"]);
+        assert_eq!("127.0.0.1:3485", record["fields"]["peer"]);
+        assert!(record["timestamp"].is_string());
+
+        let record: Value = serde_json::from_str(&lines[1]).unwrap();
+        assert_eq!("No fields", record["message"]);
+        assert_eq!(Value::Object(Default::default()), record["fields"]);
+    }
+
+    #[test]
+    fn change_level_at_runtime() {
+        let (logger, buffer) = json_logger();
+        log(&logger, Level::Debug, "runtime_level_test", "Filtered by default");
+        assert!(buffer.lines().is_empty());
+
+        set_log_level("runtime_level_test", LevelFilter::Trace);
+        log(&logger, Level::Debug, "runtime_level_test", "Debug");
+        log(&logger, Level::Trace, "runtime_level_test::sub", "Trace of the sub-target");
+        log(&logger, Level::Debug, "runtime_level_test_other", "Other target");
+        assert_eq!(2, buffer.lines().len());
+
+        set_log_level("runtime_level_test", LevelFilter::Error);
+        log(&logger, Level::Warn, "runtime_level_test", "Filtered");
+        log(&logger, Level::Error, "runtime_level_test", "Error");
+        let lines = buffer.lines();
+        assert_eq!(3, lines.len());
+        let record: Value = serde_json::from_str(&lines[2]).unwrap();
+        assert_eq!("Error", record["message"]);
+    }
+}
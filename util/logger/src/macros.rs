@@ -37,9 +37,15 @@ macro_rules! log_target {
     (IO) => {
         "io"
     };
+    (LIGHT) => {
+        "light"
+    };
     (MEM_POOL) => {
         "mem_pool"
     };
+    (METRICS) => {
+        "metrics"
+    };
     (MINER) => {
         "miner"
     };
@@ -99,8 +105,17 @@ macro_rules! log_target {
     };
 }
 
+/// Fields can be attached to a record as `cinfo!(ENGINE, { height: height, view: view }, "message")`.
+/// The values of the fields are formatted with `Display`.
 #[macro_export]
 macro_rules! clog {
+    ($target:ident, $lvl:expr, { $($key:ident : $value:expr),+ }, $($arg:tt)+) => ({
+        if log_enabled!(target: log_target!($target), $lvl) {
+            $crate::log_with_fields(vec![$((stringify!($key), format!("{}", $value))),+], || {
+                log!(target: log_target!($target), $lvl, $($arg)*);
+            });
+        }
+    });
     ($target:ident, $lvl:expr, $($arg:tt)+) => ({
         log!(target: log_target!($target), $lvl, $($arg)*);
     });
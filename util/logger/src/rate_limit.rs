@@ -0,0 +1,113 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// Lets a log message of each key through at most once per window, so a peer cannot flood the logs.
+/// The key is usually the peer and the class of the error.
+pub struct LogRateLimiter<K: Eq + Hash> {
+    window: Duration,
+    windows: HashMap<K, Window>,
+}
+
+struct Window {
+    opened_at: Instant,
+    suppressed: usize,
+}
+
+impl<K: Eq + Hash> LogRateLimiter<K> {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            windows: HashMap::new(),
+        }
+    }
+
+    /// Returns None if the message must be suppressed.
+    /// Otherwise, returns the number of the messages suppressed in the last window of the key,
+    /// which should be included in the message.
+    pub fn check(&mut self, key: K, now: Instant) -> Option<usize> {
+        match self.windows.entry(key) {
+            Entry::Vacant(entry) => {
+                entry.insert(Window {
+                    opened_at: now,
+                    suppressed: 0,
+                });
+                Some(0)
+            }
+            Entry::Occupied(mut entry) => {
+                let window = entry.get_mut();
+                if now.duration_since(window.opened_at) < self.window {
+                    window.suppressed += 1;
+                    return None
+                }
+                let suppressed = window.suppressed;
+                *window = Window {
+                    opened_at: now,
+                    suppressed: 0,
+                };
+                Some(suppressed)
+            }
+        }
+    }
+
+    /// The number of the messages suppressed in the current window of the key.
+    pub fn suppressed(&self, key: &K) -> usize {
+        self.windows.get(key).map_or(0, |window| window.suppressed)
+    }
+
+    /// Forgets the keys, e.g. of a disconnected peer. Their suppressed messages are not reported.
+    pub fn forget<F: Fn(&K) -> bool>(&mut self, f: F) {
+        self.windows.retain(|key, _| !f(key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_message_per_window_for_each_key() {
+        let mut limiter = LogRateLimiter::new(Duration::from_secs(60));
+        let now = Instant::now();
+        assert_eq!(Some(0), limiter.check(("peer1", "class1"), now));
+        assert_eq!(Some(0), limiter.check(("peer1", "class2"), now));
+        assert_eq!(Some(0), limiter.check(("peer2", "class1"), now));
+        for i in 1..1000 {
+            assert_eq!(None, limiter.check(("peer1", "class1"), now + Duration::from_millis(i)));
+        }
+        assert_eq!(999, limiter.suppressed(&("peer1", "class1")));
+        assert_eq!(0, limiter.suppressed(&("peer2", "class1")));
+
+        let next_window = now + Duration::from_secs(60);
+        assert_eq!(Some(999), limiter.check(("peer1", "class1"), next_window));
+        assert_eq!(None, limiter.check(("peer1", "class1"), next_window));
+        assert_eq!(Some(0), limiter.check(("peer2", "class1"), next_window));
+    }
+
+    #[test]
+    fn forgotten_key_starts_over() {
+        let mut limiter = LogRateLimiter::new(Duration::from_secs(60));
+        let now = Instant::now();
+        assert_eq!(Some(0), limiter.check(("peer1", "class1"), now));
+        assert_eq!(None, limiter.check(("peer1", "class1"), now));
+        limiter.forget(|(peer, _)| *peer == "peer1");
+        assert_eq!(Some(0), limiter.check(("peer1", "class1"), now));
+    }
+}
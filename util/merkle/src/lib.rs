@@ -33,12 +33,14 @@ use primitives::H256;
 
 mod nibbleslice;
 pub mod node;
+mod proof;
 mod skewed;
 pub mod triedb;
 pub mod triedbmut;
 pub mod triehash;
 
 pub use crate::node::Node;
+pub use crate::proof::{prove, verify_proof};
 pub use crate::skewed::skewed_merkle_root;
 pub use crate::triedb::TrieDB;
 pub use crate::triedbmut::TrieDBMut;
@@ -0,0 +1,154 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use std::collections::HashMap;
+
+use ccrypto::{blake256, BLAKE_NULL_RLP};
+use hashdb::HashDB;
+use primitives::{Bytes, H256};
+
+use crate::nibbleslice::NibbleSlice;
+use crate::node::Node as RlpNode;
+use crate::{Result, TrieError};
+
+/// Returns the nodes on the path from the root to the key.
+///
+/// They prove the value of the key, or that the trie doesn't have the key.
+pub fn prove(db: &HashDB, root: &H256, key: &[u8]) -> Result<Vec<Bytes>> {
+    let mut proof = Vec::new();
+    if *root == BLAKE_NULL_RLP {
+        return Ok(proof)
+    }
+    walk(root, key, |hash| {
+        let node = db.get(hash).ok_or_else(|| TrieError::IncompleteDatabase(*hash))?;
+        proof.push(node.to_vec());
+        Ok(node.to_vec())
+    })?;
+    Ok(proof)
+}
+
+/// Returns the value of the key that the proof shows, or None if the proof shows that the key is absent.
+///
+/// The nodes that the path doesn't reach are ignored. It fails if a node on the path is not in the proof.
+pub fn verify_proof(root: &H256, key: &[u8], proof: &[Bytes]) -> Result<Option<Bytes>> {
+    if *root == BLAKE_NULL_RLP {
+        return Ok(None)
+    }
+    let nodes: HashMap<H256, &Bytes> = proof.iter().map(|node| (blake256(node), node)).collect();
+    walk(root, key, |hash| {
+        nodes.get(hash).map(|node| node.to_vec()).ok_or_else(|| TrieError::IncompleteDatabase(*hash))
+    })
+}
+
+fn walk<F>(root: &H256, key: &[u8], mut node_of: F) -> Result<Option<Bytes>>
+where
+    F: FnMut(&H256) -> Result<Bytes>, {
+    let path = blake256(key);
+    let mut path = NibbleSlice::new(&path);
+    let mut hash = *root;
+    loop {
+        let node_rlp = node_of(&hash)?;
+        let next = match RlpNode::decoded(&node_rlp) {
+            Some(RlpNode::Leaf(partial, value)) => {
+                return Ok(if partial == path {
+                    Some(value.to_vec())
+                } else {
+                    None
+                })
+            }
+            Some(RlpNode::Branch(partial, children)) => {
+                if !path.starts_with(&partial) {
+                    return Ok(None)
+                }
+                match children[path.mid(partial.len()).at(0) as usize] {
+                    Some(child) => (child, partial.len() + 1),
+                    None => return Ok(None),
+                }
+            }
+            None => return Ok(None),
+        };
+        hash = next.0;
+        path = path.mid(next.1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use memorydb::MemoryDB;
+    use standardmap::*;
+
+    use super::*;
+    use crate::{TrieDBMut, TrieMut};
+
+    fn populated_trie(memdb: &mut MemoryDB, root: &mut H256) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let pairs = StandardMap {
+            alphabet: Alphabet::All,
+            min_key: 32,
+            journal_key: 0,
+            value_mode: ValueMode::Index,
+            count: 500,
+        }
+        .make_with(&mut H256::default());
+        let mut t = TrieDBMut::new(memdb, root);
+        for (key, value) in &pairs {
+            t.insert(key, value).unwrap();
+        }
+        pairs
+    }
+
+    #[test]
+    fn proof_shows_the_value() {
+        let mut memdb = MemoryDB::new();
+        let mut root = H256::new();
+        let pairs = populated_trie(&mut memdb, &mut root);
+
+        for (key, value) in pairs.iter().take(20) {
+            let proof = prove(&memdb, &root, key).unwrap();
+            assert_eq!(Ok(Some(value.clone())), verify_proof(&root, key, &proof));
+        }
+    }
+
+    #[test]
+    fn proof_shows_the_absence() {
+        let mut memdb = MemoryDB::new();
+        let mut root = H256::new();
+        populated_trie(&mut memdb, &mut root);
+
+        let proof = prove(&memdb, &root, b"absent").unwrap();
+        assert_eq!(Ok(None), verify_proof(&root, b"absent", &proof));
+        assert_eq!(Ok(None), verify_proof(&BLAKE_NULL_RLP, b"absent", &[]));
+    }
+
+    #[test]
+    fn incomplete_or_tampered_proof_is_rejected() {
+        let mut memdb = MemoryDB::new();
+        let mut root = H256::new();
+        let pairs = populated_trie(&mut memdb, &mut root);
+        let (key, _) = &pairs[0];
+
+        let mut proof = prove(&memdb, &root, key).unwrap();
+        assert!(proof.len() > 1);
+        let leaf = proof.pop().unwrap();
+        assert!(verify_proof(&root, key, &proof).is_err());
+
+        let mut tampered = leaf.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        proof.push(tampered);
+        assert!(verify_proof(&root, key, &proof).is_err());
+    }
+}
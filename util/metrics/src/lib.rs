@@ -0,0 +1,36 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+#[macro_use]
+extern crate lazy_static;
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate codechain_logger as clogger;
+extern crate parking_lot;
+
+mod metric;
+mod registry;
+mod server;
+
+pub use crate::metric::{Counter, Gauge, Histogram, Summary};
+pub use crate::registry::{Collector, ExtensionTraffic, Registry, BLOCK_IMPORT_PHASES, ENGINE_EVENT_CLASSES};
+pub use crate::server::{start_http, HealthCheck, Server};
+
+lazy_static! {
+    /// The registry which every component pushes its metrics into.
+    pub static ref METRICS: Registry = Registry::default();
+}
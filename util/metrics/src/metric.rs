@@ -0,0 +1,192 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// A value that only increases.
+#[derive(Default)]
+pub struct Counter(AtomicUsize);
+
+impl Counter {
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    pub fn add(&self, n: usize) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A value that can go up and down.
+#[derive(Default)]
+pub struct Gauge(AtomicUsize);
+
+impl Gauge {
+    pub fn set(&self, value: usize) {
+        self.0.store(value, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Upper bounds of the histogram buckets. Unit: microsecond
+const BUCKETS: [usize; 12] = [100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 1_000_000];
+
+/// Samples durations into the fixed buckets.
+///
+/// Each bucket counts the samples which are less than or equal to its bound,
+/// so the rendered buckets are cumulative as Prometheus expects.
+#[derive(Default)]
+pub struct Histogram {
+    buckets: [Counter; 12],
+    count: Counter,
+    sum_in_micros: Counter,
+}
+
+impl Histogram {
+    pub fn observe(&self, duration: Duration) {
+        let micros = duration.as_secs() as usize * 1_000_000 + duration.subsec_micros() as usize;
+        for (bound, bucket) in BUCKETS.iter().zip(self.buckets.iter()) {
+            if micros <= *bound {
+                bucket.inc();
+            }
+        }
+        self.count.inc();
+        self.sum_in_micros.add(micros);
+    }
+
+    pub fn count(&self) -> usize {
+        self.count.get()
+    }
+
+    /// Returns pairs of the upper bound in seconds and the cumulative count.
+    pub fn buckets(&self) -> Vec<(f64, usize)> {
+        BUCKETS.iter().zip(self.buckets.iter()).map(|(bound, bucket)| (micros_to_secs(*bound), bucket.get())).collect()
+    }
+
+    /// Unit: second
+    pub fn sum(&self) -> f64 {
+        micros_to_secs(self.sum_in_micros.get())
+    }
+}
+
+/// The number of the latest samples that a summary keeps.
+const SUMMARY_WINDOW: usize = 256;
+
+/// Reports the quantiles of the latest samples.
+///
+/// The samples are kept in a ring allocated once, so observing doesn't allocate.
+pub struct Summary {
+    samples_in_micros: Vec<AtomicUsize>,
+    count: Counter,
+    sum_in_micros: Counter,
+}
+
+impl Default for Summary {
+    fn default() -> Self {
+        Self {
+            samples_in_micros: (0..SUMMARY_WINDOW).map(|_| AtomicUsize::new(0)).collect(),
+            count: Counter::default(),
+            sum_in_micros: Counter::default(),
+        }
+    }
+}
+
+impl Summary {
+    pub fn observe(&self, duration: Duration) {
+        let micros = duration.as_secs() as usize * 1_000_000 + duration.subsec_micros() as usize;
+        let index = self.count.0.fetch_add(1, Ordering::Relaxed) % SUMMARY_WINDOW;
+        self.samples_in_micros[index].store(micros, Ordering::Relaxed);
+        self.sum_in_micros.add(micros);
+    }
+
+    pub fn count(&self) -> usize {
+        self.count.get()
+    }
+
+    /// Unit: second
+    pub fn sum(&self) -> f64 {
+        micros_to_secs(self.sum_in_micros.get())
+    }
+
+    /// Returns the nearest-rank quantiles of the samples in the window. Unit: second
+    pub fn quantiles(&self, quantiles: &[f64]) -> Vec<Option<f64>> {
+        let len = self.count().min(SUMMARY_WINDOW);
+        let mut samples: Vec<usize> =
+            self.samples_in_micros[..len].iter().map(|sample| sample.load(Ordering::Relaxed)).collect();
+        samples.sort_unstable();
+        quantiles
+            .iter()
+            .map(|quantile| {
+                if samples.is_empty() {
+                    return None
+                }
+                let rank = (quantile * samples.len() as f64).ceil() as usize;
+                Some(micros_to_secs(samples[rank.max(1).min(samples.len()) - 1]))
+            })
+            .collect()
+    }
+}
+
+fn micros_to_secs(micros: usize) -> f64 {
+    micros as f64 / 1_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let histogram = Histogram::default();
+        histogram.observe(Duration::from_micros(90));
+        histogram.observe(Duration::from_millis(3));
+        histogram.observe(Duration::from_secs(2));
+
+        let buckets = histogram.buckets();
+        assert_eq!((0.0001, 1), buckets[0]);
+        assert_eq!((0.0025, 1), buckets[4]);
+        assert_eq!((0.005, 2), buckets[5]);
+        assert_eq!((1.0, 2), buckets[11]);
+        assert_eq!(3, histogram.count());
+        assert!((histogram.sum() - 2.003_09).abs() < 1e-9);
+    }
+
+    #[test]
+    fn summary_reports_the_quantiles_of_the_latest_samples() {
+        let summary = Summary::default();
+        assert_eq!(vec![None], summary.quantiles(&[0.5]));
+
+        for millis in 1..=100 {
+            summary.observe(Duration::from_millis(millis));
+        }
+        assert_eq!(vec![Some(0.05), Some(0.09), Some(0.1)], summary.quantiles(&[0.5, 0.9, 1.0]));
+
+        // The oldest samples are overwritten.
+        for _ in 0..SUMMARY_WINDOW {
+            summary.observe(Duration::from_secs(1));
+        }
+        assert_eq!(vec![Some(1.0)], summary.quantiles(&[0.01]));
+        assert_eq!(100 + SUMMARY_WINDOW, summary.count());
+    }
+}
@@ -0,0 +1,244 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use parking_lot::RwLock;
+
+use crate::metric::{Counter, Gauge, Histogram, Summary};
+
+/// The phases of the block import, in the order of the pipeline.
+pub const BLOCK_IMPORT_PHASES: [&str; 7] =
+    ["decode", "header_verification", "seal_verification", "body_verification", "execution", "trie_commit", "db_write"];
+
+/// The classes of the events handled by the Tendermint worker, in the order of their priorities.
+pub const ENGINE_EVENT_CLASSES: [&str; 2] = ["consensus", "sync"];
+
+const QUANTILES: [f64; 3] = [0.5, 0.9, 0.99];
+
+/// Bytes sent and received by a network extension.
+pub struct ExtensionTraffic {
+    name: &'static str,
+    pub bytes_in: Counter,
+    pub bytes_out: Counter,
+}
+
+impl ExtensionTraffic {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// The metrics kept by another component, whose labels are known only at runtime.
+pub trait Collector: Send + Sync {
+    /// Appends the metrics in the Prometheus text exposition format.
+    fn render(&self, out: &mut String);
+}
+
+/// Every field is updated with atomic operations, so the hot paths never wait for a lock.
+/// Only registering a network extension or a collector and rendering take the lock.
+#[derive(Default)]
+pub struct Registry {
+    pub height: Gauge,
+    pub view: Gauge,
+    step: Gauge,
+    /// Unit: millisecond since the UNIX epoch
+    step_started_at: Gauge,
+    pub mem_pool_size: Gauge,
+    /// Unit: byte
+    pub mem_pool_memory: Gauge,
+    pub peer_count: Gauge,
+    pub block_import_time: Histogram,
+    /// Per block, in the order of `BLOCK_IMPORT_PHASES`
+    pub block_import_phase_time: [Summary; 7],
+    pub signature_verification_time: Histogram,
+    /// The number of the events waiting for the Tendermint worker, in the order of `ENGINE_EVENT_CLASSES`
+    pub engine_queue_depth: [Gauge; 2],
+    /// Per event, in the order of `ENGINE_EVENT_CLASSES`
+    pub engine_event_time: [Summary; 2],
+    extensions: RwLock<Vec<Arc<ExtensionTraffic>>>,
+    collectors: RwLock<Vec<Arc<Collector>>>,
+}
+
+impl Registry {
+    /// `step` is the number of the Tendermint step: 0 for Propose, 1 for Prevote, 2 for Precommit and 3 for Commit.
+    pub fn set_consensus_state(&self, height: usize, view: usize, step: usize) {
+        self.height.set(height);
+        self.view.set(view);
+        self.step.set(step);
+        self.step_started_at.set(now_in_millis());
+    }
+
+    /// Returns the traffic counters of the extension, creating them on the first call.
+    pub fn register_extension(&self, name: &'static str) -> Arc<ExtensionTraffic> {
+        let mut extensions = self.extensions.write();
+        if let Some(traffic) = extensions.iter().find(|traffic| traffic.name == name) {
+            return Arc::clone(traffic)
+        }
+        let traffic = Arc::new(ExtensionTraffic {
+            name,
+            bytes_in: Counter::default(),
+            bytes_out: Counter::default(),
+        });
+        extensions.push(Arc::clone(&traffic));
+        traffic
+    }
+
+    /// The metrics of the collector are rendered after the others.
+    pub fn register_collector(&self, collector: Arc<Collector>) {
+        self.collectors.write().push(collector);
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        gauge(&mut out, "codechain_consensus_height", "The height of the consensus", self.height.get());
+        gauge(&mut out, "codechain_consensus_view", "The view of the consensus", self.view.get());
+        gauge(
+            &mut out,
+            "codechain_consensus_step",
+            "The step of the consensus. 0: Propose, 1: Prevote, 2: Precommit, 3: Commit",
+            self.step.get(),
+        );
+        let started_at = self.step_started_at.get();
+        let time_in_step = if started_at == 0 {
+            0.0
+        } else {
+            now_in_millis().saturating_sub(started_at) as f64 / 1000.0
+        };
+        writeln!(out, "# HELP codechain_consensus_time_in_step_seconds The time spent in the current step").unwrap();
+        writeln!(out, "# TYPE codechain_consensus_time_in_step_seconds gauge").unwrap();
+        writeln!(out, "codechain_consensus_time_in_step_seconds {}", time_in_step).unwrap();
+
+        gauge(
+            &mut out,
+            "codechain_mem_pool_size",
+            "The number of transactions in the mem pool",
+            self.mem_pool_size.get(),
+        );
+        gauge(
+            &mut out,
+            "codechain_mem_pool_memory_bytes",
+            "The memory used by the mem pool",
+            self.mem_pool_memory.get(),
+        );
+        gauge(&mut out, "codechain_peer_count", "The number of connected peers", self.peer_count.get());
+
+        {
+            let extensions = self.extensions.read();
+            writeln!(out, "# HELP codechain_extension_received_bytes_total The bytes received by the extension")
+                .unwrap();
+            writeln!(out, "# TYPE codechain_extension_received_bytes_total counter").unwrap();
+            for traffic in extensions.iter() {
+                writeln!(
+                    out,
+                    "codechain_extension_received_bytes_total{{extension=\"{}\"}} {}",
+                    traffic.name,
+                    traffic.bytes_in.get()
+                )
+                .unwrap();
+            }
+            writeln!(out, "# HELP codechain_extension_sent_bytes_total The bytes sent by the extension").unwrap();
+            writeln!(out, "# TYPE codechain_extension_sent_bytes_total counter").unwrap();
+            for traffic in extensions.iter() {
+                writeln!(
+                    out,
+                    "codechain_extension_sent_bytes_total{{extension=\"{}\"}} {}",
+                    traffic.name,
+                    traffic.bytes_out.get()
+                )
+                .unwrap();
+            }
+        }
+
+        histogram(
+            &mut out,
+            "codechain_block_import_seconds",
+            "The time spent importing blocks",
+            &self.block_import_time,
+        );
+        {
+            let name = "codechain_block_import_phase_seconds";
+            writeln!(out, "# HELP {} The time spent in each phase of the recent block imports", name).unwrap();
+            writeln!(out, "# TYPE {} summary", name).unwrap();
+            for (phase, summary) in BLOCK_IMPORT_PHASES.iter().zip(self.block_import_phase_time.iter()) {
+                for (quantile, value) in QUANTILES.iter().zip(summary.quantiles(&QUANTILES)) {
+                    if let Some(value) = value {
+                        writeln!(out, "{}{{phase=\"{}\",quantile=\"{}\"}} {}", name, phase, quantile, value).unwrap();
+                    }
+                }
+                writeln!(out, "{}_sum{{phase=\"{}\"}} {}", name, phase, summary.sum()).unwrap();
+                writeln!(out, "{}_count{{phase=\"{}\"}} {}", name, phase, summary.count()).unwrap();
+            }
+        }
+        histogram(
+            &mut out,
+            "codechain_signature_verification_seconds",
+            "The time spent verifying the signatures of a block",
+            &self.signature_verification_time,
+        );
+        {
+            let name = "codechain_engine_queue_depth";
+            writeln!(out, "# HELP {} The number of the events waiting for the consensus engine", name).unwrap();
+            writeln!(out, "# TYPE {} gauge", name).unwrap();
+            for (class, depth) in ENGINE_EVENT_CLASSES.iter().zip(self.engine_queue_depth.iter()) {
+                writeln!(out, "{}{{class=\"{}\"}} {}", name, class, depth.get()).unwrap();
+            }
+        }
+        {
+            let name = "codechain_engine_event_seconds";
+            writeln!(out, "# HELP {} The time spent handling the recent events of the consensus engine", name).unwrap();
+            writeln!(out, "# TYPE {} summary", name).unwrap();
+            for (class, summary) in ENGINE_EVENT_CLASSES.iter().zip(self.engine_event_time.iter()) {
+                for (quantile, value) in QUANTILES.iter().zip(summary.quantiles(&QUANTILES)) {
+                    if let Some(value) = value {
+                        writeln!(out, "{}{{class=\"{}\",quantile=\"{}\"}} {}", name, class, quantile, value).unwrap();
+                    }
+                }
+                writeln!(out, "{}_sum{{class=\"{}\"}} {}", name, class, summary.sum()).unwrap();
+                writeln!(out, "{}_count{{class=\"{}\"}} {}", name, class, summary.count()).unwrap();
+            }
+        }
+        for collector in self.collectors.read().iter() {
+            collector.render(&mut out);
+        }
+        out
+    }
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: usize) {
+    writeln!(out, "# HELP {} {}", name, help).unwrap();
+    writeln!(out, "# TYPE {} gauge", name).unwrap();
+    writeln!(out, "{} {}", name, value).unwrap();
+}
+
+fn histogram(out: &mut String, name: &str, help: &str, histogram: &Histogram) {
+    writeln!(out, "# HELP {} {}", name, help).unwrap();
+    writeln!(out, "# TYPE {} histogram", name).unwrap();
+    for (bound, count) in histogram.buckets() {
+        writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, count).unwrap();
+    }
+    writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, histogram.count()).unwrap();
+    writeln!(out, "{}_sum {}", name, histogram.sum()).unwrap();
+    writeln!(out, "{}_count {}", name, histogram.count()).unwrap();
+}
+
+fn now_in_millis() -> usize {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("Current time should be later than unix epoch");
+    now.as_secs() as usize * 1000 + now.subsec_millis() as usize
+}
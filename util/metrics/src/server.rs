@@ -0,0 +1,268 @@
+// Copyright 2019 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread::Builder;
+use std::time::{Duration, Instant};
+
+use crate::METRICS;
+
+/// A scraper that doesn't send the whole request in time is dropped, so that it doesn't block the others.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_REQUEST_SIZE: u64 = 8 * 1024;
+
+pub struct Server {
+    address: SocketAddr,
+}
+
+impl Server {
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+}
+
+/// Answers `GET /health`.
+pub trait HealthCheck: Send + Sync {
+    /// Returns whether the node is healthy, and the details in JSON.
+    fn check(&self) -> (bool, String);
+}
+
+/// Serves the metrics on `GET /metrics`, and the health of the node on `GET /health` if `health` is given.
+///
+/// The scrapers are served one by one on a dedicated thread, which is enough for a few Prometheus servers.
+/// A connection is closed if its whole request is not received within `REQUEST_TIMEOUT`, however slowly it's sent.
+pub fn start_http(address: &SocketAddr, health: Option<Arc<HealthCheck>>) -> io::Result<Server> {
+    let listener = TcpListener::bind(address)?;
+    let address = listener.local_addr()?;
+    Builder::new().name("metrics".to_string()).spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(err) = serve(stream, health.as_ref().map(|health| &**health)) {
+                        cdebug!(METRICS, "Cannot serve metrics: {}", err);
+                    }
+                }
+                Err(err) => cwarn!(METRICS, "Cannot accept a metrics connection: {}", err),
+            }
+        }
+    })?;
+    cinfo!(METRICS, "Metrics listening on {}", address);
+    Ok(Server {
+        address,
+    })
+}
+
+/// Reads the stream until the deadline, instead of timing out each read.
+struct DeadlineReader<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl<'a> DeadlineReader<'a> {
+    fn remaining(&self) -> io::Result<Duration> {
+        let now = Instant::now();
+        if now >= self.deadline {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "The request is not received in time"))
+        }
+        Ok(self.deadline - now)
+    }
+}
+
+impl<'a> Read for DeadlineReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.set_read_timeout(Some(self.remaining()?))?;
+        self.stream.read(buf)
+    }
+}
+
+fn serve(mut stream: TcpStream, health: Option<&HealthCheck>) -> io::Result<()> {
+    let deadline = Instant::now() + REQUEST_TIMEOUT;
+    let mut request_line = String::new();
+    {
+        let reader = DeadlineReader {
+            stream: &stream,
+            deadline,
+        };
+        let mut reader = BufReader::new(reader.take(MAX_REQUEST_SIZE));
+        reader.read_line(&mut request_line)?;
+        // Drain the headers
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 2 {
+            line.clear();
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next(), health) {
+        (Some("GET"), Some("/metrics"), _) => ("200 OK", "text/plain; version=0.0.4", METRICS.render()),
+        (Some("GET"), Some("/health"), Some(health)) => {
+            let (healthy, body) = health.check();
+            let status = if healthy {
+                "200 OK"
+            } else {
+                "503 Service Unavailable"
+            };
+            (status, "application/json", body)
+        }
+        _ => ("404 Not Found", "text/plain", String::new()),
+    };
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+
+    use super::*;
+
+    fn scrape(address: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn value_of(response: &str, metric: &str) -> usize {
+        response
+            .lines()
+            .find(|line| line.starts_with(metric) && line[metric.len()..].starts_with(' '))
+            .and_then(|line| line.rsplit(' ').next())
+            .unwrap_or_else(|| panic!("{} is not exposed", metric))
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn scrape_metrics() {
+        let server = start_http(&"127.0.0.1:0".parse().unwrap(), None).unwrap();
+        let traffic = METRICS.register_extension("metrics-test");
+        METRICS.set_consensus_state(3, 1, 2);
+        METRICS.peer_count.set(4);
+        traffic.bytes_in.add(10);
+        METRICS.block_import_time.observe(Duration::from_millis(1));
+
+        let response = scrape(server.address(), "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        for name in &[
+            "codechain_consensus_height",
+            "codechain_consensus_view",
+            "codechain_consensus_step",
+            "codechain_consensus_time_in_step_seconds",
+            "codechain_mem_pool_size",
+            "codechain_mem_pool_memory_bytes",
+            "codechain_peer_count",
+            "codechain_extension_received_bytes_total",
+            "codechain_extension_sent_bytes_total",
+            "codechain_block_import_seconds_bucket",
+            "codechain_signature_verification_seconds_count",
+            "codechain_engine_queue_depth",
+            "codechain_engine_event_seconds",
+        ] {
+            assert!(response.contains(&format!("# TYPE {}", name)) || response.contains(name), "{} is missing", name);
+        }
+        assert_eq!(4, value_of(&response, "codechain_peer_count"));
+        assert!(response.contains("codechain_engine_event_seconds_count{class=\"consensus\"}"));
+        assert!(response.contains("codechain_engine_event_seconds_count{class=\"sync\"}"));
+        let received = "codechain_extension_received_bytes_total{extension=\"metrics-test\"}";
+        let first_received = value_of(&response, received);
+        let first_import_count = value_of(&response, "codechain_block_import_seconds_count");
+        assert!(first_received >= 10);
+
+        traffic.bytes_in.add(5);
+        METRICS.block_import_time.observe(Duration::from_millis(2));
+
+        let response = scrape(server.address(), "/metrics");
+        assert!(value_of(&response, received) >= first_received + 5);
+        assert!(value_of(&response, "codechain_block_import_seconds_count") > first_import_count);
+    }
+
+    #[test]
+    fn idle_connection_does_not_block_the_scrapers() {
+        let server = start_http(&"127.0.0.1:0".parse().unwrap(), None).unwrap();
+        let _idle = TcpStream::connect(server.address()).unwrap();
+        let started = Instant::now();
+        let response = scrape(server.address(), "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(started.elapsed() < REQUEST_TIMEOUT * 2, "{:?}", started.elapsed());
+    }
+
+    #[test]
+    fn slow_request_does_not_block_the_scrapers() {
+        let server = start_http(&"127.0.0.1:0".parse().unwrap(), None).unwrap();
+        let mut stream = TcpStream::connect(server.address()).unwrap();
+        let slow = thread::spawn(move || {
+            // Each byte comes before the timeout of a read, but the request never ends.
+            for _ in 0..20 {
+                if stream.write_all(b"G").is_err() {
+                    break
+                }
+                thread::sleep(REQUEST_TIMEOUT / 4);
+            }
+        });
+        let started = Instant::now();
+        let response = scrape(server.address(), "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(started.elapsed() < REQUEST_TIMEOUT * 2, "{:?}", started.elapsed());
+        slow.join().unwrap();
+    }
+
+    #[test]
+    fn unknown_path() {
+        let server = start_http(&"127.0.0.1:0".parse().unwrap(), None).unwrap();
+        let response = scrape(server.address(), "/");
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+        let response = scrape(server.address(), "/health");
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+    }
+
+    struct Toggle(AtomicBool);
+
+    impl HealthCheck for Toggle {
+        fn check(&self) -> (bool, String) {
+            let healthy = self.0.load(Ordering::SeqCst);
+            (healthy, format!("{{\"healthy\":{}}}", healthy))
+        }
+    }
+
+    #[test]
+    fn health_status_code_follows_the_check() {
+        let toggle = Arc::new(Toggle(AtomicBool::new(true)));
+        let server =
+            start_http(&"127.0.0.1:0".parse().unwrap(), Some(Arc::clone(&toggle) as Arc<HealthCheck>)).unwrap();
+
+        let response = scrape(server.address(), "/health");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("{\"healthy\":true}"));
+
+        toggle.0.store(false, Ordering::SeqCst);
+        let response = scrape(server.address(), "/health");
+        assert!(response.starts_with("HTTP/1.1 503 Service Unavailable"));
+        assert!(response.ends_with("{\"healthy\":false}"));
+    }
+}
@@ -25,7 +25,7 @@ use std::hash::Hash;
 /// You can obviously use `HashMap<(Row,Col), Val>`, but this structure gives
 /// you better access to all `Columns` in Specific `Row`. Namely you can get sub-hashmap
 /// `HashMap<Col, Val>` for specific `Row`
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Table<Row, Col, Val>
 where
     Row: Eq + Hash + Clone,